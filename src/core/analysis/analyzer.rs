@@ -0,0 +1,684 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharFilter, KeywordTokenizer, OffsetAttribute, OffsetCorrector, PayloadAttribute,
+    PositionAttribute, PositionLengthAttribute, TermFreqAttribute, TermToBytesRefAttribute,
+    TokenStream, TypeAttribute,
+};
+
+use error::{ErrorKind::IllegalState, Result};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::mem;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+
+/// A `Read` whose underlying input can be replaced after a tokenizer reading
+/// from it has been built, this is how reused components consume new text.
+#[derive(Clone)]
+pub struct ReusableReader {
+    input: Rc<RefCell<Box<dyn Read>>>,
+}
+
+impl Default for ReusableReader {
+    fn default() -> Self {
+        ReusableReader::new()
+    }
+}
+
+impl ReusableReader {
+    pub fn new() -> ReusableReader {
+        ReusableReader {
+            input: Rc::new(RefCell::new(Box::new(io::empty()))),
+        }
+    }
+
+    /// Replaces the input of every clone of this reader.
+    pub fn set_reader(&self, reader: Box<dyn Read>) {
+        *self.input.borrow_mut() = reader;
+    }
+}
+
+impl Read for ReusableReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.borrow_mut().read(buf)
+    }
+}
+
+impl fmt::Debug for ReusableReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReusableReader").finish()
+    }
+}
+
+/// The outer `TokenStream` of an analysis chain together with the input its
+/// tokenizer reads from, so that the whole chain can be reused for new text.
+#[derive(Debug)]
+pub struct TokenStreamComponents {
+    source: ReusableReader,
+    sink: Box<dyn TokenStream>,
+}
+
+impl TokenStreamComponents {
+    pub fn new(source: ReusableReader, sink: Box<dyn TokenStream>) -> Self {
+        TokenStreamComponents { source, sink }
+    }
+
+    /// Resets the encapsulated components with the given reader, the sink
+    /// must still be `reset` before it is consumed.
+    pub fn set_reader(&mut self, reader: Box<dyn Read>) {
+        self.source.set_reader(reader);
+    }
+
+    pub fn token_stream(&mut self) -> &mut dyn TokenStream {
+        self.sink.as_mut()
+    }
+}
+
+/// Decides which `TokenStreamComponents` of an analyzer are reused for a
+/// field. Components are never shared between threads, each thread reuses
+/// its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReuseStrategy {
+    /// One set of components per thread is reused for all fields, for
+    /// analyzers whose chain doesn't depend on the field name.
+    Global,
+    /// Components are created and reused per field and per thread.
+    PerField,
+}
+
+impl Default for ReuseStrategy {
+    fn default() -> Self {
+        ReuseStrategy::Global
+    }
+}
+
+type ComponentsMap = HashMap<String, TokenStreamComponents>;
+
+/// The components a thread cached for a `ComponentsCache`.
+struct ThreadComponents {
+    // the generation of the cache, dead once the cache is dropped
+    generation: Weak<AtomicUsize>,
+    cached_generation: usize,
+    components: ComponentsMap,
+}
+
+thread_local! {
+    /// The components of the current thread, by cache id.
+    static THREAD_COMPONENTS: RefCell<HashMap<usize, ThreadComponents>> =
+        RefCell::new(HashMap::new());
+}
+
+static NEXT_CACHE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The cache of `TokenStreamComponents` an analyzer hands out.
+///
+/// Components are kept in thread local storage, so that an analyzer can be
+/// shared by several indexing threads, each reusing its own components
+/// without any locking. Components are taken out of the cache while their
+/// token stream is in use and put back once it is dropped, so a field being
+/// analyzed twice at the same time just gets a fresh chain.
+pub struct ComponentsCache {
+    strategy: ReuseStrategy,
+    id: usize,
+    // bumped to invalidate the components of all threads
+    generation: Arc<AtomicUsize>,
+}
+
+impl ComponentsCache {
+    pub fn new(strategy: ReuseStrategy) -> ComponentsCache {
+        ComponentsCache {
+            strategy,
+            id: NEXT_CACHE_ID.fetch_add(1, Ordering::Relaxed),
+            generation: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn strategy(&self) -> ReuseStrategy {
+        self.strategy
+    }
+
+    /// Drops all cached components, e.g. after a setting that the
+    /// components are built from has changed. The components of other
+    /// threads are dropped the next time they use the cache.
+    pub fn clear(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        // dropped once the thread local storage isn't borrowed anymore
+        let _removed = THREAD_COMPONENTS.with(|threads| threads.borrow_mut().remove(&self.id));
+    }
+
+    /// The number of components cached for the current thread.
+    pub fn cached(&self) -> usize {
+        let generation = self.generation.load(Ordering::Acquire);
+        THREAD_COMPONENTS.with(|threads| match threads.borrow().get(&self.id) {
+            Some(thread) if thread.cached_generation == generation => thread.components.len(),
+            _ => 0,
+        })
+    }
+
+    fn key(&self, field_name: &str) -> String {
+        match self.strategy {
+            ReuseStrategy::Global => String::new(),
+            ReuseStrategy::PerField => field_name.to_string(),
+        }
+    }
+
+    /// Takes the components cached for `key` by the current thread, returns
+    /// them along with the generation they must be put back with.
+    fn take(&self, key: &str) -> (Option<TokenStreamComponents>, usize) {
+        let generation = self.generation.load(Ordering::Acquire);
+        let mut dropped = vec![];
+        let components = THREAD_COMPONENTS.with(|threads| {
+            let mut threads = threads.borrow_mut();
+            if !threads.contains_key(&self.id) {
+                // a good time to drop the components of dropped caches
+                let dead: Vec<usize> = threads
+                    .iter()
+                    .filter(|(_, thread)| thread.generation.upgrade().is_none())
+                    .map(|(&id, _)| id)
+                    .collect();
+                dropped.extend(
+                    dead.into_iter()
+                        .filter_map(|id| threads.remove(&id))
+                        .map(|thread| thread.components),
+                );
+                threads.insert(
+                    self.id,
+                    ThreadComponents {
+                        generation: Arc::downgrade(&self.generation),
+                        cached_generation: generation,
+                        components: HashMap::new(),
+                    },
+                );
+            }
+            let thread = threads.get_mut(&self.id).unwrap();
+            if thread.cached_generation != generation {
+                dropped.push(mem::replace(&mut thread.components, HashMap::new()));
+                thread.cached_generation = generation;
+            }
+            thread.components.remove(key)
+        });
+        (components, generation)
+    }
+}
+
+impl Default for ComponentsCache {
+    fn default() -> Self {
+        ComponentsCache::new(ReuseStrategy::default())
+    }
+}
+
+impl Drop for ComponentsCache {
+    fn drop(&mut self) {
+        // the components of other threads are dropped lazily
+        let _removed = THREAD_COMPONENTS.try_with(|threads| threads.borrow_mut().remove(&self.id));
+    }
+}
+
+impl fmt::Debug for ComponentsCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ComponentsCache")
+            .field("strategy", &self.strategy)
+            .field("cached", &self.cached())
+            .finish()
+    }
+}
+
+/// The offset gap `Analyzer::offset_gap` returns by default, one char for the
+/// separator between two values of a multi-valued field.
+pub const DEFAULT_OFFSET_GAP: usize = 1;
+
+/// An `Analyzer` builds `TokenStream`s, which analyze text. It thus represents
+/// a policy for extracting index terms from text.
+///
+/// Implementations only define the analysis chain in `create_components`, the
+/// chain is cached by the analyzer's `ComponentsCache` and reused by later
+/// calls of `token_stream`.
+pub trait Analyzer {
+    /// Creates the analysis chain of the given field, whose tokenizer consumes
+    /// `reader`.
+    fn create_components(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>>;
+
+    /// The cache used to reuse the components of this analyzer.
+    fn components_cache(&self) -> &ComponentsCache;
+
+    /// The position increment inserted between two values of a multi-valued
+    /// field.
+    fn position_increment_gap(&self, _field_name: &str) -> u32 {
+        0
+    }
+
+    /// The offset gap inserted between two values of a multi-valued field.
+    fn offset_gap(&self, _field_name: &str) -> usize {
+        DEFAULT_OFFSET_GAP
+    }
+
+    /// The char filters applied in order to the text of `field_name` before
+    /// it's tokenized, none by default.
+    fn char_filters(&self, _field_name: &str) -> &[Box<dyn CharFilter>] {
+        &[]
+    }
+
+    /// Returns a `TokenStream` suitable for `field_name`, tokenizing the
+    /// contents of `reader`.
+    ///
+    /// The components of a previous stream are reused when the cache holds
+    /// them, so the returned stream must be dropped before the components
+    /// can be handed out again.
+    fn token_stream(
+        &self,
+        field_name: &str,
+        mut reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let char_filters = self.char_filters(field_name);
+        let mut offset_correctors = Vec::with_capacity(char_filters.len());
+        if !char_filters.is_empty() {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            for char_filter in char_filters {
+                let mut corrector = OffsetCorrector::new();
+                text = char_filter.filter(&text, &mut corrector);
+                offset_correctors.push(corrector);
+            }
+            reader = Box::new(Cursor::new(text.into_bytes()));
+        }
+
+        let cache = self.components_cache();
+        let key = cache.key(field_name);
+        let (cached, generation) = cache.take(&key);
+        let components = match cached {
+            Some(mut components) => {
+                components.set_reader(reader);
+                components
+            }
+            None => {
+                let source = ReusableReader::new();
+                source.set_reader(reader);
+                let sink = self.create_components(field_name, Box::new(source.clone()))?;
+                TokenStreamComponents::new(source, sink)
+            }
+        };
+        Ok(Box::new(ReusedTokenStream {
+            components: Some(components),
+            offset_correctors,
+            key,
+            cache_id: cache.id,
+            cache_generation: Arc::downgrade(&cache.generation),
+            generation,
+        }))
+    }
+
+    /// Returns a `TokenStream` suitable for `field_name`, tokenizing `text`.
+    fn token_stream_from_str(&self, field_name: &str, text: &str) -> Result<Box<dyn TokenStream>> {
+        self.token_stream(field_name, Box::new(Cursor::new(text.as_bytes().to_vec())))
+    }
+
+    /// Wraps `input` with the filters of the analysis chain of `field_name`
+    /// which normalize terms without splitting them, e.g. lower casing or
+    /// folding, but not stemming or removing stop words. Used by
+    /// `normalize`, returns `input` by default.
+    fn normalize_stream(
+        &self,
+        _field_name: &str,
+        input: Box<dyn TokenStream>,
+    ) -> Box<dyn TokenStream> {
+        input
+    }
+
+    /// Normalizes `text` into a single term the way the analysis chain of
+    /// `field_name` would, e.g. for prefix, wildcard or exact match queries
+    /// which must not be tokenized.
+    fn normalize(&self, field_name: &str, text: &str) -> Result<Vec<u8>> {
+        let tokenizer = KeywordTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut stream = self.normalize_stream(field_name, Box::new(tokenizer));
+        stream.reset()?;
+        if !stream.increment_token()? {
+            bail!(IllegalState(format!(
+                "the normalization token stream is expected to produce exactly 1 token, but got 0 \
+                 for input \"{}\"",
+                text
+            )));
+        }
+        let term = stream
+            .term_bytes_attribute()
+            .get_bytes_ref()
+            .bytes()
+            .to_vec();
+        if stream.increment_token()? {
+            bail!(IllegalState(format!(
+                "the normalization token stream is expected to produce exactly 1 token, but got \
+                 2+ for input \"{}\"",
+                text
+            )));
+        }
+        stream.end()?;
+        Ok(term)
+    }
+}
+
+/// The stream handed out by `Analyzer::token_stream`, puts its components
+/// back into the analyzer's cache once dropped.
+///
+/// The offsets of the tokens are mapped back to the text before the char
+/// filters of the analyzer, which the components don't know about.
+struct ReusedTokenStream {
+    components: Option<TokenStreamComponents>,
+    offset_correctors: Vec<OffsetCorrector>,
+    key: String,
+    cache_id: usize,
+    cache_generation: Weak<AtomicUsize>,
+    // the generation of the cache the components were taken from
+    generation: usize,
+}
+
+impl ReusedTokenStream {
+    #[inline]
+    fn sink(&self) -> &dyn TokenStream {
+        self.components.as_ref().unwrap().sink.as_ref()
+    }
+
+    #[inline]
+    fn sink_mut(&mut self) -> &mut dyn TokenStream {
+        self.components.as_mut().unwrap().sink.as_mut()
+    }
+
+    fn correct_offsets(&mut self, end: bool) -> Result<()> {
+        if self.offset_correctors.is_empty() {
+            return Ok(());
+        }
+        let offset = self.sink().offset_attribute();
+        let (mut start_offset, mut end_offset) = (offset.start_offset(), offset.end_offset());
+        for corrector in self.offset_correctors.iter().rev() {
+            start_offset = corrector.correct_offset(start_offset);
+            end_offset = if end {
+                // the final offset is after any removed trailing text
+                corrector.correct_offset(end_offset)
+            } else {
+                corrector.correct_end_offset(end_offset)
+            };
+        }
+        self.sink_mut()
+            .offset_attribute_mut()
+            .set_offset(start_offset, end_offset)
+    }
+}
+
+impl Drop for ReusedTokenStream {
+    fn drop(&mut self) {
+        let components = match self.components.take() {
+            Some(components) => components,
+            None => return,
+        };
+        // components of a dropped or cleared cache are not put back
+        let generation = match self.cache_generation.upgrade() {
+            Some(generation) => generation.load(Ordering::Acquire),
+            None => return,
+        };
+        if generation != self.generation {
+            return;
+        }
+        // the thread local storage is gone if the thread is exiting
+        let _rejected = THREAD_COMPONENTS.try_with(|threads| {
+            match threads.borrow_mut().get_mut(&self.cache_id) {
+                Some(ref mut thread)
+                    if thread.cached_generation == generation
+                        && !thread.components.contains_key(&self.key) =>
+                {
+                    thread.components.insert(self.key.clone(), components);
+                    None
+                }
+                _ => Some(components),
+            }
+        });
+    }
+}
+
+impl fmt::Debug for ReusedTokenStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReusedTokenStream")
+            .field("key", &self.key)
+            .field("components", &self.components)
+            .finish()
+    }
+}
+
+impl TokenStream for ReusedTokenStream {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.sink_mut().increment_token()? {
+            return Ok(false);
+        }
+        self.correct_offsets(false)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.sink_mut().end()?;
+        self.correct_offsets(true)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.sink_mut().reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.sink_mut().offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.sink().offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.sink_mut().position_attribute_mut()
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        self.sink().position_attribute()
+    }
+
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        self.sink_mut().position_length_attribute_mut()
+    }
+
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        self.sink().position_length_attribute()
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        self.sink_mut().type_attribute_mut()
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        self.sink().type_attribute()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.sink_mut().payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.sink().payload_attribute()
+    }
+
+    fn term_freq_attribute_mut(&mut self) -> Option<&mut TermFreqAttribute> {
+        self.sink_mut().term_freq_attribute_mut()
+    }
+
+    fn term_freq_attribute(&self) -> Option<&TermFreqAttribute> {
+        self.sink().term_freq_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        self.sink_mut().term_bytes_attribute_mut()
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        self.sink().term_bytes_attribute()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::{
+        CJKAnalyzer, CustomAnalyzer, HTMLStripCharFilter, KeywordAnalyzer, MappingCharFilter,
+        NormalizeCharMap, SimpleAnalyzer, StandardAnalyzer, WhitespaceAnalyzer,
+        WhitespaceTokenizer,
+    };
+    use std::thread;
+
+    struct TestAnalyzer {
+        cache: ComponentsCache,
+        created: AtomicUsize,
+        char_filters: Vec<Box<dyn CharFilter>>,
+    }
+
+    impl Analyzer for TestAnalyzer {
+        fn create_components(
+            &self,
+            _field_name: &str,
+            reader: Box<dyn Read>,
+        ) -> Result<Box<dyn TokenStream>> {
+            self.created.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(WhitespaceTokenizer::new(reader)))
+        }
+
+        fn components_cache(&self) -> &ComponentsCache {
+            &self.cache
+        }
+
+        fn char_filters(&self, _field_name: &str) -> &[Box<dyn CharFilter>] {
+            &self.char_filters
+        }
+    }
+
+    #[test]
+    fn test_analyzer_reuses_components() {
+        let analyzer = TestAnalyzer {
+            cache: ComponentsCache::new(ReuseStrategy::PerField),
+            created: AtomicUsize::new(0),
+            char_filters: vec![],
+        };
+
+        {
+            let mut stream = analyzer
+                .token_stream_from_str("title", "quick fox")
+                .unwrap();
+            assert_token_stream_contents(stream.as_mut(), &["quick", "fox"], None, None);
+        }
+        {
+            let mut stream = analyzer.token_stream_from_str("title", "lazy dog").unwrap();
+            assert_token_stream_contents(stream.as_mut(), &["lazy", "dog"], None, None);
+        }
+        assert_eq!(analyzer.created.load(Ordering::SeqCst), 1);
+
+        // other fields and streams still in use get their own components
+        let mut body = analyzer.token_stream_from_str("body", "a b").unwrap();
+        let mut title = analyzer.token_stream_from_str("title", "c").unwrap();
+        let mut title2 = analyzer.token_stream_from_str("title", "d e").unwrap();
+        assert_token_stream_contents(body.as_mut(), &["a", "b"], None, None);
+        assert_token_stream_contents(title.as_mut(), &["c"], None, None);
+        assert_token_stream_contents(title2.as_mut(), &["d", "e"], None, None);
+        assert_eq!(analyzer.created.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_components_per_thread() {
+        let analyzer = Arc::new(TestAnalyzer {
+            cache: ComponentsCache::new(ReuseStrategy::PerField),
+            created: AtomicUsize::new(0),
+            char_filters: vec![],
+        });
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let analyzer = Arc::clone(&analyzer);
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        let term = format!("t{}", i);
+                        let text = format!("{} x", term);
+                        let mut stream = analyzer.token_stream_from_str("body", &text).unwrap();
+                        assert_token_stream_contents(
+                            stream.as_mut(),
+                            &[term.as_str(), "x"],
+                            None,
+                            None,
+                        );
+                    }
+                    analyzer.components_cache().cached()
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 1);
+        }
+        // one chain per thread, none cached for this one
+        assert_eq!(analyzer.created.load(Ordering::SeqCst), 4);
+        assert_eq!(analyzer.components_cache().cached(), 0);
+
+        analyzer.token_stream_from_str("body", "a").unwrap();
+        assert_eq!(analyzer.components_cache().cached(), 1);
+        let stream = analyzer.token_stream_from_str("body", "a").unwrap();
+        assert_eq!(analyzer.created.load(Ordering::SeqCst), 5);
+
+        // components in use when the cache is cleared are not put back
+        analyzer.components_cache().clear();
+        drop(stream);
+        assert_eq!(analyzer.components_cache().cached(), 0);
+        analyzer.token_stream_from_str("body", "a").unwrap();
+        assert_eq!(analyzer.created.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_analyzers_are_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CJKAnalyzer>();
+        assert_send_sync::<CustomAnalyzer>();
+        assert_send_sync::<KeywordAnalyzer>();
+        assert_send_sync::<SimpleAnalyzer>();
+        assert_send_sync::<StandardAnalyzer>();
+        assert_send_sync::<WhitespaceAnalyzer>();
+    }
+
+    #[test]
+    fn test_char_filters() {
+        let mut builder = NormalizeCharMap::builder();
+        builder.add("&", " and ").unwrap();
+        let analyzer = TestAnalyzer {
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+            created: AtomicUsize::new(0),
+            char_filters: vec![
+                Box::new(HTMLStripCharFilter::new()),
+                Box::new(MappingCharFilter::new(Arc::new(builder.build()))),
+            ],
+        };
+        let text = "<p>Salt&amp;<i>pepper</i></p>";
+        let mut stream = analyzer.token_stream_from_str("body", text).unwrap();
+        assert_token_stream_contents(
+            stream.as_mut(),
+            &["Salt", "and", "pepper"],
+            Some(&[(3, 7), (7, 12), (15, 21)]),
+            None,
+        );
+        assert_eq!(stream.offset_attribute().end_offset(), text.len());
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+use unicode_normalization::char::{decompose_compatible, is_combining_mark};
+
+/// Converts alphabetic, numeric, and symbolic Unicode characters which are
+/// not in the first 127 ASCII characters (the "Basic Latin" Unicode block)
+/// into their ASCII equivalents, if one exists.
+///
+/// Characters are folded by their compatibility decomposition with the
+/// combining marks dropped, e.g. `é` to `e`, `ﬁ` to `fi` or `①` to `1`,
+/// and by a table for letters without a decomposition, e.g. `ß` to `ss`,
+/// `ø` to `o` or `“` to `"`. Characters which have no ASCII equivalent
+/// are left unchanged.
+///
+/// With `preserve_original` set, the original token is emitted after the
+/// folded one at the same position, if folding changed it.
+#[derive(Debug)]
+pub struct ASCIIFoldingFilter {
+    input: Box<dyn TokenStream>,
+    preserve_original: bool,
+    output: String,
+    original: Option<Vec<u8>>,
+}
+
+impl ASCIIFoldingFilter {
+    pub fn new(input: Box<dyn TokenStream>, preserve_original: bool) -> Self {
+        ASCIIFoldingFilter {
+            input,
+            preserve_original,
+            output: String::new(),
+            original: None,
+        }
+    }
+
+    /// Does the filter preserve the original tokens?
+    pub fn preserve_original(&self) -> bool {
+        self.preserve_original
+    }
+}
+
+impl TokenStream for ASCIIFoldingFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if let Some(original) = self.original.take() {
+            if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+                term.copy_buffer(&original);
+            }
+            self.input.position_attribute_mut().set_position(0);
+            return Ok(true);
+        }
+
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            // If no characters actually require rewriting then we
+            // just return token as-is:
+            if term.term_buffer.is_ascii() {
+                return Ok(true);
+            }
+            if let Ok(text) = ::std::str::from_utf8(&term.term_buffer) {
+                self.output.clear();
+                fold_to_ascii(text, &mut self.output);
+            } else {
+                return Ok(true);
+            }
+            if self.output.as_bytes() != term.term_buffer.as_slice() {
+                if self.preserve_original {
+                    self.original = Some(term.term_buffer.clone());
+                }
+                term.copy_buffer(self.output.as_bytes());
+            }
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.original = None;
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for ASCIIFoldingFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+/// Appends the ASCII folding of `input` to `output`.
+pub fn fold_to_ascii(input: &str, output: &mut String) {
+    for c in input.chars() {
+        fold_char(c, output);
+    }
+}
+
+fn fold_char(c: char, output: &mut String) {
+    if c.is_ascii() {
+        output.push(c);
+        return;
+    }
+    if let Some(folded) = fold_undecomposable(c) {
+        output.push_str(folded);
+        return;
+    }
+
+    let start = output.len();
+    let mut foldable = true;
+    decompose_compatible(c, |d| {
+        if !foldable || is_combining_mark(d) {
+            return;
+        }
+        if d.is_ascii() {
+            output.push(d);
+        } else if let Some(folded) = fold_undecomposable(d) {
+            output.push_str(folded);
+        } else {
+            foldable = false;
+        }
+    });
+    if !foldable || output.len() == start {
+        output.truncate(start);
+        output.push(c);
+    }
+}
+
+/// The ASCII equivalents of letters and punctuation which don't have a
+/// compatibility decomposition into ASCII.
+fn fold_undecomposable(c: char) -> Option<&'static str> {
+    let folded = match c {
+        'Æ' | 'Ǣ' | 'Ǽ' | 'ᴁ' => "AE",
+        'æ' | 'ǣ' | 'ǽ' | 'ᴂ' => "ae",
+        'Ð' | 'Đ' | 'Ɖ' | 'Ɗ' | 'Ƌ' | 'ᴅ' | 'ᴆ' => "D",
+        'ð' | 'đ' | 'ƌ' | 'ȡ' | 'ɖ' | 'ɗ' | 'ᵭ' | 'ᶁ' | 'ᶑ' => "d",
+        'Ø' | 'Ǿ' | 'Ɵ' | 'Ɔ' | 'ᴏ' | 'ᴐ' => "O",
+        'ø' | 'ǿ' | 'ɵ' | 'ɔ' | 'ᴓ' => "o",
+        'Œ' | 'ɶ' => "OE",
+        'œ' | 'ᴔ' => "oe",
+        'Þ' | 'Ꝥ' => "TH",
+        'þ' | 'ꝥ' => "th",
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'Ħ' | 'ʜ' => "H",
+        'ħ' | 'ɦ' | 'ɧ' | 'ⱨ' => "h",
+        'ı' | 'ɨ' | 'ᵻ' | 'ᶖ' => "i",
+        'Ɩ' | 'Ɨ' | 'ɪ' => "I",
+        'ĸ' | 'ʠ' => "q",
+        'Ŀ' | 'Ł' | 'Ƚ' | 'ʟ' | 'ᴌ' => "L",
+        'ŀ' | 'ł' | 'ƚ' | 'ȴ' | 'ɫ' | 'ɬ' | 'ɭ' | 'ᶅ' => "l",
+        'ŉ' => "'n",
+        'Ŋ' | 'Ɲ' | 'ɴ' | 'ᴎ' => "N",
+        'ŋ' | 'ƞ' | 'ȵ' | 'ɲ' | 'ɳ' | 'ᵰ' | 'ᶇ' => "n",
+        'Ŧ' | 'Ƭ' | 'Ʈ' | 'Ⱦ' | 'ᴛ' => "T",
+        'ŧ' | 'ƫ' | 'ƭ' | 'ȶ' | 'ʈ' | 'ᵵ' => "t",
+        'ƀ' | 'ƃ' | 'ɓ' | 'ᵬ' | 'ᶀ' => "b",
+        'Ɓ' | 'Ƃ' | 'Ƀ' | 'ʙ' | 'ᴃ' => "B",
+        'Ƈ' | 'Ȼ' | 'ᴄ' => "C",
+        'ƈ' | 'ȼ' | 'ɕ' => "c",
+        'Ɛ' | 'Ǝ' | 'Ɇ' | 'ᴇ' => "E",
+        'ǝ' | 'ɇ' | 'ə' | 'ɛ' | 'ᶒ' => "e",
+        'Ƒ' | 'ꜰ' => "F",
+        'ƒ' | 'ᵮ' | 'ᶂ' => "f",
+        'Ɠ' | 'Ǥ' | 'ɢ' | 'ʛ' => "G",
+        'ǥ' | 'ɠ' | 'ɡ' | 'ᵷ' | 'ᶃ' => "g",
+        'ƕ' => "hv",
+        'Ƙ' | 'ᴋ' => "K",
+        'ƙ' | 'ᶄ' => "k",
+        'Ɯ' | 'ᴍ' => "M",
+        'ɱ' | 'ᵯ' | 'ᶆ' => "m",
+        'Ƣ' => "OI",
+        'ƣ' => "oi",
+        'Ƥ' | 'ᴘ' => "P",
+        'ƥ' | 'ᵱ' | 'ᵽ' | 'ᶈ' => "p",
+        'Ɍ' | 'ʀ' | 'ʁ' | 'ᴙ' | 'ᴚ' => "R",
+        'ɍ' | 'ɼ' | 'ɽ' | 'ɾ' | 'ɿ' | 'ᵲ' | 'ᵳ' | 'ᶉ' => "r",
+        'ȿ' | 'ʂ' | 'ᵴ' | 'ᶊ' => "s",
+        'Ʉ' | 'ᴜ' => "U",
+        'ʉ' | 'ᵾ' | 'ᶙ' => "u",
+        'Ʋ' | 'Ʌ' | 'ᴠ' => "V",
+        'ʋ' | 'ʌ' | 'ⱱ' | 'ⱴ' | 'ᶌ' => "v",
+        'Ƴ' | 'Ɏ' | 'ʏ' => "Y",
+        'ƴ' | 'ɏ' => "y",
+        'Ƶ' | 'Ȥ' | 'ᴢ' => "Z",
+        'ƶ' | 'ȥ' | 'ɀ' | 'ʐ' | 'ʑ' | 'ᵶ' | 'ᶎ' => "z",
+        'Ɉ' | 'ᴊ' => "J",
+        'ɉ' | 'ɟ' | 'ʄ' | 'ʝ' | 'ȷ' => "j",
+        'ȸ' => "db",
+        'ȹ' => "qp",
+        'Ⱥ' => "A",
+        '‘' | '’' | '‚' | '‛' | '′' | '‵' | '‹' | '›' | '❛' | '❜' => "'",
+        '“' | '”' | '„' | '‟' | '″' | '‶' | '«' | '»' | '❝' | '❞' => "\"",
+        '‐' | '‑' | '‒' | '–' | '—' | '⁻' | '₋' | '−' => "-",
+        '⁄' | '∕' => "/",
+        '⁅' | '❲' => "[",
+        '⁆' | '❳' => "]",
+        '⁎' | '⁑' => "*",
+        '⁏' => ";",
+        '⁓' => "~",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn fold(text: &str) -> String {
+        let mut output = String::new();
+        fold_to_ascii(text, &mut output);
+        output
+    }
+
+    #[test]
+    fn test_fold_to_ascii() {
+        assert_eq!(fold("Crème Brûlée"), "Creme Brulee");
+        assert_eq!(fold("Straße Øresund Łódź"), "Strasse Oresund Lodz");
+        assert_eq!(fold("ﬁnancial Ⅻ ½ ①"), "financial XII 1/2 1");
+        assert_eq!(fold("Ｆｕｌｌ “quoted” – dash"), "Full \"quoted\" - dash");
+        // no ASCII equivalent
+        assert_eq!(fold("Ελλάδα 北京 한국"), "Ελλάδα 北京 한국");
+    }
+
+    #[test]
+    fn test_ascii_folding_filter() {
+        let text = "café naïve plain";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = ASCIIFoldingFilter::new(Box::new(tokenizer), true);
+        assert_token_stream_contents(
+            &mut filter,
+            &["cafe", "café", "naive", "naïve", "plain"],
+            Some(&[(0, 4), (0, 4), (5, 10), (5, 10), (11, 16)]),
+            Some(&[1, 0, 1, 0, 1]),
+        );
+    }
+}
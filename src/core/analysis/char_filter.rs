@@ -0,0 +1,142 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Debug;
+
+/// Transforms text before it's tokenized, e.g. to strip markup or to map
+/// characters, see `Analyzer::char_filters`.
+///
+/// Char filters may change the length of the text, so they record how the
+/// offsets of their output map to the offsets of their input, which keeps
+/// the offsets of the tokens pointing into the original text, e.g. for
+/// highlighting. Offsets are in chars, as the offsets of tokens.
+pub trait CharFilter: Debug + Send + Sync {
+    /// Filters `text`, the changes of offsets are recorded in `corrector`.
+    fn filter(&self, text: &str, corrector: &mut OffsetCorrector) -> String;
+}
+
+/// Maps the offsets of the output of a `CharFilter` to the offsets of its
+/// input, like Lucene's `BaseCharFilter`.
+///
+/// Start and end offsets are corrected differently at the place of removed
+/// text, e.g. with "<b>" and "</b>" removed from "<b>bold</b>", the token
+/// "bold" starts after the opening tag but ends before the closing tag.
+/// Replacements must be recorded in the order of their output offsets.
+#[derive(Debug, Clone, Default)]
+pub struct OffsetCorrector {
+    // (output offset, cumulative diff applying from that offset on)
+    start_diffs: Vec<(usize, isize)>,
+    end_diffs: Vec<(usize, isize)>,
+    cumulative_diff: isize,
+}
+
+impl OffsetCorrector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `input_length` chars of the input were replaced by
+    /// `output_length` chars at `output_offset` of the output.
+    ///
+    /// If the replacement is longer, its extra chars are mapped to the last
+    /// replaced char.
+    pub fn replace(&mut self, output_offset: usize, input_length: usize, output_length: usize) {
+        if output_length < input_length {
+            self.cumulative_diff += (input_length - output_length) as isize;
+            let diff = self.cumulative_diff;
+            let offset = output_offset + output_length;
+            add_diff(&mut self.start_diffs, offset, diff);
+            // end offsets only skip removed text once a char follows it
+            let end_offset = if output_length == 0 {
+                offset + 1
+            } else {
+                offset
+            };
+            add_diff(&mut self.end_diffs, end_offset, diff);
+        } else if output_length > input_length {
+            let extra = output_length - input_length;
+            for i in 0..extra {
+                let diff = self.cumulative_diff - i as isize - 1;
+                let offset = output_offset + input_length + i;
+                add_diff(&mut self.start_diffs, offset, diff);
+                add_diff(&mut self.end_diffs, offset + 1, diff);
+            }
+            self.cumulative_diff -= extra as isize;
+        }
+    }
+
+    /// Returns true if no offset is changed.
+    pub fn is_empty(&self) -> bool {
+        self.start_diffs.is_empty()
+    }
+
+    /// Maps a start offset of the output to the offset of the input.
+    pub fn correct_offset(&self, offset: usize) -> usize {
+        correct(&self.start_diffs, offset)
+    }
+
+    /// Maps an end offset of the output to the offset of the input.
+    pub fn correct_end_offset(&self, offset: usize) -> usize {
+        correct(&self.end_diffs, offset)
+    }
+}
+
+fn add_diff(diffs: &mut Vec<(usize, isize)>, offset: usize, diff: isize) {
+    match diffs.last_mut() {
+        Some(last) if last.0 == offset => last.1 = diff,
+        last => {
+            debug_assert!(last.map_or(true, |last| last.0 < offset));
+            diffs.push((offset, diff));
+        }
+    }
+}
+
+fn correct(diffs: &[(usize, isize)], offset: usize) -> usize {
+    let index = match diffs.binary_search_by_key(&offset, |&(offset, _)| offset) {
+        Ok(index) => index,
+        Err(0) => return offset,
+        Err(index) => index - 1,
+    };
+    (offset as isize + diffs[index].1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_corrector() {
+        // "<b>x</b>&amp;y" filtered into "x&y"
+        let mut corrector = OffsetCorrector::new();
+        corrector.replace(0, 3, 0);
+        corrector.replace(1, 4, 0);
+        corrector.replace(1, 5, 1);
+        assert_eq!(corrector.correct_offset(0), 3);
+        assert_eq!(corrector.correct_end_offset(0), 0);
+        assert_eq!(corrector.correct_end_offset(1), 4);
+        assert_eq!(corrector.correct_offset(1), 8);
+        assert_eq!(corrector.correct_end_offset(2), 13);
+        assert_eq!(corrector.correct_offset(2), 13);
+        assert_eq!(corrector.correct_offset(3), 14);
+
+        // "æb" filtered into "aeb"
+        let mut corrector = OffsetCorrector::new();
+        corrector.replace(0, 1, 2);
+        assert_eq!(corrector.correct_offset(0), 0);
+        assert_eq!(corrector.correct_offset(1), 0);
+        assert_eq!(corrector.correct_end_offset(1), 1);
+        assert_eq!(corrector.correct_end_offset(2), 1);
+        assert_eq!(corrector.correct_offset(2), 1);
+        assert_eq!(corrector.correct_end_offset(3), 2);
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    Analyzer, CJKBigramFilter, CharArraySet, ComponentsCache, LowerCaseFilter, ReuseStrategy,
+    StandardTokenizer, StopFilter, TokenStream,
+};
+
+use error::Result;
+
+use std::io::Read;
+use std::sync::Arc;
+
+/// The stop words used by `CJKAnalyzer`, the same set Lucene uses, i.e. the
+/// english stop words plus a few fragments commonly left in CJK text.
+pub const CJK_STOP_WORDS: [&str; 35] = [
+    "a", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it", "no",
+    "not", "of", "on", "or", "s", "such", "t", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with", "www",
+];
+
+lazy_static! {
+    /// An unmodifiable set containing `CJK_STOP_WORDS`.
+    pub static ref CJK_STOP_WORDS_SET: Arc<CharArraySet> =
+        Arc::new(CharArraySet::from_words(&CJK_STOP_WORDS, false));
+}
+
+/// An analyzer for Chinese, Japanese and Korean text which doesn't need a
+/// dictionary: `StandardTokenizer` is filtered with `LowerCaseFilter`,
+/// `CJKBigramFilter` and `StopFilter`, so that CJK text is indexed as
+/// overlapping bigrams while other text is handled like `StandardAnalyzer`
+/// does.
+#[derive(Debug)]
+pub struct CJKAnalyzer {
+    stop_words: Arc<CharArraySet>,
+    flags: u32,
+    output_unigrams: bool,
+    cache: ComponentsCache,
+}
+
+impl Default for CJKAnalyzer {
+    fn default() -> Self {
+        CJKAnalyzer::new(CJK_STOP_WORDS_SET.clone())
+    }
+}
+
+impl CJKAnalyzer {
+    /// Builds an analyzer with the given stop words, bigramming all the CJK
+    /// scripts.
+    pub fn new(stop_words: Arc<CharArraySet>) -> Self {
+        CJKAnalyzer {
+            stop_words,
+            flags: CJKBigramFilter::ALL,
+            output_unigrams: false,
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        }
+    }
+
+    pub fn stop_words(&self) -> &CharArraySet {
+        &self.stop_words
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Set the scripts which are bigrammed, see `CJKBigramFilter::new`.
+    pub fn set_flags(&mut self, flags: u32) {
+        self.flags = flags;
+        self.cache.clear();
+    }
+
+    pub fn output_unigrams(&self) -> bool {
+        self.output_unigrams
+    }
+
+    /// Set whether unigrams are indexed along with the bigrams, false by
+    /// default.
+    pub fn set_output_unigrams(&mut self, output_unigrams: bool) {
+        self.output_unigrams = output_unigrams;
+        self.cache.clear();
+    }
+}
+
+impl Analyzer for CJKAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = StandardTokenizer::new(reader);
+        let stream = LowerCaseFilter::new(Box::new(tokenizer));
+        let mut stream = CJKBigramFilter::new(Box::new(stream), self.flags);
+        stream.set_output_unigrams(self.output_unigrams);
+        Ok(Box::new(StopFilter::new(
+            Box::new(stream),
+            Arc::clone(&self.stop_words),
+        )))
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+
+    fn normalize_stream(
+        &self,
+        _field_name: &str,
+        input: Box<dyn TokenStream>,
+    ) -> Box<dyn TokenStream> {
+        Box::new(LowerCaseFilter::new(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_cjk_analyzer() {
+        let mut analyzer = CJKAnalyzer::default();
+        assert_analyzes_to(
+            &analyzer,
+            "The 東京タワー is in Tokyo",
+            &["東京", "京タ", "タワ", "ワー", "tokyo"],
+            None,
+            None,
+        );
+
+        analyzer.set_flags(CJKBigramFilter::HAN);
+        analyzer.set_output_unigrams(true);
+        assert_analyzes_to(
+            &analyzer,
+            "東京タワー",
+            &["東", "東京", "京", "タワー"],
+            None,
+            None,
+        );
+    }
+}
@@ -0,0 +1,379 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, PositionLengthAttribute,
+    TermToBytesRefAttribute, TokenFilter, TokenStream, TypeAttribute, DEFAULT_TOKEN_TYPE,
+    HANGUL_TYPE, HIRAGANA_TYPE, IDEOGRAPHIC_TYPE, KATAKANA_TYPE,
+};
+
+use error::Result;
+
+use std::collections::VecDeque;
+
+/// The type of the bigrams emitted by a `CJKBigramFilter`.
+pub const DOUBLE_TYPE: &str = "<DOUBLE>";
+/// The type of the unigrams emitted by a `CJKBigramFilter` for CJK
+/// characters without neighbours.
+pub const SINGLE_TYPE: &str = "<SINGLE>";
+
+#[derive(Debug)]
+struct OutputToken {
+    term: Vec<u8>,
+    token_type: &'static str,
+    start_offset: usize,
+    end_offset: usize,
+    position_increment: u32,
+    position_length: u32,
+}
+
+/// Forms bigrams of the CJK characters tokenized by `StandardTokenizer`, e.g.
+/// "東京都" is turned into "東京" and "京都".
+///
+/// The characters of adjacent CJK tokens of the enabled scripts are joined
+/// into runs, which are broken by other tokens and by gaps in the offsets or
+/// positions. Runs of a single character are emitted as a unigram of type
+/// `SINGLE_TYPE`, longer runs as bigrams of type `DOUBLE_TYPE`. Tokens of
+/// other types are passed through unchanged.
+///
+/// Unigrams may be output along with the bigrams for better recall of
+/// single character queries, in this case the bigrams are stacked on the
+/// unigram of their first character and span two positions.
+#[derive(Debug)]
+pub struct CJKBigramFilter {
+    input: Box<dyn TokenStream>,
+    flags: u32,
+    output_unigrams: bool,
+    // chars of the current run of CJK tokens, with their offsets
+    run: Vec<(char, usize, usize)>,
+    run_position_increment: u32,
+    output: VecDeque<OutputToken>,
+    input_finished: bool,
+    term_attribute: CharTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    position_length_attribute: PositionLengthAttribute,
+    type_attribute: TypeAttribute,
+}
+
+impl CJKBigramFilter {
+    /// Flag to bigram Han (ideographic) characters.
+    pub const HAN: u32 = 1;
+    /// Flag to bigram Hiragana characters.
+    pub const HIRAGANA: u32 = 1 << 1;
+    /// Flag to bigram Katakana characters.
+    pub const KATAKANA: u32 = 1 << 2;
+    /// Flag to bigram Hangul characters.
+    pub const HANGUL: u32 = 1 << 3;
+    /// Flag to bigram all the CJK scripts.
+    pub const ALL: u32 = Self::HAN | Self::HIRAGANA | Self::KATAKANA | Self::HANGUL;
+
+    /// Creates a filter forming bigrams of the scripts enabled in `flags`, a
+    /// combination of `HAN`, `HIRAGANA`, `KATAKANA` and `HANGUL`.
+    pub fn new(input: Box<dyn TokenStream>, flags: u32) -> Self {
+        CJKBigramFilter {
+            input,
+            flags,
+            output_unigrams: false,
+            run: vec![],
+            run_position_increment: 0,
+            output: VecDeque::new(),
+            input_finished: false,
+            term_attribute: CharTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            position_length_attribute: PositionLengthAttribute::new(),
+            type_attribute: TypeAttribute::new(),
+        }
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Set whether unigrams are output along with the bigrams, false by
+    /// default.
+    pub fn set_output_unigrams(&mut self, output_unigrams: bool) {
+        self.output_unigrams = output_unigrams;
+    }
+
+    fn is_bigram_type(&self, token_type: &str) -> bool {
+        let flag = if token_type == IDEOGRAPHIC_TYPE {
+            Self::HAN
+        } else if token_type == HIRAGANA_TYPE {
+            Self::HIRAGANA
+        } else if token_type == KATAKANA_TYPE {
+            Self::KATAKANA
+        } else if token_type == HANGUL_TYPE {
+            Self::HANGUL
+        } else {
+            return false;
+        };
+        self.flags & flag != 0
+    }
+
+    /// Reads input tokens until at least one token is ready to be output.
+    fn fill_output(&mut self) -> Result<()> {
+        while self.output.is_empty() && !self.input_finished {
+            if !self.input.increment_token()? {
+                self.input_finished = true;
+                self.flush_run()?;
+                break;
+            }
+            let offset = self.input.offset_attribute();
+            let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+            let position_increment = self.input.position_attribute().get_position();
+            let token_type = self
+                .input
+                .type_attribute()
+                .map_or(DEFAULT_TOKEN_TYPE, |attr| attr.get_type());
+            let term = self
+                .input
+                .term_bytes_attribute()
+                .get_bytes_ref()
+                .bytes()
+                .to_vec();
+
+            if !self.is_bigram_type(token_type) {
+                self.flush_run()?;
+                self.output.push_back(OutputToken {
+                    term,
+                    token_type,
+                    start_offset,
+                    end_offset,
+                    position_increment,
+                    position_length: 1,
+                });
+                continue;
+            }
+
+            let contiguous = position_increment <= 1
+                && self
+                    .run
+                    .last()
+                    .map_or(false, |&(_, _, end)| end == start_offset);
+            if !contiguous {
+                self.flush_run()?;
+                self.run_position_increment = position_increment;
+            }
+            let text = String::from_utf8(term)?;
+            let char_count = text.chars().count();
+            for (i, c) in text.chars().enumerate() {
+                // offsets of the chars are only known if the token was not
+                // altered, otherwise the chars share the offsets of the token
+                if end_offset - start_offset == char_count {
+                    self.run.push((c, start_offset + i, start_offset + i + 1));
+                } else {
+                    self.run.push((c, start_offset, end_offset));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns the current run of CJK chars into output tokens.
+    fn flush_run(&mut self) -> Result<()> {
+        let mut position_increment = self.run_position_increment;
+        if self.run.len() == 1 {
+            let (c, start_offset, end_offset) = self.run[0];
+            self.output.push_back(OutputToken {
+                term: c.to_string().into_bytes(),
+                token_type: SINGLE_TYPE,
+                start_offset,
+                end_offset,
+                position_increment,
+                position_length: 1,
+            });
+        } else {
+            for i in 0..self.run.len() {
+                let (c, start_offset, end_offset) = self.run[i];
+                if self.output_unigrams {
+                    self.output.push_back(OutputToken {
+                        term: c.to_string().into_bytes(),
+                        token_type: SINGLE_TYPE,
+                        start_offset,
+                        end_offset,
+                        position_increment,
+                        position_length: 1,
+                    });
+                    position_increment = 0;
+                }
+                if i + 1 < self.run.len() {
+                    let (next, _, end_offset) = self.run[i + 1];
+                    let mut term = String::with_capacity(c.len_utf8() + next.len_utf8());
+                    term.push(c);
+                    term.push(next);
+                    self.output.push_back(OutputToken {
+                        term: term.into_bytes(),
+                        token_type: DOUBLE_TYPE,
+                        start_offset,
+                        end_offset,
+                        position_increment,
+                        position_length: if self.output_unigrams { 2 } else { 1 },
+                    });
+                }
+                position_increment = 1;
+            }
+        }
+        self.run.clear();
+        self.run_position_increment = 0;
+        Ok(())
+    }
+}
+
+impl TokenStream for CJKBigramFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.fill_output()?;
+        if let Some(token) = self.output.pop_front() {
+            self.clear_attributes();
+            self.term_attribute.copy_buffer(&token.term);
+            self.offset_attribute
+                .set_offset(token.start_offset, token.end_offset)?;
+            self.position_attribute
+                .set_position(token.position_increment);
+            self.position_length_attribute
+                .set_position_length(token.position_length);
+            self.type_attribute.set_type(token.token_type);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let offset = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        let position = self.input.position_attribute().get_position();
+        self.position_attribute.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.run.clear();
+        self.run_position_increment = 0;
+        self.output.clear();
+        self.input_finished = false;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        Some(&mut self.position_length_attribute)
+    }
+
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        Some(&self.position_length_attribute)
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attribute)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+impl TokenFilter for CJKBigramFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_graph_contents;
+    use core::analysis::StandardTokenizer;
+    use std::io::Cursor;
+
+    fn filter(text: &str, flags: u32, output_unigrams: bool) -> CJKBigramFilter {
+        let tokenizer = StandardTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = CJKBigramFilter::new(Box::new(tokenizer), flags);
+        filter.set_output_unigrams(output_unigrams);
+        filter
+    }
+
+    #[test]
+    fn test_bigrams() {
+        let mut stream = filter("東京都 in 日本 한국어", CJKBigramFilter::ALL, false);
+        assert_token_graph_contents(
+            &mut stream,
+            &["東京", "京都", "in", "日本", "한국", "국어"],
+            Some(&[(0, 2), (1, 3), (4, 6), (7, 9), (10, 12), (11, 13)]),
+            &[1, 1, 1, 1, 1, 1],
+            &[1, 1, 1, 1, 1, 1],
+        );
+        assert_eq!(stream.offset_attribute().end_offset(), 13);
+
+        let tokens = assert_token_graph_contents(
+            &mut filter("一 b", CJKBigramFilter::ALL, false),
+            &["一", "b"],
+            None,
+            &[1, 1],
+            &[1, 1],
+        );
+        assert_eq!(tokens[0].token_type, SINGLE_TYPE);
+    }
+
+    #[test]
+    fn test_unigrams_and_flags() {
+        assert_token_graph_contents(
+            &mut filter("東京都", CJKBigramFilter::ALL, true),
+            &["東", "東京", "京", "京都", "都"],
+            Some(&[(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)]),
+            &[1, 0, 1, 0, 1],
+            &[1, 2, 1, 2, 1],
+        );
+
+        // Hangul is not bigrammed, and breaks the run of Han chars
+        assert_token_graph_contents(
+            &mut filter("日本한국", CJKBigramFilter::HAN, false),
+            &["日本", "한국"],
+            Some(&[(0, 2), (2, 4)]),
+            &[1, 1],
+            &[1, 1],
+        );
+    }
+}
@@ -0,0 +1,973 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    language_stop_words, ASCIIFoldingFilter, Analyzer, CJKBigramFilter, CharArraySet, CharFilter,
+    ComponentsCache, DoubleMetaphone, EdgeNGramTokenFilter, FlattenGraphFilter,
+    HTMLStripCharFilter, KeywordTokenizer, LengthFilter, LetterTokenizer, LimitTokenCountFilter,
+    LowerCaseFilter, MappingCharFilter, Metaphone, NGramTokenFilter, NGramTokenizer,
+    NormalizeCharMap, PatternReplaceFilter, PatternTokenizer, PhoneticEncoder, PhoneticFilter,
+    RemoveDuplicatesTokenFilter, ReuseStrategy, ShingleFilter, SolrSynonymParser, Soundex,
+    StandardTokenizer, StemmerType, StopFilter, SynonymGraphFilter, SynonymMap, TokenStream,
+    TrimFilter, WhitespaceTokenizer, WordDelimiterGraphFilter, WordListFormat,
+    WordnetSynonymParser, DEFAULT_MAX_CODE_LENGTH, DEFAULT_MAX_NGRAM_SIZE,
+    DEFAULT_MAX_TOKEN_LENGTH, DEFAULT_MIN_NGRAM_SIZE, ENGLISH_STOP_WORDS_SET,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use regex::Regex;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The parameters of a component of a `CustomAnalyzer`, which are taken by
+/// its factory, so that unknown parameters can be reported.
+struct Params<'a> {
+    component: &'a str,
+    params: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Params<'a> {
+    fn new(component: &'a str, params: &[(&'a str, &'a str)]) -> Self {
+        Params {
+            component,
+            params: params.iter().cloned().collect(),
+        }
+    }
+
+    fn get(&mut self, name: &str) -> Option<&'a str> {
+        self.params.remove(name)
+    }
+
+    fn require(&mut self, name: &str) -> Result<&'a str> {
+        match self.get(name) {
+            Some(value) => Ok(value),
+            None => bail!(IllegalArgument(format!(
+                "{}: parameter \"{}\" is required",
+                self.component, name
+            ))),
+        }
+    }
+
+    fn require_usize(&mut self, name: &str) -> Result<usize> {
+        let value = self.require(name)?;
+        value.parse().map_err(|_| self.invalid(name, value))
+    }
+
+    fn get_usize(&mut self, name: &str, default: usize) -> Result<usize> {
+        match self.get(name) {
+            Some(value) => value.parse().map_err(|_| self.invalid(name, value)),
+            None => Ok(default),
+        }
+    }
+
+    fn get_bool(&mut self, name: &str, default: bool) -> Result<bool> {
+        match self.get(name) {
+            Some(value) => value.parse().map_err(|_| self.invalid(name, value)),
+            None => Ok(default),
+        }
+    }
+
+    fn get_regex(&mut self, name: &str) -> Result<Regex> {
+        let value = self.require(name)?;
+        Regex::new(value).map_err(|e| {
+            IllegalArgument(format!(
+                "{}: invalid pattern \"{}\": {}",
+                self.component, value, e
+            ))
+            .into()
+        })
+    }
+
+    fn invalid(&self, name: &str, value: &str) -> ::error::Error {
+        IllegalArgument(format!(
+            "{}: invalid value \"{}\" of parameter \"{}\"",
+            self.component, value, name
+        ))
+        .into()
+    }
+
+    /// Fails if some parameters weren't taken by the factory.
+    fn finish(self) -> Result<()> {
+        if !self.params.is_empty() {
+            let mut names: Vec<_> = self.params.keys().collect();
+            names.sort();
+            bail!(IllegalArgument(format!(
+                "{}: unknown parameters {:?}",
+                self.component, names
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum TokenizerSpec {
+    Standard {
+        max_token_length: usize,
+    },
+    Whitespace,
+    Letter,
+    Keyword,
+    NGram {
+        min_gram: usize,
+        max_gram: usize,
+        edge: bool,
+    },
+    Pattern {
+        pattern: Regex,
+        group: Option<usize>,
+    },
+}
+
+impl TokenizerSpec {
+    fn create(&self, reader: Box<dyn Read>) -> Result<Box<dyn TokenStream>> {
+        Ok(match *self {
+            TokenizerSpec::Standard { max_token_length } => {
+                let mut tokenizer = StandardTokenizer::new(reader);
+                tokenizer.set_max_token_length(max_token_length)?;
+                Box::new(tokenizer)
+            }
+            TokenizerSpec::Whitespace => Box::new(WhitespaceTokenizer::new(reader)),
+            TokenizerSpec::Letter => Box::new(LetterTokenizer::new(reader)),
+            TokenizerSpec::Keyword => Box::new(KeywordTokenizer::new(reader)),
+            TokenizerSpec::NGram {
+                min_gram,
+                max_gram,
+                edge,
+            } => {
+                if edge {
+                    Box::new(NGramTokenizer::edge(reader, min_gram, max_gram)?)
+                } else {
+                    Box::new(NGramTokenizer::new(reader, min_gram, max_gram)?)
+                }
+            }
+            TokenizerSpec::Pattern { ref pattern, group } => {
+                Box::new(PatternTokenizer::new(reader, pattern.clone(), group))
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+enum TokenFilterSpec {
+    LowerCase,
+    Stop {
+        words: Arc<CharArraySet>,
+    },
+    ASCIIFolding {
+        preserve_original: bool,
+    },
+    Stemmer(StemmerType),
+    Shingle {
+        min_shingle_size: usize,
+        max_shingle_size: usize,
+        output_unigrams: bool,
+        output_unigrams_if_no_shingles: bool,
+        token_separator: String,
+        filler_token: String,
+    },
+    NGram {
+        min_gram: usize,
+        max_gram: usize,
+        preserve_original: bool,
+        edge: bool,
+    },
+    PatternReplace {
+        pattern: Regex,
+        replacement: String,
+        replace_all: bool,
+    },
+    CJKBigram {
+        flags: u32,
+        output_unigrams: bool,
+    },
+    SynonymGraph {
+        synonyms: Arc<SynonymMap>,
+        ignore_case: bool,
+    },
+    WordDelimiterGraph {
+        flags: u32,
+        protected_words: Option<Arc<CharArraySet>>,
+    },
+    Phonetic {
+        encoder: Arc<dyn PhoneticEncoder>,
+        inject: bool,
+    },
+    Length {
+        min: usize,
+        max: usize,
+    },
+    LimitTokenCount {
+        max_token_count: usize,
+        consume_all_tokens: bool,
+    },
+    Trim,
+    RemoveDuplicates,
+    FlattenGraph,
+}
+
+impl TokenFilterSpec {
+    fn create(&self, input: Box<dyn TokenStream>) -> Result<Box<dyn TokenStream>> {
+        Ok(match *self {
+            TokenFilterSpec::LowerCase => Box::new(LowerCaseFilter::new(input)),
+            TokenFilterSpec::Stop { ref words } => {
+                Box::new(StopFilter::new(input, Arc::clone(words)))
+            }
+            TokenFilterSpec::ASCIIFolding { preserve_original } => {
+                Box::new(ASCIIFoldingFilter::new(input, preserve_original))
+            }
+            TokenFilterSpec::Stemmer(stemmer) => stemmer.create_filter(input),
+            TokenFilterSpec::Shingle {
+                min_shingle_size,
+                max_shingle_size,
+                output_unigrams,
+                output_unigrams_if_no_shingles,
+                ref token_separator,
+                ref filler_token,
+            } => {
+                let mut filter = ShingleFilter::new(input, min_shingle_size, max_shingle_size)?;
+                filter.set_output_unigrams(output_unigrams);
+                filter.set_output_unigrams_if_no_shingles(output_unigrams_if_no_shingles);
+                filter.set_token_separator(token_separator);
+                filter.set_filler_token(filler_token);
+                Box::new(filter)
+            }
+            TokenFilterSpec::NGram {
+                min_gram,
+                max_gram,
+                preserve_original,
+                edge,
+            } => {
+                if edge {
+                    Box::new(EdgeNGramTokenFilter::new(
+                        input,
+                        min_gram,
+                        max_gram,
+                        preserve_original,
+                    )?)
+                } else {
+                    Box::new(NGramTokenFilter::new(
+                        input,
+                        min_gram,
+                        max_gram,
+                        preserve_original,
+                    )?)
+                }
+            }
+            TokenFilterSpec::PatternReplace {
+                ref pattern,
+                ref replacement,
+                replace_all,
+            } => Box::new(PatternReplaceFilter::new(
+                input,
+                pattern.clone(),
+                replacement,
+                replace_all,
+            )),
+            TokenFilterSpec::CJKBigram {
+                flags,
+                output_unigrams,
+            } => {
+                let mut filter = CJKBigramFilter::new(input, flags);
+                filter.set_output_unigrams(output_unigrams);
+                Box::new(filter)
+            }
+            TokenFilterSpec::SynonymGraph {
+                ref synonyms,
+                ignore_case,
+            } => Box::new(SynonymGraphFilter::new(
+                input,
+                Arc::clone(synonyms),
+                ignore_case,
+            )),
+            TokenFilterSpec::WordDelimiterGraph {
+                flags,
+                ref protected_words,
+            } => Box::new(WordDelimiterGraphFilter::new(
+                input,
+                flags,
+                protected_words.clone(),
+            )),
+            TokenFilterSpec::Phonetic {
+                ref encoder,
+                inject,
+            } => Box::new(PhoneticFilter::new(input, Arc::clone(encoder), inject)),
+            TokenFilterSpec::Length { min, max } => Box::new(LengthFilter::new(input, min, max)?),
+            TokenFilterSpec::LimitTokenCount {
+                max_token_count,
+                consume_all_tokens,
+            } => Box::new(LimitTokenCountFilter::new(
+                input,
+                max_token_count,
+                consume_all_tokens,
+            )),
+            TokenFilterSpec::Trim => Box::new(TrimFilter::new(input)),
+            TokenFilterSpec::RemoveDuplicates => Box::new(RemoveDuplicatesTokenFilter::new(input)),
+            TokenFilterSpec::FlattenGraph => Box::new(FlattenGraphFilter::new(input)),
+        })
+    }
+}
+
+/// An analyzer assembled from char filters, a tokenizer and token filters
+/// given by name and parameters, e.g. read from a configuration file.
+///
+/// ```rust,ignore
+/// let analyzer = CustomAnalyzer::builder()
+///     .add_char_filter("htmlStrip", &[])?
+///     .with_tokenizer("standard", &[("maxTokenLength", "128")])?
+///     .add_token_filter("lowercase", &[])?
+///     .add_token_filter("stop", &[("words", "stopwords.txt"), ("ignoreCase", "true")])?
+///     .add_token_filter("snowballPorter", &[("language", "English")])?
+///     .build()?;
+/// ```
+///
+/// Names are case insensitive and parameters are named as in Lucene:
+///
+/// * char filters: `htmlStrip` (`escapedTags`), `mapping` (`mapping`)
+/// * tokenizers: `standard` (`maxTokenLength`), `whitespace`, `letter`, `keyword`, `nGram` and
+///   `edgeNGram` (`minGramSize`, `maxGramSize`), `pattern` (`pattern`, `group`)
+/// * token filters: `lowercase`, `stop` (`words`, `format`, `ignoreCase`, `language`),
+///   `asciiFolding` (`preserveOriginal`), `porterStem`, `snowballPorter` (`language`), `shingle`
+///   (`minShingleSize`, `maxShingleSize`, `outputUnigrams`, `outputUnigramsIfNoShingles`,
+///   `tokenSeparator`, `fillerToken`), `nGram` and `edgeNGram` (`minGramSize`, `maxGramSize`,
+///   `preserveOriginal`), `patternReplace` (`pattern`, `replacement`, `replace`), `cjkBigram`
+///   (`han`, `hiragana`, `katakana`, `hangul`, `outputUnigrams`), `synonymGraph` (`synonyms`,
+///   `format`, `expand`, `ignoreCase`), `wordDelimiterGraph` (`generateWordParts`,
+///   `generateNumberParts`, `catenateWords`, `catenateNumbers`, `catenateAll`, `preserveOriginal`,
+///   `splitOnCaseChange`, `splitOnNumerics`, `stemEnglishPossessive`, `protected`), `flattenGraph`
+///
+/// Parameters naming files, i.e. `words`, `mapping`, `synonyms` and
+/// `protected`, take a comma separated list of paths, relative to the
+/// config dir of the builder if any. The `language` of `stop`, exclusive
+/// with `words`, picks a bundled list, see `language_stop_words`.
+#[derive(Debug)]
+pub struct CustomAnalyzer {
+    char_filters: Vec<Box<dyn CharFilter>>,
+    tokenizer: TokenizerSpec,
+    token_filters: Vec<TokenFilterSpec>,
+    position_increment_gap: u32,
+    offset_gap: usize,
+    cache: ComponentsCache,
+}
+
+impl CustomAnalyzer {
+    pub fn builder() -> CustomAnalyzerBuilder {
+        CustomAnalyzerBuilder::new(None)
+    }
+
+    /// Creates a builder which resolves files relative to `config_dir`.
+    pub fn builder_with_dir<P: AsRef<Path>>(config_dir: P) -> CustomAnalyzerBuilder {
+        CustomAnalyzerBuilder::new(Some(config_dir.as_ref().to_path_buf()))
+    }
+}
+
+impl Analyzer for CustomAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let mut stream = self.tokenizer.create(reader)?;
+        for filter in &self.token_filters {
+            stream = filter.create(stream)?;
+        }
+        Ok(stream)
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+
+    fn position_increment_gap(&self, _field_name: &str) -> u32 {
+        self.position_increment_gap
+    }
+
+    fn offset_gap(&self, _field_name: &str) -> usize {
+        self.offset_gap
+    }
+
+    fn char_filters(&self, _field_name: &str) -> &[Box<dyn CharFilter>] {
+        &self.char_filters
+    }
+
+    fn normalize_stream(
+        &self,
+        _field_name: &str,
+        mut input: Box<dyn TokenStream>,
+    ) -> Box<dyn TokenStream> {
+        for filter in &self.token_filters {
+            input = match *filter {
+                TokenFilterSpec::LowerCase => Box::new(LowerCaseFilter::new(input)),
+                TokenFilterSpec::ASCIIFolding { .. } => {
+                    Box::new(ASCIIFoldingFilter::new(input, false))
+                }
+                _ => input,
+            };
+        }
+        input
+    }
+}
+
+/// Builds a `CustomAnalyzer`, see there for the available components.
+#[derive(Debug)]
+pub struct CustomAnalyzerBuilder {
+    config_dir: Option<PathBuf>,
+    char_filters: Vec<Box<dyn CharFilter>>,
+    tokenizer: Option<TokenizerSpec>,
+    token_filters: Vec<TokenFilterSpec>,
+    position_increment_gap: u32,
+    offset_gap: usize,
+}
+
+impl CustomAnalyzerBuilder {
+    fn new(config_dir: Option<PathBuf>) -> Self {
+        CustomAnalyzerBuilder {
+            config_dir,
+            char_filters: vec![],
+            tokenizer: None,
+            token_filters: vec![],
+            position_increment_gap: 0,
+            offset_gap: 1,
+        }
+    }
+
+    /// Adds a char filter, char filters are applied in the order they're
+    /// added.
+    pub fn add_char_filter(mut self, name: &str, params: &[(&str, &str)]) -> Result<Self> {
+        let mut params = Params::new(name, params);
+        let char_filter: Box<dyn CharFilter> = match name.to_lowercase().as_str() {
+            "htmlstrip" => {
+                let mut filter = HTMLStripCharFilter::new();
+                if let Some(tags) = params.get("escapedTags") {
+                    let tags: Vec<&str> = split_list(tags).collect();
+                    filter.set_escaped_tags(&tags);
+                }
+                Box::new(filter)
+            }
+            "mapping" => {
+                let mut builder = NormalizeCharMap::builder();
+                let rule = Regex::new(r#"^"(.*)"\s*=>\s*"(.*)"\s*$"#).unwrap();
+                for text in self.read_files(params.require("mapping")?)? {
+                    for line in text.lines() {
+                        if line.trim().is_empty() || line.starts_with('#') {
+                            continue;
+                        }
+                        let captures = match rule.captures(line) {
+                            Some(captures) => captures,
+                            None => bail!(IllegalArgument(format!(
+                                "mapping: invalid mapping rule \"{}\"",
+                                line
+                            ))),
+                        };
+                        builder.add(
+                            &unescape_mapping(&captures[1])?,
+                            &unescape_mapping(&captures[2])?,
+                        )?;
+                    }
+                }
+                Box::new(MappingCharFilter::new(Arc::new(builder.build())))
+            }
+            _ => bail!(IllegalArgument(format!("unknown char filter: {}", name))),
+        };
+        params.finish()?;
+        self.char_filters.push(char_filter);
+        Ok(self)
+    }
+
+    /// Set the tokenizer, there is exactly one.
+    pub fn with_tokenizer(mut self, name: &str, params: &[(&str, &str)]) -> Result<Self> {
+        if self.tokenizer.is_some() {
+            bail!(IllegalArgument("the tokenizer is already set".into()));
+        }
+        let mut params = Params::new(name, params);
+        let lower_name = name.to_lowercase();
+        let tokenizer = match lower_name.as_str() {
+            "standard" => TokenizerSpec::Standard {
+                max_token_length: params.get_usize("maxTokenLength", DEFAULT_MAX_TOKEN_LENGTH)?,
+            },
+            "whitespace" => TokenizerSpec::Whitespace,
+            "letter" => TokenizerSpec::Letter,
+            "keyword" => TokenizerSpec::Keyword,
+            "ngram" | "edgengram" => TokenizerSpec::NGram {
+                min_gram: params.get_usize("minGramSize", DEFAULT_MIN_NGRAM_SIZE)?,
+                max_gram: params.get_usize("maxGramSize", DEFAULT_MAX_NGRAM_SIZE)?,
+                edge: lower_name == "edgengram",
+            },
+            "pattern" => {
+                let pattern = params.get_regex("pattern")?;
+                let group = match params.get("group") {
+                    None | Some("-1") => None,
+                    Some(group) => Some(group.parse().map_err(|_| params.invalid("group", group))?),
+                };
+                TokenizerSpec::Pattern { pattern, group }
+            }
+            _ => bail!(IllegalArgument(format!("unknown tokenizer: {}", name))),
+        };
+        params.finish()?;
+        // check the parameters by creating the tokenizer once
+        tokenizer.create(Box::new(::std::io::empty()))?;
+        self.tokenizer = Some(tokenizer);
+        Ok(self)
+    }
+
+    /// Adds a token filter, token filters are applied in the order they're
+    /// added.
+    pub fn add_token_filter(mut self, name: &str, params: &[(&str, &str)]) -> Result<Self> {
+        let mut params = Params::new(name, params);
+        let lower_name = name.to_lowercase();
+        let filter = match lower_name.as_str() {
+            "lowercase" => TokenFilterSpec::LowerCase,
+            "stop" => {
+                let ignore_case = params.get_bool("ignoreCase", false)?;
+                let format: WordListFormat = match params.get("format") {
+                    Some(format) => format.parse()?,
+                    None => WordListFormat::WordSet,
+                };
+                let words = match (params.get("words"), params.get("language")) {
+                    (Some(_), Some(language)) => {
+                        // the stop words are either read from files or bundled
+                        return Err(params.invalid("language", language));
+                    }
+                    (Some(files), None) => {
+                        let mut words = CharArraySet::new(ignore_case);
+                        for text in self.read_files(files)? {
+                            format.add_words(&text, &mut words);
+                        }
+                        Arc::new(words)
+                    }
+                    (None, Some(language)) => Arc::new(language_stop_words(language, ignore_case)?),
+                    (None, None) if ignore_case => Arc::new(CharArraySet::from_words(
+                        &ENGLISH_STOP_WORDS_SET
+                            .iter()
+                            .map(|w| String::from_utf8_lossy(w).into_owned())
+                            .collect::<Vec<_>>(),
+                        true,
+                    )),
+                    (None, None) => ENGLISH_STOP_WORDS_SET.clone(),
+                };
+                TokenFilterSpec::Stop { words }
+            }
+            "asciifolding" => TokenFilterSpec::ASCIIFolding {
+                preserve_original: params.get_bool("preserveOriginal", false)?,
+            },
+            "porterstem" => TokenFilterSpec::Stemmer(StemmerType::Porter),
+            "snowballporter" => {
+                let language = params.get("language").unwrap_or("English");
+                TokenFilterSpec::Stemmer(StemmerType::Snowball(language.parse()?))
+            }
+            "shingle" => TokenFilterSpec::Shingle {
+                min_shingle_size: params.get_usize("minShingleSize", 2)?,
+                max_shingle_size: params.get_usize("maxShingleSize", 2)?,
+                output_unigrams: params.get_bool("outputUnigrams", true)?,
+                output_unigrams_if_no_shingles: params
+                    .get_bool("outputUnigramsIfNoShingles", false)?,
+                token_separator: params.get("tokenSeparator").unwrap_or(" ").to_string(),
+                filler_token: params.get("fillerToken").unwrap_or("_").to_string(),
+            },
+            "ngram" | "edgengram" => TokenFilterSpec::NGram {
+                min_gram: params.get_usize("minGramSize", DEFAULT_MIN_NGRAM_SIZE)?,
+                max_gram: params.get_usize("maxGramSize", DEFAULT_MAX_NGRAM_SIZE)?,
+                preserve_original: params.get_bool("preserveOriginal", false)?,
+                edge: lower_name == "edgengram",
+            },
+            "patternreplace" => {
+                let pattern = params.get_regex("pattern")?;
+                let replacement = params.get("replacement").unwrap_or("").to_string();
+                let replace_all = match params.get("replace") {
+                    None | Some("all") => true,
+                    Some("first") => false,
+                    Some(value) => return Err(params.invalid("replace", value)),
+                };
+                TokenFilterSpec::PatternReplace {
+                    pattern,
+                    replacement,
+                    replace_all,
+                }
+            }
+            "cjkbigram" => {
+                let mut flags = 0;
+                for &(param, flag) in &[
+                    ("han", CJKBigramFilter::HAN),
+                    ("hiragana", CJKBigramFilter::HIRAGANA),
+                    ("katakana", CJKBigramFilter::KATAKANA),
+                    ("hangul", CJKBigramFilter::HANGUL),
+                ] {
+                    if params.get_bool(param, true)? {
+                        flags |= flag;
+                    }
+                }
+                TokenFilterSpec::CJKBigram {
+                    flags,
+                    output_unigrams: params.get_bool("outputUnigrams", false)?,
+                }
+            }
+            "synonymgraph" => {
+                let files = params.require("synonyms")?;
+                let expand = params.get_bool("expand", true)?;
+                let ignore_case = params.get_bool("ignoreCase", false)?;
+                let format = params.get("format").unwrap_or("solr");
+                let mut analyzer = CustomAnalyzer::builder().with_tokenizer("whitespace", &[])?;
+                if ignore_case {
+                    analyzer = analyzer.add_token_filter("lowercase", &[])?;
+                }
+                let analyzer = analyzer.build()?;
+                let synonyms = match format {
+                    "solr" => {
+                        let mut parser = SolrSynonymParser::new(true, expand, &analyzer);
+                        for text in self.read_files(files)? {
+                            parser.parse(text.as_bytes())?;
+                        }
+                        parser.build()?
+                    }
+                    "wordnet" => {
+                        let mut parser = WordnetSynonymParser::new(true, expand, &analyzer);
+                        for text in self.read_files(files)? {
+                            parser.parse(text.as_bytes())?;
+                        }
+                        parser.build()?
+                    }
+                    _ => return Err(params.invalid("format", format)),
+                };
+                TokenFilterSpec::SynonymGraph {
+                    synonyms: Arc::new(synonyms),
+                    ignore_case,
+                }
+            }
+            "worddelimitergraph" => {
+                type F = WordDelimiterGraphFilter;
+                let mut flags = 0;
+                // the flags are given as 0 or 1 like in Lucene
+                for &(param, flag, default) in &[
+                    ("generateWordParts", F::GENERATE_WORD_PARTS, 1),
+                    ("generateNumberParts", F::GENERATE_NUMBER_PARTS, 1),
+                    ("catenateWords", F::CATENATE_WORDS, 0),
+                    ("catenateNumbers", F::CATENATE_NUMBERS, 0),
+                    ("catenateAll", F::CATENATE_ALL, 0),
+                    ("preserveOriginal", F::PRESERVE_ORIGINAL, 0),
+                    ("splitOnCaseChange", F::SPLIT_ON_CASE_CHANGE, 1),
+                    ("splitOnNumerics", F::SPLIT_ON_NUMERICS, 1),
+                    ("stemEnglishPossessive", F::STEM_ENGLISH_POSSESSIVE, 1),
+                ] {
+                    if params.get_usize(param, default)? != 0 {
+                        flags |= flag;
+                    }
+                }
+                let protected_words = match params.get("protected") {
+                    Some(files) => {
+                        let mut words = CharArraySet::new(false);
+                        for text in self.read_files(files)? {
+                            WordListFormat::WordSet.add_words(&text, &mut words);
+                        }
+                        Some(Arc::new(words))
+                    }
+                    None => None,
+                };
+                TokenFilterSpec::WordDelimiterGraph {
+                    flags,
+                    protected_words,
+                }
+            }
+            "phonetic" | "doublemetaphone" => {
+                let encoder_name = if lower_name == "phonetic" {
+                    params.require("encoder")?
+                } else {
+                    "DoubleMetaphone"
+                };
+                let max_code_length = params.get_usize("maxCodeLength", DEFAULT_MAX_CODE_LENGTH)?;
+                let encoder: Arc<dyn PhoneticEncoder> = match encoder_name.to_lowercase().as_str() {
+                    "soundex" => Arc::new(Soundex),
+                    "metaphone" => {
+                        let mut encoder = Metaphone::new();
+                        encoder.set_max_code_length(max_code_length);
+                        Arc::new(encoder)
+                    }
+                    "doublemetaphone" => {
+                        let mut encoder = DoubleMetaphone::new();
+                        encoder.set_max_code_length(max_code_length);
+                        Arc::new(encoder)
+                    }
+                    _ => return Err(params.invalid("encoder", encoder_name)),
+                };
+                TokenFilterSpec::Phonetic {
+                    encoder,
+                    inject: params.get_bool("inject", true)?,
+                }
+            }
+            "length" => TokenFilterSpec::Length {
+                min: params.require_usize("min")?,
+                max: params.require_usize("max")?,
+            },
+            "limittokencount" => TokenFilterSpec::LimitTokenCount {
+                max_token_count: params.require_usize("maxTokenCount")?,
+                consume_all_tokens: params.get_bool("consumeAllTokens", false)?,
+            },
+            "trim" => TokenFilterSpec::Trim,
+            "removeduplicates" => TokenFilterSpec::RemoveDuplicates,
+            "flattengraph" => TokenFilterSpec::FlattenGraph,
+            _ => bail!(IllegalArgument(format!("unknown token filter: {}", name))),
+        };
+        params.finish()?;
+        // check the parameters by creating the filter once
+        filter.create(Box::new(KeywordTokenizer::new(
+            Box::new(::std::io::empty()),
+        )))?;
+        self.token_filters.push(filter);
+        Ok(self)
+    }
+
+    /// Set the position increment gap between the values of multi-valued
+    /// fields, 0 by default.
+    pub fn with_position_increment_gap(mut self, gap: u32) -> Self {
+        self.position_increment_gap = gap;
+        self
+    }
+
+    /// Set the offset gap between the values of multi-valued fields, 1 by
+    /// default.
+    pub fn with_offset_gap(mut self, gap: usize) -> Self {
+        self.offset_gap = gap;
+        self
+    }
+
+    pub fn build(self) -> Result<CustomAnalyzer> {
+        let tokenizer = match self.tokenizer {
+            Some(tokenizer) => tokenizer,
+            None => bail!(IllegalArgument("a tokenizer is required".into())),
+        };
+        Ok(CustomAnalyzer {
+            char_filters: self.char_filters,
+            tokenizer,
+            token_filters: self.token_filters,
+            position_increment_gap: self.position_increment_gap,
+            offset_gap: self.offset_gap,
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        })
+    }
+
+    /// Reads the files of a comma separated list of paths.
+    fn read_files(&self, files: &str) -> Result<Vec<String>> {
+        split_list(files)
+            .map(|file| {
+                let path = match self.config_dir {
+                    Some(ref dir) => dir.join(file),
+                    None => PathBuf::from(file),
+                };
+                Ok(fs::read_to_string(path)?)
+            })
+            .collect()
+    }
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+}
+
+/// Unescapes a string of a mapping rule, which may contain `\\`, `\"`,
+/// `\n`, `\t`, `\r`, `\b`, `\f` and `\uXXXX` escapes.
+fn unescape_mapping(s: &str) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let unescaped = match chars.next() {
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(::std::char::from_u32)
+                {
+                    Some(c) if hex.len() == 4 => c,
+                    _ => bail!(IllegalArgument(format!(
+                        "mapping: invalid escape in \"{}\"",
+                        s
+                    ))),
+                }
+            }
+            _ => bail!(IllegalArgument(format!(
+                "mapping: invalid escape in \"{}\"",
+                s
+            ))),
+        };
+        result.push(unescaped);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_custom_analyzer() {
+        let analyzer = CustomAnalyzer::builder()
+            .add_char_filter("htmlStrip", &[])
+            .unwrap()
+            .with_tokenizer("standard", &[])
+            .unwrap()
+            .add_token_filter("wordDelimiterGraph", &[("catenateWords", "1")])
+            .unwrap()
+            .add_token_filter("lowercase", &[])
+            .unwrap()
+            .add_token_filter("stop", &[])
+            .unwrap()
+            .add_token_filter("porterStem", &[])
+            .unwrap()
+            .with_position_increment_gap(100)
+            .build()
+            .unwrap();
+        assert_analyzes_to(
+            &analyzer,
+            "<b>The</b> Running <i>Dogs</i> WiFi",
+            &["run", "dog", "wifi", "wi", "fi"],
+            Some(&[(11, 18), (22, 26), (31, 35), (31, 33), (33, 35)]),
+            None,
+        );
+        assert_eq!(analyzer.position_increment_gap("body"), 100);
+        assert_eq!(analyzer.normalize("body", "Dogs").unwrap(), b"dogs");
+    }
+
+    #[test]
+    fn test_invalid_components() {
+        let builder = CustomAnalyzer::builder;
+        assert!(builder().build().is_err());
+        assert!(builder().with_tokenizer("unknown", &[]).is_err());
+        assert!(builder().add_token_filter("unknown", &[]).is_err());
+        assert!(builder().add_char_filter("unknown", &[]).is_err());
+        assert!(builder()
+            .with_tokenizer("whitespace", &[("maxTokenLength", "10")])
+            .is_err());
+        assert!(builder()
+            .with_tokenizer("nGram", &[("minGramSize", "x")])
+            .is_err());
+        assert!(builder()
+            .with_tokenizer("nGram", &[("minGramSize", "3")])
+            .is_err());
+        assert!(builder().with_tokenizer("pattern", &[]).is_err());
+        assert!(builder()
+            .with_tokenizer("pattern", &[("pattern", "(")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("snowballPorter", &[("language", "Klingon")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("phonetic", &[("encoder", "Caverphone")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("length", &[("min", "2")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("length", &[("min", "3"), ("max", "2")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("stop", &[("words", "missing.txt")])
+            .is_err());
+        assert!(builder()
+            .with_tokenizer("keyword", &[])
+            .unwrap()
+            .with_tokenizer("keyword", &[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_files() {
+        let dir =
+            ::std::env::temp_dir().join(format!("rucene_custom_analyzer_{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stop.txt"), "# stop words\nthe\n  of \n").unwrap();
+        fs::write(
+            dir.join("mapping.txt"),
+            "\"ß\" => \"ss\"\n\"\\u00e6\" => \"ae\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("synonyms.txt"), "tv, television\n").unwrap();
+
+        let analyzer = CustomAnalyzer::builder_with_dir(&dir)
+            .add_char_filter("mapping", &[("mapping", "mapping.txt")])
+            .unwrap()
+            .with_tokenizer("whitespace", &[])
+            .unwrap()
+            .add_token_filter("stop", &[("words", "stop.txt"), ("ignoreCase", "true")])
+            .unwrap()
+            .add_token_filter(
+                "synonymGraph",
+                &[("synonyms", "synonyms.txt"), ("expand", "false")],
+            )
+            .unwrap()
+            .add_token_filter("flattenGraph", &[])
+            .unwrap()
+            .build()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_analyzes_to(
+            &analyzer,
+            "The Straße of æther television",
+            &["Strasse", "aether", "tv"],
+            Some(&[(4, 10), (14, 19), (20, 30)]),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_stop_words() {
+        let dir =
+            ::std::env::temp_dir().join(format!("rucene_stop_words_{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stop.txt"), "| german\nund oder | and, or\n").unwrap();
+        let analyzer = CustomAnalyzer::builder_with_dir(&dir)
+            .with_tokenizer("whitespace", &[])
+            .unwrap()
+            .add_token_filter("stop", &[("words", "stop.txt"), ("format", "snowball")])
+            .unwrap()
+            .add_token_filter("stop", &[("language", "fr"), ("ignoreCase", "true")])
+            .unwrap()
+            .build()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_analyzes_to(
+            &analyzer,
+            "Les chats und les chiens oder",
+            &["chats", "chiens"],
+            None,
+            None,
+        );
+
+        let builder = CustomAnalyzer::builder;
+        assert!(builder()
+            .add_token_filter("stop", &[("language", "tlh")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("stop", &[("format", "csv")])
+            .is_err());
+        assert!(builder()
+            .add_token_filter("stop", &[("words", "stop.txt"), ("language", "fr")])
+            .is_err());
+    }
+}
@@ -0,0 +1,745 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{PhoneticEncoder, DEFAULT_MAX_CODE_LENGTH};
+
+const L_R_N_M_B_H_F_V_W_SPACE: &[&str] = &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "];
+const ES_EP_EB_EL_EY_IB_IL_IN_IE_EI_ER: &[&str] = &[
+    "ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER",
+];
+const L_T_K_S_N_M_B_Z: &[&str] = &["L", "T", "K", "S", "N", "M", "B", "Z"];
+
+/// The Double Metaphone algorithm by Lawrence Philips, which gives a
+/// primary and an alternate code to words of ambiguous pronunciation, e.g.
+/// "Smith" is encoded into "SM0" and "XMT".
+#[derive(Debug, Clone, Copy)]
+pub struct DoubleMetaphone {
+    max_code_length: usize,
+}
+
+impl Default for DoubleMetaphone {
+    fn default() -> Self {
+        DoubleMetaphone::new()
+    }
+}
+
+impl DoubleMetaphone {
+    pub fn new() -> Self {
+        DoubleMetaphone {
+            max_code_length: DEFAULT_MAX_CODE_LENGTH,
+        }
+    }
+
+    /// Set the maximum length of the codes, `DEFAULT_MAX_CODE_LENGTH` by
+    /// default.
+    pub fn set_max_code_length(&mut self, max_code_length: usize) {
+        self.max_code_length = max_code_length;
+    }
+
+    pub fn max_code_length(&self) -> usize {
+        self.max_code_length
+    }
+
+    /// Encodes `text` into its primary and alternate codes.
+    pub fn double_metaphone(&self, text: &str) -> (String, String) {
+        let value: Vec<char> = text.trim().chars().flat_map(char::to_uppercase).collect();
+        let mut encoder = Encoder {
+            value: &value,
+            slavo_germanic: false,
+            primary: String::with_capacity(self.max_code_length),
+            alternate: String::with_capacity(self.max_code_length),
+            max_code_length: self.max_code_length,
+        };
+        if !value.is_empty() {
+            encoder.encode();
+        }
+        (encoder.primary, encoder.alternate)
+    }
+}
+
+impl PhoneticEncoder for DoubleMetaphone {
+    fn encode(&self, text: &str) -> String {
+        self.double_metaphone(text).0
+    }
+
+    fn encode_alternates(&self, text: &str) -> Vec<String> {
+        vec![self.double_metaphone(text).1]
+    }
+}
+
+struct Encoder<'a> {
+    value: &'a [char],
+    slavo_germanic: bool,
+    primary: String,
+    alternate: String,
+    max_code_length: usize,
+}
+
+impl<'a> Encoder<'a> {
+    fn len(&self) -> isize {
+        self.value.len() as isize
+    }
+
+    fn char_at(&self, index: isize) -> char {
+        if index < 0 || index >= self.len() {
+            '\0'
+        } else {
+            self.value[index as usize]
+        }
+    }
+
+    /// Whether the `length` chars at `start` are one of `criteria`.
+    fn contains(&self, start: isize, length: isize, criteria: &[&str]) -> bool {
+        if start < 0 || start + length > self.len() {
+            return false;
+        }
+        let target = &self.value[start as usize..(start + length) as usize];
+        criteria
+            .iter()
+            .any(|c| c.chars().eq(target.iter().cloned()))
+    }
+
+    fn is_vowel(c: char) -> bool {
+        "AEIOUY".contains(c)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.primary.len() >= self.max_code_length && self.alternate.len() >= self.max_code_length
+    }
+
+    fn append_primary(&mut self, code: &str) {
+        let remaining = self.max_code_length.saturating_sub(self.primary.len());
+        self.primary.extend(code.chars().take(remaining));
+    }
+
+    fn append_alternate(&mut self, code: &str) {
+        let remaining = self.max_code_length.saturating_sub(self.alternate.len());
+        self.alternate.extend(code.chars().take(remaining));
+    }
+
+    fn append(&mut self, code: &str) {
+        self.append_primary(code);
+        self.append_alternate(code);
+    }
+
+    fn append2(&mut self, primary: &str, alternate: &str) {
+        self.append_primary(primary);
+        self.append_alternate(alternate);
+    }
+
+    fn encode(&mut self) {
+        self.slavo_germanic = self.value.contains(&'W')
+            || self.value.contains(&'K')
+            || self.value.windows(2).any(|w| w == ['C', 'Z'])
+            || self.value.windows(4).any(|w| w == ['W', 'I', 'T', 'Z']);
+        let mut index = if self.contains(0, 2, &["GN", "KN", "PN", "WR", "PS"]) {
+            1
+        } else {
+            0
+        };
+        while !self.is_complete() && index < self.len() {
+            index = match self.char_at(index) {
+                'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                    if index == 0 {
+                        self.append("A");
+                    }
+                    index + 1
+                }
+                'B' => {
+                    self.append("P");
+                    self.skip_double(index, 'B')
+                }
+                'Ç' => {
+                    self.append("S");
+                    index + 1
+                }
+                'C' => self.handle_c(index),
+                'D' => self.handle_d(index),
+                'F' => {
+                    self.append("F");
+                    self.skip_double(index, 'F')
+                }
+                'G' => self.handle_g(index),
+                'H' => self.handle_h(index),
+                'J' => self.handle_j(index),
+                'K' => {
+                    self.append("K");
+                    self.skip_double(index, 'K')
+                }
+                'L' => self.handle_l(index),
+                'M' => {
+                    self.append("M");
+                    if self.condition_m0(index) {
+                        index + 2
+                    } else {
+                        index + 1
+                    }
+                }
+                'N' => {
+                    self.append("N");
+                    self.skip_double(index, 'N')
+                }
+                'Ñ' => {
+                    self.append("N");
+                    index + 1
+                }
+                'P' => self.handle_p(index),
+                'Q' => {
+                    self.append("K");
+                    self.skip_double(index, 'Q')
+                }
+                'R' => self.handle_r(index),
+                'S' => self.handle_s(index),
+                'T' => self.handle_t(index),
+                'V' => {
+                    self.append("F");
+                    self.skip_double(index, 'V')
+                }
+                'W' => self.handle_w(index),
+                'X' => self.handle_x(index),
+                'Z' => self.handle_z(index),
+                _ => index + 1,
+            };
+        }
+    }
+
+    /// The index after the char at `index`, and after the next one if it
+    /// is `c`.
+    fn skip_double(&self, index: isize, c: char) -> isize {
+        if self.char_at(index + 1) == c {
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_c(&mut self, index: isize) -> isize {
+        if self.condition_c0(index) {
+            // very confusing condition
+            self.append("K");
+            index + 2
+        } else if index == 0 && self.contains(index, 6, &["CAESAR"]) {
+            self.append("S");
+            index + 2
+        } else if self.contains(index, 2, &["CH"]) {
+            self.handle_ch(index)
+        } else if self.contains(index, 2, &["CZ"]) && !self.contains(index - 2, 4, &["WICZ"]) {
+            // "Czerny"
+            self.append2("S", "X");
+            index + 2
+        } else if self.contains(index + 1, 3, &["CIA"]) {
+            // "focaccia"
+            self.append("X");
+            index + 3
+        } else if self.contains(index, 2, &["CC"]) && !(index == 1 && self.char_at(0) == 'M') {
+            // double "cc" but not "McClelland"
+            self.handle_cc(index)
+        } else if self.contains(index, 2, &["CK", "CG", "CQ"]) {
+            self.append("K");
+            index + 2
+        } else if self.contains(index, 2, &["CI", "CE", "CY"]) {
+            // Italian vs. English
+            if self.contains(index, 3, &["CIO", "CIE", "CIA"]) {
+                self.append2("S", "X");
+            } else {
+                self.append("S");
+            }
+            index + 2
+        } else {
+            self.append("K");
+            if self.contains(index + 1, 2, &[" C", " Q", " G"]) {
+                // Mac Caffrey, Mac Gregor
+                index + 3
+            } else if self.contains(index + 1, 1, &["C", "K", "Q"])
+                && !self.contains(index + 1, 2, &["CE", "CI"])
+            {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_cc(&mut self, index: isize) -> isize {
+        if self.contains(index + 2, 1, &["I", "E", "H"]) && !self.contains(index + 2, 2, &["HU"]) {
+            // "bellocchio" but not "bacchus"
+            if (index == 1 && self.char_at(index - 1) == 'A')
+                || self.contains(index - 1, 5, &["UCCEE", "UCCES"])
+            {
+                // "accident", "accede", "succeed"
+                self.append("KS");
+            } else {
+                // "bacci", "bertucci", other Italian
+                self.append("X");
+            }
+            index + 3
+        } else {
+            // Pierce's rule
+            self.append("K");
+            index + 2
+        }
+    }
+
+    fn handle_ch(&mut self, index: isize) -> isize {
+        if index > 0 && self.contains(index, 4, &["CHAE"]) {
+            // Michael
+            self.append2("K", "X");
+        } else if self.condition_ch0(index) || self.condition_ch1(index) {
+            // Greek roots ("chemistry", "chorus"), Germanic
+            self.append("K");
+        } else if index > 0 {
+            if self.contains(0, 2, &["MC"]) {
+                self.append("K");
+            } else {
+                self.append2("X", "K");
+            }
+        } else {
+            self.append("X");
+        }
+        index + 2
+    }
+
+    fn handle_d(&mut self, index: isize) -> isize {
+        if self.contains(index, 2, &["DG"]) {
+            if self.contains(index + 2, 1, &["I", "E", "Y"]) {
+                // "edge"
+                self.append("J");
+                index + 3
+            } else {
+                // "Edgar"
+                self.append("TK");
+                index + 2
+            }
+        } else if self.contains(index, 2, &["DT", "DD"]) {
+            self.append("T");
+            index + 2
+        } else {
+            self.append("T");
+            index + 1
+        }
+    }
+
+    fn handle_g(&mut self, index: isize) -> isize {
+        let slavo_germanic = self.slavo_germanic;
+        if self.char_at(index + 1) == 'H' {
+            self.handle_gh(index)
+        } else if self.char_at(index + 1) == 'N' {
+            if index == 1 && Self::is_vowel(self.char_at(0)) && !slavo_germanic {
+                self.append2("KN", "N");
+            } else if !self.contains(index + 2, 2, &["EY"])
+                && self.char_at(index + 1) != 'Y'
+                && !slavo_germanic
+            {
+                self.append2("N", "KN");
+            } else {
+                self.append("KN");
+            }
+            index + 2
+        } else if self.contains(index + 1, 2, &["LI"]) && !slavo_germanic {
+            self.append2("KL", "L");
+            index + 2
+        } else if index == 0
+            && (self.char_at(index + 1) == 'Y'
+                || self.contains(index + 1, 2, ES_EP_EB_EL_EY_IB_IL_IN_IE_EI_ER))
+        {
+            // -ges-, -gep-, -gel-, -gie- at the beginning
+            self.append2("K", "J");
+            index + 2
+        } else if (self.contains(index + 1, 2, &["ER"]) || self.char_at(index + 1) == 'Y')
+            && !self.contains(0, 6, &["DANGER", "RANGER", "MANGER"])
+            && !self.contains(index - 1, 1, &["E", "I"])
+            && !self.contains(index - 1, 3, &["RGY", "OGY"])
+        {
+            // -ger-, -gy-
+            self.append2("K", "J");
+            index + 2
+        } else if self.contains(index + 1, 1, &["E", "I", "Y"])
+            || self.contains(index - 1, 4, &["AGGI", "OGGI"])
+        {
+            // Italian "biaggi"
+            if self.contains(0, 4, &["VAN ", "VON "])
+                || self.contains(0, 3, &["SCH"])
+                || self.contains(index + 1, 2, &["ET"])
+            {
+                // obvious germanic
+                self.append("K");
+            } else if self.contains(index + 1, 3, &["IER"]) {
+                self.append("J");
+            } else {
+                self.append2("J", "K");
+            }
+            index + 2
+        } else if self.char_at(index + 1) == 'G' {
+            self.append("K");
+            index + 2
+        } else {
+            self.append("K");
+            index + 1
+        }
+    }
+
+    fn handle_gh(&mut self, index: isize) -> isize {
+        if index > 0 && !Self::is_vowel(self.char_at(index - 1)) {
+            self.append("K");
+        } else if index == 0 {
+            if self.char_at(index + 2) == 'I' {
+                self.append("J");
+            } else {
+                self.append("K");
+            }
+        } else if (index > 1 && self.contains(index - 2, 1, &["B", "H", "D"]))
+            || (index > 2 && self.contains(index - 3, 1, &["B", "H", "D"]))
+            || (index > 3 && self.contains(index - 4, 1, &["B", "H"]))
+        {
+            // Parker's rule, "hugh", "bough", "broughton": silent
+        } else if index > 2
+            && self.char_at(index - 1) == 'U'
+            && self.contains(index - 3, 1, &["C", "G", "L", "R", "T"])
+        {
+            // "laugh", "McLaughlin", "cough", "gough", "rough", "tough"
+            self.append("F");
+        } else if index > 0 && self.char_at(index - 1) != 'I' {
+            self.append("K");
+        }
+        index + 2
+    }
+
+    fn handle_h(&mut self, index: isize) -> isize {
+        // only keep if first & before vowel or between 2 vowels
+        if (index == 0 || Self::is_vowel(self.char_at(index - 1)))
+            && Self::is_vowel(self.char_at(index + 1))
+        {
+            self.append("H");
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_j(&mut self, index: isize) -> isize {
+        if self.contains(index, 4, &["JOSE"]) || self.contains(0, 4, &["SAN "]) {
+            // obvious Spanish, "Jose", "San Jacinto"
+            if (index == 0 && self.char_at(index + 4) == ' ')
+                || self.len() == 4
+                || self.contains(0, 4, &["SAN "])
+            {
+                self.append("H");
+            } else {
+                self.append2("J", "H");
+            }
+            return index + 1;
+        }
+        if index == 0 {
+            self.append2("J", "A");
+        } else if Self::is_vowel(self.char_at(index - 1))
+            && !self.slavo_germanic
+            && (self.char_at(index + 1) == 'A' || self.char_at(index + 1) == 'O')
+        {
+            self.append2("J", "H");
+        } else if index == self.len() - 1 {
+            self.append2("J", " ");
+        } else if !self.contains(index + 1, 1, L_T_K_S_N_M_B_Z)
+            && !self.contains(index - 1, 1, &["S", "K", "L"])
+        {
+            self.append("J");
+        }
+        self.skip_double(index, 'J')
+    }
+
+    fn handle_l(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'L' {
+            if self.condition_l0(index) {
+                self.append_primary("L");
+            } else {
+                self.append("L");
+            }
+            index + 2
+        } else {
+            self.append("L");
+            index + 1
+        }
+    }
+
+    fn handle_p(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'H' {
+            self.append("F");
+            index + 2
+        } else {
+            self.append("P");
+            if self.contains(index + 1, 1, &["P", "B"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_r(&mut self, index: isize) -> isize {
+        if index == self.len() - 1
+            && !self.slavo_germanic
+            && self.contains(index - 2, 2, &["IE"])
+            && !self.contains(index - 4, 2, &["ME", "MA"])
+        {
+            // French "Rogier"
+            self.append_alternate("R");
+        } else {
+            self.append("R");
+        }
+        self.skip_double(index, 'R')
+    }
+
+    fn handle_s(&mut self, index: isize) -> isize {
+        if self.contains(index - 1, 3, &["ISL", "YSL"]) {
+            // special cases "island", "isle", "carlisle", "carlysle"
+            index + 1
+        } else if index == 0 && self.contains(index, 5, &["SUGAR"]) {
+            // special case "sugar-"
+            self.append2("X", "S");
+            index + 1
+        } else if self.contains(index, 2, &["SH"]) {
+            if self.contains(index + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+                // germanic
+                self.append("S");
+            } else {
+                self.append("X");
+            }
+            index + 2
+        } else if self.contains(index, 3, &["SIO", "SIA"]) || self.contains(index, 4, &["SIAN"]) {
+            // Italian and Armenian
+            if self.slavo_germanic {
+                self.append("S");
+            } else {
+                self.append2("S", "X");
+            }
+            index + 3
+        } else if (index == 0 && self.contains(index + 1, 1, &["M", "N", "L", "W"]))
+            || self.contains(index + 1, 1, &["Z"])
+        {
+            // german & anglicisations, e.g. "smith" matches "schmidt"
+            self.append2("S", "X");
+            if self.contains(index + 1, 1, &["Z"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        } else if self.contains(index, 2, &["SC"]) {
+            self.handle_sc(index)
+        } else {
+            if index == self.len() - 1 && self.contains(index - 2, 2, &["AI", "OI"]) {
+                // French e.g. "resnais", "artois"
+                self.append_alternate("S");
+            } else {
+                self.append("S");
+            }
+            if self.contains(index + 1, 1, &["S", "Z"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_sc(&mut self, index: isize) -> isize {
+        if self.char_at(index + 2) == 'H' {
+            // Schlesinger's rule
+            if self.contains(index + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                // Dutch origin, e.g. "school", "schooner"
+                if self.contains(index + 3, 2, &["ER", "EN"]) {
+                    // "schermerhorn", "schenker"
+                    self.append2("X", "SK");
+                } else {
+                    self.append("SK");
+                }
+            } else if index == 0 && !Self::is_vowel(self.char_at(3)) && self.char_at(3) != 'W' {
+                self.append2("X", "S");
+            } else {
+                self.append("X");
+            }
+        } else if self.contains(index + 2, 1, &["I", "E", "Y"]) {
+            self.append("S");
+        } else {
+            self.append("SK");
+        }
+        index + 3
+    }
+
+    fn handle_t(&mut self, index: isize) -> isize {
+        if self.contains(index, 4, &["TION"]) || self.contains(index, 3, &["TIA", "TCH"]) {
+            self.append("X");
+            index + 3
+        } else if self.contains(index, 2, &["TH"]) || self.contains(index, 3, &["TTH"]) {
+            if self.contains(index + 2, 2, &["OM", "AM"])
+                // special case "thomas", "thames" or germanic
+                || self.contains(0, 4, &["VAN ", "VON "])
+                || self.contains(0, 3, &["SCH"])
+            {
+                self.append("T");
+            } else {
+                self.append2("0", "T");
+            }
+            index + 2
+        } else {
+            self.append("T");
+            if self.contains(index + 1, 1, &["T", "D"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_w(&mut self, index: isize) -> isize {
+        if self.contains(index, 2, &["WR"]) {
+            // can also be in middle of word
+            self.append("R");
+            index + 2
+        } else if index == 0
+            && (Self::is_vowel(self.char_at(index + 1)) || self.contains(index, 2, &["WH"]))
+        {
+            if Self::is_vowel(self.char_at(index + 1)) {
+                // Wasserman should match Vasserman
+                self.append2("A", "F");
+            } else {
+                // need Uomo to match Womo
+                self.append("A");
+            }
+            index + 1
+        } else if (index == self.len() - 1 && Self::is_vowel(self.char_at(index - 1)))
+            || self.contains(index - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+            || self.contains(0, 3, &["SCH"])
+        {
+            // Arnow should match Arnoff
+            self.append_alternate("F");
+            index + 1
+        } else if self.contains(index, 4, &["WICZ", "WITZ"]) {
+            // Polish e.g. "filipowicz"
+            self.append2("TS", "FX");
+            index + 4
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_x(&mut self, index: isize) -> isize {
+        if index == 0 {
+            self.append("S");
+            return index + 1;
+        }
+        if !(index == self.len() - 1
+            && (self.contains(index - 3, 3, &["IAU", "EAU"])
+                || self.contains(index - 2, 2, &["AU", "OU"])))
+        {
+            // French e.g. "breaux"
+            self.append("KS");
+        }
+        if self.contains(index + 1, 1, &["C", "X"]) {
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_z(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'H' {
+            // Chinese pinyin e.g. "Zhao"
+            self.append("J");
+            index + 2
+        } else {
+            if self.contains(index + 1, 2, &["ZO", "ZI", "ZA"])
+                || (self.slavo_germanic && (index > 0 && self.char_at(index - 1) != 'T'))
+            {
+                self.append2("S", "TS");
+            } else {
+                self.append("S");
+            }
+            self.skip_double(index, 'Z')
+        }
+    }
+
+    fn condition_c0(&self, index: isize) -> bool {
+        if self.contains(index, 4, &["CHIA"]) {
+            true
+        } else if index <= 1
+            || Self::is_vowel(self.char_at(index - 2))
+            || !self.contains(index - 1, 3, &["ACH"])
+        {
+            false
+        } else {
+            let c = self.char_at(index + 2);
+            (c != 'I' && c != 'E') || self.contains(index - 2, 6, &["BACHER", "MACHER"])
+        }
+    }
+
+    fn condition_ch0(&self, index: isize) -> bool {
+        index == 0
+            && (self.contains(index + 1, 5, &["HARAC", "HARIS"])
+                || self.contains(index + 1, 3, &["HOR", "HYM", "HIA", "HEM"]))
+            && !self.contains(0, 5, &["CHORE"])
+    }
+
+    fn condition_ch1(&self, index: isize) -> bool {
+        self.contains(0, 4, &["VAN ", "VON "])
+            || self.contains(0, 3, &["SCH"])
+            || self.contains(index - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"])
+            || self.contains(index + 2, 1, &["T", "S"])
+            || ((self.contains(index - 1, 1, &["A", "O", "U", "E"]) || index == 0)
+                && (self.contains(index + 2, 1, L_R_N_M_B_H_F_V_W_SPACE)
+                    || index + 1 == self.len() - 1))
+    }
+
+    fn condition_l0(&self, index: isize) -> bool {
+        if index == self.len() - 3 && self.contains(index - 1, 4, &["ILLO", "ILLA", "ALLE"]) {
+            true
+        } else {
+            (self.contains(self.len() - 2, 2, &["AS", "OS"])
+                || self.contains(self.len() - 1, 1, &["A", "O"]))
+                && self.contains(index - 1, 4, &["ALLE"])
+        }
+    }
+
+    fn condition_m0(&self, index: isize) -> bool {
+        self.char_at(index + 1) == 'M'
+            || (self.contains(index - 1, 3, &["UMB"])
+                && (index + 1 == self.len() - 1 || self.contains(index + 2, 2, &["ER"])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_metaphone() {
+        let encoder = DoubleMetaphone::new();
+        let codes = |text| encoder.double_metaphone(text);
+        assert_eq!(codes("Smith"), ("SM0".into(), "XMT".into()));
+        assert_eq!(codes("Schmidt"), ("XMT".into(), "SMT".into()));
+        assert_eq!(codes("Thomas"), ("TMS".into(), "TMS".into()));
+        assert_eq!(codes("Jose"), ("HS".into(), "HS".into()));
+        assert_eq!(codes("Thumb"), ("0M".into(), "TM".into()));
+        assert_eq!(codes("Xavier"), ("SF".into(), "SFR".into()));
+        assert_eq!(codes("Arnow"), ("ARN".into(), "ARNF".into()));
+        assert_eq!(codes("Michael"), ("MKL".into(), "MXL".into()));
+        assert_eq!(codes(""), ("".into(), "".into()));
+
+        assert_eq!(encoder.encode("caesar"), "SSR");
+        assert_eq!(
+            encoder.encode_alternates("Gallegos"),
+            vec!["KKS".to_string()]
+        );
+
+        let mut encoder = DoubleMetaphone::new();
+        encoder.set_max_code_length(6);
+        assert_eq!(encoder.encode("Washington"), "AXNKTN");
+    }
+}
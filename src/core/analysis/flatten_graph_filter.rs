@@ -0,0 +1,290 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, PositionLengthAttribute,
+    TermToBytesRefAttribute, TokenFilter, TokenStream, TypeAttribute, DEFAULT_TOKEN_TYPE,
+};
+
+use error::Result;
+
+use std::cmp::max;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug)]
+struct BufferedToken {
+    term: Vec<u8>,
+    token_type: &'static str,
+    start_node: isize,
+    end_node: isize,
+    start_offset: usize,
+    end_offset: usize,
+}
+
+/// Converts an incoming graph token stream, such as one from
+/// `SynonymGraphFilter`, into a flat form so that all nodes form a single
+/// linear chain with no side paths, as required to index the stream.
+///
+/// Every node of the graph is assigned the position of the longest path
+/// from the start of its segment, where a segment ends at a node no token
+/// spans over. Tokens of side paths are thus merged onto the positions of
+/// the longest path, which is lossy: a phrase query may match a path which
+/// wasn't in the original graph.
+#[derive(Debug)]
+pub struct FlattenGraphFilter {
+    input: Box<dyn TokenStream>,
+    // tokens of the current segment
+    pending: Vec<BufferedToken>,
+    // flattened tokens with their positions
+    output: VecDeque<(BufferedToken, isize, isize)>,
+    last_input_node: isize,
+    max_end_node: isize,
+    // the end node of the last flushed segment and its position
+    last_node: isize,
+    last_node_position: isize,
+    last_position: isize,
+    last_start_offset: usize,
+    term_attribute: CharTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    position_length_attribute: PositionLengthAttribute,
+    type_attribute: TypeAttribute,
+}
+
+impl FlattenGraphFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        FlattenGraphFilter {
+            input,
+            pending: Vec::new(),
+            output: VecDeque::new(),
+            last_input_node: -1,
+            max_end_node: 0,
+            last_node: 0,
+            last_node_position: 0,
+            last_position: -1,
+            last_start_offset: 0,
+            term_attribute: CharTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            position_length_attribute: PositionLengthAttribute::new(),
+            type_attribute: TypeAttribute::new(),
+        }
+    }
+
+    fn read_input(&mut self) -> Result<Option<BufferedToken>> {
+        if !self.input.increment_token()? {
+            return Ok(None);
+        }
+        let start_node =
+            self.last_input_node + self.input.position_attribute().get_position() as isize;
+        self.last_input_node = start_node;
+        let offset = self.input.offset_attribute();
+        Ok(Some(BufferedToken {
+            term: self
+                .input
+                .term_bytes_attribute()
+                .get_bytes_ref()
+                .bytes()
+                .to_vec(),
+            token_type: self
+                .input
+                .type_attribute()
+                .map_or(DEFAULT_TOKEN_TYPE, |attr| attr.get_type()),
+            start_node,
+            end_node: start_node + self.input.position_length() as isize,
+            start_offset: offset.start_offset(),
+            end_offset: offset.end_offset(),
+        }))
+    }
+
+    /// Assigns positions to the nodes of the pending segment and moves its
+    /// tokens to the output, ordered by position.
+    fn flush(&mut self) {
+        let mut positions: HashMap<isize, isize> = HashMap::new();
+        let mut last_start = (self.last_node, self.last_node_position);
+        let mut tokens = Vec::with_capacity(self.pending.len());
+        for token in self.pending.drain(..) {
+            // nodes no token ends at follow a hole of the input
+            let start = match positions.get(&token.start_node) {
+                Some(position) => *position,
+                None => last_start.1 + token.start_node - last_start.0,
+            };
+            let end = positions.entry(token.end_node).or_insert(start + 1);
+            *end = max(*end, start + 1);
+            last_start = (token.start_node, start);
+            tokens.push((token, start));
+        }
+        self.last_node = self.max_end_node;
+        self.last_node_position = positions[&self.max_end_node];
+
+        tokens.sort_by_key(|&(_, start)| start);
+        for (token, start) in tokens {
+            let end = positions[&token.end_node];
+            self.output.push_back((token, start, end));
+        }
+    }
+
+    fn release_token(&mut self) -> Result<()> {
+        let (token, start, end) = self.output.pop_front().unwrap();
+        self.clear_attributes();
+        self.term_attribute.copy_buffer(&token.term);
+        self.type_attribute.set_type(token.token_type);
+        self.position_attribute
+            .set_position((start - self.last_position) as u32);
+        self.position_length_attribute
+            .set_position_length((end - start) as u32);
+        // offsets must not go backwards
+        let start_offset = max(token.start_offset, self.last_start_offset);
+        let end_offset = max(token.end_offset, start_offset);
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        self.last_position = start;
+        self.last_start_offset = start_offset;
+        Ok(())
+    }
+}
+
+impl TokenStream for FlattenGraphFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        while self.output.is_empty() {
+            match self.read_input()? {
+                Some(token) => {
+                    if token.start_node >= self.max_end_node && !self.pending.is_empty() {
+                        // no token spans over the start of this one
+                        self.flush();
+                    }
+                    self.max_end_node = max(self.max_end_node, token.end_node);
+                    self.pending.push(token);
+                }
+                None => {
+                    if self.pending.is_empty() {
+                        return Ok(false);
+                    }
+                    self.flush();
+                }
+            }
+        }
+        self.release_token()?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let offset = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        let position = self.input.position_attribute().get_position();
+        self.position_attribute.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.pending.clear();
+        self.output.clear();
+        self.last_input_node = -1;
+        self.max_end_node = 0;
+        self.last_node = 0;
+        self.last_node_position = 0;
+        self.last_position = -1;
+        self.last_start_offset = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        Some(&mut self.position_length_attribute)
+    }
+
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        Some(&self.position_length_attribute)
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attribute)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+impl TokenFilter for FlattenGraphFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_graph_contents;
+    use core::analysis::{SynonymGraphFilter, SynonymMap, WhitespaceTokenizer};
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_flatten_synonyms() {
+        let mut builder = SynonymMap::builder(true);
+        builder
+            .add(
+                &SynonymMap::join(&["a", "b"]),
+                &SynonymMap::join(&["c", "d", "e"]),
+                true,
+            )
+            .unwrap();
+        let synonyms = Arc::new(builder.build().unwrap());
+        let text = "x a b y";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let filter = SynonymGraphFilter::new(Box::new(tokenizer), synonyms, false);
+        let mut filter = FlattenGraphFilter::new(Box::new(filter));
+
+        let tokens = assert_token_graph_contents(
+            &mut filter,
+            &["x", "c", "a", "d", "b", "e", "y"],
+            None,
+            &[1, 1, 0, 1, 0, 1, 1],
+            &[1, 1, 1, 1, 2, 1, 1],
+        );
+        let start_offsets: Vec<usize> = tokens.iter().map(|t| t.start_offset).collect();
+        assert_eq!(start_offsets, vec![0, 2, 2, 2, 4, 4, 6]);
+        assert_eq!(filter.offset_attribute().end_offset(), 7);
+    }
+}
@@ -0,0 +1,450 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{CharFilter, OffsetCorrector};
+
+use std::collections::HashSet;
+
+/// The tags which are replaced by a newline, so that the words of adjacent
+/// blocks aren't joined.
+const BLOCK_TAGS: [&str; 42] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "br",
+    "caption",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// The max length of an entity name, longer entities are kept as text.
+const MAX_ENTITY_LENGTH: usize = 10;
+
+/// Strips HTML markup from the text, so that only its visible text is
+/// tokenized.
+///
+/// Tags, comments, processing instructions and doctype declarations are
+/// removed, as well as the content of `script` and `style` elements. Block
+/// level tags, e.g. `<p>` or `<br>`, are replaced by a newline. The content
+/// of CDATA sections is kept, character entity references are decoded. A
+/// `<` which doesn't start markup, or markup which isn't closed, is kept as
+/// text.
+///
+/// Tags whose names are escaped with `set_escaped_tags` are kept as they
+/// are.
+#[derive(Debug, Clone, Default)]
+pub struct HTMLStripCharFilter {
+    escaped_tags: HashSet<String>,
+}
+
+enum Markup {
+    // removed markup, replaced by a newline if it's a block
+    Removed {
+        end: usize,
+        block: bool,
+    },
+    // markup kept as text
+    Kept {
+        end: usize,
+    },
+    // a CDATA section whose content in `start..end` is kept
+    CData {
+        start: usize,
+        end: usize,
+        markup_end: usize,
+    },
+}
+
+impl HTMLStripCharFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the names of the tags which aren't stripped, case insensitive.
+    pub fn set_escaped_tags<T: AsRef<str>>(&mut self, tags: &[T]) {
+        self.escaped_tags = tags.iter().map(|t| t.as_ref().to_lowercase()).collect();
+    }
+
+    /// Parses the markup starting with the `<` at `start`.
+    fn parse_markup(&self, chars: &[char], start: usize) -> Option<Markup> {
+        let rest = &chars[start..];
+        if starts_with(rest, "<!--") {
+            let end = find(chars, start + 4, "-->")? + 3;
+            return Some(Markup::Removed { end, block: false });
+        }
+        if starts_with(rest, "<![CDATA[") {
+            let content_end = find(chars, start + 9, "]]>")?;
+            return Some(Markup::CData {
+                start: start + 9,
+                end: content_end,
+                markup_end: content_end + 3,
+            });
+        }
+        if starts_with(rest, "<!") || starts_with(rest, "<?") {
+            let end = find(chars, start + 2, ">")? + 1;
+            return Some(Markup::Removed { end, block: false });
+        }
+
+        let closing = rest.get(1) == Some(&'/');
+        let name_start = if closing { start + 2 } else { start + 1 };
+        if !chars
+            .get(name_start)
+            .map_or(false, |c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        let name_end = (name_start..chars.len())
+            .find(|&i| !(chars[i].is_ascii_alphanumeric() || chars[i] == '-' || chars[i] == ':'))
+            .unwrap_or_else(|| chars.len());
+        let name: String = chars[name_start..name_end]
+            .iter()
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let end = find_tag_end(chars, name_end)?;
+        if self.escaped_tags.contains(&name) {
+            return Some(Markup::Kept { end });
+        }
+        if !closing && (name == "script" || name == "style") && chars[end - 2] != '/' {
+            // the content of the element is removed along with it
+            let closing_tag = format!("</{}", name);
+            let mut from = end;
+            let element_end = loop {
+                let close = find_ignore_case(chars, from, &closing_tag)?;
+                let after = close + closing_tag.len();
+                if chars
+                    .get(after)
+                    .map_or(true, |&c| c == '>' || c.is_whitespace() || c == '/')
+                {
+                    break find_tag_end(chars, after)?;
+                }
+                from = after;
+            };
+            return Some(Markup::Removed {
+                end: element_end,
+                block: true,
+            });
+        }
+        let block = BLOCK_TAGS.contains(&name.as_str());
+        Some(Markup::Removed { end, block })
+    }
+}
+
+fn starts_with(chars: &[char], prefix: &str) -> bool {
+    let mut i = 0;
+    for c in prefix.chars() {
+        if chars.get(i) != Some(&c) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Returns the index of the first occurrence of `pattern` at or after
+/// `from`.
+fn find(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    (from..chars.len()).find(|&i| starts_with(&chars[i..], pattern))
+}
+
+fn find_ignore_case(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    (from..chars.len()).find(|&i| {
+        pattern.chars().enumerate().all(|(j, p)| {
+            chars
+                .get(i + j)
+                .map_or(false, |c| c.to_ascii_lowercase() == p)
+        })
+    })
+}
+
+/// Returns the index after the `>` closing a tag, skipping quoted attribute
+/// values.
+fn find_tag_end(chars: &[char], from: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, &c) in chars.iter().enumerate().skip(from) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i + 1),
+            None if c == '<' => return None,
+            None => {}
+        }
+    }
+    None
+}
+
+/// Decodes the character entity reference starting with the `&` at
+/// `start`, returns the decoded char and the index after the reference.
+fn parse_entity(chars: &[char], start: usize) -> Option<(char, usize)> {
+    let semicolon =
+        (start + 1..chars.len().min(start + MAX_ENTITY_LENGTH + 2)).find(|&i| chars[i] == ';')?;
+    let name: String = chars[start + 1..semicolon].iter().collect();
+    let c = if name.starts_with("#x") || name.starts_with("#X") {
+        u32::from_str_radix(&name[2..], 16)
+            .ok()
+            .and_then(::std::char::from_u32)?
+    } else if name.starts_with('#') {
+        name[1..]
+            .parse::<u32>()
+            .ok()
+            .and_then(::std::char::from_u32)?
+    } else {
+        named_entity(&name)?
+    };
+    Some((c, semicolon + 1))
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "iexcl" => '¡',
+        "cent" => '¢',
+        "pound" => '£',
+        "yen" => '¥',
+        "euro" => '€',
+        "sect" => '§',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "plusmn" => '±',
+        "micro" => 'µ',
+        "para" => '¶',
+        "middot" => '·',
+        "laquo" => '«',
+        "raquo" => '»',
+        "iquest" => '¿',
+        "times" => '×',
+        "divide" => '÷',
+        "ndash" => '–',
+        "mdash" => '—',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "sbquo" => '‚',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "bull" => '•',
+        "hellip" => '…',
+        "prime" => '′',
+        "szlig" => 'ß',
+        "agrave" => 'à',
+        "aacute" => 'á',
+        "acirc" => 'â',
+        "atilde" => 'ã',
+        "auml" => 'ä',
+        "aring" => 'å',
+        "aelig" => 'æ',
+        "ccedil" => 'ç',
+        "egrave" => 'è',
+        "eacute" => 'é',
+        "ecirc" => 'ê',
+        "euml" => 'ë',
+        "igrave" => 'ì',
+        "iacute" => 'í',
+        "icirc" => 'î',
+        "iuml" => 'ï',
+        "ntilde" => 'ñ',
+        "ograve" => 'ò',
+        "oacute" => 'ó',
+        "ocirc" => 'ô',
+        "otilde" => 'õ',
+        "ouml" => 'ö',
+        "oslash" => 'ø',
+        "ugrave" => 'ù',
+        "uacute" => 'ú',
+        "ucirc" => 'û',
+        "uuml" => 'ü',
+        "yacute" => 'ý',
+        "yuml" => 'ÿ',
+        "Agrave" => 'À',
+        "Aacute" => 'Á',
+        "Acirc" => 'Â',
+        "Atilde" => 'Ã',
+        "Auml" => 'Ä',
+        "Aring" => 'Å',
+        "AElig" => 'Æ',
+        "Ccedil" => 'Ç',
+        "Egrave" => 'È',
+        "Eacute" => 'É',
+        "Ecirc" => 'Ê',
+        "Euml" => 'Ë',
+        "Igrave" => 'Ì',
+        "Iacute" => 'Í',
+        "Icirc" => 'Î',
+        "Iuml" => 'Ï',
+        "Ntilde" => 'Ñ',
+        "Ograve" => 'Ò',
+        "Oacute" => 'Ó',
+        "Ocirc" => 'Ô',
+        "Otilde" => 'Õ',
+        "Ouml" => 'Ö',
+        "Oslash" => 'Ø',
+        "Ugrave" => 'Ù',
+        "Uacute" => 'Ú',
+        "Ucirc" => 'Û',
+        "Uuml" => 'Ü',
+        "Yacute" => 'Ý',
+        _ => return None,
+    };
+    Some(c)
+}
+
+impl CharFilter for HTMLStripCharFilter {
+    fn filter(&self, text: &str, corrector: &mut OffsetCorrector) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut output = String::with_capacity(text.len());
+        let mut output_offset = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let markup = if c == '<' {
+                self.parse_markup(&chars, i)
+            } else {
+                None
+            };
+            match markup {
+                Some(Markup::Removed { end, block }) => {
+                    let replacement_length = if block {
+                        output.push('\n');
+                        1
+                    } else {
+                        0
+                    };
+                    corrector.replace(output_offset, end - i, replacement_length);
+                    output_offset += replacement_length;
+                    i = end;
+                }
+                Some(Markup::Kept { end }) => {
+                    output.extend(&chars[i..end]);
+                    output_offset += end - i;
+                    i = end;
+                }
+                Some(Markup::CData {
+                    start,
+                    end,
+                    markup_end,
+                }) => {
+                    corrector.replace(output_offset, start - i, 0);
+                    output.extend(&chars[start..end]);
+                    output_offset += end - start;
+                    corrector.replace(output_offset, markup_end - end, 0);
+                    i = markup_end;
+                }
+                None => match if c == '&' {
+                    parse_entity(&chars, i)
+                } else {
+                    None
+                } {
+                    Some((decoded, end)) => {
+                        corrector.replace(output_offset, end - i, 1);
+                        output.push(decoded);
+                        output_offset += 1;
+                        i = end;
+                    }
+                    None => {
+                        output.push(c);
+                        output_offset += 1;
+                        i += 1;
+                    }
+                },
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(filter: &HTMLStripCharFilter, text: &str) -> String {
+        filter.filter(text, &mut OffsetCorrector::new())
+    }
+
+    #[test]
+    fn test_html_strip() {
+        let filter = HTMLStripCharFilter::new();
+        assert_eq!(
+            strip(
+                &filter,
+                "<!DOCTYPE html><html><head><style>p { color: red }</style></head><body><p \
+                 class=\"a>b\">Caf&eacute; &amp; <b>cr&#232;me</b><br/>br&#xFBB2;<!-- comment \
+                 --></p><script>if (a < b) {}</script>a <= b <![CDATA[x<y]]></body></html>"
+            ),
+            "\n\n\n\n\n\nCafé & crème\nbr\u{fbb2}\n\na <= b x<y\n\n"
+        );
+        assert_eq!(
+            strip(&filter, "a < b &unknown; &amp"),
+            "a < b &unknown; &amp"
+        );
+        assert_eq!(strip(&filter, "<p unclosed"), "<p unclosed");
+
+        let mut filter = HTMLStripCharFilter::new();
+        filter.set_escaped_tags(&["B"]);
+        assert_eq!(strip(&filter, "<p><b>bold</b></p>"), "\n<b>bold</b>\n");
+    }
+
+    #[test]
+    fn test_offsets() {
+        let filter = HTMLStripCharFilter::new();
+        let text = "<b>bold</b> t&eacute;a";
+        let mut corrector = OffsetCorrector::new();
+        assert_eq!(filter.filter(text, &mut corrector), "bold téa");
+        assert_eq!(corrector.correct_offset(0), 3);
+        assert_eq!(corrector.correct_end_offset(4), 7);
+        assert_eq!(corrector.correct_offset(5), 12);
+        assert_eq!(corrector.correct_end_offset(8), 22);
+        assert_eq!(corrector.correct_offset(8), 22);
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{map_term, ICUNormalizationForm, ICUNormalizer, TokenFilter, TokenStream};
+
+use error::Result;
+
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+/// Folds token text for matching regardless of case, accents and
+/// compatibility forms, e.g. "Ｃafé" becomes "cafe".
+///
+/// The text is decomposed, stripped of its combining marks, i.e. of the
+/// characters with a non zero canonical combining class such as accents,
+/// then normalized with `ICUNormalizationForm::NFKCCaseFold`. This folds much
+/// more than `ASCIIFoldingFilter` for non latin scripts, but doesn't
+/// transliterate, e.g. greek text stays greek.
+#[derive(Debug)]
+pub struct ICUFoldingFilter {
+    input: Box<dyn TokenStream>,
+    normalizer: ICUNormalizer,
+}
+
+impl ICUFoldingFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        ICUFoldingFilter {
+            input,
+            normalizer: ICUNormalizer::new(ICUNormalizationForm::NFKCCaseFold),
+        }
+    }
+}
+
+fn fold(normalizer: &ICUNormalizer, text: &str) -> String {
+    let decomposed: String = text
+        .nfd()
+        .filter(|&c| canonical_combining_class(c) == 0)
+        .collect();
+    normalizer.normalize(&decomposed)
+}
+
+impl TokenStream for ICUFoldingFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            let normalizer = &self.normalizer;
+            map_term(term, |text| fold(normalizer, text));
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for ICUFoldingFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_icu_folding_filter() {
+        let text = "Ｃafé NAÏVE Ångström Ελληνικά";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = ICUFoldingFilter::new(Box::new(tokenizer));
+        assert_token_stream_contents(
+            &mut filter,
+            &["cafe", "naive", "angstrom", "ελληνικα"],
+            None,
+            None,
+        );
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{CharTermAttribute, TokenFilter, TokenStream};
+
+use error::Result;
+
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+
+/// The Unicode normalization forms applied by `ICUNormalizer2Filter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ICUNormalizationForm {
+    /// Canonical composition.
+    NFC,
+    /// Compatibility composition, e.g. folds ligatures and full width forms.
+    NFKC,
+    /// `NFKC` combined with case folding and the removal of default ignorable
+    /// code points, as defined by the `NFKC_Casefold` Unicode property.
+    NFKCCaseFold,
+}
+
+/// Normalizes token text like the ICU normalizers, using the `NFKCCaseFold`
+/// form by default, which is a standards compliant replacement of lower
+/// casing for multilingual text.
+///
+/// Terms which aren't valid UTF-8 are left as they are.
+#[derive(Debug)]
+pub struct ICUNormalizer2Filter {
+    input: Box<dyn TokenStream>,
+    form: ICUNormalizationForm,
+    normalizer: ICUNormalizer,
+}
+
+impl ICUNormalizer2Filter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        Self::with_form(input, ICUNormalizationForm::NFKCCaseFold)
+    }
+
+    pub fn with_form(input: Box<dyn TokenStream>, form: ICUNormalizationForm) -> Self {
+        ICUNormalizer2Filter {
+            input,
+            form,
+            normalizer: ICUNormalizer::new(form),
+        }
+    }
+
+    pub fn form(&self) -> ICUNormalizationForm {
+        self.form
+    }
+}
+
+/// Normalizes text to one of the `ICUNormalizationForm`s.
+#[derive(Debug)]
+pub(crate) struct ICUNormalizer {
+    form: ICUNormalizationForm,
+}
+
+impl ICUNormalizer {
+    pub fn new(form: ICUNormalizationForm) -> Self {
+        ICUNormalizer { form }
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        match self.form {
+            ICUNormalizationForm::NFC => text.nfc().collect(),
+            ICUNormalizationForm::NFKC => text.nfkc().collect(),
+            ICUNormalizationForm::NFKCCaseFold => {
+                // case folding may denormalize the text, e.g. by expanding
+                // ligatures, so normalize again afterwards
+                let normalized: String = text.nfkc().collect();
+                default_case_fold_str(&normalized)
+                    .nfkc()
+                    .filter(|&c| !is_default_ignorable(c))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The ranges of the `Default_Ignorable_Code_Point` Unicode property, e.g. soft
+/// hyphens, zero width spaces and variation selectors.
+const DEFAULT_IGNORABLES: [(char, char); 17] = [
+    ('\u{ad}', '\u{ad}'),
+    ('\u{34f}', '\u{34f}'),
+    ('\u{61c}', '\u{61c}'),
+    ('\u{115f}', '\u{1160}'),
+    ('\u{17b4}', '\u{17b5}'),
+    ('\u{180b}', '\u{180f}'),
+    ('\u{200b}', '\u{200f}'),
+    ('\u{202a}', '\u{202e}'),
+    ('\u{2060}', '\u{206f}'),
+    ('\u{3164}', '\u{3164}'),
+    ('\u{fe00}', '\u{fe0f}'),
+    ('\u{feff}', '\u{feff}'),
+    ('\u{ffa0}', '\u{ffa0}'),
+    ('\u{fff0}', '\u{fff8}'),
+    ('\u{1bca0}', '\u{1bca3}'),
+    ('\u{1d173}', '\u{1d17a}'),
+    ('\u{e0000}', '\u{e0fff}'),
+];
+
+fn is_default_ignorable(c: char) -> bool {
+    DEFAULT_IGNORABLES
+        .iter()
+        .any(|&(start, end)| c >= start && c <= end)
+}
+
+/// Replaces the text of `term` by `f(text)`, terms which aren't valid UTF-8
+/// are kept as they are.
+pub(crate) fn map_term<F: FnOnce(&str) -> String>(term: &mut CharTermAttribute, f: F) {
+    let mapped = match ::std::str::from_utf8(&term.term_buffer) {
+        Ok(text) => {
+            let mapped = f(text);
+            if mapped == text {
+                return;
+            }
+            mapped
+        }
+        Err(_) => return,
+    };
+    term.copy_buffer(mapped.as_bytes());
+}
+
+impl TokenStream for ICUNormalizer2Filter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            let normalizer = &self.normalizer;
+            map_term(term, |text| normalizer.normalize(text));
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for ICUNormalizer2Filter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn filter(text: &str, form: ICUNormalizationForm) -> ICUNormalizer2Filter {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        ICUNormalizer2Filter::with_form(Box::new(tokenizer), form)
+    }
+
+    #[test]
+    fn test_icu_normalizer2_filter() {
+        let text = "Ｒｕｓｔ Stra\u{df}e ﬁle e\u{301}t\u{e9} so\u{ad}ft";
+        assert_token_stream_contents(
+            &mut filter(text, ICUNormalizationForm::NFKCCaseFold),
+            &["rust", "strasse", "file", "\u{e9}t\u{e9}", "soft"],
+            None,
+            None,
+        );
+        assert_token_stream_contents(
+            &mut filter(text, ICUNormalizationForm::NFKC),
+            &["Rust", "Stra\u{df}e", "file", "\u{e9}t\u{e9}", "so\u{ad}ft"],
+            None,
+            None,
+        );
+        assert_token_stream_contents(
+            &mut filter(text, ICUNormalizationForm::NFC),
+            &[
+                "Ｒｕｓｔ",
+                "Stra\u{df}e",
+                "ﬁle",
+                "\u{e9}t\u{e9}",
+                "so\u{ad}ft",
+            ],
+            None,
+            None,
+        );
+    }
+}
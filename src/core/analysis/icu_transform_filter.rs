@@ -0,0 +1,113 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{map_term, TokenFilter, TokenStream};
+
+use error::Result;
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Transforms text, e.g. converts between scripts ("Cyrillic-Latin") or from
+/// traditional to simplified Chinese, for instance with the ICU transforms.
+pub trait Transliterator: fmt::Debug + Send + Sync {
+    fn transliterate(&self, text: &str) -> String;
+}
+
+/// Transforms token text with a `Transliterator`.
+///
+/// Transliterators are usually expensive to build, so they're shared between
+/// filters.
+#[derive(Debug)]
+pub struct ICUTransformFilter {
+    input: Box<dyn TokenStream>,
+    transliterator: Arc<dyn Transliterator>,
+}
+
+impl ICUTransformFilter {
+    pub fn new(input: Box<dyn TokenStream>, transliterator: Arc<dyn Transliterator>) -> Self {
+        ICUTransformFilter {
+            input,
+            transliterator,
+        }
+    }
+
+    pub fn transliterator(&self) -> &dyn Transliterator {
+        &self.transliterator
+    }
+}
+
+impl TokenStream for ICUTransformFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            let transliterator = &self.transliterator;
+            map_term(term, |text| transliterator.transliterate(text));
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for ICUTransformFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    #[derive(Debug)]
+    struct CyrillicLatin;
+
+    impl Transliterator for CyrillicLatin {
+        fn transliterate(&self, text: &str) -> String {
+            text.chars()
+                .map(|c| match c {
+                    'м' => 'm',
+                    'и' => 'i',
+                    'р' => 'r',
+                    c => c,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_icu_transform_filter() {
+        let tokenizer =
+            WhitespaceTokenizer::new(Box::new(Cursor::new("мир peace".as_bytes().to_vec())));
+        let mut filter = ICUTransformFilter::new(Box::new(tokenizer), Arc::new(CyrillicLatin));
+        assert_token_stream_contents(&mut filter, &["mir", "peace"], None, None);
+    }
+}
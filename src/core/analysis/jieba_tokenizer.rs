@@ -0,0 +1,184 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Segment, Segmenter, SegmentingTokenizer, TokenStream, Tokenizer};
+
+use error::Result;
+
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+use jieba_rs::{Jieba, TokenizeMode};
+
+lazy_static! {
+    static ref DEFAULT_SEGMENTER: Arc<JiebaSegmenter> = Arc::new(JiebaSegmenter::new());
+}
+
+/// A `Segmenter` for Chinese text based on the jieba word segmentation.
+///
+/// Segments without any letter or digit, e.g. whitespace or punctuation,
+/// are skipped.
+pub struct JiebaSegmenter {
+    jieba: Jieba,
+    search_mode: bool,
+    hmm: bool,
+}
+
+impl JiebaSegmenter {
+    /// Creates a segmenter with the default dictionary of jieba.
+    pub fn new() -> Self {
+        Self::with_jieba(Jieba::new())
+    }
+
+    /// Creates a segmenter with a customized `Jieba`, e.g. with a user
+    /// dictionary.
+    pub fn with_jieba(jieba: Jieba) -> Self {
+        JiebaSegmenter {
+            jieba,
+            search_mode: false,
+            hmm: true,
+        }
+    }
+
+    /// If true, the long words are also split into their shorter words, which
+    /// overlap the long word, to improve the recall of searches.
+    pub fn set_search_mode(&mut self, search_mode: bool) {
+        self.search_mode = search_mode;
+    }
+
+    /// If true, the words missing from the dictionary are found with a
+    /// hidden Markov model.
+    pub fn set_hmm(&mut self, hmm: bool) {
+        self.hmm = hmm;
+    }
+}
+
+impl Default for JiebaSegmenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for JiebaSegmenter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JiebaSegmenter")
+            .field("search_mode", &self.search_mode)
+            .field("hmm", &self.hmm)
+            .finish()
+    }
+}
+
+impl Segmenter for JiebaSegmenter {
+    fn segment(&self, text: &str) -> Vec<Segment> {
+        let mode = if self.search_mode {
+            TokenizeMode::Search
+        } else {
+            TokenizeMode::Default
+        };
+        // jieba tokens are in chars, map them to bytes
+        let mut byte_offsets: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+        byte_offsets.push(text.len());
+
+        let mut segments: Vec<Segment> = self
+            .jieba
+            .tokenize(text, mode, self.hmm)
+            .into_iter()
+            .filter(|token| token.word.chars().any(char::is_alphanumeric))
+            .map(|token| Segment::new(byte_offsets[token.start], byte_offsets[token.end]))
+            .collect();
+        // the search mode emits the shorter words before the long word
+        segments.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        segments
+    }
+}
+
+/// A tokenizer for Chinese text segmenting the words with a
+/// `JiebaSegmenter`.
+#[derive(Debug)]
+pub struct JiebaTokenizer {
+    tokenizer: SegmentingTokenizer,
+}
+
+impl JiebaTokenizer {
+    /// Creates a tokenizer sharing a segmenter with the default settings.
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        Self::with_segmenter(reader, Arc::clone(&DEFAULT_SEGMENTER))
+    }
+
+    pub fn with_segmenter(reader: Box<dyn Read>, segmenter: Arc<JiebaSegmenter>) -> Self {
+        JiebaTokenizer {
+            tokenizer: SegmentingTokenizer::new(reader, segmenter),
+        }
+    }
+}
+
+impl TokenStream for JiebaTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.tokenizer.increment_token()
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.tokenizer.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.tokenizer.reset()
+    }
+
+    delegate_token_attributes!(tokenizer);
+}
+
+impl Tokenizer for JiebaTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.tokenizer.set_reader(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::{assert_token_stream_contents, collect_tokens};
+    use std::io::Cursor;
+
+    fn reader(text: &str) -> Box<dyn Read> {
+        Box::new(Cursor::new(text.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_jieba_tokenizer() {
+        assert_token_stream_contents(
+            &mut JiebaTokenizer::new(reader("我们中出了一个叛徒。")),
+            &["我们", "中出", "了", "一个", "叛徒"],
+            Some(&[(0, 2), (2, 4), (4, 5), (5, 7), (7, 9)]),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_search_mode() {
+        let mut segmenter = JiebaSegmenter::new();
+        segmenter.set_search_mode(true);
+        let tokens = collect_tokens(&mut JiebaTokenizer::with_segmenter(
+            reader("中华人民共和国"),
+            Arc::new(segmenter),
+        ));
+        let tokens: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.start_offset, t.end_offset))
+            .collect();
+        assert_eq!(tokens[0], ("中华人民共和国", 0, 7));
+        assert!(tokens.contains(&("中华", 0, 2)));
+        assert!(tokens.contains(&("共和国", 4, 7)));
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Analyzer, ComponentsCache, KeywordTokenizer, ReuseStrategy, TokenStream};
+
+use error::Result;
+
+use std::io::Read;
+
+/// Tokenizes the entire value of a field as a single token, see
+/// `KeywordTokenizer`.
+#[derive(Debug)]
+pub struct KeywordAnalyzer {
+    cache: ComponentsCache,
+}
+
+impl Default for KeywordAnalyzer {
+    fn default() -> Self {
+        KeywordAnalyzer {
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        }
+    }
+}
+
+impl Analyzer for KeywordAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        Ok(Box::new(KeywordTokenizer::new(reader)))
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_keyword_analyzer() {
+        let analyzer = KeywordAnalyzer::default();
+        for text in &["New York, NY", ""] {
+            assert_analyzes_to(
+                &analyzer,
+                text,
+                &[*text],
+                Some(&[(0, text.chars().count())]),
+                None,
+            );
+        }
+        assert_eq!(analyzer.normalize("city", "Paris").unwrap(), b"Paris");
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+    Tokenizer,
+};
+
+use error::Result;
+
+use std::fmt;
+use std::io::Read;
+
+/// Emits the entire input as a single token, e.g. for identifiers or other
+/// values matched exactly.
+///
+/// A token is emitted even if the input is empty.
+pub struct KeywordTokenizer {
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    reader: Box<dyn Read>,
+    text: String,
+    done: bool,
+    final_offset: usize,
+}
+
+impl KeywordTokenizer {
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        KeywordTokenizer {
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            reader,
+            text: String::new(),
+            done: true,
+            final_offset: 0,
+        }
+    }
+}
+
+impl fmt::Debug for KeywordTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeywordTokenizer")
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl TokenStream for KeywordTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.done {
+            return Ok(false);
+        }
+        self.clear_attributes();
+        self.done = true;
+        self.term_attr.append(&self.text);
+        self.offset_attr.set_offset(0, self.final_offset)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        self.offset_attr
+            .set_offset(self.final_offset, self.final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.text.clear();
+        self.reader.read_to_string(&mut self.text)?;
+        self.final_offset = self.text.chars().count();
+        self.done = false;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+impl Tokenizer for KeywordTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.reader = reader;
+        Ok(())
+    }
+}
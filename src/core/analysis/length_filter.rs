@@ -0,0 +1,121 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Removes the tokens which are too short or too long, their length being
+/// counted in chars.
+///
+/// Like for `StopFilter`, the position increments of the removed tokens are
+/// added to the next token kept.
+#[derive(Debug)]
+pub struct LengthFilter {
+    input: Box<dyn TokenStream>,
+    min: usize,
+    max: usize,
+    skipped_positions: u32,
+}
+
+impl LengthFilter {
+    /// Creates a filter keeping the tokens of `min` to `max` chars,
+    /// inclusive.
+    pub fn new(input: Box<dyn TokenStream>, min: usize, max: usize) -> Result<Self> {
+        if min > max {
+            bail!(IllegalArgument(format!(
+                "minimum length {} must not be greater than maximum length {}",
+                min, max
+            )));
+        }
+        Ok(LengthFilter {
+            input,
+            min,
+            max,
+            skipped_positions: 0,
+        })
+    }
+
+    fn accept(&self) -> bool {
+        let term = self.input.term_bytes_attribute().get_bytes_ref();
+        let len = match ::std::str::from_utf8(term.bytes()) {
+            Ok(text) => text.chars().count(),
+            Err(_) => term.len(),
+        };
+        len >= self.min && len <= self.max
+    }
+}
+
+impl TokenStream for LengthFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.skipped_positions = 0;
+        while self.input.increment_token()? {
+            if self.accept() {
+                if self.skipped_positions > 0 {
+                    let attr = self.input.position_attribute_mut();
+                    let position = attr.get_position() + self.skipped_positions;
+                    attr.set_position(position);
+                }
+                return Ok(true);
+            }
+            self.skipped_positions += self.input.position_attribute().get_position();
+        }
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        let attr = self.input.position_attribute_mut();
+        let position = attr.get_position() + self.skipped_positions;
+        attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.skipped_positions = 0;
+        Ok(())
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for LengthFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_length_filter() {
+        let text = "a bb ccc dddd été eeeee";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = LengthFilter::new(Box::new(tokenizer), 2, 3).unwrap();
+        assert_token_stream_contents(&mut filter, &["bb", "ccc", "été"], None, Some(&[2, 1, 2]));
+        assert_eq!(filter.position_attribute().get_position(), 1);
+
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(vec![])));
+        assert!(LengthFilter::new(Box::new(tokenizer), 3, 2).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{CharTokenizer, TokenStream, Tokenizer};
+
+use error::Result;
+
+use std::io::Read;
+
+/// A tokenizer that divides text at non-letters, i.e. tokens are maximal
+/// runs of adjacent letters as defined by `char::is_alphabetic`.
+///
+/// Note: this does a decent job for most European languages, but a
+/// terrible job for some Asian languages, where words are not separated by
+/// spaces, and for text with digits, which are dropped.
+#[derive(Debug)]
+pub struct LetterTokenizer {
+    tokenizer: CharTokenizer,
+}
+
+impl LetterTokenizer {
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        LetterTokenizer {
+            tokenizer: CharTokenizer::new(reader, char::is_alphabetic),
+        }
+    }
+
+    pub fn is_token_char(&self, c: char) -> bool {
+        self.tokenizer.is_token_char(c)
+    }
+}
+
+impl TokenStream for LetterTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.tokenizer.increment_token()
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.tokenizer.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.tokenizer.reset()
+    }
+
+    delegate_token_attributes!(tokenizer);
+}
+
+impl Tokenizer for LetterTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.tokenizer.set_reader(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_letter_tokenizer() {
+        let text = "I can't believe it's 2am, déjà vu";
+        let mut tokenizer = LetterTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        assert_token_stream_contents(
+            &mut tokenizer,
+            &["I", "can", "t", "believe", "it", "s", "am", "déjà", "vu"],
+            Some(&[
+                (0, 1),
+                (2, 5),
+                (6, 7),
+                (8, 15),
+                (16, 18),
+                (19, 20),
+                (22, 24),
+                (26, 30),
+                (31, 33),
+            ]),
+            None,
+        );
+        assert_eq!(tokenizer.offset_attribute().end_offset(), 33);
+    }
+}
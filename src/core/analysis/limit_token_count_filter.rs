@@ -0,0 +1,118 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+/// Limits the number of tokens of a stream, e.g. to bound the cost of
+/// indexing huge documents.
+///
+/// By default the input isn't read after the last token kept, so the
+/// offsets and positions reported by `end` are those of that token. With
+/// `consume_all_tokens` the rest of the input is consumed, so that they
+/// are those of the whole input, like for any other filter.
+#[derive(Debug)]
+pub struct LimitTokenCountFilter {
+    input: Box<dyn TokenStream>,
+    max_token_count: usize,
+    consume_all_tokens: bool,
+    token_count: usize,
+    exhausted: bool,
+}
+
+impl LimitTokenCountFilter {
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        max_token_count: usize,
+        consume_all_tokens: bool,
+    ) -> Self {
+        LimitTokenCountFilter {
+            input,
+            max_token_count,
+            consume_all_tokens,
+            token_count: 0,
+            exhausted: false,
+        }
+    }
+
+    pub fn max_token_count(&self) -> usize {
+        self.max_token_count
+    }
+}
+
+impl TokenStream for LimitTokenCountFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.exhausted {
+            return Ok(false);
+        }
+        if self.token_count < self.max_token_count {
+            if self.input.increment_token()? {
+                self.token_count += 1;
+                return Ok(true);
+            }
+        } else if self.consume_all_tokens {
+            while self.input.increment_token()? {}
+        }
+        self.exhausted = true;
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.token_count = 0;
+        self.exhausted = false;
+        Ok(())
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for LimitTokenCountFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn filter(consume_all_tokens: bool) -> LimitTokenCountFilter {
+        let text = "one two three four";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        LimitTokenCountFilter::new(Box::new(tokenizer), 2, consume_all_tokens)
+    }
+
+    #[test]
+    fn test_limit_token_count() {
+        for &consume_all_tokens in &[false, true] {
+            let mut filter = filter(consume_all_tokens);
+            assert_token_stream_contents(&mut filter, &["one", "two"], None, None);
+            if consume_all_tokens {
+                assert_eq!(filter.offset_attribute().end_offset(), 18);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{CharTermAttribute, TokenFilter, TokenStream};
+
+use error::Result;
+
+/// Normalizes token text to lower case.
+#[derive(Debug)]
+pub struct LowerCaseFilter {
+    input: Box<dyn TokenStream>,
+}
+
+impl LowerCaseFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        LowerCaseFilter { input }
+    }
+}
+
+/// Lower cases the term in place, binary terms which aren't valid UTF-8 are
+/// only lower cased in their ASCII range.
+pub fn lower_case_term(term: &mut CharTermAttribute) {
+    if term.term_buffer.is_ascii() {
+        term.term_buffer.make_ascii_lowercase();
+    } else {
+        let lower = match ::std::str::from_utf8(&term.term_buffer) {
+            Ok(text) => text.to_lowercase(),
+            Err(_) => {
+                term.term_buffer.make_ascii_lowercase();
+                return;
+            }
+        };
+        term.copy_buffer(lower.as_bytes());
+    }
+}
+
+impl TokenStream for LowerCaseFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            lower_case_term(term);
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for LowerCaseFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_lower_case_filter() {
+        let text = "The QUICK Ärger ΣΊΣΥΦΟΣ";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = LowerCaseFilter::new(Box::new(tokenizer));
+        assert_token_stream_contents(
+            &mut filter,
+            &["the", "quick", "ärger", "σίσυφος"],
+            None,
+            None,
+        );
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{CharFilter, OffsetCorrector};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+struct TrieNode {
+    children: HashMap<char, usize>,
+    output: Option<String>,
+}
+
+/// The mappings of a `MappingCharFilter`, built with
+/// `NormalizeCharMapBuilder`.
+#[derive(Debug)]
+pub struct NormalizeCharMap {
+    // a trie over the chars of the matched strings, the root is the first node
+    nodes: Vec<TrieNode>,
+}
+
+impl NormalizeCharMap {
+    pub fn builder() -> NormalizeCharMapBuilder {
+        NormalizeCharMapBuilder::default()
+    }
+
+    /// Returns the length in chars and the replacement of the longest match
+    /// at the start of `chars`.
+    fn longest_match(&self, chars: &[char]) -> Option<(usize, &str)> {
+        let mut node = &self.nodes[0];
+        let mut longest = None;
+        for (i, c) in chars.iter().enumerate() {
+            match node.children.get(c) {
+                Some(&child) => node = &self.nodes[child],
+                None => break,
+            }
+            if let Some(ref output) = node.output {
+                longest = Some((i + 1, output.as_str()));
+            }
+        }
+        longest
+    }
+}
+
+/// Builds a `NormalizeCharMap`.
+#[derive(Debug)]
+pub struct NormalizeCharMapBuilder {
+    nodes: Vec<TrieNode>,
+}
+
+impl Default for NormalizeCharMapBuilder {
+    fn default() -> Self {
+        NormalizeCharMapBuilder {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+}
+
+impl NormalizeCharMapBuilder {
+    /// Adds a mapping of the string `matched` to `replacement`, which may be
+    /// empty to remove the matched string.
+    pub fn add(&mut self, matched: &str, replacement: &str) -> Result<()> {
+        if matched.is_empty() {
+            bail!(IllegalArgument("cannot match the empty string".into()));
+        }
+        let mut node = 0;
+        for c in matched.chars() {
+            node = match self.nodes[node].children.get(&c) {
+                Some(&child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node].children.insert(c, child);
+                    child
+                }
+            };
+        }
+        if self.nodes[node].output.is_some() {
+            bail!(IllegalArgument(format!(
+                "match \"{}\" was already added",
+                matched
+            )));
+        }
+        self.nodes[node].output = Some(replacement.to_string());
+        Ok(())
+    }
+
+    pub fn build(self) -> NormalizeCharMap {
+        NormalizeCharMap { nodes: self.nodes }
+    }
+}
+
+/// Replaces strings of the text by others, as defined by a
+/// `NormalizeCharMap`, e.g. to fold characters or to expand abbreviations
+/// before tokenization.
+///
+/// The text is scanned from the start and the longest match at each place
+/// is replaced, the replacement isn't scanned again.
+#[derive(Debug, Clone)]
+pub struct MappingCharFilter {
+    map: Arc<NormalizeCharMap>,
+}
+
+impl MappingCharFilter {
+    pub fn new(map: Arc<NormalizeCharMap>) -> Self {
+        MappingCharFilter { map }
+    }
+
+    pub fn map(&self) -> &NormalizeCharMap {
+        &self.map
+    }
+}
+
+impl CharFilter for MappingCharFilter {
+    fn filter(&self, text: &str, corrector: &mut OffsetCorrector) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut output = String::with_capacity(text.len());
+        let mut output_offset = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            if let Some((length, replacement)) = self.map.longest_match(&chars[i..]) {
+                let replacement_length = replacement.chars().count();
+                corrector.replace(output_offset, length, replacement_length);
+                output.push_str(replacement);
+                output_offset += replacement_length;
+                i += length;
+            } else {
+                output.push(chars[i]);
+                output_offset += 1;
+                i += 1;
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(text: &str) -> (String, OffsetCorrector) {
+        let mut builder = NormalizeCharMap::builder();
+        builder.add("aa", "a").unwrap();
+        builder.add("bbb", "b").unwrap();
+        builder.add("c", "cc").unwrap();
+        builder.add("æ", "ae").unwrap();
+        builder.add("-", "").unwrap();
+        assert!(builder.add("aa", "x").is_err());
+        assert!(builder.add("", "x").is_err());
+        let filter = MappingCharFilter::new(Arc::new(builder.build()));
+        let mut corrector = OffsetCorrector::new();
+        (filter.filter(text, &mut corrector), corrector)
+    }
+
+    #[test]
+    fn test_mapping_char_filter() {
+        let (output, corrector) = filter("aaa bbbb c-æ");
+        assert_eq!(output, "aa bb ccae");
+        // "aa" is made of "aa" and "a"
+        assert_eq!(corrector.correct_offset(0), 0);
+        assert_eq!(corrector.correct_end_offset(2), 3);
+        // "bb" is made of "bbb" and "b"
+        assert_eq!(corrector.correct_offset(3), 4);
+        assert_eq!(corrector.correct_end_offset(5), 8);
+        // "ccae" is made of "c", "-" and "æ"
+        assert_eq!(corrector.correct_offset(6), 9);
+        assert_eq!(corrector.correct_end_offset(10), 12);
+        assert_eq!(corrector.correct_offset(10), 12);
+    }
+}
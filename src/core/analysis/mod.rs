@@ -11,6 +11,79 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// Implements the attribute accessors of `TokenStream` by delegating to the
+/// stream stored in the given field, for token filters which share the
+/// attributes of their input.
+macro_rules! delegate_token_attributes {
+    ($input:ident) => {
+        fn offset_attribute_mut(&mut self) -> &mut $crate::core::analysis::OffsetAttribute {
+            self.$input.offset_attribute_mut()
+        }
+
+        fn offset_attribute(&self) -> &$crate::core::analysis::OffsetAttribute {
+            self.$input.offset_attribute()
+        }
+
+        fn position_attribute_mut(&mut self) -> &mut $crate::core::analysis::PositionAttribute {
+            self.$input.position_attribute_mut()
+        }
+
+        fn position_attribute(&self) -> &$crate::core::analysis::PositionAttribute {
+            self.$input.position_attribute()
+        }
+
+        fn position_length_attribute_mut(
+            &mut self,
+        ) -> Option<&mut $crate::core::analysis::PositionLengthAttribute> {
+            self.$input.position_length_attribute_mut()
+        }
+
+        fn position_length_attribute(
+            &self,
+        ) -> Option<&$crate::core::analysis::PositionLengthAttribute> {
+            self.$input.position_length_attribute()
+        }
+
+        fn type_attribute_mut(&mut self) -> Option<&mut $crate::core::analysis::TypeAttribute> {
+            self.$input.type_attribute_mut()
+        }
+
+        fn type_attribute(&self) -> Option<&$crate::core::analysis::TypeAttribute> {
+            self.$input.type_attribute()
+        }
+
+        fn payload_attribute_mut(
+            &mut self,
+        ) -> Option<&mut $crate::core::analysis::PayloadAttribute> {
+            self.$input.payload_attribute_mut()
+        }
+
+        fn payload_attribute(&self) -> Option<&$crate::core::analysis::PayloadAttribute> {
+            self.$input.payload_attribute()
+        }
+
+        fn term_freq_attribute_mut(
+            &mut self,
+        ) -> Option<&mut $crate::core::analysis::TermFreqAttribute> {
+            self.$input.term_freq_attribute_mut()
+        }
+
+        fn term_freq_attribute(&self) -> Option<&$crate::core::analysis::TermFreqAttribute> {
+            self.$input.term_freq_attribute()
+        }
+
+        fn term_bytes_attribute_mut(
+            &mut self,
+        ) -> &mut dyn $crate::core::analysis::TermToBytesRefAttribute {
+            self.$input.term_bytes_attribute_mut()
+        }
+
+        fn term_bytes_attribute(&self) -> &dyn $crate::core::analysis::TermToBytesRefAttribute {
+            self.$input.term_bytes_attribute()
+        }
+    };
+}
+
 mod token_stream;
 
 pub use self::token_stream::*;
@@ -19,10 +92,293 @@ mod char_buffer;
 
 pub use self::char_buffer::*;
 
+mod char_tokenizer;
+
+pub use self::char_tokenizer::*;
+
 mod whitespace_tokenizer;
 
 pub use self::whitespace_tokenizer::*;
 
+mod letter_tokenizer;
+
+pub use self::letter_tokenizer::*;
+
 mod token_attributes;
 
 pub use self::token_attributes::*;
+
+mod char_filter;
+
+pub use self::char_filter::*;
+
+mod html_strip_char_filter;
+
+pub use self::html_strip_char_filter::*;
+
+mod mapping_char_filter;
+
+pub use self::mapping_char_filter::*;
+
+mod analyzer;
+
+pub use self::analyzer::*;
+
+mod keyword_tokenizer;
+
+pub use self::keyword_tokenizer::*;
+
+mod keyword_analyzer;
+
+pub use self::keyword_analyzer::*;
+
+mod whitespace_analyzer;
+
+pub use self::whitespace_analyzer::*;
+
+mod simple_analyzer;
+
+pub use self::simple_analyzer::*;
+
+mod standard_tokenizer;
+
+pub use self::standard_tokenizer::*;
+
+mod lower_case_filter;
+
+pub use self::lower_case_filter::*;
+
+mod stop_filter;
+
+pub use self::stop_filter::*;
+
+mod standard_analyzer;
+
+pub use self::standard_analyzer::*;
+
+mod porter_stemmer;
+
+pub use self::porter_stemmer::*;
+
+mod snowball_filter;
+
+pub use self::snowball_filter::*;
+
+mod ascii_folding_filter;
+
+pub use self::ascii_folding_filter::*;
+
+mod synonym_map;
+
+pub use self::synonym_map::*;
+
+mod synonym_parser;
+
+pub use self::synonym_parser::*;
+
+mod synonym_graph_filter;
+
+pub use self::synonym_graph_filter::*;
+
+mod flatten_graph_filter;
+
+pub use self::flatten_graph_filter::*;
+
+mod shingle_filter;
+
+pub use self::shingle_filter::*;
+
+mod ngram_tokenizer;
+
+pub use self::ngram_tokenizer::*;
+
+mod ngram_token_filter;
+
+pub use self::ngram_token_filter::*;
+
+mod normalizer;
+
+pub use self::normalizer::*;
+
+mod cjk_bigram_filter;
+
+pub use self::cjk_bigram_filter::*;
+
+mod cjk_analyzer;
+
+pub use self::cjk_analyzer::*;
+
+#[cfg(feature = "icu")]
+mod icu_normalizer2_filter;
+
+#[cfg(feature = "icu")]
+pub use self::icu_normalizer2_filter::*;
+
+#[cfg(feature = "icu")]
+mod icu_folding_filter;
+
+#[cfg(feature = "icu")]
+pub use self::icu_folding_filter::*;
+
+#[cfg(feature = "icu")]
+mod icu_transform_filter;
+
+#[cfg(feature = "icu")]
+pub use self::icu_transform_filter::*;
+
+mod pattern_tokenizer;
+
+pub use self::pattern_tokenizer::*;
+
+mod pattern_replace_filter;
+
+pub use self::pattern_replace_filter::*;
+
+mod custom_analyzer;
+
+pub use self::custom_analyzer::*;
+
+mod word_delimiter_graph_filter;
+
+pub use self::word_delimiter_graph_filter::*;
+
+mod phonetic_encoder;
+
+pub use self::phonetic_encoder::*;
+
+mod double_metaphone;
+
+pub use self::double_metaphone::*;
+
+mod phonetic_filter;
+
+pub use self::phonetic_filter::*;
+
+mod length_filter;
+
+pub use self::length_filter::*;
+
+mod limit_token_count_filter;
+
+pub use self::limit_token_count_filter::*;
+
+mod trim_filter;
+
+pub use self::trim_filter::*;
+
+mod remove_duplicates_token_filter;
+
+pub use self::remove_duplicates_token_filter::*;
+
+mod token_stream_to_automaton;
+
+pub use self::token_stream_to_automaton::*;
+
+mod segmenting_tokenizer;
+
+pub use self::segmenting_tokenizer::*;
+
+#[cfg(feature = "jieba")]
+mod jieba_tokenizer;
+
+#[cfg(feature = "jieba")]
+pub use self::jieba_tokenizer::*;
+
+mod word_list_loader;
+
+pub use self::word_list_loader::*;
+
+#[cfg(test)]
+pub mod tests {
+    use core::analysis::{Analyzer, TokenStream, DEFAULT_TOKEN_TYPE};
+
+    /// The attributes of a token emitted by a `TokenStream`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct TestToken {
+        pub term: String,
+        pub start_offset: usize,
+        pub end_offset: usize,
+        pub position_increment: u32,
+        pub position_length: u32,
+        pub token_type: &'static str,
+        pub payload: Vec<u8>,
+    }
+
+    /// Resets `stream`, collects all its tokens and ends it, the final offset and
+    /// position increment are left in its attributes.
+    pub fn collect_tokens(stream: &mut dyn TokenStream) -> Vec<TestToken> {
+        stream.reset().unwrap();
+        let mut tokens = vec![];
+        while stream.increment_token().unwrap() {
+            let term = stream.term_bytes_attribute().get_bytes_ref();
+            tokens.push(TestToken {
+                term: String::from_utf8(term.bytes().to_vec()).unwrap(),
+                start_offset: stream.offset_attribute().start_offset(),
+                end_offset: stream.offset_attribute().end_offset(),
+                position_increment: stream.position_attribute().get_position(),
+                position_length: stream.position_length(),
+                token_type: stream
+                    .type_attribute()
+                    .map_or(DEFAULT_TOKEN_TYPE, |attr| attr.get_type()),
+                payload: stream
+                    .payload_attribute()
+                    .map_or(vec![], |attr| attr.get_payload().to_vec()),
+            });
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    /// Consumes `stream`, asserting the terms it emits and, when given, their
+    /// offsets and position increments.
+    pub fn assert_token_stream_contents(
+        stream: &mut dyn TokenStream,
+        terms: &[&str],
+        offsets: Option<&[(usize, usize)]>,
+        pos_incs: Option<&[u32]>,
+    ) -> Vec<TestToken> {
+        let tokens = collect_tokens(stream);
+        let actual: Vec<&str> = tokens.iter().map(|t| t.term.as_str()).collect();
+        assert_eq!(actual, terms);
+        if let Some(offsets) = offsets {
+            let actual: Vec<(usize, usize)> = tokens
+                .iter()
+                .map(|t| (t.start_offset, t.end_offset))
+                .collect();
+            assert_eq!(actual, offsets, "offsets of {:?}", terms);
+        }
+        if let Some(pos_incs) = pos_incs {
+            let actual: Vec<u32> = tokens.iter().map(|t| t.position_increment).collect();
+            assert_eq!(actual, pos_incs, "position increments of {:?}", terms);
+        }
+        tokens
+    }
+
+    /// Like `assert_token_stream_contents`, also asserting the position lengths
+    /// of the tokens of a token graph.
+    pub fn assert_token_graph_contents(
+        stream: &mut dyn TokenStream,
+        terms: &[&str],
+        offsets: Option<&[(usize, usize)]>,
+        pos_incs: &[u32],
+        pos_lengths: &[u32],
+    ) -> Vec<TestToken> {
+        let tokens = assert_token_stream_contents(stream, terms, offsets, Some(pos_incs));
+        let actual: Vec<u32> = tokens.iter().map(|t| t.position_length).collect();
+        assert_eq!(actual, pos_lengths, "position lengths of {:?}", terms);
+        tokens
+    }
+
+    /// Analyzes `text` as the value of the "body" field and asserts the tokens
+    /// like `assert_token_stream_contents`.
+    pub fn assert_analyzes_to(
+        analyzer: &dyn Analyzer,
+        text: &str,
+        terms: &[&str],
+        offsets: Option<&[(usize, usize)]>,
+        pos_incs: Option<&[u32]>,
+    ) -> Vec<TestToken> {
+        let mut stream = analyzer.token_stream_from_str("body", text).unwrap();
+        assert_token_stream_contents(stream.as_mut(), terms, offsets, pos_incs)
+    }
+}
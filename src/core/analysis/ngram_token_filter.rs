@@ -0,0 +1,312 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::ngram_tokenizer::check_gram_sizes;
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+/// The term of the current input token being split into n-grams.
+#[derive(Debug)]
+struct GramTerm {
+    // byte offset of each char of the term, and of the end of the term
+    char_starts: Vec<usize>,
+    term: Vec<u8>,
+    pos: usize,
+    gram_size: usize,
+}
+
+impl GramTerm {
+    fn read(input: &dyn TokenStream, min_gram: usize) -> GramTerm {
+        let term = input
+            .term_bytes_attribute()
+            .get_bytes_ref()
+            .bytes()
+            .to_vec();
+        let mut char_starts: Vec<usize> = match ::std::str::from_utf8(&term) {
+            Ok(text) => text.char_indices().map(|(idx, _)| idx).collect(),
+            Err(_) => (0..term.len()).collect(),
+        };
+        char_starts.push(term.len());
+        GramTerm {
+            char_starts,
+            term,
+            pos: 0,
+            gram_size: min_gram,
+        }
+    }
+
+    fn char_count(&self) -> usize {
+        self.char_starts.len() - 1
+    }
+
+    /// Sets the n-gram of `size` chars starting at char `pos` as the term of
+    /// `input`.
+    fn set_gram(&self, input: &mut dyn TokenStream, pos: usize, size: usize, position: u32) {
+        let gram = &self.term[self.char_starts[pos]..self.char_starts[pos + size]];
+        if let Some(term) = input.term_bytes_attribute_mut().char_term_mut() {
+            term.copy_buffer(gram);
+        }
+        input.position_attribute_mut().set_position(position);
+    }
+}
+
+/// Tokenizes the input tokens into n-grams of the given sizes.
+///
+/// The n-grams of a token are emitted ordered by their start, then by their
+/// size, and keep the offsets of the token. The first n-gram of a token
+/// takes its position increment, the following are stacked on it. Tokens
+/// shorter than `min_gram` are dropped, unless `preserve_original` is set,
+/// which also emits tokens longer than `max_gram` after their n-grams.
+#[derive(Debug)]
+pub struct NGramTokenFilter {
+    input: Box<dyn TokenStream>,
+    min_gram: usize,
+    max_gram: usize,
+    preserve_original: bool,
+    current: Option<GramTerm>,
+    position: u32,
+}
+
+impl NGramTokenFilter {
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        min_gram: usize,
+        max_gram: usize,
+        preserve_original: bool,
+    ) -> Result<Self> {
+        check_gram_sizes(min_gram, max_gram)?;
+        Ok(NGramTokenFilter {
+            input,
+            min_gram,
+            max_gram,
+            preserve_original,
+            current: None,
+            position: 0,
+        })
+    }
+}
+
+impl TokenStream for NGramTokenFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        loop {
+            let mut current = match self.current.take() {
+                Some(current) => current,
+                None => {
+                    if !self.input.increment_token()? {
+                        return Ok(false);
+                    }
+                    self.position += self.input.position_attribute().get_position();
+                    GramTerm::read(self.input.as_ref(), self.min_gram)
+                }
+            };
+            let count = current.char_count();
+            if current.gram_size > self.max_gram || current.pos + current.gram_size > count {
+                current.pos += 1;
+                current.gram_size = self.min_gram;
+            }
+            if current.pos + current.gram_size <= count {
+                let (pos, size) = (current.pos, current.gram_size);
+                current.set_gram(self.input.as_mut(), pos, size, self.position);
+                current.gram_size += 1;
+                self.current = Some(current);
+                self.position = 0;
+                return Ok(true);
+            }
+            if self.preserve_original
+                && count > 0
+                && (count < self.min_gram || count > self.max_gram)
+            {
+                current.set_gram(self.input.as_mut(), 0, count, self.position);
+                self.position = 0;
+                return Ok(true);
+            }
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        let attr = self.input.position_attribute_mut();
+        let position = attr.get_position() + self.position;
+        attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.current = None;
+        self.position = 0;
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for NGramTokenFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+/// Tokenizes the input tokens into the n-grams of the given sizes starting
+/// at the beginning of the token, e.g. "search" with sizes 1 to 3 gives
+/// "s", "se" and "sea", the prefixes a search-as-you-type field matches.
+///
+/// The n-grams keep the offsets of the token. The first n-gram of a token
+/// takes its position increment, the following are stacked on it. Tokens
+/// shorter than `min_gram` are dropped, unless `preserve_original` is set,
+/// which also emits tokens longer than `max_gram` after their n-grams.
+#[derive(Debug)]
+pub struct EdgeNGramTokenFilter {
+    input: Box<dyn TokenStream>,
+    min_gram: usize,
+    max_gram: usize,
+    preserve_original: bool,
+    current: Option<GramTerm>,
+    position: u32,
+}
+
+impl EdgeNGramTokenFilter {
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        min_gram: usize,
+        max_gram: usize,
+        preserve_original: bool,
+    ) -> Result<Self> {
+        check_gram_sizes(min_gram, max_gram)?;
+        Ok(EdgeNGramTokenFilter {
+            input,
+            min_gram,
+            max_gram,
+            preserve_original,
+            current: None,
+            position: 0,
+        })
+    }
+}
+
+impl TokenStream for EdgeNGramTokenFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        loop {
+            let mut current = match self.current.take() {
+                Some(current) => current,
+                None => {
+                    if !self.input.increment_token()? {
+                        return Ok(false);
+                    }
+                    self.position += self.input.position_attribute().get_position();
+                    GramTerm::read(self.input.as_ref(), self.min_gram)
+                }
+            };
+            let count = current.char_count();
+            if current.gram_size <= self.max_gram && current.gram_size <= count {
+                let size = current.gram_size;
+                current.set_gram(self.input.as_mut(), 0, size, self.position);
+                current.gram_size += 1;
+                self.current = Some(current);
+                self.position = 0;
+                return Ok(true);
+            }
+            if self.preserve_original
+                && count > 0
+                && (count < self.min_gram || count > self.max_gram)
+            {
+                current.set_gram(self.input.as_mut(), 0, count, self.position);
+                self.position = 0;
+                return Ok(true);
+            }
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        let attr = self.input.position_attribute_mut();
+        let position = attr.get_position() + self.position;
+        attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.current = None;
+        self.position = 0;
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for EdgeNGramTokenFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn tokenizer(text: &str) -> Box<dyn TokenStream> {
+        Box::new(WhitespaceTokenizer::new(Box::new(Cursor::new(
+            text.as_bytes().to_vec(),
+        ))))
+    }
+
+    #[test]
+    fn test_ngram_filter() {
+        let mut filter = NGramTokenFilter::new(tokenizer("a abc"), 2, 2, false).unwrap();
+        assert_token_stream_contents(
+            &mut filter,
+            &["ab", "bc"],
+            Some(&[(2, 5), (2, 5)]),
+            Some(&[2, 0]),
+        );
+
+        let mut filter = NGramTokenFilter::new(tokenizer("a abc ab"), 2, 2, true).unwrap();
+        assert_token_stream_contents(
+            &mut filter,
+            &["a", "ab", "bc", "abc", "ab"],
+            Some(&[(0, 1), (2, 5), (2, 5), (2, 5), (6, 8)]),
+            Some(&[1, 1, 0, 0, 1]),
+        );
+    }
+
+    #[test]
+    fn test_edge_ngram_filter() {
+        let mut filter = EdgeNGramTokenFilter::new(tokenizer("a search"), 2, 3, false).unwrap();
+        assert_token_stream_contents(
+            &mut filter,
+            &["se", "sea"],
+            Some(&[(2, 8), (2, 8)]),
+            Some(&[2, 0]),
+        );
+
+        let mut filter = EdgeNGramTokenFilter::new(tokenizer("a search"), 2, 3, true).unwrap();
+        assert_token_stream_contents(
+            &mut filter,
+            &["a", "se", "sea", "search"],
+            Some(&[(0, 1), (2, 8), (2, 8), (2, 8)]),
+            Some(&[1, 1, 0, 0]),
+        );
+        assert!(EdgeNGramTokenFilter::new(tokenizer("a"), 0, 3, true).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+    Tokenizer,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::fmt;
+use std::io::Read;
+
+/// Default minimum size of the n-grams of a `NGramTokenizer`.
+pub const DEFAULT_MIN_NGRAM_SIZE: usize = 1;
+/// Default maximum size of the n-grams of a `NGramTokenizer`.
+pub const DEFAULT_MAX_NGRAM_SIZE: usize = 2;
+
+/// Tokenizes the input into character n-grams of the given sizes.
+///
+/// The n-grams are emitted ordered by their start offset, then by their
+/// size, e.g. "abc" with sizes 1 to 2 is tokenized into "a", "ab", "b",
+/// "bc" and "c". Offsets are those of the n-grams in the input, in chars.
+///
+/// N-grams never span characters which are not token characters, as set by
+/// `set_token_chars`, all characters are token characters by default. With
+/// `edge` only the n-grams starting at the beginning of a run of token
+/// characters are emitted, e.g. "ab cd" tokenized with sizes 1 to 2 and
+/// `char::is_alphanumeric` as token characters gives "a", "ab", "c" and
+/// "cd".
+pub struct NGramTokenizer {
+    min_gram: usize,
+    max_gram: usize,
+    edges_only: bool,
+    is_token_char: fn(char) -> bool,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    reader: Box<dyn Read>,
+    text: String,
+    // byte offset of each char of the text, and of the end of the text
+    char_starts: Vec<usize>,
+    // char ranges of the runs of token characters
+    runs: Vec<(usize, usize)>,
+    run: usize,
+    gram_start: usize,
+    gram_size: usize,
+}
+
+impl NGramTokenizer {
+    /// Creates a tokenizer emitting all the n-grams of `min_gram` up to
+    /// `max_gram` chars.
+    pub fn new(reader: Box<dyn Read>, min_gram: usize, max_gram: usize) -> Result<Self> {
+        Self::build(reader, min_gram, max_gram, false)
+    }
+
+    /// Creates a tokenizer emitting the n-grams of `min_gram` up to
+    /// `max_gram` chars anchored to the start of the runs of token chars.
+    pub fn edge(reader: Box<dyn Read>, min_gram: usize, max_gram: usize) -> Result<Self> {
+        Self::build(reader, min_gram, max_gram, true)
+    }
+
+    fn build(
+        reader: Box<dyn Read>,
+        min_gram: usize,
+        max_gram: usize,
+        edges_only: bool,
+    ) -> Result<Self> {
+        check_gram_sizes(min_gram, max_gram)?;
+        Ok(NGramTokenizer {
+            min_gram,
+            max_gram,
+            edges_only,
+            is_token_char: |_| true,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            reader,
+            text: String::new(),
+            char_starts: vec![],
+            runs: vec![],
+            run: 0,
+            gram_start: 0,
+            gram_size: min_gram,
+        })
+    }
+
+    pub fn min_gram(&self) -> usize {
+        self.min_gram
+    }
+
+    pub fn max_gram(&self) -> usize {
+        self.max_gram
+    }
+
+    /// Set which characters n-grams are made of, takes effect on the next
+    /// `reset`.
+    pub fn set_token_chars(&mut self, is_token_char: fn(char) -> bool) {
+        self.is_token_char = is_token_char;
+    }
+
+    fn segment(&mut self) {
+        self.char_starts.clear();
+        self.runs.clear();
+        let mut run_start = None;
+        for (i, (idx, c)) in self.text.char_indices().enumerate() {
+            self.char_starts.push(idx);
+            match (run_start, (self.is_token_char)(c)) {
+                (None, true) => run_start = Some(i),
+                (Some(start), false) => {
+                    self.runs.push((start, i));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        let length = self.char_starts.len();
+        if let Some(start) = run_start {
+            self.runs.push((start, length));
+        }
+        self.char_starts.push(self.text.len());
+    }
+
+    /// Returns the char range of the next n-gram.
+    fn next_gram(&mut self) -> Option<(usize, usize)> {
+        while self.run < self.runs.len() {
+            let run_end = self.runs[self.run].1;
+            if self.gram_size <= self.max_gram && self.gram_start + self.gram_size <= run_end {
+                let gram = (self.gram_start, self.gram_start + self.gram_size);
+                self.gram_size += 1;
+                return Some(gram);
+            }
+            self.gram_size = self.min_gram;
+            if self.edges_only || self.gram_start + self.min_gram >= run_end {
+                self.run += 1;
+                if self.run < self.runs.len() {
+                    self.gram_start = self.runs[self.run].0;
+                }
+            } else {
+                self.gram_start += 1;
+            }
+        }
+        None
+    }
+}
+
+/// Checks the n-gram sizes are valid.
+pub(crate) fn check_gram_sizes(min_gram: usize, max_gram: usize) -> Result<()> {
+    if min_gram < 1 {
+        bail!(IllegalArgument("min_gram must be greater than zero".into()));
+    }
+    if min_gram > max_gram {
+        bail!(IllegalArgument(
+            "min_gram must not be greater than max_gram".into()
+        ));
+    }
+    Ok(())
+}
+
+impl fmt::Debug for NGramTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NGramTokenizer")
+            .field("min_gram", &self.min_gram)
+            .field("max_gram", &self.max_gram)
+            .field("edges_only", &self.edges_only)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .field("runs", &self.runs)
+            .finish()
+    }
+}
+
+impl TokenStream for NGramTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        let (start, end) = match self.next_gram() {
+            Some(gram) => gram,
+            None => return Ok(false),
+        };
+        self.clear_attributes();
+        let text = &self.text[self.char_starts[start]..self.char_starts[end]];
+        self.term_attr.append(text);
+        self.offset_attr.set_offset(start, end)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        let final_offset = self.char_starts.len().saturating_sub(1);
+        self.offset_attr.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.text.clear();
+        self.reader.read_to_string(&mut self.text)?;
+        self.segment();
+        self.run = 0;
+        self.gram_start = self.runs.first().map_or(0, |run| run.0);
+        self.gram_size = self.min_gram;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+impl Tokenizer for NGramTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.reader = reader;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use std::io::Cursor;
+
+    fn assert_tokenizes_to(
+        tokenizer: &mut NGramTokenizer,
+        text: &str,
+        terms: &[&str],
+        offsets: &[(usize, usize)],
+    ) {
+        tokenizer
+            .set_reader(Box::new(Cursor::new(text.as_bytes().to_vec())))
+            .unwrap();
+        assert_token_stream_contents(tokenizer, terms, Some(offsets), None);
+    }
+
+    #[test]
+    fn test_ngrams() {
+        let mut tokenizer = NGramTokenizer::new(Box::new(Cursor::new(vec![])), 1, 2).unwrap();
+        assert_tokenizes_to(
+            &mut tokenizer,
+            "äbc",
+            &["ä", "äb", "b", "bc", "c"],
+            &[(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)],
+        );
+        assert_eq!(tokenizer.offset_attribute().end_offset(), 3);
+
+        tokenizer.set_token_chars(char::is_alphanumeric);
+        assert_tokenizes_to(
+            &mut tokenizer,
+            "a bc",
+            &["a", "b", "bc", "c"],
+            &[(0, 1), (2, 3), (2, 4), (3, 4)],
+        );
+        assert_tokenizes_to(&mut tokenizer, "", &[], &[]);
+
+        assert!(NGramTokenizer::new(Box::new(Cursor::new(vec![])), 0, 2).is_err());
+        assert!(NGramTokenizer::new(Box::new(Cursor::new(vec![])), 3, 2).is_err());
+    }
+
+    #[test]
+    fn test_edge_ngrams() {
+        let mut tokenizer = NGramTokenizer::edge(Box::new(Cursor::new(vec![])), 2, 3).unwrap();
+        tokenizer.set_token_chars(char::is_alphanumeric);
+        assert_tokenizes_to(
+            &mut tokenizer,
+            "abcd, e fg",
+            &["ab", "abc", "fg"],
+            &[(0, 2), (0, 3), (8, 10)],
+        );
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    ASCIIFoldingFilter, Analyzer, ComponentsCache, KeywordTokenizer, LowerCaseFilter,
+    ReuseStrategy, TokenStream,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::io::Read;
+use std::str::FromStr;
+
+/// A filter which normalizes a term without splitting it, so that it can be
+/// used by a `Normalizer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizerFilter {
+    /// Lower cases the term, see `LowerCaseFilter`.
+    LowerCase,
+    /// Folds the term to ASCII, see `ASCIIFoldingFilter`.
+    ASCIIFolding,
+}
+
+impl NormalizerFilter {
+    /// Wraps `input` with the filter of this type.
+    pub fn create_filter(self, input: Box<dyn TokenStream>) -> Box<dyn TokenStream> {
+        match self {
+            NormalizerFilter::LowerCase => Box::new(LowerCaseFilter::new(input)),
+            NormalizerFilter::ASCIIFolding => Box::new(ASCIIFoldingFilter::new(input, false)),
+        }
+    }
+}
+
+impl FromStr for NormalizerFilter {
+    type Err = ::error::Error;
+
+    /// Parses `lowercase` or `asciifolding`, case insensitive.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lowercase" => Ok(NormalizerFilter::LowerCase),
+            "asciifolding" => Ok(NormalizerFilter::ASCIIFolding),
+            _ => bail!(IllegalArgument(format!("unknown normalizer filter: {}", s))),
+        }
+    }
+}
+
+/// An analyzer for exact match fields, which keeps the entire value of a
+/// field as a single token like `KeywordAnalyzer`, but normalizes it with a
+/// chain of filters, e.g. to match values regardless of their case.
+#[derive(Debug)]
+pub struct Normalizer {
+    filters: Vec<NormalizerFilter>,
+    cache: ComponentsCache,
+}
+
+impl Normalizer {
+    /// Creates a normalizer applying `filters` in order.
+    pub fn new(filters: Vec<NormalizerFilter>) -> Self {
+        Normalizer {
+            filters,
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        }
+    }
+
+    pub fn filters(&self) -> &[NormalizerFilter] {
+        &self.filters
+    }
+}
+
+impl Analyzer for Normalizer {
+    fn create_components(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = KeywordTokenizer::new(reader);
+        Ok(self.normalize_stream(field_name, Box::new(tokenizer)))
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+
+    fn normalize_stream(
+        &self,
+        _field_name: &str,
+        input: Box<dyn TokenStream>,
+    ) -> Box<dyn TokenStream> {
+        self.filters
+            .iter()
+            .fold(input, |stream, filter| filter.create_filter(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_normalizer() {
+        let normalizer = Normalizer::new(vec![
+            "lowercase".parse().unwrap(),
+            NormalizerFilter::ASCIIFolding,
+        ]);
+        assert_analyzes_to(&normalizer, "Zürich Centre", &["zurich centre"], None, None);
+        assert_eq!(
+            normalizer.normalize("city", "SÃO PAULO").unwrap(),
+            b"sao paulo"
+        );
+        assert!("stem".parse::<NormalizerFilter>().is_err());
+    }
+}
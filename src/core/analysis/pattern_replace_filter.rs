@@ -0,0 +1,141 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+use regex::Regex;
+
+/// Replaces the matches of a regular expression in the text of each token.
+///
+/// The replacement may refer to the capture groups of the pattern, e.g.
+/// `$1` or `${name}`, see `Regex::replace`. Terms which aren't valid UTF-8
+/// are left as they are. Tokens are kept even if their replaced text is
+/// empty.
+#[derive(Debug)]
+pub struct PatternReplaceFilter {
+    input: Box<dyn TokenStream>,
+    pattern: Regex,
+    replacement: String,
+    replace_all: bool,
+}
+
+impl PatternReplaceFilter {
+    /// Creates a filter replacing every match of `pattern` by `replacement`
+    /// if `replace_all` is set, or only the first one otherwise.
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        pattern: Regex,
+        replacement: &str,
+        replace_all: bool,
+    ) -> Self {
+        PatternReplaceFilter {
+            input,
+            pattern,
+            replacement: replacement.to_string(),
+            replace_all,
+        }
+    }
+}
+
+impl TokenStream for PatternReplaceFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            let replaced = match ::std::str::from_utf8(&term.term_buffer) {
+                Ok(text) if self.pattern.is_match(text) => {
+                    if self.replace_all {
+                        self.pattern
+                            .replace_all(text, self.replacement.as_str())
+                            .into_owned()
+                    } else {
+                        self.pattern
+                            .replace(text, self.replacement.as_str())
+                            .into_owned()
+                    }
+                }
+                _ => return Ok(true),
+            };
+            term.copy_buffer(replaced.as_bytes());
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for PatternReplaceFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn filter(
+        text: &str,
+        pattern: &str,
+        replacement: &str,
+        replace_all: bool,
+    ) -> PatternReplaceFilter {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        PatternReplaceFilter::new(
+            Box::new(tokenizer),
+            Regex::new(pattern).unwrap(),
+            replacement,
+            replace_all,
+        )
+    }
+
+    #[test]
+    fn test_pattern_replace_filter() {
+        assert_token_stream_contents(
+            &mut filter("aabfooaabfooabfoob abcd", "a*b", "-", true),
+            &["-foo-foo-foo-", "-cd"],
+            None,
+            None,
+        );
+        assert_token_stream_contents(
+            &mut filter("aabfooaabfooabfoob abcd", "a*b", "-", false),
+            &["-fooaabfooabfoob", "-cd"],
+            None,
+            None,
+        );
+        assert_token_stream_contents(
+            &mut filter("user_id=42 none", r"(\w+)=(\d+)", "$2:$1", true),
+            &["42:user_id", "none"],
+            None,
+            None,
+        );
+    }
+}
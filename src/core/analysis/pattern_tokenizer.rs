@@ -0,0 +1,236 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+    Tokenizer,
+};
+
+use error::Result;
+
+use regex::Regex;
+
+use std::fmt;
+use std::io::Read;
+
+/// Tokenizes the input with a regular expression, either by splitting the
+/// input at the matches of the pattern, or by emitting a capture group of
+/// each match as a token.
+///
+/// For example with the pattern `\s*,\s*` and no group, "a , b,c" is split
+/// into "a", "b" and "c". With the pattern `'([^']+)'` and group 1,
+/// "'aaa' 'bbb'" gives "aaa" and "bbb". Empty tokens are never emitted.
+pub struct PatternTokenizer {
+    pattern: Regex,
+    group: Option<usize>,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    reader: Box<dyn Read>,
+    text: String,
+    // byte ranges of the tokens
+    tokens: Vec<(usize, usize)>,
+    index: usize,
+    // byte and char offset of the end of the last token, to convert byte
+    // offsets into char offsets incrementally
+    last_offset: (usize, usize),
+    final_offset: usize,
+}
+
+impl PatternTokenizer {
+    /// Creates a tokenizer splitting the input at the matches of `pattern`
+    /// if `group` is `None`, or emitting the `group` of each match
+    /// otherwise, group 0 being the whole match.
+    pub fn new(reader: Box<dyn Read>, pattern: Regex, group: Option<usize>) -> Self {
+        PatternTokenizer {
+            pattern,
+            group,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            reader,
+            text: String::new(),
+            tokens: vec![],
+            index: 0,
+            last_offset: (0, 0),
+            final_offset: 0,
+        }
+    }
+
+    pub fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    pub fn group(&self) -> Option<usize> {
+        self.group
+    }
+
+    fn find_tokens(&mut self) {
+        self.tokens.clear();
+        match self.group {
+            Some(group) => {
+                for captures in self.pattern.captures_iter(&self.text) {
+                    if let Some(m) = captures.get(group) {
+                        if m.start() < m.end() {
+                            self.tokens.push((m.start(), m.end()));
+                        }
+                    }
+                }
+            }
+            None => {
+                let mut start = 0;
+                for m in self.pattern.find_iter(&self.text) {
+                    if start < m.start() {
+                        self.tokens.push((start, m.start()));
+                    }
+                    start = m.end();
+                }
+                if start < self.text.len() {
+                    self.tokens.push((start, self.text.len()));
+                }
+            }
+        }
+    }
+
+    /// Converts a byte offset, not before the last converted one, into a
+    /// char offset.
+    fn char_offset(&mut self, byte_offset: usize) -> usize {
+        let (last_byte, last_char) = self.last_offset;
+        let offset = last_char + self.text[last_byte..byte_offset].chars().count();
+        self.last_offset = (byte_offset, offset);
+        offset
+    }
+}
+
+impl fmt::Debug for PatternTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PatternTokenizer")
+            .field("pattern", &self.pattern)
+            .field("group", &self.group)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl TokenStream for PatternTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.index >= self.tokens.len() {
+            return Ok(false);
+        }
+        self.clear_attributes();
+        let (start, end) = self.tokens[self.index];
+        self.index += 1;
+        self.term_attr.append(&self.text[start..end]);
+        let start_offset = self.char_offset(start);
+        let end_offset = self.char_offset(end);
+        self.offset_attr.set_offset(start_offset, end_offset)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        self.offset_attr
+            .set_offset(self.final_offset, self.final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.text.clear();
+        self.reader.read_to_string(&mut self.text)?;
+        self.find_tokens();
+        self.index = 0;
+        self.last_offset = (0, 0);
+        self.final_offset = self.text.chars().count();
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+impl Tokenizer for PatternTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.reader = reader;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use std::io::Cursor;
+
+    fn assert_tokenizes_to(
+        text: &str,
+        pattern: &str,
+        group: Option<usize>,
+        terms: &[&str],
+        offsets: &[(usize, usize)],
+    ) {
+        let reader = Box::new(Cursor::new(text.as_bytes().to_vec()));
+        let mut tokenizer = PatternTokenizer::new(reader, Regex::new(pattern).unwrap(), group);
+        assert_token_stream_contents(&mut tokenizer, terms, Some(offsets), None);
+        assert_eq!(
+            tokenizer.offset_attribute().end_offset(),
+            text.chars().count()
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        assert_tokenizes_to(
+            "été , b,,c ",
+            r"\s*,\s*",
+            None,
+            &["été", "b", "c "],
+            &[(0, 3), (6, 7), (9, 11)],
+        );
+        assert_tokenizes_to(",,", ",", None, &[], &[]);
+    }
+
+    #[test]
+    fn test_group() {
+        let text = "2019-10-28 ERROR [main] Connection refused";
+        assert_tokenizes_to(text, r"\[(\w+)\]", Some(1), &["main"], &[(18, 22)]);
+        assert_tokenizes_to(
+            "fooBar bazQux",
+            r"[A-Z]?[a-z]+",
+            Some(0),
+            &["foo", "Bar", "baz", "Qux"],
+            &[(0, 3), (3, 6), (7, 10), (10, 13)],
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Debug;
+
+/// The default maximum length of the codes of `Metaphone` and
+/// `DoubleMetaphone`.
+pub const DEFAULT_MAX_CODE_LENGTH: usize = 4;
+
+/// Encodes words to codes representing how they sound, so that words which
+/// sound alike, e.g. misspelled names, have the same code.
+pub trait PhoneticEncoder: Debug + Send + Sync {
+    /// Encodes `text`, returns an empty string if it can't be encoded.
+    fn encode(&self, text: &str) -> String;
+
+    /// Alternate codes of `text`, for the encoders which give several
+    /// codes for ambiguous pronunciations.
+    fn encode_alternates(&self, _text: &str) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// The American Soundex algorithm, which encodes a word into its first
+/// letter followed by three digits, e.g. "Robert" into "R163".
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Soundex;
+
+impl Soundex {
+    // the codes of the letters A-Z, H and W are silent
+    const MAPPING: &'static [u8; 26] = b"01230120022455012623010202";
+
+    fn code(c: char) -> u8 {
+        match c {
+            'H' | 'W' => b'-',
+            _ => Self::MAPPING[(c as u8 - b'A') as usize],
+        }
+    }
+}
+
+impl PhoneticEncoder for Soundex {
+    fn encode(&self, text: &str) -> String {
+        let mut letters = text
+            .chars()
+            .filter(char::is_ascii_alphabetic)
+            .map(|c| c.to_ascii_uppercase());
+        let first = match letters.next() {
+            Some(first) => first,
+            None => return String::new(),
+        };
+        let mut code = String::with_capacity(4);
+        code.push(first);
+        let mut last = Self::code(first);
+        for c in letters {
+            if code.len() == 4 {
+                break;
+            }
+            let digit = Self::code(c);
+            if digit == b'-' {
+                continue;
+            }
+            if digit != b'0' && digit != last {
+                code.push(digit as char);
+            }
+            last = digit;
+        }
+        while code.len() < 4 {
+            code.push('0');
+        }
+        code
+    }
+}
+
+/// The original Metaphone algorithm by Lawrence Philips, e.g. "Thompson"
+/// is encoded into "TMSN".
+#[derive(Debug, Clone, Copy)]
+pub struct Metaphone {
+    max_code_length: usize,
+}
+
+impl Default for Metaphone {
+    fn default() -> Self {
+        Metaphone::new()
+    }
+}
+
+impl Metaphone {
+    pub fn new() -> Self {
+        Metaphone {
+            max_code_length: DEFAULT_MAX_CODE_LENGTH,
+        }
+    }
+
+    /// Set the maximum length of the codes, `DEFAULT_MAX_CODE_LENGTH` by
+    /// default.
+    pub fn set_max_code_length(&mut self, max_code_length: usize) {
+        self.max_code_length = max_code_length;
+    }
+
+    pub fn max_code_length(&self) -> usize {
+        self.max_code_length
+    }
+}
+
+// the chars of a word being encoded, with the helpers of the algorithm
+struct Word(Vec<char>);
+
+impl Word {
+    fn at(&self, index: usize) -> char {
+        self.0.get(index).cloned().unwrap_or('\0')
+    }
+
+    fn is_vowel(&self, index: usize) -> bool {
+        "AEIOU".contains(self.at(index))
+    }
+
+    fn is_front_vowel(&self, index: usize) -> bool {
+        "EIY".contains(self.at(index))
+    }
+
+    fn is_previous(&self, index: usize, c: char) -> bool {
+        index > 0 && index < self.0.len() && self.0[index - 1] == c
+    }
+
+    fn is_next(&self, index: usize, c: char) -> bool {
+        index + 1 < self.0.len() && self.0[index + 1] == c
+    }
+
+    fn is_last(&self, index: usize) -> bool {
+        index + 1 == self.0.len()
+    }
+
+    fn region_matches(&self, index: usize, test: &str) -> bool {
+        let len = test.chars().count();
+        index + len <= self.0.len() && self.0[index..index + len].iter().cloned().eq(test.chars())
+    }
+}
+
+impl PhoneticEncoder for Metaphone {
+    fn encode(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().flat_map(char::to_uppercase).collect();
+        if chars.len() <= 1 {
+            return chars.into_iter().collect();
+        }
+
+        // initial exceptions
+        let word = Word(match (chars[0], chars[1]) {
+            ('K', 'N') | ('G', 'N') | ('P', 'N') | ('A', 'E') | ('W', 'R') => chars[1..].to_vec(),
+            ('W', 'H') => {
+                let mut chars = chars[1..].to_vec();
+                chars[0] = 'W';
+                chars
+            }
+            ('X', _) => {
+                let mut chars = chars;
+                chars[0] = 'S';
+                chars
+            }
+            _ => chars,
+        });
+
+        let mut code = String::with_capacity(self.max_code_length);
+        let mut n = 0;
+        while code.len() < self.max_code_length && n < word.0.len() {
+            let symbol = word.0[n];
+            // duplicate letters are skipped, except C
+            if symbol != 'C' && word.is_previous(n, symbol) {
+                n += 1;
+                continue;
+            }
+            match symbol {
+                'A' | 'E' | 'I' | 'O' | 'U' => {
+                    if n == 0 {
+                        code.push(symbol);
+                    }
+                }
+                'B' => {
+                    // silent in -MB
+                    if !(word.is_previous(n, 'M') && word.is_last(n)) {
+                        code.push('B');
+                    }
+                }
+                'C' => {
+                    if word.is_previous(n, 'S') && !word.is_last(n) && word.is_front_vowel(n + 1) {
+                        // silent in SCI, SCE and SCY
+                    } else if word.region_matches(n, "CIA") {
+                        code.push('X');
+                    } else if !word.is_last(n) && word.is_front_vowel(n + 1) {
+                        code.push('S');
+                    } else if word.is_previous(n, 'S') && word.is_next(n, 'H') {
+                        code.push('K');
+                    } else if word.is_next(n, 'H') {
+                        if n == 0 && word.0.len() >= 3 && word.is_vowel(2) {
+                            code.push('K');
+                        } else {
+                            code.push('X');
+                        }
+                    } else {
+                        code.push('K');
+                    }
+                }
+                'D' => {
+                    if !word.is_last(n + 1) && word.is_next(n, 'G') && word.is_front_vowel(n + 2) {
+                        code.push('J');
+                        n += 2;
+                    } else {
+                        code.push('T');
+                    }
+                }
+                'G' => {
+                    if word.is_next(n, 'H') && (word.is_last(n + 1) || !word.is_vowel(n + 2)) {
+                        // silent in GH at the end or before a consonant
+                    } else if n > 0 && word.region_matches(n, "GN") {
+                        // silent in GN and GNED
+                    } else if !word.is_last(n)
+                        && word.is_front_vowel(n + 1)
+                        && !word.is_previous(n, 'G')
+                    {
+                        code.push('J');
+                    } else {
+                        code.push('K');
+                    }
+                }
+                'H' => {
+                    let after_varson = n > 0 && "CSPTG".contains(word.0[n - 1]);
+                    if !word.is_last(n) && !after_varson && word.is_vowel(n + 1) {
+                        code.push('H');
+                    }
+                }
+                'F' | 'J' | 'L' | 'M' | 'N' | 'R' => code.push(symbol),
+                'K' => {
+                    if !word.is_previous(n, 'C') {
+                        code.push('K');
+                    }
+                }
+                'P' => code.push(if word.is_next(n, 'H') { 'F' } else { 'P' }),
+                'Q' => code.push('K'),
+                'S' => {
+                    if word.region_matches(n, "SH")
+                        || word.region_matches(n, "SIO")
+                        || word.region_matches(n, "SIA")
+                    {
+                        code.push('X');
+                    } else {
+                        code.push('S');
+                    }
+                }
+                'T' => {
+                    if word.region_matches(n, "TIA") || word.region_matches(n, "TIO") {
+                        code.push('X');
+                    } else if word.region_matches(n, "TCH") {
+                        // silent in TCH
+                    } else if word.region_matches(n, "TH") {
+                        code.push('0');
+                    } else {
+                        code.push('T');
+                    }
+                }
+                'V' => code.push('F'),
+                'W' | 'Y' => {
+                    if !word.is_last(n) && word.is_vowel(n + 1) {
+                        code.push(symbol);
+                    }
+                }
+                'X' => code.push_str("KS"),
+                'Z' => code.push('S'),
+                _ => {}
+            }
+            n += 1;
+        }
+        code.truncate(self.max_code_length);
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soundex() {
+        let soundex = Soundex;
+        assert_eq!(soundex.encode("Robert"), "R163");
+        assert_eq!(soundex.encode("Rupert"), "R163");
+        assert_eq!(soundex.encode("Ashcraft"), "A261");
+        assert_eq!(soundex.encode("Tymczak"), "T522");
+        assert_eq!(soundex.encode("Pfister"), "P236");
+        assert_eq!(soundex.encode("Lee"), "L000");
+        assert_eq!(soundex.encode("123"), "");
+    }
+
+    #[test]
+    fn test_metaphone() {
+        let metaphone = Metaphone::new();
+        let words = "The quick brown fox jumped over the lazy dogs howl testing";
+        let codes: Vec<String> = words.split(' ').map(|w| metaphone.encode(w)).collect();
+        assert_eq!(
+            codes,
+            vec!["0", "KK", "BRN", "FKS", "JMPT", "OFR", "0", "LS", "TKS", "HL", "TSTN"]
+        );
+        assert_eq!(metaphone.encode("Knight"), "NT");
+        assert_eq!(metaphone.encode("Wright"), "RT");
+        assert_eq!(metaphone.encode("Xavier"), "SFR");
+        assert_eq!(metaphone.encode("Science"), "SNS");
+        assert_eq!(metaphone.encode("Thumb"), "0M");
+
+        let mut metaphone = Metaphone::new();
+        metaphone.set_max_code_length(2);
+        assert_eq!(metaphone.encode("testing"), "TS");
+    }
+}
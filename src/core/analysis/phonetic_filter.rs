@@ -0,0 +1,171 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{PhoneticEncoder, TokenFilter, TokenStream};
+
+use error::Result;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Replaces the terms by their phonetic codes, or injects the codes as
+/// synonyms of the terms, for matching names which sound alike.
+///
+/// All the codes of a term are output, e.g. the primary and alternate codes
+/// of `DoubleMetaphone`, stacked at the position of the term. Terms which
+/// can't be encoded, e.g. numbers, are passed through unchanged.
+#[derive(Debug)]
+pub struct PhoneticFilter {
+    input: Box<dyn TokenStream>,
+    encoder: Arc<dyn PhoneticEncoder>,
+    inject: bool,
+    // codes of the current term which are still to be output
+    pending: VecDeque<String>,
+}
+
+impl PhoneticFilter {
+    /// Creates a filter encoding the terms with `encoder`, if `inject` is
+    /// true the codes are output along with the original terms, otherwise
+    /// they replace them.
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        encoder: Arc<dyn PhoneticEncoder>,
+        inject: bool,
+    ) -> Self {
+        PhoneticFilter {
+            input,
+            encoder,
+            inject,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn encoder(&self) -> &dyn PhoneticEncoder {
+        self.encoder.as_ref()
+    }
+
+    fn output_code(&mut self, code: &str, position_increment: Option<u32>) {
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            term.copy_buffer(code.as_bytes());
+        }
+        if let Some(position_increment) = position_increment {
+            self.input
+                .position_attribute_mut()
+                .set_position(position_increment);
+        }
+    }
+}
+
+impl TokenStream for PhoneticFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if let Some(code) = self.pending.pop_front() {
+            // the attributes of the input still hold the current token
+            self.output_code(&code, Some(0));
+            return Ok(true);
+        }
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+
+        let mut codes: Vec<String> = vec![];
+        if let Ok(text) =
+            ::std::str::from_utf8(self.input.term_bytes_attribute().get_bytes_ref().bytes())
+        {
+            let primary = self.encoder.encode(text);
+            for code in Some(primary)
+                .into_iter()
+                .chain(self.encoder.encode_alternates(text))
+            {
+                if !code.is_empty() && code != text && !codes.contains(&code) {
+                    codes.push(code);
+                }
+            }
+        }
+        if codes.is_empty() {
+            return Ok(true);
+        }
+        if self.inject {
+            self.pending.extend(codes);
+        } else {
+            self.output_code(&codes[0], None);
+            self.pending.extend(codes.into_iter().skip(1));
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.pending.clear();
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for PhoneticFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::{DoubleMetaphone, Metaphone, Soundex, WhitespaceTokenizer};
+    use std::io::Cursor;
+
+    fn filter(text: &str, encoder: Arc<dyn PhoneticEncoder>, inject: bool) -> PhoneticFilter {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        PhoneticFilter::new(Box::new(tokenizer), encoder, inject)
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_token_stream_contents(
+            &mut filter("Robert Rupert 42", Arc::new(Soundex), false),
+            &["R163", "R163", "42"],
+            None,
+            Some(&[1, 1, 1]),
+        );
+        assert_token_stream_contents(
+            &mut filter("Smith Thomas", Arc::new(DoubleMetaphone::new()), false),
+            &["SM0", "XMT", "TMS"],
+            None,
+            Some(&[1, 0, 1]),
+        );
+    }
+
+    #[test]
+    fn test_inject() {
+        assert_token_stream_contents(
+            &mut filter("quick fox", Arc::new(Metaphone::new()), true),
+            &["quick", "KK", "fox", "FKS"],
+            None,
+            Some(&[1, 0, 1, 0]),
+        );
+        assert_token_stream_contents(
+            &mut filter("Schmidt", Arc::new(DoubleMetaphone::new()), true),
+            &["Schmidt", "XMT", "SMT"],
+            None,
+            Some(&[1, 0, 0]),
+        );
+    }
+}
@@ -0,0 +1,444 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+/// Stemmer, implementing the Porter Stemming Algorithm.
+///
+/// The Stemmer class transforms a word into its root form. The input word
+/// is expected to be lower case, this is a port of Martin Porter's ANSI C
+/// version, including its departures from the published algorithm, like
+/// Lucene's `PorterStemmer`.
+#[derive(Debug, Default)]
+pub struct PorterStemmer {
+    b: Vec<u8>,
+    // offset to the end of the string
+    k: isize,
+    // a general offset into the string
+    j: isize,
+}
+
+impl PorterStemmer {
+    pub fn new() -> PorterStemmer {
+        Default::default()
+    }
+
+    /// Stems `word` in place, returns true if it was changed.
+    pub fn stem(&mut self, word: &mut Vec<u8>) -> bool {
+        // strings of length 1 or 2 don't go through the stemming process
+        if word.len() <= 2 {
+            return false;
+        }
+        self.b.clear();
+        self.b.extend_from_slice(word);
+        self.k = word.len() as isize - 1;
+
+        self.step1ab();
+        if self.k > 0 {
+            self.step1c();
+            self.step2();
+            self.step3();
+            self.step4();
+            self.step5();
+        }
+
+        let len = (self.k + 1) as usize;
+        if len == word.len() && self.b[..len] == word[..] {
+            false
+        } else {
+            word.clear();
+            word.extend_from_slice(&self.b[..len]);
+            true
+        }
+    }
+
+    #[inline]
+    fn at(&self, i: isize) -> u8 {
+        self.b[i as usize]
+    }
+
+    /// true if b[i] is a consonant.
+    fn cons(&self, i: isize) -> bool {
+        match self.at(i) {
+            b'a' | b'e' | b'i' | b'o' | b'u' => false,
+            b'y' => i == 0 || !self.cons(i - 1),
+            _ => true,
+        }
+    }
+
+    /// Measures the number of consonant sequences between 0 and j. if c is
+    /// a consonant sequence and v a vowel sequence, and <..> indicates
+    /// arbitrary presence,
+    ///
+    /// ```text
+    ///     <c><v>       gives 0
+    ///     <c>vc<v>     gives 1
+    ///     <c>vcvc<v>   gives 2
+    ///     <c>vcvcvc<v> gives 3
+    ///     ....
+    /// ```
+    fn m(&self) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+        loop {
+            if i > self.j {
+                return n;
+            }
+            if !self.cons(i) {
+                break;
+            }
+            i += 1;
+        }
+        i += 1;
+        loop {
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if self.cons(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            n += 1;
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if !self.cons(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+
+    /// true if 0,...j contains a vowel
+    fn vowel_in_stem(&self) -> bool {
+        (0..=self.j).any(|i| !self.cons(i))
+    }
+
+    /// true if j,(j-1) contain a double consonant.
+    fn double_c(&self, j: isize) -> bool {
+        if j < 1 || self.at(j) != self.at(j - 1) {
+            return false;
+        }
+        self.cons(j)
+    }
+
+    /// true if i-2,i-1,i has the form consonant - vowel - consonant and also
+    /// if the second c is not w,x or y. this is used when trying to restore
+    /// an e at the end of a short word. e.g.
+    ///
+    /// ```text
+    ///     cav(e), lov(e), hop(e), crim(e), but
+    ///     snow, box, tray.
+    /// ```
+    fn cvc(&self, i: isize) -> bool {
+        if i < 2 || !self.cons(i) || self.cons(i - 1) || !self.cons(i - 2) {
+            return false;
+        }
+        match self.at(i) {
+            b'w' | b'x' | b'y' => false,
+            _ => true,
+        }
+    }
+
+    /// true if 0,...k ends with the string s, sets j to the offset before s.
+    fn ends(&mut self, s: &[u8]) -> bool {
+        let length = s.len() as isize;
+        if length > self.k + 1 {
+            return false;
+        }
+        let start = (self.k + 1 - length) as usize;
+        if &self.b[start..=self.k as usize] != s {
+            return false;
+        }
+        self.j = self.k - length;
+        true
+    }
+
+    /// sets (j+1),...k to the characters in the string s, readjusting k.
+    fn set_to(&mut self, s: &[u8]) {
+        let start = (self.j + 1) as usize;
+        self.b.truncate(start);
+        self.b.extend_from_slice(s);
+        self.k = self.j + s.len() as isize;
+    }
+
+    fn r(&mut self, s: &[u8]) {
+        if self.m() > 0 {
+            self.set_to(s);
+        }
+    }
+
+    /// Gets rid of plurals and -ed or -ing. e.g.
+    ///
+    /// ```text
+    ///     caresses  ->  caress
+    ///     ponies    ->  poni
+    ///     ties      ->  ti
+    ///     caress    ->  caress
+    ///     cats      ->  cat
+    ///
+    ///     feed      ->  feed
+    ///     agreed    ->  agree
+    ///     disabled  ->  disable
+    ///
+    ///     matting   ->  mat
+    ///     mating    ->  mate
+    ///     meeting   ->  meet
+    ///     milling   ->  mill
+    ///     messing   ->  mess
+    ///
+    ///     meetings  ->  meet
+    /// ```
+    fn step1ab(&mut self) {
+        if self.at(self.k) == b's' {
+            if self.ends(b"sses") {
+                self.k -= 2;
+            } else if self.ends(b"ies") {
+                self.set_to(b"i");
+            } else if self.at(self.k - 1) != b's' {
+                self.k -= 1;
+            }
+        }
+        if self.ends(b"eed") {
+            if self.m() > 0 {
+                self.k -= 1;
+            }
+        } else if (self.ends(b"ed") || self.ends(b"ing")) && self.vowel_in_stem() {
+            self.k = self.j;
+            if self.ends(b"at") {
+                self.set_to(b"ate");
+            } else if self.ends(b"bl") {
+                self.set_to(b"ble");
+            } else if self.ends(b"iz") {
+                self.set_to(b"ize");
+            } else if self.double_c(self.k) {
+                match self.at(self.k) {
+                    b'l' | b's' | b'z' => {}
+                    _ => self.k -= 1,
+                }
+            } else if self.m() == 1 && self.cvc(self.k) {
+                self.set_to(b"e");
+            }
+        }
+    }
+
+    /// Turns terminal y to i when there is another vowel in the stem.
+    fn step1c(&mut self) {
+        if self.ends(b"y") && self.vowel_in_stem() {
+            let k = self.k as usize;
+            self.b[k] = b'i';
+        }
+    }
+
+    /// Maps double suffices to single ones. so -ization ( = -ize plus
+    /// -ation) maps to -ize etc. note that the string before the suffix must
+    /// give m() > 0.
+    fn step2(&mut self) {
+        const SUFFIXES: &[(&[u8], &[u8])] = &[
+            (b"ational", b"ate"),
+            (b"tional", b"tion"),
+            (b"enci", b"ence"),
+            (b"anci", b"ance"),
+            (b"izer", b"ize"),
+            (b"bli", b"ble"),
+            (b"alli", b"al"),
+            (b"entli", b"ent"),
+            (b"eli", b"e"),
+            (b"ousli", b"ous"),
+            (b"ization", b"ize"),
+            (b"ation", b"ate"),
+            (b"ator", b"ate"),
+            (b"alism", b"al"),
+            (b"iveness", b"ive"),
+            (b"fulness", b"ful"),
+            (b"ousness", b"ous"),
+            (b"aliti", b"al"),
+            (b"iviti", b"ive"),
+            (b"biliti", b"ble"),
+            (b"logi", b"log"),
+        ];
+        self.replace_suffix(SUFFIXES);
+    }
+
+    /// Deals with -ic-, -full, -ness etc. similar strategy to step2.
+    fn step3(&mut self) {
+        const SUFFIXES: &[(&[u8], &[u8])] = &[
+            (b"icate", b"ic"),
+            (b"ative", b""),
+            (b"alize", b"al"),
+            (b"iciti", b"ic"),
+            (b"ical", b"ic"),
+            (b"ful", b""),
+            (b"ness", b""),
+        ];
+        self.replace_suffix(SUFFIXES);
+    }
+
+    // replaces the first matching suffix if the stem before it gives m() > 0
+    fn replace_suffix(&mut self, suffixes: &[(&[u8], &[u8])]) {
+        if self.k < 1 {
+            return;
+        }
+        for &(suffix, replacement) in suffixes {
+            if self.ends(suffix) {
+                self.r(replacement);
+                return;
+            }
+        }
+    }
+
+    /// Takes off -ant, -ence etc., in context <c>vcvc<v>.
+    fn step4(&mut self) {
+        const SUFFIXES: &[&[u8]] = &[
+            b"al", b"ance", b"ence", b"er", b"ic", b"able", b"ible", b"ant", b"ement", b"ment",
+            b"ent", b"ism", b"ate", b"iti", b"ous", b"ive", b"ize",
+        ];
+        if self.k < 1 {
+            return;
+        }
+        let mut found = false;
+        for suffix in SUFFIXES {
+            if self.ends(suffix) {
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            found = (self.ends(b"ion")
+                && self.j >= 0
+                && (self.at(self.j) == b's' || self.at(self.j) == b't'))
+                || self.ends(b"ou");
+        }
+        if found && self.m() > 1 {
+            self.k = self.j;
+        }
+    }
+
+    /// Removes a final -e if m() > 1, and changes -ll to -l if m() > 1.
+    fn step5(&mut self) {
+        self.j = self.k;
+        if self.at(self.k) == b'e' {
+            let a = self.m();
+            if a > 1 || a == 1 && !self.cvc(self.k - 1) {
+                self.k -= 1;
+            }
+        }
+        if self.at(self.k) == b'l' && self.double_c(self.k) && self.m() > 1 {
+            self.k -= 1;
+        }
+    }
+}
+
+/// Transforms the token stream as per the Porter stemming algorithm.
+///
+/// The input must already be lower cased, e.g. by a `LowerCaseFilter`.
+#[derive(Debug)]
+pub struct PorterStemFilter {
+    input: Box<dyn TokenStream>,
+    stemmer: PorterStemmer,
+}
+
+impl PorterStemFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        PorterStemFilter {
+            input,
+            stemmer: PorterStemmer::new(),
+        }
+    }
+}
+
+impl TokenStream for PorterStemFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            self.stemmer.stem(&mut term.term_buffer);
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for PorterStemFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porter_stemmer() {
+        let cases = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("ties", "ti"),
+            ("caress", "caress"),
+            ("cats", "cat"),
+            ("feed", "feed"),
+            ("agreed", "agre"),
+            ("plastered", "plaster"),
+            ("motoring", "motor"),
+            ("sing", "sing"),
+            ("conflated", "conflat"),
+            ("troubled", "troubl"),
+            ("sized", "size"),
+            ("hopping", "hop"),
+            ("falling", "fall"),
+            ("filing", "file"),
+            ("happy", "happi"),
+            ("sky", "sky"),
+            ("relational", "relat"),
+            ("generalizations", "gener"),
+            ("oscillators", "oscil"),
+            ("hopefulness", "hope"),
+            ("goodness", "good"),
+            ("adjustable", "adjust"),
+            ("adoption", "adopt"),
+            ("effective", "effect"),
+            ("controll", "control"),
+            ("roll", "roll"),
+            ("is", "is"),
+        ];
+        let mut stemmer = PorterStemmer::new();
+        for &(word, stem) in &cases {
+            let mut term = word.as_bytes().to_vec();
+            assert_eq!(stemmer.stem(&mut term), word != stem, "{}", word);
+            assert_eq!(::std::str::from_utf8(&term).unwrap(), stem, "{}", word);
+        }
+    }
+}
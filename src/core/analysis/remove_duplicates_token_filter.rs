@@ -0,0 +1,96 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+use std::collections::HashSet;
+
+/// Removes the tokens whose term is the same as a previous token at the same
+/// position, e.g. a stem equal to an original term injected by another
+/// filter.
+#[derive(Debug)]
+pub struct RemoveDuplicatesTokenFilter {
+    input: Box<dyn TokenStream>,
+    // the terms of the current position
+    previous: HashSet<Vec<u8>>,
+}
+
+impl RemoveDuplicatesTokenFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        RemoveDuplicatesTokenFilter {
+            input,
+            previous: HashSet::new(),
+        }
+    }
+}
+
+impl TokenStream for RemoveDuplicatesTokenFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        while self.input.increment_token()? {
+            if self.input.position_attribute().get_position() != 0 {
+                self.previous.clear();
+            }
+            let term = self.input.term_bytes_attribute().get_bytes_ref();
+            if !self.previous.contains(term.bytes()) {
+                self.previous.insert(term.bytes().to_vec());
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.previous.clear();
+        Ok(())
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for RemoveDuplicatesTokenFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::{ASCIIFoldingFilter, PhoneticFilter, Soundex, WhitespaceTokenizer};
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_remove_duplicates() {
+        // "Café" is folded to "Cafe" with the same soundex code, stacked on
+        // the same position
+        let text = "Café Cafe";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let filter = ASCIIFoldingFilter::new(Box::new(tokenizer), true);
+        let filter = PhoneticFilter::new(Box::new(filter), Arc::new(Soundex), false);
+        let mut filter = RemoveDuplicatesTokenFilter::new(Box::new(filter));
+        assert_token_stream_contents(&mut filter, &["C100", "C100"], None, Some(&[1, 1]));
+    }
+}
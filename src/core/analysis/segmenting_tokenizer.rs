@@ -0,0 +1,264 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+    Tokenizer, TypeAttribute, DEFAULT_TOKEN_TYPE,
+};
+
+use error::{ErrorKind::IllegalState, Result};
+
+use std::fmt::{self, Debug};
+use std::io::Read;
+use std::sync::Arc;
+
+/// A token span computed by a `Segmenter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// The byte offset of the start of the token in the text.
+    pub start: usize,
+    /// The byte offset of the end of the token in the text, exclusive.
+    pub end: usize,
+    /// The type of the token, e.g. `DEFAULT_TOKEN_TYPE`.
+    pub token_type: &'static str,
+}
+
+impl Segment {
+    pub fn new(start: usize, end: usize) -> Self {
+        Segment {
+            start,
+            end,
+            token_type: DEFAULT_TOKEN_TYPE,
+        }
+    }
+}
+
+/// Splits a text into tokens, which is the extension point for external
+/// word segmentation, e.g. for the languages written without spaces.
+pub trait Segmenter: Debug + Send + Sync {
+    /// Returns the spans of the tokens of `text`, ordered by start offset.
+    /// The text between the spans, e.g. whitespace or punctuation, isn't
+    /// tokenized.
+    fn segment(&self, text: &str) -> Vec<Segment>;
+}
+
+/// A tokenizer emitting the tokens computed by a `Segmenter` on the whole
+/// input, each at the next position.
+pub struct SegmentingTokenizer {
+    segmenter: Arc<dyn Segmenter>,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    type_attr: TypeAttribute,
+    reader: Box<dyn Read>,
+    text: String,
+    segments: Vec<Segment>,
+    index: usize,
+    // byte and char offset of the start of the last token, to convert byte
+    // offsets into char offsets incrementally
+    last_offset: (usize, usize),
+    final_offset: usize,
+}
+
+impl SegmentingTokenizer {
+    pub fn new(reader: Box<dyn Read>, segmenter: Arc<dyn Segmenter>) -> Self {
+        SegmentingTokenizer {
+            segmenter,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            type_attr: TypeAttribute::new(),
+            reader,
+            text: String::new(),
+            segments: vec![],
+            index: 0,
+            last_offset: (0, 0),
+            final_offset: 0,
+        }
+    }
+
+    pub fn segmenter(&self) -> &dyn Segmenter {
+        self.segmenter.as_ref()
+    }
+
+    /// Converts a byte offset into a char offset, counting from the last
+    /// converted offset when possible.
+    fn char_offset(&self, byte_offset: usize) -> usize {
+        let (last_byte, last_char) = self.last_offset;
+        if byte_offset >= last_byte {
+            last_char + self.text[last_byte..byte_offset].chars().count()
+        } else {
+            self.text[..byte_offset].chars().count()
+        }
+    }
+}
+
+impl fmt::Debug for SegmentingTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SegmentingTokenizer")
+            .field("segmenter", &self.segmenter)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl TokenStream for SegmentingTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.index >= self.segments.len() {
+            return Ok(false);
+        }
+        self.clear_attributes();
+        let segment = self.segments[self.index];
+        self.index += 1;
+        self.term_attr
+            .append(&self.text[segment.start..segment.end]);
+        let start_offset = self.char_offset(segment.start);
+        self.last_offset = (segment.start, start_offset);
+        let end_offset = self.char_offset(segment.end);
+        self.offset_attr.set_offset(start_offset, end_offset)?;
+        self.type_attr.set_type(segment.token_type);
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        self.offset_attr
+            .set_offset(self.final_offset, self.final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.text.clear();
+        self.reader.read_to_string(&mut self.text)?;
+        self.segments = self.segmenter.segment(&self.text);
+        for segment in &self.segments {
+            if segment.start >= segment.end
+                || segment.end > self.text.len()
+                || !self.text.is_char_boundary(segment.start)
+                || !self.text.is_char_boundary(segment.end)
+            {
+                bail!(IllegalState(format!(
+                    "invalid segment {}..{} of a text of {} bytes",
+                    segment.start,
+                    segment.end,
+                    self.text.len()
+                )));
+            }
+        }
+        self.index = 0;
+        self.last_offset = (0, 0);
+        self.final_offset = self.text.chars().count();
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attr
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attr)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attr)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+impl Tokenizer for SegmentingTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.reader = reader;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use std::io::Cursor;
+
+    /// Segments the text into the runs of a same char.
+    #[derive(Debug)]
+    struct RunSegmenter;
+
+    impl Segmenter for RunSegmenter {
+        fn segment(&self, text: &str) -> Vec<Segment> {
+            let mut segments: Vec<Segment> = vec![];
+            let mut last = None;
+            for (i, c) in text.char_indices() {
+                if c.is_whitespace() {
+                    last = None;
+                    continue;
+                }
+                match segments.last_mut() {
+                    Some(segment) if last == Some(c) => segment.end = i + c.len_utf8(),
+                    _ => segments.push(Segment::new(i, i + c.len_utf8())),
+                }
+                last = Some(c);
+            }
+            segments
+        }
+    }
+
+    #[derive(Debug)]
+    struct InvalidSegmenter;
+
+    impl Segmenter for InvalidSegmenter {
+        fn segment(&self, _text: &str) -> Vec<Segment> {
+            vec![Segment::new(0, 1)]
+        }
+    }
+
+    fn tokenizer(text: &str, segmenter: Arc<dyn Segmenter>) -> SegmentingTokenizer {
+        let reader = Box::new(Cursor::new(text.as_bytes().to_vec()));
+        SegmentingTokenizer::new(reader, segmenter)
+    }
+
+    #[test]
+    fn test_segmenting_tokenizer() {
+        let mut tokenizer = tokenizer("ééab b", Arc::new(RunSegmenter));
+        assert_token_stream_contents(
+            &mut tokenizer,
+            &["éé", "a", "b", "b"],
+            Some(&[(0, 2), (2, 3), (3, 4), (5, 6)]),
+            None,
+        );
+        assert_eq!(tokenizer.offset_attribute().end_offset(), 6);
+
+        // segments must be on char boundaries
+        assert!(self::tokenizer("é", Arc::new(InvalidSegmenter))
+            .reset()
+            .is_err());
+    }
+}
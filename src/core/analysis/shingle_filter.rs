@@ -0,0 +1,418 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, PositionLengthAttribute,
+    TermToBytesRefAttribute, TokenFilter, TokenStream, TypeAttribute, DEFAULT_TOKEN_TYPE,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::collections::VecDeque;
+
+/// Default maximum number of tokens of a shingle.
+pub const DEFAULT_MAX_SHINGLE_SIZE: usize = 2;
+/// Default minimum number of tokens of a shingle.
+pub const DEFAULT_MIN_SHINGLE_SIZE: usize = 2;
+/// Default string used to join the tokens of a shingle.
+pub const DEFAULT_TOKEN_SEPARATOR: &str = " ";
+/// Default string inserted for positions without a token.
+pub const DEFAULT_FILLER_TOKEN: &str = "_";
+/// The type of the shingles emitted by a `ShingleFilter`.
+pub const SHINGLE_TYPE: &str = "shingle";
+
+#[derive(Debug)]
+struct InputToken {
+    term: Vec<u8>,
+    token_type: &'static str,
+    start_offset: usize,
+    end_offset: usize,
+    filler: bool,
+}
+
+/// Constructs shingles (token n-grams) from a token stream, e.g. the
+/// sentence "please divide this sentence into shingles" might be tokenized
+/// into the shingles "please divide", "divide this", "this sentence",
+/// "sentence into" and "into shingles".
+///
+/// Shingles of every size between the min and max shingle size are emitted
+/// at the position of their first token, after the unigram of that token if
+/// unigrams are output. Their position length is the number of tokens they
+/// join. Positions without a token, e.g. left by a `StopFilter`, are filled
+/// with the filler token; shingles made only of filler tokens are dropped.
+/// Stacked input tokens are treated as following the previous token.
+#[derive(Debug)]
+pub struct ShingleFilter {
+    input: Box<dyn TokenStream>,
+    min_shingle_size: usize,
+    max_shingle_size: usize,
+    output_unigrams: bool,
+    output_unigrams_if_no_shingles: bool,
+    token_separator: String,
+    filler_token: String,
+    // the tokens of the shingles starting at the current position
+    window: VecDeque<InputToken>,
+    // the size of the next n-gram starting at the current position
+    next_size: usize,
+    // positions since the last emitted token
+    pending_positions: u32,
+    input_finished: bool,
+    unigrams_only: bool,
+    emitted: bool,
+    term_attribute: CharTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    position_length_attribute: PositionLengthAttribute,
+    type_attribute: TypeAttribute,
+}
+
+impl ShingleFilter {
+    /// Creates a filter emitting shingles of `min_shingle_size` up to
+    /// `max_shingle_size` tokens, both of which must be at least 2.
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        min_shingle_size: usize,
+        max_shingle_size: usize,
+    ) -> Result<Self> {
+        if max_shingle_size < 2 {
+            bail!(IllegalArgument("Max shingle size must be >= 2".into()));
+        }
+        if min_shingle_size < 2 {
+            bail!(IllegalArgument("Min shingle size must be >= 2".into()));
+        }
+        if min_shingle_size > max_shingle_size {
+            bail!(IllegalArgument(
+                "Min shingle size must be <= max shingle size".into()
+            ));
+        }
+        Ok(ShingleFilter {
+            input,
+            min_shingle_size,
+            max_shingle_size,
+            output_unigrams: true,
+            output_unigrams_if_no_shingles: false,
+            token_separator: DEFAULT_TOKEN_SEPARATOR.to_string(),
+            filler_token: DEFAULT_FILLER_TOKEN.to_string(),
+            window: VecDeque::with_capacity(max_shingle_size),
+            next_size: 1,
+            pending_positions: 1,
+            input_finished: false,
+            unigrams_only: false,
+            emitted: false,
+            term_attribute: CharTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            position_length_attribute: PositionLengthAttribute::new(),
+            type_attribute: TypeAttribute::new(),
+        })
+    }
+
+    pub fn min_shingle_size(&self) -> usize {
+        self.min_shingle_size
+    }
+
+    pub fn max_shingle_size(&self) -> usize {
+        self.max_shingle_size
+    }
+
+    /// Set whether the input tokens are emitted along with the shingles,
+    /// true by default.
+    pub fn set_output_unigrams(&mut self, output_unigrams: bool) {
+        self.output_unigrams = output_unigrams;
+    }
+
+    /// Set whether the input tokens are emitted when there are no shingles
+    /// at all, i.e. the input has less tokens than the min shingle size.
+    /// Only applies if unigrams are not output, false by default.
+    pub fn set_output_unigrams_if_no_shingles(&mut self, output: bool) {
+        self.output_unigrams_if_no_shingles = output;
+    }
+
+    /// Set the string used to join the tokens of a shingle, a space by
+    /// default.
+    pub fn set_token_separator(&mut self, token_separator: &str) {
+        self.token_separator = token_separator.to_string();
+    }
+
+    /// Set the string inserted for positions without a token, `_` by
+    /// default.
+    pub fn set_filler_token(&mut self, filler_token: &str) {
+        self.filler_token = filler_token.to_string();
+    }
+
+    fn fill_window(&mut self) -> Result<()> {
+        while !self.input_finished && self.window.len() < self.max_shingle_size {
+            if !self.input.increment_token()? {
+                self.input_finished = true;
+                break;
+            }
+            let offset = self.input.offset_attribute();
+            let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+            let position = self.input.position_attribute().get_position();
+            for _ in 1..position {
+                self.window.push_back(InputToken {
+                    term: self.filler_token.as_bytes().to_vec(),
+                    token_type: DEFAULT_TOKEN_TYPE,
+                    start_offset,
+                    end_offset: start_offset,
+                    filler: true,
+                });
+            }
+            self.window.push_back(InputToken {
+                term: self
+                    .input
+                    .term_bytes_attribute()
+                    .get_bytes_ref()
+                    .bytes()
+                    .to_vec(),
+                token_type: self
+                    .input
+                    .type_attribute()
+                    .map_or(DEFAULT_TOKEN_TYPE, |attr| attr.get_type()),
+                start_offset,
+                end_offset,
+                filler: false,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the next n-gram to emit at the current position,
+    /// or `None` if all were emitted.
+    fn next_ngram_size(&mut self) -> Option<usize> {
+        while self.next_size <= self.max_shingle_size {
+            let size = self.next_size;
+            self.next_size += 1;
+            if size == 1 {
+                if (self.output_unigrams || self.unigrams_only) && !self.window[0].filler {
+                    return Some(1);
+                }
+            } else if size > self.window.len() || self.unigrams_only {
+                break;
+            } else if size >= self.min_shingle_size
+                && self.window.iter().take(size).any(|token| !token.filler)
+            {
+                return Some(size);
+            }
+        }
+        None
+    }
+
+    fn emit(&mut self, size: usize) -> Result<()> {
+        self.clear_attributes();
+        for (i, token) in self.window.iter().take(size).enumerate() {
+            if i > 0 {
+                self.term_attribute.append(&self.token_separator);
+            }
+            self.term_attribute
+                .term_buffer
+                .extend_from_slice(&token.term);
+        }
+        let start_offset = self.window[0].start_offset;
+        let end_offset = self.window[size - 1].end_offset;
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        self.position_attribute.set_position(self.pending_positions);
+        self.position_length_attribute
+            .set_position_length(size as u32);
+        if size == 1 {
+            self.type_attribute.set_type(self.window[0].token_type);
+        } else {
+            self.type_attribute.set_type(SHINGLE_TYPE);
+        }
+        self.pending_positions = 0;
+        self.emitted = true;
+        Ok(())
+    }
+}
+
+impl TokenStream for ShingleFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        loop {
+            if self.next_size == 1 {
+                self.fill_window()?;
+                if self.window.is_empty() {
+                    return Ok(false);
+                }
+                if !self.emitted
+                    && !self.output_unigrams
+                    && self.output_unigrams_if_no_shingles
+                    && self.input_finished
+                    && self.window.len() < self.min_shingle_size
+                {
+                    self.unigrams_only = true;
+                }
+            }
+            if let Some(size) = self.next_ngram_size() {
+                self.emit(size)?;
+                return Ok(true);
+            }
+            // all n-grams of the current position were emitted
+            self.window.pop_front();
+            self.next_size = 1;
+            self.pending_positions += 1;
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let offset = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        let position = self.input.position_attribute().get_position();
+        self.position_attribute.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.window.clear();
+        self.next_size = 1;
+        self.pending_positions = 1;
+        self.input_finished = false;
+        self.unigrams_only = false;
+        self.emitted = false;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        Some(&mut self.position_length_attribute)
+    }
+
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        Some(&self.position_length_attribute)
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attribute)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+impl TokenFilter for ShingleFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::{assert_token_graph_contents, collect_tokens};
+    use core::analysis::{StopFilter, WhitespaceTokenizer, ENGLISH_STOP_WORDS_SET};
+    use std::io::Cursor;
+
+    fn tokenizer(text: &str) -> Box<dyn TokenStream> {
+        Box::new(WhitespaceTokenizer::new(Box::new(Cursor::new(
+            text.as_bytes().to_vec(),
+        ))))
+    }
+
+    #[test]
+    fn test_shingles() {
+        let mut filter = ShingleFilter::new(tokenizer("please divide this"), 2, 3).unwrap();
+        assert_token_graph_contents(
+            &mut filter,
+            &[
+                "please",
+                "please divide",
+                "please divide this",
+                "divide",
+                "divide this",
+                "this",
+            ],
+            Some(&[(0, 6), (0, 13), (0, 18), (7, 13), (7, 18), (14, 18)]),
+            &[1, 0, 0, 1, 0, 1],
+            &[1, 2, 3, 1, 2, 1],
+        );
+
+        let mut filter = ShingleFilter::new(tokenizer("please divide this"), 3, 3).unwrap();
+        filter.set_output_unigrams(false);
+        filter.set_token_separator("_");
+        assert_token_graph_contents(
+            &mut filter,
+            &["please_divide_this"],
+            Some(&[(0, 18)]),
+            &[1],
+            &[3],
+        );
+
+        assert!(ShingleFilter::new(tokenizer("a"), 1, 2).is_err());
+        assert!(ShingleFilter::new(tokenizer("a"), 3, 2).is_err());
+    }
+
+    #[test]
+    fn test_filler_tokens() {
+        let stop_filter = StopFilter::new(
+            tokenizer("the quick in fox"),
+            ENGLISH_STOP_WORDS_SET.clone(),
+        );
+        let mut filter = ShingleFilter::new(Box::new(stop_filter), 2, 2).unwrap();
+        assert_token_graph_contents(
+            &mut filter,
+            &["_ quick", "quick", "quick _", "_ fox", "fox"],
+            Some(&[(4, 9), (4, 9), (4, 13), (13, 16), (13, 16)]),
+            &[1, 1, 0, 1, 1],
+            &[2, 1, 2, 2, 1],
+        );
+    }
+
+    #[test]
+    fn test_unigrams_if_no_shingles() {
+        let mut filter = ShingleFilter::new(tokenizer("please divide"), 3, 3).unwrap();
+        filter.set_output_unigrams(false);
+        assert!(collect_tokens(&mut filter).is_empty());
+
+        let mut filter = ShingleFilter::new(tokenizer("please divide"), 3, 3).unwrap();
+        filter.set_output_unigrams(false);
+        filter.set_output_unigrams_if_no_shingles(true);
+        assert_token_graph_contents(
+            &mut filter,
+            &["please", "divide"],
+            Some(&[(0, 6), (7, 13)]),
+            &[1, 1],
+            &[1, 1],
+        );
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    Analyzer, ComponentsCache, LetterTokenizer, LowerCaseFilter, ReuseStrategy, TokenStream,
+};
+
+use error::Result;
+
+use std::io::Read;
+
+/// Splits text at non-letters with `LetterTokenizer` and lower cases the
+/// tokens with `LowerCaseFilter`.
+#[derive(Debug)]
+pub struct SimpleAnalyzer {
+    cache: ComponentsCache,
+}
+
+impl Default for SimpleAnalyzer {
+    fn default() -> Self {
+        SimpleAnalyzer {
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        }
+    }
+}
+
+impl Analyzer for SimpleAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = LetterTokenizer::new(reader);
+        Ok(Box::new(LowerCaseFilter::new(Box::new(tokenizer))))
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+
+    fn normalize_stream(
+        &self,
+        _field_name: &str,
+        input: Box<dyn TokenStream>,
+    ) -> Box<dyn TokenStream> {
+        Box::new(LowerCaseFilter::new(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_simple_analyzer() {
+        let analyzer = SimpleAnalyzer::default();
+        assert_analyzes_to(
+            &analyzer,
+            "U.S.A. and Mr-Smith's 3 dogs",
+            &["u", "s", "a", "and", "mr", "smith", "s", "dogs"],
+            None,
+            None,
+        );
+        assert_eq!(analyzer.normalize("body", "Dogs").unwrap(), b"dogs");
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{PorterStemFilter, TokenFilter, TokenStream};
+
+use error::{ErrorKind, Result};
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// The languages with a Snowball stemmer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnowballLanguage {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl SnowballLanguage {
+    fn algorithm(self) -> Algorithm {
+        match self {
+            SnowballLanguage::Arabic => Algorithm::Arabic,
+            SnowballLanguage::Danish => Algorithm::Danish,
+            SnowballLanguage::Dutch => Algorithm::Dutch,
+            SnowballLanguage::English => Algorithm::English,
+            SnowballLanguage::Finnish => Algorithm::Finnish,
+            SnowballLanguage::French => Algorithm::French,
+            SnowballLanguage::German => Algorithm::German,
+            SnowballLanguage::Greek => Algorithm::Greek,
+            SnowballLanguage::Hungarian => Algorithm::Hungarian,
+            SnowballLanguage::Italian => Algorithm::Italian,
+            SnowballLanguage::Norwegian => Algorithm::Norwegian,
+            SnowballLanguage::Portuguese => Algorithm::Portuguese,
+            SnowballLanguage::Romanian => Algorithm::Romanian,
+            SnowballLanguage::Russian => Algorithm::Russian,
+            SnowballLanguage::Spanish => Algorithm::Spanish,
+            SnowballLanguage::Swedish => Algorithm::Swedish,
+            SnowballLanguage::Tamil => Algorithm::Tamil,
+            SnowballLanguage::Turkish => Algorithm::Turkish,
+        }
+    }
+
+    /// The Snowball name of the language, e.g. `English`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SnowballLanguage::Arabic => "Arabic",
+            SnowballLanguage::Danish => "Danish",
+            SnowballLanguage::Dutch => "Dutch",
+            SnowballLanguage::English => "English",
+            SnowballLanguage::Finnish => "Finnish",
+            SnowballLanguage::French => "French",
+            SnowballLanguage::German => "German",
+            SnowballLanguage::Greek => "Greek",
+            SnowballLanguage::Hungarian => "Hungarian",
+            SnowballLanguage::Italian => "Italian",
+            SnowballLanguage::Norwegian => "Norwegian",
+            SnowballLanguage::Portuguese => "Portuguese",
+            SnowballLanguage::Romanian => "Romanian",
+            SnowballLanguage::Russian => "Russian",
+            SnowballLanguage::Spanish => "Spanish",
+            SnowballLanguage::Swedish => "Swedish",
+            SnowballLanguage::Tamil => "Tamil",
+            SnowballLanguage::Turkish => "Turkish",
+        }
+    }
+}
+
+impl fmt::Display for SnowballLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SnowballLanguage {
+    type Err = ::error::Error;
+
+    /// Parses the Snowball name of a language, case insensitive.
+    fn from_str(s: &str) -> Result<Self> {
+        let language = match s.to_lowercase().as_str() {
+            "arabic" => SnowballLanguage::Arabic,
+            "danish" => SnowballLanguage::Danish,
+            "dutch" => SnowballLanguage::Dutch,
+            "english" => SnowballLanguage::English,
+            "finnish" => SnowballLanguage::Finnish,
+            "french" => SnowballLanguage::French,
+            "german" => SnowballLanguage::German,
+            "greek" => SnowballLanguage::Greek,
+            "hungarian" => SnowballLanguage::Hungarian,
+            "italian" => SnowballLanguage::Italian,
+            "norwegian" => SnowballLanguage::Norwegian,
+            "portuguese" => SnowballLanguage::Portuguese,
+            "romanian" => SnowballLanguage::Romanian,
+            "russian" => SnowballLanguage::Russian,
+            "spanish" => SnowballLanguage::Spanish,
+            "swedish" => SnowballLanguage::Swedish,
+            "tamil" => SnowballLanguage::Tamil,
+            "turkish" => SnowballLanguage::Turkish,
+            _ => bail!(ErrorKind::IllegalArgument(format!(
+                "no snowball stemmer for language '{}'",
+                s
+            ))),
+        };
+        Ok(language)
+    }
+}
+
+/// A filter that stems words using a Snowball-generated stemmer.
+///
+/// The input must already be lower cased, e.g. by a `LowerCaseFilter`.
+pub struct SnowballFilter {
+    input: Box<dyn TokenStream>,
+    language: SnowballLanguage,
+    stemmer: Stemmer,
+}
+
+impl SnowballFilter {
+    pub fn new(input: Box<dyn TokenStream>, language: SnowballLanguage) -> Self {
+        SnowballFilter {
+            input,
+            language,
+            stemmer: Stemmer::create(language.algorithm()),
+        }
+    }
+
+    pub fn language(&self) -> SnowballLanguage {
+        self.language
+    }
+}
+
+impl fmt::Debug for SnowballFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SnowballFilter")
+            .field("input", &self.input)
+            .field("language", &self.language)
+            .finish()
+    }
+}
+
+impl TokenStream for SnowballFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            let stem = match ::std::str::from_utf8(&term.term_buffer) {
+                Ok(text) => match self.stemmer.stem(text) {
+                    Cow::Owned(stem) => Some(stem),
+                    Cow::Borrowed(_) => None,
+                },
+                Err(_) => None,
+            };
+            if let Some(stem) = stem {
+                term.copy_buffer(stem.as_bytes());
+            }
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for SnowballFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+/// The stemmer an analyzer appends to its chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StemmerType {
+    /// The original Porter algorithm, see `PorterStemFilter`.
+    Porter,
+    /// A Snowball stemmer, see `SnowballFilter`.
+    Snowball(SnowballLanguage),
+}
+
+impl StemmerType {
+    /// Wraps `input` with the stem filter of this type.
+    pub fn create_filter(self, input: Box<dyn TokenStream>) -> Box<dyn TokenStream> {
+        match self {
+            StemmerType::Porter => Box::new(PorterStemFilter::new(input)),
+            StemmerType::Snowball(language) => Box::new(SnowballFilter::new(input, language)),
+        }
+    }
+}
+
+impl FromStr for StemmerType {
+    type Err = ::error::Error;
+
+    /// Parses `porter` or the name of a Snowball language.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("porter") {
+            Ok(StemmerType::Porter)
+        } else {
+            s.parse().map(StemmerType::Snowball)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn assert_stems_to(stemmer: StemmerType, text: &str, terms: &[&str]) {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut stream = stemmer.create_filter(Box::new(tokenizer));
+        assert_token_stream_contents(stream.as_mut(), terms, None, None);
+    }
+
+    #[test]
+    fn test_snowball_filter() {
+        assert_stems_to(
+            StemmerType::Snowball(SnowballLanguage::English),
+            "running consistency generously",
+            &["run", "consist", "generous"],
+        );
+        assert_stems_to(
+            "german".parse().unwrap(),
+            "häuser katzen",
+            &["haus", "katz"],
+        );
+        assert_stems_to(
+            "porter".parse().unwrap(),
+            "running generalizations",
+            &["run", "gener"],
+        );
+        assert!("klingon".parse::<StemmerType>().is_err());
+        assert_eq!(SnowballLanguage::French.to_string(), "French");
+    }
+}
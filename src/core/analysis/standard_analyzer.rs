@@ -0,0 +1,138 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    Analyzer, CharArraySet, ComponentsCache, LowerCaseFilter, ReuseStrategy, StandardTokenizer,
+    StemmerType, StopFilter, TokenStream, DEFAULT_MAX_TOKEN_LENGTH, ENGLISH_STOP_WORDS_SET,
+};
+
+use error::Result;
+
+use std::io::Read;
+use std::sync::Arc;
+
+/// Filters `StandardTokenizer` with `LowerCaseFilter` and `StopFilter`, using
+/// a configurable set of stop words, and optionally stems the remaining
+/// tokens.
+#[derive(Debug)]
+pub struct StandardAnalyzer {
+    stop_words: Arc<CharArraySet>,
+    max_token_length: usize,
+    stemmer: Option<StemmerType>,
+    cache: ComponentsCache,
+}
+
+impl Default for StandardAnalyzer {
+    fn default() -> Self {
+        StandardAnalyzer::new(ENGLISH_STOP_WORDS_SET.clone())
+    }
+}
+
+impl StandardAnalyzer {
+    /// Builds an analyzer with the given stop words.
+    pub fn new(stop_words: Arc<CharArraySet>) -> Self {
+        StandardAnalyzer {
+            stop_words,
+            max_token_length: DEFAULT_MAX_TOKEN_LENGTH,
+            stemmer: None,
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        }
+    }
+
+    pub fn stop_words(&self) -> &CharArraySet {
+        &self.stop_words
+    }
+
+    pub fn max_token_length(&self) -> usize {
+        self.max_token_length
+    }
+
+    /// Set the max allowed token length, tokens longer than this are split
+    /// by the `StandardTokenizer`.
+    pub fn set_max_token_length(&mut self, length: usize) {
+        self.max_token_length = length;
+        self.cache.clear();
+    }
+
+    pub fn stemmer(&self) -> Option<StemmerType> {
+        self.stemmer
+    }
+
+    /// Set the stemmer applied after stop words were removed, no stemming is
+    /// done by default.
+    pub fn set_stemmer(&mut self, stemmer: Option<StemmerType>) {
+        self.stemmer = stemmer;
+        self.cache.clear();
+    }
+}
+
+impl Analyzer for StandardAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let mut tokenizer = StandardTokenizer::new(reader);
+        tokenizer.set_max_token_length(self.max_token_length)?;
+        let stream = LowerCaseFilter::new(Box::new(tokenizer));
+        let stream: Box<dyn TokenStream> = Box::new(StopFilter::new(
+            Box::new(stream),
+            Arc::clone(&self.stop_words),
+        ));
+        Ok(match self.stemmer {
+            Some(stemmer) => stemmer.create_filter(stream),
+            None => stream,
+        })
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+
+    fn normalize_stream(
+        &self,
+        _field_name: &str,
+        input: Box<dyn TokenStream>,
+    ) -> Box<dyn TokenStream> {
+        Box::new(LowerCaseFilter::new(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_standard_analyzer() {
+        let mut analyzer = StandardAnalyzer::default();
+        assert_analyzes_to(
+            &analyzer,
+            "The Quick-Brown FOX, and the 2 dogs!",
+            &["quick", "brown", "fox", "2", "dogs"],
+            None,
+            None,
+        );
+
+        analyzer.set_stemmer(Some(StemmerType::Porter));
+        assert_analyzes_to(&analyzer, "Dogs", &["dog"], None, None);
+
+        assert_eq!(
+            analyzer.normalize("body", "Quick FOX").unwrap(),
+            b"quick fox"
+        );
+
+        analyzer.set_max_token_length(3);
+        assert_analyzes_to(&analyzer, "Lucene", &["luc", "ene"], None, None);
+    }
+}
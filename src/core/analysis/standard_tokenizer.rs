@@ -0,0 +1,469 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+    Tokenizer, TypeAttribute,
+};
+
+use error::{ErrorKind, Result};
+
+use std::fmt;
+use std::io::Read;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Token type of words which contain letters.
+pub const ALPHANUM_TYPE: &str = "<ALPHANUM>";
+/// Token type of numbers, with their decimal and grouping separators.
+pub const NUM_TYPE: &str = "<NUM>";
+/// Token type of runs of Thai, Lao, Myanmar and Khmer characters.
+pub const SOUTHEAST_ASIAN_TYPE: &str = "<SOUTHEAST_ASIAN>";
+/// Token type of single Han ideographs.
+pub const IDEOGRAPHIC_TYPE: &str = "<IDEOGRAPHIC>";
+/// Token type of single Hiragana characters.
+pub const HIRAGANA_TYPE: &str = "<HIRAGANA>";
+/// Token type of Katakana words.
+pub const KATAKANA_TYPE: &str = "<KATAKANA>";
+/// Token type of Hangul words.
+pub const HANGUL_TYPE: &str = "<HANGUL>";
+
+/// Default maximum allowed token length, in chars.
+pub const DEFAULT_MAX_TOKEN_LENGTH: usize = 255;
+
+/// Absolute maximum sized token.
+pub const MAX_TOKEN_LENGTH_LIMIT: usize = 1024 * 1024;
+
+#[derive(Debug)]
+struct Token {
+    // byte range of the term in the text
+    start: usize,
+    end: usize,
+    // char offsets of the term in the text
+    start_offset: usize,
+    end_offset: usize,
+    token_type: &'static str,
+}
+
+/// A grammar-based tokenizer implementing the word break rules from the
+/// Unicode Text Segmentation algorithm, as specified in
+/// [Unicode Standard Annex #29](http://unicode.org/reports/tr29/).
+///
+/// Segments made only of punctuation, symbols or whitespace are dropped, runs
+/// of South East Asian characters are kept together and every Han ideograph
+/// or Hiragana character is a token of its own, like Lucene's
+/// `StandardTokenizer` does. Tokens longer than `max_token_length` are split
+/// at `max_token_length` intervals.
+pub struct StandardTokenizer {
+    max_token_length: usize,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    type_attr: TypeAttribute,
+    reader: Box<dyn Read>,
+    text: String,
+    tokens: Vec<Token>,
+    current: usize,
+    final_offset: usize,
+}
+
+impl StandardTokenizer {
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        StandardTokenizer {
+            max_token_length: DEFAULT_MAX_TOKEN_LENGTH,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            type_attr: TypeAttribute::new(),
+            reader,
+            text: String::new(),
+            tokens: vec![],
+            current: 0,
+            final_offset: 0,
+        }
+    }
+
+    pub fn max_token_length(&self) -> usize {
+        self.max_token_length
+    }
+
+    /// Set the max allowed token length, longer tokens are split at this
+    /// length. Takes effect on the next `reset`.
+    pub fn set_max_token_length(&mut self, length: usize) -> Result<()> {
+        if length < 1 {
+            bail!(ErrorKind::IllegalArgument(
+                "max_token_length must be greater than zero".into()
+            ));
+        } else if length > MAX_TOKEN_LENGTH_LIMIT {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "max_token_length may not exceed {}",
+                MAX_TOKEN_LENGTH_LIMIT
+            )));
+        }
+        self.max_token_length = length;
+        Ok(())
+    }
+
+    fn segment(&mut self) {
+        self.tokens.clear();
+        let mut offset = 0;
+        for (start, word) in self.text.split_word_bound_indices() {
+            let length = word.chars().count();
+            if let Some(token_type) = token_type(word) {
+                let end = start + word.len();
+                let merged = if let Some(last) = self.tokens.last_mut() {
+                    if token_type == SOUTHEAST_ASIAN_TYPE
+                        && last.token_type == SOUTHEAST_ASIAN_TYPE
+                        && last.end == start
+                    {
+                        last.end = end;
+                        last.end_offset += length;
+                        true
+                    } else {
+                        false
+                    }
+                } else {
+                    false
+                };
+                if !merged {
+                    self.tokens.push(Token {
+                        start,
+                        end,
+                        start_offset: offset,
+                        end_offset: offset + length,
+                        token_type,
+                    });
+                }
+            }
+            offset += length;
+        }
+        self.final_offset = offset;
+
+        if self
+            .tokens
+            .iter()
+            .any(|t| t.end_offset - t.start_offset > self.max_token_length)
+        {
+            self.split_long_tokens();
+        }
+    }
+
+    fn split_long_tokens(&mut self) {
+        let mut tokens = Vec::with_capacity(self.tokens.len());
+        for token in self.tokens.drain(..) {
+            if token.end_offset - token.start_offset <= self.max_token_length {
+                tokens.push(token);
+                continue;
+            }
+            let mut start = token.start;
+            let mut start_offset = token.start_offset;
+            let mut length = 0;
+            for (idx, _) in self.text[token.start..token.end].char_indices() {
+                if length == self.max_token_length {
+                    let end = token.start + idx;
+                    tokens.push(Token {
+                        start,
+                        end,
+                        start_offset,
+                        end_offset: start_offset + length,
+                        token_type: token.token_type,
+                    });
+                    start = end;
+                    start_offset += length;
+                    length = 0;
+                }
+                length += 1;
+            }
+            tokens.push(Token {
+                start,
+                end: token.end,
+                start_offset,
+                end_offset: start_offset + length,
+                token_type: token.token_type,
+            });
+        }
+        self.tokens = tokens;
+    }
+}
+
+/// Returns the token type of a word segment, or None if the segment
+/// shouldn't be emitted as a token.
+fn token_type(word: &str) -> Option<&'static str> {
+    let mut has_letter = false;
+    let mut has_digit = false;
+    let mut all_hangul = true;
+    let mut all_katakana = true;
+    for c in word.chars() {
+        if is_ideographic(c) {
+            return Some(IDEOGRAPHIC_TYPE);
+        } else if is_hiragana(c) {
+            return Some(HIRAGANA_TYPE);
+        } else if is_southeast_asian(c) {
+            return Some(SOUTHEAST_ASIAN_TYPE);
+        }
+        if c.is_numeric() {
+            has_digit = true;
+        } else if c.is_alphabetic() {
+            has_letter = true;
+            all_hangul &= is_hangul(c);
+            all_katakana &= is_katakana(c);
+        }
+    }
+    if has_letter {
+        if all_hangul {
+            Some(HANGUL_TYPE)
+        } else if all_katakana {
+            Some(KATAKANA_TYPE)
+        } else {
+            Some(ALPHANUM_TYPE)
+        }
+    } else if has_digit {
+        Some(NUM_TYPE)
+    } else {
+        None
+    }
+}
+
+fn is_ideographic(c: char) -> bool {
+    match c as u32 {
+        0x2E80..=0x2FDF
+        | 0x3005
+        | 0x3007
+        | 0x3021..=0x3029
+        | 0x3038..=0x303B
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xF900..=0xFAFF
+        | 0x2_0000..=0x2_FA1F => true,
+        _ => false,
+    }
+}
+
+fn is_hiragana(c: char) -> bool {
+    match c as u32 {
+        0x3041..=0x309F | 0x1_B001 | 0x1_F200 => true,
+        _ => false,
+    }
+}
+
+fn is_katakana(c: char) -> bool {
+    match c as u32 {
+        0x30A0..=0x30FF | 0x31F0..=0x31FF | 0x32D0..=0x32FE | 0x3300..=0x3357 | 0xFF66..=0xFF9F => {
+            true
+        }
+        _ => false,
+    }
+}
+
+fn is_hangul(c: char) -> bool {
+    match c as u32 {
+        0x1100..=0x11FF
+        | 0x3131..=0x318E
+        | 0xA960..=0xA97F
+        | 0xAC00..=0xD7AF
+        | 0xD7B0..=0xD7FF
+        | 0xFFA0..=0xFFDC => true,
+        _ => false,
+    }
+}
+
+// characters with the Line_Break property value Complex_Context (SA)
+fn is_southeast_asian(c: char) -> bool {
+    match c as u32 {
+        0x0E01..=0x0E3A // Thai
+        | 0x0E40..=0x0E4E
+        | 0x0E81..=0x0EDF // Lao
+        | 0x1000..=0x103F // Myanmar
+        | 0x1050..=0x108F
+        | 0x109A..=0x109F
+        | 0x1780..=0x17D3 // Khmer
+        | 0x17D7
+        | 0x17DC..=0x17DD
+        | 0x1950..=0x19DF // Tai Le, New Tai Lue
+        | 0x1A20..=0x1AAD // Tai Tham
+        | 0xA9E0..=0xA9FE // Myanmar Extended-B
+        | 0xAA60..=0xAADF // Myanmar Extended-A, Tai Viet
+        => true,
+        _ => false,
+    }
+}
+
+impl fmt::Debug for StandardTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StandardTokenizer")
+            .field("max_token_length", &self.max_token_length)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .field("type_attr", &self.type_attr)
+            .field("tokens", &self.tokens)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl TokenStream for StandardTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.current >= self.tokens.len() {
+            return Ok(false);
+        }
+        self.clear_attributes();
+
+        let token = &self.tokens[self.current];
+        self.term_attr.append(&self.text[token.start..token.end]);
+        self.offset_attr
+            .set_offset(token.start_offset, token.end_offset)?;
+        self.type_attr.set_type(token.token_type);
+        self.current += 1;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        self.offset_attr
+            .set_offset(self.final_offset, self.final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.text.clear();
+        self.reader.read_to_string(&mut self.text)?;
+        self.segment();
+        self.current = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attr
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attr)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attr)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+impl Tokenizer for StandardTokenizer {
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()> {
+        self.reader = reader;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::{assert_token_stream_contents, collect_tokens};
+    use std::io::Cursor;
+
+    fn new_tokenizer(text: &str) -> StandardTokenizer {
+        StandardTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())))
+    }
+
+    #[test]
+    fn test_standard_tokenizer() {
+        let text = "The 2 quick-brown foxes' O'Reilly, 3.14 and 1,000 wi-fi!";
+        let mut tokenizer = new_tokenizer(text);
+        let tokens = assert_token_stream_contents(
+            &mut tokenizer,
+            &[
+                "The", "2", "quick", "brown", "foxes", "O'Reilly", "3.14", "and", "1,000", "wi",
+                "fi",
+            ],
+            None,
+            None,
+        );
+        let types: Vec<&str> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                ALPHANUM_TYPE,
+                NUM_TYPE,
+                ALPHANUM_TYPE,
+                ALPHANUM_TYPE,
+                ALPHANUM_TYPE,
+                ALPHANUM_TYPE,
+                NUM_TYPE,
+                ALPHANUM_TYPE,
+                NUM_TYPE,
+                ALPHANUM_TYPE,
+                ALPHANUM_TYPE,
+            ]
+        );
+        assert_eq!(tokenizer.offset_attribute().end_offset(), text.len());
+
+        // offsets are counted in chars
+        assert_token_stream_contents(
+            &mut new_tokenizer("élan vital"),
+            &["élan", "vital"],
+            Some(&[(0, 4), (5, 10)]),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_standard_tokenizer_scripts() {
+        let tokens = collect_tokens(&mut new_tokenizer("中文 ひらがな カタカナ 한국어 สวัสดีครับ"));
+        let types: Vec<&str> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                IDEOGRAPHIC_TYPE,
+                IDEOGRAPHIC_TYPE,
+                HIRAGANA_TYPE,
+                HIRAGANA_TYPE,
+                HIRAGANA_TYPE,
+                HIRAGANA_TYPE,
+                KATAKANA_TYPE,
+                HANGUL_TYPE,
+                SOUTHEAST_ASIAN_TYPE,
+            ]
+        );
+        assert_eq!(tokens[0].term, "中");
+        assert_eq!(tokens[6].term, "カタカナ");
+        assert_eq!(tokens[8].term, "สวัสดีครับ");
+    }
+
+    #[test]
+    fn test_max_token_length() {
+        let mut tokenizer = new_tokenizer("abcdefghij xy");
+        assert!(tokenizer.set_max_token_length(0).is_err());
+        assert!(tokenizer
+            .set_max_token_length(MAX_TOKEN_LENGTH_LIMIT + 1)
+            .is_err());
+        tokenizer.set_max_token_length(4).unwrap();
+
+        assert_token_stream_contents(&mut tokenizer, &["abcd", "efgh", "ij", "xy"], None, None);
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The stop words used by the english analyzers, the same set Lucene uses.
+pub const ENGLISH_STOP_WORDS: [&str; 33] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// A set of words looked up by the raw bytes of a term, so that checking a
+/// token against the set doesn't allocate.
+///
+/// If `ignore_case` is set the words are lower cased when added, and terms
+/// containing upper case letters are lower cased before the lookup.
+#[derive(Debug, Clone, Default)]
+pub struct CharArraySet {
+    words: HashSet<Vec<u8>>,
+    ignore_case: bool,
+}
+
+impl CharArraySet {
+    pub fn new(ignore_case: bool) -> CharArraySet {
+        CharArraySet {
+            words: HashSet::new(),
+            ignore_case,
+        }
+    }
+
+    pub fn from_words<T: AsRef<str>>(words: &[T], ignore_case: bool) -> CharArraySet {
+        let mut set = CharArraySet::new(ignore_case);
+        for word in words {
+            set.add(word.as_ref());
+        }
+        set
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// Adds a word, returns false if the set already contained it.
+    pub fn add(&mut self, word: &str) -> bool {
+        if self.ignore_case {
+            self.words.insert(word.to_lowercase().into_bytes())
+        } else {
+            self.words.insert(word.as_bytes().to_vec())
+        }
+    }
+
+    pub fn contains(&self, term: &[u8]) -> bool {
+        if self.ignore_case {
+            if term.is_ascii() {
+                if term.iter().any(u8::is_ascii_uppercase) {
+                    return self.words.contains(&term.to_ascii_lowercase());
+                }
+            } else if let Ok(text) = ::std::str::from_utf8(term) {
+                return self.words.contains(text.to_lowercase().as_bytes());
+            }
+        }
+        self.words.contains(term)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.words.iter().map(Vec::as_slice)
+    }
+}
+
+lazy_static! {
+    /// An unmodifiable set containing `ENGLISH_STOP_WORDS`.
+    pub static ref ENGLISH_STOP_WORDS_SET: Arc<CharArraySet> =
+        Arc::new(CharArraySet::from_words(&ENGLISH_STOP_WORDS, false));
+}
+
+/// Removes stop words from a token stream.
+///
+/// The position increments of the removed tokens are added to the next
+/// token kept, or to the final position increment reported by `end`, so
+/// that phrase queries don't match across a removed word.
+#[derive(Debug)]
+pub struct StopFilter {
+    input: Box<dyn TokenStream>,
+    stop_words: Arc<CharArraySet>,
+    skipped_positions: u32,
+}
+
+impl StopFilter {
+    pub fn new(input: Box<dyn TokenStream>, stop_words: Arc<CharArraySet>) -> Self {
+        StopFilter {
+            input,
+            stop_words,
+            skipped_positions: 0,
+        }
+    }
+
+    pub fn stop_words(&self) -> &CharArraySet {
+        &self.stop_words
+    }
+
+    fn accept(&self) -> bool {
+        let term = self.input.term_bytes_attribute().get_bytes_ref();
+        !self.stop_words.contains(term.bytes())
+    }
+}
+
+impl TokenStream for StopFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.skipped_positions = 0;
+        while self.input.increment_token()? {
+            if self.accept() {
+                if self.skipped_positions > 0 {
+                    let attr = self.input.position_attribute_mut();
+                    let position = attr.get_position() + self.skipped_positions;
+                    attr.set_position(position);
+                }
+                return Ok(true);
+            }
+            self.skipped_positions += self.input.position_attribute().get_position();
+        }
+        // reached EOS -- return false
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        let attr = self.input.position_attribute_mut();
+        let position = attr.get_position() + self.skipped_positions;
+        attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.skipped_positions = 0;
+        Ok(())
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for StopFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_char_array_set() {
+        let set = CharArraySet::from_words(&["The", "straße"], true);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(b"the"));
+        assert!(set.contains(b"THE"));
+        assert!(set.contains("Straße".as_bytes()));
+        assert!(!set.contains(b"then"));
+
+        let set = CharArraySet::from_words(&["The"], false);
+        assert!(set.contains(b"The"));
+        assert!(!set.contains(b"the"));
+    }
+
+    #[test]
+    fn test_stop_filter() {
+        let text = "the quick fox is in the house";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = StopFilter::new(Box::new(tokenizer), ENGLISH_STOP_WORDS_SET.clone());
+        assert_token_stream_contents(
+            &mut filter,
+            &["quick", "fox", "house"],
+            None,
+            Some(&[2, 1, 4]),
+        );
+
+        let text = "fox in the";
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = StopFilter::new(Box::new(tokenizer), ENGLISH_STOP_WORDS_SET.clone());
+        assert_token_stream_contents(&mut filter, &["fox"], None, Some(&[1]));
+        assert_eq!(filter.position_attribute().get_position(), 2);
+    }
+}
@@ -0,0 +1,544 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, PositionLengthAttribute, SynonymMap,
+    TermToBytesRefAttribute, TokenFilter, TokenStream, TypeAttribute, DEFAULT_TOKEN_TYPE,
+    WORD_SEPARATOR,
+};
+use core::util::fst::{ByteSequenceOutput, Output};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// The type of the tokens inserted by a `SynonymGraphFilter`.
+pub const TYPE_SYNONYM: &str = "SYNONYM";
+
+#[derive(Debug)]
+struct BufferedInputToken {
+    term: Vec<u8>,
+    token_type: &'static str,
+    position: u32,
+    start_offset: usize,
+    end_offset: usize,
+}
+
+#[derive(Debug)]
+struct BufferedOutputToken {
+    term: Vec<u8>,
+    token_type: &'static str,
+    start_node: isize,
+    end_node: isize,
+    start_offset: usize,
+    end_offset: usize,
+}
+
+/// Applies single- or multi-token synonyms from a `SynonymMap` to an
+/// incoming `TokenStream`, producing a fully correct graph output.
+///
+/// The longest matching input wins, e.g. with rules `a -> x` and
+/// `a b -> y`, `a b` is replaced by `y`. Synonyms are typed as
+/// `TYPE_SYNONYM` and span all the positions of the input they match, a
+/// multi-token synonym is emitted as a side path of the graph, with the
+/// `PositionLengthAttribute` of each token set to the number of positions
+/// it spans.
+///
+/// The filter can be applied at query time as is. An index doesn't record
+/// position lengths, so at index time it must be followed by a
+/// `FlattenGraphFilter`.
+///
+/// Matches don't span holes left by removed tokens nor start at stacked
+/// tokens, and the filter fails on input which is a graph itself.
+/// Payloads and term frequencies of the input tokens are dropped.
+#[derive(Debug)]
+pub struct SynonymGraphFilter {
+    input: Box<dyn TokenStream>,
+    synonyms: Arc<SynonymMap>,
+    ignore_case: bool,
+    // input tokens read ahead for the current match
+    lookahead: VecDeque<BufferedInputToken>,
+    output_buffer: VecDeque<BufferedOutputToken>,
+    // the node the next input token with an increment of 1 starts from
+    next_node_out: isize,
+    // the start node of the last consumed input token or match
+    last_start_node: isize,
+    // the start node of the last emitted token
+    last_node_out: isize,
+    finished: bool,
+    term_attribute: CharTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    position_length_attribute: PositionLengthAttribute,
+    type_attribute: TypeAttribute,
+}
+
+impl SynonymGraphFilter {
+    /// Creates a filter applying `synonyms`, if `ignore_case` is set the
+    /// tokens are lower cased before they are looked up, so the inputs of
+    /// the map must be lower cased too.
+    pub fn new(input: Box<dyn TokenStream>, synonyms: Arc<SynonymMap>, ignore_case: bool) -> Self {
+        SynonymGraphFilter {
+            input,
+            synonyms,
+            ignore_case,
+            lookahead: VecDeque::new(),
+            output_buffer: VecDeque::new(),
+            next_node_out: 0,
+            last_start_node: -1,
+            last_node_out: -1,
+            finished: false,
+            term_attribute: CharTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            position_length_attribute: PositionLengthAttribute::new(),
+            type_attribute: TypeAttribute::new(),
+        }
+    }
+
+    /// Reads the next input token into the lookahead buffer, returns false
+    /// at the end of the input.
+    fn read_input(&mut self) -> Result<bool> {
+        if self.finished {
+            return Ok(false);
+        }
+        if !self.input.increment_token()? {
+            self.finished = true;
+            return Ok(false);
+        }
+        let position_length = self.input.position_length();
+        if position_length != 1 {
+            bail!(IllegalArgument(format!(
+                "SynonymGraphFilter cannot consume an input graph, got a token with position \
+                 length {}",
+                position_length
+            )));
+        }
+        let offset = self.input.offset_attribute();
+        let token = BufferedInputToken {
+            term: self
+                .input
+                .term_bytes_attribute()
+                .get_bytes_ref()
+                .bytes()
+                .to_vec(),
+            token_type: self
+                .input
+                .type_attribute()
+                .map_or(DEFAULT_TOKEN_TYPE, |attr| attr.get_type()),
+            position: self.input.position_attribute().get_position(),
+            start_offset: offset.start_offset(),
+            end_offset: offset.end_offset(),
+        };
+        self.lookahead.push_back(token);
+        Ok(true)
+    }
+
+    /// Finds the longest match starting at the first lookahead token,
+    /// returns the output of the match and the number of tokens matched.
+    fn find_match(&mut self) -> Result<Option<(ByteSequenceOutput, usize)>> {
+        let synonyms = Arc::clone(&self.synonyms);
+        let fst = match synonyms.fst() {
+            Some(fst) if self.lookahead[0].position > 0 => fst,
+            _ => return Ok(None),
+        };
+        let mut reader = fst.bytes_reader();
+        let mut arc = fst.root_arc();
+        let mut pending = ByteSequenceOutput::empty();
+        let mut result = None;
+        let mut upto = 0;
+
+        'by_token: loop {
+            if upto == self.lookahead.len() && !self.read_input()? {
+                break;
+            }
+            let token = &self.lookahead[upto];
+            if upto > 0 && token.position != 1 {
+                break;
+            }
+            // run each byte of this token through the FST
+            for b in lookup_term(&token.term, self.ignore_case).iter() {
+                match fst.find_target_arc(i32::from(*b), &arc, &mut reader)? {
+                    Some(next) => arc = next,
+                    None => break 'by_token,
+                }
+                if let Some(ref output) = arc.output {
+                    pending = pending.cat(output);
+                }
+            }
+            upto += 1;
+
+            // the entire token matched, see if this is a final state
+            if arc.is_final() {
+                let mut output = pending.clone();
+                if let Some(ref final_output) = arc.next_final_output {
+                    output = output.cat(final_output);
+                }
+                result = Some((output, upto));
+            }
+
+            // see if the FST can continue matching with the next token
+            match fst.find_target_arc(i32::from(WORD_SEPARATOR), &arc, &mut reader)? {
+                Some(next) => arc = next,
+                None => break,
+            }
+            if let Some(ref output) = arc.output {
+                pending = pending.cat(output);
+            }
+        }
+        Ok(result)
+    }
+
+    fn buffer_input_token(&mut self) {
+        let token = self.lookahead.pop_front().unwrap();
+        let (start_node, end_node) = if token.position == 0 {
+            // stacked on the last token, or on the last token of its side
+            // path if it was a match
+            let start_node = self.last_start_node.max(self.last_node_out);
+            (start_node, self.next_node_out.max(start_node + 1))
+        } else {
+            let start_node = self.next_node_out + token.position as isize - 1;
+            (start_node, start_node + 1)
+        };
+        self.output_buffer.push_back(BufferedOutputToken {
+            term: token.term,
+            token_type: token.token_type,
+            start_node,
+            end_node,
+            start_offset: token.start_offset,
+            end_offset: token.end_offset,
+        });
+        self.last_start_node = start_node;
+        self.next_node_out = end_node;
+    }
+
+    fn buffer_output_tokens(
+        &mut self,
+        output: &ByteSequenceOutput,
+        match_length: usize,
+    ) -> Result<()> {
+        let (keep_orig, ords) = self.synonyms.decode_output(output.inner())?;
+        let paths: Vec<Vec<Vec<u8>>> = ords
+            .into_iter()
+            .map(|ord| {
+                self.synonyms
+                    .word(ord)
+                    .split(|b| *b == WORD_SEPARATOR)
+                    .map(<[u8]>::to_vec)
+                    .collect()
+            })
+            .collect();
+
+        // how many nodes along all paths, we need this to assign the node
+        // ID of the end node where all paths merge back
+        let mut total_path_nodes = if keep_orig { match_length - 1 } else { 0 };
+        for path in &paths {
+            total_path_nodes += path.len() - 1;
+        }
+        let start_node = self.next_node_out + self.lookahead[0].position as isize - 1;
+        let end_node = start_node + total_path_nodes as isize + 1;
+        let start_offset = self.lookahead[0].start_offset;
+        let end_offset = self.lookahead[match_length - 1].end_offset;
+        let synonym = |term: &[u8], start_node: isize, end_node: isize| BufferedOutputToken {
+            term: term.to_vec(),
+            token_type: TYPE_SYNONYM,
+            start_node,
+            end_node,
+            start_offset,
+            end_offset,
+        };
+
+        // first fan out all tokens departing the start node
+        let mut new_node_count = 0;
+        let mut path_end_nodes = Vec::with_capacity(paths.len());
+        for path in &paths {
+            let path_end_node = if path.len() == 1 {
+                // single token work: just directly to end node
+                end_node
+            } else {
+                let node = start_node + new_node_count as isize + 1;
+                new_node_count += path.len() - 1;
+                node
+            };
+            self.output_buffer
+                .push_back(synonym(&path[0], start_node, path_end_node));
+            path_end_nodes.push(path_end_node);
+        }
+        // the original tokens come last, else the offsets go backwards
+        let mut originals: Vec<_> = self.lookahead.drain(..match_length).collect();
+        let mut orig_end_node = end_node;
+        if keep_orig {
+            if match_length > 1 {
+                orig_end_node = start_node + new_node_count as isize + 1;
+            }
+            let token = &originals[0];
+            self.output_buffer.push_back(BufferedOutputToken {
+                term: token.term.clone(),
+                token_type: token.token_type,
+                start_node,
+                end_node: orig_end_node,
+                start_offset: token.start_offset,
+                end_offset: token.end_offset,
+            });
+        }
+
+        // then the side path of each multi-token synonym
+        for (path, path_end_node) in paths.iter().zip(path_end_nodes) {
+            let mut last_node = path_end_node;
+            for (i, word) in path.iter().enumerate().skip(1) {
+                let next_node = if i == path.len() - 1 {
+                    end_node
+                } else {
+                    last_node + 1
+                };
+                self.output_buffer
+                    .push_back(synonym(word, last_node, next_node));
+                last_node = next_node;
+            }
+        }
+        if keep_orig {
+            let mut last_node = orig_end_node;
+            for (i, token) in originals.drain(..).enumerate().skip(1) {
+                let next_node = if i == match_length - 1 {
+                    end_node
+                } else {
+                    last_node + 1
+                };
+                self.output_buffer.push_back(BufferedOutputToken {
+                    term: token.term,
+                    token_type: token.token_type,
+                    start_node: last_node,
+                    end_node: next_node,
+                    start_offset: token.start_offset,
+                    end_offset: token.end_offset,
+                });
+                last_node = next_node;
+            }
+        }
+
+        self.last_start_node = start_node;
+        self.next_node_out = end_node;
+        Ok(())
+    }
+
+    /// Consumes the next input token or match into the output buffer,
+    /// returns false at the end of the input.
+    fn parse(&mut self) -> Result<bool> {
+        if self.lookahead.is_empty() && !self.read_input()? {
+            return Ok(false);
+        }
+        match self.find_match()? {
+            Some((output, match_length)) => self.buffer_output_tokens(&output, match_length)?,
+            None => self.buffer_input_token(),
+        }
+        Ok(true)
+    }
+
+    fn release_buffered_token(&mut self) -> Result<()> {
+        let token = self.output_buffer.pop_front().unwrap();
+        debug_assert!(token.start_node >= self.last_node_out);
+        self.clear_attributes();
+        self.term_attribute.copy_buffer(&token.term);
+        self.type_attribute.set_type(token.token_type);
+        self.position_attribute
+            .set_position((token.start_node - self.last_node_out) as u32);
+        self.position_length_attribute
+            .set_position_length((token.end_node - token.start_node) as u32);
+        self.offset_attribute
+            .set_offset(token.start_offset, token.end_offset)?;
+        self.last_node_out = token.start_node;
+        Ok(())
+    }
+}
+
+fn lookup_term(term: &[u8], ignore_case: bool) -> Cow<[u8]> {
+    if ignore_case {
+        if term.is_ascii() {
+            if term.iter().any(u8::is_ascii_uppercase) {
+                return Cow::Owned(term.to_ascii_lowercase());
+            }
+        } else if let Ok(text) = ::std::str::from_utf8(term) {
+            return Cow::Owned(text.to_lowercase().into_bytes());
+        }
+    }
+    Cow::Borrowed(term)
+}
+
+impl TokenStream for SynonymGraphFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.output_buffer.is_empty() && !self.parse()? {
+            return Ok(false);
+        }
+        self.release_buffered_token()?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let offset = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        let position = self.input.position_attribute().get_position();
+        self.position_attribute.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.lookahead.clear();
+        self.output_buffer.clear();
+        self.next_node_out = 0;
+        self.last_start_node = -1;
+        self.last_node_out = -1;
+        self.finished = false;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        Some(&mut self.position_length_attribute)
+    }
+
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        Some(&self.position_length_attribute)
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attribute)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+impl TokenFilter for SynonymGraphFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::{assert_token_graph_contents, assert_token_stream_contents};
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn synonym_map(rules: &[(&str, &str, bool)]) -> Arc<SynonymMap> {
+        let mut builder = SynonymMap::builder(true);
+        for &(input, output, keep_orig) in rules {
+            let input: Vec<&str> = input.split(' ').collect();
+            let output: Vec<&str> = output.split(' ').collect();
+            builder
+                .add(
+                    &SynonymMap::join(&input),
+                    &SynonymMap::join(&output),
+                    keep_orig,
+                )
+                .unwrap();
+        }
+        Arc::new(builder.build().unwrap())
+    }
+
+    fn filter(text: &str, synonyms: Arc<SynonymMap>, ignore_case: bool) -> SynonymGraphFilter {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        SynonymGraphFilter::new(Box::new(tokenizer), synonyms, ignore_case)
+    }
+
+    #[test]
+    fn test_multi_word_synonym() {
+        let synonyms = synonym_map(&[("wtf", "what the fudge", true), ("wow", "amazing", false)]);
+        assert_eq!(synonyms.max_horizontal_context(), 3);
+        assert_token_graph_contents(
+            &mut filter("wtf happened wow", synonyms, false),
+            &["what", "wtf", "the", "fudge", "happened", "amazing"],
+            None,
+            &[1, 0, 1, 1, 1, 1],
+            &[1, 3, 1, 1, 1, 1],
+        );
+    }
+
+    #[test]
+    fn test_multi_word_input() {
+        let synonyms = synonym_map(&[
+            ("new", "neu", true),
+            ("new york", "ny", true),
+            ("new york", "big apple", true),
+        ]);
+        assert_token_graph_contents(
+            &mut filter("in New York city", synonyms.clone(), true),
+            &["in", "ny", "big", "New", "apple", "York", "city"],
+            None,
+            &[1, 1, 0, 0, 1, 1, 1],
+            &[1, 3, 1, 2, 2, 1, 1],
+        );
+        // the longest match wins, but a shorter one is used if the longer
+        // input doesn't match
+        assert_token_graph_contents(
+            &mut filter("new jersey", synonyms.clone(), false),
+            &["neu", "new", "jersey"],
+            None,
+            &[1, 0, 1],
+            &[1, 1, 1],
+        );
+        assert_token_graph_contents(
+            &mut filter("New York", synonyms, false),
+            &["New", "York"],
+            None,
+            &[1, 1],
+            &[1, 1],
+        );
+    }
+
+    #[test]
+    fn test_offsets_and_types() {
+        let synonyms = synonym_map(&[("a b", "c", false)]);
+        let mut filter = filter("x a b", synonyms, false);
+        let tokens =
+            assert_token_stream_contents(&mut filter, &["x", "c"], Some(&[(0, 1), (2, 5)]), None);
+        assert_eq!(tokens[0].token_type, DEFAULT_TOKEN_TYPE);
+        assert_eq!(tokens[1].token_type, TYPE_SYNONYM);
+        assert_eq!(filter.offset_attribute().end_offset(), 5);
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::store::io::{ByteArrayDataInput, DataInput, DataOutput};
+use core::util::fst::{ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, FST};
+use core::util::ints_ref::{to_ints_ref, IntsRefBuilder};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Separates the words of a multi-word input or output of a `SynonymMap`.
+pub const WORD_SEPARATOR: u8 = 0;
+
+/// A map of synonyms, from a (possibly multi-word) input to one or more
+/// (possibly multi-word) outputs.
+///
+/// The inputs are the keys of an FST whose output lists the ordinals of the
+/// output words, encoded as a vint header of `count << 1 | !keep_orig`
+/// followed by one vint per ordinal.
+pub struct SynonymMap {
+    fst: Option<FST<ByteSequenceOutputFactory>>,
+    words: Vec<Vec<u8>>,
+    max_horizontal_context: usize,
+}
+
+impl SynonymMap {
+    /// Creates a builder of a `SynonymMap`, if `dedup` is set duplicate
+    /// outputs of the same input are only added once.
+    pub fn builder(dedup: bool) -> SynonymMapBuilder {
+        SynonymMapBuilder::new(dedup)
+    }
+
+    /// The FST of the inputs, `None` if the map is empty.
+    pub fn fst(&self) -> Option<&FST<ByteSequenceOutputFactory>> {
+        self.fst.as_ref()
+    }
+
+    /// The output word of the given ordinal.
+    pub fn word(&self, ord: usize) -> &[u8] {
+        &self.words[ord]
+    }
+
+    /// The maximum number of words of an input or output.
+    pub fn max_horizontal_context(&self) -> usize {
+        self.max_horizontal_context
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fst.is_none()
+    }
+
+    /// Decodes an FST output into whether the original tokens are kept and
+    /// the ordinals of the output words.
+    pub fn decode_output(&self, output: &[u8]) -> Result<(bool, Vec<usize>)> {
+        let mut input = ByteArrayDataInput::new(output);
+        let code = input.read_vint()?;
+        let keep_orig = (code & 1) == 0;
+        let count = (code >> 1) as usize;
+        let mut ords = Vec::with_capacity(count);
+        for _ in 0..count {
+            ords.push(input.read_vint()? as usize);
+        }
+        Ok((keep_orig, ords))
+    }
+
+    /// Joins words into a multi-word input or output.
+    pub fn join<T: AsRef<str>>(words: &[T]) -> Vec<u8> {
+        let mut joined = Vec::new();
+        for word in words {
+            if !joined.is_empty() {
+                joined.push(WORD_SEPARATOR);
+            }
+            joined.extend_from_slice(word.as_ref().as_bytes());
+        }
+        joined
+    }
+}
+
+impl fmt::Debug for SynonymMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SynonymMap")
+            .field("words", &self.words.len())
+            .field("max_horizontal_context", &self.max_horizontal_context)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MapEntry {
+    include_orig: bool,
+    ords: Vec<usize>,
+}
+
+/// Builds a `SynonymMap`.
+///
+/// Inputs and outputs are words joined by `WORD_SEPARATOR`, as analyzed by
+/// the analyzer of the field the synonyms are applied to.
+#[derive(Debug)]
+pub struct SynonymMapBuilder {
+    entries: BTreeMap<Vec<u8>, MapEntry>,
+    words: Vec<Vec<u8>>,
+    word_ords: HashMap<Vec<u8>, usize>,
+    dedup: bool,
+    max_horizontal_context: usize,
+}
+
+impl SynonymMapBuilder {
+    pub fn new(dedup: bool) -> SynonymMapBuilder {
+        SynonymMapBuilder {
+            entries: BTreeMap::new(),
+            words: Vec::new(),
+            word_ords: HashMap::new(),
+            dedup,
+            max_horizontal_context: 0,
+        }
+    }
+
+    /// Adds a mapping from `input` to `output`, if `include_orig` is set the
+    /// original tokens are kept along with the synonym.
+    pub fn add(&mut self, input: &[u8], output: &[u8], include_orig: bool) -> Result<()> {
+        let num_input_words = Self::count_words(input, "input")?;
+        let num_output_words = Self::count_words(output, "output")?;
+
+        let ord = match self.word_ords.get(output) {
+            Some(ord) => *ord,
+            None => {
+                let ord = self.words.len();
+                self.words.push(output.to_vec());
+                self.word_ords.insert(output.to_vec(), ord);
+                ord
+            }
+        };
+        let entry = self.entries.entry(input.to_vec()).or_default();
+        entry.ords.push(ord);
+        entry.include_orig |= include_orig;
+
+        self.max_horizontal_context = max(self.max_horizontal_context, num_input_words);
+        self.max_horizontal_context = max(self.max_horizontal_context, num_output_words);
+        Ok(())
+    }
+
+    fn count_words(words: &[u8], name: &str) -> Result<usize> {
+        if words.is_empty() {
+            bail!(IllegalArgument(format!("{} must not be empty", name)));
+        }
+        let mut count = 0;
+        for word in words.split(|b| *b == WORD_SEPARATOR) {
+            if word.is_empty() {
+                bail!(IllegalArgument(format!(
+                    "{} has an empty word: {:?}",
+                    name,
+                    String::from_utf8_lossy(words)
+                )));
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Builds a `SynonymMap` of the added synonyms.
+    pub fn build(self) -> Result<SynonymMap> {
+        let fst = if self.entries.is_empty() {
+            None
+        } else {
+            let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory::new());
+            builder.init();
+            let mut scratch = IntsRefBuilder::new();
+            let mut dedup_set = HashSet::new();
+            for (input, entry) in &self.entries {
+                let mut ords = Vec::with_capacity(entry.ords.len());
+                for ord in &entry.ords {
+                    if !self.dedup || dedup_set.insert(*ord) {
+                        ords.push(*ord);
+                    }
+                }
+                dedup_set.clear();
+
+                let mut output = Vec::new();
+                let code = (ords.len() << 1) | if entry.include_orig { 0 } else { 1 };
+                output.write_vint(code as i32)?;
+                for ord in ords {
+                    output.write_vint(ord as i32)?;
+                }
+                builder.add(
+                    to_ints_ref(input, &mut scratch),
+                    ByteSequenceOutput::new(output),
+                )?;
+            }
+            builder.finish()?
+        };
+        Ok(SynonymMap {
+            fst,
+            words: self.words,
+            max_horizontal_context: self.max_horizontal_context,
+        })
+    }
+}
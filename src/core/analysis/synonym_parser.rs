@@ -0,0 +1,348 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Analyzer, SynonymMap, SynonymMapBuilder, WORD_SEPARATOR};
+use core::util::unescape;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::io::{BufRead, BufReader, Read};
+
+/// Analyzes `text` into the words of a `SynonymMap` input or output, joined
+/// by `WORD_SEPARATOR`.
+///
+/// The analyzer must produce at least one token, and must not produce
+/// empty tokens or tokens with a position increment other than 1.
+pub fn analyze_synonym(analyzer: &dyn Analyzer, text: &str) -> Result<Vec<u8>> {
+    let mut result = Vec::new();
+    {
+        let mut stream = analyzer.token_stream_from_str("", text)?;
+        stream.reset()?;
+        while stream.increment_token()? {
+            let term = stream.term_bytes_attribute().get_bytes_ref();
+            if term.is_empty() {
+                bail!(IllegalArgument(format!(
+                    "term: {} analyzed to a zero-length token",
+                    text
+                )));
+            }
+            let position = stream.position_attribute().get_position();
+            if position != 1 {
+                bail!(IllegalArgument(format!(
+                    "term: {} analyzed to a token ({}) with position increment != 1 (got: {})",
+                    text,
+                    String::from_utf8_lossy(term.bytes()),
+                    position
+                )));
+            }
+            if !result.is_empty() {
+                result.push(WORD_SEPARATOR);
+            }
+            result.extend_from_slice(term.bytes());
+        }
+        stream.end()?;
+    }
+    if result.is_empty() {
+        bail!(IllegalArgument(format!(
+            "term: {} was completely eliminated by analyzer",
+            text
+        )));
+    }
+    Ok(result)
+}
+
+/// Parses synonyms in the Solr format.
+///
+/// Blank lines and lines starting with `#` are ignored, other lines are
+/// either an explicit mapping like `i-pod, i pod => ipod`, which replaces
+/// any of the words on the left by all words on the right, or a list of
+/// equivalent words like `ipod, i-pod, i pod`. Equivalent words are all
+/// mapped to each other if `expand` is set, or else to the first word of
+/// the list. Separators can be escaped with a backslash.
+pub struct SolrSynonymParser<'a> {
+    builder: SynonymMapBuilder,
+    analyzer: &'a dyn Analyzer,
+    expand: bool,
+}
+
+impl<'a> SolrSynonymParser<'a> {
+    pub fn new(dedup: bool, expand: bool, analyzer: &'a dyn Analyzer) -> Self {
+        SolrSynonymParser {
+            builder: SynonymMapBuilder::new(dedup),
+            analyzer,
+            expand,
+        }
+    }
+
+    /// Parses the rules of `reader` into the map being built.
+    pub fn parse<R: Read>(&mut self, reader: R) -> Result<()> {
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Err(e) = self.add_rule(&line) {
+                bail!(IllegalArgument(format!(
+                    "invalid synonym rule at line {}: {}",
+                    i + 1,
+                    e
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn add_rule(&mut self, line: &str) -> Result<()> {
+        let sides = split(line, "=>");
+        if sides.len() > 1 {
+            // explicit mapping
+            if sides.len() != 2 {
+                bail!(IllegalArgument(
+                    "more than one explicit mapping specified on the same line".into()
+                ));
+            }
+            let inputs = self.analyze_all(&sides[0])?;
+            let outputs = self.analyze_all(&sides[1])?;
+            // these mappings are explicit and never preserve original
+            for input in &inputs {
+                for output in &outputs {
+                    self.builder.add(input, output, false)?;
+                }
+            }
+        } else {
+            let inputs = self.analyze_all(line)?;
+            if self.expand {
+                // all pairs
+                for (i, input) in inputs.iter().enumerate() {
+                    for (j, output) in inputs.iter().enumerate() {
+                        if i != j {
+                            self.builder.add(input, output, true)?;
+                        }
+                    }
+                }
+            } else {
+                // all inputs map to first one, the first one is mapped to
+                // itself so that all the words are typed as synonyms
+                for input in &inputs {
+                    self.builder.add(input, &inputs[0], false)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn analyze_all(&self, words: &str) -> Result<Vec<Vec<u8>>> {
+        split(words, ",")
+            .iter()
+            .map(|word| analyze_synonym(self.analyzer, unescape(word).trim()))
+            .collect()
+    }
+
+    /// Builds the `SynonymMap` of all the parsed rules.
+    pub fn build(self) -> Result<SynonymMap> {
+        self.builder.build()
+    }
+}
+
+/// Splits `s` by `separator`, skipping empty parts. Escaped characters are
+/// kept along with their backslash, and never act as separator.
+fn split(s: &str, separator: &str) -> Vec<String> {
+    let mut parts = Vec::with_capacity(2);
+    let mut part = String::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        if rest.starts_with(separator) {
+            if !part.is_empty() {
+                parts.push(part);
+                part = String::new();
+            }
+            rest = &rest[separator.len()..];
+            continue;
+        }
+        let mut chars = rest.chars();
+        let ch = chars.next().unwrap();
+        part.push(ch);
+        if ch == '\\' {
+            if let Some(escaped) = chars.next() {
+                part.push(escaped);
+            }
+        }
+        rest = chars.as_str();
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/// Parses synonyms in the prolog format of the WordNet database, e.g.
+/// `s(100002137,1,'abstraction',n,6,0).`, where the words of a synset are
+/// on consecutive lines sharing the same synset id.
+///
+/// The words of a synset are all mapped to each other if `expand` is set,
+/// or else to the first word of the synset.
+pub struct WordnetSynonymParser<'a> {
+    builder: SynonymMapBuilder,
+    analyzer: &'a dyn Analyzer,
+    expand: bool,
+}
+
+impl<'a> WordnetSynonymParser<'a> {
+    pub fn new(dedup: bool, expand: bool, analyzer: &'a dyn Analyzer) -> Self {
+        WordnetSynonymParser {
+            builder: SynonymMapBuilder::new(dedup),
+            analyzer,
+            expand,
+        }
+    }
+
+    /// Parses the synsets of `reader` into the map being built.
+    pub fn parse<R: Read>(&mut self, reader: R) -> Result<()> {
+        let mut last_synset_id = String::new();
+        let mut synset = Vec::new();
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match self.parse_synonym(&line) {
+                Ok((synset_id, word)) => {
+                    if synset_id != last_synset_id {
+                        self.add_synset(&synset)?;
+                        synset.clear();
+                        last_synset_id = synset_id.to_string();
+                    }
+                    synset.push(word);
+                }
+                Err(e) => {
+                    bail!(IllegalArgument(format!(
+                        "invalid synonym at line {}: {}",
+                        i + 1,
+                        e
+                    )));
+                }
+            }
+        }
+        // final synset in the file
+        self.add_synset(&synset)
+    }
+
+    fn parse_synonym<'b>(&self, line: &'b str) -> Result<(&'b str, Vec<u8>)> {
+        let synset_id = if line.starts_with("s(") {
+            line[2..].split(',').next().unwrap()
+        } else {
+            bail!(IllegalArgument("expected a line starting with 's('".into()));
+        };
+        match (line.find('\''), line.rfind('\'')) {
+            (Some(start), Some(end)) if start < end => {
+                let text = line[start + 1..end].replace("''", "'");
+                Ok((synset_id, analyze_synonym(self.analyzer, &text)?))
+            }
+            _ => bail!(IllegalArgument("expected a quoted word".into())),
+        }
+    }
+
+    fn add_synset(&mut self, synset: &[Vec<u8>]) -> Result<()> {
+        if synset.len() <= 1 {
+            // nothing to do
+            return Ok(());
+        }
+        if self.expand {
+            for (i, input) in synset.iter().enumerate() {
+                for (j, output) in synset.iter().enumerate() {
+                    if i != j {
+                        self.builder.add(input, output, true)?;
+                    }
+                }
+            }
+        } else {
+            for input in synset {
+                self.builder.add(input, &synset[0], false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the `SynonymMap` of all the parsed synsets.
+    pub fn build(self) -> Result<SynonymMap> {
+        self.builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::{CharArraySet, StandardAnalyzer, SynonymGraphFilter, WhitespaceTokenizer};
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    fn analyzer() -> StandardAnalyzer {
+        StandardAnalyzer::new(Arc::new(CharArraySet::new(true)))
+    }
+
+    fn assert_synonyms(text: &str, synonyms: &Arc<SynonymMap>, terms: &[&str]) {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = SynonymGraphFilter::new(Box::new(tokenizer), Arc::clone(synonyms), false);
+        assert_token_stream_contents(&mut filter, terms, None, None);
+    }
+
+    #[test]
+    fn test_solr_synonyms() {
+        let rules = "# blank lines and comments are ignored\n\ni-pod, i pod => ipod\nFoo, bar, \
+                     baz\na\\,b, c\\=>d => e\n";
+        let analyzer = analyzer();
+        let mut parser = SolrSynonymParser::new(true, true, &analyzer);
+        parser.parse(rules.as_bytes()).unwrap();
+        let synonyms = Arc::new(parser.build().unwrap());
+
+        assert_synonyms("i pod", &synonyms, &["ipod"]);
+        assert_synonyms("bar", &synonyms, &["foo", "baz", "bar"]);
+        assert_synonyms("a b", &synonyms, &["e"]);
+        assert_synonyms("c d", &synonyms, &["e"]);
+
+        let mut parser = SolrSynonymParser::new(true, false, &analyzer);
+        parser.parse(&b"foo, bar, baz"[..]).unwrap();
+        let synonyms = Arc::new(parser.build().unwrap());
+        assert_synonyms("baz foo", &synonyms, &["foo", "foo"]);
+
+        let mut parser = SolrSynonymParser::new(true, false, &analyzer);
+        assert!(parser.parse(&b"a => b => c"[..]).is_err());
+        assert!(parser.parse(&b"a, the-, b"[..]).is_ok());
+        let stop_analyzer = StandardAnalyzer::default();
+        let mut parser = SolrSynonymParser::new(true, false, &stop_analyzer);
+        assert!(parser.parse(&b"a, the => b"[..]).is_err());
+    }
+
+    #[test]
+    fn test_wordnet_synonyms() {
+        let synsets = "s(100000001,1,'woods',n,1,0).\ns(100000001,2,'wood',n,1,0).\ns(100000001,3,\
+                       'forest',n,1,0).\ns(100000002,1,'wolfish',n,1,0).\ns(100000003,1,'king''s \
+                       evil',n,1,1).\ns(100000003,2,'scrofula',n,1,1).\n";
+        let analyzer = analyzer();
+        let mut parser = WordnetSynonymParser::new(true, false, &analyzer);
+        parser.parse(synsets.as_bytes()).unwrap();
+        let synonyms = Arc::new(parser.build().unwrap());
+        assert_synonyms("forest", &synonyms, &["woods"]);
+        assert_synonyms("wolfish", &synonyms, &["wolfish"]);
+        assert_synonyms("scrofula", &synonyms, &["king's", "evil"]);
+
+        let mut parser = WordnetSynonymParser::new(true, true, &analyzer);
+        parser.parse(synsets.as_bytes()).unwrap();
+        let synonyms = Arc::new(parser.build().unwrap());
+        assert_synonyms("wood", &synonyms, &["woods", "forest", "wood"]);
+
+        let mut parser = WordnetSynonymParser::new(true, true, &analyzer);
+        assert!(parser.parse(&b"s(100000001,1,woods,n,1,0)."[..]).is_err());
+    }
+}
@@ -20,6 +20,7 @@ use std::fmt;
 ///
 /// If this attribute is present in your analysis chain for a given field,
 /// that field must be indexed with IndexOptions#DocsAndFreqs
+#[derive(Debug)]
 pub struct TermFreqAttribute {
     term_freq: u32,
 }
@@ -175,6 +176,84 @@ impl PositionAttribute {
     }
 }
 
+/// The number of positions a token spans, 1 for tokens which don't overlap
+/// with a multi-position token of a token graph.
+#[derive(Debug, Clone, Copy)]
+pub struct PositionLengthAttribute {
+    position_length: u32,
+}
+
+impl Default for PositionLengthAttribute {
+    fn default() -> Self {
+        PositionLengthAttribute::new()
+    }
+}
+
+impl PositionLengthAttribute {
+    #[inline]
+    pub fn new() -> PositionLengthAttribute {
+        PositionLengthAttribute { position_length: 1 }
+    }
+
+    pub fn set_position_length(&mut self, position_length: u32) {
+        debug_assert!(position_length >= 1);
+        self.position_length = position_length;
+    }
+
+    pub fn get_position_length(self) -> u32 {
+        self.position_length
+    }
+
+    pub fn clear(&mut self) {
+        self.position_length = 1
+    }
+
+    pub fn end(&mut self) {
+        self.clear();
+    }
+}
+
+/// The default lexical type of a token.
+pub const DEFAULT_TOKEN_TYPE: &str = "word";
+
+/// The lexical type of a token, e.g. `<ALPHANUM>` or `<NUM>` for the
+/// `StandardTokenizer`.
+#[derive(Debug, Clone, Copy)]
+pub struct TypeAttribute {
+    token_type: &'static str,
+}
+
+impl Default for TypeAttribute {
+    fn default() -> Self {
+        TypeAttribute::new()
+    }
+}
+
+impl TypeAttribute {
+    #[inline]
+    pub fn new() -> TypeAttribute {
+        TypeAttribute {
+            token_type: DEFAULT_TOKEN_TYPE,
+        }
+    }
+
+    pub fn set_type(&mut self, token_type: &'static str) {
+        self.token_type = token_type;
+    }
+
+    pub fn get_type(&self) -> &'static str {
+        self.token_type
+    }
+
+    pub fn clear(&mut self) {
+        self.token_type = DEFAULT_TOKEN_TYPE;
+    }
+
+    pub fn end(&mut self) {
+        self.clear();
+    }
+}
+
 /// The payload of a Token.
 ///
 /// The payload is stored in the index at each position, and can
@@ -224,6 +303,17 @@ pub trait TermToBytesRefAttribute {
     fn clear(&mut self);
 
     fn end(&mut self);
+
+    /// Access of the term as text, would return None if the term is binary.
+    fn char_term(&self) -> Option<&CharTermAttribute> {
+        None
+    }
+
+    /// Mutable access of the term as text, so that token filters can rewrite
+    /// it in place, would return None if the term is binary.
+    fn char_term_mut(&mut self) -> Option<&mut CharTermAttribute> {
+        None
+    }
 }
 
 const MIN_BUFFER_SIZE: usize = 10;
@@ -298,6 +388,14 @@ impl TermToBytesRefAttribute for CharTermAttribute {
     fn end(&mut self) {
         self.clear();
     }
+
+    fn char_term(&self) -> Option<&CharTermAttribute> {
+        Some(self)
+    }
+
+    fn char_term_mut(&mut self) -> Option<&mut CharTermAttribute> {
+        Some(self)
+    }
 }
 
 /// This attribute can be used if you have the raw term bytes to be indexed.
@@ -15,13 +15,14 @@ use std::fmt::Debug;
 
 use core::analysis::{
     BytesTermAttribute, CharTermAttribute, OffsetAttribute, PayloadAttribute, PositionAttribute,
-    TermToBytesRefAttribute,
+    PositionLengthAttribute, TermFreqAttribute, TermToBytesRefAttribute, TypeAttribute,
 };
 
 use core::util::BytesRef;
 use error::Result;
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::io::Read;
 
 /// A `TokenStream` enumerates the sequence of tokens, either from
 /// `Field`s of a `Document` or from query text.
@@ -113,6 +114,15 @@ pub trait TokenStream: Debug {
         if let Some(attr) = self.payload_attribute_mut() {
             attr.clear();
         }
+        if let Some(attr) = self.term_freq_attribute_mut() {
+            attr.clear();
+        }
+        if let Some(attr) = self.position_length_attribute_mut() {
+            attr.clear();
+        }
+        if let Some(attr) = self.type_attribute_mut() {
+            attr.clear();
+        }
         self.term_bytes_attribute_mut().clear();
     }
 
@@ -124,6 +134,15 @@ pub trait TokenStream: Debug {
         if let Some(attr) = self.payload_attribute_mut() {
             attr.end();
         }
+        if let Some(attr) = self.term_freq_attribute_mut() {
+            attr.end();
+        }
+        if let Some(attr) = self.position_length_attribute_mut() {
+            attr.end();
+        }
+        if let Some(attr) = self.type_attribute_mut() {
+            attr.end();
+        }
         self.term_bytes_attribute_mut().end();
     }
 
@@ -136,6 +155,40 @@ pub trait TokenStream: Debug {
     /// mutable access of the `PositionIncrementAttribute`
     fn position_attribute_mut(&mut self) -> &mut PositionAttribute;
 
+    /// access of the `PositionIncrementAttribute`
+    fn position_attribute(&self) -> &PositionAttribute;
+
+    /// mutable access of the `PositionLengthAttribute`, would return None if the
+    /// stream doesn't produce a token graph
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        None
+    }
+
+    /// access of the `PositionLengthAttribute`, would return None if the
+    /// stream doesn't produce a token graph
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        None
+    }
+
+    /// The number of positions spanned by the current token, 1 unless the
+    /// stream provides a `PositionLengthAttribute`.
+    fn position_length(&self) -> u32 {
+        self.position_length_attribute()
+            .map_or(1, |attr| attr.get_position_length())
+    }
+
+    /// mutable access of the `TypeAttribute`, would return None if the stream
+    /// doesn't type its tokens
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        None
+    }
+
+    /// access of the `TypeAttribute`, would return None if the stream
+    /// doesn't type its tokens
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        None
+    }
+
     /// mutable access of the `PayloadAttribute`, wound return None if not enabled
     fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
         None
@@ -146,6 +199,25 @@ pub trait TokenStream: Debug {
         None
     }
 
+    /// mutable access of the `TermFreqAttribute`, would return None if the
+    /// stream doesn't provide custom term frequencies
+    fn term_freq_attribute_mut(&mut self) -> Option<&mut TermFreqAttribute> {
+        None
+    }
+
+    /// access of the `TermFreqAttribute`, would return None if the stream
+    /// doesn't provide custom term frequencies
+    fn term_freq_attribute(&self) -> Option<&TermFreqAttribute> {
+        None
+    }
+
+    /// The frequency of the current token, 1 unless the stream provides
+    /// a custom `TermFreqAttribute`.
+    fn term_freq(&self) -> u32 {
+        self.term_freq_attribute()
+            .map_or(1, |attr| attr.term_freq())
+    }
+
     /// mutable access of the `TermToBytesRefAttribute`
     fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute;
 
@@ -153,6 +225,26 @@ pub trait TokenStream: Debug {
     fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute;
 }
 
+/// A `TokenStream` whose input is a character stream, e.g. a `Read` over the
+/// text of a field.
+pub trait Tokenizer: TokenStream {
+    /// Expert: sets a new input for this tokenizer, the tokenizer starts
+    /// consuming it after the next call of `reset`.
+    fn set_reader(&mut self, reader: Box<dyn Read>) -> Result<()>;
+}
+
+/// A `TokenStream` whose input is another `TokenStream`.
+///
+/// A filter usually shares the attributes of its input and only mutates them
+/// in `increment_token`, see `delegate_token_attributes!`.
+pub trait TokenFilter: TokenStream {
+    /// The stream this filter consumes.
+    fn input(&self) -> &dyn TokenStream;
+
+    /// Mutable access of the stream this filter consumes.
+    fn input_mut(&mut self) -> &mut dyn TokenStream;
+}
+
 #[derive(Debug)]
 pub struct StringTokenStream {
     term_attribute: CharTermAttribute,
@@ -212,6 +304,10 @@ impl TokenStream for StringTokenStream {
         &mut self.position_attribute
     }
 
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
     fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
         &mut self.term_attribute
     }
@@ -278,6 +374,10 @@ impl TokenStream for BinaryTokenStream {
         &mut self.position_attribute
     }
 
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
     fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
         &mut self.term_attribute
     }
@@ -393,6 +493,10 @@ impl TokenStream for WordTokenStream {
         &mut self.position_attribute
     }
 
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
     fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
         &mut self.term_attribute
     }
@@ -0,0 +1,285 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::util::automaton::{Automaton, Operations};
+
+use error::Result;
+
+/// The label separating the positions in the automata of
+/// `TokenStreamToAutomaton`.
+pub const POS_SEP: u32 = 0x1f;
+/// The label of the holes left by removed tokens, e.g. stop words, in the
+/// automata of `TokenStreamToAutomaton`.
+pub const HOLE: u32 = 0x1e;
+
+/// Consumes a `TokenStream` and creates an `Automaton` where the transition
+/// labels are the UTF-8 bytes (or the code points with `unicode_arcs`) of the
+/// terms, and the positions are separated by `POS_SEP`.
+///
+/// The token graph given by the position increments and lengths is kept, so
+/// e.g. the output of a `SynonymGraphFilter` accepts every path of the graph.
+/// A position no token arrives at, e.g. the one of a removed stop word, is
+/// represented by a `HOLE` label. The returned automaton is not
+/// deterministic.
+#[derive(Debug, Clone)]
+pub struct TokenStreamToAutomaton {
+    preserve_position_increments: bool,
+    unicode_arcs: bool,
+    final_offset_gap_as_hole: bool,
+}
+
+impl Default for TokenStreamToAutomaton {
+    fn default() -> Self {
+        TokenStreamToAutomaton::new()
+    }
+}
+
+struct Token {
+    start: usize,
+    end: usize,
+    labels: Vec<u32>,
+}
+
+impl TokenStreamToAutomaton {
+    pub fn new() -> Self {
+        TokenStreamToAutomaton {
+            preserve_position_increments: true,
+            unicode_arcs: false,
+            final_offset_gap_as_hole: false,
+        }
+    }
+
+    /// Set whether position increments greater than one create holes, true
+    /// by default. Otherwise all tokens are adjacent.
+    pub fn set_preserve_position_increments(&mut self, preserve_position_increments: bool) {
+        self.preserve_position_increments = preserve_position_increments;
+    }
+
+    /// Set whether the labels are code points instead of UTF-8 bytes, false
+    /// by default.
+    pub fn set_unicode_arcs(&mut self, unicode_arcs: bool) {
+        self.unicode_arcs = unicode_arcs;
+    }
+
+    /// Set whether text after the last token, e.g. a trailing stop word whose
+    /// position isn't reported by the stream, is represented by a trailing
+    /// hole, false by default.
+    pub fn set_final_offset_gap_as_hole(&mut self, final_offset_gap_as_hole: bool) {
+        self.final_offset_gap_as_hole = final_offset_gap_as_hole;
+    }
+
+    /// Pulls the token graph of `stream` and creates the corresponding
+    /// automaton. The stream is reset and ended by this method.
+    pub fn to_automaton(&self, stream: &mut dyn TokenStream) -> Result<Automaton> {
+        stream.reset()?;
+        let mut tokens = vec![];
+        let mut pos: Option<usize> = None;
+        let mut max_offset = 0;
+        while stream.increment_token()? {
+            let mut position_increment = stream.position_attribute().get_position() as usize;
+            if !self.preserve_position_increments && position_increment > 1 {
+                position_increment = 1;
+            }
+            let start = match pos {
+                Some(pos) => pos + position_increment,
+                // the first token is at position 0 unless there are holes
+                None => position_increment.max(1) - 1,
+            };
+            pos = Some(start);
+            let position_length = stream
+                .position_length_attribute()
+                .map_or(1, |attr| attr.get_position_length() as usize)
+                .max(1);
+            let term = stream.term_bytes_attribute().get_bytes_ref();
+            let labels = if self.unicode_arcs {
+                String::from_utf8_lossy(term.bytes())
+                    .chars()
+                    .map(|c| c as u32)
+                    .collect()
+            } else {
+                term.bytes().iter().map(|&b| u32::from(b)).collect()
+            };
+            tokens.push(Token {
+                start,
+                end: start + position_length,
+                labels,
+            });
+            max_offset = max_offset.max(stream.offset_attribute().end_offset());
+        }
+        stream.end()?;
+
+        let mut end_position_increment = stream.position_attribute().get_position();
+        if end_position_increment == 0
+            && self.final_offset_gap_as_hole
+            && stream.offset_attribute().end_offset() > max_offset
+        {
+            end_position_increment = 1;
+        } else if !self.preserve_position_increments {
+            end_position_increment = 0;
+        }
+
+        let mut automaton = Automaton::new();
+        let start_state = automaton.create_state();
+        let max_end = tokens.iter().map(|t| t.end).max().unwrap_or(0);
+        // each position has a state the tokens ending there arrive at, and a
+        // state the tokens starting there leave from, separated by POS_SEP
+        let mut arriving = vec![start_state; max_end + 1];
+        let mut leaving = vec![start_state; max_end + 1];
+        for p in 1..=max_end {
+            arriving[p] = automaton.create_state();
+            leaving[p] = automaton.create_state();
+            automaton.add_label_transition(arriving[p], leaving[p], POS_SEP);
+        }
+        let mut arrived = vec![false; max_end + 1];
+        for token in &tokens {
+            // empty terms can't be represented and are skipped
+            if token.labels.is_empty() {
+                continue;
+            }
+            let mut state = leaving[token.start];
+            for (i, &label) in token.labels.iter().enumerate() {
+                let next = if i + 1 == token.labels.len() {
+                    arriving[token.end]
+                } else {
+                    automaton.create_state()
+                };
+                automaton.add_label_transition(state, next, label);
+                state = next;
+            }
+            arrived[token.end] = true;
+        }
+        // fill the holes before the positions tokens leave from
+        for token in &tokens {
+            let mut p = token.start;
+            while p > 0 && !arrived[p] {
+                automaton.add_label_transition(leaving[p - 1], arriving[p], HOLE);
+                arrived[p] = true;
+                p -= 1;
+            }
+        }
+
+        let last_start = pos.unwrap_or(0);
+        let end_state = if end_position_increment > 0 {
+            // trailing holes after the last token
+            let end_state = automaton.create_state();
+            let mut state = end_state;
+            for i in 0..end_position_increment {
+                let next = automaton.create_state();
+                automaton.add_label_transition(state, next, HOLE);
+                state = next;
+                if i + 1 < end_position_increment {
+                    let next = automaton.create_state();
+                    automaton.add_label_transition(state, next, POS_SEP);
+                    state = next;
+                }
+            }
+            automaton.set_accept(state, true);
+            Some(end_state)
+        } else {
+            None
+        };
+        for p in last_start + 1..=max_end {
+            if arrived[p] {
+                match end_state {
+                    Some(end_state) => {
+                        automaton.add_label_transition(arriving[p], end_state, POS_SEP)
+                    }
+                    None => automaton.set_accept(arriving[p], true),
+                }
+            }
+        }
+        automaton.finish();
+        Ok(Operations::remove_dead_states(&automaton))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::{
+        CharArraySet, SolrSynonymParser, StopFilter, SynonymGraphFilter, WhitespaceAnalyzer,
+        WhitespaceTokenizer,
+    };
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    fn tokenizer(text: &str) -> WhitespaceTokenizer {
+        WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())))
+    }
+
+    fn labels(parts: &[&str]) -> Vec<u32> {
+        let mut labels = vec![];
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                labels.push(POS_SEP);
+            }
+            match *part {
+                "_" => labels.push(HOLE),
+                part => labels.extend(part.bytes().map(u32::from)),
+            }
+        }
+        labels
+    }
+
+    #[test]
+    fn test_linear_stream() {
+        let automaton = TokenStreamToAutomaton::new()
+            .to_automaton(&mut tokenizer("wi fi"))
+            .unwrap();
+        assert!(automaton.run(&labels(&["wi", "fi"])));
+        assert!(!automaton.run(&labels(&["wi"])));
+        assert!(!automaton.run(&labels(&["wifi"])));
+        assert!(Operations::is_finite(&automaton));
+    }
+
+    #[test]
+    fn test_holes() {
+        let stop_words = Arc::new(CharArraySet::from_words(&["the", "of"], false));
+        let mut stream = StopFilter::new(
+            Box::new(tokenizer("the king of the hill the")),
+            stop_words.clone(),
+        );
+        let mut to_automaton = TokenStreamToAutomaton::new();
+        let automaton = to_automaton.to_automaton(&mut stream).unwrap();
+        assert!(automaton.run(&labels(&["_", "king", "_", "_", "hill", "_"])));
+        assert!(!automaton.run(&labels(&["king", "hill"])));
+
+        to_automaton.set_preserve_position_increments(false);
+        let mut stream =
+            StopFilter::new(Box::new(tokenizer("the king of the hill the")), stop_words);
+        let automaton = to_automaton.to_automaton(&mut stream).unwrap();
+        assert!(automaton.run(&labels(&["king", "hill"])));
+    }
+
+    #[test]
+    fn test_synonym_graph() {
+        let analyzer = WhitespaceAnalyzer::default();
+        let mut parser = SolrSynonymParser::new(true, true, &analyzer);
+        parser.parse(b"wifi, wireless network".as_ref()).unwrap();
+        let synonyms = Arc::new(parser.build().unwrap());
+        let mut stream = SynonymGraphFilter::new(Box::new(tokenizer("fast wifi")), synonyms, false);
+        let automaton = TokenStreamToAutomaton::new()
+            .to_automaton(&mut stream)
+            .unwrap();
+        assert!(automaton.run(&labels(&["fast", "wifi"])));
+        assert!(automaton.run(&labels(&["fast", "wireless", "network"])));
+        assert!(!automaton.run(&labels(&["fast", "wireless"])));
+        assert!(!automaton.run(&labels(&["fast", "wifi", "network"])));
+
+        let mut to_automaton = TokenStreamToAutomaton::new();
+        to_automaton.set_unicode_arcs(true);
+        let automaton = to_automaton.to_automaton(&mut tokenizer("été")).unwrap();
+        assert!(automaton.run(&['é' as u32, 't' as u32, 'é' as u32]));
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{TokenFilter, TokenStream};
+
+use error::Result;
+
+/// Trims the leading and trailing whitespace of the terms, e.g. of the
+/// terms of a `KeywordTokenizer`.
+///
+/// The offsets are left unchanged, they still include the whitespace.
+#[derive(Debug)]
+pub struct TrimFilter {
+    input: Box<dyn TokenStream>,
+}
+
+impl TrimFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        TrimFilter { input }
+    }
+}
+
+impl TokenStream for TrimFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        if let Some(term) = self.input.term_bytes_attribute_mut().char_term_mut() {
+            let trimmed = match ::std::str::from_utf8(&term.term_buffer) {
+                Ok(text) => {
+                    let trimmed = text.trim();
+                    if trimmed.len() == text.len() {
+                        None
+                    } else {
+                        Some(trimmed.as_bytes().to_vec())
+                    }
+                }
+                Err(_) => None,
+            };
+            if let Some(trimmed) = trimmed {
+                term.copy_buffer(&trimmed);
+            }
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    delegate_token_attributes!(input);
+}
+
+impl TokenFilter for TrimFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_token_stream_contents;
+    use core::analysis::KeywordTokenizer;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_trim_filter() {
+        let text = " \t hello world\n";
+        let tokenizer = KeywordTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut filter = TrimFilter::new(Box::new(tokenizer));
+        assert_token_stream_contents(&mut filter, &["hello world"], Some(&[(0, 15)]), None);
+    }
+}
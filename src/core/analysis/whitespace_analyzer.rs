@@ -0,0 +1,64 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Analyzer, ComponentsCache, ReuseStrategy, TokenStream, WhitespaceTokenizer};
+
+use error::Result;
+
+use std::io::Read;
+
+/// Splits text at whitespace with `WhitespaceTokenizer`, keeping the tokens
+/// as they are, e.g. for pre-tokenized content.
+#[derive(Debug)]
+pub struct WhitespaceAnalyzer {
+    cache: ComponentsCache,
+}
+
+impl Default for WhitespaceAnalyzer {
+    fn default() -> Self {
+        WhitespaceAnalyzer {
+            cache: ComponentsCache::new(ReuseStrategy::Global),
+        }
+    }
+}
+
+impl Analyzer for WhitespaceAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        Ok(Box::new(WhitespaceTokenizer::new(reader)))
+    }
+
+    fn components_cache(&self) -> &ComponentsCache {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::assert_analyzes_to;
+
+    #[test]
+    fn test_whitespace_analyzer() {
+        assert_analyzes_to(
+            &WhitespaceAnalyzer::default(),
+            " foo-bar\tBaz_1\nC++ ",
+            &["foo-bar", "Baz_1", "C++"],
+            None,
+            None,
+        );
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharArraySet, CharTermAttribute, OffsetAttribute, PositionAttribute, PositionLengthAttribute,
+    TermToBytesRefAttribute, TokenFilter, TokenStream, TypeAttribute, DEFAULT_TOKEN_TYPE,
+};
+
+use error::Result;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharType {
+    Lower,
+    Upper,
+    // letters without case and marks
+    Alpha,
+    Digit,
+    Delimiter,
+}
+
+impl CharType {
+    fn of(c: char) -> CharType {
+        if c.is_lowercase() {
+            CharType::Lower
+        } else if c.is_uppercase() {
+            CharType::Upper
+        } else if c.is_alphabetic() {
+            CharType::Alpha
+        } else if c.is_numeric() {
+            CharType::Digit
+        } else {
+            CharType::Delimiter
+        }
+    }
+
+    fn is_alpha(self) -> bool {
+        self != CharType::Digit && self != CharType::Delimiter
+    }
+}
+
+/// A subword or a catenation of subwords, spanning the subwords
+/// `start_part..end_part`.
+#[derive(Debug)]
+struct Part {
+    term: String,
+    start_part: usize,
+    end_part: usize,
+    start_offset: usize,
+    end_offset: usize,
+}
+
+#[derive(Debug)]
+struct OutputToken {
+    term: Vec<u8>,
+    token_type: &'static str,
+    start_offset: usize,
+    end_offset: usize,
+    position_increment: u32,
+    position_length: u32,
+}
+
+/// Splits words into subwords and optionally catenates them, e.g. "Wi-Fi"
+/// is turned into "Wi" and "Fi", and "WiFi" with `CATENATE_WORDS`.
+///
+/// Subwords are delimited by the chars which are neither letters nor
+/// digits, and optionally by case changes ("PowerShot" is split into
+/// "Power" and "Shot") and letter-digit transitions ("SD500" is split into
+/// "SD" and "500"). Leading and trailing delimiters are ignored, as well as
+/// a trailing english possessive "'s" with `STEM_ENGLISH_POSSESSIVE`.
+///
+/// Unlike Lucene's deprecated `WordDelimiterFilter`, the output is a
+/// correct token graph: catenations and the preserved original token span
+/// the positions of the subwords they cover, so the stream must be
+/// flattened with a `FlattenGraphFilter` to be indexed.
+///
+/// Tokens which aren't split, and the protected words, are passed through
+/// unchanged.
+#[derive(Debug)]
+pub struct WordDelimiterGraphFilter {
+    input: Box<dyn TokenStream>,
+    flags: u32,
+    protected_words: Option<Arc<CharArraySet>>,
+    output: VecDeque<OutputToken>,
+    // position increment of the dropped tokens
+    pending_position_increment: u32,
+    term_attribute: CharTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    position_length_attribute: PositionLengthAttribute,
+    type_attribute: TypeAttribute,
+}
+
+impl WordDelimiterGraphFilter {
+    /// Flag to output the subwords made of letters, e.g. "Power" and
+    /// "Shot" for "Power-Shot".
+    pub const GENERATE_WORD_PARTS: u32 = 1;
+    /// Flag to output the subwords made of digits, e.g. "500" and "42" for
+    /// "500-42".
+    pub const GENERATE_NUMBER_PARTS: u32 = 1 << 1;
+    /// Flag to output the catenation of consecutive word subwords, e.g.
+    /// "wifi" for "wi-fi".
+    pub const CATENATE_WORDS: u32 = 1 << 2;
+    /// Flag to output the catenation of consecutive number subwords, e.g.
+    /// "50042" for "500-42".
+    pub const CATENATE_NUMBERS: u32 = 1 << 3;
+    /// Flag to output the catenation of all subwords, e.g. "wifi4000" for
+    /// "wi-fi-4000".
+    pub const CATENATE_ALL: u32 = 1 << 4;
+    /// Flag to also output the original token.
+    pub const PRESERVE_ORIGINAL: u32 = 1 << 5;
+    /// Flag to split on case changes, e.g. "PowerShot" into "Power" and
+    /// "Shot".
+    pub const SPLIT_ON_CASE_CHANGE: u32 = 1 << 6;
+    /// Flag to split on letter-digit transitions, e.g. "j2se" into "j",
+    /// "2" and "se".
+    pub const SPLIT_ON_NUMERICS: u32 = 1 << 7;
+    /// Flag to remove a trailing "'s", e.g. "O'Neil's" into "O" and "Neil".
+    pub const STEM_ENGLISH_POSSESSIVE: u32 = 1 << 8;
+
+    /// Creates a filter splitting words according to `flags`, a combination
+    /// of the flags of this type. The `protected_words` are never split.
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        flags: u32,
+        protected_words: Option<Arc<CharArraySet>>,
+    ) -> Self {
+        WordDelimiterGraphFilter {
+            input,
+            flags,
+            protected_words,
+            output: VecDeque::new(),
+            pending_position_increment: 0,
+            term_attribute: CharTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            position_length_attribute: PositionLengthAttribute::new(),
+            type_attribute: TypeAttribute::new(),
+        }
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    fn has(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Splits `chars` into subwords, returned as char ranges.
+    fn split(&self, chars: &[char]) -> Vec<(usize, usize)> {
+        let mut end = chars.len();
+        if self.has(Self::STEM_ENGLISH_POSSESSIVE)
+            && end >= 2
+            && chars[end - 2] == '\''
+            && (chars[end - 1] == 's' || chars[end - 1] == 'S')
+        {
+            end -= 2;
+        }
+        let types: Vec<CharType> = chars[..end].iter().map(|&c| CharType::of(c)).collect();
+        let mut subwords = vec![];
+        let mut start = None;
+        for i in 0..end {
+            if types[i] == CharType::Delimiter {
+                if let Some(start) = start.take() {
+                    subwords.push((start, i));
+                }
+                continue;
+            }
+            if let Some(s) = start {
+                if self.is_break(&types, i) {
+                    subwords.push((s, i));
+                    start = Some(i);
+                }
+            } else {
+                start = Some(i);
+            }
+        }
+        if let Some(start) = start {
+            subwords.push((start, end));
+        }
+        subwords
+    }
+
+    /// Whether a subword starts at `i`, which follows a char of the same
+    /// subword.
+    fn is_break(&self, types: &[CharType], i: usize) -> bool {
+        let (last, current) = (types[i - 1], types[i]);
+        if last.is_alpha() != current.is_alpha() {
+            return self.has(Self::SPLIT_ON_NUMERICS);
+        }
+        self.has(Self::SPLIT_ON_CASE_CHANGE)
+            && ((last == CharType::Lower && current == CharType::Upper)
+                || (last == CharType::Upper
+                    && current == CharType::Upper
+                    && types.get(i + 1) == Some(&CharType::Lower)))
+    }
+
+    /// Reads the next input token and turns it into output tokens, returns
+    /// false at the end of the input.
+    fn process_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        let offset = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+        let position_increment =
+            self.input.position_attribute().get_position() + self.pending_position_increment;
+        let token_type = self
+            .input
+            .type_attribute()
+            .map_or(DEFAULT_TOKEN_TYPE, |attr| attr.get_type());
+        let term = self
+            .input
+            .term_bytes_attribute()
+            .get_bytes_ref()
+            .bytes()
+            .to_vec();
+
+        let position_length = self
+            .input
+            .position_length_attribute()
+            .map_or(1, |attr| attr.get_position_length());
+
+        let protected = self
+            .protected_words
+            .as_ref()
+            .map_or(false, |words| words.contains(&term));
+        let chars: Vec<char> = match ::std::str::from_utf8(&term) {
+            Ok(text) if !protected => text.chars().collect(),
+            _ => vec![],
+        };
+        let subwords = self.split(&chars);
+        if chars.is_empty() || subwords == [(0, chars.len())] {
+            // nothing to split, the token is passed through
+            self.output.push_back(OutputToken {
+                term,
+                token_type,
+                start_offset,
+                end_offset,
+                position_increment,
+                position_length,
+            });
+            self.pending_position_increment = 0;
+            return Ok(true);
+        }
+
+        // the offsets of the subwords are only known if the token was not
+        // altered, otherwise they share the offsets of the token
+        let offsets_valid = end_offset - start_offset == chars.len();
+        let catenate = |from: usize, to: usize| {
+            let (start, end) = (subwords[from].0, subwords[to - 1].1);
+            let term: String = subwords[from..to]
+                .iter()
+                .flat_map(|&(s, e)| chars[s..e].iter())
+                .collect();
+            let (start_offset, end_offset) = if offsets_valid {
+                (start_offset + start, start_offset + end)
+            } else {
+                (start_offset, end_offset)
+            };
+            Part {
+                term,
+                start_part: from,
+                end_part: to,
+                start_offset,
+                end_offset,
+            }
+        };
+        let is_word = |i: usize| CharType::of(chars[subwords[i].0]).is_alpha();
+
+        let mut parts = vec![];
+        if self.has(Self::PRESERVE_ORIGINAL) {
+            parts.push(Part {
+                term: chars.iter().collect(),
+                start_part: 0,
+                end_part: subwords.len().max(1),
+                start_offset,
+                end_offset,
+            });
+        }
+        let mut from = 0;
+        while from < subwords.len() {
+            let mut to = from + 1;
+            while to < subwords.len() && is_word(to) == is_word(from) {
+                to += 1;
+            }
+            let flag = if is_word(from) {
+                Self::CATENATE_WORDS
+            } else {
+                Self::CATENATE_NUMBERS
+            };
+            if to - from > 1 && self.has(flag) {
+                parts.push(catenate(from, to));
+            }
+            from = to;
+        }
+        if subwords.len() > 1 && self.has(Self::CATENATE_ALL) {
+            parts.push(catenate(0, subwords.len()));
+        }
+        for i in 0..subwords.len() {
+            let flag = if is_word(i) {
+                Self::GENERATE_WORD_PARTS
+            } else {
+                Self::GENERATE_NUMBER_PARTS
+            };
+            if self.has(flag) {
+                parts.push(catenate(i, i + 1));
+            }
+        }
+        if parts.is_empty() {
+            self.pending_position_increment = position_increment;
+            return Ok(true);
+        }
+
+        // remove duplicates, e.g. the catenation of all subwords which are
+        // all words, then order the parts by position, longest first
+        let mut unique: Vec<Part> = Vec::with_capacity(parts.len());
+        for part in parts {
+            if !unique.iter().any(|p| {
+                p.term == part.term
+                    && p.start_part == part.start_part
+                    && p.end_part == part.end_part
+            }) {
+                unique.push(part);
+            }
+        }
+        unique.sort_by(|a, b| {
+            a.start_part
+                .cmp(&b.start_part)
+                .then(b.end_part.cmp(&a.end_part))
+        });
+
+        // the nodes of the graph are the positions where a part starts, the
+        // parts ending elsewhere end at the next node
+        let mut starts: Vec<usize> = unique.iter().map(|p| p.start_part).collect();
+        starts.dedup();
+        let node = |part: usize| starts.iter().take_while(|&&s| s < part).count() as u32;
+        let mut last_node = 0;
+        for (i, part) in unique.into_iter().enumerate() {
+            let start_node = node(part.start_part);
+            self.output.push_back(OutputToken {
+                term: part.term.into_bytes(),
+                token_type,
+                start_offset: part.start_offset,
+                end_offset: part.end_offset,
+                position_increment: if i == 0 {
+                    position_increment
+                } else {
+                    start_node - last_node
+                },
+                position_length: node(part.end_part) - start_node,
+            });
+            last_node = start_node;
+        }
+        self.pending_position_increment = 0;
+        Ok(true)
+    }
+}
+
+impl TokenStream for WordDelimiterGraphFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        loop {
+            if let Some(token) = self.output.pop_front() {
+                self.clear_attributes();
+                self.term_attribute.copy_buffer(&token.term);
+                self.offset_attribute
+                    .set_offset(token.start_offset, token.end_offset)?;
+                self.position_attribute
+                    .set_position(token.position_increment);
+                self.position_length_attribute
+                    .set_position_length(token.position_length);
+                self.type_attribute.set_type(token.token_type);
+                return Ok(true);
+            }
+            if !self.process_token()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let offset = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset.start_offset(), offset.end_offset());
+        self.offset_attribute.set_offset(start_offset, end_offset)?;
+        let position =
+            self.input.position_attribute().get_position() + self.pending_position_increment;
+        self.position_attribute.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()?;
+        self.output.clear();
+        self.pending_position_increment = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn position_length_attribute_mut(&mut self) -> Option<&mut PositionLengthAttribute> {
+        Some(&mut self.position_length_attribute)
+    }
+
+    fn position_length_attribute(&self) -> Option<&PositionLengthAttribute> {
+        Some(&self.position_length_attribute)
+    }
+
+    fn type_attribute_mut(&mut self) -> Option<&mut TypeAttribute> {
+        Some(&mut self.type_attribute)
+    }
+
+    fn type_attribute(&self) -> Option<&TypeAttribute> {
+        Some(&self.type_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+impl TokenFilter for WordDelimiterGraphFilter {
+    fn input(&self) -> &dyn TokenStream {
+        self.input.as_ref()
+    }
+
+    fn input_mut(&mut self) -> &mut dyn TokenStream {
+        self.input.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::{assert_token_graph_contents, assert_token_stream_contents};
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    type F = WordDelimiterGraphFilter;
+
+    fn filter(text: &str, flags: u32) -> WordDelimiterGraphFilter {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        WordDelimiterGraphFilter::new(Box::new(tokenizer), flags, None)
+    }
+
+    #[test]
+    fn test_split() {
+        let flags = F::GENERATE_WORD_PARTS
+            | F::GENERATE_NUMBER_PARTS
+            | F::SPLIT_ON_CASE_CHANGE
+            | F::SPLIT_ON_NUMERICS
+            | F::STEM_ENGLISH_POSSESSIVE;
+        assert_token_graph_contents(
+            &mut filter("foo PowerShot -SD500- O'Neil's", flags),
+            &["foo", "Power", "Shot", "SD", "500", "O", "Neil"],
+            Some(&[
+                (0, 3),
+                (4, 9),
+                (9, 13),
+                (15, 17),
+                (17, 20),
+                (22, 23),
+                (24, 28),
+            ]),
+            &[1, 1, 1, 1, 1, 1, 1],
+            &[1, 1, 1, 1, 1, 1, 1],
+        );
+        assert_token_graph_contents(
+            &mut filter("ABCdef", flags),
+            &["AB", "Cdef"],
+            Some(&[(0, 2), (2, 6)]),
+            &[1, 1],
+            &[1, 1],
+        );
+        assert_token_graph_contents(
+            &mut filter("j2se", F::GENERATE_WORD_PARTS | F::GENERATE_NUMBER_PARTS),
+            &["j2se"],
+            Some(&[(0, 4)]),
+            &[1],
+            &[1],
+        );
+    }
+
+    #[test]
+    fn test_catenate() {
+        let flags = F::GENERATE_WORD_PARTS | F::CATENATE_WORDS | F::SPLIT_ON_CASE_CHANGE;
+        assert_token_graph_contents(
+            &mut filter("WiFi x", flags),
+            &["WiFi", "Wi", "Fi", "x"],
+            Some(&[(0, 4), (0, 2), (2, 4), (5, 6)]),
+            &[1, 0, 1, 1],
+            &[2, 1, 1, 1],
+        );
+        let flags = F::GENERATE_WORD_PARTS
+            | F::GENERATE_NUMBER_PARTS
+            | F::CATENATE_WORDS
+            | F::CATENATE_ALL
+            | F::PRESERVE_ORIGINAL;
+        assert_token_graph_contents(
+            &mut filter("wi-fi-4000", flags),
+            &["wi-fi-4000", "wifi4000", "wifi", "wi", "fi", "4000"],
+            Some(&[(0, 10), (0, 10), (0, 5), (0, 2), (3, 5), (6, 10)]),
+            &[1, 0, 0, 0, 1, 1],
+            &[3, 3, 2, 1, 1, 1],
+        );
+    }
+
+    #[test]
+    fn test_dropped_tokens() {
+        // number parts are neither generated nor catenated
+        assert_token_graph_contents(
+            &mut filter("a 500-42 b", F::GENERATE_WORD_PARTS),
+            &["a", "b"],
+            Some(&[(0, 1), (9, 10)]),
+            &[1, 2],
+            &[1, 1],
+        );
+        assert_token_graph_contents(
+            &mut filter("500-42", F::CATENATE_NUMBERS),
+            &["50042"],
+            Some(&[(0, 6)]),
+            &[1],
+            &[1],
+        );
+    }
+
+    #[test]
+    fn test_protected_words() {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(b"c-3po r2-d2".to_vec())));
+        let mut protected = CharArraySet::new(false);
+        protected.add("c-3po");
+        let mut stream = WordDelimiterGraphFilter::new(
+            Box::new(tokenizer),
+            F::GENERATE_WORD_PARTS | F::GENERATE_NUMBER_PARTS,
+            Some(Arc::new(protected)),
+        );
+        assert_token_stream_contents(&mut stream, &["c-3po", "r2", "d2"], None, None);
+        assert_eq!(stream.offset_attribute().end_offset(), 11);
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::CharArraySet;
+
+use error::{ErrorKind, Result};
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The formats of word list files, e.g. stop word files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordListFormat {
+    /// One word per line, lines starting with `#` are comments.
+    WordSet,
+    /// The format of the Snowball stop word lists: words are separated by
+    /// whitespace and a `|` starts a comment running to the end of the line.
+    Snowball,
+}
+
+impl WordListFormat {
+    /// Returns the words of `text`, in order.
+    pub fn parse(self, text: &str) -> Vec<&str> {
+        let text = text.trim_start_matches('\u{feff}');
+        match self {
+            WordListFormat::WordSet => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect(),
+            WordListFormat::Snowball => text
+                .lines()
+                .flat_map(|line| {
+                    let end = line.find('|').unwrap_or_else(|| line.len());
+                    line[..end].split_whitespace()
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds the words of `text` to `set`.
+    pub fn add_words(self, text: &str, set: &mut CharArraySet) {
+        for word in self.parse(text) {
+            set.add(word);
+        }
+    }
+
+    /// Reads a word list file into a new set.
+    pub fn load<P: AsRef<Path>>(self, path: P, ignore_case: bool) -> Result<CharArraySet> {
+        let mut set = CharArraySet::new(ignore_case);
+        self.add_words(&fs::read_to_string(path)?, &mut set);
+        Ok(set)
+    }
+}
+
+impl FromStr for WordListFormat {
+    type Err = ::error::Error;
+
+    /// Parses `wordset` or `snowball`, case insensitive.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("wordset") {
+            Ok(WordListFormat::WordSet)
+        } else if s.eq_ignore_ascii_case("snowball") {
+            Ok(WordListFormat::Snowball)
+        } else {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "unknown word list format '{}'",
+                s
+            )))
+        }
+    }
+}
+
+/// The bundled stop word lists, by ISO 639-1 code and english name, in the
+/// `WordSet` format.
+const STOP_WORD_LISTS: [(&str, &str, &str); 17] = [
+    ("ar", "arabic", include_str!("stopwords/arabic.txt")),
+    ("da", "danish", include_str!("stopwords/danish.txt")),
+    ("de", "german", include_str!("stopwords/german.txt")),
+    ("el", "greek", include_str!("stopwords/greek.txt")),
+    ("en", "english", include_str!("stopwords/english.txt")),
+    ("es", "spanish", include_str!("stopwords/spanish.txt")),
+    ("fi", "finnish", include_str!("stopwords/finnish.txt")),
+    ("fr", "french", include_str!("stopwords/french.txt")),
+    ("hu", "hungarian", include_str!("stopwords/hungarian.txt")),
+    ("it", "italian", include_str!("stopwords/italian.txt")),
+    ("nl", "dutch", include_str!("stopwords/dutch.txt")),
+    ("no", "norwegian", include_str!("stopwords/norwegian.txt")),
+    ("pt", "portuguese", include_str!("stopwords/portuguese.txt")),
+    ("ro", "romanian", include_str!("stopwords/romanian.txt")),
+    ("ru", "russian", include_str!("stopwords/russian.txt")),
+    ("sv", "swedish", include_str!("stopwords/swedish.txt")),
+    ("tr", "turkish", include_str!("stopwords/turkish.txt")),
+];
+
+/// The ISO 639-1 codes of the languages with a bundled stop word list.
+pub fn stop_words_languages() -> impl Iterator<Item = &'static str> {
+    STOP_WORD_LISTS.iter().map(|&(code, _, _)| code)
+}
+
+/// Returns the bundled stop words of a language, given by its ISO 639-1
+/// code or its english name, e.g. `de` or `German`, case insensitive.
+///
+/// The lists are the Snowball ones, which are larger than
+/// `ENGLISH_STOP_WORDS` for english. A new set is built on each call, so
+/// it's better shared in an `Arc` by the callers.
+pub fn language_stop_words(language: &str, ignore_case: bool) -> Result<CharArraySet> {
+    match STOP_WORD_LISTS.iter().find(|&&(code, name, _)| {
+        language.eq_ignore_ascii_case(code) || language.eq_ignore_ascii_case(name)
+    }) {
+        Some(&(_, _, text)) => {
+            let mut set = CharArraySet::new(ignore_case);
+            WordListFormat::WordSet.add_words(text, &mut set);
+            Ok(set)
+        }
+        None => bail!(ErrorKind::IllegalArgument(format!(
+            "no stop words for language '{}'",
+            language
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_list_formats() {
+        let text = "\u{feff}# comment\n the \n\nof\n";
+        assert_eq!(WordListFormat::WordSet.parse(text), vec!["the", "of"]);
+
+        let text = " | a comment\nund  oder | and, or\naber|but\n";
+        assert_eq!(
+            "Snowball".parse::<WordListFormat>().unwrap().parse(text),
+            vec!["und", "oder", "aber"]
+        );
+        assert!("csv".parse::<WordListFormat>().is_err());
+    }
+
+    #[test]
+    fn test_language_stop_words() {
+        assert_eq!(stop_words_languages().count(), STOP_WORD_LISTS.len());
+        for language in stop_words_languages() {
+            assert!(!language_stop_words(language, false).unwrap().is_empty());
+        }
+
+        let german = language_stop_words("German", true).unwrap();
+        assert!(german.contains(b"Und"));
+        assert!(german.contains("über".as_bytes()));
+        assert!(!german.contains(b"haus"));
+
+        let french = language_stop_words("FR", false).unwrap();
+        assert!(french.contains(b"les"));
+        assert!(!french.contains(b"Les"));
+
+        assert!(language_stop_words("tlh", false).is_err());
+    }
+}
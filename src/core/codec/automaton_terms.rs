@@ -0,0 +1,351 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator, TermIterator};
+use core::util::automaton::{AutomatonType, ByteRunAutomaton, CompiledAutomaton};
+
+use error::Result;
+
+use std::sync::Arc;
+
+/// A `FilteredTermIterator` that enumerates terms based upon what is accepted by a
+/// DFA.
+///
+/// The algorithm is such:
+/// 1. As long as matches are successful, keep reading sequentially.
+/// 2. When a match fails, skip to the next string in lexicographic order that
+/// does not enter a reject state.
+///
+/// The algorithm does not attempt to actually skip to the next string that is
+/// completely accepted. This is not possible when the language accepted by the
+/// FSM is not finite (i.e. * operator).
+pub struct AutomatonTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    automaton_type: AutomatonType,
+    // the singleton term for `AutomatonType::Single`
+    term: Option<Vec<u8>>,
+    run_automaton: Option<Arc<ByteRunAutomaton>>,
+    // true if the automaton accepts a finite language
+    finite: bool,
+    // the reference used for seeking forwards through the term dictionary
+    seek_bytes: Vec<u8>,
+    // array of saved states used for backtracking
+    saved_states: Vec<usize>,
+    // visited states, used for loop detection
+    visited: Vec<u64>,
+    cur_gen: u64,
+    // true if we are enumerating an infinite portion of the DFA.
+    // in this case it is faster to drive the query based on the terms dictionary.
+    // when this is true, linear_upper_bound indicate the end of range
+    // of terms where we should simply do sequential reads instead.
+    linear: bool,
+    linear_upper_bound: Vec<u8>,
+    // true if the wrapped iterator already only enumerates accepted terms
+    intersected: bool,
+}
+
+impl<T: TermIterator> AutomatonTermIterator<T> {
+    pub fn new(terms: T, compiled: &CompiledAutomaton) -> AutomatonTermIterator<T> {
+        let visited = match compiled.run_automaton {
+            Some(ref run_automaton) => vec![0u64; run_automaton.size()],
+            None => Vec::new(),
+        };
+        // `All` automaton just walks the whole terms dictionary sequentially
+        let start_with_seek = compiled.automaton_type != AutomatonType::All;
+        AutomatonTermIterator {
+            base: FilteredTermIterBase::new(terms, start_with_seek),
+            automaton_type: compiled.automaton_type,
+            term: compiled.term.clone(),
+            run_automaton: compiled.run_automaton.clone(),
+            finite: compiled.finite,
+            seek_bytes: Vec::with_capacity(10),
+            saved_states: Vec::with_capacity(10),
+            visited,
+            cur_gen: 0,
+            linear: false,
+            linear_upper_bound: Vec::with_capacity(10),
+            intersected: false,
+        }
+    }
+
+    /// Wraps a terms iterator that already intersects the terms dictionary with
+    /// the automaton, e.g. the block tree intersect iterator. Every term it
+    /// returns is accepted as-is and no seeking is ever done.
+    pub fn intersected(terms: T, compiled: &CompiledAutomaton) -> AutomatonTermIterator<T> {
+        let mut iter = Self::new(terms, compiled);
+        iter.base.do_seek = false;
+        iter.intersected = true;
+        iter
+    }
+
+    fn run_automaton(&self) -> &ByteRunAutomaton {
+        self.run_automaton.as_ref().unwrap()
+    }
+
+    /// Sets the enum to operate in linear fashion, as we have found
+    /// a looping transition at position: we set an upper bound and
+    /// act like a TermRangeQuery for this portion of the term space.
+    fn set_linear(&mut self, position: usize) {
+        debug_assert!(!self.linear);
+        let mut state = 0;
+        for i in 0..position {
+            state = self
+                .run_automaton()
+                .step(state, self.seek_bytes[i])
+                .expect("linear prefix must be accepted");
+        }
+        let label = u32::from(self.seek_bytes[position]);
+        let mut max_interval = 0xffu32;
+        for t in self.run_automaton().automaton().transitions(state) {
+            if t.min <= label && label <= t.max {
+                max_interval = t.max;
+                break;
+            }
+        }
+        // 0xff terms don't get the optimization... not worth the trouble.
+        if max_interval != 0xff {
+            max_interval += 1;
+        }
+        self.linear_upper_bound.clear();
+        self.linear_upper_bound
+            .extend_from_slice(&self.seek_bytes[..position]);
+        self.linear_upper_bound.push(max_interval as u8);
+        self.linear = true;
+    }
+
+    /// Increments the byte buffer to the next string in binary order after s that
+    /// will not put the machine into a reject state. If such a string does not
+    /// exist, returns false.
+    ///
+    /// The correctness of this method depends upon the automaton being
+    /// deterministic, and having no transitions to dead states.
+    fn next_string(&mut self) -> bool {
+        let mut pos = 0;
+        self.saved_states.clear();
+        self.saved_states.push(0);
+
+        loop {
+            self.cur_gen += 1;
+            self.linear = false;
+            // walk the automaton until a character is rejected.
+            let mut state = self.saved_states[pos];
+            while pos < self.seek_bytes.len() {
+                self.visited[state] = self.cur_gen;
+                let next_state = match self.run_automaton().step(state, self.seek_bytes[pos]) {
+                    Some(s) => s,
+                    None => break,
+                };
+                self.saved_states.truncate(pos + 1);
+                self.saved_states.push(next_state);
+                // we found a loop, record it for faster enumeration
+                if !self.finite && !self.linear && self.visited[next_state] == self.cur_gen {
+                    self.set_linear(pos);
+                }
+                state = next_state;
+                pos += 1;
+            }
+
+            // take the useful portion, and the last non-reject state, and attempt to
+            // append characters that will match.
+            if self.next_string_from(state, pos) {
+                return true;
+            }
+            // no more solutions exist from this useful portion, backtrack
+            match self.backtrack(pos) {
+                Some(p) => pos = p,
+                // no more solutions at all
+                None => return false,
+            }
+            let new_state = self
+                .run_automaton()
+                .step(self.saved_states[pos], self.seek_bytes[pos]);
+            if let Some(s) = new_state {
+                if self.run_automaton().is_accept(s) {
+                    // string is good to go as-is
+                    return true;
+                }
+            }
+            // else advance further
+            // if we backtrack thru an infinite DFA, the loop detection is important!
+            // for now, restart from scratch for all infinite DFAs
+            if !self.finite {
+                pos = 0;
+            }
+        }
+    }
+
+    /// Returns the next string in lexicographic order that will not put
+    /// the machine into a reject state.
+    ///
+    /// This method traverses the DFA from the given position in the string,
+    /// starting at the given state.
+    ///
+    /// If this cannot satisfy the machine, returns false. This method will
+    /// walk the minimal path, in lexicographic order, as long as possible.
+    ///
+    /// If this method returns false, then there might still be more solutions,
+    /// it is necessary to backtrack to find out.
+    fn next_string_from(&mut self, mut state: usize, position: usize) -> bool {
+        // the next lexicographic character must be greater than the existing
+        // character, if it exists.
+        let mut c = 0u32;
+        if position < self.seek_bytes.len() {
+            c = u32::from(self.seek_bytes[position]);
+            // if the next byte is 0xff and is not part of the useful portion,
+            // then by definition it puts us in a reject state, and therefore this
+            // path is dead. there cannot be any higher transitions. backtrack.
+            if c == 0xff {
+                return false;
+            }
+            c += 1;
+        }
+
+        self.seek_bytes.truncate(position);
+        self.visited[state] = self.cur_gen;
+
+        let run_automaton = Arc::clone(self.run_automaton.as_ref().unwrap());
+        let automaton = run_automaton.automaton();
+        // find the minimal path (lexicographic order) that is >= c
+        for t in automaton.transitions(state) {
+            if t.max < c {
+                continue;
+            }
+            let next_char = c.max(t.min);
+            // append either the next sequential char, or the minimum transition
+            self.seek_bytes.push(next_char as u8);
+            state = t.dest;
+            // as long as is possible, continue down the minimal path in
+            // lexicographic order. if a loop or accept state is encountered, stop.
+            while self.visited[state] != self.cur_gen && !run_automaton.is_accept(state) {
+                self.visited[state] = self.cur_gen;
+                // Note: we work with a DFA with no transitions to dead states.
+                // so the below is ok, if it is not an accept state,
+                // then there MUST be at least one transition.
+                let first = automaton.transitions(state)[0];
+                state = first.dest;
+                // append the minimum transition
+                self.seek_bytes.push(first.min as u8);
+                // we found a loop, record it for faster enumeration
+                if !self.finite && !self.linear && self.visited[state] == self.cur_gen {
+                    let position = self.seek_bytes.len() - 1;
+                    self.set_linear(position);
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Attempts to backtrack thru the string after encountering a dead end
+    /// at some given position. Returns the position to restart from, or `None`
+    /// if no more solutions exist.
+    fn backtrack(&mut self, mut position: usize) -> Option<usize> {
+        while position > 0 {
+            position -= 1;
+            let next_char = self.seek_bytes[position];
+            // if a character is 0xff it's a dead-end too,
+            // because there is no higher character in binary sort order.
+            if next_char != 0xff {
+                self.seek_bytes[position] = next_char + 1;
+                self.seek_bytes.truncate(position + 1);
+                return Some(position);
+            }
+        }
+        // all solutions exhausted
+        None
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for AutomatonTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    /// Returns true if the term matches the automaton. Also stashes away the term
+    /// to assist with smart enumeration.
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        if self.intersected {
+            return Ok(AcceptStatus::Yes);
+        }
+        let status = match self.automaton_type {
+            AutomatonType::None => AcceptStatus::End,
+            AutomatonType::All => AcceptStatus::Yes,
+            AutomatonType::Single => {
+                if self.term.as_ref().map_or(false, |t| t.as_slice() == term) {
+                    AcceptStatus::Yes
+                } else {
+                    AcceptStatus::End
+                }
+            }
+            AutomatonType::Normal => {
+                if self.run_automaton().run(term) {
+                    if self.linear {
+                        AcceptStatus::Yes
+                    } else {
+                        AcceptStatus::YesAndSeek
+                    }
+                } else if self.linear && term < self.linear_upper_bound.as_slice() {
+                    AcceptStatus::No
+                } else {
+                    AcceptStatus::NoAndSeek
+                }
+            }
+        };
+        Ok(status)
+    }
+
+    fn next_seek_term(&mut self) -> Option<Vec<u8>> {
+        if self.intersected {
+            return None;
+        }
+        match self.automaton_type {
+            AutomatonType::None | AutomatonType::All => None,
+            AutomatonType::Single => {
+                // seek to the singleton term only once
+                if self.base.actual_term.is_none() {
+                    self.term.clone()
+                } else {
+                    None
+                }
+            }
+            AutomatonType::Normal => {
+                match self.base.actual_term {
+                    None => {
+                        debug_assert!(self.seek_bytes.is_empty());
+                        // return the empty term, as it's valid
+                        if self.run_automaton().is_accept(0) {
+                            return Some(Vec::new());
+                        }
+                    }
+                    Some(ref term) => {
+                        self.seek_bytes.clear();
+                        self.seek_bytes.extend_from_slice(term);
+                    }
+                }
+                // seek to the next possible string
+                if self.next_string() {
+                    Some(self.seek_bytes.clone())
+                } else {
+                    // no more possible strings can match
+                    None
+                }
+            }
+        }
+    }
+}
@@ -40,7 +40,6 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::ptr;
-use std::rc::Rc;
 use std::sync::Arc;
 
 /// Abstract API that consumes numeric, binary and sorted docvalues.
@@ -841,7 +840,7 @@ struct SortedDocValuesSub {
     values: *mut dyn SortedDocValues,
     doc_id: DocId,
     max_doc: i32,
-    map: Rc<dyn LongValues>,
+    map: Arc<dyn LongValues>,
     base: DocIdMergerSubBase,
 }
 
@@ -850,7 +849,7 @@ impl SortedDocValuesSub {
         doc_map: Arc<LiveDocsDocMap>,
         values: &mut (dyn SortedDocValues + 'static),
         max_doc: i32,
-        map: Rc<dyn LongValues>,
+        map: Arc<dyn LongValues>,
     ) -> Self {
         let base = DocIdMergerSubBase::new(doc_map);
         SortedDocValuesSub {
@@ -1111,7 +1110,7 @@ struct SortedSetDocValuesSub {
     values: *mut dyn SortedSetDocValues,
     doc_id: DocId,
     max_doc: i32,
-    map: Rc<dyn LongValues>,
+    map: Arc<dyn LongValues>,
     base: DocIdMergerSubBase,
 }
 
@@ -1120,7 +1119,7 @@ impl SortedSetDocValuesSub {
         doc_map: Arc<LiveDocsDocMap>,
         values: &mut (dyn SortedSetDocValues + 'static),
         max_doc: i32,
-        map: Rc<dyn LongValues>,
+        map: Arc<dyn LongValues>,
     ) -> Self {
         let base = DocIdMergerSubBase::new(doc_map);
         SortedSetDocValuesSub {
@@ -1619,7 +1618,7 @@ pub struct OrdinalMap {
     // globalOrd -> first segment container
     first_segments: PackedLongValues,
     // for every segment, segmentOrd -> globalOrd
-    segment_to_global_ords: Vec<Rc<dyn LongValues>>,
+    segment_to_global_ords: Vec<Arc<dyn LongValues>>,
     // the map from/to segment ids
     segment_map: SegmentMap,
 }
@@ -1709,14 +1708,14 @@ impl OrdinalMap {
         let first_segments = first_segments_builder.build();
         let global_ord_deltas = global_ord_deltas_builder.build();
 
-        let mut segment_to_global_ords: Vec<Rc<dyn LongValues>> = Vec::with_capacity(subs.len());
+        let mut segment_to_global_ords: Vec<Arc<dyn LongValues>> = Vec::with_capacity(subs.len());
         let mut i = 0;
         for mut d in ord_deltas {
             let deltas = d.build();
             if ord_delta_bits[i] == 0 {
                 // segment ords perfectly match global ordinals
                 // likely in case of low cardinalities and large segments
-                segment_to_global_ords.push(Rc::new(IdentityLongValues {}));
+                segment_to_global_ords.push(Arc::new(IdentityLongValues {}));
             } else {
                 let bits_required = if ord_delta_bits[i] < 0 {
                     64
@@ -1743,12 +1742,12 @@ impl OrdinalMap {
                         cnt += 1;
                     }
                     debug_assert_eq!(cnt as i64, size);
-                    segment_to_global_ords.push(Rc::new(MutableAsLongValues {
+                    segment_to_global_ords.push(Arc::new(MutableAsLongValues {
                         mutable: Arc::new(new_deltas),
                     }));
                 } else {
                     segment_to_global_ords
-                        .push(Rc::new(PackedLongValuesWrapper { values: deltas }));
+                        .push(Arc::new(PackedLongValuesWrapper { values: deltas }));
                 }
             }
             i += 1;
@@ -1774,9 +1773,9 @@ impl OrdinalMap {
         global_ord - self.global_ord_deltas.get64(global_ord).unwrap()
     }
 
-    pub fn get_global_ords(&self, index: usize) -> Rc<dyn LongValues> {
+    pub fn get_global_ords(&self, index: usize) -> Arc<dyn LongValues> {
         let i = self.segment_map.old_to_new(index as i32) as usize;
-        Rc::clone(&self.segment_to_global_ords[i])
+        Arc::clone(&self.segment_to_global_ords[i])
     }
 }
 
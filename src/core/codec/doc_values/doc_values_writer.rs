@@ -22,7 +22,7 @@ use core::index::reader::{CachedBinaryDVs, CachedNumericDVs};
 use core::search::sort_field::{SortField, SortFieldType, SortedNumericSelectorType};
 use core::search::NO_MORE_DOCS;
 use core::store::directory::Directory;
-use core::store::io::DataOutput;
+use core::store::io::{DataInput, DataOutput};
 use core::util::packed::{
     LongValuesIterator, PackedLongValues, PackedLongValuesBuilder, PackedLongValuesBuilderType,
     PagedGrowableWriter, PagedMutableHugeWriter, PagedMutableWriter, DEFAULT_PAGE_SIZE,
@@ -45,7 +45,6 @@ use error::{
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
-use std::io::Read;
 use std::mem;
 
 pub trait DocValuesWriter {
@@ -646,6 +645,10 @@ impl DocValuesWriter for SortedNumericDocValuesWriter {
                         }
                         values_iter.next().unwrap()
                     }
+                    SortedNumericSelectorType::Sum => {
+                        // rejected by `IndexWriter::validate_index_sort`
+                        unreachable!()
+                    }
                 };
                 data[i] = val;
             }
@@ -2272,8 +2275,9 @@ impl<'a> Iterator for BinaryBytesIterator<'a> {
         if self.upto < self.size {
             let length = self.lengths_iter.next().unwrap() as usize;
             self.value.resize(length, 0u8);
-            if let Err(e) = self.input.read_exact(&mut self.value) {
-                return Some(Err(e.into()));
+            // copies whole pages at once, the values may be much larger than a page
+            if let Err(e) = self.input.read_bytes(&mut self.value, 0, length) {
+                return Some(Err(e));
             }
             match self.docs_with_field.get(self.upto) {
                 Err(e) => {
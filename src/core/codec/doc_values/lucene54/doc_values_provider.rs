@@ -12,20 +12,20 @@
 // limitations under the License.
 
 use core::codec::doc_values::lucene54::{
-    DocValuesTermIterator, NumericLongValuesEnum, SortedDocValuesTermIterator,
-    SortedSetDocValuesTermIterator, TailoredBoxedBinaryDocValuesEnum,
+    DocValuesTermIterator, Lucene54DocValuesFormat, NumericLongValuesEnum,
+    SortedDocValuesTermIterator, SortedSetDocValuesTermIterator, TailoredBoxedBinaryDocValuesEnum,
 };
 use core::codec::doc_values::{
     BinaryDocValues, BinaryDocValuesProvider, EmptySortedSetDocValues, NumericDocValues,
     SortedDocValues, SortedDocValuesProvider, SortedNumericDocValues,
     SortedNumericDocValuesProvider, SortedSetDocValues, SortedSetDocValuesProvider, NO_MORE_ORDS,
 };
-use core::store::io::IndexInput;
-use core::util::packed::MixinMonotonicLongValues;
+use core::store::io::{DataInput, IndexInput};
+use core::util::packed::{MixinMonotonicLongValues, OffsetAndLength};
 use core::util::LongValues;
-use core::util::{BitsMut, DocId, UnsignedShift};
+use core::util::{BitsMut, CompressionMode, Decompress, Decompressor, DocId, UnsignedShift};
 
-use error::Result;
+use error::{ErrorKind::IllegalArgument, Result};
 use std::cmp::Ordering;
 use std::io::Read;
 use std::ops::Deref;
@@ -137,6 +137,106 @@ impl<T: LongValues + Clone + 'static> BinaryDocValues for VariableBinaryDocValue
     }
 }
 
+/// Variable length binary values LZ4 compressed in blocks of docs, the last
+/// decompressed block is kept so that reading the values of close docs only
+/// decompresses it once.
+pub struct BlockCompressedBinaryDocValues<T: LongValues + Clone + 'static> {
+    // block -> address of its lengths and compressed values
+    addresses: T,
+    data: Box<dyn IndexInput>,
+    count: i64,
+    decompressor: Decompressor,
+    current_block: i64,
+    // the offsets of the values in the decompressed block, then its length
+    offsets: Vec<usize>,
+    block: Vec<u8>,
+    block_position: OffsetAndLength,
+}
+
+impl<T: LongValues + Clone + 'static> BlockCompressedBinaryDocValues<T> {
+    pub fn new(addresses: T, data: Box<dyn IndexInput>, count: i64) -> Self {
+        BlockCompressedBinaryDocValues {
+            addresses,
+            data,
+            count,
+            decompressor: CompressionMode::FAST.new_decompressor(),
+            current_block: -1,
+            offsets: Vec::with_capacity(
+                Lucene54DocValuesFormat::BINARY_DOCS_PER_BLOCK as usize + 1,
+            ),
+            block: vec![],
+            block_position: OffsetAndLength(0, 0),
+        }
+    }
+
+    pub fn clone(&self) -> Result<Self> {
+        let data = self.data.clone()?;
+        Ok(Self::new(self.addresses.clone(), data, self.count))
+    }
+
+    fn load_block(&mut self, block: i64) -> Result<()> {
+        let first = block << Lucene54DocValuesFormat::BINARY_BLOCK_SHIFT;
+        let num_docs =
+            (self.count - first).min(i64::from(Lucene54DocValuesFormat::BINARY_DOCS_PER_BLOCK));
+        self.data.seek(self.addresses.get64(block)?)?;
+        self.offsets.clear();
+        let mut length = 0;
+        self.offsets.push(length);
+        for _ in 0..num_docs {
+            length += self.data.read_vint()? as usize;
+            self.offsets.push(length);
+        }
+        if length > 0 {
+            self.decompressor.decompress(
+                self.data.as_mut(),
+                length,
+                0,
+                length,
+                &mut self.block,
+                &mut self.block_position,
+            )?;
+        } else {
+            self.block_position = OffsetAndLength(0, 0);
+        }
+        self.current_block = block;
+        Ok(())
+    }
+}
+
+impl<T: LongValues + Clone + 'static> BinaryDocValuesProvider
+    for BlockCompressedBinaryDocValues<T>
+{
+    fn get(&self) -> Result<Box<dyn BinaryDocValues>> {
+        Ok(Box::new(self.clone()?))
+    }
+}
+
+impl<T: LongValues + Clone + 'static> LongBinaryDocValues for BlockCompressedBinaryDocValues<T> {
+    fn get64(&mut self, id: i64) -> Result<Vec<u8>> {
+        if id < 0 || id >= self.count {
+            bail!(IllegalArgument(format!("doc {} is out of bounds", id)));
+        }
+        let block = id >> Lucene54DocValuesFormat::BINARY_BLOCK_SHIFT;
+        if block != self.current_block {
+            self.load_block(block)?;
+        }
+        let index = (id & i64::from(Lucene54DocValuesFormat::BINARY_BLOCK_MASK)) as usize;
+        let start = self.block_position.0 + self.offsets[index];
+        let end = self.block_position.0 + self.offsets[index + 1];
+        Ok(self.block[start..end].to_vec())
+    }
+
+    fn clone_long(&self) -> Result<Box<dyn LongBinaryDocValues>> {
+        Ok(Box::new(self.clone()?))
+    }
+}
+
+impl<T: LongValues + Clone + 'static> BinaryDocValues for BlockCompressedBinaryDocValues<T> {
+    fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+        self.get64(i64::from(doc_id))
+    }
+}
+
 /// ################ BinaryDocValuesProvider
 pub trait CloneableNumericDocValues: NumericDocValues {
     fn clone_box(&self) -> Box<dyn NumericDocValues>;
@@ -28,6 +28,7 @@ use core::util::packed::{
 use core::util::{
     bytes_difference, sort_key_length, BytesRef, Numeric, PagedBytes, ReusableIterator,
 };
+use core::util::{Compress, CompressionMode, Compressor};
 
 use error::Result;
 use std::collections::{BTreeSet, HashMap, HashSet};
@@ -36,6 +37,7 @@ pub struct Lucene54DocValuesConsumer<O: IndexOutput> {
     data: O,
     meta: O,
     max_doc: i32,
+    version: i32,
 }
 
 impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
@@ -45,6 +47,26 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
         data_extension: &str,
         meta_codec: &str,
         meta_extension: &str,
+    ) -> Result<Self> {
+        Self::with_version(
+            state,
+            data_codec,
+            data_extension,
+            meta_codec,
+            meta_extension,
+            Lucene54DocValuesFormat::VERSION_CURRENT,
+        )
+    }
+
+    /// Writes the segment in the layout of an older format `version`, so that
+    /// readers of segments from before `VERSION_BINARY_COMPRESSED` can be tested.
+    pub(crate) fn with_version<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
+        state: &SegmentWriteState<D, DW, C>,
+        data_codec: &str,
+        data_extension: &str,
+        meta_codec: &str,
+        meta_extension: &str,
+        version: i32,
     ) -> Result<Self> {
         let data_name = segment_file_name(
             &state.segment_info.name,
@@ -55,7 +77,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
         codec_util::write_index_header(
             &mut data,
             data_codec,
-            Lucene54DocValuesFormat::VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -69,7 +91,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
         codec_util::write_index_header(
             &mut meta,
             meta_codec,
-            Lucene54DocValuesFormat::VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -80,6 +102,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
             data,
             meta,
             max_doc,
+            version,
         })
     }
 }
@@ -431,6 +454,202 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
         Ok(doc_id)
     }
 
+    fn add_uncompressed_binary(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<BytesRef>>,
+    ) -> Result<()> {
+        // write the bytes data
+        self.meta.write_vint(field_info.number as i32)?;
+        self.meta.write_byte(Lucene54DocValuesFormat::BINARY)?;
+        let mut min_length = i32::max_value();
+        let mut max_length = i32::min_value();
+        let start_fp = self.data.file_pointer();
+        let mut count = 0i64;
+        let mut missing_count = 0i64;
+
+        loop {
+            let v = match values.next() {
+                None => {
+                    break;
+                }
+                Some(r) => r?,
+            };
+            let length = v.len() as i32;
+            if length == 0 {
+                missing_count += 1;
+            }
+
+            min_length = length.min(min_length);
+            max_length = length.max(max_length);
+            if length > 0 {
+                self.data.write_bytes(v.bytes(), 0, length as usize)?;
+            }
+
+            count += 1;
+        }
+
+        let v = if min_length == max_length {
+            Lucene54DocValuesFormat::BINARY_FIXED_UNCOMPRESSED
+        } else {
+            Lucene54DocValuesFormat::BINARY_VARIABLE_UNCOMPRESSED
+        };
+        self.meta.write_vint(v)?;
+        if missing_count == 0 {
+            self.meta
+                .write_long(Lucene54DocValuesFormat::ALL_LIVE as i64)?;
+        } else if missing_count == count {
+            self.meta
+                .write_long(Lucene54DocValuesFormat::ALL_MISSING as i64)?;
+        } else {
+            self.meta.write_long(self.data.file_pointer())?;
+            values.reset();
+            self.write_missing_bitset_bytes(values)?;
+        }
+
+        self.meta.write_vint(min_length)?;
+        self.meta.write_vint(max_length)?;
+        self.meta.write_vlong(count)?;
+        self.meta.write_long(start_fp)?;
+
+        // if minLength == maxLength, it's a fixed-length bytes, we are done (the addresses are
+        // implicit) otherwise, we need to record the length fields...
+
+        if min_length != max_length {
+            self.meta.write_long(self.data.file_pointer())?;
+            self.meta
+                .write_vint(Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT)?;
+
+            {
+                let mut writer = DirectMonotonicWriter::get_instance(
+                    &mut self.meta,
+                    &mut self.data,
+                    count + 1,
+                    Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT,
+                )?;
+                let mut addr = 0;
+                writer.add(addr)?;
+                values.reset();
+                for v in values {
+                    let v = v?;
+                    if !v.is_empty() {
+                        addr += v.len() as i64;
+                    }
+                    writer.add(addr)?;
+                }
+                writer.finish()?;
+            }
+            self.meta.write_long(self.data.file_pointer())?;
+        }
+        Ok(())
+    }
+
+    /// Writes variable length binary values LZ4 compressed in blocks of
+    /// `BINARY_DOCS_PER_BLOCK` docs: large or repetitive values take much less
+    /// space, and reading a value only decompresses its block.
+    fn add_compressed_binary(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<BytesRef>>,
+    ) -> Result<()> {
+        self.meta.write_vint(field_info.number as i32)?;
+        self.meta.write_byte(Lucene54DocValuesFormat::BINARY)?;
+        let mut min_length = i32::max_value();
+        let mut max_length = i32::min_value();
+        let start_fp = self.data.file_pointer();
+        let mut count = 0i64;
+        let mut missing_count = 0i64;
+
+        let mut compressor = CompressionMode::FAST.new_compressor();
+        let mut block_lengths =
+            Vec::with_capacity(Lucene54DocValuesFormat::BINARY_DOCS_PER_BLOCK as usize);
+        let mut block = vec![];
+        // the address of every block, relative to the start of the values
+        let mut block_addresses = vec![];
+        loop {
+            let v = match values.next() {
+                None => {
+                    break;
+                }
+                Some(r) => r?,
+            };
+            let length = v.len() as i32;
+            if length == 0 {
+                missing_count += 1;
+            }
+            min_length = length.min(min_length);
+            max_length = length.max(max_length);
+            block_lengths.push(length);
+            block.extend_from_slice(v.bytes());
+            count += 1;
+
+            if block_lengths.len() == Lucene54DocValuesFormat::BINARY_DOCS_PER_BLOCK as usize {
+                block_addresses.push(self.data.file_pointer() - start_fp);
+                self.write_compressed_block(&mut compressor, &block_lengths, &block)?;
+                block_lengths.clear();
+                block.clear();
+            }
+        }
+        if !block_lengths.is_empty() {
+            block_addresses.push(self.data.file_pointer() - start_fp);
+            self.write_compressed_block(&mut compressor, &block_lengths, &block)?;
+        }
+
+        self.meta
+            .write_vint(Lucene54DocValuesFormat::BINARY_VARIABLE_COMPRESSED)?;
+        if missing_count == 0 {
+            self.meta
+                .write_long(Lucene54DocValuesFormat::ALL_LIVE as i64)?;
+        } else if missing_count == count {
+            self.meta
+                .write_long(Lucene54DocValuesFormat::ALL_MISSING as i64)?;
+        } else {
+            self.meta.write_long(self.data.file_pointer())?;
+            values.reset();
+            self.write_missing_bitset_bytes(values)?;
+        }
+
+        self.meta.write_vint(min_length)?;
+        self.meta.write_vint(max_length)?;
+        self.meta.write_vlong(count)?;
+        self.meta.write_long(start_fp)?;
+
+        self.meta.write_long(self.data.file_pointer())?;
+        self.meta
+            .write_vint(Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT)?;
+        {
+            let mut writer = DirectMonotonicWriter::get_instance(
+                &mut self.meta,
+                &mut self.data,
+                block_addresses.len() as i64,
+                Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT,
+            )?;
+            for address in block_addresses {
+                writer.add(address)?;
+            }
+            writer.finish()?;
+        }
+        self.meta.write_long(self.data.file_pointer())?;
+        Ok(())
+    }
+
+    // the lengths of the values of the block, then their compressed bytes
+    fn write_compressed_block(
+        &mut self,
+        compressor: &mut Compressor,
+        lengths: &[i32],
+        block: &[u8],
+    ) -> Result<()> {
+        for &length in lengths {
+            self.data.write_vint(length)?;
+        }
+        // a block of missing values has nothing to compress
+        if !block.is_empty() {
+            compressor.compress(block, 0, block.len(), &mut self.data)?;
+        }
+        Ok(())
+    }
+
     fn add_terms_dict(
         &mut self,
         field_info: &FieldInfo,
@@ -478,7 +697,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
             || ((num_values as i32) < Lucene54DocValuesFormat::REVERSE_INTERVAL_COUNT)
         {
             values.reset();
-            self.add_binary_field(field_info, values)?;
+            self.add_uncompressed_binary(field_info, values)?;
         } else {
             // we don't have to handle the empty case header
             debug_assert!(num_values > 0);
@@ -803,15 +1022,8 @@ impl<O: IndexOutput> DocValuesConsumer for Lucene54DocValuesConsumer<O> {
         field_info: &FieldInfo,
         values: &mut impl ReusableIterator<Item = Result<BytesRef>>,
     ) -> Result<()> {
-        // write the bytes data
-        self.meta.write_vint(field_info.number as i32)?;
-        self.meta.write_byte(Lucene54DocValuesFormat::BINARY)?;
-        let mut min_length = i32::max_value();
-        let mut max_length = i32::min_value();
-        let start_fp = self.data.file_pointer();
-        let mut count = 0i64;
-        let mut missing_count = 0i64;
-
+        let mut min_length = usize::max_value();
+        let mut max_length = 0;
         loop {
             let v = match values.next() {
                 None => {
@@ -819,73 +1031,17 @@ impl<O: IndexOutput> DocValuesConsumer for Lucene54DocValuesConsumer<O> {
                 }
                 Some(r) => r?,
             };
-            let length = v.len() as i32;
-            if length == 0 {
-                missing_count += 1;
-            }
-
-            min_length = length.min(min_length);
-            max_length = length.max(max_length);
-            if length > 0 {
-                self.data.write_bytes(v.bytes(), 0, length as usize)?;
-            }
-
-            count += 1;
+            min_length = min_length.min(v.len());
+            max_length = max_length.max(v.len());
         }
-
-        let v = if min_length == max_length {
-            Lucene54DocValuesFormat::BINARY_FIXED_UNCOMPRESSED
-        } else {
-            Lucene54DocValuesFormat::BINARY_VARIABLE_UNCOMPRESSED
-        };
-        self.meta.write_vint(v)?;
-        if missing_count == 0 {
-            self.meta
-                .write_long(Lucene54DocValuesFormat::ALL_LIVE as i64)?;
-        } else if missing_count == count {
-            self.meta
-                .write_long(Lucene54DocValuesFormat::ALL_MISSING as i64)?;
+        values.reset();
+        if min_length < max_length
+            && self.version >= Lucene54DocValuesFormat::VERSION_BINARY_COMPRESSED
+        {
+            self.add_compressed_binary(field_info, values)
         } else {
-            self.meta.write_long(self.data.file_pointer())?;
-            values.reset();
-            self.write_missing_bitset_bytes(values)?;
-        }
-
-        self.meta.write_vint(min_length)?;
-        self.meta.write_vint(max_length)?;
-        self.meta.write_vlong(count)?;
-        self.meta.write_long(start_fp)?;
-
-        // if minLength == maxLength, it's a fixed-length bytes, we are done (the addresses are
-        // implicit) otherwise, we need to record the length fields...
-
-        if min_length != max_length {
-            self.meta.write_long(self.data.file_pointer())?;
-            self.meta
-                .write_vint(Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT)?;
-
-            {
-                let mut writer = DirectMonotonicWriter::get_instance(
-                    &mut self.meta,
-                    &mut self.data,
-                    count + 1,
-                    Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT,
-                )?;
-                let mut addr = 0;
-                writer.add(addr)?;
-                values.reset();
-                for v in values {
-                    let v = v?;
-                    if !v.is_empty() {
-                        addr += v.len() as i64;
-                    }
-                    writer.add(addr)?;
-                }
-                writer.finish()?;
-            }
-            self.meta.write_long(self.data.file_pointer())?;
+            self.add_uncompressed_binary(field_info, values)
         }
-        Ok(())
     }
 
     fn add_sorted_field(
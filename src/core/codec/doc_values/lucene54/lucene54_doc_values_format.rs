@@ -28,7 +28,8 @@ impl Lucene54DocValuesFormat {
     const META_CODEC: &'static str = "Lucene54DocValuesMetadata";
     const META_EXTENSION: &'static str = "dvm";
     pub const VERSION_START: i32 = 0;
-    pub const VERSION_CURRENT: i32 = 0;
+    pub const VERSION_BINARY_COMPRESSED: i32 = 1;
+    pub const VERSION_CURRENT: i32 = Self::VERSION_BINARY_COMPRESSED;
 
     // indicates docvalues type
     pub const NUMERIC: u8 = 0;
@@ -71,6 +72,13 @@ impl Lucene54DocValuesFormat {
     pub const BINARY_VARIABLE_UNCOMPRESSED: i32 = 1;
     // Compressed binary with shared prefixes
     pub const BINARY_PREFIX_COMPRESSED: i32 = 2;
+    // LZ4 compressed binary in blocks of docs (variable length)
+    pub const BINARY_VARIABLE_COMPRESSED: i32 = 3;
+
+    // compress the variable length binary values in blocks of 32 docs
+    pub const BINARY_BLOCK_SHIFT: i32 = 5;
+    pub const BINARY_DOCS_PER_BLOCK: i32 = 1 << Self::BINARY_BLOCK_SHIFT;
+    pub const BINARY_BLOCK_MASK: i32 = Self::BINARY_DOCS_PER_BLOCK - 1;
 
     // Standard storage for sorted set values with 1 level of indirection:
     // docId -> address -> ord
@@ -125,3 +133,189 @@ impl DocValuesFormat for Lucene54DocValuesFormat {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::doc_values::{BinaryDocValues, BinaryDocValuesProvider, DocValuesConsumer};
+    use core::codec::field_infos::{FieldInfo, FieldInfos};
+    use core::codec::segment_infos::SegmentInfo;
+    use core::codec::tests::TestCodec;
+    use core::doc::{DocValuesType, IndexOptions};
+    use core::store::directory::FSDirectory;
+    use core::store::IOContext;
+    use core::util::{random_id, BitsMut, BytesRef, DocId, ReusableIterator, VERSION_LATEST};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::Arc;
+
+    struct BinaryValues<'a> {
+        values: &'a [Vec<u8>],
+        upto: usize,
+    }
+
+    impl<'a> Iterator for BinaryValues<'a> {
+        type Item = Result<BytesRef>;
+
+        fn next(&mut self) -> Option<Result<BytesRef>> {
+            let value = self.values.get(self.upto)?;
+            self.upto += 1;
+            Some(Ok(BytesRef::new(value)))
+        }
+    }
+
+    impl<'a> ReusableIterator for BinaryValues<'a> {
+        fn reset(&mut self) {
+            self.upto = 0;
+        }
+    }
+
+    struct RoundTrip {
+        values: Vec<Vec<u8>>,
+        docs_with_field: Vec<bool>,
+        data_length: u64,
+    }
+
+    // writes one binary value per doc, an empty value being a missing doc, in
+    // the layout of `version` and reads them back through the producer
+    fn round_trip(values: &[Vec<u8>], version: i32) -> RoundTrip {
+        let dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(dir.path()).unwrap());
+        let segment_info: SegmentInfo<FSDirectory, TestCodec> = SegmentInfo::new(
+            VERSION_LATEST,
+            "_0",
+            values.len() as i32,
+            Arc::clone(&directory),
+            false,
+            None,
+            HashMap::new(),
+            random_id(),
+            HashMap::new(),
+            None,
+        )
+        .unwrap();
+        let field_info = FieldInfo::new(
+            "body".to_string(),
+            0,
+            false,
+            false,
+            false,
+            IndexOptions::Null,
+            DocValuesType::Binary,
+            -1,
+            HashMap::new(),
+            0,
+            0,
+        )
+        .unwrap();
+        let field_infos = FieldInfos::new(vec![field_info.clone()]).unwrap();
+        let write_state = SegmentWriteState::new(
+            Arc::clone(&directory),
+            segment_info,
+            field_infos.clone(),
+            None,
+            IOContext::Default,
+            String::new(),
+        );
+        {
+            let mut consumer = Lucene54DocValuesConsumer::with_version(
+                &write_state,
+                Lucene54DocValuesFormat::DATA_CODEC,
+                Lucene54DocValuesFormat::DATA_EXTENSION,
+                Lucene54DocValuesFormat::META_CODEC,
+                Lucene54DocValuesFormat::META_EXTENSION,
+                version,
+            )
+            .unwrap();
+            consumer
+                .add_binary_field(&field_info, &mut BinaryValues { values, upto: 0 })
+                .unwrap();
+            // the footers are written when the consumer is dropped
+        }
+
+        let context = IOContext::READ;
+        let read_state = SegmentReadState::new(
+            Arc::clone(&directory),
+            &write_state.segment_info,
+            Arc::new(field_infos),
+            &context,
+            String::new(),
+        );
+        let producer = Lucene54DocValuesFormat
+            .fields_producer(&read_state)
+            .unwrap();
+        let mut binary = producer.get_binary(&field_info).unwrap().get().unwrap();
+        let mut docs_with_field = producer.get_docs_with_field(&field_info).unwrap();
+        let mut result = RoundTrip {
+            values: Vec::with_capacity(values.len()),
+            docs_with_field: Vec::with_capacity(values.len()),
+            data_length: fs::metadata(dir.path().join("_0.dvd")).unwrap().len(),
+        };
+        for doc in 0..values.len() {
+            result.values.push(binary.get(doc as DocId).unwrap());
+            result
+                .docs_with_field
+                .push(docs_with_field.get(doc).unwrap());
+        }
+        result
+    }
+
+    // 100 docs: three full blocks of 32 and a partial block of 4, with values
+    // over 32KB, blocks mixing missing and present docs and a block with no
+    // value at all
+    fn variable_values() -> Vec<Vec<u8>> {
+        (0..100)
+            .map(|doc: usize| match doc {
+                3 => b"0123456789".iter().cycle().take(40_000).cloned().collect(),
+                40 => (0..70_000).map(|i| (i % 251) as u8).collect(),
+                d if d % 5 == 0 || (64..96).contains(&d) => vec![],
+                d => format!("value-{}", d).repeat(d % 7 + 1).into_bytes(),
+            })
+            .collect()
+    }
+
+    fn assert_round_trip(values: &[Vec<u8>], result: &RoundTrip) {
+        assert_eq!(result.values.len(), values.len());
+        for (doc, value) in values.iter().enumerate() {
+            assert_eq!(&result.values[doc], value, "value of doc {}", doc);
+            assert_eq!(
+                result.docs_with_field[doc],
+                !value.is_empty(),
+                "docs with field of doc {}",
+                doc
+            );
+        }
+    }
+
+    #[test]
+    fn test_compressed_binary_round_trip() {
+        let values = variable_values();
+        let result = round_trip(&values, Lucene54DocValuesFormat::VERSION_CURRENT);
+        assert_round_trip(&values, &result);
+    }
+
+    #[test]
+    fn test_compressed_binary_partial_last_block() {
+        for &num_docs in &[11, 31, 33, 65] {
+            let values: Vec<Vec<u8>> = (0..num_docs)
+                .map(|doc| format!("doc{}", doc).into_bytes())
+                .collect();
+            let result = round_trip(&values, Lucene54DocValuesFormat::VERSION_CURRENT);
+            assert_round_trip(&values, &result);
+        }
+    }
+
+    #[test]
+    fn test_read_binary_written_at_version_start() {
+        let values = variable_values();
+        let start = round_trip(&values, Lucene54DocValuesFormat::VERSION_START);
+        assert_round_trip(&values, &start);
+
+        // the same values written in the current version go through the block
+        // compression, which the repetitive values shrink by a lot
+        let current = round_trip(&values, Lucene54DocValuesFormat::VERSION_CURRENT);
+        assert!(current.data_length < start.data_length / 2);
+    }
+}
@@ -509,6 +509,20 @@ impl Lucene54DocValuesProducer {
                 entry.addresses_meta = Some(Arc::clone(&addresses_meta));
                 entry.addresses_end_offset = meta.read_long()?;
             }
+            Lucene54DocValuesFormat::BINARY_VARIABLE_COMPRESSED => {
+                entry.addresses_offset = meta.read_long()?;
+                let block_shift = meta.read_vint()?;
+                let num_blocks = (entry.count
+                    + i64::from(Lucene54DocValuesFormat::BINARY_BLOCK_MASK))
+                    >> Lucene54DocValuesFormat::BINARY_BLOCK_SHIFT;
+                let addresses_meta = Arc::new(DirectMonotonicReader::load_meta(
+                    meta,
+                    num_blocks,
+                    block_shift,
+                )?);
+                entry.addresses_meta = Some(addresses_meta);
+                entry.addresses_end_offset = meta.read_long()?;
+            }
             _ => {
                 bail!(CorruptIndex(format!("unknown format: {}", entry.format)));
             }
@@ -879,6 +893,34 @@ impl Lucene54DocValuesProducer {
         Ok(variable_binary)
     }
 
+    fn get_block_compressed_binary(
+        &self,
+        _field: &FieldInfo,
+        bytes: &BinaryEntry,
+    ) -> Result<BlockCompressedBinaryDocValues<MixinMonotonicLongValues>> {
+        let addresses_length = bytes.addresses_end_offset - bytes.addresses_offset;
+        let meta_ref = bytes
+            .addresses_meta
+            .as_ref()
+            .ok_or_else(|| IllegalArgument("addresses_meta None???".to_owned()))?;
+        let meta = Arc::clone(meta_ref);
+
+        let addresses_data = self
+            .data
+            .random_access_slice(bytes.addresses_offset, addresses_length)?;
+        let addresses_data = Arc::from(addresses_data);
+        let addresses = DirectMonotonicReader::get_instance(meta.as_ref(), &addresses_data)?;
+        let data_length = bytes.addresses_offset - bytes.offset;
+        let data = self
+            .data
+            .slice("compressed-binary", bytes.offset, data_length)?;
+        Ok(BlockCompressedBinaryDocValues::new(
+            addresses,
+            data,
+            bytes.count,
+        ))
+    }
+
     fn get_interval_instance(
         &self,
         field: &FieldInfo,
@@ -1151,6 +1193,10 @@ impl DocValuesProducer for Lucene54DocValuesProducer {
                 let boxed = self.get_variable_binary(field, &bytes)?;
                 Ok(Arc::new(boxed))
             }
+            Lucene54DocValuesFormat::BINARY_VARIABLE_COMPRESSED => {
+                let boxed = self.get_block_compressed_binary(field, &bytes)?;
+                Ok(Arc::new(boxed))
+            }
 
             Lucene54DocValuesFormat::BINARY_PREFIX_COMPRESSED => {
                 let boxed = self.get_compressed_binary(field, &bytes)?;
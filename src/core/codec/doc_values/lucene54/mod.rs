@@ -32,8 +32,9 @@ mod doc_values_term_iterator;
 pub use self::doc_values_term_iterator::*;
 
 use core::codec::doc_values::{
-    BinaryDocValues, BinaryDocValuesProvider, NumericDocValues, NumericDocValuesProvider,
-    SortedDocValues, SortedNumericDocValues, SortedSetDocValues, NO_MORE_ORDS,
+    BinaryDocValues, BinaryDocValuesProvider, MultiSortedSetDocValues, NumericDocValues,
+    NumericDocValuesProvider, SortedDocValues, SortedNumericDocValues, SortedSetDocValues,
+    NO_MORE_ORDS,
 };
 use core::codec::posting_iterator::EmptyPostingIterator;
 use core::codec::terms::{EmptyTermIterator, OrdTermState, SeekStatus, TermIterator};
@@ -740,6 +741,7 @@ pub enum DocValuesTermIteratorEnum {
     Sorted(SortedDocValuesTermIterator<TailoredSortedDocValues>),
     SortedSetAddr(SortedSetDocValuesTermIterator<AddressedRandomAccessOrds>),
     SortedSetTable(SortedSetDocValuesTermIterator<TabledRandomAccessOrds>),
+    MultiSortedSet(SortedSetDocValuesTermIterator<MultiSortedSetDocValues>),
     Empty(EmptyTermIterator),
 }
 
@@ -759,6 +761,9 @@ impl DocValuesTermIterator {
     pub fn sorted_set_table(d: SortedSetDocValuesTermIterator<TabledRandomAccessOrds>) -> Self {
         DocValuesTermIterator(DocValuesTermIteratorEnum::SortedSetTable(d))
     }
+    pub fn multi_sorted_set(d: SortedSetDocValuesTermIterator<MultiSortedSetDocValues>) -> Self {
+        DocValuesTermIterator(DocValuesTermIteratorEnum::MultiSortedSet(d))
+    }
     pub fn empty() -> Self {
         DocValuesTermIterator(DocValuesTermIteratorEnum::Empty(EmptyTermIterator {}))
     }
@@ -773,6 +778,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.next(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.next(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.next(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.next(),
             DocValuesTermIteratorEnum::Empty(t) => t.next(),
         }
     }
@@ -783,6 +789,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_exact(text),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_exact(text),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_exact(text),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.seek_exact(text),
             DocValuesTermIteratorEnum::Empty(t) => t.seek_exact(text),
         }
     }
@@ -793,6 +800,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_ceil(text),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_ceil(text),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_ceil(text),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.seek_ceil(text),
             DocValuesTermIteratorEnum::Empty(t) => t.seek_ceil(text),
         }
     }
@@ -803,6 +811,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_exact_ord(ord),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_exact_ord(ord),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_exact_ord(ord),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.seek_exact_ord(ord),
             DocValuesTermIteratorEnum::Empty(t) => t.seek_exact_ord(ord),
         }
     }
@@ -813,6 +822,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_exact_state(text, state),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_exact_state(text, state),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_exact_state(text, state),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.seek_exact_state(text, state),
             DocValuesTermIteratorEnum::Empty(_) => unreachable!(),
         }
     }
@@ -823,6 +833,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.term(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.term(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.term(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.term(),
             DocValuesTermIteratorEnum::Empty(t) => t.term(),
         }
     }
@@ -833,6 +844,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.ord(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.ord(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.ord(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.ord(),
             DocValuesTermIteratorEnum::Empty(t) => t.ord(),
         }
     }
@@ -843,6 +855,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.doc_freq(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.doc_freq(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.doc_freq(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.doc_freq(),
             DocValuesTermIteratorEnum::Empty(t) => t.doc_freq(),
         }
     }
@@ -853,6 +866,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.total_term_freq(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.total_term_freq(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.total_term_freq(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.total_term_freq(),
             DocValuesTermIteratorEnum::Empty(t) => t.total_term_freq(),
         }
     }
@@ -863,6 +877,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.postings(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.postings(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.postings(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.postings(),
             DocValuesTermIteratorEnum::Empty(t) => t.postings(),
         }
     }
@@ -873,6 +888,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.postings_with_flags(flags),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.postings_with_flags(flags),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.postings_with_flags(flags),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.postings_with_flags(flags),
             DocValuesTermIteratorEnum::Empty(t) => t.postings_with_flags(flags),
         }
     }
@@ -883,6 +899,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.term_state(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.term_state(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.term_state(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.term_state(),
             DocValuesTermIteratorEnum::Empty(_) => unimplemented!(),
         }
     }
@@ -893,6 +910,7 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.is_empty(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.is_empty(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.is_empty(),
+            DocValuesTermIteratorEnum::MultiSortedSet(t) => t.is_empty(),
             DocValuesTermIteratorEnum::Empty(t) => t.is_empty(),
         }
     }
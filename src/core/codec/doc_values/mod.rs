@@ -29,6 +29,10 @@ mod doc_values_writer;
 
 pub use self::doc_values_writer::*;
 
+mod multi_doc_values;
+
+pub use self::multi_doc_values::*;
+
 use core::codec::doc_values::lucene54::DocValuesTermIterator;
 use core::util::DocId;
 
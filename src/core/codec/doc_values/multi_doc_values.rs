@@ -0,0 +1,303 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::lucene54::{DocValuesTermIterator, SortedSetDocValuesTermIterator};
+use core::codec::doc_values::{
+    EmptySortedSetDocValues, OrdinalMap, SortedSetDocValues, NO_MORE_ORDS,
+};
+use core::doc::DocValuesType;
+use core::index::reader::IndexReader;
+use core::util::packed::COMPACT;
+use core::util::{DocId, LongValues};
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::sync::Arc;
+
+/// Utility to read the doc values of all the segments of a reader as if
+/// it was a single segment.
+///
+/// Building the merged view is costly, it is meant for the top-level
+/// operations like faceting or joins, the segment doc values should be used
+/// everywhere else.
+pub struct MultiDocValues;
+
+impl MultiDocValues {
+    /// Returns the `SortedSetDocValues` of `field` over all the segments of
+    /// `reader`, with global ordinals and doc ids.
+    ///
+    /// The `OrdinalMap` is built on every call, use
+    /// `build_sorted_set_ordinal_map` and `MultiSortedSetDocValues::new` to
+    /// reuse it while the reader is not reopened.
+    pub fn get_sorted_set_values<R: IndexReader + ?Sized>(
+        reader: &R,
+        field: &str,
+    ) -> Result<Box<dyn SortedSetDocValues>> {
+        let (mut values, doc_starts) = Self::sorted_set_leaves(reader, field)?;
+        match values.len() {
+            0 => Ok(Box::new(EmptySortedSetDocValues)),
+            // the segment ordinals are already the global ones
+            1 => Ok(values.remove(0)),
+            _ => {
+                let mapping = Arc::new(Self::ordinal_map(&values)?);
+                Ok(Box::new(MultiSortedSetDocValues::new(
+                    values, doc_starts, mapping,
+                )?))
+            }
+        }
+    }
+
+    /// Build the `OrdinalMap` of the sorted set `field` over all the
+    /// segments of `reader`, segments without the field are mapped as having
+    /// no value.
+    ///
+    /// The map is only valid for this reader and should be rebuilt when the
+    /// reader is reopened.
+    pub fn build_sorted_set_ordinal_map<R: IndexReader + ?Sized>(
+        reader: &R,
+        field: &str,
+    ) -> Result<OrdinalMap> {
+        let (values, _) = Self::sorted_set_leaves(reader, field)?;
+        Self::ordinal_map(&values)
+    }
+
+    fn sorted_set_leaves<R: IndexReader + ?Sized>(
+        reader: &R,
+        field: &str,
+    ) -> Result<(Vec<Box<dyn SortedSetDocValues>>, Vec<DocId>)> {
+        let leaves = reader.leaves();
+        let mut values: Vec<Box<dyn SortedSetDocValues>> = Vec::with_capacity(leaves.len());
+        let mut doc_starts = Vec::with_capacity(leaves.len() + 1);
+        for leaf in &leaves {
+            match leaf.reader.field_info(field) {
+                Some(field_info) if field_info.doc_values_type != DocValuesType::Null => {
+                    values.push(leaf.reader.get_sorted_set_doc_values(field)?);
+                }
+                _ => values.push(Box::new(EmptySortedSetDocValues)),
+            }
+            doc_starts.push(leaf.doc_base);
+        }
+        doc_starts.push(reader.max_doc());
+        Ok((values, doc_starts))
+    }
+
+    fn ordinal_map(values: &[Box<dyn SortedSetDocValues>]) -> Result<OrdinalMap> {
+        let mut subs = Vec::with_capacity(values.len());
+        let mut weights = Vec::with_capacity(values.len());
+        for doc_values in values {
+            weights.push(doc_values.get_value_count());
+            subs.push(Some(doc_values.term_iterator()?));
+        }
+        OrdinalMap::build(subs, weights, COMPACT)
+    }
+}
+
+/// A `SortedSetDocValues` over several segments, the doc ids are the
+/// top-level ones and the ordinals the global ones of an `OrdinalMap`.
+pub struct MultiSortedSetDocValues {
+    values: Vec<Box<dyn SortedSetDocValues>>,
+    // the first doc of every segment, then the max doc
+    doc_starts: Vec<DocId>,
+    mapping: Arc<OrdinalMap>,
+    current_values: usize,
+    current_global_ords: Option<Arc<dyn LongValues>>,
+}
+
+impl MultiSortedSetDocValues {
+    /// Creates the view over the `values` of the segments starting at
+    /// `doc_starts`, which ends with the max doc of the reader, `mapping`
+    /// must have been built over the same `values`.
+    pub fn new(
+        values: Vec<Box<dyn SortedSetDocValues>>,
+        doc_starts: Vec<DocId>,
+        mapping: Arc<OrdinalMap>,
+    ) -> Result<MultiSortedSetDocValues> {
+        if doc_starts.len() != values.len() + 1 {
+            bail!(IllegalArgument(format!(
+                "expected {} doc starts for {} segments, got {}",
+                values.len() + 1,
+                values.len(),
+                doc_starts.len()
+            )));
+        }
+        Ok(MultiSortedSetDocValues {
+            values,
+            doc_starts,
+            mapping,
+            current_values: 0,
+            current_global_ords: None,
+        })
+    }
+
+    pub fn mapping(&self) -> &Arc<OrdinalMap> {
+        &self.mapping
+    }
+
+    /// The segment holding the top-level `doc`, the last one of the segments
+    /// starting at this doc as the empty segments start there too.
+    fn sub_index(&self, doc: DocId) -> usize {
+        let mut lo = 0;
+        let mut hi = self.values.len();
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.doc_starts[mid] <= doc {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo - 1
+    }
+}
+
+impl SortedSetDocValues for MultiSortedSetDocValues {
+    fn set_document(&mut self, doc: DocId) -> Result<()> {
+        if doc < 0 || doc >= self.doc_starts[self.values.len()] {
+            bail!(IllegalArgument(format!("doc {} is out of bounds", doc)));
+        }
+        let sub_index = self.sub_index(doc);
+        self.current_values = sub_index;
+        self.current_global_ords = Some(self.mapping.get_global_ords(sub_index));
+        self.values[sub_index].set_document(doc - self.doc_starts[sub_index])
+    }
+
+    fn next_ord(&mut self) -> Result<i64> {
+        let global_ords = match self.current_global_ords {
+            Some(ref global_ords) => global_ords,
+            None => return Ok(NO_MORE_ORDS),
+        };
+        let segment_ord = self.values[self.current_values].next_ord()?;
+        if segment_ord == NO_MORE_ORDS {
+            Ok(NO_MORE_ORDS)
+        } else {
+            global_ords.get64(segment_ord)
+        }
+    }
+
+    fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+        let sub_index = self.mapping.first_segment_number(ord) as usize;
+        let segment_ord = self.mapping.first_segment_ord(ord);
+        self.values[sub_index].lookup_ord(segment_ord)
+    }
+
+    fn get_value_count(&self) -> usize {
+        self.mapping.value_count() as usize
+    }
+
+    fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+        let ti = SortedSetDocValuesTermIterator::new(self);
+        Ok(DocValuesTermIterator::multi_sorted_set(ti))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::TermIterator;
+
+    struct MockSortedSetDocValues {
+        terms: Vec<&'static str>,
+        doc_ords: Vec<Vec<i64>>,
+        // the ords of the current doc, consumed from the end
+        ords: Vec<i64>,
+    }
+
+    impl SortedSetDocValues for MockSortedSetDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.ords = self.doc_ords[doc as usize].iter().rev().cloned().collect();
+            Ok(())
+        }
+
+        fn next_ord(&mut self) -> Result<i64> {
+            Ok(self.ords.pop().unwrap_or(NO_MORE_ORDS))
+        }
+
+        fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+            Ok(self.terms[ord as usize].as_bytes().to_vec())
+        }
+
+        fn get_value_count(&self) -> usize {
+            self.terms.len()
+        }
+
+        fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+            unreachable!()
+        }
+    }
+
+    fn doc_values(
+        terms: Vec<&'static str>,
+        doc_ords: Vec<Vec<i64>>,
+    ) -> Box<MockSortedSetDocValues> {
+        Box::new(MockSortedSetDocValues {
+            terms,
+            doc_ords,
+            ords: vec![],
+        })
+    }
+
+    fn ords(values: &mut dyn SortedSetDocValues, doc: DocId) -> Vec<i64> {
+        values.set_document(doc).unwrap();
+        let mut ords = vec![];
+        loop {
+            let ord = values.next_ord().unwrap();
+            if ord == NO_MORE_ORDS {
+                return ords;
+            }
+            ords.push(ord);
+        }
+    }
+
+    #[test]
+    fn test_multi_sorted_set_doc_values() {
+        let first = doc_values(vec!["b", "d"], vec![vec![0, 1], vec![1]]);
+        let empty = doc_values(vec![], vec![]);
+        let last = doc_values(vec!["a", "b", "c"], vec![vec![], vec![0, 2], vec![1]]);
+        let mapping = {
+            let subs = vec![
+                Some(SortedSetDocValuesTermIterator::new(&*first)),
+                Some(SortedSetDocValuesTermIterator::new(&*empty)),
+                Some(SortedSetDocValuesTermIterator::new(&*last)),
+            ];
+            OrdinalMap::build(subs, vec![2, 0, 3], COMPACT).unwrap()
+        };
+        let mapping = Arc::new(mapping);
+        let values: Vec<Box<dyn SortedSetDocValues>> = vec![first, empty, last];
+        assert!(MultiSortedSetDocValues::new(vec![], vec![0, 5], Arc::clone(&mapping)).is_err());
+        let mut multi = MultiSortedSetDocValues::new(values, vec![0, 2, 2, 5], mapping).unwrap();
+
+        // a, b, c, d
+        assert_eq!(multi.get_value_count(), 4);
+        assert_eq!(ords(&mut multi, 0), vec![1, 3]);
+        assert_eq!(ords(&mut multi, 1), vec![3]);
+        assert!(ords(&mut multi, 2).is_empty());
+        assert_eq!(ords(&mut multi, 3), vec![0, 2]);
+        assert_eq!(ords(&mut multi, 4), vec![1]);
+        assert!(multi.set_document(5).is_err());
+
+        for (ord, term) in ["a", "b", "c", "d"].iter().enumerate() {
+            assert_eq!(multi.lookup_ord(ord as i64).unwrap(), term.as_bytes());
+        }
+        assert_eq!(multi.lookup_term(b"c").unwrap(), 2);
+        assert_eq!(multi.lookup_term(b"bb").unwrap(), -3);
+
+        let mut terms = multi.term_iterator().unwrap();
+        let mut all = vec![];
+        while let Some(term) = terms.next().unwrap() {
+            all.push(term);
+        }
+        assert_eq!(
+            all,
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+        );
+    }
+}
@@ -14,7 +14,7 @@
 use core::codec::field_infos::{FieldInfo, FieldInfos};
 use core::codec::segment_infos::{segment_file_name, SegmentInfo};
 use core::codec::{codec_util, Codec};
-use core::doc::{DocValuesType, IndexOptions};
+use core::doc::{DocValuesType, IndexOptions, VectorSimilarity};
 use core::store::directory::Directory;
 use core::store::io::{BufferedChecksumIndexInput, ChecksumIndexInput, DataOutput, IndexInput};
 use core::store::IOContext;
@@ -48,7 +48,9 @@ const EXTENSION: &str = "fnm";
 // Codec header
 const CODEC_NAME: &str = "Lucene60FieldInfos";
 const FORMAT_START: i32 = 0;
-const FORMAT_CURRENT: i32 = FORMAT_START;
+// vector dimension and similarity of each field
+const FORMAT_VECTORS: i32 = 1;
+const FORMAT_CURRENT: i32 = FORMAT_VECTORS;
 
 // Field flags
 const STORE_TERM_VECTOR: u8 = 0x1;
@@ -61,7 +63,7 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
     suffix: &str,
 ) -> Result<Vec<FieldInfo>> {
     let mut infos: Vec<FieldInfo> = Vec::new();
-    codec_util::check_index_header(
+    let version = codec_util::check_index_header(
         input,
         CODEC_NAME,
         FORMAT_START,
@@ -94,8 +96,13 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
         } else {
             0
         };
+        let vector_dimension = if version >= FORMAT_VECTORS {
+            input.read_vint()?
+        } else {
+            0
+        };
 
-        let info = FieldInfo::new(
+        let mut info = FieldInfo::new(
             name,
             field_number as u32,
             store_term_vector,
@@ -108,6 +115,10 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
             point_dimension_count as u32,
             point_num_bytes as u32,
         )?;
+        if vector_dimension != 0 {
+            let similarity = read_vector_similarity(input)?;
+            info.set_vector_attributes(vector_dimension as u32, similarity)?;
+        }
         infos.push(info);
     }
     Ok(infos)
@@ -178,6 +189,27 @@ fn doc_values_byte(dv_type: DocValuesType) -> u8 {
     }
 }
 
+fn read_vector_similarity<T: IndexInput + ?Sized>(input: &mut T) -> Result<VectorSimilarity> {
+    let byte = input.read_byte()?;
+    Ok(match byte {
+        0 => VectorSimilarity::Euclidean,
+        1 => VectorSimilarity::DotProduct,
+        2 => VectorSimilarity::Cosine,
+        _ => bail!(CorruptIndex(format!(
+            "invalid VectorSimilarity byte: {}",
+            byte
+        ))),
+    })
+}
+
+fn vector_similarity_byte(similarity: VectorSimilarity) -> u8 {
+    match similarity {
+        VectorSimilarity::Euclidean => 0,
+        VectorSimilarity::DotProduct => 1,
+        VectorSimilarity::Cosine => 2,
+    }
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct Lucene60FieldInfosFormat;
 
@@ -214,10 +246,17 @@ impl FieldInfosFormat for Lucene60FieldInfosFormat {
     ) -> Result<()> {
         let file_name = segment_file_name(&segment_info.name, segment_suffix, EXTENSION);
         let mut output = directory.create_output(&file_name, context)?;
+        // segments without vectors keep the original format so that they
+        // stay readable by older versions
+        let version = if infos.has_vector_values {
+            FORMAT_VECTORS
+        } else {
+            FORMAT_START
+        };
         codec_util::write_index_header(
             &mut output,
             CODEC_NAME,
-            FORMAT_CURRENT,
+            version,
             segment_info.get_id(),
             segment_suffix,
         )?;
@@ -253,6 +292,12 @@ impl FieldInfosFormat for Lucene60FieldInfosFormat {
             if point_dimension_count > 0 {
                 output.write_vint(fi.point_num_bytes as i32)?;
             }
+            if version >= FORMAT_VECTORS {
+                output.write_vint(fi.vector_dimension as i32)?;
+                if fi.vector_dimension > 0 {
+                    output.write_byte(vector_similarity_byte(fi.vector_similarity))?;
+                }
+            }
         }
 
         codec_util::write_footer(&mut output)
@@ -32,7 +32,7 @@ use serde::{Serialize, Serializer};
 // use core::attribute::{OffsetAttribute, PayloadAttribute, PositionIncrementAttribute};
 use core::codec::points::{MAX_DIMENSIONS, MAX_NUM_BYTES};
 use core::codec::postings::{PER_FIELD_POSTING_FORMAT_KEY, PER_FIELD_POSTING_SUFFIX_KEY};
-use core::doc::{DocValuesType, IndexOptions};
+use core::doc::{DocValuesType, IndexOptions, VectorSimilarity, VECTOR_MAX_DIMENSIONS};
 
 /// Access to the Field Info file that describes document fields and whether or
 /// not they are indexed. Each segment has a separate Field Info file. Objects
@@ -52,6 +52,8 @@ pub struct FieldInfo {
     pub dv_gen: i64,
     pub point_dimension_count: u32,
     pub point_num_bytes: u32,
+    pub vector_dimension: u32,
+    pub vector_similarity: VectorSimilarity,
 }
 
 impl Serialize for FieldInfo {
@@ -59,7 +61,7 @@ impl Serialize for FieldInfo {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("FieldInfo", 11)?;
+        let mut s = serializer.serialize_struct("FieldInfo", 13)?;
         s.serialize_field("name", &self.name)?;
         s.serialize_field("number", &self.number)?;
         s.serialize_field("doc_values_type", &self.doc_values_type)?;
@@ -71,6 +73,8 @@ impl Serialize for FieldInfo {
         s.serialize_field("dv_gen", &self.dv_gen)?;
         s.serialize_field("point_dimension_count", &self.point_dimension_count)?;
         s.serialize_field("point_num_bytes", &self.point_num_bytes)?;
+        s.serialize_field("vector_dimension", &self.vector_dimension)?;
+        s.serialize_field("vector_similarity", &self.vector_similarity)?;
         s.end()
     }
 }
@@ -102,6 +106,8 @@ impl FieldInfo {
             dv_gen,
             point_dimension_count,
             point_num_bytes,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
         };
 
         info.check_consistency()?;
@@ -235,6 +241,32 @@ impl FieldInfo {
         Ok(())
     }
 
+    pub fn set_vector_attributes(
+        &mut self,
+        vector_dimension: u32,
+        vector_similarity: VectorSimilarity,
+    ) -> Result<()> {
+        if vector_dimension > VECTOR_MAX_DIMENSIONS {
+            bail!(IllegalArgument(format!(
+                "vector dimension of field '{}' must be <= {}",
+                self.name, VECTOR_MAX_DIMENSIONS
+            )));
+        }
+        if self.vector_dimension == 0 {
+            self.vector_dimension = vector_dimension;
+            self.vector_similarity = vector_similarity;
+        } else if vector_dimension != 0
+            && (self.vector_dimension != vector_dimension
+                || self.vector_similarity != vector_similarity)
+        {
+            bail!(IllegalArgument(format!(
+                "cannot change field '{}' vector dimension or vector similarity",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
     pub fn has_norms(&self) -> bool {
         match self.index_options {
             IndexOptions::Null => false,
@@ -383,6 +415,7 @@ pub struct FieldInfos {
     pub has_norms: bool,
     pub has_doc_values: bool,
     pub has_point_values: bool,
+    pub has_vector_values: bool,
 
     pub by_number: BTreeMap<u32, FieldInfoRef>,
     pub by_name: HashMap<String, FieldInfoRef>,
@@ -393,7 +426,7 @@ impl Serialize for FieldInfos {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("FieldInfos", 9)?;
+        let mut s = serializer.serialize_struct("FieldInfos", 10)?;
         s.serialize_field("has_freq", &self.has_freq)?;
         s.serialize_field("has_prox", &self.has_prox)?;
         s.serialize_field("has_payloads", &self.has_payloads)?;
@@ -402,6 +435,7 @@ impl Serialize for FieldInfos {
         s.serialize_field("has_norms", &self.has_norms)?;
         s.serialize_field("has_doc_values", &self.has_doc_values)?;
         s.serialize_field("has_point_values", &self.has_point_values)?;
+        s.serialize_field("has_vector_values", &self.has_vector_values)?;
 
         let fields: HashMap<&String, &FieldInfo> = self
             .by_name
@@ -433,6 +467,7 @@ impl FieldInfos {
         let mut has_norms = false;
         let mut has_doc_values = false;
         let mut has_point_values = false;
+        let mut has_vector_values = false;
 
         let mut by_number: BTreeMap<u32, FieldInfoRef> = BTreeMap::new();
         let mut by_name: HashMap<String, FieldInfoRef> = HashMap::new();
@@ -441,11 +476,13 @@ impl FieldInfos {
         let mut infos = infos;
         for info in &mut infos {
             if info.index_options != IndexOptions::Null {
-                // force put attr...
-                info.put_attribute(
-                    PER_FIELD_POSTING_FORMAT_KEY.to_string(),
-                    "Lucene50".to_string(),
-                );
+                // force put attr, keeping the format chosen by the indexed field
+                if info.attribute(PER_FIELD_POSTING_FORMAT_KEY).is_none() {
+                    info.put_attribute(
+                        PER_FIELD_POSTING_FORMAT_KEY.to_string(),
+                        "Lucene50".to_string(),
+                    );
+                }
                 info.put_attribute(PER_FIELD_POSTING_SUFFIX_KEY.to_string(), "0".to_string());
             }
         }
@@ -466,6 +503,7 @@ impl FieldInfos {
                 has_doc_values |= !info.doc_values_type.null();
                 has_payloads |= info.has_store_payloads;
                 has_point_values |= info.point_dimension_count != 0;
+                has_vector_values |= info.vector_dimension != 0;
             }
 
             if let Some(previous) = by_number.insert(number, info.clone()) {
@@ -493,6 +531,7 @@ impl FieldInfos {
             has_norms,
             has_doc_values,
             has_point_values,
+            has_vector_values,
             by_number,
             by_name,
         })
@@ -592,7 +631,21 @@ impl<T: AsRef<FieldNumbers>> FieldInfosBuilder<T> {
             fi.doc_values_type,
             fi.point_dimension_count,
             fi.point_num_bytes,
-        )
+        )?;
+        if let Some(format) = fi.attribute(PER_FIELD_POSTING_FORMAT_KEY) {
+            self.by_name[&fi.name].put_attribute(PER_FIELD_POSTING_FORMAT_KEY.to_string(), format);
+        }
+        if fi.vector_dimension > 0 {
+            let field_info = self.by_name.get_mut(&fi.name).unwrap();
+            self.global_field_numbers.as_ref().set_vector_attributes(
+                field_info.number,
+                &fi.name,
+                fi.vector_dimension,
+                fi.vector_similarity,
+            )?;
+            field_info.set_vector_attributes(fi.vector_dimension, fi.vector_similarity)?;
+        }
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -732,6 +785,18 @@ impl FieldNumbers {
             .set_dimensions(number, name, dimension_count, num_bytes)
     }
 
+    pub fn set_vector_attributes(
+        &self,
+        number: u32,
+        name: &str,
+        vector_dimension: u32,
+        vector_similarity: VectorSimilarity,
+    ) -> Result<()> {
+        self.inner
+            .lock()?
+            .set_vector_attributes(number, name, vector_dimension, vector_similarity)
+    }
+
     fn verify_consistent(&self, number: u32, name: &str, dv_type: DocValuesType) -> Result<()> {
         self.inner.lock()?.verify_consistent(number, name, dv_type)
     }
@@ -760,6 +825,7 @@ struct FieldNumbersInner {
     // sessions:
     doc_values_type: HashMap<String, DocValuesType>,
     dimensions: HashMap<String, FieldDimensions>,
+    vectors: HashMap<String, (u32, VectorSimilarity)>,
     // TODO: we should similarly catch an attempt to turn
     // norms back on after they were already ommitted; today
     // we silently discard the norm but this is badly trappy
@@ -773,6 +839,7 @@ impl FieldNumbersInner {
             name_to_number: HashMap::new(),
             doc_values_type: HashMap::new(),
             dimensions: HashMap::new(),
+            vectors: HashMap::new(),
             lowest_unassigned_field_number: 0,
         }
     }
@@ -940,6 +1007,7 @@ impl FieldNumbersInner {
         self.name_to_number.clear();
         self.doc_values_type.clear();
         self.dimensions.clear();
+        self.vectors.clear();
     }
 
     pub fn set_doc_values_type(
@@ -985,6 +1053,51 @@ impl FieldNumbersInner {
         );
         Ok(())
     }
+
+    pub fn set_vector_attributes(
+        &mut self,
+        number: u32,
+        name: &str,
+        vector_dimension: u32,
+        vector_similarity: VectorSimilarity,
+    ) -> Result<()> {
+        if vector_dimension > VECTOR_MAX_DIMENSIONS {
+            bail!(IllegalArgument(format!(
+                "vector dimension must be <= {}",
+                VECTOR_MAX_DIMENSIONS
+            )));
+        }
+        if self.number_to_name.contains_key(&number) && self.number_to_name[&number] != name {
+            bail!(IllegalArgument(format!(
+                "field number {} is already mapped to field name '{}' not '{}'",
+                number, self.number_to_name[&number], name
+            )));
+        }
+        match self.vectors.entry(name.to_string()) {
+            Entry::Occupied(entry) => {
+                if entry.get().0 != vector_dimension {
+                    bail!(IllegalArgument(format!(
+                        "cannot change vector dimension from {} to {} for field '{}'",
+                        entry.get().0,
+                        vector_dimension,
+                        name
+                    )));
+                }
+                if entry.get().1 != vector_similarity {
+                    bail!(IllegalArgument(format!(
+                        "cannot change vector similarity from {:?} to {:?} for field '{}'",
+                        entry.get().1,
+                        vector_similarity,
+                        name
+                    )));
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((vector_dimension, vector_similarity));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl AsRef<FieldNumbers> for FieldNumbers {
@@ -19,6 +19,7 @@ pub mod postings;
 pub mod segment_infos;
 pub mod stored_fields;
 pub mod term_vectors;
+pub mod vectors;
 
 mod codec_util;
 
@@ -48,6 +49,10 @@ pub(crate) mod terms;
 
 pub use self::terms::*;
 
+mod automaton_terms;
+
+pub use self::automaton_terms::*;
+
 mod fields;
 
 pub use self::fields::*;
@@ -73,6 +78,7 @@ use core::codec::stored_fields::{
 use core::codec::term_vectors::{
     term_vectors_format, CompressingTermVectorsFormat, TermVectorsFormat, TermVectorsReader,
 };
+use core::codec::vectors::{HnswVectorsFormat, VectorsFormat};
 
 use core::codec::segment_infos::{Lucene62SegmentInfoFormat, SegmentInfoFormat};
 use error::ErrorKind::{CorruptIndex, IllegalArgument};
@@ -102,6 +108,7 @@ pub trait Codec: TryFrom<String, Error = Error> + 'static {
     type LiveDocFmt: LiveDocsFormat;
     type CompoundFmt: CompoundFormat;
     type PointFmt: PointsFormat;
+    type VectorFmt: VectorsFormat;
 
     fn name(&self) -> &str;
     fn postings_format(&self) -> Self::PostingFmt;
@@ -114,6 +121,7 @@ pub trait Codec: TryFrom<String, Error = Error> + 'static {
     fn live_docs_format(&self) -> Self::LiveDocFmt;
     fn compound_format(&self) -> Self::CompoundFmt;
     fn points_format(&self) -> Self::PointFmt;
+    fn vectors_format(&self) -> Self::VectorFmt;
 }
 
 pub type CodecFieldsProducer<C> = <<C as Codec>::PostingFmt as PostingsFormat>::FieldsProducer;
@@ -145,6 +153,7 @@ impl Codec for CodecEnum {
     type LiveDocFmt = Lucene50LiveDocsFormat;
     type CompoundFmt = Lucene50CompoundFormat;
     type PointFmt = Lucene60PointsFormat;
+    type VectorFmt = HnswVectorsFormat;
 
     fn name(&self) -> &str {
         match self {
@@ -203,6 +212,13 @@ impl Codec for CodecEnum {
             CodecEnum::Lucene62(c) => c.points_format(),
         }
     }
+
+    /// Encodes/decodes dense vectors and their HNSW graphs
+    fn vectors_format(&self) -> Self::VectorFmt {
+        match self {
+            CodecEnum::Lucene62(c) => c.vectors_format(),
+        }
+    }
 }
 
 impl TryFrom<String> for CodecEnum {
@@ -239,6 +255,7 @@ pub struct Lucene62Codec {
     stored_fields_format: Lucene50StoredFieldsFormat,
     norms_format: Lucene53NormsFormat,
     points_format: Lucene60PointsFormat,
+    vectors_format: HnswVectorsFormat,
 }
 
 impl Default for Lucene62Codec {
@@ -256,6 +273,7 @@ impl Default for Lucene62Codec {
             doc_values_format: PerFieldDocValuesFormat::default(),
             norms_format: Lucene53NormsFormat::default(),
             points_format: Lucene60PointsFormat {},
+            vectors_format: HnswVectorsFormat {},
         }
     }
 }
@@ -272,6 +290,7 @@ impl Codec for Lucene62Codec {
     type LiveDocFmt = Lucene50LiveDocsFormat;
     type CompoundFmt = Lucene50CompoundFormat;
     type PointFmt = Lucene60PointsFormat;
+    type VectorFmt = HnswVectorsFormat;
 
     fn name(&self) -> &str {
         "Lucene62"
@@ -316,6 +335,10 @@ impl Codec for Lucene62Codec {
     fn points_format(&self) -> Self::PointFmt {
         self.points_format
     }
+
+    fn vectors_format(&self) -> Self::VectorFmt {
+        self.vectors_format
+    }
 }
 
 impl TryFrom<String> for Lucene62Codec {
@@ -336,6 +359,8 @@ impl TryFrom<String> for Lucene62Codec {
 #[cfg(test)]
 pub mod tests {
     use core::codec::{CodecEnum, Lucene62Codec};
+    use core::codec::{EmptyPostingIterator, SeekStatus, TermIterator, Terms};
+    use error::Result;
 
     pub type TestCodec = CodecEnum;
 
@@ -344,4 +369,134 @@ pub mod tests {
             CodecEnum::Lucene62(Lucene62Codec::default())
         }
     }
+
+    /// A `TermIterator` over a sorted list of terms, without postings.
+    pub struct MockTermIterator {
+        terms: Vec<Vec<u8>>,
+        // the index of the current term, `terms.len()` once exhausted
+        pos: Option<usize>,
+    }
+
+    impl MockTermIterator {
+        pub fn new(terms: &[&str]) -> MockTermIterator {
+            let mut terms: Vec<Vec<u8>> = terms.iter().map(|t| t.as_bytes().to_vec()).collect();
+            terms.sort();
+            terms.dedup();
+            MockTermIterator { terms, pos: None }
+        }
+    }
+
+    impl TermIterator for MockTermIterator {
+        type Postings = EmptyPostingIterator;
+        type TermState = ();
+
+        fn next(&mut self) -> Result<Option<Vec<u8>>> {
+            let pos = self.pos.map_or(0, |p| (p + 1).min(self.terms.len()));
+            self.pos = Some(pos);
+            Ok(self.terms.get(pos).cloned())
+        }
+
+        fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+            let pos = match self.terms.binary_search_by(|t| t.as_slice().cmp(text)) {
+                Ok(pos) => {
+                    self.pos = Some(pos);
+                    return Ok(SeekStatus::Found);
+                }
+                Err(pos) => pos,
+            };
+            self.pos = Some(pos);
+            if pos == self.terms.len() {
+                Ok(SeekStatus::End)
+            } else {
+                Ok(SeekStatus::NotFound)
+            }
+        }
+
+        fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
+            self.pos = Some(ord as usize);
+            Ok(())
+        }
+
+        fn term(&self) -> Result<&[u8]> {
+            Ok(&self.terms[self.pos.unwrap()])
+        }
+
+        fn ord(&self) -> Result<i64> {
+            Ok(self.pos.unwrap() as i64)
+        }
+
+        fn doc_freq(&mut self) -> Result<i32> {
+            Ok(1)
+        }
+
+        fn total_term_freq(&mut self) -> Result<i64> {
+            Ok(1)
+        }
+
+        fn postings_with_flags(&mut self, _flags: u16) -> Result<Self::Postings> {
+            Ok(EmptyPostingIterator::default())
+        }
+    }
+
+    /// The `Terms` of a field over a list of terms, iterated by `MockTermIterator`.
+    pub struct MockTerms {
+        terms: Vec<String>,
+    }
+
+    impl MockTerms {
+        pub fn new(terms: &[&str]) -> MockTerms {
+            let terms = terms.iter().map(|t| t.to_string()).collect();
+            MockTerms { terms }
+        }
+    }
+
+    impl Terms for MockTerms {
+        type Iterator = MockTermIterator;
+
+        fn iterator(&self) -> Result<MockTermIterator> {
+            let terms: Vec<&str> = self.terms.iter().map(String::as_str).collect();
+            Ok(MockTermIterator::new(&terms))
+        }
+
+        fn size(&self) -> Result<i64> {
+            Ok(self.terms.len() as i64)
+        }
+
+        fn sum_total_term_freq(&self) -> Result<i64> {
+            Ok(self.terms.len() as i64)
+        }
+
+        fn sum_doc_freq(&self) -> Result<i64> {
+            Ok(self.terms.len() as i64)
+        }
+
+        fn doc_count(&self) -> Result<i32> {
+            Ok(1)
+        }
+
+        fn has_freqs(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn has_offsets(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn has_positions(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn has_payloads(&self) -> Result<bool> {
+            Ok(false)
+        }
+    }
+
+    /// Drains `iter`, returning its terms as strings.
+    pub fn collect_terms<T: TermIterator>(mut iter: T) -> Vec<String> {
+        let mut terms = vec![];
+        while let Some(term) = iter.next().unwrap() {
+            terms.push(String::from_utf8(term).unwrap());
+        }
+        terms
+    }
 }
@@ -28,10 +28,11 @@ use core::codec::postings::{
 };
 use core::codec::segment_infos::{segment_file_name, SegmentReadState};
 use core::codec::{codec_util, Codec};
-use core::codec::{Fields, SeekStatus, TermIterator, Terms};
+use core::codec::{AutomatonTermIterator, Fields, SeekStatus, TermIterator, Terms};
 use core::doc::IndexOptions;
 use core::store::directory::Directory;
 use core::store::io::{ByteArrayDataInput, DataInput, IndexInput};
+use core::util::automaton::{AutomatonType, ByteRunAutomaton, CompiledAutomaton};
 use core::util::fst::{
     Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, DirectionalBytesReader,
     FSTBytesReader, OutputFactory, FST,
@@ -499,6 +500,33 @@ impl<'a> Terms for FieldReader {
         ))
     }
 
+    /// Walks the blocks of the terms dictionary together with the automaton,
+    /// so sub-blocks whose prefix is rejected are never loaded and each term
+    /// only has its suffix run through the automaton.
+    ///
+    /// Only `AutomatonType::Normal` is walked natively, the other types are
+    /// cheap enough with the seeking `AutomatonTermIterator`.
+    fn intersect(
+        &self,
+        compiled: &CompiledAutomaton,
+    ) -> Result<AutomatonTermIterator<Self::Iterator>> {
+        if compiled.automaton_type != AutomatonType::Normal {
+            return Ok(AutomatonTermIterator::new(self.iterator()?, compiled));
+        }
+        let run_automaton = match compiled.run_automaton {
+            Some(ref a) => Arc::clone(a),
+            None => bail!(IllegalState(
+                "normal compiled automaton without run automaton".into()
+            )),
+        };
+        let mut iter = self.iterator()?;
+        iter.intersect = Some(IntersectState {
+            run_automaton,
+            frame_states: Vec::new(),
+        });
+        Ok(AutomatonTermIterator::intersected(iter, compiled))
+    }
+
     fn size(&self) -> Result<i64> {
         Ok(self.num_terms)
     }
@@ -906,6 +934,27 @@ impl ToString for Stats {
 /// This implementation skips any auto-prefix terms it encounters.
 pub struct SegmentTermIterator {
     iter: Box<SegmentTermIteratorInner>,
+    // set when created by `FieldReader::intersect`, in which case only the
+    // accepted terms are returned and the iterator cannot seek
+    intersect: Option<IntersectState>,
+}
+
+/// Automaton state of a `SegmentTermIterator` created by `FieldReader::intersect`.
+struct IntersectState {
+    run_automaton: Arc<ByteRunAutomaton>,
+    // automaton state after the prefix of each frame, indexed by frame ord
+    frame_states: Vec<usize>,
+}
+
+impl SegmentTermIterator {
+    fn check_seekable(&self) -> Result<()> {
+        if self.intersect.is_some() {
+            bail!(UnsupportedOperation(Cow::Borrowed(
+                "intersect iterator cannot seek"
+            )));
+        }
+        Ok(())
+    }
 }
 
 impl SegmentTermIterator {
@@ -921,7 +970,10 @@ impl SegmentTermIterator {
             postings_reader,
             field_info,
         ));
-        Self { iter }
+        Self {
+            iter,
+            intersect: None,
+        }
     }
 }
 
@@ -931,26 +983,33 @@ impl TermIterator for SegmentTermIterator {
 
     #[inline]
     fn next(&mut self) -> Result<Option<Vec<u8>>> {
-        self.iter.next()
+        match self.intersect {
+            Some(ref mut intersect) => self.iter.next_intersect(intersect),
+            None => self.iter.next(),
+        }
     }
 
     #[inline]
     fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        self.check_seekable()?;
         self.iter.seek_exact(text)
     }
 
     #[inline]
     fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+        self.check_seekable()?;
         self.iter.seek_ceil(text)
     }
 
     #[inline]
     fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
+        self.check_seekable()?;
         self.iter.seek_exact_ord(ord)
     }
 
     #[inline]
     fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
+        self.check_seekable()?;
         self.iter.seek_exact_state(text, state)
     }
 
@@ -1264,6 +1323,83 @@ impl SegmentTermIteratorInner {
         self.term.resize(len, 0);
         self.term_len = len;
     }
+
+    // Like `next`, but only returns terms accepted by the automaton. Each frame
+    // remembers the automaton state after its prefix, so only the suffix of an
+    // entry is run, and a sub-block whose prefix is already rejected is skipped
+    // without being loaded.
+    fn next_intersect(&mut self, intersect: &mut IntersectState) -> Result<Option<Vec<u8>>> {
+        if !self.frame_inited {
+            self.init();
+        }
+        if self.input.is_none() {
+            let arc = {
+                if let Some(ref fst_reader) = self.field_reader().index {
+                    Some(fst_reader.root_arc())
+                } else {
+                    None
+                }
+            };
+            let root_code = self.field_reader().root_code().to_vec();
+            self.current_frame_ord = self.push_frame_by_data(arc, &root_code, 0)?;
+            self.stack[self.current_frame_ord].load_block()?;
+            intersect.frame_states.clear();
+            intersect.frame_states.resize(self.current_frame_ord + 1, 0);
+        }
+        if self.eof {
+            return Ok(None);
+        }
+
+        loop {
+            // Pop finished blocks:
+            let mut current_idx = self.current_frame_ord;
+            while self.stack[current_idx].next_ent == self.stack[current_idx].ent_count {
+                if !self.stack[current_idx].is_last_in_floor {
+                    // Floor blocks share the prefix, and so the automaton state
+                    self.stack[current_idx].load_next_floor_block()?;
+                    break;
+                }
+                if current_idx == 1 {
+                    self.eof = true;
+                    self.term.clear();
+                    self.term_len = 0;
+                    self.term_exists = false;
+                    return Ok(None);
+                }
+                self.current_frame_ord -= 1;
+                current_idx -= 1;
+            }
+
+            let is_sub_block = self.stack[current_idx].next()?;
+            let mut state = Some(intersect.frame_states[current_idx]);
+            for &b in &self.term[self.stack[current_idx].prefix..self.term_len] {
+                state = intersect.run_automaton.step(state.unwrap(), b);
+                if state.is_none() {
+                    break;
+                }
+            }
+
+            match state {
+                // rejected: skip this term, or the whole sub-block
+                None => {}
+                Some(state) if is_sub_block => {
+                    let fp = self.stack[current_idx].last_sub_fp;
+                    let term_len = self.term_len;
+                    self.current_frame_ord = self.push_frame_by_fp(None, fp, term_len)?;
+                    self.stack[self.current_frame_ord].load_block()?;
+                    if intersect.frame_states.len() <= self.current_frame_ord {
+                        intersect.frame_states.resize(self.current_frame_ord + 1, 0);
+                    }
+                    intersect.frame_states[self.current_frame_ord] = state;
+                }
+                Some(state) => {
+                    if intersect.run_automaton.is_accept(state) {
+                        return Ok(Some(self.term().to_vec()));
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl TermIterator for SegmentTermIteratorInner {
@@ -1807,3 +1943,112 @@ impl TermIterator for SegmentTermIteratorInner {
         Ok(self.stack[self.current_frame_ord].state.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::tests::collect_terms;
+    use core::doc::StringField;
+    use core::index::reader::{IndexReader, LeafReader};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::store::directory::FSDirectory;
+    use core::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+    use core::util::automaton::{Automata, Automaton, Operations, RegExp};
+
+    // enough terms sharing prefixes for the blocks to be split into floor blocks
+    // and nested sub-blocks, some terms being the prefix of others
+    fn index_terms() -> Vec<String> {
+        let chars = b"abcdefghijkl";
+        let mut terms = vec!["a".to_string(), "ab".to_string(), "abc".to_string()];
+        for &c1 in chars.iter() {
+            for &c2 in chars.iter() {
+                for &c3 in chars.iter() {
+                    terms.push(format!("a{}{}{}", c1 as char, c2 as char, c3 as char));
+                }
+            }
+        }
+        for i in 0..500 {
+            terms.push(format!("b{}", i));
+        }
+        terms.sort();
+        terms.dedup();
+        terms
+    }
+
+    fn prefix(prefix: &str) -> Automaton {
+        Operations::concatenate(&[Automata::make_string(prefix), Automata::make_any_string()])
+    }
+
+    #[test]
+    fn test_intersect() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let config = Arc::new(IndexWriterConfig::default());
+        let writer = IndexWriter::new(directory, config).unwrap();
+        let terms = index_terms();
+        for term in &terms {
+            writer
+                .add_document(vec![StringField::new("body", term, false)])
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let leaves = reader.leaves();
+        assert_eq!(leaves.len(), 1);
+        let field_terms = leaves[0].reader.terms("body").unwrap().unwrap();
+
+        let automata = vec![
+            ("prefix", prefix("abc")),
+            ("floor block prefix", prefix("a")),
+            ("term prefix", prefix("b1")),
+            (
+                "suffix",
+                Operations::concatenate(&[
+                    Automata::make_any_string(),
+                    Automata::make_string("lk"),
+                ]),
+            ),
+            (
+                "regexp",
+                RegExp::new("a[b-d]*l.")
+                    .unwrap()
+                    .to_automaton(DEFAULT_MAX_DETERMINIZED_STATES)
+                    .unwrap(),
+            ),
+            ("no match", Automata::make_string("zzz")),
+            ("empty string", Automata::make_empty_string()),
+            ("match all", Automata::make_any_string()),
+        ];
+        for (name, automaton) in &automata {
+            // not simplified, so that even the trivial automata are walked natively
+            let compiled =
+                CompiledAutomaton::new(automaton, false, false, DEFAULT_MAX_DETERMINIZED_STATES)
+                    .unwrap();
+            assert_eq!(compiled.automaton_type, AutomatonType::Normal, "{}", name);
+
+            let expected: Vec<String> = terms
+                .iter()
+                .filter(|t| compiled.run(t.as_bytes()))
+                .cloned()
+                .collect();
+            let seeking = AutomatonTermIterator::new(field_terms.iterator().unwrap(), &compiled);
+            assert_eq!(collect_terms(seeking), expected, "{}", name);
+            let intersected = field_terms.intersect(&compiled).unwrap();
+            assert_eq!(collect_terms(intersected), expected, "{}", name);
+        }
+
+        // the empty language isn't even walked
+        let compiled = CompiledAutomaton::new(
+            &Automata::make_empty(),
+            false,
+            false,
+            DEFAULT_MAX_DETERMINIZED_STATES,
+        )
+        .unwrap();
+        assert_eq!(compiled.automaton_type, AutomatonType::None);
+        assert!(collect_terms(field_terms.intersect(&compiled).unwrap()).is_empty());
+    }
+}
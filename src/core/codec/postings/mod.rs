@@ -60,6 +60,10 @@ use core::index::merge::MergeState;
 use core::index::reader::ReaderSlice;
 use core::store::directory::Directory;
 use core::store::io::{DataOutput, IndexOutput};
+use core::suggest::document::{
+    CompletionFieldsConsumer, CompletionFieldsProducer, CompletionPostingsFormat, CompletionTerms,
+    COMPLETION_POSTINGS_FORMAT_NAME,
+};
 use core::util::over_size;
 use core::util::FixedBitSet;
 
@@ -107,6 +111,7 @@ pub trait PostingsFormat {
 /// composite `PostingsFormat` use for `CodecEnum`
 pub enum PostingsFormatEnum {
     Lucene50(Lucene50PostingsFormat),
+    Completion(CompletionPostingsFormat),
 }
 
 impl PostingsFormat for PostingsFormatEnum {
@@ -120,6 +125,9 @@ impl PostingsFormat for PostingsFormatEnum {
             PostingsFormatEnum::Lucene50(f) => {
                 Ok(FieldsProducerEnum::Lucene50(f.fields_producer(state)?))
             }
+            PostingsFormatEnum::Completion(f) => {
+                Ok(FieldsProducerEnum::Completion(f.fields_producer(state)?))
+            }
         }
     }
 
@@ -129,12 +137,14 @@ impl PostingsFormat for PostingsFormatEnum {
     ) -> Result<FieldsConsumerEnum<D, DW, C>> {
         match self {
             PostingsFormatEnum::Lucene50(f) => f.fields_consumer(state),
+            PostingsFormatEnum::Completion(f) => f.fields_consumer(state),
         }
     }
 
     fn name(&self) -> &str {
         match self {
             PostingsFormatEnum::Lucene50(f) => f.name(),
+            PostingsFormatEnum::Completion(f) => f.name(),
         }
     }
 }
@@ -144,6 +154,9 @@ pub fn postings_format_for_name(name: &str) -> Result<PostingsFormatEnum> {
         "Lucene50" => Ok(PostingsFormatEnum::Lucene50(
             Lucene50PostingsFormat::default(),
         )),
+        COMPLETION_POSTINGS_FORMAT_NAME => Ok(PostingsFormatEnum::Completion(
+            CompletionPostingsFormat::default(),
+        )),
         _ => bail!(IllegalArgument(format!(
             "Invalid postings format: {}",
             name
@@ -210,6 +223,7 @@ pub trait FieldsConsumer {
 pub enum FieldsConsumerEnum<D: Directory, DW: Directory, C: Codec> {
     Lucene50(BlockTreeTermsWriter<Lucene50PostingsWriter<DW::IndexOutput>, DW::IndexOutput>),
     PerField(PerFieldFieldsWriter<D, DW, C>),
+    Completion(CompletionFieldsConsumer<D, DW, C>),
 }
 
 impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for FieldsConsumerEnum<D, DW, C> {
@@ -217,6 +231,7 @@ impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for FieldsConsumerEnu
         match self {
             FieldsConsumerEnum::Lucene50(w) => w.write(fields),
             FieldsConsumerEnum::PerField(w) => w.write(fields),
+            FieldsConsumerEnum::Completion(w) => w.write(fields),
         }
     }
 
@@ -227,6 +242,7 @@ impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for FieldsConsumerEnu
         match self {
             FieldsConsumerEnum::Lucene50(w) => w.merge(merge_state),
             FieldsConsumerEnum::PerField(w) => w.merge(merge_state),
+            FieldsConsumerEnum::Completion(w) => w.merge(merge_state),
         }
     }
 }
@@ -238,6 +254,12 @@ pub trait FieldsProducer: Fields {
     /// may involve computing a checksum value against large data files.
     fn check_integrity(&self) -> Result<()>;
 
+    /// Returns the completions of `field` if it is written by the
+    /// `CompletionPostingsFormat`.
+    fn completion_terms(&self, _field: &str) -> Option<Arc<CompletionTerms>> {
+        None
+    }
+
     // Returns an instance optimized for merging.
     // fn get_merge_instance(&self) -> Result<FieldsProducerRef>;
 }
@@ -249,6 +271,10 @@ impl<T: FieldsProducer> FieldsProducer for Arc<T> {
     fn check_integrity(&self) -> Result<()> {
         (**self).check_integrity()
     }
+
+    fn completion_terms(&self, field: &str) -> Option<Arc<CompletionTerms>> {
+        (**self).completion_terms(field)
+    }
 }
 
 impl<T: FieldsProducer> Fields for Arc<T> {
@@ -273,12 +299,21 @@ impl<T: FieldsProducer> Fields for Arc<T> {
 /// `FieldsProducer` impl for `PostingsFormatEnum`
 pub enum FieldsProducerEnum {
     Lucene50(BlockTreeTermsReader),
+    Completion(CompletionFieldsProducer),
 }
 
 impl FieldsProducer for FieldsProducerEnum {
     fn check_integrity(&self) -> Result<()> {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.check_integrity(),
+            FieldsProducerEnum::Completion(f) => f.check_integrity(),
+        }
+    }
+
+    fn completion_terms(&self, field: &str) -> Option<Arc<CompletionTerms>> {
+        match self {
+            FieldsProducerEnum::Lucene50(f) => f.completion_terms(field),
+            FieldsProducerEnum::Completion(f) => f.completion_terms(field),
         }
     }
 }
@@ -288,24 +323,28 @@ impl Fields for FieldsProducerEnum {
     fn fields(&self) -> Vec<String> {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.fields(),
+            FieldsProducerEnum::Completion(f) => f.fields(),
         }
     }
 
     fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.terms(field),
+            FieldsProducerEnum::Completion(f) => f.terms(field),
         }
     }
 
     fn size(&self) -> usize {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.size(),
+            FieldsProducerEnum::Completion(f) => f.size(),
         }
     }
 
     fn terms_freq(&self, field: &str) -> usize {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.terms_freq(field),
+            FieldsProducerEnum::Completion(f) => f.terms_freq(field),
         }
     }
 }
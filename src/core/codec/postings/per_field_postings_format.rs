@@ -26,6 +26,7 @@ use core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
 use core::codec::{Codec, Fields};
 use core::doc::IndexOptions;
 use core::store::directory::Directory;
+use core::suggest::document::CompletionTerms;
 use error::Result;
 
 /// Name of this {@link PostingsFormat}. */
@@ -87,7 +88,7 @@ impl PostingsFormat for PerFieldPostingsFormat {
 }
 
 pub struct PerFieldFieldsReader {
-    fields: BTreeMap<String, FieldsProducerEnum>,
+    fields: BTreeMap<String, Arc<FieldsProducerEnum>>,
     segment: String,
 }
 
@@ -96,7 +97,8 @@ impl PerFieldFieldsReader {
         state: &SegmentReadState<'_, D, DW, C>,
     ) -> Result<PerFieldFieldsReader> {
         let mut fields = BTreeMap::new();
-        let mut formats = HashMap::new();
+        // the fields written with the same format and suffix share their producer
+        let mut producers: HashMap<String, Arc<FieldsProducerEnum>> = HashMap::new();
         for (name, info) in &state.field_infos.by_name {
             if let IndexOptions::Null = info.index_options {
                 continue;
@@ -113,13 +115,14 @@ impl PerFieldFieldsReader {
                     .unwrap()
                     .get(PER_FIELD_POSTING_SUFFIX_KEY)
                 {
-                    if !formats.contains_key(suffix) {
-                        formats.insert(suffix.clone(), postings_format_for_name(format)?);
-                    }
-                    let postings_format = formats.get(suffix).unwrap();
                     let suffix = get_suffix(format, suffix);
-                    let state = SegmentReadState::with_suffix(state, &suffix);
-                    fields.insert(name.clone(), postings_format.fields_producer(&state)?);
+                    if !producers.contains_key(&suffix) {
+                        let postings_format = postings_format_for_name(format)?;
+                        let state = SegmentReadState::with_suffix(state, &suffix);
+                        let producer = postings_format.fields_producer(&state)?;
+                        producers.insert(suffix.clone(), Arc::new(producer));
+                    }
+                    fields.insert(name.clone(), Arc::clone(&producers[&suffix]));
                 } else {
                     bail!(
                         "Illegal State: missing attribute: {} for field {}",
@@ -158,6 +161,12 @@ impl FieldsProducer for PerFieldFieldsReader {
         }
         Ok(())
     }
+
+    fn completion_terms(&self, field: &str) -> Option<Arc<CompletionTerms>> {
+        self.fields
+            .get(field)
+            .and_then(|producer| producer.completion_terms(field))
+    }
 }
 
 impl Fields for PerFieldFieldsReader {
@@ -198,24 +207,58 @@ impl<D: Directory, DW: Directory, C: Codec> PerFieldFieldsWriter<D, DW, C> {
 }
 
 impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for PerFieldFieldsWriter<D, DW, C> {
-    // TODO this is only one postings format currently (lucene50).
-    // And we assume that we always use one format all the time.
-    // so we won't implement this like lucene
-    // when the format changes, it's easy to change the hard code then.
+    // every format is used at most once per segment, so the suffix of a
+    // format is always "0", unlike lucene which can have several instances
+    // of a format with different settings.
     fn write(&mut self, fields: &impl Fields) -> Result<()> {
-        // this is only one format, so suffix is always "0"
-        let segment_suffix =
-            self.get_full_segment_suffix(&self.write_state.segment_suffix, "Lucene50_0".into());
-        // always use lucene50, so just hard code it.
-        let format = Lucene50PostingsFormat::default();
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for field in fields.fields() {
+            let format = self
+                .write_state
+                .field_infos
+                .field_info_by_name(&field)
+                .and_then(|info| info.attribute(PER_FIELD_POSTING_FORMAT_KEY))
+                .unwrap_or_else(|| Lucene50PostingsFormat::default().name().to_string());
+            groups.entry(format).or_insert_with(Vec::new).push(field);
+        }
 
-        let old_suffix = mem::replace(&mut self.write_state.segment_suffix, segment_suffix);
+        for (format, names) in groups {
+            let segment_suffix = self.get_full_segment_suffix(
+                &self.write_state.segment_suffix,
+                get_suffix(&format, "0"),
+            );
+            let format = postings_format_for_name(&format)?;
 
-        let mut consumer = format.fields_consumer(&self.write_state)?;
-        consumer.write(fields)?;
+            let old_suffix = mem::replace(&mut self.write_state.segment_suffix, segment_suffix);
 
-        self.write_state.segment_suffix = old_suffix;
+            let mut consumer = format.fields_consumer(&self.write_state)?;
+            consumer.write(&FormatFields { fields, names })?;
+
+            self.write_state.segment_suffix = old_suffix;
+        }
 
         Ok(())
     }
 }
+
+/// The fields of a segment written with the same postings format.
+struct FormatFields<'a, T: Fields> {
+    fields: &'a T,
+    names: Vec<String>,
+}
+
+impl<'a, T: Fields> Fields for FormatFields<'a, T> {
+    type Terms = T::Terms;
+    fn fields(&self) -> Vec<String> {
+        self.names.clone()
+    }
+    fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
+        self.fields.terms(field)
+    }
+    fn size(&self) -> usize {
+        self.names.len()
+    }
+    fn terms_freq(&self, field: &str) -> usize {
+        self.fields.terms_freq(field)
+    }
+}
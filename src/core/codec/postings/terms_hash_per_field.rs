@@ -591,7 +591,7 @@ where
             self.base.postings_array.last_doc_codes[term_id] = doc_id as u32;
         } else {
             self.base.postings_array.last_doc_codes[term_id] = (doc_id << 1) as u32;
-            self.base.postings_array.term_freqs[term_id] = 1;
+            self.base.postings_array.term_freqs[term_id] = token_stream.term_freq();
             if self.has_prox {
                 self.write_prox(
                     term_id,
@@ -606,7 +606,8 @@ where
                 assert!(!self.has_offsets);
             }
         }
-        field_state.max_term_frequency = max(1, field_state.max_term_frequency);
+        field_state.max_term_frequency =
+            max(token_stream.term_freq(), field_state.max_term_frequency);
         field_state.unique_term_count += 1;
         Ok(())
     }
@@ -650,7 +651,7 @@ where
             }
 
             // Init freq for the current document
-            self.base.postings_array.term_freqs[term_id] = 1;
+            self.base.postings_array.term_freqs[term_id] = token_stream.term_freq();
             field_state.max_term_frequency = max(
                 self.base.postings_array.term_freqs[term_id],
                 field_state.max_term_frequency,
@@ -674,7 +675,7 @@ where
             }
             field_state.unique_term_count += 1;
         } else {
-            self.base.postings_array.term_freqs[term_id] += 1;
+            self.base.postings_array.term_freqs[term_id] += token_stream.term_freq();
             field_state.max_term_frequency = max(
                 field_state.max_term_frequency,
                 self.base.postings_array.term_freqs[term_id],
@@ -371,6 +371,11 @@ impl SegmentInfoFormat for Lucene62SegmentInfoFormat {
                         let select_value = match snsf.selector() {
                             SortedNumericSelectorType::Min => 0,
                             SortedNumericSelectorType::Max => 1,
+                            SortedNumericSelectorType::Sum => {
+                                bail!(IllegalArgument(
+                                    "sum selector can't be used for index sorting".into()
+                                ));
+                            }
                         };
                         output.write_byte(select_value)?;
                     }
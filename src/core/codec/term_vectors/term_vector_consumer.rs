@@ -878,7 +878,7 @@ where
         token_stream: &dyn TokenStream,
         _doc_id: i32,
     ) -> Result<()> {
-        self.base.postings_array.freqs[term_id] = 1;
+        self.base.postings_array.freqs[term_id] = token_stream.term_freq();
         self.base.postings_array.last_offsets[term_id] = 0;
         self.base.postings_array.last_positions[term_id] = 0;
 
@@ -893,7 +893,7 @@ where
         token_stream: &dyn TokenStream,
         _doc_id: i32,
     ) -> Result<()> {
-        self.base.postings_array.freqs[term_id] += 1;
+        self.base.postings_array.freqs[term_id] += token_stream.term_freq();
         self.write_prox(term_id, field_state, token_stream);
         Ok(())
     }
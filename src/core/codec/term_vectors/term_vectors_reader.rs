@@ -1336,8 +1336,8 @@ impl PostingIterator for TVPostingsIterator {
 
     fn next_position(&mut self) -> Result<i32> {
         if self.doc != 0 {
-            bail!(IllegalState("".into()));
-        } else if self.i > self.term_freq - 1 {
+            bail!(IllegalState("DocIterator not positioned on the doc".into()));
+        } else if self.i >= self.term_freq - 1 {
             bail!(IllegalState("Read past last position".into()));
         }
 
@@ -1369,7 +1369,7 @@ impl PostingIterator for TVPostingsIterator {
 
     fn start_offset(&self) -> Result<i32> {
         self.check_position()?;
-        if !self.fields_data.start_offsets.is_empty() {
+        if !self.fields_data.start_offsets[self.data_index].is_empty() {
             Ok(self.fields_data.start_offsets[self.data_index]
                 [(self.position_index + self.i) as usize])
         } else {
@@ -1426,3 +1426,82 @@ impl DocIterator for TVPostingsIterator {
         1usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::field_infos::FieldInfo;
+    use core::doc::{DocValuesType, IndexOptions};
+    use std::collections::HashMap;
+
+    // the vector of "ab ac ab" with the payloads "x", "yz" and none
+    fn tv_fields() -> TVFields {
+        let field_info = FieldInfo::new(
+            "body".to_string(),
+            0,
+            true,
+            false,
+            true,
+            IndexOptions::DocsAndFreqsAndPositionsAndOffsets,
+            DocValuesType::Null,
+            -1,
+            HashMap::new(),
+            0,
+            0,
+        )
+        .unwrap();
+        TVFields::new(
+            Arc::new(FieldInfos::new(vec![field_info]).unwrap()),
+            vec![0],
+            vec![POSITIONS | OFFSETS | PAYLOADS],
+            vec![0],
+            vec![2],
+            vec![3],
+            vec![vec![0, 1]],
+            vec![vec![2, 1]],
+            vec![vec![2, 1]],
+            vec![vec![0, 2, 3]],
+            vec![vec![0, 2, 1]],
+            vec![vec![0, 6, 3]],
+            vec![vec![2, 2, 2]],
+            vec![vec![0, 1, 1, 3]],
+            b"abcxyz".to_vec(),
+            OffsetAndLength(0, 3),
+            OffsetAndLength(3, 3),
+        )
+    }
+
+    #[test]
+    fn test_term_vector_postings() {
+        let fields = tv_fields();
+        assert!(fields.terms("title").unwrap().is_none());
+        let terms = fields.terms("body").unwrap().unwrap();
+        assert!(terms.has_positions().unwrap());
+        assert!(terms.has_offsets().unwrap());
+        assert!(terms.has_payloads().unwrap());
+
+        let mut terms_iter = terms.iterator().unwrap();
+        assert_eq!(terms_iter.next().unwrap(), Some(b"ab".to_vec()));
+        assert_eq!(terms_iter.total_term_freq().unwrap(), 2);
+        let mut postings = terms_iter.postings_with_flags(0).unwrap();
+        assert_eq!(postings.next().unwrap(), 0);
+        assert_eq!(postings.freq().unwrap(), 2);
+        assert_eq!(postings.next_position().unwrap(), 0);
+        assert_eq!(postings.start_offset().unwrap(), 0);
+        assert_eq!(postings.end_offset().unwrap(), 2);
+        assert_eq!(postings.payload().unwrap(), b"x".to_vec());
+        assert_eq!(postings.next_position().unwrap(), 2);
+        assert_eq!(postings.start_offset().unwrap(), 6);
+        assert_eq!(postings.end_offset().unwrap(), 8);
+        assert!(postings.payload().unwrap().is_empty());
+        assert!(postings.next_position().is_err());
+
+        assert_eq!(terms_iter.next().unwrap(), Some(b"ac".to_vec()));
+        let mut postings = terms_iter.postings_with_flags(0).unwrap();
+        postings.next().unwrap();
+        assert_eq!(postings.next_position().unwrap(), 1);
+        assert_eq!(postings.start_offset().unwrap(), 3);
+        assert_eq!(postings.payload().unwrap(), b"yz".to_vec());
+        assert_eq!(terms_iter.next().unwrap(), None);
+    }
+}
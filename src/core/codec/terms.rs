@@ -11,7 +11,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::codec::{EmptyPostingIterator, PostingIterator, PostingIteratorFlags};
+use core::codec::{
+    AutomatonTermIterator, EmptyPostingIterator, PostingIterator, PostingIteratorFlags,
+};
+use core::util::automaton::CompiledAutomaton;
 
 use error::ErrorKind::{IllegalArgument, UnsupportedOperation};
 use error::Result;
@@ -47,60 +50,21 @@ pub trait Terms {
     /// terms. This method will not return null. */
     fn iterator(&self) -> Result<Self::Iterator>;
 
-    /// Returns a TermsEnum that iterates over all terms and
-    /// documents that are accepted by the provided {@link
-    /// CompiledAutomaton}.  If the <code>startTerm</code> is
-    /// provided then the returned enum will only return terms
-    /// {@code > startTerm}, but you still must call
-    /// next() first to get to the first term.  Note that the
-    /// provided <code>startTerm</code> must be accepted by
-    /// the automaton.
+    /// Returns a `TermIterator` that iterates over all terms and
+    /// documents that are accepted by the provided `CompiledAutomaton`.
     ///
-    /// <p>This is an expert low-level API and will only work
-    /// for {@code NORMAL} compiled automata.  To handle any
-    /// compiled automata you should instead use
-    /// {@link CompiledAutomaton#getTermsEnum} instead.
+    /// The returned iterator uses the automaton to seek ahead to the next
+    /// term that may be accepted, so only the candidate portions of the
+    /// terms dictionary are visited rather than every term of the field.
     ///
-    /// <p><b>NOTE</b>: the returned TermsEnum cannot seek</p>.
-    ///
-    /// <p><b>NOTE</b>: the terms dictionary is free to
-    /// return arbitrary terms as long as the resulted visited
-    /// docs is the same.  E.g., {@link BlockTreeTermsWriter}
-    /// creates auto-prefix terms during indexing to reduce the
-    /// number of terms visited. */
-    // fn TermsEnum intersect(CompiledAutomaton compiled, final BytesRef startTerm) throws
-    // IOException {
-    //
-    // TODO: could we factor out a common interface b/w
-    // CompiledAutomaton and FST?  Then we could pass FST there too,
-    // and likely speed up resolving terms to deleted docs ... but
-    // AutomatonTermsEnum makes this tricky because of its on-the-fly cycle
-    // detection
-    //
-    // TODO: eventually we could support seekCeil/Exact on
-    // the returned enum, instead of only being able to seek
-    // at the start
-    //
-    // TermsEnum termsEnum = iterator();
-    //
-    // if (compiled.type != CompiledAutomaton.AUTOMATON_TYPE.NORMAL) {
-    // throw new IllegalArgumentException("please use CompiledAutomaton.getTermsEnum instead");
-    // }
-    //
-    // if (startTerm == null) {
-    // return new AutomatonTermsEnum(termsEnum, compiled);
-    // } else {
-    // return new AutomatonTermsEnum(termsEnum, compiled) {
-    // @Override
-    // protected BytesRef nextSeekTerm(BytesRef term) throws IOException {
-    // if (term == null) {
-    // term = startTerm;
-    // }
-    // return super.nextSeekTerm(term);
-    // }
-    // };
-    // }
-    // }
+    /// *NOTE*: the returned iterator cannot seek.
+    fn intersect(
+        &self,
+        compiled: &CompiledAutomaton,
+    ) -> Result<AutomatonTermIterator<Self::Iterator>> {
+        Ok(AutomatonTermIterator::new(self.iterator()?, compiled))
+    }
+
     /// Returns the number of terms for this field, or -1 if this
     /// measure isn't stored by the codec. Note that, just like
     /// other term measures, this measure does not take deleted
@@ -228,6 +192,13 @@ impl<T: Terms> Terms for Arc<T> {
         (**self).iterator()
     }
 
+    fn intersect(
+        &self,
+        compiled: &CompiledAutomaton,
+    ) -> Result<AutomatonTermIterator<Self::Iterator>> {
+        (**self).intersect(compiled)
+    }
+
     fn size(&self) -> Result<i64> {
         (**self).size()
     }
@@ -531,22 +502,22 @@ where
             debug_assert!(self.base().actual_term.is_some());
             match self.accept(self.base().actual_term.as_ref().unwrap().as_slice())? {
                 AcceptStatus::YesAndSeek => {
+                    // term accepted, but we need to seek on the next call
                     self.base_mut().do_seek = true;
+                    return Ok(self.base().actual_term.clone());
                 }
                 AcceptStatus::Yes => {
                     return Ok(self.base().actual_term.clone());
                 }
                 AcceptStatus::NoAndSeek => {
                     self.base_mut().do_seek = true;
-                    break;
                 }
                 AcceptStatus::End => {
                     return Ok(None);
                 }
-                _ => {}
+                AcceptStatus::No => {}
             }
         }
-        unreachable!()
     }
 
     fn seek_exact(&mut self, _text: &[u8]) -> Result<bool> {
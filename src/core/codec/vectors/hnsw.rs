@@ -0,0 +1,508 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::vectors::VectorValues;
+use core::store::io::{DataInput, DataOutput};
+use core::util::Bits;
+
+use error::{ErrorKind::CorruptIndex, Result};
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+/// Default maximum number of connections of a node on the upper levels,
+/// nodes of the bottom level have twice as many.
+pub const HNSW_DEFAULT_MAX_CONN: usize = 16;
+/// Default number of candidates tracked when searching the neighbors of a
+/// new node.
+pub const HNSW_DEFAULT_BEAM_WIDTH: usize = 100;
+
+// the graph is built with a fixed seed so that indexing the same vectors
+// always produces the same segment
+const HNSW_RANDOM_SEED: u64 = 42;
+
+/// A node of the graph and its similarity to the search target.
+#[derive(Clone, Copy, Debug)]
+pub struct ScoredNode {
+    pub node: u32,
+    pub score: f32,
+}
+
+impl Eq for ScoredNode {}
+
+impl PartialEq for ScoredNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Ord for ScoredNode {
+    // greater scores first, lower nodes first on ties
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for ScoredNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Default)]
+struct HnswLevel {
+    // sorted ordinals of the nodes of this level
+    nodes: Vec<u32>,
+    // neighbors of each node, in the order of `nodes`
+    neighbors: Vec<Vec<u32>>,
+}
+
+impl HnswLevel {
+    fn index(&self, node: u32) -> usize {
+        self.nodes.binary_search(&node).unwrap()
+    }
+}
+
+/// A hierarchical navigable small world graph over the vectors of a field.
+///
+/// Every vector is a node of the bottom level, each upper level holds an
+/// exponentially decreasing random subset of the nodes below it. Searches
+/// start from the single entry node of the top level and greedily walk down
+/// to the bottom level where the nearest neighbors are collected.
+#[derive(Debug, Default)]
+pub struct HnswGraph {
+    entry_node: u32,
+    levels: Vec<HnswLevel>,
+}
+
+impl HnswGraph {
+    /// Builds the graph of the given vectors with the default parameters.
+    pub fn build(vectors: &dyn VectorValues) -> Result<HnswGraph> {
+        let mut builder =
+            HnswGraphBuilder::new(vectors, HNSW_DEFAULT_MAX_CONN, HNSW_DEFAULT_BEAM_WIDTH);
+        for node in 0..vectors.size() {
+            builder.add_node(node as u32)?;
+        }
+        Ok(builder.graph)
+    }
+
+    pub fn size(&self) -> usize {
+        self.levels.first().map_or(0, |l| l.nodes.len())
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn neighbors(&self, level: usize, node: u32) -> &[u32] {
+        let level = &self.levels[level];
+        &level.neighbors[level.index(node)]
+    }
+
+    /// Returns the `k` nodes whose vectors are the most similar to `target`,
+    /// sorted by decreasing score. Only nodes whose document is accepted by
+    /// `accept_docs` are returned, but all nodes are used to navigate the graph.
+    pub fn search(
+        &self,
+        target: &[f32],
+        k: usize,
+        vectors: &dyn VectorValues,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoredNode>> {
+        if self.size() == 0 || k == 0 {
+            return Ok(Vec::new());
+        }
+        let mut entry_points = vec![self.entry_node];
+        for level in (1..self.levels.len()).rev() {
+            let best = self.search_level(target, &entry_points, 1, level, vectors, None)?;
+            entry_points = best.iter().map(|n| n.node).collect();
+        }
+        self.search_level(target, &entry_points, k, 0, vectors, accept_docs)
+    }
+
+    fn search_level(
+        &self,
+        target: &[f32],
+        entry_points: &[u32],
+        top_k: usize,
+        level: usize,
+        vectors: &dyn VectorValues,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoredNode>> {
+        let similarity = vectors.similarity();
+        let accepted = |node: u32| -> Result<bool> {
+            match accept_docs {
+                Some(bits) => bits.get(vectors.doc(node as usize) as usize),
+                None => Ok(true),
+            }
+        };
+
+        let mut visited = HashSet::new();
+        // best candidates first
+        let mut candidates = BinaryHeap::new();
+        // worst results first
+        let mut results: BinaryHeap<Reverse<ScoredNode>> = BinaryHeap::with_capacity(top_k + 1);
+        for &node in entry_points {
+            if visited.insert(node) {
+                let score = similarity.compare(target, &vectors.vector(node as usize)?);
+                let scored = ScoredNode { node, score };
+                candidates.push(scored);
+                if accepted(node)? {
+                    results.push(Reverse(scored));
+                    if results.len() > top_k {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            if results.len() >= top_k && candidate.score < results.peek().unwrap().0.score {
+                break;
+            }
+            for &neighbor in self.neighbors(level, candidate.node) {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let score = similarity.compare(target, &vectors.vector(neighbor as usize)?);
+                if results.len() < top_k || score > results.peek().unwrap().0.score {
+                    let scored = ScoredNode {
+                        node: neighbor,
+                        score,
+                    };
+                    candidates.push(scored);
+                    if accepted(neighbor)? {
+                        results.push(Reverse(scored));
+                        if results.len() > top_k {
+                            results.pop();
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut nodes: Vec<ScoredNode> = results.into_iter().map(|r| r.0).collect();
+        nodes.sort_by(|a, b| b.cmp(a));
+        Ok(nodes)
+    }
+
+    /// Writes the graph, node ordinals are delta encoded.
+    pub fn write<T: DataOutput + ?Sized>(&self, out: &mut T) -> Result<()> {
+        out.write_vint(self.levels.len() as i32)?;
+        if self.levels.is_empty() {
+            return Ok(());
+        }
+        out.write_vint(self.entry_node as i32)?;
+        for (i, level) in self.levels.iter().enumerate() {
+            out.write_vint(level.nodes.len() as i32)?;
+            if i > 0 {
+                write_sorted(out, &level.nodes)?;
+            }
+            for neighbors in &level.neighbors {
+                let mut sorted = neighbors.clone();
+                sorted.sort();
+                out.write_vint(sorted.len() as i32)?;
+                write_sorted(out, &sorted)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read<T: DataInput + ?Sized>(input: &mut T) -> Result<HnswGraph> {
+        let num_levels = input.read_vint()?;
+        if num_levels < 0 {
+            bail!(CorruptIndex(format!(
+                "invalid number of hnsw levels: {}",
+                num_levels
+            )));
+        }
+        let mut graph = HnswGraph::default();
+        if num_levels == 0 {
+            return Ok(graph);
+        }
+        graph.entry_node = input.read_vint()? as u32;
+        for i in 0..num_levels {
+            let size = input.read_vint()? as usize;
+            let nodes = if i > 0 {
+                read_sorted(input, size)?
+            } else {
+                (0..size as u32).collect()
+            };
+            let mut neighbors = Vec::with_capacity(size);
+            for _ in 0..size {
+                let count = input.read_vint()? as usize;
+                neighbors.push(read_sorted(input, count)?);
+            }
+            graph.levels.push(HnswLevel { nodes, neighbors });
+        }
+        Ok(graph)
+    }
+}
+
+fn write_sorted<T: DataOutput + ?Sized>(out: &mut T, values: &[u32]) -> Result<()> {
+    let mut last = 0;
+    for &v in values {
+        out.write_vint((v - last) as i32)?;
+        last = v;
+    }
+    Ok(())
+}
+
+fn read_sorted<T: DataInput + ?Sized>(input: &mut T, count: usize) -> Result<Vec<u32>> {
+    let mut values = Vec::with_capacity(count);
+    let mut last = 0u32;
+    for _ in 0..count {
+        last += input.read_vint()? as u32;
+        values.push(last);
+    }
+    Ok(values)
+}
+
+/// Builds a `HnswGraph` by adding the vectors one at a time, in ordinal order.
+pub struct HnswGraphBuilder<'a> {
+    vectors: &'a dyn VectorValues,
+    max_conn: usize,
+    beam_width: usize,
+    level_multiplier: f64,
+    random: SplitMix64,
+    graph: HnswGraph,
+}
+
+impl<'a> HnswGraphBuilder<'a> {
+    pub fn new(vectors: &'a dyn VectorValues, max_conn: usize, beam_width: usize) -> Self {
+        debug_assert!(max_conn > 1 && beam_width > 0);
+        HnswGraphBuilder {
+            vectors,
+            max_conn,
+            beam_width,
+            level_multiplier: 1.0 / (max_conn as f64).ln(),
+            random: SplitMix64(HNSW_RANDOM_SEED),
+            graph: HnswGraph::default(),
+        }
+    }
+
+    pub fn finish(self) -> HnswGraph {
+        self.graph
+    }
+
+    pub fn add_node(&mut self, node: u32) -> Result<()> {
+        debug_assert_eq!(node as usize, self.graph.size());
+        let node_level = self.random_level();
+        let top_level = self.graph.levels.len();
+        for level in 0..=node_level {
+            if level >= self.graph.levels.len() {
+                self.graph.levels.push(HnswLevel::default());
+            }
+            let level = &mut self.graph.levels[level];
+            level.nodes.push(node);
+            level.neighbors.push(Vec::new());
+        }
+        if top_level == 0 {
+            // the first node
+            self.graph.entry_node = node;
+            return Ok(());
+        }
+
+        let vector = self.vectors.vector(node as usize)?.into_owned();
+        let mut entry_points = vec![self.graph.entry_node];
+        for level in (node_level + 1..top_level).rev() {
+            let best =
+                self.graph
+                    .search_level(&vector, &entry_points, 1, level, self.vectors, None)?;
+            entry_points = best.iter().map(|n| n.node).collect();
+        }
+        for level in (0..=node_level.min(top_level - 1)).rev() {
+            let candidates = self.graph.search_level(
+                &vector,
+                &entry_points,
+                self.beam_width,
+                level,
+                self.vectors,
+                None,
+            )?;
+            let max_conn = self.max_conn(level);
+            let neighbors: Vec<u32> = candidates.iter().take(max_conn).map(|n| n.node).collect();
+            for &neighbor in &neighbors {
+                self.add_link(level, neighbor, node)?;
+            }
+            let idx = self.graph.levels[level].index(node);
+            self.graph.levels[level].neighbors[idx] = neighbors;
+            entry_points = candidates.iter().map(|n| n.node).collect();
+        }
+        if node_level >= top_level {
+            self.graph.entry_node = node;
+        }
+        Ok(())
+    }
+
+    fn max_conn(&self, level: usize) -> usize {
+        if level == 0 {
+            self.max_conn * 2
+        } else {
+            self.max_conn
+        }
+    }
+
+    // adds a link from `from` to `to`, dropping the least similar neighbor of
+    // `from` if it has too many connections
+    fn add_link(&mut self, level: usize, from: u32, to: u32) -> Result<()> {
+        let max_conn = self.max_conn(level);
+        let idx = self.graph.levels[level].index(from);
+        self.graph.levels[level].neighbors[idx].push(to);
+        if self.graph.levels[level].neighbors[idx].len() > max_conn {
+            let similarity = self.vectors.similarity();
+            let vector = self.vectors.vector(from as usize)?.into_owned();
+            let mut scored = Vec::with_capacity(max_conn + 1);
+            for &node in &self.graph.levels[level].neighbors[idx] {
+                let score = similarity.compare(&vector, &self.vectors.vector(node as usize)?);
+                scored.push(ScoredNode { node, score });
+            }
+            scored.sort_by(|a, b| b.cmp(a));
+            scored.truncate(max_conn);
+            self.graph.levels[level].neighbors[idx] = scored.iter().map(|n| n.node).collect();
+        }
+        Ok(())
+    }
+
+    // draws the level of a new node from an exponentially decaying distribution
+    fn random_level(&mut self) -> usize {
+        let uniform = ((self.random.next() >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() * self.level_multiplier) as usize
+    }
+}
+
+// a small and fast pseudo random generator, see http://xoshiro.di.unimi.it/splitmix64.c
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::doc::VectorSimilarity;
+    use core::store::io::{ByteArrayDataInput, GrowableByteArrayDataOutput};
+    use core::util::{DocId, FixedBitSet};
+    use std::borrow::Cow;
+
+    struct TestVectors {
+        vectors: Vec<Vec<f32>>,
+        similarity: VectorSimilarity,
+    }
+
+    impl VectorValues for TestVectors {
+        fn dimension(&self) -> usize {
+            self.vectors[0].len()
+        }
+
+        fn size(&self) -> usize {
+            self.vectors.len()
+        }
+
+        fn similarity(&self) -> VectorSimilarity {
+            self.similarity
+        }
+
+        fn doc(&self, ord: usize) -> DocId {
+            ord as DocId
+        }
+
+        fn vector(&self, ord: usize) -> Result<Cow<'_, [f32]>> {
+            Ok(Cow::Borrowed(&self.vectors[ord]))
+        }
+    }
+
+    // points on a 2d grid, the nearest neighbor of any point is itself
+    fn grid_vectors(size: usize) -> TestVectors {
+        let mut vectors = Vec::with_capacity(size * size);
+        for i in 0..size {
+            for j in 0..size {
+                vectors.push(vec![i as f32, j as f32]);
+            }
+        }
+        TestVectors {
+            vectors,
+            similarity: VectorSimilarity::Euclidean,
+        }
+    }
+
+    #[test]
+    fn test_hnsw_search() {
+        let vectors = grid_vectors(20);
+        let graph = HnswGraph::build(&vectors).unwrap();
+        assert_eq!(graph.size(), 400);
+        assert!(graph.num_levels() > 1);
+
+        for node in 0..graph.size() as u32 {
+            assert!(graph.neighbors(0, node).len() <= HNSW_DEFAULT_MAX_CONN * 2);
+        }
+
+        let hits = graph.search(&[5.1, 7.2], 5, &vectors, None).unwrap();
+        assert_eq!(hits.len(), 5);
+        assert_eq!(hits[0].node, 5 * 20 + 7);
+        for i in 1..hits.len() {
+            assert!(hits[i - 1].score >= hits[i].score);
+        }
+    }
+
+    #[test]
+    fn test_hnsw_search_accept_docs() {
+        let vectors = grid_vectors(10);
+        let graph = HnswGraph::build(&vectors).unwrap();
+
+        // only accept the nodes of the first row
+        let mut bits = FixedBitSet::new(100);
+        for i in 0..10 {
+            bits.set(i);
+        }
+        let hits = graph.search(&[9.0, 9.0], 3, &vectors, Some(&bits)).unwrap();
+        let nodes: Vec<u32> = hits.iter().map(|n| n.node).collect();
+        assert_eq!(nodes, vec![9, 8, 7]);
+    }
+
+    #[test]
+    fn test_hnsw_write_read() {
+        let vectors = grid_vectors(8);
+        let graph = HnswGraph::build(&vectors).unwrap();
+
+        let mut out = GrowableByteArrayDataOutput::new(1024);
+        graph.write(&mut out).unwrap();
+        let mut input = ByteArrayDataInput::new(&out.bytes[..out.position()]);
+        let read = HnswGraph::read(&mut input).unwrap();
+
+        assert_eq!(read.entry_node, graph.entry_node);
+        assert_eq!(read.num_levels(), graph.num_levels());
+        for level in 0..graph.num_levels() {
+            assert_eq!(read.levels[level].nodes, graph.levels[level].nodes);
+            for (node, neighbors) in graph.levels[level]
+                .nodes
+                .iter()
+                .zip(&graph.levels[level].neighbors)
+            {
+                let mut expected = neighbors.clone();
+                expected.sort();
+                assert_eq!(read.neighbors(level, *node), &expected[..]);
+            }
+        }
+    }
+}
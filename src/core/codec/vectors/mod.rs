@@ -0,0 +1,98 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod hnsw;
+
+pub use self::hnsw::*;
+
+mod vectors_reader;
+
+pub use self::vectors_reader::*;
+
+mod vectors_writer;
+
+pub use self::vectors_writer::*;
+
+mod vector_values_writer;
+
+pub use self::vector_values_writer::*;
+
+use core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use core::codec::Codec;
+use core::doc::VectorSimilarity;
+use core::search::sort_field::ScoreDoc;
+use core::store::directory::Directory;
+use core::util::{Bits, DocId};
+
+use error::Result;
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Encodes/decodes per-document dense vectors and the graph used to search
+/// their nearest neighbors.
+pub trait VectorsFormat {
+    type Reader: VectorsReader + 'static;
+
+    /// Returns a `HnswVectorsWriter` to write the vectors of a segment.
+    // TODO we need GAT to make the writer an associated type
+    fn fields_writer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<HnswVectorsWriter<D, DW, C>>;
+
+    /// Reads the vectors of a segment. NOTE: by the time this call returns,
+    /// it must hold open any files it will need to use.
+    fn fields_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::Reader>;
+}
+
+/// Reads the vectors of a segment and searches their nearest neighbors.
+pub trait VectorsReader: Send + Sync {
+    fn check_integrity(&self) -> Result<()>;
+
+    /// Returns the vectors of the given field, or None if no document of this
+    /// segment has a vector for it.
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues + '_>>>;
+
+    /// Returns the `k` documents whose vectors are the most similar to `target`
+    /// sorted by decreasing score, only documents accepted by `accept_docs`
+    /// are returned. The search is approximate.
+    fn search(
+        &self,
+        field: &str,
+        target: &[f32],
+        k: usize,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoreDoc>>;
+}
+
+pub type VectorsReaderRef = Arc<dyn VectorsReader>;
+
+/// Random access to the vectors of a field, vectors are identified by an
+/// ordinal in `[0, size)`, ordinals follow the doc ids order when the vectors
+/// are read from a segment.
+pub trait VectorValues {
+    fn dimension(&self) -> usize;
+
+    fn size(&self) -> usize;
+
+    fn similarity(&self) -> VectorSimilarity;
+
+    /// Returns the document of the vector identified by `ord`.
+    fn doc(&self, ord: usize) -> DocId;
+
+    fn vector(&self, ord: usize) -> Result<Cow<'_, [f32]>>;
+}
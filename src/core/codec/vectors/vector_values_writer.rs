@@ -0,0 +1,131 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::field_infos::FieldInfo;
+use core::codec::vectors::{HnswVectorsWriter, VectorValues};
+use core::codec::{Codec, SorterDocMap};
+use core::doc::VectorSimilarity;
+use core::store::directory::Directory;
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::borrow::Cow;
+
+/// Buffers the vectors of a field in RAM until the segment is flushed.
+pub struct VectorValuesWriter {
+    field_info: FieldInfo,
+    values: BufferedVectorValues,
+}
+
+impl VectorValuesWriter {
+    pub fn new(field_info: &FieldInfo) -> VectorValuesWriter {
+        VectorValuesWriter {
+            field_info: field_info.clone(),
+            values: BufferedVectorValues::new(
+                field_info.vector_dimension as usize,
+                field_info.vector_similarity,
+            ),
+        }
+    }
+
+    pub fn add_value(&mut self, doc_id: DocId, vector: &[f32]) -> Result<()> {
+        if self.values.docs.last() == Some(&doc_id) {
+            bail!(IllegalArgument(format!(
+                "field={}: vector fields only accept one value per document",
+                self.field_info.name
+            )));
+        }
+        if vector.len() != self.values.dimension {
+            bail!(IllegalArgument(format!(
+                "field={}: this field's vector has dimension={} but should be {}",
+                self.field_info.name,
+                vector.len(),
+                self.values.dimension
+            )));
+        }
+        self.values.add(doc_id, vector);
+        Ok(())
+    }
+
+    pub fn flush<D: Directory, DW: Directory, C: Codec, M: SorterDocMap>(
+        &mut self,
+        sort_map: Option<&M>,
+        writer: &mut HnswVectorsWriter<D, DW, C>,
+    ) -> Result<()> {
+        if let Some(sort_map) = sort_map {
+            let mut sorted =
+                BufferedVectorValues::new(self.values.dimension, self.values.similarity);
+            let mut ords: Vec<usize> = (0..self.values.size()).collect();
+            ords.sort_by_key(|&ord| sort_map.old_to_new(self.values.docs[ord]));
+            for ord in ords {
+                let vector = self.values.vector_at(ord);
+                sorted.add(sort_map.old_to_new(self.values.docs[ord]), vector);
+            }
+            writer.write_field(&self.field_info, &sorted)
+        } else {
+            writer.write_field(&self.field_info, &self.values)
+        }
+    }
+}
+
+/// In memory `VectorValues` whose vectors are appended in doc id order.
+pub(crate) struct BufferedVectorValues {
+    dimension: usize,
+    similarity: VectorSimilarity,
+    docs: Vec<DocId>,
+    vectors: Vec<f32>,
+}
+
+impl BufferedVectorValues {
+    pub(crate) fn new(dimension: usize, similarity: VectorSimilarity) -> Self {
+        BufferedVectorValues {
+            dimension,
+            similarity,
+            docs: vec![],
+            vectors: vec![],
+        }
+    }
+
+    pub(crate) fn add(&mut self, doc_id: DocId, vector: &[f32]) {
+        debug_assert_eq!(vector.len(), self.dimension);
+        self.docs.push(doc_id);
+        self.vectors.extend_from_slice(vector);
+    }
+
+    fn vector_at(&self, ord: usize) -> &[f32] {
+        &self.vectors[ord * self.dimension..(ord + 1) * self.dimension]
+    }
+}
+
+impl VectorValues for BufferedVectorValues {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn size(&self) -> usize {
+        self.docs.len()
+    }
+
+    fn similarity(&self) -> VectorSimilarity {
+        self.similarity
+    }
+
+    fn doc(&self, ord: usize) -> DocId {
+        self.docs[ord]
+    }
+
+    fn vector(&self, ord: usize) -> Result<Cow<'_, [f32]>> {
+        Ok(Cow::Borrowed(self.vector_at(ord)))
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::field_infos::FieldInfos;
+use core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use core::codec::vectors::{
+    HnswGraph, HnswVectorsWriter, VectorValues, VectorsFormat, VectorsReader,
+    VECTORS_DATA_CODEC_NAME, VECTORS_DATA_EXTENSION, VECTORS_META_CODEC_NAME,
+    VECTORS_META_EXTENSION, VECTORS_VERSION_CURRENT, VECTORS_VERSION_START,
+};
+use core::codec::{codec_util, Codec};
+use core::doc::VectorSimilarity;
+use core::search::sort_field::ScoreDoc;
+use core::store::directory::Directory;
+use core::store::io::{DataInput, IndexInput, RandomAccessInput};
+use core::util::{Bits, DocId};
+
+use error::ErrorKind::{CorruptIndex, IllegalArgument};
+use error::Result;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Default)]
+pub struct HnswVectorsFormat;
+
+impl VectorsFormat for HnswVectorsFormat {
+    type Reader = HnswVectorsReader;
+
+    fn fields_writer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<HnswVectorsWriter<D, DW, C>> {
+        HnswVectorsWriter::new(state)
+    }
+
+    fn fields_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::Reader> {
+        HnswVectorsReader::new(state)
+    }
+}
+
+struct FieldEntry {
+    dimension: usize,
+    similarity: VectorSimilarity,
+    docs: Vec<DocId>,
+    vectors: Box<dyn RandomAccessInput>,
+    graph: HnswGraph,
+}
+
+/// Reads the vectors written by `HnswVectorsWriter`.
+///
+/// The graphs are loaded in memory when the segment is opened while the
+/// vectors are read from the data file on demand.
+pub struct HnswVectorsReader {
+    data_in: Box<dyn IndexInput>,
+    fields: HashMap<String, FieldEntry>,
+}
+
+impl HnswVectorsReader {
+    pub fn new<D: Directory, DW: Directory, C: Codec>(
+        read_state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<HnswVectorsReader> {
+        let meta_file_name = segment_file_name(
+            &read_state.segment_info.name,
+            &read_state.segment_suffix,
+            VECTORS_META_EXTENSION,
+        );
+        let mut meta_in = read_state
+            .directory
+            .open_checksum_input(&meta_file_name, read_state.context)?;
+        codec_util::check_index_header(
+            &mut meta_in,
+            VECTORS_META_CODEC_NAME,
+            VECTORS_VERSION_START,
+            VECTORS_VERSION_CURRENT,
+            &read_state.segment_info.id,
+            &read_state.segment_suffix,
+        )?;
+        let metas = Self::read_fields(&mut meta_in, &read_state.field_infos)?;
+        codec_util::check_footer(&mut meta_in)?;
+
+        let data_file_name = segment_file_name(
+            &read_state.segment_info.name,
+            &read_state.segment_suffix,
+            VECTORS_DATA_EXTENSION,
+        );
+        let mut data_in = read_state
+            .directory
+            .open_input(&data_file_name, read_state.context)?;
+        codec_util::check_index_header(
+            data_in.as_mut(),
+            VECTORS_DATA_CODEC_NAME,
+            VECTORS_VERSION_START,
+            VECTORS_VERSION_CURRENT,
+            &read_state.segment_info.id,
+            &read_state.segment_suffix,
+        )?;
+        // NOTE: the data file is too costly to verify on open, we only check
+        // the structure of its footer
+        codec_util::retrieve_checksum(data_in.as_mut())?;
+
+        let mut fields = HashMap::with_capacity(metas.len());
+        for meta in metas {
+            let field_info = &read_state.field_infos.by_number[&meta.field_number];
+            let vectors = data_in.random_access_slice(meta.vector_offset, meta.vector_length)?;
+            data_in.seek(meta.graph_offset)?;
+            let graph = HnswGraph::read(data_in.as_mut())?;
+            if data_in.file_pointer() != meta.graph_offset + meta.graph_length {
+                bail!(CorruptIndex(format!(
+                    "invalid hnsw graph of field '{}'",
+                    field_info.name
+                )));
+            }
+            if graph.size() != meta.docs.len() {
+                bail!(CorruptIndex(format!(
+                    "hnsw graph size mismatch for field '{}': {} != {}",
+                    field_info.name,
+                    graph.size(),
+                    meta.docs.len()
+                )));
+            }
+            fields.insert(
+                field_info.name.clone(),
+                FieldEntry {
+                    dimension: meta.dimension,
+                    similarity: field_info.vector_similarity,
+                    docs: meta.docs,
+                    vectors,
+                    graph,
+                },
+            );
+        }
+
+        Ok(HnswVectorsReader { data_in, fields })
+    }
+
+    fn read_fields<T: DataInput + ?Sized>(
+        meta_in: &mut T,
+        field_infos: &FieldInfos,
+    ) -> Result<Vec<FieldMeta>> {
+        let mut metas = vec![];
+        loop {
+            let field_number = meta_in.read_vint()?;
+            if field_number == -1 {
+                break;
+            }
+            let field_info = match field_infos.field_info_by_number(field_number as u32) {
+                Some(fi) => fi,
+                None => bail!(CorruptIndex(format!(
+                    "invalid vector field number: {}",
+                    field_number
+                ))),
+            };
+            let dimension = meta_in.read_vint()? as usize;
+            if dimension != field_info.vector_dimension as usize {
+                bail!(CorruptIndex(format!(
+                    "vector dimension mismatch for field '{}': {} != {}",
+                    field_info.name, dimension, field_info.vector_dimension
+                )));
+            }
+            let size = meta_in.read_vint()? as usize;
+            let mut docs = Vec::with_capacity(size);
+            let mut doc = 0;
+            for _ in 0..size {
+                doc += meta_in.read_vint()?;
+                docs.push(doc);
+            }
+            let vector_offset = meta_in.read_vlong()?;
+            let vector_length = meta_in.read_vlong()?;
+            if vector_length != (size * dimension * 4) as i64 {
+                bail!(CorruptIndex(format!(
+                    "invalid vector data length for field '{}': {}",
+                    field_info.name, vector_length
+                )));
+            }
+            metas.push(FieldMeta {
+                field_number: field_number as u32,
+                dimension,
+                docs,
+                vector_offset,
+                vector_length,
+                graph_offset: meta_in.read_vlong()?,
+                graph_length: meta_in.read_vlong()?,
+            });
+        }
+        Ok(metas)
+    }
+}
+
+struct FieldMeta {
+    field_number: u32,
+    dimension: usize,
+    docs: Vec<DocId>,
+    vector_offset: i64,
+    vector_length: i64,
+    graph_offset: i64,
+    graph_length: i64,
+}
+
+impl VectorsReader for HnswVectorsReader {
+    fn check_integrity(&self) -> Result<()> {
+        codec_util::checksum_entire_file(self.data_in.as_ref())?;
+        Ok(())
+    }
+
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues + '_>>> {
+        match self.fields.get(field) {
+            Some(entry) => Ok(Some(Box::new(OffHeapVectorValues { entry }))),
+            None => Ok(None),
+        }
+    }
+
+    fn search(
+        &self,
+        field: &str,
+        target: &[f32],
+        k: usize,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoreDoc>> {
+        let entry = match self.fields.get(field) {
+            Some(entry) => entry,
+            None => return Ok(vec![]),
+        };
+        if target.len() != entry.dimension {
+            bail!(IllegalArgument(format!(
+                "vector query dimension: {} differs from field dimension: {}",
+                target.len(),
+                entry.dimension
+            )));
+        }
+        let values = OffHeapVectorValues { entry };
+        let nodes = entry.graph.search(target, k, &values, accept_docs)?;
+        Ok(nodes
+            .into_iter()
+            .map(|n| ScoreDoc::new(entry.docs[n.node as usize], n.score))
+            .collect())
+    }
+}
+
+struct OffHeapVectorValues<'a> {
+    entry: &'a FieldEntry,
+}
+
+impl<'a> VectorValues for OffHeapVectorValues<'a> {
+    fn dimension(&self) -> usize {
+        self.entry.dimension
+    }
+
+    fn size(&self) -> usize {
+        self.entry.docs.len()
+    }
+
+    fn similarity(&self) -> VectorSimilarity {
+        self.entry.similarity
+    }
+
+    fn doc(&self, ord: usize) -> DocId {
+        self.entry.docs[ord]
+    }
+
+    fn vector(&self, ord: usize) -> Result<Cow<'_, [f32]>> {
+        let dimension = self.entry.dimension;
+        let start = (ord * dimension * 4) as u64;
+        let mut vector = Vec::with_capacity(dimension);
+        for i in 0..dimension as u64 {
+            let bits = self.entry.vectors.read_int(start + i * 4)?;
+            vector.push(f32::from_bits(bits as u32));
+        }
+        Ok(Cow::Owned(vector))
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::field_infos::FieldInfo;
+use core::codec::segment_infos::{segment_file_name, SegmentWriteState};
+use core::codec::vectors::{BufferedVectorValues, HnswGraph, VectorValues};
+use core::codec::{codec_util, Codec};
+use core::index::merge::{DocMap, MergeState};
+use core::store::directory::Directory;
+use core::store::io::{DataOutput, IndexOutput};
+
+use error::{ErrorKind::IllegalState, Result};
+
+pub const VECTORS_META_CODEC_NAME: &str = "HnswVectorsFormatMeta";
+pub const VECTORS_DATA_CODEC_NAME: &str = "HnswVectorsFormatData";
+
+// Filename extension for the per field metadata
+pub const VECTORS_META_EXTENSION: &str = "vem";
+// Filename extension for the vectors and their graphs
+pub const VECTORS_DATA_EXTENSION: &str = "vec";
+
+pub const VECTORS_VERSION_START: i32 = 0;
+pub const VECTORS_VERSION_CURRENT: i32 = VECTORS_VERSION_START;
+
+/// Writes the vectors of a segment along with the HNSW graph of each field.
+///
+/// The data file holds the vectors of each field as consecutive big endian
+/// floats followed by the field's graph, the metadata file maps each field
+/// number to the documents having a vector and to the offsets of its data.
+pub struct HnswVectorsWriter<D: Directory, DW: Directory, C: Codec> {
+    meta_out: DW::IndexOutput,
+    data_out: DW::IndexOutput,
+    write_state: SegmentWriteState<D, DW, C>,
+    finished: bool,
+}
+
+impl<D: Directory, DW: Directory, C: Codec> HnswVectorsWriter<D, DW, C> {
+    pub fn new(write_state: &SegmentWriteState<D, DW, C>) -> Result<HnswVectorsWriter<D, DW, C>> {
+        let write_state = write_state.clone();
+        let meta_file_name = segment_file_name(
+            &write_state.segment_info.name,
+            &write_state.segment_suffix,
+            VECTORS_META_EXTENSION,
+        );
+        let mut meta_out = write_state
+            .directory
+            .create_output(&meta_file_name, &write_state.context)?;
+        codec_util::write_index_header(
+            &mut meta_out,
+            VECTORS_META_CODEC_NAME,
+            VECTORS_VERSION_CURRENT,
+            write_state.segment_info.get_id(),
+            &write_state.segment_suffix,
+        )?;
+        let data_file_name = segment_file_name(
+            &write_state.segment_info.name,
+            &write_state.segment_suffix,
+            VECTORS_DATA_EXTENSION,
+        );
+        let mut data_out = write_state
+            .directory
+            .create_output(&data_file_name, &write_state.context)?;
+        codec_util::write_index_header(
+            &mut data_out,
+            VECTORS_DATA_CODEC_NAME,
+            VECTORS_VERSION_CURRENT,
+            write_state.segment_info.get_id(),
+            &write_state.segment_suffix,
+        )?;
+
+        Ok(HnswVectorsWriter {
+            meta_out,
+            data_out,
+            write_state,
+            finished: false,
+        })
+    }
+
+    /// Writes the vectors of a field and builds their graph, the vectors must
+    /// be sorted by doc id.
+    pub fn write_field(&mut self, field_info: &FieldInfo, values: &dyn VectorValues) -> Result<()> {
+        debug_assert_eq!(field_info.vector_dimension as usize, values.dimension());
+        let vector_offset = self.data_out.file_pointer();
+        for ord in 0..values.size() {
+            for v in values.vector(ord)?.iter() {
+                self.data_out.write_int(v.to_bits() as i32)?;
+            }
+        }
+        let vector_length = self.data_out.file_pointer() - vector_offset;
+
+        let graph = HnswGraph::build(values)?;
+        let graph_offset = self.data_out.file_pointer();
+        graph.write(&mut self.data_out)?;
+        let graph_length = self.data_out.file_pointer() - graph_offset;
+
+        self.meta_out.write_vint(field_info.number as i32)?;
+        self.meta_out.write_vint(values.dimension() as i32)?;
+        self.meta_out.write_vint(values.size() as i32)?;
+        let mut last_doc = 0;
+        for ord in 0..values.size() {
+            let doc = values.doc(ord);
+            debug_assert!(doc >= last_doc);
+            self.meta_out.write_vint(doc - last_doc)?;
+            last_doc = doc;
+        }
+        self.meta_out.write_vlong(vector_offset)?;
+        self.meta_out.write_vlong(vector_length)?;
+        self.meta_out.write_vlong(graph_offset)?;
+        self.meta_out.write_vlong(graph_length)
+    }
+
+    /// Merges the vectors of the segments, skipping deleted documents. The
+    /// graphs are rebuilt from scratch since the ordinals of the merged
+    /// segment are different.
+    pub fn merge<D1: Directory, C1: Codec>(
+        &mut self,
+        merge_state: &MergeState<D1, C1>,
+    ) -> Result<()> {
+        for field_info in merge_state
+            .merge_field_infos
+            .as_ref()
+            .unwrap()
+            .by_number
+            .values()
+        {
+            if field_info.vector_dimension == 0 {
+                continue;
+            }
+            let mut merged = vec![];
+            for (i, reader) in merge_state.vectors_readers.iter().enumerate() {
+                if let Some(reader) = reader {
+                    if let Some(values) = reader.vector_values(&field_info.name)? {
+                        for ord in 0..values.size() {
+                            let doc = merge_state.doc_maps[i].get(values.doc(ord))?;
+                            if doc != -1 {
+                                merged.push((doc, values.vector(ord)?.into_owned()));
+                            }
+                        }
+                    }
+                }
+            }
+            if merged.is_empty() {
+                continue;
+            }
+            merged.sort_by_key(|(doc, _)| *doc);
+
+            let mut values = BufferedVectorValues::new(
+                field_info.vector_dimension as usize,
+                field_info.vector_similarity,
+            );
+            for (doc, vector) in &merged {
+                values.add(*doc, vector);
+            }
+            self.write_field(field_info, &values)?;
+        }
+
+        self.finish()
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            bail!(IllegalState("already finished".into()));
+        }
+        self.finished = true;
+
+        // end of the fields
+        self.meta_out.write_vint(-1)?;
+        codec_util::write_footer(&mut self.meta_out)?;
+        codec_util::write_footer(&mut self.data_out)
+    }
+}
@@ -150,6 +150,43 @@ impl FloatDocValuesField {
     }
 }
 
+impl Fieldable for FloatDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    // indexed as the raw bits of the value, as the sort comparators decode it
+    fn numeric_value(&self) -> Option<Numeric> {
+        Some(Numeric::Long(
+            i64::from(self.float_value().to_bits() as i32),
+        ))
+    }
+}
+
 pub struct DoubleDocValuesField {
     field: Field,
 }
@@ -171,6 +208,41 @@ impl DoubleDocValuesField {
     }
 }
 
+impl Fieldable for DoubleDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    // indexed as the raw bits of the value, as the sort comparators decode it
+    fn numeric_value(&self) -> Option<Numeric> {
+        Some(Numeric::Long(self.double_value().to_bits() as i64))
+    }
+}
+
 pub struct NumericDocValuesField {
     field: Field,
 }
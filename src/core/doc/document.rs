@@ -17,25 +17,55 @@ use core::util::VariantValue;
 
 use error::Result;
 
+/// What a `StoredFieldVisitor` wants to do with the next stored field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Status {
+    /// The field is read and passed to the visitor.
     Yes,
+    /// The field is skipped without being decoded.
     No,
+    /// The visit of the document ends, the remaining fields aren't read.
     Stop,
 }
 
 /// Expert: provides a low-level means of accessing the stored field
 /// values in an index.
 ///
+/// For each stored field of the document, in the order they were added,
+/// `needs_field` is called first and the value is only decoded and passed to
+/// the matching `add_*_field` method when it returns `Status::Yes`. Returning
+/// `Status::Stop` ends the visit, so a visitor loading a single field, e.g. an
+/// id, doesn't pay for reading the others, see `SingleValueStoredFieldVisitor`.
+/// The `add_*_field` methods ignore the values by default.
+///
 /// NOTE: a `StoredFieldVisitor` implementation should not try to load or visit other
 /// stored documents in the same reader because the implementation of stored fields for
 /// most codecs is not reentrant and you will see strange exceptions as a result.
 pub trait StoredFieldVisitor {
-    fn add_binary_field(&mut self, field_info: &FieldInfo, value: Vec<u8>) -> Result<()>;
-    fn add_string_field(&mut self, field_info: &FieldInfo, value: Vec<u8>) -> Result<()>;
-    fn add_int_field(&mut self, field_info: &FieldInfo, value: i32) -> Result<()>;
-    fn add_long_field(&mut self, field_info: &FieldInfo, value: i64) -> Result<()>;
-    fn add_float_field(&mut self, field_info: &FieldInfo, value: f32) -> Result<()>;
-    fn add_double_field(&mut self, field_info: &FieldInfo, value: f64) -> Result<()>;
+    fn add_binary_field(&mut self, _field_info: &FieldInfo, _value: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    /// `value` holds the UTF-8 bytes of the string.
+    fn add_string_field(&mut self, _field_info: &FieldInfo, _value: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_int_field(&mut self, _field_info: &FieldInfo, _value: i32) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_long_field(&mut self, _field_info: &FieldInfo, _value: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_float_field(&mut self, _field_info: &FieldInfo, _value: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_double_field(&mut self, _field_info: &FieldInfo, _value: f64) -> Result<()> {
+        Ok(())
+    }
 
     fn needs_field(&self, field_info: &FieldInfo) -> Status;
 }
@@ -100,6 +130,8 @@ impl Document {
     }
 }
 
+/// A `StoredFieldVisitor` building a `Document` from the stored fields named
+/// `fields_to_add`, or from all of them if it is empty.
 pub struct DocumentStoredFieldVisitor {
     pub fields: Vec<StoredField>,
     pub fields_to_add: Vec<String>,
@@ -202,3 +234,110 @@ impl StoredFieldVisitor for DocumentStoredFieldVisitor {
         }
     }
 }
+
+/// A `StoredFieldVisitor` loading the first stored value of a single field,
+/// e.g. the id of the document, the visit stops as soon as it is found.
+pub struct SingleValueStoredFieldVisitor {
+    field: String,
+    value: Option<VariantValue>,
+}
+
+impl SingleValueStoredFieldVisitor {
+    pub fn new(field: &str) -> SingleValueStoredFieldVisitor {
+        SingleValueStoredFieldVisitor {
+            field: field.to_string(),
+            value: None,
+        }
+    }
+
+    /// The value found, `None` if the document has no value for the field.
+    pub fn value(&self) -> Option<&VariantValue> {
+        self.value.as_ref()
+    }
+
+    pub fn into_value(self) -> Option<VariantValue> {
+        self.value
+    }
+}
+
+impl StoredFieldVisitor for SingleValueStoredFieldVisitor {
+    fn add_binary_field(&mut self, _field_info: &FieldInfo, value: Vec<u8>) -> Result<()> {
+        self.value = Some(VariantValue::Binary(value));
+        Ok(())
+    }
+
+    fn add_string_field(&mut self, _field_info: &FieldInfo, value: Vec<u8>) -> Result<()> {
+        self.value = Some(VariantValue::VString(String::from_utf8(value)?));
+        Ok(())
+    }
+
+    fn add_int_field(&mut self, _field_info: &FieldInfo, value: i32) -> Result<()> {
+        self.value = Some(VariantValue::Int(value));
+        Ok(())
+    }
+
+    fn add_long_field(&mut self, _field_info: &FieldInfo, value: i64) -> Result<()> {
+        self.value = Some(VariantValue::Long(value));
+        Ok(())
+    }
+
+    fn add_float_field(&mut self, _field_info: &FieldInfo, value: f32) -> Result<()> {
+        self.value = Some(VariantValue::Float(value));
+        Ok(())
+    }
+
+    fn add_double_field(&mut self, _field_info: &FieldInfo, value: f64) -> Result<()> {
+        self.value = Some(VariantValue::Double(value));
+        Ok(())
+    }
+
+    fn needs_field(&self, field_info: &FieldInfo) -> Status {
+        if self.value.is_some() {
+            Status::Stop
+        } else if field_info.name == self.field {
+            Status::Yes
+        } else {
+            Status::No
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn field_info(name: &str, number: u32) -> FieldInfo {
+        FieldInfo::new(
+            name.to_string(),
+            number,
+            false,
+            true,
+            false,
+            IndexOptions::Null,
+            DocValuesType::Null,
+            -1,
+            HashMap::new(),
+            0,
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_single_value_visitor() {
+        let id = field_info("id", 0);
+        let body = field_info("body", 1);
+        let mut visitor = SingleValueStoredFieldVisitor::new("id");
+        assert_eq!(visitor.needs_field(&body), Status::No);
+        assert_eq!(visitor.needs_field(&id), Status::Yes);
+        visitor.add_string_field(&id, b"doc-1".to_vec()).unwrap();
+        // the remaining fields, even other values of the field, aren't read
+        assert_eq!(visitor.needs_field(&id), Status::Stop);
+        assert_eq!(visitor.needs_field(&body), Status::Stop);
+        assert_eq!(
+            visitor.into_value(),
+            Some(VariantValue::VString("doc-1".into()))
+        );
+    }
+}
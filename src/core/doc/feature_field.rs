@@ -0,0 +1,285 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    BytesTermAttribute, OffsetAttribute, PositionAttribute, TermFreqAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+use core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions, VectorSimilarity};
+use core::util::{Numeric, VariantValue};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const FEATURE_FIELD_TYPE: FieldType = FieldType {
+    stored: false,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::DocsAndFreqs,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// The greatest term frequency a feature value can be encoded to.
+pub const FEATURE_MAX_FREQ: u32 = 0x7f7f_ffff >> 15;
+
+/// Encode a feature value as a term frequency, only the 9 most significant
+/// bits of the mantissa are kept, this gives a relative error of about 0.4%.
+pub fn encode_feature_value(value: f32) -> u32 {
+    value.to_bits() >> 15
+}
+
+/// Decode a term frequency produced by `encode_feature_value`.
+pub fn decode_feature_value(freq: f32) -> f32 {
+    if freq > FEATURE_MAX_FREQ as f32 {
+        // the sum of several feature values of the same doc
+        return ::std::f32::MAX;
+    }
+    f32::from_bits((freq as u32) << 15)
+}
+
+/// A field that indexes features that may be used to boost the score of
+/// documents, like a page rank or a recency.
+///
+/// The field name is the name of the feature group and the feature name is
+/// indexed as the term, the feature value is encoded in the term frequency so
+/// that it is available to scorers without a doc values lookup. Feature
+/// values must be positive normal floats, and greater values are expected to
+/// mean more relevant documents. Use `FeatureQuery` to search them.
+#[derive(Debug)]
+pub struct FeatureField {
+    field_name: String,
+    feature_name: String,
+    feature_value: f32,
+}
+
+impl FeatureField {
+    pub fn new(field_name: &str, feature_name: &str, feature_value: f32) -> Result<FeatureField> {
+        Self::check_value(feature_value)?;
+        Ok(FeatureField {
+            field_name: field_name.to_string(),
+            feature_name: feature_name.to_string(),
+            feature_value,
+        })
+    }
+
+    pub fn feature_name(&self) -> &str {
+        &self.feature_name
+    }
+
+    pub fn feature_value(&self) -> f32 {
+        self.feature_value
+    }
+
+    /// Update the feature value of this field, which allows reusing it across
+    /// documents.
+    pub fn set_feature_value(&mut self, feature_value: f32) -> Result<()> {
+        Self::check_value(feature_value)?;
+        self.feature_value = feature_value;
+        Ok(())
+    }
+
+    fn check_value(feature_value: f32) -> Result<()> {
+        if !feature_value.is_finite() {
+            bail!(IllegalArgument(format!(
+                "illegal feature value, must be finite, got: {}",
+                feature_value
+            )));
+        }
+        if feature_value < ::std::f32::MIN_POSITIVE {
+            bail!(IllegalArgument(format!(
+                "illegal feature value, must be a positive normal float, got: {}",
+                feature_value
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Fieldable for FeatureField {
+    fn name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn field_type(&self) -> &FieldType {
+        &FEATURE_FIELD_TYPE
+    }
+
+    fn boost(&self) -> f32 {
+        1.0
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        None
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        Ok(Box::new(FeatureTokenStream::new(
+            self.feature_name.as_bytes(),
+            encode_feature_value(self.feature_value),
+        )))
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+/// A single token stream whose term frequency is the encoded feature value.
+#[derive(Debug)]
+struct FeatureTokenStream {
+    term_attribute: BytesTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    term_freq_attribute: TermFreqAttribute,
+    used: bool,
+    feature_name: Vec<u8>,
+    freq: u32,
+}
+
+impl FeatureTokenStream {
+    fn new(feature_name: &[u8], freq: u32) -> Self {
+        FeatureTokenStream {
+            term_attribute: BytesTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            term_freq_attribute: TermFreqAttribute::new(),
+            used: true,
+            feature_name: feature_name.to_vec(),
+            freq,
+        }
+    }
+}
+
+impl TokenStream for FeatureTokenStream {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.used {
+            return Ok(false);
+        }
+        self.clear_attributes();
+
+        self.term_attribute.set_bytes(&self.feature_name);
+        self.term_freq_attribute.set_term_freq(self.freq);
+        self.used = true;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.used = false;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn term_freq_attribute_mut(&mut self) -> Option<&mut TermFreqAttribute> {
+        Some(&mut self.term_freq_attribute)
+    }
+
+    fn term_freq_attribute(&self) -> Option<&TermFreqAttribute> {
+        Some(&self.term_freq_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_feature_value() {
+        for &value in &[
+            ::std::f32::MIN_POSITIVE,
+            0.01,
+            1.0,
+            3.5,
+            1234.5,
+            ::std::f32::MAX,
+        ] {
+            let freq = encode_feature_value(value);
+            assert!(freq >= 1 && freq <= FEATURE_MAX_FREQ);
+            let decoded = decode_feature_value(freq as f32);
+            assert!(decoded <= value);
+            assert!((value - decoded) / value < 0.004);
+        }
+        // encoding is monotonic
+        assert!(encode_feature_value(2.0) > encode_feature_value(1.0));
+        assert_eq!(
+            decode_feature_value(FEATURE_MAX_FREQ as f32 * 2.0),
+            ::std::f32::MAX
+        );
+    }
+
+    #[test]
+    fn test_feature_field() {
+        assert!(FeatureField::new("features", "pagerank", 0.0).is_err());
+        assert!(FeatureField::new("features", "pagerank", -1.0).is_err());
+        assert!(FeatureField::new("features", "pagerank", ::std::f32::NAN).is_err());
+        assert!(FeatureField::new("features", "pagerank", ::std::f32::INFINITY).is_err());
+
+        let mut field = FeatureField::new("features", "pagerank", 2.5).unwrap();
+        assert!(field.set_feature_value(-2.0).is_err());
+        assert!((field.feature_value() - 2.5).abs() < ::std::f32::EPSILON);
+
+        let mut stream = field.token_stream().unwrap();
+        stream.reset().unwrap();
+        assert!(stream.increment_token().unwrap());
+        assert_eq!(
+            stream.term_bytes_attribute().get_bytes_ref().bytes(),
+            b"pagerank"
+        );
+        assert_eq!(stream.term_freq(), encode_feature_value(2.5));
+        assert!(!stream.increment_token().unwrap());
+        stream.end().unwrap();
+        assert_eq!(stream.term_freq(), 1);
+    }
+}
@@ -12,7 +12,7 @@
 // limitations under the License.
 
 use core::analysis::{BinaryTokenStream, StringTokenStream, TokenStream};
-use core::doc::{DocValuesType, IndexOptions};
+use core::doc::{DocValuesType, IndexOptions, VectorSimilarity, VECTOR_MAX_DIMENSIONS};
 use core::util::{BytesRef, Numeric, VariantValue};
 
 use error::ErrorKind::IllegalArgument;
@@ -149,6 +149,15 @@ pub trait Fieldable {
     fn binary_value(&self) -> Option<&[u8]>;
     fn string_value(&self) -> Option<&str>;
     fn numeric_value(&self) -> Option<Numeric>;
+    /// The vector indexed for this field if its type has a vector dimension.
+    fn vector_value(&self) -> Option<&[f32]> {
+        None
+    }
+    /// The name of the postings format the terms of this field are written
+    /// with, the default one if `None`.
+    fn postings_format(&self) -> Option<&str> {
+        None
+    }
 }
 
 impl<T: Fieldable + ?Sized> Fieldable for Box<T> {
@@ -176,6 +185,12 @@ impl<T: Fieldable + ?Sized> Fieldable for Box<T> {
     fn numeric_value(&self) -> Option<Numeric> {
         (**self).numeric_value()
     }
+    fn vector_value(&self) -> Option<&[f32]> {
+        (**self).vector_value()
+    }
+    fn postings_format(&self) -> Option<&str> {
+        (**self).postings_format()
+    }
 }
 
 #[derive(Clone, PartialEq, Hash, Serialize, Debug)]
@@ -191,6 +206,8 @@ pub struct FieldType {
     pub doc_values_type: DocValuesType,
     pub dimension_count: u32,
     pub dimension_num_bytes: u32,
+    pub vector_dimension: u32,
+    pub vector_similarity: VectorSimilarity,
 }
 
 impl Default for FieldType {
@@ -207,6 +224,8 @@ impl Default for FieldType {
             doc_values_type: DocValuesType::Null,
             dimension_count: 0,
             dimension_num_bytes: 0,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
         }
     }
 }
@@ -243,6 +262,8 @@ impl FieldType {
             doc_values_type,
             dimension_count,
             dimension_num_bytes,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
         }
     }
 
@@ -343,6 +364,37 @@ impl FieldType {
 
         Ok(())
     }
+
+    /// The type of a field only indexing points of `dimension_count` dimensions
+    /// of `dimension_num_bytes` bytes each.
+    pub fn point(dimension_count: u32, dimension_num_bytes: u32) -> Result<FieldType> {
+        if dimension_count == 0 {
+            bail!(IllegalArgument("dimension_count must be >= 1".into()));
+        }
+        let mut field_type = FieldType::default();
+        field_type.tokenized = false;
+        field_type.omit_norms = true;
+        field_type.set_dimensions(dimension_count, dimension_num_bytes)?;
+        Ok(field_type)
+    }
+
+    pub fn set_vector_attributes(
+        &mut self,
+        vector_dimension: u32,
+        vector_similarity: VectorSimilarity,
+    ) -> Result<()> {
+        if vector_dimension == 0 || vector_dimension > VECTOR_MAX_DIMENSIONS {
+            bail!(IllegalArgument(format!(
+                "vector_dimension must be > 0 and <= {}, got {}",
+                VECTOR_MAX_DIMENSIONS, vector_dimension
+            )));
+        }
+
+        self.vector_dimension = vector_dimension;
+        self.vector_similarity = vector_similarity;
+
+        Ok(())
+    }
 }
 
 impl fmt::Display for FieldType {
@@ -367,6 +419,8 @@ pub const NUMERIC_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     doc_values_type: DocValuesType::Numeric,
     dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -381,6 +435,8 @@ pub const SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     doc_values_type: DocValuesType::SortedNumeric,
     dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const BINARY_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -395,6 +451,8 @@ pub const BINARY_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     doc_values_type: DocValuesType::Binary,
     dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const SORTED_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -409,6 +467,8 @@ pub const SORTED_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     doc_values_type: DocValuesType::Sorted,
     dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const SORTED_SET_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -423,6 +483,8 @@ pub const SORTED_SET_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     doc_values_type: DocValuesType::SortedSet,
     dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const STORE_FIELD_TYPE: FieldType = FieldType {
@@ -437,4 +499,76 @@ pub const STORE_FIELD_TYPE: FieldType = FieldType {
     doc_values_type: DocValuesType::Null,
     dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// The type of the `StringField`s: indexed as a single token without
+/// frequencies nor norms, not stored.
+pub const STRING_FIELD_TYPE_NOT_STORED: FieldType = FieldType {
+    stored: false,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::Docs,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// The type of the stored `StringField`s.
+pub const STRING_FIELD_TYPE_STORED: FieldType = FieldType {
+    stored: true,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::Docs,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// The type of the `TextField`s: tokenized and indexed with frequencies and
+/// positions, not stored.
+pub const TEXT_FIELD_TYPE_NOT_STORED: FieldType = FieldType {
+    stored: false,
+    tokenized: true,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: false,
+    index_options: IndexOptions::DocsAndFreqsAndPositions,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// The type of the stored `TextField`s.
+pub const TEXT_FIELD_TYPE_STORED: FieldType = FieldType {
+    stored: true,
+    tokenized: true,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: false,
+    index_options: IndexOptions::DocsAndFreqsAndPositions,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
@@ -0,0 +1,141 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::doc::{Field, FieldType, IndexOptions};
+use core::geo::{
+    check_latitude, check_longitude, geohash_encode, geohash_precision_for, Rectangle,
+    MAX_GEOHASH_PRECISION,
+};
+use core::util::VariantValue;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// The default share of the size of a query box its cover may spill over.
+pub const DEFAULT_DIST_ERR_PCT: f64 = 0.025;
+
+/// Indexes locations as the geohashes of their cells at every precision up
+/// to `max_levels`, a prefix tree of the cells.
+///
+/// The docs can then be bucketed by cell with a term query on a geohash, and
+/// a box is matched by the few large cells within it plus the small cells
+/// crossing its edges. The smaller cells are only used where needed, down to
+/// the precision whose cells are `dist_err_pct` of the size of the box: the
+/// matches are approximate, they may spill over the box by up to a cell.
+pub struct GeoHashPrefixTreeStrategy {
+    field_name: String,
+    max_levels: usize,
+    dist_err_pct: f64,
+}
+
+impl GeoHashPrefixTreeStrategy {
+    pub fn new(field_name: &str, max_levels: usize) -> Result<GeoHashPrefixTreeStrategy> {
+        if max_levels == 0 || max_levels > MAX_GEOHASH_PRECISION {
+            bail!(IllegalArgument(format!(
+                "max_levels must be between 1 and {}, got {}",
+                MAX_GEOHASH_PRECISION, max_levels
+            )));
+        }
+        Ok(GeoHashPrefixTreeStrategy {
+            field_name: field_name.to_string(),
+            max_levels,
+            dist_err_pct: DEFAULT_DIST_ERR_PCT,
+        })
+    }
+
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    pub fn max_levels(&self) -> usize {
+        self.max_levels
+    }
+
+    pub fn set_dist_err_pct(&mut self, dist_err_pct: f64) -> Result<()> {
+        if !(dist_err_pct >= 0.0 && dist_err_pct <= 0.5) {
+            bail!(IllegalArgument(format!(
+                "dist_err_pct must be between 0 and 0.5, got {}",
+                dist_err_pct
+            )));
+        }
+        self.dist_err_pct = dist_err_pct;
+        Ok(())
+    }
+
+    /// Creates the fields indexing a location, one per level of the tree.
+    pub fn create_fields(&self, latitude: f64, longitude: f64) -> Result<Vec<Field>> {
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        let geohash = geohash_encode(latitude, longitude, self.max_levels)?;
+        let mut field_type = FieldType::default();
+        field_type.tokenized = false;
+        field_type.omit_norms = true;
+        field_type.index_options = IndexOptions::Docs;
+        Ok((1..=self.max_levels)
+            .map(|level| {
+                Field::new(
+                    self.field_name.clone(),
+                    field_type.clone(),
+                    Some(VariantValue::VString(geohash[..level].to_string())),
+                    None,
+                )
+            })
+            .collect())
+    }
+
+    /// The precision of the cells crossing the edges of a box in its cover.
+    pub fn detail_level(&self, rect: &Rectangle) -> usize {
+        let lon_span = if rect.crosses_dateline() {
+            rect.max_lon - rect.min_lon + 360.0
+        } else {
+            rect.max_lon - rect.min_lon
+        };
+        let span = lon_span.max(rect.max_lat - rect.min_lat);
+        geohash_precision_for(span * self.dist_err_pct).min(self.max_levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::doc::Fieldable;
+
+    #[test]
+    fn test_create_fields() {
+        let strategy = GeoHashPrefixTreeStrategy::new("cell", 5).unwrap();
+        let fields = strategy.create_fields(48.858_37, 2.294_481).unwrap();
+        let cells: Vec<_> = fields.iter().map(|f| f.string_value().unwrap()).collect();
+        assert_eq!(cells, vec!["u", "u0", "u09", "u09t", "u09tu"]);
+        assert_eq!(fields[0].name(), "cell");
+        assert!(strategy.create_fields(91.0, 0.0).is_err());
+
+        assert!(GeoHashPrefixTreeStrategy::new("cell", 0).is_err());
+        assert!(GeoHashPrefixTreeStrategy::new("cell", 13).is_err());
+    }
+
+    #[test]
+    fn test_detail_level() {
+        let mut strategy = GeoHashPrefixTreeStrategy::new("cell", 8).unwrap();
+        let rect = Rectangle::new(0.0, 40.0, 0.0, 40.0).unwrap();
+        // cells of 1 degree at most
+        assert_eq!(strategy.detail_level(&rect), 4);
+        strategy.set_dist_err_pct(0.5).unwrap();
+        assert_eq!(strategy.detail_level(&rect), 2);
+        // a point
+        let rect = Rectangle::new(1.0, 1.0, 2.0, 2.0).unwrap();
+        assert_eq!(strategy.detail_level(&rect), 8);
+        // 2 degrees wide across the dateline
+        let rect = Rectangle::new(0.0, 0.0, 179.0, -179.0).unwrap();
+        assert_eq!(strategy.detail_level(&rect), 4);
+        assert!(strategy.set_dist_err_pct(0.6).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::doc::{Field, FieldType, Fieldable, SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE};
+use core::geo::{decode_latitude, decode_longitude, encode_latitude, encode_longitude};
+use core::search::sort_field::{LatLonDistanceSortField, SortField};
+use core::util::{Numeric, VariantValue};
+
+use error::Result;
+
+/// A per-document location in sorted numeric doc values, for sorting by
+/// distance and for the doc values geo queries.
+///
+/// The latitude and longitude are quantized like `LatLonPoint`, in the high
+/// and the low 32 bits of the value. Multiple values for the same field in
+/// one document is allowed.
+pub struct LatLonDocValuesField {
+    field: Field,
+}
+
+impl LatLonDocValuesField {
+    pub fn new(name: &str, latitude: f64, longitude: f64) -> Result<LatLonDocValuesField> {
+        Ok(LatLonDocValuesField {
+            field: Field::new(
+                String::from(name),
+                SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE,
+                Some(VariantValue::Long(Self::encode(latitude, longitude)?)),
+                None,
+            ),
+        })
+    }
+
+    /// Update the location of this field, which allows reusing it across
+    /// documents.
+    pub fn set_location(&mut self, latitude: f64, longitude: f64) -> Result<()> {
+        let value = Self::encode(latitude, longitude)?;
+        self.field.set_field_data(Some(VariantValue::Long(value)));
+        Ok(())
+    }
+
+    /// Encode a location as a doc value.
+    pub fn encode(latitude: f64, longitude: f64) -> Result<i64> {
+        let lat = encode_latitude(latitude)?;
+        let lon = encode_longitude(longitude)?;
+        Ok((i64::from(lat) << 32) | (i64::from(lon) & 0xFFFF_FFFF))
+    }
+
+    /// Create a sort by ascending distance of the values of `field` to the
+    /// location, see `LatLonDistanceSortField`.
+    pub fn new_distance_sort(field: &str, latitude: f64, longitude: f64) -> Result<SortField> {
+        Ok(SortField::LatLonDistance(LatLonDistanceSortField::new(
+            field.to_string(),
+            latitude,
+            longitude,
+        )?))
+    }
+
+    /// The quantized latitude of a doc value.
+    pub fn decode_latitude(value: i64) -> f64 {
+        decode_latitude((value >> 32) as i32)
+    }
+
+    /// The quantized longitude of a doc value.
+    pub fn decode_longitude(value: i64) -> f64 {
+        decode_longitude(value as i32)
+    }
+}
+
+impl Fieldable for LatLonDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        self.field.numeric_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        for &(lat, lon) in &[(0.0, 0.0), (-90.0, -180.0), (45.5, -73.6), (-33.9, 151.2)] {
+            let value = LatLonDocValuesField::encode(lat, lon).unwrap();
+            assert!((LatLonDocValuesField::decode_latitude(value) - lat).abs() < 1e-7);
+            assert!((LatLonDocValuesField::decode_longitude(value) - lon).abs() < 1e-7);
+        }
+        assert!(LatLonDocValuesField::encode(0.0, 180.5).is_err());
+    }
+}
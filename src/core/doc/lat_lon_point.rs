@@ -0,0 +1,141 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions, VectorSimilarity};
+use core::geo::{decode_latitude, decode_longitude, encode_latitude, encode_longitude};
+use core::search::query::IntPoint;
+use core::util::{Numeric, VariantValue};
+
+use error::ErrorKind::UnsupportedOperation;
+use error::Result;
+
+use std::borrow::Cow;
+
+pub const LAT_LON_POINT_FIELD_TYPE: FieldType = FieldType {
+    stored: false,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::Null,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 2,
+    dimension_num_bytes: 4,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// An indexed location for fast geo queries, the latitude and longitude are
+/// quantized to 32 bits each and indexed as a 2 dimensions point, so the
+/// precision is about 1cm.
+///
+/// Multiple values for the same field in one document is allowed. If you
+/// also need to store the value, you should add a separate `StoredField`.
+#[derive(Debug)]
+pub struct LatLonPoint {
+    field_name: String,
+    packed: Vec<u8>,
+}
+
+impl LatLonPoint {
+    pub fn new(field_name: &str, latitude: f64, longitude: f64) -> Result<LatLonPoint> {
+        Ok(LatLonPoint {
+            field_name: field_name.to_string(),
+            packed: Self::pack(latitude, longitude)?,
+        })
+    }
+
+    /// Update the location of this field, which allows reusing it across
+    /// documents.
+    pub fn set_location(&mut self, latitude: f64, longitude: f64) -> Result<()> {
+        self.packed = Self::pack(latitude, longitude)?;
+        Ok(())
+    }
+
+    /// The quantized latitude of this point.
+    pub fn latitude(&self) -> f64 {
+        decode_latitude(IntPoint::decode_dimension(&self.packed[..4]))
+    }
+
+    /// The quantized longitude of this point.
+    pub fn longitude(&self) -> f64 {
+        decode_longitude(IntPoint::decode_dimension(&self.packed[4..]))
+    }
+
+    /// Encode a location as the packed value of the point.
+    pub fn pack(latitude: f64, longitude: f64) -> Result<Vec<u8>> {
+        let mut packed = vec![0u8; 8];
+        IntPoint::encode_dimension(encode_latitude(latitude)?, &mut packed[..4]);
+        IntPoint::encode_dimension(encode_longitude(longitude)?, &mut packed[4..]);
+        Ok(packed)
+    }
+}
+
+impl Fieldable for LatLonPoint {
+    fn name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn field_type(&self) -> &FieldType {
+        &LAT_LON_POINT_FIELD_TYPE
+    }
+
+    fn boost(&self) -> f32 {
+        1.0
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        None
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "lat lon points are not tokenized"
+        )))
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        Some(&self.packed)
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lat_lon_point() {
+        let mut point = LatLonPoint::new("location", 48.858_37, 2.294_481).unwrap();
+        assert_eq!(point.binary_value().unwrap().len(), 8);
+        assert!((point.latitude() - 48.858_37).abs() < 1e-7);
+        assert!((point.longitude() - 2.294_481).abs() < 1e-7);
+
+        point.set_location(-33.856_784, 151.215_297).unwrap();
+        assert!((point.latitude() + 33.856_784).abs() < 1e-7);
+        assert!((point.longitude() - 151.215_297).abs() < 1e-7);
+
+        assert!(LatLonPoint::new("location", 90.1, 0.0).is_err());
+        assert!(point.set_location(0.0, -180.5).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions, VectorSimilarity};
+use core::geo::{
+    decode_latitude, decode_longitude, encode_latitude, encode_longitude, tessellate, Line,
+    Polygon, Triangle,
+};
+use core::search::query::IntPoint;
+use core::util::{Numeric, VariantValue};
+
+use error::ErrorKind::UnsupportedOperation;
+use error::Result;
+
+use std::borrow::Cow;
+
+pub const LAT_LON_SHAPE_FIELD_TYPE: FieldType = FieldType {
+    stored: false,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::Null,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 6,
+    dimension_num_bytes: 4,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// Creates the fields indexing geo shapes, e.g. geofences or routes.
+///
+/// The shapes are split into triangles, each indexed as a 6 dimensions
+/// point of the quantized latitudes and longitudes of its vertices: the
+/// polygons are tessellated, the lines are indexed segment by segment as
+/// triangles degenerated to a segment, and the points as triangles
+/// degenerated to a point. All the fields of a shape are added to the
+/// document, and they can be mixed with the fields of other shapes.
+pub struct LatLonShape;
+
+impl LatLonShape {
+    pub fn create_point_fields(
+        field_name: &str,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Vec<LatLonShapeField>> {
+        let triangle = Triangle::new(
+            latitude, longitude, latitude, longitude, latitude, longitude,
+        );
+        Ok(vec![LatLonShapeField::new(field_name, &triangle)?])
+    }
+
+    pub fn create_line_fields(field_name: &str, line: &Line) -> Result<Vec<LatLonShapeField>> {
+        let (lats, lons) = (line.lats(), line.lons());
+        (1..lats.len())
+            .map(|i| {
+                let triangle = Triangle::new(
+                    lats[i - 1],
+                    lons[i - 1],
+                    lats[i],
+                    lons[i],
+                    lats[i - 1],
+                    lons[i - 1],
+                );
+                LatLonShapeField::new(field_name, &triangle)
+            })
+            .collect()
+    }
+
+    /// Errors if the polygon can't be tessellated.
+    pub fn create_polygon_fields(
+        field_name: &str,
+        polygon: &Polygon,
+    ) -> Result<Vec<LatLonShapeField>> {
+        tessellate(polygon)?
+            .iter()
+            .map(|triangle| LatLonShapeField::new(field_name, triangle))
+            .collect()
+    }
+
+    /// Encode a triangle as the packed value of the point.
+    pub fn pack(triangle: &Triangle) -> Result<Vec<u8>> {
+        let mut packed = vec![0u8; 24];
+        for (i, &(lat, lon)) in triangle.vertices().iter().enumerate() {
+            IntPoint::encode_dimension(encode_latitude(lat)?, &mut packed[i * 8..]);
+            IntPoint::encode_dimension(encode_longitude(lon)?, &mut packed[i * 8 + 4..]);
+        }
+        Ok(packed)
+    }
+
+    /// Decode the quantized triangle of a packed value.
+    pub fn unpack(packed: &[u8]) -> Triangle {
+        let lat = |i: usize| decode_latitude(IntPoint::decode_dimension(&packed[i * 8..]));
+        let lon = |i: usize| decode_longitude(IntPoint::decode_dimension(&packed[i * 8 + 4..]));
+        Triangle::new(lat(0), lon(0), lat(1), lon(1), lat(2), lon(2))
+    }
+}
+
+/// A triangle of an indexed shape, see `LatLonShape`.
+#[derive(Debug)]
+pub struct LatLonShapeField {
+    field_name: String,
+    packed: Vec<u8>,
+}
+
+impl LatLonShapeField {
+    pub fn new(field_name: &str, triangle: &Triangle) -> Result<LatLonShapeField> {
+        Ok(LatLonShapeField {
+            field_name: field_name.to_string(),
+            packed: LatLonShape::pack(triangle)?,
+        })
+    }
+
+    /// The quantized triangle of this field.
+    pub fn triangle(&self) -> Triangle {
+        LatLonShape::unpack(&self.packed)
+    }
+}
+
+impl Fieldable for LatLonShapeField {
+    fn name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn field_type(&self) -> &FieldType {
+        &LAT_LON_SHAPE_FIELD_TYPE
+    }
+
+    fn boost(&self) -> f32 {
+        1.0
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        None
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "lat lon shapes are not tokenized"
+        )))
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        Some(&self.packed)
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_fields() {
+        let fields = LatLonShape::create_point_fields("shape", 48.858_37, 2.294_481).unwrap();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].binary_value().unwrap().len(), 24);
+        let t = fields[0].triangle();
+        assert!((t.a_lat - 48.858_37).abs() < 1e-7 && (t.c_lon - 2.294_481).abs() < 1e-7);
+
+        let line = Line::new(vec![0.0, 1.0, 2.0], vec![10.0, 11.0, 10.0]).unwrap();
+        let fields = LatLonShape::create_line_fields("shape", &line).unwrap();
+        assert_eq!(fields.len(), 2);
+        let t = fields[1].triangle();
+        assert!((t.a_lat - 1.0).abs() < 1e-7 && (t.b_lon - 10.0).abs() < 1e-7);
+        assert_eq!((t.a_lat, t.a_lon), (t.c_lat, t.c_lon));
+
+        let polygon = Polygon::new(
+            vec![0.0, 0.0, 1.0, 1.0, 3.0, 3.0, 0.0],
+            vec![0.0, 3.0, 3.0, 1.0, 1.0, 0.0, 0.0],
+        )
+        .unwrap();
+        let fields = LatLonShape::create_polygon_fields("shape", &polygon).unwrap();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[0].name(), "shape");
+        assert!(LatLonShape::create_point_fields("shape", 91.0, 0.0).is_err());
+    }
+}
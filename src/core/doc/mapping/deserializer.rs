@@ -0,0 +1,303 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use core::codec::field_infos::FieldInfo;
+use core::doc::{Status, StoredFieldVisitor};
+use core::util::VariantValue;
+
+use error::{Error, ErrorKind::IllegalArgument, Result};
+
+/// A value read back for a field, either from the stored fields or from the
+/// doc values, whose numbers are only decoded once the type of the struct
+/// field is known.
+#[derive(Debug)]
+pub(crate) enum FieldValue {
+    Stored(VariantValue),
+    /// A numeric doc value, the raw bits of the floats.
+    DocValue(i64),
+    /// A binary, sorted or sorted set doc value.
+    DocBytes(Vec<u8>),
+}
+
+/// Collects the values of all the stored fields of a document.
+#[derive(Default)]
+pub(crate) struct FieldValuesVisitor {
+    pub values: HashMap<String, Vec<FieldValue>>,
+}
+
+impl FieldValuesVisitor {
+    fn add(&mut self, field_info: &FieldInfo, value: VariantValue) -> Result<()> {
+        self.values
+            .entry(field_info.name.clone())
+            .or_insert_with(Vec::new)
+            .push(FieldValue::Stored(value));
+        Ok(())
+    }
+}
+
+impl StoredFieldVisitor for FieldValuesVisitor {
+    fn add_binary_field(&mut self, field_info: &FieldInfo, value: Vec<u8>) -> Result<()> {
+        self.add(field_info, VariantValue::Binary(value))
+    }
+
+    fn add_string_field(&mut self, field_info: &FieldInfo, value: Vec<u8>) -> Result<()> {
+        self.add(field_info, VariantValue::VString(String::from_utf8(value)?))
+    }
+
+    fn add_int_field(&mut self, field_info: &FieldInfo, value: i32) -> Result<()> {
+        self.add(field_info, VariantValue::Int(value))
+    }
+
+    fn add_long_field(&mut self, field_info: &FieldInfo, value: i64) -> Result<()> {
+        self.add(field_info, VariantValue::Long(value))
+    }
+
+    fn add_float_field(&mut self, field_info: &FieldInfo, value: f32) -> Result<()> {
+        self.add(field_info, VariantValue::Float(value))
+    }
+
+    fn add_double_field(&mut self, field_info: &FieldInfo, value: f64) -> Result<()> {
+        self.add(field_info, VariantValue::Double(value))
+    }
+
+    fn needs_field(&self, _field_info: &FieldInfo) -> Status {
+        Status::Yes
+    }
+}
+
+/// Deserializes a struct from the values of its fields, the fields without
+/// values being left to serde, e.g. failing unless they have a default.
+pub(crate) fn deserialize_document<T: DeserializeOwned>(
+    values: HashMap<String, Vec<FieldValue>>,
+) -> Result<T> {
+    let fields = values
+        .into_iter()
+        .map(|(name, values)| (name.clone(), FieldValues { name, values }));
+    T::deserialize(MapDeserializer::<_, Error>::new(fields))
+}
+
+/// The values of a struct field, a sequence of all of them or its single
+/// value.
+struct FieldValues {
+    name: String,
+    values: Vec<FieldValue>,
+}
+
+impl FieldValues {
+    fn single(mut self) -> Result<FieldValue> {
+        if self.values.len() != 1 {
+            bail!(IllegalArgument(format!(
+                "field '{}' has {} values, expected one",
+                self.name,
+                self.values.len()
+            )));
+        }
+        Ok(self.values.pop().unwrap())
+    }
+}
+
+macro_rules! forward_to_single_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for FieldValues {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.values.len() == 1 {
+            self.single()?.deserialize_any(visitor)
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    forward_to_single_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.values.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqDeserializer::new(self.values.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        tuple_struct map struct ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for FieldValues {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl FieldValue {
+    fn into_string(self) -> Result<String> {
+        match self {
+            FieldValue::Stored(VariantValue::VString(s)) => Ok(s),
+            FieldValue::Stored(VariantValue::Binary(b)) | FieldValue::DocBytes(b) => {
+                Ok(String::from_utf8(b)?)
+            }
+            v => bail!(IllegalArgument(format!("expected a string, got {:?}", v))),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for FieldValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            FieldValue::Stored(value) => match value {
+                VariantValue::Bool(v) => visitor.visit_bool(v),
+                VariantValue::Char(v) => visitor.visit_char(v),
+                VariantValue::Short(v) => visitor.visit_i16(v),
+                VariantValue::Int(v) => visitor.visit_i32(v),
+                VariantValue::Long(v) => visitor.visit_i64(v),
+                VariantValue::Float(v) => visitor.visit_f32(v),
+                VariantValue::Double(v) => visitor.visit_f64(v),
+                VariantValue::VString(v) => visitor.visit_string(v),
+                VariantValue::Binary(v) => visitor.visit_byte_buf(v),
+                VariantValue::Map(_) => {
+                    bail!(IllegalArgument("map values can't be deserialized".into()))
+                }
+            },
+            FieldValue::DocValue(v) => visitor.visit_i64(v),
+            FieldValue::DocBytes(v) => visitor.visit_byte_buf(v),
+        }
+    }
+
+    // the bools are stored and added as doc values as ints
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            FieldValue::Stored(VariantValue::Int(v)) => visitor.visit_bool(v != 0),
+            FieldValue::DocValue(v) => visitor.visit_bool(v != 0),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            FieldValue::DocValue(v) => visitor.visit_f32(f32::from_bits(v as u32)),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            FieldValue::DocValue(v) => visitor.visit_f64(f64::from_bits(v as u64)),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.into_string()?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // the enums without data are mapped as the names of their variants
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let variant: de::value::StringDeserializer<Error> = self.into_string()?.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 u8 u16 u32 u64 bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for FieldValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
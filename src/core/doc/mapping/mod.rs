@@ -0,0 +1,457 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Maps the structs implementing serde's `Serialize` to the fields of a
+//! document and reads them back through `Deserialize`.
+//!
+//! The way each struct field is indexed is given by a `FieldMapping`
+//! registered in a `DocumentMapping` under the name of the field:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Product {
+//!     id: String,
+//!     title: String,
+//!     price: f64,
+//!     #[serde(default)]
+//!     tags: Vec<String>,
+//! }
+//!
+//! let mut mapping = DocumentMapping::new();
+//! mapping.set_analyzer(Box::new(WhitespaceAnalyzer::default()));
+//! mapping.add_field("id", FieldMapping::new(true).indexed(FieldIndexing::String));
+//! mapping.add_field("title", FieldMapping::new(true).indexed(FieldIndexing::Text));
+//! mapping.add_field("price", FieldMapping::new(false).indexed(FieldIndexing::Point).doc_values());
+//! mapping.add_field("tags", FieldMapping::new(false).indexed(FieldIndexing::String).doc_values());
+//!
+//! writer.add_document(mapping.to_document(&product)?)?;
+//! let product: Product = mapping.read_document(&reader, doc_id)?;
+//! ```
+//!
+//! The scalar values are mapped as follows, `Option`s are mapped as their
+//! value when present and skipped otherwise, the enums without data as the
+//! names of their variants. The sequences are multi-valued fields.
+//!
+//! | Rust type | Stored as | Point | Doc values |
+//! |---|---|---|---|
+//! | `bool`, `i8`, `i16`, `i32`, `u8`, `u16` | int | `IntPoint` | numeric |
+//! | `i64`, `u32`, `u64` | long | `LongPoint` | numeric |
+//! | `f32` | float | `FloatPoint` | numeric, raw bits |
+//! | `f64` | double | `DoublePoint` | numeric, raw bits |
+//! | `char`, `String`, unit variants | string | - | sorted |
+//! | bytes | binary | - | sorted |
+//!
+//! The numeric doc values of a sequence are sorted numeric doc values, its
+//! strings and bytes sorted set doc values.
+
+mod deserializer;
+
+use self::deserializer::*;
+
+mod serializer;
+
+use self::serializer::*;
+
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use core::analysis::Analyzer;
+use core::codec::doc_values::NO_MORE_ORDS;
+use core::codec::Codec;
+use core::doc::{DocValuesType, Document, Fieldable};
+use core::index::reader::{IndexReader, LeafReader, LeafReaderContext};
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// How the values of a field are indexed, besides being stored or added as
+/// doc values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldIndexing {
+    /// The values aren't indexed.
+    None,
+    /// The strings or bytes are indexed verbatim as a single token, as a
+    /// `StringField`.
+    String,
+    /// The strings are tokenized by the analyzer of the `DocumentMapping`, as
+    /// a `TextField`.
+    Text,
+    /// The numbers are indexed as 1 dimension points, e.g. as an `IntPoint`.
+    Point,
+}
+
+/// The options of a field of a `DocumentMapping`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldMapping {
+    pub stored: bool,
+    pub indexing: FieldIndexing,
+    pub doc_values: bool,
+}
+
+impl FieldMapping {
+    /// A field only stored when `stored` is true, neither indexed nor added as
+    /// doc values.
+    pub fn new(stored: bool) -> FieldMapping {
+        FieldMapping {
+            stored,
+            indexing: FieldIndexing::None,
+            doc_values: false,
+        }
+    }
+
+    pub fn indexed(mut self, indexing: FieldIndexing) -> FieldMapping {
+        self.indexing = indexing;
+        self
+    }
+
+    /// Also adds the values as doc values, for sorting, faceting or reading
+    /// back the fields which aren't stored.
+    pub fn doc_values(mut self) -> FieldMapping {
+        self.doc_values = true;
+        self
+    }
+}
+
+impl Default for FieldMapping {
+    fn default() -> FieldMapping {
+        FieldMapping::new(true)
+    }
+}
+
+/// Converts the values of a struct to the fields of a document according to
+/// the `FieldMapping`s of its fields, and the stored fields and doc values
+/// of a document back to the struct, see the module docs.
+///
+/// The fields without a mapping are only stored, unless the default mapping
+/// is changed by `set_default_mapping`.
+pub struct DocumentMapping {
+    fields: HashMap<String, FieldMapping>,
+    default_mapping: FieldMapping,
+    analyzer: Option<Box<dyn Analyzer>>,
+}
+
+impl Default for DocumentMapping {
+    fn default() -> DocumentMapping {
+        DocumentMapping {
+            fields: HashMap::new(),
+            default_mapping: FieldMapping::default(),
+            analyzer: None,
+        }
+    }
+}
+
+impl DocumentMapping {
+    pub fn new() -> DocumentMapping {
+        DocumentMapping::default()
+    }
+
+    pub fn add_field(&mut self, name: &str, mapping: FieldMapping) {
+        self.fields.insert(name.to_string(), mapping);
+    }
+
+    pub fn set_default_mapping(&mut self, mapping: FieldMapping) {
+        self.default_mapping = mapping;
+    }
+
+    /// Sets the analyzer tokenizing the fields indexed as `FieldIndexing::Text`.
+    pub fn set_analyzer(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.analyzer = Some(analyzer);
+    }
+
+    pub fn field_mapping(&self, name: &str) -> &FieldMapping {
+        self.fields.get(name).unwrap_or(&self.default_mapping)
+    }
+
+    pub(crate) fn analyzer(&self) -> Option<&dyn Analyzer> {
+        self.analyzer.as_ref().map(|a| a.as_ref())
+    }
+
+    /// Converts `value`, which must serialize as a struct, to the fields of a
+    /// document ready to be added to an `IndexWriter`.
+    pub fn to_document<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<Box<dyn Fieldable>>> {
+        let mut serializer = DocumentSerializer::new(self);
+        value.serialize(&mut serializer)?;
+        Ok(serializer.into_fields())
+    }
+
+    /// Reads back the document `doc_id` of `reader` from its stored fields,
+    /// and from the doc values of the mapped fields which aren't stored.
+    pub fn read_document<R, T>(&self, reader: &R, doc_id: DocId) -> Result<T>
+    where
+        R: IndexReader + ?Sized,
+        T: DeserializeOwned,
+    {
+        if doc_id < 0 || doc_id >= reader.max_doc() {
+            bail!(IllegalArgument(format!(
+                "doc_id {} invalid: [max_doc={}]",
+                doc_id,
+                reader.max_doc()
+            )));
+        }
+        let leaf = reader.leaf_reader_for_doc(doc_id);
+        self.read_leaf_document(&leaf, doc_id - leaf.doc_base)
+    }
+
+    /// Same as `read_document` with `doc_id` relative to the segment of `leaf`.
+    pub fn read_leaf_document<C, T>(
+        &self,
+        leaf: &LeafReaderContext<'_, C>,
+        doc_id: DocId,
+    ) -> Result<T>
+    where
+        C: Codec,
+        T: DeserializeOwned,
+    {
+        let mut visitor = FieldValuesVisitor::default();
+        LeafReader::document(leaf.reader, doc_id, &mut visitor)?;
+        let mut values = visitor.values;
+        for (name, mapping) in &self.fields {
+            if mapping.doc_values && !mapping.stored && !values.contains_key(name) {
+                let doc_values = Self::read_doc_values(leaf, name, doc_id)?;
+                values.insert(name.clone(), doc_values);
+            }
+        }
+        self.deserialize(values)
+    }
+
+    /// Reads back a document loaded with its stored fields, e.g. by
+    /// `IndexReader::document`.
+    pub fn from_document<T: DeserializeOwned>(&self, document: &Document) -> Result<T> {
+        let mut values: HashMap<String, Vec<FieldValue>> = HashMap::new();
+        for stored in &document.fields {
+            if let Some(value) = stored.field.field_data() {
+                values
+                    .entry(stored.field.name().to_string())
+                    .or_insert_with(Vec::new)
+                    .push(FieldValue::Stored(value.clone()));
+            }
+        }
+        self.deserialize(values)
+    }
+
+    fn deserialize<T: DeserializeOwned>(
+        &self,
+        mut values: HashMap<String, Vec<FieldValue>>,
+    ) -> Result<T> {
+        // the mapped fields without values are read as empty, so that an
+        // empty sequence or a `None` doesn't fail as a missing field
+        for name in self.fields.keys() {
+            if !values.contains_key(name) {
+                values.insert(name.clone(), vec![]);
+            }
+        }
+        deserialize_document(values)
+    }
+
+    fn read_doc_values<C: Codec>(
+        leaf: &LeafReaderContext<'_, C>,
+        field: &str,
+        doc_id: DocId,
+    ) -> Result<Vec<FieldValue>> {
+        let doc_values_type = match leaf.reader.field_info(field) {
+            Some(field_info) => field_info.doc_values_type,
+            None => {
+                return Ok(vec![]);
+            }
+        };
+        let mut values = vec![];
+        match doc_values_type {
+            DocValuesType::Numeric => {
+                if leaf
+                    .reader
+                    .get_docs_with_field(field)?
+                    .get(doc_id as usize)?
+                {
+                    let doc_values = leaf.reader.get_numeric_doc_values(field)?;
+                    values.push(FieldValue::DocValue(doc_values.get(doc_id)?));
+                }
+            }
+            DocValuesType::Binary => {
+                if leaf
+                    .reader
+                    .get_docs_with_field(field)?
+                    .get(doc_id as usize)?
+                {
+                    let mut doc_values = leaf.reader.get_binary_doc_values(field)?;
+                    values.push(FieldValue::DocBytes(doc_values.get(doc_id)?));
+                }
+            }
+            DocValuesType::Sorted => {
+                let mut doc_values = leaf.reader.get_sorted_doc_values(field)?;
+                let ord = doc_values.get_ord(doc_id)?;
+                if ord >= 0 {
+                    values.push(FieldValue::DocBytes(doc_values.lookup_ord(ord)?));
+                }
+            }
+            DocValuesType::SortedNumeric => {
+                let mut doc_values = leaf.reader.get_sorted_numeric_doc_values(field)?;
+                doc_values.set_document(doc_id)?;
+                for i in 0..doc_values.count() {
+                    values.push(FieldValue::DocValue(doc_values.value_at(i)?));
+                }
+            }
+            DocValuesType::SortedSet => {
+                let mut doc_values = leaf.reader.get_sorted_set_doc_values(field)?;
+                doc_values.set_document(doc_id)?;
+                loop {
+                    let ord = doc_values.next_ord()?;
+                    if ord == NO_MORE_ORDS {
+                        break;
+                    }
+                    values.push(FieldValue::DocBytes(doc_values.lookup_ord(ord)?));
+                }
+            }
+            _ => {}
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceAnalyzer;
+    use core::util::VariantValue;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Color {
+        Red,
+        Blue,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Product {
+        id: String,
+        title: String,
+        price: f64,
+        active: bool,
+        color: Color,
+        #[serde(default)]
+        tags: Vec<String>,
+        discount: Option<f32>,
+    }
+
+    fn mapping() -> DocumentMapping {
+        let mut mapping = DocumentMapping::new();
+        mapping.set_analyzer(Box::new(WhitespaceAnalyzer::default()));
+        mapping.add_field("id", FieldMapping::new(true).indexed(FieldIndexing::String));
+        mapping.add_field(
+            "title",
+            FieldMapping::new(true).indexed(FieldIndexing::Text),
+        );
+        mapping.add_field(
+            "price",
+            FieldMapping::new(false)
+                .indexed(FieldIndexing::Point)
+                .doc_values(),
+        );
+        mapping.add_field("active", FieldMapping::new(false).doc_values());
+        mapping.add_field(
+            "tags",
+            FieldMapping::new(false)
+                .indexed(FieldIndexing::String)
+                .doc_values(),
+        );
+        mapping
+    }
+
+    #[test]
+    fn test_to_document() {
+        let product = Product {
+            id: "p1".into(),
+            title: "red shoes".into(),
+            price: 12.5,
+            active: true,
+            color: Color::Blue,
+            tags: vec!["sale".into(), "new".into()],
+            discount: None,
+        };
+        let fields = mapping().to_document(&product).unwrap();
+        let names: Vec<&str> = fields.iter().map(|f| f.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "id", "id", "title", "title", "price", "price", "active", "color", "tags", "tags",
+                "tags", "tags"
+            ]
+        );
+        // the stored value, then the indexed one
+        assert!(fields[0].field_type().stored());
+        assert!(!fields[1].field_type().stored());
+        assert!(fields[3].field_type().tokenized());
+        assert_eq!(fields[4].field_type().dimension_count, 1);
+        assert_eq!(
+            fields[5].field_type().doc_values_type(),
+            DocValuesType::Numeric
+        );
+        assert_eq!(fields[7].string_value(), Some("Blue"));
+        assert_eq!(
+            fields[9].field_type().doc_values_type(),
+            DocValuesType::SortedSet
+        );
+
+        assert!(DocumentMapping::new().to_document(&5).is_err());
+        let mut mapping = mapping();
+        mapping.analyzer = None;
+        assert!(mapping.to_document(&product).is_err());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let mut values = HashMap::new();
+        values.insert(
+            "id".to_string(),
+            vec![FieldValue::Stored(VariantValue::VString("p1".into()))],
+        );
+        values.insert(
+            "title".to_string(),
+            vec![FieldValue::Stored(VariantValue::VString(
+                "red shoes".into(),
+            ))],
+        );
+        values.insert(
+            "price".to_string(),
+            vec![FieldValue::DocValue(12.5f64.to_bits() as i64)],
+        );
+        values.insert("active".to_string(), vec![FieldValue::DocValue(1)]);
+        values.insert(
+            "color".to_string(),
+            vec![FieldValue::Stored(VariantValue::VString("Red".into()))],
+        );
+        let product: Product = mapping().deserialize(values).unwrap();
+        assert_eq!(
+            product,
+            Product {
+                id: "p1".into(),
+                title: "red shoes".into(),
+                price: 12.5,
+                active: true,
+                color: Color::Red,
+                tags: vec![],
+                discount: None,
+            }
+        );
+
+        let mut values = HashMap::new();
+        values.insert(
+            "id".to_string(),
+            vec![
+                FieldValue::Stored(VariantValue::VString("p1".into())),
+                FieldValue::Stored(VariantValue::VString("p2".into())),
+            ],
+        );
+        assert!(mapping().deserialize::<Product>(values).is_err());
+    }
+}
@@ -0,0 +1,533 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::ser::{self, Impossible, Serialize};
+
+use core::doc::mapping::{DocumentMapping, FieldIndexing};
+use core::doc::{
+    Field, Fieldable, NumericDocValuesField, SortedNumericDocValuesField, SortedSetDocValuesField,
+    StoredField, StringField, TextField, SORTED_DOC_VALUES_FIELD_TYPE,
+};
+use core::search::query::{DoublePoint, FloatPoint, IntPoint, LongPoint};
+use core::util::VariantValue;
+
+use error::{Error, ErrorKind::IllegalArgument, Result};
+
+/// Serializes a struct to the fields of a document, each struct field being
+/// serialized by a `FieldSerializer`.
+pub(crate) struct DocumentSerializer<'a> {
+    mapping: &'a DocumentMapping,
+    fields: Vec<Box<dyn Fieldable>>,
+}
+
+impl<'a> DocumentSerializer<'a> {
+    pub(crate) fn new(mapping: &'a DocumentMapping) -> DocumentSerializer<'a> {
+        DocumentSerializer {
+            mapping,
+            fields: vec![],
+        }
+    }
+
+    pub(crate) fn into_fields(self) -> Vec<Box<dyn Fieldable>> {
+        self.fields
+    }
+
+    /// Adds the fields of one value of the field `name`, `multi_valued` when
+    /// the value is an element of a sequence.
+    fn add_value(&mut self, name: &str, value: VariantValue, multi_valued: bool) -> Result<()> {
+        let mapping = *self.mapping.field_mapping(name);
+        if mapping.stored {
+            let stored = match value {
+                VariantValue::Bool(b) => VariantValue::Int(i32::from(b)),
+                ref v => v.clone(),
+            };
+            self.fields
+                .push(Box::new(StoredField::new(name, None, stored).field));
+        }
+
+        match (mapping.indexing, &value) {
+            (FieldIndexing::None, _) => {}
+            (FieldIndexing::String, VariantValue::VString(s)) => {
+                self.fields.push(Box::new(StringField::new(name, s, false)));
+            }
+            (FieldIndexing::String, VariantValue::Binary(b)) => {
+                self.fields
+                    .push(Box::new(StringField::from_bytes(name, b, false)));
+            }
+            (FieldIndexing::Text, VariantValue::VString(s)) => {
+                let analyzer = match self.mapping.analyzer() {
+                    Some(analyzer) => analyzer,
+                    None => bail!(IllegalArgument(format!(
+                        "field '{}' is indexed as text but the mapping has no analyzer",
+                        name
+                    ))),
+                };
+                self.fields
+                    .push(Box::new(TextField::new(name, s, analyzer, false)?));
+            }
+            (FieldIndexing::Point, VariantValue::Bool(b)) => {
+                self.fields
+                    .push(Box::new(IntPoint::new(name, &[i32::from(*b)])?));
+            }
+            (FieldIndexing::Point, VariantValue::Int(v)) => {
+                self.fields.push(Box::new(IntPoint::new(name, &[*v])?));
+            }
+            (FieldIndexing::Point, VariantValue::Long(v)) => {
+                self.fields.push(Box::new(LongPoint::new(name, &[*v])?));
+            }
+            (FieldIndexing::Point, VariantValue::Float(v)) => {
+                self.fields.push(Box::new(FloatPoint::new(name, &[*v])?));
+            }
+            (FieldIndexing::Point, VariantValue::Double(v)) => {
+                self.fields.push(Box::new(DoublePoint::new(name, &[*v])?));
+            }
+            (indexing, v) => bail!(IllegalArgument(format!(
+                "field '{}' can't be indexed as {:?} from {:?}",
+                name, indexing, v
+            ))),
+        }
+
+        if mapping.doc_values {
+            // floats are added as their raw bits, as the sort comparators decode them
+            let numeric = match value {
+                VariantValue::Bool(b) => Some(i64::from(b)),
+                VariantValue::Int(v) => Some(i64::from(v)),
+                VariantValue::Long(v) => Some(v),
+                VariantValue::Float(v) => Some(i64::from(v.to_bits() as i32)),
+                VariantValue::Double(v) => Some(v.to_bits() as i64),
+                _ => None,
+            };
+            let field: Box<dyn Fieldable> = match (numeric, value) {
+                (Some(v), _) if multi_valued => Box::new(SortedNumericDocValuesField::new(name, v)),
+                (Some(v), _) => Box::new(NumericDocValuesField::new(name, v)),
+                (None, VariantValue::VString(s)) => {
+                    Self::sorted_doc_values(name, s.into_bytes(), multi_valued)
+                }
+                (None, VariantValue::Binary(b)) => Self::sorted_doc_values(name, b, multi_valued),
+                (None, v) => bail!(IllegalArgument(format!(
+                    "field '{}' can't have doc values from {:?}",
+                    name, v
+                ))),
+            };
+            self.fields.push(field);
+        }
+        Ok(())
+    }
+
+    fn sorted_doc_values(name: &str, bytes: Vec<u8>, multi_valued: bool) -> Box<dyn Fieldable> {
+        if multi_valued {
+            Box::new(SortedSetDocValuesField::new(name, &bytes))
+        } else {
+            Box::new(Field::new_bytes(
+                name.to_string(),
+                bytes,
+                SORTED_DOC_VALUES_FIELD_TYPE,
+            ))
+        }
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T> {
+    bail!(IllegalArgument(format!(
+        "a document can only be mapped from a struct, not from {}",
+        what
+    )))
+}
+
+impl<'a, 'b> ser::Serializer for &'b mut DocumentSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> {
+        unsupported("a bool")
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<()> {
+        unsupported("an integer")
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        unsupported("a float")
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        unsupported("a float")
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        unsupported("a char")
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<()> {
+        unsupported("a string")
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        unsupported("an option")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<()> {
+        unsupported("an option")
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        unsupported("a unit")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        unsupported("a unit struct")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        unsupported("an enum")
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        unsupported("an enum")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        unsupported("a sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        unsupported("a tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        unsupported("a tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        unsupported("an enum")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        unsupported("a map")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        unsupported("an enum")
+    }
+}
+
+impl<'a, 'b> ser::SerializeStruct for &'b mut DocumentSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(FieldSerializer {
+            document: &mut **self,
+            name: key,
+            multi_valued: false,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Serializes the value of the struct field `name`, or one of its values when
+/// `multi_valued`.
+struct FieldSerializer<'a, 'b> {
+    document: &'b mut DocumentSerializer<'a>,
+    name: &'static str,
+    multi_valued: bool,
+}
+
+impl<'a, 'b> FieldSerializer<'a, 'b> {
+    fn add(self, value: VariantValue) -> Result<()> {
+        self.document.add_value(self.name, value, self.multi_valued)
+    }
+
+    fn unsupported<T>(&self, what: &str) -> Result<T> {
+        bail!(IllegalArgument(format!(
+            "field '{}' can't be mapped from {}",
+            self.name, what
+        )))
+    }
+}
+
+impl<'a, 'b> ser::Serializer for FieldSerializer<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.add(VariantValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.add(VariantValue::Int(i32::from(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.add(VariantValue::Int(i32::from(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.add(VariantValue::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.add(VariantValue::Long(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.add(VariantValue::Int(i32::from(v)))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.add(VariantValue::Int(i32::from(v)))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.add(VariantValue::Long(i64::from(v)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        if v > i64::max_value() as u64 {
+            return self.unsupported(&format!("{}, which overflows a long", v));
+        }
+        self.add(VariantValue::Long(v as i64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.add(VariantValue::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.add(VariantValue::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.add(VariantValue::VString(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.add(VariantValue::VString(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.add(VariantValue::Binary(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.add(VariantValue::VString(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        self.unsupported("an enum variant with data")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self> {
+        if self.multi_valued {
+            return self.unsupported("nested sequences");
+        }
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.unsupported("a tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.unsupported("an enum variant with data")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.unsupported("a map")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.unsupported("a nested struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.unsupported("an enum variant with data")
+    }
+}
+
+impl<'a, 'b> ser::SerializeSeq for FieldSerializer<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(FieldSerializer {
+            document: &mut *self.document,
+            name: self.name,
+            multi_valued: true,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b> ser::SerializeTuple for FieldSerializer<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
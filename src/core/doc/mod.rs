@@ -30,3 +30,37 @@ pub use self::index_options::*;
 mod doc_values;
 
 pub use self::doc_values::*;
+
+mod feature_field;
+
+pub use self::feature_field::*;
+
+mod vector_field;
+
+pub use self::vector_field::*;
+
+mod lat_lon_point;
+
+pub use self::lat_lon_point::*;
+
+mod lat_lon_doc_values_field;
+
+pub use self::lat_lon_doc_values_field::*;
+
+mod lat_lon_shape;
+
+pub use self::lat_lon_shape::*;
+
+mod geo_hash_prefix_tree;
+
+pub use self::geo_hash_prefix_tree::*;
+
+mod string_field;
+
+pub use self::string_field::*;
+
+mod text_field;
+
+pub use self::text_field::*;
+
+pub mod mapping;
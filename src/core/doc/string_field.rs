@@ -0,0 +1,127 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::doc::{
+    Field, FieldType, Fieldable, STRING_FIELD_TYPE_NOT_STORED, STRING_FIELD_TYPE_STORED,
+};
+use core::util::{Numeric, VariantValue};
+
+use error::Result;
+
+/// A field indexed verbatim as a single token, e.g. an id, a country code or
+/// a tag, without frequencies nor norms.
+///
+/// Use a `TextField` for full text which must be tokenized.
+#[derive(Clone, Debug)]
+pub struct StringField {
+    field: Field,
+}
+
+impl StringField {
+    /// Creates a field indexing `value`, also stored when `stored` is true.
+    pub fn new(name: &str, value: &str, stored: bool) -> StringField {
+        StringField {
+            field: Field::new(
+                name.to_string(),
+                Self::preset_field_type(stored),
+                Some(VariantValue::VString(value.to_string())),
+                None,
+            ),
+        }
+    }
+
+    /// Creates a field indexing the binary `value`, also stored when `stored`
+    /// is true.
+    pub fn from_bytes(name: &str, value: &[u8], stored: bool) -> StringField {
+        StringField {
+            field: Field::new_bytes(
+                name.to_string(),
+                value.to_vec(),
+                Self::preset_field_type(stored),
+            ),
+        }
+    }
+
+    fn preset_field_type(stored: bool) -> FieldType {
+        if stored {
+            STRING_FIELD_TYPE_STORED
+        } else {
+            STRING_FIELD_TYPE_NOT_STORED
+        }
+    }
+}
+
+impl Fieldable for StringField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        self.field.string_value()
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::doc::IndexOptions;
+
+    #[test]
+    fn test_string_field() {
+        let mut field = StringField::new("id", "doc-1", true);
+        assert_eq!(field.name(), "id");
+        assert!(field.field_type().stored());
+        assert!(!field.field_type().tokenized());
+        assert!(field.field_type().omit_norms());
+        assert_eq!(field.field_type().index_options(), IndexOptions::Docs);
+        assert_eq!(field.string_value(), Some("doc-1"));
+
+        let mut stream = field.token_stream().unwrap();
+        stream.reset().unwrap();
+        assert!(stream.increment_token().unwrap());
+        assert_eq!(
+            stream.term_bytes_attribute().get_bytes_ref().bytes(),
+            b"doc-1"
+        );
+        assert!(!stream.increment_token().unwrap());
+
+        let field = StringField::from_bytes("hash", &[0xff, 0], false);
+        assert!(!field.field_type().stored());
+        assert_eq!(field.binary_value(), Some(&[0xffu8, 0][..]));
+    }
+}
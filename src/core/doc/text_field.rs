@@ -0,0 +1,131 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Analyzer, TokenStream};
+use core::doc::{Field, FieldType, Fieldable, TEXT_FIELD_TYPE_NOT_STORED, TEXT_FIELD_TYPE_STORED};
+use core::util::{Numeric, VariantValue};
+
+use error::Result;
+
+/// A field of full text, tokenized and indexed with frequencies and positions,
+/// e.g. the title or the body of a document.
+///
+/// Use a `StringField` for values which must be indexed as a single token.
+#[derive(Debug)]
+pub struct TextField {
+    field: Field,
+}
+
+impl TextField {
+    /// Creates a field indexing the tokens of `value` produced by `analyzer`,
+    /// `value` is also stored when `stored` is true.
+    pub fn new(
+        name: &str,
+        value: &str,
+        analyzer: &dyn Analyzer,
+        stored: bool,
+    ) -> Result<TextField> {
+        let field_type = if stored {
+            TEXT_FIELD_TYPE_STORED
+        } else {
+            TEXT_FIELD_TYPE_NOT_STORED
+        };
+        let token_stream = analyzer.token_stream_from_str(name, value)?;
+        Ok(TextField {
+            field: Field::new(
+                name.to_string(),
+                field_type,
+                Some(VariantValue::VString(value.to_string())),
+                Some(token_stream),
+            ),
+        })
+    }
+
+    /// Creates a field, not stored, indexing the tokens of `token_stream`.
+    pub fn from_token_stream(name: &str, token_stream: Box<dyn TokenStream>) -> TextField {
+        TextField {
+            field: Field::new(
+                name.to_string(),
+                TEXT_FIELD_TYPE_NOT_STORED,
+                None,
+                Some(token_stream),
+            ),
+        }
+    }
+}
+
+impl Fieldable for TextField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        self.field.string_value()
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceAnalyzer;
+    use core::doc::IndexOptions;
+
+    #[test]
+    fn test_text_field() {
+        let analyzer = WhitespaceAnalyzer::default();
+        let mut field = TextField::new("body", "quick brown fox", &analyzer, false).unwrap();
+        assert!(!field.field_type().stored());
+        assert!(field.field_type().tokenized());
+        assert!(!field.field_type().omit_norms());
+        assert_eq!(
+            field.field_type().index_options(),
+            IndexOptions::DocsAndFreqsAndPositions
+        );
+        assert_eq!(field.string_value(), Some("quick brown fox"));
+
+        let mut stream = field.token_stream().unwrap();
+        stream.reset().unwrap();
+        for term in &["quick", "brown", "fox"] {
+            assert!(stream.increment_token().unwrap());
+            assert_eq!(
+                stream.term_bytes_attribute().get_bytes_ref().bytes(),
+                term.as_bytes()
+            );
+        }
+        assert!(!stream.increment_token().unwrap());
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions};
+use core::util::{Numeric, VariantValue};
+
+use error::ErrorKind::{IllegalArgument, UnsupportedOperation};
+use error::Result;
+
+use std::borrow::Cow;
+
+/// Maximum number of dimensions of an indexed vector
+pub const VECTOR_MAX_DIMENSIONS: u32 = 1024;
+
+/// The function used to compare two vectors of a vector field.
+///
+/// Every function is mapped to a score that is greater for more similar
+/// vectors and never negative, so that it can be used as a document score.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum VectorSimilarity {
+    /// `1 / (1 + d^2)` where `d` is the euclidean distance of the vectors.
+    Euclidean,
+    /// `(1 + v1 . v2) / 2`, the vectors are expected to be unit length.
+    DotProduct,
+    /// `(1 + cos(v1, v2)) / 2`.
+    Cosine,
+}
+
+impl VectorSimilarity {
+    pub fn compare(self, v1: &[f32], v2: &[f32]) -> f32 {
+        debug_assert_eq!(v1.len(), v2.len());
+        match self {
+            VectorSimilarity::Euclidean => {
+                let mut squared = 0.0f32;
+                for (a, b) in v1.iter().zip(v2) {
+                    let diff = a - b;
+                    squared += diff * diff;
+                }
+                1.0 / (1.0 + squared)
+            }
+            VectorSimilarity::DotProduct => (1.0 + dot_product(v1, v2)).max(0.0) / 2.0,
+            VectorSimilarity::Cosine => {
+                let mut dot = 0.0f32;
+                let mut norm1 = 0.0f32;
+                let mut norm2 = 0.0f32;
+                for (a, b) in v1.iter().zip(v2) {
+                    dot += a * b;
+                    norm1 += a * a;
+                    norm2 += b * b;
+                }
+                let cosine = dot / (norm1 * norm2).sqrt();
+                (1.0 + cosine) / 2.0
+            }
+        }
+    }
+}
+
+fn dot_product(v1: &[f32], v2: &[f32]) -> f32 {
+    v1.iter().zip(v2).map(|(a, b)| a * b).sum()
+}
+
+/// A field that indexes a dense vector of floats for nearest neighbor search.
+///
+/// All the vectors of a field must have the same dimension and be compared
+/// with the same `VectorSimilarity`. They are searched with a `KnnVectorQuery`.
+pub struct VectorField {
+    field_name: String,
+    field_type: FieldType,
+    vector: Vec<f32>,
+}
+
+impl VectorField {
+    pub fn new(
+        field_name: &str,
+        vector: Vec<f32>,
+        similarity: VectorSimilarity,
+    ) -> Result<VectorField> {
+        Self::check_vector(&vector, similarity)?;
+        let mut field_type = FieldType::new(
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            true,
+            IndexOptions::Null,
+            DocValuesType::Null,
+            0,
+            0,
+        );
+        field_type.set_vector_attributes(vector.len() as u32, similarity)?;
+        Ok(VectorField {
+            field_name: field_name.to_string(),
+            field_type,
+            vector,
+        })
+    }
+
+    pub fn vector(&self) -> &[f32] {
+        &self.vector
+    }
+
+    fn check_vector(vector: &[f32], similarity: VectorSimilarity) -> Result<()> {
+        if vector.iter().any(|v| !v.is_finite()) {
+            bail!(IllegalArgument(
+                "illegal vector value, all the components must be finite".into()
+            ));
+        }
+        if similarity == VectorSimilarity::Cosine && vector.iter().all(|v| *v == 0.0) {
+            bail!(IllegalArgument(
+                "cosine similarity is not defined for a zero vector".into()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Fieldable for VectorField {
+    fn name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn field_type(&self) -> &FieldType {
+        &self.field_type
+    }
+
+    fn boost(&self) -> f32 {
+        1.0
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        None
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "vector fields are not tokenized"
+        )))
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+
+    fn vector_value(&self) -> Option<&[f32]> {
+        Some(&self.vector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_similarity() {
+        let v1 = [1.0f32, 0.0];
+        let v2 = [0.0f32, 1.0];
+
+        assert!((VectorSimilarity::Euclidean.compare(&v1, &v1) - 1.0).abs() < ::std::f32::EPSILON);
+        assert!((VectorSimilarity::Euclidean.compare(&v1, &v2) - 1.0 / 3.0).abs() < 1e-6);
+        assert!((VectorSimilarity::DotProduct.compare(&v1, &v2) - 0.5).abs() < 1e-6);
+        assert!((VectorSimilarity::Cosine.compare(&v1, &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(VectorSimilarity::Cosine.compare(&v1, &[-1.0, 0.0]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_vector_field() {
+        let field = VectorField::new("v", vec![1.0, 2.0, 3.0], VectorSimilarity::Cosine).unwrap();
+        assert_eq!(field.field_type().vector_dimension, 3);
+        assert_eq!(field.vector_value(), Some(&[1.0f32, 2.0, 3.0][..]));
+
+        assert!(VectorField::new("v", vec![], VectorSimilarity::Euclidean).is_err());
+        assert!(VectorField::new("v", vec![0.0, 0.0], VectorSimilarity::Cosine).is_err());
+        assert!(
+            VectorField::new("v", vec![::std::f32::NAN], VectorSimilarity::DotProduct).is_err()
+        );
+    }
+}
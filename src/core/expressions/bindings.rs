@@ -0,0 +1,142 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use core::expressions::{Expression, ExpressionValuesSource, VariableSource};
+use core::search::sort_field::SortFieldType;
+use core::search::NumericFieldValuesSource;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+#[derive(Clone, Debug)]
+enum Binding {
+    Score,
+    Field(String, SortFieldType),
+    Expression(Arc<Expression>),
+}
+
+/// Binds the variables of `Expression`s to the score of the wrapped query, to
+/// numeric doc values fields or to other expressions.
+///
+/// ```rust,ignore
+/// let mut bindings = SimpleBindings::new();
+/// bindings.add_score("_score");
+/// bindings.add_field("popularity", "popularity", SortFieldType::Long)?;
+/// let expression = Expression::compile("_score * log(1 + popularity)")?;
+/// let source = expression.get_values_source(&bindings)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SimpleBindings {
+    bindings: HashMap<String, Binding>,
+}
+
+impl SimpleBindings {
+    pub fn new() -> SimpleBindings {
+        SimpleBindings::default()
+    }
+
+    /// Binds `name` to the score of the wrapped query, conventionally `_score`.
+    pub fn add_score(&mut self, name: &str) {
+        self.bindings.insert(name.to_string(), Binding::Score);
+    }
+
+    /// Binds `name` to the values of the numeric doc values `field`, decoded
+    /// according to `field_type`.
+    ///
+    /// The documents without a value for the field are evaluated with 0.
+    pub fn add_field(&mut self, name: &str, field: &str, field_type: SortFieldType) -> Result<()> {
+        // checks the type is numeric
+        NumericFieldValuesSource::new(field.to_string(), field_type)?;
+        self.bindings.insert(
+            name.to_string(),
+            Binding::Field(field.to_string(), field_type),
+        );
+        Ok(())
+    }
+
+    /// Binds `name` to the result of `expression`, evaluated with these bindings.
+    pub fn add_expression(&mut self, name: &str, expression: Expression) {
+        self.bindings
+            .insert(name.to_string(), Binding::Expression(Arc::new(expression)));
+    }
+
+    /// Checks that every variable of the bound expressions is bound and that
+    /// the expressions don't refer to themselves.
+    pub fn validate(&self) -> Result<()> {
+        for name in self.bindings.keys() {
+            self.values_source(name, &mut vec![])?;
+        }
+        Ok(())
+    }
+
+    /// The values of the variable `name`, `resolving` holding the names of the
+    /// expressions being resolved to detect cycles.
+    pub(crate) fn values_source(
+        &self,
+        name: &str,
+        resolving: &mut Vec<String>,
+    ) -> Result<VariableSource> {
+        let source = match self.bindings.get(name) {
+            Some(Binding::Score) => VariableSource::Score,
+            Some(Binding::Field(field, field_type)) => {
+                VariableSource::Field(NumericFieldValuesSource::new(field.clone(), *field_type)?)
+            }
+            Some(Binding::Expression(expression)) => {
+                if resolving.iter().any(|n| n == name) {
+                    bail!(IllegalArgument(format!(
+                        "recursion detected while binding variable '{}'",
+                        name
+                    )));
+                }
+                resolving.push(name.to_string());
+                let source =
+                    ExpressionValuesSource::resolve(Arc::clone(expression), self, resolving)?;
+                resolving.pop();
+                VariableSource::Expression(Box::new(source))
+            }
+            None => bail!(IllegalArgument(format!("variable '{}' is not bound", name))),
+        };
+        Ok(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut bindings = SimpleBindings::new();
+        bindings.add_score("_score");
+        bindings
+            .add_field("popularity", "popularity", SortFieldType::Long)
+            .unwrap();
+        assert!(bindings
+            .add_field("title", "title", SortFieldType::String)
+            .is_err());
+        bindings.add_expression(
+            "boost",
+            Expression::compile("_score * log(1 + popularity)").unwrap(),
+        );
+        assert!(bindings.validate().is_ok());
+
+        bindings.add_expression("a", Expression::compile("b + 1").unwrap());
+        assert!(bindings.validate().is_err());
+        bindings.add_expression("b", Expression::compile("boost * a").unwrap());
+        assert!(bindings.validate().is_err());
+        bindings.add_expression("b", Expression::compile("boost * 2").unwrap());
+        assert!(bindings.validate().is_ok());
+    }
+}
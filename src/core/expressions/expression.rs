@@ -0,0 +1,413 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::sync::Arc;
+
+use core::expressions::{parse, ExpressionValuesSource, SimpleBindings};
+use core::geo::haversin_meters;
+use core::search::sort_field::{ExpressionSortField, SortField};
+
+use error::Result;
+
+/// A compiled arithmetic expression over named variables.
+///
+/// Expressions are written in a subset of the javascript syntax:
+/// * numbers, in decimal or hexadecimal (`0x`) notation
+/// * variables, identifiers which may contain dots, e.g. `popularity` or `doc.recency`
+/// * the arithmetic operators `+`, `-`, `*`, `/` and `%`
+/// * the comparison operators `==`, `!=`, `<`, `<=`, `>`, `>=` and the logical operators `&&`, `||`
+///   and `!`, which evaluate to 1 for true and 0 for false
+/// * the bitwise operators `&`, `|`, `^`, `~`, `<<`, `>>` and `>>>`, applied to the values
+///   truncated to 64 bits integers
+/// * the conditional operator `cond ? a : b`, `cond` is true when not 0
+/// * the functions listed in `Function`
+///
+/// The variables are bound to values with `SimpleBindings` when the expression
+/// is turned into a `DoubleValuesSource` by `get_values_source`.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    source_text: String,
+    variables: Vec<String>,
+    root: Node,
+}
+
+impl Expression {
+    /// Compiles `source_text`, failing if it is not a valid expression.
+    pub fn compile(source_text: &str) -> Result<Expression> {
+        let (root, variables) = parse(source_text)?;
+        Ok(Expression {
+            source_text: source_text.to_string(),
+            variables,
+            root,
+        })
+    }
+
+    pub fn source_text(&self) -> &str {
+        &self.source_text
+    }
+
+    /// The names of the variables of the expression, in order of first appearance.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Evaluates the expression, `values` holding the value of each of the
+    /// `variables()`.
+    pub fn evaluate(&self, values: &[f64]) -> f64 {
+        debug_assert_eq!(values.len(), self.variables.len());
+        self.root.evaluate(values)
+    }
+
+    /// Returns a `DoubleValuesSource` whose values are the results of the
+    /// expression, the variables being resolved by `bindings`.
+    pub fn get_values_source(&self, bindings: &SimpleBindings) -> Result<ExpressionValuesSource> {
+        ExpressionValuesSource::new(Arc::new(self.clone()), bindings)
+    }
+
+    /// Returns a `SortField` sorting the hits by the results of the expression,
+    /// ascending unless `reverse` is true.
+    ///
+    /// The expression can't depend on the score, use a `FunctionScoreQuery` with
+    /// `get_values_source` and sort by score instead.
+    pub fn get_sort_field(&self, bindings: &SimpleBindings, reverse: bool) -> Result<SortField> {
+        let source = self.get_values_source(bindings)?;
+        Ok(SortField::Expression(ExpressionSortField::new(
+            source, reverse,
+        )?))
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source_text)
+    }
+}
+
+/// A node of the syntax tree of an `Expression`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Node {
+    Number(f64),
+    /// The index of the variable in the variables of the expression.
+    Variable(usize),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Conditional(Box<Node>, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn evaluate(&self, values: &[f64]) -> f64 {
+        match self {
+            Node::Number(v) => *v,
+            Node::Variable(i) => values[*i],
+            Node::Unary(op, operand) => op.apply(operand.evaluate(values)),
+            Node::Binary(BinaryOp::And, left, right) => {
+                to_bool(left.evaluate(values) != 0.0 && right.evaluate(values) != 0.0)
+            }
+            Node::Binary(BinaryOp::Or, left, right) => {
+                to_bool(left.evaluate(values) != 0.0 || right.evaluate(values) != 0.0)
+            }
+            Node::Binary(op, left, right) => {
+                op.apply(left.evaluate(values), right.evaluate(values))
+            }
+            Node::Conditional(condition, then, otherwise) => {
+                if condition.evaluate(values) != 0.0 {
+                    then.evaluate(values)
+                } else {
+                    otherwise.evaluate(values)
+                }
+            }
+            Node::Call(function, args) => {
+                let mut arg_values = [0f64; MAX_ARITY];
+                for (v, arg) in arg_values.iter_mut().zip(args) {
+                    *v = arg.evaluate(values);
+                }
+                function.apply(&arg_values[..args.len()])
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Negate,
+    Not,
+    BitNot,
+}
+
+impl UnaryOp {
+    fn apply(self, v: f64) -> f64 {
+        match self {
+            UnaryOp::Negate => -v,
+            UnaryOp::Not => to_bool(v == 0.0),
+            UnaryOp::BitNot => !to_long(v) as f64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    UnsignedShiftRight,
+}
+
+impl BinaryOp {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => a / b,
+            BinaryOp::Remainder => a % b,
+            BinaryOp::Equal => to_bool(a == b),
+            BinaryOp::NotEqual => to_bool(a != b),
+            BinaryOp::Less => to_bool(a < b),
+            BinaryOp::LessOrEqual => to_bool(a <= b),
+            BinaryOp::Greater => to_bool(a > b),
+            BinaryOp::GreaterOrEqual => to_bool(a >= b),
+            // short-circuited by `Node::evaluate`
+            BinaryOp::And => to_bool(a != 0.0 && b != 0.0),
+            BinaryOp::Or => to_bool(a != 0.0 || b != 0.0),
+            BinaryOp::BitAnd => (to_long(a) & to_long(b)) as f64,
+            BinaryOp::BitOr => (to_long(a) | to_long(b)) as f64,
+            BinaryOp::BitXor => (to_long(a) ^ to_long(b)) as f64,
+            BinaryOp::ShiftLeft => to_long(a).wrapping_shl(to_long(b) as u32) as f64,
+            BinaryOp::ShiftRight => to_long(a).wrapping_shr(to_long(b) as u32) as f64,
+            BinaryOp::UnsignedShiftRight => {
+                (to_long(a) as u64).wrapping_shr(to_long(b) as u32) as f64
+            }
+        }
+    }
+}
+
+const MAX_ARITY: usize = 4;
+
+/// The functions which can be called in an `Expression`.
+///
+/// `log` is an alias of `ln`, `logn(base, x)` is the logarithm of `x` in `base`
+/// and `haversin(lat1, lon1, lat2, lon2)` is the haversine distance in
+/// kilometers between two locations in degrees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Acos,
+    Acosh,
+    Asin,
+    Asinh,
+    Atan,
+    Atan2,
+    Atanh,
+    Ceil,
+    Cos,
+    Cosh,
+    Exp,
+    Floor,
+    Haversin,
+    Ln,
+    Log10,
+    Logn,
+    Max,
+    Min,
+    Pow,
+    Sin,
+    Sinh,
+    Sqrt,
+    Tan,
+    Tanh,
+}
+
+impl Function {
+    /// Returns the function called `name`, if any.
+    pub fn from_name(name: &str) -> Option<Function> {
+        let function = match name {
+            "abs" => Function::Abs,
+            "acos" => Function::Acos,
+            "acosh" => Function::Acosh,
+            "asin" => Function::Asin,
+            "asinh" => Function::Asinh,
+            "atan" => Function::Atan,
+            "atan2" => Function::Atan2,
+            "atanh" => Function::Atanh,
+            "ceil" => Function::Ceil,
+            "cos" => Function::Cos,
+            "cosh" => Function::Cosh,
+            "exp" => Function::Exp,
+            "floor" => Function::Floor,
+            "haversin" => Function::Haversin,
+            "ln" | "log" => Function::Ln,
+            "log10" => Function::Log10,
+            "logn" => Function::Logn,
+            "max" => Function::Max,
+            "min" => Function::Min,
+            "pow" => Function::Pow,
+            "sin" => Function::Sin,
+            "sinh" => Function::Sinh,
+            "sqrt" => Function::Sqrt,
+            "tan" => Function::Tan,
+            "tanh" => Function::Tanh,
+            _ => {
+                return None;
+            }
+        };
+        Some(function)
+    }
+
+    /// The number of arguments of the function.
+    pub fn arity(self) -> usize {
+        match self {
+            Function::Atan2 | Function::Logn | Function::Max | Function::Min | Function::Pow => 2,
+            Function::Haversin => 4,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        debug_assert_eq!(args.len(), self.arity());
+        match self {
+            Function::Abs => args[0].abs(),
+            Function::Acos => args[0].acos(),
+            Function::Acosh => args[0].acosh(),
+            Function::Asin => args[0].asin(),
+            Function::Asinh => args[0].asinh(),
+            Function::Atan => args[0].atan(),
+            Function::Atan2 => args[0].atan2(args[1]),
+            Function::Atanh => args[0].atanh(),
+            Function::Ceil => args[0].ceil(),
+            Function::Cos => args[0].cos(),
+            Function::Cosh => args[0].cosh(),
+            Function::Exp => args[0].exp(),
+            Function::Floor => args[0].floor(),
+            Function::Haversin => haversin_meters(args[0], args[1], args[2], args[3]) / 1000.0,
+            Function::Ln => args[0].ln(),
+            Function::Log10 => args[0].log10(),
+            Function::Logn => args[1].ln() / args[0].ln(),
+            Function::Max => args[0].max(args[1]),
+            Function::Min => args[0].min(args[1]),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Sin => args[0].sin(),
+            Function::Sinh => args[0].sinh(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Tan => args[0].tan(),
+            Function::Tanh => args[0].tanh(),
+        }
+    }
+}
+
+fn to_bool(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Truncates to a `i64` like java does, NaN is 0 and the out of range
+/// values are clamped.
+fn to_long(v: f64) -> i64 {
+    if v.is_nan() {
+        0
+    } else if v >= i64::max_value() as f64 {
+        i64::max_value()
+    } else if v <= i64::min_value() as f64 {
+        i64::min_value()
+    } else {
+        v as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, values: &[f64]) -> f64 {
+        Expression::compile(source).unwrap().evaluate(values)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert!((eval("1 + 2 * 3", &[]) - 7.0).abs() < ::std::f64::EPSILON);
+        assert!((eval("(1 + 2) * 3", &[]) - 9.0).abs() < ::std::f64::EPSILON);
+        assert!((eval("10 - 4 - 3", &[]) - 3.0).abs() < ::std::f64::EPSILON);
+        assert!((eval("7 % 4 / 2", &[]) - 1.5).abs() < ::std::f64::EPSILON);
+        assert!((eval("-2 * -.5e1", &[]) - 10.0).abs() < ::std::f64::EPSILON);
+        assert!((eval("0x10 >>> 2 | 1", &[]) - 5.0).abs() < ::std::f64::EPSILON);
+        assert!((eval("~0 << 3 ^ -8", &[])).abs() < ::std::f64::EPSILON);
+    }
+
+    #[test]
+    fn test_logical() {
+        assert!((eval("1 < 2 && 2 <= 2", &[]) - 1.0).abs() < ::std::f64::EPSILON);
+        assert!(eval("1 == 2 || !1", &[]).abs() < ::std::f64::EPSILON);
+        assert!((eval("0 ? 1 : 2 > 1 ? 3 : 4", &[]) - 3.0).abs() < ::std::f64::EPSILON);
+    }
+
+    #[test]
+    fn test_variables_and_functions() {
+        let expression =
+            Expression::compile("_score * log(1+popularity) + 0.1*doc.recency").unwrap();
+        assert_eq!(
+            expression.variables(),
+            &[
+                "_score".to_string(),
+                "popularity".into(),
+                "doc.recency".into()
+            ]
+        );
+        let value = expression.evaluate(&[2.0, ::std::f64::consts::E - 1.0, 30.0]);
+        assert!((value - 5.0).abs() < 1e-9);
+
+        let expression = Expression::compile("max(a, pow(b, 2)) + min(a, abs(-b)) - a").unwrap();
+        assert_eq!(expression.variables(), &["a".to_string(), "b".into()]);
+        assert!((expression.evaluate(&[3.0, 2.0]) - 3.0).abs() < ::std::f64::EPSILON);
+
+        assert!((eval("logn(2, 8)", &[]) - 3.0).abs() < 1e-9);
+        assert!(eval("haversin(40.7, -74.0, 40.7, -74.0)", &[]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid() {
+        for source in &[
+            "",
+            "1 +",
+            "(1",
+            "1)",
+            "foo(1)",
+            "sqrt(1, 2)",
+            "max(1)",
+            "1 ? 2",
+            "a b",
+            "#",
+            "1e",
+        ] {
+            assert!(Expression::compile(source).is_err(), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::expressions::{Expression, SimpleBindings};
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::{DoubleValues, DoubleValuesSource, NumericFieldValuesSource, ScoreValuesSource};
+use core::util::DocId;
+
+use error::Result;
+
+/// The source of the values of a variable of an `Expression`.
+pub(crate) enum VariableSource {
+    Score,
+    Field(NumericFieldValuesSource),
+    Expression(Box<ExpressionValuesSource>),
+}
+
+impl VariableSource {
+    fn needs_scores(&self) -> bool {
+        match self {
+            VariableSource::Score => true,
+            VariableSource::Field(_) => false,
+            VariableSource::Expression(source) => source.needs_scores,
+        }
+    }
+}
+
+impl<C: Codec> DoubleValuesSource<C> for VariableSource {
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>> {
+        match self {
+            VariableSource::Score => ScoreValuesSource.get_values(reader),
+            VariableSource::Field(source) => source.get_values(reader),
+            VariableSource::Expression(source) => source.get_values(reader),
+        }
+    }
+
+    fn needs_scores(&self) -> bool {
+        VariableSource::needs_scores(self)
+    }
+
+    fn explain(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        doc: DocId,
+        score_explanation: &Explanation,
+    ) -> Result<Explanation> {
+        match self {
+            VariableSource::Score => ScoreValuesSource.explain(reader, doc, score_explanation),
+            VariableSource::Field(source) => source.explain(reader, doc, score_explanation),
+            VariableSource::Expression(source) => source.explain(reader, doc, score_explanation),
+        }
+    }
+}
+
+impl fmt::Display for VariableSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VariableSource::Score => write!(f, "{}", ScoreValuesSource),
+            VariableSource::Field(source) => write!(f, "{}", source),
+            VariableSource::Expression(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+/// A `DoubleValuesSource` whose values are the results of an `Expression`, see
+/// `Expression::get_values_source`.
+///
+/// Every document has a value, the variables bound to a field the document has
+/// no value for are evaluated with 0.
+pub struct ExpressionValuesSource {
+    expression: Arc<Expression>,
+    variables: Vec<VariableSource>,
+    needs_scores: bool,
+}
+
+impl ExpressionValuesSource {
+    pub(crate) fn new(
+        expression: Arc<Expression>,
+        bindings: &SimpleBindings,
+    ) -> Result<ExpressionValuesSource> {
+        Self::resolve(expression, bindings, &mut vec![])
+    }
+
+    pub(crate) fn resolve(
+        expression: Arc<Expression>,
+        bindings: &SimpleBindings,
+        resolving: &mut Vec<String>,
+    ) -> Result<ExpressionValuesSource> {
+        let mut variables = Vec::with_capacity(expression.variables().len());
+        for name in expression.variables() {
+            variables.push(bindings.values_source(name, resolving)?);
+        }
+        let needs_scores = variables.iter().any(|v| v.needs_scores());
+        Ok(ExpressionValuesSource {
+            expression,
+            variables,
+            needs_scores,
+        })
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// Whether one of the variables is bound to the score, directly or through
+    /// another expression.
+    pub fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+}
+
+impl<C: Codec> DoubleValuesSource<C> for ExpressionValuesSource {
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>> {
+        let mut values = Vec::with_capacity(self.variables.len());
+        for variable in &self.variables {
+            values.push(variable.get_values(reader)?);
+        }
+        Ok(Box::new(ExpressionValues {
+            expression: Arc::clone(&self.expression),
+            scratch: vec![0f64; values.len()],
+            values,
+        }))
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        doc: DocId,
+        score_explanation: &Explanation,
+    ) -> Result<Explanation> {
+        let mut values = Vec::with_capacity(self.variables.len());
+        let mut subs = Vec::with_capacity(self.variables.len());
+        for (name, variable) in self.expression.variables().iter().zip(&self.variables) {
+            let sub = variable.explain(reader, doc, score_explanation)?;
+            values.push(if sub.is_match() {
+                f64::from(sub.value())
+            } else {
+                0.0
+            });
+            subs.push(Explanation::new(
+                true,
+                values[values.len() - 1] as f32,
+                format!("{}, computed from:", name),
+                vec![sub],
+            ));
+        }
+        let value = self.expression.evaluate(&values);
+        Ok(Explanation::new(
+            true,
+            value as f32,
+            format!("{}, computed from:", self),
+            subs,
+        ))
+    }
+}
+
+impl fmt::Display for ExpressionValuesSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expr({})", self.expression)
+    }
+}
+
+struct ExpressionValues {
+    expression: Arc<Expression>,
+    values: Vec<Box<dyn DoubleValues>>,
+    scratch: Vec<f64>,
+}
+
+impl DoubleValues for ExpressionValues {
+    fn get(&mut self, doc: DocId, score: f32) -> Result<Option<f64>> {
+        for (v, values) in self.scratch.iter_mut().zip(&mut self.values) {
+            *v = values.get(doc, score)?.unwrap_or(0.0);
+        }
+        Ok(Some(self.expression.evaluate(&self.scratch)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockValues;
+
+    impl DoubleValues for MockValues {
+        fn get(&mut self, doc: DocId, _score: f32) -> Result<Option<f64>> {
+            if doc % 2 == 0 {
+                Ok(Some(f64::from(doc)))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    struct MockScoreValues;
+
+    impl DoubleValues for MockScoreValues {
+        fn get(&mut self, _doc: DocId, score: f32) -> Result<Option<f64>> {
+            Ok(Some(f64::from(score)))
+        }
+    }
+
+    #[test]
+    fn test_expression_values() {
+        let expression = Expression::compile("_score * 10 + popularity").unwrap();
+        let mut values = ExpressionValues {
+            expression: Arc::new(expression),
+            values: vec![Box::new(MockScoreValues), Box::new(MockValues)],
+            scratch: vec![0.0; 2],
+        };
+        assert_eq!(values.get(4, 0.5).unwrap(), Some(9.0));
+        // doc 5 has no popularity, evaluated as 0
+        assert_eq!(values.get(5, 2.0).unwrap(), Some(20.0));
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arithmetic expressions computing per-document values, e.g.
+//! `_score * log(1 + popularity) + 0.1 * recency`.
+//!
+//! An `Expression` is compiled from its source text once, its variables are
+//! then bound to the score of the wrapped query or to numeric doc values fields
+//! by `SimpleBindings`. The resulting `ExpressionValuesSource` is a
+//! `DoubleValuesSource`, so it can rescore the hits of a query through a
+//! `FunctionScoreQuery`, and expressions which don't use the score can sort
+//! the hits with `Expression::get_sort_field`.
+
+mod bindings;
+
+pub use self::bindings::*;
+
+mod expression;
+
+pub use self::expression::*;
+
+mod expression_values_source;
+
+pub use self::expression_values_source::*;
+
+mod parser;
+
+pub use self::parser::*;
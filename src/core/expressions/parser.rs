@@ -0,0 +1,364 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::expressions::{BinaryOp, Function, Node, UnaryOp};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Parses `source`, returning the root of its syntax tree and the names of its
+/// variables, in order of first appearance.
+pub(crate) fn parse(source: &str) -> Result<(Node, Vec<String>)> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        source,
+        tokens,
+        pos: 0,
+        variables: vec![],
+    };
+    let root = parser.parse_conditional()?;
+    if let Some(&(_, offset)) = parser.tokens.get(parser.pos) {
+        return Err(parser.error("unexpected token", offset));
+    }
+    Ok((root, parser.variables))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Symbol(&'static str),
+}
+
+// longest first, so that `>>>` isn't read as `>>` then `>`
+const SYMBOLS: [&str; 26] = [
+    ">>>", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "(", ")", ",",
+    "?", ":", "!", "~", "&", "|", "^", "<", ">",
+];
+
+/// The binary operators by increasing precedence, all left associative.
+const BINARY_OPERATORS: [&[(&str, BinaryOp)]; 10] = [
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("==", BinaryOp::Equal), ("!=", BinaryOp::NotEqual)],
+    &[
+        ("<", BinaryOp::Less),
+        ("<=", BinaryOp::LessOrEqual),
+        (">", BinaryOp::Greater),
+        (">=", BinaryOp::GreaterOrEqual),
+    ],
+    &[
+        ("<<", BinaryOp::ShiftLeft),
+        (">>", BinaryOp::ShiftRight),
+        (">>>", BinaryOp::UnsignedShiftRight),
+    ],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+    &[
+        ("*", BinaryOp::Multiply),
+        ("/", BinaryOp::Divide),
+        ("%", BinaryOp::Remainder),
+    ],
+];
+
+fn is_identifier_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c == b'$'
+}
+
+fn is_identifier_part(c: u8) -> bool {
+    is_identifier_start(c) || c.is_ascii_digit()
+}
+
+/// Splits `source` into tokens, each with its byte offset.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        let start = pos;
+        if c.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        if c.is_ascii_digit()
+            || (c == b'.' && pos + 1 < bytes.len() && bytes[pos + 1].is_ascii_digit())
+        {
+            let number = if c == b'0' && pos + 1 < bytes.len() && (bytes[pos + 1] | 0x20) == b'x' {
+                pos += 2;
+                while pos < bytes.len() && bytes[pos].is_ascii_hexdigit() {
+                    pos += 1;
+                }
+                u64::from_str_radix(&source[start + 2..pos], 16)
+                    .map(|v| v as f64)
+                    .ok()
+            } else {
+                while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'.') {
+                    pos += 1;
+                }
+                if pos < bytes.len() && (bytes[pos] | 0x20) == b'e' {
+                    pos += 1;
+                    if pos < bytes.len() && (bytes[pos] == b'+' || bytes[pos] == b'-') {
+                        pos += 1;
+                    }
+                    while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                        pos += 1;
+                    }
+                }
+                source[start..pos].parse::<f64>().ok()
+            };
+            match number {
+                Some(v) if pos == bytes.len() || !is_identifier_part(bytes[pos]) => {
+                    tokens.push((Token::Number(v), start));
+                }
+                _ => bail!(IllegalArgument(format!(
+                    "invalid expression '{}': invalid number at position {}",
+                    source, start
+                ))),
+            }
+        } else if is_identifier_start(c) {
+            // the identifiers may be dotted, e.g. `doc.popularity`
+            loop {
+                while pos < bytes.len() && is_identifier_part(bytes[pos]) {
+                    pos += 1;
+                }
+                if pos + 1 < bytes.len()
+                    && bytes[pos] == b'.'
+                    && is_identifier_start(bytes[pos + 1])
+                {
+                    pos += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push((Token::Identifier(source[start..pos].to_string()), start));
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| source[pos..].starts_with(*s)) {
+            pos += symbol.len();
+            tokens.push((Token::Symbol(*symbol), start));
+        } else {
+            bail!(IllegalArgument(format!(
+                "invalid expression '{}': unexpected character at position {}",
+                source, start
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser, one method per precedence level.
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    variables: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str, offset: usize) -> ::error::Error {
+        IllegalArgument(format!(
+            "invalid expression '{}': {} at position {}",
+            self.source, message, offset
+        ))
+        .into()
+    }
+
+    fn peek_symbol(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Symbol(symbol), _)) => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.peek_symbol() == Some(symbol) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", symbol), self.offset()))
+        }
+    }
+
+    /// The offset of the current token, the length of the source at the end.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.source.len(), |&(_, offset)| offset)
+    }
+
+    // right associative: `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+    fn parse_conditional(&mut self) -> Result<Node> {
+        let condition = self.parse_binary(0)?;
+        if self.peek_symbol() != Some("?") {
+            return Ok(condition);
+        }
+        self.pos += 1;
+        let then = self.parse_conditional()?;
+        self.expect_symbol(":")?;
+        let otherwise = self.parse_conditional()?;
+        Ok(Node::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn parse_binary(&mut self, level: usize) -> Result<Node> {
+        if level == BINARY_OPERATORS.len() {
+            return self.parse_unary();
+        }
+        let mut left = self.parse_binary(level + 1)?;
+        while let Some(symbol) = self.peek_symbol() {
+            let op = match BINARY_OPERATORS[level].iter().find(|(s, _)| *s == symbol) {
+                Some(&(_, op)) => op,
+                None => break,
+            };
+            self.pos += 1;
+            let right = self.parse_binary(level + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node> {
+        let op = match self.peek_symbol() {
+            Some("+") => {
+                self.pos += 1;
+                return self.parse_unary();
+            }
+            Some("-") => UnaryOp::Negate,
+            Some("!") => UnaryOp::Not,
+            Some("~") => UnaryOp::BitNot,
+            _ => {
+                return self.parse_primary();
+            }
+        };
+        self.pos += 1;
+        let operand = self.parse_unary()?;
+        Ok(Node::Unary(op, Box::new(operand)))
+    }
+
+    fn parse_primary(&mut self) -> Result<Node> {
+        let offset = self.offset();
+        let token = match self.tokens.get(self.pos) {
+            Some((token, _)) => token.clone(),
+            None => {
+                return Err(self.error("unexpected end", offset));
+            }
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(v) => Ok(Node::Number(v)),
+            Token::Symbol("(") => {
+                let node = self.parse_conditional()?;
+                self.expect_symbol(")")?;
+                Ok(node)
+            }
+            Token::Identifier(name) => {
+                if self.peek_symbol() == Some("(") {
+                    self.pos += 1;
+                    self.parse_call(&name, offset)
+                } else {
+                    Ok(Node::Variable(self.variable_index(name)))
+                }
+            }
+            Token::Symbol(_) => Err(self.error("unexpected token", offset)),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str, offset: usize) -> Result<Node> {
+        let function = match Function::from_name(name) {
+            Some(function) => function,
+            None => {
+                return Err(self.error(&format!("unknown function '{}'", name), offset));
+            }
+        };
+        let mut args = vec![];
+        if self.peek_symbol() == Some(")") {
+            self.pos += 1;
+        } else {
+            loop {
+                args.push(self.parse_conditional()?);
+                if self.peek_symbol() == Some(",") {
+                    self.pos += 1;
+                } else {
+                    self.expect_symbol(")")?;
+                    break;
+                }
+            }
+        }
+        if args.len() != function.arity() {
+            return Err(self.error(
+                &format!(
+                    "function '{}' expects {} arguments, got {}",
+                    name,
+                    function.arity(),
+                    args.len()
+                ),
+                offset,
+            ));
+        }
+        Ok(Node::Call(function, args))
+    }
+
+    fn variable_index(&mut self, name: String) -> usize {
+        match self.variables.iter().position(|v| *v == name) {
+            Some(i) => i,
+            None => {
+                self.variables.push(name);
+                self.variables.len() - 1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let (root, variables) = parse("-a * (b + 2) >= a").unwrap();
+        assert_eq!(variables, vec!["a".to_string(), "b".into()]);
+        assert_eq!(
+            root,
+            Node::Binary(
+                BinaryOp::GreaterOrEqual,
+                Box::new(Node::Binary(
+                    BinaryOp::Multiply,
+                    Box::new(Node::Unary(UnaryOp::Negate, Box::new(Node::Variable(0)))),
+                    Box::new(Node::Binary(
+                        BinaryOp::Add,
+                        Box::new(Node::Variable(1)),
+                        Box::new(Node::Number(2.0))
+                    ))
+                )),
+                Box::new(Node::Variable(0))
+            )
+        );
+
+        let (root, _) = parse("a >>> 1 > 0x1f").unwrap();
+        assert_eq!(
+            root,
+            Node::Binary(
+                BinaryOp::Greater,
+                Box::new(Node::Binary(
+                    BinaryOp::UnsignedShiftRight,
+                    Box::new(Node::Variable(0)),
+                    Box::new(Node::Number(1.0))
+                )),
+                Box::new(Node::Number(31.0))
+            )
+        );
+    }
+}
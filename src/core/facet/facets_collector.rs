@@ -0,0 +1,220 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::util::DocId;
+use error::{ErrorKind::IllegalState, Result, ResultExt};
+
+/// The docs of a segment that matched the search.
+#[derive(Debug, Clone)]
+pub struct MatchingDocs {
+    /// Ord of the segment in its index reader.
+    pub ord: usize,
+    /// Doc base of the segment.
+    pub doc_base: DocId,
+    /// The matching docs, in increasing order.
+    pub docs: Vec<DocId>,
+    /// The scores of the matching docs, iff scores are kept.
+    pub scores: Option<Vec<f32>>,
+}
+
+impl MatchingDocs {
+    fn new<C: Codec>(reader: &LeafReaderContext<'_, C>, keep_scores: bool) -> MatchingDocs {
+        MatchingDocs {
+            ord: reader.ord,
+            doc_base: reader.doc_base,
+            docs: vec![],
+            scores: if keep_scores { Some(vec![]) } else { None },
+        }
+    }
+
+    fn add<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        if let Some(ref mut scores) = self.scores {
+            scores.push(scorer.score()?);
+        }
+        self.docs.push(doc);
+        Ok(())
+    }
+}
+
+/// Collects the matching docs of every segment, optionally with their scores,
+/// for the facet counts to be computed after the search.
+///
+/// This collector is usually chained with the collector of the top hits, see
+/// `ChainedCollector`.
+pub struct FacetsCollector {
+    keep_scores: bool,
+    matching_docs: Vec<MatchingDocs>,
+    channel: Option<(Sender<MatchingDocs>, Receiver<MatchingDocs>)>,
+}
+
+impl Default for FacetsCollector {
+    fn default() -> Self {
+        FacetsCollector::new(false)
+    }
+}
+
+impl FacetsCollector {
+    pub fn new(keep_scores: bool) -> FacetsCollector {
+        FacetsCollector {
+            keep_scores,
+            matching_docs: vec![],
+            channel: None,
+        }
+    }
+
+    pub fn keep_scores(&self) -> bool {
+        self.keep_scores
+    }
+
+    /// Returns the matching docs of every segment, ordered by segment.
+    pub fn matching_docs(&self) -> &[MatchingDocs] {
+        &self.matching_docs
+    }
+
+    /// Returns the total number of matching docs.
+    pub fn total_hits(&self) -> usize {
+        self.matching_docs.iter().map(|m| m.docs.len()).sum()
+    }
+}
+
+impl SearchCollector for FacetsCollector {
+    type LC = FacetsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.matching_docs
+            .push(MatchingDocs::new(reader, self.keep_scores));
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<FacetsLeafCollector> {
+        Ok(FacetsLeafCollector {
+            matching_docs: MatchingDocs::new(reader, self.keep_scores),
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.matching_docs.push(docs);
+            }
+            self.matching_docs.sort_by_key(|m| m.ord);
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for FacetsCollector {
+    fn needs_scores(&self) -> bool {
+        self.keep_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        debug_assert!(!self.matching_docs.is_empty());
+        self.matching_docs.last_mut().unwrap().add(doc, scorer)
+    }
+}
+
+pub struct FacetsLeafCollector {
+    matching_docs: MatchingDocs,
+    channel: Sender<MatchingDocs>,
+}
+
+impl ParallelLeafCollector for FacetsLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let docs = MatchingDocs {
+            ord: self.matching_docs.ord,
+            doc_base: self.matching_docs.doc_base,
+            docs: self.matching_docs.docs.split_off(0),
+            scores: self.matching_docs.scores.take(),
+        };
+        self.channel
+            .send(docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for FacetsLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.matching_docs.scores.is_some()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.matching_docs.add(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    #[test]
+    fn test_facets_collector() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let mut collector = FacetsCollector::new(true);
+        assert!(collector.needs_scores());
+        for (leaf, docs) in leaves.iter().zip(vec![vec![1, 3], vec![2]]) {
+            collector.set_next_reader(leaf).unwrap();
+            let mut scorer = create_mock_scorer(docs);
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                collector.collect(doc, &mut scorer).unwrap();
+            }
+        }
+
+        assert_eq!(collector.total_hits(), 3);
+        let matching_docs = collector.matching_docs();
+        assert_eq!(matching_docs.len(), 2);
+        assert_eq!(matching_docs[0].docs, vec![1, 3]);
+        assert_eq!(matching_docs[0].scores, Some(vec![1.0, 3.0]));
+        assert_eq!(matching_docs[1].ord, 1);
+        assert_eq!(matching_docs[1].doc_base, 10);
+        assert_eq!(matching_docs[1].docs, vec![2]);
+
+        assert!(!FacetsCollector::default().needs_scores());
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::SortedNumericDocValues;
+use core::doc::LatLonDocValuesField;
+use core::facet::{top_label_values, FacetResult, Facets, FacetsCollector, LabelAndValue};
+use core::geo::{geohash_encode, MAX_GEOHASH_PRECISION};
+use core::index::reader::IndexReader;
+use core::util::DocId;
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::collections::HashMap;
+
+/// Counts the docs per geohash cell of their locations, indexed as
+/// `LatLonDocValuesField`, over the docs recorded by a `FacetsCollector`.
+///
+/// The only dimension is the field of the locations, its labels are the
+/// geohashes of `precision` characters. A doc with several locations in the
+/// same cell is counted once for it.
+pub struct GeoHashGridFacetCounts {
+    field: String,
+    precision: usize,
+    // geohash -> count
+    counts: HashMap<String, i32>,
+}
+
+impl GeoHashGridFacetCounts {
+    pub fn new<R: IndexReader + ?Sized>(
+        field: &str,
+        precision: usize,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<GeoHashGridFacetCounts> {
+        if precision == 0 || precision > MAX_GEOHASH_PRECISION {
+            bail!(IllegalArgument(format!(
+                "precision must be between 1 and {}, got {}",
+                MAX_GEOHASH_PRECISION, precision
+            )));
+        }
+        let mut facet_counts = GeoHashGridFacetCounts {
+            field: field.to_string(),
+            precision,
+            counts: HashMap::new(),
+        };
+        let leaves = reader.leaves();
+        for matching_docs in hits.matching_docs() {
+            let leaf = &leaves[matching_docs.ord];
+            if matching_docs.docs.is_empty()
+                || leaf
+                    .reader
+                    .field_infos()
+                    .field_info_by_name(field)
+                    .is_none()
+            {
+                continue;
+            }
+            let mut doc_values = leaf.reader.get_sorted_numeric_doc_values(field)?;
+            facet_counts.count_segment(doc_values.as_mut(), &matching_docs.docs)?;
+        }
+        Ok(facet_counts)
+    }
+
+    fn count_segment(
+        &mut self,
+        doc_values: &mut dyn SortedNumericDocValues,
+        docs: &[DocId],
+    ) -> Result<()> {
+        let mut cells = Vec::new();
+        for &doc in docs {
+            doc_values.set_document(doc)?;
+            cells.clear();
+            for i in 0..doc_values.count() {
+                let value = doc_values.value_at(i)?;
+                let cell = geohash_encode(
+                    LatLonDocValuesField::decode_latitude(value),
+                    LatLonDocValuesField::decode_longitude(value),
+                    self.precision,
+                )?;
+                if !cells.contains(&cell) {
+                    cells.push(cell);
+                }
+            }
+            for cell in cells.drain(..) {
+                *self.counts.entry(cell).or_insert(0) += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn dim_result(&self, top_n: usize) -> FacetResult {
+        let label_values = self
+            .counts
+            .iter()
+            .map(|(cell, &count)| LabelAndValue::new(cell.clone(), count))
+            .collect();
+        FacetResult {
+            dim: self.field.clone(),
+            path: vec![],
+            value: self.counts.values().sum(),
+            child_count: self.counts.len(),
+            label_values: top_label_values(label_values, top_n),
+        }
+    }
+}
+
+impl Facets for GeoHashGridFacetCounts {
+    fn top_children(&self, top_n: usize, dim: &str, path: &[&str]) -> Result<Option<FacetResult>> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        if !path.is_empty() {
+            bail!(IllegalArgument(
+                "geo grid facets only support flat dimensions, path must be empty".into()
+            ));
+        }
+        if dim != self.field || self.counts.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.dim_result(top_n)))
+    }
+
+    fn specific_value(&self, dim: &str, path: &[&str]) -> Result<i32> {
+        if path.len() != 1 {
+            bail!(IllegalArgument(
+                "geo grid facets only support a path of one geohash".into()
+            ));
+        }
+        if dim != self.field {
+            return Ok(0);
+        }
+        Ok(self.counts.get(path[0]).cloned().unwrap_or(0))
+    }
+
+    fn all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        Ok(self
+            .top_children(top_n, &self.field, &[])?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockSortedNumericDocValues {
+        doc_values: Vec<Vec<i64>>,
+        current: usize,
+    }
+
+    impl SortedNumericDocValues for MockSortedNumericDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.current = doc as usize;
+            Ok(())
+        }
+
+        fn value_at(&mut self, index: usize) -> Result<i64> {
+            Ok(self.doc_values[self.current][index])
+        }
+
+        fn count(&self) -> usize {
+            self.doc_values[self.current].len()
+        }
+    }
+
+    fn location(latitude: f64, longitude: f64) -> i64 {
+        LatLonDocValuesField::encode(latitude, longitude).unwrap()
+    }
+
+    #[test]
+    fn test_geo_grid_facet_counts() {
+        let mut facet_counts = GeoHashGridFacetCounts {
+            field: "location".into(),
+            precision: 2,
+            counts: HashMap::new(),
+        };
+        // paris, twice in the same doc, london, and new york
+        let mut doc_values = MockSortedNumericDocValues {
+            doc_values: vec![
+                vec![location(48.86, 2.29), location(48.85, 2.35)],
+                vec![location(51.5, -0.12)],
+                vec![location(40.71, -74.0)],
+                vec![location(48.86, 2.29)],
+                vec![],
+            ],
+            current: 0,
+        };
+        facet_counts
+            .count_segment(&mut doc_values, &[0, 1, 3, 4])
+            .unwrap();
+
+        let result = facet_counts
+            .top_children(10, "location", &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.value, 3);
+        assert_eq!(
+            result.label_values,
+            vec![
+                LabelAndValue::new("u0".into(), 2),
+                LabelAndValue::new("gc".into(), 1),
+            ]
+        );
+        assert_eq!(facet_counts.specific_value("location", &["u0"]).unwrap(), 2);
+        assert_eq!(facet_counts.specific_value("location", &["dr"]).unwrap(), 0);
+        assert!(facet_counts
+            .top_children(10, "other", &[])
+            .unwrap()
+            .is_none());
+        assert_eq!(facet_counts.all_dims(1).unwrap()[0].label_values.len(), 1);
+    }
+}
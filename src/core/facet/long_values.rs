@@ -0,0 +1,298 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::SortedNumericDocValues;
+use core::facet::{FacetResult, Facets, FacetsCollector, LabelAndValue};
+use core::index::reader::IndexReader;
+use core::search::sort_field::SortedNumericSelectorType;
+use core::util::DocId;
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::collections::HashMap;
+
+/// Counts the distinct long values of a `SortedNumericDocValuesField` over the
+/// docs recorded by a `FacetsCollector`.
+///
+/// The only dimension is the field, its labels are the values. By default a
+/// doc with several values is counted once for each of its distinct values,
+/// with a selector it is only counted for its min, max or the sum of its
+/// values, like when sorting on the field.
+pub struct LongValueFacetCounts {
+    field: String,
+    // value -> count
+    counts: HashMap<i64, i32>,
+    // the docs with a value
+    total_count: i32,
+}
+
+impl LongValueFacetCounts {
+    pub fn new<R: IndexReader + ?Sized>(
+        field: &str,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<LongValueFacetCounts> {
+        Self::count(field, None, reader, hits)
+    }
+
+    /// Count a single value per doc, picked by `selector` among its values.
+    pub fn with_selector<R: IndexReader + ?Sized>(
+        field: &str,
+        selector: SortedNumericSelectorType,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<LongValueFacetCounts> {
+        Self::count(field, Some(selector), reader, hits)
+    }
+
+    fn count<R: IndexReader + ?Sized>(
+        field: &str,
+        selector: Option<SortedNumericSelectorType>,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<LongValueFacetCounts> {
+        let mut facet_counts = LongValueFacetCounts {
+            field: field.to_string(),
+            counts: HashMap::new(),
+            total_count: 0,
+        };
+        let leaves = reader.leaves();
+        for matching_docs in hits.matching_docs() {
+            let leaf = &leaves[matching_docs.ord];
+            if matching_docs.docs.is_empty()
+                || leaf
+                    .reader
+                    .field_infos()
+                    .field_info_by_name(field)
+                    .is_none()
+            {
+                continue;
+            }
+            let mut doc_values = leaf.reader.get_sorted_numeric_doc_values(field)?;
+            facet_counts.count_segment(doc_values.as_mut(), &matching_docs.docs, selector)?;
+        }
+        Ok(facet_counts)
+    }
+
+    fn count_segment(
+        &mut self,
+        doc_values: &mut dyn SortedNumericDocValues,
+        docs: &[DocId],
+        selector: Option<SortedNumericSelectorType>,
+    ) -> Result<()> {
+        for &doc in docs {
+            doc_values.set_document(doc)?;
+            let count = doc_values.count();
+            if count == 0 {
+                continue;
+            }
+            self.total_count += 1;
+            match selector {
+                // the values of a doc are sorted
+                Some(SortedNumericSelectorType::Min) => self.increment(doc_values.value_at(0)?),
+                Some(SortedNumericSelectorType::Max) => {
+                    self.increment(doc_values.value_at(count - 1)?)
+                }
+                Some(SortedNumericSelectorType::Sum) => {
+                    let mut sum = 0i64;
+                    for i in 0..count {
+                        sum = sum.wrapping_add(doc_values.value_at(i)?);
+                    }
+                    self.increment(sum);
+                }
+                None => {
+                    let mut previous = None;
+                    for i in 0..count {
+                        let value = doc_values.value_at(i)?;
+                        if previous != Some(value) {
+                            self.increment(value);
+                            previous = Some(value);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn increment(&mut self, value: i64) {
+        *self.counts.entry(value).or_insert(0) += 1;
+    }
+
+    /// Returns all the values with their counts, sorted by value, e.g. for a
+    /// histogram.
+    pub fn all_children_sorted_by_value(&self) -> FacetResult {
+        let mut values: Vec<_> = self.counts.iter().map(|(&v, &c)| (v, c)).collect();
+        values.sort();
+        self.result(values)
+    }
+
+    fn result(&self, values: Vec<(i64, i32)>) -> FacetResult {
+        FacetResult {
+            dim: self.field.clone(),
+            path: vec![],
+            value: self.total_count,
+            child_count: self.counts.len(),
+            label_values: values
+                .into_iter()
+                .map(|(value, count)| LabelAndValue::new(value.to_string(), count))
+                .collect(),
+        }
+    }
+}
+
+impl Facets for LongValueFacetCounts {
+    /// The top values by decreasing count then increasing value. The value of
+    /// the result is the number of docs with a value, not the sum of the
+    /// counts of a multi-valued field.
+    fn top_children(&self, top_n: usize, dim: &str, path: &[&str]) -> Result<Option<FacetResult>> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        if !path.is_empty() {
+            bail!(IllegalArgument(
+                "long value facets only support flat dimensions, path must be empty".into()
+            ));
+        }
+        if dim != self.field || self.counts.is_empty() {
+            return Ok(None);
+        }
+        let mut values: Vec<_> = self.counts.iter().map(|(&v, &c)| (v, c)).collect();
+        values.sort_by(|(v1, c1), (v2, c2)| c2.cmp(c1).then(v1.cmp(v2)));
+        values.truncate(top_n);
+        Ok(Some(self.result(values)))
+    }
+
+    fn specific_value(&self, dim: &str, path: &[&str]) -> Result<i32> {
+        if path.len() != 1 {
+            bail!(IllegalArgument(
+                "long value facets only support a path of one value".into()
+            ));
+        }
+        let value: i64 = match path[0].parse() {
+            Ok(value) => value,
+            Err(_) => bail!(IllegalArgument(format!("invalid long value '{}'", path[0]))),
+        };
+        if dim != self.field {
+            return Ok(0);
+        }
+        Ok(self.counts.get(&value).cloned().unwrap_or(0))
+    }
+
+    fn all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        Ok(self
+            .top_children(top_n, &self.field, &[])?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockSortedNumericDocValues {
+        doc_values: Vec<Vec<i64>>,
+        current: usize,
+    }
+
+    impl SortedNumericDocValues for MockSortedNumericDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.current = doc as usize;
+            Ok(())
+        }
+
+        fn value_at(&mut self, index: usize) -> Result<i64> {
+            Ok(self.doc_values[self.current][index])
+        }
+
+        fn count(&self) -> usize {
+            self.doc_values[self.current].len()
+        }
+    }
+
+    fn count(selector: Option<SortedNumericSelectorType>) -> LongValueFacetCounts {
+        let mut facet_counts = LongValueFacetCounts {
+            field: "size".into(),
+            counts: HashMap::new(),
+            total_count: 0,
+        };
+        let mut doc_values = MockSortedNumericDocValues {
+            doc_values: vec![vec![1, 5, 5], vec![5, 10], vec![], vec![-3], vec![10]],
+            current: 0,
+        };
+        facet_counts
+            .count_segment(&mut doc_values, &[0, 1, 2, 3], selector)
+            .unwrap();
+        facet_counts
+    }
+
+    fn labels(result: FacetResult) -> Vec<(String, i32)> {
+        result
+            .label_values
+            .into_iter()
+            .map(|lv| (lv.label, lv.value))
+            .collect()
+    }
+
+    #[test]
+    fn test_long_value_facet_counts() {
+        let facet_counts = count(None);
+        let result = facet_counts.top_children(2, "size", &[]).unwrap().unwrap();
+        assert_eq!(result.value, 3);
+        assert_eq!(result.child_count, 4);
+        assert_eq!(
+            labels(result),
+            vec![("5".to_string(), 2), ("-3".to_string(), 1)]
+        );
+        assert_eq!(
+            labels(facet_counts.all_children_sorted_by_value()),
+            vec![
+                ("-3".to_string(), 1),
+                ("1".to_string(), 1),
+                ("5".to_string(), 2),
+                ("10".to_string(), 1),
+            ]
+        );
+        assert_eq!(facet_counts.specific_value("size", &["10"]).unwrap(), 1);
+        assert!(facet_counts.specific_value("size", &["ten"]).is_err());
+        assert!(facet_counts
+            .top_children(10, "other", &[])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_long_value_facet_counts_with_selector() {
+        let min = count(Some(SortedNumericSelectorType::Min));
+        assert_eq!(
+            labels(min.all_children_sorted_by_value()),
+            vec![
+                ("-3".to_string(), 1),
+                ("1".to_string(), 1),
+                ("5".to_string(), 1),
+            ]
+        );
+        let max = count(Some(SortedNumericSelectorType::Max));
+        assert_eq!(
+            labels(max.all_children_sorted_by_value()),
+            vec![
+                ("-3".to_string(), 1),
+                ("5".to_string(), 1),
+                ("10".to_string(), 1),
+            ]
+        );
+        let sum = count(Some(SortedNumericSelectorType::Sum));
+        assert_eq!(sum.specific_value("size", &["11"]).unwrap(), 1);
+        assert_eq!(sum.specific_value("size", &["15"]).unwrap(), 1);
+    }
+}
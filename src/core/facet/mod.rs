@@ -0,0 +1,174 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Faceted search, counting the facet labels of the docs matching a query.
+//!
+//! Facet labels are added to the docs as facet fields, e.g.
+//! `SortedSetDocValuesFacetField`. A `FacetsCollector` records the matching docs
+//! of a search, usually chained with the collector of the top hits, then a
+//! `Facets` implementation counts the labels of those docs per dimension.
+
+mod facets_collector;
+
+pub use self::facets_collector::*;
+
+mod sorted_set;
+
+pub use self::sorted_set::*;
+
+mod geo_grid;
+
+pub use self::geo_grid::*;
+
+mod long_values;
+
+pub use self::long_values::*;
+
+pub mod taxonomy;
+
+use error::Result;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Delimiter between the dimension and the path components of an indexed
+/// facet label.
+pub const DELIM_CHAR: char = '\u{1f}';
+
+/// Default index field name for the facet labels.
+pub const DEFAULT_INDEX_FIELD_NAME: &str = "$facets";
+
+/// A child label of a facet result with its count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelAndValue {
+    pub label: String,
+    pub value: i32,
+}
+
+impl LabelAndValue {
+    pub fn new(label: String, value: i32) -> LabelAndValue {
+        LabelAndValue { label, value }
+    }
+}
+
+impl fmt::Display for LabelAndValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.label, self.value)
+    }
+}
+
+/// The top children of a facet dimension or path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetResult {
+    /// Dimension that was requested.
+    pub dim: String,
+    /// Path whose children were requested.
+    pub path: Vec<String>,
+    /// Total value for this path, the sum of all the child counts.
+    pub value: i32,
+    /// How many child labels were encountered.
+    pub child_count: usize,
+    /// The top child labels with their counts.
+    pub label_values: Vec<LabelAndValue>,
+}
+
+impl fmt::Display for FacetResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "dim={} path={:?} value={} child_count={}",
+            self.dim, self.path, self.value, self.child_count
+        )?;
+        for label_value in &self.label_values {
+            write!(f, "\n  {}", label_value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Common interface of the facet counts.
+pub trait Facets {
+    /// Returns the top `top_n` child labels under the given dimension and path,
+    /// `None` if the dimension or path was never seen.
+    fn top_children(&self, top_n: usize, dim: &str, path: &[&str]) -> Result<Option<FacetResult>>;
+
+    /// Returns the count of a specific label, 0 if it was never seen.
+    fn specific_value(&self, dim: &str, path: &[&str]) -> Result<i32>;
+
+    /// Returns the top `top_n` children of all the dimensions, the dimensions
+    /// sorted by their value.
+    fn all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>>;
+}
+
+/// Encode the dimension and path components into a single indexed label.
+pub fn path_to_string(dim: &str, path: &[&str]) -> String {
+    let mut label = String::from(dim);
+    for component in path {
+        label.push(DELIM_CHAR);
+        label.push_str(component);
+    }
+    label
+}
+
+/// Split an indexed label into its dimension and path components.
+pub fn string_to_path(label: &str) -> Vec<&str> {
+    label.split(DELIM_CHAR).collect()
+}
+
+/// Sort the child labels by decreasing value then by label, keeping the first
+/// `top_n`.
+fn top_label_values(mut label_values: Vec<LabelAndValue>, top_n: usize) -> Vec<LabelAndValue> {
+    label_values.sort_by(|l1, l2| match l2.value.cmp(&l1.value) {
+        Ordering::Equal => l1.label.cmp(&l2.label),
+        ord => ord,
+    });
+    label_values.truncate(top_n);
+    label_values
+}
+
+/// Sort the facet results by decreasing value then by dimension.
+fn sort_facet_results(results: &mut Vec<FacetResult>) {
+    results.sort_by(|r1, r2| match r2.value.cmp(&r1.value) {
+        Ordering::Equal => r1.dim.cmp(&r2.dim),
+        ord => ord,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_to_string() {
+        let label = path_to_string("author", &["Bob"]);
+        assert_eq!(label, "author\u{1f}Bob");
+        assert_eq!(string_to_path(&label), vec!["author", "Bob"]);
+        assert_eq!(path_to_string("author", &[]), "author");
+    }
+
+    #[test]
+    fn test_top_label_values() {
+        let label_values = vec![
+            LabelAndValue::new("b".into(), 2),
+            LabelAndValue::new("c".into(), 3),
+            LabelAndValue::new("a".into(), 2),
+        ];
+        assert_eq!(
+            top_label_values(label_values, 2),
+            vec![
+                LabelAndValue::new("c".into(), 3),
+                LabelAndValue::new("a".into(), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::TokenStream;
+use core::codec::doc_values::{SortedSetDocValues, NO_MORE_ORDS};
+use core::doc::{FieldType, Fieldable, SortedSetDocValuesField};
+use core::facet::{
+    path_to_string, sort_facet_results, top_label_values, FacetResult, Facets, FacetsCollector,
+    LabelAndValue, DEFAULT_INDEX_FIELD_NAME, DELIM_CHAR,
+};
+use core::index::reader::IndexReader;
+use core::util::{DocId, Numeric, VariantValue};
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::collections::HashMap;
+
+/// A flat facet label, indexed as a `SortedSetDocValuesField` holding the
+/// dimension and the label, so it can be counted by
+/// `SortedSetDocValuesFacetCounts` without a taxonomy index.
+pub struct SortedSetDocValuesFacetField {
+    dim: String,
+    label: String,
+    field: SortedSetDocValuesField,
+}
+
+impl SortedSetDocValuesFacetField {
+    pub fn new(dim: &str, label: &str) -> Result<SortedSetDocValuesFacetField> {
+        Self::with_index_field(DEFAULT_INDEX_FIELD_NAME, dim, label)
+    }
+
+    /// Create a facet field indexed in `index_field`, the facet counts must
+    /// then be computed over the same field.
+    pub fn with_index_field(
+        index_field: &str,
+        dim: &str,
+        label: &str,
+    ) -> Result<SortedSetDocValuesFacetField> {
+        for component in &[dim, label] {
+            if component.is_empty() {
+                bail!(IllegalArgument("empty facet dim or label".into()));
+            }
+            if component.contains(DELIM_CHAR) {
+                bail!(IllegalArgument(format!(
+                    "facet dim or label '{}' contains the delimiter char",
+                    component
+                )));
+            }
+        }
+        let value = path_to_string(dim, &[label]);
+        Ok(SortedSetDocValuesFacetField {
+            dim: dim.to_string(),
+            label: label.to_string(),
+            field: SortedSetDocValuesField::new(index_field, value.as_bytes()),
+        })
+    }
+
+    pub fn dim(&self) -> &str {
+        &self.dim
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl Fieldable for SortedSetDocValuesFacetField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        Fieldable::binary_value(&self.field)
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        self.field.string_value()
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        self.field.numeric_value()
+    }
+}
+
+/// Counts the facet labels indexed by `SortedSetDocValuesFacetField` over the
+/// docs recorded by a `FacetsCollector`.
+///
+/// The ordinals of the labels are counted per segment, then resolved to the
+/// labels, so only the labels of the matching docs are ever looked up.
+pub struct SortedSetDocValuesFacetCounts {
+    // dim -> label -> count
+    counts: HashMap<String, HashMap<String, i32>>,
+}
+
+impl SortedSetDocValuesFacetCounts {
+    pub fn new<R: IndexReader + ?Sized>(
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<SortedSetDocValuesFacetCounts> {
+        Self::with_index_field(DEFAULT_INDEX_FIELD_NAME, reader, hits)
+    }
+
+    /// Count the facet labels indexed in `index_field`.
+    pub fn with_index_field<R: IndexReader + ?Sized>(
+        index_field: &str,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<SortedSetDocValuesFacetCounts> {
+        let mut facet_counts = SortedSetDocValuesFacetCounts {
+            counts: HashMap::new(),
+        };
+        let leaves = reader.leaves();
+        for matching_docs in hits.matching_docs() {
+            let leaf = &leaves[matching_docs.ord];
+            // segments without any facet label
+            if matching_docs.docs.is_empty()
+                || leaf
+                    .reader
+                    .field_infos()
+                    .field_info_by_name(index_field)
+                    .is_none()
+            {
+                continue;
+            }
+            let mut doc_values = leaf.reader.get_sorted_set_doc_values(index_field)?;
+            facet_counts.count_segment(doc_values.as_mut(), &matching_docs.docs)?;
+        }
+        Ok(facet_counts)
+    }
+
+    fn count_segment(
+        &mut self,
+        doc_values: &mut dyn SortedSetDocValues,
+        docs: &[DocId],
+    ) -> Result<()> {
+        let mut ord_counts = vec![0i32; doc_values.get_value_count()];
+        for &doc in docs {
+            doc_values.set_document(doc)?;
+            loop {
+                let ord = doc_values.next_ord()?;
+                if ord == NO_MORE_ORDS {
+                    break;
+                }
+                ord_counts[ord as usize] += 1;
+            }
+        }
+
+        for (ord, &count) in ord_counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let term = doc_values.lookup_ord(ord as i64)?;
+            let term = String::from_utf8(term)?;
+            let mut parts = term.splitn(2, DELIM_CHAR);
+            let dim = parts.next().unwrap();
+            let label = match parts.next() {
+                Some(label) => label,
+                None => bail!(IllegalArgument(format!("invalid facet label '{}'", term))),
+            };
+            *self
+                .counts
+                .entry(dim.to_string())
+                .or_insert_with(HashMap::new)
+                .entry(label.to_string())
+                .or_insert(0) += count;
+        }
+        Ok(())
+    }
+
+    fn dim_result(&self, dim: &str, labels: &HashMap<String, i32>, top_n: usize) -> FacetResult {
+        let label_values = labels
+            .iter()
+            .map(|(label, &count)| LabelAndValue::new(label.clone(), count))
+            .collect();
+        FacetResult {
+            dim: dim.to_string(),
+            path: vec![],
+            value: labels.values().sum(),
+            child_count: labels.len(),
+            label_values: top_label_values(label_values, top_n),
+        }
+    }
+}
+
+impl Facets for SortedSetDocValuesFacetCounts {
+    fn top_children(&self, top_n: usize, dim: &str, path: &[&str]) -> Result<Option<FacetResult>> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        if !path.is_empty() {
+            bail!(IllegalArgument(
+                "sorted set facets only support flat dimensions, path must be empty".into()
+            ));
+        }
+        Ok(self
+            .counts
+            .get(dim)
+            .map(|labels| self.dim_result(dim, labels, top_n)))
+    }
+
+    fn specific_value(&self, dim: &str, path: &[&str]) -> Result<i32> {
+        if path.len() != 1 {
+            bail!(IllegalArgument(
+                "sorted set facets only support a path of one label".into()
+            ));
+        }
+        Ok(self
+            .counts
+            .get(dim)
+            .and_then(|labels| labels.get(path[0]))
+            .cloned()
+            .unwrap_or(0))
+    }
+
+    fn all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        let mut results: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(dim, labels)| self.dim_result(dim, labels, top_n))
+            .collect();
+        sort_facet_results(&mut results);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::tests::*;
+
+    struct MockSortedSetDocValues {
+        terms: Vec<&'static str>,
+        doc_ords: Vec<Vec<i64>>,
+        current: Vec<i64>,
+    }
+
+    impl SortedSetDocValues for MockSortedSetDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.current = self.doc_ords[doc as usize].iter().rev().cloned().collect();
+            Ok(())
+        }
+
+        fn next_ord(&mut self) -> Result<i64> {
+            Ok(self.current.pop().unwrap_or(NO_MORE_ORDS))
+        }
+
+        fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+            Ok(self.terms[ord as usize].as_bytes().to_vec())
+        }
+
+        fn get_value_count(&self) -> usize {
+            self.terms.len()
+        }
+    }
+
+    fn doc_values(doc_ords: Vec<Vec<i64>>) -> MockSortedSetDocValues {
+        MockSortedSetDocValues {
+            terms: vec![
+                "author\u{1f}Bob",
+                "author\u{1f}Lisa",
+                "author\u{1f}Susan",
+                "year\u{1f}2010",
+                "year\u{1f}2012",
+            ],
+            doc_ords,
+            current: vec![],
+        }
+    }
+
+    #[test]
+    fn test_facet_field() {
+        let field = SortedSetDocValuesFacetField::new("author", "Bob").unwrap();
+        assert_eq!(field.name(), DEFAULT_INDEX_FIELD_NAME);
+        assert_eq!(field.dim(), "author");
+        assert_eq!(field.label(), "Bob");
+        assert_eq!(
+            Fieldable::binary_value(&field),
+            Some("author\u{1f}Bob".as_bytes())
+        );
+
+        assert!(SortedSetDocValuesFacetField::new("", "Bob").is_err());
+        assert!(SortedSetDocValuesFacetField::new("author", "B\u{1f}ob").is_err());
+    }
+
+    #[test]
+    fn test_sorted_set_facet_counts() {
+        let mut facet_counts = SortedSetDocValuesFacetCounts {
+            counts: HashMap::new(),
+        };
+        // two segments, docs 0 of the first and 2 of the second don't match
+        let mut segment1 = doc_values(vec![vec![0, 3], vec![0, 4], vec![1, 3]]);
+        facet_counts.count_segment(&mut segment1, &[1, 2]).unwrap();
+        let mut segment2 = doc_values(vec![vec![0], vec![2, 4], vec![1, 3]]);
+        facet_counts.count_segment(&mut segment2, &[0, 1]).unwrap();
+
+        let result = facet_counts
+            .top_children(2, "author", &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.value, 4);
+        assert_eq!(result.child_count, 3);
+        assert_eq!(
+            result.label_values,
+            vec![
+                LabelAndValue::new("Bob".into(), 2),
+                LabelAndValue::new("Lisa".into(), 1),
+            ]
+        );
+
+        assert_eq!(facet_counts.specific_value("year", &["2012"]).unwrap(), 2);
+        assert_eq!(facet_counts.specific_value("year", &["2011"]).unwrap(), 0);
+        assert!(facet_counts
+            .top_children(10, "publisher", &[])
+            .unwrap()
+            .is_none());
+        assert!(facet_counts.top_children(0, "author", &[]).is_err());
+
+        let dims: Vec<_> = facet_counts
+            .all_dims(10)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.dim, r.value))
+            .collect();
+        assert_eq!(dims, vec![("author".into(), 4), ("year".into(), 3)]);
+    }
+
+    #[test]
+    fn test_facet_counts_without_field() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let hits = FacetsCollector::new(false);
+        let facet_counts = SortedSetDocValuesFacetCounts::new(&index_reader, &hits).unwrap();
+        assert!(facet_counts.all_dims(10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+/// A path of the taxonomy, the first component is the dimension, e.g.
+/// `["date", "2010", "10"]`. The empty label is the root of the taxonomy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FacetLabel {
+    pub components: Vec<String>,
+}
+
+impl FacetLabel {
+    pub fn new(dim: &str, path: &[&str]) -> FacetLabel {
+        let mut components = Vec::with_capacity(path.len() + 1);
+        components.push(dim.to_string());
+        components.extend(path.iter().map(|c| c.to_string()));
+        FacetLabel { components }
+    }
+
+    pub fn from_components(components: Vec<String>) -> FacetLabel {
+        FacetLabel { components }
+    }
+
+    /// The label of the taxonomy root.
+    pub fn root() -> FacetLabel {
+        FacetLabel { components: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Returns the label of the first `len` components.
+    pub fn subpath(&self, len: usize) -> FacetLabel {
+        debug_assert!(len <= self.components.len());
+        FacetLabel {
+            components: self.components[..len].to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for FacetLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FacetLabel: [{}]", self.components.join(", "))
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hierarchical facets, backed by a taxonomy index.
+//!
+//! The taxonomy assigns an ordinal to every facet label and all of its
+//! ancestors, and is stored in its own directory, separate from the main index.
+//! The docs of the main index hold the ordinals of their labels in a binary doc
+//! values field, built by `DirectoryTaxonomyWriter::facet_field`, which are
+//! counted by `TaxonomyFacetCounts`.
+
+mod facet_label;
+
+pub use self::facet_label::*;
+
+mod taxonomy_writer;
+
+pub use self::taxonomy_writer::*;
+
+mod taxonomy_reader;
+
+pub use self::taxonomy_reader::*;
+
+mod taxonomy_facet_counts;
+
+pub use self::taxonomy_facet_counts::*;
+
+use core::facet::DELIM_CHAR;
+use core::store::io::{DataInput, DataOutput};
+use core::util::fst::{ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, FST};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{ErrorKind::CorruptIndex, Result};
+
+/// Ordinal of the root of the taxonomy, the parent of all the dimensions.
+pub const ROOT_ORDINAL: i32 = 0;
+
+/// Ordinal of a label that is not in the taxonomy.
+pub const INVALID_ORDINAL: i32 = -1;
+
+/// Name of the file holding the committed taxonomy.
+pub const TAXONOMY_FILE_NAME: &str = "taxonomy";
+
+const PENDING_TAXONOMY_FILE_NAME: &str = "pending_taxonomy";
+
+const TAXONOMY_CODEC: &str = "Taxonomy";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// The categories of a taxonomy, ordinal `i` has the last path component
+/// `components[i]` and the parent `parents[i]`.
+struct TaxonomyData {
+    components: Vec<String>,
+    parents: Vec<i32>,
+    // maps the encoded path of every category but the root to its ordinal
+    ordinals: Option<FST<ByteSequenceOutputFactory>>,
+}
+
+/// The path of `label` as indexed in the taxonomy.
+fn encode_label(label: &FacetLabel) -> String {
+    let mut path = String::new();
+    for (i, component) in label.components.iter().enumerate() {
+        if i > 0 {
+            path.push(DELIM_CHAR);
+        }
+        path.push_str(component);
+    }
+    path
+}
+
+fn encode_ordinal(ordinal: i32) -> ByteSequenceOutput {
+    ByteSequenceOutput::new(vec![
+        (ordinal >> 24) as u8,
+        (ordinal >> 16) as u8,
+        (ordinal >> 8) as u8,
+        ordinal as u8,
+    ])
+}
+
+fn decode_ordinal(output: &ByteSequenceOutput) -> i32 {
+    output
+        .inner()
+        .iter()
+        .fold(0i32, |ordinal, &b| (ordinal << 8) | i32::from(b))
+}
+
+/// Write the taxonomy, `paths` are the encoded paths of the categories with
+/// their ordinals, sorted by path.
+fn write_taxonomy<O: DataOutput>(
+    out: &mut O,
+    components: &[String],
+    parents: &[i32],
+    paths: &[(String, i32)],
+) -> Result<()> {
+    debug_assert_eq!(components.len(), parents.len());
+    out.write_vint(components.len() as i32)?;
+    for ordinal in 1..components.len() {
+        out.write_vint(parents[ordinal])?;
+        out.write_string(&components[ordinal])?;
+    }
+
+    if paths.is_empty() {
+        return out.write_byte(0);
+    }
+    out.write_byte(1)?;
+    let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory::new());
+    builder.init();
+    let mut scratch = IntsRefBuilder::new();
+    for (path, ordinal) in paths {
+        builder.add(
+            to_ints_ref(path.as_bytes(), &mut scratch),
+            encode_ordinal(*ordinal),
+        )?;
+    }
+    match builder.finish()? {
+        Some(fst) => fst.save(out),
+        None => unreachable!(),
+    }
+}
+
+fn read_taxonomy<I: DataInput + ?Sized>(input: &mut I) -> Result<TaxonomyData> {
+    let size = input.read_vint()?;
+    if size < 1 {
+        bail!(CorruptIndex(format!("invalid taxonomy size: {}", size)));
+    }
+    let size = size as usize;
+    let mut components = Vec::with_capacity(size);
+    let mut parents = Vec::with_capacity(size);
+    components.push(String::new());
+    parents.push(INVALID_ORDINAL);
+    for ordinal in 1..size {
+        let parent = input.read_vint()?;
+        if parent < 0 || parent as usize >= ordinal {
+            bail!(CorruptIndex(format!(
+                "invalid parent {} of taxonomy ordinal {}",
+                parent, ordinal
+            )));
+        }
+        parents.push(parent);
+        components.push(input.read_string()?);
+    }
+
+    let ordinals = if input.read_byte()? == 1 {
+        Some(FST::from_input(input, ByteSequenceOutputFactory::new())?)
+    } else {
+        None
+    };
+    Ok(TaxonomyData {
+        components,
+        parents,
+        ordinals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::store::io::{ByteArrayDataInput, GrowableByteArrayDataOutput};
+
+    #[test]
+    fn test_write_read_taxonomy() {
+        let components: Vec<String> = vec!["", "a", "b", "c"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let parents = vec![INVALID_ORDINAL, ROOT_ORDINAL, 1, ROOT_ORDINAL];
+        let paths = vec![
+            ("a".to_string(), 1),
+            ("a\u{1f}b".to_string(), 2),
+            ("c".to_string(), 3),
+        ];
+        let mut out = GrowableByteArrayDataOutput::new(64);
+        write_taxonomy(&mut out, &components, &parents, &paths).unwrap();
+
+        let length = out.position();
+        let mut input = ByteArrayDataInput::new(&out.bytes[..length]);
+        let data = read_taxonomy(&mut input).unwrap();
+        assert_eq!(data.components, components);
+        assert_eq!(data.parents, parents);
+        let fst = data.ordinals.unwrap();
+        for (path, ordinal) in &paths {
+            let output = fst.get(path.as_bytes()).unwrap().unwrap();
+            assert_eq!(decode_ordinal(&output), *ordinal);
+        }
+        assert!(fst.get(b"b").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_encode_ordinal() {
+        for &ordinal in &[0, 1, 255, 256, 70_000, i32::max_value()] {
+            assert_eq!(decode_ordinal(&encode_ordinal(ordinal)), ordinal);
+        }
+    }
+}
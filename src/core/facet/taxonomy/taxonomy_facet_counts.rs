@@ -0,0 +1,278 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::facet::taxonomy::{DirectoryTaxonomyReader, FacetLabel, INVALID_ORDINAL, ROOT_ORDINAL};
+use core::facet::{
+    sort_facet_results, top_label_values, FacetResult, Facets, FacetsCollector, LabelAndValue,
+    DEFAULT_INDEX_FIELD_NAME,
+};
+use core::index::reader::IndexReader;
+use core::store::io::{ByteArrayDataInput, DataInput};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Counts the taxonomy facet labels, indexed by `DirectoryTaxonomyWriter::facet_field`,
+/// over the docs recorded by a `FacetsCollector`.
+///
+/// Every level of the hierarchy is counted, so the children of any path can be
+/// drilled down into, e.g. the top months of `["date", "2010"]`.
+pub struct TaxonomyFacetCounts<'a> {
+    taxonomy: &'a DirectoryTaxonomyReader,
+    counts: Vec<i32>,
+}
+
+impl<'a> TaxonomyFacetCounts<'a> {
+    pub fn new<R: IndexReader + ?Sized>(
+        taxonomy: &'a DirectoryTaxonomyReader,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<TaxonomyFacetCounts<'a>> {
+        Self::with_index_field(DEFAULT_INDEX_FIELD_NAME, taxonomy, reader, hits)
+    }
+
+    /// Count the ordinals indexed in `index_field`.
+    pub fn with_index_field<R: IndexReader + ?Sized>(
+        index_field: &str,
+        taxonomy: &'a DirectoryTaxonomyReader,
+        reader: &R,
+        hits: &FacetsCollector,
+    ) -> Result<TaxonomyFacetCounts<'a>> {
+        let mut facet_counts = TaxonomyFacetCounts {
+            taxonomy,
+            counts: vec![0; taxonomy.size()],
+        };
+        let leaves = reader.leaves();
+        for matching_docs in hits.matching_docs() {
+            let leaf = &leaves[matching_docs.ord];
+            // segments without any facet label
+            if matching_docs.docs.is_empty()
+                || leaf
+                    .reader
+                    .field_infos()
+                    .field_info_by_name(index_field)
+                    .is_none()
+            {
+                continue;
+            }
+            let mut doc_values = leaf.reader.get_binary_doc_values(index_field)?;
+            for &doc in &matching_docs.docs {
+                facet_counts.count_ordinals(&doc_values.get(doc)?)?;
+            }
+        }
+        Ok(facet_counts)
+    }
+
+    fn count_ordinals(&mut self, encoded: &[u8]) -> Result<()> {
+        let mut input = ByteArrayDataInput::new(encoded);
+        let mut ordinal = 0;
+        while input.position() < encoded.len() {
+            ordinal += input.read_vint()?;
+            // ordinals added to the taxonomy after the reader was opened
+            if let Some(count) = self.counts.get_mut(ordinal as usize) {
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn children_result(&self, dim: &str, path: &[&str], ordinal: i32, top_n: usize) -> FacetResult {
+        let mut child_count = 0;
+        let mut label_values = vec![];
+        for child in self.taxonomy.children(ordinal) {
+            let count = self.counts[child as usize];
+            if count > 0 {
+                child_count += 1;
+                label_values.push(LabelAndValue::new(
+                    self.taxonomy.component(child).to_string(),
+                    count,
+                ));
+            }
+        }
+        FacetResult {
+            dim: dim.to_string(),
+            path: path.iter().map(|c| c.to_string()).collect(),
+            value: self.counts[ordinal as usize],
+            child_count,
+            label_values: top_label_values(label_values, top_n),
+        }
+    }
+}
+
+impl<'a> Facets for TaxonomyFacetCounts<'a> {
+    fn top_children(&self, top_n: usize, dim: &str, path: &[&str]) -> Result<Option<FacetResult>> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        let ordinal = self.taxonomy.ordinal(&FacetLabel::new(dim, path))?;
+        if ordinal == INVALID_ORDINAL || self.counts[ordinal as usize] == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.children_result(dim, path, ordinal, top_n)))
+    }
+
+    fn specific_value(&self, dim: &str, path: &[&str]) -> Result<i32> {
+        let ordinal = self.taxonomy.ordinal(&FacetLabel::new(dim, path))?;
+        if ordinal == INVALID_ORDINAL {
+            Ok(0)
+        } else {
+            Ok(self.counts[ordinal as usize])
+        }
+    }
+
+    fn all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        let mut results: Vec<_> = self
+            .taxonomy
+            .children(ROOT_ORDINAL)
+            .filter(|&dim| self.counts[dim as usize] > 0)
+            .map(|dim| self.children_result(self.taxonomy.component(dim), &[], dim, top_n))
+            .collect();
+        sort_facet_results(&mut results);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::doc::Fieldable;
+    use core::facet::taxonomy::DirectoryTaxonomyWriter;
+    use core::store::directory::FSDirectory;
+
+    use std::fs;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_taxonomy_facet_counts() {
+        let path = ::std::env::temp_dir().join("rucene_test_taxonomy_facet_counts");
+        let directory = Arc::new(FSDirectory::new(&path).unwrap());
+
+        let docs = vec![
+            vec![
+                FacetLabel::new("author", &["Bob"]),
+                FacetLabel::new("date", &["2010", "10", "15"]),
+            ],
+            vec![
+                FacetLabel::new("author", &["Lisa"]),
+                FacetLabel::new("date", &["2010", "10", "20"]),
+            ],
+            vec![
+                FacetLabel::new("author", &["Lisa"]),
+                FacetLabel::new("date", &["2012", "1", "1"]),
+            ],
+            vec![FacetLabel::new("author", &["Susan"])],
+        ];
+        let mut fields = vec![];
+        {
+            let mut writer = DirectoryTaxonomyWriter::new(Arc::clone(&directory)).unwrap();
+            for labels in &docs {
+                fields.push(writer.facet_field(labels).unwrap());
+            }
+            assert_eq!(writer.size(), 13);
+            writer.commit().unwrap();
+        }
+
+        // reopening the writer keeps the ordinals
+        let mut writer = DirectoryTaxonomyWriter::new(Arc::clone(&directory)).unwrap();
+        assert_eq!(writer.size(), 13);
+        let ordinal = writer
+            .add_category(&FacetLabel::new("author", &["Lisa"]))
+            .unwrap();
+        assert!(writer
+            .add_category(&FacetLabel::new("author", &["Li\u{1f}sa"]))
+            .is_err());
+
+        let taxonomy = DirectoryTaxonomyReader::open(directory.as_ref()).unwrap();
+        assert_eq!(taxonomy.size(), 13);
+        assert_eq!(
+            taxonomy
+                .ordinal(&FacetLabel::new("author", &["Lisa"]))
+                .unwrap(),
+            ordinal
+        );
+        assert_eq!(
+            taxonomy.path(ordinal),
+            Some(FacetLabel::new("author", &["Lisa"]))
+        );
+        assert_eq!(
+            taxonomy
+                .ordinal(&FacetLabel::new("author", &["Tom"]))
+                .unwrap(),
+            INVALID_ORDINAL
+        );
+        assert_eq!(taxonomy.ordinal(&FacetLabel::root()).unwrap(), ROOT_ORDINAL);
+
+        // count all the docs but the last one
+        let mut facet_counts = TaxonomyFacetCounts {
+            taxonomy: &taxonomy,
+            counts: vec![0; taxonomy.size()],
+        };
+        for field in &fields[..3] {
+            facet_counts
+                .count_ordinals(Fieldable::binary_value(field).unwrap())
+                .unwrap();
+        }
+
+        let result = facet_counts
+            .top_children(10, "author", &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.value, 3);
+        assert_eq!(result.child_count, 2);
+        assert_eq!(
+            result.label_values,
+            vec![
+                LabelAndValue::new("Lisa".into(), 2),
+                LabelAndValue::new("Bob".into(), 1),
+            ]
+        );
+
+        // drill down into the date hierarchy
+        let result = facet_counts
+            .top_children(10, "date", &["2010"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.value, 2);
+        assert_eq!(
+            result.label_values,
+            vec![LabelAndValue::new("10".into(), 2)]
+        );
+        assert_eq!(
+            facet_counts
+                .specific_value("date", &["2010", "10", "20"])
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            facet_counts.specific_value("author", &["Susan"]).unwrap(),
+            0
+        );
+        assert!(facet_counts
+            .top_children(10, "author", &["Susan"])
+            .unwrap()
+            .is_none());
+
+        let dims: Vec<_> = facet_counts
+            .all_dims(1)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.dim, r.value, r.label_values.len()))
+            .collect();
+        assert_eq!(dims, vec![("author".into(), 3, 1), ("date".into(), 3, 1)]);
+
+        drop(directory);
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
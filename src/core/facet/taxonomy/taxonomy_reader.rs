@@ -0,0 +1,131 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{check_footer, check_header};
+use core::facet::taxonomy::{
+    decode_ordinal, encode_label, read_taxonomy, FacetLabel, TaxonomyData, INVALID_ORDINAL,
+    ROOT_ORDINAL, TAXONOMY_CODEC, TAXONOMY_FILE_NAME, VERSION_CURRENT, VERSION_START,
+};
+use core::store::directory::Directory;
+use core::store::IOContext;
+
+use error::Result;
+
+/// Reads a taxonomy committed by a `DirectoryTaxonomyWriter`.
+///
+/// The reader is a point in time view of the taxonomy, categories added and
+/// committed afterwards are only visible to a newly opened reader.
+pub struct DirectoryTaxonomyReader {
+    data: TaxonomyData,
+    // youngest child of every ordinal
+    children: Vec<i32>,
+    // next older sibling of every ordinal
+    siblings: Vec<i32>,
+}
+
+impl DirectoryTaxonomyReader {
+    pub fn open<D: Directory>(directory: &D) -> Result<DirectoryTaxonomyReader> {
+        let mut input = directory.open_checksum_input(TAXONOMY_FILE_NAME, &IOContext::READ)?;
+        check_header(&mut input, TAXONOMY_CODEC, VERSION_START, VERSION_CURRENT)?;
+        let data = read_taxonomy(&mut input)?;
+        check_footer(&mut input)?;
+        Ok(Self::new(data))
+    }
+
+    fn new(data: TaxonomyData) -> DirectoryTaxonomyReader {
+        let size = data.parents.len();
+        let mut children = vec![INVALID_ORDINAL; size];
+        let mut siblings = vec![INVALID_ORDINAL; size];
+        for ordinal in 1..size {
+            let parent = data.parents[ordinal] as usize;
+            siblings[ordinal] = children[parent];
+            children[parent] = ordinal as i32;
+        }
+        DirectoryTaxonomyReader {
+            data,
+            children,
+            siblings,
+        }
+    }
+
+    /// Returns the number of categories in the taxonomy, including the root.
+    pub fn size(&self) -> usize {
+        self.data.parents.len()
+    }
+
+    /// Returns the ordinal of `label`, `INVALID_ORDINAL` if it's not in the taxonomy.
+    pub fn ordinal(&self, label: &FacetLabel) -> Result<i32> {
+        if label.is_empty() {
+            return Ok(ROOT_ORDINAL);
+        }
+        if let Some(ref fst) = self.data.ordinals {
+            if let Some(output) = fst.get(encode_label(label).as_bytes())? {
+                return Ok(decode_ordinal(&output));
+            }
+        }
+        Ok(INVALID_ORDINAL)
+    }
+
+    /// Returns the label of `ordinal`, `None` if it's not in the taxonomy.
+    pub fn path(&self, ordinal: i32) -> Option<FacetLabel> {
+        if ordinal < 0 || ordinal as usize >= self.size() {
+            return None;
+        }
+        let mut components = vec![];
+        let mut current = ordinal;
+        while current != ROOT_ORDINAL {
+            components.push(self.data.components[current as usize].clone());
+            current = self.data.parents[current as usize];
+        }
+        components.reverse();
+        Some(FacetLabel::from_components(components))
+    }
+
+    /// Returns the last path component of `ordinal`, the empty string for the root.
+    pub fn component(&self, ordinal: i32) -> &str {
+        &self.data.components[ordinal as usize]
+    }
+
+    /// Returns the parent of `ordinal`, `INVALID_ORDINAL` for the root.
+    pub fn parent(&self, ordinal: i32) -> i32 {
+        self.data.parents[ordinal as usize]
+    }
+
+    /// Returns the children of `ordinal`, youngest first.
+    pub fn children(&self, ordinal: i32) -> ChildrenIterator {
+        ChildrenIterator {
+            siblings: &self.siblings,
+            child: self.children[ordinal as usize],
+        }
+    }
+}
+
+/// Iterates over the children of a category, youngest first.
+pub struct ChildrenIterator<'a> {
+    siblings: &'a [i32],
+    child: i32,
+}
+
+impl<'a> Iterator for ChildrenIterator<'a> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        if self.child == INVALID_ORDINAL {
+            None
+        } else {
+            let child = self.child;
+            self.child = self.siblings[child as usize];
+            Some(child)
+        }
+    }
+}
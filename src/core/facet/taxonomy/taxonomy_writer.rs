@@ -0,0 +1,206 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{check_footer, check_header, write_footer, write_header};
+use core::doc::BinaryDocValuesField;
+use core::facet::taxonomy::{
+    encode_label, read_taxonomy, write_taxonomy, FacetLabel, INVALID_ORDINAL,
+    PENDING_TAXONOMY_FILE_NAME, ROOT_ORDINAL, TAXONOMY_CODEC, TAXONOMY_FILE_NAME, VERSION_CURRENT,
+    VERSION_START,
+};
+use core::facet::{DEFAULT_INDEX_FIELD_NAME, DELIM_CHAR};
+use core::store::directory::Directory;
+use core::store::io::{DataOutput, GrowableByteArrayDataOutput};
+use core::store::IOContext;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Adds categories to a taxonomy stored in its own directory.
+///
+/// Every category gets an ordinal, which never changes once assigned, and all
+/// the ancestors of a category are added along with it. Added categories are
+/// only visible to the readers once `commit` is called.
+pub struct DirectoryTaxonomyWriter<D: Directory> {
+    directory: Arc<D>,
+    ordinals: HashMap<FacetLabel, i32>,
+    components: Vec<String>,
+    parents: Vec<i32>,
+    committed_size: usize,
+}
+
+impl<D: Directory> DirectoryTaxonomyWriter<D> {
+    /// Open a writer on `directory`, loading the taxonomy committed in it if any.
+    pub fn new(directory: Arc<D>) -> Result<DirectoryTaxonomyWriter<D>> {
+        let mut writer = DirectoryTaxonomyWriter {
+            directory,
+            ordinals: HashMap::new(),
+            components: vec![String::new()],
+            parents: vec![INVALID_ORDINAL],
+            committed_size: 0,
+        };
+        if writer
+            .directory
+            .list_all()?
+            .iter()
+            .any(|f| f == TAXONOMY_FILE_NAME)
+        {
+            writer.load()?;
+        }
+        Ok(writer)
+    }
+
+    fn load(&mut self) -> Result<()> {
+        let mut input = self
+            .directory
+            .open_checksum_input(TAXONOMY_FILE_NAME, &IOContext::READ_ONCE)?;
+        check_header(&mut input, TAXONOMY_CODEC, VERSION_START, VERSION_CURRENT)?;
+        let data = read_taxonomy(&mut input)?;
+        check_footer(&mut input)?;
+        // the ordinals are rebuilt from the parents, which precede their children
+        let mut labels = Vec::with_capacity(data.parents.len());
+        labels.push(FacetLabel::root());
+        for ordinal in 1..data.parents.len() {
+            let mut components = labels[data.parents[ordinal] as usize].components.clone();
+            components.push(data.components[ordinal].clone());
+            let label = FacetLabel::from_components(components);
+            self.ordinals.insert(label.clone(), ordinal as i32);
+            labels.push(label);
+        }
+        self.committed_size = data.parents.len();
+        self.components = data.components;
+        self.parents = data.parents;
+        Ok(())
+    }
+
+    /// Returns the number of categories in the taxonomy, including the root.
+    pub fn size(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Returns the ordinal of `label`, adding it and its ancestors to the
+    /// taxonomy if it's not there yet.
+    pub fn add_category(&mut self, label: &FacetLabel) -> Result<i32> {
+        if label.is_empty() {
+            return Ok(ROOT_ORDINAL);
+        }
+        if let Some(&ordinal) = self.ordinals.get(label) {
+            return Ok(ordinal);
+        }
+        for component in &label.components {
+            if component.is_empty() {
+                bail!(IllegalArgument(format!(
+                    "empty path component in {}",
+                    label
+                )));
+            }
+            if component.contains(DELIM_CHAR) {
+                bail!(IllegalArgument(format!(
+                    "path component '{}' contains the delimiter char",
+                    component
+                )));
+            }
+        }
+
+        let mut parent = ROOT_ORDINAL;
+        for len in 1..=label.len() {
+            let path = label.subpath(len);
+            parent = match self.ordinals.get(&path) {
+                Some(&ordinal) => ordinal,
+                None => {
+                    let ordinal = self.parents.len() as i32;
+                    self.components.push(path.components[len - 1].clone());
+                    self.parents.push(parent);
+                    self.ordinals.insert(path, ordinal);
+                    ordinal
+                }
+            };
+        }
+        Ok(parent)
+    }
+
+    /// Build the field holding the ordinals of the given labels of a doc, to be
+    /// counted by `TaxonomyFacetCounts`.
+    pub fn facet_field(&mut self, labels: &[FacetLabel]) -> Result<BinaryDocValuesField> {
+        self.facet_field_with_index_field(DEFAULT_INDEX_FIELD_NAME, labels)
+    }
+
+    /// Like `facet_field`, with the ordinals indexed in `index_field`.
+    pub fn facet_field_with_index_field(
+        &mut self,
+        index_field: &str,
+        labels: &[FacetLabel],
+    ) -> Result<BinaryDocValuesField> {
+        // all the ancestors are indexed, so the counts of every level of the
+        // hierarchy are exact without rolling them up
+        let mut ordinals = HashSet::new();
+        for label in labels {
+            if label.is_empty() {
+                bail!(IllegalArgument("empty facet label".into()));
+            }
+            self.add_category(label)?;
+            for len in 1..=label.len() {
+                ordinals.insert(self.ordinals[&label.subpath(len)]);
+            }
+        }
+        let mut ordinals: Vec<_> = ordinals.into_iter().collect();
+        ordinals.sort();
+
+        let mut out = GrowableByteArrayDataOutput::new(ordinals.len() * 2);
+        let mut prev = 0;
+        for ordinal in ordinals {
+            out.write_vint(ordinal - prev)?;
+            prev = ordinal;
+        }
+        let length = out.position();
+        Ok(BinaryDocValuesField::new(index_field, &out.bytes[..length]))
+    }
+
+    /// Commit the added categories, making them visible to newly opened readers.
+    pub fn commit(&mut self) -> Result<()> {
+        if self.committed_size == self.size() {
+            return Ok(());
+        }
+
+        let mut paths: Vec<_> = self
+            .ordinals
+            .iter()
+            .map(|(label, &ordinal)| (encode_label(label), ordinal))
+            .collect();
+        paths.sort();
+
+        let existing = self.directory.list_all()?;
+        if existing.iter().any(|f| f == PENDING_TAXONOMY_FILE_NAME) {
+            self.directory.delete_file(PENDING_TAXONOMY_FILE_NAME)?;
+        }
+        {
+            let mut out = self
+                .directory
+                .create_output(PENDING_TAXONOMY_FILE_NAME, &IOContext::Default)?;
+            write_header(&mut out, TAXONOMY_CODEC, VERSION_CURRENT)?;
+            write_taxonomy(&mut out, &self.components, &self.parents, &paths)?;
+            write_footer(&mut out)?;
+        }
+        let mut pending = HashSet::new();
+        pending.insert(PENDING_TAXONOMY_FILE_NAME.to_string());
+        self.directory.sync(&pending)?;
+        self.directory
+            .rename(PENDING_TAXONOMY_FILE_NAME, TAXONOMY_FILE_NAME)?;
+        self.directory.sync_meta_data()?;
+
+        self.committed_size = self.size();
+        Ok(())
+    }
+}
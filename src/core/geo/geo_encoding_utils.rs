@@ -0,0 +1,120 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::geo::{check_latitude, check_longitude, MAX_LAT_INCL, MAX_LON_INCL};
+
+use error::Result;
+
+const LAT_SCALE: f64 = (1u64 << 32) as f64 / 180.0;
+const LAT_DECODE: f64 = 1.0 / LAT_SCALE;
+const LON_SCALE: f64 = (1u64 << 32) as f64 / 360.0;
+const LON_DECODE: f64 = 1.0 / LON_SCALE;
+
+/// Quantizes a latitude in degrees to a 32 bits int, rounding down.
+pub fn encode_latitude(latitude: f64) -> Result<i32> {
+    check_latitude(latitude)?;
+    // the max value would overflow, it is encoded as the previous value
+    let latitude = if latitude == MAX_LAT_INCL {
+        next_down(latitude)
+    } else {
+        latitude
+    };
+    Ok((latitude / LAT_DECODE).floor() as i32)
+}
+
+/// Quantizes a latitude in degrees to a 32 bits int, rounding up, for the
+/// lower bounds of the queries.
+pub fn encode_latitude_ceil(latitude: f64) -> Result<i32> {
+    check_latitude(latitude)?;
+    let latitude = if latitude == MAX_LAT_INCL {
+        next_down(latitude)
+    } else {
+        latitude
+    };
+    Ok(to_int_saturating((latitude / LAT_DECODE).ceil()))
+}
+
+/// Quantizes a longitude in degrees to a 32 bits int, rounding down.
+pub fn encode_longitude(longitude: f64) -> Result<i32> {
+    check_longitude(longitude)?;
+    let longitude = if longitude == MAX_LON_INCL {
+        next_down(longitude)
+    } else {
+        longitude
+    };
+    Ok((longitude / LON_DECODE).floor() as i32)
+}
+
+/// Quantizes a longitude in degrees to a 32 bits int, rounding up, for the
+/// lower bounds of the queries.
+pub fn encode_longitude_ceil(longitude: f64) -> Result<i32> {
+    check_longitude(longitude)?;
+    let longitude = if longitude == MAX_LON_INCL {
+        next_down(longitude)
+    } else {
+        longitude
+    };
+    Ok(to_int_saturating((longitude / LON_DECODE).ceil()))
+}
+
+/// Returns the latitude in degrees of a quantized latitude.
+pub fn decode_latitude(encoded: i32) -> f64 {
+    f64::from(encoded) * LAT_DECODE
+}
+
+/// Returns the longitude in degrees of a quantized longitude.
+pub fn decode_longitude(encoded: i32) -> f64 {
+    f64::from(encoded) * LON_DECODE
+}
+
+/// Casts to `i32`, the values just above the max coming from the ceiled
+/// max degrees are mapped to the max.
+fn to_int_saturating(d: f64) -> i32 {
+    if d >= f64::from(i32::max_value()) {
+        i32::max_value()
+    } else {
+        d as i32
+    }
+}
+
+/// The greatest `f64` lower than the positive `d`.
+fn next_down(d: f64) -> f64 {
+    debug_assert!(d > 0.0 && d.is_finite());
+    f64::from_bits(d.to_bits() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        for &lat in &[-90.0, -45.5, 0.0, 12.345_678, 89.999_999, 90.0] {
+            let decoded = decode_latitude(encode_latitude(lat).unwrap());
+            assert!(decoded <= lat && lat - decoded <= LAT_DECODE, "{}", lat);
+            let decoded = decode_latitude(encode_latitude_ceil(lat).unwrap());
+            assert!((decoded - lat).abs() <= LAT_DECODE, "{}", lat);
+        }
+        for &lon in &[-180.0, -90.25, 0.0, 123.456_789, 180.0] {
+            let decoded = decode_longitude(encode_longitude(lon).unwrap());
+            assert!(decoded <= lon && lon - decoded <= LON_DECODE, "{}", lon);
+        }
+        assert_eq!(encode_latitude(-90.0).unwrap(), i32::min_value());
+        assert_eq!(encode_latitude(90.0).unwrap(), i32::max_value());
+        assert_eq!(encode_longitude(-180.0).unwrap(), i32::min_value());
+        assert_eq!(encode_longitude(180.0).unwrap(), i32::max_value());
+        assert_eq!(encode_latitude_ceil(90.0).unwrap(), i32::max_value());
+        assert!(encode_latitude(91.0).is_err());
+        assert!(encode_longitude(::std::f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::points::Relation;
+use core::geo::{
+    decode_latitude, decode_longitude, encode_latitude, encode_longitude, Rectangle, MIN_LAT_INCL,
+    MIN_LON_INCL,
+};
+use core::util::{deinterleave, interleave};
+
+use error::{ErrorKind, Result};
+
+/// The max length of a geohash, 60 bits of the morton code.
+pub const MAX_GEOHASH_PRECISION: usize = 12;
+
+/// The characters of the geohashes, 5 bits each.
+pub const BASE_32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Encodes a location as the morton code of its quantized latitude and
+/// longitude, the longitude bits going first.
+///
+/// Like geohashes, the locations sharing a prefix of their morton codes
+/// are in the same cell, so sorting by morton code keeps the nearby
+/// locations close.
+pub fn morton_encode(latitude: f64, longitude: f64) -> Result<u64> {
+    let lat = flip_sign(encode_latitude(latitude)?);
+    let lon = flip_sign(encode_longitude(longitude)?);
+    Ok(interleave(lat, lon))
+}
+
+/// Returns the latitude in degrees of a morton code.
+pub fn morton_decode_latitude(hash: u64) -> f64 {
+    decode_latitude(flip_sign_back(deinterleave(hash)))
+}
+
+/// Returns the longitude in degrees of a morton code.
+pub fn morton_decode_longitude(hash: u64) -> f64 {
+    decode_longitude(flip_sign_back(deinterleave(hash >> 1)))
+}
+
+/// Encodes a location as a geohash of `precision` characters.
+pub fn geohash_encode(latitude: f64, longitude: f64, precision: usize) -> Result<String> {
+    check_precision(precision)?;
+    let hash = morton_encode(latitude, longitude)?;
+    let geohash = (0..precision)
+        .map(|i| BASE_32[((hash >> (59 - i * 5)) & 0x1f) as usize] as char)
+        .collect();
+    Ok(geohash)
+}
+
+/// Returns the cell of a geohash.
+pub fn geohash_bbox(geohash: &str) -> Result<Rectangle> {
+    check_precision(geohash.len())?;
+    let mut hash = 0u64;
+    for c in geohash.bytes() {
+        let value = match BASE_32.iter().position(|&b| b == c) {
+            Some(value) => value as u64,
+            None => bail!(ErrorKind::IllegalArgument(format!(
+                "invalid character '{}' in geohash '{}'",
+                c as char, geohash
+            ))),
+        };
+        hash = (hash << 5) | value;
+    }
+    let bits = geohash.len() * 5;
+    let hash = hash << (64 - bits);
+    // the longitude gets the extra bit of the odd lengths
+    let lon_bits = (bits + 1) / 2;
+    let lat_bits = bits / 2;
+    let lon_index = deinterleave(hash >> 1) >> (32 - lon_bits);
+    let lat_index = deinterleave(hash) >> (32 - lat_bits);
+    let lon_width = 360.0 / (1u64 << lon_bits) as f64;
+    let lat_height = 180.0 / (1u64 << lat_bits) as f64;
+    let min_lon = MIN_LON_INCL + f64::from(lon_index) * lon_width;
+    let min_lat = MIN_LAT_INCL + f64::from(lat_index) * lat_height;
+    Ok(Rectangle {
+        min_lat,
+        max_lat: min_lat + lat_height,
+        min_lon,
+        max_lon: min_lon + lon_width,
+    })
+}
+
+/// Returns the shortest geohash length whose cells are at most `degrees`
+/// wide and high, `MAX_GEOHASH_PRECISION` if none is.
+pub fn geohash_precision_for(degrees: f64) -> usize {
+    (1..=MAX_GEOHASH_PRECISION)
+        .find(|&precision| {
+            let bits = precision * 5;
+            360.0 / (1u64 << ((bits + 1) / 2)) as f64 <= degrees
+                && 180.0 / (1u64 << (bits / 2)) as f64 <= degrees
+        })
+        .unwrap_or(MAX_GEOHASH_PRECISION)
+}
+
+/// Returns the geohashes of the cells covering a box, the largest cells
+/// within the box and the cells of `precision` characters crossing its
+/// edges or touching them. The cover is coarse, it may spill over the box by
+/// a cell.
+pub fn geohash_cover(rect: &Rectangle, precision: usize) -> Result<Vec<String>> {
+    check_precision(precision)?;
+    let mut cells = vec![];
+    cover_cells(
+        rect,
+        &mut String::with_capacity(precision),
+        precision,
+        &mut cells,
+    )?;
+    Ok(cells)
+}
+
+fn cover_cells(
+    rect: &Rectangle,
+    prefix: &mut String,
+    precision: usize,
+    cells: &mut Vec<String>,
+) -> Result<()> {
+    for &c in BASE_32.iter() {
+        prefix.push(c as char);
+        let cell = geohash_bbox(prefix)?;
+        match rect.relate(cell.min_lat, cell.max_lat, cell.min_lon, cell.max_lon) {
+            Relation::CellInsideQuery => cells.push(prefix.clone()),
+            Relation::CellCrossesQuery if prefix.len() == precision => cells.push(prefix.clone()),
+            Relation::CellCrossesQuery => cover_cells(rect, prefix, precision, cells)?,
+            Relation::CellOutsideQuery => {}
+        }
+        prefix.pop();
+    }
+    Ok(())
+}
+
+fn check_precision(precision: usize) -> Result<()> {
+    if precision == 0 || precision > MAX_GEOHASH_PRECISION {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "geohash precision must be between 1 and {}, got {}",
+            MAX_GEOHASH_PRECISION, precision
+        )));
+    }
+    Ok(())
+}
+
+/// Maps the quantized values to unsigned ints in the same order.
+fn flip_sign(encoded: i32) -> u32 {
+    (encoded as u32) ^ 0x8000_0000
+}
+
+fn flip_sign_back(bits: u32) -> i32 {
+    (bits ^ 0x8000_0000) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave() {
+        assert_eq!(interleave(0b11, 0), 0b101);
+        assert_eq!(interleave(0, 0b11), 0b1010);
+        let hash = interleave(0xdead_beef, 0x1234_5678);
+        assert_eq!(deinterleave(hash), 0xdead_beef);
+        assert_eq!(deinterleave(hash >> 1), 0x1234_5678);
+    }
+
+    #[test]
+    fn test_morton() {
+        let hash = morton_encode(48.858_37, 2.294_481).unwrap();
+        assert!((morton_decode_latitude(hash) - 48.858_37).abs() < 1e-7);
+        assert!((morton_decode_longitude(hash) - 2.294_481).abs() < 1e-7);
+        assert!(morton_encode(-10.0, 0.0).unwrap() < morton_encode(10.0, 0.0).unwrap());
+        assert!(morton_encode(91.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_geohash() {
+        // well known geohashes
+        assert_eq!(
+            geohash_encode(57.64911, 10.40744, 11).unwrap(),
+            "u4pruydqqvj"
+        );
+        assert_eq!(geohash_encode(48.858_37, 2.294_481, 5).unwrap(), "u09tu");
+        assert_eq!(geohash_encode(-90.0, -180.0, 3).unwrap(), "000");
+        assert_eq!(geohash_encode(90.0, 180.0, 3).unwrap(), "zzz");
+
+        let cell = geohash_bbox("u4pruydqqvj").unwrap();
+        assert!(cell.min_lat <= 57.64911 && cell.max_lat >= 57.64911);
+        assert!(cell.min_lon <= 10.40744 && cell.max_lon >= 10.40744);
+        assert!(cell.max_lon - cell.min_lon < 2e-6);
+        let cell = geohash_bbox("s").unwrap();
+        assert_eq!(
+            (cell.min_lat, cell.max_lat, cell.min_lon, cell.max_lon),
+            (0.0, 45.0, 0.0, 45.0)
+        );
+        let cell = geohash_bbox("s0").unwrap();
+        assert_eq!(
+            (cell.min_lat, cell.max_lat, cell.min_lon, cell.max_lon),
+            (0.0, 5.625, 0.0, 11.25)
+        );
+
+        assert!(geohash_encode(0.0, 0.0, 0).is_err());
+        assert!(geohash_encode(0.0, 0.0, 13).is_err());
+        assert!(geohash_bbox("u4a").is_err());
+        assert!(geohash_bbox("").is_err());
+    }
+
+    #[test]
+    fn test_geohash_cover() {
+        assert_eq!(geohash_precision_for(360.0), 1);
+        assert_eq!(geohash_precision_for(11.25), 2);
+        assert_eq!(geohash_precision_for(0.0), MAX_GEOHASH_PRECISION);
+
+        // within the cell "s"
+        let rect = Rectangle::new(1.0, 44.0, 1.0, 44.0).unwrap();
+        assert_eq!(geohash_cover(&rect, 1).unwrap(), vec!["s"]);
+        // the cells of "s" within the box, and the ones crossing it
+        let rect = Rectangle::new(1.0, 40.0, 1.0, 44.0).unwrap();
+        let cells = geohash_cover(&rect, 2).unwrap();
+        assert_eq!(cells.len(), 32);
+        assert!(cells.iter().all(|c| c.starts_with('s')));
+        let cells = geohash_cover(&rect, 3).unwrap();
+        for cell in &cells {
+            let bbox = geohash_bbox(cell).unwrap();
+            let relation = rect.relate(bbox.min_lat, bbox.max_lat, bbox.min_lon, bbox.max_lon);
+            // only the finest cells may cross the box
+            assert!(relation == Relation::CellInsideQuery || cell.len() == 3);
+            assert!(relation != Relation::CellOutsideQuery);
+        }
+        assert!(cells.iter().any(|c| c.len() == 2));
+        let geohash = geohash_encode(20.0, 20.0, 3).unwrap();
+        assert!(cells.iter().any(|c| geohash.starts_with(c.as_str())));
+        // a box crossing the dateline
+        let rect = Rectangle::new(1.0, 2.0, 179.0, -179.0).unwrap();
+        assert_eq!(geohash_cover(&rect, 1).unwrap(), vec!["8", "x"]);
+    }
+}
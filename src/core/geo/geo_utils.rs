@@ -0,0 +1,244 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::points::Relation;
+
+use error::{ErrorKind, Result};
+
+pub const MIN_LAT_INCL: f64 = -90.0;
+pub const MAX_LAT_INCL: f64 = 90.0;
+pub const MIN_LON_INCL: f64 = -180.0;
+pub const MAX_LON_INCL: f64 = 180.0;
+
+/// The mean radius of the earth, in meters, of the WGS84 ellipsoid.
+pub const EARTH_MEAN_RADIUS_METERS: f64 = 6_371_008.771_4;
+
+/// Errors if `latitude` is not a valid latitude in degrees.
+pub fn check_latitude(latitude: f64) -> Result<()> {
+    if latitude.is_nan() || latitude < MIN_LAT_INCL || latitude > MAX_LAT_INCL {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "invalid latitude {}; must be between {} and {}",
+            latitude, MIN_LAT_INCL, MAX_LAT_INCL
+        )));
+    }
+    Ok(())
+}
+
+/// Errors if `longitude` is not a valid longitude in degrees.
+pub fn check_longitude(longitude: f64) -> Result<()> {
+    if longitude.is_nan() || longitude < MIN_LON_INCL || longitude > MAX_LON_INCL {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "invalid longitude {}; must be between {} and {}",
+            longitude, MIN_LON_INCL, MAX_LON_INCL
+        )));
+    }
+    Ok(())
+}
+
+/// Errors if `radius_meters` is not a valid distance.
+pub fn check_radius(radius_meters: f64) -> Result<()> {
+    if !radius_meters.is_finite() || radius_meters < 0.0 {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "radius_meters must be finite and >= 0, got: {}",
+            radius_meters
+        )));
+    }
+    Ok(())
+}
+
+/// The great circle distance in meters between two points, in degrees, by
+/// the haversine formula.
+pub fn haversin_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_MEAN_RADIUS_METERS * h.sqrt().min(1.0).asin()
+}
+
+/// Relates the cell `min_lat..=max_lat`, `min_lon..=max_lon`, which must not
+/// cross the dateline, to the circle of `radius_meters` around `lat`, `lon`.
+///
+/// The cell is only reported inside the circle if its 4 corners are, and if
+/// it spans less than 90 degrees of longitude on each side of the center,
+/// otherwise its edges may bulge out of the circle. The cells which are not
+/// are reported crossing, their points being checked one by one.
+pub fn relate(
+    min_lat: f64,
+    max_lat: f64,
+    min_lon: f64,
+    max_lon: f64,
+    lat: f64,
+    lon: f64,
+    radius_meters: f64,
+) -> Relation {
+    debug_assert!(min_lon <= max_lon);
+    let within =
+        |corner_lat, corner_lon| haversin_meters(lat, lon, corner_lat, corner_lon) <= radius_meters;
+    if within_90_lon_degrees(lon, min_lon, max_lon)
+        && within(min_lat, min_lon)
+        && within(min_lat, max_lon)
+        && within(max_lat, min_lon)
+        && within(max_lat, max_lon)
+    {
+        Relation::CellInsideQuery
+    } else {
+        Relation::CellCrossesQuery
+    }
+}
+
+/// Whether `min_lon..=max_lon` is within 90 degrees of `lon` on both sides,
+/// wrapping around the dateline.
+fn within_90_lon_degrees(lon: f64, min_lon: f64, max_lon: f64) -> bool {
+    let lon = if max_lon <= lon - 180.0 {
+        lon - 360.0
+    } else if min_lon >= lon + 180.0 {
+        lon + 360.0
+    } else {
+        lon
+    };
+    max_lon - lon < 90.0 && lon - min_lon < 90.0
+}
+
+/// Whether `c` is on the left (1), on the right (-1) or on the line (0) going
+/// from `a` to `b`.
+pub(crate) fn orient(ax: f64, ay: f64, bx: f64, by: f64, cx: f64, cy: f64) -> i32 {
+    let v1 = (bx - ax) * (cy - ay);
+    let v2 = (cx - ax) * (by - ay);
+    if v1 > v2 {
+        1
+    } else if v1 < v2 {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Whether the segments `a`-`b` and `c`-`d`, whose ends are given as
+/// `(latitude, longitude)`, share at least a point.
+pub(crate) fn segments_intersect(
+    a: (f64, f64),
+    b: (f64, f64),
+    c: (f64, f64),
+    d: (f64, f64),
+) -> bool {
+    if a.0.max(b.0) < c.0.min(d.0)
+        || c.0.max(d.0) < a.0.min(b.0)
+        || a.1.max(b.1) < c.1.min(d.1)
+        || c.1.max(d.1) < a.1.min(b.1)
+    {
+        return false;
+    }
+    orient(a.1, a.0, b.1, b.0, c.1, c.0) * orient(a.1, a.0, b.1, b.0, d.1, d.0) <= 0
+        && orient(c.1, c.0, d.1, d.0, a.1, a.0) * orient(c.1, c.0, d.1, d.0, b.1, b.0) <= 0
+}
+
+/// Whether `p` is within the triangle `a`, `b`, `c` or on its edges, the
+/// triangle may be degenerated to a segment or a point.
+pub(crate) fn point_in_triangle(
+    p: (f64, f64),
+    a: (f64, f64),
+    b: (f64, f64),
+    c: (f64, f64),
+) -> bool {
+    if orient(a.1, a.0, b.1, b.0, c.1, c.0) == 0 {
+        return segments_intersect(p, p, a, b)
+            || segments_intersect(p, p, b, c)
+            || segments_intersect(p, p, a, c);
+    }
+    let o1 = orient(a.1, a.0, b.1, b.0, p.1, p.0);
+    let o2 = orient(b.1, b.0, c.1, c.0, p.1, p.0);
+    let o3 = orient(c.1, c.0, a.1, a.0, p.1, p.0);
+    (o1 >= 0 && o2 >= 0 && o3 >= 0) || (o1 <= 0 && o2 <= 0 && o3 <= 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversin_meters() {
+        assert!(haversin_meters(40.7143528, -74.0059731, 40.7143528, -74.0059731).abs() < 1e-9);
+        // New York to London, about 5570 km
+        let d = haversin_meters(40.7143528, -74.0059731, 51.5072, -0.1275);
+        assert!((d - 5_570_000.0).abs() < 10_000.0, "{}", d);
+        // a degree of latitude is about 111.2 km
+        let d = haversin_meters(0.0, 0.0, 1.0, 0.0);
+        assert!((d - 111_195.0).abs() < 1.0, "{}", d);
+        // across the dateline
+        let d = haversin_meters(0.0, 179.5, 0.0, -179.5);
+        assert!((d - 111_195.0).abs() < 1.0, "{}", d);
+    }
+
+    #[test]
+    fn test_check_lat_lon() {
+        assert!(check_latitude(90.0).is_ok());
+        assert!(check_latitude(90.1).is_err());
+        assert!(check_latitude(::std::f64::NAN).is_err());
+        assert!(check_longitude(-180.0).is_ok());
+        assert!(check_longitude(-180.1).is_err());
+    }
+
+    #[test]
+    fn test_relate() {
+        // 0.01 degrees is about 1.1 km
+        assert!(relate(-0.01, 0.01, -0.01, 0.01, 0.0, 0.0, 2_000.0) == Relation::CellInsideQuery);
+        assert!(relate(-0.01, 0.01, -0.01, 0.01, 0.0, 0.0, 1_200.0) == Relation::CellCrossesQuery);
+        // all the corners are within the radius, but not the whole cell
+        assert!(
+            relate(-80.0, 80.0, -170.0, 170.0, 0.0, 0.0, 19_000_000.0)
+                == Relation::CellCrossesQuery
+        );
+    }
+
+    #[test]
+    fn test_triangle_predicates() {
+        assert!(segments_intersect(
+            (0.0, 0.0),
+            (2.0, 2.0),
+            (0.0, 2.0),
+            (2.0, 0.0)
+        ));
+        // touching at an end
+        assert!(segments_intersect(
+            (0.0, 0.0),
+            (1.0, 1.0),
+            (1.0, 1.0),
+            (2.0, 0.0)
+        ));
+        // collinear but apart
+        assert!(!segments_intersect(
+            (0.0, 0.0),
+            (1.0, 1.0),
+            (2.0, 2.0),
+            (3.0, 3.0)
+        ));
+        assert!(!segments_intersect(
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+            (1.0, 1.0)
+        ));
+
+        let (a, b, c) = ((0.0, 0.0), (0.0, 4.0), (4.0, 0.0));
+        assert!(point_in_triangle((1.0, 1.0), a, b, c));
+        assert!(point_in_triangle((0.0, 2.0), c, b, a));
+        assert!(!point_in_triangle((3.0, 3.0), a, b, c));
+        // a segment
+        assert!(point_in_triangle((0.0, 2.0), a, b, a));
+        assert!(!point_in_triangle((0.0, 5.0), a, b, a));
+        // a point
+        assert!(point_in_triangle(a, a, a, a));
+        assert!(!point_in_triangle(b, a, a, a));
+    }
+}
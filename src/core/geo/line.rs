@@ -0,0 +1,69 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::geo::{check_latitude, check_longitude};
+
+use error::{ErrorKind, Result};
+
+/// A line string in latitude and longitude degrees, e.g. a route. Like the
+/// polygons, the lines do not cross the dateline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    lats: Vec<f64>,
+    lons: Vec<f64>,
+}
+
+impl Line {
+    pub fn new(lats: Vec<f64>, lons: Vec<f64>) -> Result<Line> {
+        if lats.len() != lons.len() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "lats and lons must be equal length, got {} and {}",
+                lats.len(),
+                lons.len()
+            )));
+        }
+        if lats.len() < 2 {
+            bail!(ErrorKind::IllegalArgument(
+                "at least 2 line points required".into()
+            ));
+        }
+        for (&lat, &lon) in lats.iter().zip(&lons) {
+            check_latitude(lat)?;
+            check_longitude(lon)?;
+        }
+        Ok(Line { lats, lons })
+    }
+
+    pub fn lats(&self) -> &[f64] {
+        &self.lats
+    }
+
+    pub fn lons(&self) -> &[f64] {
+        &self.lons
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line(")?;
+        for (i, (lat, lon)) in self.lats.iter().zip(&self.lons).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "[{}, {}]", lat, lon)?;
+        }
+        write!(f, ")")
+    }
+}
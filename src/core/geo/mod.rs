@@ -0,0 +1,44 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod geo_encoding_utils;
+
+pub use self::geo_encoding_utils::*;
+
+mod geo_hash_utils;
+
+pub use self::geo_hash_utils::*;
+
+mod geo_utils;
+
+pub use self::geo_utils::*;
+
+mod rectangle;
+
+pub use self::rectangle::*;
+
+mod line;
+
+pub use self::line::*;
+
+mod polygon;
+
+pub use self::polygon::*;
+
+mod polygon_2d;
+
+pub use self::polygon_2d::*;
+
+mod tessellator;
+
+pub use self::tessellator::*;
@@ -0,0 +1,155 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::geo::{check_latitude, check_longitude};
+
+use error::{ErrorKind, Result};
+
+/// A closed polygon in latitude and longitude degrees, possibly with holes.
+///
+/// The first and last points must be the same, and the holes cannot have
+/// holes themselves. The polygons do not cross the dateline, split them in
+/// two instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    polygon_lats: Vec<f64>,
+    polygon_lons: Vec<f64>,
+    holes: Vec<Polygon>,
+    min_lat: f64,
+    max_lat: f64,
+    min_lon: f64,
+    max_lon: f64,
+}
+
+impl Polygon {
+    pub fn new(polygon_lats: Vec<f64>, polygon_lons: Vec<f64>) -> Result<Polygon> {
+        Polygon::with_holes(polygon_lats, polygon_lons, vec![])
+    }
+
+    pub fn with_holes(
+        polygon_lats: Vec<f64>,
+        polygon_lons: Vec<f64>,
+        holes: Vec<Polygon>,
+    ) -> Result<Polygon> {
+        if polygon_lats.len() != polygon_lons.len() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "polygon_lats and polygon_lons must be equal length, got {} and {}",
+                polygon_lats.len(),
+                polygon_lons.len()
+            )));
+        }
+        if polygon_lats.len() < 4 {
+            bail!(ErrorKind::IllegalArgument(
+                "at least 4 polygon points required".into()
+            ));
+        }
+        let last = polygon_lats.len() - 1;
+        if polygon_lats[0] != polygon_lats[last] || polygon_lons[0] != polygon_lons[last] {
+            bail!(ErrorKind::IllegalArgument(
+                "first and last points of the polygon must be the same (it must close itself)"
+                    .into()
+            ));
+        }
+        if holes.iter().any(|h| !h.holes.is_empty()) {
+            bail!(ErrorKind::IllegalArgument(
+                "holes may not contain holes: polygons may not nest".into()
+            ));
+        }
+
+        let mut min_lat = ::std::f64::INFINITY;
+        let mut max_lat = ::std::f64::NEG_INFINITY;
+        let mut min_lon = ::std::f64::INFINITY;
+        let mut max_lon = ::std::f64::NEG_INFINITY;
+        for (&lat, &lon) in polygon_lats.iter().zip(&polygon_lons) {
+            check_latitude(lat)?;
+            check_longitude(lon)?;
+            min_lat = min_lat.min(lat);
+            max_lat = max_lat.max(lat);
+            min_lon = min_lon.min(lon);
+            max_lon = max_lon.max(lon);
+        }
+        Ok(Polygon {
+            polygon_lats,
+            polygon_lons,
+            holes,
+            min_lat,
+            max_lat,
+            min_lon,
+            max_lon,
+        })
+    }
+
+    pub fn polygon_lats(&self) -> &[f64] {
+        &self.polygon_lats
+    }
+
+    pub fn polygon_lons(&self) -> &[f64] {
+        &self.polygon_lons
+    }
+
+    pub fn holes(&self) -> &[Polygon] {
+        &self.holes
+    }
+
+    pub fn min_lat(&self) -> f64 {
+        self.min_lat
+    }
+
+    pub fn max_lat(&self) -> f64 {
+        self.max_lat
+    }
+
+    pub fn min_lon(&self) -> f64 {
+        self.min_lon
+    }
+
+    pub fn max_lon(&self) -> f64 {
+        self.max_lon
+    }
+}
+
+impl fmt::Display for Polygon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Polygon(")?;
+        for (i, (lat, lon)) in self.polygon_lats.iter().zip(&self.polygon_lons).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "[{}, {}]", lat, lon)?;
+        }
+        for hole in &self.holes {
+            write!(f, ", hole: {}", hole)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon_validation() {
+        assert!(Polygon::new(vec![0.0, 1.0, 0.0], vec![0.0, 1.0, 0.0]).is_err());
+        assert!(Polygon::new(vec![0.0, 1.0, 1.0, 0.5], vec![0.0, 0.0, 1.0, 0.0]).is_err());
+        assert!(Polygon::new(vec![0.0, 1.0, 1.0, 0.0], vec![0.0, 0.0, 1.0]).is_err());
+        assert!(Polygon::new(vec![0.0, 91.0, 1.0, 0.0], vec![0.0, 0.0, 1.0, 0.0]).is_err());
+        let lats = vec![0.0, 0.0, 1.0, 1.0, 0.0];
+        let lons = vec![0.0, 1.0, 1.0, 0.0, 0.0];
+        let inner = Polygon::new(lats.clone(), lons.clone()).unwrap();
+        let hole = Polygon::with_holes(lats.clone(), lons.clone(), vec![inner]).unwrap();
+        assert!(Polygon::with_holes(lats, lons, vec![hole]).is_err());
+    }
+}
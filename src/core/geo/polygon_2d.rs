@@ -0,0 +1,415 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::points::Relation;
+use core::geo::{orient, point_in_triangle, segments_intersect, Polygon, Triangle};
+
+/// Polygons prepared for fast relations with points and boxes.
+///
+/// The edges of each polygon are kept in an interval tree on their
+/// latitudes, so that only the edges spanning a latitude are visited for the
+/// point in polygon tests, and the tests of the cells of a BKD tree stop at
+/// the first edge crossing them.
+pub struct Polygon2D {
+    components: Vec<Component>,
+}
+
+impl Polygon2D {
+    pub fn new(polygons: &[Polygon]) -> Polygon2D {
+        Polygon2D {
+            components: polygons.iter().map(Component::new).collect(),
+        }
+    }
+
+    /// Whether the point is within one of the polygons, and not in one of its
+    /// holes.
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        self.components
+            .iter()
+            .any(|c| c.contains(latitude, longitude))
+    }
+
+    /// Relates a box to the polygons, the box is inside the polygons if it
+    /// is fully within one of them.
+    pub fn relate(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation {
+        for component in &self.components {
+            let relation = component.relate(min_lat, max_lat, min_lon, max_lon);
+            if relation != Relation::CellOutsideQuery {
+                return relation;
+            }
+        }
+        Relation::CellOutsideQuery
+    }
+
+    /// Relates a triangle, which may be degenerated to a segment or a point,
+    /// to the polygons, the triangle is inside the polygons if it is fully
+    /// within one of them.
+    pub fn relate_triangle(&self, triangle: &Triangle) -> Relation {
+        for component in &self.components {
+            let relation = component.relate_triangle(triangle);
+            if relation != Relation::CellOutsideQuery {
+                return relation;
+            }
+        }
+        Relation::CellOutsideQuery
+    }
+}
+
+struct Component {
+    min_lat: f64,
+    max_lat: f64,
+    min_lon: f64,
+    max_lon: f64,
+    tree: Box<Edge>,
+    holes: Polygon2D,
+}
+
+impl Component {
+    fn new(polygon: &Polygon) -> Component {
+        Component {
+            min_lat: polygon.min_lat(),
+            max_lat: polygon.max_lat(),
+            min_lon: polygon.min_lon(),
+            max_lon: polygon.max_lon(),
+            tree: Edge::create_tree(polygon.polygon_lats(), polygon.polygon_lons()),
+            holes: Polygon2D::new(polygon.holes()),
+        }
+    }
+
+    fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        if latitude < self.min_lat
+            || latitude > self.max_lat
+            || longitude < self.min_lon
+            || longitude > self.max_lon
+        {
+            return false;
+        }
+        self.tree.contains(latitude, longitude) && !self.holes.contains(latitude, longitude)
+    }
+
+    fn relate(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation {
+        if max_lon < self.min_lon
+            || min_lon > self.max_lon
+            || max_lat < self.min_lat
+            || min_lat > self.max_lat
+        {
+            return Relation::CellOutsideQuery;
+        }
+        // the box encloses the whole polygon
+        if min_lat <= self.min_lat
+            && max_lat >= self.max_lat
+            && min_lon <= self.min_lon
+            && max_lon >= self.max_lon
+        {
+            return Relation::CellCrossesQuery;
+        }
+        match self.holes.relate(min_lat, max_lat, min_lon, max_lon) {
+            Relation::CellCrossesQuery => return Relation::CellCrossesQuery,
+            Relation::CellInsideQuery => return Relation::CellOutsideQuery,
+            Relation::CellOutsideQuery => {}
+        }
+
+        // the corners are cheaper to check than the edges
+        let corners = [
+            (min_lat, min_lon),
+            (min_lat, max_lon),
+            (max_lat, min_lon),
+            (max_lat, max_lon),
+        ];
+        let inside = corners
+            .iter()
+            .filter(|&&(lat, lon)| self.contains(lat, lon))
+            .count();
+        if inside > 0 && inside < 4 {
+            Relation::CellCrossesQuery
+        } else if self.tree.crosses(min_lat, max_lat, min_lon, max_lon) {
+            Relation::CellCrossesQuery
+        } else if inside == 4 {
+            Relation::CellInsideQuery
+        } else {
+            Relation::CellOutsideQuery
+        }
+    }
+
+    fn relate_triangle(&self, triangle: &Triangle) -> Relation {
+        let (min_lat, max_lat, min_lon, max_lon) = triangle.bbox();
+        if max_lon < self.min_lon
+            || min_lon > self.max_lon
+            || max_lat < self.min_lat
+            || min_lat > self.max_lat
+        {
+            return Relation::CellOutsideQuery;
+        }
+        match self.holes.relate_triangle(triangle) {
+            Relation::CellCrossesQuery => return Relation::CellCrossesQuery,
+            Relation::CellInsideQuery => return Relation::CellOutsideQuery,
+            Relation::CellOutsideQuery => {}
+        }
+
+        let vertices = triangle.vertices();
+        let inside = vertices
+            .iter()
+            .filter(|&&(lat, lon)| self.contains(lat, lon))
+            .count();
+        if inside > 0 && inside < 3 {
+            Relation::CellCrossesQuery
+        } else if self.tree.crosses_triangle(min_lat, max_lat, &vertices) {
+            Relation::CellCrossesQuery
+        } else if inside == 3 {
+            Relation::CellInsideQuery
+        } else if point_in_triangle(
+            (self.tree.lat1, self.tree.lon1),
+            vertices[0],
+            vertices[1],
+            vertices[2],
+        ) {
+            // the triangle encloses the whole polygon
+            Relation::CellCrossesQuery
+        } else {
+            Relation::CellOutsideQuery
+        }
+    }
+}
+
+/// A node of the interval tree of the edges, sorted by their lowest
+/// latitude, `max` is the highest latitude of the subtree.
+struct Edge {
+    lat1: f64,
+    lon1: f64,
+    lat2: f64,
+    lon2: f64,
+    low: f64,
+    max: f64,
+    left: Option<Box<Edge>>,
+    right: Option<Box<Edge>>,
+}
+
+impl Edge {
+    fn new(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Edge {
+        Edge {
+            lat1,
+            lon1,
+            lat2,
+            lon2,
+            low: lat1.min(lat2),
+            max: lat1.max(lat2),
+            left: None,
+            right: None,
+        }
+    }
+
+    fn create_tree(lats: &[f64], lons: &[f64]) -> Box<Edge> {
+        let mut edges: Vec<Edge> = (1..lats.len())
+            .map(|i| Edge::new(lats[i - 1], lons[i - 1], lats[i], lons[i]))
+            .collect();
+        edges.sort_by(|a, b| {
+            a.low
+                .partial_cmp(&b.low)
+                .unwrap()
+                .then(a.max.partial_cmp(&b.max).unwrap())
+        });
+        let mut edges: Vec<Option<Edge>> = edges.into_iter().map(Some).collect();
+        let len = edges.len();
+        Self::build(&mut edges, 0, len).unwrap()
+    }
+
+    fn build(edges: &mut [Option<Edge>], low: usize, high: usize) -> Option<Box<Edge>> {
+        if low >= high {
+            return None;
+        }
+        let mid = (low + high) / 2;
+        let mut node = Box::new(edges[mid].take().unwrap());
+        node.left = Self::build(edges, low, mid);
+        node.right = Self::build(edges, mid + 1, high);
+        if let Some(ref left) = node.left {
+            node.max = node.max.max(left.max);
+        }
+        if let Some(ref right) = node.right {
+            node.max = node.max.max(right.max);
+        }
+        Some(node)
+    }
+
+    /// The crossing number test: the point is inside when a ray going west
+    /// crosses an odd number of edges, so the results of the nodes along
+    /// the path are xor'ed.
+    fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        let mut res = false;
+        if latitude <= self.max {
+            if (self.lat1 > latitude) != (self.lat2 > latitude)
+                && longitude
+                    < (self.lon1 - self.lon2) * (latitude - self.lat2) / (self.lat1 - self.lat2)
+                        + self.lon2
+            {
+                res = true;
+            }
+            if let Some(ref left) = self.left {
+                res ^= left.contains(latitude, longitude);
+            }
+            if let Some(ref right) = self.right {
+                if latitude >= self.low {
+                    res ^= right.contains(latitude, longitude);
+                }
+            }
+        }
+        res
+    }
+
+    /// Whether one of the edges intersects one of the sides of the box.
+    fn crosses(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> bool {
+        if min_lat > self.max {
+            return false;
+        }
+        let (cy, dy, cx, dx) = (self.lat1, self.lat2, self.lon1, self.lon2);
+        let outside = (cy < min_lat && dy < min_lat)
+            || (cy > max_lat && dy > max_lat)
+            || (cx < min_lon && dx < min_lon)
+            || (cx > max_lon && dx > max_lon);
+        if !outside {
+            let sides = [
+                (min_lon, max_lat, max_lon, max_lat),
+                (max_lon, max_lat, max_lon, min_lat),
+                (max_lon, min_lat, min_lon, min_lat),
+                (min_lon, min_lat, min_lon, max_lat),
+            ];
+            for &(ax, ay, bx, by) in &sides {
+                if orient(cx, cy, dx, dy, ax, ay) * orient(cx, cy, dx, dy, bx, by) <= 0
+                    && orient(ax, ay, bx, by, cx, cy) * orient(ax, ay, bx, by, dx, dy) <= 0
+                {
+                    return true;
+                }
+            }
+        }
+        if let Some(ref left) = self.left {
+            if left.crosses(min_lat, max_lat, min_lon, max_lon) {
+                return true;
+            }
+        }
+        if let Some(ref right) = self.right {
+            if max_lat >= self.low && right.crosses(min_lat, max_lat, min_lon, max_lon) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether one of the edges intersects one of the sides of the triangle.
+    fn crosses_triangle(&self, min_lat: f64, max_lat: f64, vertices: &[(f64, f64); 3]) -> bool {
+        if min_lat > self.max {
+            return false;
+        }
+        let (c, d) = ((self.lat1, self.lon1), (self.lat2, self.lon2));
+        if segments_intersect(vertices[0], vertices[1], c, d)
+            || segments_intersect(vertices[1], vertices[2], c, d)
+            || segments_intersect(vertices[2], vertices[0], c, d)
+        {
+            return true;
+        }
+        if let Some(ref left) = self.left {
+            if left.crosses_triangle(min_lat, max_lat, vertices) {
+                return true;
+            }
+        }
+        if let Some(ref right) = self.right {
+            if max_lat >= self.low && right.crosses_triangle(min_lat, max_lat, vertices) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(min: f64, max: f64) -> (Vec<f64>, Vec<f64>) {
+        (vec![min, min, max, max, min], vec![min, max, max, min, min])
+    }
+
+    #[test]
+    fn test_contains() {
+        let (lats, lons) = square(-10.0, 10.0);
+        let (hole_lats, hole_lons) = square(-2.0, 2.0);
+        let hole = Polygon::new(hole_lats, hole_lons).unwrap();
+        let polygon = Polygon2D::new(&[Polygon::with_holes(lats, lons, vec![hole]).unwrap()]);
+        assert!(polygon.contains(5.0, 5.0));
+        assert!(polygon.contains(-9.0, 3.0));
+        assert!(!polygon.contains(0.0, 0.0));
+        assert!(!polygon.contains(11.0, 0.0));
+        assert!(!polygon.contains(0.0, -10.5));
+
+        // a triangle
+        let triangle = Polygon::new(vec![0.0, 0.0, 10.0, 0.0], vec![0.0, 10.0, 0.0, 0.0]).unwrap();
+        let polygon = Polygon2D::new(&[triangle]);
+        assert!(polygon.contains(2.0, 2.0));
+        assert!(!polygon.contains(6.0, 6.0));
+    }
+
+    #[test]
+    fn test_relate() {
+        let (lats, lons) = square(-10.0, 10.0);
+        let (hole_lats, hole_lons) = square(-2.0, 2.0);
+        let hole = Polygon::new(hole_lats, hole_lons).unwrap();
+        let polygon = Polygon2D::new(&[Polygon::with_holes(lats, lons, vec![hole]).unwrap()]);
+        assert!(polygon.relate(4.0, 6.0, 4.0, 6.0) == Relation::CellInsideQuery);
+        assert!(polygon.relate(8.0, 12.0, 4.0, 6.0) == Relation::CellCrossesQuery);
+        assert!(polygon.relate(11.0, 12.0, 4.0, 6.0) == Relation::CellOutsideQuery);
+        // within the hole
+        assert!(polygon.relate(-1.0, 1.0, -1.0, 1.0) == Relation::CellOutsideQuery);
+        assert!(polygon.relate(-3.0, 1.0, -1.0, 1.0) == Relation::CellCrossesQuery);
+        // enclosing the whole polygon
+        assert!(polygon.relate(-20.0, 20.0, -20.0, 20.0) == Relation::CellCrossesQuery);
+        // the hole is within the box, but no corner is in the hole
+        assert!(polygon.relate(-3.0, 3.0, -3.0, 3.0) == Relation::CellCrossesQuery);
+    }
+
+    #[test]
+    fn test_multi_polygons() {
+        let (lats1, lons1) = square(0.0, 1.0);
+        let (lats2, lons2) = square(5.0, 6.0);
+        let polygon = Polygon2D::new(&[
+            Polygon::new(lats1, lons1).unwrap(),
+            Polygon::new(lats2, lons2).unwrap(),
+        ]);
+        assert!(polygon.contains(0.5, 0.5));
+        assert!(polygon.contains(5.5, 5.5));
+        assert!(!polygon.contains(3.0, 3.0));
+        assert!(polygon.relate(5.2, 5.8, 5.2, 5.8) == Relation::CellInsideQuery);
+        assert!(polygon.relate(2.0, 3.0, 2.0, 3.0) == Relation::CellOutsideQuery);
+    }
+
+    #[test]
+    fn test_relate_triangle() {
+        let (lats, lons) = square(-10.0, 10.0);
+        let (hole_lats, hole_lons) = square(-2.0, 2.0);
+        let hole = Polygon::new(hole_lats, hole_lons).unwrap();
+        let polygon = Polygon2D::new(&[Polygon::with_holes(lats, lons, vec![hole]).unwrap()]);
+        let relate = |a_lat, a_lon, b_lat, b_lon, c_lat, c_lon| {
+            polygon.relate_triangle(&Triangle::new(a_lat, a_lon, b_lat, b_lon, c_lat, c_lon))
+        };
+        assert!(relate(4.0, 4.0, 6.0, 4.0, 4.0, 6.0) == Relation::CellInsideQuery);
+        assert!(relate(4.0, 4.0, 12.0, 4.0, 4.0, 6.0) == Relation::CellCrossesQuery);
+        assert!(relate(14.0, 4.0, 16.0, 4.0, 14.0, 6.0) == Relation::CellOutsideQuery);
+        // within the hole, and around the hole
+        assert!(relate(-1.0, -1.0, 1.0, -1.0, -1.0, 1.0) == Relation::CellOutsideQuery);
+        assert!(relate(-5.0, -5.0, 9.0, -5.0, -5.0, 9.0) == Relation::CellCrossesQuery);
+        // around the whole polygon
+        assert!(relate(-50.0, -50.0, 50.0, -50.0, -50.0, 50.0) == Relation::CellCrossesQuery);
+        // a segment going through the polygon, its ends outside of it
+        assert!(relate(-20.0, 5.0, 20.0, 5.0, -20.0, 5.0) == Relation::CellCrossesQuery);
+        // points
+        assert!(relate(5.0, 5.0, 5.0, 5.0, 5.0, 5.0) == Relation::CellInsideQuery);
+        assert!(relate(0.0, 0.0, 0.0, 0.0, 0.0, 0.0) == Relation::CellOutsideQuery);
+    }
+}
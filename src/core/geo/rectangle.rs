@@ -0,0 +1,267 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use core::codec::points::Relation;
+use core::geo::{
+    check_latitude, check_longitude, point_in_triangle, segments_intersect, Triangle,
+    EARTH_MEAN_RADIUS_METERS, MAX_LON_INCL, MIN_LON_INCL,
+};
+
+use error::{ErrorKind, Result};
+
+/// A rectangle in latitude and longitude degrees. It crosses the dateline
+/// when its min longitude is greater than its max longitude.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rectangle {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl Rectangle {
+    pub fn new(min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Result<Rectangle> {
+        check_latitude(min_lat)?;
+        check_latitude(max_lat)?;
+        check_longitude(min_lon)?;
+        check_longitude(max_lon)?;
+        if min_lat > max_lat {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "min_lat {} cannot be greater than max_lat {}",
+                min_lat, max_lat
+            )));
+        }
+        Ok(Rectangle {
+            min_lat,
+            max_lat,
+            min_lon,
+            max_lon,
+        })
+    }
+
+    /// Whether the rectangle crosses the dateline.
+    pub fn crosses_dateline(&self) -> bool {
+        self.max_lon < self.min_lon
+    }
+
+    /// Relates a box, which must not cross the dateline, to the rectangle.
+    pub fn relate(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation {
+        self.relate_parts(|part| part.relate_box(min_lat, max_lat, min_lon, max_lon))
+    }
+
+    /// Relates a triangle, which may be degenerated to a segment or a point,
+    /// to the rectangle.
+    pub fn relate_triangle(&self, triangle: &Triangle) -> Relation {
+        self.relate_parts(|part| part.relate_triangle_part(triangle))
+    }
+
+    /// Relates a shape to the rectangle, or to its two halves on each side of
+    /// the dateline when it crosses it. A shape can't be in both halves
+    /// without crossing them.
+    fn relate_parts(&self, relate: impl Fn(&Rectangle) -> Relation) -> Relation {
+        if !self.crosses_dateline() {
+            return relate(self);
+        }
+        let east = Rectangle {
+            max_lon: MAX_LON_INCL,
+            ..*self
+        };
+        let west = Rectangle {
+            min_lon: MIN_LON_INCL,
+            ..*self
+        };
+        match relate(&east) {
+            Relation::CellOutsideQuery => relate(&west),
+            relation => relation,
+        }
+    }
+
+    fn disjoint(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> bool {
+        max_lat < self.min_lat
+            || min_lat > self.max_lat
+            || max_lon < self.min_lon
+            || min_lon > self.max_lon
+    }
+
+    fn contains(&self, lat: f64, lon: f64) -> bool {
+        lat >= self.min_lat && lat <= self.max_lat && lon >= self.min_lon && lon <= self.max_lon
+    }
+
+    fn relate_box(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation {
+        if self.disjoint(min_lat, max_lat, min_lon, max_lon) {
+            Relation::CellOutsideQuery
+        } else if min_lat >= self.min_lat
+            && max_lat <= self.max_lat
+            && min_lon >= self.min_lon
+            && max_lon <= self.max_lon
+        {
+            Relation::CellInsideQuery
+        } else {
+            Relation::CellCrossesQuery
+        }
+    }
+
+    fn relate_triangle_part(&self, triangle: &Triangle) -> Relation {
+        let (min_lat, max_lat, min_lon, max_lon) = triangle.bbox();
+        match self.relate_box(min_lat, max_lat, min_lon, max_lon) {
+            Relation::CellCrossesQuery => {}
+            relation => return relation,
+        }
+        let vertices = triangle.vertices();
+        if vertices.iter().any(|&(lat, lon)| self.contains(lat, lon)) {
+            return Relation::CellCrossesQuery;
+        }
+        let corners = [
+            (self.min_lat, self.min_lon),
+            (self.min_lat, self.max_lon),
+            (self.max_lat, self.max_lon),
+            (self.max_lat, self.min_lon),
+        ];
+        // the triangle encloses the rectangle
+        if point_in_triangle(corners[0], vertices[0], vertices[1], vertices[2]) {
+            return Relation::CellCrossesQuery;
+        }
+        for i in 0..3 {
+            let (a, b) = (vertices[i], vertices[(i + 1) % 3]);
+            for j in 0..4 {
+                if segments_intersect(a, b, corners[j], corners[(j + 1) % 4]) {
+                    return Relation::CellCrossesQuery;
+                }
+            }
+        }
+        Relation::CellOutsideQuery
+    }
+
+    /// Returns the bounding box of the circle of `radius_meters` around a
+    /// point. The box spans all the longitudes if the circle contains a
+    /// pole, and crosses the dateline if the circle does.
+    pub fn from_point_distance(lat: f64, lon: f64, radius_meters: f64) -> Result<Rectangle> {
+        check_latitude(lat)?;
+        check_longitude(lon)?;
+        let rad_lat = lat.to_radians();
+        let rad_lon = lon.to_radians();
+        // a little bigger, for the rounding errors of the distances
+        let rad_distance = (radius_meters + 7e-2) / EARTH_MEAN_RADIUS_METERS;
+        let mut min_lat = rad_lat - rad_distance;
+        let mut max_lat = rad_lat + rad_distance;
+        let (min_lon, max_lon);
+        if min_lat > -PI / 2.0 && max_lat < PI / 2.0 {
+            let delta_lon = (rad_distance.sin() / rad_lat.cos()).min(1.0).asin();
+            let mut lon = rad_lon - delta_lon;
+            if lon < -PI {
+                lon += 2.0 * PI;
+            }
+            min_lon = lon;
+            let mut lon = rad_lon + delta_lon;
+            if lon > PI {
+                lon -= 2.0 * PI;
+            }
+            max_lon = lon;
+        } else {
+            // a pole is within the distance
+            min_lat = min_lat.max(-PI / 2.0);
+            max_lat = max_lat.min(PI / 2.0);
+            min_lon = -PI;
+            max_lon = PI;
+        }
+        Ok(Rectangle {
+            min_lat: min_lat.to_degrees(),
+            max_lat: max_lat.to_degrees(),
+            min_lon: min_lon.to_degrees(),
+            max_lon: max_lon.to_degrees(),
+        })
+    }
+}
+
+impl fmt::Display for Rectangle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Rectangle(lat={} TO {} lon={} TO {}{})",
+            self.min_lat,
+            self.max_lat,
+            self.min_lon,
+            self.max_lon,
+            if self.crosses_dateline() {
+                " [crosses dateline!]"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::geo::haversin_meters;
+
+    #[test]
+    fn test_from_point_distance() {
+        let rect = Rectangle::from_point_distance(45.0, 10.0, 100_000.0).unwrap();
+        assert!(!rect.crosses_dateline());
+        assert!(rect.min_lat < 45.0 && rect.max_lat > 45.0);
+        // the box touches the circle
+        let d = haversin_meters(45.0, 10.0, rect.max_lat, 10.0);
+        assert!((d - 100_000.0).abs() < 1.0, "{}", d);
+        let d = haversin_meters(45.0, 10.0, 45.0, rect.max_lon);
+        assert!(d >= 100_000.0 && d < 101_000.0, "{}", d);
+
+        let rect = Rectangle::from_point_distance(0.0, 179.9, 50_000.0).unwrap();
+        assert!(rect.crosses_dateline());
+        assert!(rect.min_lon > 179.0 && rect.max_lon < -179.0);
+
+        let rect = Rectangle::from_point_distance(89.5, 0.0, 100_000.0).unwrap();
+        assert_eq!(rect.max_lat, 90.0);
+        assert_eq!((rect.min_lon, rect.max_lon), (-180.0, 180.0));
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(Rectangle::new(10.0, 20.0, 170.0, -170.0)
+            .unwrap()
+            .crosses_dateline());
+        assert!(Rectangle::new(20.0, 10.0, 0.0, 1.0).is_err());
+        assert!(Rectangle::new(10.0, 20.0, 0.0, 181.0).is_err());
+    }
+
+    #[test]
+    fn test_relate_triangle() {
+        let rect = Rectangle::new(0.0, 10.0, 0.0, 10.0).unwrap();
+        let relate = |a_lat, a_lon, b_lat, b_lon, c_lat, c_lon| {
+            rect.relate_triangle(&Triangle::new(a_lat, a_lon, b_lat, b_lon, c_lat, c_lon))
+        };
+        assert!(relate(1.0, 1.0, 2.0, 1.0, 1.0, 2.0) == Relation::CellInsideQuery);
+        assert!(relate(1.0, 1.0, 20.0, 1.0, 1.0, 2.0) == Relation::CellCrossesQuery);
+        // the bounding boxes overlap, but not the shapes
+        assert!(relate(9.0, 20.0, 20.0, 20.0, 20.0, 9.0) == Relation::CellOutsideQuery);
+        // a segment through the rectangle
+        assert!(relate(5.0, -5.0, 5.0, 15.0, 5.0, -5.0) == Relation::CellCrossesQuery);
+        // around the rectangle
+        assert!(relate(-10.0, -10.0, 50.0, -10.0, -10.0, 50.0) == Relation::CellCrossesQuery);
+        assert!(rect.relate(1.0, 2.0, 1.0, 2.0) == Relation::CellInsideQuery);
+        assert!(rect.relate(-1.0, 2.0, 1.0, 2.0) == Relation::CellCrossesQuery);
+        assert!(rect.relate(11.0, 12.0, 1.0, 2.0) == Relation::CellOutsideQuery);
+
+        let rect = Rectangle::new(0.0, 10.0, 170.0, -170.0).unwrap();
+        let point = |lat, lon| Triangle::new(lat, lon, lat, lon, lat, lon);
+        assert!(rect.relate_triangle(&point(5.0, 175.0)) == Relation::CellInsideQuery);
+        assert!(rect.relate_triangle(&point(5.0, -175.0)) == Relation::CellInsideQuery);
+        assert!(rect.relate_triangle(&point(5.0, 0.0)) == Relation::CellOutsideQuery);
+        assert!(rect.relate(1.0, 2.0, -180.0, -175.0) == Relation::CellInsideQuery);
+        assert!(rect.relate(1.0, 2.0, -175.0, 175.0) == Relation::CellCrossesQuery);
+    }
+}
@@ -0,0 +1,668 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::geo::{point_in_triangle, Polygon};
+
+use error::{ErrorKind, Result};
+
+/// A triangle of a tessellated shape, in degrees. Lines and points are
+/// indexed as triangles too, degenerated to a segment or a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub a_lat: f64,
+    pub a_lon: f64,
+    pub b_lat: f64,
+    pub b_lon: f64,
+    pub c_lat: f64,
+    pub c_lon: f64,
+}
+
+impl Triangle {
+    pub fn new(a_lat: f64, a_lon: f64, b_lat: f64, b_lon: f64, c_lat: f64, c_lon: f64) -> Self {
+        Triangle {
+            a_lat,
+            a_lon,
+            b_lat,
+            b_lon,
+            c_lat,
+            c_lon,
+        }
+    }
+
+    /// The vertices as `(latitude, longitude)`.
+    pub fn vertices(&self) -> [(f64, f64); 3] {
+        [
+            (self.a_lat, self.a_lon),
+            (self.b_lat, self.b_lon),
+            (self.c_lat, self.c_lon),
+        ]
+    }
+
+    /// The bounding box of the triangle, as
+    /// `(min_lat, max_lat, min_lon, max_lon)`.
+    pub fn bbox(&self) -> (f64, f64, f64, f64) {
+        (
+            self.a_lat.min(self.b_lat).min(self.c_lat),
+            self.a_lat.max(self.b_lat).max(self.c_lat),
+            self.a_lon.min(self.b_lon).min(self.c_lon),
+            self.a_lon.max(self.b_lon).max(self.c_lon),
+        )
+    }
+}
+
+/// Splits a polygon with holes into triangles, by ear clipping.
+///
+/// The holes are first merged into the outer ring by bridges from their
+/// leftmost vertex, then the ears are cut off the ring. When no ear is left
+/// the ring is cleaned of its duplicated and collinear points, then of its
+/// local self intersections, and at last split in two along a diagonal.
+/// Errors if the polygon can't be split, e.g. when it is self intersecting.
+pub fn tessellate(polygon: &Polygon) -> Result<Vec<Triangle>> {
+    let mut tessellator = Tessellator::default();
+    let outer = tessellator.linked_list(polygon.polygon_lats(), polygon.polygon_lons(), true);
+    let mut outer = match outer {
+        Some(outer) => outer,
+        None => return Ok(vec![]),
+    };
+    if !polygon.holes().is_empty() {
+        let mut queue = Vec::with_capacity(polygon.holes().len());
+        for hole in polygon.holes() {
+            if let Some(list) =
+                tessellator.linked_list(hole.polygon_lats(), hole.polygon_lons(), false)
+            {
+                if tessellator.nodes[list].next == list {
+                    tessellator.nodes[list].steiner = true;
+                }
+                queue.push(tessellator.leftmost(list));
+            }
+        }
+        {
+            let nodes = &tessellator.nodes;
+            queue.sort_by(|&a, &b| nodes[a].x.partial_cmp(&nodes[b].x).unwrap());
+        }
+        for hole in queue {
+            outer = tessellator.eliminate_hole(hole, outer);
+            let next = tessellator.nodes[outer].next;
+            outer = tessellator.filter_points(outer, Some(next));
+        }
+    }
+    tessellator.earcut_linked(outer, 0)?;
+    let nodes = &tessellator.nodes;
+    Ok(tessellator
+        .triangles
+        .iter()
+        .map(|&(a, b, c)| {
+            Triangle::new(
+                nodes[a].y, nodes[a].x, nodes[b].y, nodes[b].x, nodes[c].y, nodes[c].x,
+            )
+        })
+        .collect())
+}
+
+/// A vertex of a ring, the rings are circular doubly linked lists of the
+/// nodes of the tessellator. `i` is the index of the vertex in the polygon,
+/// which is shared by the copies of the vertex made by the splits, `x` is
+/// the longitude and `y` the latitude.
+struct Node {
+    i: usize,
+    x: f64,
+    y: f64,
+    prev: usize,
+    next: usize,
+    steiner: bool,
+}
+
+#[derive(Default)]
+struct Tessellator {
+    nodes: Vec<Node>,
+    vertices: usize,
+    // the nodes of the vertices of the triangles
+    triangles: Vec<(usize, usize, usize)>,
+}
+
+impl Tessellator {
+    /// Links the points of a closed ring in the given winding order, returns
+    /// the last node or `None` if the ring is empty.
+    fn linked_list(&mut self, lats: &[f64], lons: &[f64], clockwise: bool) -> Option<usize> {
+        // the last point is the same as the first one
+        let len = lats.len() - 1;
+        let base = self.vertices;
+        self.vertices += len;
+        let mut last = None;
+        if clockwise == (signed_area(lats, lons, len) > 0.0) {
+            for i in 0..len {
+                last = Some(self.insert_node(base + i, lons[i], lats[i], last));
+            }
+        } else {
+            for i in (0..len).rev() {
+                last = Some(self.insert_node(base + i, lons[i], lats[i], last));
+            }
+        }
+        if let Some(node) = last {
+            let next = self.nodes[node].next;
+            if self.equals(node, next) {
+                self.remove_node(node);
+                return Some(next);
+            }
+        }
+        last
+    }
+
+    fn insert_node(&mut self, i: usize, x: f64, y: f64, last: Option<usize>) -> usize {
+        let p = self.nodes.len();
+        let (prev, next) = match last {
+            Some(last) => (last, self.nodes[last].next),
+            None => (p, p),
+        };
+        self.nodes.push(Node {
+            i,
+            x,
+            y,
+            prev,
+            next,
+            steiner: false,
+        });
+        if let Some(last) = last {
+            self.nodes[next].prev = p;
+            self.nodes[last].next = p;
+        }
+        p
+    }
+
+    fn remove_node(&mut self, p: usize) {
+        let (prev, next) = (self.nodes[p].prev, self.nodes[p].next);
+        self.nodes[next].prev = prev;
+        self.nodes[prev].next = next;
+    }
+
+    fn prev(&self, p: usize) -> usize {
+        self.nodes[p].prev
+    }
+
+    fn next(&self, p: usize) -> usize {
+        self.nodes[p].next
+    }
+
+    fn equals(&self, p: usize, q: usize) -> bool {
+        self.nodes[p].x == self.nodes[q].x && self.nodes[p].y == self.nodes[q].y
+    }
+
+    /// Twice the signed area of the triangle `p`, `q`, `r`, negative when
+    /// the triangle is convex in the winding order of the rings.
+    fn area(&self, p: usize, q: usize, r: usize) -> f64 {
+        let (p, q, r) = (&self.nodes[p], &self.nodes[q], &self.nodes[r]);
+        (q.y - p.y) * (r.x - q.x) - (q.x - p.x) * (r.y - q.y)
+    }
+
+    /// Removes the duplicated and collinear points of the ring between
+    /// `start` and `end`.
+    fn filter_points(&mut self, start: usize, end: Option<usize>) -> usize {
+        let mut end = end.unwrap_or(start);
+        let mut p = start;
+        loop {
+            let mut again = false;
+            let (prev, next) = (self.prev(p), self.next(p));
+            if !self.nodes[p].steiner && (self.equals(p, next) || self.area(prev, p, next) == 0.0) {
+                self.remove_node(p);
+                p = prev;
+                end = prev;
+                if p == self.next(p) {
+                    break;
+                }
+                again = true;
+            } else {
+                p = next;
+            }
+            if !again && p == end {
+                break;
+            }
+        }
+        end
+    }
+
+    /// Cuts the ears of the ring, falling back on the cleaning passes when
+    /// there is no ear left.
+    fn earcut_linked(&mut self, ear: usize, pass: u8) -> Result<()> {
+        let mut ear = ear;
+        let mut stop = ear;
+        while self.prev(ear) != self.next(ear) {
+            let (prev, next) = (self.prev(ear), self.next(ear));
+            if self.is_ear(ear) {
+                self.triangles.push((prev, ear, next));
+                self.remove_node(ear);
+                ear = self.next(next);
+                stop = ear;
+                continue;
+            }
+            ear = next;
+            if ear == stop {
+                match pass {
+                    0 => {
+                        let ear = self.filter_points(ear, None);
+                        self.earcut_linked(ear, 1)?;
+                    }
+                    1 => {
+                        let ear = self.filter_points(ear, None);
+                        let ear = self.cure_local_intersections(ear);
+                        self.earcut_linked(ear, 2)?;
+                    }
+                    _ => self.split_earcut(ear)?,
+                }
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the node is a convex vertex whose triangle with its
+    /// neighbours holds no other reflex vertex of the ring.
+    fn is_ear(&self, ear: usize) -> bool {
+        let (a, b, c) = (self.prev(ear), ear, self.next(ear));
+        if self.area(a, b, c) >= 0.0 {
+            return false;
+        }
+        let (na, nb, nc) = (&self.nodes[a], &self.nodes[b], &self.nodes[c]);
+        let mut p = self.next(c);
+        while p != a {
+            let np = &self.nodes[p];
+            if point_in_triangle((np.y, np.x), (na.y, na.x), (nb.y, nb.x), (nc.y, nc.x))
+                && self.area(self.prev(p), p, self.next(p)) >= 0.0
+            {
+                return false;
+            }
+            p = self.next(p);
+        }
+        true
+    }
+
+    /// Cuts off the local self intersections, where the segment before a
+    /// node crosses the segment after its next node.
+    fn cure_local_intersections(&mut self, start: usize) -> usize {
+        let mut start = start;
+        let mut p = start;
+        loop {
+            let a = self.prev(p);
+            let b = self.next(self.next(p));
+            if !self.equals(a, b)
+                && self.intersects(a, p, self.next(p), b)
+                && self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+            {
+                self.triangles.push((a, p, b));
+                let next = self.next(p);
+                self.remove_node(p);
+                self.remove_node(next);
+                p = b;
+                start = b;
+            }
+            p = self.next(p);
+            if p == start {
+                break;
+            }
+        }
+        self.filter_points(p, None)
+    }
+
+    /// Splits the ring in two along a valid diagonal and tessellates both
+    /// halves.
+    fn split_earcut(&mut self, start: usize) -> Result<()> {
+        let mut a = start;
+        loop {
+            let mut b = self.next(self.next(a));
+            while b != self.prev(a) {
+                if self.nodes[a].i != self.nodes[b].i && self.is_valid_diagonal(a, b) {
+                    let c = self.split_polygon(a, b);
+                    let next = self.next(a);
+                    let a = self.filter_points(a, Some(next));
+                    let next = self.next(c);
+                    let c = self.filter_points(c, Some(next));
+                    self.earcut_linked(a, 0)?;
+                    return self.earcut_linked(c, 0);
+                }
+                b = self.next(b);
+            }
+            a = self.next(a);
+            if a == start {
+                break;
+            }
+        }
+        bail!(ErrorKind::IllegalArgument(
+            "unable to tessellate the polygon, it may be self intersecting".into()
+        ))
+    }
+
+    /// Merges a hole into the outer ring by a bridge from its leftmost
+    /// vertex, returns the outer ring.
+    fn eliminate_hole(&mut self, hole: usize, outer: usize) -> usize {
+        let bridge = match self.find_hole_bridge(hole, outer) {
+            Some(bridge) => bridge,
+            None => return outer,
+        };
+        let bridge_reverse = self.split_polygon(bridge, hole);
+        let next = self.next(bridge);
+        let filtered_bridge = self.filter_points(bridge, Some(next));
+        let next = self.next(bridge_reverse);
+        self.filter_points(bridge_reverse, Some(next));
+        if outer == bridge {
+            filtered_bridge
+        } else {
+            outer
+        }
+    }
+
+    /// Finds the vertex of the outer ring which can be bridged with the
+    /// leftmost vertex of a hole: the one seen first by a ray going west
+    /// from the hole, or the reflex vertex the closest to the ray if some
+    /// are in the way.
+    fn find_hole_bridge(&self, hole: usize, outer: usize) -> Option<usize> {
+        let (hx, hy) = (self.nodes[hole].x, self.nodes[hole].y);
+        let mut qx = ::std::f64::NEG_INFINITY;
+        let mut m = None;
+        let mut p = outer;
+        loop {
+            let (np, nn) = (&self.nodes[p], &self.nodes[self.next(p)]);
+            if hy <= np.y && hy >= nn.y && nn.y != np.y {
+                let x = np.x + (hy - np.y) * (nn.x - np.x) / (nn.y - np.y);
+                if x <= hx && x > qx {
+                    qx = x;
+                    if x == hx {
+                        if hy == np.y {
+                            return Some(p);
+                        }
+                        if hy == nn.y {
+                            return Some(self.next(p));
+                        }
+                    }
+                    m = Some(if np.x < nn.x { p } else { self.next(p) });
+                }
+            }
+            p = self.next(p);
+            if p == outer {
+                break;
+            }
+        }
+        let mut m = m?;
+        if hx == qx {
+            // the hole touches the outer segment
+            return Some(m);
+        }
+
+        let stop = m;
+        let (mx, my) = (self.nodes[m].x, self.nodes[m].y);
+        let mut tan_min = ::std::f64::INFINITY;
+        let mut p = m;
+        loop {
+            let (px, py) = (self.nodes[p].x, self.nodes[p].y);
+            let (ax, cx) = if hy < my { (hx, qx) } else { (qx, hx) };
+            if hx >= px
+                && px >= mx
+                && hx != px
+                && point_in_triangle((py, px), (hy, ax), (my, mx), (hy, cx))
+            {
+                let tan = (hy - py).abs() / (hx - px);
+                if self.locally_inside(p, hole)
+                    && (tan < tan_min
+                        || (tan == tan_min
+                            && (px > self.nodes[m].x
+                                || (px == self.nodes[m].x && self.sector_contains_sector(m, p)))))
+                {
+                    m = p;
+                    tan_min = tan;
+                }
+            }
+            p = self.next(p);
+            if p == stop {
+                break;
+            }
+        }
+        Some(m)
+    }
+
+    /// Whether the sector of the vertex `m` contains the sector of `p`.
+    fn sector_contains_sector(&self, m: usize, p: usize) -> bool {
+        self.area(self.prev(m), m, self.prev(p)) < 0.0
+            && self.area(self.next(p), m, self.next(m)) < 0.0
+    }
+
+    fn leftmost(&self, start: usize) -> usize {
+        let mut p = start;
+        let mut leftmost = start;
+        loop {
+            let (np, nl) = (&self.nodes[p], &self.nodes[leftmost]);
+            if np.x < nl.x || (np.x == nl.x && np.y < nl.y) {
+                leftmost = p;
+            }
+            p = self.next(p);
+            if p == start {
+                break;
+            }
+        }
+        leftmost
+    }
+
+    /// Whether the diagonal `a`-`b` splits the ring in two valid rings.
+    fn is_valid_diagonal(&self, a: usize, b: usize) -> bool {
+        let ib = self.nodes[b].i;
+        if self.nodes[self.next(a)].i == ib
+            || self.nodes[self.prev(a)].i == ib
+            || self.intersects_polygon(a, b)
+        {
+            return false;
+        }
+        (self.locally_inside(a, b)
+            && self.locally_inside(b, a)
+            && self.middle_inside(a, b)
+            && (self.area(self.prev(a), a, self.prev(b)) != 0.0
+                || self.area(a, self.prev(b), b) != 0.0))
+            || (self.equals(a, b)
+                && self.area(self.prev(a), a, self.next(a)) > 0.0
+                && self.area(self.prev(b), b, self.next(b)) > 0.0)
+    }
+
+    /// Whether the segments `p1`-`q1` and `p2`-`q2` share a point.
+    fn intersects(&self, p1: usize, q1: usize, p2: usize, q2: usize) -> bool {
+        let o1 = sign(self.area(p1, q1, p2));
+        let o2 = sign(self.area(p1, q1, q2));
+        let o3 = sign(self.area(p2, q2, p1));
+        let o4 = sign(self.area(p2, q2, q1));
+        (o1 != o2 && o3 != o4)
+            || (o1 == 0 && self.on_segment(p1, p2, q1))
+            || (o2 == 0 && self.on_segment(p1, q2, q1))
+            || (o3 == 0 && self.on_segment(p2, p1, q2))
+            || (o4 == 0 && self.on_segment(p2, q1, q2))
+    }
+
+    /// Whether `q` is within the bounding box of the segment `p`-`r`, for
+    /// collinear points.
+    fn on_segment(&self, p: usize, q: usize, r: usize) -> bool {
+        let (p, q, r) = (&self.nodes[p], &self.nodes[q], &self.nodes[r]);
+        q.x <= p.x.max(r.x) && q.x >= p.x.min(r.x) && q.y <= p.y.max(r.y) && q.y >= p.y.min(r.y)
+    }
+
+    /// Whether the diagonal `a`-`b` crosses a segment of the ring.
+    fn intersects_polygon(&self, a: usize, b: usize) -> bool {
+        let (ia, ib) = (self.nodes[a].i, self.nodes[b].i);
+        let mut p = a;
+        loop {
+            let next = self.next(p);
+            let (ip, inext) = (self.nodes[p].i, self.nodes[next].i);
+            if ip != ia && inext != ia && ip != ib && inext != ib && self.intersects(p, next, a, b)
+            {
+                return true;
+            }
+            p = next;
+            if p == a {
+                return false;
+            }
+        }
+    }
+
+    /// Whether the diagonal `a`-`b` starts inside the ring at `a`.
+    fn locally_inside(&self, a: usize, b: usize) -> bool {
+        let (prev, next) = (self.prev(a), self.next(a));
+        if self.area(prev, a, next) < 0.0 {
+            self.area(a, b, next) >= 0.0 && self.area(a, prev, b) >= 0.0
+        } else {
+            self.area(a, b, prev) < 0.0 || self.area(a, next, b) < 0.0
+        }
+    }
+
+    /// Whether the middle of the diagonal `a`-`b` is inside the ring.
+    fn middle_inside(&self, a: usize, b: usize) -> bool {
+        let px = (self.nodes[a].x + self.nodes[b].x) / 2.0;
+        let py = (self.nodes[a].y + self.nodes[b].y) / 2.0;
+        let mut inside = false;
+        let mut p = a;
+        loop {
+            let (np, nn) = (&self.nodes[p], &self.nodes[self.next(p)]);
+            if (np.y > py) != (nn.y > py)
+                && nn.y != np.y
+                && px < (nn.x - np.x) * (py - np.y) / (nn.y - np.y) + np.x
+            {
+                inside = !inside;
+            }
+            p = self.next(p);
+            if p == a {
+                return inside;
+            }
+        }
+    }
+
+    /// Links `a` to `b` by two copies of the diagonal, which splits the
+    /// ring in two. Returns the copy of `b`, in the second ring.
+    fn split_polygon(&mut self, a: usize, b: usize) -> usize {
+        let a2 = self.nodes.len();
+        let b2 = a2 + 1;
+        let (an, bp) = (self.next(a), self.prev(b));
+        let (ia, ax, ay) = (self.nodes[a].i, self.nodes[a].x, self.nodes[a].y);
+        let (ib, bx, by) = (self.nodes[b].i, self.nodes[b].x, self.nodes[b].y);
+        self.nodes.push(Node {
+            i: ia,
+            x: ax,
+            y: ay,
+            prev: b2,
+            next: an,
+            steiner: false,
+        });
+        self.nodes.push(Node {
+            i: ib,
+            x: bx,
+            y: by,
+            prev: bp,
+            next: a2,
+            steiner: false,
+        });
+        self.nodes[a].next = b;
+        self.nodes[b].prev = a;
+        self.nodes[an].prev = a2;
+        self.nodes[bp].next = b2;
+        b2
+    }
+}
+
+/// Twice the signed area of a closed ring of `len` distinct points, in the
+/// orientation convention of `Tessellator::area`.
+fn signed_area(lats: &[f64], lons: &[f64], len: usize) -> f64 {
+    let mut sum = 0.0;
+    let mut j = len - 1;
+    for i in 0..len {
+        sum += (lons[j] - lons[i]) * (lats[i] + lats[j]);
+        j = i;
+    }
+    sum
+}
+
+fn sign(v: f64) -> i32 {
+    if v > 0.0 {
+        1
+    } else if v < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring_area(lats: &[f64], lons: &[f64]) -> f64 {
+        signed_area(lats, lons, lats.len() - 1).abs() / 2.0
+    }
+
+    fn triangles_area(triangles: &[Triangle]) -> f64 {
+        triangles
+            .iter()
+            .map(|t| {
+                ((t.b_lon - t.a_lon) * (t.c_lat - t.a_lat)
+                    - (t.c_lon - t.a_lon) * (t.b_lat - t.a_lat))
+                    .abs()
+                    / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_tessellate_square() {
+        let polygon =
+            Polygon::new(vec![0.0, 0.0, 1.0, 1.0, 0.0], vec![0.0, 1.0, 1.0, 0.0, 0.0]).unwrap();
+        let triangles = tessellate(&polygon).unwrap();
+        assert_eq!(triangles.len(), 2);
+        assert!((triangles_area(&triangles) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tessellate_concave() {
+        // an L, in both winding orders
+        let mut lats = vec![0.0, 0.0, 1.0, 1.0, 3.0, 3.0, 0.0];
+        let mut lons = vec![0.0, 3.0, 3.0, 1.0, 1.0, 0.0, 0.0];
+        for _ in 0..2 {
+            let polygon = Polygon::new(lats.clone(), lons.clone()).unwrap();
+            let triangles = tessellate(&polygon).unwrap();
+            assert_eq!(triangles.len(), 4);
+            assert!((triangles_area(&triangles) - ring_area(&lats, &lons)).abs() < 1e-9);
+            lats.reverse();
+            lons.reverse();
+        }
+    }
+
+    #[test]
+    fn test_tessellate_with_holes() {
+        let hole1 =
+            Polygon::new(vec![1.0, 1.0, 2.0, 2.0, 1.0], vec![1.0, 2.0, 2.0, 1.0, 1.0]).unwrap();
+        let hole2 = Polygon::new(vec![5.0, 6.0, 6.0, 5.0], vec![5.0, 5.0, 7.0, 5.0]).unwrap();
+        let polygon = Polygon::with_holes(
+            vec![0.0, 0.0, 10.0, 10.0, 0.0],
+            vec![0.0, 10.0, 10.0, 0.0, 0.0],
+            vec![hole1, hole2],
+        )
+        .unwrap();
+        let triangles = tessellate(&polygon).unwrap();
+        assert!((triangles_area(&triangles) - (100.0 - 1.0 - 1.0)).abs() < 1e-9);
+        // no triangle overlaps the holes
+        for t in &triangles {
+            let lat = (t.a_lat + t.b_lat + t.c_lat) / 3.0;
+            let lon = (t.a_lon + t.b_lon + t.c_lon) / 3.0;
+            assert!(!(lat > 1.0 && lat < 2.0 && lon > 1.0 && lon < 2.0));
+        }
+    }
+
+    #[test]
+    fn test_tessellate_collinear_points() {
+        // the duplicated and collinear points are dropped
+        let lats = vec![0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 0.0];
+        let lons = vec![0.0, 1.0, 1.0, 2.0, 2.0, 0.0, 0.0];
+        let triangles = tessellate(&Polygon::new(lats, lons).unwrap()).unwrap();
+        assert!((triangles_area(&triangles) - 4.0).abs() < 1e-9);
+    }
+}
@@ -26,6 +26,24 @@ use core::util::DocId;
 
 use error::Result;
 
+///
+// Tags highlighting each term or phrase of the query with its own color,
+// cycling through the colors when the query has more terms.
+//
+pub const COLORED_PRE_TAGS: [&str; 10] = [
+    "<b style=\"background:yellow\">",
+    "<b style=\"background:lawngreen\">",
+    "<b style=\"background:aquamarine\">",
+    "<b style=\"background:magenta\">",
+    "<b style=\"background:palegreen\">",
+    "<b style=\"background:coral\">",
+    "<b style=\"background:wheat\">",
+    "<b style=\"background:khaki\">",
+    "<b style=\"background:lime\">",
+    "<b style=\"background:deepskyblue\">",
+];
+pub const COLORED_POST_TAGS: [&str; 1] = ["</b>"];
+
 pub struct BaseFragmentsBuilder {
     pre_tags: Vec<String>,
     post_tags: Vec<String>,
@@ -50,6 +68,17 @@ impl BaseFragmentsBuilder {
         }
     }
 
+    ///
+    // A builder highlighting the terms and phrases with `COLORED_PRE_TAGS`.
+    //
+    pub fn with_colored_tags(
+        boundary_scanner: Option<Box<dyn BoundaryScanner>>,
+    ) -> BaseFragmentsBuilder {
+        let pre_tags: Vec<String> = COLORED_PRE_TAGS.iter().map(|t| t.to_string()).collect();
+        let post_tags: Vec<String> = COLORED_POST_TAGS.iter().map(|t| t.to_string()).collect();
+        BaseFragmentsBuilder::new(Some(&pre_tags), Some(&post_tags), boundary_scanner)
+    }
+
     fn fields<C: Codec>(
         &self,
         reader: &dyn IndexReader<Codec = C>,
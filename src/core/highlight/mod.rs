@@ -23,10 +23,18 @@ mod fvh_highlighter;
 
 pub use self::fvh_highlighter::*;
 
+mod passage;
+
+pub use self::passage::*;
+
+mod unified_highlighter;
+
+pub use self::unified_highlighter::*;
+
 use core::codec::{Codec, Fields, PostingIterator, PostingIteratorFlags, TermIterator, Terms};
 use core::doc::Term;
 use core::index::reader::{IndexReader, LeafReaderContext};
-use core::search::query::{Query, TermQuery};
+use core::search::query::{BooleanQuery, BoostQuery, PhraseQuery, Query, TermQuery};
 use core::search::DocIterator;
 use core::util::DocId;
 
@@ -454,6 +462,28 @@ impl QueryPhraseMap {
         Ok(())
     }
 
+    ///
+    // Adds a phrase, whose terms must occur in order within `slop` positions
+    // of each other.
+    //
+    pub fn add_phrase(
+        &mut self,
+        terms: &[Term],
+        slop: i32,
+        boost: f32,
+        term_or_phrase_number: i32,
+    ) -> Result<()> {
+        let mut map = self;
+        for term in terms {
+            map = map
+                .sub_map
+                .entry(term.text()?)
+                .or_insert_with(QueryPhraseMap::default);
+        }
+        map.mark_terminal(Some(slop), boost, term_or_phrase_number);
+        Ok(())
+    }
+
     pub fn is_valid_term_or_phrase(&self, phrase_candidate: &[TermInfo]) -> bool {
         if !self.terminal {
             return false;
@@ -499,9 +529,19 @@ impl QueryPhraseMap {
 // The maximum number of different matching terms accumulated from any one MultiTermQuery
 pub const MAX_MTQ_TERMS: i32 = 1024;
 
+///
+// A term or a phrase of a query, flattened out of its boolean structure.
+#[derive(Debug, PartialEq)]
+struct FlatQuery {
+    terms: Vec<Term>,
+    slop: i32,
+    boost: f32,
+}
+
 #[derive(Debug)]
 pub struct FieldQuery {
     field_match: bool,
+    phrase_highlight: bool,
     // fieldMatch==true,  Map<fieldName,QueryPhraseMap>
     // fieldMatch==false, Map<null,QueryPhraseMap>
     root_maps: HashMap<String, QueryPhraseMap>,
@@ -516,12 +556,13 @@ impl FieldQuery {
     pub fn new<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
         query: &dyn Query<C>,
         reader: Option<&IR>,
-        _phrase_highlight: bool,
+        phrase_highlight: bool,
         field_match: bool,
     ) -> Result<FieldQuery> {
-        let mut flat_queries: Vec<TermQuery> = vec![];
+        let mut flat_queries: Vec<FlatQuery> = vec![];
         let mut field_query = FieldQuery {
             field_match,
+            phrase_highlight,
             root_maps: HashMap::new(),
             term_set_map: HashMap::new(),
             term_or_phrase_number: 0,
@@ -540,26 +581,74 @@ impl FieldQuery {
         Ok(field_query)
     }
 
+    // Flattens the boolean and boost queries into their terms and phrases.
+    // The phrases are kept whole if phraseHighlight, so that their terms
+    // are only highlighted together.
+    //
     fn flatten<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
         &self,
         source_query: &dyn Query<C>,
-        _reader: Option<&IR>,
-        flat_queries: &mut Vec<TermQuery>,
+        reader: Option<&IR>,
+        flat_queries: &mut Vec<FlatQuery>,
         boost: f32,
     ) -> Result<()> {
-        if (boost - 1f32).abs() > EPSILON {
-            unimplemented!()
-        }
-
-        for term_query in source_query.extract_terms() {
-            if !flat_queries.contains(&term_query) {
-                flat_queries.push(term_query);
+        let any = source_query.as_any();
+        if let Some(query) = any.downcast_ref::<BooleanQuery<C>>() {
+            // prohibited clauses never match
+            for clause in query
+                .must_queries()
+                .iter()
+                .chain(query.should_queries())
+                .chain(query.filter_queries())
+            {
+                self.flatten(clause.as_ref(), reader, flat_queries, boost)?;
+            }
+        } else if let Some(query) = any.downcast_ref::<BoostQuery<C>>() {
+            self.flatten(query.query(), reader, flat_queries, boost * query.boost())?;
+        } else if let Some(query) = any.downcast_ref::<PhraseQuery>() {
+            if self.phrase_highlight {
+                Self::add_flat_query(
+                    flat_queries,
+                    FlatQuery {
+                        terms: query.terms().to_vec(),
+                        slop: query.slop(),
+                        boost,
+                    },
+                );
+            } else {
+                for term in query.terms() {
+                    Self::add_flat_query(
+                        flat_queries,
+                        FlatQuery {
+                            terms: vec![term.clone()],
+                            slop: 0,
+                            boost,
+                        },
+                    );
+                }
+            }
+        } else {
+            for term_query in source_query.extract_terms() {
+                Self::add_flat_query(
+                    flat_queries,
+                    FlatQuery {
+                        terms: vec![term_query.term],
+                        slop: 0,
+                        boost: boost * term_query.boost,
+                    },
+                );
             }
         }
 
         Ok(())
     }
 
+    fn add_flat_query(flat_queries: &mut Vec<FlatQuery>, flat_query: FlatQuery) {
+        if !flat_queries.contains(&flat_query) {
+            flat_queries.push(flat_query);
+        }
+    }
+
     // Save the set of terms in the queries to termSetMap.
     // ex1) q=name:john
     //      - fieldMatch==true termSetMap=Map<"name",Set<"john">>
@@ -573,13 +662,15 @@ impl FieldQuery {
     //      - fieldMatch==true termSetMap=Map<"name",Set<"john","lennon">>
     //      - fieldMatch==false termSetMap=Map<null,Set<"john","lennon">>
     //
-    fn save_terms<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
+    fn save_terms<IR: IndexReader + ?Sized>(
         &mut self,
         _reader: Option<&IR>,
-        flat_queries: &[TermQuery],
+        flat_queries: &[FlatQuery],
     ) -> Result<()> {
         for query in flat_queries {
-            self.add_term_set_by_query(query, query.term().text()?);
+            for term in &query.terms {
+                self.add_term_set_by_query(query, term.text()?);
+            }
         }
 
         Ok(())
@@ -588,57 +679,49 @@ impl FieldQuery {
     // Return 'key' string. 'key' is the field name of the Query.
     // If not fieldMatch, 'key' will be null.
     //
-    fn get_key(&self, query: &TermQuery) -> String {
+    fn get_key(&self, query: &FlatQuery) -> String {
         if !self.field_match {
             return String::from("");
         }
 
-        String::from(query.term().field())
+        String::from(query.terms[0].field())
     }
 
-    fn add_term_set_by_query(&mut self, query: &TermQuery, value: String) {
+    fn add_term_set_by_query(&mut self, query: &FlatQuery, value: String) {
         let key = self.get_key(query);
-
-        if !self.term_set_map.contains_key(&key) {
-            let set: Vec<String> = vec![];
-            self.term_set_map.insert(key.clone(), set);
+        let set = self.term_set_map.entry(key).or_insert_with(Vec::new);
+        if !set.contains(&value) {
+            set.push(value);
         }
-
-        debug_assert!(self.term_set_map.contains_key(&key));
-        self.term_set_map.get_mut(&key).unwrap().push(value)
     }
 
     pub fn get_term_set_by_field(&self, field: &str) -> Option<&Vec<String>> {
         self.term_set_map.get(field)
     }
 
-    fn expand(&self, flat_queries: Vec<TermQuery>) -> Vec<TermQuery> {
+    fn expand(&self, flat_queries: Vec<FlatQuery>) -> Vec<FlatQuery> {
         flat_queries
     }
 
+    /// The number of the next term or phrase, used to pick its tags when
+    /// several are given, e.g. `COLORED_PRE_TAGS`.
     pub fn next_term_or_phrase_number(&mut self) -> i32 {
+        let number = self.term_or_phrase_number;
         self.term_or_phrase_number += 1;
-        self.term_or_phrase_number
+        number
     }
 
-    fn add_root_map_by_query<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
+    fn add_root_map_by_query<IR: IndexReader + ?Sized>(
         &mut self,
-        query: &TermQuery,
-        reader: Option<&IR>,
+        query: &FlatQuery,
+        _reader: Option<&IR>,
         term_or_phrase_number: i32,
     ) -> Result<()> {
         let key = self.get_key(query);
-
-        if !self.root_maps.contains_key(&key) {
-            let map = QueryPhraseMap::default();
-            self.root_maps.insert(key.clone(), map);
-        }
-
-        assert!(self.root_maps.contains_key(&key));
         self.root_maps
-            .get_mut(&key)
-            .unwrap()
-            .add(query, reader, term_or_phrase_number)
+            .entry(key)
+            .or_insert_with(QueryPhraseMap::default)
+            .add_phrase(&query.terms, query.slop, query.boost, term_or_phrase_number)
     }
 
     pub fn get_root_map_by_field(&self, field_name: &str) -> Option<&QueryPhraseMap> {
@@ -1156,6 +1239,91 @@ impl BoundaryScanner for SimpleBoundaryScanner {
     }
 }
 
+///
+// Boundary scanner moving the fragment boundaries to the nearest sentence
+// boundaries within `max_scan` chars, so that the fragments read as whole
+// sentences when possible.
+//
+pub struct SentenceBoundaryScanner {
+    max_scan: i32,
+}
+
+impl SentenceBoundaryScanner {
+    pub fn new(max_scan: Option<i32>) -> SentenceBoundaryScanner {
+        SentenceBoundaryScanner {
+            max_scan: max_scan.unwrap_or(DEFAULT_MAX_SCAN),
+        }
+    }
+
+    fn is_terminal(c: char) -> bool {
+        match c {
+            '.' | '!' | '?' | '。' | '！' | '？' => true,
+            _ => false,
+        }
+    }
+
+    // whether a sentence starts at `offset`
+    fn is_sentence_start(chars: &[char], offset: usize) -> bool {
+        let prev = chars[offset - 1];
+        prev == '\n'
+            || prev == '。'
+            || prev == '！'
+            || prev == '？'
+            || (prev.is_whitespace() && offset > 1 && Self::is_terminal(chars[offset - 2]))
+    }
+}
+
+impl BoundaryScanner for SentenceBoundaryScanner {
+    fn find_start_offset(&self, buffer: &str, start: i32) -> i32 {
+        let chars: Vec<char> = buffer.chars().collect();
+        if start > chars.len() as i32 || start < 1 {
+            return start;
+        }
+
+        let mut offset = start;
+        let mut count = self.max_scan;
+        while offset > 0 && count > 0 {
+            if Self::is_sentence_start(&chars, offset as usize) {
+                return offset;
+            }
+
+            count -= 1;
+            offset -= 1;
+        }
+
+        if offset == 0 {
+            return 0;
+        }
+
+        start
+    }
+
+    fn find_end_offset(&self, buffer: &str, start: i32) -> i32 {
+        let chars: Vec<char> = buffer.chars().collect();
+        if start > chars.len() as i32 || start < 0 {
+            return start;
+        }
+
+        let mut offset = start;
+        let mut count = self.max_scan;
+        while offset < chars.len() as i32 && count > 0 {
+            let c = chars[offset as usize];
+            if c == '\n' {
+                return offset;
+            }
+            // keep the punctuation in the fragment
+            if Self::is_terminal(c) {
+                return offset + 1;
+            }
+
+            count -= 1;
+            offset += 1;
+        }
+
+        start
+    }
+}
+
 ///
 // {@link org.apache.lucene.search.vectorhighlight.FragmentsBuilder} is an interface for fragments
 // (snippets) builder classes. A {@link org.apache.lucene.search.vectorhighlight.FragmentsBuilder}
@@ -1211,3 +1379,74 @@ pub trait FragListBuilder {
         frag_char_size: i32,
     ) -> Result<Box<dyn FieldFragList>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    fn term(text: &str) -> Term {
+        Term::new("body".into(), text.as_bytes().to_vec())
+    }
+
+    fn phrase_list(phrase_highlight: bool) -> Vec<WeightedPhraseInfo> {
+        // "the quick brown fox and the dog"
+        let phrase = PhraseQuery::build(vec![term("quick"), term("fox")], 1, None, None).unwrap();
+        let query: Box<dyn Query<TestCodec>> = BooleanQuery::build(
+            vec![Box::new(phrase)],
+            vec![Box::new(TermQuery::new(term("dog"), 2.0, None))],
+            vec![],
+        )
+        .unwrap();
+        let field_query = FieldQuery::new(
+            query.as_ref(),
+            None::<&dyn IndexReader<Codec = TestCodec>>,
+            phrase_highlight,
+            true,
+        )
+        .unwrap();
+
+        // the stack pops the first term last
+        let mut stack = FieldTermStack {
+            field_name: "body".into(),
+            term_list: vec![
+                TermInfo::new("dog".into(), 28, 31, 6, 1.0),
+                TermInfo::new("fox".into(), 16, 19, 3, 1.0),
+                TermInfo::new("quick".into(), 4, 9, 1, 1.0),
+            ],
+        };
+        FieldPhraseList::new(&mut stack, &field_query, 256).phrase_list
+    }
+
+    #[test]
+    fn test_phrase_highlight() {
+        let phrases = phrase_list(true);
+        assert_eq!(phrases.len(), 2);
+        assert_eq!(
+            phrases[0].terms_offsets,
+            vec![Toffs::new(4, 9), Toffs::new(16, 19)]
+        );
+        assert_eq!(phrases[0].seqnum, 0);
+        assert_eq!(phrases[1].terms_offsets, vec![Toffs::new(28, 31)]);
+        assert_eq!(phrases[1].seqnum, 1);
+        assert!((phrases[1].boost - 2.0).abs() < EPSILON);
+
+        // the terms of the phrase are highlighted on their own
+        let terms = phrase_list(false);
+        assert_eq!(terms.len(), 3);
+        assert_eq!(terms[0].terms_offsets, vec![Toffs::new(4, 9)]);
+    }
+
+    #[test]
+    fn test_sentence_boundary_scanner() {
+        let buffer = "First one. Second sentence here. Third";
+        let scanner = SentenceBoundaryScanner::new(None);
+        assert_eq!(scanner.find_start_offset(buffer, 20), 11);
+        assert_eq!(scanner.find_end_offset(buffer, 20), 32);
+        assert_eq!(scanner.find_start_offset(buffer, 5), 0);
+
+        let scanner = SentenceBoundaryScanner::new(Some(3));
+        assert_eq!(scanner.find_start_offset(buffer, 20), 20);
+        assert_eq!(scanner.find_end_offset(buffer, 20), 20);
+    }
+}
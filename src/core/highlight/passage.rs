@@ -0,0 +1,424 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::highlight::{DefaultEncoder, Encoder};
+
+use std::cmp::Ordering;
+
+/// The separator of the values of a multi-valued field in the highlighted
+/// content, a paragraph separator so that no passage spans two values.
+pub const MULTI_VALUE_SEPARATOR: char = '\u{2029}';
+
+/// A match of a query term in the highlighted content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassageMatch {
+    /// The byte offset of the start of the match in the content.
+    pub start_offset: usize,
+    /// The byte offset of the end of the match in the content, exclusive.
+    pub end_offset: usize,
+    /// The index of the matched term in the terms of the query.
+    pub term: usize,
+}
+
+/// A snippet of the highlighted content, usually a sentence, with the query
+/// terms it contains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passage {
+    /// The byte offset of the start of the passage in the content.
+    pub start_offset: usize,
+    /// The byte offset of the end of the passage in the content, exclusive.
+    pub end_offset: usize,
+    pub score: f32,
+    /// The matches in the passage, ordered by start offset.
+    pub matches: Vec<PassageMatch>,
+}
+
+/// Ranks passages, see `BM25PassageScorer`.
+///
+/// A passage scores `norm(start) * sum(weight(t) * tf(freq(t), length))`
+/// over the terms `t` it contains, all lengths and offsets in chars.
+pub trait PassageScorer {
+    /// The weight of a term occurring `term_freq` times in the whole content.
+    fn weight(&self, content_length: usize, term_freq: usize) -> f32;
+
+    /// The score of a term occurring `freq` times in a passage.
+    fn tf(&self, freq: usize, passage_length: usize) -> f32;
+
+    /// The normalization of a passage starting at `passage_start`, which
+    /// favors the passages near the start of the content.
+    fn norm(&self, passage_start: usize) -> f32;
+}
+
+/// Scores passages with BM25, the content being split into pseudo documents
+/// of `pivot` chars for the term weights.
+#[derive(Debug, Clone, Copy)]
+pub struct BM25PassageScorer {
+    k1: f32,
+    b: f32,
+    pivot: f32,
+}
+
+impl BM25PassageScorer {
+    pub fn new(k1: f32, b: f32, pivot: f32) -> BM25PassageScorer {
+        BM25PassageScorer { k1, b, pivot }
+    }
+}
+
+impl Default for BM25PassageScorer {
+    fn default() -> Self {
+        // 87 is the average english sentence length
+        BM25PassageScorer::new(1.2, 0.75, 87.0)
+    }
+}
+
+impl PassageScorer for BM25PassageScorer {
+    fn weight(&self, content_length: usize, term_freq: usize) -> f32 {
+        let num_docs = 1.0 + content_length as f32 / self.pivot;
+        let doc_freq = 1.0 + term_freq as f32;
+        (self.k1 + 1.0) * (1.0 + (num_docs + 0.5) / (doc_freq + 0.5)).ln()
+    }
+
+    fn tf(&self, freq: usize, passage_length: usize) -> f32 {
+        let norm = self.k1 * ((1.0 - self.b) + self.b * (passage_length as f32 / self.pivot));
+        freq as f32 / (freq as f32 + norm)
+    }
+
+    fn norm(&self, passage_start: usize) -> f32 {
+        1.0 + 1.0 / (self.pivot + passage_start as f32).ln()
+    }
+}
+
+/// Renders the top passages of a content into a snippet.
+pub trait PassageFormatter {
+    /// Formats `passages`, ordered by start offset, of `content`.
+    fn format(&self, passages: &[Passage], content: &str) -> String;
+}
+
+/// Wraps the matches in tags and joins the passages with an ellipsis.
+pub struct DefaultPassageFormatter {
+    pre_tag: String,
+    post_tag: String,
+    ellipsis: String,
+    encoder: Box<dyn Encoder>,
+}
+
+impl DefaultPassageFormatter {
+    pub fn new(
+        pre_tag: &str,
+        post_tag: &str,
+        ellipsis: &str,
+        encoder: Box<dyn Encoder>,
+    ) -> DefaultPassageFormatter {
+        DefaultPassageFormatter {
+            pre_tag: pre_tag.to_string(),
+            post_tag: post_tag.to_string(),
+            ellipsis: ellipsis.to_string(),
+            encoder,
+        }
+    }
+}
+
+impl Default for DefaultPassageFormatter {
+    fn default() -> Self {
+        DefaultPassageFormatter::new("<b>", "</b>", "... ", Box::new(DefaultEncoder))
+    }
+}
+
+impl PassageFormatter for DefaultPassageFormatter {
+    fn format(&self, passages: &[Passage], content: &str) -> String {
+        let mut snippet = String::new();
+        let mut pos = 0;
+        for passage in passages {
+            // don't add an ellipsis if the passage follows the previous one
+            if passage.start_offset > pos && pos > 0 {
+                snippet.push_str(&self.ellipsis);
+            }
+            pos = passage.start_offset;
+            let mut i = 0;
+            while i < passage.matches.len() {
+                let start = passage.matches[i].start_offset;
+                let mut end = passage.matches[i].end_offset;
+                // merge the overlapping matches, e.g. of synonyms
+                while i + 1 < passage.matches.len() && passage.matches[i + 1].start_offset < end {
+                    i += 1;
+                    end = end.max(passage.matches[i].end_offset);
+                }
+                i += 1;
+                let start = start.max(pos);
+                if end > start {
+                    snippet.push_str(&self.encoder.encode_text(&content[pos..start]));
+                    snippet.push_str(&self.pre_tag);
+                    snippet.push_str(&self.encoder.encode_text(&content[start..end]));
+                    snippet.push_str(&self.post_tag);
+                    pos = end;
+                }
+            }
+            let end = passage.end_offset.max(pos);
+            snippet.push_str(&self.encoder.encode_text(&content[pos..end]));
+            pos = end;
+        }
+        snippet
+    }
+}
+
+/// A sentence of the content, in byte offsets and its start in chars.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sentence {
+    start: usize,
+    end: usize,
+    char_start: usize,
+}
+
+/// Splits `content` into sentences, which end after a terminal punctuation
+/// followed by whitespace, or after a line or value separator. The trailing
+/// whitespace belongs to the sentence.
+fn split_sentences(content: &str) -> Vec<Sentence> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut char_start = 0;
+    let mut chars = 0;
+    let mut terminated = false;
+    for (i, c) in content.char_indices() {
+        if terminated && !c.is_whitespace() {
+            sentences.push(Sentence {
+                start,
+                end: i,
+                char_start,
+            });
+            start = i;
+            char_start = chars;
+            terminated = false;
+        }
+        chars += 1;
+        match c {
+            '\n' | MULTI_VALUE_SEPARATOR | '。' | '！' | '？' => terminated = true,
+            '.' | '!' | '?' => {
+                terminated = content[i + 1..]
+                    .chars()
+                    .next()
+                    .map_or(true, char::is_whitespace)
+            }
+            _ => {}
+        }
+    }
+    if start < content.len() {
+        sentences.push(Sentence {
+            start,
+            end: content.len(),
+            char_start,
+        });
+    }
+    sentences
+}
+
+/// Trims the trailing value separator of a passage.
+fn trim_end(content: &str, start: usize, end: usize) -> usize {
+    start
+        + content[start..end]
+            .trim_end_matches(MULTI_VALUE_SEPARATOR)
+            .len()
+}
+
+/// Picks the `max_passages` best scored sentences containing `matches`,
+/// which are ordered by start offset. `term_freqs` are the numbers of
+/// matches of each term in the content. The passages are returned ordered
+/// by start offset.
+pub(crate) fn top_passages(
+    content: &str,
+    matches: &[PassageMatch],
+    term_freqs: &[usize],
+    scorer: &dyn PassageScorer,
+    max_passages: usize,
+) -> Vec<Passage> {
+    let content_length = content.chars().count();
+    let weights: Vec<f32> = term_freqs
+        .iter()
+        .map(|&freq| scorer.weight(content_length, freq))
+        .collect();
+
+    let mut passages = vec![];
+    let mut upto = 0;
+    for sentence in split_sentences(content) {
+        if upto >= matches.len() {
+            break;
+        }
+        let mut passage = Passage {
+            start_offset: sentence.start,
+            end_offset: trim_end(content, sentence.start, sentence.end),
+            score: 0.0,
+            matches: vec![],
+        };
+        while upto < matches.len() && matches[upto].start_offset < sentence.end {
+            // the matches before the sentence overlapped the previous one
+            if matches[upto].start_offset >= sentence.start {
+                let m = matches[upto].clone();
+                passage.end_offset = passage.end_offset.max(m.end_offset);
+                passage.matches.push(m);
+            }
+            upto += 1;
+        }
+        if passage.matches.is_empty() {
+            continue;
+        }
+
+        let length = content[passage.start_offset..passage.end_offset]
+            .chars()
+            .count();
+        let mut freqs = vec![0; weights.len()];
+        for m in &passage.matches {
+            freqs[m.term] += 1;
+        }
+        let score: f32 = freqs
+            .iter()
+            .zip(&weights)
+            .filter(|&(&freq, _)| freq > 0)
+            .map(|(&freq, &weight)| weight * scorer.tf(freq, length))
+            .sum();
+        passage.score = score * scorer.norm(sentence.char_start);
+        passages.push(passage);
+    }
+
+    passages.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then(a.start_offset.cmp(&b.start_offset))
+    });
+    passages.truncate(max_passages);
+    passages.sort_by_key(|p| p.start_offset);
+    passages
+}
+
+/// The first `max_passages` sentences of `content`, used as a summary when
+/// no query term matches.
+pub(crate) fn summary_passages(content: &str, max_passages: usize) -> Vec<Passage> {
+    split_sentences(content)
+        .into_iter()
+        .map(|sentence| Passage {
+            start_offset: sentence.start,
+            end_offset: trim_end(content, sentence.start, sentence.end),
+            score: 0.0,
+            matches: vec![],
+        })
+        .filter(|passage| passage.end_offset > passage.start_offset)
+        .take(max_passages)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::highlight::SimpleHtmlEncoder;
+
+    fn matches(content: &str, terms: &[&str]) -> (Vec<PassageMatch>, Vec<usize>) {
+        let mut matches = vec![];
+        let mut freqs = vec![0; terms.len()];
+        for (term, word) in terms.iter().enumerate() {
+            for (start, _) in content.match_indices(word) {
+                matches.push(PassageMatch {
+                    start_offset: start,
+                    end_offset: start + word.len(),
+                    term,
+                });
+                freqs[term] += 1;
+            }
+        }
+        matches.sort_by_key(|m| m.start_offset);
+        (matches, freqs)
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        let content = "Hello world. Version 1.5 is out!\nNew line。中文";
+        let sentences: Vec<&str> = split_sentences(content)
+            .iter()
+            .map(|s| &content[s.start..s.end])
+            .collect();
+        assert_eq!(
+            sentences,
+            vec![
+                "Hello world. ",
+                "Version 1.5 is out!\n",
+                "New line。",
+                "中文"
+            ]
+        );
+        assert_eq!(split_sentences(content)[3].char_start, 42);
+    }
+
+    #[test]
+    fn test_top_passages() {
+        let content = "The dog barks. A cat sleeps. The cat and the dog play. Birds sing.";
+        let (matches, freqs) = matches(content, &["dog", "cat"]);
+        let scorer = BM25PassageScorer::default();
+
+        let passages = top_passages(content, &matches, &freqs, &scorer, 1);
+        assert_eq!(passages.len(), 1);
+        assert_eq!(
+            &content[passages[0].start_offset..passages[0].end_offset],
+            "The cat and the dog play. "
+        );
+
+        let passages = top_passages(content, &matches, &freqs, &scorer, 5);
+        assert_eq!(passages.len(), 3);
+        assert!(passages[0].start_offset < passages[1].start_offset);
+        assert!(passages[2].score > passages[0].score);
+
+        let formatter = DefaultPassageFormatter::default();
+        assert_eq!(
+            formatter.format(&passages, content),
+            "The <b>dog</b> barks. A <b>cat</b> sleeps. The <b>cat</b> and the <b>dog</b> play. "
+        );
+        let passages = [passages[0].clone(), passages[2].clone()];
+        assert_eq!(
+            formatter.format(&passages, content),
+            "The <b>dog</b> barks. ... The <b>cat</b> and the <b>dog</b> play. "
+        );
+    }
+
+    #[test]
+    fn test_format_overlapping_matches() {
+        let content = "<wi-fi> network";
+        let passage = Passage {
+            start_offset: 0,
+            end_offset: content.len(),
+            score: 1.0,
+            matches: vec![
+                PassageMatch {
+                    start_offset: 1,
+                    end_offset: 6,
+                    term: 0,
+                },
+                PassageMatch {
+                    start_offset: 4,
+                    end_offset: 6,
+                    term: 1,
+                },
+            ],
+        };
+        let formatter =
+            DefaultPassageFormatter::new("[", "]", "...", Box::new(SimpleHtmlEncoder::default()));
+        assert_eq!(
+            formatter.format(&[passage], content),
+            "&lt;[wi-fi]&gt; network"
+        );
+
+        assert_eq!(
+            summary_passages("First one. Second one. Third.", 2)
+                .iter()
+                .map(|p| p.end_offset)
+                .collect::<Vec<_>>(),
+            vec![11, 23]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::Analyzer;
+use core::codec::{Codec, PostingIterator, PostingIteratorFlags, TermIterator, Terms};
+use core::doc::{Fieldable, IndexOptions, Term};
+use core::highlight::passage::{summary_passages, top_passages};
+use core::highlight::{
+    BM25PassageScorer, DefaultPassageFormatter, PassageFormatter, PassageMatch, PassageScorer,
+    MULTI_VALUE_SEPARATOR,
+};
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::search::query::Query;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::Result;
+
+/// The number of chars of a content that are highlighted by default, the
+/// rest being ignored.
+pub const DEFAULT_MAX_LENGTH: usize = 10_000;
+
+/// Where the `UnifiedHighlighter` reads the offsets of the matches from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetSource {
+    /// The postings of the fields indexed with
+    /// `IndexOptions::DocsAndFreqsAndPositionsAndOffsets`.
+    Postings,
+    /// The term vectors of the fields storing them with offsets.
+    TermVectors,
+    /// The analysis of the stored content again, with the analyzer of the
+    /// highlighter.
+    Analysis,
+    /// No offsets are needed, the query having no term in the field.
+    NoneNeeded,
+}
+
+/// A (start, end, term) match in char offsets.
+type CharMatch = (usize, usize, usize);
+
+/// Highlights the stored content of a field, picking the sentences which
+/// best match the terms of a query.
+///
+/// The offsets of the matches are read from the postings, the term vectors
+/// or by analyzing the content again, whichever is available first, see
+/// `offset_source`. The passages are ranked by a `PassageScorer` and
+/// rendered by a `PassageFormatter`.
+///
+/// ```ignore
+/// let mut highlighter = UnifiedHighlighter::new();
+/// highlighter.set_analyzer(Box::new(StandardAnalyzer::default()));
+/// let snippets = highlighter.highlight("body", &query, searcher.reader(), &docs, 2)?;
+/// ```
+pub struct UnifiedHighlighter {
+    analyzer: Option<Box<dyn Analyzer>>,
+    offset_source: Option<OffsetSource>,
+    max_length: usize,
+    default_summary: bool,
+    scorer: Box<dyn PassageScorer>,
+    formatter: Box<dyn PassageFormatter>,
+}
+
+impl Default for UnifiedHighlighter {
+    fn default() -> Self {
+        UnifiedHighlighter::new()
+    }
+}
+
+impl UnifiedHighlighter {
+    pub fn new() -> UnifiedHighlighter {
+        UnifiedHighlighter {
+            analyzer: None,
+            offset_source: None,
+            max_length: DEFAULT_MAX_LENGTH,
+            default_summary: true,
+            scorer: Box::new(BM25PassageScorer::default()),
+            formatter: Box::new(DefaultPassageFormatter::default()),
+        }
+    }
+
+    /// Sets the analyzer used for the fields without offsets in the index,
+    /// which must be the one the fields were indexed with.
+    pub fn set_analyzer(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.analyzer = Some(analyzer);
+    }
+
+    /// Forces the source of the offsets instead of picking it per field.
+    pub fn set_offset_source(&mut self, offset_source: OffsetSource) {
+        self.offset_source = Some(offset_source);
+    }
+
+    /// Sets the number of chars of the content which are highlighted.
+    pub fn set_max_length(&mut self, max_length: usize) {
+        self.max_length = max_length;
+    }
+
+    /// If true, the first sentences of a content without any match are
+    /// returned, otherwise no snippet is.
+    pub fn set_default_summary(&mut self, default_summary: bool) {
+        self.default_summary = default_summary;
+    }
+
+    pub fn set_scorer(&mut self, scorer: Box<dyn PassageScorer>) {
+        self.scorer = scorer;
+    }
+
+    pub fn set_formatter(&mut self, formatter: Box<dyn PassageFormatter>) {
+        self.formatter = formatter;
+    }
+
+    /// The source of the offsets of `field` in the segment of `ctx`.
+    pub fn offset_source<C: Codec>(
+        &self,
+        ctx: &LeafReaderContext<'_, C>,
+        field: &str,
+    ) -> OffsetSource {
+        if let Some(offset_source) = self.offset_source {
+            return offset_source;
+        }
+        match ctx.reader.field_infos().field_info_by_name(field) {
+            Some(info)
+                if info.index_options >= IndexOptions::DocsAndFreqsAndPositionsAndOffsets =>
+            {
+                OffsetSource::Postings
+            }
+            Some(info) if info.has_store_term_vector => OffsetSource::TermVectors,
+            _ if self.analyzer.is_some() => OffsetSource::Analysis,
+            _ => OffsetSource::NoneNeeded,
+        }
+    }
+
+    /// Highlights `field` of each of `docs`, returning at most
+    /// `max_passages` passages per doc, or `None` for the docs without
+    /// content.
+    pub fn highlight<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
+        &self,
+        field: &str,
+        query: &dyn Query<C>,
+        reader: &IR,
+        docs: &[DocId],
+        max_passages: usize,
+    ) -> Result<Vec<Option<String>>> {
+        let terms = Self::query_terms(field, query);
+        docs.iter()
+            .map(|&doc_id| self.highlight_terms(field, &terms, reader, doc_id, max_passages))
+            .collect()
+    }
+
+    /// Highlights `field` of the doc `doc_id`.
+    pub fn highlight_doc<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
+        &self,
+        field: &str,
+        query: &dyn Query<C>,
+        reader: &IR,
+        doc_id: DocId,
+        max_passages: usize,
+    ) -> Result<Option<String>> {
+        let terms = Self::query_terms(field, query);
+        self.highlight_terms(field, &terms, reader, doc_id, max_passages)
+    }
+
+    fn query_terms<C: Codec>(field: &str, query: &dyn Query<C>) -> Vec<Vec<u8>> {
+        let mut terms: Vec<Vec<u8>> = vec![];
+        for term_query in query.extract_terms() {
+            let term = term_query.term();
+            if term.field() == field && !terms.contains(&term.bytes) {
+                terms.push(term.bytes.clone());
+            }
+        }
+        terms
+    }
+
+    fn highlight_terms<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
+        &self,
+        field: &str,
+        terms: &[Vec<u8>],
+        reader: &IR,
+        doc_id: DocId,
+        max_passages: usize,
+    ) -> Result<Option<String>> {
+        let content = self.load_content(reader, doc_id, field)?;
+        if content.is_empty() {
+            return Ok(None);
+        }
+        let ctx = reader.leaf_reader_for_doc(doc_id);
+        let mut char_matches = vec![];
+        if !terms.is_empty() {
+            let leaf_doc = doc_id - ctx.doc_base;
+            match self.offset_source(&ctx, field) {
+                OffsetSource::Postings => {
+                    Self::postings_matches(&ctx, leaf_doc, field, terms, &mut char_matches)?
+                }
+                OffsetSource::TermVectors => {
+                    Self::term_vector_matches(&ctx, leaf_doc, field, terms, &mut char_matches)?
+                }
+                OffsetSource::Analysis => {
+                    self.analysis_matches(field, terms, &content, &mut char_matches)?
+                }
+                OffsetSource::NoneNeeded => {}
+            }
+        }
+
+        // the offsets are in chars, the passages in bytes
+        let mut byte_offsets: Vec<usize> = content.char_indices().map(|(i, _)| i).collect();
+        byte_offsets.push(content.len());
+        let num_chars = byte_offsets.len() - 1;
+        char_matches.retain(|&(start, end, _)| start < end && end <= num_chars);
+        char_matches.sort();
+
+        let mut term_freqs = vec![0; terms.len()];
+        let matches: Vec<PassageMatch> = char_matches
+            .into_iter()
+            .map(|(start, end, term)| {
+                term_freqs[term] += 1;
+                PassageMatch {
+                    start_offset: byte_offsets[start],
+                    end_offset: byte_offsets[end],
+                    term,
+                }
+            })
+            .collect();
+
+        let passages = if matches.is_empty() {
+            if !self.default_summary {
+                return Ok(None);
+            }
+            summary_passages(&content, max_passages)
+        } else {
+            top_passages(
+                &content,
+                &matches,
+                &term_freqs,
+                self.scorer.as_ref(),
+                max_passages,
+            )
+        };
+        Ok(Some(self.formatter.format(&passages, &content)))
+    }
+
+    /// Loads the stored values of `field`, separated by
+    /// `MULTI_VALUE_SEPARATOR` and truncated to the max length.
+    fn load_content<IR: IndexReader + ?Sized>(
+        &self,
+        reader: &IR,
+        doc_id: DocId,
+        field: &str,
+    ) -> Result<String> {
+        let doc = reader.document(doc_id, &[field.to_string()])?;
+        let mut content = String::new();
+        let mut first = true;
+        for stored in &doc.fields {
+            if stored.field.name() != field {
+                continue;
+            }
+            if let Some(text) = stored.field.field_data().and_then(|v| v.get_string()) {
+                if !first {
+                    content.push(MULTI_VALUE_SEPARATOR);
+                }
+                content.push_str(text);
+                first = false;
+            }
+        }
+        if let Some((end, _)) = content.char_indices().nth(self.max_length) {
+            content.truncate(end);
+        }
+        Ok(content)
+    }
+
+    fn add_matches(
+        postings: &mut impl PostingIterator,
+        term: usize,
+        matches: &mut Vec<CharMatch>,
+    ) -> Result<()> {
+        for _ in 0..postings.freq()? {
+            postings.next_position()?;
+            let start = postings.start_offset()?;
+            let end = postings.end_offset()?;
+            // offsets weren't indexed
+            if start < 0 {
+                break;
+            }
+            matches.push((start as usize, end as usize, term));
+        }
+        Ok(())
+    }
+
+    fn postings_matches<C: Codec>(
+        ctx: &LeafReaderContext<'_, C>,
+        leaf_doc: DocId,
+        field: &str,
+        terms: &[Vec<u8>],
+        matches: &mut Vec<CharMatch>,
+    ) -> Result<()> {
+        for (i, bytes) in terms.iter().enumerate() {
+            let term = Term::new(field.to_string(), bytes.clone());
+            let flags = i32::from(PostingIteratorFlags::OFFSETS);
+            if let Some(mut postings) = ctx.reader.postings(&term, flags)? {
+                if postings.advance(leaf_doc)? == leaf_doc {
+                    Self::add_matches(&mut postings, i, matches)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn term_vector_matches<C: Codec>(
+        ctx: &LeafReaderContext<'_, C>,
+        leaf_doc: DocId,
+        field: &str,
+        terms: &[Vec<u8>],
+        matches: &mut Vec<CharMatch>,
+    ) -> Result<()> {
+        if let Some(vector) = ctx.reader.get_term_vector(leaf_doc, field)? {
+            let mut terms_iter = vector.iterator()?;
+            for (i, term) in terms.iter().enumerate() {
+                if terms_iter.seek_exact(term)? {
+                    let mut postings =
+                        terms_iter.postings_with_flags(PostingIteratorFlags::OFFSETS)?;
+                    // the term vector of a doc is a single doc index
+                    postings.next()?;
+                    Self::add_matches(&mut postings, i, matches)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn analysis_matches(
+        &self,
+        field: &str,
+        terms: &[Vec<u8>],
+        content: &str,
+        matches: &mut Vec<CharMatch>,
+    ) -> Result<()> {
+        let analyzer = match self.analyzer {
+            Some(ref analyzer) => analyzer,
+            None => return Ok(()),
+        };
+        let mut base = 0;
+        for value in content.split(MULTI_VALUE_SEPARATOR) {
+            let mut stream = analyzer.token_stream_from_str(field, value)?;
+            stream.reset()?;
+            while stream.increment_token()? {
+                let term = stream.term_bytes_attribute().get_bytes_ref();
+                if let Some(i) = terms.iter().position(|t| t.as_slice() == term.bytes()) {
+                    let offsets = stream.offset_attribute();
+                    matches.push((
+                        base + offsets.start_offset(),
+                        base + offsets.end_offset(),
+                        i,
+                    ));
+                }
+            }
+            stream.end()?;
+            base += value.chars().count() + 1;
+        }
+        Ok(())
+    }
+}
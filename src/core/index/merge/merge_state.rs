@@ -22,6 +22,7 @@ use core::codec::postings::FieldsProducer;
 use core::codec::segment_infos::SegmentInfo;
 use core::codec::stored_fields::StoredFieldsReader;
 use core::codec::term_vectors::TermVectorsReader;
+use core::codec::vectors::VectorsReaderRef;
 use core::codec::{
     Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
     CodecTVFields, CodecTVReader,
@@ -79,6 +80,7 @@ pub struct MergeState<D: Directory + 'static, C: Codec> {
     pub live_docs: Vec<BitsRef>,
     pub fields_producers: Vec<MergeFieldsProducer<CodecFieldsProducer<C>>>,
     pub points_readers: Vec<Option<MergePointValuesEnum<Arc<CodecPointsReader<C>>>>>,
+    pub vectors_readers: Vec<Option<VectorsReaderRef>>,
     pub max_docs: Vec<i32>,
     /// Indicates if the index needs to be sorted
     pub needs_index_sort: bool,
@@ -114,6 +116,7 @@ impl<D: Directory + 'static, C: Codec> MergeState<D, C> {
         let mut term_vectors_readers = Vec::with_capacity(num_readers);
         let mut doc_values_producers = Vec::with_capacity(num_readers);
         let mut points_readers = Vec::with_capacity(num_readers);
+        let mut vectors_readers = Vec::with_capacity(num_readers);
         let mut fields_infos = Vec::with_capacity(num_readers);
         let mut live_docs = Vec::with_capacity(num_readers);
 
@@ -134,6 +137,7 @@ impl<D: Directory + 'static, C: Codec> MergeState<D, C> {
             term_vectors_readers.push(reader.term_vectors_reader()?);
             fields_producers.push(reader.postings_reader()?);
             points_readers.push(reader.point_values());
+            vectors_readers.push(reader.vectors_reader());
             num_docs += reader.num_docs();
         }
         // TODO: hack logic
@@ -154,6 +158,7 @@ impl<D: Directory + 'static, C: Codec> MergeState<D, C> {
             live_docs,
             fields_producers,
             points_readers,
+            vectors_readers,
             max_docs,
             needs_index_sort,
         })
@@ -435,6 +440,13 @@ impl<D: Directory + 'static, C: Codec> LeafReader for ReaderWrapperEnum<D, C> {
         }
     }
 
+    fn vectors_reader(&self) -> Option<VectorsReaderRef> {
+        match self {
+            ReaderWrapperEnum::Segment(s) => s.vectors_reader(),
+            ReaderWrapperEnum::SortedSegment(s) => s.vectors_reader(),
+        }
+    }
+
     /// Expert: Returns a key for this IndexReader, so CachingWrapperFilter can find
     // it again.
     // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
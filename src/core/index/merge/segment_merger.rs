@@ -19,6 +19,7 @@ use core::codec::postings::{FieldsConsumer, PostingsFormat};
 use core::codec::segment_infos::{SegmentInfo, SegmentWriteState};
 use core::codec::stored_fields::{StoredFieldsFormat, StoredFieldsWriter};
 use core::codec::term_vectors::{TermVectorsFormat, TermVectorsWriter};
+use core::codec::vectors::VectorsFormat;
 use core::codec::Codec;
 use core::index::merge::MergeState;
 use core::index::reader::SegmentReader;
@@ -120,6 +121,15 @@ where
         {
             self.merge_points(&segment_write_state)?;
         }
+        if self
+            .merge_state
+            .merge_field_infos
+            .as_ref()
+            .unwrap()
+            .has_vector_values
+        {
+            self.merge_vector_values(&segment_write_state)?;
+        }
 
         if self
             .merge_state
@@ -175,6 +185,17 @@ where
         writer.merge(&self.merge_state)
     }
 
+    fn merge_vector_values(
+        &mut self,
+        segment_write_state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<()> {
+        let mut writer = self
+            .codec
+            .vectors_format()
+            .fields_writer(segment_write_state)?;
+        writer.merge(&self.merge_state)
+    }
+
     fn merge_norms(&mut self, segment_write_state: &SegmentWriteState<D, DW, C>) -> Result<()> {
         let mut consumer = self
             .codec
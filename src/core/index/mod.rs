@@ -38,6 +38,7 @@ pub mod tests {
     };
     use core::codec::field_infos::{FieldInfo, FieldInfos};
     use core::codec::tests::TestCodec;
+    use core::codec::vectors::VectorsReaderRef;
     use core::codec::*;
     use core::doc::{DocValuesType, Document, IndexOptions, StoredFieldVisitor};
     use core::index::reader::*;
@@ -233,6 +234,10 @@ pub mod tests {
             unimplemented!()
         }
 
+        fn vectors_reader(&self) -> Option<VectorsReaderRef> {
+            None
+        }
+
         /// Expert: Returns a key for this IndexReader, so CachingWrapperFilter can find
         // it again.
         // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
@@ -21,6 +21,7 @@ use core::codec::points::PointValues;
 use core::codec::postings::FieldsProducer;
 use core::codec::stored_fields::StoredFieldsReader;
 use core::codec::term_vectors::TermVectorsReader;
+use core::codec::vectors::VectorsReaderRef;
 use core::codec::{
     Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
     CodecTVFields, CodecTVReader,
@@ -119,6 +120,21 @@ pub trait LeafReader {
 
     fn term_vector(&self, leaf_doc_id: DocId) -> Result<Option<Self::TVFields>>;
 
+    /// The term vector of `field` in the doc `leaf_doc_id`, a single doc index
+    /// whose postings give the positions, offsets and payloads stored with the
+    /// vector, see `Terms::has_positions`. None if the field of the doc has no
+    /// term vector.
+    fn get_term_vector(
+        &self,
+        leaf_doc_id: DocId,
+        field: &str,
+    ) -> Result<Option<<Self::TVFields as Fields>::Terms>> {
+        match self.term_vector(leaf_doc_id)? {
+            Some(vectors) => vectors.terms(field),
+            None => Ok(None),
+        }
+    }
+
     fn document(&self, doc_id: DocId, visitor: &mut dyn StoredFieldVisitor) -> Result<()>;
 
     fn live_docs(&self) -> BitsRef;
@@ -152,6 +168,10 @@ pub trait LeafReader {
     /// spatial searches, or None if there are no point fields.
     fn point_values(&self) -> Option<Self::PointsReader>;
 
+    /// Returns the `VectorsReader` used for nearest neighbor
+    /// searches, or None if there are no vector fields.
+    fn vectors_reader(&self) -> Option<VectorsReaderRef>;
+
     /// Expert: Returns a key for this IndexReader, so CachingWrapperFilter can find
     // it again.
     // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
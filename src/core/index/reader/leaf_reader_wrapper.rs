@@ -19,13 +19,14 @@ use core::codec::points::{IntersectVisitor, PointValues, Relation};
 use core::codec::postings::FieldsProducer;
 use core::codec::stored_fields::StoredFieldsReader;
 use core::codec::term_vectors::TermVectorsReader;
+use core::codec::vectors::{VectorValues, VectorsReader, VectorsReaderRef};
 use core::codec::*;
 use core::codec::{Fields, SeekStatus, TermIterator, Terms};
 use core::codec::{PackedLongDocMap, SorterDocMap};
 use core::codec::{PostingIterator, PostingIteratorFlags};
-use core::doc::{IndexOptions, StoredFieldVisitor};
+use core::doc::{IndexOptions, StoredFieldVisitor, VectorSimilarity};
 use core::index::reader::{LeafReader, SegmentReader};
-use core::search::sort_field::Sort;
+use core::search::sort_field::{ScoreDoc, Sort};
 use core::search::{DocIterator, Payload, NO_MORE_DOCS};
 use core::store::directory::Directory;
 use core::store::io::{DataInput, IndexInput, IndexOutput, RAMOutputStream};
@@ -37,6 +38,7 @@ use error::{ErrorKind::IllegalArgument, Result};
 
 use core::util::FixedBitSet;
 use std::any::Any;
+use std::borrow::Cow;
 use std::io::Read;
 use std::mem;
 use std::ops::DerefMut;
@@ -232,6 +234,10 @@ impl<D: Directory + 'static, C: Codec> LeafReader for MergeReaderWrapper<D, C> {
         self.reader.point_values()
     }
 
+    fn vectors_reader(&self) -> Option<VectorsReaderRef> {
+        self.reader.vectors_reader()
+    }
+
     fn core_cache_key(&self) -> &str {
         self.reader.core_cache_key()
     }
@@ -409,6 +415,16 @@ impl<T: LeafReader + 'static> LeafReader for SortingLeafReader<T> {
         }
     }
 
+    fn vectors_reader(&self) -> Option<VectorsReaderRef> {
+        match self.reader.vectors_reader() {
+            Some(v) => Some(Arc::new(SortingVectorsReader::new(
+                v,
+                Arc::clone(&self.doc_map),
+            ))),
+            None => None,
+        }
+    }
+
     /// Expert: Returns a key for this IndexReader, so CachingWrapperFilter can find
     // it again.
     // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
@@ -1281,6 +1297,101 @@ impl<'a, IV: IntersectVisitor> IntersectVisitor for SortingIntersectVisitor<'a,
     }
 }
 
+pub struct SortingVectorsReader {
+    reader: VectorsReaderRef,
+    doc_map: Arc<PackedLongDocMap>,
+}
+
+impl SortingVectorsReader {
+    fn new(reader: VectorsReaderRef, doc_map: Arc<PackedLongDocMap>) -> Self {
+        SortingVectorsReader { reader, doc_map }
+    }
+}
+
+impl VectorsReader for SortingVectorsReader {
+    fn check_integrity(&self) -> Result<()> {
+        self.reader.check_integrity()
+    }
+
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues + '_>>> {
+        match self.reader.vector_values(field)? {
+            Some(values) => Ok(Some(Box::new(SortingVectorValues {
+                values,
+                doc_map: &self.doc_map,
+            }))),
+            None => Ok(None),
+        }
+    }
+
+    fn search(
+        &self,
+        field: &str,
+        target: &[f32],
+        k: usize,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoreDoc>> {
+        let hits = match accept_docs {
+            Some(bits) => {
+                let bits = SortingAcceptBits {
+                    bits,
+                    doc_map: &self.doc_map,
+                };
+                self.reader.search(field, target, k, Some(&bits))?
+            }
+            None => self.reader.search(field, target, k, None)?,
+        };
+        Ok(hits
+            .into_iter()
+            .map(|hit| ScoreDoc::new(self.doc_map.old_to_new(hit.doc), hit.score))
+            .collect())
+    }
+}
+
+// NOTE: the ordinals no longer follow the doc ids order
+struct SortingVectorValues<'a> {
+    values: Box<dyn VectorValues + 'a>,
+    doc_map: &'a PackedLongDocMap,
+}
+
+impl<'a> VectorValues for SortingVectorValues<'a> {
+    fn dimension(&self) -> usize {
+        self.values.dimension()
+    }
+
+    fn size(&self) -> usize {
+        self.values.size()
+    }
+
+    fn similarity(&self) -> VectorSimilarity {
+        self.values.similarity()
+    }
+
+    fn doc(&self, ord: usize) -> DocId {
+        self.doc_map.old_to_new(self.values.doc(ord))
+    }
+
+    fn vector(&self, ord: usize) -> Result<Cow<'_, [f32]>> {
+        self.values.vector(ord)
+    }
+}
+
+// accept docs of the sorted reader viewed from the unsorted one
+struct SortingAcceptBits<'a> {
+    bits: &'a dyn Bits,
+    doc_map: &'a PackedLongDocMap,
+}
+
+impl<'a> Bits for SortingAcceptBits<'a> {
+    fn get(&self, index: usize) -> Result<bool> {
+        self.bits
+            .get(self.doc_map.old_to_new(index as DocId) as usize)
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+}
+
 struct SortingSortedDocValues {
     doc_values: Box<dyn SortedDocValues>,
     doc_map: Arc<PackedLongDocMap>,
@@ -1461,6 +1572,10 @@ impl<T: LeafReader + 'static> LeafReader for SlowCodecReaderWrapper<T> {
         self.reader.point_values()
     }
 
+    fn vectors_reader(&self) -> Option<VectorsReaderRef> {
+        self.reader.vectors_reader()
+    }
+
     /// Expert: Returns a key for this IndexReader, so CachingWrapperFilter can find
     // it again.
     // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
@@ -33,10 +33,11 @@ pub use self::index_lookup::*;
 
 use core::codec::Codec;
 use core::codec::CodecTVFields;
-use core::doc::Document;
+use core::codec::Fields;
+use core::doc::{Document, StoredFieldVisitor};
 use core::util::DocId;
 
-use error::Result;
+use error::{ErrorKind::IllegalArgument, Result};
 
 /// `IndexReader` providing an interface for accessing a point-in-time view of an index.
 ///
@@ -68,7 +69,32 @@ pub trait IndexReader {
     type Codec: Codec;
     fn leaves(&self) -> Vec<LeafReaderContext<'_, Self::Codec>>;
     fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<Self::Codec>>>;
+    /// The term vector of `field` in the doc `doc_id`, see
+    /// `LeafReader::get_term_vector`.
+    fn get_term_vector(
+        &self,
+        doc_id: DocId,
+        field: &str,
+    ) -> Result<Option<<CodecTVFields<Self::Codec> as Fields>::Terms>> {
+        match self.term_vector(doc_id)? {
+            Some(vectors) => vectors.terms(field),
+            None => Ok(None),
+        }
+    }
     fn document(&self, doc_id: DocId, fields: &[String]) -> Result<Document>;
+    /// Visits the stored fields of `doc_id`, the visitor choosing which fields
+    /// are read and when the visit stops, see `StoredFieldVisitor`.
+    fn visit_document(&self, doc_id: DocId, visitor: &mut dyn StoredFieldVisitor) -> Result<()> {
+        if doc_id < 0 || doc_id >= self.max_doc() {
+            bail!(IllegalArgument(format!(
+                "doc_id {} invalid: [max_doc={}]",
+                doc_id,
+                self.max_doc()
+            )));
+        }
+        let leaf = self.leaf_reader_for_doc(doc_id);
+        LeafReader::document(leaf.reader, doc_id - leaf.doc_base, visitor)
+    }
     fn max_doc(&self) -> i32;
     fn num_docs(&self) -> i32;
     fn num_deleted_docs(&self) -> i32 {
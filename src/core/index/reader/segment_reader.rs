@@ -35,6 +35,7 @@ use core::codec::postings::PostingsFormat;
 use core::codec::segment_infos::{SegmentCommitInfo, SegmentInfo, SegmentReadState};
 use core::codec::stored_fields::{StoredFieldsFormat, StoredFieldsReader};
 use core::codec::term_vectors::{TermVectorsFormat, TermVectorsReader};
+use core::codec::vectors::{VectorsFormat, VectorsReaderRef};
 use core::codec::{
     Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
     CodecTVFields, CodecTVReader, CompoundFormat, LiveDocsFormat, Lucene50CompoundReader,
@@ -63,6 +64,7 @@ pub struct SegmentCoreReaders<D: Directory, C: Codec> {
     /// in the case of DV updates, SR may hold a newer version.
     pub core_field_infos: Arc<FieldInfos>,
     pub points_reader: Option<Arc<CodecPointsReader<C>>>,
+    pub vectors_reader: Option<VectorsReaderRef>,
     pub core_dropped_listeners: Mutex<Vec<Deferred>>,
     pub core_cache_key: String,
 }
@@ -139,6 +141,13 @@ impl<D: Directory, C: Codec> SegmentCoreReaders<D, C> {
         } else {
             None
         };
+        let vectors_reader = if core_field_infos.has_vector_values {
+            let reader: VectorsReaderRef =
+                Arc::new(codec.vectors_format().fields_reader(&segment_read_state)?);
+            Some(reader)
+        } else {
+            None
+        };
         // TODO process norms_producers/store_fields_reader/term vectors
 
         Ok(SegmentCoreReaders {
@@ -151,6 +160,7 @@ impl<D: Directory, C: Codec> SegmentCoreReaders<D, C> {
             cfs_reader,
             core_field_infos,
             points_reader,
+            vectors_reader,
             core_dropped_listeners: Mutex::new(vec![]),
             core_cache_key: format!("{}@{}", si.name, id2str(&random_id())),
         })
@@ -1120,6 +1130,10 @@ where
         self.core.points_reader.clone()
     }
 
+    fn vectors_reader(&self) -> Option<VectorsReaderRef> {
+        self.core.vectors_reader.clone()
+    }
+
     fn core_cache_key(&self) -> &str {
         // use segment name as unique segment cache key
         &self.core.core_cache_key
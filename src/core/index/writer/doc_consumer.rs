@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::analysis::TokenStream;
+use core::analysis::{TokenStream, DEFAULT_OFFSET_GAP};
 use core::codec::doc_values::*;
 use core::codec::field_infos::{
     FieldInfo, FieldInfosBuilder, FieldInfosFormat, FieldInvertState, FieldNumbersRef,
@@ -22,10 +22,12 @@ use core::codec::points::PointValuesWriter;
 use core::codec::points::{PointsFormat, PointsWriter};
 use core::codec::postings::{
     FreqProxTermsWriter, FreqProxTermsWriterPerField, TermsHash, TermsHashPerField,
+    PER_FIELD_POSTING_FORMAT_KEY,
 };
 use core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
 use core::codec::stored_fields::StoredFieldsConsumer;
 use core::codec::term_vectors::TermVectorsConsumer;
+use core::codec::vectors::{VectorValuesWriter, VectorsFormat};
 use core::codec::Codec;
 use core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions};
 use core::index::merge::MergePolicy;
@@ -34,7 +36,7 @@ use core::store::directory::Directory;
 use core::store::IOContext;
 use core::util::{BytesRef, DocId, VariantValue};
 
-use core::search::similarity::BM25Similarity;
+use core::search::similarity::Similarity;
 
 use error::{
     ErrorKind::{IllegalArgument, UnsupportedOperation},
@@ -66,7 +68,7 @@ pub struct DocConsumer<
     // but it was ~2% slower on Wiki and Geonames with Java
     // but we will use may anyway.
     // TODO, maybe we should use `TermsHashPerField` instead
-    pub field_hash: Vec<PerField<FreqProxTermsWriterPerField<D, C, MS, MP>>>,
+    pub field_hash: Vec<PerField<C, FreqProxTermsWriterPerField<D, C, MS, MP>>>,
     total_field_count: u32,
     next_field_gen: i64,
     inited: bool,
@@ -168,6 +170,40 @@ where
         Ok(())
     }
 
+    /// Writes all buffered vectors and builds their graphs.
+    fn write_vectors<DW: Directory>(
+        &mut self,
+        state: &SegmentWriteState<D, DW, C>,
+        sort_map: Option<&PackedLongDocMap>,
+    ) -> Result<()> {
+        let mut vectors_writer = None;
+        for per_field in &mut self.field_hash {
+            if per_field.vector_values_writer.is_some() {
+                debug_assert!(per_field.field_info().vector_dimension > 0);
+                if vectors_writer.is_none() {
+                    // lazy init
+                    vectors_writer = Some(
+                        state
+                            .segment_info
+                            .codec()
+                            .vectors_format()
+                            .fields_writer(state)?,
+                    );
+                }
+                per_field
+                    .vector_values_writer
+                    .as_mut()
+                    .unwrap()
+                    .flush(sort_map, vectors_writer.as_mut().unwrap())?;
+                per_field.vector_values_writer = None;
+            }
+        }
+        if let Some(ref mut writer) = vectors_writer {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
     /// Writes all buffered doc values (called from {@link #flush}).
     fn write_doc_values<DW: Directory>(
         &mut self,
@@ -271,6 +307,11 @@ where
 
             let idx = self.get_or_add_field(field.name(), field.field_type(), true)?;
             let first = self.field_hash[idx].field_gen != field_gen;
+            if let Some(format) = field.postings_format() {
+                self.field_hash[idx]
+                    .field_info()
+                    .put_attribute(PER_FIELD_POSTING_FORMAT_KEY.to_string(), format.to_string());
+            }
 
             let ptr = self as *mut DocConsumer<D, C, MS, MP>;
             self.field_hash[idx].invert(field, doc_state, first, &mut *ptr)?;
@@ -316,6 +357,13 @@ where
             self.index_point(per_field.unwrap(), field, doc_state)?;
         }
 
+        if field.field_type().vector_dimension > 0 {
+            if per_field.is_none() {
+                per_field = Some(self.get_or_add_field(field.name(), field.field_type(), false)?);
+            }
+            self.index_vector(per_field.unwrap(), field, doc_state)?;
+        }
+
         Ok(field_count)
     }
 
@@ -373,6 +421,11 @@ where
         // Make sure we have a PerField allocated
         if idx == self.field_hash.len() {
             // First time we are seeing this field in this segment
+            let similarity = self
+                .doc_writer()
+                .index_writer_config
+                .similarity()
+                .create(name);
             let mut fi = self.field_infos.get_or_add(name)?;
             // Messy: must set this here because e.g. FreqProxTermsWriterPerField looks at the
             // initial IndexOptions to decide what arrays it must create).  Then, we
@@ -380,7 +433,7 @@ where
             // the index options:
             fi.set_index_options(field_type.index_options);
 
-            let fp = PerField::new(&mut fi, invert, &mut self.terms_hash);
+            let fp = PerField::new(&mut fi, invert, similarity, &mut self.terms_hash);
             self.field_hash.push(fp);
 
             if let Some(fp) = self.field_hash.last_mut() {
@@ -549,6 +602,55 @@ where
             )
     }
 
+    /// Called from process_document to index one field's vector
+    fn index_vector(
+        &mut self,
+        field_idx: usize,
+        field: &impl Fieldable,
+        doc_state: &DocState,
+    ) -> Result<()> {
+        let per_field = &mut self.field_hash[field_idx];
+        let vector_dimension = field.field_type().vector_dimension;
+        let vector_similarity = field.field_type().vector_similarity;
+
+        // Record the vector attributes of this field; this setter will throw
+        // IllegalArgExc if they were already set to something different:
+        if per_field.field_info().vector_dimension == 0 {
+            self.field_infos
+                .global_field_numbers
+                .as_ref()
+                .set_vector_attributes(
+                    per_field.field_info().number,
+                    &per_field.field_info().name,
+                    vector_dimension,
+                    vector_similarity,
+                )?;
+            self.field_infos
+                .get_or_add(&per_field.field_info().name)?
+                .set_vector_attributes(vector_dimension, vector_similarity)?;
+        } else {
+            per_field
+                .field_info_mut()
+                .set_vector_attributes(vector_dimension, vector_similarity)?;
+        }
+
+        let vector = match field.vector_value() {
+            Some(v) => v,
+            None => bail!(IllegalArgument(format!(
+                "field={}: vector value must not be null",
+                field.name()
+            ))),
+        };
+        if per_field.vector_values_writer.is_none() {
+            per_field.vector_values_writer = Some(VectorValuesWriter::new(per_field.field_info()));
+        }
+        per_field
+            .vector_values_writer
+            .as_mut()
+            .unwrap()
+            .add_value(doc_state.doc_id, vector)
+    }
+
     fn get_per_field_index(&mut self, name: &str) -> Option<usize> {
         for (idx, pf) in self.field_hash.iter().enumerate() {
             if pf.field_info().name.as_str() == name {
@@ -673,6 +775,7 @@ where
 
         self.write_doc_values(state, sort_map.as_ref().map(|m| m.as_ref()))?;
         self.write_points(state, sort_map.as_ref().map(|m| m.as_ref()))?;
+        self.write_vectors(state, sort_map.as_ref().map(|m| m.as_ref()))?;
 
         // it's possible all docs hit non-aborting exceptions...
         self.stored_fields_consumer.finish(max_doc)?;
@@ -739,16 +842,18 @@ where
     }
 }
 
-pub struct PerField<T: TermsHashPerField> {
+pub struct PerField<C: Codec, T: TermsHashPerField> {
     name: String,
     field_info: *mut FieldInfo,
-    // similarity: Similarity,
+    similarity: Box<dyn Similarity<C>>,
     invert_state: FieldInvertState,
     pub term_hash_per_field: Option<T>,
     // Non-null if this field ever had doc values in this segment:
     doc_values_writer: Option<DocValuesWriterEnum>,
     // Non-null if this field ever had points in this segment:
     point_values_writer: Option<PointValuesWriter>,
+    // Non-null if this field ever had vectors in this segment:
+    vector_values_writer: Option<VectorValuesWriter>,
     /// We use this to know when a PerField is seen for the
     /// first time in the current document
     field_gen: i64,
@@ -756,10 +861,11 @@ pub struct PerField<T: TermsHashPerField> {
     invert: bool,
 }
 
-impl<T: TermsHashPerField> PerField<T> {
-    fn new<D: Directory, C: Codec, TH: TermsHash<D, C, PerField = T>>(
+impl<C: Codec, T: TermsHashPerField> PerField<C, T> {
+    fn new<D: Directory, TH: TermsHash<D, C, PerField = T>>(
         field_info: &mut FieldInfo,
         invert: bool,
+        similarity: Box<dyn Similarity<C>>,
         terms_hash: &mut TH,
     ) -> Self {
         let term_hash_per_field: Option<T> = None;
@@ -767,10 +873,12 @@ impl<T: TermsHashPerField> PerField<T> {
         let mut per_field = PerField {
             name: field_info.name.clone(),
             field_info,
+            similarity,
             invert_state,
             term_hash_per_field,
             doc_values_writer: None,
             point_values_writer: None,
+            vector_values_writer: None,
             field_gen: -1,
             norms: None,
             invert,
@@ -796,7 +904,7 @@ impl<T: TermsHashPerField> PerField<T> {
         self.field_info = field_info;
     }
 
-    fn set_invert_state<D: Directory, C: Codec, TH: TermsHash<D, C, PerField = T>>(
+    fn set_invert_state<D: Directory, TH: TermsHash<D, C, PerField = T>>(
         &mut self,
         terms_hash: &mut TH,
     ) {
@@ -817,7 +925,7 @@ impl<T: TermsHashPerField> PerField<T> {
             self.norms
                 .as_mut()
                 .unwrap()
-                .add_value(doc_id, BM25Similarity::compute_norm(&self.invert_state));
+                .add_value(doc_id, self.similarity.compute_norm(&self.invert_state));
         }
 
         self.term_hash_per_field
@@ -838,7 +946,7 @@ impl<T: TermsHashPerField> PerField<T> {
         Ok(())
     }
 
-    fn invert<D, C, MS, MP>(
+    fn invert<D, MS, MP>(
         &mut self,
         field: &mut impl Fieldable,
         doc_state: &DocState,
@@ -847,7 +955,6 @@ impl<T: TermsHashPerField> PerField<T> {
     ) -> Result<()>
     where
         D: Directory + Send + Sync + 'static,
-        C: Codec,
         MS: MergeScheduler,
         MP: MergePolicy,
     {
@@ -858,12 +965,14 @@ impl<T: TermsHashPerField> PerField<T> {
         }
 
         let index_options = field.field_type().index_options;
+        // downgrade rather than overwrite, the postings of earlier documents were
+        // buffered with the options this field had when it was first seen
         consumer
             .field_infos
             .by_name
             .get_mut(&self.field_info().name)
             .unwrap()
-            .index_options = index_options;
+            .set_index_options(index_options);
 
         if field.field_type().omit_norms {
             consumer
@@ -876,8 +985,8 @@ impl<T: TermsHashPerField> PerField<T> {
 
         // let analyzed = field.field_type().tokenized() && doc_state.analyzer.is_some();
         // only bother checking offsets if something will consume them.
-        // TODO: after we fix analyzers, also check if termVectorOffsets will be indexed.
-        let check_offset = index_options == IndexOptions::DocsAndFreqsAndPositionsAndOffsets;
+        let check_offset = index_options == IndexOptions::DocsAndFreqsAndPositionsAndOffsets
+            || field.field_type().store_term_vector_offsets();
 
         // To assist people in tracking down problems in analysis components, we wish to
         // write the field name to the infostream when we fail. We expect some caller to
@@ -942,7 +1051,26 @@ impl<T: TermsHashPerField> PerField<T> {
                 self.invert_state.last_start_offset = start_offset as i32;
             }
 
-            self.invert_state.length += 1;
+            let term_freq = token_stream.term_freq();
+            if term_freq != 1 {
+                if index_options < IndexOptions::DocsAndFreqs {
+                    bail!(IllegalArgument(format!(
+                        "field '{}': must index term freq while using custom term freq",
+                        &self.field_info().name
+                    )));
+                }
+                if index_options >= IndexOptions::DocsAndFreqsAndPositions {
+                    bail!(IllegalArgument(format!(
+                        "field '{}': cannot index positions while using custom term freq",
+                        &self.field_info().name
+                    )));
+                }
+            }
+            self.invert_state.length = self
+                .invert_state
+                .length
+                .checked_add(term_freq as i32)
+                .unwrap_or(-1);
             if self.invert_state.length < 0 {
                 bail!(IllegalArgument("too many tokens in field".into()));
             }
@@ -967,10 +1095,12 @@ impl<T: TermsHashPerField> PerField<T> {
         self.invert_state.position += token_stream.position_attribute_mut().get_position() as i32;
         self.invert_state.offset += token_stream.offset_attribute_mut().end_offset();
 
-        //        if analyzed {
-        //            self.invert_state.position += doc_state.analyzer.get_position_increment_gap();
-        //            self.invert_state.offset += doc_state.analyzer.get_offset_gap();
-        //        }
+        // the indexing chain has no analyzer to ask for the gaps, so the `Analyzer`
+        // defaults are used: no position gap, and an offset gap of one char which lines
+        // the offsets up with the separator highlighters put between the values
+        if field.field_type().tokenized() {
+            self.invert_state.offset += DEFAULT_OFFSET_GAP;
+        }
 
         self.invert_state.boost *= field.boost();
 
@@ -978,22 +1108,200 @@ impl<T: TermsHashPerField> PerField<T> {
     }
 }
 
-impl<T: TermsHashPerField> Eq for PerField<T> {}
+impl<C: Codec, T: TermsHashPerField> Eq for PerField<C, T> {}
 
-impl<T: TermsHashPerField> PartialEq for PerField<T> {
+impl<C: Codec, T: TermsHashPerField> PartialEq for PerField<C, T> {
     fn eq(&self, other: &Self) -> bool {
         self.field_info().name.eq(&other.field_info().name)
     }
 }
 
-impl<T: TermsHashPerField> Ord for PerField<T> {
+impl<C: Codec, T: TermsHashPerField> Ord for PerField<C, T> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.field_info().name.cmp(&other.field_info().name)
     }
 }
 
-impl<T: TermsHashPerField> PartialOrd for PerField<T> {
+impl<C: Codec, T: TermsHashPerField> PartialOrd for PerField<C, T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::analysis::{CharTermAttribute, OffsetAttribute, PositionAttribute};
+    use core::analysis::{TermToBytesRefAttribute, WhitespaceTokenizer};
+    use core::codec::{PostingIterator, PostingIteratorFlags};
+    use core::doc::{Field, Term};
+    use core::index::reader::{IndexReader, LeafReader};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::{DocIterator, NO_MORE_DOCS};
+    use core::store::directory::FSDirectory;
+
+    /// Emits the given tokens with their offsets as they are.
+    #[derive(Debug)]
+    struct CannedTokenStream {
+        tokens: Vec<(&'static str, usize, usize)>,
+        upto: usize,
+        term_attribute: CharTermAttribute,
+        offset_attribute: OffsetAttribute,
+        position_attribute: PositionAttribute,
+    }
+
+    impl CannedTokenStream {
+        fn new(tokens: Vec<(&'static str, usize, usize)>) -> Self {
+            CannedTokenStream {
+                tokens,
+                upto: 0,
+                term_attribute: CharTermAttribute::new(),
+                offset_attribute: OffsetAttribute::new(),
+                position_attribute: PositionAttribute::new(),
+            }
+        }
+    }
+
+    impl TokenStream for CannedTokenStream {
+        fn increment_token(&mut self) -> Result<bool> {
+            if self.upto == self.tokens.len() {
+                return Ok(false);
+            }
+            self.clear_attributes();
+            let (term, start_offset, end_offset) = self.tokens[self.upto];
+            self.term_attribute.append(term);
+            self.offset_attribute.set_offset(start_offset, end_offset)?;
+            self.upto += 1;
+            Ok(true)
+        }
+
+        fn end(&mut self) -> Result<()> {
+            self.end_attributes();
+            let final_offset = self.tokens.iter().map(|t| t.2).max().unwrap_or(0);
+            self.offset_attribute.set_offset(final_offset, final_offset)
+        }
+
+        fn reset(&mut self) -> Result<()> {
+            self.upto = 0;
+            Ok(())
+        }
+
+        fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+            &mut self.offset_attribute
+        }
+
+        fn offset_attribute(&self) -> &OffsetAttribute {
+            &self.offset_attribute
+        }
+
+        fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+            &mut self.position_attribute
+        }
+
+        fn position_attribute(&self) -> &PositionAttribute {
+            &self.position_attribute
+        }
+
+        fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+            &mut self.term_attribute
+        }
+
+        fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+            &self.term_attribute
+        }
+    }
+
+    fn offsets_field_type() -> FieldType {
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqsAndPositionsAndOffsets;
+        field_type
+    }
+
+    fn text_field(text: &'static str) -> Field {
+        let token_stream = WhitespaceTokenizer::new(Box::new(text.as_bytes()));
+        Field::new(
+            "body".into(),
+            offsets_field_type(),
+            None,
+            Some(Box::new(token_stream)),
+        )
+    }
+
+    #[test]
+    fn test_offsets_across_values() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let config = Arc::new(IndexWriterConfig::default());
+        let writer = IndexWriter::new(directory, config).unwrap();
+
+        // the values are indexed as "quick fox lazy dog fox", one char of offset
+        // gap standing for the separator between two values
+        writer
+            .add_document(vec![
+                text_field("quick fox"),
+                text_field("lazy dog"),
+                text_field("fox"),
+            ])
+            .unwrap();
+        // the offsets start over with the next document
+        writer.add_document(vec![text_field("dog")]).unwrap();
+
+        // offsets must not go backwards within a value
+        let backwards = Field::new(
+            "body".into(),
+            offsets_field_type(),
+            None,
+            Some(Box::new(CannedTokenStream::new(vec![
+                ("b", 2, 3),
+                ("a", 0, 1),
+            ]))),
+        );
+        assert!(writer.add_document(vec![backwards]).is_err());
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let leaves = reader.leaves();
+        assert_eq!(leaves.len(), 1);
+        let leaf = &leaves[0];
+        let postings = |term: &str| -> Vec<(DocId, Vec<(i32, i32, i32)>)> {
+            let term = Term::new("body".into(), term.as_bytes().to_vec());
+            let mut iter = leaf
+                .reader
+                .postings(&term, i32::from(PostingIteratorFlags::OFFSETS))
+                .unwrap()
+                .unwrap();
+            let mut docs = vec![];
+            loop {
+                let doc = iter.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                let mut positions = vec![];
+                for _ in 0..iter.freq().unwrap() {
+                    let position = iter.next_position().unwrap();
+                    positions.push((
+                        position,
+                        iter.start_offset().unwrap(),
+                        iter.end_offset().unwrap(),
+                    ));
+                }
+                docs.push((doc, positions));
+            }
+            docs
+        };
+
+        let gap = DEFAULT_OFFSET_GAP as i32;
+        assert_eq!(postings("quick"), vec![(0, vec![(0, 0, 5)])]);
+        assert_eq!(postings("lazy"), vec![(0, vec![(2, 9 + gap, 13 + gap)])]);
+        assert_eq!(
+            postings("fox"),
+            vec![(0, vec![(1, 6, 9), (4, 17 + 2 * gap, 20 + 2 * gap)])]
+        );
+        assert_eq!(
+            postings("dog"),
+            vec![(0, vec![(3, 14 + gap, 17 + gap)]), (1, vec![(0, 0, 3)])]
+        );
+    }
+}
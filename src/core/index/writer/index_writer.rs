@@ -36,6 +36,7 @@ use core::index::writer::{
     NumericDocValuesUpdate, OpenMode,
 };
 use core::search::query::{MatchAllDocsQuery, Query};
+use core::search::sort_field::{SortField, SortedNumericSelectorType};
 use core::store::directory::{Directory, LockValidatingDirectoryWrapper, TrackingDirectoryWrapper};
 use core::store::{FlushInfo, IOContext};
 use core::util::random_id;
@@ -1201,6 +1202,31 @@ where
         segment_infos: &SegmentInfos<D, C>,
     ) -> Result<()> {
         if let Some(index_sort) = config.index_sort() {
+            for field in index_sort.get_sort() {
+                match field {
+                    SortField::SortedNumeric(s)
+                        if s.selector() == SortedNumericSelectorType::Sum =>
+                    {
+                        bail!(IllegalArgument(format!(
+                            "sum selector can't be used for index sorting, field: {}",
+                            field.field()
+                        )));
+                    }
+                    SortField::LatLonDistance(_) => {
+                        bail!(IllegalArgument(format!(
+                            "distance sort can't be used for index sorting, field: {}",
+                            field.field()
+                        )));
+                    }
+                    SortField::Expression(_) => {
+                        bail!(IllegalArgument(format!(
+                            "expression sort can't be used for index sorting, expression: {}",
+                            field.field()
+                        )));
+                    }
+                    _ => {}
+                }
+            }
             for info in &segment_infos.segments {
                 if let Some(segment_sort) = info.info.index_sort() {
                     if segment_sort != index_sort {
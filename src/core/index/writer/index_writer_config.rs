@@ -16,7 +16,9 @@ use core::index::merge::MergeScheduler;
 use core::index::merge::SerialMergeScheduler;
 use core::index::merge::{MergePolicy, TieredMergePolicy};
 use core::index::writer::KeepOnlyLastCommitDeletionPolicy;
+use core::search::similarity::SimilarityProducer;
 use core::search::sort_field::Sort;
+use core::search::DefaultSimilarityProducer;
 
 use std::sync::Arc;
 
@@ -70,6 +72,9 @@ pub struct IndexWriterConfig<C: Codec, MS: MergeScheduler, MP: MergePolicy> {
     pub open_mode: OpenMode,
     pub codec: Arc<C>,
     pub commit_on_close: bool,
+    /// Produces the `Similarity` of each field, used to compute the norms at
+    /// index time. Defaults to `BM25Similarity`.
+    pub similarity: Arc<dyn SimilarityProducer<C> + Send + Sync>,
 }
 
 impl Default for IndexWriterConfig<CodecEnum, SerialMergeScheduler, TieredMergePolicy> {
@@ -95,6 +100,7 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
             open_mode: OpenMode::CreateOrAppend,
             codec,
             commit_on_close: true,
+            similarity: Arc::new(DefaultSimilarityProducer {}),
         }
     }
 
@@ -133,4 +139,8 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
     pub fn codec(&self) -> &C {
         self.codec.as_ref()
     }
+
+    pub fn similarity(&self) -> &dyn SimilarityProducer<C> {
+        &*self.similarity
+    }
 }
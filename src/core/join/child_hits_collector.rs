@@ -0,0 +1,329 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::join::{parents_bit_set, ScoreMode};
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::query::Query;
+use core::search::scorer::Scorer;
+use core::search::searcher::IndexSearcher;
+use core::search::sort_field::ScoreDoc;
+use core::search::NO_MORE_DOCS;
+use core::util::{Bits, DocId, FixedBitSet, ImmutableBitSet};
+use error::{ErrorKind::IllegalState, Result, ResultExt};
+
+/// The matching children of a parent doc, as collected by a
+/// `ChildHitsCollector`.
+#[derive(Debug)]
+pub struct ParentChildHits {
+    /// The parent doc.
+    pub parent_doc: DocId,
+    /// The score of the parent, computed from all its matching children.
+    pub score: f32,
+    /// Total matching children of this parent.
+    pub total_hits: usize,
+    /// The top children of this parent, by decreasing score.
+    pub children: Vec<ScoreDoc>,
+}
+
+// parent doc -> matching children, in increasing doc order per segment
+type ParentGroups = HashMap<DocId, Vec<ScoreDoc>>;
+
+/// A `SearchCollector` grouping the hits of a child query by their parent
+/// doc, for the blocks indexed as for `ToParentBlockJoinQuery`.
+///
+/// This returns the top parents like `ToParentBlockJoinQuery` does, along with
+/// the child hits that made each parent match.
+pub struct ChildHitsCollector {
+    // leaf ord -> parent docs of the segment
+    parent_bits: Vec<Option<Arc<FixedBitSet>>>,
+    score_mode: ScoreMode,
+    max_children_per_parent: usize,
+    groups: ParentGroups,
+    leaf_parent_bits: Option<Arc<FixedBitSet>>,
+    doc_base: DocId,
+    channel: Option<(Sender<ParentGroups>, Receiver<ParentGroups>)>,
+}
+
+impl ChildHitsCollector {
+    /// Create a collector for the blocks of the reader of `searcher`, whose
+    /// parent docs are matched by `parents_filter`.
+    pub fn new<C, IS>(
+        searcher: &IS,
+        parents_filter: &dyn Query<C>,
+        score_mode: ScoreMode,
+        max_children_per_parent: usize,
+    ) -> Result<ChildHitsCollector>
+    where
+        C: Codec,
+        IS: IndexSearcher<C>,
+    {
+        let parents_weight = parents_filter.create_weight(searcher, false)?;
+        let leaves = searcher.reader().leaves();
+        let mut parent_bits = Vec::with_capacity(leaves.len());
+        for leaf in &leaves {
+            parent_bits.push(parents_bit_set(parents_weight.as_ref(), leaf)?.map(Arc::new));
+        }
+        Ok(Self::with_parent_bits(
+            parent_bits,
+            score_mode,
+            max_children_per_parent,
+        ))
+    }
+
+    fn with_parent_bits(
+        parent_bits: Vec<Option<Arc<FixedBitSet>>>,
+        score_mode: ScoreMode,
+        max_children_per_parent: usize,
+    ) -> ChildHitsCollector {
+        ChildHitsCollector {
+            parent_bits,
+            score_mode,
+            max_children_per_parent,
+            groups: HashMap::new(),
+            leaf_parent_bits: None,
+            doc_base: 0,
+            channel: None,
+        }
+    }
+
+    /// Returns the top `num_parents` parents by decreasing score, each with its
+    /// top `max_children_per_parent` children.
+    pub fn top_parents(&self, num_parents: usize) -> Vec<ParentChildHits> {
+        let mut parents: Vec<ParentChildHits> = self
+            .groups
+            .iter()
+            .map(|(&parent_doc, children)| {
+                let score = parent_score(self.score_mode, children);
+                let mut children = children.clone();
+                children.sort_by(compare_hits);
+                children.truncate(self.max_children_per_parent);
+                ParentChildHits {
+                    parent_doc,
+                    score,
+                    total_hits: self.groups[&parent_doc].len(),
+                    children,
+                }
+            })
+            .collect();
+        parents.sort_by(|p1, p2| {
+            p2.score
+                .partial_cmp(&p1.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| p1.parent_doc.cmp(&p2.parent_doc))
+        });
+        parents.truncate(num_parents);
+        parents
+    }
+
+    /// Total number of parents with at least one matching child.
+    pub fn total_parents(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+/// Orders the hits by decreasing score, then by doc id.
+fn compare_hits(h1: &ScoreDoc, h2: &ScoreDoc) -> Ordering {
+    h2.score
+        .partial_cmp(&h1.score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| h1.doc.cmp(&h2.doc))
+}
+
+fn parent_score(score_mode: ScoreMode, children: &[ScoreDoc]) -> f32 {
+    if !score_mode.needs_scores() || children.is_empty() {
+        return 1.0;
+    }
+    let score = children[1..]
+        .iter()
+        .fold(children[0].score, |score, child| {
+            score_mode.combine(score, child.score)
+        });
+    if score_mode == ScoreMode::Avg {
+        score / children.len() as f32
+    } else {
+        score
+    }
+}
+
+impl SearchCollector for ChildHitsCollector {
+    type LC = ChildHitsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.leaf_parent_bits = self.parent_bits.get(reader.ord).and_then(|b| b.clone());
+        self.doc_base = reader.doc_base;
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<ChildHitsLeafCollector> {
+        Ok(ChildHitsLeafCollector {
+            parent_bits: self.parent_bits.get(reader.ord).and_then(|b| b.clone()),
+            doc_base: reader.doc_base,
+            groups: HashMap::new(),
+            sender: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        // a block never spans several segments, so the groups are disjoint
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(groups) = receiver.recv() {
+                self.groups.extend(groups);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for ChildHitsCollector {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        if let Some(ref parent_bits) = self.leaf_parent_bits {
+            collect_child(&mut self.groups, parent_bits, self.doc_base, doc, scorer)?;
+        }
+        Ok(())
+    }
+}
+
+fn collect_child<S: Scorer + ?Sized>(
+    groups: &mut ParentGroups,
+    parent_bits: &FixedBitSet,
+    doc_base: DocId,
+    doc: DocId,
+    scorer: &mut S,
+) -> Result<()> {
+    if parent_bits.get(doc as usize)? {
+        bail!(IllegalState(format!(
+            "Child query must not match the same docs as the parents filter, doc: {}",
+            doc + doc_base
+        )));
+    }
+    let parent_doc = parent_bits.next_set_bit(doc as usize);
+    // children after the last parent don't belong to any block
+    if parent_doc != NO_MORE_DOCS {
+        groups
+            .entry(parent_doc + doc_base)
+            .or_insert_with(Vec::new)
+            .push(ScoreDoc::new(doc + doc_base, scorer.score()?));
+    }
+    Ok(())
+}
+
+pub struct ChildHitsLeafCollector {
+    parent_bits: Option<Arc<FixedBitSet>>,
+    doc_base: DocId,
+    groups: ParentGroups,
+    sender: Sender<ParentGroups>,
+}
+
+impl Collector for ChildHitsLeafCollector {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        if let Some(ref parent_bits) = self.parent_bits {
+            collect_child(&mut self.groups, parent_bits, self.doc_base, doc, scorer)?;
+        }
+        Ok(())
+    }
+}
+
+impl ParallelLeafCollector for ChildHitsLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let groups = mem::replace(&mut self.groups, HashMap::new());
+        self.sender
+            .send(groups)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::tests::*;
+    use core::search::tests::*;
+    use core::search::DocIterator;
+    use core::util::BitSet;
+
+    fn parent_bits(parents: &[usize]) -> Option<Arc<FixedBitSet>> {
+        let mut bits = FixedBitSet::new(10);
+        for &parent in parents {
+            bits.set(parent);
+        }
+        Some(Arc::new(bits))
+    }
+
+    #[test]
+    fn test_child_hits_collector() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let mut collector = ChildHitsCollector::with_parent_bits(
+            vec![parent_bits(&[3, 9]), parent_bits(&[4])],
+            ScoreMode::Max,
+            2,
+        );
+        for (leaf, docs) in leaves.iter().zip(vec![vec![0, 1, 2, 5, 7], vec![1, 2, 6]]) {
+            collector.set_next_reader(leaf).unwrap();
+            let mut scorer = create_mock_scorer(docs);
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                collector.collect(doc, &mut scorer).unwrap();
+            }
+        }
+
+        // doc 16 is an orphan child
+        assert_eq!(collector.total_parents(), 3);
+        let parents = collector.top_parents(2);
+        assert_eq!(parents.len(), 2);
+        assert_eq!(parents[0].parent_doc, 9);
+        assert!((parents[0].score - 7.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(parents[0].total_hits, 2);
+        let children: Vec<DocId> = parents[0].children.iter().map(|c| c.doc).collect();
+        assert_eq!(children, vec![7, 5]);
+        // ties are broken by the parent doc
+        assert_eq!(parents[1].parent_doc, 3);
+        assert_eq!(parents[1].total_hits, 3);
+        let children: Vec<DocId> = parents[1].children.iter().map(|c| c.doc).collect();
+        assert_eq!(children, vec![2, 1]);
+    }
+}
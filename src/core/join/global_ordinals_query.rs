@@ -0,0 +1,403 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::doc_values::SortedDocValues;
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::join::{sorted_doc_values, ScoreMode};
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{two_phase_next, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::{Bits, DocId, FixedBitSet};
+
+use error::Result;
+
+pub const GLOBAL_ORDINALS: &str = "global_ordinals";
+
+/// The join keys collected from the "from" side, as the ordinals of the join
+/// field of one "to" segment.
+pub struct JoinSegment {
+    matched: FixedBitSet,
+    // segment ord -> score, empty if the join is not scored
+    scores: Vec<f32>,
+}
+
+impl JoinSegment {
+    pub(crate) fn new(matched: FixedBitSet, scores: Vec<f32>) -> JoinSegment {
+        JoinSegment { matched, scores }
+    }
+
+    fn matches(&self, ord: i32) -> Result<bool> {
+        if ord < 0 {
+            Ok(false)
+        } else {
+            self.matched.get(ord as usize)
+        }
+    }
+
+    fn score(&self, ord: i32) -> f32 {
+        if ord < 0 || self.scores.is_empty() {
+            1.0
+        } else {
+            self.scores[ord as usize]
+        }
+    }
+}
+
+/// The join query created by `JoinUtil::create_join_query`, matching the docs
+/// of the "to" query holding one of the collected join keys.
+///
+/// The collected keys are resolved per segment of the reader that was
+/// searched for the "from" side, this query must not be used with another
+/// reader.
+pub struct GlobalOrdinalsQuery<C: Codec> {
+    join_field: String,
+    to_query: Box<dyn Query<C>>,
+    score_mode: ScoreMode,
+    // doc base -> collected keys of the segment
+    segments: HashMap<DocId, Arc<JoinSegment>>,
+}
+
+impl<C: Codec> GlobalOrdinalsQuery<C> {
+    pub(crate) fn new(
+        join_field: String,
+        to_query: Box<dyn Query<C>>,
+        score_mode: ScoreMode,
+        segments: HashMap<DocId, Arc<JoinSegment>>,
+    ) -> GlobalOrdinalsQuery<C> {
+        GlobalOrdinalsQuery {
+            join_field,
+            to_query,
+            score_mode,
+            segments,
+        }
+    }
+
+    pub fn join_field(&self) -> &str {
+        &self.join_field
+    }
+
+    pub fn to_query(&self) -> &dyn Query<C> {
+        self.to_query.as_ref()
+    }
+
+    pub fn score_mode(&self) -> ScoreMode {
+        self.score_mode
+    }
+}
+
+impl<C: Codec> Query<C> for GlobalOrdinalsQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        // the to query only filters, the scores come from the from side
+        let to_weight = self.to_query.create_weight(searcher, false)?;
+        Ok(Box::new(GlobalOrdinalsWeight {
+            join_field: self.join_field.clone(),
+            to_weight,
+            score_mode: self.score_mode,
+            segments: self.segments.clone(),
+            query_weight: 1.0,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.to_query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for GlobalOrdinalsQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GlobalOrdinalsQuery(join_field: {}, to_query: {}, score_mode: {})",
+            &self.join_field, &self.to_query, self.score_mode
+        )
+    }
+}
+
+struct GlobalOrdinalsWeight<C: Codec> {
+    join_field: String,
+    to_weight: Box<dyn Weight<C>>,
+    score_mode: ScoreMode,
+    segments: HashMap<DocId, Arc<JoinSegment>>,
+    query_weight: f32,
+    needs_scores: bool,
+}
+
+impl<C: Codec> Weight<C> for GlobalOrdinalsWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let segment = match self.segments.get(&reader.doc_base) {
+            Some(segment) => Arc::clone(segment),
+            None => return Ok(None),
+        };
+        if let Some(to_scorer) = self.to_weight.create_scorer(reader)? {
+            let doc_values = sorted_doc_values(reader, &self.join_field)?;
+            Ok(Some(Box::new(GlobalOrdinalsScorer::new(
+                to_scorer,
+                doc_values,
+                segment,
+                self.query_weight,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        GLOBAL_ORDINALS
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.query_weight = norm * boost;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.query_weight * self.query_weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let to_explanation = self.to_weight.explain(reader, doc)?;
+        if !to_explanation.is_match() {
+            return Ok(Explanation::new(
+                false,
+                0f32,
+                "Not a match, the to query doesn't match".into(),
+                vec![to_explanation],
+            ));
+        }
+        let ord = sorted_doc_values(reader, &self.join_field)?.get_ord(doc)?;
+        match self.segments.get(&reader.doc_base) {
+            Some(segment) if segment.matches(ord)? => {
+                let score = segment.score(ord) * self.query_weight;
+                Ok(Explanation::new(
+                    true,
+                    score,
+                    format!(
+                        "A match, join value of {} collected, score mode {}",
+                        &self.join_field, self.score_mode
+                    ),
+                    vec![to_explanation],
+                ))
+            }
+            _ => Ok(Explanation::new(
+                false,
+                0f32,
+                format!(
+                    "Not a match, join value of {} not collected",
+                    &self.join_field
+                ),
+                vec![to_explanation],
+            )),
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for GlobalOrdinalsWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GlobalOrdinalsWeight(join_field: {}, to_weight: {}, score_mode: {})",
+            &self.join_field, &self.to_weight, self.score_mode
+        )
+    }
+}
+
+/// Iterates the docs of the to scorer, only confirming the docs whose join
+/// value was collected.
+struct GlobalOrdinalsScorer {
+    to_scorer: Box<dyn Scorer>,
+    doc_values: Box<dyn SortedDocValues>,
+    segment: Arc<JoinSegment>,
+    query_weight: f32,
+    // segment ord of the current doc, as read by `matches`
+    curr_ord: i32,
+}
+
+impl GlobalOrdinalsScorer {
+    fn new(
+        to_scorer: Box<dyn Scorer>,
+        doc_values: Box<dyn SortedDocValues>,
+        segment: Arc<JoinSegment>,
+        query_weight: f32,
+    ) -> GlobalOrdinalsScorer {
+        GlobalOrdinalsScorer {
+            to_scorer,
+            doc_values,
+            segment,
+            query_weight,
+            curr_ord: -1,
+        }
+    }
+}
+
+impl Scorer for GlobalOrdinalsScorer {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.segment.score(self.curr_ord) * self.query_weight)
+    }
+}
+
+impl DocIterator for GlobalOrdinalsScorer {
+    fn doc_id(&self) -> DocId {
+        self.to_scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.approximate_next()?;
+        two_phase_next(self)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximate_advance(target)?;
+        two_phase_next(self)
+    }
+
+    fn cost(&self) -> usize {
+        self.to_scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let doc = self.to_scorer.doc_id();
+        if doc == NO_MORE_DOCS {
+            return Ok(false);
+        }
+        self.curr_ord = self.doc_values.get_ord(doc)?;
+        if !self.segment.matches(self.curr_ord)? {
+            return Ok(false);
+        }
+        if self.to_scorer.support_two_phase() {
+            self.to_scorer.matches()
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn match_cost(&self) -> f32 {
+        // reading the ord of the join value
+        let to_cost = if self.to_scorer.support_two_phase() {
+            self.to_scorer.match_cost()
+        } else {
+            0f32
+        };
+        1.0 + to_cost
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.to_scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.to_scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::doc_values::{BinaryDocValues, DocValuesTermIterator};
+    use core::search::tests::*;
+    use core::util::BitSet;
+
+    struct MockSortedDocValues;
+
+    impl SortedDocValues for MockSortedDocValues {
+        fn get_ord(&mut self, doc_id: DocId) -> Result<i32> {
+            // docs multiple of 5 have no value
+            if doc_id % 5 == 0 {
+                Ok(-1)
+            } else {
+                Ok(doc_id % 3)
+            }
+        }
+
+        fn lookup_ord(&mut self, ord: i32) -> Result<Vec<u8>> {
+            Ok(vec![ord as u8])
+        }
+
+        fn value_count(&self) -> usize {
+            3
+        }
+
+        fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+            Ok(DocValuesTermIterator::empty())
+        }
+    }
+
+    impl BinaryDocValues for MockSortedDocValues {
+        fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+            let ord = self.get_ord(doc_id)?;
+            Ok(vec![ord as u8])
+        }
+    }
+
+    #[test]
+    fn test_global_ordinals_scorer() {
+        let mut matched = FixedBitSet::new(3);
+        matched.set(1);
+        matched.set(2);
+        let segment = Arc::new(JoinSegment::new(matched, vec![0.0, 2.0, 4.0]));
+
+        let mut scorer = GlobalOrdinalsScorer::new(
+            Box::new(create_mock_scorer(vec![1, 2, 3, 4, 5, 6, 7, 8, 10, 11])),
+            Box::new(MockSortedDocValues),
+            Arc::clone(&segment),
+            0.5,
+        );
+        assert!(scorer.support_two_phase());
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 1.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert!((scorer.score().unwrap() - 2.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert_eq!(scorer.advance(6).unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), 8);
+        assert_eq!(scorer.next().unwrap(), 11);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+
+        let mut scorer = GlobalOrdinalsScorer::new(
+            Box::new(create_mock_two_phase_scorer(
+                vec![1, 2, 4, 7, 8],
+                vec![2, 3, 7, 8],
+            )),
+            Box::new(MockSortedDocValues),
+            segment,
+            1.0,
+        );
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert_eq!(scorer.next().unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), 8);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::{EmptySortedDocValues, OrdinalMap, SortedDocValues};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::facet::FacetsCollector;
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::join::{GlobalOrdinalsQuery, JoinSegment, ScoreMode};
+use core::search::query::Query;
+use core::search::searcher::IndexSearcher;
+use core::util::packed::COMPACT;
+use core::util::{BitSet, Bits, FixedBitSet};
+use error::Result;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Utility to create the query-time join queries.
+pub struct JoinUtil;
+
+impl JoinUtil {
+    /// Build the `OrdinalMap` of the join field over all the segments of
+    /// `reader`, segments without the field are mapped as having no value.
+    ///
+    /// The map is only valid for this reader and should be rebuilt when the
+    /// reader is reopened.
+    pub fn build_ordinal_map<R: IndexReader + ?Sized>(
+        reader: &R,
+        join_field: &str,
+    ) -> Result<OrdinalMap> {
+        let leaves = reader.leaves();
+        let mut subs = Vec::with_capacity(leaves.len());
+        let mut weights = Vec::with_capacity(leaves.len());
+        for leaf in &leaves {
+            let doc_values = sorted_doc_values(leaf, join_field)?;
+            weights.push(doc_values.value_count());
+            subs.push(Some(doc_values.term_iterator()?));
+        }
+        OrdinalMap::build(subs, weights, COMPACT)
+    }
+
+    /// Create a query matching the docs of `to_query` whose join field holds
+    /// a value that the join field of a doc matching `from_query` holds too.
+    ///
+    /// Both sides must index the join field as a `SortedDocValuesField`, and
+    /// `ordinal_map` must have been built by `build_ordinal_map` over the
+    /// reader of `searcher`. The from side is searched eagerly, so the returned
+    /// query is only valid for that reader. The joined docs are scored from the
+    /// scores of the from docs as defined by `score_mode`.
+    pub fn create_join_query<C, IS>(
+        join_field: &str,
+        from_query: &dyn Query<C>,
+        to_query: Box<dyn Query<C>>,
+        searcher: &IS,
+        score_mode: ScoreMode,
+        ordinal_map: &OrdinalMap,
+    ) -> Result<GlobalOrdinalsQuery<C>>
+    where
+        C: Codec,
+        IS: IndexSearcher<C>,
+    {
+        let mut collector = FacetsCollector::new(score_mode.needs_scores());
+        searcher.search(from_query, &mut collector)?;
+
+        // collect the join keys of the from docs as global ordinals
+        let value_count = ordinal_map.value_count() as usize;
+        let mut collected = FixedBitSet::new(value_count);
+        let (mut scores, mut occurrences) = if score_mode.needs_scores() {
+            (vec![0f32; value_count], vec![0u32; value_count])
+        } else {
+            (vec![], vec![])
+        };
+        let leaves = searcher.reader().leaves();
+        for matching_docs in collector.matching_docs() {
+            let leaf = &leaves[matching_docs.ord];
+            let mut doc_values = sorted_doc_values(leaf, join_field)?;
+            let global_ords = ordinal_map.get_global_ords(matching_docs.ord);
+            for (i, &doc) in matching_docs.docs.iter().enumerate() {
+                let ord = doc_values.get_ord(doc)?;
+                if ord < 0 {
+                    continue;
+                }
+                let global_ord = global_ords.get64(i64::from(ord))? as usize;
+                collected.set(global_ord);
+                if let Some(ref doc_scores) = matching_docs.scores {
+                    scores[global_ord] = if occurrences[global_ord] == 0 {
+                        doc_scores[i]
+                    } else {
+                        score_mode.combine(scores[global_ord], doc_scores[i])
+                    };
+                    occurrences[global_ord] += 1;
+                }
+            }
+        }
+        if score_mode == ScoreMode::Avg {
+            for (score, &occurrence) in scores.iter_mut().zip(occurrences.iter()) {
+                if occurrence > 0 {
+                    *score /= occurrence as f32;
+                }
+            }
+        }
+
+        // translate them back to the segment ordinals of every to segment
+        let mut segments = HashMap::with_capacity(leaves.len());
+        for leaf in &leaves {
+            let doc_values = sorted_doc_values(leaf, join_field)?;
+            let segment_value_count = doc_values.value_count();
+            if segment_value_count == 0 {
+                continue;
+            }
+            let global_ords = ordinal_map.get_global_ords(leaf.ord);
+            let mut matched = FixedBitSet::new(segment_value_count);
+            let mut segment_scores = if score_mode.needs_scores() {
+                vec![0f32; segment_value_count]
+            } else {
+                vec![]
+            };
+            for ord in 0..segment_value_count {
+                let global_ord = global_ords.get64(ord as i64)? as usize;
+                if collected.get(global_ord)? {
+                    matched.set(ord);
+                    if score_mode.needs_scores() {
+                        segment_scores[ord] = scores[global_ord];
+                    }
+                }
+            }
+            segments.insert(
+                leaf.doc_base,
+                Arc::new(JoinSegment::new(matched, segment_scores)),
+            );
+        }
+
+        Ok(GlobalOrdinalsQuery::new(
+            join_field.to_string(),
+            to_query,
+            score_mode,
+            segments,
+        ))
+    }
+}
+
+/// The sorted doc values of the join field, empty if the segment doesn't
+/// contain the field.
+pub(crate) fn sorted_doc_values<C: Codec>(
+    leaf: &LeafReaderContext<'_, C>,
+    join_field: &str,
+) -> Result<Box<dyn SortedDocValues>> {
+    match leaf.reader.field_info(join_field) {
+        Some(field_info) if field_info.doc_values_type != DocValuesType::Null => {
+            leaf.reader.get_sorted_doc_values(join_field)
+        }
+        _ => Ok(Box::new(EmptySortedDocValues)),
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Query-time joins, relating documents indexed separately through a shared
+//! join field, e.g. the products and the offers of a shop.
+//!
+//! Both sides of the relation store the join key in a `SortedDocValuesField`.
+//! The keys of the docs matching the "from" query are collected as global
+//! ordinals through an `OrdinalMap` built for the reader, then the "to" query
+//! is restricted to the docs holding one of those keys.
+//!
+//! Relations indexed as blocks of documents with `IndexWriter::add_documents`,
+//! the children followed by their parent, are joined by the block join
+//! queries instead: `ToParentBlockJoinQuery` and `ToChildBlockJoinQuery`.
+
+mod child_hits_collector;
+
+pub use self::child_hits_collector::*;
+
+mod global_ordinals_query;
+
+pub use self::global_ordinals_query::*;
+
+mod join_util;
+
+pub use self::join_util::*;
+
+mod to_child_block_join_query;
+
+pub use self::to_child_block_join_query::*;
+
+mod to_parent_block_join_query;
+
+pub use self::to_parent_block_join_query::*;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::query::Weight;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::{BitSet, FixedBitSet};
+use error::Result;
+
+use std::fmt;
+
+/// How the scores of the "from" docs sharing a join key are combined into the
+/// score of the "to" docs holding that key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreMode {
+    /// Do not score, the joined docs get a constant score.
+    None,
+    /// The average score of the "from" docs.
+    Avg,
+    /// The maximum score of the "from" docs.
+    Max,
+    /// The sum of the scores of the "from" docs.
+    Total,
+    /// The minimum score of the "from" docs.
+    Min,
+}
+
+impl ScoreMode {
+    pub fn needs_scores(self) -> bool {
+        self != ScoreMode::None
+    }
+
+    /// Combine a new score with the current one of a join key, `Avg` is summed
+    /// and must be divided by the number of occurrences eventually.
+    fn combine(self, current: f32, score: f32) -> f32 {
+        match self {
+            ScoreMode::None => 1.0,
+            ScoreMode::Avg | ScoreMode::Total => current + score,
+            ScoreMode::Max => current.max(score),
+            ScoreMode::Min => current.min(score),
+        }
+    }
+}
+
+impl fmt::Display for ScoreMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self {
+            ScoreMode::None => "none",
+            ScoreMode::Avg => "avg",
+            ScoreMode::Max => "max",
+            ScoreMode::Total => "total",
+            ScoreMode::Min => "min",
+        };
+        write!(f, "{}", mode)
+    }
+}
+
+/// Collect the parent docs of a segment, as matched by the weight of the
+/// parents filter, `None` if the segment has no parent doc.
+fn parents_bit_set<C: Codec>(
+    parents_weight: &dyn Weight<C>,
+    reader: &LeafReaderContext<'_, C>,
+) -> Result<Option<FixedBitSet>> {
+    let mut scorer = match parents_weight.create_scorer(reader)? {
+        Some(scorer) => scorer,
+        None => return Ok(None),
+    };
+    let mut bits = FixedBitSet::new(reader.reader.max_doc() as usize);
+    loop {
+        let doc = scorer.next()?;
+        if doc == NO_MORE_DOCS {
+            break;
+        }
+        bits.set(doc as usize);
+    }
+    Ok(Some(bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_mode_combine() {
+        let scores = [2.0f32, 1.0, 3.0];
+        let combined = |mode: ScoreMode| {
+            scores[1..]
+                .iter()
+                .fold(scores[0], |current, &score| mode.combine(current, score))
+        };
+        assert!((combined(ScoreMode::Total) - 6.0).abs() < ::std::f32::EPSILON);
+        assert!((combined(ScoreMode::Avg) - 6.0).abs() < ::std::f32::EPSILON);
+        assert!((combined(ScoreMode::Max) - 3.0).abs() < ::std::f32::EPSILON);
+        assert!((combined(ScoreMode::Min) - 1.0).abs() < ::std::f32::EPSILON);
+        assert!((combined(ScoreMode::None) - 1.0).abs() < ::std::f32::EPSILON);
+        assert!(!ScoreMode::None.needs_scores());
+        assert!(ScoreMode::Avg.needs_scores());
+    }
+}
@@ -0,0 +1,322 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::join::parents_bit_set;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::{Bits, DocId, FixedBitSet, ImmutableBitSet};
+
+use error::{ErrorKind::IllegalState, Result};
+
+pub const TO_CHILD_BLOCK_JOIN: &str = "to_child_block_join";
+
+/// A query that matches the children of the parent docs matching a parent
+/// query, this is the reverse of `ToParentBlockJoinQuery`.
+///
+/// The blocks must be indexed as for `ToParentBlockJoinQuery`, and the parent
+/// query must only match docs matched by `parents_filter`. Each child gets the
+/// score of its parent.
+pub struct ToChildBlockJoinQuery<C: Codec> {
+    parent_query: Box<dyn Query<C>>,
+    parents_filter: Box<dyn Query<C>>,
+}
+
+impl<C: Codec> ToChildBlockJoinQuery<C> {
+    pub fn new(
+        parent_query: Box<dyn Query<C>>,
+        parents_filter: Box<dyn Query<C>>,
+    ) -> ToChildBlockJoinQuery<C> {
+        ToChildBlockJoinQuery {
+            parent_query,
+            parents_filter,
+        }
+    }
+
+    pub fn parent_query(&self) -> &dyn Query<C> {
+        self.parent_query.as_ref()
+    }
+
+    pub fn parents_filter(&self) -> &dyn Query<C> {
+        self.parents_filter.as_ref()
+    }
+}
+
+impl<C: Codec> Query<C> for ToChildBlockJoinQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(ToChildBlockJoinWeight {
+            parent_weight: self.parent_query.create_weight(searcher, needs_scores)?,
+            parents_weight: self.parents_filter.create_weight(searcher, false)?,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.parent_query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for ToChildBlockJoinQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ToChildBlockJoinQuery(parent_query: {}, parents_filter: {})",
+            &self.parent_query, &self.parents_filter
+        )
+    }
+}
+
+struct ToChildBlockJoinWeight<C: Codec> {
+    parent_weight: Box<dyn Weight<C>>,
+    parents_weight: Box<dyn Weight<C>>,
+    needs_scores: bool,
+}
+
+impl<C: Codec> Weight<C> for ToChildBlockJoinWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let parent_scorer = match self.parent_weight.create_scorer(reader)? {
+            Some(scorer) => scorer,
+            None => return Ok(None),
+        };
+        if let Some(parent_bits) = parents_bit_set(self.parents_weight.as_ref(), reader)? {
+            Ok(Some(Box::new(ToChildBlockJoinScorer::new(
+                parent_scorer,
+                parent_bits,
+                self.needs_scores,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        TO_CHILD_BLOCK_JOIN
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.parent_weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.parent_weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        if let Some(parent_bits) = parents_bit_set(self.parents_weight.as_ref(), reader)? {
+            if !parent_bits.get(doc as usize)? {
+                let parent_doc = parent_bits.next_set_bit(doc as usize);
+                if parent_doc != NO_MORE_DOCS {
+                    let explanation = self.parent_weight.explain(reader, parent_doc)?;
+                    if explanation.is_match() {
+                        return Ok(Explanation::new(
+                            true,
+                            explanation.value(),
+                            format!(
+                                "Score based on parent document {}",
+                                parent_doc + reader.doc_base
+                            ),
+                            vec![explanation],
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(Explanation::new(false, 0f32, "Not a match".into(), vec![]))
+    }
+}
+
+impl<C: Codec> fmt::Display for ToChildBlockJoinWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ToChildBlockJoinWeight(parent_weight: {}, parents_weight: {})",
+            &self.parent_weight, &self.parents_weight
+        )
+    }
+}
+
+/// Iterates the children of the docs of the parent scorer.
+struct ToChildBlockJoinScorer {
+    parent_scorer: Box<dyn Scorer>,
+    parent_bits: FixedBitSet,
+    needs_scores: bool,
+    child_doc: DocId,
+    parent_doc: DocId,
+    parent_score: f32,
+}
+
+impl ToChildBlockJoinScorer {
+    fn new(
+        parent_scorer: Box<dyn Scorer>,
+        parent_bits: FixedBitSet,
+        needs_scores: bool,
+    ) -> ToChildBlockJoinScorer {
+        ToChildBlockJoinScorer {
+            parent_scorer,
+            parent_bits,
+            needs_scores,
+            child_doc: -1,
+            parent_doc: -1,
+            parent_score: 0f32,
+        }
+    }
+
+    /// Moves to the block of `parent_doc`, the next child being the first
+    /// child after `min_child`.
+    fn set_parent(&mut self, parent_doc: DocId, min_child: DocId) -> Result<()> {
+        if parent_doc == NO_MORE_DOCS {
+            self.child_doc = NO_MORE_DOCS;
+            self.parent_doc = NO_MORE_DOCS;
+            return Ok(());
+        }
+        if !self.parent_bits.get(parent_doc as usize)? {
+            bail!(IllegalState(format!(
+                "Parent query must not match any doc besides the parents filter, doc: {}",
+                parent_doc
+            )));
+        }
+        let prev_parent = if parent_doc == 0 {
+            -1
+        } else {
+            self.parent_bits.prev_set_bit(parent_doc as usize - 1)
+        };
+        self.child_doc = prev_parent.max(min_child);
+        self.parent_doc = parent_doc;
+        if self.needs_scores {
+            self.parent_score = self.parent_scorer.score()?;
+        }
+        Ok(())
+    }
+}
+
+impl Scorer for ToChildBlockJoinScorer {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.parent_score)
+    }
+}
+
+impl DocIterator for ToChildBlockJoinScorer {
+    fn doc_id(&self) -> DocId {
+        self.child_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        loop {
+            if self.parent_doc == NO_MORE_DOCS {
+                return Ok(NO_MORE_DOCS);
+            }
+            if self.child_doc + 1 < self.parent_doc {
+                self.child_doc += 1;
+                return Ok(self.child_doc);
+            }
+            // the block is exhausted or has no child
+            let parent_doc = self.parent_scorer.next()?;
+            self.set_parent(parent_doc, -1)?;
+        }
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        if self.parent_doc == NO_MORE_DOCS {
+            return Ok(NO_MORE_DOCS);
+        }
+        if target < self.parent_doc {
+            // the target is a child of the current block
+            self.child_doc = target - 1;
+            return self.next();
+        }
+        if target == NO_MORE_DOCS {
+            self.set_parent(NO_MORE_DOCS, -1)?;
+            return Ok(NO_MORE_DOCS);
+        }
+        // the children of the parents after the target
+        let parent_doc = self.parent_scorer.advance(target + 1)?;
+        self.set_parent(parent_doc, target - 1)?;
+        self.next()
+    }
+
+    fn cost(&self) -> usize {
+        self.parent_scorer.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+    use core::util::BitSet;
+
+    #[test]
+    fn test_to_child_block_join_scorer() {
+        // blocks: [0, 1, 2] -> 3, [4] -> 5, [] -> 6, [7, 8] -> 9
+        let mut parent_bits = FixedBitSet::new(12);
+        for &parent in &[3, 5, 6, 9] {
+            parent_bits.set(parent);
+        }
+        let mut scorer = ToChildBlockJoinScorer::new(
+            Box::new(create_mock_scorer(vec![3, 6, 9])),
+            parent_bits,
+            true,
+        );
+        assert_eq!(scorer.next().unwrap(), 0);
+        assert!((scorer.score().unwrap() - 3.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(2).unwrap(), 2);
+        // the block of 6 has no child
+        assert_eq!(scorer.next().unwrap(), 7);
+        assert!((scorer.score().unwrap() - 9.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 8);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+
+        let mut parent_bits = FixedBitSet::new(12);
+        for &parent in &[3, 5, 6, 9] {
+            parent_bits.set(parent);
+        }
+        let mut scorer = ToChildBlockJoinScorer::new(
+            Box::new(create_mock_scorer(vec![3, 5, 9])),
+            parent_bits,
+            false,
+        );
+        assert_eq!(scorer.advance(4).unwrap(), 4);
+        assert_eq!(scorer.advance(5).unwrap(), 7);
+        assert_eq!(scorer.advance(8).unwrap(), 8);
+        assert_eq!(scorer.advance(10).unwrap(), NO_MORE_DOCS);
+
+        let mut parent_bits = FixedBitSet::new(12);
+        parent_bits.set(3);
+        let mut scorer = ToChildBlockJoinScorer::new(
+            Box::new(create_mock_scorer(vec![2, 3])),
+            parent_bits,
+            false,
+        );
+        // the parent query matches a child
+        assert!(scorer.next().is_err());
+    }
+}
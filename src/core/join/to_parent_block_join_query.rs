@@ -0,0 +1,363 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::join::{parents_bit_set, ScoreMode};
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::{Bits, DocId, FixedBitSet, ImmutableBitSet};
+
+use error::{ErrorKind::IllegalState, Result};
+
+pub const TO_PARENT_BLOCK_JOIN: &str = "to_parent_block_join";
+
+/// A query that matches the parent docs of the docs matching a child query.
+///
+/// The children and their parent must be indexed as a single block with
+/// `IndexWriter::add_documents`, the parent being the last doc of the block.
+/// `parents_filter` must match all the parent docs and only them, and the
+/// child query must not match any parent doc.
+///
+/// The score of a parent is computed from the scores of its matching children
+/// as defined by `score_mode`.
+pub struct ToParentBlockJoinQuery<C: Codec> {
+    child_query: Box<dyn Query<C>>,
+    parents_filter: Box<dyn Query<C>>,
+    score_mode: ScoreMode,
+}
+
+impl<C: Codec> ToParentBlockJoinQuery<C> {
+    pub fn new(
+        child_query: Box<dyn Query<C>>,
+        parents_filter: Box<dyn Query<C>>,
+        score_mode: ScoreMode,
+    ) -> ToParentBlockJoinQuery<C> {
+        ToParentBlockJoinQuery {
+            child_query,
+            parents_filter,
+            score_mode,
+        }
+    }
+
+    pub fn child_query(&self) -> &dyn Query<C> {
+        self.child_query.as_ref()
+    }
+
+    pub fn parents_filter(&self) -> &dyn Query<C> {
+        self.parents_filter.as_ref()
+    }
+
+    pub fn score_mode(&self) -> ScoreMode {
+        self.score_mode
+    }
+}
+
+impl<C: Codec> Query<C> for ToParentBlockJoinQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let needs_scores = needs_scores && self.score_mode.needs_scores();
+        Ok(Box::new(ToParentBlockJoinWeight {
+            child_weight: self.child_query.create_weight(searcher, needs_scores)?,
+            parents_weight: self.parents_filter.create_weight(searcher, false)?,
+            score_mode: self.score_mode,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.child_query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for ToParentBlockJoinQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ToParentBlockJoinQuery(child_query: {}, parents_filter: {}, score_mode: {})",
+            &self.child_query, &self.parents_filter, self.score_mode
+        )
+    }
+}
+
+struct ToParentBlockJoinWeight<C: Codec> {
+    child_weight: Box<dyn Weight<C>>,
+    parents_weight: Box<dyn Weight<C>>,
+    score_mode: ScoreMode,
+    needs_scores: bool,
+}
+
+impl<C: Codec> Weight<C> for ToParentBlockJoinWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let child_scorer = match self.child_weight.create_scorer(reader)? {
+            Some(scorer) => scorer,
+            None => return Ok(None),
+        };
+        if let Some(parent_bits) = parents_bit_set(self.parents_weight.as_ref(), reader)? {
+            let score_mode = if self.needs_scores {
+                self.score_mode
+            } else {
+                ScoreMode::None
+            };
+            Ok(Some(Box::new(ToParentBlockJoinScorer::new(
+                child_scorer,
+                parent_bits,
+                score_mode,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        TO_PARENT_BLOCK_JOIN
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.child_weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.child_weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        if let Some(mut scorer) = self.create_scorer(reader)? {
+            if scorer.advance(doc)? == doc {
+                let parent_bits = parents_bit_set(self.parents_weight.as_ref(), reader)?.unwrap();
+                let start = if doc == 0 {
+                    0
+                } else {
+                    parent_bits.prev_set_bit(doc as usize - 1) + 1
+                };
+                let mut child_explanations = vec![];
+                for child in start..doc {
+                    let explanation = self.child_weight.explain(reader, child)?;
+                    if explanation.is_match() {
+                        child_explanations.push(explanation);
+                    }
+                }
+                return Ok(Explanation::new(
+                    true,
+                    scorer.score()?,
+                    format!(
+                        "Score based on {} child docs in range from {} to {}, score mode {}:",
+                        child_explanations.len(),
+                        start + reader.doc_base,
+                        doc - 1 + reader.doc_base,
+                        self.score_mode
+                    ),
+                    child_explanations,
+                ));
+            }
+        }
+        Ok(Explanation::new(false, 0f32, "Not a match".into(), vec![]))
+    }
+}
+
+impl<C: Codec> fmt::Display for ToParentBlockJoinWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ToParentBlockJoinWeight(child_weight: {}, parents_weight: {}, score_mode: {})",
+            &self.child_weight, &self.parents_weight, self.score_mode
+        )
+    }
+}
+
+/// Iterates the parents of the docs of the child scorer, scoring each parent
+/// over all its matching children.
+struct ToParentBlockJoinScorer {
+    child_scorer: Box<dyn Scorer>,
+    parent_bits: FixedBitSet,
+    score_mode: ScoreMode,
+    parent_doc: DocId,
+    score: f32,
+    // number of matching children of the current parent
+    freq: usize,
+}
+
+impl ToParentBlockJoinScorer {
+    fn new(
+        child_scorer: Box<dyn Scorer>,
+        parent_bits: FixedBitSet,
+        score_mode: ScoreMode,
+    ) -> ToParentBlockJoinScorer {
+        ToParentBlockJoinScorer {
+            child_scorer,
+            parent_bits,
+            score_mode,
+            parent_doc: -1,
+            score: 0f32,
+            freq: 0,
+        }
+    }
+
+    /// Moves to the parent of the current child and aggregates the scores of
+    /// all its matching children, leaving the child scorer on the first child
+    /// of the next parents.
+    fn collect_children(&mut self) -> Result<DocId> {
+        let mut child_doc = self.child_scorer.doc_id();
+        if child_doc == NO_MORE_DOCS {
+            self.parent_doc = NO_MORE_DOCS;
+            return Ok(NO_MORE_DOCS);
+        }
+        if self.parent_bits.get(child_doc as usize)? {
+            bail!(IllegalState(format!(
+                "Child query must not match the same docs as the parents filter, doc: {}",
+                child_doc
+            )));
+        }
+        let parent_doc = self.parent_bits.next_set_bit(child_doc as usize);
+        if parent_doc == NO_MORE_DOCS {
+            // children after the last parent, they don't belong to any block
+            self.parent_doc = NO_MORE_DOCS;
+            return Ok(NO_MORE_DOCS);
+        }
+
+        self.score = 1.0;
+        self.freq = 0;
+        while child_doc < parent_doc {
+            if self.score_mode.needs_scores() {
+                let score = self.child_scorer.score()?;
+                self.score = if self.freq == 0 {
+                    score
+                } else {
+                    self.score_mode.combine(self.score, score)
+                };
+            }
+            self.freq += 1;
+            child_doc = self.child_scorer.next()?;
+        }
+        if self.score_mode == ScoreMode::Avg {
+            self.score /= self.freq as f32;
+        }
+        self.parent_doc = parent_doc;
+        Ok(parent_doc)
+    }
+}
+
+impl Scorer for ToParentBlockJoinScorer {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.score)
+    }
+}
+
+impl DocIterator for ToParentBlockJoinScorer {
+    fn doc_id(&self) -> DocId {
+        self.parent_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        if self.parent_doc == NO_MORE_DOCS {
+            return Ok(NO_MORE_DOCS);
+        }
+        if self.child_scorer.doc_id() == -1 {
+            self.child_scorer.next()?;
+        }
+        self.collect_children()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        if target as usize >= self.parent_bits.len() {
+            self.parent_doc = NO_MORE_DOCS;
+            return Ok(NO_MORE_DOCS);
+        }
+        // the children of the target parent follow the previous parent
+        let child_target = if target == 0 {
+            0
+        } else {
+            self.parent_bits.prev_set_bit(target as usize - 1) + 1
+        };
+        if self.child_scorer.doc_id() < child_target {
+            self.child_scorer.advance(child_target)?;
+        }
+        self.collect_children()
+    }
+
+    fn cost(&self) -> usize {
+        self.child_scorer.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+    use core::util::BitSet;
+
+    fn parent_bits(parents: &[usize], num_bits: usize) -> FixedBitSet {
+        let mut bits = FixedBitSet::new(num_bits);
+        for &parent in parents {
+            bits.set(parent);
+        }
+        bits
+    }
+
+    #[test]
+    fn test_to_parent_block_join_scorer() {
+        // blocks: [0, 1, 2] -> 3, [4] -> 5, [] -> 6, [7, 8] -> 9
+        let parents = [3, 5, 6, 9];
+
+        let mut scorer = ToParentBlockJoinScorer::new(
+            Box::new(create_mock_scorer(vec![1, 2, 7, 8, 10])),
+            parent_bits(&parents, 12),
+            ScoreMode::Avg,
+        );
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.freq, 2);
+        assert!((scorer.score().unwrap() - 1.5).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 9);
+        assert!((scorer.score().unwrap() - 7.5).abs() < ::std::f32::EPSILON);
+        // doc 10 is an orphan child
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+
+        let mut scorer = ToParentBlockJoinScorer::new(
+            Box::new(create_mock_scorer(vec![0, 2, 4, 7])),
+            parent_bits(&parents, 12),
+            ScoreMode::Max,
+        );
+        assert_eq!(scorer.advance(4).unwrap(), 5);
+        assert!((scorer.score().unwrap() - 4.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(6).unwrap(), 9);
+        assert!((scorer.score().unwrap() - 7.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+
+        let mut scorer = ToParentBlockJoinScorer::new(
+            Box::new(create_mock_scorer(vec![0, 2, 3])),
+            parent_bits(&parents, 12),
+            ScoreMode::Total,
+        );
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert!((scorer.score().unwrap() - 2.0).abs() < ::std::f32::EPSILON);
+        // the child query matches a parent
+        assert!(scorer.next().is_err());
+    }
+}
@@ -14,8 +14,13 @@
 pub mod analysis;
 pub mod codec;
 pub mod doc;
+pub mod expressions;
+pub mod facet;
+pub mod geo;
 pub mod highlight;
 pub mod index;
+pub mod join;
 pub mod search;
 pub mod store;
+pub mod suggest;
 pub mod util;
@@ -287,3 +287,97 @@ impl IntBag {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::reader::LeafReaderContext;
+    use core::search::scorer::Scorer;
+    use core::search::Explanation;
+    use core::util::DocId;
+    use std::fmt;
+
+    struct TypedWeight(&'static str);
+
+    impl Weight<TestCodec> for TypedWeight {
+        fn create_scorer(
+            &self,
+            _reader: &LeafReaderContext<'_, TestCodec>,
+        ) -> Result<Option<Box<dyn Scorer>>> {
+            unimplemented!()
+        }
+
+        fn query_type(&self) -> &'static str {
+            self.0
+        }
+
+        fn normalize(&mut self, _norm: f32, _boost: f32) {}
+
+        fn value_for_normalization(&self) -> f32 {
+            1.0
+        }
+
+        fn needs_scores(&self) -> bool {
+            false
+        }
+
+        fn explain(
+            &self,
+            _reader: &LeafReaderContext<'_, TestCodec>,
+            _doc: DocId,
+        ) -> Result<Explanation> {
+            unimplemented!()
+        }
+    }
+
+    impl fmt::Display for TypedWeight {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "TypedWeight({})", self.0)
+        }
+    }
+
+    #[test]
+    fn test_frequency_tracking_ring_buffer() {
+        let mut buffer = FrequencyTrackingRingBuffer::new(4, SENTINEL);
+        assert_eq!(buffer.frequency(SENTINEL), 4);
+        for i in &[1, 2, 1, 3] {
+            buffer.add(*i);
+        }
+        assert_eq!(buffer.frequency(1), 2);
+        assert_eq!(buffer.frequency(2), 1);
+        assert_eq!(buffer.frequency(SENTINEL), 0);
+
+        // the oldest entries are evicted first
+        buffer.add(4);
+        buffer.add(4);
+        assert_eq!(buffer.frequency(1), 1);
+        assert_eq!(buffer.frequency(2), 0);
+        assert_eq!(buffer.frequency(4), 2);
+    }
+
+    #[test]
+    fn test_usage_tracking_policy() {
+        let policy: &dyn QueryCachingPolicy<TestCodec> = &UsageTrackingQueryCachingPolicy::new(64);
+        let costly = TypedWeight(POINT_RANGE);
+        let cheap = TypedWeight(TERM);
+
+        policy.on_use(&costly);
+        assert!(!policy.should_cache(&costly).unwrap());
+        policy.on_use(&costly);
+        assert!(policy.should_cache(&costly).unwrap());
+
+        for _ in 0..19 {
+            policy.on_use(&cheap);
+        }
+        assert!(!policy.should_cache(&cheap).unwrap());
+        policy.on_use(&cheap);
+        assert!(policy.should_cache(&cheap).unwrap());
+
+        let match_all = TypedWeight(MATCH_ALL);
+        for _ in 0..10 {
+            policy.on_use(&match_all);
+        }
+        assert!(!policy.should_cache(&match_all).unwrap());
+    }
+}
@@ -349,6 +349,15 @@ impl<K: Clone + Hash + Eq, V> LRUCache<K, V> {
         }
         key
     }
+
+    /// Removes all the items of the cache.
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.entries.clear();
+        self.free_indexes.clear();
+        self.first = None;
+        self.last = None;
+    }
 }
 
 #[cfg(test)]
@@ -384,5 +393,11 @@ pub mod tests {
         cache.insert("6".into(), 6);
         assert_eq!(cache.peek("6"), Some(&6));
         assert_eq!(cache.entries.len(), 3);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.peek("6"), None);
+        cache.insert("7".into(), 7);
+        assert_eq!(cache.peek("7"), Some(&7));
+        assert_eq!(cache.len(), 1);
     }
 }
@@ -22,10 +22,10 @@ use core::index::reader::LeafReaderContext;
 use core::search::cache::{LRUCache, QueryCachingPolicy};
 use core::search::collector::Collector;
 use core::search::scorer::{BulkScorer, ConstantScoreScorer};
-use core::search::Explanation;
 use core::search::{
     query::Weight, scorer::two_phase_next, scorer::Scorer, DocIdSet, DocIterator, NO_MORE_DOCS,
 };
+use core::search::{Explanation, Matches};
 use core::util::external::Deferred;
 use core::util::UnsignedShift;
 use core::util::{
@@ -93,14 +93,23 @@ impl LeafCache {
         }
     }
 
-    pub fn put_if_absent(&mut self, query_key: &str, set: CacheDocIdSetEnum) {
+    /// Returns true if the set was added to the cache.
+    pub fn put_if_absent(&mut self, query_key: &str, set: CacheDocIdSetEnum) -> bool {
         if !self.leaf_cache.contains_key(query_key) {
             self.leaf_cache.insert(query_key.to_string(), set);
+            true
+        } else {
+            false
         }
     }
 
-    pub fn remove(&mut self, query_key: &str) {
-        self.leaf_cache.remove(query_key);
+    /// Returns true if a set was removed from the cache.
+    pub fn remove(&mut self, query_key: &str) -> bool {
+        self.leaf_cache.remove(query_key).is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.leaf_cache.len()
     }
 }
 
@@ -117,6 +126,11 @@ struct CacheData {
     max_size: usize,
     min_size: i32,
     min_size_ratio: f32,
+
+    hit_count: u64,
+    miss_count: u64,
+    cache_count: u64,
+    eviction_count: u64,
 }
 
 impl CacheData {
@@ -179,9 +193,13 @@ impl CacheData {
             true
         };
 
+        if self
+            .cache
+            .get_mut(key)
+            .unwrap()
+            .put_if_absent(&query_key, set)
         {
-            let leaf_cache = self.cache.get_mut(key).unwrap();
-            leaf_cache.put_if_absent(&query_key, set);
+            self.cache_count += 1;
         }
 
         Ok(new_entry)
@@ -211,9 +229,26 @@ impl CacheData {
 
     fn on_eviction(&mut self, query_key: &str) {
         for leaf_cache in self.cache.values_mut() {
-            leaf_cache.remove(query_key);
+            if leaf_cache.remove(query_key) {
+                self.eviction_count += 1;
+            }
+        }
+    }
+
+    /// Remove all the cache entries of the given segment core.
+    fn clear_core_cache_key(&mut self, core_key: &str) {
+        if let Some(leaf_cache) = self.cache.remove(core_key) {
+            self.eviction_count += leaf_cache.len() as u64;
         }
     }
+
+    fn clear(&mut self) {
+        for leaf_cache in self.cache.values() {
+            self.eviction_count += leaf_cache.len() as u64;
+        }
+        self.cache.clear();
+        self.unique_queries.clear();
+    }
 }
 
 /// A [`QueryCache`] that evicts queries using a LRU (least-recently-used)
@@ -231,20 +266,78 @@ pub struct LRUQueryCache {
 }
 
 impl LRUQueryCache {
+    /// Create a new instance that will cache at most `max_size` queries, only on
+    /// the segments that have more than 10000 docs and at least 3% of the docs of
+    /// the index.
     pub fn new(max_size: usize) -> LRUQueryCache {
-        // let max_size = 10;
+        Self::with_min_segment_size(max_size, 10000, 0.03f32)
+    }
+
+    /// Create a new instance that will cache at most `max_size` queries, only on
+    /// the segments that have at least `min_size` docs and at least
+    /// `min_size_ratio` of the docs of the index.
+    pub fn with_min_segment_size(
+        max_size: usize,
+        min_size: i32,
+        min_size_ratio: f32,
+    ) -> LRUQueryCache {
         let cache_data = CacheData {
             unique_queries: LRUCache::with_capacity(max_size),
             cache: HashMap::new(),
             max_size,
-            min_size: 10000,
-            min_size_ratio: 0.03f32,
+            min_size,
+            min_size_ratio,
+            hit_count: 0,
+            miss_count: 0,
+            cache_count: 0,
+            eviction_count: 0,
         };
 
         LRUQueryCache {
             cache_data: Arc::new(RwLock::new(cache_data)),
         }
     }
+
+    /// Over the total number of times that a query has been looked up, return how
+    /// many times a cached `DocIdSet` has been found and returned.
+    pub fn hit_count(&self) -> u64 {
+        self.cache_data.read().map_or(0, |d| d.hit_count)
+    }
+
+    /// Over the total number of times that a query has been looked up, return how
+    /// many times this query was not contained in the cache.
+    pub fn miss_count(&self) -> u64 {
+        self.cache_data.read().map_or(0, |d| d.miss_count)
+    }
+
+    /// Return the total number of times that a query has been looked up in this
+    /// cache, that is `hit_count() + miss_count()`.
+    pub fn total_count(&self) -> u64 {
+        self.hit_count() + self.miss_count()
+    }
+
+    /// Return the total number of cache entries that have been generated and put
+    /// in the cache, including the evicted ones.
+    pub fn cache_count(&self) -> u64 {
+        self.cache_data.read().map_or(0, |d| d.cache_count)
+    }
+
+    /// Return the number of cache entries that have been removed from the cache,
+    /// either because of evictions or because the segment was closed.
+    pub fn eviction_count(&self) -> u64 {
+        self.cache_data.read().map_or(0, |d| d.eviction_count)
+    }
+
+    /// Return the number of `DocIdSet`s which are currently stored in the cache.
+    pub fn cache_size(&self) -> u64 {
+        self.cache_count() - self.eviction_count()
+    }
+
+    /// Clear the content of this cache.
+    pub fn clear(&self) -> Result<()> {
+        self.cache_data.write()?.clear();
+        Ok(())
+    }
 }
 
 impl<C: Codec> QueryCache<C> for LRUQueryCache {
@@ -399,7 +492,7 @@ impl<C: Codec> CachingWrapperWeight<C> {
                         .reader
                         .add_core_drop_listener(Deferred::new(move || {
                             let core_key = key;
-                            cache_data.write().unwrap().cache.remove(&core_key);
+                            cache_data.write().unwrap().clear_core_cache_key(&core_key);
                         }))
                 }
 
@@ -408,15 +501,21 @@ impl<C: Codec> CachingWrapperWeight<C> {
             None => Ok(None),
         }
     }
-}
-
-static CACHING_QUERY_TYPE_STR: &str = "CachingWrapperWeight";
 
-impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
-    fn create_scorer(
+    /// Creates the scorer from the cache if possible, otherwise with the wrapped
+    /// weight, passing it the lead cost if any.
+    fn create_cached_scorer(
         &self,
         leaf_reader: &LeafReaderContext<'_, C>,
+        lead_cost: Option<usize>,
     ) -> Result<Option<Box<dyn Scorer>>> {
+        let create_uncached_scorer = || match lead_cost {
+            Some(lead_cost) => self
+                .weight
+                .create_scorer_with_lead_cost(leaf_reader, lead_cost),
+            None => self.weight.create_scorer(leaf_reader),
+        };
+
         if !self.used.compare_and_swap(false, true, Ordering::AcqRel) {
             self.policy.on_use(self)
         }
@@ -424,7 +523,7 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
         // Short-circuit: Check whether this segment is eligible for caching
         // before we take a lock because of #get
         if !self.should_cache(leaf_reader)? {
-            return self.weight.create_scorer(leaf_reader);
+            return create_uncached_scorer();
         }
 
         {
@@ -432,12 +531,14 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
             match self.cache_data.try_write() {
                 Ok(mut cache_data) => {
                     if let Some(disi) = cache_data.get(&self.query_key, leaf_reader)? {
+                        cache_data.hit_count += 1;
                         let cost = disi.cost();
                         return Ok(Some(Box::new(ConstantScoreScorer::new(0.0f32, disi, cost))));
                     }
+                    cache_data.miss_count += 1;
                 }
                 _ => {
-                    return self.weight.create_scorer(leaf_reader);
+                    return create_uncached_scorer();
                 }
             }
         }
@@ -451,9 +552,20 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
                 None => Ok(None),
             }
         } else {
-            self.weight.create_scorer(leaf_reader)
+            create_uncached_scorer()
         }
     }
+}
+
+static CACHING_QUERY_TYPE_STR: &str = "CachingWrapperWeight";
+
+impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
+    fn create_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.create_cached_scorer(leaf_reader, None)
+    }
 
     fn hash_code(&self) -> u32 {
         self.hash_code
@@ -463,6 +575,26 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
         CACHING_QUERY_TYPE_STR
     }
 
+    fn count(&self, leaf_reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        self.weight.count(leaf_reader)
+    }
+
+    fn estimate_cost(&self, leaf_reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        self.weight.estimate_cost(leaf_reader)
+    }
+
+    fn uses_lead_cost(&self) -> bool {
+        self.weight.uses_lead_cost()
+    }
+
+    fn create_scorer_with_lead_cost(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        lead_cost: usize,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.create_cached_scorer(leaf_reader, Some(lead_cost))
+    }
+
     fn actual_query_type(&self) -> &'static str {
         self.weight.query_type()
     }
@@ -509,6 +641,10 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
             ))
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
 }
 
 impl<C: Codec> fmt::Display for CachingWrapperWeight<C> {
@@ -944,3 +1080,109 @@ impl DocIterator for CachedDocIdSetIterEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::doc::StringField;
+    use core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
+    use core::index::reader::{IndexReader, StandardDirectoryReader};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::cache::AlwaysCacheQueryCachingPolicy;
+    use core::search::tests::MockSimpleWeight;
+    use core::store::directory::FSDirectory;
+
+    type TestReader =
+        StandardDirectoryReader<FSDirectory, TestCodec, SerialMergeScheduler, TieredMergePolicy>;
+
+    fn docs(weight: &dyn Weight<TestCodec>, leaf: &LeafReaderContext<'_, TestCodec>) -> Vec<DocId> {
+        let mut scorer = weight.create_scorer(leaf).unwrap().unwrap();
+        let mut docs = vec![];
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                return docs;
+            }
+            docs.push(doc);
+        }
+    }
+
+    // hit, miss, cache, eviction counts and cache size
+    fn stats(cache: &LRUQueryCache) -> (u64, u64, u64, u64, u64) {
+        (
+            cache.hit_count(),
+            cache.miss_count(),
+            cache.cache_count(),
+            cache.eviction_count(),
+            cache.cache_size(),
+        )
+    }
+
+    #[test]
+    fn test_cache_stats() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        {
+            let config = Arc::new(IndexWriterConfig::default());
+            let writer = IndexWriter::new(Arc::clone(&directory), config).unwrap();
+            for i in 0..20 {
+                writer
+                    .add_document(vec![StringField::new("id", &i.to_string(), false)])
+                    .unwrap();
+            }
+            writer.commit().unwrap();
+        }
+
+        let policy: Arc<dyn QueryCachingPolicy<TestCodec>> =
+            Arc::new(AlwaysCacheQueryCachingPolicy::default());
+        let cache = LRUQueryCache::with_min_segment_size(2, 10, 0.0);
+        let large_segments_only = LRUQueryCache::with_min_segment_size(2, 100, 0.0);
+        let weights: Vec<_> = vec![vec![1, 3, 5], vec![2, 4], vec![0, 19]]
+            .into_iter()
+            .map(|ids| {
+                let weight: Box<dyn Weight<TestCodec>> = Box::new(MockSimpleWeight::new(ids));
+                cache.do_cache(weight, Arc::clone(&policy))
+            })
+            .collect();
+
+        let reader = TestReader::open(Arc::clone(&directory)).unwrap();
+        {
+            let leaves = reader.leaves();
+            assert_eq!(leaves.len(), 1);
+            let leaf = &leaves[0];
+
+            // the segment is below the size threshold, the cache is not looked up
+            let weight: Box<dyn Weight<TestCodec>> = Box::new(MockSimpleWeight::new(vec![1]));
+            let weight = large_segments_only.do_cache(weight, Arc::clone(&policy));
+            assert_eq!(docs(weight.as_ref(), leaf), vec![1]);
+            assert_eq!(stats(&large_segments_only), (0, 0, 0, 0, 0));
+
+            assert_eq!(docs(weights[0].as_ref(), leaf), vec![1, 3, 5]);
+            assert_eq!(stats(&cache), (0, 1, 1, 0, 1));
+            assert_eq!(docs(weights[0].as_ref(), leaf), vec![1, 3, 5]);
+            assert_eq!(stats(&cache), (1, 1, 1, 0, 1));
+            assert_eq!(docs(weights[1].as_ref(), leaf), vec![2, 4]);
+            assert_eq!(stats(&cache), (1, 2, 2, 0, 2));
+
+            // at most 2 queries are cached, the least recently used one is evicted
+            assert_eq!(docs(weights[2].as_ref(), leaf), vec![0, 19]);
+            assert_eq!(stats(&cache), (1, 3, 3, 1, 2));
+            assert_eq!(docs(weights[0].as_ref(), leaf), vec![1, 3, 5]);
+            assert_eq!(stats(&cache), (1, 4, 4, 2, 2));
+            assert_eq!(docs(weights[2].as_ref(), leaf), vec![0, 19]);
+            assert_eq!(stats(&cache), (2, 4, 4, 2, 2));
+
+            cache.clear().unwrap();
+            assert_eq!(stats(&cache), (2, 4, 4, 4, 0));
+            assert_eq!(docs(weights[1].as_ref(), leaf), vec![2, 4]);
+            assert_eq!(stats(&cache), (2, 5, 5, 4, 1));
+        }
+
+        // dropping the segment core evicts its entries
+        drop(reader);
+        assert_eq!(stats(&cache), (2, 5, 5, 5, 0));
+    }
+}
@@ -0,0 +1,463 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{self, Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+use error::{
+    Error,
+    ErrorKind::{self, IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// The hits recorded for a single segment.
+struct CachedSegment {
+    ord: usize,
+    docs: Vec<DocId>,
+    scores: Vec<f32>,
+}
+
+impl CachedSegment {
+    fn new(ord: usize) -> CachedSegment {
+        CachedSegment {
+            ord,
+            docs: vec![],
+            scores: vec![],
+        }
+    }
+
+    /// Records the current hit, returns false once the RAM budget is exhausted.
+    fn add<S: Scorer + ?Sized>(
+        &mut self,
+        doc: DocId,
+        scorer: &mut S,
+        budget: &CacheBudget,
+    ) -> Result<bool> {
+        if !budget.acquire() {
+            self.docs = vec![];
+            self.scores = vec![];
+            return Ok(false);
+        }
+        self.docs.push(doc);
+        if budget.cache_scores {
+            self.scores.push(scorer.score()?);
+        }
+        Ok(true)
+    }
+}
+
+/// RAM budget shared by all the leaf collectors of a `CachingCollector`.
+#[derive(Clone)]
+struct CacheBudget {
+    cache_scores: bool,
+    max_docs_to_cache: usize,
+    cached_docs: Arc<AtomicUsize>,
+    overflowed: Arc<AtomicBool>,
+}
+
+impl CacheBudget {
+    fn acquire(&self) -> bool {
+        if self.overflowed.load(Ordering::Acquire) {
+            return false;
+        }
+        if self.cached_docs.fetch_add(1, Ordering::AcqRel) >= self.max_docs_to_cache {
+            self.overflowed.store(true, Ordering::Release);
+            return false;
+        }
+        true
+    }
+
+    fn overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Acquire)
+    }
+}
+
+/// Caches all docs, and optionally also scores, coming from
+/// a search, and is then able to replay them to another
+/// collector. You specify the max RAM this class may use.
+/// Once the collection is done, call `is_cached`. If
+/// this returns true, you can use `replay` against a new
+/// collector. If it returns false, this means too much RAM was
+/// required and you must instead re-run the original search.
+///
+/// The hits are forwarded to the wrapped collector while they are
+/// recorded, so the first pass doesn't need to be run twice.
+///
+/// *NOTE*: this class caches at least an int per collected doc.
+/// If scores are cached, it also caches a float per collected doc.
+pub struct CachingCollector<T: SearchCollector> {
+    collector: T,
+    budget: CacheBudget,
+    segments: Vec<CachedSegment>,
+    channel: Option<(Sender<CachedSegment>, Receiver<CachedSegment>)>,
+}
+
+impl<T: SearchCollector> CachingCollector<T> {
+    /// Create a new `CachingCollector` that wraps the given collector and
+    /// caches documents and scores up to the specified RAM threshold.
+    ///
+    /// `max_ram_mb` is the maximum RAM in MB to consume for caching the
+    /// documents and scores. If the collector exceeds the threshold, no
+    /// documents and scores are cached.
+    pub fn new(collector: T, cache_scores: bool, max_ram_mb: f64) -> CachingCollector<T> {
+        let bytes_per_doc = if cache_scores { 8.0 } else { 4.0 };
+        let max_docs_to_cache = (max_ram_mb * 1024.0 * 1024.0 / bytes_per_doc).max(0.0) as usize;
+        Self::with_max_docs(collector, cache_scores, max_docs_to_cache)
+    }
+
+    /// Create a new `CachingCollector` that caches at most `max_docs_to_cache` hits.
+    pub fn with_max_docs(
+        collector: T,
+        cache_scores: bool,
+        max_docs_to_cache: usize,
+    ) -> CachingCollector<T> {
+        CachingCollector {
+            collector,
+            budget: CacheBudget {
+                cache_scores,
+                max_docs_to_cache,
+                cached_docs: Arc::new(AtomicUsize::new(0)),
+                overflowed: Arc::new(AtomicBool::new(false)),
+            },
+            segments: vec![],
+            channel: None,
+        }
+    }
+
+    /// Return true if this collector is able to replay collection.
+    pub fn is_cached(&self) -> bool {
+        !self.budget.overflowed()
+    }
+
+    pub fn cache_scores(&self) -> bool {
+        self.budget.cache_scores
+    }
+
+    pub fn collector(&self) -> &T {
+        &self.collector
+    }
+
+    pub fn collector_mut(&mut self) -> &mut T {
+        &mut self.collector
+    }
+
+    pub fn into_inner(self) -> T {
+        self.collector
+    }
+
+    /// Replays the cached doc IDs (and scores) to the given collector.
+    ///
+    /// `leaves` must be the leaves of the reader the hits were collected on.
+    /// Fails with `IllegalState` if the RAM budget was exceeded during the
+    /// collection, and with `IllegalArgument` if `other` needs scores but
+    /// they were not cached.
+    pub fn replay<C: Codec, O: SearchCollector>(
+        &self,
+        leaves: &[LeafReaderContext<'_, C>],
+        other: &mut O,
+    ) -> Result<()> {
+        if !self.is_cached() {
+            bail!(IllegalState(
+                "cannot replay: cache was cleared because too much RAM was required".into()
+            ));
+        }
+        if other.needs_scores() && !self.budget.cache_scores {
+            bail!(IllegalArgument(
+                "cannot replay scores to a collector that needs them: scores were not cached"
+                    .into()
+            ));
+        }
+
+        for segment in &self.segments {
+            if segment.ord >= leaves.len() {
+                bail!(IllegalArgument(format!(
+                    "cached segment {} is out of the {} leaves to replay on",
+                    segment.ord,
+                    leaves.len()
+                )));
+            }
+            other.set_next_reader(&leaves[segment.ord])?;
+            let mut scorer = CachedScorer::new(segment);
+            loop {
+                let doc = scorer.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                match other.collect(doc, &mut scorer) {
+                    Ok(()) => {}
+                    Err(Error(
+                        ErrorKind::Collector(collector::ErrorKind::LeafCollectionTerminated),
+                        _,
+                    )) => break,
+                    Err(Error(
+                        ErrorKind::Collector(collector::ErrorKind::CollectionTerminated),
+                        _,
+                    )) => return Ok(()),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: SearchCollector> SearchCollector for CachingCollector<T> {
+    type LC = CachingLeafCollector<T::LC>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.collector.set_next_reader(reader)?;
+        if !self.budget.overflowed() {
+            self.segments.push(CachedSegment::new(reader.ord));
+        }
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        self.collector.support_parallel()
+    }
+
+    fn init_parallel(&mut self) {
+        self.collector.init_parallel();
+        if self.channel.is_none() {
+            self.channel = Some(unbounded());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<CachingLeafCollector<T::LC>> {
+        Ok(CachingLeafCollector {
+            collector: self.collector.leaf_collector(reader)?,
+            budget: self.budget.clone(),
+            segment: CachedSegment::new(reader.ord),
+            sender: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        self.collector.finish_parallel()?;
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(segment) = receiver.recv() {
+                self.segments.push(segment);
+            }
+        }
+        if self.budget.overflowed() {
+            self.segments.clear();
+        } else {
+            self.segments.sort_by_key(|s| s.ord);
+        }
+        Ok(())
+    }
+}
+
+impl<T: SearchCollector> Collector for CachingCollector<T> {
+    fn needs_scores(&self) -> bool {
+        self.budget.cache_scores || self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        if !self.budget.overflowed() {
+            let cached = match self.segments.last_mut() {
+                Some(segment) => segment.add(doc, scorer, &self.budget)?,
+                None => bail!(IllegalState("set_next_reader must be called first".into())),
+            };
+            if !cached {
+                self.segments.clear();
+            }
+        }
+        self.collector.collect(doc, scorer)
+    }
+}
+
+pub struct CachingLeafCollector<T: ParallelLeafCollector> {
+    collector: T,
+    budget: CacheBudget,
+    segment: CachedSegment,
+    sender: Sender<CachedSegment>,
+}
+
+impl<T: ParallelLeafCollector> Collector for CachingLeafCollector<T> {
+    fn needs_scores(&self) -> bool {
+        self.budget.cache_scores || self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        if !self.budget.overflowed() {
+            self.segment.add(doc, scorer, &self.budget)?;
+        }
+        self.collector.collect(doc, scorer)
+    }
+}
+
+impl<T: ParallelLeafCollector> ParallelLeafCollector for CachingLeafCollector<T> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        self.collector.finish_leaf()?;
+        if self.budget.overflowed() {
+            return Ok(());
+        }
+        let segment = ::std::mem::replace(&mut self.segment, CachedSegment::new(0));
+        self.sender
+            .send(segment)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+/// Iterates over the cached hits of a segment while replaying them.
+struct CachedScorer<'a> {
+    segment: &'a CachedSegment,
+    // index of the current hit, `-1` before the iteration starts
+    index: isize,
+}
+
+impl<'a> CachedScorer<'a> {
+    fn new(segment: &'a CachedSegment) -> CachedScorer<'a> {
+        CachedScorer { segment, index: -1 }
+    }
+}
+
+impl<'a> Scorer for CachedScorer<'a> {
+    fn score(&mut self) -> Result<f32> {
+        match self.segment.scores.get(self.index as usize) {
+            Some(score) => Ok(*score),
+            None => bail!(IllegalState("scores were not cached".into())),
+        }
+    }
+}
+
+impl<'a> DocIterator for CachedScorer<'a> {
+    fn doc_id(&self) -> DocId {
+        if self.index < 0 {
+            -1
+        } else if self.index as usize >= self.segment.docs.len() {
+            NO_MORE_DOCS
+        } else {
+            self.segment.docs[self.index as usize]
+        }
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        if self.index < self.segment.docs.len() as isize {
+            self.index += 1;
+        }
+        Ok(self.doc_id())
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.slow_advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.segment.docs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::collector::{TopDocsCollector, TotalHitCountCollector};
+    use core::search::tests::*;
+
+    fn collect_leaf<T: Collector>(collector: &mut T, docs: Vec<DocId>) -> Result<()> {
+        let mut scorer = create_mock_scorer(docs);
+        loop {
+            let doc = scorer.next()?;
+            if doc == NO_MORE_DOCS {
+                return Ok(());
+            }
+            collector.collect(doc, &mut scorer)?;
+        }
+    }
+
+    #[test]
+    fn test_caching_collector_replay() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let mut collector = CachingCollector::new(TopDocsCollector::new(10), true, 1.0);
+        assert!(collector.needs_scores());
+        collector.set_next_reader(&leaves[0]).unwrap();
+        collect_leaf(&mut collector, vec![1, 3]).unwrap();
+
+        // the second leaf is collected in parallel
+        collector.init_parallel();
+        let mut leaf_collector = collector.leaf_collector(&leaves[1]).unwrap();
+        collect_leaf(&mut leaf_collector, vec![2, 4]).unwrap();
+        leaf_collector.finish_leaf().unwrap();
+        drop(leaf_collector);
+        collector.finish_parallel().unwrap();
+        assert!(collector.is_cached());
+
+        let first_pass = collector.collector_mut().top_docs();
+        let mut replayed = TopDocsCollector::new(10);
+        collector.replay(&leaves, &mut replayed).unwrap();
+        let second_pass = replayed.top_docs();
+        assert_eq!(first_pass.total_hits(), 4);
+        assert_eq!(second_pass.total_hits(), 4);
+
+        let docs: Vec<(DocId, f32)> = second_pass
+            .score_docs()
+            .iter()
+            .map(|d| (d.doc_id(), d.score()))
+            .collect();
+        assert_eq!(docs, vec![(14, 4.0), (3, 3.0), (12, 2.0), (1, 1.0)]);
+    }
+
+    #[test]
+    fn test_caching_collector_overflow() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let mut collector = CachingCollector::with_max_docs(TopDocsCollector::new(10), false, 2);
+        collector.set_next_reader(&leaves[0]).unwrap();
+        collect_leaf(&mut collector, vec![1, 2, 3]).unwrap();
+        assert!(!collector.is_cached());
+        // the wrapped collector still sees all the hits
+        assert_eq!(collector.collector_mut().top_docs().total_hits(), 3);
+
+        let mut replayed = TopDocsCollector::new(10);
+        assert!(collector.replay(&leaves, &mut replayed).is_err());
+    }
+
+    #[test]
+    fn test_caching_collector_without_scores() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let mut collector = CachingCollector::new(TotalHitCountCollector::new(), false, 1.0);
+        assert!(!collector.needs_scores());
+        collector.set_next_reader(&leaves[0]).unwrap();
+        collect_leaf(&mut collector, vec![1, 2, 3]).unwrap();
+        assert!(collector.is_cached());
+
+        // scores were not cached
+        let mut top_docs = TopDocsCollector::new(10);
+        assert!(collector.replay(&leaves, &mut top_docs).is_err());
+
+        let mut count = TotalHitCountCollector::new();
+        collector.replay(&leaves, &mut count).unwrap();
+        assert_eq!(count.total_hits(), 3);
+    }
+}
@@ -0,0 +1,372 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::grouping::GroupSelector;
+use core::search::scorer::Scorer;
+use core::search::sort_field::{ScoreDoc, ScoreDocHit, TopDocs, TopScoreDocs};
+use core::util::{DocId, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// A collected hit, ordered from the least to the most competitive: by score,
+/// then by reversed doc id.
+#[derive(Clone, Copy, Debug)]
+struct DiversifiedHit {
+    doc: DocId,
+    score: f32,
+}
+
+impl Ord for DiversifiedHit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.doc.cmp(&self.doc))
+    }
+}
+
+impl PartialOrd for DiversifiedHit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for DiversifiedHit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DiversifiedHit {}
+
+/// Holds the top hits, with at most `max_hits_per_key` hits for each key.
+struct DiversifiedQueue {
+    num_hits: usize,
+    max_hits_per_key: usize,
+    hits: BTreeSet<DiversifiedHit>,
+    hits_per_key: HashMap<Option<VariantValue>, BTreeSet<DiversifiedHit>>,
+    keys: HashMap<DocId, Option<VariantValue>>,
+    total_hits: usize,
+}
+
+impl DiversifiedQueue {
+    fn new(num_hits: usize, max_hits_per_key: usize) -> DiversifiedQueue {
+        DiversifiedQueue {
+            num_hits,
+            max_hits_per_key,
+            hits: BTreeSet::new(),
+            hits_per_key: HashMap::new(),
+            keys: HashMap::new(),
+            total_hits: 0,
+        }
+    }
+
+    /// Returns false if the hit can't make it into the top hits, whatever its key.
+    fn is_competitive(&self, hit: &DiversifiedHit) -> bool {
+        if self.hits.len() < self.num_hits {
+            return true;
+        }
+        match self.hits.iter().next() {
+            Some(worst) => hit > worst,
+            None => false,
+        }
+    }
+
+    fn add(&mut self, hit: DiversifiedHit, key: Option<VariantValue>) {
+        if !self.is_competitive(&hit) {
+            return;
+        }
+
+        // the key is full, the hit replaces the worst one of the same key
+        let key_hits = self
+            .hits_per_key
+            .entry(key.clone())
+            .or_insert_with(BTreeSet::new);
+        if key_hits.len() >= self.max_hits_per_key {
+            let worst = *key_hits.iter().next().unwrap();
+            if hit <= worst {
+                return;
+            }
+            key_hits.remove(&worst);
+            key_hits.insert(hit);
+            self.hits.remove(&worst);
+            self.keys.remove(&worst.doc);
+            self.hits.insert(hit);
+            self.keys.insert(hit.doc, key);
+            return;
+        }
+        key_hits.insert(hit);
+
+        // the queue is full, the hit replaces the worst one of all keys
+        if self.hits.len() >= self.num_hits {
+            let worst = *self.hits.iter().next().unwrap();
+            self.hits.remove(&worst);
+            let worst_key = self.keys.remove(&worst.doc).unwrap();
+            let remove_key = {
+                let worst_key_hits = self.hits_per_key.get_mut(&worst_key).unwrap();
+                worst_key_hits.remove(&worst);
+                worst_key_hits.is_empty()
+            };
+            if remove_key {
+                self.hits_per_key.remove(&worst_key);
+            }
+        }
+        self.hits.insert(hit);
+        self.keys.insert(hit.doc, key);
+    }
+
+    fn merge(&mut self, mut other: DiversifiedQueue) {
+        self.total_hits += other.total_hits;
+        for hit in &other.hits {
+            let key = other.keys.remove(&hit.doc).unwrap();
+            self.add(*hit, key);
+        }
+    }
+
+    fn top_docs(&self) -> TopDocs {
+        let score_docs = self
+            .hits
+            .iter()
+            .rev()
+            .map(|hit| ScoreDocHit::Score(ScoreDoc::new(hit.doc, hit.score)))
+            .collect();
+        TopDocs::Score(TopScoreDocs::new(self.total_hits, score_docs))
+    }
+}
+
+/// A `SearchCollector` that, like `TopDocsCollector`, collects the top scoring
+/// docs, but which limits the number of hits sharing the same key, e.g. the
+/// value of a doc values field, so that the results are not dominated by a
+/// single source or author.
+///
+/// The key of each competitive doc is read by a `GroupSelector`, the docs
+/// without a value share the `None` key.
+pub struct DiversifiedTopDocsCollector<G: GroupSelector> {
+    queue: DiversifiedQueue,
+    selector: G,
+    leaf_selector: Option<G>,
+    doc_base: DocId,
+    channel: Option<(Sender<DiversifiedQueue>, Receiver<DiversifiedQueue>)>,
+}
+
+impl<G: GroupSelector> DiversifiedTopDocsCollector<G> {
+    pub fn new(
+        num_hits: usize,
+        max_hits_per_key: usize,
+        selector: G,
+    ) -> Result<DiversifiedTopDocsCollector<G>> {
+        if max_hits_per_key == 0 {
+            bail!(IllegalArgument("max_hits_per_key must be positive".into()));
+        }
+        Ok(DiversifiedTopDocsCollector {
+            queue: DiversifiedQueue::new(num_hits, max_hits_per_key),
+            selector,
+            leaf_selector: None,
+            doc_base: 0,
+            channel: None,
+        })
+    }
+
+    /// Returns the top docs that were collected by this collector.
+    pub fn top_docs(&self) -> TopDocs {
+        self.queue.top_docs()
+    }
+}
+
+impl<G: GroupSelector> SearchCollector for DiversifiedTopDocsCollector<G> {
+    type LC = DiversifiedLeafCollector<G>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.leaf_selector = Some(self.selector.leaf_selector(reader)?);
+        self.doc_base = reader.doc_base;
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<DiversifiedLeafCollector<G>> {
+        Ok(DiversifiedLeafCollector {
+            queue: DiversifiedQueue::new(self.queue.num_hits, self.queue.max_hits_per_key),
+            selector: self.selector.leaf_selector(reader)?,
+            doc_base: reader.doc_base,
+            sender: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        // the top hits of the whole index are among the top hits of the segments,
+        // as a hit can't be filtered by its key in a segment and not in the index
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(queue) = receiver.recv() {
+                self.queue.merge(queue);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<G: GroupSelector> Collector for DiversifiedTopDocsCollector<G> {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        debug_assert!(self.leaf_selector.is_some());
+        collect_hit(
+            &mut self.queue,
+            self.leaf_selector.as_mut().unwrap(),
+            self.doc_base,
+            doc,
+            scorer,
+        )
+    }
+}
+
+fn collect_hit<G: GroupSelector, S: Scorer + ?Sized>(
+    queue: &mut DiversifiedQueue,
+    selector: &mut G,
+    doc_base: DocId,
+    doc: DocId,
+    scorer: &mut S,
+) -> Result<()> {
+    queue.total_hits += 1;
+    let hit = DiversifiedHit {
+        doc: doc + doc_base,
+        score: scorer.score()?,
+    };
+    // only read the key of the competitive hits
+    if queue.is_competitive(&hit) {
+        let key = selector.group_value(doc)?;
+        queue.add(hit, key);
+    }
+    Ok(())
+}
+
+pub struct DiversifiedLeafCollector<G: GroupSelector> {
+    queue: DiversifiedQueue,
+    selector: G,
+    doc_base: DocId,
+    sender: Sender<DiversifiedQueue>,
+}
+
+impl<G: GroupSelector> Collector for DiversifiedLeafCollector<G> {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        collect_hit(
+            &mut self.queue,
+            &mut self.selector,
+            self.doc_base,
+            doc,
+            scorer,
+        )
+    }
+}
+
+impl<G: GroupSelector> ParallelLeafCollector for DiversifiedLeafCollector<G> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let queue = DiversifiedQueue::new(self.queue.num_hits, self.queue.max_hits_per_key);
+        let queue = ::std::mem::replace(&mut self.queue, queue);
+        self.sender
+            .send(queue)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::grouping::tests::*;
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    fn doc_ids(top_docs: &TopDocs) -> Vec<DocId> {
+        top_docs.score_docs().iter().map(|d| d.doc_id()).collect()
+    }
+
+    #[test]
+    fn test_diversified_top_docs() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(20)]);
+        let leaves = index_reader.leaves();
+
+        // at most 2 hits for each doc id modulo 3
+        let mut collector =
+            DiversifiedTopDocsCollector::new(4, 2, ModGroupSelector::new(3)).unwrap();
+        collect_segments(&mut collector, &leaves, vec![(1..13).collect()]);
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 12);
+        assert_eq!(doc_ids(&top_docs), vec![12, 11, 10, 9]);
+
+        let mut collector =
+            DiversifiedTopDocsCollector::new(4, 1, ModGroupSelector::new(3)).unwrap();
+        collect_segments(&mut collector, &leaves, vec![(1..13).collect()]);
+        assert_eq!(doc_ids(&collector.top_docs()), vec![12, 11, 10]);
+
+        assert!(DiversifiedTopDocsCollector::new(4, 0, ModGroupSelector::new(3)).is_err());
+    }
+
+    #[test]
+    fn test_diversified_top_docs_parallel() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        // at most one hit for each doc id modulo 4
+        let mut collector =
+            DiversifiedTopDocsCollector::new(3, 1, ModGroupSelector::new(4)).unwrap();
+        collector.init_parallel();
+        for (leaf, docs) in leaves.iter().zip(vec![vec![1, 5, 9], vec![2, 3, 7]]) {
+            let mut leaf_collector = collector.leaf_collector(leaf).unwrap();
+            let mut scorer = create_mock_scorer(docs);
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                leaf_collector.collect(doc, &mut scorer).unwrap();
+            }
+            leaf_collector.finish_leaf().unwrap();
+        }
+        collector.finish_parallel().unwrap();
+
+        // all the hits but 12 share the key 1 of the best hit 9
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 6);
+        assert_eq!(doc_ids(&top_docs), vec![9, 12]);
+    }
+}
@@ -15,6 +15,10 @@ mod top_docs;
 
 pub use self::top_docs::*;
 
+mod top_field;
+
+pub use self::top_field::*;
+
 mod early_terminating;
 
 pub use self::early_terminating::*;
@@ -27,6 +31,18 @@ mod chain;
 
 pub use self::chain::*;
 
+mod total_hit_count;
+
+pub use self::total_hit_count::*;
+
+mod caching;
+
+pub use self::caching::*;
+
+mod diversified;
+
+pub use self::diversified::*;
+
 use error::Result;
 
 use core::codec::Codec;
@@ -118,6 +134,18 @@ pub trait Collector {
     /// return `true` if scores are needed.
     fn needs_scores(&self) -> bool;
 
+    /// Indicates if this collector only keeps the top scoring docs and feeds the
+    /// score they have to beat back to the scorer through
+    /// `Scorer::set_min_competitive_score`, which lets the scorer skip the docs
+    /// that can't be competitive, see `Weight::create_top_scores_scorer`.
+    ///
+    /// A collector wrapping other ones may only forward it when it doesn't need to
+    /// see every hit itself: the chained and caching collectors don't, the
+    /// `TimeLimitingCollector` does.
+    fn needs_top_scores_only(&self) -> bool {
+        false
+    }
+
     /// Called once for every document matching a query, with the unbased document
     /// number.
     /// Note: The collection of the current segment can be terminated by throwing
@@ -136,6 +164,10 @@ impl<'a, T: Collector + 'a> Collector for &'a mut T {
         (**self).needs_scores()
     }
 
+    fn needs_top_scores_only(&self) -> bool {
+        (**self).needs_top_scores_only()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         (**self).collect(doc, scorer)
     }
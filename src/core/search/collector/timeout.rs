@@ -14,13 +14,17 @@
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::collector;
-use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::collector::{
+    Collector, ParallelLeafCollector, SearchCollector, TopDocsCollector, TopFieldCollector,
+};
 use core::search::scorer::Scorer;
+use core::search::sort_field::TopDocs;
 use core::util::external::Volatile;
 use core::util::DocId;
 use error::{ErrorKind, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 /// the `TimeoutCollector` collector is used to timeout search requests that
 /// take longer than the maximum allowed search time limit.
@@ -137,3 +141,240 @@ impl ParallelLeafCollector for TimeoutLeafCollector {
         Ok(())
     }
 }
+
+/// A wrapper of another collector, which aborts the collection once a deadline
+/// has passed.
+///
+/// Unlike the `TimeoutCollector`, the hits are forwarded to the wrapped collector
+/// until the deadline, so the partial results collected so far are kept, and the
+/// `TopDocs` are flagged with `timed_out`. Once a leaf collector of a parallel
+/// search times out, all the other ones stop at their next hit.
+pub struct TimeLimitingCollector<T: SearchCollector> {
+    collector: T,
+    deadline: Instant,
+    timed_out: Arc<AtomicBool>,
+}
+
+impl<T: SearchCollector> TimeLimitingCollector<T> {
+    /// Wrap `collector`, allowing the search to take at most `time_allowed` from now.
+    pub fn new(collector: T, time_allowed: Duration) -> TimeLimitingCollector<T> {
+        Self::with_deadline(collector, Instant::now() + time_allowed)
+    }
+
+    pub fn with_deadline(collector: T, deadline: Instant) -> TimeLimitingCollector<T> {
+        TimeLimitingCollector {
+            collector,
+            deadline,
+            timed_out: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns true if the collection was aborted because the deadline passed.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Acquire)
+    }
+
+    pub fn collector(&self) -> &T {
+        &self.collector
+    }
+
+    pub fn collector_mut(&mut self) -> &mut T {
+        &mut self.collector
+    }
+
+    pub fn into_inner(self) -> T {
+        self.collector
+    }
+}
+
+impl TimeLimitingCollector<TopDocsCollector> {
+    /// Returns the top docs collected until the deadline.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let mut top_docs = self.collector.top_docs();
+        top_docs.set_timed_out(self.timed_out());
+        top_docs
+    }
+}
+
+impl TimeLimitingCollector<TopFieldCollector> {
+    /// Returns the top docs collected until the deadline.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let mut top_docs = self.collector.top_docs();
+        top_docs.set_timed_out(self.timed_out());
+        top_docs
+    }
+}
+
+/// Fails with `CollectionTimeout` if the deadline has passed or another
+/// collector sharing `timed_out` already timed out.
+fn check_deadline(deadline: Instant, timed_out: &AtomicBool) -> Result<()> {
+    if timed_out.load(Ordering::Acquire) {
+        bail!(ErrorKind::Collector(
+            collector::ErrorKind::CollectionTimeout,
+        ))
+    }
+    if Instant::now() >= deadline {
+        timed_out.store(true, Ordering::Release);
+        bail!(ErrorKind::Collector(
+            collector::ErrorKind::CollectionTimeout,
+        ))
+    }
+    Ok(())
+}
+
+impl<T: SearchCollector> SearchCollector for TimeLimitingCollector<T> {
+    type LC = TimeLimitingLeafCollector<T::LC>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.collector.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        self.collector.support_parallel()
+    }
+
+    fn init_parallel(&mut self) {
+        self.collector.init_parallel()
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TimeLimitingLeafCollector<T::LC>> {
+        Ok(TimeLimitingLeafCollector {
+            collector: self.collector.leaf_collector(reader)?,
+            deadline: self.deadline,
+            timed_out: Arc::clone(&self.timed_out),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        self.collector.finish_parallel()
+    }
+}
+
+impl<T: SearchCollector> Collector for TimeLimitingCollector<T> {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn needs_top_scores_only(&self) -> bool {
+        self.collector.needs_top_scores_only()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        check_deadline(self.deadline, &self.timed_out)?;
+        self.collector.collect(doc, scorer)
+    }
+}
+
+pub struct TimeLimitingLeafCollector<T: ParallelLeafCollector> {
+    collector: T,
+    deadline: Instant,
+    timed_out: Arc<AtomicBool>,
+}
+
+impl<T: ParallelLeafCollector> ParallelLeafCollector for TimeLimitingLeafCollector<T> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        self.collector.finish_leaf()
+    }
+}
+
+impl<T: ParallelLeafCollector> Collector for TimeLimitingLeafCollector<T> {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn needs_top_scores_only(&self) -> bool {
+        self.collector.needs_top_scores_only()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        check_deadline(self.deadline, &self.timed_out)?;
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    fn collect_docs<T: SearchCollector>(
+        collector: &mut TimeLimitingCollector<T>,
+        docs: Vec<DocId>,
+    ) -> Result<()> {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        collector.set_next_reader(&leaves[0])?;
+        let mut scorer = create_mock_scorer(docs);
+        loop {
+            let doc = scorer.next()?;
+            if doc == NO_MORE_DOCS {
+                return Ok(());
+            }
+            collector.collect(doc, &mut scorer)?;
+        }
+    }
+
+    #[test]
+    fn test_time_limiting_collector() {
+        let mut collector =
+            TimeLimitingCollector::new(TopDocsCollector::new(10), Duration::from_secs(3600));
+        collect_docs(&mut collector, vec![1, 2, 3]).unwrap();
+        assert!(!collector.timed_out());
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 3);
+        assert!(!top_docs.timed_out());
+    }
+
+    #[test]
+    fn test_time_limiting_collector_timed_out() {
+        let mut collector =
+            TimeLimitingCollector::with_deadline(TopDocsCollector::new(10), Instant::now());
+        let result = collect_docs(&mut collector, vec![1, 2, 3]);
+        match result {
+            Err(::error::Error(
+                ErrorKind::Collector(collector::ErrorKind::CollectionTimeout),
+                _,
+            )) => {}
+            _ => panic!("expected collection timeout"),
+        }
+        assert!(collector.timed_out());
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 0);
+        assert!(top_docs.timed_out());
+
+        // leaf collectors share the timed out flag
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        let mut collector =
+            TimeLimitingCollector::new(TopDocsCollector::new(10), Duration::from_secs(3600));
+        collector.init_parallel();
+        let mut leaf_collector = collector.leaf_collector(&leaves[0]).unwrap();
+        collector.timed_out.store(true, Ordering::Release);
+        let mut scorer = create_mock_scorer(vec![1]);
+        assert!(leaf_collector.collect(1, &mut scorer).is_err());
+    }
+
+    #[test]
+    fn test_time_limiting_collector_needs_top_scores_only() {
+        let collector =
+            TimeLimitingCollector::new(TopDocsCollector::new(10), Duration::from_secs(3600));
+        assert!(!collector.needs_top_scores_only());
+
+        let mut collector = TimeLimitingCollector::new(
+            TopDocsCollector::with_total_hits_threshold(10, 100),
+            Duration::from_secs(3600),
+        );
+        assert!(collector.needs_top_scores_only());
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        collector.init_parallel();
+        let leaf_collector = collector.leaf_collector(&leaves[0]).unwrap();
+        assert!(leaf_collector.needs_top_scores_only());
+    }
+}
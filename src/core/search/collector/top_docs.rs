@@ -37,16 +37,21 @@ struct TopDocsBaseCollector {
     /// The total number of documents that the collector encountered.
     total_hits: usize,
 
+    /// Once more than this many hits are collected, the scorer is told to skip the
+    /// non competitive docs.
+    total_hits_threshold: usize,
+
     cur_doc_base: DocId,
 }
 
 impl TopDocsBaseCollector {
-    fn new(estimated_hits: usize) -> Self {
+    fn new(estimated_hits: usize, total_hits_threshold: usize) -> Self {
         let pq = BinaryHeap::with_capacity(estimated_hits);
         Self {
             pq,
             estimated_hits,
             total_hits: 0,
+            total_hits_threshold,
             cur_doc_base: 0,
         }
     }
@@ -87,6 +92,10 @@ impl Collector for TopDocsBaseCollector {
         true
     }
 
+    fn needs_top_scores_only(&self) -> bool {
+        self.total_hits_threshold < usize::MAX
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         let score = scorer.score()?;
         debug_assert!((score - f32::NEG_INFINITY).abs() >= f32::EPSILON);
@@ -95,6 +104,12 @@ impl Collector for TopDocsBaseCollector {
         let id = doc + self.cur_doc_base;
         self.add_doc(id, score);
 
+        if self.total_hits > self.total_hits_threshold && self.pq.len() == self.estimated_hits {
+            if let Some(min) = self.pq.peek() {
+                scorer.set_min_competitive_score(min.score);
+            }
+        }
+
         Ok(())
     }
 }
@@ -111,7 +126,20 @@ pub struct TopDocsCollector {
 
 impl TopDocsCollector {
     pub fn new(estimated_hits: usize) -> Self {
-        let base = TopDocsBaseCollector::new(estimated_hits);
+        Self::with_total_hits_threshold(estimated_hits, usize::MAX)
+    }
+
+    /// Create a collector that stops counting hits accurately once more than
+    /// `total_hits_threshold` hits are collected, which allows the scorers to skip
+    /// the documents that can't make it into the top `estimated_hits`, e.g. with
+    /// `WANDScorer` for disjunctions. The skipping relies on per clause score
+    /// upper bounds only, so it pays off on disjunctions mixing rare and frequent
+    /// terms, not on the blocks of a single term.
+    ///
+    /// Past the threshold, the total hits of the returned `TopDocs` is only a lower
+    /// bound of the number of matching documents.
+    pub fn with_total_hits_threshold(estimated_hits: usize, total_hits_threshold: usize) -> Self {
+        let base = TopDocsBaseCollector::new(estimated_hits, total_hits_threshold);
         Self {
             base,
             channel: None,
@@ -151,7 +179,8 @@ impl SearchCollector for TopDocsCollector {
         &self,
         reader: &LeafReaderContext<'_, C>,
     ) -> Result<TopDocsLeafCollector> {
-        let mut collector = TopDocsBaseCollector::new(self.base.estimated_hits);
+        let mut collector =
+            TopDocsBaseCollector::new(self.base.estimated_hits, self.base.total_hits_threshold);
         collector.cur_doc_base = reader.doc_base;
         Ok(TopDocsLeafCollector::new(
             collector,
@@ -182,6 +211,10 @@ impl Collector for TopDocsCollector {
         true
     }
 
+    fn needs_top_scores_only(&self) -> bool {
+        self.base.needs_top_scores_only()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
         self.base.collect(doc, scorer)
     }
@@ -223,6 +256,10 @@ impl Collector for TopDocsLeafCollector {
         true
     }
 
+    fn needs_top_scores_only(&self) -> bool {
+        self.collector.needs_top_scores_only()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         self.collector.collect(doc, scorer)
     }
@@ -267,4 +304,64 @@ mod tests {
         assert_eq!(score_docs[1].doc_id(), 3);
         assert_eq!(score_docs[2].doc_id(), 3);
     }
+
+    struct MinScoreRecorder {
+        scorer: MockSimpleScorer<MockDocIterator>,
+        min_scores: Vec<f32>,
+    }
+
+    impl Scorer for MinScoreRecorder {
+        fn score(&mut self) -> Result<f32> {
+            self.scorer.score()
+        }
+
+        fn set_min_competitive_score(&mut self, min_score: f32) {
+            self.min_scores.push(min_score);
+        }
+    }
+
+    impl DocIterator for MinScoreRecorder {
+        fn doc_id(&self) -> DocId {
+            self.scorer.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.scorer.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.scorer.advance(target)
+        }
+
+        fn cost(&self) -> usize {
+            self.scorer.cost()
+        }
+    }
+
+    #[test]
+    fn test_min_competitive_score() {
+        let mut scorer = MinScoreRecorder {
+            scorer: create_mock_scorer(vec![1, 2, 3, 4, 5]),
+            min_scores: vec![],
+        };
+
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+        assert!(!TopDocsCollector::new(2).needs_top_scores_only());
+        let mut collector = TopDocsCollector::with_total_hits_threshold(2, 3);
+        assert!(collector.needs_top_scores_only());
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            collector.collect(doc, &mut scorer).unwrap();
+        }
+
+        // the min score is only fed back once more than 3 hits were collected
+        assert_eq!(scorer.min_scores, vec![3.0, 4.0]);
+        assert_eq!(collector.top_docs().score_docs().len(), 2);
+    }
 }
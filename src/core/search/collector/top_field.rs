@@ -0,0 +1,466 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{
+    ComparatorValue, FieldComparator, FieldComparatorEnum, FieldDoc, ScoreDocHit, Sort, SortField,
+    TopDocs, TopFieldDocs,
+};
+use core::util::DocId;
+use error::{ErrorKind::IllegalState, Result, ResultExt};
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    slot: usize,
+    doc: DocId,
+    score: f32,
+}
+
+/// A priority queue of hits ordered by the `FieldComparator`s of a `Sort`.
+///
+/// The queue is a binary heap on top of the comparator slots, its top entry is
+/// the least competitive hit, whose slot is the bottom of all the comparators.
+struct FieldValueHitQueue {
+    comparators: Vec<FieldComparatorEnum>,
+    reverse: Vec<bool>,
+    heap: Vec<Entry>,
+    num_hits: usize,
+}
+
+impl FieldValueHitQueue {
+    fn new(sort: &Sort, num_hits: usize) -> FieldValueHitQueue {
+        let fields = sort.get_sort();
+        let comparators = fields
+            .iter()
+            .map(|f| f.get_comparator(num_hits, f.missing_value()))
+            .collect();
+        let reverse = fields.iter().map(SortField::is_reverse).collect();
+        FieldValueHitQueue {
+            comparators,
+            reverse,
+            heap: Vec::with_capacity(num_hits),
+            num_hits,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        for comparator in &mut self.comparators {
+            comparator.get_information_from_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    /// Compare two hits by sort order, `Ordering::Greater` means `e1` is less competitive.
+    fn compare(&self, e1: &Entry, e2: &Entry) -> Ordering {
+        for (comparator, reverse) in self.comparators.iter().zip(&self.reverse) {
+            let ord = comparator.compare(e1.slot, e2.slot);
+            if ord != Ordering::Equal {
+                return if *reverse { ord.reverse() } else { ord };
+            }
+        }
+        e1.doc.cmp(&e2.doc)
+    }
+
+    /// Whether the doc sorts before the bottom hit, only called once the queue is full.
+    fn competitive(&mut self, doc: DocId, score: f32) -> Result<bool> {
+        for (comparator, reverse) in self.comparators.iter_mut().zip(&self.reverse) {
+            let value = ComparatorValue::of_hit(comparator.get_type(), doc, score);
+            let ord = comparator.compare_bottom(value)?;
+            let ord = if *reverse { ord.reverse() } else { ord };
+            if ord != Ordering::Equal {
+                return Ok(ord == Ordering::Greater);
+            }
+        }
+        // docs are collected in order, so on a tie the bottom hit wins
+        Ok(false)
+    }
+
+    fn add(&mut self, doc: DocId, doc_base: DocId, score: f32) -> Result<()> {
+        if self.heap.len() == self.num_hits {
+            if !self.competitive(doc, score)? {
+                return Ok(());
+            }
+            let slot = self.heap[0].slot;
+            self.copy(slot, doc, score)?;
+            self.heap[0] = Entry {
+                slot,
+                doc: doc + doc_base,
+                score,
+            };
+            self.sift_down(0);
+            self.set_bottom();
+        } else {
+            let slot = self.heap.len();
+            self.copy(slot, doc, score)?;
+            self.heap.push(Entry {
+                slot,
+                doc: doc + doc_base,
+                score,
+            });
+            self.sift_up(slot);
+            if self.heap.len() == self.num_hits {
+                self.set_bottom();
+            }
+        }
+        Ok(())
+    }
+
+    fn copy(&mut self, slot: usize, doc: DocId, score: f32) -> Result<()> {
+        for comparator in &mut self.comparators {
+            let value = ComparatorValue::of_hit(comparator.get_type(), doc, score);
+            comparator.copy(slot, value)?;
+        }
+        Ok(())
+    }
+
+    fn set_bottom(&mut self) {
+        let slot = self.heap[0].slot;
+        for comparator in &mut self.comparators {
+            comparator.set_bottom(slot);
+        }
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.compare(&self.heap[i], &self.heap[parent]) != Ordering::Greater {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        let len = self.heap.len();
+        loop {
+            let mut largest = i;
+            for child in &[2 * i + 1, 2 * i + 2] {
+                if *child < len
+                    && self.compare(&self.heap[*child], &self.heap[largest]) == Ordering::Greater
+                {
+                    largest = *child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.heap.swap(i, largest);
+            i = largest;
+        }
+    }
+
+    /// Drain the queue into `FieldDoc`s holding the sort values, in no particular order.
+    fn take_field_docs(&mut self) -> Vec<FieldDoc> {
+        let heap = mem::replace(&mut self.heap, Vec::with_capacity(self.num_hits));
+        heap.into_iter()
+            .map(|e| {
+                let fields = self.comparators.iter().map(|c| c.value(e.slot)).collect();
+                FieldDoc::new(e.doc, e.score, fields)
+            })
+            .collect()
+    }
+}
+
+struct TopFieldBaseCollector {
+    queue: FieldValueHitQueue,
+    needs_scores: bool,
+    track_scores: bool,
+    total_hits: usize,
+    max_score: f32,
+    cur_doc_base: DocId,
+}
+
+impl TopFieldBaseCollector {
+    fn new(sort: &Sort, num_hits: usize, track_scores: bool) -> TopFieldBaseCollector {
+        TopFieldBaseCollector {
+            queue: FieldValueHitQueue::new(sort, num_hits),
+            needs_scores: track_scores || sort.needs_scores(),
+            track_scores,
+            total_hits: 0,
+            max_score: f32::NAN,
+            cur_doc_base: 0,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        self.queue.set_next_reader(reader)
+    }
+}
+
+impl Collector for TopFieldBaseCollector {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let score = if self.needs_scores {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        if self.track_scores {
+            self.max_score = self.max_score.max(score);
+        }
+        self.queue.add(doc, self.cur_doc_base, score)
+    }
+}
+
+/// A `Collector` that sorts the hits by the fields of a `Sort`, e.g. numeric or
+/// string doc values, the score and the doc id, producing `TopFieldDocs` whose
+/// hits hold the sort values in `FieldDoc::fields`.
+///
+/// Scores are only computed when the sort needs them or `track_scores` is set,
+/// otherwise the scores of the hits are `NaN`.
+pub struct TopFieldCollector {
+    sort: Sort,
+    num_hits: usize,
+    base: TopFieldBaseCollector,
+    // hits collected by the leaf collectors of a parallel search
+    leaf_docs: Vec<FieldDoc>,
+    channel: Option<(Sender<LeafFieldDocs>, Receiver<LeafFieldDocs>)>,
+}
+
+impl TopFieldCollector {
+    pub fn new(sort: Sort, num_hits: usize, track_scores: bool) -> TopFieldCollector {
+        assert!(num_hits > 0, "num_hits must be > 0");
+        let base = TopFieldBaseCollector::new(&sort, num_hits, track_scores);
+        TopFieldCollector {
+            sort,
+            num_hits,
+            base,
+            leaf_docs: vec![],
+            channel: None,
+        }
+    }
+
+    /// Returns the top docs that were collected by this collector.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let mut docs = self.base.queue.take_field_docs();
+        docs.extend(self.leaf_docs.drain(..));
+
+        let fields = self.sort.get_sort();
+        docs.sort_by(|d1, d2| self.sort.compare_field_docs(d1, d2));
+        docs.truncate(self.num_hits);
+
+        TopDocs::Field(TopFieldDocs {
+            total_hits: self.base.total_hits,
+            score_docs: docs.into_iter().map(ScoreDocHit::Field).collect(),
+            max_score: self.base.max_score,
+            fields: fields.to_vec(),
+            timed_out: false,
+        })
+    }
+}
+
+impl SearchCollector for TopFieldCollector {
+    type LC = TopFieldLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TopFieldLeafCollector> {
+        let mut collector =
+            TopFieldBaseCollector::new(&self.sort, self.num_hits, self.base.track_scores);
+        collector.set_next_reader(reader)?;
+        Ok(TopFieldLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.base.total_hits += docs.total_hits;
+                self.base.max_score = self.base.max_score.max(docs.max_score);
+                self.leaf_docs.extend(docs.docs);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for TopFieldCollector {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+struct LeafFieldDocs {
+    docs: Vec<FieldDoc>,
+    total_hits: usize,
+    max_score: f32,
+}
+
+pub struct TopFieldLeafCollector {
+    collector: TopFieldBaseCollector,
+    channel: Sender<LeafFieldDocs>,
+}
+
+impl ParallelLeafCollector for TopFieldLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let docs = LeafFieldDocs {
+            docs: self.collector.queue.take_field_docs(),
+            total_hits: self.collector.total_hits,
+            max_score: self.collector.max_score,
+        };
+        self.channel
+            .send(docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for TopFieldLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::sort_field::{SimpleSortField, SortFieldType};
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+    use core::util::VariantValue;
+
+    fn sort_by(field_type: SortFieldType, reverse: bool) -> Sort {
+        Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            String::new(),
+            field_type,
+            reverse,
+        ))])
+    }
+
+    fn collect_docs(collector: &mut TopFieldCollector, segments: Vec<Vec<DocId>>) {
+        let leaf_readers = segments.iter().map(|_| MockLeafReader::new(10)).collect();
+        let index_reader = MockIndexReader::new(leaf_readers);
+        let leaves = index_reader.leaves();
+        for (leaf, docs) in leaves.iter().zip(segments) {
+            collector.set_next_reader(leaf).unwrap();
+            let mut scorer = create_mock_scorer(docs);
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                collector.collect(doc, &mut scorer).unwrap();
+            }
+        }
+    }
+
+    fn hit_docs(top_docs: &TopDocs) -> Vec<DocId> {
+        top_docs.score_docs().iter().map(|d| d.doc_id()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_score() {
+        // mock scorers score by doc id
+        let mut collector = TopFieldCollector::new(sort_by(SortFieldType::Score, false), 3, true);
+        assert!(collector.needs_scores());
+        collect_docs(&mut collector, vec![vec![1, 4, 2, 5, 3]]);
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 5);
+        assert_eq!(hit_docs(&top_docs), vec![5, 4, 3]);
+
+        let mut collector = TopFieldCollector::new(sort_by(SortFieldType::Score, true), 3, false);
+        collect_docs(&mut collector, vec![vec![1, 2, 3, 4, 5]]);
+        assert_eq!(hit_docs(&collector.top_docs()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sort_by_doc_across_segments() {
+        let mut collector = TopFieldCollector::new(sort_by(SortFieldType::Doc, true), 3, false);
+        assert!(!collector.needs_scores());
+        collect_docs(&mut collector, vec![vec![1, 3, 5], vec![2, 4]]);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 5);
+        assert_eq!(hit_docs(&top_docs), vec![14, 12, 5]);
+        if let ScoreDocHit::Field(ref doc) = top_docs.score_docs()[0] {
+            assert_eq!(doc.fields, vec![VariantValue::Int(14)]);
+            assert!(doc.score.is_nan());
+        } else {
+            panic!("expected field docs");
+        }
+
+        let mut collector = TopFieldCollector::new(sort_by(SortFieldType::Doc, false), 3, false);
+        collect_docs(&mut collector, vec![vec![1, 3, 5], vec![2, 4]]);
+        assert_eq!(hit_docs(&collector.top_docs()), vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn test_multi_field_sort() {
+        // sort by score, then by reversed doc id on ties
+        let sort = Sort::new(vec![
+            SortField::new_score(),
+            SortField::Simple(SimpleSortField::new(
+                String::new(),
+                SortFieldType::Doc,
+                true,
+            )),
+        ]);
+        let mut collector = TopFieldCollector::new(sort, 4, true);
+        // scores are local doc ids, so docs 2 and 12 tie
+        collect_docs(&mut collector, vec![vec![1, 2], vec![1, 2]]);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(hit_docs(&top_docs), vec![12, 2, 11, 1]);
+        if let TopDocs::Field(ref field_docs) = top_docs {
+            assert!((field_docs.max_score - 2.0).abs() < ::std::f32::EPSILON);
+            assert_eq!(field_docs.fields.len(), 2);
+        } else {
+            panic!("expected field docs");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::util::DocId;
+use error::{ErrorKind, Result};
+
+/// Just counts the total number of hits, without computing the scores.
+pub struct TotalHitCountCollector {
+    total_hits: i32,
+    channel: Option<(Sender<i32>, Receiver<i32>)>,
+}
+
+impl Default for TotalHitCountCollector {
+    fn default() -> Self {
+        TotalHitCountCollector::new()
+    }
+}
+
+impl TotalHitCountCollector {
+    pub fn new() -> Self {
+        TotalHitCountCollector {
+            total_hits: 0,
+            channel: None,
+        }
+    }
+
+    pub fn total_hits(&self) -> i32 {
+        self.total_hits
+    }
+}
+impl SearchCollector for TotalHitCountCollector {
+    type LC = TotalHitsCountLeafCollector;
+    fn set_next_reader<C: Codec>(&mut self, _reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(unbounded());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        _reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TotalHitsCountLeafCollector> {
+        Ok(TotalHitsCountLeafCollector {
+            count: 0,
+            sender: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(v) = receiver.recv() {
+                self.total_hits += v;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for TotalHitCountCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        Ok(())
+    }
+}
+
+pub struct TotalHitsCountLeafCollector {
+    count: i32,
+    sender: Sender<i32>,
+}
+
+impl Collector for TotalHitsCountLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+}
+
+impl ParallelLeafCollector for TotalHitsCountLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        self.sender.send(self.count).map_err(|e| {
+            ErrorKind::IllegalState(format!(
+                "channel unexpected closed before search complete with err: {:?}",
+                e
+            ))
+            .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::tests::*;
+
+    #[test]
+    fn test_total_hit_count_collector() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        let mut scorer = create_mock_scorer(vec![]);
+
+        let mut collector = TotalHitCountCollector::new();
+        assert!(!collector.needs_scores());
+        collector.set_next_reader(&leaves[0]).unwrap();
+        collector.collect(1, &mut scorer).unwrap();
+        collector.collect(2, &mut scorer).unwrap();
+
+        // hits of the leaves collected in parallel are added on finish
+        collector.init_parallel();
+        let mut leaf_collector = collector.leaf_collector(&leaves[1]).unwrap();
+        leaf_collector.collect(3, &mut scorer).unwrap();
+        leaf_collector.finish_leaf().unwrap();
+        drop(leaf_collector);
+        collector.finish_parallel().unwrap();
+        assert_eq!(collector.total_hits(), 3);
+    }
+}
@@ -0,0 +1,403 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::sort_field::SortFieldType;
+use core::util::{BitsMut, DocId};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Per-segment, per-document double values, obtained from a `DoubleValuesSource`.
+pub trait DoubleValues: Send {
+    /// Returns the value for `doc`, or `None` if the document has no value.
+    ///
+    /// `score` is the score of the wrapped query for `doc`, it is only meaningful
+    /// when the owning source returns `true` for `needs_scores()`. Docs must be
+    /// requested in increasing order.
+    fn get(&mut self, doc: DocId, score: f32) -> Result<Option<f64>>;
+}
+
+/// Base trait for producing `DoubleValues`.
+///
+/// Sources can read per-document values from doc values, from the scores of the
+/// wrapped query, constants, or combine other sources arithmetically, see
+/// `FunctionScoreQuery` for scoring documents with them.
+pub trait DoubleValuesSource<C: Codec>: fmt::Display + Send + Sync {
+    /// Returns a `DoubleValues` instance for the passed-in `LeafReaderContext`.
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>>;
+
+    /// Return true if the values depend on the score of the wrapped query.
+    fn needs_scores(&self) -> bool;
+
+    /// An explanation of the value for the named document.
+    ///
+    /// `score_explanation` is the explanation of the wrapped query's score for `doc`.
+    fn explain(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        doc: DocId,
+        score_explanation: &Explanation,
+    ) -> Result<Explanation> {
+        let mut values = self.get_values(reader)?;
+        match values.get(doc, score_explanation.value())? {
+            Some(v) => Ok(Explanation::new(
+                true,
+                v as f32,
+                format!("{}", self),
+                vec![],
+            )),
+            None => Ok(Explanation::new(
+                false,
+                0.0f32,
+                format!("{} doesn't have a value for doc {}", self, doc),
+                vec![],
+            )),
+        }
+    }
+}
+
+/// A `DoubleValuesSource` that always returns the same value.
+pub struct ConstantValuesSource {
+    value: f64,
+}
+
+impl ConstantValuesSource {
+    pub fn new(value: f64) -> ConstantValuesSource {
+        ConstantValuesSource { value }
+    }
+}
+
+impl<C: Codec> DoubleValuesSource<C> for ConstantValuesSource {
+    fn get_values(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>> {
+        Ok(Box::new(ConstantValues { value: self.value }))
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for ConstantValuesSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "constant({})", self.value)
+    }
+}
+
+struct ConstantValues {
+    value: f64,
+}
+
+impl DoubleValues for ConstantValues {
+    fn get(&mut self, _doc: DocId, _score: f32) -> Result<Option<f64>> {
+        Ok(Some(self.value))
+    }
+}
+
+/// A `DoubleValuesSource` that exposes the score of the wrapped query.
+pub struct ScoreValuesSource;
+
+impl<C: Codec> DoubleValuesSource<C> for ScoreValuesSource {
+    fn get_values(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>> {
+        Ok(Box::new(ScoreValues))
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(
+        &self,
+        _reader: &LeafReaderContext<'_, C>,
+        _doc: DocId,
+        score_explanation: &Explanation,
+    ) -> Result<Explanation> {
+        Ok(Explanation::new(
+            score_explanation.is_match(),
+            score_explanation.value(),
+            "score".to_string(),
+            vec![],
+        ))
+    }
+}
+
+impl fmt::Display for ScoreValuesSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "score()")
+    }
+}
+
+struct ScoreValues;
+
+impl DoubleValues for ScoreValues {
+    fn get(&mut self, _doc: DocId, score: f32) -> Result<Option<f64>> {
+        Ok(Some(f64::from(score)))
+    }
+}
+
+/// A `DoubleValuesSource` that reads the values of a numeric doc values field.
+///
+/// Values are decoded according to `field_type`, which must be one of
+/// `SortFieldType::Int`, `Long`, `Float` or `Double`.
+pub struct NumericFieldValuesSource {
+    field: String,
+    field_type: SortFieldType,
+}
+
+impl NumericFieldValuesSource {
+    pub fn new(field: String, field_type: SortFieldType) -> Result<NumericFieldValuesSource> {
+        match field_type {
+            SortFieldType::Int
+            | SortFieldType::Long
+            | SortFieldType::Float
+            | SortFieldType::Double => Ok(NumericFieldValuesSource { field, field_type }),
+            _ => bail!(IllegalArgument(format!(
+                "field type {:?} is not numeric",
+                field_type
+            ))),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+}
+
+impl<C: Codec> DoubleValuesSource<C> for NumericFieldValuesSource {
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>> {
+        Ok(Box::new(NumericFieldValues {
+            values: reader.reader.get_numeric_doc_values(&self.field)?,
+            docs_with_field: reader.reader.get_docs_with_field(&self.field)?,
+            field_type: self.field_type,
+        }))
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for NumericFieldValuesSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}({})", self.field_type, self.field)
+    }
+}
+
+struct NumericFieldValues {
+    values: Box<dyn NumericDocValues>,
+    docs_with_field: Box<dyn BitsMut>,
+    field_type: SortFieldType,
+}
+
+impl DoubleValues for NumericFieldValues {
+    fn get(&mut self, doc: DocId, _score: f32) -> Result<Option<f64>> {
+        if !self.docs_with_field.get(doc as usize)? {
+            return Ok(None);
+        }
+        let raw_value = self.values.get_mut(doc)?;
+        let value = match self.field_type {
+            SortFieldType::Int => f64::from(raw_value as i32),
+            SortFieldType::Long => raw_value as f64,
+            SortFieldType::Float => f64::from(f32::from_bits(raw_value as u32)),
+            SortFieldType::Double => f64::from_bits(raw_value as u64),
+            _ => unreachable!(),
+        };
+        Ok(Some(value))
+    }
+}
+
+/// The arithmetic operation used by a `CombinedValuesSource`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuesCombination {
+    Sum,
+    Product,
+    Max,
+    Min,
+}
+
+impl ValuesCombination {
+    fn combine(self, a: f64, b: f64) -> f64 {
+        match self {
+            ValuesCombination::Sum => a + b,
+            ValuesCombination::Product => a * b,
+            ValuesCombination::Max => a.max(b),
+            ValuesCombination::Min => a.min(b),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ValuesCombination::Sum => "sum",
+            ValuesCombination::Product => "product",
+            ValuesCombination::Max => "max",
+            ValuesCombination::Min => "min",
+        }
+    }
+}
+
+/// A `DoubleValuesSource` that arithmetically combines the values of other sources.
+///
+/// Sub sources without a value for a document are skipped, the document only
+/// has no value when none of the sub sources has one.
+pub struct CombinedValuesSource<C: Codec> {
+    combination: ValuesCombination,
+    sources: Vec<Box<dyn DoubleValuesSource<C>>>,
+}
+
+impl<C: Codec> CombinedValuesSource<C> {
+    pub fn new(
+        combination: ValuesCombination,
+        sources: Vec<Box<dyn DoubleValuesSource<C>>>,
+    ) -> Result<CombinedValuesSource<C>> {
+        if sources.is_empty() {
+            bail!(IllegalArgument(
+                "CombinedValuesSource: sources should not be empty!".into()
+            ));
+        }
+        Ok(CombinedValuesSource {
+            combination,
+            sources,
+        })
+    }
+
+    pub fn sum(sources: Vec<Box<dyn DoubleValuesSource<C>>>) -> Result<CombinedValuesSource<C>> {
+        Self::new(ValuesCombination::Sum, sources)
+    }
+
+    pub fn product(
+        sources: Vec<Box<dyn DoubleValuesSource<C>>>,
+    ) -> Result<CombinedValuesSource<C>> {
+        Self::new(ValuesCombination::Product, sources)
+    }
+}
+
+impl<C: Codec> DoubleValuesSource<C> for CombinedValuesSource<C> {
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DoubleValues>> {
+        let mut values = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            values.push(source.get_values(reader)?);
+        }
+        Ok(Box::new(CombinedValues {
+            combination: self.combination,
+            values,
+        }))
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.sources.iter().any(|s| s.needs_scores())
+    }
+
+    fn explain(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        doc: DocId,
+        score_explanation: &Explanation,
+    ) -> Result<Explanation> {
+        let mut value: Option<f64> = None;
+        let mut subs = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            let sub = source.explain(reader, doc, score_explanation)?;
+            if sub.is_match() {
+                let v = f64::from(sub.value());
+                value = Some(value.map_or(v, |acc| self.combination.combine(acc, v)));
+            }
+            subs.push(sub);
+        }
+        let description = format!("{} of:", self.combination.name());
+        Ok(Explanation::new(
+            value.is_some(),
+            value.unwrap_or(0.0) as f32,
+            description,
+            subs,
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for CombinedValuesSource<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sources: Vec<String> = self.sources.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}({})", self.combination.name(), sources.join(", "))
+    }
+}
+
+struct CombinedValues {
+    combination: ValuesCombination,
+    values: Vec<Box<dyn DoubleValues>>,
+}
+
+impl DoubleValues for CombinedValues {
+    fn get(&mut self, doc: DocId, score: f32) -> Result<Option<f64>> {
+        let mut result: Option<f64> = None;
+        for values in &mut self.values {
+            if let Some(v) = values.get(doc, score)? {
+                result = Some(result.map_or(v, |acc| self.combination.combine(acc, v)));
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockDocValues;
+
+    impl DoubleValues for MockDocValues {
+        fn get(&mut self, doc: DocId, _score: f32) -> Result<Option<f64>> {
+            if doc % 2 == 0 {
+                Ok(Some(f64::from(doc)))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    fn combined(combination: ValuesCombination) -> CombinedValues {
+        CombinedValues {
+            combination,
+            values: vec![
+                Box::new(ConstantValues { value: 3.0 }),
+                Box::new(ScoreValues),
+                Box::new(MockDocValues),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_combined_values() {
+        let mut sum = combined(ValuesCombination::Sum);
+        assert_eq!(sum.get(4, 2.0).unwrap(), Some(9.0));
+        // doc 5 has no mock value, so only the constant and the score are summed
+        assert_eq!(sum.get(5, 2.0).unwrap(), Some(5.0));
+
+        let mut product = combined(ValuesCombination::Product);
+        assert_eq!(product.get(4, 2.0).unwrap(), Some(24.0));
+        assert_eq!(product.get(5, 2.0).unwrap(), Some(6.0));
+
+        let mut max = combined(ValuesCombination::Max);
+        assert_eq!(max.get(4, 2.0).unwrap(), Some(4.0));
+        let mut min = combined(ValuesCombination::Min);
+        assert_eq!(min.get(4, 2.0).unwrap(), Some(2.0));
+
+        let mut missing = CombinedValues {
+            combination: ValuesCombination::Sum,
+            values: vec![Box::new(MockDocValues)],
+        };
+        assert_eq!(missing.get(3, 1.0).unwrap(), None);
+    }
+}
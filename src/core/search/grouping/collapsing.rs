@@ -0,0 +1,509 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::grouping::{GroupSelector, SearchGroup, SortValuesReader, TopGroupsCollector};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{CollapseTopFieldDocs, FieldDoc, ScoreDocHit, Sort, TopDocs};
+use core::util::{DocId, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// Keeps the `top_n` most competitive hits, with at most one hit per collapse
+/// value.
+struct CollapseQueue {
+    sort: Sort,
+    top_n: usize,
+    hits: HashMap<Option<VariantValue>, FieldDoc>,
+    // collapse value of the least competitive hit, only valid when the queue is full
+    bottom: Option<Option<VariantValue>>,
+}
+
+impl CollapseQueue {
+    fn new(sort: Sort, top_n: usize) -> CollapseQueue {
+        CollapseQueue {
+            sort,
+            top_n,
+            hits: HashMap::with_capacity(top_n),
+            bottom: None,
+        }
+    }
+
+    fn add(&mut self, value: Option<VariantValue>, hit: FieldDoc) {
+        if let Some(existing) = self.hits.get_mut(&value) {
+            if self.sort.compare_field_docs(&hit, existing) == Ordering::Less {
+                *existing = hit;
+                self.bottom = None;
+            }
+            return;
+        }
+
+        if self.hits.len() < self.top_n {
+            self.hits.insert(value, hit);
+            return;
+        }
+
+        // a hit of an evicted value can't come back unless it beats the bottom hit,
+        // which is better than all the hits of that value seen so far
+        if self.bottom.is_none() {
+            let sort = &self.sort;
+            self.bottom = self
+                .hits
+                .iter()
+                .max_by(|(_, h1), (_, h2)| sort.compare_field_docs(h1, h2))
+                .map(|(v, _)| v.clone());
+        }
+        let bottom_value = self.bottom.take().unwrap();
+        if self
+            .sort
+            .compare_field_docs(&hit, &self.hits[&bottom_value])
+            == Ordering::Less
+        {
+            self.hits.remove(&bottom_value);
+            self.hits.insert(value, hit);
+        } else {
+            self.bottom = Some(bottom_value);
+        }
+    }
+
+    fn take_hits(&mut self) -> Vec<(Option<VariantValue>, FieldDoc)> {
+        self.bottom = None;
+        mem::replace(&mut self.hits, HashMap::new())
+            .into_iter()
+            .collect()
+    }
+}
+
+struct CollapseBaseCollector<G: GroupSelector> {
+    selector: Option<G>,
+    values_reader: SortValuesReader,
+    queue: CollapseQueue,
+    needs_scores: bool,
+    track_scores: bool,
+    total_hits: usize,
+    max_score: f32,
+    doc_base: DocId,
+}
+
+impl<G: GroupSelector> CollapseBaseCollector<G> {
+    fn new(sort: &Sort, top_n: usize, track_scores: bool) -> CollapseBaseCollector<G> {
+        CollapseBaseCollector {
+            selector: None,
+            values_reader: SortValuesReader::new(sort),
+            queue: CollapseQueue::new(sort.clone(), top_n),
+            needs_scores: track_scores || sort.needs_scores(),
+            track_scores,
+            total_hits: 0,
+            max_score: f32::NAN,
+            doc_base: 0,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+        selector: &G,
+    ) -> Result<()> {
+        self.doc_base = reader.doc_base;
+        self.selector = Some(selector.leaf_selector(reader)?);
+        self.values_reader.set_next_reader(reader)
+    }
+}
+
+impl<G: GroupSelector> Collector for CollapseBaseCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        debug_assert!(self.selector.is_some());
+        self.total_hits += 1;
+        let score = if self.needs_scores {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        if self.track_scores {
+            self.max_score = self.max_score.max(score);
+        }
+        let value = self.selector.as_mut().unwrap().group_value(doc)?;
+        let fields = self.values_reader.values(doc, score)?;
+        self.queue
+            .add(value, FieldDoc::new(self.doc_base + doc, score, fields));
+        Ok(())
+    }
+}
+
+/// A `SearchCollector` that collapses the hits by the value of a doc values
+/// field, keeping only the most competitive hit of each value by `sort`.
+///
+/// The top `top_n` collapsed hits are returned as `TopDocs::Collapse`, sorted
+/// by `sort`, with the collapse value of each hit. The docs without a value are
+/// collapsed together with the `None` value.
+///
+/// Only the best hit of each value is kept, the inner hits of the collapsed
+/// hits are retrieved by a second search with the collector built by
+/// `inner_hits_collector`.
+pub struct CollapsingTopDocsCollector<G: GroupSelector> {
+    collapse_field: String,
+    selector: G,
+    sort: Sort,
+    top_n: usize,
+    base: CollapseBaseCollector<G>,
+    channel: Option<(Sender<LeafCollapsedHits>, Receiver<LeafCollapsedHits>)>,
+}
+
+impl<G: GroupSelector> CollapsingTopDocsCollector<G> {
+    pub fn new(
+        collapse_field: String,
+        selector: G,
+        sort: Sort,
+        top_n: usize,
+        track_scores: bool,
+    ) -> Result<Self> {
+        if top_n < 1 {
+            bail!(IllegalArgument("top_n must be >= 1".into()));
+        }
+        let base = CollapseBaseCollector::new(&sort, top_n, track_scores);
+        Ok(CollapsingTopDocsCollector {
+            collapse_field,
+            selector,
+            sort,
+            top_n,
+            base,
+            channel: None,
+        })
+    }
+
+    /// Returns the collapsed hits that were collected by this collector.
+    ///
+    /// `total_groups` of the result is the number of collapsed hits returned, as
+    /// the less competitive values are not tracked.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let mut hits = self.base.queue.take_hits();
+        let sort = &self.sort;
+        hits.sort_by(|(_, h1), (_, h2)| sort.compare_field_docs(h1, h2));
+
+        let total_groups = hits.len();
+        let mut score_docs = Vec::with_capacity(hits.len());
+        let mut collapse_values = Vec::with_capacity(hits.len());
+        for (value, hit) in hits {
+            collapse_values.push(value);
+            score_docs.push(ScoreDocHit::Field(hit));
+        }
+        TopDocs::Collapse(CollapseTopFieldDocs::new(
+            self.collapse_field.clone(),
+            self.base.total_hits,
+            total_groups,
+            score_docs,
+            self.sort.get_sort().to_vec(),
+            collapse_values,
+            self.base.max_score,
+        ))
+    }
+}
+
+/// Returns a `TopGroupsCollector` which collects the top `max_inner_hits` docs
+/// of each of the collapsed hits of `collapsed`, sorted by `inner_hits_sort`.
+///
+/// The groups of the `TopGroups` found by a search with this collector are in
+/// the order of the collapsed hits.
+pub fn inner_hits_collector<G: GroupSelector>(
+    selector: G,
+    collapsed: &CollapseTopFieldDocs,
+    inner_hits_sort: Sort,
+    max_inner_hits: usize,
+    track_scores: bool,
+) -> Result<TopGroupsCollector<G>> {
+    let mut groups = Vec::with_capacity(collapsed.score_docs.len());
+    for (hit, value) in collapsed.score_docs.iter().zip(&collapsed.collapse_values) {
+        match hit {
+            ScoreDocHit::Field(doc) => {
+                groups.push(SearchGroup::new(value.clone(), doc.fields.clone(), doc.doc));
+            }
+            ScoreDocHit::Score(_) => bail!(IllegalArgument(
+                "collapsed hits must hold their sort values".into()
+            )),
+        }
+    }
+    TopGroupsCollector::new(
+        selector,
+        groups,
+        Sort::new(collapsed.fields.clone()),
+        inner_hits_sort,
+        max_inner_hits,
+        track_scores,
+    )
+}
+
+impl<G: GroupSelector> SearchCollector for CollapsingTopDocsCollector<G> {
+    type LC = CollapsingLeafCollector<G>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader, &self.selector)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<CollapsingLeafCollector<G>> {
+        let mut collector =
+            CollapseBaseCollector::new(&self.sort, self.top_n, self.base.track_scores);
+        collector.set_next_reader(reader, &self.selector)?;
+        Ok(CollapsingLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            // the best hit of each of the top values is among the top hits of its leaf
+            while let Ok(leaf_hits) = receiver.recv() {
+                self.base.total_hits += leaf_hits.total_hits;
+                self.base.max_score = self.base.max_score.max(leaf_hits.max_score);
+                for (value, hit) in leaf_hits.hits {
+                    self.base.queue.add(value, hit);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<G: GroupSelector> Collector for CollapsingTopDocsCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+struct LeafCollapsedHits {
+    hits: Vec<(Option<VariantValue>, FieldDoc)>,
+    total_hits: usize,
+    max_score: f32,
+}
+
+pub struct CollapsingLeafCollector<G: GroupSelector> {
+    collector: CollapseBaseCollector<G>,
+    channel: Sender<LeafCollapsedHits>,
+}
+
+impl<G: GroupSelector> ParallelLeafCollector for CollapsingLeafCollector<G> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let hits = LeafCollapsedHits {
+            hits: self.collector.queue.take_hits(),
+            total_hits: self.collector.total_hits,
+            max_score: self.collector.max_score,
+        };
+        self.channel
+            .send(hits)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl<G: GroupSelector> Collector for CollapsingLeafCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::grouping::tests::*;
+    use core::search::sort_field::{SimpleSortField, SortField, SortFieldType};
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    fn collapsed(top_docs: &TopDocs) -> &CollapseTopFieldDocs {
+        match top_docs {
+            TopDocs::Collapse(c) => c,
+            _ => panic!("expected collapsed top docs"),
+        }
+    }
+
+    fn doc_ids(top_docs: &TopDocs) -> Vec<DocId> {
+        top_docs.score_docs().iter().map(|d| d.doc_id()).collect()
+    }
+
+    #[test]
+    fn test_collapse_top_docs() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(20)]);
+        let leaves = index_reader.leaves();
+
+        // mock scorers score by doc id
+        let sort = Sort::new(vec![SortField::new_score()]);
+        let mut collector = CollapsingTopDocsCollector::new(
+            "group".into(),
+            ModGroupSelector::new(3),
+            sort,
+            2,
+            true,
+        )
+        .unwrap();
+        assert!(collector.needs_scores());
+        collect_segments(&mut collector, &leaves, vec![(1..10).collect()]);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 9);
+        assert_eq!(doc_ids(&top_docs), vec![9, 8]);
+        let collapsed = collapsed(&top_docs);
+        assert_eq!(collapsed.field, "group");
+        assert_eq!(
+            collapsed.collapse_values,
+            vec![Some(VariantValue::Int(0)), Some(VariantValue::Int(2))]
+        );
+        assert!((collapsed.max_score() - 9.0).abs() < ::std::f32::EPSILON);
+
+        let sort = Sort::new(vec![SortField::new_score()]);
+        assert!(CollapsingTopDocsCollector::new(
+            "group".into(),
+            ModGroupSelector::new(3),
+            sort,
+            0,
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_collapse_parallel() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        // sort by doc id, so the first doc of each value is kept
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            String::new(),
+            SortFieldType::Doc,
+            false,
+        ))]);
+        let mut collector = CollapsingTopDocsCollector::new(
+            "group".into(),
+            ModGroupSelector::new(4),
+            sort,
+            3,
+            false,
+        )
+        .unwrap();
+        assert!(!collector.needs_scores());
+        collector.init_parallel();
+        for (leaf, docs) in leaves.iter().zip(vec![vec![1, 5, 9], vec![0, 3, 6]]) {
+            let mut leaf_collector = collector.leaf_collector(leaf).unwrap();
+            let mut scorer = create_mock_scorer(docs);
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                leaf_collector.collect(doc, &mut scorer).unwrap();
+            }
+            leaf_collector.finish_leaf().unwrap();
+        }
+        collector.finish_parallel().unwrap();
+
+        // docs 1, 5 and 9 share the value 1, then 10, 13 and 16 have values 2, 1 and 0
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 6);
+        assert_eq!(doc_ids(&top_docs), vec![1, 10, 16]);
+        assert_eq!(
+            collapsed(&top_docs).collapse_values,
+            vec![
+                Some(VariantValue::Int(1)),
+                Some(VariantValue::Int(2)),
+                Some(VariantValue::Int(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collapse_inner_hits() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        let segments = || vec![vec![1, 2, 4, 7], vec![2, 5]];
+
+        let sort = Sort::new(vec![SortField::new_score()]);
+        let mut collector = CollapsingTopDocsCollector::new(
+            "group".into(),
+            ModGroupSelector::new(3),
+            sort.clone(),
+            2,
+            false,
+        )
+        .unwrap();
+        collect_segments(&mut collector, &leaves, segments());
+        let top_docs = collector.top_docs();
+        // docs 7 and 15 have the best local scores of the values 1 and 0
+        assert_eq!(doc_ids(&top_docs), vec![7, 15]);
+
+        let mut inner_collector = inner_hits_collector(
+            ModGroupSelector::new(3),
+            collapsed(&top_docs),
+            sort,
+            2,
+            false,
+        )
+        .unwrap();
+        collect_segments(&mut inner_collector, &leaves, segments());
+        let top_groups = inner_collector.top_groups();
+        assert_eq!(top_groups.groups.len(), 2);
+
+        let inner_docs = |i: usize| -> Vec<DocId> {
+            top_groups.groups[i]
+                .score_docs
+                .iter()
+                .map(|d| d.doc_id())
+                .collect()
+        };
+        assert_eq!(top_groups.groups[0].group_value, Some(VariantValue::Int(1)));
+        assert_eq!(inner_docs(0), vec![7, 4]);
+        assert_eq!(top_groups.groups[1].group_value, Some(VariantValue::Int(0)));
+        assert_eq!(inner_docs(1), vec![15, 12]);
+    }
+}
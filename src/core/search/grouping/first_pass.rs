@@ -0,0 +1,350 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::grouping::{GroupSelector, SortValuesReader};
+use core::search::scorer::Scorer;
+use core::search::sort_field::Sort;
+use core::util::{DocId, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// A group found by the first pass of grouping, represented by its most
+/// competitive doc by the group sort.
+#[derive(Debug, Clone)]
+pub struct SearchGroup {
+    /// The group value, `None` for the docs without a value.
+    pub group_value: Option<VariantValue>,
+    /// The sort values of the top doc of this group.
+    pub sort_values: Vec<VariantValue>,
+    top_doc: DocId,
+}
+
+impl SearchGroup {
+    pub(super) fn new(
+        group_value: Option<VariantValue>,
+        sort_values: Vec<VariantValue>,
+        top_doc: DocId,
+    ) -> SearchGroup {
+        SearchGroup {
+            group_value,
+            sort_values,
+            top_doc,
+        }
+    }
+
+    /// The top doc of this group by the group sort.
+    pub fn top_doc(&self) -> DocId {
+        self.top_doc
+    }
+}
+
+fn compare_groups(sort: &Sort, g1: &SearchGroup, g2: &SearchGroup) -> Ordering {
+    sort.compare_values(&g1.sort_values, &g2.sort_values)
+        .then_with(|| g1.top_doc.cmp(&g2.top_doc))
+}
+
+/// Keeps the `top_n` most competitive groups.
+struct SearchGroupQueue {
+    group_sort: Sort,
+    top_n: usize,
+    groups: HashMap<Option<VariantValue>, SearchGroup>,
+    // key of the least competitive group, only valid when the queue is full
+    bottom: Option<Option<VariantValue>>,
+}
+
+impl SearchGroupQueue {
+    fn new(group_sort: Sort, top_n: usize) -> SearchGroupQueue {
+        SearchGroupQueue {
+            group_sort,
+            top_n,
+            groups: HashMap::with_capacity(top_n),
+            bottom: None,
+        }
+    }
+
+    fn add(&mut self, group: SearchGroup) {
+        if let Some(existing) = self.groups.get_mut(&group.group_value) {
+            if compare_groups(&self.group_sort, &group, existing) == Ordering::Less {
+                *existing = group;
+                self.bottom = None;
+            }
+            return;
+        }
+
+        if self.groups.len() < self.top_n {
+            self.groups.insert(group.group_value.clone(), group);
+            return;
+        }
+
+        if self.bottom.is_none() {
+            let sort = &self.group_sort;
+            self.bottom = self
+                .groups
+                .values()
+                .max_by(|g1, g2| compare_groups(sort, g1, g2))
+                .map(|g| g.group_value.clone());
+        }
+        let bottom_key = self.bottom.take().unwrap();
+        if compare_groups(&self.group_sort, &group, &self.groups[&bottom_key]) == Ordering::Less {
+            self.groups.remove(&bottom_key);
+            self.groups.insert(group.group_value.clone(), group);
+        } else {
+            self.bottom = Some(bottom_key);
+        }
+    }
+
+    fn take_groups(&mut self) -> Vec<SearchGroup> {
+        self.bottom = None;
+        mem::replace(&mut self.groups, HashMap::new())
+            .into_iter()
+            .map(|(_, g)| g)
+            .collect()
+    }
+}
+
+struct FirstPassBaseCollector<G: GroupSelector> {
+    selector: Option<G>,
+    values_reader: SortValuesReader,
+    queue: SearchGroupQueue,
+    needs_scores: bool,
+    doc_base: DocId,
+}
+
+impl<G: GroupSelector> FirstPassBaseCollector<G> {
+    fn new(group_sort: &Sort, top_n_groups: usize) -> FirstPassBaseCollector<G> {
+        FirstPassBaseCollector {
+            selector: None,
+            values_reader: SortValuesReader::new(group_sort),
+            queue: SearchGroupQueue::new(group_sort.clone(), top_n_groups),
+            needs_scores: group_sort.needs_scores(),
+            doc_base: 0,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+        selector: &G,
+    ) -> Result<()> {
+        self.doc_base = reader.doc_base;
+        self.selector = Some(selector.leaf_selector(reader)?);
+        self.values_reader.set_next_reader(reader)
+    }
+}
+
+impl<G: GroupSelector> Collector for FirstPassBaseCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        debug_assert!(self.selector.is_some());
+        let group_value = self.selector.as_mut().unwrap().group_value(doc)?;
+        let score = if self.needs_scores {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        let sort_values = self.values_reader.values(doc, score)?;
+        self.queue.add(SearchGroup {
+            group_value,
+            sort_values,
+            top_doc: self.doc_base + doc,
+        });
+        Ok(())
+    }
+}
+
+/// The first pass of grouping, which finds the top `top_n_groups` groups of the
+/// hits, each group sorted by its most competitive doc according to `group_sort`.
+///
+/// The groups found are passed to a `TopGroupsCollector` to collect the top docs
+/// of each group.
+pub struct FirstPassGroupingCollector<G: GroupSelector> {
+    selector: G,
+    group_sort: Sort,
+    top_n_groups: usize,
+    base: FirstPassBaseCollector<G>,
+    channel: Option<(Sender<Vec<SearchGroup>>, Receiver<Vec<SearchGroup>>)>,
+}
+
+impl<G: GroupSelector> FirstPassGroupingCollector<G> {
+    pub fn new(selector: G, group_sort: Sort, top_n_groups: usize) -> Result<Self> {
+        if top_n_groups < 1 {
+            bail!(IllegalArgument("top_n_groups must be >= 1".into()));
+        }
+        let base = FirstPassBaseCollector::new(&group_sort, top_n_groups);
+        Ok(FirstPassGroupingCollector {
+            selector,
+            group_sort,
+            top_n_groups,
+            base,
+            channel: None,
+        })
+    }
+
+    /// Returns the top groups sorted by `group_sort`, skipping the first `offset`
+    /// groups.
+    pub fn top_groups(&mut self, offset: usize) -> Vec<SearchGroup> {
+        let mut groups = self.base.queue.take_groups();
+        let sort = &self.group_sort;
+        groups.sort_by(|g1, g2| compare_groups(sort, g1, g2));
+        groups.into_iter().skip(offset).collect()
+    }
+}
+
+impl<G: GroupSelector> SearchCollector for FirstPassGroupingCollector<G> {
+    type LC = FirstPassLeafCollector<G>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader, &self.selector)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<FirstPassLeafCollector<G>> {
+        let mut collector = FirstPassBaseCollector::new(&self.group_sort, self.top_n_groups);
+        collector.set_next_reader(reader, &self.selector)?;
+        Ok(FirstPassLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            // the top groups of every leaf contain the leaf's top docs of all the top
+            // groups, so merging them gives the exact top groups
+            while let Ok(groups) = receiver.recv() {
+                for group in groups {
+                    self.base.queue.add(group);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<G: GroupSelector> Collector for FirstPassGroupingCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+pub struct FirstPassLeafCollector<G: GroupSelector> {
+    collector: FirstPassBaseCollector<G>,
+    channel: Sender<Vec<SearchGroup>>,
+}
+
+impl<G: GroupSelector> ParallelLeafCollector for FirstPassLeafCollector<G> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let groups = self.collector.queue.take_groups();
+        self.channel
+            .send(groups)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl<G: GroupSelector> Collector for FirstPassLeafCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::grouping::tests::*;
+    use core::search::sort_field::SortField;
+
+    fn group_ids(groups: &[SearchGroup]) -> Vec<(Option<VariantValue>, DocId)> {
+        groups
+            .iter()
+            .map(|g| (g.group_value.clone(), g.top_doc()))
+            .collect()
+    }
+
+    #[test]
+    fn test_first_pass_top_groups() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let sort = Sort::new(vec![SortField::new_score()]);
+        let mut collector =
+            FirstPassGroupingCollector::new(ModGroupSelector::new(3), sort.clone(), 2).unwrap();
+        assert!(collector.needs_scores());
+        // mock scorers score by doc id
+        collect_segments(&mut collector, &leaves, vec![(1..10).collect()]);
+
+        let groups = collector.top_groups(0);
+        assert_eq!(
+            group_ids(&groups),
+            vec![
+                (Some(VariantValue::Int(0)), 9),
+                (Some(VariantValue::Int(2)), 8),
+            ]
+        );
+        assert_eq!(groups[0].sort_values, vec![VariantValue::Float(9.0)]);
+
+        let mut collector =
+            FirstPassGroupingCollector::new(ModGroupSelector::new(3), sort, 2).unwrap();
+        collect_segments(&mut collector, &leaves, vec![(1..10).collect()]);
+        assert_eq!(
+            group_ids(&collector.top_groups(1)),
+            vec![(Some(VariantValue::Int(2)), 8)]
+        );
+    }
+
+    #[test]
+    fn test_first_pass_invalid_top_n() {
+        let sort = Sort::new(vec![SortField::new_score()]);
+        assert!(FirstPassGroupingCollector::new(ModGroupSelector::new(3), sort, 0).is_err());
+    }
+}
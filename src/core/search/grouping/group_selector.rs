@@ -0,0 +1,108 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::{NumericDocValues, SortedDocValues};
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::util::{BitsMut, DocId, VariantValue};
+
+use error::Result;
+
+/// Selects the group value of the docs of a segment.
+///
+/// A selector is created per grouping collector, which asks it for a selector of
+/// each segment with `leaf_selector`, so the leaf selectors may be collected in
+/// parallel.
+pub trait GroupSelector: Send + 'static {
+    /// Returns a selector for the docs of the given segment.
+    fn leaf_selector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Returns the group value of `doc` in the current segment, `None` if the doc
+    /// has no value, in which case it belongs to the null group.
+    fn group_value(&mut self, doc: DocId) -> Result<Option<VariantValue>>;
+}
+
+/// Groups the docs by the terms of a `SortedDocValues` field.
+pub struct TermGroupSelector {
+    field: String,
+    doc_values: Option<Box<dyn SortedDocValues>>,
+}
+
+impl TermGroupSelector {
+    pub fn new(field: String) -> TermGroupSelector {
+        TermGroupSelector {
+            field,
+            doc_values: None,
+        }
+    }
+}
+
+impl GroupSelector for TermGroupSelector {
+    fn leaf_selector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self> {
+        Ok(TermGroupSelector {
+            field: self.field.clone(),
+            doc_values: Some(reader.reader.get_sorted_doc_values(&self.field)?),
+        })
+    }
+
+    fn group_value(&mut self, doc: DocId) -> Result<Option<VariantValue>> {
+        debug_assert!(self.doc_values.is_some());
+        let doc_values = self.doc_values.as_mut().unwrap();
+        let ord = doc_values.get_ord(doc)?;
+        if ord < 0 {
+            Ok(None)
+        } else {
+            Ok(Some(VariantValue::Binary(doc_values.lookup_ord(ord)?)))
+        }
+    }
+}
+
+/// Groups the docs by the values of a `NumericDocValues` field.
+pub struct NumericGroupSelector {
+    field: String,
+    doc_values: Option<Box<dyn NumericDocValues>>,
+    docs_with_field: Option<Box<dyn BitsMut>>,
+}
+
+impl NumericGroupSelector {
+    pub fn new(field: String) -> NumericGroupSelector {
+        NumericGroupSelector {
+            field,
+            doc_values: None,
+            docs_with_field: None,
+        }
+    }
+}
+
+impl GroupSelector for NumericGroupSelector {
+    fn leaf_selector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self> {
+        Ok(NumericGroupSelector {
+            field: self.field.clone(),
+            doc_values: Some(reader.reader.get_numeric_doc_values(&self.field)?),
+            docs_with_field: Some(reader.reader.get_docs_with_field(&self.field)?),
+        })
+    }
+
+    fn group_value(&mut self, doc: DocId) -> Result<Option<VariantValue>> {
+        debug_assert!(self.doc_values.is_some());
+        let value = self.doc_values.as_ref().unwrap().get(doc)?;
+        // a value of 0 may also mean the doc has no value
+        if value == 0 && !self.docs_with_field.as_mut().unwrap().get(doc as usize)? {
+            Ok(None)
+        } else {
+            Ok(Some(VariantValue::Long(value)))
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Result grouping, collapsing the hits of a search by a doc values field.
+//!
+//! Grouping takes two passes over the index: `FirstPassGroupingCollector` finds
+//! the top groups by `group_sort`, then `TopGroupsCollector` collects the top
+//! docs of each of those groups, sorted by `within_group_sort`.
+//!
+//! `CollapsingTopDocsCollector` finds the top hits keeping only the best hit of
+//! each group, their inner hits are collected by a second pass with the
+//! `TopGroupsCollector` built by `inner_hits_collector`.
+
+mod group_selector;
+
+pub use self::group_selector::*;
+
+mod first_pass;
+
+pub use self::first_pass::*;
+
+mod top_groups;
+
+pub use self::top_groups::*;
+
+mod collapsing;
+
+pub use self::collapsing::*;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::sort_field::{ComparatorValue, FieldComparator, FieldComparatorEnum, Sort};
+use core::util::{DocId, VariantValue};
+
+use error::Result;
+
+/// Reads the sort values of single hits, with one slot comparators.
+struct SortValuesReader {
+    comparators: Vec<FieldComparatorEnum>,
+}
+
+impl SortValuesReader {
+    fn new(sort: &Sort) -> SortValuesReader {
+        let comparators = sort
+            .get_sort()
+            .iter()
+            .map(|f| f.get_comparator(1, f.missing_value()))
+            .collect();
+        SortValuesReader { comparators }
+    }
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        for comparator in &mut self.comparators {
+            comparator.get_information_from_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    fn values(&mut self, doc: DocId, score: f32) -> Result<Vec<VariantValue>> {
+        let mut values = Vec::with_capacity(self.comparators.len());
+        for comparator in &mut self.comparators {
+            let value = ComparatorValue::of_hit(comparator.get_type(), doc, score);
+            comparator.copy(0, value)?;
+            values.push(comparator.value(0));
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use core::search::collector::{Collector, SearchCollector};
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    /// Groups the docs by the remainder of their global doc id.
+    pub struct ModGroupSelector {
+        modulo: DocId,
+        doc_base: DocId,
+    }
+
+    impl ModGroupSelector {
+        pub fn new(modulo: DocId) -> ModGroupSelector {
+            ModGroupSelector {
+                modulo,
+                doc_base: 0,
+            }
+        }
+    }
+
+    impl GroupSelector for ModGroupSelector {
+        fn leaf_selector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self> {
+            Ok(ModGroupSelector {
+                modulo: self.modulo,
+                doc_base: reader.doc_base,
+            })
+        }
+
+        fn group_value(&mut self, doc: DocId) -> Result<Option<VariantValue>> {
+            Ok(Some(VariantValue::Int((self.doc_base + doc) % self.modulo)))
+        }
+    }
+
+    /// Collect the docs of each segment with mock scorers, which score by doc id.
+    pub fn collect_segments<C: Codec, T: SearchCollector>(
+        collector: &mut T,
+        leaves: &[LeafReaderContext<'_, C>],
+        segments: Vec<Vec<DocId>>,
+    ) {
+        for (leaf, docs) in leaves.iter().zip(segments) {
+            collector.set_next_reader(leaf).unwrap();
+            let mut scorer = create_mock_scorer(docs);
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                collector.collect(doc, &mut scorer).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{
+    Collector, ParallelLeafCollector, SearchCollector, TopFieldCollector,
+};
+use core::search::grouping::{GroupSelector, SearchGroup};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{FieldDoc, ScoreDocHit, Sort, SortField, TopDocs, TopFieldDocs};
+use core::util::{DocId, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// The top docs of a single group.
+#[derive(Debug)]
+pub struct GroupDocs {
+    /// The group value, `None` for the docs without a value.
+    pub group_value: Option<VariantValue>,
+    /// The sort values of the group, as found by the first pass.
+    pub group_sort_values: Vec<VariantValue>,
+    /// Total hits within this group.
+    pub total_hits: usize,
+    /// Max score in this group, `NaN` if scores were not tracked.
+    pub max_score: f32,
+    /// The top docs of this group, sorted by the within group sort.
+    pub score_docs: Vec<ScoreDocHit>,
+}
+
+/// The result of grouping, the top groups with the top docs of each group.
+#[derive(Debug)]
+pub struct TopGroups {
+    /// The fields the groups were sorted by.
+    pub group_sort: Vec<SortField>,
+    /// The fields the docs of each group were sorted by.
+    pub within_group_sort: Vec<SortField>,
+    /// Total hits of the search.
+    pub total_hits: usize,
+    /// Total hits belonging to one of the top groups.
+    pub total_grouped_hits: usize,
+    pub groups: Vec<GroupDocs>,
+}
+
+struct TopGroupsBaseCollector<G: GroupSelector> {
+    selector: Option<G>,
+    group_index: HashMap<Option<VariantValue>, usize>,
+    collectors: Vec<TopFieldCollector>,
+    needs_scores: bool,
+    total_hits: usize,
+    total_grouped_hits: usize,
+}
+
+impl<G: GroupSelector> TopGroupsBaseCollector<G> {
+    fn new(
+        group_index: HashMap<Option<VariantValue>, usize>,
+        within_group_sort: &Sort,
+        max_docs_per_group: usize,
+        track_scores: bool,
+    ) -> TopGroupsBaseCollector<G> {
+        let collectors = (0..group_index.len())
+            .map(|_| {
+                TopFieldCollector::new(within_group_sort.clone(), max_docs_per_group, track_scores)
+            })
+            .collect();
+        TopGroupsBaseCollector {
+            selector: None,
+            group_index,
+            collectors,
+            needs_scores: track_scores || within_group_sort.needs_scores(),
+            total_hits: 0,
+            total_grouped_hits: 0,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+        selector: &G,
+    ) -> Result<()> {
+        self.selector = Some(selector.leaf_selector(reader)?);
+        for collector in &mut self.collectors {
+            collector.set_next_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    fn take_top_docs(&mut self) -> Vec<TopDocs> {
+        self.collectors.iter_mut().map(|c| c.top_docs()).collect()
+    }
+}
+
+impl<G: GroupSelector> Collector for TopGroupsBaseCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        debug_assert!(self.selector.is_some());
+        self.total_hits += 1;
+        let group_value = self.selector.as_mut().unwrap().group_value(doc)?;
+        if let Some(&index) = self.group_index.get(&group_value) {
+            self.total_grouped_hits += 1;
+            self.collectors[index].collect(doc, scorer)?;
+        }
+        Ok(())
+    }
+}
+
+/// The second pass of grouping, which collects the top `max_docs_per_group` docs
+/// of each of the groups found by a `FirstPassGroupingCollector`, sorted by
+/// `within_group_sort`.
+pub struct TopGroupsCollector<G: GroupSelector> {
+    selector: G,
+    groups: Vec<SearchGroup>,
+    group_sort: Sort,
+    within_group_sort: Sort,
+    max_docs_per_group: usize,
+    track_scores: bool,
+    base: TopGroupsBaseCollector<G>,
+    // top docs of each group collected by the leaf collectors of a parallel search
+    leaf_docs: Vec<Vec<TopDocs>>,
+    channel: Option<(Sender<LeafGroupDocs>, Receiver<LeafGroupDocs>)>,
+}
+
+impl<G: GroupSelector> TopGroupsCollector<G> {
+    pub fn new(
+        selector: G,
+        groups: Vec<SearchGroup>,
+        group_sort: Sort,
+        within_group_sort: Sort,
+        max_docs_per_group: usize,
+        track_scores: bool,
+    ) -> Result<Self> {
+        if max_docs_per_group < 1 {
+            bail!(IllegalArgument("max_docs_per_group must be >= 1".into()));
+        }
+        let base = TopGroupsBaseCollector::new(
+            Self::group_index(&groups),
+            &within_group_sort,
+            max_docs_per_group,
+            track_scores,
+        );
+        let leaf_docs = groups.iter().map(|_| vec![]).collect();
+        Ok(TopGroupsCollector {
+            selector,
+            groups,
+            group_sort,
+            within_group_sort,
+            max_docs_per_group,
+            track_scores,
+            base,
+            leaf_docs,
+            channel: None,
+        })
+    }
+
+    fn group_index(groups: &[SearchGroup]) -> HashMap<Option<VariantValue>, usize> {
+        groups
+            .iter()
+            .enumerate()
+            .map(|(i, g)| (g.group_value.clone(), i))
+            .collect()
+    }
+
+    /// Returns the top groups with their top docs, in the order of the groups
+    /// passed to this collector.
+    pub fn top_groups(&mut self) -> TopGroups {
+        let top_docs = self.base.take_top_docs();
+        let mut groups = Vec::with_capacity(self.groups.len());
+        for (i, group_top_docs) in top_docs.into_iter().enumerate() {
+            let mut total_hits = 0;
+            let mut max_score = f32::NAN;
+            let mut docs: Vec<FieldDoc> = vec![];
+            let leaf_docs = mem::replace(&mut self.leaf_docs[i], vec![]);
+            for top_docs in Some(group_top_docs).into_iter().chain(leaf_docs) {
+                if let TopDocs::Field(field_docs) = top_docs {
+                    let TopFieldDocs {
+                        total_hits: hits,
+                        score_docs,
+                        max_score: score,
+                        ..
+                    } = field_docs;
+                    total_hits += hits;
+                    max_score = max_score.max(score);
+                    docs.extend(score_docs.into_iter().map(|hit| match hit {
+                        ScoreDocHit::Field(doc) => doc,
+                        _ => unreachable!(),
+                    }));
+                }
+            }
+            let sort = &self.within_group_sort;
+            docs.sort_by(|d1, d2| sort.compare_field_docs(d1, d2));
+            docs.truncate(self.max_docs_per_group);
+
+            let group = &self.groups[i];
+            groups.push(GroupDocs {
+                group_value: group.group_value.clone(),
+                group_sort_values: group.sort_values.clone(),
+                total_hits,
+                max_score,
+                score_docs: docs.into_iter().map(ScoreDocHit::Field).collect(),
+            });
+        }
+
+        TopGroups {
+            group_sort: self.group_sort.get_sort().to_vec(),
+            within_group_sort: self.within_group_sort.get_sort().to_vec(),
+            total_hits: self.base.total_hits,
+            total_grouped_hits: self.base.total_grouped_hits,
+            groups,
+        }
+    }
+}
+
+impl<G: GroupSelector> SearchCollector for TopGroupsCollector<G> {
+    type LC = TopGroupsLeafCollector<G>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader, &self.selector)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TopGroupsLeafCollector<G>> {
+        let mut collector = TopGroupsBaseCollector::new(
+            self.base.group_index.clone(),
+            &self.within_group_sort,
+            self.max_docs_per_group,
+            self.track_scores,
+        );
+        collector.set_next_reader(reader, &self.selector)?;
+        Ok(TopGroupsLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.base.total_hits += docs.total_hits;
+                self.base.total_grouped_hits += docs.total_grouped_hits;
+                for (i, top_docs) in docs.top_docs.into_iter().enumerate() {
+                    self.leaf_docs[i].push(top_docs);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<G: GroupSelector> Collector for TopGroupsCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+struct LeafGroupDocs {
+    top_docs: Vec<TopDocs>,
+    total_hits: usize,
+    total_grouped_hits: usize,
+}
+
+pub struct TopGroupsLeafCollector<G: GroupSelector> {
+    collector: TopGroupsBaseCollector<G>,
+    channel: Sender<LeafGroupDocs>,
+}
+
+impl<G: GroupSelector> ParallelLeafCollector for TopGroupsLeafCollector<G> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let docs = LeafGroupDocs {
+            top_docs: self.collector.take_top_docs(),
+            total_hits: self.collector.total_hits,
+            total_grouped_hits: self.collector.total_grouped_hits,
+        };
+        self.channel
+            .send(docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl<G: GroupSelector> Collector for TopGroupsLeafCollector<G> {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::grouping::tests::*;
+    use core::search::grouping::FirstPassGroupingCollector;
+    use core::search::sort_field::{SimpleSortField, SortFieldType};
+
+    fn group_docs(group: &GroupDocs) -> Vec<DocId> {
+        group.score_docs.iter().map(|d| d.doc_id()).collect()
+    }
+
+    #[test]
+    fn test_top_groups() {
+        let index_reader =
+            MockIndexReader::new(vec![MockLeafReader::new(10), MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        let segments = || vec![vec![1, 2, 3, 5, 7], vec![1, 2, 4]];
+
+        // groups sorted by their first doc, group 0 has docs 3 and 12 only
+        let group_sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            String::new(),
+            SortFieldType::Doc,
+            false,
+        ))]);
+        let mut first_pass =
+            FirstPassGroupingCollector::new(ModGroupSelector::new(3), group_sort.clone(), 2)
+                .unwrap();
+        collect_segments(&mut first_pass, &leaves, segments());
+        let groups = first_pass.top_groups(0);
+        assert_eq!(groups.len(), 2);
+
+        let within_group_sort = group_sort.clone();
+        let mut collector = TopGroupsCollector::new(
+            ModGroupSelector::new(3),
+            groups,
+            group_sort,
+            within_group_sort,
+            2,
+            false,
+        )
+        .unwrap();
+        assert!(!collector.needs_scores());
+        collect_segments(&mut collector, &leaves, segments());
+
+        let top_groups = collector.top_groups();
+        assert_eq!(top_groups.total_hits, 8);
+        assert_eq!(top_groups.total_grouped_hits, 6);
+        assert_eq!(top_groups.groups.len(), 2);
+
+        let group = &top_groups.groups[0];
+        assert_eq!(group.group_value, Some(VariantValue::Int(1)));
+        assert_eq!(group.group_sort_values, vec![VariantValue::Int(1)]);
+        assert_eq!(group.total_hits, 2);
+        assert_eq!(group_docs(group), vec![1, 7]);
+
+        let group = &top_groups.groups[1];
+        assert_eq!(group.group_value, Some(VariantValue::Int(2)));
+        assert_eq!(group.total_hits, 4);
+        assert_eq!(group_docs(group), vec![2, 5]);
+    }
+
+    #[test]
+    fn test_top_groups_invalid_docs_per_group() {
+        let sort = Sort::new(vec![SortField::new_score()]);
+        let result = TopGroupsCollector::new(
+            ModGroupSelector::new(3),
+            vec![],
+            sort.clone(),
+            sort,
+            0,
+            true,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::util::{BitsMut, DocId};
+
+use error::Result;
+
+pub trait DocLongValues: Send {
+    /// Returns the value for `doc`, or `None` if the document has no value.
+    ///
+    /// Docs must be requested in increasing order.
+    fn get(&mut self, doc: DocId) -> Result<Option<i64>>;
+}
+
+/// A source of per-document long values, unlike `DoubleValuesSource` the
+/// values never depend on the score.
+pub trait LongValuesSource<C: Codec>: fmt::Display + Send + Sync {
+    /// Returns a `DocLongValues` instance for the passed-in `LeafReaderContext`.
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DocLongValues>>;
+}
+
+pub struct ConstantLongValuesSource {
+    value: i64,
+}
+
+impl ConstantLongValuesSource {
+    pub fn new(value: i64) -> ConstantLongValuesSource {
+        ConstantLongValuesSource { value }
+    }
+}
+
+impl<C: Codec> LongValuesSource<C> for ConstantLongValuesSource {
+    fn get_values(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DocLongValues>> {
+        Ok(Box::new(ConstantLongValues { value: self.value }))
+    }
+}
+
+impl fmt::Display for ConstantLongValuesSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "constant({})", self.value)
+    }
+}
+
+struct ConstantLongValues {
+    value: i64,
+}
+
+impl DocLongValues for ConstantLongValues {
+    fn get(&mut self, _doc: DocId) -> Result<Option<i64>> {
+        Ok(Some(self.value))
+    }
+}
+
+/// The values of a numeric doc values field.
+pub struct LongFieldValuesSource {
+    field: String,
+}
+
+impl LongFieldValuesSource {
+    pub fn new(field: String) -> LongFieldValuesSource {
+        LongFieldValuesSource { field }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+}
+
+impl<C: Codec> LongValuesSource<C> for LongFieldValuesSource {
+    fn get_values(&self, reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DocLongValues>> {
+        Ok(Box::new(LongFieldValues {
+            values: reader.reader.get_numeric_doc_values(&self.field)?,
+            docs_with_field: reader.reader.get_docs_with_field(&self.field)?,
+        }))
+    }
+}
+
+impl fmt::Display for LongFieldValuesSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "long({})", self.field)
+    }
+}
+
+struct LongFieldValues {
+    values: Box<dyn NumericDocValues>,
+    docs_with_field: Box<dyn BitsMut>,
+}
+
+impl DocLongValues for LongFieldValues {
+    fn get(&mut self, doc: DocId) -> Result<Option<i64>> {
+        if !self.docs_with_field.get(doc as usize)? {
+            return Ok(None);
+        }
+        Ok(Some(self.values.get_mut(doc)?))
+    }
+}
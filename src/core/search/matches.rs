@@ -0,0 +1,389 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use core::codec::PostingIterator;
+use core::search::scorer::Scorer;
+use core::util::DocId;
+
+use error::Result;
+
+/// An iterator over the matches of a query in one field of one document.
+///
+/// The iterator is unpositioned until `next` is called for the first time.
+pub trait MatchesIterator {
+    /// Advances to the next match, returns false once all the matches were visited.
+    fn next(&mut self) -> Result<bool>;
+
+    /// The position of the first term of the current match.
+    fn start_position(&self) -> i32;
+
+    /// The position of the last term of the current match.
+    fn end_position(&self) -> i32;
+
+    /// The start offset of the current match, or -1 if offsets were not indexed.
+    fn start_offset(&self) -> Result<i32>;
+
+    /// The end offset of the current match, or -1 if offsets were not indexed.
+    fn end_offset(&self) -> Result<i32>;
+
+    /// The leaf query the current match is for, e.g. `body:fox` or `body:"quick fox"`.
+    fn query(&self) -> &str;
+}
+
+/// The matches of a query on one document, per field, see `Weight::matches`.
+///
+/// A document can match without any term matching, e.g. for a
+/// `MatchAllDocsQuery` or a query whose weight doesn't report positions, in
+/// which case the matches have no field.
+#[derive(Default)]
+pub struct Matches {
+    fields: BTreeMap<String, Vec<Box<dyn MatchesIterator>>>,
+}
+
+impl Matches {
+    /// The matches of a document matched without any term.
+    pub fn no_terms() -> Matches {
+        Matches::default()
+    }
+
+    /// The matches of a document matched in `field` at the positions of `iterator`.
+    pub fn from_field(field: String, iterator: Box<dyn MatchesIterator>) -> Matches {
+        let mut fields = BTreeMap::new();
+        fields.insert(field, vec![iterator]);
+        Matches { fields }
+    }
+
+    /// Combines the matches of the sub queries of a query, the matches of sub
+    /// queries on a same field are merged in position order.
+    pub fn from_sub_matches(subs: Vec<Matches>) -> Matches {
+        let mut fields: BTreeMap<String, Vec<Box<dyn MatchesIterator>>> = BTreeMap::new();
+        for sub in subs {
+            for (field, iterators) in sub.fields {
+                fields.entry(field).or_default().extend(iterators);
+            }
+        }
+        Matches { fields }
+    }
+
+    /// Returns matches without any term if `scorer` matches `doc`, `None` otherwise.
+    /// This is what weights which can't report positions return.
+    pub fn from_scorer(scorer: Option<Box<dyn Scorer>>, doc: DocId) -> Result<Option<Matches>> {
+        if let Some(mut scorer) = scorer {
+            if scorer.advance(doc)? == doc {
+                return Ok(Some(Matches::no_terms()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The fields where terms matched, in name order.
+    pub fn fields(&self) -> Vec<&str> {
+        self.fields.keys().map(String::as_str).collect()
+    }
+
+    /// Whether no term matched in any field.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Takes the iterator over the matches in `field`, `None` if no term matched
+    /// in it.
+    pub fn take(&mut self, field: &str) -> Option<Box<dyn MatchesIterator>> {
+        let mut iterators = self.fields.remove(field)?;
+        if iterators.len() == 1 {
+            iterators.pop()
+        } else {
+            Some(Box::new(DisjunctionMatchesIterator::new(iterators)))
+        }
+    }
+}
+
+impl fmt::Debug for Matches {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Matches")
+            .field("fields", &self.fields())
+            .finish()
+    }
+}
+
+/// A `MatchesIterator` over the positions of a term, read from postings
+/// positioned on the matching document.
+pub struct TermMatchesIterator<T: PostingIterator> {
+    postings: T,
+    upto: i32,
+    position: i32,
+    query: String,
+}
+
+impl<T: PostingIterator> TermMatchesIterator<T> {
+    pub fn new(postings: T, query: String) -> Result<Self> {
+        let upto = postings.freq()?;
+        Ok(TermMatchesIterator {
+            postings,
+            upto,
+            position: -1,
+            query,
+        })
+    }
+}
+
+impl<T: PostingIterator> MatchesIterator for TermMatchesIterator<T> {
+    fn next(&mut self) -> Result<bool> {
+        if self.upto <= 0 {
+            return Ok(false);
+        }
+        self.upto -= 1;
+        self.position = self.postings.next_position()?;
+        Ok(true)
+    }
+
+    fn start_position(&self) -> i32 {
+        self.position
+    }
+
+    fn end_position(&self) -> i32 {
+        self.position
+    }
+
+    fn start_offset(&self) -> Result<i32> {
+        self.postings.start_offset()
+    }
+
+    fn end_offset(&self) -> Result<i32> {
+        self.postings.end_offset()
+    }
+
+    fn query(&self) -> &str {
+        &self.query
+    }
+}
+
+/// The positions and offsets of one match, offsets are -1 if they were not indexed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MatchPosition {
+    pub start_position: i32,
+    pub end_position: i32,
+    pub start_offset: i32,
+    pub end_offset: i32,
+}
+
+/// A `MatchesIterator` over matches collected beforehand, e.g. the occurrences
+/// of a phrase.
+pub struct PositionsMatchesIterator {
+    positions: Vec<MatchPosition>,
+    upto: usize,
+    query: String,
+}
+
+impl PositionsMatchesIterator {
+    pub fn new(mut positions: Vec<MatchPosition>, query: String) -> Self {
+        positions.sort();
+        PositionsMatchesIterator {
+            positions,
+            upto: 0,
+            query,
+        }
+    }
+
+    fn current(&self) -> &MatchPosition {
+        debug_assert!(self.upto > 0 && self.upto <= self.positions.len());
+        &self.positions[self.upto - 1]
+    }
+}
+
+impl MatchesIterator for PositionsMatchesIterator {
+    fn next(&mut self) -> Result<bool> {
+        if self.upto < self.positions.len() {
+            self.upto += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn start_position(&self) -> i32 {
+        self.current().start_position
+    }
+
+    fn end_position(&self) -> i32 {
+        self.current().end_position
+    }
+
+    fn start_offset(&self) -> Result<i32> {
+        Ok(self.current().start_offset)
+    }
+
+    fn end_offset(&self) -> Result<i32> {
+        Ok(self.current().end_offset)
+    }
+
+    fn query(&self) -> &str {
+        &self.query
+    }
+}
+
+/// Merges the matches of several iterators on a same field in the order of
+/// their start, then end position.
+struct DisjunctionMatchesIterator {
+    iterators: Vec<Box<dyn MatchesIterator>>,
+    current: Option<usize>,
+    started: bool,
+}
+
+impl DisjunctionMatchesIterator {
+    fn new(iterators: Vec<Box<dyn MatchesIterator>>) -> Self {
+        DisjunctionMatchesIterator {
+            iterators,
+            current: None,
+            started: false,
+        }
+    }
+
+    fn top(&self) -> &dyn MatchesIterator {
+        self.iterators[self.current.unwrap()].as_ref()
+    }
+}
+
+impl MatchesIterator for DisjunctionMatchesIterator {
+    fn next(&mut self) -> Result<bool> {
+        if !self.started {
+            self.started = true;
+            let mut iterators = Vec::with_capacity(self.iterators.len());
+            for mut iterator in self.iterators.drain(..) {
+                if iterator.next()? {
+                    iterators.push(iterator);
+                }
+            }
+            self.iterators = iterators;
+        } else if let Some(current) = self.current {
+            if !self.iterators[current].next()? {
+                self.iterators.swap_remove(current);
+            }
+        }
+
+        self.current = self
+            .iterators
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, it)| (it.start_position(), it.end_position()))
+            .map(|(i, _)| i);
+        Ok(self.current.is_some())
+    }
+
+    fn start_position(&self) -> i32 {
+        self.top().start_position()
+    }
+
+    fn end_position(&self) -> i32 {
+        self.top().end_position()
+    }
+
+    fn start_offset(&self) -> Result<i32> {
+        self.top().start_offset()
+    }
+
+    fn end_offset(&self) -> Result<i32> {
+        self.top().end_offset()
+    }
+
+    fn query(&self) -> &str {
+        self.top().query()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::create_mock_scorer;
+
+    fn position(start: i32, end: i32) -> MatchPosition {
+        MatchPosition {
+            start_position: start,
+            end_position: end,
+            start_offset: start * 4,
+            end_offset: end * 4 + 3,
+        }
+    }
+
+    fn collect(iterator: &mut dyn MatchesIterator) -> Vec<(i32, i32, i32, String)> {
+        let mut matches = vec![];
+        while iterator.next().unwrap() {
+            matches.push((
+                iterator.start_position(),
+                iterator.end_position(),
+                iterator.start_offset().unwrap(),
+                iterator.query().to_string(),
+            ));
+        }
+        matches
+    }
+
+    #[test]
+    fn test_sub_matches() {
+        let fox = Matches::from_field(
+            "body".into(),
+            Box::new(PositionsMatchesIterator::new(
+                vec![position(7, 7), position(2, 2)],
+                "body:fox".into(),
+            )),
+        );
+        let phrase = Matches::from_field(
+            "body".into(),
+            Box::new(PositionsMatchesIterator::new(
+                vec![position(1, 2)],
+                "body:\"quick fox\"".into(),
+            )),
+        );
+        let title = Matches::from_field(
+            "title".into(),
+            Box::new(PositionsMatchesIterator::new(
+                vec![position(0, 0)],
+                "title:fox".into(),
+            )),
+        );
+        let mut matches = Matches::from_sub_matches(vec![fox, Matches::no_terms(), phrase, title]);
+        assert_eq!(matches.fields(), vec!["body", "title"]);
+
+        let mut body = matches.take("body").unwrap();
+        assert_eq!(
+            collect(body.as_mut()),
+            vec![
+                (1, 2, 4, "body:\"quick fox\"".to_string()),
+                (2, 2, 8, "body:fox".to_string()),
+                (7, 7, 28, "body:fox".to_string()),
+            ]
+        );
+        assert!(matches.take("body").is_none());
+
+        let mut title = matches.take("title").unwrap();
+        assert_eq!(
+            collect(title.as_mut()),
+            vec![(0, 0, 0, "title:fox".to_string())]
+        );
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_from_scorer() {
+        let scorer = Box::new(create_mock_scorer(vec![1, 3, 5]));
+        let matches = Matches::from_scorer(Some(scorer), 3).unwrap();
+        assert!(matches.unwrap().is_empty());
+
+        let scorer = Box::new(create_mock_scorer(vec![1, 3, 5]));
+        assert!(Matches::from_scorer(Some(scorer), 4).unwrap().is_none());
+        assert!(Matches::from_scorer(None, 4).unwrap().is_none());
+    }
+}
@@ -13,6 +13,7 @@
 
 pub mod cache;
 pub mod collector;
+pub mod grouping;
 pub mod query;
 pub mod scorer;
 pub mod similarity;
@@ -26,6 +27,14 @@ mod explanation;
 
 pub use self::explanation::*;
 
+mod matches;
+
+pub use self::matches::*;
+
+mod profile;
+
+pub use self::profile::*;
+
 mod statistics;
 
 pub use self::statistics::*;
@@ -34,6 +43,14 @@ mod search_manager;
 
 pub use self::search_manager::*;
 
+mod double_values_source;
+
+pub use self::double_values_source::*;
+
+mod long_values_source;
+
+pub use self::long_values_source::*;
+
 use std::i32;
 
 use core::util::DocId;
@@ -210,7 +227,7 @@ pub mod tests {
     use super::query::Weight;
     use super::scorer::Scorer;
     use super::*;
-    use core::codec::Codec;
+    use core::codec::{Codec, PostingIterator};
     use core::index::reader::LeafReaderContext;
     use std::fmt;
 
@@ -261,6 +278,162 @@ pub mod tests {
         }
     }
 
+    /// A two phase iterator whose approximation is `approximation` and which only
+    /// matches the docs of `matches`.
+    pub struct MockTwoPhaseIterator {
+        approximation: MockDocIterator,
+        matches: Vec<DocId>,
+    }
+
+    impl MockTwoPhaseIterator {
+        pub fn new(approximation: Vec<DocId>, matches: Vec<DocId>) -> MockTwoPhaseIterator {
+            MockTwoPhaseIterator {
+                approximation: MockDocIterator::new(approximation),
+                matches,
+            }
+        }
+
+        fn to_match(&mut self, mut doc: DocId) -> Result<DocId> {
+            while doc != NO_MORE_DOCS && !self.matches()? {
+                doc = self.approximation.next()?;
+            }
+            Ok(doc)
+        }
+    }
+
+    impl DocIterator for MockTwoPhaseIterator {
+        fn doc_id(&self) -> DocId {
+            self.approximation.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            let doc = self.approximation.next()?;
+            self.to_match(doc)
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            let doc = self.approximation.advance(target)?;
+            self.to_match(doc)
+        }
+
+        fn cost(&self) -> usize {
+            self.approximation.cost()
+        }
+
+        fn matches(&mut self) -> Result<bool> {
+            Ok(self.matches.contains(&self.doc_id()))
+        }
+
+        fn match_cost(&self) -> f32 {
+            1.0
+        }
+
+        fn support_two_phase(&self) -> bool {
+            true
+        }
+
+        fn approximate_next(&mut self) -> Result<DocId> {
+            self.approximation.next()
+        }
+
+        fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+            self.approximation.advance(target)
+        }
+    }
+
+    /// A posting iterator over `docs` whose freq is the freq at the same index
+    /// in `freqs`, and whose positions and payloads, when set, are the ones at
+    /// the same index in `positions` and `payloads`.
+    pub struct MockPostingIterator {
+        docs: MockDocIterator,
+        freqs: Vec<i32>,
+        positions: Vec<Vec<i32>>,
+        payloads: Vec<Vec<Payload>>,
+        index: usize,
+        // the number of positions read in the current doc
+        upto: usize,
+    }
+
+    impl MockPostingIterator {
+        pub fn new(docs: Vec<DocId>, freqs: Vec<i32>) -> MockPostingIterator {
+            assert_eq!(docs.len(), freqs.len());
+            MockPostingIterator {
+                docs: MockDocIterator::new(docs),
+                freqs,
+                positions: vec![],
+                payloads: vec![],
+                index: 0,
+                upto: 0,
+            }
+        }
+
+        /// The postings of `docs` with the positions at the same index in
+        /// `positions`, the freqs being their numbers of positions.
+        pub fn with_positions(docs: Vec<DocId>, positions: Vec<Vec<i32>>) -> MockPostingIterator {
+            let freqs = positions.iter().map(|p| p.len() as i32).collect();
+            let mut postings = Self::new(docs, freqs);
+            postings.positions = positions;
+            postings
+        }
+
+        /// Sets the payloads of the positions, `payloads[i][j]` being the payload
+        /// of the j-th position of the i-th doc.
+        pub fn set_payloads(&mut self, payloads: Vec<Vec<Payload>>) {
+            assert_eq!(payloads.len(), self.positions.len());
+            self.payloads = payloads;
+        }
+    }
+
+    impl DocIterator for MockPostingIterator {
+        fn doc_id(&self) -> DocId {
+            self.docs.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            if self.docs.doc_id() != -1 {
+                self.index += 1;
+            }
+            self.upto = 0;
+            self.docs.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            loop {
+                let doc_id = self.next()?;
+                if doc_id >= target {
+                    return Ok(doc_id);
+                }
+            }
+        }
+
+        fn cost(&self) -> usize {
+            self.docs.cost()
+        }
+    }
+
+    impl PostingIterator for MockPostingIterator {
+        fn freq(&self) -> Result<i32> {
+            Ok(self.freqs[self.index])
+        }
+
+        fn next_position(&mut self) -> Result<i32> {
+            self.upto += 1;
+            Ok(self.positions[self.index][self.upto - 1])
+        }
+
+        fn start_offset(&self) -> Result<i32> {
+            unimplemented!()
+        }
+
+        fn end_offset(&self) -> Result<i32> {
+            unimplemented!()
+        }
+
+        fn payload(&self) -> Result<Payload> {
+            Ok(self.payloads[self.index][self.upto - 1].clone())
+        }
+    }
+
     pub struct MockSimpleScorer<T: DocIterator> {
         iterator: T,
     }
@@ -301,6 +474,10 @@ pub mod tests {
             self.iterator.match_cost()
         }
 
+        fn support_two_phase(&self) -> bool {
+            self.iterator.support_two_phase()
+        }
+
         fn approximate_next(&mut self) -> Result<DocId> {
             self.iterator.approximate_next()
         }
@@ -342,14 +519,32 @@ pub mod tests {
             false
         }
 
-        fn explain(&self, _reader: &LeafReaderContext<'_, C>, _doc: DocId) -> Result<Explanation> {
-            unimplemented!()
+        fn estimate_cost(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+            Ok(Some(self.docs.len()))
+        }
+
+        fn explain(&self, _reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+            if self.docs.contains(&doc) {
+                Ok(Explanation::new(
+                    true,
+                    doc as f32,
+                    format!("{}", self),
+                    vec![],
+                ))
+            } else {
+                Ok(Explanation::new(
+                    false,
+                    0.0f32,
+                    format!("{} doesn't match id {}", self, doc),
+                    vec![],
+                ))
+            }
         }
     }
 
     impl fmt::Display for MockSimpleWeight {
-        fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
-            unimplemented!()
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "MockSimpleWeight(docs: {:?})", self.docs)
         }
     }
 
@@ -357,6 +552,13 @@ pub mod tests {
         MockSimpleScorer::new(MockDocIterator::new(docs))
     }
 
+    pub fn create_mock_two_phase_scorer(
+        approximation: Vec<DocId>,
+        matches: Vec<DocId>,
+    ) -> MockSimpleScorer<MockTwoPhaseIterator> {
+        MockSimpleScorer::new(MockTwoPhaseIterator::new(approximation, matches))
+    }
+
     pub fn create_mock_weight(docs: Vec<DocId>) -> MockSimpleWeight {
         MockSimpleWeight::new(docs)
     }
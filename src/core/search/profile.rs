@@ -0,0 +1,426 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use core::codec::{Codec, CodecTermState};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, Weight};
+use core::search::scorer::{FeatureResult, Scorer};
+use core::search::searcher::{SearchPlanBuilder, TermContext};
+use core::search::similarity::Similarity;
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::search::{DocIterator, Matches};
+use core::util::{DocId, IndexedContext};
+use error::{ErrorKind::IllegalState, Result};
+
+/// The timed operations of a query profile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileTiming {
+    CreateWeight,
+    BuildScorer,
+    NextDoc,
+    Advance,
+    Match,
+    Score,
+}
+
+impl ProfileTiming {
+    pub const ALL: [ProfileTiming; 6] = [
+        ProfileTiming::CreateWeight,
+        ProfileTiming::BuildScorer,
+        ProfileTiming::NextDoc,
+        ProfileTiming::Advance,
+        ProfileTiming::Match,
+        ProfileTiming::Score,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ProfileTiming::CreateWeight => "create_weight",
+            ProfileTiming::BuildScorer => "build_scorer",
+            ProfileTiming::NextDoc => "next_doc",
+            ProfileTiming::Advance => "advance",
+            ProfileTiming::Match => "match",
+            ProfileTiming::Score => "score",
+        }
+    }
+}
+
+impl fmt::Display for ProfileTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The accumulated time and call count of each `ProfileTiming` of a query node,
+/// shared by the weight and the scorers of all the segments.
+#[derive(Default)]
+struct ProfileBreakdown {
+    times: [AtomicU64; 6],
+    counts: [AtomicU64; 6],
+}
+
+impl ProfileBreakdown {
+    fn record(&self, timing: ProfileTiming, start: Instant) {
+        let elapsed = start.elapsed();
+        let nanos = elapsed.as_secs() * 1_000_000_000 + u64::from(elapsed.subsec_nanos());
+        self.times[timing as usize].fetch_add(nanos, Ordering::Relaxed);
+        self.counts[timing as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn timings(&self) -> Vec<TimingBreakdown> {
+        ProfileTiming::ALL
+            .iter()
+            .map(|&timing| TimingBreakdown {
+                timing,
+                time: Duration::from_nanos(self.times[timing as usize].load(Ordering::Relaxed)),
+                count: self.counts[timing as usize].load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// The time spent in, and the number of calls to, one operation of a query node.
+#[derive(Clone, Debug)]
+pub struct TimingBreakdown {
+    pub timing: ProfileTiming,
+    pub time: Duration,
+    pub count: u64,
+}
+
+/// The profile of a node of the query tree.
+///
+/// `time` is the sum of the times of the breakdown, so it includes the time
+/// spent in the children of the node.
+#[derive(Clone, Debug)]
+pub struct QueryProfile {
+    pub query_type: &'static str,
+    pub description: String,
+    pub time: Duration,
+    pub breakdown: Vec<TimingBreakdown>,
+    pub children: Vec<QueryProfile>,
+}
+
+impl QueryProfile {
+    /// Returns the breakdown of the given operation.
+    pub fn timing(&self, timing: ProfileTiming) -> &TimingBreakdown {
+        &self.breakdown[timing as usize]
+    }
+}
+
+struct ProfileNode {
+    query_type: &'static str,
+    description: String,
+    breakdown: Arc<ProfileBreakdown>,
+    children: Vec<ProfileNode>,
+}
+
+impl ProfileNode {
+    fn profile(&self) -> QueryProfile {
+        let breakdown = self.breakdown.timings();
+        QueryProfile {
+            query_type: self.query_type,
+            description: self.description.clone(),
+            time: breakdown.iter().map(|t| t.time).sum(),
+            breakdown,
+            children: self.children.iter().map(ProfileNode::profile).collect(),
+        }
+    }
+}
+
+/// A `SearchPlanBuilder` that wraps the weights of every node of the query tree
+/// with a `ProfileWeight`, so that the creation of the weights and scorers and
+/// the iteration over the matches are timed.
+///
+/// The query cache is bypassed, so that the profile reflects the actual work
+/// done by the queries.
+pub struct ProfilePlanBuilder<'a, C: Codec, B: SearchPlanBuilder<C> + ?Sized + 'a> {
+    builder: &'a B,
+    // the nodes whose weight is being created
+    stack: RefCell<Vec<ProfileNode>>,
+    roots: RefCell<Vec<ProfileNode>>,
+    _codec: ::std::marker::PhantomData<C>,
+}
+
+impl<'a, C: Codec, B: SearchPlanBuilder<C> + ?Sized + 'a> ProfilePlanBuilder<'a, C, B> {
+    pub fn new(builder: &'a B) -> ProfilePlanBuilder<'a, C, B> {
+        ProfilePlanBuilder {
+            builder,
+            stack: RefCell::new(vec![]),
+            roots: RefCell::new(vec![]),
+            _codec: ::std::marker::PhantomData,
+        }
+    }
+
+    /// Returns the profile of the last top level query whose weight was created.
+    pub fn profile(&self) -> Result<QueryProfile> {
+        match self.roots.borrow().last() {
+            Some(root) => Ok(root.profile()),
+            None => bail!(IllegalState("no query was profiled".into())),
+        }
+    }
+}
+
+impl<'a, C: Codec, B: SearchPlanBuilder<C> + ?Sized + 'a> SearchPlanBuilder<C>
+    for ProfilePlanBuilder<'a, C, B>
+{
+    fn num_docs(&self) -> i32 {
+        self.builder.num_docs()
+    }
+
+    fn max_doc(&self) -> i32 {
+        self.builder.max_doc()
+    }
+
+    fn create_weight(
+        &self,
+        query: &dyn Query<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let breakdown = Arc::new(ProfileBreakdown::default());
+        self.stack.borrow_mut().push(ProfileNode {
+            query_type: "",
+            description: query.to_string(),
+            breakdown: Arc::clone(&breakdown),
+            children: vec![],
+        });
+        // the sub queries are created with this builder, and so profiled as children
+        let start = Instant::now();
+        let weight = query.create_weight(self, needs_scores);
+        breakdown.record(ProfileTiming::CreateWeight, start);
+
+        let mut node = self.stack.borrow_mut().pop().unwrap();
+        let weight = weight?;
+        node.query_type = weight.query_type();
+        match self.stack.borrow_mut().last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.roots.borrow_mut().push(node),
+        }
+        Ok(Box::new(ProfileWeight { weight, breakdown }))
+    }
+
+    fn create_normalized_weight(
+        &self,
+        query: &dyn Query<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.create_weight(query, needs_scores)
+    }
+
+    fn similarity(&self, field: &str, needs_scores: bool) -> Box<dyn Similarity<C>> {
+        self.builder.similarity(field, needs_scores)
+    }
+
+    fn term_state(&self, term: &Term) -> Result<Arc<TermContext<CodecTermState<C>>>> {
+        self.builder.term_state(term)
+    }
+
+    fn term_statistics(
+        &self,
+        term: &Term,
+        context: &TermContext<CodecTermState<C>>,
+    ) -> TermStatistics {
+        self.builder.term_statistics(term, context)
+    }
+
+    fn collections_statistics(&self, field: &str) -> Result<CollectionStatistics> {
+        self.builder.collections_statistics(field)
+    }
+}
+
+/// A `Weight` that times the creation of its scorers, which are wrapped by
+/// a `ProfileScorer`.
+pub struct ProfileWeight<C: Codec> {
+    weight: Box<dyn Weight<C>>,
+    breakdown: Arc<ProfileBreakdown>,
+}
+
+impl<C: Codec> ProfileWeight<C> {
+    fn profile_scorer(&self, scorer: Box<dyn Scorer>) -> Box<dyn Scorer> {
+        Box::new(ProfileScorer {
+            scorer,
+            breakdown: Arc::clone(&self.breakdown),
+        })
+    }
+}
+
+impl<C: Codec> Weight<C> for ProfileWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let start = Instant::now();
+        let scorer = self.weight.create_scorer(reader);
+        self.breakdown.record(ProfileTiming::BuildScorer, start);
+        Ok(scorer?.map(|scorer| self.profile_scorer(scorer)))
+    }
+
+    fn hash_code(&self) -> u32 {
+        self.weight.hash_code()
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.weight.query_type()
+    }
+
+    fn actual_query_type(&self) -> &'static str {
+        self.weight.actual_query_type()
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.weight.needs_scores()
+    }
+
+    fn count(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        self.weight.count(reader)
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        self.weight.estimate_cost(reader)
+    }
+
+    fn uses_lead_cost(&self) -> bool {
+        self.weight.uses_lead_cost()
+    }
+
+    fn create_scorer_with_lead_cost(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        lead_cost: usize,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let start = Instant::now();
+        let scorer = self.weight.create_scorer_with_lead_cost(reader, lead_cost);
+        self.breakdown.record(ProfileTiming::BuildScorer, start);
+        Ok(scorer?.map(|scorer| self.profile_scorer(scorer)))
+    }
+
+    fn create_top_scores_scorer(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let start = Instant::now();
+        let scorer = self.weight.create_top_scores_scorer(reader);
+        self.breakdown.record(ProfileTiming::BuildScorer, start);
+        Ok(scorer?.map(|scorer| self.profile_scorer(scorer)))
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for ProfileWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.weight.fmt(f)
+    }
+}
+
+/// A `Scorer` that times the iteration over the matches and the scoring.
+pub struct ProfileScorer {
+    scorer: Box<dyn Scorer>,
+    breakdown: Arc<ProfileBreakdown>,
+}
+
+impl Scorer for ProfileScorer {
+    fn score(&mut self) -> Result<f32> {
+        let start = Instant::now();
+        let score = self.scorer.score();
+        self.breakdown.record(ProfileTiming::Score, start);
+        score
+    }
+
+    fn max_score(&self) -> f32 {
+        self.scorer.max_score()
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) {
+        self.scorer.set_min_competitive_score(min_score)
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        self.scorer.score_context()
+    }
+
+    fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
+        self.scorer.score_feature()
+    }
+}
+
+impl DocIterator for ProfileScorer {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.next();
+        self.breakdown.record(ProfileTiming::NextDoc, start);
+        doc
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.advance(target);
+        self.breakdown.record(ProfileTiming::Advance, start);
+        doc
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let start = Instant::now();
+        let matches = self.scorer.matches();
+        self.breakdown.record(ProfileTiming::Match, start);
+        matches
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.approximate_next();
+        self.breakdown.record(ProfileTiming::NextDoc, start);
+        doc
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.approximate_advance(target);
+        self.breakdown.record(ProfileTiming::Advance, start);
+        doc
+    }
+}
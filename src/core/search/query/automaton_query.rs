@@ -0,0 +1,141 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AutomatonTermIterator, Codec, CodecTermIterator, CodecTerms, Terms};
+use core::doc::Term;
+use core::search::query::{MultiTermQuery, MultiTermQueryConstantScoreWeight};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{Automaton, CompiledAutomaton, DEFAULT_MAX_DETERMINIZED_STATES};
+
+use error::Result;
+
+use std::fmt;
+
+pub const AUTOMATON: &str = "automaton";
+
+/// A `Query` that matches the documents containing terms accepted by a
+/// finite-state automaton, e.g. a `LevenshteinAutomata` or a combination of
+/// automata built with `Operations`.
+///
+/// The automaton is compiled once, then intersected with the terms dictionary
+/// of each segment, see `AutomatonTermIterator`. Every matching document gets a
+/// constant score equal to the query boost.
+#[derive(Clone)]
+pub struct AutomatonQuery {
+    term: Term,
+    compiled: CompiledAutomaton,
+}
+
+impl AutomatonQuery {
+    /// Create a query for the terms accepted by a unicode `automaton`, the field
+    /// of `term` is searched and its text is only used to display the query.
+    pub fn new(term: Term, automaton: &Automaton) -> Result<AutomatonQuery> {
+        AutomatonQuery::with_params(term, automaton, DEFAULT_MAX_DETERMINIZED_STATES, false)
+    }
+
+    /// Create a query for the terms accepted by `automaton`, whose labels are
+    /// bytes rather than code points if `is_binary` is true.
+    /// `max_determinized_states` is the maximum number of states that compiling
+    /// the automaton can result in.
+    pub fn with_params(
+        term: Term,
+        automaton: &Automaton,
+        max_determinized_states: usize,
+        is_binary: bool,
+    ) -> Result<AutomatonQuery> {
+        let compiled = CompiledAutomaton::new(automaton, true, is_binary, max_determinized_states)?;
+        Ok(AutomatonQuery { term, compiled })
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn compiled(&self) -> &CompiledAutomaton {
+        &self.compiled
+    }
+}
+
+impl<C: Codec> MultiTermQuery<C> for AutomatonQuery {
+    type Iter = AutomatonTermIterator<CodecTermIterator<C>>;
+
+    fn field(&self) -> &str {
+        self.term.field()
+    }
+
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter> {
+        terms.intersect(&self.compiled)
+    }
+}
+
+impl<C: Codec> Query<C> for AutomatonQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            AUTOMATON,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for AutomatonQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AutomatonQuery(field: {}, term: {})",
+            self.term.field(),
+            String::from_utf8_lossy(&self.term.bytes)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::util::automaton::{AutomatonType, LevenshteinAutomata};
+
+    #[test]
+    fn test_automaton_query() {
+        let automaton = LevenshteinAutomata::new("rucene", false)
+            .to_automaton(1)
+            .unwrap();
+        let term = Term::new("name".into(), b"rucene~1".to_vec());
+        let query = AutomatonQuery::new(term, &automaton).unwrap();
+
+        let compiled = query.compiled();
+        assert_eq!(compiled.automaton_type, AutomatonType::Normal);
+        assert!(compiled.run(b"rucene"));
+        assert!(compiled.run(b"lucene"));
+        assert!(compiled.run(b"rucne"));
+        assert!(!compiled.run(b"lucne"));
+
+        assert_eq!(MultiTermQuery::<TestCodec>::field(&query), "name");
+        assert_eq!(
+            format!("{}", query),
+            "AutomatonQuery(field: name, term: rucene~1)"
+        );
+    }
+}
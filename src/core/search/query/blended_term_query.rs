@@ -0,0 +1,274 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{DisjunctionMaxWeight, Query, TermQuery, TermWeight, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// How the blended terms of a `BlendedTermQuery` are combined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendedRewrite {
+    /// The score is the sum of the scores of the matching terms, like a
+    /// boolean query of `SHOULD` clauses.
+    Boolean,
+    /// The score is the score of the best matching term plus
+    /// `tie_breaker_multiplier` times the scores of the other matching terms,
+    /// like a `DisjunctionMaxQuery`.
+    DisjunctionMax { tie_breaker_multiplier: f32 },
+}
+
+impl BlendedRewrite {
+    fn tie_breaker_multiplier(self) -> f32 {
+        match self {
+            // summing the scores is a tie breaker of 1
+            BlendedRewrite::Boolean => 1.0,
+            BlendedRewrite::DisjunctionMax {
+                tie_breaker_multiplier,
+            } => tie_breaker_multiplier,
+        }
+    }
+}
+
+/// A query that blends the statistics of several terms, usually the same
+/// term over several fields.
+///
+/// Every term is scored with the max of the doc freqs of all terms and the
+/// sum of their total term freqs, so that a term that is rare in one field
+/// but frequent in another one doesn't get a boost only because of the
+/// field it's in. This is the behavior of the `cross_fields` multi match of
+/// Elasticsearch, which treats several fields as one big field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlendedTermQuery {
+    terms: Vec<Term>,
+    boosts: Vec<f32>,
+    rewrite: BlendedRewrite,
+}
+
+impl BlendedTermQuery {
+    /// Create a query blending `terms`, each term comes with its boost.
+    pub fn new(terms: Vec<(Term, f32)>, rewrite: BlendedRewrite) -> Result<BlendedTermQuery> {
+        if terms.is_empty() {
+            bail!(IllegalArgument(
+                "BlendedTermQuery: terms should not be empty!".into()
+            ));
+        }
+        if let BlendedRewrite::DisjunctionMax {
+            tie_breaker_multiplier,
+        } = rewrite
+        {
+            if !(0.0..=1.0).contains(&tie_breaker_multiplier) {
+                bail!(IllegalArgument(format!(
+                    "BlendedTermQuery: tie_breaker_multiplier must be in [0, 1], got {}",
+                    tie_breaker_multiplier
+                )));
+            }
+        }
+        let (terms, boosts) = terms.into_iter().unzip();
+        Ok(BlendedTermQuery {
+            terms,
+            boosts,
+            rewrite,
+        })
+    }
+
+    /// Blend the same term text over `fields`, the boost of each field is
+    /// applied to its term.
+    pub fn cross_fields(
+        fields: &[(&str, f32)],
+        text: &[u8],
+        rewrite: BlendedRewrite,
+    ) -> Result<BlendedTermQuery> {
+        let terms = fields
+            .iter()
+            .map(|&(field, boost)| (Term::new(field.to_string(), text.to_vec()), boost))
+            .collect();
+        Self::new(terms, rewrite)
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    pub fn boosts(&self) -> &[f32] {
+        &self.boosts
+    }
+
+    pub fn rewrite(&self) -> BlendedRewrite {
+        self.rewrite
+    }
+}
+
+/// Blend the statistics of several terms into the doc freq and the total term
+/// freq they are all scored with: the max doc freq and the sum of the total
+/// term freqs, which is `-1` if any of them is unknown.
+fn blend_statistics(term_stats: &[TermStatistics]) -> (i64, i64) {
+    let mut doc_freq = 0i64;
+    let mut total_term_freq = 0i64;
+    for stats in term_stats {
+        doc_freq = doc_freq.max(stats.doc_freq);
+        if total_term_freq >= 0 && stats.total_term_freq >= 0 {
+            total_term_freq += stats.total_term_freq;
+        } else {
+            total_term_freq = -1;
+        }
+    }
+    (doc_freq, total_term_freq)
+}
+
+impl<C: Codec> Query<C> for BlendedTermQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let max_doc = i64::from(searcher.max_doc());
+        let mut term_contexts = Vec::with_capacity(self.terms.len());
+        let mut term_stats = Vec::with_capacity(self.terms.len());
+        for term in &self.terms {
+            let term_context = searcher.term_state(term)?;
+            term_stats.push(searcher.term_statistics(term, term_context.as_ref()));
+            term_contexts.push(term_context);
+        }
+        let (doc_freq, total_term_freq) = blend_statistics(&term_stats);
+
+        let mut weights: Vec<Box<dyn Weight<C>>> = Vec::with_capacity(self.terms.len());
+        for ((term, term_context), &boost) in self
+            .terms
+            .iter()
+            .zip(term_contexts.iter())
+            .zip(self.boosts.iter())
+        {
+            let (term_stats, collection_stats) = if needs_scores {
+                (
+                    TermStatistics::new(term.bytes.clone(), doc_freq, total_term_freq),
+                    searcher.collections_statistics(&term.field)?,
+                )
+            } else {
+                (
+                    TermStatistics::new(term.bytes.clone(), max_doc, -1),
+                    CollectionStatistics::new(term.field.clone(), max_doc, -1, -1, -1),
+                )
+            };
+            let similarity = searcher.similarity(&term.field, needs_scores);
+            let sim_weight =
+                similarity.compute_weight(&collection_stats, &[term_stats], None, boost);
+            weights.push(Box::new(TermWeight::new(
+                term.clone(),
+                term_context.term_states(),
+                boost,
+                similarity,
+                sim_weight,
+                needs_scores,
+            )));
+        }
+        Ok(Box::new(DisjunctionMaxWeight::new(
+            weights,
+            self.rewrite.tie_breaker_multiplier(),
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.terms
+            .iter()
+            .zip(self.boosts.iter())
+            .map(|(term, &boost)| TermQuery::new(term.clone(), boost, None))
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for BlendedTermQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .zip(self.boosts.iter())
+            .map(|(t, boost)| {
+                format!(
+                    "{}:{}^{}",
+                    t.field(),
+                    String::from_utf8_lossy(&t.bytes),
+                    boost
+                )
+            })
+            .collect();
+        write!(
+            f,
+            "BlendedTermQuery(terms: [{}], rewrite: {:?})",
+            terms.join(", "),
+            self.rewrite
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_statistics() {
+        let stats = vec![
+            TermStatistics::new(b"foo".to_vec(), 10, 30),
+            TermStatistics::new(b"foo".to_vec(), 2, 2),
+            TermStatistics::new(b"foo".to_vec(), 0, 0),
+        ];
+        assert_eq!(blend_statistics(&stats), (10, 32));
+
+        let stats = vec![
+            TermStatistics::new(b"foo".to_vec(), 10, 30),
+            TermStatistics::new(b"foo".to_vec(), 2, -1),
+        ];
+        assert_eq!(blend_statistics(&stats), (10, -1));
+    }
+
+    #[test]
+    fn test_blended_term_query() {
+        let query = BlendedTermQuery::cross_fields(
+            &[("title", 2.0), ("body", 1.0)],
+            b"rust",
+            BlendedRewrite::DisjunctionMax {
+                tie_breaker_multiplier: 0.1,
+            },
+        )
+        .unwrap();
+        assert_eq!(query.terms().len(), 2);
+        assert_eq!(query.terms()[0].field(), "title");
+        assert_eq!(query.boosts(), &[2.0, 1.0]);
+        assert_eq!(
+            format!("{}", query),
+            "BlendedTermQuery(terms: [title:rust^2, body:rust^1], rewrite: DisjunctionMax { \
+             tie_breaker_multiplier: 0.1 })"
+        );
+
+        assert!(BlendedTermQuery::new(vec![], BlendedRewrite::Boolean).is_err());
+        assert!(BlendedTermQuery::cross_fields(
+            &[("title", 1.0)],
+            b"rust",
+            BlendedRewrite::DisjunctionMax {
+                tie_breaker_multiplier: 1.5
+            }
+        )
+        .is_err());
+    }
+}
@@ -17,9 +17,13 @@ use std::fmt;
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
-use core::search::query::{ConstantScoreQuery, Query, TermQuery, Weight};
-use core::search::scorer::{ConjunctionScorer, DisjunctionSumScorer, ReqOptScorer, Scorer};
+use core::search::query::{ConstantScoreQuery, MatchAllDocsQuery, Query, TermQuery, Weight};
+use core::search::scorer::{
+    ConjunctionScorer, ConstantScoreScorer, DisjunctionSumScorer, ReqExclScorer, ReqOptScorer,
+    Scorer, WANDScorer,
+};
 use core::search::searcher::SearchPlanBuilder;
+use core::search::Matches;
 use core::util::DocId;
 use error::{ErrorKind::IllegalArgument, Result};
 
@@ -28,6 +32,7 @@ pub struct BooleanQuery<C: Codec> {
     must_queries: Vec<Box<dyn Query<C>>>,
     should_queries: Vec<Box<dyn Query<C>>>,
     filter_queries: Vec<Box<dyn Query<C>>>,
+    must_not_queries: Vec<Box<dyn Query<C>>>,
     minimum_should_match: i32,
 }
 
@@ -38,6 +43,19 @@ impl<C: Codec> BooleanQuery<C> {
         musts: Vec<Box<dyn Query<C>>>,
         shoulds: Vec<Box<dyn Query<C>>>,
         filters: Vec<Box<dyn Query<C>>>,
+    ) -> Result<Box<dyn Query<C>>> {
+        Self::build_with_must_not(musts, shoulds, filters, vec![])
+    }
+
+    /// Build a boolean query that also excludes the docs matching any of `must_nots`.
+    ///
+    /// The prohibited clauses do not participate in scoring, and at least one
+    /// positive (must, should or filter) clause is required.
+    pub fn build_with_must_not(
+        musts: Vec<Box<dyn Query<C>>>,
+        shoulds: Vec<Box<dyn Query<C>>>,
+        filters: Vec<Box<dyn Query<C>>>,
+        must_nots: Vec<Box<dyn Query<C>>>,
     ) -> Result<Box<dyn Query<C>>> {
         let minimum_should_match = if musts.is_empty() { 1 } else { 0 };
         let mut musts = musts;
@@ -48,7 +66,7 @@ impl<C: Codec> BooleanQuery<C> {
                 "boolean query should at least contain one inner query!".into()
             ));
         }
-        if musts.len() + shoulds.len() + filters.len() == 1 {
+        if musts.len() + shoulds.len() + filters.len() == 1 && must_nots.is_empty() {
             let query = if musts.len() == 1 {
                 musts.remove(0)
             } else if shoulds.len() == 1 {
@@ -62,10 +80,31 @@ impl<C: Codec> BooleanQuery<C> {
             must_queries: musts,
             should_queries: shoulds,
             filter_queries: filters,
+            must_not_queries: must_nots,
             minimum_should_match,
         }))
     }
 
+    pub fn must_queries(&self) -> &[Box<dyn Query<C>>] {
+        &self.must_queries
+    }
+
+    pub fn should_queries(&self) -> &[Box<dyn Query<C>>] {
+        &self.should_queries
+    }
+
+    pub fn filter_queries(&self) -> &[Box<dyn Query<C>>] {
+        &self.filter_queries
+    }
+
+    pub fn must_not_queries(&self) -> &[Box<dyn Query<C>>] {
+        &self.must_not_queries
+    }
+
+    fn is_match_all(query: &dyn Query<C>) -> bool {
+        query.as_any().is::<MatchAllDocsQuery>()
+    }
+
     fn queries_to_str(&self, queries: &[Box<dyn Query<C>>]) -> String {
         let query_strs: Vec<String> = queries.iter().map(|q| format!("{}", q)).collect();
         query_strs.join(", ")
@@ -78,24 +117,41 @@ impl<C: Codec> Query<C> for BooleanQuery<C> {
         searcher: &dyn SearchPlanBuilder<C>,
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>> {
-        let mut must_weights =
-            Vec::with_capacity(self.must_queries.len() + self.filter_queries.len());
+        // nothing is scored when there are only filter clauses
+        let filter_only = self.must_queries.is_empty() && self.should_queries.is_empty();
+        let needs_scores = needs_scores && !filter_only;
+
+        // a match all clause that isn't scored doesn't restrict the other
+        // required clauses, so it is dropped as long as one of them is left
+        let mut required = self.must_queries.len() + self.filter_queries.len();
+        let mut must_weights = Vec::with_capacity(required);
         for q in &self.must_queries {
+            if !needs_scores && required > 1 && Self::is_match_all(q.as_ref()) {
+                required -= 1;
+                continue;
+            }
             must_weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
         }
         for q in &self.filter_queries {
+            if required > 1 && Self::is_match_all(q.as_ref()) {
+                required -= 1;
+                continue;
+            }
             must_weights.push(searcher.create_weight(q.as_ref(), false)?);
         }
         let mut should_weights = Vec::with_capacity(self.should_queries.len());
         for q in &self.should_queries {
             should_weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
         }
+        let mut must_not_weights = Vec::with_capacity(self.must_not_queries.len());
+        for q in &self.must_not_queries {
+            must_not_weights.push(searcher.create_weight(q.as_ref(), false)?);
+        }
 
-        Ok(Box::new(BooleanWeight::new(
-            must_weights,
-            should_weights,
-            needs_scores,
-        )))
+        let mut weight = BooleanWeight::new(must_weights, should_weights, needs_scores);
+        weight.must_not_weights = must_not_weights;
+        weight.filter_only = filter_only;
+        Ok(Box::new(weight))
     }
 
     fn extract_terms(&self) -> Vec<TermQuery> {
@@ -128,18 +184,29 @@ impl<C: Codec> fmt::Display for BooleanQuery<C> {
         let filters_str = self.queries_to_str(&self.filter_queries);
         write!(
             f,
-            "BooleanQuery(must: [{}], should: [{}], filters: [{}], match: {})",
-            must_str, should_str, filters_str, self.minimum_should_match
-        )
+            "BooleanQuery(must: [{}], should: [{}], filters: [{}], ",
+            must_str, should_str, filters_str
+        )?;
+        if !self.must_not_queries.is_empty() {
+            write!(
+                f,
+                "must_not: [{}], ",
+                self.queries_to_str(&self.must_not_queries)
+            )?;
+        }
+        write!(f, "match: {})", self.minimum_should_match)
     }
 }
 
 struct BooleanWeight<C: Codec> {
     must_weights: Vec<Box<dyn Weight<C>>>,
     should_weights: Vec<Box<dyn Weight<C>>>,
+    must_not_weights: Vec<Box<dyn Weight<C>>>,
     #[allow(dead_code)]
     minimum_should_match: i32,
     needs_scores: bool,
+    // only filter clauses, the matching docs all score 0
+    filter_only: bool,
 }
 
 impl<C: Codec> BooleanWeight<C> {
@@ -152,8 +219,10 @@ impl<C: Codec> BooleanWeight<C> {
         BooleanWeight {
             must_weights: musts,
             should_weights: shoulds,
+            must_not_weights: vec![],
             minimum_should_match,
             needs_scores,
+            filter_only: false,
         }
     }
 
@@ -161,22 +230,47 @@ impl<C: Codec> BooleanWeight<C> {
         let weight_strs: Vec<String> = weights.iter().map(|q| format!("{}", q)).collect();
         weight_strs.join(", ")
     }
-}
 
-impl<C: Codec> Weight<C> for BooleanWeight<C> {
-    fn create_scorer(
+    // `top_scores` is set when the collector only needs the top scoring docs, the
+    // docs which can't be competitive may then be skipped
+    fn create_boolean_scorer(
         &self,
         leaf_reader: &LeafReaderContext<'_, C>,
+        top_scores: bool,
     ) -> Result<Option<Box<dyn Scorer>>> {
         let must_scorer: Option<Box<dyn Scorer>> = if !self.must_weights.is_empty() {
             let mut scorers = vec![];
             for weight in &self.must_weights {
+                if weight.uses_lead_cost() && self.must_weights.len() > 1 {
+                    continue;
+                }
                 if let Some(scorer) = weight.create_scorer(leaf_reader)? {
                     scorers.push(scorer);
                 } else {
                     return Ok(None);
                 }
             }
+            // the weights which may choose a cheaper scorer when intersected with
+            // selective scorers are created last, knowing the cost of the lead scorer
+            if scorers.len() < self.must_weights.len() {
+                let lead_cost = scorers
+                    .iter()
+                    .map(|s| s.cost())
+                    .min()
+                    .unwrap_or(usize::max_value());
+                for weight in &self.must_weights {
+                    if !weight.uses_lead_cost() {
+                        continue;
+                    }
+                    if let Some(scorer) =
+                        weight.create_scorer_with_lead_cost(leaf_reader, lead_cost)?
+                    {
+                        scorers.push(scorer);
+                    } else {
+                        return Ok(None);
+                    }
+                }
+            }
             if scorers.len() > 1 {
                 Some(Box::new(ConjunctionScorer::new(scorers)))
             } else {
@@ -195,6 +289,14 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
             match scorers.len() {
                 0 => None,
                 1 => Some(scorers.remove(0)),
+                _ if top_scores
+                    && self.needs_scores
+                    && self.must_weights.is_empty()
+                    && scorers.iter().all(|s| !s.support_two_phase()) =>
+                {
+                    // pure disjunction, non competitive docs may be skipped
+                    Some(Box::new(WANDScorer::new(scorers)))
+                }
                 _ => Some(Box::new(DisjunctionSumScorer::new(
                     scorers,
                     self.needs_scores,
@@ -202,18 +304,56 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
             }
         };
 
-        if let Some(must) = must_scorer {
+        let positive_scorer = if let Some(must) = must_scorer {
             if let Some(should) = should_scorer {
-                Ok(Some(Box::new(ReqOptScorer::new(must, should))))
+                Some(Box::new(ReqOptScorer::new(must, should)) as Box<dyn Scorer>)
             } else {
-                Ok(Some(must))
+                Some(must)
             }
         } else if let Some(should) = should_scorer {
-            Ok(Some(should))
+            Some(should)
+        } else {
+            None
+        };
+
+        if let Some(mut positive) = positive_scorer {
+            if self.filter_only {
+                // don't let the scores of the sub scorers be computed
+                let cost = positive.cost();
+                positive = Box::new(ConstantScoreScorer::new(0f32, positive, cost));
+            }
+            let mut excl_scorers = vec![];
+            for weight in &self.must_not_weights {
+                if let Some(scorer) = weight.create_scorer(leaf_reader)? {
+                    excl_scorers.push(scorer);
+                }
+            }
+            let excl_scorer: Box<dyn Scorer> = match excl_scorers.len() {
+                0 => return Ok(Some(positive)),
+                1 => excl_scorers.remove(0),
+                _ => Box::new(DisjunctionSumScorer::new(excl_scorers, false)),
+            };
+            Ok(Some(Box::new(ReqExclScorer::new(positive, excl_scorer))))
         } else {
             Ok(None)
         }
     }
+}
+
+impl<C: Codec> Weight<C> for BooleanWeight<C> {
+    fn create_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.create_boolean_scorer(leaf_reader, false)
+    }
+
+    fn create_top_scores_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.create_boolean_scorer(leaf_reader, true)
+    }
 
     fn query_type(&self) -> &'static str {
         BOOLEAN
@@ -243,8 +383,6 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
     }
 
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
-        let mut coord = 0;
-        let mut max_coord = 0;
         let mut sum = 0.0f32;
         let mut fail = false;
         let mut match_count = 0;
@@ -253,11 +391,8 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
         let mut subs: Vec<Explanation> = vec![];
         for w in &self.must_weights {
             let e = w.explain(reader, doc)?;
-            max_coord += 1;
-
             if e.is_match() {
                 sum += e.value();
-                coord += 1;
                 match_count += 1;
                 subs.push(e);
             } else {
@@ -271,13 +406,23 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
             }
         }
 
-        for w in &self.should_weights {
+        for w in &self.must_not_weights {
             let e = w.explain(reader, doc)?;
-            max_coord += 1;
+            if e.is_match() {
+                fail = true;
+                subs.push(Explanation::new(
+                    false,
+                    0.0f32,
+                    format!("match on prohibited clause ({})", w),
+                    vec![e],
+                ));
+            }
+        }
 
+        for w in &self.should_weights {
+            let e = w.explain(reader, doc)?;
             if e.is_match() {
                 sum += e.value();
-                coord += 1;
                 match_count += 1;
                 should_match_count += 1;
                 subs.push(e);
@@ -309,29 +454,38 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
                 subs,
             ))
         } else {
-            // we have a match
-            let result = Explanation::new(true, sum, "sum of:".to_string(), subs);
-
-            let coord_factor = 1.0f32;
-            if (coord_factor - 1.0).abs() < ::std::f32::EPSILON {
-                Ok(Explanation::new(
-                    true,
-                    sum * coord_factor,
-                    "product of:".to_string(),
-                    vec![
-                        result,
-                        Explanation::new(
-                            true,
-                            coord_factor,
-                            format!("coord({}/{})", coord, max_coord + 1),
-                            vec![],
-                        ),
-                    ],
-                ))
-            } else {
-                Ok(result)
+            Ok(Explanation::new(true, sum, "sum of:".to_string(), subs))
+        }
+    }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let mut subs = vec![];
+        for w in &self.must_weights {
+            match w.matches(reader, doc)? {
+                Some(m) => subs.push(m),
+                None => return Ok(None),
+            }
+        }
+
+        for w in &self.must_not_weights {
+            if w.matches(reader, doc)?.is_some() {
+                return Ok(None);
+            }
+        }
+
+        let mut should_match_count = 0;
+        for w in &self.should_weights {
+            if let Some(m) = w.matches(reader, doc)? {
+                should_match_count += 1;
+                subs.push(m);
             }
         }
+
+        if subs.is_empty() || should_match_count < self.minimum_should_match {
+            Ok(None)
+        } else {
+            Ok(Some(Matches::from_sub_matches(subs)))
+        }
     }
 }
 
@@ -341,8 +495,111 @@ impl<C: Codec> fmt::Display for BooleanWeight<C> {
         let should_str = self.weights_to_str(&self.should_weights);
         write!(
             f,
-            "BooleanWeight(must: [{}], should: [{}], min match: {}, needs score: {})",
-            must_str, should_str, self.minimum_should_match, self.needs_scores
+            "BooleanWeight(must: [{}], should: [{}], ",
+            must_str, should_str
+        )?;
+        if !self.must_not_weights.is_empty() {
+            write!(
+                f,
+                "must_not: [{}], ",
+                self.weights_to_str(&self.must_not_weights)
+            )?;
+        }
+        write!(
+            f,
+            "min match: {}, needs score: {})",
+            self.minimum_should_match, self.needs_scores
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::query::MatchNoDocsQuery;
+    use core::search::searcher::DefaultIndexSearcher;
+    use core::search::tests::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_boolean_weight_explain() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+
+        let musts: Vec<Box<dyn Weight<TestCodec>>> =
+            vec![Box::new(create_mock_weight(vec![1, 2, 3]))];
+        let shoulds: Vec<Box<dyn Weight<TestCodec>>> = vec![Box::new(create_mock_weight(vec![2]))];
+        let mut weight = BooleanWeight::new(musts, shoulds, true);
+        weight.must_not_weights = vec![Box::new(create_mock_weight(vec![3]))];
+
+        // mock weights explain a match by its doc id
+        let explanation = weight.explain(&leaves[0], 2).unwrap();
+        assert!(explanation.is_match());
+        assert!((explanation.value() - 4.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(explanation.details().len(), 2);
+
+        let explanation = weight.explain(&leaves[0], 1).unwrap();
+        assert!(explanation.is_match());
+        assert!((explanation.value() - 1.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(explanation.details().len(), 1);
+
+        let explanation = weight.explain(&leaves[0], 3).unwrap();
+        assert!(!explanation.is_match());
+        assert!(explanation.details()[1]
+            .description()
+            .starts_with("match on prohibited clause"));
+
+        let explanation = weight.explain(&leaves[0], 4).unwrap();
+        assert!(!explanation.is_match());
+        assert!(explanation.details()[0]
+            .description()
+            .starts_with("no match on required clause"));
+    }
+
+    #[test]
+    fn test_boolean_query_match_all_clauses() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![MockLeafReader::new(3)]));
+        let leaves = index_reader.leaves();
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&index_reader), None, None);
+
+        // filter only, one of the match all clauses is dropped and nothing is scored
+        let query = BooleanQuery::build(
+            vec![],
+            vec![],
+            vec![Box::new(MatchAllDocsQuery), Box::new(MatchAllDocsQuery)],
+        )
+        .unwrap();
+        let weight = query.create_weight(&searcher, true).unwrap();
+        assert!(!weight.needs_scores());
+        assert_eq!(
+            format!("{}", weight).matches("MatchAllDocsWeight").count(),
+            1
+        );
+        let mut scorer = weight.create_scorer(&leaves[0]).unwrap().unwrap();
+        assert_eq!(scorer.next().unwrap(), 0);
+        assert!(scorer.score().unwrap().abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(2).unwrap(), 2);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+
+        // a must match all clause is dropped when it isn't scored
+        let query = BooleanQuery::build(
+            vec![Box::new(MatchAllDocsQuery)],
+            vec![],
+            vec![Box::new(MatchNoDocsQuery::new("empty"))],
         )
+        .unwrap();
+        let weight = query.create_weight(&searcher, false).unwrap();
+        assert!(!format!("{}", weight).contains("MatchAllDocsWeight"));
+        assert!(weight.create_scorer(&leaves[0]).unwrap().is_none());
+        let weight = query.create_weight(&searcher, true).unwrap();
+        assert!(format!("{}", weight).contains("MatchAllDocsWeight"));
+        assert!(weight.create_scorer(&leaves[0]).unwrap().is_none());
+        let explanation = weight.explain(&leaves[0], 1).unwrap();
+        assert!(!explanation.is_match());
     }
 }
@@ -21,6 +21,7 @@ use core::search::explanation::Explanation;
 use core::search::query::{Query, TermQuery, Weight};
 use core::search::scorer::Scorer;
 use core::search::searcher::SearchPlanBuilder;
+use core::search::Matches;
 use core::util::DocId;
 
 use error::Result;
@@ -45,6 +46,16 @@ impl<C: Codec> BoostQuery<C> {
             Box::new(BoostQuery { query, boost })
         }
     }
+
+    /// Return the wrapped `Query`.
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    /// Return the applied boost.
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
 }
 
 impl<C: Codec> Query<C> for BoostQuery<C> {
@@ -53,10 +64,15 @@ impl<C: Codec> Query<C> for BoostQuery<C> {
         searcher: &dyn SearchPlanBuilder<C>,
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>> {
-        let mut weight = self.query.create_weight(searcher, needs_scores)?;
-        Weight::<C>::normalize(weight.as_mut(), 1.0f32, self.boost);
-        // weight.normalize(1.0f32, self.boost);
-        Ok(Box::new(BoostWeight::new(weight, self.boost)))
+        let weight = self.query.create_weight(searcher, needs_scores)?;
+        if !needs_scores {
+            return Ok(weight);
+        }
+        // the boost is pushed down to the leaf weights through `normalize`, an enclosing
+        // `BoostQuery` will normalize again with its own boost multiplied in.
+        let mut weight = BoostWeight::new(weight, self.boost);
+        Weight::<C>::normalize(&mut weight, 1.0f32, 1.0f32);
+        Ok(Box::new(weight))
     }
 
     fn extract_terms(&self) -> Vec<TermQuery> {
@@ -119,9 +135,41 @@ impl<C: Codec> Weight<C> for BoostWeight<C> {
         self.weight.needs_scores()
     }
 
+    fn count(&self, leaf_reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        self.weight.count(leaf_reader)
+    }
+
+    fn estimate_cost(&self, leaf_reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        self.weight.estimate_cost(leaf_reader)
+    }
+
+    fn uses_lead_cost(&self) -> bool {
+        self.weight.uses_lead_cost()
+    }
+
+    fn create_scorer_with_lead_cost(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        lead_cost: usize,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.weight
+            .create_scorer_with_lead_cost(leaf_reader, lead_cost)
+    }
+
+    fn create_top_scores_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.weight.create_top_scores_scorer(leaf_reader)
+    }
+
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
         self.weight.explain(reader, doc)
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
 }
 
 impl<C: Codec> fmt::Display for BoostWeight<C> {
@@ -133,3 +181,62 @@ impl<C: Codec> fmt::Display for BoostWeight<C> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    struct MockBoostedWeight {
+        boost: f32,
+    }
+
+    impl<C: Codec> Weight<C> for MockBoostedWeight {
+        fn create_scorer(
+            &self,
+            _reader: &LeafReaderContext<'_, C>,
+        ) -> Result<Option<Box<dyn Scorer>>> {
+            Ok(None)
+        }
+
+        fn query_type(&self) -> &'static str {
+            "mock"
+        }
+
+        fn normalize(&mut self, _norm: f32, boost: f32) {
+            self.boost = boost;
+        }
+
+        fn value_for_normalization(&self) -> f32 {
+            self.boost * self.boost
+        }
+
+        fn needs_scores(&self) -> bool {
+            true
+        }
+
+        fn explain(&self, _reader: &LeafReaderContext<'_, C>, _doc: DocId) -> Result<Explanation> {
+            unimplemented!()
+        }
+    }
+
+    impl fmt::Display for MockBoostedWeight {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "MockBoostedWeight(boost: {})", self.boost)
+        }
+    }
+
+    #[test]
+    fn test_nested_boost_propagation() {
+        let leaf: Box<dyn Weight<TestCodec>> = Box::new(MockBoostedWeight { boost: 1.0 });
+        let inner: Box<dyn Weight<TestCodec>> = Box::new(BoostWeight::new(leaf, 2.0));
+        let mut outer = BoostWeight::new(inner, 3.0);
+
+        Weight::<TestCodec>::normalize(&mut outer, 1.0, 1.0);
+        assert!((Weight::<TestCodec>::value_for_normalization(&outer) - 36.0).abs() < 1e-5);
+
+        // the boost of an enclosing clause is multiplied, not replaced
+        Weight::<TestCodec>::normalize(&mut outer, 1.0, 0.5);
+        assert!((Weight::<TestCodec>::value_for_normalization(&outer) - 9.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,475 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{two_phase_next, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIterator, DocLongValues, LongValuesSource, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const COVERING: &str = "covering";
+
+/// A query that matches the documents which match at least `N` of the
+/// wrapped queries, where `N` is read per document from a
+/// `LongValuesSource`.
+///
+/// This is typically used to index the minimum number of terms that a query
+/// must contain to match a document, e.g. for percolation. Documents without
+/// a value don't match, the score is the sum of the scores of the matching
+/// clauses.
+pub struct CoveringQuery<C: Codec> {
+    queries: Vec<Box<dyn Query<C>>>,
+    minimum_number_match: Arc<dyn LongValuesSource<C>>,
+}
+
+impl<C: Codec> CoveringQuery<C> {
+    pub fn new(
+        queries: Vec<Box<dyn Query<C>>>,
+        minimum_number_match: Arc<dyn LongValuesSource<C>>,
+    ) -> Result<CoveringQuery<C>> {
+        if queries.is_empty() {
+            bail!(IllegalArgument(
+                "CoveringQuery: queries should not be empty!".into()
+            ));
+        }
+        Ok(CoveringQuery {
+            queries,
+            minimum_number_match,
+        })
+    }
+
+    pub fn queries(&self) -> &[Box<dyn Query<C>>] {
+        &self.queries
+    }
+
+    pub fn minimum_number_match(&self) -> &dyn LongValuesSource<C> {
+        self.minimum_number_match.as_ref()
+    }
+}
+
+impl<C: Codec> Query<C> for CoveringQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let mut weights = Vec::with_capacity(self.queries.len());
+        for query in &self.queries {
+            weights.push(query.create_weight(searcher, needs_scores)?);
+        }
+        Ok(Box::new(CoveringWeight {
+            weights,
+            minimum_number_match: Arc::clone(&self.minimum_number_match),
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.queries
+            .iter()
+            .flat_map(|q| q.extract_terms())
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for CoveringQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let queries: Vec<String> = self.queries.iter().map(|q| format!("{}", q)).collect();
+        write!(
+            f,
+            "CoveringQuery(queries: [{}], minimum_number_match: {})",
+            queries.join(", "),
+            &self.minimum_number_match
+        )
+    }
+}
+
+struct CoveringWeight<C: Codec> {
+    weights: Vec<Box<dyn Weight<C>>>,
+    minimum_number_match: Arc<dyn LongValuesSource<C>>,
+    needs_scores: bool,
+}
+
+impl<C: Codec> Weight<C> for CoveringWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for weight in &self.weights {
+            if let Some(scorer) = weight.create_scorer(reader)? {
+                scorers.push(scorer);
+            }
+        }
+        if scorers.is_empty() {
+            return Ok(None);
+        }
+        let values = self.minimum_number_match.get_values(reader)?;
+        Ok(Some(Box::new(CoveringScorer::new(scorers, values))))
+    }
+
+    fn query_type(&self) -> &'static str {
+        COVERING
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        for weight in &mut self.weights {
+            weight.normalize(norm, boost);
+        }
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weights
+            .iter()
+            .filter(|w| w.needs_scores())
+            .map(|w| w.value_for_normalization())
+            .sum()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let min_match = self.minimum_number_match.get_values(reader)?.get(doc)?;
+        let mut sub_explanations = Vec::with_capacity(self.weights.len());
+        let mut freq = 0i64;
+        let mut score = 0f32;
+        for weight in &self.weights {
+            let expl = weight.explain(reader, doc)?;
+            if expl.is_match() {
+                freq += 1;
+                score += expl.value();
+            }
+            sub_explanations.push(expl);
+        }
+        match min_match {
+            Some(min_match) if freq >= min_match => Ok(Explanation::new(
+                true,
+                score,
+                format!(
+                    "{} matches for {} required matches, sum of:",
+                    freq, min_match
+                ),
+                sub_explanations,
+            )),
+            Some(min_match) => Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} matches for {} required matches", freq, min_match),
+                sub_explanations,
+            )),
+            None => Ok(Explanation::new(
+                false,
+                0f32,
+                format!(
+                    "{} doesn't have a value for doc {}",
+                    &self.minimum_number_match, doc
+                ),
+                sub_explanations,
+            )),
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for CoveringWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let weights: Vec<String> = self.weights.iter().map(|w| format!("{}", w)).collect();
+        write!(
+            f,
+            "CoveringWeight(weights: [{}], minimum_number_match: {}, needs_scores: {})",
+            weights.join(", "),
+            &self.minimum_number_match,
+            self.needs_scores
+        )
+    }
+}
+
+/// Iterates the union of the sub scorers and only confirms the docs that
+/// enough sub scorers match.
+struct CoveringScorer {
+    sub_scorers: Vec<Box<dyn Scorer>>,
+    // which sub scorers are verified to match the current doc
+    matched: Vec<bool>,
+    minimum_number_match: Box<dyn DocLongValues>,
+    curr_doc: DocId,
+    cost: usize,
+}
+
+impl CoveringScorer {
+    fn new(
+        sub_scorers: Vec<Box<dyn Scorer>>,
+        minimum_number_match: Box<dyn DocLongValues>,
+    ) -> CoveringScorer {
+        let cost = sub_scorers.iter().map(|s| s.cost()).sum();
+        let curr_doc = sub_scorers
+            .iter()
+            .map(|s| s.doc_id())
+            .min()
+            .unwrap_or(NO_MORE_DOCS);
+        let matched = vec![false; sub_scorers.len()];
+        CoveringScorer {
+            sub_scorers,
+            matched,
+            minimum_number_match,
+            curr_doc,
+            cost,
+        }
+    }
+}
+
+impl Scorer for CoveringScorer {
+    fn score(&mut self) -> Result<f32> {
+        let mut score = 0f32;
+        for (s, &m) in self.sub_scorers.iter_mut().zip(self.matched.iter()) {
+            if m {
+                score += s.score()?;
+            }
+        }
+        Ok(score)
+    }
+
+    fn max_score(&self) -> f32 {
+        self.sub_scorers.iter().map(|s| s.max_score()).sum()
+    }
+}
+
+impl DocIterator for CoveringScorer {
+    fn doc_id(&self) -> DocId {
+        self.curr_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.approximate_next()?;
+        two_phase_next(self)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximate_advance(target)?;
+        two_phase_next(self)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let doc = self.curr_doc;
+        let min_match = match self.minimum_number_match.get(doc)? {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+        // check the approximations first, the doc can't match if too few
+        // sub scorers are positioned on it
+        let positioned = self
+            .sub_scorers
+            .iter()
+            .filter(|s| s.doc_id() == doc)
+            .count() as i64;
+        if positioned < min_match {
+            return Ok(false);
+        }
+        let mut freq = 0i64;
+        for (s, m) in self.sub_scorers.iter_mut().zip(self.matched.iter_mut()) {
+            *m = s.doc_id() == doc && (!s.support_two_phase() || s.matches()?);
+            if *m {
+                freq += 1;
+            }
+        }
+        Ok(freq > 0 && freq >= min_match)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // reading the required number of matches, plus verifying the sub scorers
+        let sub_cost: f32 = self
+            .sub_scorers
+            .iter()
+            .filter(|s| s.support_two_phase())
+            .map(|s| s.match_cost())
+            .sum();
+        1.0 + sub_cost
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        let curr_doc = self.curr_doc;
+        let mut min_doc = NO_MORE_DOCS;
+        for s in self.sub_scorers.iter_mut() {
+            if s.doc_id() == curr_doc {
+                s.approximate_next()?;
+            }
+            min_doc = min_doc.min(s.doc_id());
+        }
+        self.curr_doc = min_doc;
+        Ok(min_doc)
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        let mut min_doc = NO_MORE_DOCS;
+        for s in self.sub_scorers.iter_mut() {
+            if s.doc_id() < target {
+                s.approximate_advance(target)?;
+            }
+            min_doc = min_doc.min(s.doc_id());
+        }
+        self.curr_doc = min_doc;
+        Ok(min_doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::collector::TotalHitCountCollector;
+    use core::search::searcher::{DefaultIndexSearcher, IndexSearcher};
+    use core::search::tests::*;
+
+    struct MockLongValues;
+
+    impl DocLongValues for MockLongValues {
+        fn get(&mut self, doc: DocId) -> Result<Option<i64>> {
+            // docs multiple of 5 have no value
+            if doc % 5 == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(i64::from(doc % 3) + 1))
+            }
+        }
+    }
+
+    struct MockLongValuesSource;
+
+    impl<C: Codec> LongValuesSource<C> for MockLongValuesSource {
+        fn get_values(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Box<dyn DocLongValues>> {
+            Ok(Box::new(MockLongValues))
+        }
+    }
+
+    impl fmt::Display for MockLongValuesSource {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "mock()")
+        }
+    }
+
+    struct MockQuery {
+        docs: Vec<DocId>,
+    }
+
+    impl<C: Codec> Query<C> for MockQuery {
+        fn create_weight(
+            &self,
+            _searcher: &dyn SearchPlanBuilder<C>,
+            _needs_scores: bool,
+        ) -> Result<Box<dyn Weight<C>>> {
+            Ok(Box::new(create_mock_weight(self.docs.clone())))
+        }
+
+        fn extract_terms(&self) -> Vec<TermQuery> {
+            vec![]
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl fmt::Display for MockQuery {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "MockQuery(docs: {:?})", self.docs)
+        }
+    }
+
+    #[test]
+    fn test_covering_scorer() {
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(create_mock_scorer(vec![1, 2, 3, 4, 6, 7, 8])),
+            Box::new(create_mock_scorer(vec![2, 3, 5, 7, 8])),
+            Box::new(create_mock_two_phase_scorer(
+                vec![1, 2, 3, 4, 5, 8],
+                vec![2, 5, 8],
+            )),
+        ];
+        let mut scorer = CoveringScorer::new(scorers, Box::new(MockLongValues));
+        assert!(scorer.support_two_phase());
+
+        // doc 1 needs 2 matches, doc 2 needs 3, doc 3 needs 1, doc 4 needs 2, doc 5
+        // has no value, doc 6 needs 1, doc 7 needs 2 and doc 8 needs 3
+        let mut docs = vec![];
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            docs.push((doc, scorer.score().unwrap()));
+        }
+        assert_eq!(
+            docs,
+            vec![(2, 6.0), (3, 6.0), (6, 6.0), (7, 14.0), (8, 24.0)]
+        );
+
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(create_mock_scorer(vec![1, 2, 3, 4, 6, 7, 8])),
+            Box::new(create_mock_scorer(vec![2, 3, 5, 7, 8])),
+        ];
+        let mut scorer = CoveringScorer::new(scorers, Box::new(MockLongValues));
+        assert_eq!(scorer.advance(4).unwrap(), 6);
+        assert_eq!(scorer.advance(8).unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_covering_query() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![MockLeafReader::new(20)]));
+        let leaves = index_reader.leaves();
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&index_reader), None, None);
+
+        let queries: Vec<Box<dyn Query<TestCodec>>> = vec![
+            Box::new(MockQuery {
+                docs: vec![1, 2, 3, 4, 6, 7, 8],
+            }),
+            Box::new(MockQuery {
+                docs: vec![2, 3, 5, 7, 8],
+            }),
+        ];
+        let query = CoveringQuery::new(queries, Arc::new(MockLongValuesSource)).unwrap();
+        let mut collector = TotalHitCountCollector::new();
+        searcher.search(&query, &mut collector).unwrap();
+        // docs 3, 6 and 7, doc 2 and 8 require 3 matches
+        assert_eq!(collector.total_hits(), 3);
+
+        let weight = query.create_weight(&searcher, true).unwrap();
+        let explanation = weight.explain(&leaves[0], 7).unwrap();
+        assert!(explanation.is_match());
+        assert!((explanation.value() - 14.0).abs() < ::std::f32::EPSILON);
+        assert!(!weight.explain(&leaves[0], 4).unwrap().is_match());
+        assert!(!weight.explain(&leaves[0], 5).unwrap().is_match());
+
+        assert!(CoveringQuery::<TestCodec>::new(vec![], Arc::new(MockLongValuesSource)).is_err());
+    }
+}
@@ -17,6 +17,7 @@ use core::search::explanation::Explanation;
 use core::search::query::{Query, TermQuery, Weight};
 use core::search::scorer::{DisjunctionMaxScorer, Scorer};
 use core::search::searcher::SearchPlanBuilder;
+use core::search::Matches;
 use core::util::DocId;
 
 use error::ErrorKind::IllegalArgument;
@@ -48,11 +49,23 @@ pub struct DisjunctionMaxQuery<C: Codec> {
 }
 
 impl<C: Codec> DisjunctionMaxQuery<C> {
+    /// Creates a new DisjunctionMaxQuery.
+    ///
+    /// The score of each non-maximum disjunct for a document is multiplied by
+    /// `tie_breaker_multiplier` and added into the final score, which must be
+    /// in `[0, 1]`. A small value such as 0.1 lets a term matching several
+    /// fields win over one that only matches the best field.
     pub fn build(
         disjuncts: Vec<Box<dyn Query<C>>>,
         tie_breaker_multiplier: f32,
     ) -> Result<Box<dyn Query<C>>> {
         let mut disjuncts = disjuncts;
+        if !(0.0..=1.0).contains(&tie_breaker_multiplier) {
+            bail!(IllegalArgument(format!(
+                "DisjunctionMaxQuery: tie_breaker_multiplier must be in [0, 1], got {}",
+                tie_breaker_multiplier
+            )))
+        }
         if disjuncts.is_empty() {
             bail!(IllegalArgument(
                 "DisjunctionMaxQuery: sub query should not be empty!".into()
@@ -78,7 +91,7 @@ impl<C: Codec> Query<C> for DisjunctionMaxQuery<C> {
     ) -> Result<Box<dyn Weight<C>>> {
         let mut weights = Vec::with_capacity(self.disjuncts.len());
         for q in &self.disjuncts {
-            weights.push(q.create_weight(searcher, needs_scores)?);
+            weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
         }
 
         Ok(Box::new(DisjunctionMaxWeight::new(
@@ -178,7 +191,8 @@ impl<C: Codec> Weight<C> for DisjunctionMaxWeight<C> {
             sum += sub;
             max_value = max_value.max(sub);
         }
-        max_value + (sum - max_value) * self.tie_breaker_multiplier
+        // the values are squared weights, so is the multiplier
+        max_value + (sum - max_value) * self.tie_breaker_multiplier * self.tie_breaker_multiplier
     }
 
     fn needs_scores(&self) -> bool {
@@ -204,17 +218,12 @@ impl<C: Codec> Weight<C> for DisjunctionMaxWeight<C> {
         if matched {
             let score = max + (sum - max) * self.tie_breaker_multiplier;
             let desc = if self.tie_breaker_multiplier == 0.0f32 {
-                "max of:"
+                "max of:".to_string()
             } else {
-                "max plus "
+                format!("max plus {} times others of:", self.tie_breaker_multiplier)
             };
 
-            Ok(Explanation::new(
-                true,
-                score,
-                format!("{} {} times others of:", desc, self.tie_breaker_multiplier),
-                subs,
-            ))
+            Ok(Explanation::new(true, score, desc, subs))
         } else {
             Ok(Explanation::new(
                 false,
@@ -224,6 +233,20 @@ impl<C: Codec> Weight<C> for DisjunctionMaxWeight<C> {
             ))
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let mut subs = vec![];
+        for w in &self.weights {
+            if let Some(m) = w.matches(reader, doc)? {
+                subs.push(m);
+            }
+        }
+        if subs.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(Matches::from_sub_matches(subs)))
+        }
+    }
 }
 
 impl<C: Codec> fmt::Display for DisjunctionMaxWeight<C> {
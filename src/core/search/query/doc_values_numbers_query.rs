@@ -0,0 +1,332 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{DocValuesMatcher, DocValuesScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::{BitsMut, DocId};
+
+use error::Result;
+
+pub const DOC_VALUES_NUMBERS: &str = "doc_values_numbers";
+
+/// A query that matches the docs having one of the given numbers in the numeric
+/// or sorted numeric doc values of a field.
+///
+/// Like `DocValuesTermsQuery` it only reads doc values, so it works for fields
+/// indexed without points, but all the docs of a segment are checked when it
+/// leads the iteration. It is best used as the `dv_query` of an
+/// `IndexOrDocValuesQuery`, next to a `PointInSetQuery`.
+pub struct DocValuesNumbersQuery {
+    field: String,
+    // sorted and deduplicated
+    numbers: Vec<i64>,
+}
+
+impl DocValuesNumbersQuery {
+    pub fn new(field: &str, mut numbers: Vec<i64>) -> DocValuesNumbersQuery {
+        numbers.sort();
+        numbers.dedup();
+        DocValuesNumbersQuery {
+            field: field.to_string(),
+            numbers,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn numbers(&self) -> &[i64] {
+        &self.numbers
+    }
+}
+
+impl<C: Codec> Query<C> for DocValuesNumbersQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(DocValuesNumbersWeight {
+            field: self.field.clone(),
+            numbers: self.numbers.clone(),
+            weight: 0f32,
+            norm: 1f32,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for DocValuesNumbersQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DocValuesNumbersQuery(field: {}, numbers: {:?})",
+            &self.field, &self.numbers
+        )
+    }
+}
+
+struct DocValuesNumbersWeight {
+    field: String,
+    numbers: Vec<i64>,
+    weight: f32,
+    norm: f32,
+}
+
+impl<C: Codec> Weight<C> for DocValuesNumbersWeight {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        if self.numbers.is_empty() {
+            return Ok(None);
+        }
+        let max_doc = reader.reader.max_doc();
+        let doc_values_type = match reader.reader.field_info(&self.field) {
+            Some(fi) => fi.doc_values_type,
+            None => return Ok(None),
+        };
+        match doc_values_type {
+            DocValuesType::Numeric => {
+                let matcher = NumericNumbersMatcher {
+                    doc_values: reader.reader.get_numeric_doc_values(&self.field)?,
+                    docs_with_field: reader.reader.get_docs_with_field(&self.field)?,
+                    numbers: self.numbers.clone(),
+                };
+                Ok(Some(Box::new(DocValuesScorer::new(
+                    max_doc,
+                    matcher,
+                    self.weight,
+                ))))
+            }
+            DocValuesType::SortedNumeric => {
+                let matcher = SortedNumericNumbersMatcher {
+                    doc_values: reader.reader.get_sorted_numeric_doc_values(&self.field)?,
+                    numbers: self.numbers.clone(),
+                };
+                Ok(Some(Box::new(DocValuesScorer::new(
+                    max_doc,
+                    matcher,
+                    self.weight,
+                ))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        DOC_VALUES_NUMBERS
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl fmt::Display for DocValuesNumbersWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DocValuesNumbersWeight(field: {}, numbers: {:?})",
+            &self.field, &self.numbers
+        )
+    }
+}
+
+/// Matches the docs whose numeric doc value is one of the numbers.
+struct NumericNumbersMatcher {
+    doc_values: Box<dyn NumericDocValues>,
+    // the docs without value read as 0
+    docs_with_field: Box<dyn BitsMut>,
+    numbers: Vec<i64>,
+}
+
+impl DocValuesMatcher for NumericNumbersMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        if !self.docs_with_field.get(doc as usize)? {
+            return Ok(false);
+        }
+        let value = self.doc_values.get_mut(doc)?;
+        Ok(self.numbers.binary_search(&value).is_ok())
+    }
+
+    fn match_cost(&self) -> f32 {
+        // reading the value and searching the numbers
+        2.0
+    }
+}
+
+/// Matches the docs having at least one sorted numeric doc value among the
+/// numbers.
+struct SortedNumericNumbersMatcher {
+    doc_values: Box<dyn SortedNumericDocValues>,
+    numbers: Vec<i64>,
+}
+
+impl DocValuesMatcher for SortedNumericNumbersMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        self.doc_values.set_document(doc)?;
+        for i in 0..self.doc_values.count() {
+            let value = self.doc_values.value_at(i)?;
+            if self.numbers.binary_search(&value).is_ok() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // a doc usually has a few values
+        3.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+    use core::util::{BitSet, Bits, FixedBitSet};
+
+    struct MockNumericDocValues;
+
+    impl NumericDocValues for MockNumericDocValues {
+        fn get(&self, doc_id: DocId) -> Result<i64> {
+            Ok(i64::from(doc_id % 3))
+        }
+    }
+
+    struct MockDocsWithField(FixedBitSet);
+
+    impl BitsMut for MockDocsWithField {
+        fn get(&mut self, index: usize) -> Result<bool> {
+            Bits::get(&self.0, index)
+        }
+
+        fn len(&self) -> usize {
+            Bits::len(&self.0)
+        }
+    }
+
+    struct MockSortedNumericDocValues {
+        doc: DocId,
+    }
+
+    impl SortedNumericDocValues for MockSortedNumericDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.doc = doc;
+            Ok(())
+        }
+
+        // the values of doc `doc` are `doc` and `doc * 10`
+        fn value_at(&mut self, index: usize) -> Result<i64> {
+            Ok(i64::from(self.doc) * if index == 0 { 1 } else { 10 })
+        }
+
+        fn count(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_doc_values_numbers_query_new() {
+        let query = DocValuesNumbersQuery::new("price", vec![30, 10, 30, -5]);
+        assert_eq!(query.field(), "price");
+        assert_eq!(query.numbers(), &[-5, 10, 30]);
+        assert_eq!(
+            format!("{}", query),
+            "DocValuesNumbersQuery(field: price, numbers: [-5, 10, 30])"
+        );
+    }
+
+    #[test]
+    fn test_numeric_numbers_matcher() {
+        // odd docs only have a value, the others read as 0
+        let mut docs_with_field = FixedBitSet::new(10);
+        for doc in (1..10).step_by(2) {
+            docs_with_field.set(doc);
+        }
+        let matcher = NumericNumbersMatcher {
+            doc_values: Box::new(MockNumericDocValues),
+            docs_with_field: Box::new(MockDocsWithField(docs_with_field)),
+            numbers: vec![0, 2],
+        };
+        let mut scorer = DocValuesScorer::new(10, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.next().unwrap(), 5);
+        assert_eq!(scorer.next().unwrap(), 9);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_sorted_numeric_numbers_matcher() {
+        let matcher = SortedNumericNumbersMatcher {
+            doc_values: Box::new(MockSortedNumericDocValues { doc: -1 }),
+            numbers: vec![4, 30, 70],
+        };
+        let mut scorer = DocValuesScorer::new(10, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert_eq!(scorer.next().unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,415 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::doc_values::{SortedDocValues, SortedSetDocValues, NO_MORE_ORDS};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{DocValuesMatcher, DocValuesScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::{BitSet, Bits, DocId, FixedBitSet};
+
+use error::Result;
+
+pub const DOC_VALUES_TERMS: &str = "doc_values_terms";
+
+/// A query that matches the docs having one of the given terms in the sorted or
+/// sorted set doc values of a field.
+///
+/// It doesn't use the terms index, so it works for fields indexed without
+/// postings, but all the docs of a segment are checked when it leads the
+/// iteration. It is best used as the `dv_query` of an `IndexOrDocValuesQuery`,
+/// next to a terms query, or intersected with more selective clauses.
+pub struct DocValuesTermsQuery {
+    field: String,
+    // sorted and deduplicated
+    terms: Vec<Vec<u8>>,
+}
+
+impl DocValuesTermsQuery {
+    pub fn new(field: &str, mut terms: Vec<Vec<u8>>) -> DocValuesTermsQuery {
+        terms.sort();
+        terms.dedup();
+        DocValuesTermsQuery {
+            field: field.to_string(),
+            terms,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn terms(&self) -> &[Vec<u8>] {
+        &self.terms
+    }
+}
+
+impl<C: Codec> Query<C> for DocValuesTermsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(DocValuesTermsWeight {
+            field: self.field.clone(),
+            terms: self.terms.clone(),
+            weight: 0f32,
+            norm: 1f32,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for DocValuesTermsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<_> = self
+            .terms
+            .iter()
+            .map(|t| String::from_utf8_lossy(t))
+            .collect();
+        write!(
+            f,
+            "DocValuesTermsQuery(field: {}, terms: [{}])",
+            &self.field,
+            terms.join(", ")
+        )
+    }
+}
+
+struct DocValuesTermsWeight {
+    field: String,
+    terms: Vec<Vec<u8>>,
+    weight: f32,
+    norm: f32,
+}
+
+impl<C: Codec> Weight<C> for DocValuesTermsWeight {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let max_doc = reader.reader.max_doc();
+        let doc_values_type = match reader.reader.field_info(&self.field) {
+            Some(fi) => fi.doc_values_type,
+            None => return Ok(None),
+        };
+        match doc_values_type {
+            DocValuesType::Sorted => {
+                let doc_values = reader.reader.get_sorted_doc_values(&self.field)?;
+                if let Some(matcher) = SortedTermsMatcher::new(doc_values, &self.terms)? {
+                    return Ok(Some(Box::new(DocValuesScorer::new(
+                        max_doc,
+                        matcher,
+                        self.weight,
+                    ))));
+                }
+            }
+            DocValuesType::SortedSet => {
+                let doc_values = reader.reader.get_sorted_set_doc_values(&self.field)?;
+                if let Some(matcher) = SortedSetTermsMatcher::new(doc_values, &self.terms)? {
+                    return Ok(Some(Box::new(DocValuesScorer::new(
+                        max_doc,
+                        matcher,
+                        self.weight,
+                    ))));
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn query_type(&self) -> &'static str {
+        DOC_VALUES_TERMS
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl fmt::Display for DocValuesTermsWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DocValuesTermsWeight(field: {}, terms: {})",
+            &self.field,
+            self.terms.len()
+        )
+    }
+}
+
+/// Matches the docs whose sorted doc values ord is one of the terms' ords.
+struct SortedTermsMatcher {
+    doc_values: Box<dyn SortedDocValues>,
+    ords: FixedBitSet,
+}
+
+impl SortedTermsMatcher {
+    // `None` if none of the terms is in the segment
+    fn new(
+        mut doc_values: Box<dyn SortedDocValues>,
+        terms: &[Vec<u8>],
+    ) -> Result<Option<SortedTermsMatcher>> {
+        let mut ords = FixedBitSet::new(doc_values.value_count());
+        let mut found = false;
+        for term in terms {
+            let ord = doc_values.lookup_term(term)?;
+            if ord >= 0 {
+                ords.set(ord as usize);
+                found = true;
+            }
+        }
+        if found {
+            Ok(Some(SortedTermsMatcher { doc_values, ords }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl DocValuesMatcher for SortedTermsMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        let ord = self.doc_values.get_ord(doc)?;
+        if ord < 0 {
+            Ok(false)
+        } else {
+            self.ords.get(ord as usize)
+        }
+    }
+
+    fn match_cost(&self) -> f32 {
+        // reading the ord and checking the bit set
+        2.0
+    }
+}
+
+/// Matches the docs having at least one sorted set doc values ord among the
+/// terms' ords.
+struct SortedSetTermsMatcher {
+    doc_values: Box<dyn SortedSetDocValues>,
+    ords: FixedBitSet,
+}
+
+impl SortedSetTermsMatcher {
+    // `None` if none of the terms is in the segment
+    fn new(
+        mut doc_values: Box<dyn SortedSetDocValues>,
+        terms: &[Vec<u8>],
+    ) -> Result<Option<SortedSetTermsMatcher>> {
+        let mut ords = FixedBitSet::new(doc_values.get_value_count());
+        let mut found = false;
+        for term in terms {
+            let ord = doc_values.lookup_term(term)?;
+            if ord >= 0 {
+                ords.set(ord as usize);
+                found = true;
+            }
+        }
+        if found {
+            Ok(Some(SortedSetTermsMatcher { doc_values, ords }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl DocValuesMatcher for SortedSetTermsMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        self.doc_values.set_document(doc)?;
+        loop {
+            let ord = self.doc_values.next_ord()?;
+            if ord == NO_MORE_ORDS {
+                return Ok(false);
+            }
+            if self.ords.get(ord as usize)? {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn match_cost(&self) -> f32 {
+        // a doc usually has a few ords
+        3.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::doc_values::{BinaryDocValues, DocValuesTermIterator};
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    const TERMS: [&[u8]; 4] = [b"apple", b"banana", b"cherry", b"grape"];
+
+    struct MockSortedDocValues;
+
+    impl SortedDocValues for MockSortedDocValues {
+        fn get_ord(&mut self, doc_id: DocId) -> Result<i32> {
+            // docs multiple of 5 have no value
+            if doc_id % 5 == 0 {
+                Ok(-1)
+            } else {
+                Ok(doc_id % 4)
+            }
+        }
+
+        fn lookup_ord(&mut self, ord: i32) -> Result<Vec<u8>> {
+            Ok(TERMS[ord as usize].to_vec())
+        }
+
+        fn value_count(&self) -> usize {
+            TERMS.len()
+        }
+
+        fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+            Ok(DocValuesTermIterator::empty())
+        }
+    }
+
+    impl BinaryDocValues for MockSortedDocValues {
+        fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+            let ord = self.get_ord(doc_id)?;
+            self.lookup_ord(ord)
+        }
+    }
+
+    struct MockSortedSetDocValues {
+        // the ords of doc `doc_id` are `doc_id % 4` and `(doc_id + 1) % 4`
+        ords: Vec<i64>,
+    }
+
+    impl SortedSetDocValues for MockSortedSetDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            let mut ords = vec![i64::from(doc % 4), i64::from((doc + 1) % 4)];
+            ords.sort();
+            // consumed from the end
+            self.ords = ords.into_iter().rev().collect();
+            Ok(())
+        }
+
+        fn next_ord(&mut self) -> Result<i64> {
+            Ok(self.ords.pop().unwrap_or(NO_MORE_ORDS))
+        }
+
+        fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+            Ok(TERMS[ord as usize].to_vec())
+        }
+
+        fn get_value_count(&self) -> usize {
+            TERMS.len()
+        }
+
+        fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+            Ok(DocValuesTermIterator::empty())
+        }
+    }
+
+    #[test]
+    fn test_doc_values_terms_query_new() {
+        let query = DocValuesTermsQuery::new(
+            "fruit",
+            vec![b"grape".to_vec(), b"apple".to_vec(), b"grape".to_vec()],
+        );
+        assert_eq!(query.field(), "fruit");
+        assert_eq!(query.terms(), &[b"apple".to_vec(), b"grape".to_vec()]);
+        assert_eq!(
+            format!("{}", query),
+            "DocValuesTermsQuery(field: fruit, terms: [apple, grape])"
+        );
+    }
+
+    #[test]
+    fn test_sorted_terms_matcher() {
+        let terms = vec![b"banana".to_vec(), b"grape".to_vec(), b"kiwi".to_vec()];
+        let matcher = SortedTermsMatcher::new(Box::new(MockSortedDocValues), &terms)
+            .unwrap()
+            .unwrap();
+        let mut scorer = DocValuesScorer::new(12, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.next().unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), 9);
+        assert_eq!(scorer.next().unwrap(), 11);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+
+        let terms = vec![b"kiwi".to_vec()];
+        assert!(
+            SortedTermsMatcher::new(Box::new(MockSortedDocValues), &terms)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_sorted_set_terms_matcher() {
+        let terms = vec![b"apple".to_vec()];
+        let doc_values = MockSortedSetDocValues { ords: vec![] };
+        let matcher = SortedSetTermsMatcher::new(Box::new(doc_values), &terms)
+            .unwrap()
+            .unwrap();
+        let mut scorer = DocValuesScorer::new(10, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 0);
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert_eq!(scorer.advance(5).unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), 8);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
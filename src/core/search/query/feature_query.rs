@@ -0,0 +1,542 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+use core::codec::{Codec, CodecPostingIterator, CodecTermState};
+use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::doc::{decode_feature_value, Term};
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const FEATURE: &str = "feature";
+
+/// The greatest weight a feature query accepts, which keeps the static scores
+/// within the same order of magnitude as BM25 scores.
+pub const FEATURE_MAX_WEIGHT: f32 = 64.0;
+
+/// The function that turns the value `S` of a feature into a score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatureFunction {
+    /// `weight * ln(scaling_factor + S)`
+    Log { scaling_factor: f32 },
+    /// `weight * S / (S + pivot)`, the pivot is computed from the index
+    /// statistics if absent.
+    Saturation { pivot: Option<f32> },
+    /// `weight * S^exp / (S^exp + pivot^exp)`
+    Sigmoid { pivot: f32, exp: f32 },
+}
+
+impl FeatureFunction {
+    fn validate(&self) -> Result<()> {
+        match *self {
+            FeatureFunction::Log { scaling_factor } => {
+                if !(scaling_factor >= 1.0 && scaling_factor.is_finite()) {
+                    bail!(IllegalArgument(format!(
+                        "scaling_factor must be >= 1 and finite, got: {}",
+                        scaling_factor
+                    )));
+                }
+            }
+            FeatureFunction::Saturation { pivot } => {
+                if let Some(pivot) = pivot {
+                    Self::check_positive("pivot", pivot)?;
+                }
+            }
+            FeatureFunction::Sigmoid { pivot, exp } => {
+                Self::check_positive("pivot", pivot)?;
+                Self::check_positive("exp", exp)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_positive(name: &str, value: f32) -> Result<()> {
+        if !(value > 0.0 && value.is_finite()) {
+            bail!(IllegalArgument(format!(
+                "{} must be > 0 and finite, got: {}",
+                name, value
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compute the score of a feature value.
+    pub fn score(&self, weight: f32, value: f32) -> f32 {
+        match *self {
+            FeatureFunction::Log { scaling_factor } => weight * (scaling_factor + value).ln(),
+            FeatureFunction::Saturation { pivot } => {
+                let pivot = pivot.unwrap_or(1.0);
+                // the value may be MAX for aggregated features, avoid `INF / INF`
+                weight * (1.0 - pivot / (value + pivot))
+            }
+            FeatureFunction::Sigmoid { pivot, exp } => {
+                let value = value.powf(exp);
+                weight * (1.0 - pivot.powf(exp) / (value + pivot.powf(exp)))
+            }
+        }
+    }
+
+    /// An upper bound of the scores of this function.
+    pub fn max_score(&self, weight: f32) -> f32 {
+        match *self {
+            FeatureFunction::Log { .. } => self.score(weight, ::std::f32::MAX),
+            FeatureFunction::Saturation { .. } | FeatureFunction::Sigmoid { .. } => weight,
+        }
+    }
+
+    fn explain(&self, weight: f32, field: &str, feature: &str, freq: f32) -> Explanation {
+        let value = decode_feature_value(freq);
+        let description = match *self {
+            FeatureFunction::Log { scaling_factor } => format!(
+                "Log function on the {} field for the {} feature, computed as w * ln(a + S) from:",
+                field, feature
+            ),
+            FeatureFunction::Saturation { .. } => format!(
+                "Saturation function on the {} field for the {} feature, computed as w * S / (S + \
+                 k) from:",
+                field, feature
+            ),
+            FeatureFunction::Sigmoid { .. } => format!(
+                "Sigmoid function on the {} field for the {} feature, computed as w * S^a / (S^a \
+                 + k^a) from:",
+                field, feature
+            ),
+        };
+        let mut details = vec![Explanation::new(true, weight, "w, weight".into(), vec![])];
+        match *self {
+            FeatureFunction::Log { scaling_factor } => details.push(Explanation::new(
+                true,
+                scaling_factor,
+                "a, scaling factor".into(),
+                vec![],
+            )),
+            FeatureFunction::Saturation { pivot } => details.push(Explanation::new(
+                true,
+                pivot.unwrap_or(1.0),
+                "k, pivot feature value that would give a score contribution equal to w/2".into(),
+                vec![],
+            )),
+            FeatureFunction::Sigmoid { pivot, exp } => {
+                details.push(Explanation::new(
+                    true,
+                    pivot,
+                    "k, pivot feature value that would give a score contribution equal to w/2"
+                        .into(),
+                    vec![],
+                ));
+                details.push(Explanation::new(
+                    true,
+                    exp,
+                    "a, exponent, higher values make the function grow slower before k and faster \
+                     after k"
+                        .into(),
+                    vec![],
+                ));
+            }
+        }
+        details.push(Explanation::new(
+            true,
+            value,
+            "S, feature value".into(),
+            vec![],
+        ));
+        Explanation::new(true, self.score(weight, value), description, details)
+    }
+}
+
+impl fmt::Display for FeatureFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FeatureFunction::Log { scaling_factor } => {
+                write!(f, "LogFunction(scaling_factor: {})", scaling_factor)
+            }
+            FeatureFunction::Saturation { pivot } => {
+                write!(f, "SaturationFunction(pivot: {:?})", pivot)
+            }
+            FeatureFunction::Sigmoid { pivot, exp } => {
+                write!(f, "SigmoidFunction(pivot: {}, exp: {})", pivot, exp)
+            }
+        }
+    }
+}
+
+/// A query that scores the documents by the value of a feature indexed with
+/// `FeatureField`.
+///
+/// The scores only depend on the feature value, so that this query is usually
+/// added as a `SHOULD` clause next to the text query to boost its hits with a
+/// static ranking signal. It exposes the upper bound of its scores so that
+/// `WANDScorer` can skip the documents that can't be competitive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureQuery {
+    field: String,
+    feature_name: String,
+    function: FeatureFunction,
+    weight: f32,
+}
+
+impl FeatureQuery {
+    pub fn new(
+        field: &str,
+        feature_name: &str,
+        function: FeatureFunction,
+        weight: f32,
+    ) -> Result<FeatureQuery> {
+        if !(weight > 0.0 && weight <= FEATURE_MAX_WEIGHT) {
+            bail!(IllegalArgument(format!(
+                "weight must be in (0, {}], got: {}",
+                FEATURE_MAX_WEIGHT, weight
+            )));
+        }
+        function.validate()?;
+        Ok(FeatureQuery {
+            field: field.to_string(),
+            feature_name: feature_name.to_string(),
+            function,
+            weight,
+        })
+    }
+
+    /// Score by `weight * ln(scaling_factor + S)`, this is usually the best
+    /// function when the feature value follows a power law.
+    pub fn log(
+        field: &str,
+        feature_name: &str,
+        weight: f32,
+        scaling_factor: f32,
+    ) -> Result<FeatureQuery> {
+        Self::new(
+            field,
+            feature_name,
+            FeatureFunction::Log { scaling_factor },
+            weight,
+        )
+    }
+
+    /// Score by `weight * S / (S + pivot)`, scores are in `(0, weight)` and
+    /// a value equal to the pivot scores `weight / 2`. The pivot is
+    /// approximated with the geometric mean of the indexed values if `None`.
+    pub fn saturation(
+        field: &str,
+        feature_name: &str,
+        weight: f32,
+        pivot: Option<f32>,
+    ) -> Result<FeatureQuery> {
+        Self::new(
+            field,
+            feature_name,
+            FeatureFunction::Saturation { pivot },
+            weight,
+        )
+    }
+
+    /// Score by `weight * S^exp / (S^exp + pivot^exp)`, which grows slower
+    /// than the saturation before the pivot and faster after.
+    pub fn sigmoid(
+        field: &str,
+        feature_name: &str,
+        weight: f32,
+        pivot: f32,
+        exp: f32,
+    ) -> Result<FeatureQuery> {
+        Self::new(
+            field,
+            feature_name,
+            FeatureFunction::Sigmoid { pivot, exp },
+            weight,
+        )
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn feature_name(&self) -> &str {
+        &self.feature_name
+    }
+
+    pub fn function(&self) -> FeatureFunction {
+        self.function
+    }
+
+    fn term(&self) -> Term {
+        Term::new(self.field.clone(), self.feature_name.as_bytes().to_vec())
+    }
+}
+
+/// Approximate the geometric mean of the values of a feature, which is a good
+/// pivot for the saturation function.
+///
+/// The feature values are encoded with their exponent in the high bits, so
+/// the average of the encoded frequencies decodes to the geometric mean.
+pub fn compute_pivot_feature_value<C: Codec>(
+    searcher: &dyn SearchPlanBuilder<C>,
+    field: &str,
+    feature_name: &str,
+) -> Result<f32> {
+    let term = Term::new(field.to_string(), feature_name.as_bytes().to_vec());
+    let term_context = searcher.term_state(&term)?;
+    let stats = searcher.term_statistics(&term, term_context.as_ref());
+    if stats.doc_freq <= 0 || stats.total_term_freq <= 0 {
+        // the feature is absent, any pivot works
+        return Ok(1.0);
+    }
+    let avg_freq = (stats.total_term_freq as f64 / stats.doc_freq as f64) as f32;
+    Ok(decode_feature_value(avg_freq))
+}
+
+impl<C: Codec> Query<C> for FeatureQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let function = match self.function {
+            FeatureFunction::Saturation { pivot: None } if needs_scores => {
+                FeatureFunction::Saturation {
+                    pivot: Some(compute_pivot_feature_value(
+                        searcher,
+                        &self.field,
+                        &self.feature_name,
+                    )?),
+                }
+            }
+            function => function,
+        };
+        let term = self.term();
+        let term_context = searcher.term_state(&term)?;
+        Ok(Box::new(FeatureWeight {
+            term,
+            function,
+            weight: self.weight,
+            needs_scores,
+            term_states: term_context.term_states(),
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![TermQuery::new(self.term(), self.weight, None)]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for FeatureQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FeatureQuery(field: {}, feature: {}, function: {}, weight: {})",
+            &self.field, &self.feature_name, &self.function, self.weight
+        )
+    }
+}
+
+struct FeatureWeight<C: Codec> {
+    term: Term,
+    function: FeatureFunction,
+    weight: f32,
+    needs_scores: bool,
+    term_states: HashMap<DocId, CodecTermState<C>>,
+}
+
+impl<C: Codec> FeatureWeight<C> {
+    fn create_postings_iterator(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<CodecPostingIterator<C>>> {
+        let flags = if self.needs_scores {
+            PostingIteratorFlags::FREQS
+        } else {
+            PostingIteratorFlags::NONE
+        };
+        if let Some(state) = self.term_states.get(&reader.doc_base) {
+            reader
+                .reader
+                .postings_from_state(&self.term, &state, i32::from(flags))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: Codec> Weight<C> for FeatureWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        if let Some(postings) = self.create_postings_iterator(reader)? {
+            Ok(Some(Box::new(FeatureScorer {
+                postings,
+                function: self.function,
+                weight: self.weight,
+            })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        FEATURE
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        if !self.term_states.contains_key(&reader.doc_base) {
+            Ok(Some(0))
+        } else {
+            Ok(Some(reader.reader.doc_freq(&self.term)? as usize))
+        }
+    }
+
+    fn normalize(&mut self, _norm: f32, boost: f32) {
+        self.weight *= boost;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        if let Some(mut postings) = self.create_postings_iterator(reader)? {
+            if postings.advance(doc)? == doc {
+                let freq = postings.freq()? as f32;
+                return Ok(self.function.explain(
+                    self.weight,
+                    self.term.field(),
+                    &String::from_utf8_lossy(&self.term.bytes),
+                    freq,
+                ));
+            }
+        }
+        Ok(Explanation::new(
+            false,
+            0.0,
+            "feature not present in the document".into(),
+            vec![],
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for FeatureWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FeatureWeight(field: {}, feature: {}, function: {}, weight: {})",
+            self.term.field(),
+            String::from_utf8_lossy(&self.term.bytes),
+            &self.function,
+            self.weight
+        )
+    }
+}
+
+struct FeatureScorer<T: PostingIterator> {
+    postings: T,
+    function: FeatureFunction,
+    weight: f32,
+}
+
+impl<T: PostingIterator> Scorer for FeatureScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        let freq = self.postings.freq()? as f32;
+        Ok(self.function.score(self.weight, decode_feature_value(freq)))
+    }
+
+    fn max_score(&self) -> f32 {
+        self.function.max_score(self.weight)
+    }
+}
+
+impl<T: PostingIterator> DocIterator for FeatureScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.postings.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.postings.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.postings.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.postings.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_query_validation() {
+        assert!(FeatureQuery::log("features", "pagerank", 1.0, 0.5).is_err());
+        assert!(FeatureQuery::log("features", "pagerank", 0.0, 4.0).is_err());
+        assert!(FeatureQuery::log("features", "pagerank", 65.0, 4.0).is_err());
+        assert!(FeatureQuery::saturation("features", "pagerank", 1.0, Some(-1.0)).is_err());
+        assert!(FeatureQuery::sigmoid("features", "pagerank", 1.0, 2.0, 0.0).is_err());
+        assert!(FeatureQuery::sigmoid("features", "pagerank", 1.0, 2.0, 0.6).is_ok());
+        assert!(FeatureQuery::saturation("features", "pagerank", 2.0, None).is_ok());
+    }
+
+    #[test]
+    fn test_feature_functions() {
+        let log = FeatureFunction::Log {
+            scaling_factor: 1.0,
+        };
+        assert!((log.score(2.0, ::std::f32::consts::E - 1.0) - 2.0).abs() < 1e-5);
+        assert!(log.max_score(2.0) >= log.score(2.0, ::std::f32::MAX));
+
+        let saturation = FeatureFunction::Saturation { pivot: Some(10.0) };
+        assert!((saturation.score(2.0, 10.0) - 1.0).abs() < 1e-5);
+        assert!(saturation.score(2.0, 1.0) < saturation.score(2.0, 100.0));
+        assert!(saturation.score(2.0, ::std::f32::MAX) <= saturation.max_score(2.0));
+
+        let sigmoid = FeatureFunction::Sigmoid {
+            pivot: 10.0,
+            exp: 0.6,
+        };
+        assert!((sigmoid.score(2.0, 10.0) - 1.0).abs() < 1e-5);
+        assert!(sigmoid.score(2.0, 1.0) < sigmoid.score(2.0, 100.0));
+        assert!(sigmoid.score(2.0, ::std::f32::MAX) <= sigmoid.max_score(2.0));
+    }
+
+    #[test]
+    fn test_feature_scorer() {
+        use core::doc::encode_feature_value;
+        use core::search::tests::MockPostingIterator;
+
+        let freq = encode_feature_value(10.0) as i32;
+        let mut scorer = FeatureScorer {
+            postings: MockPostingIterator::new(vec![1, 3], vec![freq, freq]),
+            function: FeatureFunction::Saturation { pivot: Some(10.0) },
+            weight: 4.0,
+        };
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 2.0).abs() < 1e-5);
+        assert!((scorer.max_score() - 4.0).abs() < ::std::f32::EPSILON);
+    }
+}
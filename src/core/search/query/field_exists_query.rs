@@ -0,0 +1,329 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{DocValuesMatcher, DocValuesScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::{BitsMut, DocId};
+
+use error::Result;
+
+pub const DOC_VALUES_FIELD_EXISTS: &str = "doc_values_field_exists";
+pub const NORMS_FIELD_EXISTS: &str = "norms_field_exists";
+
+/// A query that matches the docs having a doc value of any type for a field.
+///
+/// This is useful for "field is present" filters, or wrapped in a `MUST_NOT`
+/// clause for "field is missing" ones, without indexing a sentinel term.
+pub struct DocValuesFieldExistsQuery {
+    field: String,
+}
+
+impl DocValuesFieldExistsQuery {
+    pub fn new(field: &str) -> DocValuesFieldExistsQuery {
+        DocValuesFieldExistsQuery {
+            field: field.to_string(),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+}
+
+impl<C: Codec> Query<C> for DocValuesFieldExistsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(FieldExistsWeight::new(&self.field, false)))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for DocValuesFieldExistsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DocValuesFieldExistsQuery(field: {})", &self.field)
+    }
+}
+
+/// A query that matches the docs having norms for a field, that is the docs
+/// where an indexed field that doesn't omit norms has a value.
+///
+/// Norms of the docs without the field are stored as 0, so a doc whose encoded
+/// norm is 0, e.g. because of a 0 index time boost, is not matched either.
+pub struct NormsFieldExistsQuery {
+    field: String,
+}
+
+impl NormsFieldExistsQuery {
+    pub fn new(field: &str) -> NormsFieldExistsQuery {
+        NormsFieldExistsQuery {
+            field: field.to_string(),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+}
+
+impl<C: Codec> Query<C> for NormsFieldExistsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(FieldExistsWeight::new(&self.field, true)))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for NormsFieldExistsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NormsFieldExistsQuery(field: {})", &self.field)
+    }
+}
+
+struct FieldExistsWeight {
+    field: String,
+    // check the norms rather than the doc values
+    norms: bool,
+    weight: f32,
+    norm: f32,
+}
+
+impl FieldExistsWeight {
+    fn new(field: &str, norms: bool) -> FieldExistsWeight {
+        FieldExistsWeight {
+            field: field.to_string(),
+            norms,
+            weight: 0f32,
+            norm: 1f32,
+        }
+    }
+}
+
+impl<C: Codec> Weight<C> for FieldExistsWeight {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let field_info = match reader.reader.field_info(&self.field) {
+            Some(fi) => fi,
+            None => return Ok(None),
+        };
+        let max_doc = reader.reader.max_doc();
+        if self.norms {
+            if !field_info.has_norms() {
+                return Ok(None);
+            }
+            match reader.reader.norm_values(&self.field)? {
+                Some(norms) => Ok(Some(Box::new(DocValuesScorer::new(
+                    max_doc,
+                    NormsExistsMatcher { norms },
+                    self.weight,
+                )))),
+                None => Ok(None),
+            }
+        } else {
+            if field_info.doc_values_type == DocValuesType::Null {
+                return Ok(None);
+            }
+            let matcher = DocsWithFieldMatcher {
+                docs_with_field: reader.reader.get_docs_with_field(&self.field)?,
+            };
+            Ok(Some(Box::new(DocValuesScorer::new(
+                max_doc,
+                matcher,
+                self.weight,
+            ))))
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        if self.norms {
+            NORMS_FIELD_EXISTS
+        } else {
+            DOC_VALUES_FIELD_EXISTS
+        }
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl fmt::Display for FieldExistsWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.norms {
+            write!(f, "NormsFieldExistsWeight(field: {})", &self.field)
+        } else {
+            write!(f, "DocValuesFieldExistsWeight(field: {})", &self.field)
+        }
+    }
+}
+
+/// Matches the docs having a doc value.
+struct DocsWithFieldMatcher {
+    docs_with_field: Box<dyn BitsMut>,
+}
+
+impl DocValuesMatcher for DocsWithFieldMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        self.docs_with_field.get(doc as usize)
+    }
+
+    fn match_cost(&self) -> f32 {
+        1.0
+    }
+}
+
+/// Matches the docs having a non zero norm.
+struct NormsExistsMatcher {
+    norms: Box<dyn NumericDocValues>,
+}
+
+impl DocValuesMatcher for NormsExistsMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        Ok(self.norms.get_mut(doc)? != 0)
+    }
+
+    fn match_cost(&self) -> f32 {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+    use core::util::{BitSet, Bits, FixedBitSet};
+
+    struct MockDocsWithField(FixedBitSet);
+
+    impl BitsMut for MockDocsWithField {
+        fn get(&mut self, index: usize) -> Result<bool> {
+            Bits::get(&self.0, index)
+        }
+
+        fn len(&self) -> usize {
+            Bits::len(&self.0)
+        }
+    }
+
+    // the docs that are multiple of 4 have no norm
+    struct MockNorms;
+
+    impl NumericDocValues for MockNorms {
+        fn get(&self, doc_id: DocId) -> Result<i64> {
+            Ok(i64::from(doc_id % 4))
+        }
+    }
+
+    #[test]
+    fn test_field_exists_query_display() {
+        let query = DocValuesFieldExistsQuery::new("price");
+        assert_eq!(query.field(), "price");
+        assert_eq!(
+            format!("{}", query),
+            "DocValuesFieldExistsQuery(field: price)"
+        );
+        let query = NormsFieldExistsQuery::new("title");
+        assert_eq!(query.field(), "title");
+        assert_eq!(format!("{}", query), "NormsFieldExistsQuery(field: title)");
+    }
+
+    #[test]
+    fn test_docs_with_field_matcher() {
+        let mut docs_with_field = FixedBitSet::new(10);
+        docs_with_field.set(2);
+        docs_with_field.set(7);
+        let matcher = DocsWithFieldMatcher {
+            docs_with_field: Box::new(MockDocsWithField(docs_with_field)),
+        };
+        let mut scorer = DocValuesScorer::new(10, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert_eq!(scorer.next().unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_norms_exists_matcher() {
+        let matcher = NormsExistsMatcher {
+            norms: Box::new(MockNorms),
+        };
+        let mut scorer = DocValuesScorer::new(10, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert_eq!(scorer.advance(4).unwrap(), 5);
+        assert_eq!(scorer.advance(7).unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), 9);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIterator, DoubleValues, DoubleValuesSource};
+use core::util::DocId;
+
+use error::Result;
+
+pub const FUNCTION_SCORE: &str = "function_score";
+
+/// How the value of a `DoubleValuesSource` is applied to the wrapped query's score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreCombination {
+    /// The score is replaced by the value, documents without a value score 0.
+    Replace,
+    /// The score is multiplied by the value, documents without a value keep their score.
+    Multiply,
+}
+
+/// A query that wraps another query, and uses a `DoubleValuesSource` to
+/// replace or modify the wrapped query's score.
+///
+/// If the source needs scores it is passed the score of the wrapped query, so
+/// popularity or recency boosting can be expressed without a custom scorer.
+pub struct FunctionScoreQuery<C: Codec> {
+    query: Box<dyn Query<C>>,
+    source: Arc<dyn DoubleValuesSource<C>>,
+    combination: ScoreCombination,
+}
+
+impl<C: Codec> FunctionScoreQuery<C> {
+    /// Create a new `FunctionScoreQuery` whose scores are the values of `source`.
+    pub fn new(
+        query: Box<dyn Query<C>>,
+        source: Arc<dyn DoubleValuesSource<C>>,
+    ) -> FunctionScoreQuery<C> {
+        FunctionScoreQuery {
+            query,
+            source,
+            combination: ScoreCombination::Replace,
+        }
+    }
+
+    /// Returns a `FunctionScoreQuery` where the scores of the wrapped query are
+    /// multiplied by the values of `boost`.
+    pub fn boost_by_value(
+        query: Box<dyn Query<C>>,
+        boost: Arc<dyn DoubleValuesSource<C>>,
+    ) -> FunctionScoreQuery<C> {
+        FunctionScoreQuery {
+            query,
+            source: boost,
+            combination: ScoreCombination::Multiply,
+        }
+    }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    pub fn source(&self) -> &dyn DoubleValuesSource<C> {
+        self.source.as_ref()
+    }
+
+    pub fn combination(&self) -> ScoreCombination {
+        self.combination
+    }
+
+    fn needs_inner_scores(&self) -> bool {
+        self.combination == ScoreCombination::Multiply || self.source.needs_scores()
+    }
+}
+
+impl<C: Codec> Query<C> for FunctionScoreQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        if !needs_scores {
+            return searcher.create_weight(self.query.as_ref(), false);
+        }
+        let needs_inner_scores = self.needs_inner_scores();
+        let weight = searcher.create_weight(self.query.as_ref(), needs_inner_scores)?;
+        Ok(Box::new(FunctionScoreWeight {
+            weight,
+            source: Arc::clone(&self.source),
+            combination: self.combination,
+            needs_inner_scores,
+            boost: 1.0f32,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for FunctionScoreQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FunctionScoreQuery(query: {}, source: {}, combination: {:?})",
+            &self.query, &self.source, self.combination
+        )
+    }
+}
+
+struct FunctionScoreWeight<C: Codec> {
+    weight: Box<dyn Weight<C>>,
+    source: Arc<dyn DoubleValuesSource<C>>,
+    combination: ScoreCombination,
+    needs_inner_scores: bool,
+    boost: f32,
+}
+
+impl<C: Codec> Weight<C> for FunctionScoreWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        if let Some(scorer) = self.weight.create_scorer(reader)? {
+            Ok(Some(Box::new(FunctionScoreScorer {
+                scorer,
+                values: self.source.get_values(reader)?,
+                combination: self.combination,
+                needs_inner_scores: self.needs_inner_scores,
+                boost: self.boost,
+            })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        FUNCTION_SCORE
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        // the boost is applied on the final score, so the wrapped weight is left unboosted
+        self.weight.normalize(norm, 1.0f32);
+        self.boost = norm * boost;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.boost * self.boost
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let inner = self.weight.explain(reader, doc)?;
+        if !inner.is_match() {
+            return Ok(inner);
+        }
+        let value = self.source.explain(reader, doc, &inner)?;
+        let boost = Explanation::new(true, self.boost, "boost".to_string(), vec![]);
+        let (score, details) = match self.combination {
+            ScoreCombination::Replace => {
+                let v = if value.is_match() { value.value() } else { 0.0 };
+                (v * self.boost, vec![value, boost])
+            }
+            ScoreCombination::Multiply => {
+                let v = if value.is_match() { value.value() } else { 1.0 };
+                (inner.value() * v * self.boost, vec![inner, value, boost])
+            }
+        };
+        Ok(Explanation::new(
+            true,
+            score,
+            format!("{}, product of:", self),
+            details,
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for FunctionScoreWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FunctionScoreWeight(weight: {}, source: {}, combination: {:?}, boost: {})",
+            &self.weight, &self.source, self.combination, self.boost
+        )
+    }
+}
+
+struct FunctionScoreScorer {
+    scorer: Box<dyn Scorer>,
+    values: Box<dyn DoubleValues>,
+    combination: ScoreCombination,
+    needs_inner_scores: bool,
+    boost: f32,
+}
+
+impl Scorer for FunctionScoreScorer {
+    fn score(&mut self) -> Result<f32> {
+        let inner = if self.needs_inner_scores {
+            self.scorer.score()?
+        } else {
+            0.0f32
+        };
+        let value = self.values.get(self.scorer.doc_id(), inner)?;
+        let score = match self.combination {
+            ScoreCombination::Replace => value.unwrap_or(0.0) as f32,
+            ScoreCombination::Multiply => inner * value.unwrap_or(1.0) as f32,
+        };
+        Ok(score * self.boost)
+    }
+}
+
+impl DocIterator for FunctionScoreScorer {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.scorer.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.scorer.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    struct HalfDocValues;
+
+    impl DoubleValues for HalfDocValues {
+        fn get(&mut self, doc: DocId, _score: f32) -> Result<Option<f64>> {
+            if doc == 3 {
+                Ok(None)
+            } else {
+                Ok(Some(f64::from(doc) * 0.5))
+            }
+        }
+    }
+
+    fn scorer(combination: ScoreCombination) -> FunctionScoreScorer {
+        FunctionScoreScorer {
+            scorer: Box::new(create_mock_scorer(vec![2, 3, 4])),
+            values: Box::new(HalfDocValues),
+            combination,
+            needs_inner_scores: true,
+            boost: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_function_score_scorer() {
+        // mock scores are the doc ids
+        let mut replace = scorer(ScoreCombination::Replace);
+        assert_eq!(replace.next().unwrap(), 2);
+        assert!((replace.score().unwrap() - 2.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(replace.next().unwrap(), 3);
+        assert!(replace.score().unwrap().abs() < ::std::f32::EPSILON);
+
+        let mut multiply = scorer(ScoreCombination::Multiply);
+        assert_eq!(multiply.advance(3).unwrap(), 3);
+        assert!((multiply.score().unwrap() - 6.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(multiply.next().unwrap(), 4);
+        assert!((multiply.score().unwrap() - 16.0).abs() < ::std::f32::EPSILON);
+    }
+}
@@ -0,0 +1,368 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator, TermIterator};
+use core::codec::{AutomatonTermIterator, Codec, CodecTermIterator, CodecTerms, Terms};
+use core::doc::Term;
+use core::search::query::{multi_term_weight, MultiTermQuery, RewriteMethod};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{CompiledAutomaton, LevenshteinAutomata};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::cell::Cell;
+use std::cmp::min;
+use std::fmt;
+
+pub const FUZZY: &str = "fuzzy";
+
+/// The maximum number of edits supported by `FuzzyQuery`.
+pub const MAXIMUM_SUPPORTED_DISTANCE: u32 = 2;
+
+pub const DEFAULT_MAX_EDITS: u32 = MAXIMUM_SUPPORTED_DISTANCE;
+pub const DEFAULT_PREFIX_LENGTH: usize = 0;
+pub const DEFAULT_TRANSPOSITIONS: bool = true;
+
+/// Implements the fuzzy search query. The similarity measurement is based on
+/// the Damerau-Levenshtein (optimal string alignment) algorithm.
+///
+/// At most, this query will match terms up to `MAXIMUM_SUPPORTED_DISTANCE` edits.
+/// Higher distances (especially with transpositions enabled), are generally not
+/// useful and will match a significant amount of the term dictionary.
+///
+/// The terms dictionary is intersected with a `LevenshteinAutomata` accepting
+/// the `prefix_length` leading characters exactly followed by the rest of the
+/// term within `max_edits` edits, so only the blocks of terms which may match
+/// are visited, and a non-zero prefix reduces them even more.
+///
+/// With a scoring `RewriteMethod`, a matched term is boosted by its similarity
+/// `1 - edits / min(term_len, candidate_len)` to the query term, so the closest
+/// terms are kept by `RewriteMethod::TopTerms`.
+#[derive(Clone, Debug)]
+pub struct FuzzyQuery {
+    term: Term,
+    max_edits: u32,
+    prefix_length: usize,
+    transpositions: bool,
+    rewrite_method: RewriteMethod,
+}
+
+impl FuzzyQuery {
+    /// Calls `FuzzyQuery::with_params(term, DEFAULT_MAX_EDITS, DEFAULT_PREFIX_LENGTH,
+    /// DEFAULT_TRANSPOSITIONS)`.
+    pub fn new(term: Term) -> FuzzyQuery {
+        FuzzyQuery {
+            term,
+            max_edits: DEFAULT_MAX_EDITS,
+            prefix_length: DEFAULT_PREFIX_LENGTH,
+            transpositions: DEFAULT_TRANSPOSITIONS,
+            rewrite_method: RewriteMethod::default(),
+        }
+    }
+
+    /// Create a new FuzzyQuery that will match terms with an edit distance of at
+    /// most `max_edits` to `term`. If a `prefix_length` > 0 is specified, a common
+    /// prefix of that length is also required. `transpositions` controls whether
+    /// a transposition of two adjacent characters counts as a single edit.
+    pub fn with_params(
+        term: Term,
+        max_edits: u32,
+        prefix_length: usize,
+        transpositions: bool,
+    ) -> Result<FuzzyQuery> {
+        if max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            bail!(IllegalArgument(format!(
+                "max_edits must be between 0 and {}",
+                MAXIMUM_SUPPORTED_DISTANCE
+            )));
+        }
+        Ok(FuzzyQuery {
+            term,
+            max_edits,
+            prefix_length,
+            transpositions,
+            rewrite_method: RewriteMethod::default(),
+        })
+    }
+
+    /// Helper function to convert from deprecated "minimumSimilarity" fractions
+    /// to raw edit distances.
+    pub fn float_to_edits(min_sim: f32, term_len: usize) -> u32 {
+        if min_sim >= 1.0 {
+            min(min_sim as u32, MAXIMUM_SUPPORTED_DISTANCE)
+        } else if min_sim <= 0.0 {
+            0
+        } else {
+            min(
+                ((1.0 - min_sim) * term_len as f32) as u32,
+                MAXIMUM_SUPPORTED_DISTANCE,
+            )
+        }
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn max_edits(&self) -> u32 {
+        self.max_edits
+    }
+
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    pub fn transpositions(&self) -> bool {
+        self.transpositions
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.rewrite_method = rewrite_method;
+    }
+}
+
+impl<C: Codec> MultiTermQuery<C> for FuzzyQuery {
+    type Iter = FuzzyTermIterator<CodecTermIterator<C>>;
+
+    fn field(&self) -> &str {
+        self.term.field()
+    }
+
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter> {
+        FuzzyTermIterator::new(
+            terms,
+            &self.term.text()?,
+            self.max_edits,
+            self.prefix_length,
+            self.transpositions,
+        )
+    }
+
+    fn term_boost(&self, terms: &Self::Iter) -> f32 {
+        terms.boost()
+    }
+}
+
+impl<C: Codec> Query<C> for FuzzyQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        multi_term_weight(self, FUZZY, self.rewrite_method, searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for FuzzyQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FuzzyQuery(field: {}, term: {}, max_edits: {}, prefix_length: {}, transpositions: {})",
+            self.term.field(),
+            String::from_utf8_lossy(&self.term.bytes),
+            self.max_edits,
+            self.prefix_length,
+            self.transpositions
+        )
+    }
+}
+
+/// Subclass of `FilteredTermIterator` for enumerating all terms that are similar
+/// to the specified filter term.
+///
+/// The terms are enumerated by `Terms::intersect` with the Levenshtein automaton
+/// of the filter term, so every term returned is within the maximum edits. The
+/// edit distance of the current term is the one of the first automaton of
+/// `0..=max_edits` edits accepting it, and its boost is derived from it.
+pub struct FuzzyTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<AutomatonTermIterator<T>>,
+    // the automata accepting the terms within 0, 1, ... `max_edits` edits
+    automata: Vec<CompiledAutomaton>,
+    // the length of the filter term in code points
+    text_len: usize,
+    edits: Cell<u32>,
+    boost: Cell<f32>,
+}
+
+impl<T: TermIterator> FuzzyTermIterator<T> {
+    pub fn new<TS: Terms<Iterator = T>>(
+        terms: &TS,
+        text: &str,
+        max_edits: u32,
+        prefix_length: usize,
+        transpositions: bool,
+    ) -> Result<FuzzyTermIterator<T>> {
+        let automata = (0..=max_edits)
+            .map(|edits| fuzzy_automaton(text, edits, prefix_length, transpositions))
+            .collect::<Result<Vec<_>>>()?;
+        let terms = terms.intersect(&automata[max_edits as usize])?;
+        Ok(FuzzyTermIterator {
+            base: FilteredTermIterBase::new(terms, false),
+            automata,
+            text_len: text.chars().count(),
+            edits: Cell::new(0),
+            boost: Cell::new(1f32),
+        })
+    }
+
+    /// The number of edits between the current term and the filter term.
+    pub fn edits(&self) -> u32 {
+        self.edits.get()
+    }
+
+    /// The similarity `1 - edits / min(text_len, term_len)` of the current term
+    /// to the filter term, 1 for the filter term itself.
+    pub fn boost(&self) -> f32 {
+        self.boost.get()
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for FuzzyTermIterator<T> {
+    type Iter = AutomatonTermIterator<T>;
+
+    fn base(&self) -> &FilteredTermIterBase<AutomatonTermIterator<T>> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<AutomatonTermIterator<T>> {
+        &mut self.base
+    }
+
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        // every term is accepted by the last automaton, the intersected one
+        let edits = self
+            .automata
+            .iter()
+            .position(|automaton| automaton.run(term))
+            .unwrap_or(self.automata.len() - 1);
+        let len = min(self.text_len, String::from_utf8_lossy(term).chars().count());
+        self.edits.set(edits as u32);
+        self.boost.set(similarity(edits, len));
+        Ok(AcceptStatus::Yes)
+    }
+}
+
+/// The automaton accepting the terms within `max_edits` edits of `text`, whose
+/// first `prefix_length` code points are matched exactly.
+fn fuzzy_automaton(
+    text: &str,
+    max_edits: u32,
+    prefix_length: usize,
+    transpositions: bool,
+) -> Result<CompiledAutomaton> {
+    let chars: Vec<char> = text.chars().collect();
+    let prefix_length = min(prefix_length, chars.len());
+    let prefix: String = chars[..prefix_length].iter().collect();
+    let suffix: String = chars[prefix_length..].iter().collect();
+    let automaton = LevenshteinAutomata::new(&suffix, transpositions)
+        .to_automaton_with_prefix(max_edits, &prefix)?;
+    CompiledAutomaton::with_automaton(&automaton)
+}
+
+/// The similarity of two terms `edits` apart, the shortest being `len`
+/// code points long.
+pub(crate) fn similarity(edits: usize, len: usize) -> f32 {
+    if edits == 0 {
+        1f32
+    } else if len == 0 {
+        0f32
+    } else {
+        (1f32 - edits as f32 / len as f32).max(0f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::MockTerms;
+
+    fn within(a: &str, b: &str, max_edits: u32, transpositions: bool) -> bool {
+        fuzzy_automaton(a, max_edits, 0, transpositions)
+            .unwrap()
+            .run(b.as_bytes())
+    }
+
+    #[test]
+    fn test_within_distance() {
+        assert!(within("lucene", "lucene", 0, true));
+        assert!(within("lucene", "lucine", 1, true));
+        assert!(within("lucene", "luence", 2, false));
+        assert!(!within("lucene", "luecne", 1, false));
+        assert!(within("lucene", "luecne", 1, true));
+        assert!(within("lucene", "lucenes", 1, true));
+        assert!(!within("lucene", "lu", 2, true));
+        assert!(within("", "ab", 2, true));
+        assert!(within("搜索", "搜", 1, true));
+
+        // the prefix must match exactly
+        let automaton = fuzzy_automaton("lucene", 1, 2, true).unwrap();
+        assert!(automaton.run(b"lucine"));
+        assert!(!automaton.run(b"pucene"));
+        assert!(fuzzy_automaton("lucene", 2, 10, true)
+            .unwrap()
+            .run(b"lucene"));
+    }
+
+    #[test]
+    fn test_term_boost() {
+        let terms = MockTerms::new(&["luce", "lucene", "lucenes", "lucine", "lucky", "solr"]);
+        let mut iter = FuzzyTermIterator::new(&terms, "lucene", 2, 0, true).unwrap();
+        let mut matches = vec![];
+        while let Some(term) = iter.next().unwrap() {
+            matches.push((String::from_utf8(term).unwrap(), iter.edits(), iter.boost()));
+        }
+        let expected = vec![
+            ("luce", 2, 0.5),
+            ("lucene", 0, 1.0),
+            ("lucenes", 1, 5.0 / 6.0),
+            ("lucine", 1, 5.0 / 6.0),
+        ];
+        assert_eq!(matches.len(), expected.len());
+        for ((term, edits, boost), (t, e, b)) in matches.into_iter().zip(expected) {
+            assert_eq!(term, t);
+            assert_eq!(edits, e);
+            assert!((boost - b).abs() < ::std::f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert!((similarity(0, 0) - 1.0).abs() < ::std::f32::EPSILON);
+        assert!((similarity(1, 4) - 0.75).abs() < ::std::f32::EPSILON);
+        assert!((similarity(2, 4) - 0.5).abs() < ::std::f32::EPSILON);
+        assert!((similarity(3, 2)).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_float_to_edits() {
+        assert_eq!(FuzzyQuery::float_to_edits(2.0, 5), 2);
+        assert_eq!(FuzzyQuery::float_to_edits(3.0, 5), 2);
+        assert_eq!(FuzzyQuery::float_to_edits(1.0, 5), 1);
+        assert_eq!(FuzzyQuery::float_to_edits(0.0, 5), 0);
+        assert_eq!(FuzzyQuery::float_to_edits(0.75, 4), 1);
+        assert_eq!(FuzzyQuery::float_to_edits(0.5, 4), 2);
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::doc::{GeoHashPrefixTreeStrategy, Term};
+use core::geo::{geohash_bbox, geohash_cover, Rectangle};
+use core::search::query::{BooleanQuery, ConstantScoreQuery, Query, TermQuery};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+impl GeoHashPrefixTreeStrategy {
+    /// Create a query matching the docs with a location in the cell of
+    /// `geohash`, which can't be longer than the max levels of the tree.
+    pub fn new_cell_query<C: Codec>(&self, geohash: &str) -> Result<Box<dyn Query<C>>> {
+        geohash_bbox(geohash)?;
+        if geohash.len() > self.max_levels() {
+            bail!(IllegalArgument(format!(
+                "geohash '{}' is longer than the {} levels of the tree",
+                geohash,
+                self.max_levels()
+            )));
+        }
+        Ok(Box::new(TermQuery::new(
+            Term::new(self.field_name().to_string(), geohash.as_bytes().to_vec()),
+            1.0,
+            None,
+        )))
+    }
+
+    /// Create a query matching the docs with a location in the cells covering
+    /// a box, which crosses the dateline when `min_longitude` is greater than
+    /// `max_longitude`. See `detail_level` for the precision of the cover.
+    pub fn new_box_query<C: Codec>(
+        &self,
+        min_latitude: f64,
+        max_latitude: f64,
+        min_longitude: f64,
+        max_longitude: f64,
+    ) -> Result<Box<dyn Query<C>>> {
+        let rect = Rectangle::new(min_latitude, max_latitude, min_longitude, max_longitude)?;
+        let cells = geohash_cover(&rect, self.detail_level(&rect))?;
+        let shoulds = cells
+            .iter()
+            .map(|cell| self.new_cell_query(cell))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(ConstantScoreQuery::new(BooleanQuery::build(
+            vec![],
+            shoulds,
+            vec![],
+        )?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    #[test]
+    fn test_geo_hash_prefix_tree_queries() {
+        let strategy = GeoHashPrefixTreeStrategy::new("cell", 4).unwrap();
+        let query = strategy.new_cell_query::<TestCodec>("u09").unwrap();
+        let term_query = query.as_any().downcast_ref::<TermQuery>().unwrap();
+        assert_eq!(term_query.term().bytes, b"u09".to_vec());
+        assert!(strategy.new_cell_query::<TestCodec>("u09tu").is_err());
+        assert!(strategy.new_cell_query::<TestCodec>("a").is_err());
+
+        let query = strategy
+            .new_box_query::<TestCodec>(1.0, 44.0, 1.0, 44.0)
+            .unwrap();
+        assert!(query.as_any().is::<ConstantScoreQuery<TestCodec>>());
+        assert!(strategy
+            .new_box_query::<TestCodec>(1.0, 44.0, 1.0, 181.0)
+            .is_err());
+    }
+}
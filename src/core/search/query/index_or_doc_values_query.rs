@@ -0,0 +1,279 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::util::DocId;
+
+use error::Result;
+
+pub const INDEX_OR_DOC_VALUES: &str = "index_or_doc_values";
+
+/// A query that uses either an index structure (points or terms) or doc values
+/// in order to run a query, depending which one is more efficient.
+///
+/// This is typically useful for range queries, whose `Weight::create_scorer` is
+/// costly and only worth paying when the range leads the iteration. When it is
+/// intersected with more selective clauses, checking the doc values of the few
+/// docs they match is cheaper.
+///
+/// `index_query` and `dv_query` must match the same docs, and the scores of
+/// `index_query` are used.
+pub struct IndexOrDocValuesQuery<C: Codec> {
+    index_query: Box<dyn Query<C>>,
+    dv_query: Box<dyn Query<C>>,
+}
+
+impl<C: Codec> IndexOrDocValuesQuery<C> {
+    pub fn new(
+        index_query: Box<dyn Query<C>>,
+        dv_query: Box<dyn Query<C>>,
+    ) -> IndexOrDocValuesQuery<C> {
+        IndexOrDocValuesQuery {
+            index_query,
+            dv_query,
+        }
+    }
+
+    /// Return the wrapped query that may be costly to initialize but has a good
+    /// iterator.
+    pub fn index_query(&self) -> &dyn Query<C> {
+        self.index_query.as_ref()
+    }
+
+    /// Return the wrapped query that may be slow at identifying all matching
+    /// documents, but which is cheap to initialize and can efficiently verify
+    /// that some documents match.
+    pub fn dv_query(&self) -> &dyn Query<C> {
+        self.dv_query.as_ref()
+    }
+}
+
+impl<C: Codec> Query<C> for IndexOrDocValuesQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let index_weight = self.index_query.create_weight(searcher, needs_scores)?;
+        let dv_weight = self.dv_query.create_weight(searcher, needs_scores)?;
+        Ok(Box::new(IndexOrDocValuesWeight {
+            index_weight,
+            dv_weight,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.index_query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for IndexOrDocValuesQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IndexOrDocValuesQuery(index_query: {}, dv_query: {})",
+            &self.index_query, &self.dv_query
+        )
+    }
+}
+
+struct IndexOrDocValuesWeight<C: Codec> {
+    index_weight: Box<dyn Weight<C>>,
+    dv_weight: Box<dyn Weight<C>>,
+}
+
+impl<C: Codec> Weight<C> for IndexOrDocValuesWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        // the query leads the iteration, the index is needed
+        self.index_weight.create_scorer(reader)
+    }
+
+    fn query_type(&self) -> &'static str {
+        INDEX_OR_DOC_VALUES
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.index_weight.normalize(norm, boost);
+        self.dv_weight.normalize(norm, boost);
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.index_weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.index_weight.needs_scores()
+    }
+
+    fn count(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        self.index_weight.count(reader)
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        self.index_weight.estimate_cost(reader)
+    }
+
+    fn uses_lead_cost(&self) -> bool {
+        true
+    }
+
+    fn create_scorer_with_lead_cost(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        lead_cost: usize,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        // the same threshold as Lucene: the doc values are used if the index
+        // would match 8 times more docs than the lead iterator
+        match self.index_weight.estimate_cost(reader)? {
+            Some(index_cost) if index_cost / 8 > lead_cost => self.dv_weight.create_scorer(reader),
+            _ => self.index_weight.create_scorer(reader),
+        }
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.index_weight.explain(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for IndexOrDocValuesWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IndexOrDocValuesWeight(index_weight: {}, dv_weight: {})",
+            &self.index_weight, &self.dv_weight
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::collector::TotalHitCountCollector;
+    use core::search::query::BooleanQuery;
+    use core::search::searcher::{DefaultIndexSearcher, IndexSearcher};
+    use core::search::tests::*;
+    use core::search::DocIterator;
+    use std::sync::Arc;
+
+    struct MockQuery {
+        docs: Vec<DocId>,
+    }
+
+    impl<C: Codec> Query<C> for MockQuery {
+        fn create_weight(
+            &self,
+            _searcher: &dyn SearchPlanBuilder<C>,
+            _needs_scores: bool,
+        ) -> Result<Box<dyn Weight<C>>> {
+            Ok(Box::new(create_mock_weight(self.docs.clone())))
+        }
+
+        fn extract_terms(&self) -> Vec<TermQuery> {
+            vec![]
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl fmt::Display for MockQuery {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "MockQuery(docs: {:?})", self.docs)
+        }
+    }
+
+    fn index_or_doc_values_query() -> Box<dyn Query<TestCodec>> {
+        // the docs differ so that the variant in use can be told
+        Box::new(IndexOrDocValuesQuery::new(
+            Box::new(MockQuery {
+                docs: (0..80).collect(),
+            }),
+            Box::new(MockQuery { docs: vec![5, 7] }),
+        ))
+    }
+
+    #[test]
+    fn test_index_or_doc_values_weight() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![MockLeafReader::new(100)]));
+        let leaves = index_reader.leaves();
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&index_reader), None, None);
+
+        let query = index_or_doc_values_query();
+        let weight = query.create_weight(&searcher, false).unwrap();
+        assert!(weight.uses_lead_cost());
+        assert_eq!(weight.estimate_cost(&leaves[0]).unwrap(), Some(80));
+
+        let scorer = weight.create_scorer(&leaves[0]).unwrap().unwrap();
+        assert_eq!(scorer.cost(), 80);
+        let scorer = weight
+            .create_scorer_with_lead_cost(&leaves[0], 20)
+            .unwrap()
+            .unwrap();
+        assert_eq!(scorer.cost(), 80);
+        let scorer = weight
+            .create_scorer_with_lead_cost(&leaves[0], 5)
+            .unwrap()
+            .unwrap();
+        assert_eq!(scorer.cost(), 2);
+    }
+
+    #[test]
+    fn test_index_or_doc_values_in_conjunction() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![MockLeafReader::new(100)]));
+        let searcher = DefaultIndexSearcher::new(index_reader, None, None);
+
+        // the selective clause leads, the doc values are used
+        let query = BooleanQuery::build(
+            vec![Box::new(MockQuery {
+                docs: vec![5, 6, 7],
+            })],
+            vec![],
+            vec![index_or_doc_values_query()],
+        )
+        .unwrap();
+        let mut collector = TotalHitCountCollector::new();
+        searcher.search(query.as_ref(), &mut collector).unwrap();
+        assert_eq!(collector.total_hits(), 2);
+
+        // the index is used when the other clause isn't selective
+        let query = BooleanQuery::build(
+            vec![Box::new(MockQuery {
+                docs: (0..50).collect(),
+            })],
+            vec![],
+            vec![index_or_doc_values_query()],
+        )
+        .unwrap();
+        let mut collector = TotalHitCountCollector::new();
+        searcher.search(query.as_ref(), &mut collector).unwrap();
+        assert_eq!(collector.total_hits(), 50);
+    }
+}
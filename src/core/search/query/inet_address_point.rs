@@ -0,0 +1,341 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::{ErrorKind, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use core::analysis::TokenStream;
+use core::codec::Codec;
+use core::doc::{Field, FieldType, Fieldable};
+use core::search::query::{PointInSetQuery, PointRangeQuery, PointValueType, Query};
+use core::util::{Numeric, VariantValue};
+
+/// An indexed IPv4 or IPv6 address field for fast exact, range and prefix
+/// (CIDR) filters.
+///
+/// The addresses are indexed as 16 bytes points, IPv4 addresses being mapped
+/// to IPv6 (`::ffff:a.b.c.d`), so that both kinds may be mixed in a field.
+///
+/// If you also need to store the value, you should add a separate `StoredField` instance.
+///
+/// This field defines static factory methods for creating common queries
+#[derive(Debug)]
+pub struct InetAddressPoint {
+    field: Field,
+}
+
+impl InetAddressPoint {
+    /// The number of bytes per dimension.
+    pub const BYTES: usize = 16;
+
+    /// The smallest address, `::`.
+    pub const MIN_VALUE: IpAddr = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+
+    /// The largest address, `ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff`.
+    pub const MAX_VALUE: IpAddr = IpAddr::V6(Ipv6Addr::new(
+        0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff,
+    ));
+
+    pub fn new(field_name: &str, address: IpAddr) -> Result<InetAddressPoint> {
+        let field_type = FieldType::point(1, InetAddressPoint::BYTES as u32)?;
+        Ok(InetAddressPoint {
+            field: Field::new_bytes(
+                field_name.to_string(),
+                InetAddressPoint::encode(address),
+                field_type,
+            ),
+        })
+    }
+
+    pub fn address(&self) -> IpAddr {
+        InetAddressPoint::decode(self.field.binary_value().unwrap())
+    }
+
+    /// Encodes an address into its 16 bytes sortable form.
+    pub fn encode(address: IpAddr) -> Vec<u8> {
+        match address {
+            IpAddr::V4(v4) => v4.to_ipv6_mapped().octets().to_vec(),
+            IpAddr::V6(v6) => v6.octets().to_vec(),
+        }
+    }
+
+    /// Decodes an address from its 16 bytes encoded form, IPv4-mapped addresses
+    /// being decoded as IPv4.
+    pub fn decode(value: &[u8]) -> IpAddr {
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&value[..InetAddressPoint::BYTES]);
+        if octets[..10].iter().all(|&b| b == 0) && octets[10] == 0xff && octets[11] == 0xff {
+            IpAddr::V4(Ipv4Addr::new(
+                octets[12], octets[13], octets[14], octets[15],
+            ))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    }
+
+    /// The address immediately following `address`, to make exclusive lower
+    /// bounds inclusive.
+    pub fn next_up(address: IpAddr) -> Result<IpAddr> {
+        let value = InetAddressPoint::to_u128(address);
+        if value == u128::max_value() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "next_up called on the maximum address {}",
+                address
+            )));
+        }
+        Ok(InetAddressPoint::from_u128(value + 1))
+    }
+
+    /// The address immediately preceding `address`, to make exclusive upper
+    /// bounds inclusive.
+    pub fn next_down(address: IpAddr) -> Result<IpAddr> {
+        let value = InetAddressPoint::to_u128(address);
+        if value == 0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "next_down called on the minimum address {}",
+                address
+            )));
+        }
+        Ok(InetAddressPoint::from_u128(value - 1))
+    }
+
+    fn to_u128(address: IpAddr) -> u128 {
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&InetAddressPoint::encode(address));
+        u128::from_be_bytes(octets)
+    }
+
+    fn from_u128(value: u128) -> IpAddr {
+        InetAddressPoint::decode(&value.to_be_bytes())
+    }
+
+    /// Create a query for matching a network address.
+    pub fn new_exact_query<C: Codec>(field: String, value: IpAddr) -> Result<Box<dyn Query<C>>> {
+        InetAddressPoint::new_range_query(field, value, value)
+    }
+
+    /// Create a range query for network addresses, both bounds being inclusive.
+    pub fn new_range_query<C: Codec>(
+        field: String,
+        lower: IpAddr,
+        upper: IpAddr,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(PointRangeQuery::new(
+            field,
+            InetAddressPoint::encode(lower),
+            InetAddressPoint::encode(upper),
+            1,
+            PointValueType::InetAddress,
+        )?))
+    }
+
+    /// Create a prefix query for matching a CIDR network range, e.g. `10.0.0.0`
+    /// with a `prefix_length` of 8 for `10.0.0.0/8`.
+    pub fn new_prefix_query<C: Codec>(
+        field: String,
+        address: IpAddr,
+        prefix_length: u32,
+    ) -> Result<Box<dyn Query<C>>> {
+        // the IPv4 prefixes apply to the last 32 bits of the mapped address
+        let (max_length, offset) = match address {
+            IpAddr::V4(_) => (32, 96),
+            IpAddr::V6(_) => (128, 0),
+        };
+        if prefix_length > max_length {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "illegal prefix length {} for address {}, must be in 0..={}",
+                prefix_length, address, max_length
+            )));
+        }
+        let prefix_length = prefix_length + offset;
+        let mask = if prefix_length == 0 {
+            0
+        } else {
+            u128::max_value() << (128 - prefix_length)
+        };
+        let value = InetAddressPoint::to_u128(address);
+        InetAddressPoint::new_range_query(
+            field,
+            InetAddressPoint::from_u128(value & mask),
+            InetAddressPoint::from_u128(value | !mask),
+        )
+    }
+
+    /// Create a query matching a network address or a CIDR notation network
+    /// range, e.g. `192.168.0.0/16` or `2001:db8::/32`.
+    pub fn new_cidr_query<C: Codec>(field: String, cidr: &str) -> Result<Box<dyn Query<C>>> {
+        let (address, prefix_length) = InetAddressPoint::parse_cidr(cidr)?;
+        match prefix_length {
+            Some(prefix_length) => {
+                InetAddressPoint::new_prefix_query(field, address, prefix_length)
+            }
+            None => InetAddressPoint::new_exact_query(field, address),
+        }
+    }
+
+    /// Create a query matching any of the specified addresses.
+    /// This is the points equivalent of a terms query.
+    pub fn new_set_query<C: Codec>(field: String, values: &[IpAddr]) -> Result<Box<dyn Query<C>>> {
+        let points = values
+            .iter()
+            .map(|&v| InetAddressPoint::encode(v))
+            .collect();
+        Ok(Box::new(PointInSetQuery::new(
+            field,
+            1,
+            InetAddressPoint::BYTES,
+            points,
+            PointValueType::InetAddress,
+        )?))
+    }
+
+    /// Parses an address, optionally followed by a `/` and a prefix length.
+    pub fn parse_cidr(cidr: &str) -> Result<(IpAddr, Option<u32>)> {
+        let (address, prefix_length) = match cidr.find('/') {
+            Some(i) => (&cidr[..i], Some(&cidr[i + 1..])),
+            None => (cidr, None),
+        };
+        let address = match address.parse::<IpAddr>() {
+            Ok(address) => address,
+            Err(_) => bail!(ErrorKind::IllegalArgument(format!(
+                "invalid network address '{}'",
+                cidr
+            ))),
+        };
+        let prefix_length = match prefix_length.map(str::parse::<u32>) {
+            Some(Ok(prefix_length)) => Some(prefix_length),
+            Some(Err(_)) => bail!(ErrorKind::IllegalArgument(format!(
+                "invalid prefix length in '{}'",
+                cidr
+            ))),
+            None => None,
+        };
+        Ok((address, prefix_length))
+    }
+}
+
+impl Fieldable for InetAddressPoint {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for s in &[
+            "0.0.0.0",
+            "192.168.1.10",
+            "::",
+            "::1",
+            "2001:db8::ff00:42:8329",
+        ] {
+            let address = ip(s);
+            let encoded = InetAddressPoint::encode(address);
+            assert_eq!(encoded.len(), InetAddressPoint::BYTES);
+            assert_eq!(InetAddressPoint::decode(&encoded), address);
+        }
+
+        // the encoded addresses sort as their bytes
+        assert!(
+            InetAddressPoint::encode(ip("10.0.0.1")) < InetAddressPoint::encode(ip("10.0.1.0"))
+        );
+        assert!(InetAddressPoint::encode(ip("::1")) < InetAddressPoint::encode(ip("1.2.3.4")));
+
+        let point = InetAddressPoint::new("ip", ip("10.1.2.3")).unwrap();
+        assert_eq!(point.address(), ip("10.1.2.3"));
+        assert_eq!(point.field_type().dimension_count, 1);
+        assert_eq!(point.field_type().dimension_num_bytes, 16);
+    }
+
+    #[test]
+    fn test_next_up_down() {
+        assert_eq!(
+            InetAddressPoint::next_up(ip("10.0.0.255")).unwrap(),
+            ip("10.0.1.0")
+        );
+        assert_eq!(
+            InetAddressPoint::next_down(ip("10.0.1.0")).unwrap(),
+            ip("10.0.0.255")
+        );
+        assert!(InetAddressPoint::next_up(InetAddressPoint::MAX_VALUE).is_err());
+        assert!(InetAddressPoint::next_down(InetAddressPoint::MIN_VALUE).is_err());
+    }
+
+    #[test]
+    fn test_prefix_query() {
+        let q: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_cidr_query("ip".into(), "10.1.2.3/16").unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_range_query("ip".into(), ip("10.1.0.0"), ip("10.1.255.255"))
+                .unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        let q: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_cidr_query("ip".into(), "2001:db8::/32").unwrap();
+        let expected: Box<dyn Query<TestCodec>> = InetAddressPoint::new_range_query(
+            "ip".into(),
+            ip("2001:db8::"),
+            ip("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"),
+        )
+        .unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        let q: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_cidr_query("ip".into(), "0.0.0.0/0").unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_range_query("ip".into(), ip("0.0.0.0"), ip("255.255.255.255"))
+                .unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        assert!(InetAddressPoint::new_cidr_query::<TestCodec>("ip".into(), "10.0.0.0/33").is_err());
+        assert!(InetAddressPoint::new_cidr_query::<TestCodec>("ip".into(), "10.0.0/8").is_err());
+        assert!(InetAddressPoint::new_cidr_query::<TestCodec>("ip".into(), "10.0.0.0/x").is_err());
+    }
+}
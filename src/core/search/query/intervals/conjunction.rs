@@ -0,0 +1,620 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::intervals::{fmt_sources, IntervalsSourceRef};
+use core::search::query::intervals::{IntervalIterator, IntervalsSource, NO_MORE_INTERVALS};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+
+/// How a `ConjunctionIntervalsSource` combines the intervals of its sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConjunctionKind {
+    /// The minimal intervals containing an interval of each source, in order.
+    Ordered,
+    /// The minimal intervals containing an interval of each source.
+    Unordered,
+    /// The intervals of the first source containing an interval of the second.
+    Containing,
+    /// The intervals of the first source contained by an interval of the second.
+    ContainedBy,
+}
+
+impl fmt::Display for ConjunctionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConjunctionKind::Ordered => write!(f, "ORDERED"),
+            ConjunctionKind::Unordered => write!(f, "UNORDERED"),
+            ConjunctionKind::Containing => write!(f, "CONTAINING"),
+            ConjunctionKind::ContainedBy => write!(f, "CONTAINED_BY"),
+        }
+    }
+}
+
+/// The intervals of the docs matching all the sources.
+pub struct ConjunctionIntervalsSource<C: Codec> {
+    sources: Vec<IntervalsSourceRef<C>>,
+    kind: ConjunctionKind,
+}
+
+impl<C: Codec> ConjunctionIntervalsSource<C> {
+    pub fn new(
+        sources: Vec<IntervalsSourceRef<C>>,
+        kind: ConjunctionKind,
+    ) -> ConjunctionIntervalsSource<C> {
+        debug_assert!(
+            (kind != ConjunctionKind::Containing && kind != ConjunctionKind::ContainedBy)
+                || sources.len() == 2
+        );
+        ConjunctionIntervalsSource { sources, kind }
+    }
+}
+
+impl<C: Codec> IntervalsSource<C> for ConjunctionIntervalsSource<C> {
+    fn intervals(
+        &self,
+        field: &str,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn IntervalIterator>>> {
+        if self.sources.is_empty() {
+            return Ok(None);
+        }
+        let mut subs = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            match source.intervals(field, ctx)? {
+                Some(sub) => subs.push(sub),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(conjunction_iterator(subs, self.kind)))
+    }
+
+    fn extract_terms(&self, field: &str, terms: &mut Vec<Term>) {
+        for source in &self.sources {
+            source.extract_terms(field, terms);
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for ConjunctionIntervalsSource<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_sources(f, &self.kind.to_string(), &self.sources)
+    }
+}
+
+fn conjunction_iterator(
+    mut subs: Vec<Box<dyn IntervalIterator>>,
+    kind: ConjunctionKind,
+) -> Box<dyn IntervalIterator> {
+    if subs.len() == 1 && (kind == ConjunctionKind::Ordered || kind == ConjunctionKind::Unordered) {
+        // the intervals of the single sub are minimal already
+        return subs.pop().unwrap();
+    }
+    match kind {
+        ConjunctionKind::Ordered => Box::new(ConjunctionIntervalIterator::new(
+            subs,
+            OrderedFunction::default(),
+        )),
+        ConjunctionKind::Unordered => Box::new(ConjunctionIntervalIterator::new(
+            subs,
+            UnorderedFunction::default(),
+        )),
+        ConjunctionKind::Containing => Box::new(ConjunctionIntervalIterator::new(
+            subs,
+            ContainingFunction::new(false),
+        )),
+        ConjunctionKind::ContainedBy => Box::new(ConjunctionIntervalIterator::new(
+            subs,
+            ContainingFunction::new(true),
+        )),
+    }
+}
+
+/// Computes the intervals of a conjunction from its sub iterators, all
+/// positioned on the same doc.
+trait IntervalFunction: Send {
+    /// Called once the sub iterators are positioned on a new doc.
+    fn reset(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<()>;
+
+    fn next_interval(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<i32>;
+
+    fn start(&self) -> i32;
+
+    fn end(&self) -> i32;
+
+    fn gaps(&self) -> i32;
+}
+
+/// Iterates the docs of all the sub iterators, the intervals of each doc are
+/// computed by an `IntervalFunction`.
+struct ConjunctionIntervalIterator<F: IntervalFunction> {
+    subs: Vec<Box<dyn IntervalIterator>>,
+    function: F,
+    doc: DocId,
+    cost: usize,
+    match_cost: f32,
+}
+
+impl<F: IntervalFunction> ConjunctionIntervalIterator<F> {
+    fn new(subs: Vec<Box<dyn IntervalIterator>>, function: F) -> ConjunctionIntervalIterator<F> {
+        debug_assert!(!subs.is_empty());
+        let cost = subs.iter().map(|s| s.cost()).min().unwrap_or(0);
+        let match_cost = subs.iter().map(|s| s.match_cost()).sum();
+        ConjunctionIntervalIterator {
+            subs,
+            function,
+            doc: -1,
+            cost,
+            match_cost,
+        }
+    }
+
+    // leap frog until all the subs are on the same doc
+    fn do_next(&mut self, mut target: DocId) -> Result<DocId> {
+        let mut i = 0;
+        while i < self.subs.len() {
+            let mut doc = self.subs[i].doc_id();
+            if doc < target {
+                doc = self.subs[i].advance(target)?;
+            }
+            if doc == NO_MORE_DOCS {
+                self.doc = NO_MORE_DOCS;
+                return Ok(NO_MORE_DOCS);
+            }
+            if doc > target {
+                target = doc;
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+        self.doc = target;
+        self.function.reset(&mut self.subs)?;
+        Ok(target)
+    }
+}
+
+impl<F: IntervalFunction> DocIterator for ConjunctionIntervalIterator<F> {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.do_next(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.do_next(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.match_cost
+    }
+}
+
+impl<F: IntervalFunction> IntervalIterator for ConjunctionIntervalIterator<F> {
+    fn start(&self) -> i32 {
+        self.function.start()
+    }
+
+    fn end(&self) -> i32 {
+        self.function.end()
+    }
+
+    fn gaps(&self) -> i32 {
+        self.function.gaps()
+    }
+
+    fn next_interval(&mut self) -> Result<i32> {
+        if self.doc == NO_MORE_DOCS {
+            return Ok(NO_MORE_INTERVALS);
+        }
+        self.function.next_interval(&mut self.subs)
+    }
+}
+
+/// The minimal intervals containing an interval of each sub iterator, in
+/// order and without overlap.
+#[derive(Default)]
+struct OrderedFunction {
+    start: i32,
+    end: i32,
+    gaps: i32,
+}
+
+impl IntervalFunction for OrderedFunction {
+    fn reset(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<()> {
+        subs[0].next_interval()?;
+        self.start = -1;
+        self.end = -1;
+        self.gaps = -1;
+        Ok(())
+    }
+
+    fn next_interval(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<i32> {
+        if self.start == NO_MORE_INTERVALS {
+            return Ok(NO_MORE_INTERVALS);
+        }
+        self.start = NO_MORE_INTERVALS;
+        self.end = NO_MORE_INTERVALS;
+        self.gaps = NO_MORE_INTERVALS;
+        let n = subs.len();
+        // the start of the last sub of the recorded interval, a narrower one
+        // must keep this last sub
+        let mut last_start = NO_MORE_INTERVALS;
+        loop {
+            // move each sub after the previous one
+            for i in 1..n {
+                if subs[i - 1].end() >= last_start {
+                    return Ok(self.start);
+                }
+                while subs[i].start() <= subs[i - 1].end() {
+                    if subs[i].end() >= last_start || subs[i].next_interval()? == NO_MORE_INTERVALS
+                    {
+                        return Ok(self.start);
+                    }
+                }
+            }
+            self.start = subs[0].start();
+            self.end = subs[n - 1].end();
+            self.gaps = self.end - self.start + 1 - subs.iter().map(|s| s.width()).sum::<i32>();
+            last_start = subs[n - 1].start();
+            // try to narrow it from the left
+            if subs[0].next_interval()? == NO_MORE_INTERVALS {
+                return Ok(self.start);
+            }
+        }
+    }
+
+    fn start(&self) -> i32 {
+        self.start
+    }
+
+    fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn gaps(&self) -> i32 {
+        self.gaps
+    }
+}
+
+/// The minimal intervals containing an interval of each sub iterator, in any
+/// order.
+#[derive(Default)]
+struct UnorderedFunction {
+    start: i32,
+    end: i32,
+    gaps: i32,
+    // the subs having a current interval
+    queue: Vec<usize>,
+    // the greatest end of the subs in the queue
+    queue_end: i32,
+}
+
+impl UnorderedFunction {
+    // the position in the queue of the sub with the smallest start, the
+    // greatest end on ties
+    fn top(&self, subs: &[Box<dyn IntervalIterator>]) -> usize {
+        let mut top = 0;
+        for k in 1..self.queue.len() {
+            let (a, b) = (&subs[self.queue[k]], &subs[self.queue[top]]);
+            if a.start() < b.start() || (a.start() == b.start() && a.end() > b.end()) {
+                top = k;
+            }
+        }
+        top
+    }
+
+    // moves the top sub to its next interval, dropping it once exhausted
+    fn advance_top(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<()> {
+        let k = self.top(subs);
+        let sub = self.queue[k];
+        if subs[sub].next_interval()? == NO_MORE_INTERVALS {
+            self.queue.swap_remove(k);
+        } else {
+            self.queue_end = self.queue_end.max(subs[sub].end());
+        }
+        Ok(())
+    }
+}
+
+impl IntervalFunction for UnorderedFunction {
+    fn reset(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<()> {
+        self.start = -1;
+        self.end = -1;
+        self.gaps = -1;
+        self.queue_end = -1;
+        self.queue.clear();
+        for (i, sub) in subs.iter_mut().enumerate() {
+            if sub.next_interval()? == NO_MORE_INTERVALS {
+                break;
+            }
+            self.queue.push(i);
+            self.queue_end = self.queue_end.max(sub.end());
+        }
+        Ok(())
+    }
+
+    fn next_interval(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<i32> {
+        if self.start == NO_MORE_INTERVALS {
+            return Ok(NO_MORE_INTERVALS);
+        }
+        let n = subs.len();
+        // first move past the previous interval
+        while self.queue.len() == n && subs[self.queue[self.top(subs)]].start() == self.start {
+            self.advance_top(subs)?;
+        }
+        if self.queue.len() < n {
+            self.start = NO_MORE_INTERVALS;
+            self.end = NO_MORE_INTERVALS;
+            self.gaps = NO_MORE_INTERVALS;
+            return Ok(NO_MORE_INTERVALS);
+        }
+        // then narrow the interval from the left
+        loop {
+            let top = self.queue[self.top(subs)];
+            self.start = subs[top].start();
+            self.end = self.queue_end;
+            self.gaps = self.end - self.start + 1 - subs.iter().map(|s| s.width()).sum::<i32>();
+            if subs[top].end() == self.end {
+                break;
+            }
+            self.advance_top(subs)?;
+            if self.queue.len() < n || self.end != self.queue_end {
+                break;
+            }
+        }
+        Ok(self.start)
+    }
+
+    fn start(&self) -> i32 {
+        self.start
+    }
+
+    fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn gaps(&self) -> i32 {
+        self.gaps
+    }
+}
+
+/// Whether the current interval of `a` contains the next intervals of `b`,
+/// `None` once `b` is exhausted. The intervals of `b` starting before `a` are
+/// skipped as they can't be contained by the next intervals of `a` either.
+pub(super) fn contains(
+    a: &dyn IntervalIterator,
+    b: &mut dyn IntervalIterator,
+) -> Result<Option<bool>> {
+    while b.start() < a.start() {
+        if b.next_interval()? == NO_MORE_INTERVALS {
+            return Ok(None);
+        }
+    }
+    if b.start() == NO_MORE_INTERVALS {
+        return Ok(None);
+    }
+    Ok(Some(b.end() <= a.end()))
+}
+
+/// Whether the current interval of `a` is contained by the next intervals of
+/// `b`, `None` once `b` is exhausted. The intervals of `b` ending before `a` are
+/// skipped as they can't contain the next intervals of `a` either.
+pub(super) fn contained_by(
+    a: &dyn IntervalIterator,
+    b: &mut dyn IntervalIterator,
+) -> Result<Option<bool>> {
+    while b.end() < a.end() {
+        if b.next_interval()? == NO_MORE_INTERVALS {
+            return Ok(None);
+        }
+    }
+    if b.start() == NO_MORE_INTERVALS {
+        return Ok(None);
+    }
+    Ok(Some(b.start() <= a.start()))
+}
+
+/// The intervals of the first sub iterator containing, or contained by, an
+/// interval of the second one.
+struct ContainingFunction {
+    contained_by: bool,
+    // false once the second sub is exhausted
+    has_more: bool,
+    start: i32,
+    end: i32,
+    gaps: i32,
+}
+
+impl ContainingFunction {
+    fn new(contained_by: bool) -> ContainingFunction {
+        ContainingFunction {
+            contained_by,
+            has_more: true,
+            start: -1,
+            end: -1,
+            gaps: -1,
+        }
+    }
+}
+
+impl IntervalFunction for ContainingFunction {
+    fn reset(&mut self, _subs: &mut [Box<dyn IntervalIterator>]) -> Result<()> {
+        self.has_more = true;
+        self.start = -1;
+        self.end = -1;
+        self.gaps = -1;
+        Ok(())
+    }
+
+    fn next_interval(&mut self, subs: &mut [Box<dyn IntervalIterator>]) -> Result<i32> {
+        let (a, b) = subs.split_at_mut(1);
+        let (a, b) = (&mut a[0], &mut b[0]);
+        while self.has_more && a.next_interval()? != NO_MORE_INTERVALS {
+            let found = if self.contained_by {
+                contained_by(a.as_ref(), b.as_mut())?
+            } else {
+                contains(a.as_ref(), b.as_mut())?
+            };
+            match found {
+                Some(true) => {
+                    self.start = a.start();
+                    self.end = a.end();
+                    self.gaps = a.gaps();
+                    return Ok(self.start);
+                }
+                Some(false) => {}
+                None => self.has_more = false,
+            }
+        }
+        self.has_more = false;
+        self.start = NO_MORE_INTERVALS;
+        self.end = NO_MORE_INTERVALS;
+        self.gaps = NO_MORE_INTERVALS;
+        Ok(NO_MORE_INTERVALS)
+    }
+
+    fn start(&self) -> i32 {
+        self.start
+    }
+
+    fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn gaps(&self) -> i32 {
+        self.gaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::intervals::tests::{collect_intervals, MockIntervalIterator};
+
+    fn conjunction(subs: Vec<Vec<(i32, i32)>>, kind: ConjunctionKind) -> Box<dyn IntervalIterator> {
+        let subs = subs.into_iter().map(MockIntervalIterator::single).collect();
+        let mut iterator = conjunction_iterator(subs, kind);
+        assert_eq!(iterator.next().unwrap(), 0);
+        iterator
+    }
+
+    #[test]
+    fn test_conjunction_docs() {
+        let subs: Vec<Box<dyn IntervalIterator>> = vec![
+            Box::new(MockIntervalIterator::new(
+                vec![1, 3, 5, 8],
+                vec![vec![(0, 0)]; 4],
+            )),
+            Box::new(MockIntervalIterator::new(
+                vec![2, 3, 4, 8, 9],
+                vec![vec![(1, 1)]; 5],
+            )),
+        ];
+        let mut iterator = conjunction_iterator(subs, ConjunctionKind::Ordered);
+        assert_eq!(iterator.cost(), 4);
+        assert_eq!(iterator.next().unwrap(), 3);
+        assert_eq!(collect_intervals(iterator.as_mut()), vec![(0, 1)]);
+        assert_eq!(iterator.next().unwrap(), 8);
+        assert_eq!(iterator.next().unwrap(), NO_MORE_DOCS);
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+    }
+
+    #[test]
+    fn test_ordered() {
+        let mut iterator = conjunction(
+            vec![vec![(0, 0), (5, 5)], vec![(2, 2), (6, 6)]],
+            ConjunctionKind::Ordered,
+        );
+        assert_eq!(iterator.next_interval().unwrap(), 0);
+        assert_eq!((iterator.end(), iterator.gaps()), (2, 1));
+        assert_eq!(iterator.next_interval().unwrap(), 5);
+        assert_eq!((iterator.end(), iterator.gaps()), (6, 0));
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+
+        // "a b c" in "a a b a c b c"
+        let mut iterator = conjunction(
+            vec![
+                vec![(0, 0), (1, 1), (3, 3)],
+                vec![(2, 2), (5, 5)],
+                vec![(4, 4), (6, 6)],
+            ],
+            ConjunctionKind::Ordered,
+        );
+        assert_eq!(collect_intervals(iterator.as_mut()), vec![(1, 4), (3, 6)]);
+
+        // the same term twice
+        let mut iterator = conjunction(
+            vec![vec![(0, 0), (4, 4), (5, 5)], vec![(0, 0), (4, 4), (5, 5)]],
+            ConjunctionKind::Ordered,
+        );
+        assert_eq!(collect_intervals(iterator.as_mut()), vec![(0, 4), (4, 5)]);
+
+        // no match in order
+        let mut iterator = conjunction(vec![vec![(3, 3)], vec![(1, 1)]], ConjunctionKind::Ordered);
+        assert!(collect_intervals(iterator.as_mut()).is_empty());
+    }
+
+    #[test]
+    fn test_unordered() {
+        let mut iterator = conjunction(
+            vec![vec![(3, 3), (8, 8)], vec![(1, 1), (5, 5)]],
+            ConjunctionKind::Unordered,
+        );
+        assert_eq!(iterator.next_interval().unwrap(), 1);
+        assert_eq!((iterator.end(), iterator.gaps()), (3, 1));
+        assert_eq!(iterator.next_interval().unwrap(), 3);
+        assert_eq!((iterator.end(), iterator.gaps()), (5, 1));
+        assert_eq!(iterator.next_interval().unwrap(), 5);
+        assert_eq!((iterator.end(), iterator.gaps()), (8, 2));
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+
+        let mut iterator = conjunction(
+            vec![vec![(2, 2)], vec![(0, 0), (1, 1), (6, 6)], vec![(4, 4)]],
+            ConjunctionKind::Unordered,
+        );
+        assert_eq!(collect_intervals(iterator.as_mut()), vec![(1, 4), (2, 6)]);
+
+        let mut iterator = conjunction(vec![vec![(2, 2)], vec![]], ConjunctionKind::Unordered);
+        assert!(collect_intervals(iterator.as_mut()).is_empty());
+    }
+
+    #[test]
+    fn test_containing() {
+        let mut iterator = conjunction(
+            vec![vec![(0, 3), (2, 5), (6, 9)], vec![(1, 1), (7, 7)]],
+            ConjunctionKind::Containing,
+        );
+        assert_eq!(collect_intervals(iterator.as_mut()), vec![(0, 3), (6, 9)]);
+
+        let mut iterator = conjunction(
+            vec![vec![(1, 1), (4, 4), (7, 7)], vec![(0, 3), (6, 9)]],
+            ConjunctionKind::ContainedBy,
+        );
+        assert_eq!(collect_intervals(iterator.as_mut()), vec![(1, 1), (7, 7)]);
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::intervals::conjunction::{contained_by, contains};
+use core::search::query::intervals::NO_MORE_INTERVALS;
+use core::search::query::intervals::{IntervalIterator, IntervalsSource, IntervalsSourceRef};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+
+/// How a `DifferenceIntervalsSource` filters the intervals of its minuend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// The intervals not containing any interval of the subtrahend.
+    NotContaining,
+    /// The intervals not contained by any interval of the subtrahend.
+    NotContainedBy,
+}
+
+impl fmt::Display for DifferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DifferenceKind::NotContaining => write!(f, "NOT_CONTAINING"),
+            DifferenceKind::NotContainedBy => write!(f, "NOT_CONTAINED_BY"),
+        }
+    }
+}
+
+/// The intervals of a minuend source, filtered by the intervals of a
+/// subtrahend source in the same doc.
+pub struct DifferenceIntervalsSource<C: Codec> {
+    minuend: IntervalsSourceRef<C>,
+    subtrahend: IntervalsSourceRef<C>,
+    kind: DifferenceKind,
+}
+
+impl<C: Codec> DifferenceIntervalsSource<C> {
+    pub fn new(
+        minuend: IntervalsSourceRef<C>,
+        subtrahend: IntervalsSourceRef<C>,
+        kind: DifferenceKind,
+    ) -> DifferenceIntervalsSource<C> {
+        DifferenceIntervalsSource {
+            minuend,
+            subtrahend,
+            kind,
+        }
+    }
+}
+
+impl<C: Codec> IntervalsSource<C> for DifferenceIntervalsSource<C> {
+    fn intervals(
+        &self,
+        field: &str,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn IntervalIterator>>> {
+        let minuend = match self.minuend.intervals(field, ctx)? {
+            Some(minuend) => minuend,
+            None => return Ok(None),
+        };
+        match self.subtrahend.intervals(field, ctx)? {
+            Some(subtrahend) => Ok(Some(Box::new(DifferenceIntervalIterator::new(
+                minuend, subtrahend, self.kind,
+            )))),
+            None => Ok(Some(minuend)),
+        }
+    }
+
+    fn extract_terms(&self, field: &str, terms: &mut Vec<Term>) {
+        // only the minuend contributes to the matches
+        self.minuend.extract_terms(field, terms);
+    }
+}
+
+impl<C: Codec> fmt::Display for DifferenceIntervalsSource<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({},{})", self.kind, &self.minuend, &self.subtrahend)
+    }
+}
+
+/// Iterates the docs of the minuend, and its intervals the subtrahend doesn't
+/// filter out.
+struct DifferenceIntervalIterator {
+    minuend: Box<dyn IntervalIterator>,
+    subtrahend: Box<dyn IntervalIterator>,
+    kind: DifferenceKind,
+    // whether the subtrahend has intervals left in the current doc
+    has_subtrahend: bool,
+}
+
+impl DifferenceIntervalIterator {
+    fn new(
+        minuend: Box<dyn IntervalIterator>,
+        subtrahend: Box<dyn IntervalIterator>,
+        kind: DifferenceKind,
+    ) -> DifferenceIntervalIterator {
+        DifferenceIntervalIterator {
+            minuend,
+            subtrahend,
+            kind,
+            has_subtrahend: false,
+        }
+    }
+
+    fn reset(&mut self, doc: DocId) -> Result<DocId> {
+        self.has_subtrahend = doc != NO_MORE_DOCS
+            && (self.subtrahend.doc_id() == doc
+                || (self.subtrahend.doc_id() < doc && self.subtrahend.advance(doc)? == doc));
+        Ok(doc)
+    }
+}
+
+impl DocIterator for DifferenceIntervalIterator {
+    fn doc_id(&self) -> DocId {
+        self.minuend.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let doc = self.minuend.next()?;
+        self.reset(doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let doc = self.minuend.advance(target)?;
+        self.reset(doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.minuend.cost()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.minuend.match_cost() + self.subtrahend.match_cost()
+    }
+}
+
+impl IntervalIterator for DifferenceIntervalIterator {
+    fn start(&self) -> i32 {
+        self.minuend.start()
+    }
+
+    fn end(&self) -> i32 {
+        self.minuend.end()
+    }
+
+    fn gaps(&self) -> i32 {
+        self.minuend.gaps()
+    }
+
+    fn next_interval(&mut self) -> Result<i32> {
+        loop {
+            let start = self.minuend.next_interval()?;
+            if start == NO_MORE_INTERVALS || !self.has_subtrahend {
+                return Ok(start);
+            }
+            let found = match self.kind {
+                DifferenceKind::NotContaining => {
+                    contains(self.minuend.as_ref(), self.subtrahend.as_mut())?
+                }
+                DifferenceKind::NotContainedBy => {
+                    contained_by(self.minuend.as_ref(), self.subtrahend.as_mut())?
+                }
+            };
+            match found {
+                Some(true) => {}
+                Some(false) => return Ok(start),
+                None => {
+                    self.has_subtrahend = false;
+                    return Ok(start);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::intervals::tests::{collect_intervals, MockIntervalIterator};
+
+    #[test]
+    fn test_not_containing() {
+        let mut iterator = DifferenceIntervalIterator::new(
+            MockIntervalIterator::single(vec![(0, 3), (2, 5), (6, 9)]),
+            MockIntervalIterator::single(vec![(1, 1), (7, 7)]),
+            DifferenceKind::NotContaining,
+        );
+        assert_eq!(iterator.next().unwrap(), 0);
+        assert_eq!(collect_intervals(&mut iterator), vec![(2, 5)]);
+
+        let mut iterator = DifferenceIntervalIterator::new(
+            MockIntervalIterator::single(vec![(0, 3), (2, 5), (6, 9)]),
+            MockIntervalIterator::single(vec![(1, 1)]),
+            DifferenceKind::NotContaining,
+        );
+        assert_eq!(iterator.next().unwrap(), 0);
+        assert_eq!(collect_intervals(&mut iterator), vec![(2, 5), (6, 9)]);
+    }
+
+    #[test]
+    fn test_not_contained_by() {
+        let mut iterator = DifferenceIntervalIterator::new(
+            MockIntervalIterator::single(vec![(1, 1), (4, 4), (7, 7)]),
+            MockIntervalIterator::single(vec![(0, 3), (6, 9)]),
+            DifferenceKind::NotContainedBy,
+        );
+        assert_eq!(iterator.next().unwrap(), 0);
+        assert_eq!(collect_intervals(&mut iterator), vec![(4, 4)]);
+    }
+
+    #[test]
+    fn test_difference_docs() {
+        // the subtrahend only filters the docs it is on
+        let mut iterator = DifferenceIntervalIterator::new(
+            Box::new(MockIntervalIterator::new(
+                vec![1, 4, 6],
+                vec![vec![(2, 2)], vec![(2, 2)], vec![(2, 2)]],
+            )),
+            Box::new(MockIntervalIterator::new(
+                vec![3, 4],
+                vec![vec![(1, 3)], vec![(1, 3)]],
+            )),
+            DifferenceKind::NotContainedBy,
+        );
+        assert_eq!(iterator.cost(), 3);
+        assert_eq!(iterator.next().unwrap(), 1);
+        assert_eq!(collect_intervals(&mut iterator), vec![(2, 2)]);
+        assert_eq!(iterator.next().unwrap(), 4);
+        assert!(collect_intervals(&mut iterator).is_empty());
+        assert_eq!(iterator.next().unwrap(), 6);
+        assert_eq!(collect_intervals(&mut iterator), vec![(2, 2)]);
+        assert_eq!(iterator.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::intervals::{fmt_sources, IntervalsSourceRef};
+use core::search::query::intervals::{IntervalIterator, IntervalsSource, NO_MORE_INTERVALS};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+
+/// The intervals of any of the sources.
+pub struct DisjunctionIntervalsSource<C: Codec> {
+    sources: Vec<IntervalsSourceRef<C>>,
+}
+
+impl<C: Codec> DisjunctionIntervalsSource<C> {
+    pub fn new(sources: Vec<IntervalsSourceRef<C>>) -> DisjunctionIntervalsSource<C> {
+        DisjunctionIntervalsSource { sources }
+    }
+}
+
+impl<C: Codec> IntervalsSource<C> for DisjunctionIntervalsSource<C> {
+    fn intervals(
+        &self,
+        field: &str,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn IntervalIterator>>> {
+        let mut subs = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            if let Some(sub) = source.intervals(field, ctx)? {
+                subs.push(sub);
+            }
+        }
+        match subs.len() {
+            0 => Ok(None),
+            1 => Ok(subs.pop()),
+            _ => Ok(Some(Box::new(DisjunctionIntervalIterator::new(subs)))),
+        }
+    }
+
+    fn extract_terms(&self, field: &str, terms: &mut Vec<Term>) {
+        for source in &self.sources {
+            source.extract_terms(field, terms);
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for DisjunctionIntervalsSource<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_sources(f, "OR", &self.sources)
+    }
+}
+
+/// Iterates the docs of any of the sub iterators, and merges their intervals
+/// by start then end. An interval found by several subs is returned once.
+struct DisjunctionIntervalIterator {
+    subs: Vec<Box<dyn IntervalIterator>>,
+    doc: DocId,
+    // the subs on the current doc having intervals left
+    queue: Vec<usize>,
+    // the sub of the current interval
+    current: Option<usize>,
+    start: i32,
+    end: i32,
+    gaps: i32,
+    cost: usize,
+    match_cost: f32,
+}
+
+impl DisjunctionIntervalIterator {
+    fn new(subs: Vec<Box<dyn IntervalIterator>>) -> DisjunctionIntervalIterator {
+        let cost = subs.iter().map(|s| s.cost()).sum();
+        let match_cost = subs.iter().map(|s| s.match_cost()).sum();
+        let queue = Vec::with_capacity(subs.len());
+        DisjunctionIntervalIterator {
+            subs,
+            doc: -1,
+            queue,
+            current: None,
+            start: -1,
+            end: -1,
+            gaps: -1,
+            cost,
+            match_cost,
+        }
+    }
+
+    fn reset(&mut self) -> Result<DocId> {
+        self.doc = self
+            .subs
+            .iter()
+            .map(|s| s.doc_id())
+            .min()
+            .unwrap_or(NO_MORE_DOCS);
+        self.queue.clear();
+        self.current = None;
+        self.start = -1;
+        self.end = -1;
+        self.gaps = -1;
+        if self.doc != NO_MORE_DOCS {
+            for (i, sub) in self.subs.iter_mut().enumerate() {
+                if sub.doc_id() == self.doc && sub.next_interval()? != NO_MORE_INTERVALS {
+                    self.queue.push(i);
+                }
+            }
+        }
+        Ok(self.doc)
+    }
+}
+
+impl DocIterator for DisjunctionIntervalIterator {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.advance(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        for sub in &mut self.subs {
+            if sub.doc_id() < target {
+                sub.advance(target)?;
+            }
+        }
+        self.reset()
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.match_cost
+    }
+}
+
+impl IntervalIterator for DisjunctionIntervalIterator {
+    fn start(&self) -> i32 {
+        self.start
+    }
+
+    fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn gaps(&self) -> i32 {
+        self.gaps
+    }
+
+    fn next_interval(&mut self) -> Result<i32> {
+        if self.current.is_some() {
+            // move all the subs past the current interval
+            let mut k = 0;
+            while k < self.queue.len() {
+                let sub = &mut self.subs[self.queue[k]];
+                if sub.start() == self.start
+                    && sub.end() == self.end
+                    && sub.next_interval()? == NO_MORE_INTERVALS
+                {
+                    self.queue.swap_remove(k);
+                } else {
+                    k += 1;
+                }
+            }
+        }
+        let subs = &self.subs;
+        self.current = self.queue.iter().cloned().min_by_key(|&i| {
+            let sub = &subs[i];
+            (sub.start(), sub.end())
+        });
+        if let Some(i) = self.current {
+            self.start = self.subs[i].start();
+            self.end = self.subs[i].end();
+            self.gaps = self.subs[i].gaps();
+        } else {
+            self.start = NO_MORE_INTERVALS;
+            self.end = NO_MORE_INTERVALS;
+            self.gaps = NO_MORE_INTERVALS;
+        }
+        Ok(self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::intervals::tests::{collect_intervals, MockIntervalIterator};
+
+    #[test]
+    fn test_disjunction() {
+        let mut iterator = DisjunctionIntervalIterator::new(vec![
+            Box::new(MockIntervalIterator::new(
+                vec![1, 3],
+                vec![vec![(0, 0), (4, 6)], vec![(2, 2)]],
+            )),
+            Box::new(MockIntervalIterator::new(
+                vec![3, 5],
+                vec![vec![(1, 1), (2, 2), (3, 5)], vec![]],
+            )),
+        ]);
+        assert_eq!(iterator.cost(), 4);
+        assert_eq!(iterator.next().unwrap(), 1);
+        assert_eq!(collect_intervals(&mut iterator), vec![(0, 0), (4, 6)]);
+        assert_eq!(iterator.next().unwrap(), 3);
+        assert_eq!(iterator.start(), -1);
+        assert_eq!(
+            collect_intervals(&mut iterator),
+            vec![(1, 1), (2, 2), (3, 5)]
+        );
+        assert_eq!(iterator.next().unwrap(), 5);
+        assert!(collect_intervals(&mut iterator).is_empty());
+        assert_eq!(iterator.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::intervals::NO_MORE_INTERVALS;
+use core::search::query::intervals::{IntervalIterator, IntervalsSource, IntervalsSourceRef};
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::Result;
+
+/// The intervals of a source having at most `max_gaps` positions between their
+/// sub intervals.
+pub struct MaxGapsIntervalsSource<C: Codec> {
+    source: IntervalsSourceRef<C>,
+    max_gaps: i32,
+}
+
+impl<C: Codec> MaxGapsIntervalsSource<C> {
+    pub fn new(source: IntervalsSourceRef<C>, max_gaps: i32) -> MaxGapsIntervalsSource<C> {
+        MaxGapsIntervalsSource { source, max_gaps }
+    }
+}
+
+impl<C: Codec> IntervalsSource<C> for MaxGapsIntervalsSource<C> {
+    fn intervals(
+        &self,
+        field: &str,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn IntervalIterator>>> {
+        Ok(self
+            .source
+            .intervals(field, ctx)?
+            .map(|iterator| -> Box<dyn IntervalIterator> {
+                Box::new(MaxGapsIntervalIterator {
+                    iterator,
+                    max_gaps: self.max_gaps,
+                })
+            }))
+    }
+
+    fn extract_terms(&self, field: &str, terms: &mut Vec<Term>) {
+        self.source.extract_terms(field, terms);
+    }
+}
+
+impl<C: Codec> fmt::Display for MaxGapsIntervalsSource<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MAXGAPS/{}({})", self.max_gaps, &self.source)
+    }
+}
+
+struct MaxGapsIntervalIterator {
+    iterator: Box<dyn IntervalIterator>,
+    max_gaps: i32,
+}
+
+impl DocIterator for MaxGapsIntervalIterator {
+    fn doc_id(&self) -> DocId {
+        self.iterator.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.iterator.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.iterator.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.iterator.cost()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.iterator.match_cost()
+    }
+}
+
+impl IntervalIterator for MaxGapsIntervalIterator {
+    fn start(&self) -> i32 {
+        self.iterator.start()
+    }
+
+    fn end(&self) -> i32 {
+        self.iterator.end()
+    }
+
+    fn gaps(&self) -> i32 {
+        self.iterator.gaps()
+    }
+
+    fn next_interval(&mut self) -> Result<i32> {
+        loop {
+            let start = self.iterator.next_interval()?;
+            if start == NO_MORE_INTERVALS || self.iterator.gaps() <= self.max_gaps {
+                return Ok(start);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Intervals of doc 0 as `(start, end, gaps)`.
+    struct GapsIntervalIterator {
+        intervals: Vec<(i32, i32, i32)>,
+        upto: usize,
+    }
+
+    impl GapsIntervalIterator {
+        fn current(&self) -> (i32, i32, i32) {
+            match self.upto {
+                0 => (-1, -1, -1),
+                i if i <= self.intervals.len() => self.intervals[i - 1],
+                _ => (NO_MORE_INTERVALS, NO_MORE_INTERVALS, NO_MORE_INTERVALS),
+            }
+        }
+    }
+
+    impl DocIterator for GapsIntervalIterator {
+        fn doc_id(&self) -> DocId {
+            0
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            unimplemented!()
+        }
+
+        fn advance(&mut self, _target: DocId) -> Result<DocId> {
+            unimplemented!()
+        }
+
+        fn cost(&self) -> usize {
+            1
+        }
+    }
+
+    impl IntervalIterator for GapsIntervalIterator {
+        fn start(&self) -> i32 {
+            self.current().0
+        }
+
+        fn end(&self) -> i32 {
+            self.current().1
+        }
+
+        fn gaps(&self) -> i32 {
+            self.current().2
+        }
+
+        fn next_interval(&mut self) -> Result<i32> {
+            if self.upto <= self.intervals.len() {
+                self.upto += 1;
+            }
+            Ok(self.start())
+        }
+    }
+
+    #[test]
+    fn test_max_gaps() {
+        let mut iterator = MaxGapsIntervalIterator {
+            iterator: Box::new(GapsIntervalIterator {
+                intervals: vec![(0, 4, 3), (2, 4, 1), (5, 6, 0), (7, 12, 4)],
+                upto: 0,
+            }),
+            max_gaps: 1,
+        };
+        assert_eq!(iterator.next_interval().unwrap(), 2);
+        assert_eq!((iterator.end(), iterator.gaps()), (4, 1));
+        assert_eq!(iterator.next_interval().unwrap(), 5);
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::intervals::NO_MORE_INTERVALS;
+use core::search::query::intervals::{IntervalIterator, IntervalsSource, IntervalsSourceRef};
+use core::search::query::spans::build_sim_weight;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{two_phase_next, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::{SimScorer, SimWeight};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+
+pub const INTERVAL: &str = "interval";
+
+/// A query that matches the docs of a field having intervals from an
+/// `IntervalsSource`.
+///
+/// The score is computed by the similarity of the field from a sloppy freq,
+/// each interval adds the inverse of its width: narrow intervals weigh more.
+pub struct IntervalQuery<C: Codec> {
+    field: String,
+    source: Arc<dyn IntervalsSource<C>>,
+}
+
+impl<C: Codec> IntervalQuery<C> {
+    pub fn new(field: &str, source: IntervalsSourceRef<C>) -> IntervalQuery<C> {
+        IntervalQuery {
+            field: field.to_string(),
+            source: Arc::from(source),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn source(&self) -> &dyn IntervalsSource<C> {
+        self.source.as_ref()
+    }
+}
+
+impl<C: Codec> Query<C> for IntervalQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let sim_weight = if needs_scores {
+            let mut terms = vec![];
+            self.source.extract_terms(&self.field, &mut terms);
+            let mut term_contexts = HashMap::with_capacity(terms.len());
+            for term in terms {
+                let term_context = searcher.term_state(&term)?;
+                term_contexts.insert(term, term_context);
+            }
+            build_sim_weight(&self.field, searcher, term_contexts, None)?
+        } else {
+            None
+        };
+        Ok(Box::new(IntervalWeight {
+            field: self.field.clone(),
+            source: Arc::clone(&self.source),
+            sim_weight,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        let mut terms = vec![];
+        self.source.extract_terms(&self.field, &mut terms);
+        terms
+            .into_iter()
+            .map(|term| TermQuery::new(term, 1.0, None))
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for IntervalQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IntervalQuery(field: {}, source: {})",
+            &self.field, &self.source
+        )
+    }
+}
+
+struct IntervalWeight<C: Codec> {
+    field: String,
+    source: Arc<dyn IntervalsSource<C>>,
+    sim_weight: Option<Box<dyn SimWeight<C>>>,
+    needs_scores: bool,
+}
+
+impl<C: Codec> IntervalWeight<C> {
+    fn interval_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<IntervalScorer>> {
+        if let Some(intervals) = self.source.intervals(&self.field, reader)? {
+            let doc_scorer = match self.sim_weight {
+                Some(ref sim_weight) => Some(sim_weight.sim_scorer(reader.reader)?),
+                None => None,
+            };
+            Ok(Some(IntervalScorer::new(intervals, doc_scorer)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: Codec> Weight<C> for IntervalWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        Ok(self
+            .interval_scorer(reader)?
+            .map(|scorer| -> Box<dyn Scorer> { Box::new(scorer) }))
+    }
+
+    fn query_type(&self) -> &'static str {
+        INTERVAL
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        if let Some(ref mut sim_weight) = self.sim_weight {
+            sim_weight.normalize(norm, boost);
+        }
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        match self.sim_weight {
+            Some(ref sim_weight) => sim_weight.get_value_for_normalization(),
+            None => 1.0,
+        }
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        if let Some(mut scorer) = self.interval_scorer(reader)? {
+            if scorer.advance(doc)? == doc {
+                scorer.ensure_freq()?;
+                let freq = scorer.freq;
+                let freq_expl =
+                    Explanation::new(true, freq, format!("intervalFreq={}", freq), vec![]);
+                return match self.sim_weight {
+                    Some(ref sim_weight) => {
+                        let score_expl = sim_weight.explain(reader.reader, doc, freq_expl)?;
+                        Ok(Explanation::new(
+                            true,
+                            score_expl.value(),
+                            format!("weight({} in {}), result of:", self, doc),
+                            vec![score_expl],
+                        ))
+                    }
+                    None => Ok(Explanation::new(
+                        true,
+                        freq,
+                        format!("{} matches doc {}", self, doc),
+                        vec![freq_expl],
+                    )),
+                };
+            }
+        }
+        Ok(Explanation::new(
+            false,
+            0f32,
+            format!("{} has no intervals in doc {}", self, doc),
+            vec![],
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for IntervalWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IntervalWeight(field: {}, source: {})",
+            &self.field, &self.source
+        )
+    }
+}
+
+/// Matches the docs having at least one interval, the iteration over the docs
+/// of the intervals is the approximation.
+struct IntervalScorer {
+    intervals: Box<dyn IntervalIterator>,
+    doc_scorer: Option<Box<dyn SimScorer>>,
+    // the sloppy freq of `last_scored_doc`
+    freq: f32,
+    last_scored_doc: DocId,
+}
+
+impl IntervalScorer {
+    fn new(
+        intervals: Box<dyn IntervalIterator>,
+        doc_scorer: Option<Box<dyn SimScorer>>,
+    ) -> IntervalScorer {
+        IntervalScorer {
+            intervals,
+            doc_scorer,
+            freq: 0f32,
+            last_scored_doc: -1,
+        }
+    }
+
+    // the current interval was found by `matches`, the remaining ones are
+    // consumed
+    fn ensure_freq(&mut self) -> Result<()> {
+        let doc = self.intervals.doc_id();
+        if self.last_scored_doc != doc {
+            self.last_scored_doc = doc;
+            self.freq = 0f32;
+            while self.intervals.start() != NO_MORE_INTERVALS {
+                self.freq += 1.0 / self.intervals.width() as f32;
+                self.intervals.next_interval()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Scorer for IntervalScorer {
+    fn score(&mut self) -> Result<f32> {
+        self.ensure_freq()?;
+        let doc = self.intervals.doc_id();
+        match self.doc_scorer {
+            Some(ref mut doc_scorer) => doc_scorer.score(doc, self.freq),
+            None => Ok(self.freq),
+        }
+    }
+}
+
+impl DocIterator for IntervalScorer {
+    fn doc_id(&self) -> DocId {
+        self.intervals.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.approximate_next()?;
+        two_phase_next(self)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximate_advance(target)?;
+        two_phase_next(self)
+    }
+
+    fn cost(&self) -> usize {
+        self.intervals.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        if self.intervals.doc_id() == NO_MORE_DOCS {
+            return Ok(false);
+        }
+        Ok(self.intervals.next_interval()? != NO_MORE_INTERVALS)
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.intervals.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.intervals.next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.intervals.advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::search::query::intervals;
+    use core::search::query::intervals::tests::MockIntervalIterator;
+
+    struct FreqSimScorer;
+
+    impl SimScorer for FreqSimScorer {
+        fn score(&mut self, _doc: DocId, freq: f32) -> Result<f32> {
+            Ok(freq * 2.0)
+        }
+
+        fn compute_slop_factor(&self, _distance: i32) -> f32 {
+            1.0
+        }
+    }
+
+    #[test]
+    fn test_interval_scorer() {
+        let intervals = MockIntervalIterator::new(
+            vec![1, 4, 6, 9],
+            vec![vec![(0, 0), (3, 6)], vec![], vec![(2, 3)], vec![(0, 4)]],
+        );
+        let mut scorer = IntervalScorer::new(Box::new(intervals), Some(Box::new(FreqSimScorer)));
+        assert!(scorer.support_two_phase());
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 2.5).abs() < ::std::f32::EPSILON);
+        // the freq is computed once
+        assert!((scorer.score().unwrap() - 2.5).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 6);
+        assert!((scorer.score().unwrap() - 1.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(7).unwrap(), 9);
+        assert!((scorer.score().unwrap() - 0.4).abs() < 1e-6);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_interval_query() {
+        let source = intervals::max_gaps(
+            intervals::ordered(vec![
+                intervals::term("quick"),
+                intervals::or(vec![intervals::term("brown"), intervals::term("red")]),
+                intervals::term("fox"),
+            ]),
+            2,
+        );
+        let query: IntervalQuery<TestCodec> = IntervalQuery::new("title", source);
+        assert_eq!(query.field(), "title");
+        assert_eq!(
+            query.to_string(),
+            "IntervalQuery(field: title, source: MAXGAPS/2(ORDERED(quick,OR(brown,red),fox)))"
+        );
+        let terms: Vec<_> = Query::<TestCodec>::extract_terms(&query)
+            .into_iter()
+            .map(|t| t.term.text().unwrap())
+            .collect();
+        assert_eq!(terms, vec!["quick", "brown", "red", "fox"]);
+
+        let source = intervals::not_containing(
+            intervals::unordered(vec![intervals::term("a"), intervals::term("b")]),
+            intervals::term("c"),
+        );
+        let query: IntervalQuery<TestCodec> = IntervalQuery::new("title", source);
+        assert_eq!(
+            query.source().to_string(),
+            "NOT_CONTAINING(UNORDERED(a,b),c)"
+        );
+    }
+}
@@ -0,0 +1,327 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Intervals are a modern alternative to span queries for fine-grained
+//! proximity matching.
+//!
+//! An `IntervalsSource` describes how to find the intervals of positions of a
+//! field that match, and the functions of this module combine them: the
+//! intervals of `ordered` contain all the sub sources in order, the ones of
+//! `unordered` contain them in any order, `max_gaps` filters out the intervals
+//! with too many positions between their sub intervals, and `containing`,
+//! `not_containing`, `contained_by` and `not_contained_by` filter intervals
+//! depending on the intervals of another source.
+//!
+//! `ordered` and `unordered` build minimal intervals, that don't contain any
+//! other matching interval. An `IntervalQuery` matches the docs of a field
+//! having at least one interval, and the narrower the intervals the higher the
+//! score.
+
+mod conjunction;
+
+pub use self::conjunction::*;
+
+mod difference;
+
+pub use self::difference::*;
+
+mod disjunction;
+
+pub use self::disjunction::*;
+
+mod filtered;
+
+pub use self::filtered::*;
+
+mod interval_query;
+
+pub use self::interval_query::*;
+
+mod term;
+
+pub use self::term::*;
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::DocIterator;
+
+use error::Result;
+
+/// Returned by `IntervalIterator::next_interval` once the intervals of the
+/// current doc are exhausted.
+pub const NO_MORE_INTERVALS: i32 = i32::max_value();
+
+/// Iterates the docs of a field matching an `IntervalsSource`, and the intervals
+/// of positions of each doc.
+///
+/// The doc iteration is an approximation: `next_interval` may return
+/// `NO_MORE_INTERVALS` right away on a doc. Once positioned on a doc, `start`
+/// and `end` are -1 until `next_interval` is called. The intervals of a doc are
+/// returned by increasing start, both `start` and `end` are inclusive.
+///
+/// `match_cost` is an estimate of the cost of finding the intervals of a doc.
+pub trait IntervalIterator: DocIterator {
+    /// The start of the current interval.
+    fn start(&self) -> i32;
+
+    /// The end of the current interval.
+    fn end(&self) -> i32;
+
+    /// The number of positions between the sub intervals of the current
+    /// interval.
+    fn gaps(&self) -> i32;
+
+    /// Advances to the next interval of the current doc and returns its start,
+    /// or `NO_MORE_INTERVALS`.
+    fn next_interval(&mut self) -> Result<i32>;
+
+    /// The number of positions covered by the current interval.
+    fn width(&self) -> i32 {
+        self.end() - self.start() + 1
+    }
+}
+
+/// Describes how to build the intervals of a field for a segment.
+pub trait IntervalsSource<C: Codec>: fmt::Display {
+    /// The intervals of `field` in a segment, `None` if no doc can match.
+    fn intervals(
+        &self,
+        field: &str,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn IntervalIterator>>>;
+
+    /// Collects the terms of `field` this source reads, for scoring.
+    fn extract_terms(&self, field: &str, terms: &mut Vec<Term>);
+}
+
+pub type IntervalsSourceRef<C> = Box<dyn IntervalsSource<C>>;
+
+/// The intervals of a single term.
+pub fn term<C: Codec>(term: &str) -> IntervalsSourceRef<C> {
+    Box::new(TermIntervalsSource::new(term.as_bytes().to_vec()))
+}
+
+/// The intervals of any of the sources.
+pub fn or<C: Codec>(sources: Vec<IntervalsSourceRef<C>>) -> IntervalsSourceRef<C> {
+    Box::new(DisjunctionIntervalsSource::new(sources))
+}
+
+/// The minimal intervals containing the intervals of all the sources in order,
+/// without overlap.
+pub fn ordered<C: Codec>(sources: Vec<IntervalsSourceRef<C>>) -> IntervalsSourceRef<C> {
+    Box::new(ConjunctionIntervalsSource::new(
+        sources,
+        ConjunctionKind::Ordered,
+    ))
+}
+
+/// The minimal intervals containing the intervals of all the sources in any
+/// order.
+pub fn unordered<C: Codec>(sources: Vec<IntervalsSourceRef<C>>) -> IntervalsSourceRef<C> {
+    Box::new(ConjunctionIntervalsSource::new(
+        sources,
+        ConjunctionKind::Unordered,
+    ))
+}
+
+/// The intervals of `source` having at most `max_gaps` positions between their
+/// sub intervals.
+pub fn max_gaps<C: Codec>(source: IntervalsSourceRef<C>, max_gaps: i32) -> IntervalsSourceRef<C> {
+    Box::new(MaxGapsIntervalsSource::new(source, max_gaps))
+}
+
+/// The intervals of `big` containing an interval of `small`.
+pub fn containing<C: Codec>(
+    big: IntervalsSourceRef<C>,
+    small: IntervalsSourceRef<C>,
+) -> IntervalsSourceRef<C> {
+    Box::new(ConjunctionIntervalsSource::new(
+        vec![big, small],
+        ConjunctionKind::Containing,
+    ))
+}
+
+/// The intervals of `small` contained by an interval of `big`.
+pub fn contained_by<C: Codec>(
+    small: IntervalsSourceRef<C>,
+    big: IntervalsSourceRef<C>,
+) -> IntervalsSourceRef<C> {
+    Box::new(ConjunctionIntervalsSource::new(
+        vec![small, big],
+        ConjunctionKind::ContainedBy,
+    ))
+}
+
+/// The intervals of `minuend` that don't contain any interval of `subtrahend`.
+pub fn not_containing<C: Codec>(
+    minuend: IntervalsSourceRef<C>,
+    subtrahend: IntervalsSourceRef<C>,
+) -> IntervalsSourceRef<C> {
+    Box::new(DifferenceIntervalsSource::new(
+        minuend,
+        subtrahend,
+        DifferenceKind::NotContaining,
+    ))
+}
+
+/// The intervals of `minuend` that aren't contained by any interval of
+/// `subtrahend`.
+pub fn not_contained_by<C: Codec>(
+    minuend: IntervalsSourceRef<C>,
+    subtrahend: IntervalsSourceRef<C>,
+) -> IntervalsSourceRef<C> {
+    Box::new(DifferenceIntervalsSource::new(
+        minuend,
+        subtrahend,
+        DifferenceKind::NotContainedBy,
+    ))
+}
+
+/// Formats sources as `name(a,b)`.
+fn fmt_sources<C: Codec>(
+    f: &mut fmt::Formatter,
+    name: &str,
+    sources: &[IntervalsSourceRef<C>],
+) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    for (i, source) in sources.iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", source)?;
+    }
+    write!(f, ")")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use core::search::NO_MORE_DOCS;
+    use core::util::DocId;
+
+    /// An `IntervalIterator` over `docs`, whose intervals are the ones at the
+    /// same index in `intervals`.
+    pub struct MockIntervalIterator {
+        docs: Vec<DocId>,
+        intervals: Vec<Vec<(i32, i32)>>,
+        // index of the current doc, `docs.len()` once exhausted
+        index: usize,
+        doc: DocId,
+        // index of the current interval + 1, 0 when unpositioned
+        upto: usize,
+    }
+
+    impl MockIntervalIterator {
+        pub fn new(docs: Vec<DocId>, intervals: Vec<Vec<(i32, i32)>>) -> MockIntervalIterator {
+            assert_eq!(docs.len(), intervals.len());
+            MockIntervalIterator {
+                docs,
+                intervals,
+                index: 0,
+                doc: -1,
+                upto: 0,
+            }
+        }
+
+        /// A single doc 0 with the given intervals.
+        pub fn single(intervals: Vec<(i32, i32)>) -> Box<dyn IntervalIterator> {
+            Box::new(MockIntervalIterator::new(vec![0], vec![intervals]))
+        }
+
+        fn current(&self) -> Option<(i32, i32)> {
+            if self.upto == 0 {
+                Some((-1, -1))
+            } else {
+                self.intervals[self.index].get(self.upto - 1).cloned()
+            }
+        }
+    }
+
+    impl DocIterator for MockIntervalIterator {
+        fn doc_id(&self) -> DocId {
+            self.doc
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            if self.doc != -1 {
+                self.index += 1;
+            }
+            self.upto = 0;
+            self.doc = if self.index < self.docs.len() {
+                self.docs[self.index]
+            } else {
+                NO_MORE_DOCS
+            };
+            Ok(self.doc)
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            loop {
+                let doc = self.next()?;
+                if doc >= target {
+                    return Ok(doc);
+                }
+            }
+        }
+
+        fn cost(&self) -> usize {
+            self.docs.len()
+        }
+    }
+
+    impl IntervalIterator for MockIntervalIterator {
+        fn start(&self) -> i32 {
+            self.current().map_or(NO_MORE_INTERVALS, |i| i.0)
+        }
+
+        fn end(&self) -> i32 {
+            self.current().map_or(NO_MORE_INTERVALS, |i| i.1)
+        }
+
+        fn gaps(&self) -> i32 {
+            0
+        }
+
+        fn next_interval(&mut self) -> Result<i32> {
+            if self.current().is_some() {
+                self.upto += 1;
+            }
+            Ok(self.start())
+        }
+    }
+
+    /// Collects the remaining intervals of the current doc.
+    pub fn collect_intervals(iterator: &mut dyn IntervalIterator) -> Vec<(i32, i32)> {
+        let mut intervals = vec![];
+        while iterator.next_interval().unwrap() != NO_MORE_INTERVALS {
+            intervals.push((iterator.start(), iterator.end()));
+        }
+        intervals
+    }
+
+    #[test]
+    fn test_mock_interval_iterator() {
+        let mut iterator =
+            MockIntervalIterator::new(vec![1, 3], vec![vec![(0, 0), (2, 4)], vec![]]);
+        assert_eq!(iterator.next().unwrap(), 1);
+        assert_eq!(iterator.start(), -1);
+        assert_eq!(collect_intervals(&mut iterator), vec![(0, 0), (2, 4)]);
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+        assert_eq!(iterator.next().unwrap(), 3);
+        assert!(collect_intervals(&mut iterator).is_empty());
+        assert_eq!(iterator.next().unwrap(), NO_MORE_DOCS);
+    }
+}
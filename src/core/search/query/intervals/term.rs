@@ -0,0 +1,203 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::{Codec, PostingIterator, PostingIteratorFlags};
+use core::codec::{TermIterator, Terms};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::intervals::{IntervalIterator, IntervalsSource, NO_MORE_INTERVALS};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::{ErrorKind, Result};
+
+/// The same costs as `PhraseQuery`, used for the match cost.
+const TERM_POSNS_SEEK_OPS_PER_DOC: i32 = 128;
+const TERM_OPS_PER_POS: i32 = 7;
+
+/// The intervals of a term, one per position.
+pub struct TermIntervalsSource {
+    term: Vec<u8>,
+}
+
+impl TermIntervalsSource {
+    pub fn new(term: Vec<u8>) -> TermIntervalsSource {
+        TermIntervalsSource { term }
+    }
+
+    fn term_positions_cost(term_iter: &mut impl TermIterator) -> Result<f32> {
+        let doc_freq = term_iter.doc_freq()?;
+        debug_assert!(doc_freq > 0);
+        let total_term_freq = term_iter.total_term_freq()?; // -1 when not available
+        let exp_occurrences_in_matching_doc = if total_term_freq < i64::from(doc_freq) {
+            1.0f32
+        } else {
+            total_term_freq as f32 / doc_freq as f32
+        };
+        Ok(TERM_POSNS_SEEK_OPS_PER_DOC as f32
+            + exp_occurrences_in_matching_doc * TERM_OPS_PER_POS as f32)
+    }
+}
+
+impl<C: Codec> IntervalsSource<C> for TermIntervalsSource {
+    fn intervals(
+        &self,
+        field: &str,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn IntervalIterator>>> {
+        let terms = match ctx.reader.terms(field)? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+        if !terms.has_positions()? {
+            bail!(ErrorKind::IllegalState(format!(
+                "field '{}' was indexed without position data; cannot run an interval query \
+                 (term={})",
+                field, self
+            )));
+        }
+        let mut terms_iter = terms.iterator()?;
+        if !terms_iter.seek_exact(&self.term)? {
+            return Ok(None);
+        }
+        let positions_cost = Self::term_positions_cost(&mut terms_iter)?;
+        let postings = terms_iter.postings_with_flags(PostingIteratorFlags::POSITIONS)?;
+        Ok(Some(Box::new(TermIntervalIterator::new(
+            postings,
+            positions_cost,
+        ))))
+    }
+
+    fn extract_terms(&self, field: &str, terms: &mut Vec<Term>) {
+        terms.push(Term::new(field.to_string(), self.term.clone()));
+    }
+}
+
+impl fmt::Display for TermIntervalsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.term))
+    }
+}
+
+/// Iterates the positions of a term as intervals of width 1.
+pub struct TermIntervalIterator<T: PostingIterator> {
+    postings: T,
+    freq: i32,
+    // number of positions read in the current doc
+    upto: i32,
+    position: i32,
+    positions_cost: f32,
+}
+
+impl<T: PostingIterator> TermIntervalIterator<T> {
+    pub fn new(postings: T, positions_cost: f32) -> TermIntervalIterator<T> {
+        TermIntervalIterator {
+            postings,
+            freq: 0,
+            upto: 0,
+            position: -1,
+            positions_cost,
+        }
+    }
+
+    fn reset(&mut self, doc: DocId) -> Result<DocId> {
+        self.freq = if doc == NO_MORE_DOCS {
+            0
+        } else {
+            self.postings.freq()?
+        };
+        self.upto = 0;
+        self.position = -1;
+        Ok(doc)
+    }
+}
+
+impl<T: PostingIterator> DocIterator for TermIntervalIterator<T> {
+    fn doc_id(&self) -> DocId {
+        self.postings.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let doc = self.postings.next()?;
+        self.reset(doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let doc = self.postings.advance(target)?;
+        self.reset(doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.postings.cost()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.positions_cost
+    }
+}
+
+impl<T: PostingIterator> IntervalIterator for TermIntervalIterator<T> {
+    fn start(&self) -> i32 {
+        self.position
+    }
+
+    fn end(&self) -> i32 {
+        self.position
+    }
+
+    fn gaps(&self) -> i32 {
+        0
+    }
+
+    fn next_interval(&mut self) -> Result<i32> {
+        self.position = if self.upto == self.freq {
+            NO_MORE_INTERVALS
+        } else {
+            self.upto += 1;
+            self.postings.next_position()?
+        };
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::intervals::tests::collect_intervals;
+    use core::search::tests::MockPostingIterator;
+
+    #[test]
+    fn test_term_interval_iterator() {
+        let postings = MockPostingIterator::with_positions(
+            vec![2, 5, 9],
+            vec![vec![1, 4], vec![0], vec![3, 7, 8]],
+        );
+        let mut iterator = TermIntervalIterator::new(postings, 10.0);
+        assert_eq!(iterator.next().unwrap(), 2);
+        assert_eq!(iterator.start(), -1);
+        assert_eq!(iterator.next_interval().unwrap(), 1);
+        assert_eq!(
+            (iterator.start(), iterator.end(), iterator.width()),
+            (1, 1, 1)
+        );
+        assert_eq!(collect_intervals(&mut iterator), vec![(4, 4)]);
+
+        // the remaining positions are skipped
+        assert_eq!(iterator.advance(6).unwrap(), 9);
+        assert_eq!(iterator.next_interval().unwrap(), 3);
+        assert_eq!(iterator.next().unwrap(), NO_MORE_DOCS);
+        assert_eq!(iterator.next_interval().unwrap(), NO_MORE_INTERVALS);
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::sort_field::ScoreDoc;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const KNN_VECTOR: &str = "knn_vector";
+
+/// A query that matches the `k` documents whose vectors are the nearest to a
+/// target vector, scored by the similarity of the field.
+///
+/// The nearest neighbors are searched through the HNSW graph of each
+/// segment, so the results are approximate. The top `k` are computed per
+/// segment, thus up to `k` documents may match in each segment and the
+/// caller should only keep the top `k` hits of the search. Deleted
+/// documents are never returned.
+///
+/// Combine it with lexical queries in a `BooleanQuery` for hybrid retrieval.
+pub struct KnnVectorQuery {
+    field: String,
+    target: Vec<f32>,
+    k: usize,
+}
+
+impl KnnVectorQuery {
+    pub fn new(field: &str, target: Vec<f32>, k: usize) -> Result<KnnVectorQuery> {
+        if k == 0 {
+            bail!(IllegalArgument("k must be at least 1".into()));
+        }
+        if target.is_empty() || target.iter().any(|v| !v.is_finite()) {
+            bail!(IllegalArgument(
+                "target vector must be non empty and finite".into()
+            ));
+        }
+        Ok(KnnVectorQuery {
+            field: field.to_string(),
+            target,
+            k,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn target(&self) -> &[f32] {
+        &self.target
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+}
+
+impl<C: Codec> Query<C> for KnnVectorQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(KnnVectorWeight {
+            field: self.field.clone(),
+            target: self.target.clone(),
+            k: self.k,
+            query_weight: 1.0,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for KnnVectorQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "KnnVectorQuery(field: {}, target: {:?}, k: {})",
+            &self.field, &self.target, self.k
+        )
+    }
+}
+
+struct KnnVectorWeight {
+    field: String,
+    target: Vec<f32>,
+    k: usize,
+    query_weight: f32,
+    needs_scores: bool,
+}
+
+impl KnnVectorWeight {
+    // the nearest live docs of the segment, sorted by doc id
+    fn search<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Vec<ScoreDoc>> {
+        let vectors_reader = match reader.reader.vectors_reader() {
+            Some(r) => r,
+            None => return Ok(vec![]),
+        };
+        match reader.reader.field_info(&self.field) {
+            Some(fi) if fi.vector_dimension > 0 => {
+                if fi.vector_dimension as usize != self.target.len() {
+                    bail!(IllegalArgument(format!(
+                        "vector query dimension: {} differs from field dimension: {}",
+                        self.target.len(),
+                        fi.vector_dimension
+                    )));
+                }
+            }
+            _ => return Ok(vec![]),
+        }
+        let live_docs = reader.reader.live_docs();
+        let mut hits =
+            vectors_reader.search(&self.field, &self.target, self.k, Some(live_docs.as_ref()))?;
+        hits.sort_by_key(|hit| hit.doc);
+        Ok(hits)
+    }
+}
+
+impl<C: Codec> Weight<C> for KnnVectorWeight {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let hits = self.search(reader)?;
+        if hits.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(KnnVectorScorer::new(
+            hits,
+            self.query_weight,
+        ))))
+    }
+
+    fn query_type(&self) -> &'static str {
+        KNN_VECTOR
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.query_weight = norm * boost;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.query_weight * self.query_weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let hits = self.search(reader)?;
+        match hits.iter().find(|hit| hit.doc == doc) {
+            Some(hit) => Ok(Explanation::new(
+                true,
+                hit.score * self.query_weight,
+                format!(
+                    "within the {} nearest neighbors of the target vector in {}",
+                    self.k, &self.field
+                ),
+                vec![],
+            )),
+            None => Ok(Explanation::new(
+                false,
+                0f32,
+                format!(
+                    "not in the {} nearest neighbors of the target vector in {}",
+                    self.k, &self.field
+                ),
+                vec![],
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KnnVectorWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "KnnVectorWeight(field: {}, k: {}, query_weight: {})",
+            &self.field, self.k, self.query_weight
+        )
+    }
+}
+
+/// Iterates the nearest neighbors found in a segment.
+struct KnnVectorScorer {
+    // sorted by doc id
+    hits: Vec<ScoreDoc>,
+    query_weight: f32,
+    // index of the current hit, `hits.len()` once exhausted
+    upto: usize,
+    doc: DocId,
+}
+
+impl KnnVectorScorer {
+    fn new(hits: Vec<ScoreDoc>, query_weight: f32) -> KnnVectorScorer {
+        KnnVectorScorer {
+            hits,
+            query_weight,
+            upto: 0,
+            doc: -1,
+        }
+    }
+}
+
+impl Scorer for KnnVectorScorer {
+    fn score(&mut self) -> Result<f32> {
+        debug_assert!(self.doc != -1 && self.doc != NO_MORE_DOCS);
+        Ok(self.hits[self.upto].score * self.query_weight)
+    }
+}
+
+impl DocIterator for KnnVectorScorer {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        if self.doc != -1 {
+            self.upto += 1;
+        }
+        self.doc = if self.upto < self.hits.len() {
+            self.hits[self.upto].doc
+        } else {
+            NO_MORE_DOCS
+        };
+        Ok(self.doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        while self.doc < target {
+            self.next()?;
+        }
+        Ok(self.doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.hits.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::reader::IndexReader;
+    use core::index::tests::{MockIndexReader, MockLeafReader};
+
+    #[test]
+    fn test_knn_vector_query_new() {
+        assert!(KnnVectorQuery::new("v", vec![1.0, 2.0], 0).is_err());
+        assert!(KnnVectorQuery::new("v", vec![], 3).is_err());
+        assert!(KnnVectorQuery::new("v", vec![::std::f32::INFINITY], 3).is_err());
+
+        let query = KnnVectorQuery::new("v", vec![1.0, 2.0], 3).unwrap();
+        assert_eq!(query.field(), "v");
+        assert_eq!(query.target(), &[1.0, 2.0]);
+        assert_eq!(query.k(), 3);
+    }
+
+    #[test]
+    fn test_knn_vector_scorer() {
+        let hits = vec![
+            ScoreDoc::new(1, 0.5),
+            ScoreDoc::new(4, 0.25),
+            ScoreDoc::new(7, 1.0),
+            ScoreDoc::new(9, 0.75),
+        ];
+        let mut scorer = KnnVectorScorer::new(hits, 2.0);
+        assert_eq!(scorer.cost(), 4);
+        assert_eq!(scorer.doc_id(), -1);
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 1.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(5).unwrap(), 7);
+        assert!((scorer.score().unwrap() - 2.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(7).unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), 9);
+        assert!((scorer.score().unwrap() - 1.5).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_knn_vector_weight_without_vectors() {
+        let weight = KnnVectorWeight {
+            field: "test".into(),
+            target: vec![1.0],
+            k: 10,
+            query_weight: 1.0,
+            needs_scores: true,
+        };
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaves = index_reader.leaves();
+        let scorer = Weight::<TestCodec>::create_scorer(&weight, &leaves[0]).unwrap();
+        assert!(scorer.is_none());
+    }
+}
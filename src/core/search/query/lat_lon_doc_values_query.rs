@@ -0,0 +1,310 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::doc_values::SortedNumericDocValues;
+use core::codec::Codec;
+use core::doc::{DocValuesType, LatLonDocValuesField};
+use core::geo::{check_latitude, check_longitude, check_radius, haversin_meters, Rectangle};
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{DocValuesMatcher, DocValuesScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::DocId;
+
+use error::Result;
+
+pub const LAT_LON_DOC_VALUES_DISTANCE: &str = "lat_lon_doc_values_distance";
+
+impl LatLonDocValuesField {
+    /// Create a query matching the docs having a value of `field` within
+    /// `radius_meters` of the location, by haversine distance.
+    ///
+    /// All the docs of a segment are checked when it leads the iteration, so
+    /// it is best used as the `dv_query` of an `IndexOrDocValuesQuery`, next
+    /// to a `LatLonPoint::new_distance_query` of the same location.
+    pub fn new_slow_distance_query<C: Codec>(
+        field: String,
+        latitude: f64,
+        longitude: f64,
+        radius_meters: f64,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(LatLonDocValuesDistanceQuery::new(
+            field,
+            latitude,
+            longitude,
+            radius_meters,
+        )?))
+    }
+}
+
+/// Distance query for `LatLonDocValuesField`.
+pub struct LatLonDocValuesDistanceQuery {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+    radius_meters: f64,
+}
+
+impl LatLonDocValuesDistanceQuery {
+    pub fn new(
+        field: String,
+        latitude: f64,
+        longitude: f64,
+        radius_meters: f64,
+    ) -> Result<LatLonDocValuesDistanceQuery> {
+        check_radius(radius_meters)?;
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        Ok(LatLonDocValuesDistanceQuery {
+            field,
+            latitude,
+            longitude,
+            radius_meters,
+        })
+    }
+}
+
+impl<C: Codec> Query<C> for LatLonDocValuesDistanceQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(LatLonDocValuesDistanceWeight {
+            field: self.field.clone(),
+            latitude: self.latitude,
+            longitude: self.longitude,
+            radius_meters: self.radius_meters,
+            bbox: Rectangle::from_point_distance(
+                self.latitude,
+                self.longitude,
+                self.radius_meters,
+            )?,
+            weight: 0f32,
+            norm: 1f32,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for LatLonDocValuesDistanceQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonDocValuesDistanceQuery(field: {}, latitude: {}, longitude: {}, radius: {})",
+            &self.field, self.latitude, self.longitude, self.radius_meters
+        )
+    }
+}
+
+struct LatLonDocValuesDistanceWeight {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+    radius_meters: f64,
+    bbox: Rectangle,
+    weight: f32,
+    norm: f32,
+}
+
+impl<C: Codec> Weight<C> for LatLonDocValuesDistanceWeight {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        match reader.reader.field_info(&self.field) {
+            Some(fi) if fi.doc_values_type == DocValuesType::SortedNumeric => {}
+            _ => return Ok(None),
+        }
+        let matcher = DistanceMatcher {
+            doc_values: reader.reader.get_sorted_numeric_doc_values(&self.field)?,
+            latitude: self.latitude,
+            longitude: self.longitude,
+            radius_meters: self.radius_meters,
+            bbox: self.bbox,
+        };
+        Ok(Some(Box::new(DocValuesScorer::new(
+            reader.reader.max_doc(),
+            matcher,
+            self.weight,
+        ))))
+    }
+
+    fn query_type(&self) -> &'static str {
+        LAT_LON_DOC_VALUES_DISTANCE
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl fmt::Display for LatLonDocValuesDistanceWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonDocValuesDistanceWeight(field: {}, latitude: {}, longitude: {}, radius: {})",
+            &self.field, self.latitude, self.longitude, self.radius_meters
+        )
+    }
+}
+
+/// Matches the docs having at least one location within the distance.
+struct DistanceMatcher {
+    doc_values: Box<dyn SortedNumericDocValues>,
+    latitude: f64,
+    longitude: f64,
+    radius_meters: f64,
+    // the bounding box of the circle, checked before the distance
+    bbox: Rectangle,
+}
+
+impl DistanceMatcher {
+    fn within_bbox(&self, lat: f64, lon: f64) -> bool {
+        if lat < self.bbox.min_lat || lat > self.bbox.max_lat {
+            return false;
+        }
+        if self.bbox.crosses_dateline() {
+            lon >= self.bbox.min_lon || lon <= self.bbox.max_lon
+        } else {
+            lon >= self.bbox.min_lon && lon <= self.bbox.max_lon
+        }
+    }
+}
+
+impl DocValuesMatcher for DistanceMatcher {
+    fn matches(&mut self, doc: DocId) -> Result<bool> {
+        self.doc_values.set_document(doc)?;
+        for i in 0..self.doc_values.count() {
+            let value = self.doc_values.value_at(i)?;
+            let lat = LatLonDocValuesField::decode_latitude(value);
+            let lon = LatLonDocValuesField::decode_longitude(value);
+            if self.within_bbox(lat, lon)
+                && haversin_meters(self.latitude, self.longitude, lat, lon) <= self.radius_meters
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // decoding the values and computing the distances
+        20.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+
+    struct MockSortedNumericDocValues {
+        doc: DocId,
+        // the locations of each doc
+        locations: Vec<Vec<(f64, f64)>>,
+    }
+
+    impl SortedNumericDocValues for MockSortedNumericDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.doc = doc;
+            Ok(())
+        }
+
+        fn value_at(&mut self, index: usize) -> Result<i64> {
+            let (lat, lon) = self.locations[self.doc as usize][index];
+            LatLonDocValuesField::encode(lat, lon)
+        }
+
+        fn count(&self) -> usize {
+            self.locations[self.doc as usize].len()
+        }
+    }
+
+    #[test]
+    fn test_distance_matcher() {
+        let locations = vec![
+            vec![(10.0, 20.0)],
+            vec![],
+            vec![(11.0, 20.0), (10.1, 20.1)],
+            vec![(11.0, 20.0)],
+            vec![(10.4, 20.4)],
+        ];
+        let matcher = DistanceMatcher {
+            doc_values: Box::new(MockSortedNumericDocValues { doc: -1, locations }),
+            latitude: 10.0,
+            longitude: 20.0,
+            radius_meters: 50_000.0,
+            bbox: Rectangle::from_point_distance(10.0, 20.0, 50_000.0).unwrap(),
+        };
+        let mut scorer = DocValuesScorer::new(5, matcher, 1.0);
+        assert_eq!(scorer.next().unwrap(), 0);
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_distance_query_new() {
+        assert!(LatLonDocValuesDistanceQuery::new("f".into(), 10.0, 20.0, -1.0).is_err());
+        assert!(LatLonDocValuesDistanceQuery::new("f".into(), 10.0, 181.0, 1.0).is_err());
+        let query = LatLonDocValuesDistanceQuery::new("f".into(), 10.0, 20.0, 5.0).unwrap();
+        assert_eq!(
+            format!("{}", query),
+            "LatLonDocValuesDistanceQuery(field: f, latitude: 10, longitude: 20, radius: 5)"
+        );
+    }
+}
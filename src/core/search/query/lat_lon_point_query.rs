@@ -0,0 +1,694 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::{ErrorKind, Result};
+use std::fmt;
+
+use core::codec::points::{IntersectVisitor, PointValues, Relation};
+use core::codec::Codec;
+use core::doc::LatLonPoint;
+use core::geo::{
+    check_latitude, check_longitude, check_radius, decode_latitude, decode_longitude,
+    encode_latitude, encode_latitude_ceil, encode_longitude, encode_longitude_ceil,
+    haversin_meters, relate, Polygon, Polygon2D, Rectangle, MAX_LAT_INCL, MAX_LON_INCL,
+    MIN_LAT_INCL, MIN_LON_INCL,
+};
+use core::index::reader::{LeafReader, LeafReaderContext};
+use core::search::explanation::Explanation;
+use core::search::query::{
+    BooleanQuery, IntPoint, MatchNoDocsQuery, PointRangeQuery, PointValueType, Query, TermQuery,
+    Weight,
+};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIdSet, DocIterator};
+use core::util::*;
+
+impl LatLonPoint {
+    /// Create a query matching the points of `field` within `radius_meters`
+    /// of the location, by haversine distance.
+    pub fn new_distance_query<C: Codec>(
+        field: String,
+        latitude: f64,
+        longitude: f64,
+        radius_meters: f64,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(LatLonPointDistanceQuery::new(
+            field,
+            latitude,
+            longitude,
+            radius_meters,
+        )?))
+    }
+
+    /// Create a query matching the points of `field` within a box, which
+    /// crosses the dateline when `min_longitude` is greater than
+    /// `max_longitude`.
+    pub fn new_box_query<C: Codec>(
+        field: String,
+        min_latitude: f64,
+        max_latitude: f64,
+        min_longitude: f64,
+        max_longitude: f64,
+    ) -> Result<Box<dyn Query<C>>> {
+        let rect = Rectangle::new(min_latitude, max_latitude, min_longitude, max_longitude)?;
+        // the max latitude and longitude are encoded as their previous
+        // values, so nothing can be found above them
+        if min_latitude == MAX_LAT_INCL {
+            return Ok(Box::new(MatchNoDocsQuery::new(
+                "min_latitude is the max latitude",
+            )));
+        }
+        let mut min_longitude = min_longitude;
+        if min_longitude == MAX_LON_INCL {
+            if max_longitude == MAX_LON_INCL {
+                return Ok(Box::new(MatchNoDocsQuery::new(
+                    "min_longitude and max_longitude are the max longitude",
+                )));
+            } else if rect.crosses_dateline() {
+                // only the part after the dateline is left
+                min_longitude = MIN_LON_INCL;
+            }
+        }
+
+        let min_lat = encode_latitude_ceil(min_latitude)?;
+        let max_lat = encode_latitude(max_latitude)?;
+        if min_longitude > max_longitude {
+            // split the box in two at the dateline
+            let east = Self::new_encoded_box_query(
+                field.clone(),
+                min_lat,
+                max_lat,
+                encode_longitude_ceil(min_longitude)?,
+                i32::max_value(),
+            )?;
+            let west = Self::new_encoded_box_query(
+                field,
+                min_lat,
+                max_lat,
+                i32::min_value(),
+                encode_longitude(max_longitude)?,
+            )?;
+            BooleanQuery::build(vec![], vec![east, west], vec![])
+        } else {
+            Self::new_encoded_box_query(
+                field,
+                min_lat,
+                max_lat,
+                encode_longitude_ceil(min_longitude)?,
+                encode_longitude(max_longitude)?,
+            )
+        }
+    }
+
+    /// Create a query matching the points of `field` within any of the
+    /// polygons, and not in one of their holes.
+    pub fn new_polygon_query<C: Codec>(
+        field: String,
+        polygons: Vec<Polygon>,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(LatLonPointInPolygonQuery::new(field, polygons)?))
+    }
+
+    fn new_encoded_box_query<C: Codec>(
+        field: String,
+        min_lat: i32,
+        max_lat: i32,
+        min_lon: i32,
+        max_lon: i32,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(PointRangeQuery::new(
+            field,
+            IntPoint::pack(&[min_lat, min_lon]),
+            IntPoint::pack(&[max_lat, max_lon]),
+            2,
+            PointValueType::Integer,
+        )?))
+    }
+}
+
+/// Distance query for `LatLonPoint`.
+///
+/// The BKD tree is first pruned with the bounding box of the circle, the
+/// cells overlapping the box are then related to the circle itself and only
+/// the points of the cells crossing it have their distance computed.
+pub struct LatLonPointDistanceQuery {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+    radius_meters: f64,
+}
+
+impl LatLonPointDistanceQuery {
+    pub fn new(
+        field: String,
+        latitude: f64,
+        longitude: f64,
+        radius_meters: f64,
+    ) -> Result<LatLonPointDistanceQuery> {
+        assert!(!field.is_empty());
+        check_radius(radius_meters)?;
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        Ok(LatLonPointDistanceQuery {
+            field,
+            latitude,
+            longitude,
+            radius_meters,
+        })
+    }
+}
+
+pub const LAT_LON_POINT_DISTANCE: &str = "lat_lon_point_distance";
+
+impl<C: Codec> Query<C> for LatLonPointDistanceQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(LatLonPointWeight::new(
+            self.field.clone(),
+            DistanceChecker::new(self.latitude, self.longitude, self.radius_meters)?,
+            LAT_LON_POINT_DISTANCE,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for LatLonPointDistanceQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonPointDistanceQuery(field: {}, latitude: {}, longitude: {}, radius: {})",
+            &self.field, self.latitude, self.longitude, self.radius_meters
+        )
+    }
+}
+
+/// Polygon query for `LatLonPoint`.
+///
+/// The cells of the BKD tree are related to the edges of the polygons, only
+/// the points of the cells crossing an edge are tested one by one.
+pub struct LatLonPointInPolygonQuery {
+    field: String,
+    polygons: Vec<Polygon>,
+}
+
+impl LatLonPointInPolygonQuery {
+    pub fn new(field: String, polygons: Vec<Polygon>) -> Result<LatLonPointInPolygonQuery> {
+        assert!(!field.is_empty());
+        if polygons.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "polygons must not be empty".into()
+            ));
+        }
+        Ok(LatLonPointInPolygonQuery { field, polygons })
+    }
+}
+
+pub const LAT_LON_POINT_IN_POLYGON: &str = "lat_lon_point_in_polygon";
+
+impl<C: Codec> Query<C> for LatLonPointInPolygonQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(LatLonPointWeight::new(
+            self.field.clone(),
+            PolygonChecker::new(&self.polygons)?,
+            LAT_LON_POINT_IN_POLYGON,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for LatLonPointInPolygonQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let polygons: Vec<String> = self.polygons.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "LatLonPointInPolygonQuery(field: {}, polygons: [{}])",
+            &self.field,
+            polygons.join(", ")
+        )
+    }
+}
+
+/// Checks the points and the cells of the BKD tree against a shape.
+trait LatLonChecker: fmt::Display {
+    fn matches(&self, packed_value: &[u8]) -> bool;
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation;
+}
+
+/// Decodes the bounds of a cell as `(min_lat, max_lat, min_lon, max_lon)`.
+pub(crate) fn decode_cell(
+    min_packed_value: &[u8],
+    max_packed_value: &[u8],
+) -> (i32, i32, i32, i32) {
+    (
+        IntPoint::decode_dimension(&min_packed_value[..4]),
+        IntPoint::decode_dimension(&max_packed_value[..4]),
+        IntPoint::decode_dimension(&min_packed_value[4..8]),
+        IntPoint::decode_dimension(&max_packed_value[4..8]),
+    )
+}
+
+/// Checks the points and the cells of the BKD tree against the circle.
+struct DistanceChecker {
+    latitude: f64,
+    longitude: f64,
+    radius_meters: f64,
+    // the encoded bounding box of the circle
+    min_lat: i32,
+    max_lat: i32,
+    min_lon: i32,
+    max_lon: i32,
+    crosses_dateline: bool,
+}
+
+impl DistanceChecker {
+    fn new(latitude: f64, longitude: f64, radius_meters: f64) -> Result<DistanceChecker> {
+        let bbox = Rectangle::from_point_distance(latitude, longitude, radius_meters)?;
+        Ok(DistanceChecker {
+            latitude,
+            longitude,
+            radius_meters,
+            min_lat: encode_latitude_ceil(bbox.min_lat)?,
+            max_lat: encode_latitude(bbox.max_lat)?,
+            min_lon: encode_longitude_ceil(bbox.min_lon)?,
+            max_lon: encode_longitude(bbox.max_lon)?,
+            crosses_dateline: bbox.crosses_dateline(),
+        })
+    }
+
+    fn lon_outside(&self, min_lon: i32, max_lon: i32) -> bool {
+        if self.crosses_dateline {
+            max_lon < self.min_lon && min_lon > self.max_lon
+        } else {
+            max_lon < self.min_lon || min_lon > self.max_lon
+        }
+    }
+}
+
+impl LatLonChecker for DistanceChecker {
+    fn matches(&self, packed_value: &[u8]) -> bool {
+        let (lat, _, lon, _) = decode_cell(packed_value, packed_value);
+        // the cheap bounding box check first, then the exact distance
+        if lat < self.min_lat || lat > self.max_lat || self.lon_outside(lon, lon) {
+            return false;
+        }
+        haversin_meters(
+            self.latitude,
+            self.longitude,
+            decode_latitude(lat),
+            decode_longitude(lon),
+        ) <= self.radius_meters
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        let (min_lat, max_lat, min_lon, max_lon) = decode_cell(min_packed_value, max_packed_value);
+        if max_lat < self.min_lat || min_lat > self.max_lat || self.lon_outside(min_lon, max_lon) {
+            return Relation::CellOutsideQuery;
+        }
+        relate(
+            decode_latitude(min_lat),
+            decode_latitude(max_lat),
+            decode_longitude(min_lon),
+            decode_longitude(max_lon),
+            self.latitude,
+            self.longitude,
+            self.radius_meters,
+        )
+    }
+}
+
+impl fmt::Display for DistanceChecker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "latitude: {}, longitude: {}, radius: {}",
+            self.latitude, self.longitude, self.radius_meters
+        )
+    }
+}
+
+/// Checks the points and the cells of the BKD tree against the polygons.
+struct PolygonChecker {
+    polygon_2d: Polygon2D,
+    // the encoded bounding box of all the polygons
+    min_lat: i32,
+    max_lat: i32,
+    min_lon: i32,
+    max_lon: i32,
+}
+
+impl PolygonChecker {
+    fn new(polygons: &[Polygon]) -> Result<PolygonChecker> {
+        let min_lat = polygons
+            .iter()
+            .map(Polygon::min_lat)
+            .fold(MAX_LAT_INCL, f64::min);
+        let max_lat = polygons
+            .iter()
+            .map(Polygon::max_lat)
+            .fold(MIN_LAT_INCL, f64::max);
+        let min_lon = polygons
+            .iter()
+            .map(Polygon::min_lon)
+            .fold(MAX_LON_INCL, f64::min);
+        let max_lon = polygons
+            .iter()
+            .map(Polygon::max_lon)
+            .fold(MIN_LON_INCL, f64::max);
+        Ok(PolygonChecker {
+            polygon_2d: Polygon2D::new(polygons),
+            min_lat: encode_latitude_ceil(min_lat)?,
+            max_lat: encode_latitude(max_lat)?,
+            min_lon: encode_longitude_ceil(min_lon)?,
+            max_lon: encode_longitude(max_lon)?,
+        })
+    }
+}
+
+impl LatLonChecker for PolygonChecker {
+    fn matches(&self, packed_value: &[u8]) -> bool {
+        let (lat, _, lon, _) = decode_cell(packed_value, packed_value);
+        if lat < self.min_lat || lat > self.max_lat || lon < self.min_lon || lon > self.max_lon {
+            return false;
+        }
+        self.polygon_2d
+            .contains(decode_latitude(lat), decode_longitude(lon))
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        let (min_lat, max_lat, min_lon, max_lon) = decode_cell(min_packed_value, max_packed_value);
+        if max_lat < self.min_lat
+            || min_lat > self.max_lat
+            || max_lon < self.min_lon
+            || min_lon > self.max_lon
+        {
+            return Relation::CellOutsideQuery;
+        }
+        self.polygon_2d.relate(
+            decode_latitude(min_lat),
+            decode_latitude(max_lat),
+            decode_longitude(min_lon),
+            decode_longitude(max_lon),
+        )
+    }
+}
+
+impl fmt::Display for PolygonChecker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "bbox: [{}, {}] x [{}, {}]",
+            decode_latitude(self.min_lat),
+            decode_latitude(self.max_lat),
+            decode_longitude(self.min_lon),
+            decode_longitude(self.max_lon)
+        )
+    }
+}
+
+struct LatLonPointWeight<T: LatLonChecker> {
+    field: String,
+    checker: T,
+    query_type: &'static str,
+    weight: f32,
+    norm: f32,
+}
+
+impl<T: LatLonChecker> LatLonPointWeight<T> {
+    fn new(field: String, checker: T, query_type: &'static str) -> LatLonPointWeight<T> {
+        LatLonPointWeight {
+            field,
+            checker,
+            query_type,
+            weight: 0f32,
+            norm: 1f32,
+        }
+    }
+
+    fn build_matching_doc_set<R: LeafReader + ?Sized>(
+        &self,
+        reader: &R,
+        values: &impl PointValues,
+    ) -> Result<DocIdSetEnum> {
+        let mut result = DocIdSetBuilder::from_values(reader.max_doc(), values, &self.field)?;
+        {
+            let mut visitor = LatLonIntersectVisitor {
+                doc_id_set_builder: &mut result,
+                checker: &self.checker,
+            };
+            values.intersect(&self.field, &mut visitor)?;
+        }
+        Ok(result.build())
+    }
+}
+
+impl<C: Codec, T: LatLonChecker> Weight<C> for LatLonPointWeight<T> {
+    fn create_scorer(
+        &self,
+        leaf_reader_ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let leaf_reader = leaf_reader_ctx.reader;
+        if let Some(ref values) = leaf_reader.point_values() {
+            if let Some(field_info) = leaf_reader.field_info(&self.field) {
+                if field_info.point_dimension_count != 2 || field_info.point_num_bytes != 4 {
+                    bail!(ErrorKind::IllegalArgument(format!(
+                        "field '{}' was indexed with num_dims={} and bytes_per_dim={} but \
+                         LatLonPoint has num_dims=2 and bytes_per_dim=4",
+                        &self.field, field_info.point_dimension_count, field_info.point_num_bytes
+                    )));
+                }
+
+                if let Some(iterator) = self
+                    .build_matching_doc_set(leaf_reader, values)?
+                    .iterator()?
+                {
+                    let cost = iterator.cost();
+                    return Ok(Some(Box::new(ConstantScoreScorer::new(
+                        self.weight,
+                        iterator,
+                        cost,
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.query_type
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        // bounded by the number of docs having a value for the field
+        match reader.reader.point_values() {
+            Some(ref values) if reader.reader.field_info(&self.field).is_some() => {
+                Ok(Some(values.doc_count(&self.field)? as usize))
+            }
+            _ => Ok(Some(0)),
+        }
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl<T: LatLonChecker> fmt::Display for LatLonPointWeight<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonPointWeight(field: {}, type: {}, {})",
+            &self.field, self.query_type, &self.checker
+        )
+    }
+}
+
+struct LatLonIntersectVisitor<'a, T: LatLonChecker> {
+    doc_id_set_builder: &'a mut DocIdSetBuilder,
+    checker: &'a T,
+}
+
+impl<'a, T: LatLonChecker> IntersectVisitor for LatLonIntersectVisitor<'a, T> {
+    fn visit(&mut self, doc_id: DocId) -> Result<()> {
+        self.doc_id_set_builder.add_doc(doc_id);
+        Ok(())
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        if self.checker.matches(packed_value) {
+            self.doc_id_set_builder.add_doc(doc_id);
+        }
+        Ok(())
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        self.checker.compare(min_packed_value, max_packed_value)
+    }
+
+    fn grow(&mut self, count: usize) {
+        self.doc_id_set_builder.grow(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    fn pack(lat: f64, lon: f64) -> Vec<u8> {
+        LatLonPoint::pack(lat, lon).unwrap()
+    }
+
+    #[test]
+    fn test_distance_checker() {
+        // about 111 km per degree of latitude
+        let checker = DistanceChecker::new(10.0, 20.0, 50_000.0).unwrap();
+        assert!(checker.matches(&pack(10.0, 20.0)));
+        assert!(checker.matches(&pack(10.4, 20.0)));
+        assert!(!checker.matches(&pack(10.5, 20.0)));
+        // inside the bounding box but not the circle
+        assert!(!checker.matches(&pack(10.4, 20.4)));
+
+        assert!(
+            checker.compare(&pack(11.0, 19.0), &pack(12.0, 21.0)) == Relation::CellOutsideQuery
+        );
+        assert!(checker.compare(&pack(9.0, 19.0), &pack(11.0, 21.0)) == Relation::CellCrossesQuery);
+        assert!(checker.compare(&pack(9.9, 19.9), &pack(10.1, 20.1)) == Relation::CellInsideQuery);
+    }
+
+    #[test]
+    fn test_distance_checker_dateline() {
+        let checker = DistanceChecker::new(0.0, 179.9, 50_000.0).unwrap();
+        assert!(checker.crosses_dateline);
+        assert!(checker.matches(&pack(0.0, -179.9)));
+        assert!(!checker.matches(&pack(0.0, 0.0)));
+        assert!(
+            checker.compare(&pack(-1.0, -10.0), &pack(1.0, 10.0)) == Relation::CellOutsideQuery
+        );
+        assert!(
+            checker.compare(&pack(-1.0, -180.0), &pack(1.0, -179.0)) == Relation::CellCrossesQuery
+        );
+    }
+
+    #[test]
+    fn test_new_distance_query() {
+        assert!(LatLonPointDistanceQuery::new("f".into(), 10.0, 20.0, -1.0).is_err());
+        assert!(
+            LatLonPointDistanceQuery::new("f".into(), 10.0, 20.0, ::std::f64::INFINITY).is_err()
+        );
+        assert!(LatLonPointDistanceQuery::new("f".into(), 91.0, 20.0, 10.0).is_err());
+        assert!(LatLonPointDistanceQuery::new("f".into(), 10.0, 20.0, 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_polygon_checker() {
+        let lats = vec![0.0, 0.0, 10.0, 10.0, 0.0];
+        let lons = vec![0.0, 10.0, 10.0, 0.0, 0.0];
+        let hole_lats = vec![4.0, 4.0, 6.0, 6.0, 4.0];
+        let hole_lons = vec![4.0, 6.0, 6.0, 4.0, 4.0];
+        let hole = Polygon::new(hole_lats, hole_lons).unwrap();
+        let polygon = Polygon::with_holes(lats, lons, vec![hole]).unwrap();
+        let checker = PolygonChecker::new(&[polygon]).unwrap();
+        assert!(checker.matches(&pack(1.0, 1.0)));
+        assert!(!checker.matches(&pack(5.0, 5.0)));
+        assert!(!checker.matches(&pack(11.0, 5.0)));
+
+        assert!(checker.compare(&pack(1.0, 1.0), &pack(2.0, 2.0)) == Relation::CellInsideQuery);
+        assert!(checker.compare(&pack(4.5, 4.5), &pack(5.5, 5.5)) == Relation::CellOutsideQuery);
+        assert!(checker.compare(&pack(-1.0, 1.0), &pack(2.0, 2.0)) == Relation::CellCrossesQuery);
+        assert!(
+            checker.compare(&pack(20.0, 20.0), &pack(30.0, 30.0)) == Relation::CellOutsideQuery
+        );
+        assert!(LatLonPointInPolygonQuery::new("f".into(), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_new_box_query() {
+        let query = LatLonPoint::new_box_query::<TestCodec>("f".into(), 10.0, 20.0, -5.0, 5.0);
+        assert!(query.unwrap().as_any().is::<PointRangeQuery>());
+
+        let query = LatLonPoint::new_box_query::<TestCodec>("f".into(), 10.0, 20.0, 170.0, -170.0);
+        assert!(query.unwrap().as_any().is::<BooleanQuery<TestCodec>>());
+        // the box starting at the dateline only has its western part
+        let query = LatLonPoint::new_box_query::<TestCodec>("f".into(), 10.0, 20.0, 180.0, -170.0);
+        assert!(query.unwrap().as_any().is::<PointRangeQuery>());
+
+        let query = LatLonPoint::new_box_query::<TestCodec>("f".into(), 90.0, 90.0, -5.0, 5.0);
+        assert!(query.unwrap().as_any().is::<MatchNoDocsQuery>());
+        let query = LatLonPoint::new_box_query::<TestCodec>("f".into(), 10.0, 20.0, 180.0, 180.0);
+        assert!(query.unwrap().as_any().is::<MatchNoDocsQuery>());
+
+        assert!(LatLonPoint::new_box_query::<TestCodec>("f".into(), 20.0, 10.0, 0.0, 5.0).is_err());
+        assert!(
+            LatLonPoint::new_box_query::<TestCodec>("f".into(), 10.0, 20.0, 0.0, 181.0).is_err()
+        );
+    }
+}
@@ -0,0 +1,552 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::{ErrorKind, Result};
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::points::{IntersectVisitor, PointValues, Relation};
+use core::codec::Codec;
+use core::doc::LatLonShape;
+use core::geo::{decode_latitude, decode_longitude, Polygon, Polygon2D, Rectangle, Triangle};
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{decode_cell, Query, TermQuery, Weight};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::*;
+
+/// How the indexed shapes must relate to the shape of a query to match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryRelation {
+    /// The indexed shapes sharing at least a point with the query shape.
+    Intersects,
+    /// The indexed shapes fully within the query shape.
+    Within,
+    /// The indexed shapes sharing no point with the query shape.
+    Disjoint,
+}
+
+impl fmt::Display for QueryRelation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryRelation::Intersects => write!(f, "INTERSECTS"),
+            QueryRelation::Within => write!(f, "WITHIN"),
+            QueryRelation::Disjoint => write!(f, "DISJOINT"),
+        }
+    }
+}
+
+impl LatLonShape {
+    /// Create a query matching the shapes of `field` related to a box, which
+    /// crosses the dateline when `min_longitude` is greater than
+    /// `max_longitude`.
+    pub fn new_box_query<C: Codec>(
+        field: String,
+        relation: QueryRelation,
+        min_latitude: f64,
+        max_latitude: f64,
+        min_longitude: f64,
+        max_longitude: f64,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(LatLonShapeBoundingBoxQuery::new(
+            field,
+            relation,
+            Rectangle::new(min_latitude, max_latitude, min_longitude, max_longitude)?,
+        )))
+    }
+
+    /// Create a query matching the shapes of `field` related to polygons.
+    pub fn new_polygon_query<C: Codec>(
+        field: String,
+        relation: QueryRelation,
+        polygons: Vec<Polygon>,
+    ) -> Result<Box<dyn Query<C>>> {
+        Ok(Box::new(LatLonShapePolygonQuery::new(
+            field, relation, polygons,
+        )?))
+    }
+}
+
+/// Box query for `LatLonShape`.
+pub struct LatLonShapeBoundingBoxQuery {
+    field: String,
+    relation: QueryRelation,
+    rectangle: Rectangle,
+}
+
+impl LatLonShapeBoundingBoxQuery {
+    pub fn new(
+        field: String,
+        relation: QueryRelation,
+        rectangle: Rectangle,
+    ) -> LatLonShapeBoundingBoxQuery {
+        assert!(!field.is_empty());
+        LatLonShapeBoundingBoxQuery {
+            field,
+            relation,
+            rectangle,
+        }
+    }
+}
+
+pub const LAT_LON_SHAPE_BOUNDING_BOX: &str = "lat_lon_shape_bounding_box";
+
+impl<C: Codec> Query<C> for LatLonShapeBoundingBoxQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(LatLonShapeWeight::new(
+            self.field.clone(),
+            self.relation,
+            self.rectangle,
+            LAT_LON_SHAPE_BOUNDING_BOX,
+            self.to_string(),
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for LatLonShapeBoundingBoxQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonShapeBoundingBoxQuery(field: {}, relation: {}, {})",
+            &self.field, self.relation, &self.rectangle
+        )
+    }
+}
+
+/// Polygon query for `LatLonShape`.
+pub struct LatLonShapePolygonQuery {
+    field: String,
+    relation: QueryRelation,
+    polygons: Vec<Polygon>,
+}
+
+impl LatLonShapePolygonQuery {
+    pub fn new(
+        field: String,
+        relation: QueryRelation,
+        polygons: Vec<Polygon>,
+    ) -> Result<LatLonShapePolygonQuery> {
+        assert!(!field.is_empty());
+        if polygons.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "polygons must not be empty".into()
+            ));
+        }
+        Ok(LatLonShapePolygonQuery {
+            field,
+            relation,
+            polygons,
+        })
+    }
+}
+
+pub const LAT_LON_SHAPE_POLYGON: &str = "lat_lon_shape_polygon";
+
+impl<C: Codec> Query<C> for LatLonShapePolygonQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(LatLonShapeWeight::new(
+            self.field.clone(),
+            self.relation,
+            Polygon2D::new(&self.polygons),
+            LAT_LON_SHAPE_POLYGON,
+            self.to_string(),
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for LatLonShapePolygonQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let polygons: Vec<String> = self.polygons.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "LatLonShapePolygonQuery(field: {}, relation: {}, polygons: [{}])",
+            &self.field,
+            self.relation,
+            polygons.join(", ")
+        )
+    }
+}
+
+/// Relates the triangles and the cells of the BKD tree to a shape.
+trait ShapeRelator {
+    fn relate_cell(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation;
+
+    fn relate_triangle(&self, triangle: &Triangle) -> Relation;
+}
+
+impl ShapeRelator for Rectangle {
+    fn relate_cell(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation {
+        self.relate(min_lat, max_lat, min_lon, max_lon)
+    }
+
+    fn relate_triangle(&self, triangle: &Triangle) -> Relation {
+        Rectangle::relate_triangle(self, triangle)
+    }
+}
+
+impl ShapeRelator for Polygon2D {
+    fn relate_cell(&self, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Relation {
+        self.relate(min_lat, max_lat, min_lon, max_lon)
+    }
+
+    fn relate_triangle(&self, triangle: &Triangle) -> Relation {
+        Polygon2D::relate_triangle(self, triangle)
+    }
+}
+
+/// Decodes the bounds of all the triangles of a cell as
+/// `(min_lat, max_lat, min_lon, max_lon)`, from the cells of their vertices.
+fn decode_triangle_cell(min_packed_value: &[u8], max_packed_value: &[u8]) -> (f64, f64, f64, f64) {
+    let (mut min_lat, mut max_lat, mut min_lon, mut max_lon) =
+        decode_cell(min_packed_value, max_packed_value);
+    for v in 1..3 {
+        let vertex = decode_cell(&min_packed_value[v * 8..], &max_packed_value[v * 8..]);
+        min_lat = min_lat.min(vertex.0);
+        max_lat = max_lat.max(vertex.1);
+        min_lon = min_lon.min(vertex.2);
+        max_lon = max_lon.max(vertex.3);
+    }
+    (
+        decode_latitude(min_lat),
+        decode_latitude(max_lat),
+        decode_longitude(min_lon),
+        decode_longitude(max_lon),
+    )
+}
+
+struct LatLonShapeWeight<S: ShapeRelator> {
+    field: String,
+    relation: QueryRelation,
+    shape: S,
+    query_type: &'static str,
+    // the query, to tell the weights apart
+    description: String,
+    weight: f32,
+    norm: f32,
+}
+
+impl<S: ShapeRelator> LatLonShapeWeight<S> {
+    fn new(
+        field: String,
+        relation: QueryRelation,
+        shape: S,
+        query_type: &'static str,
+        description: String,
+    ) -> LatLonShapeWeight<S> {
+        LatLonShapeWeight {
+            field,
+            relation,
+            shape,
+            query_type,
+            description,
+            weight: 0f32,
+            norm: 1f32,
+        }
+    }
+}
+
+impl<C: Codec, S: ShapeRelator> Weight<C> for LatLonShapeWeight<S> {
+    fn create_scorer(
+        &self,
+        leaf_reader_ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let leaf_reader = leaf_reader_ctx.reader;
+        if let Some(ref values) = leaf_reader.point_values() {
+            if let Some(field_info) = leaf_reader.field_info(&self.field) {
+                if field_info.point_dimension_count != 6 || field_info.point_num_bytes != 4 {
+                    bail!(ErrorKind::IllegalArgument(format!(
+                        "field '{}' was indexed with num_dims={} and bytes_per_dim={} but \
+                         LatLonShape has num_dims=6 and bytes_per_dim=4",
+                        &self.field, field_info.point_dimension_count, field_info.point_num_bytes
+                    )));
+                }
+
+                let mut visitor =
+                    ShapeIntersectVisitor::new(&self.shape, self.relation, leaf_reader.max_doc());
+                values.intersect(&self.field, &mut visitor)?;
+                let matching = visitor.into_matching();
+                let cost = matching.cardinality();
+                if cost > 0 {
+                    let iterator = BitSetDocIterator::new(Arc::new(matching), cost)?;
+                    return Ok(Some(Box::new(ConstantScoreScorer::new(
+                        self.weight,
+                        iterator,
+                        cost,
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.query_type
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        // bounded by the number of docs having a value for the field
+        match reader.reader.point_values() {
+            Some(ref values) if reader.reader.field_info(&self.field).is_some() => {
+                Ok(Some(values.doc_count(&self.field)? as usize))
+            }
+            _ => Ok(Some(0)),
+        }
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl<S: ShapeRelator> fmt::Display for LatLonShapeWeight<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonShapeWeight(type: {}, {})",
+            self.query_type, &self.description
+        )
+    }
+}
+
+/// Collects the docs whose triangles relate to the shape as the query
+/// requires.
+///
+/// A doc intersects the shape if one of its triangles does, so the cells
+/// outside of the shape are skipped. But a doc is only within the shape, or
+/// disjoint from it, if all its triangles are: the triangles ruling out a
+/// doc are then collected too, whatever the cell they are in, and the docs
+/// they rule out are removed at the end.
+struct ShapeIntersectVisitor<'a, S: ShapeRelator> {
+    shape: &'a S,
+    relation: QueryRelation,
+    matching: FixedBitSet,
+    excluded: FixedBitSet,
+}
+
+impl<'a, S: ShapeRelator> ShapeIntersectVisitor<'a, S> {
+    fn new(shape: &'a S, relation: QueryRelation, max_doc: DocId) -> Self {
+        let excluded = if relation == QueryRelation::Intersects {
+            FixedBitSet::default()
+        } else {
+            FixedBitSet::new(max_doc as usize)
+        };
+        ShapeIntersectVisitor {
+            shape,
+            relation,
+            matching: FixedBitSet::new(max_doc as usize),
+            excluded,
+        }
+    }
+
+    fn collect(&mut self, doc_id: DocId, relation: Relation) {
+        let doc = doc_id as usize;
+        match self.relation {
+            QueryRelation::Intersects => {
+                if relation != Relation::CellOutsideQuery {
+                    self.matching.set(doc);
+                }
+            }
+            QueryRelation::Within => {
+                if relation == Relation::CellInsideQuery {
+                    self.matching.set(doc);
+                } else {
+                    self.excluded.set(doc);
+                }
+            }
+            QueryRelation::Disjoint => {
+                self.matching.set(doc);
+                if relation != Relation::CellOutsideQuery {
+                    self.excluded.set(doc);
+                }
+            }
+        }
+    }
+
+    fn into_matching(self) -> FixedBitSet {
+        let mut matching = self.matching;
+        matching.and_not(&self.excluded);
+        matching
+    }
+}
+
+impl<'a, S: ShapeRelator> IntersectVisitor for ShapeIntersectVisitor<'a, S> {
+    fn visit(&mut self, doc_id: DocId) -> Result<()> {
+        self.collect(doc_id, Relation::CellInsideQuery);
+        Ok(())
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        let relation = self
+            .shape
+            .relate_triangle(&LatLonShape::unpack(packed_value));
+        self.collect(doc_id, relation);
+        Ok(())
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        let (min_lat, max_lat, min_lon, max_lon) =
+            decode_triangle_cell(min_packed_value, max_packed_value);
+        match self.shape.relate_cell(min_lat, max_lat, min_lon, max_lon) {
+            Relation::CellInsideQuery => Relation::CellInsideQuery,
+            Relation::CellOutsideQuery if self.relation == QueryRelation::Intersects => {
+                Relation::CellOutsideQuery
+            }
+            _ => Relation::CellCrossesQuery,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    fn pack(triangle: Triangle) -> Vec<u8> {
+        LatLonShape::pack(&triangle).unwrap()
+    }
+
+    fn visit(relation: QueryRelation) -> Vec<i32> {
+        let rect = Rectangle::new(0.0, 10.0, 0.0, 10.0).unwrap();
+        let mut visitor = ShapeIntersectVisitor::new(&rect, relation, 4);
+        let inside = pack(Triangle::new(1.0, 1.0, 2.0, 1.0, 1.0, 2.0));
+        let crossing = pack(Triangle::new(1.0, 1.0, 20.0, 1.0, 1.0, 2.0));
+        let outside = pack(Triangle::new(-5.0, -5.0, -5.0, -5.0, -5.0, -5.0));
+        // doc 0 is a polygon within the box, doc 1 a line crossing it, doc 2
+        // has a point in it and another one away and doc 3 a point away
+        for &(doc, ref packed) in &[
+            (0, &inside),
+            (0, &inside),
+            (1, &crossing),
+            (2, &inside),
+            (2, &outside),
+            (3, &outside),
+        ] {
+            visitor.visit_by_packed_value(doc, packed).unwrap();
+        }
+        let matching = visitor.into_matching();
+        BitSetIterator::new(&matching).collect()
+    }
+
+    #[test]
+    fn test_shape_relations() {
+        assert_eq!(visit(QueryRelation::Intersects), vec![0, 1, 2]);
+        assert_eq!(visit(QueryRelation::Within), vec![0]);
+        assert_eq!(visit(QueryRelation::Disjoint), vec![3]);
+    }
+
+    #[test]
+    fn test_compare_cell() {
+        let rect = Rectangle::new(0.0, 10.0, 0.0, 10.0).unwrap();
+        let min = pack(Triangle::new(1.0, 1.0, 2.0, 3.0, 1.0, 2.0));
+        let max = pack(Triangle::new(2.0, 2.0, 4.0, 4.0, 3.0, 3.0));
+        let far_min = pack(Triangle::new(20.0, 20.0, 20.0, 20.0, 20.0, 20.0));
+        let far_max = pack(Triangle::new(30.0, 30.0, 30.0, 30.0, 30.0, 30.0));
+        let visitor = ShapeIntersectVisitor::new(&rect, QueryRelation::Intersects, 1);
+        assert!(visitor.compare(&min, &max) == Relation::CellInsideQuery);
+        assert!(visitor.compare(&far_min, &far_max) == Relation::CellOutsideQuery);
+        // the docs of a cell outside of the shape are not within it
+        let visitor = ShapeIntersectVisitor::new(&rect, QueryRelation::Within, 1);
+        assert!(visitor.compare(&far_min, &far_max) == Relation::CellCrossesQuery);
+    }
+
+    #[test]
+    fn test_new_shape_queries() {
+        let query = LatLonShape::new_box_query::<TestCodec>(
+            "shape".into(),
+            QueryRelation::Within,
+            10.0,
+            20.0,
+            170.0,
+            -170.0,
+        )
+        .unwrap();
+        assert!(query.as_any().is::<LatLonShapeBoundingBoxQuery>());
+        assert!(LatLonShape::new_box_query::<TestCodec>(
+            "shape".into(),
+            QueryRelation::Within,
+            20.0,
+            10.0,
+            0.0,
+            1.0
+        )
+        .is_err());
+        assert!(LatLonShape::new_polygon_query::<TestCodec>(
+            "shape".into(),
+            QueryRelation::Intersects,
+            vec![]
+        )
+        .is_err());
+    }
+}
@@ -17,7 +17,7 @@ use core::search::explanation::Explanation;
 use core::search::query::{Query, TermQuery, Weight};
 use core::search::scorer::{two_phase_next, ConstantScoreScorer, Scorer};
 use core::search::searcher::SearchPlanBuilder;
-use core::search::{DocIterator, NO_MORE_DOCS};
+use core::search::{DocIterator, Matches, NO_MORE_DOCS};
 use core::util::DocId;
 use error::Result;
 use std::fmt;
@@ -82,6 +82,14 @@ impl<C: Codec> Weight<C> for MatchAllDocsWeight {
         MATCH_ALL
     }
 
+    fn count(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        Ok(Some(reader.reader.num_docs()))
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        Ok(Some(reader.reader.max_doc() as usize))
+    }
+
     fn normalize(&mut self, norm: f32, boost: f32) {
         self.norm = norm;
         self.weight = norm * boost;
@@ -114,6 +122,110 @@ impl fmt::Display for MatchAllDocsWeight {
     }
 }
 
+pub const MATCH_NO: &str = "match_no";
+
+/// A query that matches no documents.
+///
+/// It is useful as the rewrite of a query that is known not to match anything,
+/// the reason is reported when explaining a doc.
+pub struct MatchNoDocsQuery {
+    reason: String,
+}
+
+impl MatchNoDocsQuery {
+    pub fn new(reason: &str) -> MatchNoDocsQuery {
+        MatchNoDocsQuery {
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl Default for MatchNoDocsQuery {
+    fn default() -> Self {
+        MatchNoDocsQuery::new("")
+    }
+}
+
+impl<C: Codec> Query<C> for MatchNoDocsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MatchNoDocsWeight {
+            reason: self.reason.clone(),
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for MatchNoDocsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MatchNoDocsQuery(reason: {})", &self.reason)
+    }
+}
+
+struct MatchNoDocsWeight {
+    reason: String,
+}
+
+impl<C: Codec> Weight<C> for MatchNoDocsWeight {
+    fn create_scorer(
+        &self,
+        _leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        Ok(None)
+    }
+
+    fn query_type(&self) -> &'static str {
+        MATCH_NO
+    }
+
+    fn count(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        Ok(Some(0))
+    }
+
+    fn estimate_cost(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        Ok(Some(0))
+    }
+
+    fn normalize(&mut self, _norm: f32, _boost: f32) {}
+
+    fn value_for_normalization(&self) -> f32 {
+        0f32
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, _reader: &LeafReaderContext<'_, C>, _doc: DocId) -> Result<Explanation> {
+        Ok(Explanation::new(
+            false,
+            0f32,
+            format!("{} doesn't match any doc", self),
+            vec![],
+        ))
+    }
+}
+
+impl fmt::Display for MatchNoDocsWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MatchNoDocsWeight(reason: {})", &self.reason)
+    }
+}
+
 /// a `DocIterator` for all docs
 pub struct AllDocsIterator {
     doc: DocId,
@@ -217,6 +329,7 @@ impl<C: Codec> Query<C> for ConstantScoreQuery<C> {
 
 struct ConstantScoreWeight<C: Codec> {
     sub_weight: Box<dyn Weight<C>>,
+    boost: f32,
     query_norm: f32,
     query_weight: f32,
 }
@@ -225,32 +338,58 @@ impl<C: Codec> ConstantScoreWeight<C> {
     pub fn new(sub_weight: Box<dyn Weight<C>>, boost: f32) -> ConstantScoreWeight<C> {
         ConstantScoreWeight {
             sub_weight,
+            boost,
             query_weight: boost,
             query_norm: 1.0f32,
         }
     }
-}
 
-impl<C: Codec> Weight<C> for ConstantScoreWeight<C> {
-    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
-        if let Some(inner_scorer) = self.sub_weight.create_scorer(reader)? {
+    fn constant_scorer(&self, inner_scorer: Option<Box<dyn Scorer>>) -> Option<Box<dyn Scorer>> {
+        inner_scorer.map(|inner_scorer| -> Box<dyn Scorer> {
             let cost = inner_scorer.cost();
-            Ok(Some(Box::new(ConstantScoreScorer {
+            Box::new(ConstantScoreScorer {
                 score: self.query_weight,
                 iterator: inner_scorer,
                 cost,
-            })))
-        } else {
-            Ok(None)
-        }
+            })
+        })
+    }
+}
+
+impl<C: Codec> Weight<C> for ConstantScoreWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        Ok(self.constant_scorer(self.sub_weight.create_scorer(reader)?))
     }
 
     fn query_type(&self) -> &'static str {
         CONSTANT
     }
 
+    fn count(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        self.sub_weight.count(reader)
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        self.sub_weight.estimate_cost(reader)
+    }
+
+    fn uses_lead_cost(&self) -> bool {
+        self.sub_weight.uses_lead_cost()
+    }
+
+    fn create_scorer_with_lead_cost(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        lead_cost: usize,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let inner_scorer = self
+            .sub_weight
+            .create_scorer_with_lead_cost(reader, lead_cost)?;
+        Ok(self.constant_scorer(inner_scorer))
+    }
+
     fn normalize(&mut self, norm: f32, boost: f32) {
-        self.query_weight = norm * boost;
+        self.query_weight = norm * boost * self.boost;
         self.query_norm = norm;
     }
 
@@ -292,6 +431,10 @@ impl<C: Codec> Weight<C> for ConstantScoreWeight<C> {
             ))
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.sub_weight.matches(reader, doc)
+    }
 }
 
 impl<C: Codec> fmt::Display for ConstantScoreWeight<C> {
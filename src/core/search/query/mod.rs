@@ -14,6 +14,8 @@
 #[macro_use]
 pub mod spans;
 
+pub mod intervals;
+
 mod boolean_query;
 
 pub use self::boolean_query::*;
@@ -34,10 +36,20 @@ mod phrase_query;
 
 pub use self::phrase_query::*;
 
+mod point_in_set_query;
+
+pub use self::point_in_set_query::*;
+
 mod point_range_query;
 
 pub use self::point_range_query::*;
 
+mod point_nearest;
+
+mod inet_address_point;
+
+pub use self::inet_address_point::*;
+
 mod query_string;
 
 pub use self::query_string::*;
@@ -50,11 +62,110 @@ mod disjunction_max_query;
 
 pub use self::disjunction_max_query::*;
 
+mod multi_term_query;
+
+pub use self::multi_term_query::*;
+
+mod function_score_query;
+
+pub use self::function_score_query::*;
+
+mod automaton_query;
+
+pub use self::automaton_query::*;
+
+mod fuzzy_query;
+
+pub use self::fuzzy_query::*;
+
+mod more_like_this;
+
+pub use self::more_like_this::*;
+
+mod prefix_query;
+
+pub use self::prefix_query::*;
+
+mod query_dsl;
+
+pub use self::query_dsl::*;
+
+mod query_parser;
+
+pub use self::query_parser::*;
+
+mod regexp_query;
+
+pub use self::regexp_query::*;
+
+mod term_range_query;
+
+pub use self::term_range_query::*;
+
+mod wildcard_query;
+
+pub use self::wildcard_query::*;
+
+mod index_or_doc_values_query;
+
+pub use self::index_or_doc_values_query::*;
+
+mod doc_values_terms_query;
+
+pub use self::doc_values_terms_query::*;
+
+mod doc_values_numbers_query;
+
+pub use self::doc_values_numbers_query::*;
+
+mod field_exists_query;
+
+pub use self::field_exists_query::*;
+
+mod feature_query;
+
+pub use self::feature_query::*;
+
+mod covering_query;
+
+pub use self::covering_query::*;
+
+mod synonym_query;
+
+pub use self::synonym_query::*;
+
+mod blended_term_query;
+
+pub use self::blended_term_query::*;
+
+mod payload_score_query;
+
+pub use self::payload_score_query::*;
+
+mod knn_vector_query;
+
+pub use self::knn_vector_query::*;
+
+mod lat_lon_point_query;
+
+pub use self::lat_lon_point_query::*;
+
+mod lat_lon_doc_values_query;
+
+pub use self::lat_lon_doc_values_query::*;
+
+mod lat_lon_shape_query;
+
+pub use self::lat_lon_shape_query::*;
+
+mod geo_hash_prefix_tree_query;
+
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
 use core::search::scorer::{BatchScorer, Scorer};
 use core::search::searcher::SearchPlanBuilder;
+use core::search::Matches;
 use core::util::DocId;
 
 use error::Result;
@@ -87,9 +198,23 @@ use std::hash::{Hash, Hasher};
 /// * [`BoostQuery`]
 /// * [`PhraseQuery`]
 /// * [`PointRangeQuery`](point_range/struct.PointRangeQuery.html)
+/// * [`PointInSetQuery`]
 /// * [`ConstantScoreQuery`](match_all/struct.ConstantScoreQuery.html)
 /// * [`DisjunctionMaxQuery`](disjunction/struct.DisjunctionMaxQuery.html)
+/// * [`FunctionScoreQuery`]
+/// * [`FuzzyQuery`]
+/// * [`AutomatonQuery`]
 /// * [`MatchAllDocsQuery`](match_all/struct.MatchAllDocsQuery.html)
+/// * [`MatchNoDocsQuery`]
+/// * [`DocValuesFieldExistsQuery`] and [`NormsFieldExistsQuery`]
+/// * [`PrefixQuery`]
+/// * [`RegexpQuery`]
+/// * [`TermRangeQuery`]
+/// * [`WildcardQuery`]
+///
+/// User-entered query strings can be turned into queries with [`QueryParser`], and JSON
+/// queries with [`QueryDslParser`]. [`MoreLikeThis`] builds queries finding the docs
+/// similar to a given doc or text.
 ///
 /// See also the family of [`Span Queries`](spans/index.html)
 pub trait Query<C: Codec>: Display {
@@ -146,6 +271,10 @@ pub trait Weight<C: Codec>: Display {
     }
 
     /// Assigns the query normalization factor and boost to this.
+    ///
+    /// `boost` is the product of the boosts of all the enclosing queries, a weight
+    /// that carries its own boost should multiply it in and pass the result down
+    /// to its sub weights rather than replacing it.
     fn normalize(&mut self, norm: f32, boost: f32);
 
     /// The value for normalization of contained query clauses (e.g. sum of squared weights).
@@ -157,6 +286,56 @@ pub trait Weight<C: Codec>: Display {
         None
     }
 
+    /// Returns the number of docs of the segment matching this weight when it
+    /// can be computed without iterating the matches, e.g. from the index
+    /// statistics, `None` otherwise.
+    fn count(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        Ok(None)
+    }
+
+    /// Returns an estimate of the number of docs of the segment matching this weight,
+    /// computed without creating the scorer, `None` if it can't be estimated cheaply.
+    fn estimate_cost(&self, _reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Whether this weight creates cheaper scorers when it knows the cost of the
+    /// scorers it is intersected with, see `create_scorer_with_lead_cost`.
+    fn uses_lead_cost(&self) -> bool {
+        false
+    }
+
+    /// Creates the scorer of a required clause of a conjunction, `lead_cost` being
+    /// the cost of the most selective scorer of the conjunction, which leads the
+    /// iteration: the scorer created is then only advanced to about `lead_cost` docs.
+    fn create_scorer_with_lead_cost(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        _lead_cost: usize,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.create_scorer(reader)
+    }
+
+    /// Creates the scorer of a top-level weight whose collector only needs the top
+    /// scoring docs, see `Collector::needs_top_scores_only`: the scorer may skip the
+    /// docs scoring below the minimum competitive score set by the collector.
+    fn create_top_scores_scorer(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.create_scorer(reader)
+    }
+
     /// An explanation of the score computation for the named document.
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation>;
+
+    /// Returns the matches of this weight on the named document: which fields,
+    /// positions and offsets matched which leaf queries, or `None` if the document
+    /// doesn't match.
+    ///
+    /// The default implementation only checks the document matches with the scorer
+    /// and reports no term.
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        Matches::from_scorer(self.create_scorer(reader)?, doc)
+    }
 }
@@ -0,0 +1,378 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+use core::analysis::{TokenStream, WhitespaceTokenizer};
+use core::codec::{Codec, Fields, TermIterator, Terms};
+use core::doc::{Fieldable, Term};
+use core::index::reader::IndexReader;
+use core::search::query::{BooleanQuery, Query, TermQuery};
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Ignore terms with less than this frequency in the source doc.
+pub const DEFAULT_MIN_TERM_FREQ: i32 = 2;
+/// Ignore words which do not occur in at least this many docs.
+pub const DEFAULT_MIN_DOC_FREQ: i32 = 5;
+/// Ignore words which occur in more than this many docs.
+pub const DEFAULT_MAX_DOC_FREQ: i32 = i32::max_value();
+/// Return a query with no more than this many terms.
+pub const DEFAULT_MAX_QUERY_TERMS: usize = 25;
+/// The maximum number of tokens to parse in each stored field that is not stored with
+/// term vectors.
+pub const DEFAULT_MAX_NUM_TOKENS_PARSED: usize = 5000;
+
+/// A term selected by `MoreLikeThis`, along with the statistics it was scored with.
+#[derive(Debug)]
+pub struct ScoreTerm {
+    pub term: Term,
+    pub score: f32,
+    pub term_freq: i32,
+    pub doc_freq: i32,
+}
+
+/// Generate "more like this" similarity queries.
+///
+/// The terms of the source document (or text) are weighted by `tf * idf`, and the
+/// top weighted ones are OR'ed together in a `BooleanQuery`. The term frequencies
+/// are read from the term vectors of the document, or, for the fields not stored
+/// with term vectors, by splitting the stored values on whitespaces.
+///
+/// ```ignore
+/// let mut mlt = MoreLikeThis::new(vec!["title".into(), "body".into()]);
+/// mlt.set_min_doc_freq(2);
+/// let query = mlt.like(searcher.reader(), doc_id)?;
+/// ```
+pub struct MoreLikeThis {
+    field_names: Vec<String>,
+    min_term_freq: i32,
+    min_doc_freq: i32,
+    max_doc_freq: i32,
+    min_word_len: usize,
+    max_word_len: usize,
+    max_query_terms: usize,
+    max_num_tokens_parsed: usize,
+    stop_words: HashSet<String>,
+    boost: bool,
+    boost_factor: f32,
+}
+
+impl MoreLikeThis {
+    pub fn new(field_names: Vec<String>) -> MoreLikeThis {
+        MoreLikeThis {
+            field_names,
+            min_term_freq: DEFAULT_MIN_TERM_FREQ,
+            min_doc_freq: DEFAULT_MIN_DOC_FREQ,
+            max_doc_freq: DEFAULT_MAX_DOC_FREQ,
+            min_word_len: 0,
+            max_word_len: 0,
+            max_query_terms: DEFAULT_MAX_QUERY_TERMS,
+            max_num_tokens_parsed: DEFAULT_MAX_NUM_TOKENS_PARSED,
+            stop_words: HashSet::new(),
+            boost: false,
+            boost_factor: 1.0,
+        }
+    }
+
+    pub fn set_min_term_freq(&mut self, min_term_freq: i32) {
+        self.min_term_freq = min_term_freq;
+    }
+
+    pub fn set_min_doc_freq(&mut self, min_doc_freq: i32) {
+        self.min_doc_freq = min_doc_freq;
+    }
+
+    pub fn set_max_doc_freq(&mut self, max_doc_freq: i32) {
+        self.max_doc_freq = max_doc_freq;
+    }
+
+    /// Set the maximum percentage, in [0, 100], of the docs a word may occur in.
+    pub fn set_max_doc_freq_pct(&mut self, max_num_docs: i32, max_percentage: i32) {
+        self.max_doc_freq = (i64::from(max_percentage) * i64::from(max_num_docs) / 100) as i32;
+    }
+
+    /// Words shorter than this are ignored, 0 disables the check.
+    pub fn set_min_word_len(&mut self, min_word_len: usize) {
+        self.min_word_len = min_word_len;
+    }
+
+    /// Words longer than this are ignored, 0 disables the check.
+    pub fn set_max_word_len(&mut self, max_word_len: usize) {
+        self.max_word_len = max_word_len;
+    }
+
+    pub fn set_max_query_terms(&mut self, max_query_terms: usize) {
+        self.max_query_terms = max_query_terms;
+    }
+
+    pub fn set_max_num_tokens_parsed(&mut self, max_num_tokens_parsed: usize) {
+        self.max_num_tokens_parsed = max_num_tokens_parsed;
+    }
+
+    pub fn set_stop_words(&mut self, stop_words: HashSet<String>) {
+        self.stop_words = stop_words;
+    }
+
+    /// Whether the term queries are boosted by their relative score, the best term
+    /// getting a boost of `boost_factor`.
+    pub fn set_boost(&mut self, boost: bool) {
+        self.boost = boost;
+    }
+
+    pub fn set_boost_factor(&mut self, boost_factor: f32) {
+        self.boost_factor = boost_factor;
+    }
+
+    /// Return a query that will return docs like the doc `doc_id`.
+    pub fn like<R: IndexReader + ?Sized>(
+        &self,
+        reader: &R,
+        doc_id: DocId,
+    ) -> Result<Box<dyn Query<R::Codec>>> {
+        let terms = self.interesting_terms(reader, doc_id)?;
+        self.create_query(terms)
+    }
+
+    /// Return a query that will return docs like `text`, as if it was the content of
+    /// `field`.
+    pub fn like_text<R: IndexReader + ?Sized>(
+        &self,
+        reader: &R,
+        field: &str,
+        text: &str,
+    ) -> Result<Box<dyn Query<R::Codec>>> {
+        let terms = self.interesting_text_terms(reader, field, text)?;
+        self.create_query(terms)
+    }
+
+    /// The top weighted terms of the doc `doc_id`, best first.
+    pub fn interesting_terms<R: IndexReader + ?Sized>(
+        &self,
+        reader: &R,
+        doc_id: DocId,
+    ) -> Result<Vec<ScoreTerm>> {
+        let mut field_term_freqs = HashMap::with_capacity(self.field_names.len());
+        let vectors = reader.term_vector(doc_id)?;
+        for field in &self.field_names {
+            let mut term_freqs = HashMap::new();
+            let vector = match vectors {
+                Some(ref vectors) => vectors.terms(field)?,
+                None => None,
+            };
+            if let Some(vector) = vector {
+                self.add_term_freqs(&mut term_freqs, &vector)?;
+            } else {
+                let doc = reader.document(doc_id, &[field.clone()])?;
+                for stored in &doc.fields {
+                    if stored.field.name() != field {
+                        continue;
+                    }
+                    if let Some(text) = stored.field.field_data().and_then(|v| v.get_string()) {
+                        self.add_text_term_freqs(&mut term_freqs, text)?;
+                    }
+                }
+            }
+            field_term_freqs.insert(field.clone(), term_freqs);
+        }
+        self.score_terms(field_term_freqs, reader.num_docs(), |t| doc_freq(reader, t))
+    }
+
+    /// The top weighted terms of `text`, best first.
+    pub fn interesting_text_terms<R: IndexReader + ?Sized>(
+        &self,
+        reader: &R,
+        field: &str,
+        text: &str,
+    ) -> Result<Vec<ScoreTerm>> {
+        let mut term_freqs = HashMap::new();
+        self.add_text_term_freqs(&mut term_freqs, text)?;
+        let mut field_term_freqs = HashMap::with_capacity(1);
+        field_term_freqs.insert(field.to_string(), term_freqs);
+        self.score_terms(field_term_freqs, reader.num_docs(), |t| doc_freq(reader, t))
+    }
+
+    fn add_term_freqs<T: Terms>(
+        &self,
+        term_freqs: &mut HashMap<String, i32>,
+        vector: &T,
+    ) -> Result<()> {
+        let mut terms_iter = vector.iterator()?;
+        while let Some(bytes) = terms_iter.next()? {
+            let text = String::from_utf8(bytes)?;
+            if self.is_noise_word(&text) {
+                continue;
+            }
+            let freq = terms_iter.total_term_freq()? as i32;
+            *term_freqs.entry(text).or_insert(0) += freq;
+        }
+        Ok(())
+    }
+
+    fn add_text_term_freqs(&self, term_freqs: &mut HashMap<String, i32>, text: &str) -> Result<()> {
+        let mut tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.to_string())));
+        tokenizer.reset()?;
+        let mut tokens = 0;
+        while tokenizer.increment_token()? {
+            tokens += 1;
+            if tokens > self.max_num_tokens_parsed {
+                break;
+            }
+            let word = String::from_utf8(
+                tokenizer
+                    .term_bytes_attribute()
+                    .get_bytes_ref()
+                    .bytes()
+                    .to_vec(),
+            )?;
+            if self.is_noise_word(&word) {
+                continue;
+            }
+            *term_freqs.entry(word).or_insert(0) += 1;
+        }
+        tokenizer.end()
+    }
+
+    fn is_noise_word(&self, word: &str) -> bool {
+        let len = word.chars().count();
+        (self.min_word_len > 0 && len < self.min_word_len)
+            || (self.max_word_len > 0 && len > self.max_word_len)
+            || self.stop_words.contains(word)
+    }
+
+    fn score_terms<F>(
+        &self,
+        field_term_freqs: HashMap<String, HashMap<String, i32>>,
+        num_docs: i32,
+        mut doc_freq_of: F,
+    ) -> Result<Vec<ScoreTerm>>
+    where
+        F: FnMut(&Term) -> Result<i32>,
+    {
+        let mut terms = Vec::new();
+        for (field, term_freqs) in field_term_freqs {
+            for (text, term_freq) in term_freqs {
+                if self.min_term_freq > 0 && term_freq < self.min_term_freq {
+                    continue;
+                }
+                let term = Term::new(field.clone(), text.into_bytes());
+                let doc_freq = doc_freq_of(&term)?;
+                if self.min_doc_freq > 0 && doc_freq < self.min_doc_freq {
+                    continue;
+                }
+                if doc_freq > self.max_doc_freq || doc_freq == 0 {
+                    continue;
+                }
+                // the classic tf-idf weighting
+                let idf = (f64::from(num_docs + 1) / f64::from(doc_freq + 1)).ln() + 1.0;
+                terms.push(ScoreTerm {
+                    term,
+                    score: (f64::from(term_freq) * idf) as f32,
+                    term_freq,
+                    doc_freq,
+                });
+            }
+        }
+        terms.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.term.cmp(&b.term))
+        });
+        terms.truncate(self.max_query_terms);
+        Ok(terms)
+    }
+
+    fn create_query<C: Codec>(&self, terms: Vec<ScoreTerm>) -> Result<Box<dyn Query<C>>> {
+        if terms.is_empty() {
+            bail!(IllegalArgument(
+                "more like this found no interesting terms".into()
+            ));
+        }
+        let best_score = terms[0].score;
+        let shoulds = terms
+            .into_iter()
+            .map(|t| -> Box<dyn Query<C>> {
+                let boost = if self.boost && best_score > 0.0 {
+                    self.boost_factor * t.score / best_score
+                } else {
+                    1.0
+                };
+                Box::new(TermQuery::new(t.term, boost, None))
+            })
+            .collect();
+        BooleanQuery::build(vec![], shoulds, vec![])
+    }
+}
+
+fn doc_freq<R: IndexReader + ?Sized>(reader: &R, term: &Term) -> Result<i32> {
+    let mut doc_freq = 0;
+    for ctx in reader.leaves() {
+        doc_freq += ctx.reader.doc_freq(term)?;
+    }
+    Ok(doc_freq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    #[test]
+    fn test_score_terms() {
+        let mut mlt = MoreLikeThis::new(vec!["body".into()]);
+        mlt.set_min_doc_freq(1);
+        mlt.set_max_query_terms(2);
+        mlt.set_min_word_len(2);
+        mlt.set_stop_words(vec!["the".to_string()].into_iter().collect());
+
+        let mut term_freqs = HashMap::new();
+        mlt.add_text_term_freqs(
+            &mut term_freqs,
+            "the fox and the dog a fox and a rare dog fox",
+        )
+        .unwrap();
+        assert_eq!(term_freqs.get("fox"), Some(&3));
+        assert_eq!(term_freqs.get("dog"), Some(&2));
+        assert!(!term_freqs.contains_key("the"));
+        assert!(!term_freqs.contains_key("a"));
+
+        let mut field_term_freqs = HashMap::new();
+        field_term_freqs.insert("body".to_string(), term_freqs);
+        let doc_freqs: HashMap<&str, i32> = vec![("fox", 5), ("dog", 1), ("and", 90)]
+            .into_iter()
+            .collect();
+        let terms = mlt
+            .score_terms(field_term_freqs, 100, |t| {
+                Ok(*doc_freqs.get(t.text()?.as_str()).unwrap_or(&0))
+            })
+            .unwrap();
+
+        // "rare" is below the min term freq, "and" is too common to beat "fox" and "dog"
+        assert_eq!(terms.len(), 2);
+        assert_eq!(terms[0].term.text().unwrap(), "fox");
+        assert_eq!(terms[1].term.text().unwrap(), "dog");
+        assert!(terms[0].score > terms[1].score);
+
+        mlt.set_boost(true);
+        let query: Box<dyn Query<TestCodec>> = mlt.create_query(terms).unwrap();
+        let query = query.to_string();
+        assert!(query.starts_with(
+            "BooleanQuery(must: [], should: [TermQuery(field: body, term: fox, boost: 1), \
+             TermQuery(field: body, term: dog, boost: 0."
+        ));
+    }
+}
@@ -0,0 +1,393 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{Codec, CodecPostingIterator, CodecTerms};
+use core::codec::{PostingIteratorFlags, TermIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{BooleanQuery, MatchNoDocsQuery, Query, TermQuery, Weight};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIdSet, DocIterator};
+use core::util::{DocId, DocIdSetBuilder};
+
+use error::{ErrorKind::IllegalState, Result};
+
+use std::cmp::{min, Ordering};
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The maximum number of term clauses a scoring rewrite may expand to.
+pub const MAX_CLAUSE_COUNT: usize = 1024;
+
+/// Defines how a `MultiTermQuery` turns the terms it matches into a `Weight`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RewriteMethod {
+    /// Collect the documents of every matched term into a doc id set, each one
+    /// gets a constant score equal to the query boost. The number of matched
+    /// terms is unbounded, this is the default.
+    ConstantScore,
+    /// Score the matched terms as a disjunction of `TermQuery`s, creating the
+    /// weight fails once more than `MAX_CLAUSE_COUNT` terms are matched.
+    ScoringBoolean,
+    /// Score only the best `size` matched terms as a disjunction of `TermQuery`s,
+    /// ranked by `MultiTermQuery::term_boost` and then by term order. `size` is
+    /// capped to `MAX_CLAUSE_COUNT`.
+    TopTerms(usize),
+}
+
+impl Default for RewriteMethod {
+    fn default() -> Self {
+        RewriteMethod::ConstantScore
+    }
+}
+
+/// An abstract query that matches documents containing a subset of terms
+/// provided by a `FilteredTermIterator` enumeration.
+///
+/// This query cannot be used directly; you must implement it for a concrete
+/// query (such as `RegexpQuery`) that provides the term iterator, and let its
+/// `Query::create_weight` return a `MultiTermQueryConstantScoreWeight`.
+pub trait MultiTermQuery<C: Codec>: fmt::Display {
+    type Iter: TermIterator<Postings = CodecPostingIterator<C>>;
+
+    /// Returns the field name for this query
+    fn field(&self) -> &str;
+
+    /// Construct the iterator to be used, expanding the pattern term against
+    /// the terms of the field in one segment.
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter>;
+
+    /// The boost of the term `terms` is positioned on when the query is rewritten
+    /// into term clauses, the best terms are kept first by `RewriteMethod::TopTerms`.
+    fn term_boost(&self, _terms: &Self::Iter) -> f32 {
+        1f32
+    }
+}
+
+/// Create the weight of `query` according to `rewrite_method`.
+///
+/// The scoring rewrites expand the query against the terms of every segment
+/// of the searcher up front, and score the collected terms as a `BooleanQuery`
+/// of `TermQuery` should clauses.
+pub fn multi_term_weight<C, Q>(
+    query: &Q,
+    query_type: &'static str,
+    rewrite_method: RewriteMethod,
+    searcher: &dyn SearchPlanBuilder<C>,
+    needs_scores: bool,
+) -> Result<Box<dyn Weight<C>>>
+where
+    C: Codec,
+    Q: MultiTermQuery<C> + Clone + 'static,
+{
+    let max_size = match rewrite_method {
+        RewriteMethod::ConstantScore => {
+            return Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+                query.clone(),
+                query_type,
+            )));
+        }
+        RewriteMethod::ScoringBoolean => None,
+        RewriteMethod::TopTerms(size) => Some(min(size, MAX_CLAUSE_COUNT)),
+    };
+
+    let terms = collect_terms(query, searcher, max_size)?;
+    if terms.is_empty() {
+        let no_match = MatchNoDocsQuery::new(&format!("no terms matched by {}", query));
+        return no_match.create_weight(searcher, needs_scores);
+    }
+    let shoulds = terms
+        .into_iter()
+        .map(|t| {
+            let term = Term::new(query.field().to_string(), t.term);
+            Box::new(TermQuery::new(term, t.boost, None)) as Box<dyn Query<C>>
+        })
+        .collect();
+    BooleanQuery::build(vec![], shoulds, vec![])?.create_weight(searcher, needs_scores)
+}
+
+/// Collect the distinct terms matched by `query` over all the segments, keeping
+/// only the best `max_size` ones if set. The terms are returned in term order.
+fn collect_terms<C: Codec, Q: MultiTermQuery<C>>(
+    query: &Q,
+    searcher: &dyn SearchPlanBuilder<C>,
+    max_size: Option<usize>,
+) -> Result<Vec<ScoreTerm>> {
+    let mut collector = TermCollector::new(max_size);
+    for leaf in searcher.leaves() {
+        let terms = match leaf.reader.terms(query.field())? {
+            Some(terms) => terms,
+            None => continue,
+        };
+        let mut term_iter = query.term_iterator(&terms)?;
+        while let Some(term) = term_iter.next()? {
+            if collector.contains(&term) {
+                continue;
+            }
+            let boost = query.term_boost(&term_iter);
+            if !collector.collect(term, boost) {
+                bail!(IllegalState(format!(
+                    "too many clauses: {} matches more than {} terms",
+                    query, MAX_CLAUSE_COUNT
+                )));
+            }
+        }
+    }
+    Ok(collector.into_terms())
+}
+
+/// Collects distinct terms, either the best `max_size` ones or at most
+/// `MAX_CLAUSE_COUNT` of them when unbounded.
+struct TermCollector {
+    // the worst collected term is on the top of the queue
+    queue: BinaryHeap<ScoreTerm>,
+    collected: HashSet<Vec<u8>>,
+    max_size: Option<usize>,
+}
+
+impl TermCollector {
+    fn new(max_size: Option<usize>) -> TermCollector {
+        TermCollector {
+            queue: BinaryHeap::new(),
+            collected: HashSet::new(),
+            max_size,
+        }
+    }
+
+    fn contains(&self, term: &[u8]) -> bool {
+        self.collected.contains(term)
+    }
+
+    /// Returns false if the term can't be collected because the clause limit
+    /// is exceeded.
+    fn collect(&mut self, term: Vec<u8>, boost: f32) -> bool {
+        let candidate = ScoreTerm { term, boost };
+        match self.max_size {
+            Some(size) if self.queue.len() >= size => {
+                let better = match self.queue.peek() {
+                    Some(worst) => candidate < *worst,
+                    None => false,
+                };
+                if better {
+                    let evicted = self.queue.pop().unwrap();
+                    self.collected.remove(&evicted.term);
+                    self.push(candidate);
+                }
+            }
+            None if self.queue.len() >= MAX_CLAUSE_COUNT => {
+                return false;
+            }
+            _ => self.push(candidate),
+        }
+        true
+    }
+
+    fn push(&mut self, term: ScoreTerm) {
+        self.collected.insert(term.term.clone());
+        self.queue.push(term);
+    }
+
+    fn into_terms(self) -> Vec<ScoreTerm> {
+        let mut terms = self.queue.into_vec();
+        terms.sort_by(|a, b| a.term.cmp(&b.term));
+        terms
+    }
+}
+
+/// A term collected by a scoring rewrite, a term compares lower than another
+/// if it is better, that is it has a higher boost or an equal boost and sorts
+/// first in term order.
+struct ScoreTerm {
+    term: Vec<u8>,
+    boost: f32,
+}
+
+impl PartialEq for ScoreTerm {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoreTerm {}
+
+impl PartialOrd for ScoreTerm {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoreTerm {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .boost
+            .partial_cmp(&self.boost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.term.cmp(&other.term))
+    }
+}
+
+/// A `Weight` that collects the documents of all the terms matched by a
+/// `MultiTermQuery` into a doc id set, every matched document gets a constant
+/// score equal to the query boost.
+pub struct MultiTermQueryConstantScoreWeight<C: Codec, Q: MultiTermQuery<C>> {
+    query: Q,
+    query_type: &'static str,
+    weight: f32,
+    norm: f32,
+    _codec: PhantomData<C>,
+}
+
+impl<C: Codec, Q: MultiTermQuery<C>> MultiTermQueryConstantScoreWeight<C, Q> {
+    pub fn new(query: Q, query_type: &'static str) -> MultiTermQueryConstantScoreWeight<C, Q> {
+        MultiTermQueryConstantScoreWeight {
+            query,
+            query_type,
+            weight: 1f32,
+            norm: 1f32,
+            _codec: PhantomData,
+        }
+    }
+}
+
+impl<C: Codec, Q: MultiTermQuery<C>> Weight<C> for MultiTermQueryConstantScoreWeight<C, Q> {
+    fn create_scorer(
+        &self,
+        leaf_reader_ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let reader = leaf_reader_ctx.reader;
+        let terms = match reader.terms(self.query.field())? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+
+        let mut term_iter = self.query.term_iterator(&terms)?;
+        if term_iter.next()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut builder = DocIdSetBuilder::from_terms(reader.max_doc(), &terms)?;
+        loop {
+            let mut postings = term_iter.postings_with_flags(PostingIteratorFlags::NONE)?;
+            builder.add(&mut postings)?;
+            if term_iter.next()?.is_none() {
+                break;
+            }
+        }
+
+        if let Some(iterator) = builder.build().iterator()? {
+            let cost = iterator.cost();
+            Ok(Some(Box::new(ConstantScoreScorer::new(
+                self.weight,
+                iterator,
+                cost,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.query_type
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self.query),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self.query, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl<C: Codec, Q: MultiTermQuery<C>> fmt::Display for MultiTermQueryConstantScoreWeight<C, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MultiTermQueryConstantScoreWeight(query: {}, weight: {})",
+            &self.query, self.weight
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collected(collector: TermCollector) -> Vec<(String, f32)> {
+        collector
+            .into_terms()
+            .into_iter()
+            .map(|t| (String::from_utf8(t.term).unwrap(), t.boost))
+            .collect()
+    }
+
+    #[test]
+    fn test_top_terms_collector() {
+        let mut collector = TermCollector::new(Some(2));
+        assert!(collector.collect(b"lucent".to_vec(), 0.5));
+        assert!(collector.collect(b"lucene".to_vec(), 1.0));
+        assert!(collector.collect(b"lucine".to_vec(), 0.5));
+        assert!(collector.collect(b"luce".to_vec(), 0.25));
+        assert!(collector.contains(b"lucene"));
+        assert!(!collector.contains(b"lucent"));
+        assert_eq!(
+            collected(collector),
+            vec![("lucene".to_string(), 1.0), ("lucine".to_string(), 0.5)]
+        );
+    }
+
+    #[test]
+    fn test_scoring_collector_clause_limit() {
+        let mut collector = TermCollector::new(None);
+        for i in 0..MAX_CLAUSE_COUNT {
+            assert!(collector.collect(format!("term{:04}", i).into_bytes(), 1.0));
+        }
+        assert!(!collector.collect(b"overflow".to_vec(), 1.0));
+        assert_eq!(collector.into_terms().len(), MAX_CLAUSE_COUNT);
+    }
+}
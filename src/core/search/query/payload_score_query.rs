@@ -0,0 +1,507 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::{Codec, CodecPostingIterator, PostingIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    PostingsFlag, SpanCollector, SpanQuery, SpanQueryEnum, SpanWeight, SpanWeightEnum, Spans,
+    SpansEnum, NO_MORE_POSITIONS,
+};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::SimScorer;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::Result;
+
+pub const PAYLOAD_SCORE: &str = "payload_score";
+
+/// Converts the payloads of the matching positions into score factors.
+pub trait PayloadDecoder: fmt::Display + Send + Sync {
+    fn compute_payload_factor(&self, payload: &[u8]) -> f32;
+}
+
+/// Decodes the payloads written as big endian `f32`, positions without
+/// payload have a factor of 1.
+pub struct FloatPayloadDecoder;
+
+impl PayloadDecoder for FloatPayloadDecoder {
+    fn compute_payload_factor(&self, payload: &[u8]) -> f32 {
+        if payload.len() < 4 {
+            return 1.0;
+        }
+        let bits = u32::from(payload[0]) << 24
+            | u32::from(payload[1]) << 16
+            | u32::from(payload[2]) << 8
+            | u32::from(payload[3]);
+        f32::from_bits(bits)
+    }
+}
+
+impl fmt::Display for FloatPayloadDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FloatPayloadDecoder")
+    }
+}
+
+/// How the payload factors of all the matching positions of a doc are
+/// combined into the payload score of the doc.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadFunction {
+    Min,
+    Max,
+    Average,
+    Sum,
+}
+
+impl PayloadFunction {
+    /// Combine the score of the payloads seen so far with the payload factor of
+    /// a new matching position.
+    pub fn current_score(
+        self,
+        num_payloads_seen: usize,
+        current_score: f32,
+        payload_factor: f32,
+    ) -> f32 {
+        if num_payloads_seen == 0 {
+            return payload_factor;
+        }
+        match self {
+            PayloadFunction::Min => current_score.min(payload_factor),
+            PayloadFunction::Max => current_score.max(payload_factor),
+            PayloadFunction::Average | PayloadFunction::Sum => current_score + payload_factor,
+        }
+    }
+
+    /// The payload score of a doc, 1 if it has no payloads.
+    pub fn doc_score(self, num_payloads_seen: usize, payload_score: f32) -> f32 {
+        if num_payloads_seen == 0 {
+            return 1.0;
+        }
+        match self {
+            PayloadFunction::Average => payload_score / num_payloads_seen as f32,
+            _ => payload_score,
+        }
+    }
+}
+
+/// A query that wraps a span query and scores the matching docs with the
+/// payloads of the matching positions.
+///
+/// Every payload is converted into a factor with the `PayloadDecoder` and the
+/// factors of a doc are combined with the `PayloadFunction`. If
+/// `include_span_score` is true, the payload score is multiplied by the score
+/// of the wrapped query.
+pub struct PayloadScoreQuery {
+    wrapped: SpanQueryEnum,
+    function: PayloadFunction,
+    decoder: Arc<dyn PayloadDecoder>,
+    include_span_score: bool,
+}
+
+impl PayloadScoreQuery {
+    pub fn new(
+        wrapped: SpanQueryEnum,
+        function: PayloadFunction,
+        decoder: Arc<dyn PayloadDecoder>,
+        include_span_score: bool,
+    ) -> PayloadScoreQuery {
+        PayloadScoreQuery {
+            wrapped,
+            function,
+            decoder,
+            include_span_score,
+        }
+    }
+
+    pub fn wrapped(&self) -> &SpanQueryEnum {
+        &self.wrapped
+    }
+
+    pub fn function(&self) -> PayloadFunction {
+        self.function
+    }
+}
+
+impl<C: Codec> Query<C> for PayloadScoreQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let inner = self.wrapped.span_weight(searcher, needs_scores)?;
+        Ok(Box::new(PayloadScoreWeight {
+            inner,
+            function: self.function,
+            decoder: Arc::clone(&self.decoder),
+            include_span_score: self.include_span_score,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        Query::<C>::extract_terms(&self.wrapped)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for PayloadScoreQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PayloadScoreQuery(wrapped: {}, function: {:?}, decoder: {}, include_span_score: {})",
+            &self.wrapped, self.function, &self.decoder, self.include_span_score
+        )
+    }
+}
+
+struct PayloadScoreWeight<C: Codec> {
+    inner: SpanWeightEnum<C>,
+    function: PayloadFunction,
+    decoder: Arc<dyn PayloadDecoder>,
+    include_span_score: bool,
+    needs_scores: bool,
+}
+
+impl<C: Codec> PayloadScoreWeight<C> {
+    fn payload_scorer(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<PayloadSpanScorer<SpansEnum<CodecPostingIterator<C>>>>> {
+        if let Some(spans) = self.inner.get_spans(reader, &PostingsFlag::Payloads)? {
+            let doc_scorer = self.inner.sim_scorer(reader.reader)?;
+            Ok(Some(PayloadSpanScorer::new(
+                spans,
+                doc_scorer,
+                PayloadSpanCollector::new(self.function, Arc::clone(&self.decoder)),
+                self.include_span_score,
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: Codec> Weight<C> for PayloadScoreWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        if let Some(scorer) = self.payload_scorer(reader)? {
+            Ok(Some(Box::new(scorer)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        PAYLOAD_SCORE
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.inner.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.inner.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        if let Some(mut scorer) = self.payload_scorer(reader)? {
+            if scorer.advance(doc)? == doc {
+                let score = scorer.score()?;
+                let payload_expl = Explanation::new(
+                    true,
+                    scorer.payload_score(),
+                    format!(
+                        "{:?} of {} payloads decoded by {}",
+                        self.function, scorer.collector.num_payloads_seen, &self.decoder
+                    ),
+                    vec![],
+                );
+                if self.include_span_score {
+                    let span_expl = self.inner.explain(reader, doc)?;
+                    return Ok(Explanation::new(
+                        true,
+                        score,
+                        "PayloadSpanQuery, product of:".to_string(),
+                        vec![span_expl, payload_expl],
+                    ));
+                }
+                return Ok(Explanation::new(
+                    true,
+                    score,
+                    "PayloadSpanQuery, payload score of:".to_string(),
+                    vec![payload_expl],
+                ));
+            }
+        }
+        Ok(Explanation::new(
+            false,
+            0.0f32,
+            "no matching term".to_string(),
+            vec![],
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for PayloadScoreWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PayloadScoreWeight(inner: {}, function: {:?}, include_span_score: {})",
+            &self.inner, self.function, self.include_span_score
+        )
+    }
+}
+
+/// Accumulates the payload factors of the positions of the current span.
+struct PayloadSpanCollector {
+    function: PayloadFunction,
+    decoder: Arc<dyn PayloadDecoder>,
+    num_payloads_seen: usize,
+    payload_score: f32,
+}
+
+impl PayloadSpanCollector {
+    fn new(function: PayloadFunction, decoder: Arc<dyn PayloadDecoder>) -> PayloadSpanCollector {
+        PayloadSpanCollector {
+            function,
+            decoder,
+            num_payloads_seen: 0,
+            payload_score: 0.0,
+        }
+    }
+
+    /// Forget the payloads of the previous doc.
+    fn start_doc(&mut self) {
+        self.num_payloads_seen = 0;
+        self.payload_score = 0.0;
+    }
+
+    fn doc_score(&self) -> f32 {
+        self.function
+            .doc_score(self.num_payloads_seen, self.payload_score)
+    }
+}
+
+impl SpanCollector for PayloadSpanCollector {
+    fn collect_leaf(
+        &mut self,
+        postings: &impl PostingIterator,
+        _position: i32,
+        _term: &Term,
+    ) -> Result<()> {
+        let payload = postings.payload()?;
+        if payload.is_empty() {
+            return Ok(());
+        }
+        let factor = self.decoder.compute_payload_factor(&payload);
+        self.payload_score =
+            self.function
+                .current_score(self.num_payloads_seen, self.payload_score, factor);
+        self.num_payloads_seen += 1;
+        Ok(())
+    }
+
+    fn reset(&mut self) {}
+}
+
+struct PayloadSpanScorer<S: Spans> {
+    spans: S,
+    doc_scorer: Option<Box<dyn SimScorer>>,
+    collector: PayloadSpanCollector,
+    include_span_score: bool,
+    // accumulated sloppy freq of the current doc
+    freq: f32,
+    last_scored_doc: DocId,
+}
+
+impl<S: Spans> PayloadSpanScorer<S> {
+    fn new(
+        spans: S,
+        doc_scorer: Option<Box<dyn SimScorer>>,
+        collector: PayloadSpanCollector,
+        include_span_score: bool,
+    ) -> Self {
+        PayloadSpanScorer {
+            spans,
+            doc_scorer,
+            collector,
+            include_span_score,
+            freq: 0.0,
+            last_scored_doc: -1,
+        }
+    }
+
+    fn ensure_freq(&mut self) -> Result<()> {
+        let current_doc = self.spans.doc_id();
+        if self.last_scored_doc != current_doc {
+            self.set_freq_current_doc()?;
+            self.last_scored_doc = current_doc;
+        }
+        Ok(())
+    }
+
+    fn set_freq_current_doc(&mut self) -> Result<()> {
+        self.freq = 0.0;
+        self.collector.start_doc();
+        self.spans.do_start_current_doc()?;
+
+        let mut start_pos = self.spans.next_start_position()?;
+        debug_assert_ne!(start_pos, NO_MORE_POSITIONS);
+        while start_pos != NO_MORE_POSITIONS {
+            if let Some(ref doc_scorer) = self.doc_scorer {
+                self.freq += doc_scorer.compute_slop_factor(self.spans.width());
+            }
+            self.collector.reset();
+            self.spans.collect(&mut self.collector)?;
+            self.spans.do_current_spans()?;
+            start_pos = self.spans.next_start_position()?;
+        }
+        Ok(())
+    }
+
+    fn payload_score(&self) -> f32 {
+        self.collector.doc_score()
+    }
+}
+
+impl<S: Spans> Scorer for PayloadSpanScorer<S> {
+    fn score(&mut self) -> Result<f32> {
+        self.ensure_freq()?;
+        let payload_score = self.payload_score();
+        if !self.include_span_score {
+            return Ok(payload_score);
+        }
+        let doc = self.spans.doc_id();
+        let freq = self.freq;
+        let span_score = match self.doc_scorer {
+            Some(ref mut doc_scorer) => doc_scorer.score(doc, freq)?,
+            None => 1.0,
+        };
+        Ok(span_score * payload_score)
+    }
+}
+
+impl<S: Spans> DocIterator for PayloadSpanScorer<S> {
+    fn doc_id(&self) -> DocId {
+        self.spans.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.spans.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.spans.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.spans.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.spans.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.spans.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.spans.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.spans.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.spans.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::MockPostingIterator;
+    use core::search::Payload;
+
+    fn float_payload(value: f32) -> Payload {
+        let bits = value.to_bits();
+        vec![
+            (bits >> 24) as u8,
+            (bits >> 16) as u8,
+            (bits >> 8) as u8,
+            bits as u8,
+        ]
+    }
+
+    #[test]
+    fn test_float_payload_decoder() {
+        let decoder = FloatPayloadDecoder;
+        assert!((decoder.compute_payload_factor(&float_payload(2.5)) - 2.5).abs() < 1e-6);
+        assert!((decoder.compute_payload_factor(&[]) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_payload_functions() {
+        let factors = [2.0f32, 5.0, 3.0];
+        let expected = [
+            (PayloadFunction::Min, 2.0),
+            (PayloadFunction::Max, 5.0),
+            (PayloadFunction::Average, 10.0 / 3.0),
+            (PayloadFunction::Sum, 10.0),
+        ];
+        for &(function, score) in &expected {
+            let mut current = 0.0;
+            for (i, &factor) in factors.iter().enumerate() {
+                current = function.current_score(i, current, factor);
+            }
+            assert!((function.doc_score(factors.len(), current) - score).abs() < 1e-5);
+            assert!((function.doc_score(0, 0.0) - 1.0).abs() < ::std::f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_payload_span_collector() {
+        let term = Term::new("body".to_string(), b"rust".to_vec());
+        let mut collector =
+            PayloadSpanCollector::new(PayloadFunction::Max, Arc::new(FloatPayloadDecoder));
+        let mut postings = MockPostingIterator::with_positions(vec![0], vec![vec![1, 4, 7]]);
+        postings.set_payloads(vec![vec![float_payload(1.5), vec![], float_payload(4.0)]]);
+        postings.next().unwrap();
+        for _ in 0..postings.freq().unwrap() {
+            let position = postings.next_position().unwrap();
+            collector.collect_leaf(&postings, position, &term).unwrap();
+        }
+        assert_eq!(collector.num_payloads_seen, 2);
+        assert!((collector.doc_score() - 4.0).abs() < ::std::f32::EPSILON);
+
+        collector.start_doc();
+        assert!((collector.doc_score() - 1.0).abs() < ::std::f32::EPSILON);
+    }
+}
@@ -30,7 +30,7 @@ use core::search::scorer::{two_phase_next, ConjunctionScorer, Scorer};
 use core::search::searcher::SearchPlanBuilder;
 use core::search::similarity::{SimScorer, SimWeight, Similarity};
 use core::search::statistics::{CollectionStatistics, TermStatistics};
-use core::search::{DocIterator, NO_MORE_DOCS};
+use core::search::{DocIterator, MatchPosition, Matches, PositionsMatchesIterator, NO_MORE_DOCS};
 use core::util::{BitSet, FixedBitSet, ImmutableBitSet};
 use core::util::{Bits, DocId, KeyedContext};
 
@@ -137,6 +137,19 @@ impl PhraseQuery {
     fn increment_positions(length: usize) -> Vec<i32> {
         (0..length as i32).collect()
     }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    /// The positions of the terms, relative to the first one.
+    pub fn positions(&self) -> &[i32] {
+        &self.positions
+    }
+
+    pub fn slop(&self) -> i32 {
+        self.slop
+    }
 }
 
 impl<C: Codec> Query<C> for PhraseQuery {
@@ -447,6 +460,92 @@ impl<C: Codec> Weight<C> for PhraseWeight<C> {
             vec![],
         ))
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        if self.slop != 0 {
+            // TODO: report the positions of sloppy phrases
+            return Matches::from_scorer(self.create_scorer(reader)?, doc);
+        }
+
+        let field_terms = match reader.reader.terms(&self.field)? {
+            Some(field_terms) => field_terms,
+            None => return Ok(None),
+        };
+        let flags = if field_terms.has_offsets()? {
+            PostingIteratorFlags::OFFSETS
+        } else {
+            PostingIteratorFlags::POSITIONS
+        };
+
+        // the offsets of each term of the phrase in the doc, by position
+        let mut term_iter = field_terms.iterator()?;
+        let mut term_positions = Vec::with_capacity(self.terms.len());
+        for (term, term_states) in self.terms.iter().zip(&self.term_states) {
+            let state = match term_states.get(&reader.doc_base) {
+                Some(state) => state,
+                None => return Ok(None),
+            };
+            term_iter.seek_exact_state(term.bytes.as_ref(), state)?;
+            let mut postings = term_iter.postings_with_flags(flags)?;
+            if postings.advance(doc)? != doc {
+                return Ok(None);
+            }
+            let freq = postings.freq()?;
+            let mut positions = HashMap::with_capacity(freq as usize);
+            for _ in 0..freq {
+                let position = postings.next_position()?;
+                positions.insert(position, (postings.start_offset()?, postings.end_offset()?));
+            }
+            term_positions.push(positions);
+        }
+
+        let mut matches = vec![];
+        'phrases: for &first in term_positions[0].keys() {
+            let phrase_position = first - self.positions[0];
+            let mut phrase: Option<MatchPosition> = None;
+            for (positions, &offset) in term_positions.iter().zip(&self.positions) {
+                let position = phrase_position + offset;
+                let (start_offset, end_offset) = match positions.get(&position) {
+                    Some(&offsets) => offsets,
+                    None => continue 'phrases,
+                };
+                phrase = Some(match phrase {
+                    Some(mut phrase) => {
+                        if position < phrase.start_position {
+                            phrase.start_position = position;
+                            phrase.start_offset = start_offset;
+                        }
+                        if position > phrase.end_position {
+                            phrase.end_position = position;
+                            phrase.end_offset = end_offset;
+                        }
+                        phrase
+                    }
+                    None => MatchPosition {
+                        start_position: position,
+                        end_position: position,
+                        start_offset,
+                        end_offset,
+                    },
+                });
+            }
+            matches.extend(phrase);
+        }
+        if matches.is_empty() {
+            return Ok(None);
+        }
+
+        let texts = self
+            .terms
+            .iter()
+            .map(Term::text)
+            .collect::<Result<Vec<_>>>()?;
+        let query = format!("{}:\"{}\"", self.field, texts.join(" "));
+        Ok(Some(Matches::from_field(
+            self.field.clone(),
+            Box::new(PositionsMatchesIterator::new(matches, query)),
+        )))
+    }
 }
 
 impl<C: Codec> fmt::Display for PhraseWeight<C> {
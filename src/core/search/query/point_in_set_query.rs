@@ -0,0 +1,487 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::{ErrorKind, Result};
+use std::fmt;
+
+use core::codec::points::{IntersectVisitor, PointValues, Relation};
+use core::codec::Codec;
+use core::index::reader::{LeafReader, LeafReaderContext};
+use core::search::explanation::Explanation;
+use core::search::query::{PointValueType, Query, TermQuery, Weight};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIdSet, DocIterator};
+use core::util::{DocId, DocIdSetBuilder};
+
+pub const POINT_IN_SET: &str = "point_in_set";
+
+/// Abstract query class to find all documents whose single or multi-dimensional
+/// point values, previously indexed with e.g. `IntPoint`, is contained in the
+/// specified set.
+///
+/// This is the points equivalent of a terms query, the BKD tree is only
+/// descended into the cells that may contain one of the points.
+pub struct PointInSetQuery {
+    field: String,
+    num_dims: usize,
+    bytes_per_dim: usize,
+    // sorted and de-duplicated packed points
+    points: Vec<Vec<u8>>,
+    value_type: PointValueType,
+}
+
+impl PointInSetQuery {
+    pub fn new(
+        field: String,
+        num_dims: usize,
+        bytes_per_dim: usize,
+        mut points: Vec<Vec<u8>>,
+        value_type: PointValueType,
+    ) -> Result<PointInSetQuery> {
+        if field.is_empty() {
+            bail!(ErrorKind::IllegalArgument("field must not be empty".into()));
+        }
+        if num_dims == 0 || bytes_per_dim == 0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "num_dims and bytes_per_dim must be > 0, got num_dims={}, bytes_per_dim={}",
+                num_dims, bytes_per_dim
+            )));
+        }
+        let packed_length = num_dims * bytes_per_dim;
+        for point in &points {
+            if point.len() != packed_length {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "all points should have length={}, but got {}",
+                    packed_length,
+                    point.len()
+                )));
+            }
+        }
+        points.sort();
+        points.dedup();
+        Ok(PointInSetQuery {
+            field,
+            num_dims,
+            bytes_per_dim,
+            points,
+            value_type,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn num_dims(&self) -> usize {
+        self.num_dims
+    }
+
+    pub fn bytes_per_dim(&self) -> usize {
+        self.bytes_per_dim
+    }
+
+    /// Returns the sorted and de-duplicated packed points of this query.
+    pub fn packed_points(&self) -> &[Vec<u8>] {
+        &self.points
+    }
+}
+
+impl<C: Codec> Query<C> for PointInSetQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(PointInSetWeight {
+            field: self.field.clone(),
+            num_dims: self.num_dims,
+            bytes_per_dim: self.bytes_per_dim,
+            points: self.points.clone(),
+            value_type: self.value_type,
+            weight: 1f32,
+            norm: 1f32,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for PointInSetQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|p| self.value_type.format_bytes(p, self.bytes_per_dim))
+            .collect();
+        write!(
+            f,
+            "PointInSetQuery(field: {}, type: {}, num_dims: {}, bytes_per_dim: {}, points: {})",
+            &self.field,
+            &self.value_type,
+            self.num_dims,
+            self.bytes_per_dim,
+            points.join(" ")
+        )
+    }
+}
+
+struct PointInSetWeight {
+    field: String,
+    num_dims: usize,
+    bytes_per_dim: usize,
+    points: Vec<Vec<u8>>,
+    value_type: PointValueType,
+    weight: f32,
+    norm: f32,
+}
+
+impl PointInSetWeight {
+    fn build_matching_doc_set<R: LeafReader + ?Sized>(
+        &self,
+        reader: &R,
+        values: &impl PointValues,
+    ) -> Result<DocIdSetBuilder> {
+        let mut result = DocIdSetBuilder::from_values(reader.max_doc(), values, &self.field)?;
+        {
+            let mut visitor = PointInSetIntersectVisitor {
+                doc_id_set_builder: &mut result,
+                weight: self,
+            };
+            values.intersect(&self.field, &mut visitor)?;
+        }
+        Ok(result)
+    }
+}
+
+impl<C: Codec> Weight<C> for PointInSetWeight {
+    fn create_scorer(
+        &self,
+        leaf_reader_ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        if self.points.is_empty() {
+            return Ok(None);
+        }
+        let leaf_reader = leaf_reader_ctx.reader;
+        if let Some(ref values) = leaf_reader.point_values() {
+            if let Some(field_info) = leaf_reader.field_info(&self.field) {
+                if field_info.point_dimension_count != self.num_dims as u32 {
+                    bail!(ErrorKind::IllegalArgument(format!(
+                        "field '{}' was indexed with num_dims={} but this query has num_dims={}",
+                        &self.field, field_info.point_dimension_count, self.num_dims
+                    )));
+                }
+                if self.bytes_per_dim as u32 != field_info.point_num_bytes {
+                    bail!(ErrorKind::IllegalArgument(format!(
+                        "field '{}' was indexed with bytes_per_dim={} but this query has \
+                         bytes_per_dim={}",
+                        &self.field, field_info.point_num_bytes, self.bytes_per_dim
+                    )));
+                }
+
+                let mut builder = self.build_matching_doc_set(leaf_reader, values)?;
+                if let Some(iterator) = builder.build().iterator()? {
+                    let cost = iterator.cost();
+                    return Ok(Some(Box::new(ConstantScoreScorer::new(
+                        self.weight,
+                        iterator,
+                        cost,
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn query_type(&self) -> &'static str {
+        POINT_IN_SET
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl fmt::Display for PointInSetWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PointInSetWeight(field: {}, type: {}, num_dims: {}, bytes_per_dim: {}, points: {})",
+            &self.field,
+            &self.value_type,
+            self.num_dims,
+            self.bytes_per_dim,
+            self.points.len()
+        )
+    }
+}
+
+struct PointInSetIntersectVisitor<'a> {
+    doc_id_set_builder: &'a mut DocIdSetBuilder,
+    weight: &'a PointInSetWeight,
+}
+
+impl<'a> PointInSetIntersectVisitor<'a> {
+    fn point_in_cell(
+        &self,
+        point: &[u8],
+        min_packed_value: &[u8],
+        max_packed_value: &[u8],
+    ) -> bool {
+        let bytes = self.weight.bytes_per_dim;
+        for dim in 0..self.weight.num_dims {
+            let offset = dim * bytes;
+            let end = offset + bytes;
+            if point[offset..end] < min_packed_value[offset..end]
+                || point[offset..end] > max_packed_value[offset..end]
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<'a> IntersectVisitor for PointInSetIntersectVisitor<'a> {
+    fn visit(&mut self, doc_id: DocId) -> Result<()> {
+        self.doc_id_set_builder.add_doc(doc_id);
+        Ok(())
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        if self
+            .weight
+            .points
+            .binary_search_by(|p| p.as_slice().cmp(packed_value))
+            .is_ok()
+        {
+            self.doc_id_set_builder.add_doc(doc_id);
+        }
+        Ok(())
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        let points = &self.weight.points;
+        let found = if self.weight.num_dims == 1 {
+            // points are sorted, find the first one that is not below the cell
+            let idx = match points.binary_search_by(|p| p.as_slice().cmp(min_packed_value)) {
+                Ok(idx) | Err(idx) => idx,
+            };
+            idx < points.len() && points[idx].as_slice() <= max_packed_value
+        } else {
+            points
+                .iter()
+                .any(|p| self.point_in_cell(p, min_packed_value, max_packed_value))
+        };
+
+        if !found {
+            Relation::CellOutsideQuery
+        } else if min_packed_value == max_packed_value {
+            // the cell is a single point, which is in the set
+            Relation::CellInsideQuery
+        } else {
+            Relation::CellCrossesQuery
+        }
+    }
+
+    fn grow(&mut self, count: usize) {
+        self.doc_id_set_builder.grow(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::IntPoint;
+    use core::search::NO_MORE_DOCS;
+
+    fn points(values: &[&[i32]]) -> Vec<Vec<u8>> {
+        values.iter().map(|v| IntPoint::pack(v)).collect()
+    }
+
+    fn weight(query: &PointInSetQuery) -> PointInSetWeight {
+        PointInSetWeight {
+            field: query.field.clone(),
+            num_dims: query.num_dims,
+            bytes_per_dim: query.bytes_per_dim,
+            points: query.points.clone(),
+            value_type: query.value_type,
+            weight: 1f32,
+            norm: 1f32,
+        }
+    }
+
+    /// the docs matched among `docs`, the packed value of doc `i` being `docs[i]`
+    fn matching_docs(query: &PointInSetQuery, docs: &[Vec<u8>]) -> Vec<DocId> {
+        let weight = weight(query);
+        let mut builder = DocIdSetBuilder::with_max_doc(docs.len() as DocId);
+        {
+            let mut visitor = PointInSetIntersectVisitor {
+                doc_id_set_builder: &mut builder,
+                weight: &weight,
+            };
+            visitor.grow(docs.len());
+            for (doc, value) in docs.iter().enumerate() {
+                visitor.visit_by_packed_value(doc as DocId, value).unwrap();
+            }
+        }
+        let mut result = vec![];
+        if let Some(mut iterator) = builder.build().iterator().unwrap() {
+            loop {
+                let doc = iterator.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                result.push(doc);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_1d_points() {
+        let query = PointInSetQuery::new(
+            "f".into(),
+            1,
+            4,
+            points(&[&[7], &[-3], &[7], &[42], &[-3]]),
+            PointValueType::Integer,
+        )
+        .unwrap();
+        // the duplicates are removed and the points sorted
+        assert_eq!(
+            query.packed_points(),
+            points(&[&[-3], &[7], &[42]]).as_slice()
+        );
+        assert_eq!(
+            query.to_string(),
+            "PointInSetQuery(field: f, type: int, num_dims: 1, bytes_per_dim: 4, points: [-3] [7] \
+             [42])"
+        );
+
+        let docs = points(&[&[1], &[7], &[-3], &[8], &[42], &[7]]);
+        assert_eq!(matching_docs(&query, &docs), vec![1, 2, 4, 5]);
+
+        let weight = weight(&query);
+        let mut builder = DocIdSetBuilder::with_max_doc(1);
+        let visitor = PointInSetIntersectVisitor {
+            doc_id_set_builder: &mut builder,
+            weight: &weight,
+        };
+        let (min, max) = (IntPoint::pack(&[8]), IntPoint::pack(&[41]));
+        assert!(visitor.compare(&min, &max) == Relation::CellOutsideQuery);
+        let (min, max) = (IntPoint::pack(&[8]), IntPoint::pack(&[42]));
+        assert!(visitor.compare(&min, &max) == Relation::CellCrossesQuery);
+        let value = IntPoint::pack(&[7]);
+        assert!(visitor.compare(&value, &value) == Relation::CellInsideQuery);
+    }
+
+    #[test]
+    fn test_multi_dimensional_points() {
+        let query = PointInSetQuery::new(
+            "f".into(),
+            2,
+            4,
+            points(&[&[1, 2], &[3, 4], &[1, 2]]),
+            PointValueType::Integer,
+        )
+        .unwrap();
+        assert_eq!(
+            query.packed_points(),
+            points(&[&[1, 2], &[3, 4]]).as_slice()
+        );
+
+        // a point must match in all its dimensions
+        let docs = points(&[&[1, 4], &[3, 4], &[2, 1], &[1, 2]]);
+        assert_eq!(matching_docs(&query, &docs), vec![1, 3]);
+
+        let weight = weight(&query);
+        let mut builder = DocIdSetBuilder::with_max_doc(1);
+        let visitor = PointInSetIntersectVisitor {
+            doc_id_set_builder: &mut builder,
+            weight: &weight,
+        };
+        // the cell contains both dimensions of neither point
+        let (min, max) = (IntPoint::pack(&[0, 3]), IntPoint::pack(&[2, 5]));
+        assert!(visitor.compare(&min, &max) == Relation::CellOutsideQuery);
+        let (min, max) = (IntPoint::pack(&[0, 0]), IntPoint::pack(&[2, 5]));
+        assert!(visitor.compare(&min, &max) == Relation::CellCrossesQuery);
+    }
+
+    #[test]
+    fn test_empty_set() {
+        let query =
+            PointInSetQuery::new("f".into(), 1, 4, vec![], PointValueType::Integer).unwrap();
+        assert!(query.packed_points().is_empty());
+        assert!(matching_docs(&query, &points(&[&[1], &[2]])).is_empty());
+    }
+
+    #[test]
+    fn test_illegal_arguments() {
+        assert!(
+            PointInSetQuery::new("".into(), 1, 4, points(&[&[1]]), PointValueType::Integer)
+                .is_err()
+        );
+        assert!(
+            PointInSetQuery::new("f".into(), 0, 4, points(&[&[1]]), PointValueType::Integer)
+                .is_err()
+        );
+        // the points must have num_dims * bytes_per_dim bytes
+        assert!(
+            PointInSetQuery::new("f".into(), 2, 4, points(&[&[1]]), PointValueType::Integer)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::points::{IntersectVisitor, Lucene60PointsReader, PointsReader, Relation};
+use core::codec::Codec;
+use core::index::reader::IndexReader;
+use core::search::query::FloatPoint;
+use core::search::searcher::IndexSearcher;
+use core::search::sort_field::{
+    FieldDoc, ScoreDocHit, SimpleSortField, SortField, SortFieldType, TopDocs, TopFieldDocs,
+};
+use core::util::bkd::BestFirstVisitor;
+use core::util::{Bits, BitsRef, DocId, VariantValue};
+
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result,
+};
+
+impl FloatPoint {
+    /// Finds the `top_n` docs whose points of `field` are the closest to
+    /// `origin` by euclidean distance, closest first.
+    ///
+    /// The BKD trees of all the segments are walked best-first, so only the
+    /// cells that may hold one of the closest points are read. Each hit is a
+    /// `FieldDoc` whose only sort value is the distance, as a `Double`. A doc
+    /// with several points is ranked by its closest one.
+    pub fn nearest<C: Codec, IS: IndexSearcher<C> + ?Sized>(
+        searcher: &IS,
+        field: &str,
+        top_n: usize,
+        origin: &[f32],
+    ) -> Result<TopDocs> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be at least 1".into()));
+        }
+        if origin.is_empty() {
+            bail!(IllegalArgument(
+                "origin must have at least 1 dimension".into()
+            ));
+        }
+
+        let mut hits = NearestHits::new(top_n);
+        for leaf in searcher.reader().leaves() {
+            let points = match leaf.reader.point_values() {
+                Some(points) => points,
+                None => continue,
+            };
+            let reader = match PointsReader::as_any(&*points).downcast_ref::<Lucene60PointsReader>()
+            {
+                Some(reader) => reader,
+                None => bail!(IllegalState(
+                    "nearest search requires the Lucene60 points format".into()
+                )),
+            };
+            let bkd_reader = match reader.bkd_reader(field)? {
+                Some(bkd_reader) => bkd_reader,
+                None => continue,
+            };
+            if bkd_reader.num_dims != origin.len() || bkd_reader.bytes_per_dim != 4 {
+                bail!(IllegalArgument(format!(
+                    "field '{}' is not a {} dimensions float point field",
+                    field,
+                    origin.len()
+                )));
+            }
+
+            let mut visitor = NearestVisitor {
+                origin,
+                doc_base: leaf.doc_base,
+                live_docs: leaf.reader.live_docs(),
+                hits: &mut hits,
+            };
+            bkd_reader.visit_best_first(&mut visitor)?;
+        }
+
+        let score_docs: Vec<ScoreDocHit> = hits
+            .hits
+            .into_iter()
+            .map(|(distance, doc)| {
+                ScoreDocHit::Field(FieldDoc::new(
+                    doc,
+                    0f32,
+                    vec![VariantValue::Double(distance.sqrt())],
+                ))
+            })
+            .collect();
+        Ok(TopDocs::Field(TopFieldDocs {
+            total_hits: score_docs.len(),
+            score_docs,
+            max_score: ::std::f32::NAN,
+            fields: vec![SortField::Simple(SimpleSortField::new(
+                field.to_string(),
+                SortFieldType::Double,
+                false,
+            ))],
+            timed_out: false,
+        }))
+    }
+}
+
+/// The closest hits found so far, by squared distance.
+struct NearestHits {
+    top_n: usize,
+    // sorted by distance, then doc
+    hits: Vec<(f64, DocId)>,
+}
+
+impl NearestHits {
+    fn new(top_n: usize) -> NearestHits {
+        NearestHits {
+            top_n,
+            hits: Vec::with_capacity(top_n),
+        }
+    }
+
+    fn max_distance(&self) -> f64 {
+        if self.hits.len() < self.top_n {
+            ::std::f64::INFINITY
+        } else {
+            self.hits[self.hits.len() - 1].0
+        }
+    }
+
+    fn insert(&mut self, doc: DocId, distance: f64) {
+        if let Some(i) = self.hits.iter().position(|h| h.1 == doc) {
+            // a doc is only ranked by its closest point
+            if self.hits[i].0 <= distance {
+                return;
+            }
+            self.hits.remove(i);
+        }
+        let hit = (distance, doc);
+        let pos = self
+            .hits
+            .iter()
+            .position(|h| *h > hit)
+            .unwrap_or_else(|| self.hits.len());
+        if pos < self.top_n {
+            self.hits.insert(pos, hit);
+            self.hits.truncate(self.top_n);
+        }
+    }
+}
+
+struct NearestVisitor<'a> {
+    origin: &'a [f32],
+    doc_base: DocId,
+    live_docs: BitsRef,
+    hits: &'a mut NearestHits,
+}
+
+impl<'a> NearestVisitor<'a> {
+    fn distance(&self, packed_value: &[u8]) -> f64 {
+        let mut distance = 0f64;
+        for (dim, &o) in self.origin.iter().enumerate() {
+            let v = FloatPoint::decode_dimension(&packed_value[dim * 4..]);
+            let diff = f64::from(v) - f64::from(o);
+            distance += diff * diff;
+        }
+        distance
+    }
+}
+
+impl<'a> IntersectVisitor for NearestVisitor<'a> {
+    fn visit(&mut self, _doc_id: DocId) -> Result<()> {
+        bail!(IllegalState(
+            "nearest search only visits points by value".into()
+        ))
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        if !self.live_docs.get(doc_id as usize)? {
+            return Ok(());
+        }
+        let distance = self.distance(packed_value);
+        if distance <= self.hits.max_distance() {
+            self.hits.insert(self.doc_base + doc_id, distance);
+        }
+        Ok(())
+    }
+
+    fn compare(&self, _min_packed_value: &[u8], _max_packed_value: &[u8]) -> Relation {
+        Relation::CellCrossesQuery
+    }
+}
+
+impl<'a> BestFirstVisitor for NearestVisitor<'a> {
+    fn cell_distance(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> f64 {
+        let mut distance = 0f64;
+        for (dim, &o) in self.origin.iter().enumerate() {
+            let o = f64::from(o);
+            let min = f64::from(FloatPoint::decode_dimension(&min_packed_value[dim * 4..]));
+            let max = f64::from(FloatPoint::decode_dimension(&max_packed_value[dim * 4..]));
+            let diff = if o < min {
+                min - o
+            } else if o > max {
+                o - max
+            } else {
+                0f64
+            };
+            distance += diff * diff;
+        }
+        distance
+    }
+
+    fn max_distance(&self) -> f64 {
+        self.hits.max_distance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::MatchAllBits;
+    use std::sync::Arc;
+
+    fn pack(point: &[f32]) -> Vec<u8> {
+        let mut packed = vec![0u8; point.len() * 4];
+        for (dim, &v) in point.iter().enumerate() {
+            FloatPoint::encode_dimension(v, &mut packed[dim * 4..]);
+        }
+        packed
+    }
+
+    #[test]
+    fn test_nearest_hits() {
+        let mut hits = NearestHits::new(2);
+        assert!(hits.max_distance().is_infinite());
+        hits.insert(3, 4.0);
+        hits.insert(1, 9.0);
+        assert!((hits.max_distance() - 9.0).abs() < ::std::f64::EPSILON);
+        // ties are broken by doc
+        hits.insert(0, 4.0);
+        assert_eq!(hits.hits, vec![(4.0, 0), (4.0, 3)]);
+        // only the closest point of a doc counts
+        hits.insert(3, 1.0);
+        hits.insert(3, 2.0);
+        assert_eq!(hits.hits, vec![(1.0, 3), (4.0, 0)]);
+        hits.insert(5, 16.0);
+        assert_eq!(hits.hits, vec![(1.0, 3), (4.0, 0)]);
+    }
+
+    #[test]
+    fn test_nearest_visitor() {
+        let origin = [1.0f32, 2.0];
+        let mut hits = NearestHits::new(2);
+        {
+            let mut visitor = NearestVisitor {
+                origin: &origin,
+                doc_base: 10,
+                live_docs: Arc::new(MatchAllBits::new(5)),
+                hits: &mut hits,
+            };
+            // the origin is inside the cell on the first dim only
+            let distance = visitor.cell_distance(&pack(&[0.0, 4.0]), &pack(&[2.0, 6.0]));
+            assert!((distance - 4.0).abs() < ::std::f64::EPSILON);
+            let distance = visitor.cell_distance(&pack(&[-1.0, 0.0]), &pack(&[3.0, 3.0]));
+            assert!(distance.abs() < ::std::f64::EPSILON);
+
+            visitor
+                .visit_by_packed_value(0, &pack(&[4.0, 6.0]))
+                .unwrap();
+            visitor
+                .visit_by_packed_value(1, &pack(&[1.0, 3.0]))
+                .unwrap();
+            visitor
+                .visit_by_packed_value(2, &pack(&[0.0, 2.0]))
+                .unwrap();
+            assert!((visitor.max_distance() - 1.0).abs() < ::std::f64::EPSILON);
+        }
+        assert_eq!(hits.hits, vec![(1.0, 11), (1.0, 12)]);
+    }
+}
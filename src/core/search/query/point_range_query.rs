@@ -14,11 +14,14 @@
 use error::{ErrorKind, Result};
 use std::fmt;
 
+use core::analysis::TokenStream;
 use core::codec::points::{IntersectVisitor, PointValues, Relation};
 use core::codec::Codec;
+use core::doc::{Field, FieldType, Fieldable};
 use core::index::reader::{LeafReader, LeafReaderContext};
 use core::search::explanation::Explanation;
-use core::search::query::{AllDocsIterator, Query, TermQuery, Weight};
+use core::search::query::{AllDocsIterator, InetAddressPoint, PointInSetQuery, Query};
+use core::search::query::{TermQuery, Weight};
 use core::search::scorer::{ConstantScoreScorer, Scorer};
 use core::search::searcher::SearchPlanBuilder;
 use core::search::{DocIdSet, DocIterator, EmptyDocIterator};
@@ -26,6 +29,45 @@ use core::util::*;
 
 use num_traits::float::Float;
 
+/// Implements `Fieldable` for a point field wrapping a `Field`.
+macro_rules! point_fieldable {
+    ($point:ident) => {
+        impl Fieldable for $point {
+            fn name(&self) -> &str {
+                self.field.name()
+            }
+
+            fn field_type(&self) -> &FieldType {
+                self.field.field_type()
+            }
+
+            fn boost(&self) -> f32 {
+                self.field.boost()
+            }
+
+            fn field_data(&self) -> Option<&VariantValue> {
+                self.field.field_data()
+            }
+
+            fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+                self.field.token_stream()
+            }
+
+            fn binary_value(&self) -> Option<&[u8]> {
+                self.field.binary_value()
+            }
+
+            fn string_value(&self) -> Option<&str> {
+                None
+            }
+
+            fn numeric_value(&self) -> Option<Numeric> {
+                None
+            }
+        }
+    };
+}
+
 /// An indexed `f32` field for fast range filters.
 ///
 /// If you also need to store the value, you should add a separate `StoredField` instance.
@@ -34,9 +76,26 @@ use num_traits::float::Float;
 /// Multiple values for the same field in one document is allowed.
 ///
 /// This field defines static factory methods for creating common queries
-pub struct FloatPoint;
+#[derive(Debug)]
+pub struct FloatPoint {
+    field: Field,
+}
 
 impl FloatPoint {
+    /// Creates a new `f32` point with the values of its dimensions, e.g. `&[x, y]`
+    /// for a 2 dimensions point.
+    pub fn new(field_name: &str, point: &[f32]) -> Result<FloatPoint> {
+        if point.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "point must have at least one dimension".into()
+            ));
+        }
+        let field_type = FieldType::point(point.len() as u32, 4)?;
+        Ok(FloatPoint {
+            field: Field::new_bytes(field_name.to_string(), FloatPoint::pack(point), field_type),
+        })
+    }
+
     pub fn next_up(f: f32) -> f32 {
         let mut int_value = f32::to_bits(f);
         if int_value == 0x8000_0000u32 {
@@ -96,6 +155,19 @@ impl FloatPoint {
         FloatPoint::new_multi_range_query(field, &[lower], &[upper])
     }
 
+    /// Create a query matching any of the specified 1D values.
+    /// This is the points equivalent of a terms query.
+    pub fn new_set_query<C: Codec>(field: String, values: &[f32]) -> Result<Box<dyn Query<C>>> {
+        let points = values.iter().map(|&v| FloatPoint::pack(&[v])).collect();
+        Ok(Box::new(PointInSetQuery::new(
+            field,
+            1,
+            4,
+            points,
+            PointValueType::Float,
+        )?))
+    }
+
     /// Create a range query for n-dimensional float values.
     pub fn new_multi_range_query<C: Codec>(
         field: String,
@@ -129,9 +201,31 @@ impl FloatPoint {
     }
 }
 
-pub struct DoublePoint;
+/// An indexed `f64` field for fast range filters.
+///
+/// If you also need to store the value, you should add a separate `StoredField` instance.
+///
+/// This field defines static factory methods for creating common queries
+#[derive(Debug)]
+pub struct DoublePoint {
+    field: Field,
+}
 
 impl DoublePoint {
+    /// Creates a new `f64` point with the values of its dimensions, e.g. `&[x, y]`
+    /// for a 2 dimensions point.
+    pub fn new(field_name: &str, point: &[f64]) -> Result<DoublePoint> {
+        if point.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "point must have at least one dimension".into()
+            ));
+        }
+        let field_type = FieldType::point(point.len() as u32, 8)?;
+        Ok(DoublePoint {
+            field: Field::new_bytes(field_name.to_string(), DoublePoint::pack(point), field_type),
+        })
+    }
+
     pub fn next_up(d: f64) -> f64 {
         let mut bits = f64::to_bits(d);
         if bits == 0x8000_0000_0000_0000u64 {
@@ -204,6 +298,19 @@ impl DoublePoint {
         DoublePoint::new_multi_range_query(field, &[lower], &[upper])
     }
 
+    /// Create a query matching any of the specified 1D values.
+    /// This is the points equivalent of a terms query.
+    pub fn new_set_query<C: Codec>(field: String, values: &[f64]) -> Result<Box<dyn Query<C>>> {
+        let points = values.iter().map(|&v| DoublePoint::pack(&[v])).collect();
+        Ok(Box::new(PointInSetQuery::new(
+            field,
+            1,
+            8,
+            points,
+            PointValueType::Double,
+        )?))
+    }
+
     /// Create a range query for n-dimensional double values.
     pub fn new_multi_range_query<C: Codec>(
         field: String,
@@ -220,9 +327,31 @@ impl DoublePoint {
     }
 }
 
-pub struct IntPoint;
+/// An indexed `i32` field for fast range filters.
+///
+/// If you also need to store the value, you should add a separate `StoredField` instance.
+///
+/// This field defines static factory methods for creating common queries
+#[derive(Debug)]
+pub struct IntPoint {
+    field: Field,
+}
 
 impl IntPoint {
+    /// Creates a new `i32` point with the values of its dimensions, e.g. `&[x, y]`
+    /// for a 2 dimensions point.
+    pub fn new(field_name: &str, point: &[i32]) -> Result<IntPoint> {
+        if point.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "point must have at least one dimension".into()
+            ));
+        }
+        let field_type = FieldType::point(point.len() as u32, 4)?;
+        Ok(IntPoint {
+            field: Field::new_bytes(field_name.to_string(), IntPoint::pack(point), field_type),
+        })
+    }
+
     pub fn pack(point: &[i32]) -> Vec<u8> {
         assert!(!point.is_empty());
         let mut packed = vec![0u8; point.len() * 4];
@@ -252,6 +381,19 @@ impl IntPoint {
         IntPoint::new_multi_range_query(field, &[lower], &[upper])
     }
 
+    /// Create a query matching any of the specified 1D values.
+    /// This is the points equivalent of a terms query.
+    pub fn new_set_query<C: Codec>(field: String, values: &[i32]) -> Result<Box<dyn Query<C>>> {
+        let points = values.iter().map(|&v| IntPoint::pack(&[v])).collect();
+        Ok(Box::new(PointInSetQuery::new(
+            field,
+            1,
+            4,
+            points,
+            PointValueType::Integer,
+        )?))
+    }
+
     pub fn new_multi_range_query<C: Codec>(
         field: String,
         lower: &[i32],
@@ -267,9 +409,31 @@ impl IntPoint {
     }
 }
 
-pub struct LongPoint;
+/// An indexed `i64` field for fast range filters.
+///
+/// If you also need to store the value, you should add a separate `StoredField` instance.
+///
+/// This field defines static factory methods for creating common queries
+#[derive(Debug)]
+pub struct LongPoint {
+    field: Field,
+}
 
 impl LongPoint {
+    /// Creates a new `i64` point with the values of its dimensions, e.g. `&[x, y]`
+    /// for a 2 dimensions point.
+    pub fn new(field_name: &str, point: &[i64]) -> Result<LongPoint> {
+        if point.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "point must have at least one dimension".into()
+            ));
+        }
+        let field_type = FieldType::point(point.len() as u32, 8)?;
+        Ok(LongPoint {
+            field: Field::new_bytes(field_name.to_string(), LongPoint::pack(point), field_type),
+        })
+    }
+
     pub fn pack(point: &[i64]) -> Vec<u8> {
         assert!(!point.is_empty());
         let mut packed = vec![0u8; point.len() * 8];
@@ -299,6 +463,19 @@ impl LongPoint {
         LongPoint::new_multi_range_query(field, &[lower], &[upper])
     }
 
+    /// Create a query matching any of the specified 1D values.
+    /// This is the points equivalent of a terms query.
+    pub fn new_set_query<C: Codec>(field: String, values: &[i64]) -> Result<Box<dyn Query<C>>> {
+        let points = values.iter().map(|&v| LongPoint::pack(&[v])).collect();
+        Ok(Box::new(PointInSetQuery::new(
+            field,
+            1,
+            8,
+            points,
+            PointValueType::Long,
+        )?))
+    }
+
     pub fn new_multi_range_query<C: Codec>(
         field: String,
         lower: &[i64],
@@ -314,12 +491,18 @@ impl LongPoint {
     }
 }
 
+point_fieldable!(FloatPoint);
+point_fieldable!(DoublePoint);
+point_fieldable!(IntPoint);
+point_fieldable!(LongPoint);
+
 #[derive(Copy, Clone)]
 pub enum PointValueType {
     Integer,
     Float,
     Double,
     Long,
+    InetAddress,
     /* Byte,
      * SmallFloat,
      * Short */
@@ -332,6 +515,7 @@ impl PointValueType {
             PointValueType::Double => DoublePoint::decode_dimension(bytes).to_string(),
             PointValueType::Integer => IntPoint::decode_dimension(bytes).to_string(),
             PointValueType::Long => LongPoint::decode_dimension(bytes).to_string(),
+            PointValueType::InetAddress => InetAddressPoint::decode(bytes).to_string(),
         }
     }
 
@@ -354,6 +538,7 @@ impl fmt::Display for PointValueType {
             PointValueType::Float => "float",
             PointValueType::Double => "double",
             PointValueType::Long => "long",
+            PointValueType::InetAddress => "inet_address",
         };
         write!(f, "{}", value)
     }
@@ -426,7 +611,7 @@ impl<C: Codec> Query<C> for PointRangeQuery {
     }
 
     fn extract_terms(&self) -> Vec<TermQuery> {
-        unimplemented!()
+        vec![]
     }
 
     fn as_any(&self) -> &dyn (::std::any::Any) {
@@ -564,6 +749,16 @@ impl<C: Codec> Weight<C> for PointRangeWeight {
         POINT_RANGE
     }
 
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        // bounded by the number of docs having a value for the field
+        match reader.reader.point_values() {
+            Some(ref values) if reader.reader.field_info(&self.field).is_some() => {
+                Ok(Some(values.doc_count(&self.field)? as usize))
+            }
+            _ => Ok(Some(0)),
+        }
+    }
+
     fn normalize(&mut self, norm: f32, boost: f32) {
         self.weight = norm * boost;
         self.norm = norm;
@@ -577,8 +772,31 @@ impl<C: Codec> Weight<C> for PointRangeWeight {
         false
     }
 
-    fn explain(&self, _reader: &LeafReaderContext<'_, C>, _doc: DocId) -> Result<Explanation> {
-        unimplemented!()
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = self.create_scorer(reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
     }
 }
 
@@ -646,12 +864,11 @@ impl<'a> IntersectVisitor for PointRangeIntersectVisitor<'a> {
             let offset = dim * bytes;
             let end = offset + bytes;
             if min_packed_value[offset..end] > self.weight.upper_point[offset..end]
-                || max_packed_value[offset..end] < self.weight.lower_point[offset..]
+                || max_packed_value[offset..end] < self.weight.lower_point[offset..end]
             {
                 return Relation::CellOutsideQuery;
             }
 
-            let end = offset + bytes;
             crosses |= min_packed_value[offset..end] < self.weight.lower_point[offset..end]
                 || max_packed_value[offset..end] > self.weight.upper_point[offset..end];
         }
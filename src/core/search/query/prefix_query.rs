@@ -0,0 +1,218 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator, TermIterator};
+use core::codec::{Codec, CodecTermIterator, CodecTerms, Terms};
+use core::doc::Term;
+use core::search::query::{multi_term_weight, MultiTermQuery, RewriteMethod};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+
+use error::Result;
+
+use std::fmt;
+
+pub const PREFIX: &str = "prefix";
+
+/// A Query that matches documents containing terms with a specified prefix.
+///
+/// The terms dictionary is positioned directly at the prefix with a single
+/// seek, and the enumeration stops at the first term that no longer starts
+/// with the prefix, so only the matching block of terms is visited.
+#[derive(Clone, Debug)]
+pub struct PrefixQuery {
+    prefix: Term,
+    rewrite_method: RewriteMethod,
+}
+
+impl PrefixQuery {
+    /// Constructs a query for terms starting with `prefix`.
+    pub fn new(prefix: Term) -> PrefixQuery {
+        PrefixQuery {
+            prefix,
+            rewrite_method: RewriteMethod::default(),
+        }
+    }
+
+    /// Returns the prefix of this query.
+    pub fn prefix(&self) -> &Term {
+        &self.prefix
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.rewrite_method = rewrite_method;
+    }
+}
+
+impl<C: Codec> MultiTermQuery<C> for PrefixQuery {
+    type Iter = PrefixTermIterator<CodecTermIterator<C>>;
+
+    fn field(&self) -> &str {
+        self.prefix.field()
+    }
+
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter> {
+        Ok(PrefixTermIterator::new(
+            terms.iterator()?,
+            self.prefix.bytes.clone(),
+        ))
+    }
+}
+
+impl<C: Codec> Query<C> for PrefixQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        multi_term_weight(self, PREFIX, self.rewrite_method, searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for PrefixQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PrefixQuery(field: {}, prefix: {})",
+            self.prefix.field(),
+            String::from_utf8_lossy(&self.prefix.bytes)
+        )
+    }
+}
+
+/// Subclass of `FilteredTermIterator` for enumerating all terms that match the
+/// specified prefix filter term.
+///
+/// Term enumerations are always ordered by byte order, so all the matching
+/// terms are consecutive, the iteration seeks to the prefix once and ends at
+/// the first non-matching term.
+pub struct PrefixTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    prefix: Vec<u8>,
+}
+
+impl<T: TermIterator> PrefixTermIterator<T> {
+    pub fn new(terms: T, prefix: Vec<u8>) -> PrefixTermIterator<T> {
+        // an empty prefix matches all the terms, no need to seek
+        let mut base = FilteredTermIterBase::new(terms, !prefix.is_empty());
+        if !prefix.is_empty() {
+            base.initial_seek_term = Some(prefix.clone());
+        }
+        PrefixTermIterator { base, prefix }
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for PrefixTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        if term.starts_with(&self.prefix) {
+            Ok(AcceptStatus::Yes)
+        } else {
+            Ok(AcceptStatus::End)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::tests::{collect_terms, MockTermIterator};
+    use core::doc::StringField;
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::DocId;
+
+    use std::sync::Arc;
+
+    fn prefixed(prefix: &str) -> Vec<String> {
+        let terms = MockTermIterator::new(&["ab", "abc", "abd", "b", "ba", "c"]);
+        collect_terms(PrefixTermIterator::new(terms, prefix.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_prefix_term_iterator() {
+        assert_eq!(prefixed("ab"), vec!["ab", "abc", "abd"]);
+        assert_eq!(prefixed("b"), vec!["b", "ba"]);
+        // a prefix equal to a whole term matches it and its extensions only
+        assert_eq!(prefixed("abc"), vec!["abc"]);
+        assert_eq!(prefixed("ba"), vec!["ba"]);
+        // an empty prefix matches all the terms
+        assert_eq!(prefixed(""), vec!["ab", "abc", "abd", "b", "ba", "c"]);
+        // no match, before, between and after the terms
+        assert!(prefixed("a0").is_empty());
+        assert!(prefixed("abe").is_empty());
+        assert!(prefixed("d").is_empty());
+    }
+
+    #[test]
+    fn test_prefix_query_search() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let config = Arc::new(IndexWriterConfig::default());
+        let writer = IndexWriter::new(directory, config).unwrap();
+        for term in &["ab", "abc", "abd", "b", "ba", "c"] {
+            writer
+                .add_document(vec![StringField::new("body", term, false)])
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = Arc::new(writer.get_reader(true, false).unwrap());
+        let searcher = DefaultIndexSearcher::new(reader, None, None);
+        let search = |prefix: &str, rewrite_method: RewriteMethod| -> Vec<DocId> {
+            let term = Term::new("body".into(), prefix.as_bytes().to_vec());
+            let mut query = PrefixQuery::new(term);
+            query.set_rewrite_method(rewrite_method);
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(&query, &mut collector).unwrap();
+            let top_docs = collector.top_docs();
+            let mut docs: Vec<DocId> = top_docs.score_docs().iter().map(|d| d.doc_id()).collect();
+            docs.sort();
+            docs
+        };
+
+        for &rewrite_method in &[RewriteMethod::ConstantScore, RewriteMethod::ScoringBoolean] {
+            assert_eq!(search("ab", rewrite_method), vec![0, 1, 2]);
+            assert_eq!(search("b", rewrite_method), vec![3, 4]);
+            assert_eq!(search("", rewrite_method), vec![0, 1, 2, 3, 4, 5]);
+            assert!(search("d", rewrite_method).is_empty());
+        }
+        // only the docs of the 2 best terms are kept
+        assert_eq!(search("ab", RewriteMethod::TopTerms(2)).len(), 2);
+    }
+}
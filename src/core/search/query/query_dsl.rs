@@ -0,0 +1,974 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use serde_json::{Map, Value};
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::InetAddressPoint;
+use core::search::query::{BooleanQuery, BoostQuery, ConstantScoreQuery, DisjunctionMaxQuery};
+use core::search::query::{DoublePoint, FloatPoint, IntPoint, LongPoint, PointValueType};
+use core::search::query::{FuzzyQuery, PrefixQuery, RegexpQuery, TermRangeQuery, WildcardQuery};
+use core::search::query::{MatchAllDocsQuery, PhraseQuery, Query, TermQuery};
+use core::search::query::{Operator, QueryParser, DEFAULT_TRANSPOSITIONS};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Parses an Elasticsearch-like JSON query DSL into `Query`s.
+///
+/// The supported queries are `match_all`, `term`, `terms`, `match`, `match_phrase`,
+/// `prefix`, `wildcard`, `regexp`, `fuzzy`, `range`, `bool`, `dis_max`,
+/// `constant_score` and `query_string`, all of them accept a `boost`. For example:
+///
+/// ```json
+/// {
+///     "bool": {
+///         "must": { "match": { "title": { "query": "quick fox", "operator": "and" } } },
+///         "filter": [ { "range": { "year": { "gte": 2010, "lt": 2020 } } } ],
+///         "must_not": { "term": { "status": "deleted" } }
+///     }
+/// }
+/// ```
+///
+/// No analysis is applied, `match` queries split their text on whitespaces.
+/// Numeric `term`, `terms` and `range` queries are run against point fields,
+/// whose type is declared with `set_point_type`. Undeclared fields use
+/// `PointValueType::Long` for integer values and `PointValueType::Double` for
+/// the others. The string values of `PointValueType::InetAddress` fields are
+/// parsed as IP addresses, a `term` query also accepting a CIDR network range
+/// such as `10.0.0.0/8`.
+#[derive(Default)]
+pub struct QueryDslParser {
+    point_types: HashMap<String, PointValueType>,
+}
+
+impl QueryDslParser {
+    pub fn new() -> QueryDslParser {
+        QueryDslParser::default()
+    }
+
+    /// Declare `field` as a point field of type `point_type`.
+    pub fn set_point_type(&mut self, field: String, point_type: PointValueType) {
+        self.point_types.insert(field, point_type);
+    }
+
+    /// Parses a JSON query string.
+    pub fn parse<C: Codec>(&self, json: &str) -> Result<Box<dyn Query<C>>> {
+        let value: Value = ::serde_json::from_str(json)?;
+        self.parse_value(&value)
+    }
+
+    /// Parses an already deserialized JSON query.
+    pub fn parse_value<C: Codec>(&self, value: &Value) -> Result<Box<dyn Query<C>>> {
+        let (name, body) = single_entry("query", value)?;
+        match name {
+            "match_all" => self.match_all_query(body),
+            "term" => self.term_query(body),
+            "terms" => self.terms_query(body),
+            "match" => self.match_query(body),
+            "match_phrase" => self.match_phrase_query(body),
+            "prefix" => self.prefix_query(body),
+            "wildcard" => self.wildcard_query(body),
+            "regexp" => self.regexp_query(body),
+            "fuzzy" => self.fuzzy_query(body),
+            "range" => self.range_query(body),
+            "bool" => self.bool_query(body),
+            "dis_max" => self.dis_max_query(body),
+            "constant_score" => self.constant_score_query(body),
+            "query_string" => self.query_string_query(body),
+            _ => bail!(IllegalArgument(format!("unknown query [{}]", name))),
+        }
+    }
+
+    fn match_all_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let mut params = object("match_all", body)?.clone();
+        let boost = take_boost("match_all", &mut params)?;
+        check_empty("match_all", &params)?;
+        Ok(apply_boost(Box::new(MatchAllDocsQuery), boost))
+    }
+
+    fn term_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("term", body, "value")?;
+        let boost = take_boost("term", &mut params)?;
+        let value = take_required("term", &mut params, "value")?;
+        check_empty("term", &params)?;
+        let query = match value {
+            Value::Number(_) => self.point_exact_query(field, &value)?,
+            Value::String(ref cidr) if self.is_inet_address(&field) => {
+                InetAddressPoint::new_cidr_query(field, cidr)?
+            }
+            _ => Box::new(TermQuery::new(term("term", field, &value)?, 1.0, None)),
+        };
+        Ok(apply_boost(query, boost))
+    }
+
+    fn terms_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let mut params = object("terms", body)?.clone();
+        let boost = take_boost("terms", &mut params)?;
+        if params.len() != 1 {
+            bail!(IllegalArgument(
+                "[terms] query requires exactly one field".into()
+            ));
+        }
+        let (field, values) = params.into_iter().next().unwrap();
+        let values = match values {
+            Value::Array(values) if !values.is_empty() => values,
+            _ => bail!(IllegalArgument(format!(
+                "[terms] query requires a non empty array of values for field [{}]",
+                field
+            ))),
+        };
+        let points = values.iter().all(Value::is_number) || self.is_inet_address(&field);
+        let query: Box<dyn Query<C>> = if points {
+            self.point_set_query(field, &values)?
+        } else {
+            let mut shoulds: Vec<Box<dyn Query<C>>> = Vec::with_capacity(values.len());
+            for v in &values {
+                shoulds.push(Box::new(TermQuery::new(
+                    term("terms", field.clone(), v)?,
+                    1.0,
+                    None,
+                )));
+            }
+            Box::new(ConstantScoreQuery::with_boost(
+                BooleanQuery::build(vec![], shoulds, vec![])?,
+                1.0,
+            ))
+        };
+        Ok(apply_boost(query, boost))
+    }
+
+    fn match_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("match", body, "query")?;
+        let boost = take_boost("match", &mut params)?;
+        let text = take_string("match", &mut params, "query")?;
+        let operator = match params.remove("operator") {
+            None => Operator::Or,
+            Some(Value::String(ref op)) if op.eq_ignore_ascii_case("or") => Operator::Or,
+            Some(Value::String(ref op)) if op.eq_ignore_ascii_case("and") => Operator::And,
+            Some(op) => bail!(IllegalArgument(format!(
+                "[match] query does not support operator [{}]",
+                op
+            ))),
+        };
+        check_empty("match", &params)?;
+
+        let mut queries: Vec<Box<dyn Query<C>>> = text
+            .split_whitespace()
+            .map(|t| -> Box<dyn Query<C>> {
+                Box::new(TermQuery::new(
+                    Term::new(field.clone(), t.as_bytes().to_vec()),
+                    1.0,
+                    None,
+                ))
+            })
+            .collect();
+        if queries.is_empty() {
+            bail!(IllegalArgument(format!(
+                "[match] query has no terms for field [{}]",
+                field
+            )));
+        }
+        let query = if queries.len() == 1 {
+            queries.remove(0)
+        } else if operator == Operator::And {
+            BooleanQuery::build(queries, vec![], vec![])?
+        } else {
+            BooleanQuery::build(vec![], queries, vec![])?
+        };
+        Ok(apply_boost(query, boost))
+    }
+
+    fn match_phrase_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("match_phrase", body, "query")?;
+        let boost = take_boost("match_phrase", &mut params)?;
+        let text = take_string("match_phrase", &mut params, "query")?;
+        let slop = match params.remove("slop") {
+            Some(v) => as_non_negative("match_phrase", "slop", &v)?,
+            None => 0,
+        };
+        check_empty("match_phrase", &params)?;
+
+        let mut terms: Vec<Term> = text
+            .split_whitespace()
+            .map(|t| Term::new(field.clone(), t.as_bytes().to_vec()))
+            .collect();
+        let query: Box<dyn Query<C>> = match terms.len() {
+            0 => bail!(IllegalArgument(format!(
+                "[match_phrase] query has no terms for field [{}]",
+                field
+            ))),
+            1 => Box::new(TermQuery::new(terms.remove(0), 1.0, None)),
+            _ => Box::new(PhraseQuery::build(terms, slop, None, None)?),
+        };
+        Ok(apply_boost(query, boost))
+    }
+
+    fn prefix_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("prefix", body, "value")?;
+        let boost = take_boost("prefix", &mut params)?;
+        let value = take_required("prefix", &mut params, "value")?;
+        check_empty("prefix", &params)?;
+        let query = Box::new(PrefixQuery::new(term("prefix", field, &value)?));
+        Ok(apply_boost(query, boost))
+    }
+
+    fn wildcard_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("wildcard", body, "value")?;
+        let boost = take_boost("wildcard", &mut params)?;
+        let value = take_required("wildcard", &mut params, "value")?;
+        check_empty("wildcard", &params)?;
+        let query = Box::new(WildcardQuery::new(term("wildcard", field, &value)?)?);
+        Ok(apply_boost(query, boost))
+    }
+
+    fn regexp_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("regexp", body, "value")?;
+        let boost = take_boost("regexp", &mut params)?;
+        let value = take_required("regexp", &mut params, "value")?;
+        check_empty("regexp", &params)?;
+        let query = Box::new(RegexpQuery::new(term("regexp", field, &value)?)?);
+        Ok(apply_boost(query, boost))
+    }
+
+    fn fuzzy_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("fuzzy", body, "value")?;
+        let boost = take_boost("fuzzy", &mut params)?;
+        let value = take_required("fuzzy", &mut params, "value")?;
+        let term = term("fuzzy", field, &value)?;
+        let max_edits = match params.remove("fuzziness") {
+            None => auto_fuzziness(&term.text()?),
+            Some(Value::String(ref s)) if s.eq_ignore_ascii_case("auto") => {
+                auto_fuzziness(&term.text()?)
+            }
+            Some(v) => as_non_negative("fuzzy", "fuzziness", &v)? as u32,
+        };
+        let prefix_length = match params.remove("prefix_length") {
+            Some(v) => as_non_negative("fuzzy", "prefix_length", &v)? as usize,
+            None => 0,
+        };
+        let transpositions = match params.remove("transpositions") {
+            Some(Value::Bool(b)) => b,
+            Some(v) => bail!(IllegalArgument(format!(
+                "[fuzzy] query expects a boolean for [transpositions], got {}",
+                v
+            ))),
+            None => DEFAULT_TRANSPOSITIONS,
+        };
+        check_empty("fuzzy", &params)?;
+        let query = Box::new(FuzzyQuery::with_params(
+            term,
+            max_edits,
+            prefix_length,
+            transpositions,
+        )?);
+        Ok(apply_boost(query, boost))
+    }
+
+    fn range_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let (field, mut params) = field_params("range", body, "")?;
+        let boost = take_boost("range", &mut params)?;
+        let mut lower = None;
+        let mut upper = None;
+        let mut include_lower = true;
+        let mut include_upper = true;
+        for (key, value) in params {
+            match key.as_str() {
+                "gte" => lower = Some(value),
+                "gt" => {
+                    lower = Some(value);
+                    include_lower = false;
+                }
+                "lte" => upper = Some(value),
+                "lt" => {
+                    upper = Some(value);
+                    include_upper = false;
+                }
+                _ => bail!(IllegalArgument(format!(
+                    "[range] query does not support [{}]",
+                    key
+                ))),
+            }
+        }
+
+        let numeric =
+            lower.iter().chain(upper.iter()).all(Value::is_number) || self.is_inet_address(&field);
+        let query = if numeric && (lower.is_some() || upper.is_some()) {
+            let bounds = PointBounds {
+                lower: lower.as_ref(),
+                upper: upper.as_ref(),
+                include_lower,
+                include_upper,
+            };
+            self.point_range_query(field, &bounds)?
+        } else {
+            let lower = lower.map(|v| value_text("range", &v)).transpose()?;
+            let upper = upper.map(|v| value_text("range", &v)).transpose()?;
+            Box::new(TermRangeQuery::new(
+                field,
+                lower.map(String::into_bytes),
+                upper.map(String::into_bytes),
+                include_lower,
+                include_upper,
+            ))
+        };
+        Ok(apply_boost(query, boost))
+    }
+
+    fn bool_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let mut params = object("bool", body)?.clone();
+        let boost = take_boost("bool", &mut params)?;
+        let mut musts: Vec<Box<dyn Query<C>>> = Vec::new();
+        let mut shoulds: Vec<Box<dyn Query<C>>> = Vec::new();
+        let mut filters: Vec<Box<dyn Query<C>>> = Vec::new();
+        let mut must_nots: Vec<Box<dyn Query<C>>> = Vec::new();
+        for (key, value) in &params {
+            let clauses = match key.as_str() {
+                "must" => &mut musts,
+                "should" => &mut shoulds,
+                "filter" => &mut filters,
+                "must_not" => &mut must_nots,
+                _ => bail!(IllegalArgument(format!(
+                    "[bool] query does not support [{}]",
+                    key
+                ))),
+            };
+            match value {
+                Value::Array(queries) => {
+                    for q in queries {
+                        clauses.push(self.parse_value(q)?);
+                    }
+                }
+                _ => clauses.push(self.parse_value(value)?),
+            }
+        }
+        if musts.is_empty() && shoulds.is_empty() && filters.is_empty() {
+            // a purely negative query matches all the other documents
+            filters.push(Box::new(MatchAllDocsQuery));
+        }
+        let query = BooleanQuery::build_with_must_not(musts, shoulds, filters, must_nots)?;
+        Ok(apply_boost(query, boost))
+    }
+
+    fn dis_max_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let mut params = object("dis_max", body)?.clone();
+        let boost = take_boost("dis_max", &mut params)?;
+        let tie_breaker = match params.remove("tie_breaker") {
+            Some(v) => as_f64("dis_max", "tie_breaker", &v)? as f32,
+            None => 0.0,
+        };
+        let queries = match params.remove("queries") {
+            Some(Value::Array(queries)) => queries,
+            _ => bail!(IllegalArgument(
+                "[dis_max] query requires an array of [queries]".into()
+            )),
+        };
+        check_empty("dis_max", &params)?;
+        let mut disjuncts = Vec::with_capacity(queries.len());
+        for q in &queries {
+            disjuncts.push(self.parse_value(q)?);
+        }
+        let query = DisjunctionMaxQuery::build(disjuncts, tie_breaker)?;
+        Ok(apply_boost(query, boost))
+    }
+
+    fn constant_score_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let mut params = object("constant_score", body)?.clone();
+        let boost = take_boost("constant_score", &mut params)?.unwrap_or(1.0);
+        let filter = take_required("constant_score", &mut params, "filter")?;
+        check_empty("constant_score", &params)?;
+        Ok(Box::new(ConstantScoreQuery::with_boost(
+            self.parse_value(&filter)?,
+            boost,
+        )))
+    }
+
+    fn query_string_query<C: Codec>(&self, body: &Value) -> Result<Box<dyn Query<C>>> {
+        let mut params = object("query_string", body)?.clone();
+        let boost = take_boost("query_string", &mut params)?;
+        let query = take_string("query_string", &mut params, "query")?;
+        let default_field = match params.remove("default_field") {
+            Some(v) => value_text("query_string", &v)?,
+            None => bail!(IllegalArgument(
+                "[query_string] query requires a [default_field]".into()
+            )),
+        };
+        let mut parser = QueryParser::new(default_field);
+        if let Some(v) = params.remove("default_operator") {
+            match value_text("query_string", &v)?
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "or" => parser.set_default_operator(Operator::Or),
+                "and" => parser.set_default_operator(Operator::And),
+                op => bail!(IllegalArgument(format!(
+                    "[query_string] query does not support operator [{}]",
+                    op
+                ))),
+            }
+        }
+        if let Some(v) = params.remove("allow_leading_wildcard") {
+            match v {
+                Value::Bool(b) => parser.set_allow_leading_wildcard(b),
+                _ => bail!(IllegalArgument(
+                    "[query_string] query expects a boolean for [allow_leading_wildcard]".into()
+                )),
+            }
+        }
+        if let Some(v) = params.remove("phrase_slop") {
+            parser.set_phrase_slop(as_non_negative("query_string", "phrase_slop", &v)?);
+        }
+        if let Some(v) = params.remove("fuzzy_prefix_length") {
+            parser.set_fuzzy_prefix_length(as_non_negative(
+                "query_string",
+                "fuzzy_prefix_length",
+                &v,
+            )? as usize);
+        }
+        check_empty("query_string", &params)?;
+        Ok(apply_boost(parser.parse(&query)?, boost))
+    }
+
+    fn point_type(&self, field: &str, values: &[&Value]) -> PointValueType {
+        if let Some(point_type) = self.point_types.get(field) {
+            *point_type
+        } else if values.iter().all(|v| v.is_i64()) {
+            PointValueType::Long
+        } else {
+            PointValueType::Double
+        }
+    }
+
+    fn is_inet_address(&self, field: &str) -> bool {
+        match self.point_types.get(field) {
+            Some(PointValueType::InetAddress) => true,
+            _ => false,
+        }
+    }
+
+    fn point_exact_query<C: Codec>(
+        &self,
+        field: String,
+        value: &Value,
+    ) -> Result<Box<dyn Query<C>>> {
+        let bounds = PointBounds {
+            lower: Some(value),
+            upper: Some(value),
+            include_lower: true,
+            include_upper: true,
+        };
+        self.point_range_query(field, &bounds)
+    }
+
+    fn point_set_query<C: Codec>(
+        &self,
+        field: String,
+        values: &[Value],
+    ) -> Result<Box<dyn Query<C>>> {
+        let refs: Vec<&Value> = values.iter().collect();
+        match self.point_type(&field, &refs) {
+            PointValueType::Integer => {
+                let values = values
+                    .iter()
+                    .map(|v| as_i32("terms", &field, v))
+                    .collect::<Result<Vec<i32>>>()?;
+                IntPoint::new_set_query(field, &values)
+            }
+            PointValueType::Long => {
+                let values = values
+                    .iter()
+                    .map(|v| as_i64("terms", &field, v))
+                    .collect::<Result<Vec<i64>>>()?;
+                LongPoint::new_set_query(field, &values)
+            }
+            PointValueType::Float => {
+                let values = values
+                    .iter()
+                    .map(|v| as_f64("terms", &field, v).map(|v| v as f32))
+                    .collect::<Result<Vec<f32>>>()?;
+                FloatPoint::new_set_query(field, &values)
+            }
+            PointValueType::Double => {
+                let values = values
+                    .iter()
+                    .map(|v| as_f64("terms", &field, v))
+                    .collect::<Result<Vec<f64>>>()?;
+                DoublePoint::new_set_query(field, &values)
+            }
+            PointValueType::InetAddress => {
+                let values = values
+                    .iter()
+                    .map(|v| as_inet_address("terms", &field, v))
+                    .collect::<Result<Vec<IpAddr>>>()?;
+                InetAddressPoint::new_set_query(field, &values)
+            }
+        }
+    }
+
+    fn point_range_query<C: Codec>(
+        &self,
+        field: String,
+        bounds: &PointBounds,
+    ) -> Result<Box<dyn Query<C>>> {
+        let values: Vec<&Value> = bounds
+            .lower
+            .iter()
+            .chain(bounds.upper.iter())
+            .cloned()
+            .collect();
+        match self.point_type(&field, &values) {
+            PointValueType::Integer => {
+                let lower = match bounds.lower {
+                    Some(v) => exclusive_i64(as_i64("range", &field, v)?, bounds.include_lower, 1),
+                    None => i64::from(i32::min_value()),
+                };
+                let upper = match bounds.upper {
+                    Some(v) => exclusive_i64(as_i64("range", &field, v)?, bounds.include_upper, -1),
+                    None => i64::from(i32::max_value()),
+                };
+                let clamp = |v: i64| {
+                    v.max(i64::from(i32::min_value()))
+                        .min(i64::from(i32::max_value())) as i32
+                };
+                IntPoint::new_range_query(field, clamp(lower), clamp(upper))
+            }
+            PointValueType::Long => {
+                let lower = match bounds.lower {
+                    Some(v) => exclusive_i64(as_i64("range", &field, v)?, bounds.include_lower, 1),
+                    None => i64::min_value(),
+                };
+                let upper = match bounds.upper {
+                    Some(v) => exclusive_i64(as_i64("range", &field, v)?, bounds.include_upper, -1),
+                    None => i64::max_value(),
+                };
+                LongPoint::new_range_query(field, lower, upper)
+            }
+            PointValueType::Float => {
+                let lower = match bounds.lower {
+                    Some(v) => {
+                        let v = as_f64("range", &field, v)? as f32;
+                        if bounds.include_lower {
+                            v
+                        } else {
+                            FloatPoint::next_up(v)
+                        }
+                    }
+                    None => ::std::f32::NEG_INFINITY,
+                };
+                let upper = match bounds.upper {
+                    Some(v) => {
+                        let v = as_f64("range", &field, v)? as f32;
+                        if bounds.include_upper {
+                            v
+                        } else {
+                            FloatPoint::next_down(v)
+                        }
+                    }
+                    None => ::std::f32::INFINITY,
+                };
+                FloatPoint::new_range_query(field, lower, upper)
+            }
+            PointValueType::Double => {
+                let lower = match bounds.lower {
+                    Some(v) => {
+                        let v = as_f64("range", &field, v)?;
+                        if bounds.include_lower {
+                            v
+                        } else {
+                            DoublePoint::next_up(v)
+                        }
+                    }
+                    None => ::std::f64::NEG_INFINITY,
+                };
+                let upper = match bounds.upper {
+                    Some(v) => {
+                        let v = as_f64("range", &field, v)?;
+                        if bounds.include_upper {
+                            v
+                        } else {
+                            DoublePoint::next_down(v)
+                        }
+                    }
+                    None => ::std::f64::INFINITY,
+                };
+                DoublePoint::new_range_query(field, lower, upper)
+            }
+            PointValueType::InetAddress => {
+                let lower = match bounds.lower {
+                    Some(v) => {
+                        let v = as_inet_address("range", &field, v)?;
+                        if bounds.include_lower {
+                            v
+                        } else {
+                            InetAddressPoint::next_up(v)?
+                        }
+                    }
+                    None => InetAddressPoint::MIN_VALUE,
+                };
+                let upper = match bounds.upper {
+                    Some(v) => {
+                        let v = as_inet_address("range", &field, v)?;
+                        if bounds.include_upper {
+                            v
+                        } else {
+                            InetAddressPoint::next_down(v)?
+                        }
+                    }
+                    None => InetAddressPoint::MAX_VALUE,
+                };
+                InetAddressPoint::new_range_query(field, lower, upper)
+            }
+        }
+    }
+}
+
+struct PointBounds<'a> {
+    lower: Option<&'a Value>,
+    upper: Option<&'a Value>,
+    include_lower: bool,
+    include_upper: bool,
+}
+
+/// make an exclusive integer bound inclusive by moving it one step in `delta` direction
+fn exclusive_i64(value: i64, inclusive: bool, delta: i64) -> i64 {
+    if inclusive {
+        value
+    } else {
+        value.saturating_add(delta)
+    }
+}
+
+/// The edit distance of the `AUTO` fuzziness, based on the length of the term.
+fn auto_fuzziness(text: &str) -> u32 {
+    match text.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+fn apply_boost<C: Codec>(query: Box<dyn Query<C>>, boost: Option<f32>) -> Box<dyn Query<C>> {
+    match boost {
+        Some(boost) => BoostQuery::build(query, boost),
+        None => query,
+    }
+}
+
+fn object<'a>(name: &str, value: &'a Value) -> Result<&'a Map<String, Value>> {
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => bail!(IllegalArgument(format!(
+            "[{}] expects an object, got {}",
+            name, value
+        ))),
+    }
+}
+
+/// the only key and value of an object
+fn single_entry<'a>(name: &str, value: &'a Value) -> Result<(&'a str, &'a Value)> {
+    let map = object(name, value)?;
+    if map.len() != 1 {
+        bail!(IllegalArgument(format!(
+            "[{}] expects an object with a single key, got {}",
+            name, value
+        )));
+    }
+    let (key, value) = map.iter().next().unwrap();
+    Ok((key.as_str(), value))
+}
+
+/// Parses the `{"field": value}` or `{"field": {"key": value, ...}}` forms, the short
+/// form being normalized to `{"key": value}`.
+fn field_params(name: &str, body: &Value, key: &str) -> Result<(String, Map<String, Value>)> {
+    let (field, value) = single_entry(name, body)?;
+    let params = match value {
+        Value::Object(map) => map.clone(),
+        _ if !key.is_empty() => {
+            let mut map = Map::new();
+            map.insert(key.to_string(), value.clone());
+            map
+        }
+        _ => bail!(IllegalArgument(format!(
+            "[{}] query expects an object for field [{}]",
+            name, field
+        ))),
+    };
+    Ok((field.to_string(), params))
+}
+
+fn take_boost(name: &str, params: &mut Map<String, Value>) -> Result<Option<f32>> {
+    match params.remove("boost") {
+        Some(v) => Ok(Some(as_f64(name, "boost", &v)? as f32)),
+        None => Ok(None),
+    }
+}
+
+fn take_required(name: &str, params: &mut Map<String, Value>, key: &str) -> Result<Value> {
+    match params.remove(key) {
+        Some(v) => Ok(v),
+        None => bail!(IllegalArgument(format!(
+            "[{}] query requires [{}]",
+            name, key
+        ))),
+    }
+}
+
+fn take_string(name: &str, params: &mut Map<String, Value>, key: &str) -> Result<String> {
+    let value = take_required(name, params, key)?;
+    value_text(name, &value)
+}
+
+fn check_empty(name: &str, params: &Map<String, Value>) -> Result<()> {
+    if let Some(key) = params.keys().next() {
+        bail!(IllegalArgument(format!(
+            "[{}] query does not support [{}]",
+            name, key
+        )));
+    }
+    Ok(())
+}
+
+/// the text of a scalar value, used for term values
+fn value_text(name: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        _ => bail!(IllegalArgument(format!(
+            "[{}] query expects a scalar value, got {}",
+            name, value
+        ))),
+    }
+}
+
+fn term(name: &str, field: String, value: &Value) -> Result<Term> {
+    Ok(Term::new(field, value_text(name, value)?.into_bytes()))
+}
+
+fn as_i64(name: &str, key: &str, value: &Value) -> Result<i64> {
+    match value.as_i64() {
+        Some(v) => Ok(v),
+        None => bail!(IllegalArgument(format!(
+            "[{}] query expects an integer for [{}], got {}",
+            name, key, value
+        ))),
+    }
+}
+
+fn as_i32(name: &str, key: &str, value: &Value) -> Result<i32> {
+    let v = as_i64(name, key, value)?;
+    if v < i64::from(i32::min_value()) || v > i64::from(i32::max_value()) {
+        bail!(IllegalArgument(format!(
+            "[{}] query expects a 32 bits integer for [{}], got {}",
+            name, key, value
+        )));
+    }
+    Ok(v as i32)
+}
+
+fn as_non_negative(name: &str, key: &str, value: &Value) -> Result<i32> {
+    let v = as_i32(name, key, value)?;
+    if v < 0 {
+        bail!(IllegalArgument(format!(
+            "[{}] query expects a non negative integer for [{}], got {}",
+            name, key, value
+        )));
+    }
+    Ok(v)
+}
+
+fn as_f64(name: &str, key: &str, value: &Value) -> Result<f64> {
+    match value.as_f64() {
+        Some(v) => Ok(v),
+        None => bail!(IllegalArgument(format!(
+            "[{}] query expects a number for [{}], got {}",
+            name, key, value
+        ))),
+    }
+}
+
+fn as_inet_address(name: &str, key: &str, value: &Value) -> Result<IpAddr> {
+    match value.as_str().map(str::parse::<IpAddr>) {
+        Some(Ok(v)) => Ok(v),
+        _ => bail!(IllegalArgument(format!(
+            "[{}] query expects an IP address for [{}], got {}",
+            name, key, value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    fn parse(json: &str) -> String {
+        let q: Box<dyn Query<TestCodec>> = QueryDslParser::new().parse(json).unwrap();
+        q.to_string()
+    }
+
+    fn term(field: &str, text: &str) -> String {
+        format!("TermQuery(field: {}, term: {}, boost: 1)", field, text)
+    }
+
+    #[test]
+    fn test_term_queries() {
+        assert_eq!(
+            parse(r#"{"term": {"title": "rust"}}"#),
+            term("title", "rust")
+        );
+        assert_eq!(
+            parse(r#"{"term": {"title": {"value": "rust", "boost": 2}}}"#),
+            format!("BoostQuery(query: {}, boost: 2)", term("title", "rust"))
+        );
+        assert_eq!(
+            parse(r#"{"match": {"body": {"query": "quick fox", "operator": "and"}}}"#),
+            format!(
+                "BooleanQuery(must: [{}, {}], should: [], filters: [], match: 0)",
+                term("body", "quick"),
+                term("body", "fox")
+            )
+        );
+        assert_eq!(
+            parse(r#"{"prefix": {"title": "ru"}}"#),
+            "PrefixQuery(field: title, prefix: ru)"
+        );
+        assert_eq!(
+            parse(r#"{"range": {"title": {"gte": "a", "lt": "c"}}}"#),
+            "TermRangeQuery(field: title, range: [a TO c})"
+        );
+    }
+
+    #[test]
+    fn test_bool_query() {
+        let json = r#"{
+            "bool": {
+                "must": {"term": {"title": "rust"}},
+                "should": [{"term": {"body": "fast"}}, {"term": {"body": "safe"}}],
+                "must_not": {"term": {"status": "deleted"}}
+            }
+        }"#;
+        assert_eq!(
+            parse(json),
+            format!(
+                "BooleanQuery(must: [{}], should: [{}, {}], filters: [], must_not: [{}], match: 0)",
+                term("title", "rust"),
+                term("body", "fast"),
+                term("body", "safe"),
+                term("status", "deleted")
+            )
+        );
+
+        let json = r#"{"bool": {"must_not": {"term": {"status": "deleted"}}}}"#;
+        assert!(parse(json).contains("filters: [MatchAllDocsQuery()]"));
+    }
+
+    #[test]
+    fn test_numeric_queries() {
+        let mut parser = QueryDslParser::new();
+        parser.set_point_type("year".into(), PointValueType::Integer);
+
+        let q: Box<dyn Query<TestCodec>> = parser
+            .parse(r#"{"range": {"year": {"gt": 2010, "lte": 2020}}}"#)
+            .unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            IntPoint::new_range_query("year".into(), 2011, 2020).unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        let q: Box<dyn Query<TestCodec>> = parser
+            .parse(r#"{"range": {"price": {"lt": 9.5}}}"#)
+            .unwrap();
+        let expected: Box<dyn Query<TestCodec>> = DoublePoint::new_range_query(
+            "price".into(),
+            ::std::f64::NEG_INFINITY,
+            DoublePoint::next_down(9.5),
+        )
+        .unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        let q: Box<dyn Query<TestCodec>> = parser.parse(r#"{"terms": {"id": [3, 1, 2]}}"#).unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            LongPoint::new_set_query("id".into(), &[3, 1, 2]).unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_inet_address_queries() {
+        let mut parser = QueryDslParser::new();
+        parser.set_point_type("ip".into(), PointValueType::InetAddress);
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        let q: Box<dyn Query<TestCodec>> = parser
+            .parse(r#"{"term": {"ip": "192.168.0.0/16"}}"#)
+            .unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_prefix_query("ip".into(), ip("192.168.0.0"), 16).unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        let q: Box<dyn Query<TestCodec>> = parser
+            .parse(r#"{"range": {"ip": {"gt": "10.0.0.255", "lt": "10.0.2.0"}}}"#)
+            .unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_range_query("ip".into(), ip("10.0.1.0"), ip("10.0.1.255"))
+                .unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        let q: Box<dyn Query<TestCodec>> = parser
+            .parse(r#"{"terms": {"ip": ["::1", "127.0.0.1"]}}"#)
+            .unwrap();
+        let expected: Box<dyn Query<TestCodec>> =
+            InetAddressPoint::new_set_query("ip".into(), &[ip("::1"), ip("127.0.0.1")]).unwrap();
+        assert_eq!(q.to_string(), expected.to_string());
+
+        assert!(parser
+            .parse::<TestCodec>(r#"{"term": {"ip": "not an ip"}}"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_malformed_queries() {
+        let parser = QueryDslParser::new();
+        for json in &[
+            r#"{"unknown": {}}"#,
+            r#"{"term": {"a": "b", "c": "d"}}"#,
+            r#"{"term": {"a": {"value": "b", "foo": 1}}}"#,
+            r#"{"bool": {"must": [], "foo": []}}"#,
+            r#"{"match": {"a": {"query": "b", "operator": "xor"}}}"#,
+            r#"{"range": {"a": {"from": 1}}}"#,
+            r#"{"term": "#,
+        ] {
+            let q: Result<Box<dyn Query<TestCodec>>> = parser.parse(json);
+            assert!(q.is_err(), "query {} should fail", json);
+        }
+    }
+    #[test]
+    fn test_out_of_range_integers() {
+        let mut parser = QueryDslParser::new();
+        parser.set_point_type("year".into(), PointValueType::Integer);
+        for json in &[
+            r#"{"match_phrase": {"f": {"query": "a b", "slop": -1}}}"#,
+            r#"{"match_phrase": {"f": {"query": "a b", "slop": 4294967296}}}"#,
+            r#"{"fuzzy": {"f": {"value": "abc", "fuzziness": -1}}}"#,
+            r#"{"fuzzy": {"f": {"value": "abc", "prefix_length": -1}}}"#,
+            r#"{"query_string": {"query": "a", "default_field": "f", "phrase_slop": -1}}"#,
+            r#"{"query_string": {"query": "a~", "default_field": "f", "fuzzy_prefix_length": -1}}"#,
+            r#"{"terms": {"year": [2010, 4294967297]}}"#,
+        ] {
+            let q: Result<Box<dyn Query<TestCodec>>> = parser.parse(json);
+            assert!(q.is_err(), "query {} should fail", json);
+        }
+    }
+}
@@ -0,0 +1,831 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{BooleanQuery, BoostQuery, MatchAllDocsQuery, PhraseQuery, Query};
+use core::search::query::{FuzzyQuery, PrefixQuery, TermQuery, TermRangeQuery, WildcardQuery};
+use core::search::query::{DEFAULT_MAX_EDITS, DEFAULT_TRANSPOSITIONS};
+use core::search::query::{WILDCARD_CHAR, WILDCARD_ESCAPE, WILDCARD_STRING};
+use core::util::unescape;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// The default operator used to combine clauses without an explicit conjunction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Or,
+    And,
+}
+
+/// A parser for user-entered query strings, following the syntax of Lucene's
+/// classic query parser.
+///
+/// The supported syntax includes:
+/// * terms, optionally prefixed by a field: `title:rust`
+/// * phrases with an optional slop: `"quick fox"~2`
+/// * grouping with a field applied to the whole group: `title:(quick fox)`
+/// * boolean operators `AND`, `OR`, `NOT`, `&&`, `||`, `!` and the `+`/`-` modifiers
+/// * inclusive and exclusive ranges, `*` for an open end: `[a TO c}`, `{* TO c]`
+/// * prefix and wildcard terms: `qui*`, `q?ick`
+/// * fuzzy terms with an optional max edits: `quick~`, `quick~1`
+/// * boosts on any clause: `quick^2`, `(quick fox)^0.5`
+/// * `*:*` for matching all the documents
+///
+/// Special characters are escaped with `\`, see `QueryParser::escape`. Terms are
+/// used as is, no analysis is applied, and phrases are split on whitespaces.
+pub struct QueryParser {
+    default_field: String,
+    default_operator: Operator,
+    allow_leading_wildcard: bool,
+    fuzzy_prefix_length: usize,
+    phrase_slop: i32,
+}
+
+impl QueryParser {
+    /// Create a query parser, `default_field` is the field used for terms
+    /// without an explicit field.
+    pub fn new(default_field: String) -> QueryParser {
+        QueryParser {
+            default_field,
+            default_operator: Operator::Or,
+            allow_leading_wildcard: false,
+            fuzzy_prefix_length: 0,
+            phrase_slop: 0,
+        }
+    }
+
+    pub fn default_field(&self) -> &str {
+        &self.default_field
+    }
+
+    pub fn default_operator(&self) -> Operator {
+        self.default_operator
+    }
+
+    /// Sets the boolean operator of the query parser. In default mode
+    /// (`Operator::Or`) terms without any modifiers are considered optional:
+    /// for example `capital of Hungary` is equal to `capital OR of OR Hungary`.
+    /// In `Operator::And` mode terms are considered to be in conjunction: the
+    /// above mentioned query is parsed as `capital AND of AND Hungary`
+    pub fn set_default_operator(&mut self, operator: Operator) {
+        self.default_operator = operator;
+    }
+
+    /// Set to `true` to allow leading wildcard characters.
+    ///
+    /// When set, `*` or `?` are allowed as the first character of a prefix or
+    /// wildcard term. Note that this can produce very slow queries on big indexes.
+    /// Default: false.
+    pub fn set_allow_leading_wildcard(&mut self, allow: bool) {
+        self.allow_leading_wildcard = allow;
+    }
+
+    /// Set the prefix length for fuzzy queries. Default is 0.
+    pub fn set_fuzzy_prefix_length(&mut self, fuzzy_prefix_length: usize) {
+        self.fuzzy_prefix_length = fuzzy_prefix_length;
+    }
+
+    /// Sets the default slop for phrases. If zero, then exact phrase matches are
+    /// required. Default value is zero.
+    pub fn set_phrase_slop(&mut self, phrase_slop: i32) {
+        self.phrase_slop = phrase_slop;
+    }
+
+    /// Parses a query string, returning a `Query`.
+    pub fn parse<C: Codec>(&self, query: &str) -> Result<Box<dyn Query<C>>> {
+        let tokens = match tokenize(query) {
+            Ok(tokens) => tokens,
+            Err(msg) => return Err(parse_error(query, &msg)),
+        };
+        let mut parser = Parser {
+            config: self,
+            tokens,
+            pos: 0,
+        };
+        let res = match parser.parse_query(&self.default_field) {
+            Ok(Some(q)) => {
+                if parser.pos < parser.tokens.len() {
+                    Err(format!("unexpected {:?}", parser.tokens[parser.pos]))
+                } else {
+                    Ok(q)
+                }
+            }
+            Ok(None) => Err("empty query".to_string()),
+            Err(msg) => Err(msg),
+        };
+        res.map_err(|msg| parse_error(query, &msg))
+    }
+
+    /// Returns a string where the special characters of the query syntax are
+    /// escaped with `\`.
+    pub fn escape(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                '\\' | '+' | '-' | '!' | '(' | ')' | ':' | '^' | '[' | ']' | '"' | '{' | '}'
+                | '~' | '*' | '?' | '|' | '&' | '/' => escaped.push('\\'),
+                _ => {}
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+}
+
+fn parse_error(query: &str, msg: &str) -> ::error::Error {
+    IllegalArgument(format!("Cannot parse '{}': {}", query, msg)).into()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+    LParen,
+    RParen,
+    Colon,
+    Caret(f32),
+    Tilde(Option<f32>),
+    Quoted(String),
+    /// The raw term text, escapes are kept so wildcards can be told apart
+    Term(String),
+    Range {
+        lower: Option<String>,
+        upper: Option<String>,
+        include_lower: bool,
+        include_upper: bool,
+    },
+}
+
+fn is_term_delimiter(c: char) -> bool {
+    c.is_whitespace()
+        || match c {
+            '(' | ')' | ':' | '^' | '~' | '"' | '[' | ']' | '{' | '}' => true,
+            _ => false,
+        }
+}
+
+fn tokenize(query: &str) -> ::std::result::Result<Vec<Token>, String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+        i += 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ':' => Token::Colon,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '!' => Token::Not,
+            '&' if next == Some('&') => {
+                i += 1;
+                Token::And
+            }
+            '|' if next == Some('|') => {
+                i += 1;
+                Token::Or
+            }
+            '^' => {
+                let number = read_number(&chars, &mut i);
+                match number.parse::<f32>() {
+                    Ok(boost) => Token::Caret(boost),
+                    Err(_) => return Err(format!("invalid boost '{}'", number)),
+                }
+            }
+            '~' => {
+                let number = read_number(&chars, &mut i);
+                if number.is_empty() {
+                    Token::Tilde(None)
+                } else {
+                    match number.parse::<f32>() {
+                        Ok(v) => Token::Tilde(Some(v)),
+                        Err(_) => return Err(format!("invalid fuzzy or slop '{}'", number)),
+                    }
+                }
+            }
+            '"' => Token::Quoted(read_quoted(&chars, &mut i)?),
+            '[' | '{' => read_range(&chars, &mut i, c == '[')?,
+            _ => {
+                let mut raw = String::new();
+                raw.push(c);
+                if c == '\\' {
+                    match next {
+                        Some(n) => {
+                            raw.push(n);
+                            i += 1;
+                        }
+                        None => return Err("term can not end with escape character".into()),
+                    }
+                }
+                while i < chars.len() && !is_term_delimiter(chars[i]) {
+                    raw.push(chars[i]);
+                    if chars[i] == '\\' {
+                        if i + 1 == chars.len() {
+                            return Err("term can not end with escape character".into());
+                        }
+                        raw.push(chars[i + 1]);
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                match raw.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Term(raw),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn read_number(chars: &[char], i: &mut usize) -> String {
+    let mut number = String::new();
+    while *i < chars.len() && (chars[*i].is_ascii_digit() || chars[*i] == '.') {
+        number.push(chars[*i]);
+        *i += 1;
+    }
+    number
+}
+
+/// read a quoted string whose opening quote has been consumed
+fn read_quoted(chars: &[char], i: &mut usize) -> ::std::result::Result<String, String> {
+    let mut text = String::new();
+    while *i < chars.len() {
+        let c = chars[*i];
+        *i += 1;
+        match c {
+            '"' => return Ok(text),
+            '\\' if *i < chars.len() => {
+                text.push(chars[*i]);
+                *i += 1;
+            }
+            _ => text.push(c),
+        }
+    }
+    Err("unterminated quoted string".into())
+}
+
+/// read a range whose opening bracket has been consumed
+fn read_range(
+    chars: &[char],
+    i: &mut usize,
+    include_lower: bool,
+) -> ::std::result::Result<Token, String> {
+    let lower = read_range_term(chars, i)?;
+    match read_range_term(chars, i)? {
+        Some(ref to) if to == "TO" => {}
+        _ => return Err("missing 'TO' in range".into()),
+    }
+    let upper = read_range_term(chars, i)?;
+    skip_whitespaces(chars, i);
+    let include_upper = match chars.get(*i) {
+        Some(']') => true,
+        Some('}') => false,
+        _ => return Err("unterminated range".into()),
+    };
+    *i += 1;
+    Ok(Token::Range {
+        lower,
+        upper,
+        include_lower,
+        include_upper,
+    })
+}
+
+/// read a term of a range, `None` is returned for an open end `*`
+fn read_range_term(chars: &[char], i: &mut usize) -> ::std::result::Result<Option<String>, String> {
+    skip_whitespaces(chars, i);
+    if *i < chars.len() && chars[*i] == '"' {
+        *i += 1;
+        return read_quoted(chars, i).map(Some);
+    }
+    let mut text = String::new();
+    while *i < chars.len() {
+        let c = chars[*i];
+        if c.is_whitespace() || c == ']' || c == '}' {
+            break;
+        }
+        *i += 1;
+        if c == '\\' && *i < chars.len() {
+            text.push(chars[*i]);
+            *i += 1;
+        } else {
+            text.push(c);
+        }
+    }
+    if text.is_empty() {
+        Err("missing range term".into())
+    } else if text == "*" {
+        Ok(None)
+    } else {
+        Ok(Some(text))
+    }
+}
+
+fn skip_whitespaces(chars: &[char], i: &mut usize) {
+    while *i < chars.len() && chars[*i].is_whitespace() {
+        *i += 1;
+    }
+}
+
+/// Returns the positions of the unescaped wildcard characters of a raw term.
+fn wildcard_positions(raw: &str) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut escaped = false;
+    for (i, c) in raw.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == WILDCARD_ESCAPE {
+            escaped = true;
+        } else if c == WILDCARD_STRING || c == WILDCARD_CHAR {
+            positions.push(i);
+        }
+    }
+    positions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conjunction {
+    None,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    None,
+    Required,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Occur {
+    Must,
+    Should,
+    MustNot,
+}
+
+struct Parser<'a> {
+    config: &'a QueryParser,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+type ParseResult<T> = ::std::result::Result<T, String>;
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn parse_query<C: Codec>(&mut self, field: &str) -> ParseResult<Option<Box<dyn Query<C>>>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query<C>>)> = Vec::new();
+        let mut first = true;
+        loop {
+            match self.peek() {
+                None | Some(Token::RParen) => break,
+                _ => {}
+            }
+            let conjunction = if first {
+                Conjunction::None
+            } else {
+                self.conjunction()
+            };
+            let modifier = self.modifier();
+            let query = self.parse_clause(field)?;
+            self.add_clause(&mut clauses, conjunction, modifier, query);
+            first = false;
+        }
+        self.boolean_query(clauses)
+    }
+
+    fn conjunction(&mut self) -> Conjunction {
+        let conjunction = match self.peek() {
+            Some(Token::And) => Conjunction::And,
+            Some(Token::Or) => Conjunction::Or,
+            _ => return Conjunction::None,
+        };
+        self.pos += 1;
+        conjunction
+    }
+
+    fn modifier(&mut self) -> Modifier {
+        let modifier = match self.peek() {
+            Some(Token::Plus) => Modifier::Required,
+            Some(Token::Minus) | Some(Token::Not) => Modifier::Not,
+            _ => return Modifier::None,
+        };
+        self.pos += 1;
+        modifier
+    }
+
+    fn add_clause<C: Codec>(
+        &self,
+        clauses: &mut Vec<(Occur, Box<dyn Query<C>>)>,
+        conjunction: Conjunction,
+        modifier: Modifier,
+        query: Option<Box<dyn Query<C>>>,
+    ) {
+        // If this term is introduced by AND, make the preceding term required,
+        // unless it's already prohibited
+        if let Some(last) = clauses.last_mut() {
+            if last.0 != Occur::MustNot {
+                if conjunction == Conjunction::And {
+                    last.0 = Occur::Must;
+                } else if self.config.default_operator == Operator::And
+                    && conjunction == Conjunction::Or
+                {
+                    // If this term is introduced by OR, make the preceding term
+                    // optional, unless it's prohibited (that means we leave -a OR b
+                    // but +a OR b-->a OR b). Notice if the input is a OR b, first
+                    // term is parsed as required; without this modification a OR b
+                    // would be parsed as +a OR b
+                    last.0 = Occur::Should;
+                }
+            }
+        }
+
+        // We might have been passed a null query; the term might have been
+        // filtered away by the analyzer.
+        let query = match query {
+            Some(q) => q,
+            None => return,
+        };
+
+        let prohibited = modifier == Modifier::Not;
+        let required = match self.config.default_operator {
+            Operator::Or => {
+                modifier == Modifier::Required || (conjunction == Conjunction::And && !prohibited)
+            }
+            Operator::And => !prohibited && conjunction != Conjunction::Or,
+        };
+        let occur = if prohibited {
+            Occur::MustNot
+        } else if required {
+            Occur::Must
+        } else {
+            Occur::Should
+        };
+        clauses.push((occur, query));
+    }
+
+    fn boolean_query<C: Codec>(
+        &self,
+        clauses: Vec<(Occur, Box<dyn Query<C>>)>,
+    ) -> ParseResult<Option<Box<dyn Query<C>>>> {
+        if clauses.is_empty() {
+            return Ok(None);
+        }
+        let mut musts = Vec::new();
+        let mut shoulds = Vec::new();
+        let mut must_nots = Vec::new();
+        for (occur, query) in clauses {
+            match occur {
+                Occur::Must => musts.push(query),
+                Occur::Should => shoulds.push(query),
+                Occur::MustNot => must_nots.push(query),
+            }
+        }
+        if musts.is_empty() && shoulds.is_empty() {
+            return Err("purely negative queries are not supported".into());
+        }
+        BooleanQuery::build_with_must_not(musts, shoulds, vec![], must_nots)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    fn parse_clause<C: Codec>(&mut self, field: &str) -> ParseResult<Option<Box<dyn Query<C>>>> {
+        let field = match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+            (Some(Token::Term(raw)), Some(Token::Colon)) => {
+                let field = unescape(raw);
+                self.pos += 2;
+                field
+            }
+            _ => field.to_string(),
+        };
+
+        match self.next_token() {
+            Some(Token::LParen) => {
+                let query = self.parse_query(&field)?;
+                if self.next_token() != Some(Token::RParen) {
+                    return Err("missing closing parenthesis".into());
+                }
+                let boost = self.boost();
+                Ok(query.map(|q| Self::apply_boost(q, boost)))
+            }
+            Some(Token::Term(raw)) => {
+                let (fuzzy, boost) = self.term_suffixes();
+                let query = self.term_query(&field, &raw, fuzzy)?;
+                Ok(Some(Self::apply_boost(query, boost)))
+            }
+            Some(Token::Quoted(text)) => {
+                let (slop, boost) = self.term_suffixes();
+                let query = self.phrase_query(&field, &text, slop)?;
+                Ok(query.map(|q| Self::apply_boost(q, boost)))
+            }
+            Some(Token::Range {
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            }) => {
+                let boost = self.boost();
+                let query = Box::new(TermRangeQuery::new_string_range(
+                    field,
+                    lower.as_ref().map(String::as_str),
+                    upper.as_ref().map(String::as_str),
+                    include_lower,
+                    include_upper,
+                ));
+                Ok(Some(Self::apply_boost(query, boost)))
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of query".into()),
+        }
+    }
+
+    fn boost(&mut self) -> Option<f32> {
+        if let Some(Token::Caret(boost)) = self.peek() {
+            let boost = *boost;
+            self.pos += 1;
+            Some(boost)
+        } else {
+            None
+        }
+    }
+
+    /// the optional `~` and `^` following a term or phrase, in any order
+    fn term_suffixes(&mut self) -> (Option<Option<f32>>, Option<f32>) {
+        let mut fuzzy = None;
+        let mut boost = None;
+        loop {
+            match self.peek() {
+                Some(Token::Tilde(v)) if fuzzy.is_none() => fuzzy = Some(*v),
+                Some(Token::Caret(b)) if boost.is_none() => boost = Some(*b),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        (fuzzy, boost)
+    }
+
+    fn apply_boost<C: Codec>(query: Box<dyn Query<C>>, boost: Option<f32>) -> Box<dyn Query<C>> {
+        match boost {
+            Some(boost) => BoostQuery::build(query, boost),
+            None => query,
+        }
+    }
+
+    fn term_query<C: Codec>(
+        &self,
+        field: &str,
+        raw: &str,
+        fuzzy: Option<Option<f32>>,
+    ) -> ParseResult<Box<dyn Query<C>>> {
+        let wildcards = wildcard_positions(raw);
+        if !wildcards.is_empty() {
+            if field == "*" && raw == "*" {
+                return Ok(Box::new(MatchAllDocsQuery));
+            }
+            if !self.config.allow_leading_wildcard && wildcards[0] == 0 {
+                return Err("'*' or '?' not allowed as first character in WildcardQuery".into());
+            }
+            let last = raw.len() - 1;
+            if wildcards.len() == 1 && wildcards[0] == last && raw.ends_with(WILDCARD_STRING) {
+                let prefix = unescape(&raw[..last]);
+                return Ok(Box::new(PrefixQuery::new(Term::new(
+                    field.to_string(),
+                    prefix.into_bytes(),
+                ))));
+            }
+            let term = Term::new(field.to_string(), raw.as_bytes().to_vec());
+            return match WildcardQuery::new(term) {
+                Ok(q) => Ok(Box::new(q)),
+                Err(e) => Err(e.to_string()),
+            };
+        }
+
+        let text = unescape(raw);
+        if let Some(min_sim) = fuzzy {
+            let max_edits = match min_sim {
+                Some(min_sim) => FuzzyQuery::float_to_edits(min_sim, text.chars().count()),
+                None => DEFAULT_MAX_EDITS,
+            };
+            let term = Term::new(field.to_string(), text.into_bytes());
+            return match FuzzyQuery::with_params(
+                term,
+                max_edits,
+                self.config.fuzzy_prefix_length,
+                DEFAULT_TRANSPOSITIONS,
+            ) {
+                Ok(q) => Ok(Box::new(q)),
+                Err(e) => Err(e.to_string()),
+            };
+        }
+        Ok(Box::new(TermQuery::new(
+            Term::new(field.to_string(), text.into_bytes()),
+            1.0,
+            None,
+        )))
+    }
+
+    fn phrase_query<C: Codec>(
+        &self,
+        field: &str,
+        text: &str,
+        slop: Option<Option<f32>>,
+    ) -> ParseResult<Option<Box<dyn Query<C>>>> {
+        let mut terms: Vec<Term> = text
+            .split_whitespace()
+            .map(|t| Term::new(field.to_string(), t.as_bytes().to_vec()))
+            .collect();
+        match terms.len() {
+            0 => Ok(None),
+            1 => Ok(Some(Box::new(TermQuery::new(terms.remove(0), 1.0, None)))),
+            _ => {
+                let slop = match slop {
+                    Some(Some(slop)) => slop as i32,
+                    _ => self.config.phrase_slop,
+                };
+                match PhraseQuery::build(terms, slop, None, None) {
+                    Ok(q) => Ok(Some(Box::new(q))),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    fn parse(parser: &QueryParser, query: &str) -> String {
+        let q: Box<dyn Query<TestCodec>> = parser.parse(query).unwrap();
+        q.to_string()
+    }
+
+    fn term(field: &str, text: &str) -> String {
+        format!("TermQuery(field: {}, term: {}, boost: 1)", field, text)
+    }
+
+    #[test]
+    fn test_terms_and_operators() {
+        let mut parser = QueryParser::new("body".to_string());
+        assert_eq!(parse(&parser, "rust"), term("body", "rust"));
+        assert_eq!(parse(&parser, "title:rust"), term("title", "rust"));
+        assert_eq!(
+            parse(&parser, "a b"),
+            format!(
+                "BooleanQuery(must: [], should: [{}, {}], filters: [], match: 1)",
+                term("body", "a"),
+                term("body", "b")
+            )
+        );
+        assert_eq!(
+            parse(&parser, "a AND b"),
+            format!(
+                "BooleanQuery(must: [{}, {}], should: [], filters: [], match: 0)",
+                term("body", "a"),
+                term("body", "b")
+            )
+        );
+        assert_eq!(
+            parse(&parser, "+a -b c"),
+            format!(
+                "BooleanQuery(must: [{}], should: [{}], filters: [], must_not: [{}], match: 0)",
+                term("body", "a"),
+                term("body", "c"),
+                term("body", "b")
+            )
+        );
+        assert_eq!(
+            parse(&parser, "a && NOT b"),
+            format!(
+                "BooleanQuery(must: [{}], should: [], filters: [], must_not: [{}], match: 0)",
+                term("body", "a"),
+                term("body", "b")
+            )
+        );
+
+        parser.set_default_operator(Operator::And);
+        assert_eq!(
+            parse(&parser, "a b OR c"),
+            format!(
+                "BooleanQuery(must: [{}], should: [{}, {}], filters: [], match: 0)",
+                term("body", "a"),
+                term("body", "b"),
+                term("body", "c")
+            )
+        );
+    }
+
+    #[test]
+    fn test_groups_and_boosts() {
+        let parser = QueryParser::new("body".to_string());
+        assert_eq!(
+            parse(&parser, "title:(a b)^2"),
+            format!(
+                "BoostQuery(query: BooleanQuery(must: [], should: [{}, {}], filters: [], match: \
+                 1), boost: 2)",
+                term("title", "a"),
+                term("title", "b")
+            )
+        );
+        assert_eq!(
+            parse(&parser, "a^0.5"),
+            format!("BoostQuery(query: {}, boost: 0.5)", term("body", "a"))
+        );
+        assert_eq!(parse(&parser, "*:*"), "MatchAllDocsQuery()");
+    }
+
+    #[test]
+    fn test_multi_term_queries() {
+        let mut parser = QueryParser::new("body".to_string());
+        assert_eq!(
+            parse(&parser, "qui*"),
+            "PrefixQuery(field: body, prefix: qui)"
+        );
+        assert_eq!(
+            parse(&parser, "q?i*k"),
+            "WildcardQuery(field: body, wildcard: q?i*k)"
+        );
+        assert_eq!(
+            parse(&parser, "date:[a TO c}"),
+            "TermRangeQuery(field: date, range: [a TO c})"
+        );
+        assert_eq!(
+            parse(&parser, "date:{* TO \"c d\"]"),
+            "TermRangeQuery(field: date, range: {* TO c d])"
+        );
+        assert_eq!(
+            parse(&parser, "quick~1"),
+            "FuzzyQuery(field: body, term: quick, max_edits: 1, prefix_length: 0, transpositions: \
+             true)"
+        );
+        assert_eq!(parse(&parser, "a\\:b\\*"), term("body", "a:b*"));
+
+        let q: Result<Box<dyn Query<TestCodec>>> = parser.parse("*ick");
+        assert!(q.is_err());
+        parser.set_allow_leading_wildcard(true);
+        assert_eq!(
+            parse(&parser, "*ick"),
+            "WildcardQuery(field: body, wildcard: *ick)"
+        );
+    }
+
+    #[test]
+    fn test_phrases() {
+        let parser = QueryParser::new("body".to_string());
+        assert_eq!(parse(&parser, "\"rust\""), term("body", "rust"));
+        let q = parse(&parser, "title:\"quick fox\"~2^3");
+        assert!(q.starts_with("BoostQuery(query: PhraseQuery(field: title"));
+        assert!(q.contains("slop: 2"));
+        assert!(q.ends_with("boost: 3)"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parser = QueryParser::new("body".to_string());
+        for query in &[
+            "", "(a b", "a b)", "\"a b", "[a TO", "-a", "a AND", "title:",
+        ] {
+            let q: Result<Box<dyn Query<TestCodec>>> = parser.parse(query);
+            assert!(q.is_err(), "query '{}' should fail", query);
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(QueryParser::escape("a+b:(c)"), "a\\+b\\:\\(c\\)");
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AutomatonTermIterator, Codec, CodecTermIterator, CodecTerms, Terms};
+use core::doc::Term;
+use core::search::query::{multi_term_weight, MultiTermQuery, RewriteMethod};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{CompiledAutomaton, RegExp, DEFAULT_MAX_DETERMINIZED_STATES};
+
+use error::Result;
+
+use std::fmt;
+
+pub const REGEXP: &str = "regexp";
+
+/// A fast regular expression query based on the `core::util::automaton` package.
+///
+/// - Comparisons are fast
+/// - The term dictionary is enumerated in an intelligent way, to avoid comparisons. See
+///   `AutomatonTermIterator` for more details.
+///
+/// The supported syntax is documented in the `RegExp` struct. Note this might be
+/// different than other regular expression implementations. For some
+/// alternatives with different syntax, look under the sandbox.
+///
+/// Note this query can be slow, as it needs to iterate over many terms. In
+/// order to prevent extremely slow RegexpQueries, a Regexp term should not
+/// start with the expression `.*`
+#[derive(Clone)]
+pub struct RegexpQuery {
+    term: Term,
+    regexp: RegExp,
+    compiled: CompiledAutomaton,
+    rewrite_method: RewriteMethod,
+}
+
+impl RegexpQuery {
+    /// Constructs a query for terms matching `term`, with all optional syntax
+    /// enabled.
+    pub fn new(term: Term) -> Result<RegexpQuery> {
+        RegexpQuery::with_flags(term, RegExp::ALL, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Constructs a query for terms matching `term`.
+    ///
+    /// `flags` is the optional `RegExp` syntax features, `max_determinized_states`
+    /// is the maximum number of states that compiling the automaton for the
+    /// regexp can result in. Set higher to allow more complex queries and lower
+    /// to prevent memory exhaustion.
+    pub fn with_flags(
+        term: Term,
+        flags: u32,
+        max_determinized_states: usize,
+    ) -> Result<RegexpQuery> {
+        let regexp = RegExp::with_flags(&term.text()?, flags)?;
+        let automaton = regexp.to_automaton(max_determinized_states)?;
+        let compiled = CompiledAutomaton::new(&automaton, true, false, max_determinized_states)?;
+        Ok(RegexpQuery {
+            term,
+            regexp,
+            compiled,
+            rewrite_method: RewriteMethod::default(),
+        })
+    }
+
+    /// Returns the regexp of this query wrapped in a Term.
+    pub fn regexp(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn compiled(&self) -> &CompiledAutomaton {
+        &self.compiled
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.rewrite_method = rewrite_method;
+    }
+}
+
+impl<C: Codec> MultiTermQuery<C> for RegexpQuery {
+    type Iter = AutomatonTermIterator<CodecTermIterator<C>>;
+
+    fn field(&self) -> &str {
+        self.term.field()
+    }
+
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter> {
+        terms.intersect(&self.compiled)
+    }
+}
+
+impl<C: Codec> Query<C> for RegexpQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        multi_term_weight(self, REGEXP, self.rewrite_method, searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for RegexpQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RegexpQuery(field: {}, regexp: {})",
+            self.term.field(),
+            &self.regexp
+        )
+    }
+}
@@ -0,0 +1,438 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+use core::codec::{Codec, CodecPostingIterator, CodecTermState};
+use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{Scorer, TermScorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::{SimScorer, SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::{DocId, KeyedContext};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const SYNONYM: &str = "synonym";
+
+/// A query that treats multiple terms as synonyms.
+///
+/// For scoring purposes, this query tries to score the terms as if you had
+/// indexed them as one term: the doc freq is the max of the doc freqs of the
+/// terms, the total term freq their sum, and the term freq of a document is
+/// the sum of the freqs of the terms it contains. So unlike a boolean `OR`,
+/// rare synonyms don't get a higher IDF than the term of the query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SynonymQuery {
+    terms: Vec<Term>,
+    boost: f32,
+    ctx: Option<KeyedContext>,
+}
+
+impl SynonymQuery {
+    pub fn new<T: Into<Option<KeyedContext>>>(
+        terms: Vec<Term>,
+        boost: f32,
+        ctx: T,
+    ) -> Result<SynonymQuery> {
+        if terms.is_empty() {
+            bail!(IllegalArgument(
+                "SynonymQuery: terms should not be empty!".into()
+            ));
+        }
+        let field = terms[0].field();
+        if terms.iter().any(|t| t.field() != field) {
+            bail!(IllegalArgument(
+                "SynonymQuery: synonyms must be across the same field".into()
+            ));
+        }
+        let mut terms = terms;
+        terms.sort_by(|a, b| a.bytes.cmp(&b.bytes));
+        terms.dedup();
+        Ok(SynonymQuery {
+            terms,
+            boost,
+            ctx: ctx.into(),
+        })
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    pub fn field(&self) -> &str {
+        self.terms[0].field()
+    }
+}
+
+impl<C: Codec> Query<C> for SynonymQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let max_doc = i64::from(searcher.max_doc());
+        let mut term_states = Vec::with_capacity(self.terms.len());
+        // blend the statistics of the terms as if they were a single term
+        let mut doc_freq = 0i64;
+        let mut total_term_freq = 0i64;
+        for term in &self.terms {
+            let term_context = searcher.term_state(term)?;
+            if needs_scores {
+                let stats = searcher.term_statistics(term, term_context.as_ref());
+                doc_freq = doc_freq.max(stats.doc_freq);
+                if total_term_freq >= 0 && stats.total_term_freq >= 0 {
+                    total_term_freq += stats.total_term_freq;
+                } else {
+                    total_term_freq = -1;
+                }
+            }
+            term_states.push(term_context.term_states());
+        }
+        let field = self.field();
+        let (term_stats, collection_stats) = if needs_scores {
+            (
+                TermStatistics::new(self.terms[0].bytes.clone(), doc_freq, total_term_freq),
+                searcher.collections_statistics(field)?,
+            )
+        } else {
+            (
+                TermStatistics::new(self.terms[0].bytes.clone(), max_doc, -1),
+                CollectionStatistics::new(field.to_string(), max_doc, -1, -1, -1),
+            )
+        };
+        let similarity = searcher.similarity(field, needs_scores);
+        let sim_weight = similarity.compute_weight(
+            &collection_stats,
+            &[term_stats],
+            self.ctx.as_ref(),
+            self.boost,
+        );
+        Ok(Box::new(SynonymWeight {
+            terms: self.terms.clone(),
+            boost: self.boost,
+            term_states,
+            similarity,
+            sim_weight,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.terms
+            .iter()
+            .map(|t| TermQuery::new(t.clone(), self.boost, self.ctx.clone()))
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for SynonymQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|t| String::from_utf8_lossy(&t.bytes).into_owned())
+            .collect();
+        write!(
+            f,
+            "SynonymQuery(field: {}, terms: [{}], boost: {})",
+            self.field(),
+            terms.join(", "),
+            self.boost
+        )
+    }
+}
+
+struct SynonymWeight<C: Codec> {
+    terms: Vec<Term>,
+    boost: f32,
+    // the term states of each term, keyed by the doc base of the segments
+    term_states: Vec<HashMap<DocId, CodecTermState<C>>>,
+    similarity: Box<dyn Similarity<C>>,
+    sim_weight: Box<dyn SimWeight<C>>,
+    needs_scores: bool,
+}
+
+impl<C: Codec> SynonymWeight<C> {
+    fn create_postings_iterators(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Vec<CodecPostingIterator<C>>> {
+        let flags = if self.needs_scores {
+            PostingIteratorFlags::FREQS
+        } else {
+            PostingIteratorFlags::NONE
+        };
+        let mut iterators = Vec::with_capacity(self.terms.len());
+        for (term, states) in self.terms.iter().zip(self.term_states.iter()) {
+            if let Some(state) = states.get(&reader.doc_base) {
+                if let Some(postings) =
+                    reader
+                        .reader
+                        .postings_from_state(term, &state, i32::from(flags))?
+                {
+                    iterators.push(postings);
+                }
+            }
+        }
+        Ok(iterators)
+    }
+}
+
+impl<C: Codec> Weight<C> for SynonymWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let mut iterators = self.create_postings_iterators(reader)?;
+        if iterators.is_empty() {
+            return Ok(None);
+        }
+        let sim_scorer = self.sim_weight.sim_scorer(reader.reader)?;
+        if iterators.len() == 1 {
+            Ok(Some(Box::new(TermScorer::new(
+                sim_scorer,
+                iterators.remove(0),
+            ))))
+        } else {
+            Ok(Some(Box::new(SynonymScorer::new(sim_scorer, iterators))))
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        SYNONYM
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.sim_weight.normalize(norm, boost * self.boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.sim_weight.get_value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let mut freq = 0;
+        for mut postings in self.create_postings_iterators(reader)? {
+            if postings.advance(doc)? == doc {
+                freq += if self.needs_scores {
+                    postings.freq()?
+                } else {
+                    1
+                };
+            }
+        }
+        if freq > 0 {
+            let freq = freq as f32;
+            let freq_expl = Explanation::new(true, freq, format!("termFreq={}", freq), vec![]);
+            let score_expl = self.sim_weight.explain(reader.reader, doc, freq_expl)?;
+            Ok(Explanation::new(
+                true,
+                score_expl.value(),
+                format!(
+                    "weight({} in {}) [{}], result of:",
+                    self, doc, self.similarity
+                ),
+                vec![score_expl],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                "no matching term".to_string(),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for SynonymWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|t| String::from_utf8_lossy(&t.bytes).into_owned())
+            .collect();
+        write!(
+            f,
+            "SynonymWeight(field: {}, terms: [{}], similarity: {}, need_score: {})",
+            self.terms[0].field(),
+            terms.join(", "),
+            &self.similarity,
+            self.needs_scores
+        )
+    }
+}
+
+/// Iterates the union of the postings of the synonyms and scores the docs with
+/// the sum of the freqs of the terms on them.
+struct SynonymScorer<T: PostingIterator> {
+    sim_scorer: Box<dyn SimScorer>,
+    sub_iterators: Vec<T>,
+    curr_doc: DocId,
+    cost: usize,
+}
+
+impl<T: PostingIterator> SynonymScorer<T> {
+    fn new(sim_scorer: Box<dyn SimScorer>, sub_iterators: Vec<T>) -> SynonymScorer<T> {
+        let cost = sub_iterators.iter().map(|s| s.cost()).sum();
+        let curr_doc = sub_iterators
+            .iter()
+            .map(|s| s.doc_id())
+            .min()
+            .unwrap_or(NO_MORE_DOCS);
+        SynonymScorer {
+            sim_scorer,
+            sub_iterators,
+            curr_doc,
+            cost,
+        }
+    }
+
+    fn freq(&self) -> Result<i32> {
+        let mut freq = 0;
+        for s in &self.sub_iterators {
+            if s.doc_id() == self.curr_doc {
+                freq += s.freq()?;
+            }
+        }
+        Ok(freq)
+    }
+}
+
+impl<T: PostingIterator> Scorer for SynonymScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        let doc_id = self.curr_doc;
+        let freq = self.freq()?;
+        self.sim_scorer.score(doc_id, freq as f32)
+    }
+
+    fn max_score(&self) -> f32 {
+        self.sim_scorer.max_score()
+    }
+}
+
+impl<T: PostingIterator> DocIterator for SynonymScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.curr_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let curr_doc = self.curr_doc;
+        let mut min_doc = NO_MORE_DOCS;
+        for s in self.sub_iterators.iter_mut() {
+            if s.doc_id() == curr_doc {
+                s.next()?;
+            }
+            min_doc = min_doc.min(s.doc_id());
+        }
+        self.curr_doc = min_doc;
+        Ok(min_doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let mut min_doc = NO_MORE_DOCS;
+        for s in self.sub_iterators.iter_mut() {
+            if s.doc_id() < target {
+                s.advance(target)?;
+            }
+            min_doc = min_doc.min(s.doc_id());
+        }
+        self.curr_doc = min_doc;
+        Ok(min_doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::MockPostingIterator;
+
+    struct FreqSimScorer;
+
+    impl SimScorer for FreqSimScorer {
+        fn score(&mut self, _doc: DocId, freq: f32) -> Result<f32> {
+            Ok(freq)
+        }
+
+        fn compute_slop_factor(&self, _distance: i32) -> f32 {
+            1.0
+        }
+    }
+
+    #[test]
+    fn test_synonym_query() {
+        let field = "title".to_string();
+        let query = SynonymQuery::new(
+            vec![
+                Term::new(field.clone(), b"tv".to_vec()),
+                Term::new(field.clone(), b"television".to_vec()),
+                Term::new(field.clone(), b"tv".to_vec()),
+            ],
+            1.0,
+            None,
+        )
+        .unwrap();
+        assert_eq!(query.terms().len(), 2);
+        assert_eq!(query.field(), "title");
+
+        assert!(SynonymQuery::new(vec![], 1.0, None).is_err());
+        assert!(SynonymQuery::new(
+            vec![
+                Term::new(field.clone(), b"tv".to_vec()),
+                Term::new("body".to_string(), b"television".to_vec()),
+            ],
+            1.0,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_synonym_scorer() {
+        let mut scorer = SynonymScorer::new(
+            Box::new(FreqSimScorer),
+            vec![
+                MockPostingIterator::new(vec![1, 3, 5], vec![2, 1, 4]),
+                MockPostingIterator::new(vec![3, 4, 5], vec![3, 2, 1]),
+            ],
+        );
+        assert_eq!(scorer.cost(), 6);
+
+        let mut docs = vec![];
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            docs.push((doc, scorer.score().unwrap()));
+        }
+        assert_eq!(docs, vec![(1, 2.0), (3, 4.0), (4, 2.0), (5, 5.0)]);
+    }
+}
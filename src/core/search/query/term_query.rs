@@ -17,7 +17,7 @@ use std::collections::HashMap;
 use std::fmt;
 
 use core::codec::{Codec, CodecPostingIterator, CodecTermState};
-use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::codec::{PostingIterator, PostingIteratorFlags, Terms};
 use core::doc::Term;
 use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
@@ -26,7 +26,7 @@ use core::search::scorer::{Scorer, TermScorer};
 use core::search::searcher::SearchPlanBuilder;
 use core::search::similarity::{SimWeight, Similarity};
 use core::search::statistics::{CollectionStatistics, TermStatistics};
-use core::search::DocIterator;
+use core::search::{DocIterator, Matches, TermMatchesIterator};
 
 use core::util::{DocId, KeyedContext};
 
@@ -112,7 +112,7 @@ impl fmt::Display for TermQuery {
     }
 }
 
-struct TermWeight<C: Codec> {
+pub struct TermWeight<C: Codec> {
     term: Term,
     boost: f32,
     similarity: Box<dyn Similarity<C>>,
@@ -178,6 +178,26 @@ impl<C: Codec> Weight<C> for TermWeight<C> {
         TERM
     }
 
+    fn count(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<i32>> {
+        if !self.term_states.contains_key(&reader.doc_base) {
+            // the term doesn't exist in this segment
+            Ok(Some(0))
+        } else if reader.reader.num_docs() == reader.reader.max_doc() {
+            // the doc freq includes the deleted docs
+            Ok(Some(reader.reader.doc_freq(&self.term)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn estimate_cost(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<usize>> {
+        if !self.term_states.contains_key(&reader.doc_base) {
+            Ok(Some(0))
+        } else {
+            Ok(Some(reader.reader.doc_freq(&self.term)? as usize))
+        }
+    }
+
     fn normalize(&mut self, norm: f32, boost: f32) {
         self.sim_weight.normalize(norm, boost * self.boost)
     }
@@ -225,6 +245,35 @@ impl<C: Codec> Weight<C> for TermWeight<C> {
             vec![],
         ))
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let (has_positions, has_offsets) = match reader.reader.terms(&self.term.field)? {
+            Some(terms) => (terms.has_positions()?, terms.has_offsets()?),
+            None => return Ok(None),
+        };
+        let flags = if has_offsets {
+            PostingIteratorFlags::OFFSETS
+        } else if has_positions {
+            PostingIteratorFlags::POSITIONS
+        } else {
+            PostingIteratorFlags::NONE
+        };
+
+        if let Some(mut postings) = self.create_postings_iterator(reader, i32::from(flags))? {
+            if postings.advance(doc)? == doc {
+                if !has_positions {
+                    return Ok(Some(Matches::no_terms()));
+                }
+                let query = format!("{}:{}", self.term.field(), self.term.text()?);
+                let iterator = TermMatchesIterator::new(postings, query)?;
+                return Ok(Some(Matches::from_field(
+                    self.term.field.clone(),
+                    Box::new(iterator),
+                )));
+            }
+        }
+        Ok(None)
+    }
 }
 
 impl<C: Codec> fmt::Display for TermWeight<C> {
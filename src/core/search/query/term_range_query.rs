@@ -0,0 +1,321 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator, TermIterator};
+use core::codec::{Codec, CodecTermIterator, CodecTerms, Terms};
+use core::search::query::{MultiTermQuery, MultiTermQueryConstantScoreWeight};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+
+use error::Result;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+pub const TERM_RANGE: &str = "term_range";
+
+/// A Query that matches documents within an range of terms.
+///
+/// This query matches the documents looking for terms that fall into the
+/// supplied range according to byte order. It is not intended for numerical
+/// ranges; use `PointRangeQuery` instead. It is useful for fields like version
+/// strings or zero-padded ids.
+///
+/// Either bound may be open (`None`), in which case the range is unbounded
+/// on that side and the corresponding include flag is ignored.
+#[derive(Clone, Debug)]
+pub struct TermRangeQuery {
+    field: String,
+    lower_term: Option<Vec<u8>>,
+    upper_term: Option<Vec<u8>>,
+    include_lower: bool,
+    include_upper: bool,
+}
+
+impl TermRangeQuery {
+    /// Constructs a query selecting all terms greater/equal than `lower_term`
+    /// but less/equal than `upper_term`.
+    ///
+    /// If an endpoint is `None`, it is said to be "open". Either or both
+    /// endpoints may be open. Open endpoints may not be exclusive (you can't
+    /// select all but the first or last term without explicitly specifying the
+    /// term to exclude.)
+    pub fn new(
+        field: String,
+        lower_term: Option<Vec<u8>>,
+        upper_term: Option<Vec<u8>>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeQuery {
+        TermRangeQuery {
+            field,
+            lower_term,
+            upper_term,
+            include_lower,
+            include_upper,
+        }
+    }
+
+    /// Factory that creates a new `TermRangeQuery` using strings for term text.
+    pub fn new_string_range(
+        field: String,
+        lower_term: Option<&str>,
+        upper_term: Option<&str>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeQuery {
+        TermRangeQuery::new(
+            field,
+            lower_term.map(|t| t.as_bytes().to_vec()),
+            upper_term.map(|t| t.as_bytes().to_vec()),
+            include_lower,
+            include_upper,
+        )
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the lower value of this range query
+    pub fn lower_term(&self) -> Option<&[u8]> {
+        self.lower_term.as_ref().map(|t| t.as_slice())
+    }
+
+    /// Returns the upper value of this range query
+    pub fn upper_term(&self) -> Option<&[u8]> {
+        self.upper_term.as_ref().map(|t| t.as_slice())
+    }
+
+    /// Returns `true` if the lower endpoint is inclusive
+    pub fn includes_lower(&self) -> bool {
+        self.include_lower
+    }
+
+    /// Returns `true` if the upper endpoint is inclusive
+    pub fn includes_upper(&self) -> bool {
+        self.include_upper
+    }
+}
+
+impl<C: Codec> MultiTermQuery<C> for TermRangeQuery {
+    type Iter = TermRangeTermIterator<CodecTermIterator<C>>;
+
+    fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter> {
+        Ok(TermRangeTermIterator::new(
+            terms.iterator()?,
+            self.lower_term.clone(),
+            self.upper_term.clone(),
+            self.include_lower,
+            self.include_upper,
+        ))
+    }
+}
+
+impl<C: Codec> Query<C> for TermRangeQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            TERM_RANGE,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for TermRangeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lower = self
+            .lower_term
+            .as_ref()
+            .map_or_else(|| "*".into(), |t| String::from_utf8_lossy(t));
+        let upper = self
+            .upper_term
+            .as_ref()
+            .map_or_else(|| "*".into(), |t| String::from_utf8_lossy(t));
+        write!(
+            f,
+            "TermRangeQuery(field: {}, range: {}{} TO {}{})",
+            &self.field,
+            if self.include_lower { '[' } else { '{' },
+            lower,
+            upper,
+            if self.include_upper { ']' } else { '}' },
+        )
+    }
+}
+
+/// Subclass of `FilteredTermIterator` for enumerating all terms that match the
+/// specified range parameters.
+///
+/// The iteration seeks to the lower bound and stops at the first term that is
+/// greater than the upper bound.
+pub struct TermRangeTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    lower_term: Option<Vec<u8>>,
+    upper_term: Option<Vec<u8>>,
+    include_lower: bool,
+    include_upper: bool,
+}
+
+impl<T: TermIterator> TermRangeTermIterator<T> {
+    pub fn new(
+        terms: T,
+        lower_term: Option<Vec<u8>>,
+        upper_term: Option<Vec<u8>>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeTermIterator<T> {
+        // do a little bit of normalization: open ended range queries should
+        // always be inclusive.
+        let include_lower = include_lower || lower_term.is_none();
+        let include_upper = include_upper || upper_term.is_none();
+        let mut base = FilteredTermIterBase::new(terms, lower_term.is_some());
+        base.initial_seek_term = lower_term.clone();
+        TermRangeTermIterator {
+            base,
+            lower_term,
+            upper_term,
+            include_lower,
+            include_upper,
+        }
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for TermRangeTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        if !self.include_lower {
+            if let Some(ref lower) = self.lower_term {
+                if term == lower.as_slice() {
+                    return Ok(AcceptStatus::No);
+                }
+            }
+        }
+
+        // Use this field's default sort ordering
+        if let Some(ref upper) = self.upper_term {
+            match term.cmp(upper.as_slice()) {
+                Ordering::Greater => return Ok(AcceptStatus::End),
+                Ordering::Equal if !self.include_upper => return Ok(AcceptStatus::End),
+                _ => {}
+            }
+        }
+        Ok(AcceptStatus::Yes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::tests::{collect_terms, MockTermIterator};
+    use core::doc::StringField;
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::DocId;
+
+    use std::sync::Arc;
+
+    fn range(lower: Option<&str>, upper: Option<&str>, incl: bool, incu: bool) -> Vec<String> {
+        let terms = MockTermIterator::new(&["a", "b", "c", "d", "e"]);
+        collect_terms(TermRangeTermIterator::new(
+            terms,
+            lower.map(|t| t.as_bytes().to_vec()),
+            upper.map(|t| t.as_bytes().to_vec()),
+            incl,
+            incu,
+        ))
+    }
+
+    #[test]
+    fn test_term_range_term_iterator() {
+        assert_eq!(range(Some("b"), Some("d"), true, true), vec!["b", "c", "d"]);
+        // exclusive bounds
+        assert_eq!(range(Some("b"), Some("d"), false, true), vec!["c", "d"]);
+        assert_eq!(range(Some("b"), Some("d"), true, false), vec!["b", "c"]);
+        assert_eq!(range(Some("b"), Some("d"), false, false), vec!["c"]);
+        // bounds which are not terms
+        assert_eq!(range(Some("bb"), Some("cc"), false, false), vec!["c"]);
+        // open bounds are always inclusive
+        assert_eq!(range(None, Some("b"), false, true), vec!["a", "b"]);
+        assert_eq!(range(Some("d"), None, true, false), vec!["d", "e"]);
+        assert_eq!(
+            range(None, None, false, false),
+            vec!["a", "b", "c", "d", "e"]
+        );
+        // an empty range
+        assert!(range(Some("d"), Some("b"), true, true).is_empty());
+        assert!(range(Some("c"), Some("c"), false, true).is_empty());
+        assert!(range(Some("f"), None, true, true).is_empty());
+    }
+
+    #[test]
+    fn test_term_range_query_search() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let config = Arc::new(IndexWriterConfig::default());
+        let writer = IndexWriter::new(directory, config).unwrap();
+        for term in &["a", "b", "bb", "c", "d", "e"] {
+            writer
+                .add_document(vec![StringField::new("body", term, false)])
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = Arc::new(writer.get_reader(true, false).unwrap());
+        let searcher = DefaultIndexSearcher::new(reader, None, None);
+        let search = |lower: Option<&str>, upper: Option<&str>, incl: bool, incu: bool| {
+            let query = TermRangeQuery::new_string_range("body".into(), lower, upper, incl, incu);
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(&query, &mut collector).unwrap();
+            let top_docs = collector.top_docs();
+            let mut docs: Vec<DocId> = top_docs.score_docs().iter().map(|d| d.doc_id()).collect();
+            docs.sort();
+            docs
+        };
+
+        assert_eq!(search(Some("b"), Some("c"), true, true), vec![1, 2, 3]);
+        assert_eq!(search(Some("b"), Some("c"), false, false), vec![2]);
+        assert_eq!(search(None, Some("b"), true, false), vec![0]);
+        assert_eq!(search(Some("d"), None, false, true), vec![5]);
+        assert_eq!(search(None, None, true, true), vec![0, 1, 2, 3, 4, 5]);
+        assert!(search(Some("f"), None, true, true).is_empty());
+    }
+}
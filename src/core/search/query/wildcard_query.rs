@@ -0,0 +1,180 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{AutomatonTermIterator, Codec, CodecTermIterator, CodecTerms, Terms};
+use core::doc::Term;
+use core::search::query::{multi_term_weight, MultiTermQuery, RewriteMethod};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+use core::util::automaton::{Automata, Automaton, CompiledAutomaton, Operations};
+
+use error::Result;
+
+use std::fmt;
+
+pub const WILDCARD: &str = "wildcard";
+
+/// String equality with support for wildcards
+pub const WILDCARD_STRING: char = '*';
+
+/// Char equality with support for wildcards
+pub const WILDCARD_CHAR: char = '?';
+
+/// Escape character
+pub const WILDCARD_ESCAPE: char = '\\';
+
+/// Implements the wildcard search query.
+///
+/// Supported wildcards are `*`, which matches any character sequence (including
+/// the empty one), and `?`, which matches any single character. '\' is the
+/// escape character.
+///
+/// Note this query can be slow, as it needs to iterate over many terms. In
+/// order to prevent extremely slow WildcardQueries, a Wildcard term should not
+/// start with the wildcard `*`
+#[derive(Clone)]
+pub struct WildcardQuery {
+    term: Term,
+    compiled: CompiledAutomaton,
+    rewrite_method: RewriteMethod,
+}
+
+impl WildcardQuery {
+    /// Constructs a query for terms matching `term`.
+    pub fn new(term: Term) -> Result<WildcardQuery> {
+        WildcardQuery::with_max_determinized_states(term, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Constructs a query for terms matching `term`, `max_determinized_states` is
+    /// the maximum number of states that compiling the automaton for the wildcard
+    /// pattern can result in.
+    pub fn with_max_determinized_states(
+        term: Term,
+        max_determinized_states: usize,
+    ) -> Result<WildcardQuery> {
+        let automaton = WildcardQuery::to_automaton(&term.text()?);
+        let compiled = CompiledAutomaton::new(&automaton, true, false, max_determinized_states)?;
+        Ok(WildcardQuery {
+            term,
+            compiled,
+            rewrite_method: RewriteMethod::default(),
+        })
+    }
+
+    /// Convert Lucene wildcard syntax into an automaton.
+    pub fn to_automaton(wildcard_text: &str) -> Automaton {
+        let mut automata = Vec::new();
+        let mut chars = wildcard_text.chars();
+        while let Some(c) = chars.next() {
+            let automaton = match c {
+                WILDCARD_STRING => Automata::make_any_string(),
+                WILDCARD_CHAR => Automata::make_any_char(),
+                WILDCARD_ESCAPE => {
+                    // add the next codepoint instead, if it exists
+                    Automata::make_char(chars.next().unwrap_or(WILDCARD_ESCAPE) as u32)
+                }
+                _ => Automata::make_char(c as u32),
+            };
+            automata.push(automaton);
+        }
+        Operations::concatenate(&automata)
+    }
+
+    /// Returns the pattern term.
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn compiled(&self) -> &CompiledAutomaton {
+        &self.compiled
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.rewrite_method = rewrite_method;
+    }
+}
+
+impl<C: Codec> MultiTermQuery<C> for WildcardQuery {
+    type Iter = AutomatonTermIterator<CodecTermIterator<C>>;
+
+    fn field(&self) -> &str {
+        self.term.field()
+    }
+
+    fn term_iterator(&self, terms: &CodecTerms<C>) -> Result<Self::Iter> {
+        terms.intersect(&self.compiled)
+    }
+}
+
+impl<C: Codec> Query<C> for WildcardQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        multi_term_weight(self, WILDCARD, self.rewrite_method, searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for WildcardQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WildcardQuery(field: {}, wildcard: {})",
+            self.term.field(),
+            String::from_utf8_lossy(&self.term.bytes)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, s: &str) -> bool {
+        let automaton = Operations::determinize(
+            &WildcardQuery::to_automaton(pattern),
+            DEFAULT_MAX_DETERMINIZED_STATES,
+        )
+        .unwrap();
+        let chars: Vec<u32> = s.chars().map(|c| c as u32).collect();
+        automaton.run(&chars)
+    }
+
+    #[test]
+    fn test_wildcard_automaton() {
+        assert!(matches("te?t", "test"));
+        assert!(matches("te?t", "text"));
+        assert!(!matches("te?t", "tet"));
+        assert!(matches("te*", "te"));
+        assert!(matches("te*", "terms"));
+        assert!(matches("*st", "test"));
+        assert!(!matches("*st", "tests"));
+        assert!(matches("t*s?", "terms"));
+        assert!(matches("a\\*b", "a*b"));
+        assert!(!matches("a\\*b", "axb"));
+    }
+}
@@ -11,16 +11,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::search::scorer::Scorer;
+use std::cmp::Ordering;
+
+use core::search::scorer::{two_phase_next, Scorer};
 use core::search::{DocIterator, NO_MORE_DOCS};
 use core::util::DocId;
 use error::Result;
 
 /// Scorer for conjunctions, sets of queries, all of which are required.
+///
+/// The sub scorers are intersected on their approximations, and the two phase
+/// ones are only asked to confirm the docs all the approximations agree on, the
+/// cheapest match first. The conjunction is itself two phase if any of its sub
+/// scorers is.
 pub struct ConjunctionScorer<T: Scorer> {
     lead1: T,
     lead2: T,
     others: Vec<T>,
+    // indexes of the two phase sub scorers ordered by match cost, see `sub_scorer`
+    two_phase: Vec<usize>,
 }
 
 impl<T: Scorer> ConjunctionScorer<T> {
@@ -34,10 +43,38 @@ impl<T: Scorer> ConjunctionScorer<T> {
         let lead2 = children.remove(1);
         let lead1 = children.remove(0);
 
-        ConjunctionScorer {
+        let mut scorer = ConjunctionScorer {
             lead1,
             lead2,
             others,
+            two_phase: vec![],
+        };
+        let mut two_phase: Vec<usize> = (0..scorer.others.len() + 2)
+            .filter(|&i| scorer.sub_scorer(i).support_two_phase())
+            .collect();
+        two_phase.sort_by(|&a, &b| {
+            let cost_a = scorer.sub_scorer(a).match_cost();
+            let cost_b = scorer.sub_scorer(b).match_cost();
+            cost_a.partial_cmp(&cost_b).unwrap_or(Ordering::Equal)
+        });
+        scorer.two_phase = two_phase;
+        scorer
+    }
+
+    /// the `i`th sub scorer, the leads being the first two
+    fn sub_scorer(&self, i: usize) -> &T {
+        match i {
+            0 => &self.lead1,
+            1 => &self.lead2,
+            _ => &self.others[i - 2],
+        }
+    }
+
+    fn sub_scorer_mut(&mut self, i: usize) -> &mut T {
+        match i {
+            0 => &mut self.lead1,
+            1 => &mut self.lead2,
+            _ => &mut self.others[i - 2],
         }
     }
 
@@ -101,11 +138,21 @@ impl<T: Scorer> DocIterator for ConjunctionScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
-        self.approximate_next()
+        let doc = self.approximate_next()?;
+        if self.two_phase.is_empty() {
+            Ok(doc)
+        } else {
+            two_phase_next(self)
+        }
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
-        self.approximate_advance(target)
+        let doc = self.approximate_advance(target)?;
+        if self.two_phase.is_empty() {
+            Ok(doc)
+        } else {
+            two_phase_next(self)
+        }
     }
 
     fn cost(&self) -> usize {
@@ -113,9 +160,26 @@ impl<T: Scorer> DocIterator for ConjunctionScorer<T> {
     }
 
     fn matches(&mut self) -> Result<bool> {
+        for i in 0..self.two_phase.len() {
+            let idx = self.two_phase[i];
+            if !self.sub_scorer_mut(idx).matches()? {
+                return Ok(false);
+            }
+        }
         Ok(true)
     }
 
+    fn match_cost(&self) -> f32 {
+        self.two_phase
+            .iter()
+            .map(|&i| self.sub_scorer(i).match_cost())
+            .sum()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        !self.two_phase.is_empty()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         let doc = self.lead1.approximate_next()?;
         self.skip_to_approx(doc)
@@ -213,6 +277,23 @@ mod tests {
         assert_eq!(scorer.doc_id(), NO_MORE_DOCS);
     }
 
+    #[test]
+    fn test_conjunction_two_phase() {
+        let s1: Box<dyn Scorer> = Box::new(create_mock_scorer(vec![1, 2, 3, 4, 5, 6]));
+        let s2: Box<dyn Scorer> =
+            Box::new(create_mock_two_phase_scorer(vec![2, 3, 5, 6], vec![3, 6]));
+        let s3: Box<dyn Scorer> = Box::new(create_mock_scorer(vec![2, 3, 4, 5, 6]));
+        let mut scorer = ConjunctionScorer::new(vec![s1, s2, s3]);
+        assert!(scorer.support_two_phase());
+
+        // the approximations agree on 2 but the two phase scorer doesn't match it
+        assert_eq!(scorer.approximate_next().unwrap(), 2);
+        assert!(!scorer.matches().unwrap());
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.advance(4).unwrap(), 6);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
     fn create_conjunction_scorer() -> ConjunctionScorer<MockSimpleScorer<MockDocIterator>> {
         let s1 = create_mock_scorer(vec![1, 2, 3, 4, 5]);
         let s2 = create_mock_scorer(vec![2, 5]);
@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::search::scorer::Scorer;
+use core::search::scorer::{two_phase_next, Scorer};
 use core::search::{DocIterator, NO_MORE_DOCS};
 use core::util::DocId;
 
@@ -23,6 +23,10 @@ pub struct DisjunctionSumScorer<T: Scorer> {
     sub_scorers: Vec<T>,
     curr_doc: DocId,
     cost: usize,
+    // whether any of the sub scorers support two phase iteration
+    two_phase: bool,
+    // for two phase iteration, which sub scorers are verified to match the current doc
+    matched: Vec<bool>,
 }
 
 impl<T: Scorer> DisjunctionSumScorer<T> {
@@ -34,11 +38,15 @@ impl<T: Scorer> DisjunctionSumScorer<T> {
         for s in children.iter() {
             curr_doc = curr_doc.min(s.doc_id());
         }
+        let two_phase = children.iter().any(|s| s.support_two_phase());
+        let matched = vec![false; children.len()];
 
         DisjunctionSumScorer {
             sub_scorers: children,
             curr_doc,
             cost,
+            two_phase,
+            matched,
         }
     }
 }
@@ -48,8 +56,8 @@ impl<T: Scorer> Scorer for DisjunctionSumScorer<T> {
         let mut score: f32 = 0.0f32;
 
         let doc_id = self.doc_id();
-        for s in self.sub_scorers.iter_mut() {
-            if s.doc_id() == doc_id {
+        for (i, s) in self.sub_scorers.iter_mut().enumerate() {
+            if is_matched(s, doc_id, self.two_phase, self.matched[i]) {
                 let sub_score = s.score()?;
                 score += sub_score;
             }
@@ -65,11 +73,21 @@ impl<T: Scorer> DocIterator for DisjunctionSumScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
-        self.approximate_next()
+        let doc = self.approximate_next()?;
+        if self.two_phase {
+            two_phase_next(self)
+        } else {
+            Ok(doc)
+        }
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
-        self.approximate_advance(target)
+        let doc = self.approximate_advance(target)?;
+        if self.two_phase {
+            two_phase_next(self)
+        } else {
+            Ok(doc)
+        }
     }
 
     fn cost(&self) -> usize {
@@ -77,11 +95,19 @@ impl<T: Scorer> DocIterator for DisjunctionSumScorer<T> {
     }
 
     fn matches(&mut self) -> Result<bool> {
-        Ok(true)
+        if self.two_phase {
+            verify_sub_scorers(&mut self.sub_scorers, &mut self.matched, self.curr_doc)
+        } else {
+            Ok(true)
+        }
     }
 
     fn match_cost(&self) -> f32 {
-        0f32
+        sub_match_cost(&self.sub_scorers)
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.two_phase
     }
 
     fn approximate_next(&mut self) -> Result<DocId> {
@@ -123,6 +149,10 @@ pub struct DisjunctionMaxScorer<T: Scorer> {
     sub_scorers: Vec<T>,
     curr_doc: DocId,
     cost: usize,
+    // whether any of the sub scorers support two phase iteration
+    two_phase: bool,
+    // for two phase iteration, which sub scorers are verified to match the current doc
+    matched: Vec<bool>,
     tie_breaker_multiplier: f32,
 }
 
@@ -139,11 +169,15 @@ impl<T: Scorer> DisjunctionMaxScorer<T> {
         for s in children.iter() {
             curr_doc = curr_doc.min(s.doc_id());
         }
+        let two_phase = children.iter().any(|s| s.support_two_phase());
+        let matched = vec![false; children.len()];
 
         DisjunctionMaxScorer {
             sub_scorers: children,
             curr_doc,
             cost,
+            two_phase,
+            matched,
             tie_breaker_multiplier,
         }
     }
@@ -155,8 +189,8 @@ impl<T: Scorer> Scorer for DisjunctionMaxScorer<T> {
         let mut score_max = f32::NEG_INFINITY;
 
         let doc_id = self.doc_id();
-        for s in self.sub_scorers.iter_mut() {
-            if s.doc_id() == doc_id {
+        for (i, s) in self.sub_scorers.iter_mut().enumerate() {
+            if is_matched(s, doc_id, self.two_phase, self.matched[i]) {
                 let sub_score = s.score()?;
 
                 score_sum += sub_score;
@@ -174,11 +208,21 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
-        self.approximate_next()
+        let doc = self.approximate_next()?;
+        if self.two_phase {
+            two_phase_next(self)
+        } else {
+            Ok(doc)
+        }
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
-        self.approximate_advance(target)
+        let doc = self.approximate_advance(target)?;
+        if self.two_phase {
+            two_phase_next(self)
+        } else {
+            Ok(doc)
+        }
     }
 
     fn cost(&self) -> usize {
@@ -186,11 +230,19 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
     }
 
     fn matches(&mut self) -> Result<bool> {
-        Ok(true)
+        if self.two_phase {
+            verify_sub_scorers(&mut self.sub_scorers, &mut self.matched, self.curr_doc)
+        } else {
+            Ok(true)
+        }
     }
 
     fn match_cost(&self) -> f32 {
-        0f32
+        sub_match_cost(&self.sub_scorers)
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.two_phase
     }
 
     fn approximate_next(&mut self) -> Result<DocId> {
@@ -222,3 +274,84 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
         Ok(self.doc_id())
     }
 }
+
+/// Whether the sub scorer contributes to the current doc of a disjunction.
+fn is_matched<T: Scorer>(scorer: &T, doc: DocId, two_phase: bool, matched: bool) -> bool {
+    scorer.doc_id() == doc && (!two_phase || matched)
+}
+
+/// Confirm the sub scorers positioned on `doc`, recording which of them really match it.
+///
+/// Sub scorers without two phase support match as soon as they are positioned on the doc.
+fn verify_sub_scorers<T: Scorer>(
+    sub_scorers: &mut [T],
+    matched: &mut [bool],
+    doc: DocId,
+) -> Result<bool> {
+    let mut any_matched = false;
+    for (s, m) in sub_scorers.iter_mut().zip(matched.iter_mut()) {
+        *m = s.doc_id() == doc && (!s.support_two_phase() || s.matches()?);
+        any_matched |= *m;
+    }
+    Ok(any_matched)
+}
+
+fn sub_match_cost<T: Scorer>(sub_scorers: &[T]) -> f32 {
+    sub_scorers
+        .iter()
+        .filter(|s| s.support_two_phase())
+        .map(|s| s.match_cost())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    #[test]
+    fn test_disjunction_max_scorer_tie_breaker() {
+        let s1 = create_mock_scorer(vec![1, 2, 3]);
+        let s2 = create_mock_scorer(vec![2, 3, 4]);
+        let s3 = create_mock_scorer(vec![3]);
+        let mut scorer = DisjunctionMaxScorer::new(vec![s1, s2, s3], 0.5, true);
+
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 1.0).abs() < ::std::f32::EPSILON);
+
+        // mock scorers score by doc id: max 2 plus 0.5 * 2
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert!((scorer.score().unwrap() - 3.0).abs() < ::std::f32::EPSILON);
+
+        // max 3 plus 0.5 * (3 + 3)
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert!((scorer.score().unwrap() - 6.0).abs() < ::std::f32::EPSILON);
+
+        assert_eq!(scorer.advance(4).unwrap(), 4);
+        assert!((scorer.score().unwrap() - 4.0).abs() < ::std::f32::EPSILON);
+
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_disjunction_sum_two_phase() {
+        let s1: Box<dyn Scorer> = Box::new(create_mock_scorer(vec![1, 4]));
+        let s2: Box<dyn Scorer> =
+            Box::new(create_mock_two_phase_scorer(vec![1, 2, 3, 4], vec![1, 3]));
+        let mut scorer = DisjunctionSumScorer::new(vec![s1, s2], true);
+        assert!(scorer.support_two_phase());
+
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 2.0).abs() < ::std::f32::EPSILON);
+
+        // doc 2 is only an approximate match of the second scorer
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert!((scorer.score().unwrap() - 3.0).abs() < ::std::f32::EPSILON);
+
+        // the second scorer is positioned on doc 4 but does not match it
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert!((scorer.score().unwrap() - 4.0).abs() < ::std::f32::EPSILON);
+
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::query::AllDocsIterator;
+use core::search::scorer::{two_phase_next, Scorer};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+
+/// Checks whether the doc values of a doc match a query.
+pub trait DocValuesMatcher: Send {
+    /// Whether `doc` matches, docs are checked in increasing order.
+    fn matches(&mut self, doc: DocId) -> Result<bool>;
+
+    /// An estimate of the cost of a single `matches` call.
+    fn match_cost(&self) -> f32;
+}
+
+/// A constant-scoring `Scorer` that walks all the docs of a segment and only
+/// confirms the ones accepted by a `DocValuesMatcher`.
+///
+/// It doesn't need any index structure so it is slow when it leads the
+/// iteration, but cheap when it only verifies the docs of a more selective
+/// clause.
+pub struct DocValuesScorer<M: DocValuesMatcher> {
+    approximation: AllDocsIterator,
+    matcher: M,
+    score: f32,
+    cost: usize,
+}
+
+impl<M: DocValuesMatcher> DocValuesScorer<M> {
+    pub fn new(max_doc: DocId, matcher: M, score: f32) -> DocValuesScorer<M> {
+        DocValuesScorer {
+            approximation: AllDocsIterator::new(max_doc),
+            matcher,
+            score,
+            cost: max_doc as usize,
+        }
+    }
+}
+
+impl<M: DocValuesMatcher> Scorer for DocValuesScorer<M> {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.score)
+    }
+}
+
+impl<M: DocValuesMatcher> DocIterator for DocValuesScorer<M> {
+    fn doc_id(&self) -> DocId {
+        self.approximation.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.approximate_next()?;
+        two_phase_next(self)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximate_advance(target)?;
+        two_phase_next(self)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let doc = self.approximation.doc_id();
+        if doc == NO_MORE_DOCS {
+            return Ok(false);
+        }
+        self.matcher.matches(doc)
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.matcher.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.approximation.next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximation.advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MultipleOfMatcher(DocId);
+
+    impl DocValuesMatcher for MultipleOfMatcher {
+        fn matches(&mut self, doc: DocId) -> Result<bool> {
+            Ok(doc % self.0 == 0)
+        }
+
+        fn match_cost(&self) -> f32 {
+            1.0
+        }
+    }
+
+    #[test]
+    fn test_doc_values_scorer() {
+        let mut scorer = DocValuesScorer::new(20, MultipleOfMatcher(3), 2.0);
+        assert!(scorer.support_two_phase());
+        assert_eq!(scorer.cost(), 20);
+        assert_eq!(scorer.next().unwrap(), 0);
+        assert!((scorer.score().unwrap() - 2.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.advance(7).unwrap(), 9);
+        assert_eq!(scorer.advance(9).unwrap(), 9);
+        assert_eq!(scorer.approximate_next().unwrap(), 10);
+        assert!(!scorer.matches().unwrap());
+        assert_eq!(scorer.advance(19).unwrap(), NO_MORE_DOCS);
+    }
+}
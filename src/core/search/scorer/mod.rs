@@ -23,10 +23,18 @@ mod disjunction_scorer;
 
 pub use self::disjunction_scorer::*;
 
+mod doc_values_scorer;
+
+pub use self::doc_values_scorer::*;
+
 mod min_scorer;
 
 pub use self::min_scorer::*;
 
+mod req_excl_scorer;
+
+pub use self::req_excl_scorer::*;
+
 mod req_opt_scorer;
 
 pub use self::req_opt_scorer::*;
@@ -39,7 +47,12 @@ mod term_scorer;
 
 pub use self::term_scorer::*;
 
+mod wand_scorer;
+
+pub use self::wand_scorer::*;
+
 use std::collections::HashMap;
+use std::f32;
 use std::i32;
 
 use core::util::{DocId, IndexedContext, VariantValue};
@@ -77,6 +90,18 @@ pub trait Scorer: DocIterator {
     /// the first time, or when called from within `LeafCollector::collect`.
     fn score(&mut self) -> Result<f32>;
 
+    /// Return an upper bound of the scores this scorer may produce, or
+    /// `f32::INFINITY` if it is not known.
+    fn max_score(&self) -> f32 {
+        f32::INFINITY
+    }
+
+    /// Optional method: tell the scorer that its iterator may safely ignore all
+    /// documents whose score is less than `min_score`. This is typically called
+    /// by collectors that only need the top scoring documents, so that scorers
+    /// such as `WANDScorer` can skip over non competitive documents.
+    fn set_min_competitive_score(&mut self, _min_score: f32) {}
+
     fn score_context(&mut self) -> Result<IndexedContext> {
         unimplemented!()
     }
@@ -91,6 +116,14 @@ impl Scorer for Box<dyn Scorer> {
         (**self).score()
     }
 
+    fn max_score(&self) -> f32 {
+        (**self).max_score()
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) {
+        (**self).set_min_competitive_score(min_score)
+    }
+
     fn score_context(&mut self) -> Result<IndexedContext> {
         (**self).score_context()
     }
@@ -283,6 +316,18 @@ impl<T: DocIterator> DocIterator for ConstantScoreScorer<T> {
         self.cost
     }
 
+    fn matches(&mut self) -> Result<bool> {
+        self.iterator.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.iterator.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.iterator.support_two_phase()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         self.iterator.approximate_next()
     }
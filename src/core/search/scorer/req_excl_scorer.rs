@@ -0,0 +1,127 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::scorer::Scorer;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+use error::Result;
+
+/// A Scorer for queries with a required sub scorer and an excluding (prohibited)
+/// sub scorer.
+///
+/// This `Scorer` implements `DocIterator::advance()`, and it uses the `advance()`
+/// on the given scorers.
+pub struct ReqExclScorer {
+    req_scorer: Box<dyn Scorer>,
+    excl_scorer: Box<dyn Scorer>,
+}
+
+impl ReqExclScorer {
+    pub fn new(req_scorer: Box<dyn Scorer>, excl_scorer: Box<dyn Scorer>) -> ReqExclScorer {
+        ReqExclScorer {
+            req_scorer,
+            excl_scorer,
+        }
+    }
+
+    /// Whether `doc`, which matches the required scorer, is excluded.
+    fn excluded(&mut self, doc: DocId) -> Result<bool> {
+        let mut excl_doc = self.excl_scorer.doc_id();
+        if excl_doc < doc {
+            excl_doc = self.excl_scorer.advance(doc)?;
+        }
+        Ok(excl_doc == doc)
+    }
+
+    fn to_non_excluded(&mut self, mut doc: DocId) -> Result<DocId> {
+        while doc != NO_MORE_DOCS && self.excluded(doc)? {
+            doc = self.req_scorer.next()?;
+        }
+        Ok(doc)
+    }
+}
+
+impl Scorer for ReqExclScorer {
+    fn score(&mut self) -> Result<f32> {
+        self.req_scorer.score()
+    }
+
+    fn max_score(&self) -> f32 {
+        self.req_scorer.max_score()
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) {
+        self.req_scorer.set_min_competitive_score(min_score)
+    }
+}
+
+impl DocIterator for ReqExclScorer {
+    fn doc_id(&self) -> DocId {
+        self.req_scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let doc = self.req_scorer.next()?;
+        self.to_non_excluded(doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let doc = self.req_scorer.advance(target)?;
+        self.to_non_excluded(doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.req_scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let doc = self.doc_id();
+        Ok(self.req_scorer.matches()? && !self.excluded(doc)?)
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.req_scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.req_scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.req_scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.req_scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    #[test]
+    fn test_excluded_docs() {
+        let req = create_mock_scorer(vec![1, 2, 3, 4, 5, 7]);
+        let excl = create_mock_scorer(vec![2, 3, 6, 7]);
+        let mut scorer = ReqExclScorer::new(Box::new(req), Box::new(excl));
+
+        assert_eq!(scorer.doc_id(), -1);
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 1.0) < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert_eq!(scorer.advance(5).unwrap(), 5);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
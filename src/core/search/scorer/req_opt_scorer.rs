@@ -86,6 +86,14 @@ impl DocIterator for ReqOptScorer {
         self.req_scorer.matches()
     }
 
+    fn match_cost(&self) -> f32 {
+        self.req_scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.req_scorer.support_two_phase()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         self.req_scorer.approximate_next()
     }
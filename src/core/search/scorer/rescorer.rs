@@ -91,6 +91,25 @@ impl<C: Codec> RescoreRequest<C> {
             rescore_movedout,
         }
     }
+
+    /// The query used to re-score the top window of the first pass hits.
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    /// Weight applied to the first pass score before combining.
+    pub fn query_weight(&self) -> f32 {
+        self.query_weight
+    }
+
+    /// Weight applied to the rescore query score before combining.
+    pub fn rescore_weight(&self) -> f32 {
+        self.rescore_weight
+    }
+
+    pub fn rescore_mode(&self) -> &RescoreMode {
+        &self.rescore_mode
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -426,7 +445,7 @@ impl QueryRescorer {
         let rescore = searcher.explain(req.query.as_ref(), doc)?;
         let rescore_value = rescore.value();
         let first_value = first.value();
-        let primary_weight = 1.0f32;
+        let primary_weight = req.query_weight;
 
         let prim = if first.is_match() {
             Explanation::new(
@@ -451,7 +470,7 @@ impl QueryRescorer {
         // description with which ScoreMode was used.  Maybe we should add
         // QueryRescorer.explainCombine to Lucene?
         if rescore.is_match() {
-            let secondary_weight = 1.0f32;
+            let secondary_weight = req.rescore_weight;
             let sec = Explanation::new(
                 true,
                 rescore_value * secondary_weight,
@@ -469,8 +488,8 @@ impl QueryRescorer {
 
             Ok(Explanation::new(
                 true,
-                self.combine_score(req, prim.value(), true, sec.value()),
-                "sum of:".to_string(),
+                req.rescore_mode.combine(prim.value(), sec.value()),
+                format!("{} of:", req.rescore_mode),
                 vec![prim, sec],
             ))
         } else {
@@ -605,3 +624,63 @@ impl Rescorer for QueryRescorer {
         self.explain_inner(searcher, req, first, doc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::doc::Term;
+    use core::search::query::TermQuery;
+    use core::search::sort_field::{ScoreDoc, TopScoreDocs};
+
+    fn request(mode: RescoreMode, window_size: usize) -> RescoreRequest<TestCodec> {
+        let query = TermQuery::new(Term::new("title".into(), b"rust".to_vec()), 1.0, None);
+        RescoreRequest::new(Box::new(query), 0.5, 2.0, mode, window_size, false)
+    }
+
+    fn hit(doc: DocId, score: f32) -> ScoreDocHit {
+        ScoreDocHit::Score(ScoreDoc::new(doc, score))
+    }
+
+    #[test]
+    fn test_rescore_mode_combine() {
+        assert!((RescoreMode::Avg.combine(1.0, 3.0) - 2.0).abs() < ::std::f32::EPSILON);
+        assert!((RescoreMode::Max.combine(1.0, 3.0) - 3.0).abs() < ::std::f32::EPSILON);
+        assert!((RescoreMode::Min.combine(1.0, 3.0) - 1.0).abs() < ::std::f32::EPSILON);
+        assert!((RescoreMode::Total.combine(1.0, 3.0) - 4.0).abs() < ::std::f32::EPSILON);
+        assert!((RescoreMode::Multiply.combine(2.0, 3.0) - 6.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(format!("{}", RescoreMode::Total), "sum");
+    }
+
+    #[test]
+    fn test_combine_score_weights() {
+        let rescorer = QueryRescorer::default();
+        let req = request(RescoreMode::Total, 10);
+        // 4 * 0.5 + 3 * 2.0
+        let score = rescorer.combine_score(&req, 4.0, true, 3.0);
+        assert!((score - 8.0).abs() < ::std::f32::EPSILON);
+        // rescore query did not match, only the weighted first pass score is kept
+        let score = rescorer.combine_score(&req, 4.0, false, 3.0);
+        assert!((score - 2.0).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_combine_docs_outside_window() {
+        let rescorer = QueryRescorer::default();
+        let req = request(RescoreMode::Total, 2);
+        let mut top_docs = TopDocs::Score(TopScoreDocs::new(
+            3,
+            vec![hit(1, 4.0), hit(5, 3.0), hit(7, 1.0)],
+        ));
+        // the rescored window has already been re-sorted by the new score
+        let resorted = vec![hit(5, 9.0), hit(1, 6.0)];
+        rescorer.combine_docs(&mut top_docs, resorted, &req);
+
+        let hits = top_docs.score_docs();
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].doc_id(), 5);
+        assert_eq!(hits[1].doc_id(), 1);
+        assert_eq!(hits[2].doc_id(), 7);
+        assert!((hits[2].score() - 0.5).abs() < ::std::f32::EPSILON);
+    }
+}
@@ -46,6 +46,10 @@ impl<T: PostingIterator> Scorer for TermScorer<T> {
         let freq = self.freq();
         Ok(self.sim_scorer.score(doc_id, freq as f32)?)
     }
+
+    fn max_score(&self) -> f32 {
+        self.sim_scorer.max_score()
+    }
 }
 
 impl<T: PostingIterator> DocIterator for TermScorer<T> {
@@ -0,0 +1,378 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::f32;
+
+use core::search::scorer::Scorer;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+use error::Result;
+
+struct WANDEntry<T: Scorer> {
+    scorer: T,
+    max_score: f32,
+}
+
+// a clause of the tail, the clause with the highest max score is on top
+struct TailEntry {
+    max_score: f32,
+    index: usize,
+}
+
+impl Ord for TailEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.max_score
+            .partial_cmp(&other.max_score)
+            .unwrap_or(Ordering::Equal)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for TailEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TailEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TailEntry {}
+
+/// A disjunction `Scorer` that sums the scores of its clauses and uses the WAND
+/// (Weak AND) algorithm to skip the documents that can't be competitive.
+///
+/// Each clause advertises an upper bound of its scores through `Scorer::max_score`.
+/// The clauses are split in three groups:
+/// - the lead, the clauses positioned on the current doc,
+/// - the head, a min heap by doc of the clauses positioned after the current doc,
+/// - the tail, a max heap by max score of the clauses behind the current doc whose max scores sum
+///   up to less than the minimum competitive score.
+///
+/// The clauses of the tail can't make a doc competitive on their own, so they are
+/// only advanced when the lead of a candidate doc needs them to reach the minimum
+/// competitive score, which skips all the docs matching only tail clauses.
+///
+/// This is plain WAND, not Block-Max WAND: the postings format doesn't record
+/// impacts, so the upper bounds are the max score of each clause over the whole
+/// segment rather than of each block of postings, and a clause holding a single
+/// high scoring doc keeps all its other docs from being skipped. Until the
+/// collector sets a minimum competitive score, the tail stays empty and this
+/// scorer behaves as a plain `DisjunctionSumScorer`.
+///
+/// The sub scorers must not support two phase iteration.
+pub struct WANDScorer<T: Scorer> {
+    entries: Vec<WANDEntry<T>>,
+    lead: Vec<usize>,
+    lead_max_score: f32,
+    head: BinaryHeap<Reverse<(DocId, usize)>>,
+    tail: BinaryHeap<TailEntry>,
+    tail_max_score: f32,
+    doc: DocId,
+    cost: usize,
+    max_score: f32,
+    min_competitive_score: f32,
+}
+
+impl<T: Scorer> WANDScorer<T> {
+    pub fn new(scorers: Vec<T>) -> WANDScorer<T> {
+        assert!(scorers.len() > 1);
+        debug_assert!(scorers.iter().all(|s| !s.support_two_phase()));
+
+        let cost = scorers.iter().map(|s| s.cost()).sum();
+        let max_score = scorers.iter().map(|s| s.max_score()).sum();
+        let head = scorers
+            .iter()
+            .enumerate()
+            .map(|(index, scorer)| Reverse((scorer.doc_id(), index)))
+            .collect();
+        let entries = scorers
+            .into_iter()
+            .map(|scorer| {
+                let max_score = scorer.max_score();
+                WANDEntry { scorer, max_score }
+            })
+            .collect();
+
+        WANDScorer {
+            entries,
+            lead: vec![],
+            lead_max_score: 0f32,
+            head,
+            tail: BinaryHeap::new(),
+            tail_max_score: 0f32,
+            doc: -1,
+            cost,
+            max_score,
+            min_competitive_score: f32::NEG_INFINITY,
+        }
+    }
+
+    fn add_lead(&mut self, index: usize) {
+        self.lead_max_score += self.entries[index].max_score;
+        self.lead.push(index);
+    }
+
+    /// Moves a clause behind the target to the tail, unless the tail could then
+    /// make a doc competitive, in which case the clause is advanced to the target
+    /// and goes to the head.
+    fn push_behind(&mut self, index: usize, target: DocId) -> Result<()> {
+        let max_score = self.entries[index].max_score;
+        if self.tail_max_score + max_score < self.min_competitive_score {
+            self.tail.push(TailEntry { max_score, index });
+            self.tail_max_score += max_score;
+        } else {
+            let doc = self.entries[index].scorer.advance(target)?;
+            self.head.push(Reverse((doc, index)));
+        }
+        Ok(())
+    }
+
+    fn pop_tail(&mut self) -> Option<usize> {
+        let entry = self.tail.pop()?;
+        // reset rather than subtract once empty, not to accumulate rounding errors
+        self.tail_max_score = if self.tail.is_empty() {
+            0f32
+        } else {
+            self.tail_max_score - entry.max_score
+        };
+        Some(entry.index)
+    }
+
+    /// Advances a clause popped from the tail to the current doc.
+    fn advance_tail(&mut self, index: usize) -> Result<()> {
+        let doc = self.entries[index].scorer.advance(self.doc)?;
+        if doc == self.doc {
+            self.add_lead(index);
+        } else {
+            self.head.push(Reverse((doc, index)));
+        }
+        Ok(())
+    }
+
+    /// Find the next doc on or after `target` that may be competitive.
+    fn find_next(&mut self, mut target: DocId) -> Result<DocId> {
+        loop {
+            while let Some(index) = self.lead.pop() {
+                self.push_behind(index, target)?;
+            }
+            self.lead_max_score = 0f32;
+            while let Some(&Reverse((doc, index))) = self.head.peek() {
+                if doc >= target {
+                    break;
+                }
+                self.head.pop();
+                self.push_behind(index, target)?;
+            }
+
+            self.doc = match self.head.peek() {
+                Some(&Reverse((doc, _))) => doc,
+                // the clauses left in the tail can't compete on their own
+                None => NO_MORE_DOCS,
+            };
+            if self.doc == NO_MORE_DOCS {
+                return Ok(NO_MORE_DOCS);
+            }
+            while let Some(&Reverse((doc, index))) = self.head.peek() {
+                if doc != self.doc {
+                    break;
+                }
+                self.head.pop();
+                self.add_lead(index);
+            }
+
+            // pull the tail clauses with the highest max scores first, as long as
+            // the doc may still be competitive
+            while self.lead_max_score < self.min_competitive_score
+                && self.lead_max_score + self.tail_max_score >= self.min_competitive_score
+            {
+                match self.pop_tail() {
+                    Some(index) => self.advance_tail(index)?,
+                    None => break,
+                }
+            }
+
+            if self.lead_max_score >= self.min_competitive_score {
+                // the score sums all the matching clauses
+                while let Some(index) = self.pop_tail() {
+                    self.advance_tail(index)?;
+                }
+                return Ok(self.doc);
+            }
+            target = self.doc + 1;
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for WANDScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        let mut score = 0f32;
+        for &index in &self.lead {
+            score += self.entries[index].scorer.score()?;
+        }
+        Ok(score)
+    }
+
+    fn max_score(&self) -> f32 {
+        self.max_score
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) {
+        if min_score > self.min_competitive_score {
+            self.min_competitive_score = min_score;
+        }
+    }
+}
+
+impl<T: Scorer> DocIterator for WANDScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.find_next(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.find_next(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    /// a mock scorer, that scores each doc with its id, and knows its max score
+    struct BoundedScorer {
+        scorer: MockSimpleScorer<MockDocIterator>,
+        max_score: f32,
+    }
+
+    impl BoundedScorer {
+        fn new(docs: Vec<DocId>) -> BoundedScorer {
+            let max_score = *docs.last().unwrap() as f32;
+            BoundedScorer {
+                scorer: create_mock_scorer(docs),
+                max_score,
+            }
+        }
+    }
+
+    impl Scorer for BoundedScorer {
+        fn score(&mut self) -> Result<f32> {
+            self.scorer.score()
+        }
+
+        fn max_score(&self) -> f32 {
+            self.max_score
+        }
+    }
+
+    impl DocIterator for BoundedScorer {
+        fn doc_id(&self) -> DocId {
+            self.scorer.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.scorer.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.scorer.advance(target)
+        }
+
+        fn cost(&self) -> usize {
+            self.scorer.cost()
+        }
+    }
+
+    #[test]
+    fn test_wand_scorer() {
+        let s1 = BoundedScorer::new(vec![1, 2, 3, 5, 8]);
+        let s2 = BoundedScorer::new(vec![2, 4, 6, 8, 9]);
+        let s3 = BoundedScorer::new(vec![3, 8, 10]);
+        let mut scorer = WANDScorer::new(vec![s1, s2, s3]);
+
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 1.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert!((scorer.score().unwrap() - 4.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.advance(4).unwrap(), 4);
+
+        // nothing below 20 is competitive anymore, only docs matching at least two
+        // clauses may reach it.
+        scorer.set_min_competitive_score(20.0);
+        let mut docs = vec![];
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            docs.push((doc, scorer.score().unwrap()));
+        }
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].0, 8);
+        assert!((docs[0].1 - 24.0).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_wand_scorer_finds_all_competitive_docs() {
+        let postings = vec![
+            vec![1, 4, 7, 10, 13, 16, 19],
+            vec![3, 6, 9, 12, 15, 18],
+            vec![2, 4, 6, 8, 10, 12, 14, 16, 18, 20],
+            vec![5, 10, 15, 20],
+        ];
+
+        for &min_score in &[0.0, 10.0, 20.0, 30.0, 40.0, 60.0, 100.0] {
+            // each clause scores a doc with its id
+            let expected: Vec<(DocId, f32)> = (0..=20)
+                .map(|doc| {
+                    let count = postings.iter().filter(|p| p.contains(&doc)).count();
+                    (doc, (doc as usize * count) as f32)
+                })
+                .filter(|&(_, score)| score > 0.0 && score >= min_score)
+                .collect();
+
+            let scorers = postings
+                .iter()
+                .map(|docs| BoundedScorer::new(docs.clone()))
+                .collect();
+            let mut scorer = WANDScorer::new(scorers);
+            scorer.set_min_competitive_score(min_score);
+            let mut docs = vec![];
+            loop {
+                let doc = scorer.next().unwrap();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                let score = scorer.score().unwrap();
+                if score >= min_score {
+                    docs.push((doc, score));
+                }
+            }
+            assert_eq!(docs, expected, "min competitive score {}", min_score);
+        }
+    }
+}
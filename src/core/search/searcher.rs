@@ -16,8 +16,6 @@ use std::fmt;
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
 
-use crossbeam::channel::{unbounded, Receiver, Sender};
-
 use core::codec::postings::FieldsProducer;
 use core::codec::{get_terms, TermIterator, TermState};
 use core::codec::{Codec, CodecTermState, Terms};
@@ -26,13 +24,17 @@ use core::index::reader::{IndexReader, LeafReaderContext, LeafReaderContextPtr,
 use core::search::cache::{
     LRUQueryCache, QueryCache, QueryCachingPolicy, UsageTrackingQueryCachingPolicy,
 };
-use core::search::collector::{self, Collector, ParallelLeafCollector, SearchCollector};
+use core::search::collector::{
+    self, Collector, SearchCollector, TopDocsCollector, TotalHitCountCollector,
+};
 use core::search::explanation::Explanation;
-use core::search::query::{ConstantScoreQuery, MatchAllDocsQuery, Query, TermQuery, Weight};
+use core::search::profile::{ProfilePlanBuilder, QueryProfile};
+use core::search::query::{Query, Weight};
 use core::search::scorer::{BulkScorer, Scorer};
 use core::search::similarity::{
     BM25Similarity, SimScorer, SimWeight, Similarity, SimilarityProducer,
 };
+use core::search::sort_field::TopDocs;
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use core::search::NO_MORE_DOCS;
 use core::util::external::{DefaultContext, ThreadPool, ThreadPoolBuilder};
@@ -203,6 +205,9 @@ pub trait SearchPlanBuilder<C: Codec> {
     /// max doc of the reader in searcher, same as IndexSearcher::reader()::max_doc()
     fn max_doc(&self) -> i32;
 
+    /// leaf contexts of the reader in searcher, same as IndexSearcher::reader()::leaves()
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>>;
+
     /// Creates a `Weight` for the given query, potentially adding caching
     /// if possible and configured.
     fn create_weight(&self, query: &dyn Query<C>, needs_scores: bool)
@@ -276,16 +281,43 @@ pub struct DefaultIndexSearcher<
     collection_statistics: RwLock<HashMap<String, CollectionStatistics>>,
     term_contexts: RwLock<HashMap<String, Arc<TermContext<CodecTermState<C>>>>>,
     term_contexts_limit: usize,
-    thread_pool: Option<Arc<ThreadPool<DefaultContext>>>,
+    executor: Option<Arc<dyn SearchExecutor>>,
     // used for concurrent search - each slice holds a set of LeafReader's ord that
     // executed within one thread.
     leaf_ord_slices: Vec<LeafOrdSlice>,
+    max_docs_per_slice: i32,
+    max_segments_per_slice: usize,
     next_limit: usize,
 }
 
 const MAX_DOCS_PER_SLICE: i32 = 250_000;
 const MAX_SEGMENTS_PER_SLICE: usize = 20;
 
+/// Executes the jobs of a parallel search, each job collecting the leaves of one
+/// slice of the index.
+///
+/// It is implemented for the `ThreadPool` of rucene, other pools, e.g. a rayon
+/// `ThreadPool` through its `spawn` method, can be plugged in by implementing it.
+/// The jobs must all be run eventually, as the search waits for them to complete.
+///
+/// # Safety
+///
+/// The jobs reference the weight and the leaf readers of the search through raw
+/// pointers, which are only valid until the search returns. An implementation
+/// must run every job to completion before `execute` returns, unless the search
+/// waits for the job to complete, as the parallel collectors of rucene do in
+/// `finish_parallel` for the jobs run on a `ThreadPool`. A job must never be
+/// run, or kept to be run, after the search returned.
+pub unsafe trait SearchExecutor: Send + Sync {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>);
+}
+
+unsafe impl SearchExecutor for ThreadPool<DefaultContext> {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        ThreadPool::execute(self, move |_| job())
+    }
+}
+
 struct LeafOrdSlice(Vec<usize>);
 
 impl<'a> IntoIterator for &'a LeafOrdSlice {
@@ -347,8 +379,10 @@ where
             collection_statistics: RwLock::new(HashMap::new()),
             term_contexts: RwLock::new(HashMap::with_capacity(term_contexts_limit * 2)),
             term_contexts_limit,
-            thread_pool: None,
+            executor: None,
             leaf_ord_slices: vec![],
+            max_docs_per_slice: MAX_DOCS_PER_SLICE,
+            max_segments_per_slice: MAX_SEGMENTS_PER_SLICE,
             next_limit,
         }
     }
@@ -364,14 +398,36 @@ where
     }
 
     pub fn set_thread_pool(&mut self, pool: Arc<ThreadPool<DefaultContext>>) {
-        self.thread_pool = Some(pool);
+        self.set_executor(pool);
+    }
+
+    /// Use `executor` to collect the slices of the index in parallel in
+    /// `IndexSearcher::search_parallel`.
+    pub fn set_executor(&mut self, executor: Arc<dyn SearchExecutor>) {
+        self.executor = Some(executor);
         self.leaf_ord_slices = Self::slice(
             self.reader.leaves(),
-            MAX_DOCS_PER_SLICE,
-            MAX_SEGMENTS_PER_SLICE,
+            self.max_docs_per_slice,
+            self.max_segments_per_slice,
         );
     }
 
+    /// Sets the size of the slices collected in parallel: a slice holds either a
+    /// single segment of at least `max_docs_per_slice` docs, or at most
+    /// `max_segments_per_slice` smaller segments totaling at most `max_docs_per_slice`
+    /// docs.
+    pub fn set_slice_limits(&mut self, max_docs_per_slice: i32, max_segments_per_slice: usize) {
+        self.max_docs_per_slice = max_docs_per_slice;
+        self.max_segments_per_slice = max_segments_per_slice.max(1);
+        if self.executor.is_some() {
+            self.leaf_ord_slices = Self::slice(
+                self.reader.leaves(),
+                self.max_docs_per_slice,
+                self.max_segments_per_slice,
+            );
+        }
+    }
+
     pub fn set_query_cache(&mut self, cache: Arc<dyn QueryCache<C>>) {
         self.query_cache = cache;
     }
@@ -380,6 +436,77 @@ where
         self.cache_policy = cache_policy;
     }
 
+    /// Searches the query like `IndexSearcher::search`, timing the creation of the
+    /// weights and scorers of every node of the query tree, and the iteration over
+    /// their matches.
+    ///
+    /// The segments are searched sequentially and the query cache is bypassed.
+    pub fn profile<S: SearchCollector>(
+        &self,
+        query: &dyn Query<C>,
+        collector: &mut S,
+    ) -> Result<QueryProfile> {
+        let builder = ProfilePlanBuilder::new(self);
+        let weight = builder.create_weight(query, collector.needs_scores())?;
+        self.search_with_weight(weight.as_ref(), collector)?;
+        builder.profile()
+    }
+
+    /// Profiles the query, returning its `top_n` hits with the profile.
+    pub fn profile_top_docs(
+        &self,
+        query: &dyn Query<C>,
+        top_n: usize,
+    ) -> Result<(TopDocs, QueryProfile)> {
+        let mut collector = TopDocsCollector::new(top_n);
+        let profile = self.profile(query, &mut collector)?;
+        Ok((collector.top_docs(), profile))
+    }
+
+    fn search_with_weight<S: SearchCollector>(
+        &self,
+        weight: &dyn Weight<C>,
+        collector: &mut S,
+    ) -> Result<()> {
+        for reader in self.reader.leaves() {
+            let scorer = if collector.needs_top_scores_only() {
+                weight.create_top_scores_scorer(&reader)?
+            } else {
+                weight.create_scorer(&reader)?
+            };
+            if let Some(mut scorer) = scorer {
+                // some in running segment maybe wrong, just skip it!
+                // TODO maybe we should matching more specific error type
+                if let Err(e) = collector.set_next_reader(&reader) {
+                    error!(
+                        "set next reader for leaf {} failed!, {:?}",
+                        reader.reader.name(),
+                        e
+                    );
+                    continue;
+                }
+                let live_docs = reader.reader.live_docs();
+
+                match Self::do_search(&mut *scorer, collector, live_docs.as_ref(), self.next_limit)
+                {
+                    Ok(()) => {}
+                    Err(Error(
+                        ErrorKind::Collector(collector::ErrorKind::CollectionTimeout),
+                        _,
+                    )) => {
+                        // Collection timeout, we must terminate the search
+                        break;
+                    }
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn do_search<S: Scorer + ?Sized, T: Collector, B: Bits + ?Sized>(
         scorer: &mut S,
         collector: &mut T,
@@ -463,39 +590,7 @@ where
         S: SearchCollector,
     {
         let weight = self.create_weight(query, collector.needs_scores())?;
-
-        for reader in self.reader.leaves() {
-            if let Some(mut scorer) = weight.create_scorer(&reader)? {
-                // some in running segment maybe wrong, just skip it!
-                // TODO maybe we should matching more specific error type
-                if let Err(e) = collector.set_next_reader(&reader) {
-                    error!(
-                        "set next reader for leaf {} failed!, {:?}",
-                        reader.reader.name(),
-                        e
-                    );
-                    continue;
-                }
-                let live_docs = reader.reader.live_docs();
-
-                match Self::do_search(&mut *scorer, collector, live_docs.as_ref(), self.next_limit)
-                {
-                    Ok(()) => {}
-                    Err(Error(
-                        ErrorKind::Collector(collector::ErrorKind::CollectionTimeout),
-                        _,
-                    )) => {
-                        // Collection timeout, we must terminate the search
-                        break;
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
-                }
-            }
-        }
-
-        Ok(())
+        self.search_with_weight(weight.as_ref(), collector)
     }
 
     fn search_parallel<S>(&self, query: &dyn Query<C>, collector: &mut S) -> Result<()>
@@ -503,8 +598,8 @@ where
         S: SearchCollector,
     {
         if collector.support_parallel() && self.leaf_ord_slices.len() > 1 {
-            debug_assert!(self.thread_pool.is_some());
-            let thread_pool = self.thread_pool.as_ref().unwrap();
+            debug_assert!(self.executor.is_some());
+            let executor = self.executor.as_ref().unwrap();
             let weight = self.create_weight(query, collector.needs_scores())?;
             let leaf_readers = self.reader.leaves();
 
@@ -541,7 +636,7 @@ where
                 if !scorer_and_collectors.is_empty() {
                     let next_limit = self.next_limit;
 
-                    thread_pool.execute(move |_| {
+                    executor.execute(Box::new(move || {
                         for (w, leaf_ctx_ptr, mut collector) in scorer_and_collectors {
                             let weight = unsafe { &*(w as *const Box<dyn Weight<C>>) };
                             let reader = unsafe { &(*leaf_ctx_ptr.reader) };
@@ -553,9 +648,12 @@ where
                                 leaf_ctx_ptr.doc_base,
                             );
 
-                            if let Some(mut scorer) =
-                                weight.create_scorer(&leaf_ctx).unwrap_or(None)
-                            {
+                            let scorer = if collector.needs_top_scores_only() {
+                                weight.create_top_scores_scorer(&leaf_ctx)
+                            } else {
+                                weight.create_scorer(&leaf_ctx)
+                            };
+                            if let Some(mut scorer) = scorer.unwrap_or(None) {
                                 let live_docs = leaf_ctx.reader.live_docs();
 
                                 let should_terminate = match Self::do_search(
@@ -595,7 +693,7 @@ where
                                 }
                             }
                         }
-                    });
+                    }));
                 }
             }
             return collector.finish_parallel();
@@ -604,23 +702,23 @@ where
     }
 
     fn count(&self, query: &dyn Query<C>) -> Result<i32> {
-        let mut query = query;
-        while let Some(constant_query) = query.as_any().downcast_ref::<ConstantScoreQuery<C>>() {
-            query = constant_query.get_raw_query();
-        }
-
-        if query.as_any().downcast_ref::<MatchAllDocsQuery>().is_some() {
-            return Ok(self.reader().num_docs());
-        } else if let Some(term_query) = query.as_any().downcast_ref::<TermQuery>() {
-            if !self.reader().has_deletions() {
-                let term = &term_query.term;
-                let mut count = 0;
-                for leaf in self.reader().leaves() {
-                    count += leaf.reader.doc_freq(term)?;
+        // the counts of term or match all queries come from the index statistics
+        // unless the segments have deletions, in which case the hits are collected
+        let weight = self.create_weight(query, false)?;
+        let mut count = 0;
+        let mut counted_all = true;
+        for leaf in self.reader().leaves() {
+            match weight.count(&leaf)? {
+                Some(leaf_count) => count += leaf_count,
+                None => {
+                    counted_all = false;
+                    break;
                 }
-                return Ok(count);
             }
         }
+        if counted_all {
+            return Ok(count);
+        }
 
         let mut collector = TotalHitCountCollector::new();
         self.search_parallel(query, &mut collector)?;
@@ -634,7 +732,7 @@ where
             Ok(Explanation::new(
                 false,
                 0.0f32,
-                format!("Document {} if deleted", doc),
+                format!("Document {} is deleted", doc),
                 vec![],
             ))
         } else {
@@ -659,6 +757,10 @@ where
         self.reader.max_doc()
     }
 
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>> {
+        self.reader.leaves()
+    }
+
     /// Creates a {@link Weight} for the given query, potentially adding caching
     /// if possible and configured.
     fn create_weight(
@@ -769,113 +871,18 @@ where
     }
 }
 
-struct TotalHitCountCollector {
-    total_hits: i32,
-    channel: Option<(Sender<i32>, Receiver<i32>)>,
-}
-
-impl TotalHitCountCollector {
-    pub fn new() -> Self {
-        TotalHitCountCollector {
-            total_hits: 0,
-            channel: None,
-        }
-    }
-
-    pub fn total_hits(&self) -> i32 {
-        self.total_hits
-    }
-}
-
-impl SearchCollector for TotalHitCountCollector {
-    type LC = TotalHitsCountLeafCollector;
-    fn set_next_reader<C: Codec>(&mut self, _reader: &LeafReaderContext<'_, C>) -> Result<()> {
-        Ok(())
-    }
-
-    fn support_parallel(&self) -> bool {
-        true
-    }
-
-    fn init_parallel(&mut self) {
-        if self.channel.is_none() {
-            self.channel = Some(unbounded());
-        }
-    }
-
-    fn leaf_collector<C: Codec>(
-        &self,
-        _reader: &LeafReaderContext<'_, C>,
-    ) -> Result<TotalHitsCountLeafCollector> {
-        Ok(TotalHitsCountLeafCollector {
-            count: 0,
-            sender: self.channel.as_ref().unwrap().0.clone(),
-        })
-    }
-
-    fn finish_parallel(&mut self) -> Result<()> {
-        let channel = self.channel.take();
-        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
-        // inited and thus stay None
-        if let Some((sender, receiver)) = channel {
-            drop(sender);
-            while let Ok(v) = receiver.recv() {
-                self.total_hits += v;
-            }
-        }
-
-        Ok(())
-    }
-}
-
-impl Collector for TotalHitCountCollector {
-    fn needs_scores(&self) -> bool {
-        false
-    }
-
-    fn collect<S: Scorer + ?Sized>(&mut self, _doc: i32, _scorer: &mut S) -> Result<()> {
-        self.total_hits += 1;
-        Ok(())
-    }
-}
-
-struct TotalHitsCountLeafCollector {
-    count: i32,
-    sender: Sender<i32>,
-}
-
-impl Collector for TotalHitsCountLeafCollector {
-    fn needs_scores(&self) -> bool {
-        false
-    }
-
-    fn collect<S: Scorer + ?Sized>(&mut self, _doc: i32, _scorer: &mut S) -> Result<()> {
-        self.count += 1;
-        Ok(())
-    }
-}
-
-impl ParallelLeafCollector for TotalHitsCountLeafCollector {
-    fn finish_leaf(&mut self) -> Result<()> {
-        self.sender.send(self.count).map_err(|e| {
-            ErrorKind::IllegalState(format!(
-                "channel unexpected closed before search complete with err: {:?}",
-                e
-            ))
-            .into()
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use core::codec::tests::TestCodec;
     use core::index::tests::*;
     use core::search::collector::*;
-    use core::search::query::TermQuery;
+    use core::search::query::{MatchAllDocsQuery, TermQuery};
     use core::search::tests::*;
+    use core::search::ProfileTiming;
     use core::util::DocId;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
 
     struct MockQuery {
         docs: Vec<DocId>,
@@ -948,4 +955,103 @@ mod tests {
         assert!((score_docs[1].score() - 5f32) < ::std::f32::EPSILON);
         assert!((score_docs[2].score() - 5f32) < ::std::f32::EPSILON);
     }
+
+    #[test]
+    fn test_explain() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![
+                MockLeafReader::new(10),
+                MockLeafReader::new(10),
+            ]));
+        let searcher = DefaultIndexSearcher::new(index_reader, None, None);
+        let query = MockQuery::new(vec![1, 2, 5]);
+
+        // doc 12 is the local doc 2 of the second segment
+        let explanation = searcher.explain(&query, 12).unwrap();
+        assert!(explanation.is_match());
+        assert!((explanation.value() - 2.0).abs() < ::std::f32::EPSILON);
+
+        let explanation = searcher.explain(&query, 13).unwrap();
+        assert!(!explanation.is_match());
+        assert!(explanation.value().abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_count() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![
+                MockLeafReader::new(10),
+                MockLeafReader::new(5),
+            ]));
+        let searcher = DefaultIndexSearcher::new(index_reader, None, None);
+
+        // counted from the segments' num docs
+        assert_eq!(searcher.count(&MatchAllDocsQuery).unwrap(), 15);
+        // the hits of the mock weight are collected, in every segment
+        assert_eq!(searcher.count(&MockQuery::new(vec![1, 2, 3])).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_profile() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![
+                MockLeafReader::new(10),
+                MockLeafReader::new(10),
+            ]));
+        let searcher = DefaultIndexSearcher::new(index_reader, None, None);
+
+        let (top_docs, profile) = searcher
+            .profile_top_docs(&MockQuery::new(vec![1, 2, 3]), 10)
+            .unwrap();
+        assert_eq!(top_docs.total_hits(), 6);
+
+        assert_eq!(profile.description, "MockQuery");
+        assert!(profile.children.is_empty());
+        assert_eq!(profile.timing(ProfileTiming::CreateWeight).count, 1);
+        assert_eq!(profile.timing(ProfileTiming::BuildScorer).count, 2);
+        // the first doc and the 3 following ones of each segment
+        assert_eq!(profile.timing(ProfileTiming::NextDoc).count, 8);
+        assert_eq!(profile.timing(ProfileTiming::Advance).count, 0);
+        assert_eq!(profile.timing(ProfileTiming::Score).count, 6);
+        let time: Duration = profile.breakdown.iter().map(|t| t.time).sum();
+        assert_eq!(profile.time, time);
+    }
+
+    struct InlineExecutor {
+        jobs: AtomicUsize,
+    }
+
+    unsafe impl SearchExecutor for InlineExecutor {
+        fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+            self.jobs.fetch_add(1, Ordering::AcqRel);
+            job()
+        }
+    }
+
+    #[test]
+    fn test_search_parallel_with_executor() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![
+                MockLeafReader::new(10),
+                MockLeafReader::new(10),
+                MockLeafReader::new(10),
+            ]));
+        let mut searcher = DefaultIndexSearcher::new(index_reader, None, None);
+        let executor = Arc::new(InlineExecutor {
+            jobs: AtomicUsize::new(0),
+        });
+        searcher.set_executor(Arc::clone(&executor) as Arc<dyn SearchExecutor>);
+        // a single slice, searched sequentially
+        assert_eq!(searcher.leaf_ord_slices.len(), 1);
+
+        searcher.set_slice_limits(20, 2);
+        assert_eq!(searcher.leaf_ord_slices.len(), 2);
+
+        let mut collector = TopDocsCollector::new(10);
+        searcher
+            .search_parallel(&MockQuery::new(vec![1, 2]), &mut collector)
+            .unwrap();
+        assert_eq!(executor.jobs.load(Ordering::Acquire), 2);
+        assert_eq!(collector.top_docs().total_hits(), 6);
+    }
 }
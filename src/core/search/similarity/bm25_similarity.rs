@@ -25,7 +25,7 @@ use core::codec::field_infos::FieldInvertState;
 use core::codec::Codec;
 use core::index::reader::SearchLeafReader;
 use core::search::explanation::Explanation;
-use core::search::similarity::{SimScorer, SimWeight, Similarity};
+use core::search::similarity::{NormOptions, SimScorer, SimWeight, Similarity, SimilarityProducer};
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use core::util::SmallFloat;
 use core::util::{DocId, KeyedContext};
@@ -46,9 +46,17 @@ pub const DEFAULT_BM25_K1: f32 = 1.2;
 pub const DEFAULT_BM25_B: f32 = 0.75;
 
 /// BM25 Similarity.
+/// BM25 Similarity. Introduced in Stephen E. Robertson, Steve Walker,
+/// Susan Jones, Micheline Hancock-Beaulieu, and Mike Gatford. Okapi at TREC-3.
+///
+/// `k1` controls non-linear term frequency normalization (saturation) and `b`
+/// controls to what degree document length normalizes tf values. As a
+/// `SimilarityProducer` it scores every field with the same parameters.
+#[derive(Clone, Debug)]
 pub struct BM25Similarity {
     k1: f32,
     b: f32,
+    norm_options: NormOptions,
 }
 
 impl Default for BM25Similarity {
@@ -59,7 +67,38 @@ impl Default for BM25Similarity {
 
 impl BM25Similarity {
     pub fn new(k1: f32, b: f32) -> BM25Similarity {
-        BM25Similarity { k1, b }
+        debug_assert!(k1.is_finite() && k1 >= 0.0);
+        debug_assert!(b >= 0.0 && b <= 1.0);
+        BM25Similarity {
+            k1,
+            b,
+            norm_options: NormOptions::default(),
+        }
+    }
+
+    /// Returns the `k1` parameter
+    pub fn k1(&self) -> f32 {
+        self.k1
+    }
+
+    /// Returns the `b` parameter
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+
+    pub fn norm_options(&self) -> NormOptions {
+        self.norm_options
+    }
+
+    /// Sets how the norms are computed at index time.
+    pub fn set_norm_options(&mut self, norm_options: NormOptions) {
+        self.norm_options = norm_options;
+    }
+
+    /// Sets whether overlap tokens (tokens with a position increment of zero)
+    /// are discounted from the field length.
+    pub fn set_discount_overlaps(&mut self, discount_overlaps: bool) {
+        self.norm_options.discount_overlaps = discount_overlaps;
     }
 
     fn sloppy_freq(distance: i32) -> f32 {
@@ -175,6 +214,16 @@ impl<C: Codec> Similarity<C> for BM25Similarity {
             boost,
         ))
     }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.norm_options.encode(state)
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for BM25Similarity {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(self.clone())
+    }
 }
 
 impl fmt::Display for BM25Similarity {
@@ -217,6 +266,11 @@ impl SimScorer for BM25SimScorer {
         self.compute_score(doc, freq)
     }
 
+    fn max_score(&self) -> f32 {
+        // `freq / (freq + norm)` tends to 1 as the freq grows
+        self.weight * (self.k1 + 1.0)
+    }
+
     fn compute_slop_factor(&self, distance: i32) -> f32 {
         BM25Similarity::sloppy_freq(distance)
     }
@@ -340,14 +394,14 @@ impl BM25SimWeight {
 
         let boost_explanation = Explanation::new(true, self.boost, "boost".to_string(), vec![]);
         let boost_value = boost_explanation.value();
-        if (boost_value - 1.0).abs() < ::std::f32::EPSILON {
+        if (boost_value - 1.0).abs() >= ::std::f32::EPSILON {
             subs.push(boost_explanation);
         }
 
         let idf_value = self.idf_explanation.value();
         subs.push(self.idf_explanation.clone());
 
-        let freq_string = freq.to_string(0);
+        let freq_value = freq.value();
         let tf_explanation = self.explain_tf_norm(doc, freq, norms)?;
         let tf_value = tf_explanation.value();
         subs.push(tf_explanation);
@@ -355,7 +409,7 @@ impl BM25SimWeight {
         Ok(Explanation::new(
             true,
             boost_value * idf_value * tf_value,
-            format!("score(doc={},freq={}), product of:", doc, freq_string),
+            format!("score(doc={},freq={}), product of:", doc, freq_value),
             subs,
         ))
     }
@@ -394,6 +448,7 @@ impl<C: Codec> SimWeight<C> for BM25SimWeight {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::codec::tests::TestCodec;
     use core::index::tests::MockLeafReader;
 
     // copy from Lucene TestBM25Similarity
@@ -463,4 +518,27 @@ mod tests {
 
         assert!(score1 > score2);
     }
+
+    #[test]
+    fn test_bm25_similarity_parameters() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 32, 32, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
+        let leaf_reader = MockLeafReader::new(1);
+
+        let producer = BM25Similarity::new(2.0, 0.0);
+        let sim: Box<dyn Similarity<TestCodec>> = producer.create("world");
+        assert_eq!(sim.to_string(), "BM25Similarity(k1: 2, b: 0)");
+
+        let default_sim = BM25Similarity::default();
+        let mut default_scorer = default_sim
+            .compute_weight(&collection_stats, &term_stats, None, 1.0f32)
+            .sim_scorer(&leaf_reader)
+            .unwrap();
+        let mut scorer = sim
+            .compute_weight(&collection_stats, &term_stats, None, 1.0f32)
+            .sim_scorer(&leaf_reader)
+            .unwrap();
+        // a larger k1 saturates the term frequency later
+        assert!(scorer.score(1, 10.0).unwrap() > default_scorer.score(1, 10.0).unwrap());
+    }
 }
@@ -0,0 +1,349 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::field_infos::FieldInvertState;
+use core::codec::Codec;
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{NormOptions, SimScorer, SimWeight, Similarity, SimilarityProducer};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::SmallFloat;
+use core::util::{DocId, KeyedContext};
+
+use std::fmt;
+
+lazy_static! {
+    static ref NORM_TABLE: [f32; 256] = {
+        let mut norm_table: [f32; 256] = [0f32; 256];
+        for (i, norm) in norm_table.iter_mut().enumerate() {
+            *norm = SmallFloat::byte315_to_float(i as u8);
+        }
+        norm_table
+    };
+}
+
+/// Expert: Classic scoring implementation, the vector space model of
+/// TF-IDF used by Lucene before `BM25Similarity` became the default.
+///
+/// The score of a document for a term is `tf * idf^2 * boost * queryNorm * lengthNorm`
+/// with:
+/// - `tf = sqrt(freq)`
+/// - `idf = 1 + ln((docCount + 1) / (docFreq + 1))`
+/// - `lengthNorm = 1 / sqrt(numTerms)`, encoded into a single byte at index time exactly like
+///   `BM25Similarity` does, so both can score the same index. It can be changed with `NormOptions`.
+/// - `queryNorm = 1 / sqrt(sumOfSquaredWeights)`
+#[derive(Clone, Debug, Default)]
+pub struct ClassicSimilarity {
+    norm_options: NormOptions,
+}
+
+impl ClassicSimilarity {
+    pub fn norm_options(&self) -> NormOptions {
+        self.norm_options
+    }
+
+    /// Sets how the norms are computed at index time.
+    pub fn set_norm_options(&mut self, norm_options: NormOptions) {
+        self.norm_options = norm_options;
+    }
+
+    /// Sets whether overlap tokens (tokens with a position increment of zero)
+    /// are discounted from the field length.
+    pub fn set_discount_overlaps(&mut self, discount_overlaps: bool) {
+        self.norm_options.discount_overlaps = discount_overlaps;
+    }
+
+    /// Implemented as `sqrt(freq)`.
+    pub fn tf(freq: f32) -> f32 {
+        freq.sqrt()
+    }
+
+    /// Implemented as `1 / (distance + 1)`.
+    pub fn sloppy_freq(distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+
+    /// Implemented as `ln((docCount + 1) / (docFreq + 1)) + 1`.
+    pub fn idf(doc_freq: i64, doc_count: i64) -> f32 {
+        (((doc_count + 1) as f64 / (doc_freq + 1) as f64).ln() + 1.0) as f32
+    }
+
+    pub fn encode_norm_value(f: f32) -> u8 {
+        SmallFloat::float_to_byte315(f)
+    }
+
+    #[inline]
+    pub fn decode_norm_value(b: u8) -> f32 {
+        NORM_TABLE[b as usize]
+    }
+
+    fn idf_explain(
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Explanation {
+        let doc_count = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+
+        let mut idf_total = 0f32;
+        let mut details: Vec<Explanation> = vec![];
+        for stat in term_stats {
+            let idf = ClassicSimilarity::idf(stat.doc_freq, doc_count);
+            idf_total += idf;
+            details.push(Explanation::new(
+                true,
+                idf,
+                format!("idf(docFreq={}, docCount={})", stat.doc_freq, doc_count),
+                vec![],
+            ));
+        }
+
+        if details.len() == 1 {
+            details.remove(0)
+        } else {
+            Explanation::new(true, idf_total, "idf(), sum of:".to_string(), details)
+        }
+    }
+}
+
+impl<C: Codec> Similarity<C> for ClassicSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let idf = ClassicSimilarity::idf_explain(collection_stats, term_stats);
+        Box::new(IDFStats::new(collection_stats.field.clone(), idf, boost))
+    }
+
+    fn query_norm(&self, value_for_normalization: f32, _context: Option<&KeyedContext>) -> f32 {
+        if value_for_normalization > 0.0 {
+            1.0 / value_for_normalization.sqrt()
+        } else {
+            1.0
+        }
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.norm_options.encode(state)
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for ClassicSimilarity {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(self.clone())
+    }
+}
+
+impl fmt::Display for ClassicSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClassicSimilarity")
+    }
+}
+
+struct IDFStats {
+    field: String,
+    idf: Explanation,
+    boost: f32,
+    query_norm: f32,
+    query_weight: f32,
+    // idf^2 * boost * queryNorm
+    value: f32,
+}
+
+impl IDFStats {
+    fn new(field: String, idf: Explanation, boost: f32) -> IDFStats {
+        let mut stats = IDFStats {
+            field,
+            idf,
+            boost: 1.0,
+            query_norm: 1.0,
+            query_weight: 0.0,
+            value: 0.0,
+        };
+        stats.do_normalize(1.0, boost);
+        stats
+    }
+
+    fn do_normalize(&mut self, query_norm: f32, boost: f32) {
+        self.boost = boost;
+        self.query_norm = query_norm;
+        self.query_weight = query_norm * boost * self.idf.value();
+        self.value = self.query_weight * self.idf.value();
+    }
+
+    fn explain_query(&self) -> Explanation {
+        let mut subs = vec![];
+        if (self.boost - 1.0).abs() >= ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                self.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(self.idf.clone());
+        subs.push(Explanation::new(
+            true,
+            self.query_norm,
+            "queryNorm".to_string(),
+            vec![],
+        ));
+        Explanation::new(
+            true,
+            self.query_weight,
+            "queryWeight, product of:".to_string(),
+            subs,
+        )
+    }
+
+    fn explain_field(
+        &self,
+        doc: DocId,
+        freq: Explanation,
+        norms: Option<Box<dyn NumericDocValues>>,
+    ) -> Result<Explanation> {
+        let tf = Explanation::new(
+            true,
+            ClassicSimilarity::tf(freq.value()),
+            format!("tf(freq={}), with freq of:", freq.value()),
+            vec![freq],
+        );
+        let field_norm = match norms {
+            Some(n) => ClassicSimilarity::decode_norm_value(n.get(doc)? as u8),
+            None => 1.0,
+        };
+        let field_norm =
+            Explanation::new(true, field_norm, format!("fieldNorm(doc={})", doc), vec![]);
+        Ok(Explanation::new(
+            true,
+            tf.value() * self.idf.value() * field_norm.value(),
+            format!("fieldWeight in {}, product of:", doc),
+            vec![tf, self.idf.clone(), field_norm],
+        ))
+    }
+}
+
+impl<C: Codec> SimWeight<C> for IDFStats {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.query_weight * self.query_weight
+    }
+
+    fn normalize(&mut self, query_norm: f32, boost: f32) {
+        self.do_normalize(query_norm, boost)
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        let norms = reader.norm_values(&self.field)?;
+        Ok(Box::new(TFIDFSimScorer {
+            weight_value: self.value,
+            norms,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let norms = reader.norm_values(&self.field)?;
+        let freq_value = freq.value();
+        let query = self.explain_query();
+        let field = self.explain_field(doc, freq, norms)?;
+        Ok(Explanation::new(
+            true,
+            query.value() * field.value(),
+            format!("score(doc={},freq={}), product of:", doc, freq_value),
+            vec![query, field],
+        ))
+    }
+}
+
+struct TFIDFSimScorer {
+    weight_value: f32,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl SimScorer for TFIDFSimScorer {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let raw = ClassicSimilarity::tf(freq) * self.weight_value;
+        match self.norms {
+            Some(ref norms) => {
+                Ok(raw * ClassicSimilarity::decode_norm_value(norms.get(doc)? as u8))
+            }
+            None => Ok(raw),
+        }
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        ClassicSimilarity::sloppy_freq(distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::tests::MockLeafReader;
+
+    #[test]
+    fn test_tf_idf() {
+        assert!((ClassicSimilarity::tf(4.0) - 2.0).abs() < ::std::f32::EPSILON);
+        assert!((ClassicSimilarity::idf(0, 0) - 1.0).abs() < ::std::f32::EPSILON);
+        let idf = ClassicSimilarity::idf(1, 9);
+        assert!((idf - (1.0 + 5f32.ln())).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_norm_round_trip() {
+        let state = FieldInvertState::new("field".to_string(), 0, 4, 0, 0, 1.0);
+        let norm = Similarity::<TestCodec>::compute_norm(&ClassicSimilarity::default(), &state);
+        let length_norm = ClassicSimilarity::decode_norm_value(norm as u8);
+        assert!((length_norm - 0.5).abs() < ::std::f32::EPSILON);
+
+        for i in 1..256 {
+            assert!(
+                ClassicSimilarity::decode_norm_value(i as u8)
+                    > ClassicSimilarity::decode_norm_value(i as u8 - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_classic_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 9, 9, -1, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
+        let sim = ClassicSimilarity::default();
+        let mut weight: Box<dyn SimWeight<TestCodec>> =
+            sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+        let idf = ClassicSimilarity::idf(1, 9);
+        assert!((weight.get_value_for_normalization() - idf * idf).abs() < 1e-5);
+
+        let query_norm =
+            Similarity::<TestCodec>::query_norm(&sim, weight.get_value_for_normalization(), None);
+        weight.normalize(query_norm, 1.0);
+        assert!((weight.get_value_for_normalization() - 1.0).abs() < 1e-5);
+
+        let leaf_reader = MockLeafReader::new(1);
+        let mut scorer = weight.sim_scorer(&leaf_reader).unwrap();
+        assert!(scorer.score(1, 4.0).unwrap() > scorer.score(1, 1.0).unwrap());
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::field_infos::FieldInvertState;
+use core::codec::Codec;
+use core::search::explanation::Explanation;
+use core::search::similarity::{BaseSimWeight, BasicStats, NormOptions, SimilarityBase};
+use core::search::similarity::{SimWeight, Similarity, SimilarityProducer};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+use std::f64::consts::{E, LOG2_E, PI};
+use std::fmt;
+
+/// The basic models of information content of the divergence from
+/// randomness framework.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BasicModel {
+    /// Limiting form of the Bose-Einstein model.
+    Be,
+    /// Divergence approximation of the binomial model.
+    D,
+    /// Geometric as limiting form of the Bose-Einstein model.
+    G,
+    /// Tf-idf model with the term frequency.
+    IF,
+    /// Tf-idf model with the document frequency.
+    In,
+    /// Tf-idf model with the expected document frequency.
+    Ine,
+    /// Poisson approximation of the binomial model.
+    P,
+}
+
+impl BasicModel {
+    /// Returns the informative content score for the normalized term
+    /// frequency `tfn`.
+    pub fn score(self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let n = stats.number_of_documents as f64;
+        let score = match self {
+            BasicModel::Be => {
+                let f = stats.total_term_freq as f64 + 1.0 + tfn;
+                // approximation only holds true when F << N, so we use N += F
+                let n = n + f;
+                -((n - 1.0) * E).log2() + Self::be_f(n + f - 1.0, n + f - tfn - 2.0)
+                    - Self::be_f(f, f - tfn)
+            }
+            BasicModel::D => {
+                let f = stats.total_term_freq as f64 + 1.0 + tfn;
+                let phi = tfn / f;
+                let nphi = 1.0 - phi;
+                let p = 1.0 / (n + 1.0);
+                let d = phi * (phi / p).log2() + nphi * (nphi / (1.0 - p)).log2();
+                d * f + 0.5 * (1.0 + 2.0 * PI * tfn * nphi).log2()
+            }
+            BasicModel::G => {
+                let f = stats.total_term_freq as f64 + 1.0;
+                let lambda = f / (n + f);
+                (lambda + 1.0).log2() + tfn * ((1.0 + lambda) / lambda).log2()
+            }
+            BasicModel::IF => {
+                let f = stats.total_term_freq as f64;
+                tfn * (1.0 + (n + 1.0) / (f + 0.5)).log2()
+            }
+            BasicModel::In => {
+                let doc_freq = stats.doc_freq as f64;
+                tfn * ((n + 1.0) / (doc_freq + 0.5)).log2()
+            }
+            BasicModel::Ine => {
+                let f = stats.total_term_freq as f64;
+                let ne = n * (1.0 - ((n - 1.0) / n).powf(f));
+                tfn * ((n + 1.0) / (ne + 0.5)).log2()
+            }
+            BasicModel::P => {
+                let f = stats.total_term_freq as f64;
+                let lambda = (f + 1.0) / (n + 1.0);
+                tfn * (tfn / lambda).log2()
+                    + (lambda + 1.0 / (12.0 * tfn) - tfn) * LOG2_E
+                    + 0.5 * (2.0 * PI * tfn).log2()
+            }
+        };
+        score as f32
+    }
+
+    fn be_f(n: f64, m: f64) -> f64 {
+        (m + 0.5) * (n / m).log2() + (n - m) * n.log2()
+    }
+}
+
+impl fmt::Display for BasicModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BasicModel::Be => "Be",
+            BasicModel::D => "D",
+            BasicModel::G => "G",
+            BasicModel::IF => "I(F)",
+            BasicModel::In => "I(n)",
+            BasicModel::Ine => "I(ne)",
+            BasicModel::P => "P",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The first normalization of the information content, it models the gain of
+/// information when a term is seen once more in a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AfterEffect {
+    /// Ratio of two Bernoulli processes.
+    B,
+    /// Laplace's law of succession.
+    L,
+    /// Disables the first normalization.
+    NoAfterEffect,
+}
+
+impl AfterEffect {
+    /// Returns the aftereffect score for the normalized term frequency `tfn`.
+    pub fn score(self, stats: &BasicStats, tfn: f32) -> f32 {
+        match self {
+            AfterEffect::B => {
+                let f = (stats.total_term_freq + 1) as f32;
+                let n = (stats.doc_freq + 1) as f32;
+                (f + 1.0) / (n * (tfn + 1.0))
+            }
+            AfterEffect::L => 1.0 / (tfn + 1.0),
+            AfterEffect::NoAfterEffect => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for AfterEffect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AfterEffect::B => write!(f, "B"),
+            AfterEffect::L => write!(f, "L"),
+            AfterEffect::NoAfterEffect => Ok(()),
+        }
+    }
+}
+
+/// The second (length) normalization of the term frequency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    /// Uniform distribution of the term frequency with the hyper-parameter `c`,
+    /// usually 1.
+    H1(f32),
+    /// The term frequency density is inversely related to the length, with
+    /// the hyper-parameter `c`, usually 1.
+    H2(f32),
+    /// Dirichlet priors with the hyper-parameter `mu`, usually 800.
+    H3(f32),
+    /// Pareto-Zipf normalization with the hyper-parameter `z`, which must be in
+    /// `(0, 0.5)` and is usually 0.3.
+    Z(f32),
+    /// Disables the second normalization.
+    NoNormalization,
+}
+
+impl Normalization {
+    /// Returns the normalized term frequency of `tf` in a field of length `len`.
+    pub fn tfn(self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        match self {
+            Normalization::H1(c) => tf * c * stats.avg_field_length / len,
+            Normalization::H2(c) => {
+                tf * (1.0 + f64::from(c * stats.avg_field_length / len)).log2() as f32
+            }
+            Normalization::H3(mu) => {
+                let p = (stats.total_term_freq as f32 + 1.0)
+                    / (stats.number_of_field_tokens as f32 + 1.0);
+                (tf + mu * p) / (len + mu) * mu
+            }
+            Normalization::Z(z) => tf * (stats.avg_field_length / len).powf(z),
+            Normalization::NoNormalization => tf,
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Normalization::H1(_) => write!(f, "1"),
+            Normalization::H2(_) => write!(f, "2"),
+            Normalization::H3(_) => write!(f, "3"),
+            Normalization::Z(z) => write!(f, "Z({})", z),
+            Normalization::NoNormalization => Ok(()),
+        }
+    }
+}
+
+/// Implements the divergence from randomness (DFR) framework introduced in
+/// Gianni Amati and Cornelis Joost Van Rijsbergen. 2002. Probabilistic models
+/// of information retrieval based on measuring the divergence from randomness.
+///
+/// The score of a term is `boost * basic_model(tfn) * after_effect(tfn)`, where
+/// `tfn` is the term frequency normalized by `normalization`. For example the
+/// `I(n)B2` model is built with `BasicModel::In`, `AfterEffect::B` and
+/// `Normalization::H2(1.0)`.
+#[derive(Clone, Debug)]
+pub struct DFRSimilarity {
+    basic_model: BasicModel,
+    after_effect: AfterEffect,
+    normalization: Normalization,
+    norm_options: NormOptions,
+}
+
+impl DFRSimilarity {
+    pub fn new(
+        basic_model: BasicModel,
+        after_effect: AfterEffect,
+        normalization: Normalization,
+    ) -> DFRSimilarity {
+        DFRSimilarity {
+            basic_model,
+            after_effect,
+            normalization,
+            norm_options: NormOptions::default(),
+        }
+    }
+
+    pub fn basic_model(&self) -> BasicModel {
+        self.basic_model
+    }
+
+    pub fn after_effect(&self) -> AfterEffect {
+        self.after_effect
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    pub fn norm_options(&self) -> NormOptions {
+        self.norm_options
+    }
+
+    /// Sets how the norms are computed at index time.
+    pub fn set_norm_options(&mut self, norm_options: NormOptions) {
+        self.norm_options = norm_options;
+    }
+
+    /// Sets whether overlap tokens (tokens with a position increment of zero)
+    /// are discounted from the field length.
+    pub fn set_discount_overlaps(&mut self, discount_overlaps: bool) {
+        self.norm_options.discount_overlaps = discount_overlaps;
+    }
+}
+
+impl SimilarityBase for DFRSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let tfn = self.normalization.tfn(stats, freq, doc_len);
+        stats.boost * self.basic_model.score(stats, tfn) * self.after_effect.score(stats, tfn)
+    }
+
+    fn explain(
+        &self,
+        stats: &BasicStats,
+        doc: DocId,
+        freq: Explanation,
+        doc_len: f32,
+    ) -> Explanation {
+        let freq_value = freq.value();
+        let tfn = self.normalization.tfn(stats, freq_value, doc_len);
+        let mut subs = vec![];
+        if (stats.boost - 1.0).abs() >= ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(Explanation::new(
+            true,
+            tfn,
+            format!(
+                "tfn, normalized by {:?} with fieldLength {} and avgFieldLength {}, from:",
+                self.normalization, doc_len, stats.avg_field_length
+            ),
+            vec![freq],
+        ));
+        subs.push(Explanation::new(
+            true,
+            self.basic_model.score(stats, tfn),
+            format!("basic model {:?}", self.basic_model),
+            vec![],
+        ));
+        subs.push(Explanation::new(
+            true,
+            self.after_effect.score(stats, tfn),
+            format!("after effect {:?}", self.after_effect),
+            vec![],
+        ));
+        Explanation::new(
+            true,
+            self.score(stats, freq_value, doc_len),
+            format!(
+                "score({}, doc={}, freq={}), product of:",
+                self, doc, freq_value
+            ),
+            subs,
+        )
+    }
+}
+
+impl<C: Codec> Similarity<C> for DFRSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        Box::new(BaseSimWeight::new(
+            self.clone(),
+            collection_stats,
+            term_stats,
+            boost,
+        ))
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.norm_options.encode(state)
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for DFRSimilarity {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(self.clone())
+    }
+}
+
+impl fmt::Display for DFRSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DFR {}{}{}",
+            self.basic_model, self.after_effect, self.normalization
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> BasicStats {
+        let collection_stats = CollectionStatistics::new("field".to_string(), 100, 100, 1000, 500);
+        let term_stats = TermStatistics::new(Vec::new(), 10, 20);
+        BasicStats::new(&collection_stats, &term_stats, 1.0)
+    }
+
+    #[test]
+    fn test_dfr_score() {
+        let stats = stats();
+        assert!((stats.avg_field_length - 10.0).abs() < ::std::f32::EPSILON);
+
+        let sim = DFRSimilarity::new(BasicModel::In, AfterEffect::B, Normalization::H2(1.0));
+        assert_eq!(sim.to_string(), "DFR I(n)B2");
+        // tfn = 2 * log2(1 + 10 / 10) = 2
+        let expected = 2.0 * (101f32 / 10.5).log2() * (22.0 / (11.0 * 3.0));
+        assert!((sim.score(&stats, 2.0, 10.0) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_dfr_basic_models_increase_with_freq() {
+        let stats = stats();
+        let basic_models = [
+            BasicModel::Be,
+            BasicModel::D,
+            BasicModel::G,
+            BasicModel::IF,
+            BasicModel::In,
+            BasicModel::Ine,
+            BasicModel::P,
+        ];
+        let normalizations = [
+            Normalization::H1(1.0),
+            Normalization::H2(1.0),
+            Normalization::H3(800.0),
+            Normalization::Z(0.3),
+            Normalization::NoNormalization,
+        ];
+        for basic_model in &basic_models {
+            for normalization in &normalizations {
+                let sim =
+                    DFRSimilarity::new(*basic_model, AfterEffect::NoAfterEffect, *normalization);
+                let low = sim.score(&stats, 2.0, 10.0);
+                let high = sim.score(&stats, 4.0, 10.0);
+                assert!(low.is_finite() && low > 0.0, "{}", sim);
+                assert!(high > low, "{}", sim);
+            }
+        }
+    }
+}
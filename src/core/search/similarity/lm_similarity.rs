@@ -0,0 +1,345 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::field_infos::FieldInvertState;
+use core::codec::Codec;
+use core::search::explanation::Explanation;
+use core::search::similarity::{BaseSimWeight, BasicStats, NormOptions, SimilarityBase};
+use core::search::similarity::{SimWeight, Similarity, SimilarityProducer};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+use std::fmt;
+
+pub const DEFAULT_DIRICHLET_MU: f32 = 2000.0;
+
+pub const DEFAULT_JELINEK_MERCER_LAMBDA: f32 = 0.7;
+
+/// The probability of a term in the language model of the whole collection,
+/// computed as `(totalTermFreq + 1) / (numberOfFieldTokens + 1)`.
+pub fn collection_probability(stats: &BasicStats) -> f32 {
+    (stats.total_term_freq as f32 + 1.0) / (stats.number_of_field_tokens as f32 + 1.0)
+}
+
+/// Bayesian smoothing using Dirichlet priors. From Chengxiang Zhai and John
+/// Lafferty. 2001. A study of smoothing methods for language models applied
+/// to Ad Hoc information retrieval.
+///
+/// The formula as defined by the paper assigns a negative score to documents
+/// that contain the term, but with fewer occurrences than predicted by the
+/// collection language model, such scores are clamped to 0.
+#[derive(Clone, Debug)]
+pub struct LMDirichletSimilarity {
+    mu: f32,
+    norm_options: NormOptions,
+}
+
+impl Default for LMDirichletSimilarity {
+    fn default() -> Self {
+        LMDirichletSimilarity::new(DEFAULT_DIRICHLET_MU)
+    }
+}
+
+impl LMDirichletSimilarity {
+    pub fn new(mu: f32) -> LMDirichletSimilarity {
+        debug_assert!(mu.is_finite() && mu >= 0.0);
+        LMDirichletSimilarity {
+            mu,
+            norm_options: NormOptions::default(),
+        }
+    }
+
+    /// Returns the `mu` parameter.
+    pub fn mu(&self) -> f32 {
+        self.mu
+    }
+
+    pub fn norm_options(&self) -> NormOptions {
+        self.norm_options
+    }
+
+    /// Sets how the norms are computed at index time.
+    pub fn set_norm_options(&mut self, norm_options: NormOptions) {
+        self.norm_options = norm_options;
+    }
+
+    /// Sets whether overlap tokens (tokens with a position increment of zero)
+    /// are discounted from the field length.
+    pub fn set_discount_overlaps(&mut self, discount_overlaps: bool) {
+        self.norm_options.discount_overlaps = discount_overlaps;
+    }
+}
+
+impl SimilarityBase for LMDirichletSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let collection_probability = f64::from(collection_probability(stats));
+        let mu = f64::from(self.mu);
+        let score = (1.0 + f64::from(freq) / (mu * collection_probability)).ln()
+            + (mu / (f64::from(doc_len) + mu)).ln();
+        (stats.boost * score as f32).max(0.0)
+    }
+
+    fn explain(
+        &self,
+        stats: &BasicStats,
+        doc: DocId,
+        freq: Explanation,
+        doc_len: f32,
+    ) -> Explanation {
+        let freq_value = freq.value();
+        let mut subs = vec![];
+        if (stats.boost - 1.0).abs() >= ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(freq);
+        subs.push(Explanation::new(true, self.mu, "mu".to_string(), vec![]));
+        subs.push(Explanation::new(
+            true,
+            collection_probability(stats),
+            "collection probability".to_string(),
+            vec![],
+        ));
+        subs.push(Explanation::new(
+            true,
+            doc_len,
+            "fieldLength".to_string(),
+            vec![],
+        ));
+        Explanation::new(
+            true,
+            self.score(stats, freq_value, doc_len),
+            format!(
+                "score({}, doc={}, freq={}), computed as boost * max(0, log(1 + freq / (mu * \
+                 collectionProbability)) + log(mu / (fieldLength + mu))) from:",
+                self, doc, freq_value
+            ),
+            subs,
+        )
+    }
+}
+
+impl<C: Codec> Similarity<C> for LMDirichletSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        Box::new(BaseSimWeight::new(
+            self.clone(),
+            collection_stats,
+            term_stats,
+            boost,
+        ))
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.norm_options.encode(state)
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for LMDirichletSimilarity {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(self.clone())
+    }
+}
+
+impl fmt::Display for LMDirichletSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LM Dirichlet({})", self.mu)
+    }
+}
+
+/// Language model based on the Jelinek-Mercer smoothing method. From
+/// Chengxiang Zhai and John Lafferty. 2001. A study of smoothing methods for
+/// language models applied to Ad Hoc information retrieval.
+///
+/// The model depends on `lambda`, the optimal value is around 0.1 for title
+/// queries and 0.7 for long queries.
+#[derive(Clone, Debug)]
+pub struct LMJelinekMercerSimilarity {
+    lambda: f32,
+    norm_options: NormOptions,
+}
+
+impl Default for LMJelinekMercerSimilarity {
+    fn default() -> Self {
+        LMJelinekMercerSimilarity::new(DEFAULT_JELINEK_MERCER_LAMBDA)
+    }
+}
+
+impl LMJelinekMercerSimilarity {
+    pub fn new(lambda: f32) -> LMJelinekMercerSimilarity {
+        debug_assert!(lambda > 0.0 && lambda <= 1.0);
+        LMJelinekMercerSimilarity {
+            lambda,
+            norm_options: NormOptions::default(),
+        }
+    }
+
+    /// Returns the `lambda` parameter.
+    pub fn lambda(&self) -> f32 {
+        self.lambda
+    }
+
+    pub fn norm_options(&self) -> NormOptions {
+        self.norm_options
+    }
+
+    /// Sets how the norms are computed at index time.
+    pub fn set_norm_options(&mut self, norm_options: NormOptions) {
+        self.norm_options = norm_options;
+    }
+
+    /// Sets whether overlap tokens (tokens with a position increment of zero)
+    /// are discounted from the field length.
+    pub fn set_discount_overlaps(&mut self, discount_overlaps: bool) {
+        self.norm_options.discount_overlaps = discount_overlaps;
+    }
+}
+
+impl SimilarityBase for LMJelinekMercerSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let lambda = f64::from(self.lambda);
+        let collection_probability = f64::from(collection_probability(stats));
+        let score = (1.0
+            + ((1.0 - lambda) * f64::from(freq) / f64::from(doc_len))
+                / (lambda * collection_probability))
+            .ln();
+        stats.boost * score as f32
+    }
+
+    fn explain(
+        &self,
+        stats: &BasicStats,
+        doc: DocId,
+        freq: Explanation,
+        doc_len: f32,
+    ) -> Explanation {
+        let freq_value = freq.value();
+        let mut subs = vec![];
+        if (stats.boost - 1.0).abs() >= ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(freq);
+        subs.push(Explanation::new(
+            true,
+            self.lambda,
+            "lambda".to_string(),
+            vec![],
+        ));
+        subs.push(Explanation::new(
+            true,
+            collection_probability(stats),
+            "collection probability".to_string(),
+            vec![],
+        ));
+        subs.push(Explanation::new(
+            true,
+            doc_len,
+            "fieldLength".to_string(),
+            vec![],
+        ));
+        Explanation::new(
+            true,
+            self.score(stats, freq_value, doc_len),
+            format!(
+                "score({}, doc={}, freq={}), computed as boost * log(1 + ((1 - lambda) * freq / \
+                 fieldLength) / (lambda * collectionProbability)) from:",
+                self, doc, freq_value
+            ),
+            subs,
+        )
+    }
+}
+
+impl<C: Codec> Similarity<C> for LMJelinekMercerSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        Box::new(BaseSimWeight::new(
+            self.clone(),
+            collection_stats,
+            term_stats,
+            boost,
+        ))
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.norm_options.encode(state)
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for LMJelinekMercerSimilarity {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(self.clone())
+    }
+}
+
+impl fmt::Display for LMJelinekMercerSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LM Jelinek-Mercer({})", self.lambda)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> BasicStats {
+        let collection_stats = CollectionStatistics::new("field".to_string(), 100, 100, 999, 500);
+        let term_stats = TermStatistics::new(Vec::new(), 10, 19);
+        BasicStats::new(&collection_stats, &term_stats, 1.0)
+    }
+
+    #[test]
+    fn test_lm_dirichlet() {
+        let stats = stats();
+        // collection probability = 20 / 1000
+        assert!((collection_probability(&stats) - 0.02).abs() < ::std::f32::EPSILON);
+
+        let sim = LMDirichletSimilarity::new(100.0);
+        assert_eq!(sim.to_string(), "LM Dirichlet(100)");
+        let expected = (1.0f32 + 3.0 / 2.0).ln() + (100f32 / 110.0).ln();
+        assert!((sim.score(&stats, 3.0, 10.0) - expected).abs() < 1e-5);
+        assert!(sim.score(&stats, 4.0, 10.0) > sim.score(&stats, 3.0, 10.0));
+        assert!(sim.score(&stats, 3.0, 100.0) < sim.score(&stats, 3.0, 10.0));
+        // less occurrences than predicted by the collection model
+        assert!(sim.score(&stats, 0.01, 1000.0).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_lm_jelinek_mercer() {
+        let stats = stats();
+        let sim = LMJelinekMercerSimilarity::new(0.5);
+        assert_eq!(sim.to_string(), "LM Jelinek-Mercer(0.5)");
+        let expected = (1.0f32 + (0.5 * 2.0 / 10.0) / (0.5 * 0.02)).ln();
+        assert!((sim.score(&stats, 2.0, 10.0) - expected).abs() < 1e-5);
+        assert!(sim.score(&stats, 2.0, 20.0) < sim.score(&stats, 2.0, 10.0));
+    }
+}
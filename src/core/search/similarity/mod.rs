@@ -15,14 +15,44 @@ mod bm25_similarity;
 
 pub use self::bm25_similarity::*;
 
+mod boolean_similarity;
+
+pub use self::boolean_similarity::*;
+
+mod classic_similarity;
+
+pub use self::classic_similarity::*;
+
+mod similarity_base;
+
+pub use self::similarity_base::*;
+
+mod dfr_similarity;
+
+pub use self::dfr_similarity::*;
+
+mod lm_similarity;
+
+pub use self::lm_similarity::*;
+
+mod norm_options;
+
+pub use self::norm_options::*;
+
+mod per_field_similarity;
+
+pub use self::per_field_similarity::*;
+
 use core::util::{DocId, KeyedContext};
 
+use core::codec::field_infos::FieldInvertState;
 use core::codec::Codec;
 use core::index::reader::SearchLeafReader;
 use core::search::explanation::Explanation;
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use error::Result;
 use std::fmt::Display;
+use std::sync::Arc;
 
 /// Similarity defines the components of Lucene scoring.
 ///
@@ -39,7 +69,7 @@ use std::fmt::Display;
 ///
 ///
 /// `Indexing Time`
-/// At indexing time, the indexer calls `computeNorm(FieldInvertState)`, allowing
+/// At indexing time, the indexer calls `compute_norm(FieldInvertState)`, allowing
 /// the Similarity implementation to set a per-document value for the field that will
 /// be later accessible via `org.apache.lucene.index.LeafReader#getNormValues(String)`.  Lucene
 /// makes no assumption about what is in this norm, but it is most useful for encoding length
@@ -113,9 +143,25 @@ pub trait Similarity<C: Codec>: Display {
     fn query_norm(&self, _value_for_normalization: f32, _context: Option<&KeyedContext>) -> f32 {
         1.0f32
     }
+
+    /// Computes the normalization value for a field, given the accumulated
+    /// state of term processing for this field (see `FieldInvertState`).
+    ///
+    /// Matches in longer fields are less precise, so implementations usually
+    /// set smaller values when `state.length` is large, and larger values when
+    /// it is small. The default encodes the length like `BM25Similarity`, with
+    /// the default `NormOptions`.
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        NormOptions::default().encode(state)
+    }
 }
 
 /// Per-field similarity provider.
+/// Creates the `Similarity` used for a field.
+///
+/// A producer is plugged into `DefaultIndexSearcher::with_similarity` to score
+/// the queries, and into `IndexWriterConfig::similarity` to compute the norms
+/// at index time, the same one should usually be used for both.
 pub trait SimilarityProducer<C> {
     fn create(&self, field: &str) -> Box<dyn Similarity<C>>;
 }
@@ -126,6 +172,12 @@ impl<C: Codec> SimilarityProducer<C> for Box<dyn SimilarityProducer<C>> {
     }
 }
 
+impl<C: Codec> SimilarityProducer<C> for Arc<dyn SimilarityProducer<C> + Send + Sync> {
+    fn create(&self, field: &str) -> Box<dyn Similarity<C>> {
+        (**self).create(field)
+    }
+}
+
 /// API for scoring "sloppy" queries such as `TermQuery`, `SpanQuery`, `PhraseQuery`.
 ///
 /// Frequencies are floating-point values: an approximate within-document
@@ -137,6 +189,12 @@ pub trait SimScorer: Send {
     /// @return document's score
     fn score(&mut self, doc: DocId, freq: f32) -> Result<f32>;
 
+    /// Return an upper bound of the scores returned by `score`, whatever the doc
+    /// and freq, or `f32::INFINITY` if there is no such bound.
+    fn max_score(&self) -> f32 {
+        ::std::f32::INFINITY
+    }
+
     /// Computes the amount of a sloppy phrase match, based on an edit distance.
     fn compute_slop_factor(&self, distance: i32) -> f32;
 
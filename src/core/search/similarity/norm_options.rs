@@ -0,0 +1,116 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::field_infos::FieldInvertState;
+use core::util::SmallFloat;
+
+use std::fmt;
+
+/// Computes the length normalization factor of a field from its invert state
+/// and its number of terms, the result is encoded into a single byte norm.
+///
+/// The built-in similarities decode the norm as `1 / factor^2`, the field
+/// length, so a function should return `boost / sqrt(length)` for whatever
+/// length it considers.
+pub type LengthNormFn = fn(state: &FieldInvertState, num_terms: i32) -> f32;
+
+/// The default length normalization, `boost / sqrt(num_terms)`.
+pub fn sqrt_length_norm(state: &FieldInvertState, num_terms: i32) -> f32 {
+    state.boost / (num_terms as f32).sqrt()
+}
+
+/// Disables the length normalization, every field is considered to have a
+/// length of 1.
+pub fn no_length_norm(state: &FieldInvertState, _num_terms: i32) -> f32 {
+    state.boost
+}
+
+/// Controls how a `Similarity` computes the norm of a field at index time.
+#[derive(Clone, Copy)]
+pub struct NormOptions {
+    /// Whether the tokens with a position increment of zero, e.g. synonyms,
+    /// are discounted from the field length. Defaults to true.
+    pub discount_overlaps: bool,
+    /// The length normalization factor, defaults to `sqrt_length_norm`.
+    pub length_norm: LengthNormFn,
+}
+
+impl Default for NormOptions {
+    fn default() -> Self {
+        NormOptions {
+            discount_overlaps: true,
+            length_norm: sqrt_length_norm,
+        }
+    }
+}
+
+impl fmt::Debug for NormOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NormOptions")
+            .field("discount_overlaps", &self.discount_overlaps)
+            .finish()
+    }
+}
+
+impl NormOptions {
+    /// Options that give every field the same norm, so the length of the
+    /// fields isn't taken into account when scoring.
+    pub fn without_length_norm() -> NormOptions {
+        NormOptions {
+            discount_overlaps: true,
+            length_norm: no_length_norm,
+        }
+    }
+
+    /// Returns the number of terms of the field.
+    pub fn num_terms(&self, state: &FieldInvertState) -> i32 {
+        if self.discount_overlaps {
+            state.length - state.num_overlap
+        } else {
+            state.length
+        }
+    }
+
+    /// Encodes the norm of the field.
+    pub fn encode(&self, state: &FieldInvertState) -> i64 {
+        let factor = (self.length_norm)(state, self.num_terms(state));
+        i64::from(SmallFloat::float_to_byte315(factor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_norm_options() {
+        // 6 of the 10 tokens are synonyms
+        let state = FieldInvertState::new("body".to_string(), 10, 10, 6, 0, 1.0);
+
+        let options = NormOptions::default();
+        assert_eq!(options.num_terms(&state), 4);
+        let discounted = options.encode(&state);
+
+        let options = NormOptions {
+            discount_overlaps: false,
+            ..NormOptions::default()
+        };
+        assert_eq!(options.num_terms(&state), 10);
+        // a longer field gets a smaller norm
+        assert!(options.encode(&state) < discounted);
+
+        let options = NormOptions::without_length_norm();
+        let short = FieldInvertState::new("body".to_string(), 1, 1, 0, 0, 1.0);
+        assert_eq!(options.encode(&state), options.encode(&short));
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::search::similarity::{Similarity, SimilarityProducer};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Provides the ability to use a different `Similarity` for different fields.
+///
+/// The fields without a specific similarity use the default one. The wrapper
+/// should be shared by the `IndexWriterConfig` and the searcher, e.g. through
+/// an `Arc<dyn SimilarityProducer<C> + Send + Sync>`, so that the norms are
+/// encoded and decoded consistently for every field.
+pub struct PerFieldSimilarityWrapper<C: Codec> {
+    default: Arc<dyn SimilarityProducer<C> + Send + Sync>,
+    fields: HashMap<String, Arc<dyn SimilarityProducer<C> + Send + Sync>>,
+}
+
+impl<C: Codec> PerFieldSimilarityWrapper<C> {
+    /// Creates a wrapper using `default` for all the fields.
+    pub fn new<S>(default: S) -> PerFieldSimilarityWrapper<C>
+    where
+        S: SimilarityProducer<C> + Send + Sync + 'static,
+    {
+        PerFieldSimilarityWrapper {
+            default: Arc::new(default),
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Use `similarity` for `field` instead of the default one.
+    pub fn set_similarity<S>(&mut self, field: &str, similarity: S)
+    where
+        S: SimilarityProducer<C> + Send + Sync + 'static,
+    {
+        self.fields.insert(field.to_string(), Arc::new(similarity));
+    }
+
+    /// Returns the producer used for `field`.
+    pub fn get(&self, field: &str) -> &dyn SimilarityProducer<C> {
+        match self.fields.get(field) {
+            Some(similarity) => similarity.as_ref(),
+            None => self.default.as_ref(),
+        }
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for PerFieldSimilarityWrapper<C> {
+    fn create(&self, field: &str) -> Box<dyn Similarity<C>> {
+        self.get(field).create(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::search::similarity::{BM25Similarity, BooleanSimilarity};
+
+    #[test]
+    fn test_per_field_similarity() {
+        let mut wrapper: PerFieldSimilarityWrapper<TestCodec> =
+            PerFieldSimilarityWrapper::new(BM25Similarity::default());
+        wrapper.set_similarity("title", BM25Similarity::new(1.2, 0.3));
+        wrapper.set_similarity("flag", BooleanSimilarity);
+
+        assert_eq!(
+            wrapper.create("title").to_string(),
+            "BM25Similarity(k1: 1.2, b: 0.3)"
+        );
+        assert_eq!(wrapper.create("flag").to_string(), "BooleanSimilarity");
+        assert_eq!(
+            wrapper.create("body").to_string(),
+            "BM25Similarity(k1: 1.2, b: 0.75)"
+        );
+
+        let shared: Arc<dyn SimilarityProducer<TestCodec> + Send + Sync> = Arc::new(wrapper);
+        assert_eq!(shared.create("flag").to_string(), "BooleanSimilarity");
+    }
+}
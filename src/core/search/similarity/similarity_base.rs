@@ -0,0 +1,237 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{SimScorer, SimWeight};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, SmallFloat};
+
+use std::fmt;
+
+lazy_static! {
+    static ref NORM_TABLE: [f32; 256] = {
+        let mut norm_table: [f32; 256] = [0f32; 256];
+        for (i, norm) in norm_table.iter_mut().enumerate().skip(1) {
+            let f = SmallFloat::byte315_to_float(i as u8);
+            *norm = 1f32 / (f * f);
+        }
+        norm_table[0] = 1f32 / norm_table[255];
+        norm_table
+    };
+}
+
+/// Stores all statistics commonly used by the ranking methods of a
+/// `SimilarityBase`.
+#[derive(Clone, Debug)]
+pub struct BasicStats {
+    pub field: String,
+    /// The number of documents.
+    pub number_of_documents: i64,
+    /// The total number of tokens in the field.
+    pub number_of_field_tokens: i64,
+    /// The average field length.
+    pub avg_field_length: f32,
+    /// The document frequency.
+    pub doc_freq: i64,
+    /// The total number of occurrences of this term across all documents.
+    pub total_term_freq: i64,
+    /// The query boost.
+    pub boost: f32,
+}
+
+impl BasicStats {
+    pub fn new(
+        collection_stats: &CollectionStatistics,
+        term_stats: &TermStatistics,
+        boost: f32,
+    ) -> BasicStats {
+        let number_of_documents = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+        let doc_freq = term_stats.doc_freq;
+        let total_term_freq = if term_stats.total_term_freq == -1 {
+            doc_freq
+        } else {
+            term_stats.total_term_freq
+        };
+        // the field does not store the term frequencies
+        let (number_of_field_tokens, avg_field_length) =
+            if collection_stats.sum_total_term_freq <= 0 {
+                (doc_freq, 1f32)
+            } else {
+                let tokens = collection_stats.sum_total_term_freq;
+                (tokens, (tokens as f64 / number_of_documents as f64) as f32)
+            };
+
+        BasicStats {
+            field: collection_stats.field.clone(),
+            number_of_documents,
+            number_of_field_tokens,
+            avg_field_length,
+            doc_freq,
+            total_term_freq,
+            boost,
+        }
+    }
+}
+
+/// A simpler API to implement a ranking model than `Similarity`, the
+/// implementations only score a single term given its `BasicStats`, the term
+/// frequency and the length of the field in the document.
+///
+/// The norms are encoded like `BM25Similarity` does and decoded into the field
+/// length by `decode_norm_value`. A `Similarity` built on top of this trait
+/// returns a `BaseSimWeight` from `Similarity::compute_weight`.
+pub trait SimilarityBase: fmt::Display + Clone + Send + 'static {
+    /// Scores the document `doc`.
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32;
+
+    /// Explains the score, the default implementation only reports the score
+    /// and the term frequency.
+    fn explain(
+        &self,
+        stats: &BasicStats,
+        doc: DocId,
+        freq: Explanation,
+        doc_len: f32,
+    ) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, freq.value(), doc_len),
+            format!(
+                "score({}, doc={}, freq={}), computed from:",
+                self,
+                doc,
+                freq.value()
+            ),
+            vec![freq],
+        )
+    }
+}
+
+/// Decodes a norm encoded at index time into the field length.
+#[inline]
+pub fn decode_norm_value(b: u8) -> f32 {
+    NORM_TABLE[b as usize]
+}
+
+/// The `SimWeight` of a `SimilarityBase`, the score of a document is the sum of
+/// the scores of every term.
+pub struct BaseSimWeight<S: SimilarityBase> {
+    similarity: S,
+    stats: Vec<BasicStats>,
+}
+
+impl<S: SimilarityBase> BaseSimWeight<S> {
+    pub fn new(
+        similarity: S,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        boost: f32,
+    ) -> BaseSimWeight<S> {
+        let stats = term_stats
+            .iter()
+            .map(|t| BasicStats::new(collection_stats, t, boost))
+            .collect();
+        BaseSimWeight { similarity, stats }
+    }
+
+    fn field(&self) -> Option<&str> {
+        self.stats.first().map(|s| s.field.as_str())
+    }
+}
+
+impl<C: Codec, S: SimilarityBase> SimWeight<C> for BaseSimWeight<S> {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.stats.iter().map(|s| s.boost * s.boost).sum()
+    }
+
+    fn normalize(&mut self, _query_norm: f32, boost: f32) {
+        for stats in &mut self.stats {
+            stats.boost = boost;
+        }
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        let norms = match self.field() {
+            Some(field) => reader.norm_values(field)?,
+            None => None,
+        };
+        Ok(Box::new(BaseSimScorer {
+            similarity: self.similarity.clone(),
+            stats: self.stats.clone(),
+            norms,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let doc_len = match self.field() {
+            Some(field) => match reader.norm_values(field)? {
+                Some(norms) => decode_norm_value(norms.get(doc)? as u8),
+                None => 1f32,
+            },
+            None => 1f32,
+        };
+        let mut subs: Vec<Explanation> = self
+            .stats
+            .iter()
+            .map(|s| self.similarity.explain(s, doc, freq.clone(), doc_len))
+            .collect();
+        if subs.len() == 1 {
+            Ok(subs.remove(0))
+        } else {
+            Ok(Explanation::new(
+                true,
+                subs.iter().map(Explanation::value).sum(),
+                format!("score(doc={},freq={}), sum of:", doc, freq.value()),
+                subs,
+            ))
+        }
+    }
+}
+
+struct BaseSimScorer<S: SimilarityBase> {
+    similarity: S,
+    stats: Vec<BasicStats>,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl<S: SimilarityBase> SimScorer for BaseSimScorer<S> {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let doc_len = match self.norms {
+            Some(ref norms) => decode_norm_value(norms.get(doc)? as u8),
+            None => 1f32,
+        };
+        Ok(self
+            .stats
+            .iter()
+            .map(|s| self.similarity.score(s, freq, doc_len))
+            .sum())
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+}
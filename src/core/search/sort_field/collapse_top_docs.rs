@@ -11,9 +11,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::search::sort_field::SortField;
+use core::search::sort_field::{Sort, SortField};
 use core::util::DocId;
 use core::util::VariantValue;
+use error::{ErrorKind::IllegalArgument, Result};
 use std::cmp::{Ord, Ordering};
 use std::f32;
 
@@ -22,11 +23,17 @@ use std::f32;
 pub struct ScoreDoc {
     pub doc: DocId,
     pub score: f32,
+    /// Index of the shard the hit comes from, set by `TopDocs::merge`.
+    pub shard_index: usize,
 }
 
 impl ScoreDoc {
     pub fn new(doc: DocId, score: f32) -> ScoreDoc {
-        ScoreDoc { doc, score }
+        ScoreDoc {
+            doc,
+            score,
+            shard_index: 0,
+        }
     }
 
     pub fn reset(&mut self, doc: DocId, score: f32) {
@@ -158,6 +165,20 @@ impl ScoreDocHit {
         }
     }
 
+    pub fn shard_index(&self) -> usize {
+        match *self {
+            ScoreDocHit::Score(ref s) => s.shard_index,
+            ScoreDocHit::Field(ref f) => f.shard_index,
+        }
+    }
+
+    pub fn set_shard_index(&mut self, shard_index: usize) {
+        match *self {
+            ScoreDocHit::Score(ref mut s) => s.shard_index = shard_index,
+            ScoreDocHit::Field(ref mut f) => f.shard_index = shard_index,
+        }
+    }
+
     pub fn order_by_doc(d1: &ScoreDocHit, d2: &ScoreDocHit) -> Ordering {
         if d1.doc_id() < d2.doc_id() {
             Ordering::Less
@@ -212,6 +233,9 @@ pub struct TopScoreDocs {
 
     /// Stores the maximum score value encountered, needed for normalizing.
     max_score: f32,
+
+    /// Whether the search timed out, in which case the hits are partial.
+    pub timed_out: bool,
 }
 
 impl TopScoreDocs {
@@ -220,6 +244,7 @@ impl TopScoreDocs {
             total_hits,
             score_docs,
             max_score: f32::NAN,
+            timed_out: false,
         }
     }
 
@@ -234,6 +259,8 @@ pub struct TopFieldDocs {
     pub score_docs: Vec<ScoreDocHit>,
     pub max_score: f32,
     pub fields: Vec<SortField>,
+    /// Whether the search timed out, in which case the hits are partial.
+    pub timed_out: bool,
 }
 
 pub struct CollapseTopFieldDocs {
@@ -255,8 +282,11 @@ pub struct CollapseTopFieldDocs {
     /// The field used for collapsing
     pub field: String,
 
-    /// The collapse value for each top doc
-    pub collapse_values: Vec<VariantValue>,
+    /// The collapse value for each top doc, `None` for the docs without a value
+    pub collapse_values: Vec<Option<VariantValue>>,
+
+    /// Whether the search timed out, in which case the hits are partial.
+    pub timed_out: bool,
 }
 
 impl CollapseTopFieldDocs {
@@ -266,7 +296,7 @@ impl CollapseTopFieldDocs {
         total_groups: usize,
         score_docs: Vec<ScoreDocHit>,
         sort_fields: Vec<SortField>,
-        collapse_values: Vec<VariantValue>,
+        collapse_values: Vec<Option<VariantValue>>,
         max_score: f32,
     ) -> CollapseTopFieldDocs {
         CollapseTopFieldDocs {
@@ -277,6 +307,7 @@ impl CollapseTopFieldDocs {
             fields: sort_fields,
             field,
             collapse_values,
+            timed_out: false,
         }
     }
 
@@ -316,6 +347,23 @@ impl TopDocs {
         }
     }
 
+    /// Returns true if the search timed out before all the hits were collected.
+    pub fn timed_out(&self) -> bool {
+        match *self {
+            TopDocs::Score(ref s) => s.timed_out,
+            TopDocs::Field(ref f) => f.timed_out,
+            TopDocs::Collapse(ref c) => c.timed_out,
+        }
+    }
+
+    pub fn set_timed_out(&mut self, timed_out: bool) {
+        match *self {
+            TopDocs::Score(ref mut s) => s.timed_out = timed_out,
+            TopDocs::Field(ref mut f) => f.timed_out = timed_out,
+            TopDocs::Collapse(ref mut c) => c.timed_out = timed_out,
+        }
+    }
+
     pub fn score_docs_mut(&mut self) -> &mut Vec<ScoreDocHit> {
         match *self {
             TopDocs::Score(ref mut s) => &mut s.score_docs,
@@ -323,4 +371,204 @@ impl TopDocs {
             TopDocs::Collapse(ref mut c) => &mut c.score_docs,
         }
     }
+
+    fn into_score_docs(self) -> Vec<ScoreDocHit> {
+        match self {
+            TopDocs::Score(s) => s.score_docs,
+            TopDocs::Field(f) => f.score_docs,
+            TopDocs::Collapse(c) => c.score_docs,
+        }
+    }
+
+    /// Merges the top docs of several shards, each sorted by score, into the top
+    /// `top_n` hits by score, e.g. on the node coordinating a distributed search.
+    ///
+    /// Hits with the same score are sorted by shard index, then by doc id. When
+    /// `set_shard_index` is true, the shard index of each hit is set to the index
+    /// of its top docs in `shard_hits`, otherwise the hits must hold it already.
+    pub fn merge(top_n: usize, shard_hits: Vec<TopDocs>, set_shard_index: bool) -> TopDocs {
+        let mut total_hits = 0;
+        let mut timed_out = false;
+        let mut hits = Vec::new();
+        for (shard_index, top_docs) in shard_hits.into_iter().enumerate() {
+            total_hits += top_docs.total_hits();
+            timed_out |= top_docs.timed_out();
+            let start = hits.len();
+            hits.extend(top_docs.into_score_docs());
+            if set_shard_index {
+                for hit in &mut hits[start..] {
+                    hit.set_shard_index(shard_index);
+                }
+            }
+        }
+
+        hits.sort_by(|h1, h2| {
+            h2.score()
+                .partial_cmp(&h1.score())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| h1.shard_index().cmp(&h2.shard_index()))
+                .then_with(|| h1.doc_id().cmp(&h2.doc_id()))
+        });
+        hits.truncate(top_n);
+
+        let mut merged = TopScoreDocs::new(total_hits, hits);
+        merged.timed_out = timed_out;
+        TopDocs::Score(merged)
+    }
+
+    /// Merges the `TopDocs::Field` top docs of several shards, each sorted by
+    /// `sort`, into the top `top_n` hits by `sort`.
+    ///
+    /// Hits with the same sort values are sorted by shard index, then by doc id,
+    /// `set_shard_index` has the same meaning as in `merge`.
+    pub fn merge_field_docs(
+        sort: &Sort,
+        top_n: usize,
+        shard_hits: Vec<TopDocs>,
+        set_shard_index: bool,
+    ) -> Result<TopDocs> {
+        let mut total_hits = 0;
+        let mut max_score = f32::NAN;
+        let mut timed_out = false;
+        let mut hits = Vec::new();
+        for (shard_index, top_docs) in shard_hits.into_iter().enumerate() {
+            let field_docs = match top_docs {
+                TopDocs::Field(f) => f,
+                _ => bail!(IllegalArgument(format!(
+                    "shard {} top docs are not sorted by fields",
+                    shard_index
+                ))),
+            };
+            total_hits += field_docs.total_hits;
+            max_score = max_score.max(field_docs.max_score);
+            timed_out |= field_docs.timed_out;
+            for hit in field_docs.score_docs {
+                let mut doc = match hit {
+                    ScoreDocHit::Field(doc) => doc,
+                    ScoreDocHit::Score(_) => bail!(IllegalArgument(format!(
+                        "shard {} hits do not hold their sort values",
+                        shard_index
+                    ))),
+                };
+                if doc.fields.len() != sort.get_sort().len() {
+                    bail!(IllegalArgument(format!(
+                        "shard {} hits have {} sort values, but the sort has {} fields",
+                        shard_index,
+                        doc.fields.len(),
+                        sort.get_sort().len()
+                    )));
+                }
+                if set_shard_index {
+                    doc.shard_index = shard_index;
+                }
+                hits.push(doc);
+            }
+        }
+
+        hits.sort_by(|d1, d2| {
+            sort.compare_values(&d1.fields, &d2.fields)
+                .then_with(|| d1.shard_index.cmp(&d2.shard_index))
+                .then_with(|| d1.doc.cmp(&d2.doc))
+        });
+        hits.truncate(top_n);
+
+        Ok(TopDocs::Field(TopFieldDocs {
+            total_hits,
+            score_docs: hits.into_iter().map(ScoreDocHit::Field).collect(),
+            max_score,
+            fields: sort.get_sort().to_vec(),
+            timed_out,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::sort_field::{SimpleSortField, SortFieldType};
+
+    fn score_hits(hits: &[(DocId, f32)]) -> Vec<ScoreDocHit> {
+        hits.iter()
+            .map(|&(doc, score)| ScoreDocHit::Score(ScoreDoc::new(doc, score)))
+            .collect()
+    }
+
+    fn hit_ids(top_docs: &TopDocs) -> Vec<(usize, DocId)> {
+        top_docs
+            .score_docs()
+            .iter()
+            .map(|h| (h.shard_index(), h.doc_id()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_by_score() {
+        let shard_hits = vec![
+            TopDocs::Score(TopScoreDocs::new(5, score_hits(&[(3, 4.0), (1, 2.0)]))),
+            TopDocs::Score(TopScoreDocs::new(2, score_hits(&[(0, 3.0), (7, 2.0)]))),
+            TopDocs::Score(TopScoreDocs::new(0, vec![])),
+        ];
+        let merged = TopDocs::merge(3, shard_hits, true);
+        assert_eq!(merged.total_hits(), 7);
+        assert!(!merged.timed_out());
+        // the tie on score 2.0 is broken by the shard index
+        assert_eq!(hit_ids(&merged), vec![(0, 3), (1, 0), (0, 1)]);
+
+        // keep the shard index of the hits
+        let mut hits = score_hits(&[(2, 1.0)]);
+        hits[0].set_shard_index(4);
+        let mut shard = TopScoreDocs::new(1, hits);
+        shard.timed_out = true;
+        let shard_hits = vec![
+            TopDocs::Score(shard),
+            TopDocs::Score(TopScoreDocs::new(1, score_hits(&[(2, 1.0)]))),
+        ];
+        let merged = TopDocs::merge(10, shard_hits, false);
+        assert!(merged.timed_out());
+        assert_eq!(hit_ids(&merged), vec![(0, 2), (4, 2)]);
+    }
+
+    fn field_docs(sort: &Sort, hits: &[(DocId, i64)], max_score: f32) -> TopDocs {
+        TopDocs::Field(TopFieldDocs {
+            total_hits: hits.len(),
+            score_docs: hits
+                .iter()
+                .map(|&(doc, price)| {
+                    ScoreDocHit::Field(FieldDoc::new(doc, 1.0, vec![VariantValue::Long(price)]))
+                })
+                .collect(),
+            max_score,
+            fields: sort.get_sort().to_vec(),
+            timed_out: false,
+        })
+    }
+
+    #[test]
+    fn test_merge_field_docs() {
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            false,
+        ))]);
+
+        let shard_hits = vec![
+            field_docs(&sort, &[(4, 10), (2, 30)], 1.5),
+            field_docs(&sort, &[(1, 10), (8, 20)], 2.5),
+        ];
+        let merged = TopDocs::merge_field_docs(&sort, 3, shard_hits, true).unwrap();
+        assert_eq!(merged.total_hits(), 4);
+        assert_eq!(hit_ids(&merged), vec![(0, 4), (1, 1), (1, 8)]);
+        if let TopDocs::Field(ref f) = merged {
+            assert!((f.max_score - 2.5).abs() < ::std::f32::EPSILON);
+            assert_eq!(f.fields.len(), 1);
+        } else {
+            panic!("expected field docs");
+        }
+
+        let shard_hits = vec![
+            field_docs(&sort, &[(4, 10)], 1.0),
+            TopDocs::Score(TopScoreDocs::new(1, score_hits(&[(1, 1.0)]))),
+        ];
+        assert!(TopDocs::merge_field_docs(&sort, 3, shard_hits, true).is_err());
+    }
 }
@@ -11,15 +11,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::codec::doc_values::NumericDocValues;
+use core::codec::doc_values::{NumericDocValues, SortedDocValues, SortedNumericDocValues};
+use core::doc::{DocValuesType, LatLonDocValuesField};
+use core::expressions::ExpressionValuesSource;
+use core::geo::haversin_meters;
 use core::index::reader::{LeafReaderContext, SearchLeafReader};
 use core::search::sort_field::{SortFieldType, SortedWrapperDocValuesSource};
+use core::search::{DoubleValues, DoubleValuesSource};
 use core::util::{BitsMut, DocId, VariantValue};
-use error::Result;
+use error::{ErrorKind::IllegalState, Result};
 
 use core::codec::Codec;
 use std::cmp::Ordering;
 use std::fmt;
+use std::sync::Arc;
 
 #[derive(Copy, Clone, Debug)]
 pub enum ComparatorValue {
@@ -28,6 +33,16 @@ pub enum ComparatorValue {
 }
 
 impl ComparatorValue {
+    /// The value compared for a hit by a comparator of `field_type`, which is the
+    /// score for `SortFieldType::Score`, the doc id otherwise.
+    pub fn of_hit(field_type: SortFieldType, doc: DocId, score: f32) -> ComparatorValue {
+        if field_type == SortFieldType::Score {
+            ComparatorValue::Score(score)
+        } else {
+            ComparatorValue::Doc(doc)
+        }
+    }
+
     fn is_doc(self) -> bool {
         match self {
             ComparatorValue::Doc(_) => true,
@@ -145,6 +160,9 @@ pub enum FieldComparatorEnum {
     Doc(DocComparator),
     NumericDV(NumericDocValuesComparator<DefaultDocValuesSource>),
     SortedNumericDV(NumericDocValuesComparator<SortedWrapperDocValuesSource>),
+    TermOrdVal(TermOrdValComparator),
+    LatLonDistance(LatLonDistanceComparator),
+    Expression(ExpressionComparator),
 }
 
 impl FieldComparator for FieldComparatorEnum {
@@ -154,6 +172,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::NumericDV(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::TermOrdVal(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::LatLonDistance(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::Expression(c) => c.compare(slot1, slot2),
         }
     }
 
@@ -163,6 +184,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.value(slot),
             FieldComparatorEnum::NumericDV(c) => c.value(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.value(slot),
+            FieldComparatorEnum::TermOrdVal(c) => c.value(slot),
+            FieldComparatorEnum::LatLonDistance(c) => c.value(slot),
+            FieldComparatorEnum::Expression(c) => c.value(slot),
         }
     }
 
@@ -172,6 +196,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.set_bottom(slot),
             FieldComparatorEnum::NumericDV(c) => c.set_bottom(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.set_bottom(slot),
+            FieldComparatorEnum::TermOrdVal(c) => c.set_bottom(slot),
+            FieldComparatorEnum::LatLonDistance(c) => c.set_bottom(slot),
+            FieldComparatorEnum::Expression(c) => c.set_bottom(slot),
         }
     }
 
@@ -181,6 +208,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare_bottom(value),
             FieldComparatorEnum::NumericDV(c) => c.compare_bottom(value),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare_bottom(value),
+            FieldComparatorEnum::TermOrdVal(c) => c.compare_bottom(value),
+            FieldComparatorEnum::LatLonDistance(c) => c.compare_bottom(value),
+            FieldComparatorEnum::Expression(c) => c.compare_bottom(value),
         }
     }
 
@@ -190,6 +220,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.copy(slot, value),
             FieldComparatorEnum::NumericDV(c) => c.copy(slot, value),
             FieldComparatorEnum::SortedNumericDV(c) => c.copy(slot, value),
+            FieldComparatorEnum::TermOrdVal(c) => c.copy(slot, value),
+            FieldComparatorEnum::LatLonDistance(c) => c.copy(slot, value),
+            FieldComparatorEnum::Expression(c) => c.copy(slot, value),
         }
     }
 
@@ -202,6 +235,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::NumericDV(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::TermOrdVal(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::LatLonDistance(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::Expression(c) => c.get_information_from_reader(reader),
         }
     }
 
@@ -211,6 +247,9 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_type(),
             FieldComparatorEnum::NumericDV(c) => c.get_type(),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_type(),
+            FieldComparatorEnum::TermOrdVal(c) => c.get_type(),
+            FieldComparatorEnum::LatLonDistance(c) => c.get_type(),
+            FieldComparatorEnum::Expression(c) => c.get_type(),
         }
     }
 }
@@ -222,6 +261,9 @@ impl fmt::Display for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::NumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::SortedNumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::TermOrdVal(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::LatLonDistance(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::Expression(c) => write!(f, "FieldComparatorEnum({})", c),
         }
     }
 }
@@ -331,7 +373,7 @@ impl FieldComparator for DocComparator {
 
     fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
         debug_assert!(value.is_doc());
-        Ok(self.bottom.cmp(&value.doc()))
+        Ok(self.bottom.cmp(&(value.doc() + self.doc_base)))
     }
 
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
@@ -432,7 +474,7 @@ impl<T: DocValuesSource> FieldComparator for NumericDocValuesComparator<T> {
         let doc_id = value.doc();
         let value = self.get_doc_value(doc_id)?;
         if let Some(ref mut bits) = self.docs_with_fields {
-            if value.is_zero() && bits.get(doc_id as usize)? {
+            if value.is_zero() && !bits.get(doc_id as usize)? {
                 return Ok(self.bottom.cmp(self.missing_value.as_ref().unwrap()));
             }
         }
@@ -444,7 +486,7 @@ impl<T: DocValuesSource> FieldComparator for NumericDocValuesComparator<T> {
         let doc_id = value.doc();
         let mut value = self.get_doc_value(doc_id)?;
         if let Some(ref mut bits) = self.docs_with_fields {
-            if value.is_zero() && bits.get(doc_id as usize)? {
+            if value.is_zero() && !bits.get(doc_id as usize)? {
                 value = self.missing_value.as_ref().unwrap().clone();
             }
         }
@@ -485,6 +527,353 @@ impl<T: DocValuesSource> fmt::Display for NumericDocValuesComparator<T> {
     }
 }
 
+/// Sorts by the ordinals of a `SortedDocValues` field.
+///
+/// Hits of the current segment are compared by their ordinals, while hits collected from
+/// previous segments are compared by their term bytes. Documents without a value sort
+/// first unless `missing_last` is set, and report an empty binary value.
+pub struct TermOrdValComparator {
+    field: String,
+    missing_last: bool,
+    // the ordinal used for documents without a value
+    missing_ord: i32,
+    ords: Vec<i32>,
+    values: Vec<Option<Vec<u8>>>,
+    reader_gen: Vec<usize>,
+    current_reader_gen: usize,
+    current_doc_values: Option<Box<dyn SortedDocValues>>,
+    bottom_slot: Option<usize>,
+    // whether `bottom_ord` was resolved against the current segment
+    bottom_resolved: bool,
+    bottom_ord: i32,
+    // whether the bottom value exists in the current segment, thus `bottom_ord` is exact
+    bottom_same_reader: bool,
+}
+
+impl TermOrdValComparator {
+    pub fn new(num_hits: usize, field: String, missing_last: bool) -> TermOrdValComparator {
+        let missing_ord = if missing_last { i32::max_value() } else { -1 };
+        TermOrdValComparator {
+            field,
+            missing_last,
+            missing_ord,
+            ords: vec![missing_ord; num_hits],
+            values: vec![None; num_hits],
+            reader_gen: vec![0; num_hits],
+            current_reader_gen: 0,
+            current_doc_values: None,
+            bottom_slot: None,
+            bottom_resolved: false,
+            bottom_ord: -1,
+            bottom_same_reader: false,
+        }
+    }
+
+    fn doc_values(&mut self) -> &mut dyn SortedDocValues {
+        debug_assert!(self.current_doc_values.is_some());
+        self.current_doc_values.as_mut().unwrap().as_mut()
+    }
+
+    fn doc_ord(&mut self, doc: DocId) -> Result<i32> {
+        let ord = self.doc_values().get_ord(doc)?;
+        Ok(if ord < 0 { self.missing_ord } else { ord })
+    }
+
+    fn resolve_bottom(&mut self, slot: usize) -> Result<()> {
+        if self.reader_gen[slot] == self.current_reader_gen {
+            self.bottom_ord = self.ords[slot];
+            self.bottom_same_reader = true;
+        } else if let Some(value) = self.values[slot].take() {
+            let ord = self.doc_values().lookup_term(&value)?;
+            if ord >= 0 {
+                self.bottom_ord = ord;
+                self.bottom_same_reader = true;
+                self.ords[slot] = ord;
+                self.reader_gen[slot] = self.current_reader_gen;
+            } else {
+                // the bottom value sorts between the ordinals `-ord - 2` and `-ord - 1`
+                self.bottom_ord = -ord - 2;
+                self.bottom_same_reader = false;
+            }
+            self.values[slot] = Some(value);
+        } else {
+            self.bottom_ord = self.missing_ord;
+            self.bottom_same_reader = true;
+        }
+        self.bottom_resolved = true;
+        Ok(())
+    }
+}
+
+impl FieldComparator for TermOrdValComparator {
+    fn compare(&self, slot1: usize, slot2: usize) -> Ordering {
+        if self.reader_gen[slot1] == self.reader_gen[slot2] {
+            return self.ords[slot1].cmp(&self.ords[slot2]);
+        }
+        match (&self.values[slot1], &self.values[slot2]) {
+            (Some(v1), Some(v2)) => v1.cmp(v2),
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if self.missing_last => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) if self.missing_last => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+        }
+    }
+
+    fn value(&self, slot: usize) -> VariantValue {
+        VariantValue::Binary(self.values[slot].clone().unwrap_or_default())
+    }
+
+    fn set_bottom(&mut self, slot: usize) {
+        self.bottom_slot = Some(slot);
+        self.bottom_resolved = false;
+    }
+
+    fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        debug_assert!(self.bottom_slot.is_some());
+        if !self.bottom_resolved {
+            let slot = self.bottom_slot.unwrap();
+            self.resolve_bottom(slot)?;
+        }
+        let doc_ord = self.doc_ord(value.doc())?;
+        if self.bottom_same_reader {
+            Ok(self.bottom_ord.cmp(&doc_ord))
+        } else if self.bottom_ord >= doc_ord {
+            Ok(Ordering::Greater)
+        } else {
+            Ok(Ordering::Less)
+        }
+    }
+
+    fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
+        debug_assert!(value.is_doc());
+        let ord = self.doc_ord(value.doc())?;
+        self.values[slot] = if ord == self.missing_ord {
+            None
+        } else {
+            Some(self.doc_values().lookup_ord(ord)?)
+        };
+        self.ords[slot] = ord;
+        self.reader_gen[slot] = self.current_reader_gen;
+        if self.bottom_slot == Some(slot) {
+            self.bottom_resolved = false;
+        }
+        Ok(())
+    }
+
+    fn get_information_from_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.current_doc_values = Some(reader.reader.get_sorted_doc_values(&self.field)?);
+        self.current_reader_gen += 1;
+        self.bottom_resolved = false;
+        Ok(())
+    }
+
+    fn get_type(&self) -> SortFieldType {
+        SortFieldType::String
+    }
+}
+
+impl fmt::Display for TermOrdValComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TermOrdValComparator(field: {}, bottom_ord: {})",
+            self.field, self.bottom_ord
+        )
+    }
+}
+
+/// Sorts by the haversine distance in meters of the closest
+/// `LatLonDocValuesField` value of the docs to a location, the docs without
+/// value are infinitely far.
+pub struct LatLonDistanceComparator {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+    values: Vec<f64>,
+    bottom: f64,
+    current_doc_values: Option<Box<dyn SortedNumericDocValues>>,
+}
+
+impl LatLonDistanceComparator {
+    pub fn new(
+        num_hits: usize,
+        field: String,
+        latitude: f64,
+        longitude: f64,
+    ) -> LatLonDistanceComparator {
+        LatLonDistanceComparator {
+            field,
+            latitude,
+            longitude,
+            values: vec![0.0; num_hits],
+            bottom: 0.0,
+            current_doc_values: None,
+        }
+    }
+
+    fn distance(&mut self, doc: DocId) -> Result<f64> {
+        let mut min_distance = ::std::f64::INFINITY;
+        if let Some(ref mut doc_values) = self.current_doc_values {
+            doc_values.set_document(doc)?;
+            for i in 0..doc_values.count() {
+                let value = doc_values.value_at(i)?;
+                let distance = haversin_meters(
+                    self.latitude,
+                    self.longitude,
+                    LatLonDocValuesField::decode_latitude(value),
+                    LatLonDocValuesField::decode_longitude(value),
+                );
+                min_distance = min_distance.min(distance);
+            }
+        }
+        Ok(min_distance)
+    }
+}
+
+impl FieldComparator for LatLonDistanceComparator {
+    fn compare(&self, slot1: usize, slot2: usize) -> Ordering {
+        self.values[slot1]
+            .partial_cmp(&self.values[slot2])
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn value(&self, slot: usize) -> VariantValue {
+        VariantValue::Double(self.values[slot])
+    }
+
+    fn set_bottom(&mut self, slot: usize) {
+        self.bottom = self.values[slot];
+    }
+
+    fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        let distance = self.distance(value.doc())?;
+        Ok(self
+            .bottom
+            .partial_cmp(&distance)
+            .unwrap_or(Ordering::Equal))
+    }
+
+    fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
+        debug_assert!(value.is_doc());
+        self.values[slot] = self.distance(value.doc())?;
+        Ok(())
+    }
+
+    fn get_information_from_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.current_doc_values = match reader.reader.field_info(&self.field) {
+            Some(fi) if fi.doc_values_type == DocValuesType::SortedNumeric => {
+                Some(reader.reader.get_sorted_numeric_doc_values(&self.field)?)
+            }
+            _ => None,
+        };
+        Ok(())
+    }
+
+    fn get_type(&self) -> SortFieldType {
+        SortFieldType::Custom
+    }
+}
+
+impl fmt::Display for LatLonDistanceComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LatLonDistanceComparator(field: {}, latitude: {}, longitude: {}, bottom: {})",
+            self.field, self.latitude, self.longitude, self.bottom
+        )
+    }
+}
+
+/// Sorts by the results of an `Expression` which doesn't use the score.
+pub struct ExpressionComparator {
+    source: Arc<ExpressionValuesSource>,
+    values: Vec<f64>,
+    bottom: f64,
+    current_values: Option<Box<dyn DoubleValues>>,
+}
+
+impl ExpressionComparator {
+    pub fn new(num_hits: usize, source: Arc<ExpressionValuesSource>) -> ExpressionComparator {
+        ExpressionComparator {
+            source,
+            values: vec![0.0; num_hits],
+            bottom: 0.0,
+            current_values: None,
+        }
+    }
+
+    fn value_of(&mut self, doc: DocId) -> Result<f64> {
+        match self.current_values {
+            Some(ref mut values) => Ok(values.get(doc, 0.0)?.unwrap_or(0.0)),
+            None => bail!(IllegalState(
+                "the comparator has not been set a reader".into()
+            )),
+        }
+    }
+}
+
+impl FieldComparator for ExpressionComparator {
+    fn compare(&self, slot1: usize, slot2: usize) -> Ordering {
+        self.values[slot1]
+            .partial_cmp(&self.values[slot2])
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn value(&self, slot: usize) -> VariantValue {
+        VariantValue::Double(self.values[slot])
+    }
+
+    fn set_bottom(&mut self, slot: usize) {
+        self.bottom = self.values[slot];
+    }
+
+    fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        let v = self.value_of(value.doc())?;
+        Ok(self.bottom.partial_cmp(&v).unwrap_or(Ordering::Equal))
+    }
+
+    fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
+        debug_assert!(value.is_doc());
+        self.values[slot] = self.value_of(value.doc())?;
+        Ok(())
+    }
+
+    fn get_information_from_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.current_values = Some(DoubleValuesSource::<C>::get_values(
+            self.source.as_ref(),
+            reader,
+        )?);
+        Ok(())
+    }
+
+    fn get_type(&self) -> SortFieldType {
+        SortFieldType::Custom
+    }
+}
+
+impl fmt::Display for ExpressionComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ExpressionComparator(source: {}, bottom: {})",
+            self.source, self.bottom
+        )
+    }
+}
+
 pub trait DocValuesSource {
     fn numeric_doc_values<C: Codec>(
         &self,
@@ -522,6 +911,8 @@ impl DocValuesSource for DefaultDocValuesSource {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::codec::doc_values::lucene54::DocValuesTermIterator;
+    use core::codec::doc_values::BinaryDocValues;
     use core::index::reader::IndexReader;
     use core::index::tests::*;
 
@@ -577,4 +968,121 @@ mod tests {
             Ordering::Greater
         );
     }
+
+    struct MockSortedDocValues {
+        terms: Vec<&'static str>,
+        ords: Vec<i32>,
+    }
+
+    impl BinaryDocValues for MockSortedDocValues {
+        fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+            let ord = self.get_ord(doc_id)?;
+            self.lookup_ord(ord)
+        }
+    }
+
+    impl SortedDocValues for MockSortedDocValues {
+        fn get_ord(&mut self, doc_id: DocId) -> Result<i32> {
+            Ok(self.ords[doc_id as usize])
+        }
+
+        fn lookup_ord(&mut self, ord: i32) -> Result<Vec<u8>> {
+            Ok(self.terms[ord as usize].as_bytes().to_vec())
+        }
+
+        fn value_count(&self) -> usize {
+            self.terms.len()
+        }
+
+        fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+            unimplemented!()
+        }
+    }
+
+    fn next_segment(comparator: &mut TermOrdValComparator, doc_values: MockSortedDocValues) {
+        comparator.current_doc_values = Some(Box::new(doc_values));
+        comparator.current_reader_gen += 1;
+        comparator.bottom_resolved = false;
+    }
+
+    #[test]
+    fn test_term_ord_val_comparator() {
+        let mut comparator = TermOrdValComparator::new(2, "field".into(), false);
+        next_segment(
+            &mut comparator,
+            MockSortedDocValues {
+                terms: vec!["b", "d"],
+                ords: vec![0, 1, -1],
+            },
+        );
+        comparator.copy(0, ComparatorValue::Doc(0)).unwrap();
+        comparator.copy(1, ComparatorValue::Doc(2)).unwrap();
+
+        // documents without a value sort first
+        assert_eq!(comparator.compare(0, 1), Ordering::Greater);
+        assert_eq!(comparator.value(1), VariantValue::Binary(vec![]));
+
+        comparator.set_bottom(0);
+        assert_eq!(
+            comparator.compare_bottom(ComparatorValue::Doc(1)).unwrap(),
+            Ordering::Less
+        );
+
+        // the bottom value "b" doesn't exist in the next segment
+        next_segment(
+            &mut comparator,
+            MockSortedDocValues {
+                terms: vec!["a", "c"],
+                ords: vec![0, 1],
+            },
+        );
+        assert_eq!(
+            comparator.compare_bottom(ComparatorValue::Doc(0)).unwrap(),
+            Ordering::Greater
+        );
+        assert_eq!(
+            comparator.compare_bottom(ComparatorValue::Doc(1)).unwrap(),
+            Ordering::Less
+        );
+
+        comparator.copy(0, ComparatorValue::Doc(0)).unwrap();
+        assert_eq!(comparator.value(0), VariantValue::Binary(b"a".to_vec()));
+        assert_eq!(comparator.compare(0, 1), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_term_ord_val_comparator_missing_last() {
+        let mut comparator = TermOrdValComparator::new(2, "field".into(), true);
+        next_segment(
+            &mut comparator,
+            MockSortedDocValues {
+                terms: vec!["b"],
+                ords: vec![-1, 0, 0],
+            },
+        );
+        comparator.copy(0, ComparatorValue::Doc(0)).unwrap();
+        comparator.copy(1, ComparatorValue::Doc(1)).unwrap();
+        assert_eq!(comparator.compare(0, 1), Ordering::Greater);
+
+        // the missing bottom sorts after any value of the next segment
+        comparator.set_bottom(0);
+        next_segment(
+            &mut comparator,
+            MockSortedDocValues {
+                terms: vec!["z"],
+                ords: vec![0, -1],
+            },
+        );
+        assert_eq!(
+            comparator.compare_bottom(ComparatorValue::Doc(0)).unwrap(),
+            Ordering::Greater
+        );
+        assert_eq!(
+            comparator.compare_bottom(ComparatorValue::Doc(1)).unwrap(),
+            Ordering::Equal
+        );
+
+        comparator.copy(1, ComparatorValue::Doc(0)).unwrap();
+        assert_eq!(comparator.compare(0, 1), Ordering::Greater);
+    }
 }
@@ -27,6 +27,10 @@ mod search_group;
 
 pub use self::search_group::*;
 
+use core::util::VariantValue;
+
+use std::cmp::Ordering;
+
 /// Encapsulates sort criteria for returned hits.
 ///
 /// The fields used to determine sort order must be carefully chosen.
@@ -111,6 +115,42 @@ impl Sort {
     pub fn needs_scores(&self) -> bool {
         self.fields.iter().any(|f| f.needs_scores())
     }
+
+    /// Compare the sort values of two hits, as returned by `FieldComparator::value`
+    /// for each field of this sort. `Ordering::Less` means the first hit sorts first.
+    pub fn compare_values(&self, values1: &[VariantValue], values2: &[VariantValue]) -> Ordering {
+        debug_assert_eq!(values1.len(), self.fields.len());
+        debug_assert_eq!(values2.len(), self.fields.len());
+        for (field, (v1, v2)) in self.fields.iter().zip(values1.iter().zip(values2)) {
+            let mut ord = match (v1, v2) {
+                // documents without a string value report an empty one
+                (VariantValue::Binary(b1), VariantValue::Binary(b2))
+                    if field.missing_string() == Some(SortFieldMissingValue::StringLast)
+                        && b1.is_empty() != b2.is_empty() =>
+                {
+                    b1.is_empty().cmp(&b2.is_empty())
+                }
+                _ => v1.cmp(v2),
+            };
+            if field.field_type() == SortFieldType::Score {
+                // higher scores sort first
+                ord = ord.reverse();
+            }
+            if field.is_reverse() {
+                ord = ord.reverse();
+            }
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Compare two hits by their sort values, then by doc id.
+    pub fn compare_field_docs(&self, doc1: &FieldDoc, doc2: &FieldDoc) -> Ordering {
+        self.compare_values(&doc1.fields, &doc2.fields)
+            .then_with(|| doc1.doc.cmp(&doc2.doc))
+    }
 }
 
 #[cfg(test)]
@@ -144,4 +184,58 @@ mod tests {
         let doc_field = &fields[1];
         assert_eq!(doc_field.field(), &String::from("field_two"));
     }
+
+    #[test]
+    fn test_compare_field_docs() {
+        let sort = Sort::new(vec![
+            SortField::new_score(),
+            SortField::Simple(SimpleSortField::new(
+                String::from("id"),
+                SortFieldType::Long,
+                true,
+            )),
+        ]);
+        let doc1 = FieldDoc::new(
+            1,
+            2.0,
+            vec![VariantValue::Float(2.0), VariantValue::Long(5)],
+        );
+        let doc2 = FieldDoc::new(
+            2,
+            1.0,
+            vec![VariantValue::Float(1.0), VariantValue::Long(9)],
+        );
+        let doc3 = FieldDoc::new(
+            3,
+            1.0,
+            vec![VariantValue::Float(1.0), VariantValue::Long(9)],
+        );
+
+        // higher scores first, then the reversed long values, then doc ids
+        assert_eq!(sort.compare_field_docs(&doc1, &doc2), Ordering::Less);
+        assert_eq!(sort.compare_field_docs(&doc3, &doc2), Ordering::Greater);
+        let doc4 = FieldDoc::new(
+            4,
+            1.0,
+            vec![VariantValue::Float(1.0), VariantValue::Long(3)],
+        );
+        assert_eq!(sort.compare_field_docs(&doc2, &doc4), Ordering::Less);
+    }
+
+    #[test]
+    fn test_compare_missing_strings_last() {
+        let mut field = SortField::Simple(SimpleSortField::new(
+            "name".into(),
+            SortFieldType::String,
+            false,
+        ));
+        let missing = vec![VariantValue::Binary(vec![])];
+        let value = vec![VariantValue::Binary(b"a".to_vec())];
+        let sort = Sort::new(vec![field.clone()]);
+        assert_eq!(sort.compare_values(&missing, &value), Ordering::Less);
+
+        field.set_missing_last();
+        let sort = Sort::new(vec![field]);
+        assert_eq!(sort.compare_values(&missing, &value), Ordering::Greater);
+    }
 }
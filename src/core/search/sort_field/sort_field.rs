@@ -13,10 +13,13 @@
 
 use core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
 use core::codec::Codec;
+use core::expressions::ExpressionValuesSource;
+use core::geo::{check_latitude, check_longitude};
 use core::index::reader::SearchLeafReader;
 use core::search::sort_field::{
-    DefaultDocValuesSource, DocComparator, DocValuesSource, FieldComparatorEnum,
-    NumericDocValuesComparator, RelevanceComparator,
+    DefaultDocValuesSource, DocComparator, DocValuesSource, ExpressionComparator,
+    FieldComparatorEnum, LatLonDistanceComparator, NumericDocValuesComparator, RelevanceComparator,
+    TermOrdValComparator,
 };
 use core::util::{sortable_double_bits, sortable_float_bits};
 use core::util::{BitsMut, DocId, VariantValue};
@@ -24,6 +27,9 @@ use core::util::{BitsMut, DocId, VariantValue};
 use error::ErrorKind::IllegalArgument;
 use error::Result;
 
+use std::fmt;
+use std::sync::Arc;
+
 #[derive(PartialEq, Debug, Clone, Copy, Eq)]
 pub enum SortFieldType {
     String,
@@ -50,6 +56,8 @@ pub enum SortedSetSelectorType {
 pub enum SortedNumericSelectorType {
     Min,
     Max,
+    /// Sum all the values of the document
+    Sum,
 }
 
 #[derive(PartialEq, Debug, Clone, Copy, Eq)]
@@ -63,6 +71,8 @@ pub enum SortField {
     Simple(SimpleSortField),
     SortedNumeric(SortedNumericSortField),
     // SortedSet(SortedSetSortField),
+    LatLonDistance(LatLonDistanceSortField),
+    Expression(ExpressionSortField),
 }
 
 impl SortField {
@@ -74,6 +84,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => &s.field,
             SortField::SortedNumeric(s) => &s.raw_field.field,
+            SortField::LatLonDistance(s) => &s.field,
+            SortField::Expression(s) => s.source.expression().source_text(),
         }
     }
 
@@ -81,6 +93,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.field_type,
             SortField::SortedNumeric(s) => s.raw_field.field_type,
+            SortField::LatLonDistance(_) | SortField::Expression(_) => SortFieldType::Custom,
         }
     }
 
@@ -88,6 +101,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.is_reverse,
             SortField::SortedNumeric(s) => s.raw_field.is_reverse,
+            SortField::LatLonDistance(_) => false,
+            SortField::Expression(s) => s.is_reverse,
         }
     }
 
@@ -95,6 +110,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.missing_value.as_ref(),
             SortField::SortedNumeric(s) => s.raw_field.missing_value.as_ref(),
+            SortField::LatLonDistance(_) | SortField::Expression(_) => None,
         }
     }
 
@@ -102,6 +118,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.needs_scores(),
             SortField::SortedNumeric(s) => s.raw_field.needs_scores(),
+            SortField::LatLonDistance(_) | SortField::Expression(_) => false,
         }
     }
 
@@ -113,9 +130,83 @@ impl SortField {
             SortField::SortedNumeric(s) => {
                 s.raw_field.missing_value = value;
             }
+            // the docs without a location are always the farthest
+            SortField::LatLonDistance(_) => {}
+            // every document has a value, the missing variables being 0
+            SortField::Expression(_) => {}
         }
     }
 
+    /// How documents without a value sort for a `SortFieldType::String` field,
+    /// `None` means they sort first.
+    pub fn missing_string(&self) -> Option<SortFieldMissingValue> {
+        match self {
+            SortField::Simple(s) => s.missing_string,
+            SortField::SortedNumeric(_)
+            | SortField::LatLonDistance(_)
+            | SortField::Expression(_) => None,
+        }
+    }
+
+    /// Sort the documents without a value for this field before all the others,
+    /// whether the sort is reversed or not.
+    pub fn set_missing_first(&mut self) {
+        self.set_missing_position(true);
+    }
+
+    /// Sort the documents without a value for this field after all the others,
+    /// whether the sort is reversed or not.
+    pub fn set_missing_last(&mut self) {
+        self.set_missing_position(false);
+    }
+
+    fn set_missing_position(&mut self, first: bool) {
+        // whether the missing value must be lower than all the others in natural order
+        let lowest = first != self.is_reverse();
+        let value_type = match self {
+            SortField::Simple(s) => s.field_type,
+            SortField::SortedNumeric(s) => s.real_type,
+            SortField::LatLonDistance(_) | SortField::Expression(_) => SortFieldType::Custom,
+        };
+        let missing_value = match value_type {
+            SortFieldType::Int => VariantValue::Int(if lowest {
+                i32::min_value()
+            } else {
+                i32::max_value()
+            }),
+            SortFieldType::Long => VariantValue::Long(if lowest {
+                i64::min_value()
+            } else {
+                i64::max_value()
+            }),
+            SortFieldType::Float => VariantValue::Float(if lowest {
+                ::std::f32::NEG_INFINITY
+            } else {
+                ::std::f32::INFINITY
+            }),
+            SortFieldType::Double => VariantValue::Double(if lowest {
+                ::std::f64::NEG_INFINITY
+            } else {
+                ::std::f64::INFINITY
+            }),
+            SortFieldType::String => {
+                if let SortField::Simple(s) = self {
+                    s.missing_string = Some(if lowest {
+                        SortFieldMissingValue::StringFirst
+                    } else {
+                        SortFieldMissingValue::StringLast
+                    });
+                }
+                return;
+            }
+            // every document has a score and a doc id
+            SortFieldType::Score | SortFieldType::Doc | SortFieldType::Custom => {
+                return;
+            }
+        };
+        self.set_missing_value(Some(missing_value));
+    }
+
     pub fn get_comparator(
         &self,
         num_hits: usize,
@@ -124,6 +215,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.get_comparator(num_hits, missing_value),
             SortField::SortedNumeric(s) => s.get_comparator(num_hits, missing_value),
+            SortField::LatLonDistance(s) => s.get_comparator(num_hits),
+            SortField::Expression(s) => s.get_comparator(num_hits),
         }
     }
 }
@@ -134,6 +227,7 @@ pub struct SimpleSortField {
     field_type: SortFieldType,
     is_reverse: bool,
     missing_value: Option<VariantValue>,
+    missing_string: Option<SortFieldMissingValue>,
 }
 
 impl SimpleSortField {
@@ -143,6 +237,7 @@ impl SimpleSortField {
             field_type,
             is_reverse,
             missing_value: None,
+            missing_string: None,
         }
     }
 
@@ -152,6 +247,7 @@ impl SimpleSortField {
             field_type: SortFieldType::Score,
             is_reverse: false,
             missing_value: None,
+            missing_string: None,
         }
     }
 
@@ -179,9 +275,11 @@ impl SimpleSortField {
         match self.field_type {
             SortFieldType::Score => FieldComparatorEnum::Score(RelevanceComparator::new(num_hits)),
             SortFieldType::Doc => FieldComparatorEnum::Doc(DocComparator::new(num_hits)),
-            SortFieldType::String => {
-                unimplemented!();
-            }
+            SortFieldType::String => FieldComparatorEnum::TermOrdVal(TermOrdValComparator::new(
+                num_hits,
+                self.field.clone(),
+                self.missing_string == Some(SortFieldMissingValue::StringLast),
+            )),
             _ => {
                 // debug_assert!(missing_value.is_some());
 
@@ -270,6 +368,103 @@ impl SortedNumericSortField {
     }
 }
 
+/// SortField for the distance of the `LatLonDocValuesField` values of a field
+/// to a location.
+///
+/// The hits are sorted by ascending haversine distance in meters, a document
+/// with several values is sorted by the closest one and the documents without
+/// value are sorted last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatLonDistanceSortField {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+// the location is checked, thus never NaN
+impl Eq for LatLonDistanceSortField {}
+
+impl LatLonDistanceSortField {
+    pub fn new(field: String, latitude: f64, longitude: f64) -> Result<Self> {
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        Ok(LatLonDistanceSortField {
+            field,
+            latitude,
+            longitude,
+        })
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    pub fn get_comparator(&self, num_hits: usize) -> FieldComparatorEnum {
+        FieldComparatorEnum::LatLonDistance(LatLonDistanceComparator::new(
+            num_hits,
+            self.field.clone(),
+            self.latitude,
+            self.longitude,
+        ))
+    }
+}
+
+/// SortField for the results of an `Expression`, see `Expression::get_sort_field`.
+#[derive(Clone)]
+pub struct ExpressionSortField {
+    source: Arc<ExpressionValuesSource>,
+    is_reverse: bool,
+}
+
+impl ExpressionSortField {
+    pub fn new(source: ExpressionValuesSource, is_reverse: bool) -> Result<Self> {
+        if source.needs_scores() {
+            bail!(IllegalArgument(format!(
+                "expression '{}' uses the score, it can't be used for sorting",
+                source.expression()
+            )));
+        }
+        Ok(ExpressionSortField {
+            source: Arc::new(source),
+            is_reverse,
+        })
+    }
+
+    pub fn source(&self) -> &ExpressionValuesSource {
+        &self.source
+    }
+
+    pub fn get_comparator(&self, num_hits: usize) -> FieldComparatorEnum {
+        FieldComparatorEnum::Expression(ExpressionComparator::new(
+            num_hits,
+            Arc::clone(&self.source),
+        ))
+    }
+}
+
+// the sources aren't comparable, the expressions are compared by their text
+impl PartialEq for ExpressionSortField {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_reverse == other.is_reverse
+            && self.source.expression().source_text() == other.source.expression().source_text()
+    }
+}
+
+impl Eq for ExpressionSortField {}
+
+impl fmt::Debug for ExpressionSortField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExpressionSortField")
+            .field("expression", &self.source.expression().source_text())
+            .field("is_reverse", &self.is_reverse)
+            .finish()
+    }
+}
+
 pub struct SortedWrapperDocValuesSource {
     selector: SortedNumericSelectorType,
     field_type: SortFieldType,
@@ -327,10 +522,7 @@ impl SortedNumericSelector {
                 "numeric_type must be a numeric type".into()
             ));
         }
-        let view = match selector {
-            SortedNumericSelectorType::Min => SortedNumAsNumDocValues::min(sorted_numeric),
-            SortedNumericSelectorType::Max => SortedNumAsNumDocValues::max(sorted_numeric),
-        };
+        let view = SortedNumAsNumDocValues::new(sorted_numeric, selector, numeric_type);
         let res: Box<dyn NumericDocValues> = match numeric_type {
             SortFieldType::Float => Box::new(SortableFloatNumericDocValues::new(view)),
             SortFieldType::Double => Box::new(SortableDoubleNumericDocValues::new(view)),
@@ -386,21 +578,50 @@ impl NumericDocValues for SortableDoubleNumericDocValues {
 
 struct SortedNumAsNumDocValues {
     doc_values: Box<dyn SortedNumericDocValues>,
-    index_fn: fn(usize) -> usize,
+    selector: SortedNumericSelectorType,
+    numeric_type: SortFieldType,
 }
 
 impl SortedNumAsNumDocValues {
-    fn min(doc_values: Box<dyn SortedNumericDocValues>) -> Self {
+    fn new(
+        doc_values: Box<dyn SortedNumericDocValues>,
+        selector: SortedNumericSelectorType,
+        numeric_type: SortFieldType,
+    ) -> Self {
         Self {
             doc_values,
-            index_fn: |_| 0,
+            selector,
+            numeric_type,
         }
     }
 
-    fn max(doc_values: Box<dyn SortedNumericDocValues>) -> Self {
-        Self {
-            doc_values,
-            index_fn: |cnt| cnt - 1,
+    /// Sum the values of the current document, floating point values are decoded
+    /// from and encoded back to their sortable bits.
+    fn sum(&mut self, count: usize) -> Result<i64> {
+        match self.numeric_type {
+            SortFieldType::Float => {
+                let mut sum = 0f32;
+                for i in 0..count {
+                    let bits = sortable_float_bits(self.doc_values.value_at(i)? as i32);
+                    sum += f32::from_bits(bits as u32);
+                }
+                Ok(i64::from(sortable_float_bits(sum.to_bits() as i32)))
+            }
+            SortFieldType::Double => {
+                let mut sum = 0f64;
+                for i in 0..count {
+                    let bits = sortable_double_bits(self.doc_values.value_at(i)?);
+                    sum += f64::from_bits(bits as u64);
+                }
+                Ok(sortable_double_bits(sum.to_bits() as i64))
+            }
+            _ => {
+                let mut sum = 0i64;
+                for i in 0..count {
+                    sum = sum.wrapping_add(self.doc_values.value_at(i)?);
+                }
+                Ok(sum)
+            }
         }
     }
 }
@@ -415,9 +636,12 @@ impl NumericDocValues for SortedNumAsNumDocValues {
         self.doc_values.set_document(doc_id)?;
         let count = self.doc_values.count();
         if count == 0 {
-            Ok(0)
-        } else {
-            self.doc_values.value_at((self.index_fn)(count))
+            return Ok(0);
+        }
+        match self.selector {
+            SortedNumericSelectorType::Min => self.doc_values.value_at(0),
+            SortedNumericSelectorType::Max => self.doc_values.value_at(count - 1),
+            SortedNumericSelectorType::Sum => self.sum(count),
         }
     }
 }
@@ -470,4 +694,120 @@ mod tests {
         assert_eq!(SortFieldType::Doc, sort_field.field_type());
         assert_eq!(true, sort_field.is_reverse());
     }
+
+    #[test]
+    fn test_missing_first_and_last() {
+        let mut sort_field = SortField::Simple(SimpleSortField::new(
+            String::from("test"),
+            SortFieldType::Int,
+            false,
+        ));
+        sort_field.set_missing_last();
+        assert_eq!(
+            sort_field.missing_value(),
+            Some(&VariantValue::Int(i32::max_value()))
+        );
+        sort_field.set_missing_first();
+        assert_eq!(
+            sort_field.missing_value(),
+            Some(&VariantValue::Int(i32::min_value()))
+        );
+
+        // a reversed sort flips the natural order of the missing value
+        let mut sort_field =
+            SortField::SortedNumeric(SortedNumericSortField::with_default_selector(
+                String::from("test"),
+                SortFieldType::Long,
+                true,
+            ));
+        sort_field.set_missing_last();
+        assert_eq!(
+            sort_field.missing_value(),
+            Some(&VariantValue::Long(i64::min_value()))
+        );
+
+        let mut sort_field = SortField::Simple(SimpleSortField::new(
+            String::from("test"),
+            SortFieldType::String,
+            true,
+        ));
+        assert_eq!(sort_field.missing_string(), None);
+        sort_field.set_missing_first();
+        assert_eq!(
+            sort_field.missing_string(),
+            Some(SortFieldMissingValue::StringLast)
+        );
+        assert!(sort_field.missing_value().is_none());
+    }
+
+    struct MockSortedNumericDocValues {
+        values: Vec<Vec<i64>>,
+        doc: usize,
+    }
+
+    impl SortedNumericDocValues for MockSortedNumericDocValues {
+        fn set_document(&mut self, doc: DocId) -> Result<()> {
+            self.doc = doc as usize;
+            Ok(())
+        }
+
+        fn value_at(&mut self, index: usize) -> Result<i64> {
+            Ok(self.values[self.doc][index])
+        }
+
+        fn count(&self) -> usize {
+            self.values[self.doc].len()
+        }
+    }
+
+    fn selected_values(
+        values: Vec<Vec<i64>>,
+        selector: SortedNumericSelectorType,
+        numeric_type: SortFieldType,
+    ) -> Vec<i64> {
+        let doc_values = MockSortedNumericDocValues {
+            values: values.clone(),
+            doc: 0,
+        };
+        let mut doc_values =
+            SortedNumericSelector::wrap(Box::new(doc_values), selector, numeric_type).unwrap();
+        (0..values.len())
+            .map(|doc| doc_values.get_mut(doc as DocId).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sorted_numeric_selector() {
+        let values = vec![vec![1, 5, 7], vec![], vec![-3, 2]];
+        assert_eq!(
+            selected_values(
+                values.clone(),
+                SortedNumericSelectorType::Min,
+                SortFieldType::Long
+            ),
+            vec![1, 0, -3]
+        );
+        assert_eq!(
+            selected_values(
+                values.clone(),
+                SortedNumericSelectorType::Max,
+                SortFieldType::Long
+            ),
+            vec![7, 0, 2]
+        );
+        assert_eq!(
+            selected_values(values, SortedNumericSelectorType::Sum, SortFieldType::Long),
+            vec![13, 0, -1]
+        );
+
+        // doubles are stored as sortable bits, the selector returns their raw bits
+        let encode = |v: f64| sortable_double_bits(v.to_bits() as i64);
+        let values = vec![vec![encode(-1.5), encode(4.0)]];
+        let sum = selected_values(
+            values,
+            SortedNumericSelectorType::Sum,
+            SortFieldType::Double,
+        );
+        assert!((f64::from_bits(sum[0] as u64) - 2.5).abs() < ::std::f64::EPSILON);
+    }
 }
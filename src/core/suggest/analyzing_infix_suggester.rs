@@ -0,0 +1,440 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use core::analysis::Analyzer;
+use core::codec::Codec;
+use core::doc::{
+    Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField, Term, STORE_FIELD_TYPE,
+};
+use core::index::merge::{MergePolicy, MergeScheduler};
+use core::index::reader::{IndexReader, StandardDirectoryReader};
+use core::index::writer::{IndexWriter, IndexWriterConfig};
+use core::search::collector::TopFieldCollector;
+use core::search::query::{BooleanQuery, PrefixQuery, Query, TermQuery};
+use core::search::sort_field::{ScoreDocHit, SimpleSortField, Sort, SortField, SortFieldType};
+use core::search::{DefaultIndexSearcher, IndexSearcher};
+use core::store::directory::Directory;
+use core::suggest::{Input, Lookup, LookupResult};
+use core::util::VariantValue;
+
+use error::Result;
+
+/// The field the analyzed text of the suggestions is indexed in.
+pub const TEXT_FIELD_NAME: &str = "text";
+/// The field the whole text of the suggestions is indexed in, as a single
+/// term, to replace a suggestion with `update`.
+pub const EXACT_TEXT_FIELD_NAME: &str = "exacttext";
+
+const WEIGHT_FIELD_NAME: &str = "weight";
+const PAYLOAD_FIELD_NAME: &str = "payloads";
+
+const PRE_TAG: &str = "<b>";
+const POST_TAG: &str = "</b>";
+
+/// Suggests the entries containing the tokens of the key anywhere in their
+/// text, the last token of the key being a prefix unless the key ends with
+/// a separator, e.g. "christ" suggests "The Ghost of Christmas Past".
+///
+/// The suggestions are the documents of an auxiliary index, searched with a
+/// boolean query of the tokens and sorted by weight. The index is near real
+/// time: `add` and `update` change it at any time, the changes being
+/// visible to the lookups after `refresh`, and durable after `commit`.
+///
+/// The matched parts of the suggestions are highlighted with `<b>` tags in
+/// the `highlight_key` of the results.
+///
+/// ```ignore
+/// let dir = Arc::new(FSDirectory::with_path(&path)?);
+/// let config = Arc::new(IndexWriterConfig::default());
+/// let mut suggester =
+///     AnalyzingInfixSuggester::new(dir, config, Box::new(StandardAnalyzer::default()))?;
+/// suggester.build(&mut inputs.into_iter())?;
+/// let results = suggester.lookup("christ", 5)?;
+/// ```
+pub struct AnalyzingInfixSuggester<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    index_analyzer: Box<dyn Analyzer>,
+    query_analyzer: Option<Box<dyn Analyzer>>,
+    writer: IndexWriter<D, C, MS, MP>,
+    reader: RwLock<Option<Arc<StandardDirectoryReader<D, C, MS, MP>>>>,
+    all_terms_required: bool,
+    highlight: bool,
+}
+
+impl<D, C, MS, MP> AnalyzingInfixSuggester<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    /// Opens the suggester stored in `directory`, the suggestions already
+    /// indexed there being looked up right away.
+    pub fn new(
+        directory: Arc<D>,
+        config: Arc<IndexWriterConfig<C, MS, MP>>,
+        analyzer: Box<dyn Analyzer>,
+    ) -> Result<Self> {
+        let writer = IndexWriter::new(directory, config)?;
+        let suggester = AnalyzingInfixSuggester {
+            index_analyzer: analyzer,
+            query_analyzer: None,
+            writer,
+            reader: RwLock::new(None),
+            all_terms_required: true,
+            highlight: true,
+        };
+        if suggester.writer.num_docs() > 0 {
+            suggester.refresh()?;
+        }
+        Ok(suggester)
+    }
+
+    /// Sets a different analyzer for the keys than for the suggestions.
+    pub fn set_query_analyzer(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.query_analyzer = Some(analyzer);
+    }
+
+    /// If true (the default), the suggestions must contain all the tokens of
+    /// the key, otherwise any of them.
+    pub fn set_all_terms_required(&mut self, all_terms_required: bool) {
+        self.all_terms_required = all_terms_required;
+    }
+
+    /// If true (the default), the `highlight_key` of the results is set.
+    pub fn set_highlight(&mut self, highlight: bool) {
+        self.highlight = highlight;
+    }
+
+    fn query_analyzer(&self) -> &dyn Analyzer {
+        match self.query_analyzer {
+            Some(ref analyzer) => analyzer.as_ref(),
+            None => self.index_analyzer.as_ref(),
+        }
+    }
+
+    /// Adds a suggestion, visible to the lookups after the next `refresh`.
+    pub fn add(&self, text: &str, weight: i64, payload: Option<&[u8]>) -> Result<()> {
+        let doc = self.build_document(text, weight, payload)?;
+        self.writer.add_document(doc)?;
+        Ok(())
+    }
+
+    /// Replaces the suggestions whose text is exactly `text`, or adds it if
+    /// there are none, visible to the lookups after the next `refresh`.
+    pub fn update(&self, text: &str, weight: i64, payload: Option<&[u8]>) -> Result<()> {
+        let doc = self.build_document(text, weight, payload)?;
+        let term = Term::new(EXACT_TEXT_FIELD_NAME.into(), text.as_bytes().to_vec());
+        self.writer.update_document(doc, Some(term))?;
+        Ok(())
+    }
+
+    /// Makes the changes since the last refresh visible to the lookups.
+    pub fn refresh(&self) -> Result<()> {
+        let reader = self.writer.get_reader(true, false)?;
+        *self.reader.write()? = Some(Arc::new(reader));
+        Ok(())
+    }
+
+    /// Commits the changes to the directory.
+    pub fn commit(&self) -> Result<()> {
+        self.writer.commit()?;
+        Ok(())
+    }
+
+    fn build_document(
+        &self,
+        text: &str,
+        weight: i64,
+        payload: Option<&[u8]>,
+    ) -> Result<Vec<Box<dyn Fieldable>>> {
+        let mut text_field_type = FieldType::default();
+        text_field_type.stored = true;
+        text_field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+        let token_stream = self
+            .index_analyzer
+            .token_stream_from_str(TEXT_FIELD_NAME, text)?;
+
+        let mut exact_field_type = FieldType::default();
+        exact_field_type.tokenized = false;
+        exact_field_type.omit_norms = true;
+        exact_field_type.index_options = IndexOptions::Docs;
+
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(Field::new(
+                TEXT_FIELD_NAME.into(),
+                text_field_type,
+                Some(VariantValue::VString(text.to_string())),
+                Some(token_stream),
+            )),
+            Box::new(Field::new(
+                EXACT_TEXT_FIELD_NAME.into(),
+                exact_field_type,
+                Some(VariantValue::VString(text.to_string())),
+                None,
+            )),
+            Box::new(NumericDocValuesField::new(WEIGHT_FIELD_NAME, weight)),
+        ];
+        if let Some(payload) = payload {
+            doc.push(Box::new(Field::new_bytes(
+                PAYLOAD_FIELD_NAME.into(),
+                payload.to_vec(),
+                STORE_FIELD_TYPE,
+            )));
+        }
+        Ok(doc)
+    }
+
+    /// Returns at most `num` suggestions containing the tokens of `key`,
+    /// with the given options instead of the ones of the suggester.
+    pub fn lookup_with_options(
+        &self,
+        key: &str,
+        num: usize,
+        all_terms_required: bool,
+        highlight: bool,
+    ) -> Result<Vec<LookupResult>> {
+        let reader = match *self.reader.read()? {
+            Some(ref reader) => Arc::clone(reader),
+            None => return Ok(vec![]),
+        };
+        if num == 0 {
+            return Ok(vec![]);
+        }
+
+        let (matched_tokens, prefix_token) = analyze_key(self.query_analyzer(), key)?;
+        let mut clauses: Vec<Box<dyn Query<C>>> = matched_tokens
+            .iter()
+            .map(|token| -> Box<dyn Query<C>> {
+                Box::new(TermQuery::new(
+                    Term::new(TEXT_FIELD_NAME.into(), token.as_bytes().to_vec()),
+                    1.0,
+                    None,
+                ))
+            })
+            .collect();
+        if let Some(ref prefix) = prefix_token {
+            clauses.push(Box::new(PrefixQuery::new(Term::new(
+                TEXT_FIELD_NAME.into(),
+                prefix.as_bytes().to_vec(),
+            ))));
+        }
+        if clauses.is_empty() {
+            return Ok(vec![]);
+        }
+        let query = if all_terms_required {
+            BooleanQuery::build(clauses, vec![], vec![])?
+        } else {
+            BooleanQuery::build(vec![], clauses, vec![])?
+        };
+
+        let mut weight_field =
+            SimpleSortField::new(WEIGHT_FIELD_NAME.into(), SortFieldType::Long, true);
+        weight_field.set_missing_value(Some(VariantValue::Long(0)));
+        let sort = Sort::new(vec![SortField::Simple(weight_field)]);
+        let mut collector = TopFieldCollector::new(sort, num, false);
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&reader), None, None);
+        searcher.search(query.as_ref(), &mut collector)?;
+
+        let fields = [TEXT_FIELD_NAME.to_string(), PAYLOAD_FIELD_NAME.to_string()];
+        let mut results = vec![];
+        for hit in collector.top_docs().score_docs() {
+            let weight = match hit {
+                ScoreDocHit::Field(doc) => doc.fields[0].get_long().unwrap_or(0),
+                _ => 0,
+            };
+            let doc = reader.document(hit.doc_id(), &fields)?;
+            let mut text = String::new();
+            let mut payload = None;
+            for stored in &doc.fields {
+                if stored.field.name() == TEXT_FIELD_NAME {
+                    if let Some(value) = stored.field.string_value() {
+                        text = value.to_string();
+                    }
+                } else if stored.field.name() == PAYLOAD_FIELD_NAME {
+                    payload = stored.field.binary_value().map(|b| b.to_vec());
+                }
+            }
+            let highlight_key = if highlight {
+                Some(highlight_text(
+                    self.query_analyzer(),
+                    &text,
+                    &matched_tokens,
+                    prefix_token.as_ref().map(String::as_str),
+                )?)
+            } else {
+                None
+            };
+            results.push(LookupResult {
+                key: text,
+                highlight_key,
+                value: weight,
+                payload,
+            });
+        }
+        Ok(results)
+    }
+}
+
+impl<D, C, MS, MP> Lookup for AnalyzingInfixSuggester<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    fn build(&mut self, inputs: &mut dyn Iterator<Item = Input>) -> Result<()> {
+        self.writer.delete_all()?;
+        for input in inputs {
+            self.add(
+                &input.term,
+                input.weight,
+                input.payload.as_ref().map(Vec::as_slice),
+            )?;
+        }
+        self.commit()?;
+        self.refresh()
+    }
+
+    fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>> {
+        self.lookup_with_options(key, num, self.all_terms_required, self.highlight)
+    }
+
+    fn count(&self) -> usize {
+        match self.reader.read() {
+            Ok(ref reader) => reader.as_ref().map_or(0, |r| r.num_docs() as usize),
+            Err(_) => 0,
+        }
+    }
+}
+
+/// Splits the tokens of `key` into the ones which must match exactly and
+/// the last one, which is a prefix unless the key ends with chars dropped
+/// by the analyzer, e.g. a whitespace.
+fn analyze_key(analyzer: &dyn Analyzer, key: &str) -> Result<(HashSet<String>, Option<String>)> {
+    let mut stream = analyzer.token_stream_from_str(TEXT_FIELD_NAME, key)?;
+    stream.reset()?;
+    let mut tokens = vec![];
+    let mut max_end_offset = 0;
+    while stream.increment_token()? {
+        let bytes = stream.term_bytes_attribute().get_bytes_ref();
+        tokens.push(String::from_utf8(bytes.bytes().to_vec())?);
+        max_end_offset = max_end_offset.max(stream.offset_attribute().end_offset());
+    }
+    stream.end()?;
+
+    let prefix_token =
+        if !tokens.is_empty() && max_end_offset == stream.offset_attribute().end_offset() {
+            tokens.pop()
+        } else {
+            None
+        };
+    Ok((tokens.into_iter().collect(), prefix_token))
+}
+
+/// Marks up the tokens of `text` which are matched tokens or start with
+/// the prefix token, the offsets of the tokens being in chars.
+fn highlight_text(
+    analyzer: &dyn Analyzer,
+    text: &str,
+    matched_tokens: &HashSet<String>,
+    prefix_token: Option<&str>,
+) -> Result<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut highlighted = String::with_capacity(text.len() + 16);
+    let mut stream = analyzer.token_stream_from_str(TEXT_FIELD_NAME, text)?;
+    stream.reset()?;
+    let mut upto = 0;
+    while stream.increment_token()? {
+        let bytes = stream.term_bytes_attribute().get_bytes_ref();
+        let token = String::from_utf8(bytes.bytes().to_vec())?;
+        let start = stream.offset_attribute().start_offset().min(chars.len());
+        let end = stream.offset_attribute().end_offset().min(chars.len());
+        if upto < start {
+            highlighted.extend(&chars[upto..start]);
+            upto = start;
+        } else if upto > start {
+            // overlaps a token highlighted already, e.g. a synonym
+            continue;
+        }
+
+        if matched_tokens.contains(&token) {
+            highlighted.push_str(PRE_TAG);
+            highlighted.extend(&chars[start..end]);
+            highlighted.push_str(POST_TAG);
+            upto = end;
+        } else if let Some(prefix) = prefix_token.filter(|p| token.starts_with(p)) {
+            // the surface form is assumed to start with as many chars as
+            // the prefix, which holds as long as the analyzer only folds
+            let prefix_len = prefix.chars().count().min(end - start);
+            highlighted.push_str(PRE_TAG);
+            highlighted.extend(&chars[start..start + prefix_len]);
+            highlighted.push_str(POST_TAG);
+            highlighted.extend(&chars[start + prefix_len..end]);
+            upto = end;
+        }
+    }
+    stream.end()?;
+    if upto < chars.len() {
+        highlighted.extend(&chars[upto..]);
+    }
+    Ok(highlighted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::StandardAnalyzer;
+
+    #[test]
+    fn test_analyze_key() {
+        let analyzer = StandardAnalyzer::default();
+        let (matched, prefix) = analyze_key(&analyzer, "Ghost of chr").unwrap();
+        assert_eq!(matched, vec!["ghost".to_string()].into_iter().collect());
+        assert_eq!(prefix, Some("chr".to_string()));
+
+        // a trailing separator makes the last token exact
+        let (matched, prefix) = analyze_key(&analyzer, "ghost ").unwrap();
+        assert!(matched.contains("ghost"));
+        assert_eq!(prefix, None);
+
+        let (matched, prefix) = analyze_key(&analyzer, "  ").unwrap();
+        assert!(matched.is_empty());
+        assert_eq!(prefix, None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let analyzer = StandardAnalyzer::default();
+        let matched = vec!["ghost".to_string()].into_iter().collect();
+        let highlighted = highlight_text(
+            &analyzer,
+            "The Ghost of Christmas Past",
+            &matched,
+            Some("chr"),
+        )
+        .unwrap();
+        assert_eq!(highlighted, "The <b>Ghost</b> of <b>Chr</b>istmas Past");
+
+        let highlighted = highlight_text(&analyzer, "ghosts, ghost!", &matched, None).unwrap();
+        assert_eq!(highlighted, "ghosts, <b>ghost</b>!");
+    }
+}
@@ -0,0 +1,632 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use core::analysis::{Analyzer, TokenStreamToAutomaton, HOLE, POS_SEP};
+use core::suggest::{decode_weight, encode_weight, Input, Lookup, LookupResult};
+use core::util::automaton::{Automaton, Operations, DEFAULT_MAX_DETERMINIZED_STATES};
+use core::util::fst::{
+    Arc, ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, OutputFactory,
+    PairOutput, PairOutputFactory, PositiveIntOutput, PositiveIntOutputFactory, END_LABEL, FST,
+};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{ErrorKind, Result};
+
+/// The label separating the tokens of the analyzed forms when
+/// `preserve_sep` is set.
+pub const SEP_LABEL: u32 = POS_SEP;
+
+/// The default value of `max_surface_forms_per_analyzed_form`, which is
+/// also its upper bound as the dedup index of the FST inputs is one byte.
+pub const DEFAULT_MAX_SURFACE_FORMS_PER_ANALYZED_FORM: usize = 256;
+
+/// Ends the analyzed form in the FST inputs, before the dedup byte.
+const END_BYTE: u8 = 0;
+
+/// Separates the surface form from the payload in the FST outputs.
+const PAYLOAD_SEP: u8 = 0x1f;
+
+type SuggestOutputFactory = PairOutputFactory<PositiveIntOutputFactory, ByteSequenceOutputFactory>;
+
+type SuggestOutput = PairOutput<PositiveIntOutput, ByteSequenceOutput>;
+
+/// Suggests the entries whose analyzed form starts with the analyzed form
+/// of the typed key, e.g. "ghost chr" completes to "Ghost Christmas Past"
+/// with a lower casing analyzer. Stop words and synonyms are handled as
+/// well, the analyzed forms being built from the token graph.
+///
+/// The analyzed forms are the keys of a weighted FST whose outputs are the
+/// cost of the entry (the max weight minus its weight) and its surface
+/// form, so the completions of a prefix are found by a best first search
+/// from the node of the prefix. Weights must be within `0..=i32::MAX`.
+///
+/// ```ignore
+/// let mut suggester = AnalyzingSuggester::new(Box::new(StandardAnalyzer::default()));
+/// suggester.build(&mut inputs.into_iter())?;
+/// let results = suggester.lookup("ghost chr", 5)?;
+/// ```
+pub struct AnalyzingSuggester {
+    index_analyzer: Box<dyn Analyzer>,
+    query_analyzer: Option<Box<dyn Analyzer>>,
+    exact_first: bool,
+    preserve_sep: bool,
+    preserve_position_increments: bool,
+    max_surface_forms_per_analyzed_form: usize,
+    max_graph_expansions: Option<usize>,
+    fst: Option<FST<SuggestOutputFactory>>,
+    has_payloads: bool,
+    count: usize,
+}
+
+impl AnalyzingSuggester {
+    /// Creates a suggester analyzing both the entries and the keys with
+    /// `analyzer`.
+    pub fn new(analyzer: Box<dyn Analyzer>) -> AnalyzingSuggester {
+        AnalyzingSuggester {
+            index_analyzer: analyzer,
+            query_analyzer: None,
+            exact_first: true,
+            preserve_sep: true,
+            preserve_position_increments: true,
+            max_surface_forms_per_analyzed_form: DEFAULT_MAX_SURFACE_FORMS_PER_ANALYZED_FORM,
+            max_graph_expansions: None,
+            fst: None,
+            has_payloads: false,
+            count: 0,
+        }
+    }
+
+    /// Sets a different analyzer for the keys than for the entries.
+    pub fn set_query_analyzer(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.query_analyzer = Some(analyzer);
+    }
+
+    /// If true (the default), the entry whose surface form is exactly the
+    /// key is always returned first, even if its weight is lower than the
+    /// other completions.
+    pub fn set_exact_first(&mut self, exact_first: bool) {
+        self.exact_first = exact_first;
+    }
+
+    /// If true (the default), the token boundaries are kept in the analyzed
+    /// forms, otherwise "ghostchr" also matches "ghost christmas". Must be
+    /// set before `build`.
+    pub fn set_preserve_sep(&mut self, preserve_sep: bool) {
+        self.preserve_sep = preserve_sep;
+    }
+
+    /// If false, the holes left by removed tokens, e.g. stop words, are
+    /// ignored. Must be set before `build`.
+    pub fn set_preserve_position_increments(&mut self, preserve_position_increments: bool) {
+        self.preserve_position_increments = preserve_position_increments;
+    }
+
+    /// Sets how many entries sharing the same analyzed form are kept, the
+    /// ones with the highest weights, at most 256.
+    pub fn set_max_surface_forms_per_analyzed_form(&mut self, max: usize) -> Result<()> {
+        if max == 0 || max > DEFAULT_MAX_SURFACE_FORMS_PER_ANALYZED_FORM {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "max_surface_forms_per_analyzed_form must be in 1..={}, got {}",
+                DEFAULT_MAX_SURFACE_FORMS_PER_ANALYZED_FORM, max
+            )));
+        }
+        self.max_surface_forms_per_analyzed_form = max;
+        Ok(())
+    }
+
+    /// Limits how many analyzed forms are indexed for one entry when the
+    /// analyzer produces a token graph, all of them are by default.
+    pub fn set_max_graph_expansions(&mut self, max_graph_expansions: Option<usize>) {
+        self.max_graph_expansions = max_graph_expansions;
+    }
+
+    /// Returns the analyzed forms of a surface form, the labels of the
+    /// automaton being the bytes of the forms.
+    fn analyzed_forms(&self, surface: &str) -> Result<Vec<Vec<u8>>> {
+        let automaton = self.to_automaton(self.index_analyzer.as_ref(), surface)?;
+        let automaton = Operations::determinize(&automaton, DEFAULT_MAX_DETERMINIZED_STATES)?;
+        Ok(
+            Operations::finite_strings(&automaton, self.max_graph_expansions)
+                .into_iter()
+                .map(|labels| labels.into_iter().map(|l| l as u8).collect())
+                .collect(),
+        )
+    }
+
+    /// Returns the deterministic automaton of the analyzed forms of `key`,
+    /// which the analyzed forms of its completions start with.
+    pub(crate) fn lookup_automaton(&self, key: &str) -> Result<Automaton> {
+        let analyzer = match self.query_analyzer {
+            Some(ref analyzer) => analyzer.as_ref(),
+            None => self.index_analyzer.as_ref(),
+        };
+        let automaton = self.to_automaton(analyzer, key)?;
+        Operations::determinize(&automaton, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    fn to_automaton(&self, analyzer: &dyn Analyzer, text: &str) -> Result<Automaton> {
+        let mut stream = analyzer.token_stream_from_str("", text)?;
+        let mut ts2a = TokenStreamToAutomaton::new();
+        ts2a.set_preserve_position_increments(self.preserve_position_increments);
+        let automaton = ts2a.to_automaton(stream.as_mut())?;
+        Ok(self.replace_sep(&automaton))
+    }
+
+    /// Replaces the position separators by `SEP_LABEL`, or by epsilons
+    /// when `preserve_sep` is false, and the holes by epsilons.
+    fn replace_sep(&self, a: &Automaton) -> Automaton {
+        let mut result = Automaton::new();
+        for state in 0..a.num_states() {
+            result.create_state();
+            result.set_accept(state, a.is_accept(state));
+        }
+        // in reverse topological order the transitions an epsilon copies
+        // are all added already
+        for &state in Operations::topo_sort_states(a).iter().rev() {
+            for t in a.transitions(state) {
+                if t.min == POS_SEP {
+                    debug_assert_eq!(t.max, POS_SEP);
+                    if self.preserve_sep {
+                        result.add_label_transition(state, t.dest, SEP_LABEL);
+                    } else {
+                        result.add_epsilon(state, t.dest);
+                    }
+                } else if t.min == HOLE {
+                    debug_assert_eq!(t.max, HOLE);
+                    result.add_epsilon(state, t.dest);
+                } else {
+                    result.add_transition(state, t.dest, t.min, t.max);
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns at most `num` completions of `key` whose analyzed forms
+    /// start with a string accepted by `automaton`.
+    pub(crate) fn lookup_with_automaton(
+        &self,
+        key: &str,
+        automaton: &Automaton,
+        num: usize,
+    ) -> Result<Vec<LookupResult>> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(vec![]),
+        };
+        if num == 0 {
+            return Ok(vec![]);
+        }
+        let prefix_paths = intersect_prefix_paths(fst, automaton)?;
+
+        let mut results = Vec::with_capacity(num);
+        if self.exact_first {
+            // the entries whose analyzed form is exactly the one of the key
+            // follow the END_BYTE arc, look for the one with the same surface
+            // form among them
+            let mut reader = fst.bytes_reader();
+            let mut start_paths = vec![];
+            for path in &prefix_paths {
+                if let Some(arc) =
+                    fst.find_target_arc(i32::from(END_BYTE), &path.arc, &mut reader)?
+                {
+                    start_paths.push(path.follow(fst, arc));
+                }
+            }
+            let max = start_paths.len() * self.max_surface_forms_per_analyzed_form;
+            let exact = top_n(fst, start_paths, max, |output| {
+                self.surface_form(output) == key.as_bytes()
+            })?;
+            if let Some(output) = exact.first() {
+                results.push(self.lookup_result(output)?);
+                if results.len() == num {
+                    return Ok(results);
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let outputs = top_n(fst, prefix_paths, num - results.len(), |output| {
+            let surface = self.surface_form(output);
+            // the exact match, if any, was added already
+            if self.exact_first && surface == key.as_bytes() {
+                return false;
+            }
+            seen.insert(surface.to_vec())
+        })?;
+        for output in &outputs {
+            results.push(self.lookup_result(output)?);
+        }
+        Ok(results)
+    }
+
+    fn surface_form<'a>(&self, output: &'a SuggestOutput) -> &'a [u8] {
+        let bytes = output.output2.inner();
+        if self.has_payloads {
+            let end = bytes
+                .iter()
+                .position(|&b| b == PAYLOAD_SEP)
+                .unwrap_or_else(|| bytes.len());
+            &bytes[..end]
+        } else {
+            bytes
+        }
+    }
+
+    fn lookup_result(&self, output: &SuggestOutput) -> Result<LookupResult> {
+        let surface = self.surface_form(output);
+        let payload = if self.has_payloads {
+            // the surface form is followed by PAYLOAD_SEP
+            Some(output.output2.inner()[surface.len() + 1..].to_vec())
+        } else {
+            None
+        };
+        Ok(LookupResult {
+            key: String::from_utf8(surface.to_vec())?,
+            highlight_key: None,
+            value: decode_weight(output.output1.get()),
+            payload,
+        })
+    }
+}
+
+impl Lookup for AnalyzingSuggester {
+    fn build(&mut self, inputs: &mut dyn Iterator<Item = Input>) -> Result<()> {
+        // (analyzed form, cost, index of the input)
+        let mut entries: Vec<(Vec<u8>, i64, usize)> = vec![];
+        let mut surfaces = vec![];
+        for input in inputs {
+            let cost = encode_weight(input.weight)?;
+            for analyzed in self.analyzed_forms(&input.term)? {
+                entries.push((analyzed, cost, surfaces.len()));
+            }
+            surfaces.push(input);
+        }
+
+        self.has_payloads = surfaces.iter().any(|input| input.payload.is_some());
+        if self.has_payloads {
+            if let Some(input) = surfaces
+                .iter()
+                .find(|input| input.term.as_bytes().contains(&PAYLOAD_SEP))
+            {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "surface form cannot contain unit separator character U+001F when payloads \
+                     are used: {:?}",
+                    input.term
+                )));
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then(a.1.cmp(&b.1))
+                .then_with(|| surfaces[a.2].term.cmp(&surfaces[b.2].term))
+        });
+
+        let outputs = PairOutputFactory::new(
+            PositiveIntOutputFactory::new(),
+            ByteSequenceOutputFactory::new(),
+        );
+        let mut builder = FstBuilder::new(InputType::Byte1, outputs);
+        builder.init();
+        let mut scratch = IntsRefBuilder::new();
+        let mut dedup = 0;
+        for i in 0..entries.len() {
+            let (ref analyzed, cost, idx) = entries[i];
+            if i > 0 && entries[i - 1].0 == *analyzed {
+                dedup += 1;
+                if dedup >= self.max_surface_forms_per_analyzed_form {
+                    // only the surface forms with the lowest costs are kept
+                    continue;
+                }
+            } else {
+                dedup = 0;
+            }
+
+            let mut input = Vec::with_capacity(analyzed.len() + 2);
+            input.extend_from_slice(analyzed);
+            input.push(END_BYTE);
+            input.push(dedup as u8);
+
+            let surface = &surfaces[idx];
+            let mut output = surface.term.as_bytes().to_vec();
+            if self.has_payloads {
+                output.push(PAYLOAD_SEP);
+                if let Some(ref payload) = surface.payload {
+                    output.extend_from_slice(payload);
+                }
+            }
+            builder.add(
+                to_ints_ref(&input, &mut scratch),
+                PairOutput::new(
+                    PositiveIntOutput::new(cost),
+                    ByteSequenceOutput::new(output),
+                ),
+            )?;
+        }
+        self.fst = builder.finish()?;
+        self.count = surfaces.len();
+        Ok(())
+    }
+
+    fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>> {
+        let automaton = self.lookup_automaton(key)?;
+        self.lookup_with_automaton(key, &automaton, num)
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// A path from the root of the FST, with the output and the input labels
+/// accumulated along the way.
+struct FSTPath {
+    arc: Arc<SuggestOutput>,
+    output: SuggestOutput,
+    input: Vec<u8>,
+}
+
+impl FSTPath {
+    fn follow(&self, fst: &FST<SuggestOutputFactory>, arc: Arc<SuggestOutput>) -> FSTPath {
+        let output = match arc.output {
+            Some(ref output) => fst.outputs().add(&self.output, output),
+            None => self.output.clone(),
+        };
+        let mut input = self.input.clone();
+        if arc.label != END_LABEL {
+            input.push(arc.label as u8);
+        }
+        FSTPath { arc, output, input }
+    }
+
+    fn cost(&self) -> i64 {
+        self.output.output1.get()
+    }
+}
+
+impl PartialEq for FSTPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FSTPath {}
+
+impl PartialOrd for FSTPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FSTPath {
+    // reversed, the path with the lowest cost being the top of the heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost()
+            .cmp(&self.cost())
+            .then_with(|| other.input.cmp(&self.input))
+    }
+}
+
+/// Returns the paths of the FST whose inputs are accepted by the
+/// deterministic and acyclic `automaton`.
+fn intersect_prefix_paths(
+    fst: &FST<SuggestOutputFactory>,
+    automaton: &Automaton,
+) -> Result<Vec<FSTPath>> {
+    let mut end_paths = vec![];
+    if automaton.num_states() == 0 {
+        return Ok(end_paths);
+    }
+    let mut reader = fst.bytes_reader();
+    let root = FSTPath {
+        arc: fst.root_arc(),
+        output: fst.outputs().empty(),
+        input: vec![],
+    };
+    let mut queue = vec![(0, root)];
+    while let Some((state, path)) = queue.pop() {
+        if automaton.is_accept(state) {
+            end_paths.push(path);
+            continue;
+        }
+        for t in automaton.transitions(state) {
+            if t.min == t.max {
+                if let Some(arc) = fst.find_target_arc(t.min as i32, &path.arc, &mut reader)? {
+                    queue.push((t.dest, path.follow(fst, arc)));
+                }
+            } else {
+                let mut arc = fst.read_first_target_arc(&path.arc, &mut reader)?;
+                loop {
+                    if arc.label != END_LABEL
+                        && arc.label as u32 >= t.min
+                        && arc.label as u32 <= t.max
+                    {
+                        queue.push((t.dest, path.follow(fst, arc.clone())));
+                    }
+                    if arc.is_last() || arc.label as u32 > t.max && arc.label != END_LABEL {
+                        break;
+                    }
+                    fst.read_next_arc(&mut arc, &mut reader)?;
+                }
+            }
+        }
+    }
+    Ok(end_paths)
+}
+
+/// Returns the outputs of the `num` lowest cost completions of
+/// `start_paths` which are accepted by `accept`, lowest cost first.
+fn top_n<F>(
+    fst: &FST<SuggestOutputFactory>,
+    start_paths: Vec<FSTPath>,
+    num: usize,
+    mut accept: F,
+) -> Result<Vec<SuggestOutput>>
+where
+    F: FnMut(&SuggestOutput) -> bool,
+{
+    let mut results = Vec::with_capacity(num);
+    let mut reader = fst.bytes_reader();
+    // outputs are never negative so the cost of a path is a lower bound
+    // of the cost of its completions
+    let mut queue: BinaryHeap<FSTPath> = start_paths.into_iter().collect();
+    while results.len() < num {
+        let path = match queue.pop() {
+            Some(path) => path,
+            None => break,
+        };
+        if path.arc.label == END_LABEL {
+            if accept(&path.output) {
+                results.push(path.output);
+            }
+            continue;
+        }
+        let mut arc = fst.read_first_target_arc(&path.arc, &mut reader)?;
+        loop {
+            let is_last = arc.is_last();
+            queue.push(path.follow(fst, arc.clone()));
+            if is_last {
+                break;
+            }
+            fst.read_next_arc(&mut arc, &mut reader)?;
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::StandardAnalyzer;
+
+    fn build(suggester: &mut AnalyzingSuggester, inputs: &[(&str, i64)]) {
+        let mut inputs = inputs
+            .iter()
+            .map(|&(term, weight)| Input::new(term, weight));
+        suggester.build(&mut inputs).unwrap();
+    }
+
+    fn keys(results: &[LookupResult]) -> Vec<&str> {
+        results.iter().map(|r| r.key.as_str()).collect()
+    }
+
+    fn suggester() -> AnalyzingSuggester {
+        let mut suggester = AnalyzingSuggester::new(Box::new(StandardAnalyzer::default()));
+        build(
+            &mut suggester,
+            &[
+                ("Ghost Rider", 100),
+                ("ghostbusters", 80),
+                ("Golden Eye", 200),
+                ("ghost", 10),
+                ("the ghost of christmas past", 50),
+            ],
+        );
+        suggester
+    }
+
+    #[test]
+    fn test_prefix_completions() {
+        let suggester = suggester();
+        assert_eq!(suggester.count(), 5);
+
+        let results = suggester.lookup("gho", 10).unwrap();
+        assert_eq!(keys(&results), vec!["Ghost Rider", "ghostbusters", "ghost"]);
+        assert_eq!(results[0].value, 100);
+        assert_eq!(results[0].payload, None);
+
+        assert_eq!(
+            keys(&suggester.lookup("GHOST R", 10).unwrap()),
+            vec!["Ghost Rider"]
+        );
+        assert_eq!(
+            keys(&suggester.lookup("gho", 1).unwrap()),
+            vec!["Ghost Rider"]
+        );
+        assert!(suggester.lookup("ghostr", 10).unwrap().is_empty());
+        assert!(suggester.lookup("x", 10).unwrap().is_empty());
+
+        // the removed stop word leaves a hole the key must have as well
+        assert_eq!(
+            keys(&suggester.lookup("the ghost of", 10).unwrap()),
+            vec!["the ghost of christmas past"]
+        );
+    }
+
+    #[test]
+    fn test_exact_first() {
+        let mut suggester = suggester();
+        let results = suggester.lookup("ghost", 2).unwrap();
+        assert_eq!(keys(&results), vec!["ghost", "Ghost Rider"]);
+        assert_eq!(results[0].value, 10);
+
+        suggester.set_exact_first(false);
+        let results = suggester.lookup("ghost", 2).unwrap();
+        assert_eq!(keys(&results), vec!["Ghost Rider", "ghostbusters"]);
+    }
+
+    #[test]
+    fn test_no_preserve_sep() {
+        let mut suggester = AnalyzingSuggester::new(Box::new(StandardAnalyzer::default()));
+        suggester.set_preserve_sep(false);
+        build(
+            &mut suggester,
+            &[("Ghost Rider", 100), ("ghostbusters", 80)],
+        );
+        assert_eq!(
+            keys(&suggester.lookup("ghostr", 10).unwrap()),
+            vec!["Ghost Rider"]
+        );
+    }
+
+    #[test]
+    fn test_max_surface_forms() {
+        let mut suggester = AnalyzingSuggester::new(Box::new(StandardAnalyzer::default()));
+        suggester
+            .set_max_surface_forms_per_analyzed_form(2)
+            .unwrap();
+        build(&mut suggester, &[("abc", 1), ("ABC", 3), ("Abc", 2)]);
+        assert_eq!(
+            keys(&suggester.lookup("ab", 10).unwrap()),
+            vec!["ABC", "Abc"]
+        );
+        assert!(suggester
+            .set_max_surface_forms_per_analyzed_form(257)
+            .is_err());
+    }
+
+    #[test]
+    fn test_payloads_and_weights() {
+        let mut suggester = AnalyzingSuggester::new(Box::new(StandardAnalyzer::default()));
+        let mut inputs = vec![
+            Input::with_payload("foo bar", 3, vec![1, 2]),
+            Input::new("foo", 5),
+        ]
+        .into_iter();
+        suggester.build(&mut inputs).unwrap();
+        let results = suggester.lookup("fo", 10).unwrap();
+        assert_eq!(keys(&results), vec!["foo", "foo bar"]);
+        assert_eq!(results[0].payload, Some(vec![]));
+        assert_eq!(results[1].payload, Some(vec![1, 2]));
+        assert_eq!(results[1].value, 3);
+
+        let mut inputs = vec![Input::new("foo", -1)].into_iter();
+        assert!(suggester.build(&mut inputs).is_err());
+    }
+}
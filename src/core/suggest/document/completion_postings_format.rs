@@ -0,0 +1,343 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
+use core::codec::postings::blocktree::{BlockTreeTermsReader, FieldReaderRef};
+use core::codec::postings::{
+    FieldsConsumer, FieldsConsumerEnum, FieldsProducer, Lucene50PostingsFormat, PostingsFormat,
+};
+use core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use core::codec::{codec_util, Codec, Fields, PostingIterator, PostingIteratorFlags};
+use core::codec::{TermIterator, Terms};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::store::directory::Directory;
+use core::store::io::{DataInput, DataOutput};
+use core::suggest::document::decode_payload;
+use core::suggest::{decode_weight, encode_weight};
+use core::util::fst::{
+    Arc as FSTArc, FstBuilder, InputType, OutputFactory, PositiveIntOutput,
+    PositiveIntOutputFactory, END_LABEL, FST,
+};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::ErrorKind::CorruptIndex;
+use error::Result;
+
+/// Name of the `CompletionPostingsFormat`, set as the postings format of
+/// the suggest fields.
+pub const COMPLETION_POSTINGS_FORMAT_NAME: &str = "Completion50";
+
+const COMPLETION_CODEC_NAME: &str = "completion";
+const COMPLETION_VERSION_START: i32 = 0;
+const COMPLETION_VERSION_CURRENT: i32 = COMPLETION_VERSION_START;
+const COMPLETION_EXTENSION: &str = "lkp";
+
+/// The postings format of the `SuggestField`s and `ContextSuggestField`s.
+///
+/// The terms and postings are written with `Lucene50PostingsFormat`, and
+/// a weighted FST of the terms of each field is written to the `.lkp` file
+/// and loaded in memory when the segment is opened, so a
+/// `SuggestIndexSearcher` visits the completions by descending weight and
+/// stops as soon as the best ones are found.
+#[derive(Copy, Clone, Default)]
+pub struct CompletionPostingsFormat;
+
+impl PostingsFormat for CompletionPostingsFormat {
+    type FieldsProducer = CompletionFieldsProducer;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        CompletionFieldsProducer::new(state)
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        Ok(FieldsConsumerEnum::Completion(
+            CompletionFieldsConsumer::new(state),
+        ))
+    }
+
+    fn name(&self) -> &str {
+        COMPLETION_POSTINGS_FORMAT_NAME
+    }
+}
+
+/// Writes the postings of the completion fields and their FSTs.
+pub struct CompletionFieldsConsumer<D: Directory, DW: Directory, C: Codec> {
+    write_state: SegmentWriteState<D, DW, C>,
+}
+
+impl<D: Directory, DW: Directory, C: Codec> CompletionFieldsConsumer<D, DW, C> {
+    pub fn new(write_state: &SegmentWriteState<D, DW, C>) -> Self {
+        CompletionFieldsConsumer {
+            write_state: write_state.clone(),
+        }
+    }
+}
+
+impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for CompletionFieldsConsumer<D, DW, C> {
+    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+        Lucene50PostingsFormat::default()
+            .fields_consumer(&self.write_state)?
+            .write(fields)?;
+
+        let state = &self.write_state;
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            COMPLETION_EXTENSION,
+        );
+        let mut out = state.directory.create_output(&name, &state.context)?;
+        codec_util::write_index_header(
+            &mut out,
+            COMPLETION_CODEC_NAME,
+            COMPLETION_VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        for field in fields.fields() {
+            let terms = match fields.terms(&field)? {
+                Some(terms) => terms,
+                None => continue,
+            };
+            let field_info = match state.field_infos.field_info_by_name(&field) {
+                Some(field_info) => field_info,
+                None => continue,
+            };
+            if let Some(fst) = build_fst(&terms)? {
+                out.write_vint(field_info.number as i32)?;
+                fst.save(&mut out)?;
+            }
+        }
+        out.write_vint(-1)?;
+        codec_util::write_footer(&mut out)
+    }
+}
+
+/// Builds the FST mapping each term of a completion field to the best weight
+/// of its completions, encoded as a cost.
+fn build_fst(terms: &impl Terms) -> Result<Option<FST<PositiveIntOutputFactory>>> {
+    let mut builder = FstBuilder::new(InputType::Byte1, PositiveIntOutputFactory::new());
+    builder.init();
+    let mut scratch = IntsRefBuilder::new();
+    let mut terms_iter = terms.iterator()?;
+    while let Some(term) = terms_iter.next()? {
+        let mut postings = terms_iter.postings_with_flags(PostingIteratorFlags::PAYLOADS)?;
+        let mut best = None;
+        loop {
+            let doc = postings.next()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            for _ in 0..postings.freq()? {
+                postings.next_position()?;
+                let (weight, _) = decode_payload(&postings.payload()?)?;
+                best = Some(best.map_or(weight, |best: i64| best.max(weight)));
+            }
+        }
+        if let Some(weight) = best {
+            builder.add(
+                to_ints_ref(&term, &mut scratch),
+                PositiveIntOutput::new(encode_weight(weight)?),
+            )?;
+        }
+    }
+    builder.finish()
+}
+
+/// Reads the postings of the completion fields and loads their FSTs.
+pub struct CompletionFieldsProducer {
+    delegate: BlockTreeTermsReader,
+    completions: HashMap<String, Arc<CompletionTerms>>,
+}
+
+impl CompletionFieldsProducer {
+    pub fn new<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<CompletionFieldsProducer> {
+        let delegate = Lucene50PostingsFormat::default().fields_producer(state)?;
+
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            COMPLETION_EXTENSION,
+        );
+        let mut input = state.directory.open_checksum_input(&name, state.context)?;
+        codec_util::check_index_header(
+            &mut input,
+            COMPLETION_CODEC_NAME,
+            COMPLETION_VERSION_START,
+            COMPLETION_VERSION_CURRENT,
+            &state.segment_info.id,
+            &state.segment_suffix,
+        )?;
+        let mut completions = HashMap::new();
+        loop {
+            let field_number = input.read_vint()?;
+            if field_number == -1 {
+                break;
+            }
+            let field_info = match state.field_infos.field_info_by_number(field_number as u32) {
+                Some(field_info) => field_info,
+                None => bail!(CorruptIndex(format!(
+                    "invalid completion field number: {}",
+                    field_number
+                ))),
+            };
+            let fst = FST::from_input(&mut input, PositiveIntOutputFactory::new())?;
+            completions.insert(field_info.name.clone(), Arc::new(CompletionTerms { fst }));
+        }
+        codec_util::check_footer(&mut input)?;
+
+        Ok(CompletionFieldsProducer {
+            delegate,
+            completions,
+        })
+    }
+}
+
+impl FieldsProducer for CompletionFieldsProducer {
+    fn check_integrity(&self) -> Result<()> {
+        self.delegate.check_integrity()
+    }
+
+    fn completion_terms(&self, field: &str) -> Option<Arc<CompletionTerms>> {
+        self.completions.get(field).cloned()
+    }
+}
+
+impl Fields for CompletionFieldsProducer {
+    type Terms = FieldReaderRef;
+    fn fields(&self) -> Vec<String> {
+        self.delegate.fields()
+    }
+    fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
+        self.delegate.terms(field)
+    }
+    fn size(&self) -> usize {
+        self.delegate.size()
+    }
+    fn terms_freq(&self, field: &str) -> usize {
+        self.delegate.terms_freq(field)
+    }
+}
+
+/// The terms of a completion field, weighted by the best weight of their
+/// completions.
+pub struct CompletionTerms {
+    fst: FST<PositiveIntOutputFactory>,
+}
+
+impl CompletionTerms {
+    /// Visits the terms starting with `prefix` and their weight, by
+    /// descending weight, until `visitor` returns false.
+    pub fn visit_by_weight<F>(&self, prefix: &[u8], mut visitor: F) -> Result<()>
+    where
+        F: FnMut(&[u8], i64) -> Result<bool>,
+    {
+        let fst = &self.fst;
+        let mut reader = fst.bytes_reader();
+        let mut path = WeightedPath {
+            arc: fst.root_arc(),
+            cost: fst.outputs().empty(),
+            input: Vec::with_capacity(prefix.len()),
+        };
+        for &b in prefix {
+            match fst.find_target_arc(i32::from(b), &path.arc, &mut reader)? {
+                Some(arc) => path = path.follow(fst, arc),
+                None => return Ok(()),
+            }
+        }
+
+        // outputs are never negative so the cost of a path is a lower bound
+        // of the cost of its terms
+        let mut queue = BinaryHeap::new();
+        queue.push(path);
+        while let Some(path) = queue.pop() {
+            if path.arc.label == END_LABEL {
+                if !visitor(&path.input, decode_weight(path.cost.get()))? {
+                    break;
+                }
+                continue;
+            }
+            let mut arc = fst.read_first_target_arc(&path.arc, &mut reader)?;
+            loop {
+                let is_last = arc.is_last();
+                queue.push(path.follow(fst, arc.clone()));
+                if is_last {
+                    break;
+                }
+                fst.read_next_arc(&mut arc, &mut reader)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A path from the root of a completion FST, with its cost and the input
+/// labels accumulated along the way.
+struct WeightedPath {
+    arc: FSTArc<PositiveIntOutput>,
+    cost: PositiveIntOutput,
+    input: Vec<u8>,
+}
+
+impl WeightedPath {
+    fn follow(
+        &self,
+        fst: &FST<PositiveIntOutputFactory>,
+        arc: FSTArc<PositiveIntOutput>,
+    ) -> WeightedPath {
+        let cost = match arc.output {
+            Some(ref output) => fst.outputs().add(&self.cost, output),
+            None => self.cost.clone(),
+        };
+        let mut input = self.input.clone();
+        if arc.label != END_LABEL {
+            input.push(arc.label as u8);
+        }
+        WeightedPath { arc, cost, input }
+    }
+}
+
+impl PartialEq for WeightedPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for WeightedPath {}
+
+impl PartialOrd for WeightedPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WeightedPath {
+    // reversed, the path with the lowest cost being the top of the heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .get()
+            .cmp(&self.cost.get())
+            .then_with(|| other.input.cmp(&self.input))
+    }
+}
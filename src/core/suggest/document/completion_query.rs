@@ -0,0 +1,238 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::suggest::document::CONTEXT_SEPARATOR;
+
+use error::{ErrorKind, Result};
+
+/// A query looking up the completions of a suggest field, run by a
+/// `SuggestIndexSearcher`.
+pub trait CompletionQuery {
+    /// The suggest field the completions are looked up in.
+    fn field(&self) -> &str;
+
+    /// The prefixes of the indexed terms to enumerate, the terms starting
+    /// with none of them never match.
+    fn term_prefixes(&self) -> Vec<Vec<u8>>;
+
+    /// Returns the boost and the context of the completion indexed as
+    /// `term` if it matches, the score of the completion being its weight
+    /// multiplied by the boost.
+    fn accept(&self, term: &[u8]) -> Option<(f32, Option<String>)>;
+
+    /// An upper bound of the boosts returned by `accept`, which lets the
+    /// lookup stop once the best completions are found.
+    fn max_boost(&self) -> f32 {
+        1.0
+    }
+}
+
+/// Looks up the completions of a `SuggestField` starting with a prefix.
+#[derive(Debug, Clone)]
+pub struct PrefixCompletionQuery {
+    field: String,
+    prefix: String,
+}
+
+impl PrefixCompletionQuery {
+    pub fn new(field: &str, prefix: &str) -> PrefixCompletionQuery {
+        PrefixCompletionQuery {
+            field: field.to_string(),
+            prefix: prefix.to_string(),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl CompletionQuery for PrefixCompletionQuery {
+    fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn term_prefixes(&self) -> Vec<Vec<u8>> {
+        vec![self.prefix.as_bytes().to_vec()]
+    }
+
+    fn accept(&self, term: &[u8]) -> Option<(f32, Option<String>)> {
+        if term.starts_with(self.prefix.as_bytes()) {
+            Some((1.0, None))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct QueryContext {
+    context: String,
+    boost: f32,
+    exact: bool,
+}
+
+impl QueryContext {
+    fn matches(&self, context: &str) -> bool {
+        if self.exact {
+            context == self.context
+        } else {
+            context.starts_with(&self.context)
+        }
+    }
+}
+
+/// Looks up the completions of a `ContextSuggestField` starting with the
+/// prefix of a `PrefixCompletionQuery` and indexed with one of the added
+/// contexts, their weights being boosted by the best boost of the matched
+/// contexts.
+///
+/// Without any added context, the completions of all the contexts match.
+///
+/// ```ignore
+/// let mut query = ContextQuery::new(PrefixCompletionQuery::new("suggest", "gho"));
+/// query.add_context("eu", 2.0, true)?;
+/// query.add_context("us", 1.0, true)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContextQuery {
+    inner: PrefixCompletionQuery,
+    contexts: Vec<QueryContext>,
+}
+
+impl ContextQuery {
+    pub fn new(inner: PrefixCompletionQuery) -> ContextQuery {
+        ContextQuery {
+            inner,
+            contexts: vec![],
+        }
+    }
+
+    /// Matches the completions indexed with `context`, or with a context
+    /// starting with it if `exact` is false.
+    pub fn add_context(&mut self, context: &str, boost: f32, exact: bool) -> Result<()> {
+        if context.contains(CONTEXT_SEPARATOR) {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "illegal context '{}': contains the reserved context separator {:?}",
+                context, CONTEXT_SEPARATOR
+            )));
+        }
+        if boost < 0.0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "boost must be >= 0, got {}",
+                boost
+            )));
+        }
+        self.contexts.push(QueryContext {
+            context: context.to_string(),
+            boost,
+            exact,
+        });
+        Ok(())
+    }
+}
+
+impl CompletionQuery for ContextQuery {
+    fn field(&self) -> &str {
+        self.inner.field()
+    }
+
+    fn term_prefixes(&self) -> Vec<Vec<u8>> {
+        if self.contexts.is_empty() {
+            return vec![vec![]];
+        }
+        self.contexts
+            .iter()
+            .map(|c| {
+                if c.exact {
+                    format!("{}{}{}", c.context, CONTEXT_SEPARATOR, self.inner.prefix).into_bytes()
+                } else {
+                    c.context.as_bytes().to_vec()
+                }
+            })
+            .collect()
+    }
+
+    fn accept(&self, term: &[u8]) -> Option<(f32, Option<String>)> {
+        let term = ::std::str::from_utf8(term).ok()?;
+        let sep = term.find(CONTEXT_SEPARATOR)?;
+        let context = &term[..sep];
+        let value = &term[sep + CONTEXT_SEPARATOR.len_utf8()..];
+        if !value.starts_with(&self.inner.prefix) {
+            return None;
+        }
+        let boost = if self.contexts.is_empty() {
+            1.0
+        } else {
+            self.contexts
+                .iter()
+                .filter(|c| c.matches(context))
+                .map(|c| c.boost)
+                .fold(None, |best: Option<f32>, b| {
+                    Some(best.map_or(b, |best| best.max(b)))
+                })?
+        };
+        Some((boost, Some(context.to_string())))
+    }
+
+    fn max_boost(&self) -> f32 {
+        if self.contexts.is_empty() {
+            1.0
+        } else {
+            self.contexts.iter().map(|c| c.boost).fold(0.0, f32::max)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_completion_query() {
+        let query = PrefixCompletionQuery::new("suggest", "gho");
+        assert_eq!(query.term_prefixes(), vec![b"gho".to_vec()]);
+        assert_eq!(query.accept(b"ghost"), Some((1.0, None)));
+        assert_eq!(query.accept(b"goat"), None);
+    }
+
+    #[test]
+    fn test_context_query() {
+        let mut query = ContextQuery::new(PrefixCompletionQuery::new("suggest", "gho"));
+        assert_eq!(query.term_prefixes(), vec![Vec::<u8>::new()]);
+        assert_eq!(
+            query.accept("eu\u{1d}ghost".as_bytes()),
+            Some((1.0, Some("eu".to_string())))
+        );
+        assert_eq!(query.accept(b"ghost"), None);
+
+        query.add_context("eu", 2.0, true).unwrap();
+        query.add_context("u", 3.0, false).unwrap();
+        assert_eq!(
+            query.term_prefixes(),
+            vec!["eu\u{1d}gho".as_bytes().to_vec(), b"u".to_vec()]
+        );
+        assert_eq!(
+            query.accept("eu\u{1d}ghost".as_bytes()),
+            Some((2.0, Some("eu".to_string())))
+        );
+        assert_eq!(
+            query.accept("us\u{1d}ghost".as_bytes()),
+            Some((3.0, Some("us".to_string())))
+        );
+        assert_eq!(query.accept("eu\u{1d}goat".as_bytes()), None);
+        assert_eq!(query.accept("fr\u{1d}ghost".as_bytes()), None);
+        assert!((query.max_boost() - 3.0).abs() < ::std::f32::EPSILON);
+        assert!(query.add_context("e\u{1d}u", 1.0, true).is_err());
+    }
+}
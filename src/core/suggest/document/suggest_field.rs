@@ -0,0 +1,309 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+use core::doc::{Field, FieldType, Fieldable, IndexOptions};
+use core::suggest::document::COMPLETION_POSTINGS_FORMAT_NAME;
+use core::suggest::encode_weight;
+use core::util::{Numeric, VariantValue};
+
+use error::{ErrorKind, Result};
+
+/// Separates the context of a completion from its value in the indexed terms
+/// of a `ContextSuggestField`, reserved in the values and the contexts.
+pub const CONTEXT_SEPARATOR: char = '\u{1d}';
+
+const WEIGHT_BYTES: usize = 8;
+
+/// A field holding a completion of its document, looked up by a
+/// `PrefixCompletionQuery` through a `SuggestIndexSearcher`.
+///
+/// The value is indexed as a single term, its weight and the value itself
+/// being the payload of the term, so the suggestions are read from the
+/// postings of the field without loading the documents. The field is
+/// written with the `CompletionPostingsFormat`, whose FST lets the best
+/// completions be found first.
+///
+/// ```ignore
+/// let doc: Vec<Box<dyn Fieldable>> =
+///     vec![Box::new(SuggestField::new("suggest", "ghostbusters", 4)?)];
+/// writer.add_document(doc)?;
+/// ```
+#[derive(Debug)]
+pub struct SuggestField {
+    field: Field,
+}
+
+impl SuggestField {
+    /// Creates a completion of `value` scored with `weight`, which must be
+    /// between 0 and `i32::max_value()`.
+    pub fn new(name: &str, value: &str, weight: i64) -> Result<SuggestField> {
+        check_value(value, "value")?;
+        let terms = vec![value.to_string()];
+        Ok(SuggestField {
+            field: completion_field(name, value, weight, terms)?,
+        })
+    }
+}
+
+/// A `SuggestField` filtered by contexts at query time, e.g. the regions a
+/// completion is relevant for, with a `ContextQuery`.
+///
+/// The value is indexed once per context, each term being the context, the
+/// `CONTEXT_SEPARATOR` and the value. A field without contexts is indexed
+/// with the empty context and only matches the context queries not
+/// restricting the contexts.
+#[derive(Debug)]
+pub struct ContextSuggestField {
+    field: Field,
+}
+
+impl ContextSuggestField {
+    pub fn new(
+        name: &str,
+        value: &str,
+        weight: i64,
+        contexts: &[&str],
+    ) -> Result<ContextSuggestField> {
+        check_value(value, "value")?;
+        let mut terms = Vec::with_capacity(contexts.len().max(1));
+        if contexts.is_empty() {
+            terms.push(format!("{}{}", CONTEXT_SEPARATOR, value));
+        }
+        for context in contexts {
+            check_value(context, "context")?;
+            terms.push(format!("{}{}{}", context, CONTEXT_SEPARATOR, value));
+        }
+        Ok(ContextSuggestField {
+            field: completion_field(name, value, weight, terms)?,
+        })
+    }
+}
+
+fn check_value(value: &str, what: &str) -> Result<()> {
+    if value.contains(CONTEXT_SEPARATOR) {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "illegal {} '{}': contains the reserved context separator {:?}",
+            what, value, CONTEXT_SEPARATOR
+        )));
+    }
+    Ok(())
+}
+
+fn completion_field(name: &str, value: &str, weight: i64, terms: Vec<String>) -> Result<Field> {
+    // the weights are encoded as costs in the FST of the field
+    encode_weight(weight)?;
+    let mut field_type = FieldType::default();
+    field_type.omit_norms = true;
+    field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+
+    let payload = encode_payload(weight, value);
+    Ok(Field::new(
+        name.to_string(),
+        field_type,
+        Some(VariantValue::VString(value.to_string())),
+        Some(Box::new(CompletionTokenStream::new(terms, payload))),
+    ))
+}
+
+/// The payload of the terms of a completion: the weight in big endian
+/// followed by the value.
+fn encode_payload(weight: i64, value: &str) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(WEIGHT_BYTES + value.len());
+    payload.extend_from_slice(&weight.to_be_bytes());
+    payload.extend_from_slice(value.as_bytes());
+    payload
+}
+
+/// Returns the weight and the value of a completion from the payload of one
+/// of its terms.
+pub(crate) fn decode_payload(payload: &[u8]) -> Result<(i64, String)> {
+    if payload.len() < WEIGHT_BYTES {
+        bail!(ErrorKind::CorruptIndex(format!(
+            "completion payload too short: {} bytes",
+            payload.len()
+        )));
+    }
+    let mut weight = [0u8; WEIGHT_BYTES];
+    weight.copy_from_slice(&payload[..WEIGHT_BYTES]);
+    let value = String::from_utf8(payload[WEIGHT_BYTES..].to_vec())?;
+    Ok((i64::from_be_bytes(weight), value))
+}
+
+macro_rules! impl_completion_fieldable {
+    ($t:ty) => {
+        impl Fieldable for $t {
+            fn name(&self) -> &str {
+                self.field.name()
+            }
+
+            fn field_type(&self) -> &FieldType {
+                self.field.field_type()
+            }
+
+            fn boost(&self) -> f32 {
+                self.field.boost()
+            }
+
+            fn field_data(&self) -> Option<&VariantValue> {
+                self.field.field_data()
+            }
+
+            fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+                self.field.token_stream()
+            }
+
+            fn binary_value(&self) -> Option<&[u8]> {
+                None
+            }
+
+            fn string_value(&self) -> Option<&str> {
+                self.field.string_value()
+            }
+
+            fn numeric_value(&self) -> Option<Numeric> {
+                None
+            }
+
+            fn postings_format(&self) -> Option<&str> {
+                Some(COMPLETION_POSTINGS_FORMAT_NAME)
+            }
+        }
+    };
+}
+
+impl_completion_fieldable!(SuggestField);
+impl_completion_fieldable!(ContextSuggestField);
+
+/// Emits the terms of a completion, all with the same payload.
+#[derive(Debug)]
+struct CompletionTokenStream {
+    term_attribute: CharTermAttribute,
+    offset_attribute: OffsetAttribute,
+    position_attribute: PositionAttribute,
+    payload_attribute: PayloadAttribute,
+    terms: Vec<String>,
+    payload: Vec<u8>,
+    upto: usize,
+}
+
+impl CompletionTokenStream {
+    fn new(terms: Vec<String>, payload: Vec<u8>) -> Self {
+        CompletionTokenStream {
+            term_attribute: CharTermAttribute::new(),
+            offset_attribute: OffsetAttribute::new(),
+            position_attribute: PositionAttribute::new(),
+            payload_attribute: PayloadAttribute::new(Vec::with_capacity(0)),
+            upto: terms.len(),
+            terms,
+            payload,
+        }
+    }
+}
+
+impl TokenStream for CompletionTokenStream {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.upto >= self.terms.len() {
+            return Ok(false);
+        }
+        self.clear_attributes();
+
+        let term = &self.terms[self.upto];
+        self.term_attribute.append(term);
+        self.offset_attribute.set_offset(0, term.len())?;
+        self.payload_attribute.set_payload(self.payload.clone());
+        self.upto += 1;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        let final_offset = self.terms.last().map_or(0, String::len);
+        self.offset_attribute.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.upto = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attribute
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attribute
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attribute
+    }
+
+    fn position_attribute(&self) -> &PositionAttribute {
+        &self.position_attribute
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        Some(&mut self.payload_attribute)
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        Some(&self.payload_attribute)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attribute
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attribute
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::tests::{collect_tokens, TestToken};
+
+    fn tokens(field: &mut dyn Fieldable) -> Vec<TestToken> {
+        collect_tokens(field.token_stream().unwrap().as_mut())
+    }
+
+    #[test]
+    fn test_context_suggest_field_tokens() {
+        let mut field = ContextSuggestField::new("suggest", "ghost", 7, &["eu", "us"]).unwrap();
+        let tokens = tokens(&mut field);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].term, "eu\u{1d}ghost");
+        assert_eq!(tokens[1].term, "us\u{1d}ghost");
+        assert_eq!(
+            decode_payload(&tokens[0].payload).unwrap(),
+            (7, "ghost".to_string())
+        );
+        assert_eq!(tokens[0].payload, tokens[1].payload);
+
+        let mut field = ContextSuggestField::new("suggest", "ghost", 7, &[]).unwrap();
+        assert_eq!(tokens(&mut field)[0].term, "\u{1d}ghost");
+    }
+
+    #[test]
+    fn test_illegal_suggest_field() {
+        assert!(SuggestField::new("suggest", "gh\u{1d}ost", 1).is_err());
+        assert!(SuggestField::new("suggest", "ghost", -1).is_err());
+        assert!(SuggestField::new("suggest", "ghost", 1 << 31).is_err());
+        assert!(ContextSuggestField::new("suggest", "ghost", 1, &["e\u{1d}u"]).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use core::codec::postings::FieldsProducer;
+use core::codec::{Codec, PostingIterator, PostingIteratorFlags, SeekStatus};
+use core::codec::{TermIterator, Terms};
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::suggest::document::{decode_payload, CompletionQuery};
+use core::util::DocId;
+
+use error::Result;
+
+/// A completion of a document matched by a `CompletionQuery`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestScoreDoc {
+    pub doc: DocId,
+    pub score: f32,
+    /// The value of the suggest field.
+    pub key: String,
+    /// The context the completion matched with, for a `ContextQuery`.
+    pub context: Option<String>,
+}
+
+/// The top completions of a `CompletionQuery`, by descending score.
+#[derive(Debug, Clone)]
+pub struct TopSuggestDocs {
+    /// The number of documents whose completions were collected, which may
+    /// be less than the number of matching documents as the lookup stops
+    /// once the best completions are found.
+    pub total_hits: usize,
+    pub score_docs: Vec<SuggestScoreDoc>,
+}
+
+/// Runs `CompletionQuery`s against the suggest fields of an index.
+///
+/// The completions of the segments written with the
+/// `CompletionPostingsFormat` are visited by descending weight from the FST
+/// of the field, the lookup of a prefix stopping once no remaining
+/// completion can make it to the best ones. The other segments enumerate
+/// the term dictionary of the field from the prefixes of the query. In both
+/// cases, the weights and values of the completions are read from the
+/// payloads of their postings, and a document is suggested once, with its
+/// best scored completion.
+pub struct SuggestIndexSearcher<'a, C: Codec> {
+    reader: &'a dyn IndexReader<Codec = C>,
+}
+
+impl<'a, C: Codec> SuggestIndexSearcher<'a, C> {
+    pub fn new(reader: &'a dyn IndexReader<Codec = C>) -> SuggestIndexSearcher<'a, C> {
+        SuggestIndexSearcher { reader }
+    }
+
+    /// Returns the `num` best completions matched by `query`.
+    pub fn suggest(&self, query: &dyn CompletionQuery, num: usize) -> Result<TopSuggestDocs> {
+        let mut hits = HashMap::new();
+        for leaf in self.reader.leaves() {
+            Self::suggest_leaf(&leaf, query, num, &mut hits)?;
+        }
+
+        let total_hits = hits.len();
+        let mut score_docs: Vec<SuggestScoreDoc> = hits.into_iter().map(|(_, d)| d).collect();
+        score_docs.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then(a.doc.cmp(&b.doc))
+        });
+        score_docs.truncate(num);
+        Ok(TopSuggestDocs {
+            total_hits,
+            score_docs,
+        })
+    }
+
+    fn suggest_leaf(
+        leaf: &LeafReaderContext<'_, C>,
+        query: &dyn CompletionQuery,
+        num: usize,
+        hits: &mut HashMap<DocId, SuggestScoreDoc>,
+    ) -> Result<()> {
+        let terms = match leaf.reader.terms(query.field())? {
+            Some(terms) => terms,
+            None => return Ok(()),
+        };
+        let completions = leaf.reader.fields()?.completion_terms(query.field());
+        let mut collector = LeafCollector {
+            leaf,
+            terms_iter: terms.iterator()?,
+            hits: HashMap::new(),
+        };
+        for prefix in query.term_prefixes() {
+            match completions {
+                Some(ref completions) => {
+                    let max_boost = query.max_boost();
+                    completions.visit_by_weight(&prefix, |term, weight| {
+                        // the documents of the leaf are enough to fill the
+                        // top `num`, with scores no completion left can beat
+                        if num > 0
+                            && collector.hits.len() >= num
+                            && weight as f32 * max_boost <= collector.min_top_score(num)
+                        {
+                            return Ok(false);
+                        }
+                        if let Some((boost, context)) = query.accept(term) {
+                            if collector.terms_iter.seek_exact(term)? {
+                                collector.collect(boost, context)?;
+                            }
+                        }
+                        Ok(true)
+                    })?;
+                }
+                None => {
+                    collector.terms_iter = terms.iterator()?;
+                    if collector.terms_iter.seek_ceil(&prefix)? == SeekStatus::End {
+                        continue;
+                    }
+                    loop {
+                        let term = collector.terms_iter.term()?.to_vec();
+                        if !term.starts_with(&prefix) {
+                            break;
+                        }
+                        if let Some((boost, context)) = query.accept(&term) {
+                            collector.collect(boost, context)?;
+                        }
+                        if collector.terms_iter.next()?.is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        hits.extend(collector.hits);
+        Ok(())
+    }
+}
+
+/// Collects the best completion of each document of a leaf.
+struct LeafCollector<'a, 'b, C: Codec, T: TermIterator> {
+    leaf: &'a LeafReaderContext<'b, C>,
+    terms_iter: T,
+    hits: HashMap<DocId, SuggestScoreDoc>,
+}
+
+impl<'a, 'b, C: Codec, T: TermIterator> LeafCollector<'a, 'b, C, T> {
+    /// Collects the completions of the current term, whose weights are
+    /// boosted by `boost`.
+    fn collect(&mut self, boost: f32, context: Option<String>) -> Result<()> {
+        let live_docs = self.leaf.reader.live_docs();
+        let mut postings = self
+            .terms_iter
+            .postings_with_flags(PostingIteratorFlags::PAYLOADS)?;
+        loop {
+            let doc = postings.next()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            if !live_docs.get(doc as usize)? {
+                continue;
+            }
+            for _ in 0..postings.freq()? {
+                postings.next_position()?;
+                let (weight, key) = decode_payload(&postings.payload()?)?;
+                let hit = SuggestScoreDoc {
+                    doc: self.leaf.doc_base + doc,
+                    score: weight as f32 * boost,
+                    key,
+                    context: context.clone(),
+                };
+                let best = self.hits.entry(hit.doc).or_insert_with(|| hit.clone());
+                if hit.score > best.score {
+                    *best = hit;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The lowest score of the `num` best documents collected so far.
+    fn min_top_score(&self, num: usize) -> f32 {
+        let mut scores: Vec<f32> = self.hits.values().map(|hit| hit.score).collect();
+        scores.sort_by(|a, b| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        scores[num - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Fieldable, StringField};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::store::directory::FSDirectory;
+    use core::suggest::document::{
+        ContextQuery, ContextSuggestField, PrefixCompletionQuery, SuggestField,
+    };
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_suggest_from_completion_postings_format() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let config = Arc::new(IndexWriterConfig::default());
+        let writer = IndexWriter::new(directory, config).unwrap();
+        let docs: &[(&str, i64, &[&str])] = &[
+            ("ghost", 4, &["eu"]),
+            ("ghostbusters", 10, &["us"]),
+            ("goat", 7, &["eu", "us"]),
+            ("ghoul", 2, &["eu"]),
+        ];
+        for (i, &(value, weight, contexts)) in docs.iter().enumerate() {
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(StringField::new("id", &i.to_string(), false)),
+                Box::new(SuggestField::new("suggest", value, weight).unwrap()),
+                Box::new(ContextSuggestField::new("region", value, weight, contexts).unwrap()),
+            ];
+            writer.add_document(doc).unwrap();
+            // two segments
+            if i == 1 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let check = |num_leaves: usize| {
+            let reader = writer.get_reader(true, false).unwrap();
+            let leaves = reader.leaves();
+            assert_eq!(leaves.len(), num_leaves);
+            for leaf in &leaves {
+                let fields = leaf.reader.fields().unwrap();
+                assert!(fields.completion_terms("suggest").is_some());
+                assert!(fields.completion_terms("region").is_some());
+                assert!(fields.completion_terms("id").is_none());
+            }
+
+            let searcher = SuggestIndexSearcher::new(&reader);
+            let keys = |top: &TopSuggestDocs| -> Vec<String> {
+                top.score_docs.iter().map(|d| d.key.clone()).collect()
+            };
+
+            let query = PrefixCompletionQuery::new("suggest", "gho");
+            let top = searcher.suggest(&query, 10).unwrap();
+            assert_eq!(keys(&top), vec!["ghostbusters", "ghost", "ghoul"]);
+            assert_eq!(top.score_docs[0].doc, 1);
+            assert!((top.score_docs[0].score - 10.0).abs() < ::std::f32::EPSILON);
+
+            let top = searcher.suggest(&query, 1).unwrap();
+            assert_eq!(keys(&top), vec!["ghostbusters"]);
+            // the lookup stops before the completions of "ghoul"
+            assert!(top.total_hits < 3);
+
+            let mut query = ContextQuery::new(PrefixCompletionQuery::new("region", "g"));
+            query.add_context("eu", 2.0, true).unwrap();
+            let top = searcher.suggest(&query, 2).unwrap();
+            assert_eq!(keys(&top), vec!["goat", "ghost"]);
+            assert!((top.score_docs[0].score - 14.0).abs() < ::std::f32::EPSILON);
+            assert_eq!(top.score_docs[0].context, Some("eu".to_string()));
+        };
+        check(2);
+
+        // the merged segment is written with the completion postings format too
+        writer.force_merge(1, true).unwrap();
+        writer.commit().unwrap();
+        check(1);
+    }
+}
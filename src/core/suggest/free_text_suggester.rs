@@ -0,0 +1,399 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
+
+use core::analysis::Analyzer;
+use core::suggest::{decode_weight, encode_weight, Input, Lookup, LookupResult};
+use core::util::fst::{
+    Arc, FstBuilder, InputType, OutputFactory, PositiveIntOutput, PositiveIntOutputFactory,
+    END_LABEL, FST,
+};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{ErrorKind, Result};
+
+/// The default order of the n-gram model, i.e. bigrams.
+pub const DEFAULT_GRAMS: usize = 2;
+
+/// The factor the score of an n-gram is multiplied by when backing off to
+/// the (n-1)-grams ("stupid backoff").
+pub const ALPHA: f64 = 0.4;
+
+/// Separates the tokens of an n-gram, reserved in the tokens.
+pub const SEPARATOR: u8 = 0x1e;
+
+/// Predicts the next words of a key from an n-gram language model of the
+/// texts it is built from, e.g. "ghost" suggests "ghost rider" if the
+/// bigram "ghost rider" is frequent, unlike the suggesters only completing
+/// the entries starting with the key.
+///
+/// The n-grams of the analyzed texts, up to `grams` tokens, are the keys of
+/// an FST whose outputs are their counts. The last token of the key is a
+/// prefix unless the key ends with chars dropped by the analyzer, e.g. a
+/// whitespace, in which case the next token is predicted. The completions
+/// of the longest n-gram ending the key are scored by their count divided
+/// by the count of their context, the shorter n-grams being tried as well
+/// with a score multiplied by `ALPHA` per backoff.
+///
+/// The weights and payloads of the inputs are ignored.
+///
+/// ```ignore
+/// let mut suggester = FreeTextSuggester::new(Box::new(SimpleAnalyzer::default()));
+/// suggester.build(&mut texts.into_iter())?;
+/// let results = suggester.lookup("ghost ", 5)?;
+/// ```
+pub struct FreeTextSuggester {
+    index_analyzer: Box<dyn Analyzer>,
+    query_analyzer: Option<Box<dyn Analyzer>>,
+    grams: usize,
+    fst: Option<FST<PositiveIntOutputFactory>>,
+    total_tokens: i64,
+    count: usize,
+}
+
+impl FreeTextSuggester {
+    /// Creates a suggester of bigrams analyzing both the texts and the keys
+    /// with `analyzer`.
+    pub fn new(analyzer: Box<dyn Analyzer>) -> FreeTextSuggester {
+        FreeTextSuggester {
+            index_analyzer: analyzer,
+            query_analyzer: None,
+            grams: DEFAULT_GRAMS,
+            fst: None,
+            total_tokens: 0,
+            count: 0,
+        }
+    }
+
+    /// Sets a different analyzer for the keys than for the texts.
+    pub fn set_query_analyzer(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.query_analyzer = Some(analyzer);
+    }
+
+    /// Sets the maximal number of tokens of the n-grams, at least 1. Takes
+    /// effect on the next `build`.
+    pub fn set_grams(&mut self, grams: usize) -> Result<()> {
+        if grams < 1 {
+            bail!(ErrorKind::IllegalArgument("grams must be >= 1".into()));
+        }
+        self.grams = grams;
+        Ok(())
+    }
+
+    fn query_analyzer(&self) -> &dyn Analyzer {
+        match self.query_analyzer {
+            Some(ref analyzer) => analyzer.as_ref(),
+            None => self.index_analyzer.as_ref(),
+        }
+    }
+
+    /// Returns the tokens of `text`, and whether its last token ends it.
+    fn analyze(analyzer: &dyn Analyzer, text: &str) -> Result<(Vec<Vec<u8>>, bool)> {
+        let mut stream = analyzer.token_stream_from_str("", text)?;
+        stream.reset()?;
+        let mut tokens = vec![];
+        let mut max_end_offset = 0;
+        while stream.increment_token()? {
+            let token = stream
+                .term_bytes_attribute()
+                .get_bytes_ref()
+                .bytes()
+                .to_vec();
+            if token.contains(&SEPARATOR) {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "tokens must not contain the separator byte {:#x}: {:?}",
+                    SEPARATOR,
+                    String::from_utf8_lossy(&token)
+                )));
+            }
+            if !token.is_empty() {
+                tokens.push(token);
+            }
+            max_end_offset = max_end_offset.max(stream.offset_attribute().end_offset());
+        }
+        stream.end()?;
+        let ends_text = stream.offset_attribute().end_offset() <= max_end_offset;
+        Ok((tokens, ends_text))
+    }
+}
+
+impl Lookup for FreeTextSuggester {
+    fn build(&mut self, inputs: &mut dyn Iterator<Item = Input>) -> Result<()> {
+        let mut counts: BTreeMap<Vec<u8>, i64> = BTreeMap::new();
+        let mut total_tokens = 0;
+        let mut count = 0;
+        for input in inputs {
+            let (tokens, _) = Self::analyze(self.index_analyzer.as_ref(), &input.term)?;
+            total_tokens += tokens.len() as i64;
+            for n in 1..=self.grams.min(tokens.len()) {
+                for window in tokens.windows(n) {
+                    *counts.entry(window.join(&SEPARATOR)).or_insert(0) += 1;
+                }
+            }
+            count += 1;
+        }
+
+        let mut builder = FstBuilder::new(InputType::Byte1, PositiveIntOutputFactory::new());
+        builder.init();
+        let mut scratch = IntsRefBuilder::new();
+        for (gram, gram_count) in &counts {
+            builder.add(
+                to_ints_ref(gram, &mut scratch),
+                PositiveIntOutput::new(encode_weight(*gram_count)?),
+            )?;
+        }
+        self.fst = builder.finish()?;
+        self.total_tokens = total_tokens;
+        self.count = count;
+        Ok(())
+    }
+
+    fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(vec![]),
+        };
+        let (tokens, ends_key) = Self::analyze(self.query_analyzer(), key)?;
+        if num == 0 || tokens.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // last_grams[i] holds the last i + 1 tokens, the last one being a
+        // prefix, or empty to predict the next token
+        let mut last_tokens: Vec<&[u8]> = tokens.iter().map(Vec::as_slice).collect();
+        if !ends_key {
+            last_tokens.push(&[]);
+        }
+        let mut last_grams = vec![];
+        for n in 1..=self.grams.min(last_tokens.len()) {
+            last_grams.push(last_tokens[last_tokens.len() - n..].join(&SEPARATOR));
+        }
+
+        let mut results: Vec<(f64, Vec<u8>)> = vec![];
+        // the final tokens suggested by the longer n-grams already
+        let mut seen: HashSet<Vec<u8>> = HashSet::new();
+        let mut backoff = 1.0;
+        for gram in last_grams.iter().rev() {
+            let context_len = gram
+                .iter()
+                .rposition(|&b| b == SEPARATOR)
+                .map_or(0, |pos| pos + 1);
+            let context_count = if context_len == 0 {
+                self.total_tokens
+            } else {
+                match fst.get(&gram[..context_len - 1])? {
+                    Some(output) => decode_weight(output.get()),
+                    None => {
+                        backoff *= ALPHA;
+                        continue;
+                    }
+                }
+            };
+
+            let completions = top_n(fst, gram, num, |input| {
+                !input[gram.len()..].contains(&SEPARATOR) && !seen.contains(&input[context_len..])
+            })?;
+            for (input, gram_count) in completions {
+                let score = backoff * gram_count as f64 / context_count as f64;
+                seen.insert(input[context_len..].to_vec());
+                results.push((score, input));
+            }
+            backoff *= ALPHA;
+        }
+
+        results.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.1.cmp(&b.1))
+        });
+        results.truncate(num);
+        results
+            .into_iter()
+            .map(|(score, input)| {
+                let key: Vec<u8> = input
+                    .into_iter()
+                    .map(|b| if b == SEPARATOR { b' ' } else { b })
+                    .collect();
+                Ok(LookupResult {
+                    key: String::from_utf8(key)?,
+                    highlight_key: None,
+                    value: (score * i64::max_value() as f64) as i64,
+                    payload: None,
+                })
+            })
+            .collect()
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// A path from the root of the FST, ordered by ascending cost then input
+/// in a max heap.
+struct FSTPath {
+    arc: Arc<PositiveIntOutput>,
+    cost: i64,
+    input: Vec<u8>,
+}
+
+impl PartialEq for FSTPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FSTPath {}
+
+impl PartialOrd for FSTPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FSTPath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .cmp(&self.cost)
+            .then_with(|| other.input.cmp(&self.input))
+    }
+}
+
+/// Returns the `num` most frequent n-grams starting with `prefix` and
+/// accepted by `accept`, with their counts.
+fn top_n<F>(
+    fst: &FST<PositiveIntOutputFactory>,
+    prefix: &[u8],
+    num: usize,
+    mut accept: F,
+) -> Result<Vec<(Vec<u8>, i64)>>
+where
+    F: FnMut(&[u8]) -> bool,
+{
+    let mut reader = fst.bytes_reader();
+    let mut arc = fst.root_arc();
+    let mut cost = 0;
+    for &label in prefix {
+        arc = match fst.find_target_arc(i32::from(label), &arc, &mut reader)? {
+            Some(arc) => arc,
+            None => return Ok(vec![]),
+        };
+        if let Some(ref output) = arc.output {
+            cost += output.get();
+        }
+    }
+
+    let mut results = Vec::with_capacity(num);
+    let mut queue = BinaryHeap::new();
+    queue.push(FSTPath {
+        arc,
+        cost,
+        input: prefix.to_vec(),
+    });
+    while results.len() < num {
+        let path = match queue.pop() {
+            Some(path) => path,
+            None => break,
+        };
+        if path.arc.label == END_LABEL {
+            if accept(&path.input) {
+                results.push((path.input, decode_weight(path.cost)));
+            }
+            continue;
+        }
+        let mut arc = fst.read_first_target_arc(&path.arc, &mut reader)?;
+        loop {
+            let mut input = path.input.clone();
+            if arc.label != END_LABEL {
+                input.push(arc.label as u8);
+            }
+            let cost = match arc.output {
+                Some(ref output) => fst
+                    .outputs()
+                    .add(&PositiveIntOutput::new(path.cost), output),
+                None => PositiveIntOutput::new(path.cost),
+            };
+            let is_last = arc.is_last();
+            queue.push(FSTPath {
+                arc: arc.clone(),
+                cost: cost.get(),
+                input,
+            });
+            if is_last {
+                break;
+            }
+            fst.read_next_arc(&mut arc, &mut reader)?;
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::SimpleAnalyzer;
+
+    fn suggester(grams: usize) -> FreeTextSuggester {
+        let mut suggester = FreeTextSuggester::new(Box::new(SimpleAnalyzer::default()));
+        suggester.set_grams(grams).unwrap();
+        let mut inputs = vec![
+            "foo bar baz blah",
+            "boo foo bar foo bee",
+            "foo bar",
+            "the ghost rider",
+        ]
+        .into_iter()
+        .map(|text| Input::new(text, 1));
+        suggester.build(&mut inputs).unwrap();
+        suggester
+    }
+
+    fn keys(results: &[LookupResult]) -> Vec<&str> {
+        results.iter().map(|r| r.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_predict_next_token() {
+        let suggester = suggester(2);
+        assert_eq!(suggester.count(), 4);
+
+        // "foo" is followed by "bar" 3 times out of 4, then by "bee"
+        let results = suggester.lookup("foo ", 5).unwrap();
+        assert_eq!(keys(&results)[..2], ["foo bar", "foo bee"]);
+        // the unigrams follow, backed off
+        assert!(results.len() > 2);
+        assert!(!keys(&results)[2..].contains(&"bar"));
+        assert!(results[0].value > results[1].value);
+        assert!(results[1].value > results[2].value);
+
+        // the last token is a prefix
+        let results = suggester.lookup("foo b", 2).unwrap();
+        assert_eq!(keys(&results), vec!["foo bar", "foo bee"]);
+
+        let results = suggester.lookup("ghost r", 1).unwrap();
+        assert_eq!(keys(&results), vec!["ghost rider"]);
+    }
+
+    #[test]
+    fn test_unigrams() {
+        let suggester = suggester(1);
+        let results = suggester.lookup("fo", 5).unwrap();
+        assert_eq!(keys(&results), vec!["foo"]);
+        let results = suggester.lookup("b", 2).unwrap();
+        assert_eq!(keys(&results), vec!["bar", "baz"]);
+        assert!(suggester.lookup("", 2).unwrap().is_empty());
+    }
+}
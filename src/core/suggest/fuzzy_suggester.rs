@@ -0,0 +1,203 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::query::MAXIMUM_SUPPORTED_DISTANCE;
+use core::suggest::{AnalyzingSuggester, Input, Lookup, LookupResult};
+use core::util::automaton::{
+    Automata, Automaton, LevenshteinAutomata, Operations, DEFAULT_MAX_DETERMINIZED_STATES,
+};
+
+use error::{ErrorKind, Result};
+
+pub const DEFAULT_MAX_EDITS: u32 = 1;
+pub const DEFAULT_TRANSPOSITIONS: bool = true;
+pub const DEFAULT_NON_FUZZY_PREFIX: usize = 1;
+pub const DEFAULT_MIN_FUZZY_LENGTH: usize = 3;
+
+/// An `AnalyzingSuggester` which also completes the keys whose analyzed form
+/// is within `max_edits` edits of the analyzed form of a prefix, so that
+/// "ghsot r" still suggests "Ghost Rider".
+///
+/// The first `non_fuzzy_prefix` bytes of the analyzed key must match
+/// exactly and keys shorter than `min_fuzzy_length` bytes are not fuzzy at
+/// all. The edits don't lower the weights of the completions, an exact
+/// prefix match is not preferred over a fuzzy one.
+///
+/// ```ignore
+/// let mut suggester = FuzzySuggester::new(AnalyzingSuggester::new(analyzer));
+/// suggester.build(&mut inputs.into_iter())?;
+/// let results = suggester.lookup("ghsot", 5)?;
+/// ```
+pub struct FuzzySuggester {
+    suggester: AnalyzingSuggester,
+    max_edits: u32,
+    transpositions: bool,
+    non_fuzzy_prefix: usize,
+    min_fuzzy_length: usize,
+}
+
+impl FuzzySuggester {
+    /// Wraps `suggester`, whose analyzers and options are used for the
+    /// analysis of the entries and the keys.
+    pub fn new(suggester: AnalyzingSuggester) -> FuzzySuggester {
+        FuzzySuggester {
+            suggester,
+            max_edits: DEFAULT_MAX_EDITS,
+            transpositions: DEFAULT_TRANSPOSITIONS,
+            non_fuzzy_prefix: DEFAULT_NON_FUZZY_PREFIX,
+            min_fuzzy_length: DEFAULT_MIN_FUZZY_LENGTH,
+        }
+    }
+
+    /// Sets the maximum number of edits, at most `MAXIMUM_SUPPORTED_DISTANCE`.
+    pub fn set_max_edits(&mut self, max_edits: u32) -> Result<()> {
+        if max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "max_edits must be between 0 and {}",
+                MAXIMUM_SUPPORTED_DISTANCE
+            )));
+        }
+        self.max_edits = max_edits;
+        Ok(())
+    }
+
+    /// If true (the default), the transposition of two adjacent bytes counts
+    /// as a single edit.
+    pub fn set_transpositions(&mut self, transpositions: bool) {
+        self.transpositions = transpositions;
+    }
+
+    /// Sets the length of the prefix of the analyzed key which must match
+    /// exactly.
+    pub fn set_non_fuzzy_prefix(&mut self, non_fuzzy_prefix: usize) {
+        self.non_fuzzy_prefix = non_fuzzy_prefix;
+    }
+
+    /// Sets the minimum length of the analyzed key for edits to be allowed.
+    pub fn set_min_fuzzy_length(&mut self, min_fuzzy_length: usize) {
+        self.min_fuzzy_length = min_fuzzy_length;
+    }
+
+    pub fn suggester(&self) -> &AnalyzingSuggester {
+        &self.suggester
+    }
+
+    /// Replaces each analyzed form accepted by `automaton` with an automaton
+    /// accepting the forms within `max_edits` edits of it.
+    fn to_levenshtein_automata(&self, automaton: &Automaton) -> Result<Automaton> {
+        let mut subs = vec![];
+        for form in Operations::finite_strings(automaton, None) {
+            if form.len() <= self.non_fuzzy_prefix || form.len() < self.min_fuzzy_length {
+                let bytes: Vec<u8> = form.iter().map(|&l| l as u8).collect();
+                subs.push(Automata::make_binary(&bytes));
+            } else {
+                let prefix: Vec<u8> = form[..self.non_fuzzy_prefix]
+                    .iter()
+                    .map(|&l| l as u8)
+                    .collect();
+                let lev = LevenshteinAutomata::with_labels(
+                    form[self.non_fuzzy_prefix..].to_vec(),
+                    255,
+                    self.transpositions,
+                );
+                subs.push(Operations::concatenate(&[
+                    Automata::make_binary(&prefix),
+                    lev.to_automaton(self.max_edits)?,
+                ]));
+            }
+        }
+        if subs.is_empty() {
+            return Ok(Automata::make_empty());
+        }
+        Operations::determinize(&Operations::union(&subs), DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+}
+
+impl Lookup for FuzzySuggester {
+    fn build(&mut self, inputs: &mut dyn Iterator<Item = Input>) -> Result<()> {
+        self.suggester.build(inputs)
+    }
+
+    fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>> {
+        let automaton = self.suggester.lookup_automaton(key)?;
+        let automaton = self.to_levenshtein_automata(&automaton)?;
+        self.suggester.lookup_with_automaton(key, &automaton, num)
+    }
+
+    fn count(&self) -> usize {
+        self.suggester.count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::StandardAnalyzer;
+
+    fn suggester() -> FuzzySuggester {
+        let mut suggester = FuzzySuggester::new(AnalyzingSuggester::new(Box::new(
+            StandardAnalyzer::default(),
+        )));
+        let mut inputs = vec![
+            Input::new("Ghost Rider", 100),
+            Input::new("ghostbusters", 80),
+            Input::new("Golden Eye", 200),
+            Input::new("goat", 10),
+        ]
+        .into_iter();
+        suggester.build(&mut inputs).unwrap();
+        suggester
+    }
+
+    fn keys(suggester: &FuzzySuggester, key: &str) -> Vec<String> {
+        suggester
+            .lookup(key, 10)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.key)
+            .collect()
+    }
+
+    #[test]
+    fn test_one_edit() {
+        let suggester = suggester();
+        assert_eq!(suggester.count(), 4);
+        // transposition
+        assert_eq!(keys(&suggester, "ghsot r"), vec!["Ghost Rider"]);
+        // substitution
+        assert_eq!(
+            keys(&suggester, "ghist"),
+            vec!["Ghost Rider", "ghostbusters"]
+        );
+        // the first byte is not fuzzy
+        assert!(keys(&suggester, "xhost").is_empty());
+        // too short to be fuzzy
+        assert_eq!(keys(&suggester, "gh"), vec!["Ghost Rider", "ghostbusters"]);
+        assert_eq!(keys(&suggester, "ghx"), vec!["Ghost Rider", "ghostbusters"]);
+        assert!(keys(&suggester, "ghoxtbx").is_empty());
+    }
+
+    #[test]
+    fn test_two_edits() {
+        let mut suggester = suggester();
+        assert!(keys(&suggester, "gohsbt").is_empty());
+        suggester.set_max_edits(2).unwrap();
+        // "host" is two edits away from "ohsbt" as well
+        assert_eq!(
+            keys(&suggester, "gohsbt"),
+            vec!["Ghost Rider", "ghostbusters"]
+        );
+        assert_eq!(keys(&suggester, "gohsbu"), vec!["ghostbusters"]);
+        assert!(suggester.set_max_edits(3).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod document;
+pub mod spell;
+
+mod analyzing_infix_suggester;
+
+pub use self::analyzing_infix_suggester::*;
+
+mod analyzing_suggester;
+
+pub use self::analyzing_suggester::*;
+
+mod free_text_suggester;
+
+pub use self::free_text_suggester::*;
+
+mod fuzzy_suggester;
+
+pub use self::fuzzy_suggester::*;
+
+use error::{ErrorKind, Result};
+
+/// An entry a suggester is built from: the surface form returned when the
+/// entry is suggested, its weight and an optional payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub term: String,
+    pub weight: i64,
+    pub payload: Option<Vec<u8>>,
+}
+
+impl Input {
+    pub fn new(term: &str, weight: i64) -> Input {
+        Input {
+            term: term.to_string(),
+            weight,
+            payload: None,
+        }
+    }
+
+    pub fn with_payload(term: &str, weight: i64, payload: Vec<u8>) -> Input {
+        Input {
+            term: term.to_string(),
+            weight,
+            payload: Some(payload),
+        }
+    }
+}
+
+/// A suggestion returned by a `Lookup`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupResult {
+    /// The surface form of the suggested entry.
+    pub key: String,
+    /// The surface form with the matched parts marked up, for the
+    /// suggesters which highlight them.
+    pub highlight_key: Option<String>,
+    /// The weight of the suggested entry.
+    pub value: i64,
+    pub payload: Option<Vec<u8>>,
+}
+
+/// A suggester, returning the best completions of a prefix among the
+/// entries it was built from.
+pub trait Lookup {
+    /// Builds the suggester from `inputs`, replacing any previous entries.
+    fn build(&mut self, inputs: &mut dyn Iterator<Item = Input>) -> Result<()>;
+
+    /// Returns at most `num` completions of `key`, best first.
+    fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>>;
+
+    /// The number of entries the suggester was built from.
+    fn count(&self) -> usize;
+}
+
+/// Encodes a weight of `0..=i32::max_value()` as the cost of a FST output, so
+/// that the best weights have the lowest costs.
+pub(crate) fn encode_weight(weight: i64) -> Result<i64> {
+    if weight < 0 || weight > i64::from(i32::max_value()) {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "weight must be in 0..={}, got {}",
+            i32::max_value(),
+            weight
+        )));
+    }
+    Ok(i64::from(i32::max_value()) - weight)
+}
+
+/// Decodes the weight of a FST output cost encoded by `encode_weight`.
+pub(crate) fn decode_weight(cost: i64) -> i64 {
+    i64::from(i32::max_value()) - cost
+}
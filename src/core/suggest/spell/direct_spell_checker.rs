@@ -0,0 +1,269 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use core::codec::{Codec, TermIterator};
+use core::doc::Term;
+use core::index::reader::IndexReader;
+use core::search::query::{FuzzyTermIterator, MAXIMUM_SUPPORTED_DISTANCE};
+use core::suggest::spell::{doc_freq, SuggestMode, SuggestWord};
+
+use error::{ErrorKind, Result};
+
+pub const DEFAULT_ACCURACY: f32 = 0.5;
+pub const DEFAULT_MAX_EDITS: u32 = MAXIMUM_SUPPORTED_DISTANCE;
+pub const DEFAULT_MIN_PREFIX: usize = 1;
+pub const DEFAULT_THRESHOLD_FREQUENCY: f32 = 0.01;
+pub const DEFAULT_MIN_QUERY_LENGTH: usize = 4;
+pub const DEFAULT_MAX_QUERY_FREQUENCY: f32 = 0.01;
+
+/// Proposes corrections of a term ("did you mean") from the terms of its
+/// field in the index, within a few edits of it, so no separate spell
+/// index needs to be built and kept in sync.
+///
+/// The candidates are enumerated from the term dictionary of each segment
+/// like a `FuzzyQuery` does, scored by their similarity
+/// `1 - edits / min(term_len, candidate_len)` and filtered by the accuracy
+/// and by their doc frequency.
+///
+/// The frequencies are either absolute, if at least 1, or fractions of the
+/// number of documents of the index.
+#[derive(Debug, Clone)]
+pub struct DirectSpellChecker {
+    accuracy: f32,
+    max_edits: u32,
+    min_prefix: usize,
+    threshold_frequency: f32,
+    min_query_length: usize,
+    max_query_frequency: f32,
+    lower_case_terms: bool,
+}
+
+impl Default for DirectSpellChecker {
+    fn default() -> Self {
+        DirectSpellChecker {
+            accuracy: DEFAULT_ACCURACY,
+            max_edits: DEFAULT_MAX_EDITS,
+            min_prefix: DEFAULT_MIN_PREFIX,
+            threshold_frequency: DEFAULT_THRESHOLD_FREQUENCY,
+            min_query_length: DEFAULT_MIN_QUERY_LENGTH,
+            max_query_frequency: DEFAULT_MAX_QUERY_FREQUENCY,
+            lower_case_terms: true,
+        }
+    }
+}
+
+impl DirectSpellChecker {
+    /// Sets the minimal similarity of the corrections, from 0 to 1.
+    pub fn set_accuracy(&mut self, accuracy: f32) {
+        self.accuracy = accuracy;
+    }
+
+    /// Sets the maximum number of edits of the corrections, from 1 to
+    /// `MAXIMUM_SUPPORTED_DISTANCE`.
+    pub fn set_max_edits(&mut self, max_edits: u32) -> Result<()> {
+        if max_edits < 1 || max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "max_edits must be between 1 and {}",
+                MAXIMUM_SUPPORTED_DISTANCE
+            )));
+        }
+        self.max_edits = max_edits;
+        Ok(())
+    }
+
+    /// Sets the number of leading chars the corrections share with the
+    /// term, which also bounds the enumerated terms.
+    pub fn set_min_prefix(&mut self, min_prefix: usize) {
+        self.min_prefix = min_prefix;
+    }
+
+    /// Sets the minimal doc frequency of the corrections.
+    pub fn set_threshold_frequency(&mut self, threshold_frequency: f32) -> Result<()> {
+        if threshold_frequency >= 1.0 && threshold_frequency.fract() != 0.0 {
+            bail!(ErrorKind::IllegalArgument(
+                "fractional threshold_frequency must be < 1".into()
+            ));
+        }
+        self.threshold_frequency = threshold_frequency;
+        Ok(())
+    }
+
+    /// Sets the minimal length, in chars, of the terms to correct.
+    pub fn set_min_query_length(&mut self, min_query_length: usize) {
+        self.min_query_length = min_query_length;
+    }
+
+    /// Sets the maximal doc frequency of the terms to correct, more frequent
+    /// terms being assumed to be spelled right.
+    pub fn set_max_query_frequency(&mut self, max_query_frequency: f32) -> Result<()> {
+        if max_query_frequency >= 1.0 && max_query_frequency.fract() != 0.0 {
+            bail!(ErrorKind::IllegalArgument(
+                "fractional max_query_frequency must be < 1".into()
+            ));
+        }
+        self.max_query_frequency = max_query_frequency;
+        Ok(())
+    }
+
+    /// If true (the default), the terms are lower cased before being
+    /// corrected, which suits the fields indexed in lower case.
+    pub fn set_lower_case_terms(&mut self, lower_case_terms: bool) {
+        self.lower_case_terms = lower_case_terms;
+    }
+
+    /// Returns at most `num` corrections of `term`, the best first, with the
+    /// accuracy of the spell checker.
+    pub fn suggest_similar<C: Codec>(
+        &self,
+        term: &Term,
+        num: usize,
+        reader: &dyn IndexReader<Codec = C>,
+        mode: SuggestMode,
+    ) -> Result<Vec<SuggestWord>> {
+        self.suggest_similar_with_accuracy(term, num, reader, mode, self.accuracy)
+    }
+
+    /// Returns at most `num` corrections of `term`, the best first, at least
+    /// as similar to it as `accuracy`.
+    pub fn suggest_similar_with_accuracy<C: Codec>(
+        &self,
+        term: &Term,
+        num: usize,
+        reader: &dyn IndexReader<Codec = C>,
+        mode: SuggestMode,
+        accuracy: f32,
+    ) -> Result<Vec<SuggestWord>> {
+        let mut text = term.text()?;
+        if num == 0 || text.chars().count() < self.min_query_length {
+            return Ok(vec![]);
+        }
+        if self.lower_case_terms {
+            text = text.to_lowercase();
+        }
+        let term = Term::new(term.field().to_string(), text.as_bytes().to_vec());
+
+        let doc_freq = doc_freq(reader, &term)?;
+        if mode == SuggestMode::WhenNotInIndex && doc_freq > 0 {
+            return Ok(vec![]);
+        }
+        let max_doc = reader.max_doc();
+        if doc_freq > 0 && doc_freq > absolute_frequency(self.max_query_frequency, max_doc, true) {
+            return Ok(vec![]);
+        }
+
+        // the corrections must be more frequent than this
+        let mut min_freq = if mode == SuggestMode::MorePopular {
+            doc_freq
+        } else {
+            0
+        };
+        if self.threshold_frequency > 0.0 {
+            min_freq = min_freq.max(absolute_frequency(self.threshold_frequency, max_doc, false));
+        }
+
+        let mut candidates = self.candidates(&term, &text, reader, min_freq, accuracy)?;
+        candidates.sort_by(SuggestWord::compare);
+        candidates.truncate(num);
+        Ok(candidates)
+    }
+
+    fn candidates<C: Codec>(
+        &self,
+        term: &Term,
+        text: &str,
+        reader: &dyn IndexReader<Codec = C>,
+        min_freq: i32,
+        accuracy: f32,
+    ) -> Result<Vec<SuggestWord>> {
+        // the summed doc freq and the similarity of each candidate
+        let mut freqs: HashMap<Vec<u8>, (i32, f32)> = HashMap::new();
+        for leaf in reader.leaves() {
+            let terms = match leaf.reader.terms(term.field())? {
+                Some(terms) => terms,
+                None => continue,
+            };
+            let mut terms_iter =
+                FuzzyTermIterator::new(&terms, text, self.max_edits, self.min_prefix, true)?;
+            while let Some(candidate) = terms_iter.next()? {
+                if candidate != term.bytes {
+                    let doc_freq = terms_iter.doc_freq()?;
+                    freqs.entry(candidate).or_insert((0, terms_iter.boost())).0 += doc_freq;
+                }
+            }
+        }
+
+        let mut words = vec![];
+        for (candidate, (freq, score)) in freqs {
+            if freq <= min_freq || score < accuracy {
+                continue;
+            }
+            let string = match String::from_utf8(candidate) {
+                Ok(string) => string,
+                Err(_) => continue,
+            };
+            words.push(SuggestWord {
+                string,
+                score,
+                freq,
+            });
+        }
+        Ok(words)
+    }
+}
+
+/// Converts a frequency which is a fraction of `max_doc` if below 1, rounded
+/// up or down, to an absolute one.
+fn absolute_frequency(frequency: f32, max_doc: i32, round_up: bool) -> i32 {
+    if frequency >= 1.0 {
+        frequency as i32
+    } else if round_up {
+        (frequency * max_doc as f32).ceil() as i32
+    } else {
+        (frequency * max_doc as f32) as i32 - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absolute_frequency() {
+        assert_eq!(absolute_frequency(3.0, 1000, true), 3);
+        assert_eq!(absolute_frequency(0.01, 1000, true), 10);
+        assert_eq!(absolute_frequency(0.01, 50, true), 1);
+        assert_eq!(absolute_frequency(0.01, 1000, false), 9);
+        // any term of a small index passes the threshold
+        assert_eq!(absolute_frequency(0.01, 50, false), -1);
+    }
+
+    #[test]
+    fn test_suggest_word_order() {
+        let word = |string: &str, score, freq| SuggestWord {
+            string: string.to_string(),
+            score,
+            freq,
+        };
+        let mut words = vec![
+            word("lucent", 0.8, 3),
+            word("lucene", 0.8, 7),
+            word("lucine", 0.8, 7),
+            word("lucerne", 0.9, 1),
+        ];
+        words.sort_by(SuggestWord::compare);
+        let strings: Vec<&str> = words.iter().map(|w| w.string.as_str()).collect();
+        assert_eq!(strings, vec!["lucerne", "lucene", "lucine", "lucent"]);
+    }
+}
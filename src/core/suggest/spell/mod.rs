@@ -0,0 +1,78 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod direct_spell_checker;
+
+pub use self::direct_spell_checker::*;
+
+mod word_break_spell_checker;
+
+pub use self::word_break_spell_checker::*;
+
+use std::cmp::Ordering;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::IndexReader;
+
+use error::Result;
+
+/// A correction proposed by a spell checker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestWord {
+    /// The suggested term.
+    pub string: String,
+    /// How close the suggested term is to the checked one, from 0 to 1.
+    pub score: f32,
+    /// The doc frequency of the suggested term.
+    pub freq: i32,
+}
+
+impl SuggestWord {
+    /// Orders the best suggestions first: by descending score, then by
+    /// descending frequency, then by term.
+    pub fn compare(&self, other: &SuggestWord) -> Ordering {
+        other
+            .score
+            .partial_cmp(&self.score)
+            .unwrap_or(Ordering::Equal)
+            .then(other.freq.cmp(&self.freq))
+            .then_with(|| self.string.cmp(&other.string))
+    }
+}
+
+/// When the spell checkers propose corrections of a term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestMode {
+    /// Only for the terms missing from the index.
+    WhenNotInIndex,
+    /// For any term, even the ones in the index.
+    Always,
+    /// For any term, the corrections being more frequent than the term.
+    MorePopular,
+}
+
+impl Default for SuggestMode {
+    fn default() -> Self {
+        SuggestMode::WhenNotInIndex
+    }
+}
+
+/// The number of documents of `reader` containing `term`.
+pub(crate) fn doc_freq<C: Codec>(reader: &dyn IndexReader<Codec = C>, term: &Term) -> Result<i32> {
+    let mut doc_freq = 0;
+    for leaf in reader.leaves() {
+        doc_freq += leaf.reader.doc_freq(term)?;
+    }
+    Ok(doc_freq)
+}
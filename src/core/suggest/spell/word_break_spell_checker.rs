@@ -0,0 +1,446 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::index::reader::IndexReader;
+use core::suggest::spell::{doc_freq, SuggestMode, SuggestWord};
+
+use error::{ErrorKind, Result};
+
+pub const DEFAULT_MIN_SUGGESTION_FREQUENCY: i32 = 1;
+pub const DEFAULT_MAX_COMBINE_WORD_LENGTH: usize = 20;
+pub const DEFAULT_MIN_BREAK_WORD_LENGTH: usize = 1;
+pub const DEFAULT_MAX_CHANGES: usize = 1;
+pub const DEFAULT_MAX_EVALUATIONS: usize = 1000;
+
+/// How the word break suggestions are ordered, always the ones with the
+/// fewest breaks first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakSuggestionSortMethod {
+    /// Then by the summed frequency of the words, descending.
+    NumChangesThenSummedFrequency,
+    /// Then by the maximum frequency of the words, descending.
+    NumChangesThenMaxFrequency,
+}
+
+/// A suggestion combining adjacent terms into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CombineSuggestion {
+    /// The indexes of the combined terms in the checked terms.
+    pub original_term_indexes: Vec<usize>,
+    /// The combined term, its score being the number of changes.
+    pub suggestion: SuggestWord,
+}
+
+/// Repairs the words a query splits or joins differently than the index,
+/// e.g. "wi fi router" and "wifi router", by breaking a term into terms
+/// present in the index, or by combining adjacent terms into one.
+///
+/// The score of the suggested words is the number of breaks or joins they
+/// required, fewer being better.
+#[derive(Debug, Clone)]
+pub struct WordBreakSpellChecker {
+    min_suggestion_frequency: i32,
+    max_combine_word_length: usize,
+    min_break_word_length: usize,
+    max_changes: usize,
+    max_evaluations: usize,
+}
+
+impl Default for WordBreakSpellChecker {
+    fn default() -> Self {
+        WordBreakSpellChecker {
+            min_suggestion_frequency: DEFAULT_MIN_SUGGESTION_FREQUENCY,
+            max_combine_word_length: DEFAULT_MAX_COMBINE_WORD_LENGTH,
+            min_break_word_length: DEFAULT_MIN_BREAK_WORD_LENGTH,
+            max_changes: DEFAULT_MAX_CHANGES,
+            max_evaluations: DEFAULT_MAX_EVALUATIONS,
+        }
+    }
+}
+
+impl WordBreakSpellChecker {
+    /// Sets the minimal doc frequency of the suggested words.
+    pub fn set_min_suggestion_frequency(&mut self, min_suggestion_frequency: i32) {
+        self.min_suggestion_frequency = min_suggestion_frequency;
+    }
+
+    /// Sets the maximal length, in chars, of a combined word.
+    pub fn set_max_combine_word_length(&mut self, max_combine_word_length: usize) {
+        self.max_combine_word_length = max_combine_word_length;
+    }
+
+    /// Sets the minimal length, in chars, of the words a term breaks into.
+    pub fn set_min_break_word_length(&mut self, min_break_word_length: usize) -> Result<()> {
+        if min_break_word_length == 0 {
+            bail!(ErrorKind::IllegalArgument(
+                "min_break_word_length must be >= 1".into()
+            ));
+        }
+        self.min_break_word_length = min_break_word_length;
+        Ok(())
+    }
+
+    /// Sets the maximal number of breaks in a term, or of joins into a
+    /// combined term.
+    pub fn set_max_changes(&mut self, max_changes: usize) {
+        self.max_changes = max_changes;
+    }
+
+    /// Sets the maximal number of doc frequency lookups per call, bounding
+    /// the cost of the long terms.
+    pub fn set_max_evaluations(&mut self, max_evaluations: usize) {
+        self.max_evaluations = max_evaluations;
+    }
+
+    /// Returns at most `max_suggestions` ways to break `term` into terms of
+    /// the index, each one the list of the words, the best first.
+    pub fn suggest_word_breaks<C: Codec>(
+        &self,
+        term: &Term,
+        max_suggestions: usize,
+        reader: &dyn IndexReader<Codec = C>,
+        mode: SuggestMode,
+        sort_method: BreakSuggestionSortMethod,
+    ) -> Result<Vec<Vec<SuggestWord>>> {
+        let field = term.field().to_string();
+        let text = term.text()?;
+        self.word_breaks(&text, max_suggestions, mode, sort_method, |word| {
+            doc_freq(reader, &Term::new(field.clone(), word.as_bytes().to_vec()))
+        })
+    }
+
+    /// Returns at most `max_suggestions` ways to combine adjacent terms of
+    /// `terms` into a term of the index, the best first. The terms of
+    /// different fields are never combined.
+    pub fn suggest_word_combinations<C: Codec>(
+        &self,
+        terms: &[Term],
+        max_suggestions: usize,
+        reader: &dyn IndexReader<Codec = C>,
+        mode: SuggestMode,
+    ) -> Result<Vec<CombineSuggestion>> {
+        let mut words = Vec::with_capacity(terms.len());
+        for term in terms {
+            words.push((term.field().to_string(), term.text()?));
+        }
+        self.word_combinations(&words, max_suggestions, mode, |field, word| {
+            doc_freq(
+                reader,
+                &Term::new(field.to_string(), word.as_bytes().to_vec()),
+            )
+        })
+    }
+
+    fn word_breaks<F>(
+        &self,
+        text: &str,
+        max_suggestions: usize,
+        mode: SuggestMode,
+        sort_method: BreakSuggestionSortMethod,
+        mut doc_freq: F,
+    ) -> Result<Vec<Vec<SuggestWord>>>
+    where
+        F: FnMut(&str) -> Result<i32>,
+    {
+        if max_suggestions == 0 {
+            return Ok(vec![]);
+        }
+        let orig_freq = doc_freq(text)?;
+        if orig_freq > 0 && mode == SuggestMode::WhenNotInIndex {
+            return Ok(vec![]);
+        }
+        let min_freq = if mode == SuggestMode::MorePopular {
+            orig_freq.max(1)
+        } else {
+            self.min_suggestion_frequency
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut suggestions = vec![];
+        let mut evaluations = 0;
+        self.generate_breaks(
+            &chars,
+            &mut vec![],
+            min_freq,
+            &mut doc_freq,
+            &mut evaluations,
+            &mut suggestions,
+        )?;
+
+        suggestions.sort_by(|a, b| compare_breaks(a, b, sort_method));
+        suggestions.truncate(max_suggestions);
+        Ok(suggestions)
+    }
+
+    /// Breaks `chars` in two at each position, collecting the breaks whose
+    /// both sides are in the index, and breaking the right side further
+    /// while the number of changes allows it.
+    fn generate_breaks<F>(
+        &self,
+        chars: &[char],
+        prefix: &mut Vec<SuggestWord>,
+        min_freq: i32,
+        doc_freq: &mut F,
+        evaluations: &mut usize,
+        suggestions: &mut Vec<Vec<SuggestWord>>,
+    ) -> Result<()>
+    where
+        F: FnMut(&str) -> Result<i32>,
+    {
+        let min_len = self.min_break_word_length;
+        if chars.len() < 2 * min_len {
+            return Ok(());
+        }
+        for i in min_len..=chars.len() - min_len {
+            if *evaluations >= self.max_evaluations {
+                break;
+            }
+            *evaluations += 1;
+            let left: String = chars[..i].iter().collect();
+            let left_freq = doc_freq(&left)?;
+            if left_freq < min_freq {
+                continue;
+            }
+            let left_word = SuggestWord {
+                string: left,
+                score: 0.0,
+                freq: left_freq,
+            };
+
+            *evaluations += 1;
+            let right: String = chars[i..].iter().collect();
+            let right_freq = doc_freq(&right)?;
+            let changes = prefix.len() + 1;
+            if right_freq >= min_freq {
+                let mut words = prefix.clone();
+                words.push(left_word.clone());
+                words.push(SuggestWord {
+                    string: right,
+                    score: 0.0,
+                    freq: right_freq,
+                });
+                for word in &mut words {
+                    word.score = changes as f32;
+                }
+                suggestions.push(words);
+            }
+            if changes < self.max_changes {
+                prefix.push(left_word);
+                self.generate_breaks(
+                    &chars[i..],
+                    prefix,
+                    min_freq,
+                    doc_freq,
+                    evaluations,
+                    suggestions,
+                )?;
+                prefix.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn word_combinations<F>(
+        &self,
+        words: &[(String, String)],
+        max_suggestions: usize,
+        mode: SuggestMode,
+        mut doc_freq: F,
+    ) -> Result<Vec<CombineSuggestion>>
+    where
+        F: FnMut(&str, &str) -> Result<i32>,
+    {
+        if max_suggestions == 0 {
+            return Ok(vec![]);
+        }
+        let mut orig_freqs = vec![0; words.len()];
+        if mode == SuggestMode::MorePopular {
+            for (i, (field, word)) in words.iter().enumerate() {
+                orig_freqs[i] = doc_freq(field, word)?;
+            }
+        }
+
+        let mut suggestions = vec![];
+        let mut evaluations = 0;
+        'outer: for i in 0..words.len() {
+            let field = &words[i].0;
+            let mut combined = words[i].1.clone();
+            let mut combined_len = combined.chars().count();
+            let mut max_orig_freq = orig_freqs[i];
+            for j in i + 1..words.len().min(i + self.max_changes + 1) {
+                if words[j].0 != *field {
+                    break;
+                }
+                combined.push_str(&words[j].1);
+                combined_len += words[j].1.chars().count();
+                if combined_len > self.max_combine_word_length {
+                    break;
+                }
+                if evaluations >= self.max_evaluations {
+                    break 'outer;
+                }
+                evaluations += 1;
+                max_orig_freq = max_orig_freq.max(orig_freqs[j]);
+
+                let freq = doc_freq(field, &combined)?;
+                if freq < self.min_suggestion_frequency
+                    || (mode == SuggestMode::MorePopular && freq <= max_orig_freq)
+                {
+                    continue;
+                }
+                suggestions.push(CombineSuggestion {
+                    original_term_indexes: (i..=j).collect(),
+                    suggestion: SuggestWord {
+                        string: combined.clone(),
+                        score: (j - i) as f32,
+                        freq,
+                    },
+                });
+            }
+        }
+
+        suggestions.sort_by(|a, b| {
+            a.suggestion
+                .score
+                .partial_cmp(&b.suggestion.score)
+                .unwrap_or(Ordering::Equal)
+                .then(b.suggestion.freq.cmp(&a.suggestion.freq))
+                .then(a.original_term_indexes.cmp(&b.original_term_indexes))
+        });
+        suggestions.truncate(max_suggestions);
+        Ok(suggestions)
+    }
+}
+
+fn compare_breaks(
+    a: &[SuggestWord],
+    b: &[SuggestWord],
+    sort_method: BreakSuggestionSortMethod,
+) -> Ordering {
+    let freq = |words: &[SuggestWord]| -> i64 {
+        match sort_method {
+            BreakSuggestionSortMethod::NumChangesThenSummedFrequency => {
+                words.iter().map(|w| i64::from(w.freq)).sum()
+            }
+            BreakSuggestionSortMethod::NumChangesThenMaxFrequency => {
+                words.iter().map(|w| i64::from(w.freq)).max().unwrap_or(0)
+            }
+        }
+    };
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| freq(b).cmp(&freq(a)))
+        .then_with(|| {
+            let strings = |words: &[SuggestWord]| -> Vec<String> {
+                words.iter().map(|w| w.string.clone()).collect()
+            };
+            strings(a).cmp(&strings(b))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn index() -> HashMap<&'static str, i32> {
+        vec![
+            ("wi", 2),
+            ("fi", 1),
+            ("wifi", 5),
+            ("router", 9),
+            ("rout", 1),
+            ("er", 1),
+            ("one", 3),
+            ("two", 3),
+            ("three", 2),
+            ("onetwo", 1),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn strings(words: &[SuggestWord]) -> Vec<&str> {
+        words.iter().map(|w| w.string.as_str()).collect()
+    }
+
+    #[test]
+    fn test_word_breaks() {
+        let index = index();
+        let doc_freq = |w: &str| Ok(*index.get(w).unwrap_or(&0));
+        let checker = WordBreakSpellChecker::default();
+        let sort = BreakSuggestionSortMethod::NumChangesThenMaxFrequency;
+
+        let breaks = checker
+            .word_breaks("wifirouter", 5, SuggestMode::Always, sort, doc_freq)
+            .unwrap();
+        assert_eq!(breaks.len(), 1);
+        assert_eq!(strings(&breaks[0]), vec!["wifi", "router"]);
+        assert!((breaks[0][0].score - 1.0).abs() < ::std::f32::EPSILON);
+
+        // the term is in the index
+        let breaks = checker
+            .word_breaks("wifi", 5, SuggestMode::WhenNotInIndex, sort, doc_freq)
+            .unwrap();
+        assert!(breaks.is_empty());
+        let breaks = checker
+            .word_breaks("wifi", 5, SuggestMode::Always, sort, doc_freq)
+            .unwrap();
+        assert_eq!(strings(&breaks[0]), vec!["wi", "fi"]);
+
+        let mut checker = WordBreakSpellChecker::default();
+        checker.set_max_changes(2);
+        let breaks = checker
+            .word_breaks("onetwothree", 5, SuggestMode::Always, sort, doc_freq)
+            .unwrap();
+        assert_eq!(breaks.len(), 2);
+        assert_eq!(strings(&breaks[0]), vec!["onetwo", "three"]);
+        assert_eq!(strings(&breaks[1]), vec!["one", "two", "three"]);
+        assert!((breaks[1][0].score - 2.0).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_word_combinations() {
+        let index = index();
+        let doc_freq = |_: &str, w: &str| Ok(*index.get(w).unwrap_or(&0));
+        let words: Vec<(String, String)> = vec!["wi", "fi", "rout", "er"]
+            .into_iter()
+            .map(|w| ("body".to_string(), w.to_string()))
+            .collect();
+        let checker = WordBreakSpellChecker::default();
+
+        let combinations = checker
+            .word_combinations(&words, 5, SuggestMode::Always, doc_freq)
+            .unwrap();
+        assert_eq!(combinations.len(), 2);
+        assert_eq!(combinations[0].suggestion.string, "router");
+        assert_eq!(combinations[0].original_term_indexes, vec![2, 3]);
+        assert_eq!(combinations[1].suggestion.string, "wifi");
+        assert_eq!(combinations[1].original_term_indexes, vec![0, 1]);
+
+        // "router" is less popular than "rout" and "er" here
+        let index: HashMap<&str, i32> = vec![("wifi", 5), ("wi", 1), ("router", 1), ("rout", 3)]
+            .into_iter()
+            .collect();
+        let doc_freq = |_: &str, w: &str| Ok(*index.get(w).unwrap_or(&0));
+        let combinations = checker
+            .word_combinations(&words, 5, SuggestMode::MorePopular, doc_freq)
+            .unwrap();
+        assert_eq!(combinations.len(), 1);
+        assert_eq!(combinations[0].suggestion.string, "wifi");
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{Automaton, MAX_CODE_POINT};
+
+/// Construction of basic automata.
+pub struct Automata;
+
+impl Automata {
+    /// Returns a new (deterministic) automaton with the empty language.
+    pub fn make_empty() -> Automaton {
+        let mut a = Automaton::new();
+        a.finish();
+        a
+    }
+
+    /// Returns a new (deterministic) automaton that accepts only the empty string.
+    pub fn make_empty_string() -> Automaton {
+        let mut a = Automaton::new();
+        a.create_state();
+        a.set_accept(0, true);
+        a
+    }
+
+    /// Returns a new (deterministic) automaton that accepts all strings.
+    pub fn make_any_string() -> Automaton {
+        let mut a = Automaton::new();
+        let s = a.create_state();
+        a.set_accept(s, true);
+        a.add_transition(s, s, 0, MAX_CODE_POINT);
+        a.finish();
+        a
+    }
+
+    /// Returns a new (deterministic) automaton that accepts all binary terms.
+    pub fn make_any_binary() -> Automaton {
+        let mut a = Automaton::new();
+        let s = a.create_state();
+        a.set_accept(s, true);
+        a.add_transition(s, s, 0, 255);
+        a.finish();
+        a
+    }
+
+    /// Returns a new (deterministic) automaton that accepts any single code point.
+    pub fn make_any_char() -> Automaton {
+        Automata::make_char_range(0, MAX_CODE_POINT)
+    }
+
+    /// Returns a new (deterministic) automaton that accepts a single code point
+    /// of the given value.
+    pub fn make_char(c: u32) -> Automaton {
+        Automata::make_char_range(c, c)
+    }
+
+    /// Returns a new (deterministic) automaton that accepts a single code point
+    /// whose value is in the given interval (including both end points).
+    pub fn make_char_range(min: u32, max: u32) -> Automaton {
+        if min > max {
+            return Automata::make_empty();
+        }
+        let mut a = Automaton::new();
+        let s1 = a.create_state();
+        let s2 = a.create_state();
+        a.set_accept(s2, true);
+        a.add_transition(s1, s2, min, max);
+        a.finish();
+        a
+    }
+
+    /// Returns a new (deterministic) automaton that accepts the single given string.
+    pub fn make_string(s: &str) -> Automaton {
+        let labels: Vec<u32> = s.chars().map(|c| c as u32).collect();
+        Automata::make_labels(&labels)
+    }
+
+    /// Returns a new (deterministic) automaton that accepts the single given binary term.
+    pub fn make_binary(term: &[u8]) -> Automaton {
+        let labels: Vec<u32> = term.iter().map(|&b| u32::from(b)).collect();
+        Automata::make_labels(&labels)
+    }
+
+    fn make_labels(labels: &[u32]) -> Automaton {
+        let mut a = Automaton::new();
+        let mut last = a.create_state();
+        for &label in labels {
+            let state = a.create_state();
+            a.add_label_transition(last, state, label);
+            last = state;
+        }
+        a.set_accept(last, true);
+        a.finish();
+        a
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Holds one transition from an `Automaton`, the transition accepts labels
+/// in range of `[min, max]` and moves to state `dest`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Transition {
+    /// Minimum accepted label (inclusive).
+    pub min: u32,
+    /// Maximum accepted label (inclusive).
+    pub max: u32,
+    /// Target state.
+    pub dest: usize,
+}
+
+impl Transition {
+    pub fn new(min: u32, max: u32, dest: usize) -> Transition {
+        debug_assert!(min <= max);
+        Transition { min, max, dest }
+    }
+}
+
+/// Represents an automaton and all its states and transitions. States are
+/// integers and must be created using `create_state`, the initial state is
+/// always the first created state (state 0). Labels are code points for
+/// unicode automaton or byte values for binary automaton.
+///
+/// Once all transitions of the automaton are added, `finish` must be called
+/// which sorts and reduces the transitions of each state.
+///
+/// An automaton with no states accepts no strings at all.
+#[derive(Clone, Default)]
+pub struct Automaton {
+    accept: Vec<bool>,
+    transitions: Vec<Vec<Transition>>,
+}
+
+impl Automaton {
+    pub fn new() -> Automaton {
+        Automaton::default()
+    }
+
+    /// Create a new state and return its index.
+    pub fn create_state(&mut self) -> usize {
+        self.accept.push(false);
+        self.transitions.push(Vec::new());
+        self.accept.len() - 1
+    }
+
+    /// Set or clear this state as an accept state.
+    pub fn set_accept(&mut self, state: usize, accept: bool) {
+        debug_assert!(state < self.num_states());
+        self.accept[state] = accept;
+    }
+
+    /// Returns true if this state is an accept state.
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accept[state]
+    }
+
+    /// How many states this automaton has.
+    #[inline]
+    pub fn num_states(&self) -> usize {
+        self.accept.len()
+    }
+
+    /// How many transitions the state has.
+    #[inline]
+    pub fn num_transitions(&self, state: usize) -> usize {
+        self.transitions[state].len()
+    }
+
+    /// All transitions leaving the state, sorted by `min` once `finish` is called.
+    #[inline]
+    pub fn transitions(&self, state: usize) -> &[Transition] {
+        &self.transitions[state]
+    }
+
+    /// Add a new transition with min = max = label.
+    pub fn add_label_transition(&mut self, source: usize, dest: usize, label: u32) {
+        self.add_transition(source, dest, label, label);
+    }
+
+    /// Add a new transition with the specified source, dest, min, max.
+    pub fn add_transition(&mut self, source: usize, dest: usize, min: u32, max: u32) {
+        debug_assert!(source < self.num_states() && dest < self.num_states());
+        self.transitions[source].push(Transition::new(min, max, dest));
+    }
+
+    /// Add a [virtual] epsilon transition between source and dest.
+    /// Dest state must already have all transitions added because this
+    /// method simply copies those same transitions over to source.
+    pub fn add_epsilon(&mut self, source: usize, dest: usize) {
+        let transitions = self.transitions[dest].clone();
+        self.transitions[source].extend(transitions);
+        if self.accept[dest] {
+            self.accept[source] = true;
+        }
+    }
+
+    /// Copies over all states/transitions from other. The states numbers
+    /// are sequentially assigned (appended), the offset of the copied states
+    /// is returned.
+    pub fn copy(&mut self, other: &Automaton) -> usize {
+        let offset = self.num_states();
+        for s in 0..other.num_states() {
+            self.accept.push(other.accept[s]);
+            self.transitions.push(
+                other.transitions[s]
+                    .iter()
+                    .map(|t| Transition::new(t.min, t.max, t.dest + offset))
+                    .collect(),
+            );
+        }
+        offset
+    }
+
+    /// Sorts transitions of each state by min label, and merges overlapping or
+    /// adjacent ranges that lead to the same state.
+    pub fn finish(&mut self) {
+        for transitions in &mut self.transitions {
+            if transitions.len() <= 1 {
+                continue;
+            }
+            transitions.sort_by(|a, b| {
+                a.dest
+                    .cmp(&b.dest)
+                    .then(a.min.cmp(&b.min))
+                    .then(a.max.cmp(&b.max))
+            });
+            let mut reduced: Vec<Transition> = Vec::with_capacity(transitions.len());
+            for t in transitions.iter() {
+                if let Some(last) = reduced.last_mut() {
+                    if last.dest == t.dest && t.min <= last.max.saturating_add(1) {
+                        if t.max > last.max {
+                            last.max = t.max;
+                        }
+                        continue;
+                    }
+                }
+                reduced.push(*t);
+            }
+            reduced.sort_by(Automaton::compare_transition);
+            *transitions = reduced;
+        }
+    }
+
+    fn compare_transition(a: &Transition, b: &Transition) -> Ordering {
+        a.min
+            .cmp(&b.min)
+            .then(a.max.cmp(&b.max))
+            .then(a.dest.cmp(&b.dest))
+    }
+
+    /// Returns true if this automaton is deterministic (for ever state
+    /// there is only one transition for each label).
+    pub fn is_deterministic(&self) -> bool {
+        self.transitions.iter().all(|transitions| {
+            let mut sorted = transitions.clone();
+            sorted.sort_by(Automaton::compare_transition);
+            sorted.windows(2).all(|w| w[0].max < w[1].min)
+        })
+    }
+
+    /// Performs lookup in transitions, assuming determinism.
+    ///
+    /// Returns the destination state, or `None` if there is no
+    /// transition for the label.
+    pub fn step(&self, state: usize, label: u32) -> Option<usize> {
+        for t in &self.transitions[state] {
+            if t.min <= label && label <= t.max {
+                return Some(t.dest);
+            }
+        }
+        None
+    }
+
+    /// Returns sorted array of all interval start points.
+    pub fn start_points(&self) -> Vec<u32> {
+        let mut points = vec![0u32];
+        for transitions in &self.transitions {
+            for t in transitions {
+                points.push(t.min);
+                if t.max < u32::max_value() {
+                    points.push(t.max + 1);
+                }
+            }
+        }
+        points.sort();
+        points.dedup();
+        points
+    }
+
+    /// Returns true if the given label sequence is accepted by this automaton.
+    ///
+    /// This works on non-deterministic automaton as well, but is not efficient,
+    /// use a `RunAutomaton` for repeated matching.
+    pub fn run(&self, labels: &[u32]) -> bool {
+        if self.num_states() == 0 {
+            return false;
+        }
+        let mut current = vec![0usize];
+        let mut next = Vec::new();
+        for &label in labels {
+            next.clear();
+            for &s in &current {
+                for t in &self.transitions[s] {
+                    if t.min <= label && label <= t.max && !next.contains(&t.dest) {
+                        next.push(t.dest);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return false;
+            }
+            ::std::mem::swap(&mut current, &mut next);
+        }
+        current.iter().any(|&s| self.accept[s])
+    }
+}
+
+impl fmt::Debug for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Automaton(states: {})", self.num_states())?;
+        for s in 0..self.num_states() {
+            write!(f, "  {}{}:", s, if self.accept[s] { "*" } else { "" })?;
+            for t in &self.transitions[s] {
+                write!(f, " [{:x}-{:x}] -> {}", t.min, t.max, t.dest)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
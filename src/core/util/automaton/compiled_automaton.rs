@@ -0,0 +1,142 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{
+    Automaton, ByteRunAutomaton, Operations, Utf32ToUtf8, DEFAULT_MAX_DETERMINIZED_STATES,
+};
+
+use error::Result;
+
+use std::sync::Arc;
+
+/// Automata are compiled into different internal forms for the most efficient
+/// execution depending upon the language they accept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutomatonType {
+    /// Automaton that accepts no strings.
+    None,
+    /// Automaton that accepts all possible strings.
+    All,
+    /// Automaton that accepts only a single fixed string.
+    Single,
+    /// Catch-all for any other automata.
+    Normal,
+}
+
+/// Immutable class holding compiled details for a given `Automaton`. The
+/// automaton is determinized and converted into a UTF-8 (or binary) byte
+/// automaton, so that it can be run directly against terms.
+#[derive(Clone)]
+pub struct CompiledAutomaton {
+    pub automaton_type: AutomatonType,
+    /// For `AutomatonType::Single` this is the singleton term.
+    pub term: Option<Vec<u8>>,
+    /// Matcher for quickly determining if a byte[] is accepted, only valid for
+    /// `AutomatonType::Normal`.
+    pub run_automaton: Option<Arc<ByteRunAutomaton>>,
+    /// Indicates if the automaton accepts a finite set of strings, only valid
+    /// for `AutomatonType::Normal`.
+    pub finite: bool,
+}
+
+impl CompiledAutomaton {
+    /// Create this, passing simplify=true, so that we try to simplify the
+    /// automaton.
+    pub fn with_automaton(automaton: &Automaton) -> Result<CompiledAutomaton> {
+        CompiledAutomaton::new(automaton, true, false, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Create this. If simplify is true, we run possibly expensive operations to
+    /// determine if the automaton is one the cases in `AutomatonType`. If
+    /// `is_binary` is true, the labels of the input automaton are bytes rather
+    /// than unicode code points.
+    pub fn new(
+        automaton: &Automaton,
+        simplify: bool,
+        is_binary: bool,
+        max_determinized_states: usize,
+    ) -> Result<CompiledAutomaton> {
+        let automaton = Operations::determinize(
+            &Operations::remove_dead_states(automaton),
+            max_determinized_states,
+        )?;
+
+        if simplify {
+            if Operations::is_empty(&automaton) {
+                return Ok(CompiledAutomaton::with_type(AutomatonType::None, None));
+            }
+            let is_total = if is_binary {
+                Operations::is_total_range(&automaton, 0, 255)
+            } else {
+                Operations::is_total(&automaton)
+            };
+            if is_total {
+                return Ok(CompiledAutomaton::with_type(AutomatonType::All, None));
+            }
+            if let Some(labels) = Operations::get_singleton(&automaton) {
+                let term = if is_binary {
+                    labels.iter().map(|&l| l as u8).collect()
+                } else {
+                    labels
+                        .iter()
+                        .filter_map(|&l| ::std::char::from_u32(l))
+                        .collect::<String>()
+                        .into_bytes()
+                };
+                return Ok(CompiledAutomaton::with_type(
+                    AutomatonType::Single,
+                    Some(term),
+                ));
+            }
+        }
+
+        let binary = if is_binary {
+            automaton
+        } else {
+            Utf32ToUtf8::convert(&automaton)
+        };
+        let binary = Operations::remove_dead_states(&Operations::determinize(
+            &binary,
+            max_determinized_states,
+        )?);
+        if binary.num_states() == 0 {
+            return Ok(CompiledAutomaton::with_type(AutomatonType::None, None));
+        }
+        let finite = Operations::is_finite(&binary);
+        Ok(CompiledAutomaton {
+            automaton_type: AutomatonType::Normal,
+            term: None,
+            run_automaton: Some(Arc::new(ByteRunAutomaton::new(binary))),
+            finite,
+        })
+    }
+
+    fn with_type(automaton_type: AutomatonType, term: Option<Vec<u8>>) -> CompiledAutomaton {
+        CompiledAutomaton {
+            automaton_type,
+            term,
+            run_automaton: None,
+            finite: true,
+        }
+    }
+
+    /// Returns true if the given term is accepted by this automaton.
+    pub fn run(&self, term: &[u8]) -> bool {
+        match self.automaton_type {
+            AutomatonType::None => false,
+            AutomatonType::All => true,
+            AutomatonType::Single => self.term.as_ref().map_or(false, |t| t.as_slice() == term),
+            AutomatonType::Normal => self.run_automaton.as_ref().unwrap().run(term),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{
+    Automata, Automaton, Operations, DEFAULT_MAX_DETERMINIZED_STATES, MAX_CODE_POINT,
+};
+
+use error::Result;
+
+/// Builds automata accepting the strings within a maximum edit distance of an
+/// input string.
+///
+/// An edit is the insertion, deletion or substitution of a single label, and
+/// optionally the transposition of two adjacent labels, in which case the
+/// distance is the Damerau-Levenshtein (optimal string alignment) distance.
+pub struct LevenshteinAutomata {
+    word: Vec<u32>,
+    alphabet_max: u32,
+    with_transpositions: bool,
+}
+
+impl LevenshteinAutomata {
+    /// Create a builder for the code points of `input`.
+    pub fn new(input: &str, with_transpositions: bool) -> LevenshteinAutomata {
+        let word = input.chars().map(|c| c as u32).collect();
+        LevenshteinAutomata::with_labels(word, MAX_CODE_POINT, with_transpositions)
+    }
+
+    /// Create a builder for arbitrary labels, e.g. bytes, inserted or substituted
+    /// labels are in range of `[0, alphabet_max]`.
+    pub fn with_labels(
+        word: Vec<u32>,
+        alphabet_max: u32,
+        with_transpositions: bool,
+    ) -> LevenshteinAutomata {
+        debug_assert!(word.iter().all(|&l| l <= alphabet_max));
+        LevenshteinAutomata {
+            word,
+            alphabet_max,
+            with_transpositions,
+        }
+    }
+
+    /// Returns a minimal deterministic automaton accepting the strings within `n`
+    /// edits of the input.
+    pub fn to_automaton(&self, n: u32) -> Result<Automaton> {
+        Operations::minimize(&self.build(n as usize), DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Returns a minimal deterministic automaton accepting `prefix` followed by
+    /// the strings within `n` edits of the input.
+    pub fn to_automaton_with_prefix(&self, n: u32, prefix: &str) -> Result<Automaton> {
+        let automaton =
+            Operations::concatenate(&[Automata::make_string(prefix), self.build(n as usize)]);
+        Operations::minimize(&automaton, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Builds the non-deterministic automaton whose state `(i, e)` means that the
+    /// first `i` labels of the word were consumed with `e` edits.
+    fn build(&self, n: usize) -> Automaton {
+        let len = self.word.len();
+        let state = |i: usize, e: usize| i * (n + 1) + e;
+
+        let mut a = Automaton::new();
+        for _ in 0..(len + 1) * (n + 1) {
+            a.create_state();
+        }
+        for e in 0..=n {
+            a.set_accept(state(len, e), true);
+        }
+
+        // the epsilon transitions of deletions copy the transitions of their
+        // destination, which must be complete, so the states are built backwards
+        for i in (0..=len).rev() {
+            for e in 0..=n {
+                let s = state(i, e);
+                if i < len {
+                    a.add_label_transition(s, state(i + 1, e), self.word[i]);
+                }
+                if e == n {
+                    continue;
+                }
+                // insertion
+                a.add_transition(s, state(i, e + 1), 0, self.alphabet_max);
+                if i < len {
+                    // substitution
+                    a.add_transition(s, state(i + 1, e + 1), 0, self.alphabet_max);
+                }
+                if self.with_transpositions && i + 1 < len && self.word[i] != self.word[i + 1] {
+                    let swapped = a.create_state();
+                    a.add_label_transition(s, swapped, self.word[i + 1]);
+                    a.add_label_transition(swapped, state(i + 2, e + 1), self.word[i]);
+                }
+                if i < len {
+                    // deletion
+                    a.add_epsilon(s, state(i + 1, e + 1));
+                }
+            }
+        }
+        a.finish();
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(s: &str) -> Vec<u32> {
+        s.chars().map(|c| c as u32).collect()
+    }
+
+    #[test]
+    fn test_levenshtein_automaton() {
+        let a = LevenshteinAutomata::new("test", false)
+            .to_automaton(1)
+            .unwrap();
+        assert!(a.is_deterministic());
+        for s in &["test", "tst", "tests", "text", "atest"] {
+            assert!(a.run(&labels(s)), "{} should match", s);
+        }
+        for s in &["tset", "tt", "txxt", "testss"] {
+            assert!(!a.run(&labels(s)), "{} should not match", s);
+        }
+
+        let a = LevenshteinAutomata::new("test", false)
+            .to_automaton(0)
+            .unwrap();
+        assert_eq!(Operations::get_singleton(&a), Some(labels("test")));
+    }
+
+    #[test]
+    fn test_levenshtein_transpositions() {
+        let a = LevenshteinAutomata::new("abcd", true)
+            .to_automaton(1)
+            .unwrap();
+        assert!(a.run(&labels("bacd")));
+        assert!(a.run(&labels("abdc")));
+        assert!(!a.run(&labels("badc")));
+
+        let a = LevenshteinAutomata::new("abcd", true)
+            .to_automaton(2)
+            .unwrap();
+        assert!(a.run(&labels("badc")));
+        assert!(a.run(&labels("ab")));
+        assert!(!a.run(&labels("a")));
+    }
+
+    #[test]
+    fn test_levenshtein_with_prefix() {
+        let lev = LevenshteinAutomata::new("ing", false);
+        let a = lev.to_automaton_with_prefix(1, "runn").unwrap();
+        assert!(a.run(&labels("running")));
+        assert!(a.run(&labels("runnin")));
+        assert!(a.run(&labels("runnang")));
+        assert!(!a.run(&labels("rinning")));
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Finite-state automaton for regular expressions and other multi-term matching.
+//!
+//! Automata are built over unicode code points (see `Automata`, `RegExp` and
+//! `LevenshteinAutomata`), and converted into UTF-8 byte automata by
+//! `CompiledAutomaton` before they are run against the terms dictionary.
+
+mod automaton;
+
+pub use self::automaton::*;
+
+mod automata;
+
+pub use self::automata::*;
+
+mod operations;
+
+pub use self::operations::*;
+
+mod regexp;
+
+pub use self::regexp::*;
+
+mod run_automaton;
+
+pub use self::run_automaton::*;
+
+mod utf32_to_utf8;
+
+pub use self::utf32_to_utf8::*;
+
+mod compiled_automaton;
+
+pub use self::compiled_automaton::*;
+
+mod levenshtein_automata;
+
+pub use self::levenshtein_automata::*;
+
+/// Maximum code point value, labels of unicode automaton are in `[0, MAX_CODE_POINT]`.
+pub const MAX_CODE_POINT: u32 = 0x0010_ffff;
+
+/// Default maximum number of states that `Operations::determinize` should create.
+pub const DEFAULT_MAX_DETERMINIZED_STATES: usize = 10_000;
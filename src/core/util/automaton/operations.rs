@@ -0,0 +1,778 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{Automata, Automaton, MAX_CODE_POINT};
+
+use error::ErrorKind::IllegalArgument;
+use error::Result;
+
+use std::cmp::{max, min};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+/// Automata operations.
+///
+/// Unless noted otherwise, the operations accept non-deterministic automaton and
+/// never modify the input automaton, the result is a new automaton.
+pub struct Operations;
+
+impl Operations {
+    /// Returns an automaton that accepts the concatenation of the languages of the
+    /// given automata.
+    pub fn concatenate(automata: &[Automaton]) -> Automaton {
+        if automata.is_empty() {
+            return Automata::make_empty_string();
+        }
+        let mut result = Automaton::new();
+        // First pass: create all states
+        let mut offsets = Vec::with_capacity(automata.len());
+        for a in automata {
+            if a.num_states() == 0 {
+                return Automata::make_empty();
+            }
+            offsets.push(result.num_states());
+            for _ in 0..a.num_states() {
+                result.create_state();
+            }
+        }
+
+        // Second pass: add transitions, carefully linking accept
+        // states of A to init state of next A:
+        for (i, a) in automata.iter().enumerate() {
+            let offset = offsets[i];
+            for s in 0..a.num_states() {
+                for t in a.transitions(s) {
+                    result.add_transition(offset + s, offset + t.dest, t.min, t.max);
+                }
+                if !a.is_accept(s) {
+                    continue;
+                }
+                let mut follow = i + 1;
+                loop {
+                    if follow < automata.len() {
+                        // Adds a "virtual" epsilon transition:
+                        let follow_a = &automata[follow];
+                        for t in follow_a.transitions(0) {
+                            result.add_transition(
+                                offset + s,
+                                offsets[follow] + t.dest,
+                                t.min,
+                                t.max,
+                            );
+                        }
+                        if follow_a.is_accept(0) {
+                            // Keep chaining if follow_a accepts empty string
+                            follow += 1;
+                        } else {
+                            break;
+                        }
+                    } else {
+                        result.set_accept(offset + s, true);
+                        break;
+                    }
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns an automaton that accepts the union of the languages of the given automata.
+    pub fn union(automata: &[Automaton]) -> Automaton {
+        let mut result = Automaton::new();
+        // Create initial state:
+        result.create_state();
+        let mut offsets = Vec::with_capacity(automata.len());
+        for a in automata {
+            offsets.push(result.copy(a));
+        }
+        // Add epsilon transition from new initial state
+        for (a, offset) in automata.iter().zip(offsets) {
+            if a.num_states() > 0 {
+                result.add_epsilon(0, offset);
+            }
+        }
+        result.finish();
+        Operations::remove_dead_states(&result)
+    }
+
+    /// Returns an automaton that accepts the union of the empty string and the
+    /// language of the given automaton.
+    pub fn optional(a: &Automaton) -> Automaton {
+        let mut result = Automaton::new();
+        result.create_state();
+        result.set_accept(0, true);
+        if a.num_states() > 0 {
+            result.copy(a);
+            result.add_epsilon(0, 1);
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns an automaton that accepts the Kleene star (zero or more
+    /// concatenated repetitions) of the language of the given automaton.
+    pub fn repeat(a: &Automaton) -> Automaton {
+        if a.num_states() == 0 {
+            // Repeating the empty automata will still only accept the empty automata.
+            return a.clone();
+        }
+        let mut result = Automaton::new();
+        result.create_state();
+        result.set_accept(0, true);
+        result.copy(a);
+        for t in a.transitions(0) {
+            result.add_transition(0, t.dest + 1, t.min, t.max);
+        }
+        for s in 0..a.num_states() {
+            if a.is_accept(s) {
+                for t in a.transitions(0) {
+                    result.add_transition(s + 1, t.dest + 1, t.min, t.max);
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns an automaton that accepts `min` or more concatenated
+    /// repetitions of the language of the given automaton.
+    pub fn repeat_min(a: &Automaton, min: usize) -> Automaton {
+        if min == 0 {
+            return Operations::repeat(a);
+        }
+        let mut automata = vec![a.clone(); min];
+        automata.push(Operations::repeat(a));
+        Operations::concatenate(&automata)
+    }
+
+    /// Returns an automaton that accepts between `min` and `max` (including
+    /// both) concatenated repetitions of the language of the given automaton.
+    pub fn repeat_range(a: &Automaton, min: usize, max: usize) -> Automaton {
+        if min > max {
+            return Automata::make_empty();
+        }
+        let mut automata = vec![a.clone(); min];
+        if max > min {
+            let optional = Operations::optional(a);
+            for _ in min..max {
+                automata.push(optional.clone());
+            }
+        }
+        Operations::concatenate(&automata)
+    }
+
+    /// Returns a (deterministic) automaton that accepts the complement of the
+    /// language of the given automaton.
+    pub fn complement(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+        let mut a = Operations::totalize(&Operations::determinize(a, max_determinized_states)?);
+        for s in 0..a.num_states() {
+            let accept = a.is_accept(s);
+            a.set_accept(s, !accept);
+        }
+        Ok(Operations::remove_dead_states(&a))
+    }
+
+    /// Returns a (deterministic) automaton that accepts the intersection of the
+    /// language of `a1` and the complement of the language of `a2`.
+    pub fn minus(
+        a1: &Automaton,
+        a2: &Automaton,
+        max_determinized_states: usize,
+    ) -> Result<Automaton> {
+        if Operations::is_empty(a1) {
+            return Ok(Automata::make_empty());
+        }
+        if Operations::is_empty(a2) {
+            return Ok(a1.clone());
+        }
+        let complement = Operations::complement(a2, max_determinized_states)?;
+        Ok(Operations::intersection(a1, &complement))
+    }
+
+    /// Returns an automaton that accepts the intersection of the languages of
+    /// the given automata.
+    pub fn intersection(a1: &Automaton, a2: &Automaton) -> Automaton {
+        if a1.num_states() == 0 || a2.num_states() == 0 {
+            return Automata::make_empty();
+        }
+        let mut result = Automaton::new();
+        let mut states: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut worklist = VecDeque::new();
+
+        let initial = result.create_state();
+        result.set_accept(initial, a1.is_accept(0) && a2.is_accept(0));
+        states.insert((0, 0), initial);
+        worklist.push_back((0, 0));
+
+        while let Some((s1, s2)) = worklist.pop_front() {
+            let r = states[&(s1, s2)];
+            for t1 in a1.transitions(s1) {
+                for t2 in a2.transitions(s2) {
+                    if t1.min > t2.max || t2.min > t1.max {
+                        continue;
+                    }
+                    let key = (t1.dest, t2.dest);
+                    let q = match states.get(&key) {
+                        Some(&q) => q,
+                        None => {
+                            let q = result.create_state();
+                            result.set_accept(q, a1.is_accept(key.0) && a2.is_accept(key.1));
+                            states.insert(key, q);
+                            worklist.push_back(key);
+                            q
+                        }
+                    };
+                    result.add_transition(r, q, max(t1.min, t2.min), min(t1.max, t2.max));
+                }
+            }
+        }
+        result.finish();
+        Operations::remove_dead_states(&result)
+    }
+
+    /// Determinizes the given automaton using the subset construction.
+    ///
+    /// Worst case complexity is exponential in the number of states, so an
+    /// `IllegalArgument` error is returned if the result would have more than
+    /// `max_determinized_states` states.
+    pub fn determinize(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+        if a.num_states() == 0 || a.is_deterministic() {
+            return Ok(a.clone());
+        }
+
+        let points = a.start_points();
+        let mut result = Automaton::new();
+        let mut new_states: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut worklist: VecDeque<Vec<usize>> = VecDeque::new();
+
+        let initial = result.create_state();
+        result.set_accept(initial, a.is_accept(0));
+        new_states.insert(vec![0], initial);
+        worklist.push_back(vec![0]);
+
+        let mut dests = BTreeSet::new();
+        while let Some(set) = worklist.pop_front() {
+            let r = new_states[&set];
+            for (i, &point) in points.iter().enumerate() {
+                dests.clear();
+                for &s in &set {
+                    for t in a.transitions(s) {
+                        if t.min <= point && point <= t.max {
+                            dests.insert(t.dest);
+                        }
+                    }
+                }
+                if dests.is_empty() {
+                    continue;
+                }
+
+                let key: Vec<usize> = dests.iter().cloned().collect();
+                let q = match new_states.get(&key) {
+                    Some(&q) => q,
+                    None => {
+                        if result.num_states() >= max_determinized_states {
+                            bail!(IllegalArgument(format!(
+                                "Determinizing automaton would result in more than {} states.",
+                                max_determinized_states
+                            )));
+                        }
+                        let q = result.create_state();
+                        result.set_accept(q, key.iter().any(|&s| a.is_accept(s)));
+                        new_states.insert(key.clone(), q);
+                        worklist.push_back(key);
+                        q
+                    }
+                };
+                let max = if i + 1 < points.len() {
+                    points[i + 1] - 1
+                } else {
+                    u32::max_value()
+                };
+                result.add_transition(r, q, point, max);
+            }
+        }
+        result.finish();
+        Ok(result)
+    }
+
+    /// Minimizes the given automaton with Moore's partition refinement, the
+    /// automaton is determinized first.
+    ///
+    /// The result is deterministic and has no dead states, so two automata that
+    /// accept the same language have the same number of states once minimized.
+    pub fn minimize(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+        let a =
+            Operations::determinize(&Operations::remove_dead_states(a), max_determinized_states)?;
+        if a.num_states() == 0 {
+            return Ok(a);
+        }
+        let a = Operations::totalize(&a);
+        let num_states = a.num_states();
+        let points = a.start_points();
+        // destination of each state for each interval of labels
+        let dests: Vec<Vec<Option<usize>>> = (0..num_states)
+            .map(|s| points.iter().map(|&p| a.step(s, p)).collect())
+            .collect();
+
+        let mut classes: Vec<usize> = (0..num_states)
+            .map(|s| if a.is_accept(s) { 1 } else { 0 })
+            .collect();
+        let mut num_classes = if classes.iter().all(|&c| c == classes[0]) {
+            1
+        } else {
+            2
+        };
+        // split the classes until no states of a class differ by the classes of
+        // their destinations, the initial state always ends up in class 0
+        loop {
+            let mut signatures: HashMap<(usize, Vec<Option<usize>>), usize> = HashMap::new();
+            let mut new_classes = Vec::with_capacity(num_states);
+            for s in 0..num_states {
+                let signature = (
+                    classes[s],
+                    dests[s].iter().map(|d| d.map(|d| classes[d])).collect(),
+                );
+                let next = signatures.len();
+                new_classes.push(*signatures.entry(signature).or_insert(next));
+            }
+            let stable = signatures.len() == num_classes;
+            num_classes = signatures.len();
+            classes = new_classes;
+            if stable {
+                break;
+            }
+        }
+
+        let mut result = Automaton::new();
+        for _ in 0..num_classes {
+            result.create_state();
+        }
+        let mut built = vec![false; num_classes];
+        for s in 0..num_states {
+            let c = classes[s];
+            if built[c] {
+                continue;
+            }
+            built[c] = true;
+            result.set_accept(c, a.is_accept(s));
+            for (i, dest) in dests[s].iter().enumerate() {
+                if let Some(d) = *dest {
+                    let max = if i + 1 < points.len() {
+                        points[i + 1] - 1
+                    } else {
+                        u32::max_value()
+                    };
+                    result.add_transition(c, classes[d], points[i], max);
+                }
+            }
+        }
+        result.finish();
+        Ok(Operations::remove_dead_states(&result))
+    }
+
+    /// Removes transitions to dead states (states that are not reachable from the
+    /// initial state, or can't reach an accept state), and the dead states themselves.
+    pub fn remove_dead_states(a: &Automaton) -> Automaton {
+        let num_states = a.num_states();
+        if num_states == 0 {
+            return a.clone();
+        }
+        let live = Operations::live_states(a);
+        let mut map = vec![0usize; num_states];
+        let mut result = Automaton::new();
+        for s in 0..num_states {
+            if live[s] {
+                map[s] = result.create_state();
+                result.set_accept(map[s], a.is_accept(s));
+            }
+        }
+        for s in 0..num_states {
+            if live[s] {
+                for t in a.transitions(s) {
+                    if live[t.dest] {
+                        result.add_transition(map[s], map[t.dest], t.min, t.max);
+                    }
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns true if the given automaton accepts no strings.
+    pub fn is_empty(a: &Automaton) -> bool {
+        a.num_states() == 0 || !Operations::live_states(a)[0]
+    }
+
+    /// Returns true if the given deterministic automaton accepts all unicode strings.
+    pub fn is_total(a: &Automaton) -> bool {
+        Operations::is_total_range(a, 0, MAX_CODE_POINT)
+    }
+
+    /// Returns true if the given deterministic automaton accepts all strings
+    /// whose labels are in range of `[min, max]`.
+    pub fn is_total_range(a: &Automaton, min: u32, max: u32) -> bool {
+        if a.num_states() == 0 {
+            return false;
+        }
+        let reachable = Operations::reachable_states(a);
+        (0..a.num_states()).filter(|&s| reachable[s]).all(|s| {
+            if !a.is_accept(s) {
+                return false;
+            }
+            let mut next = min;
+            for t in a.transitions(s) {
+                if t.min > next {
+                    return false;
+                }
+                if t.max >= next {
+                    if t.max >= max {
+                        return true;
+                    }
+                    next = t.max + 1;
+                }
+            }
+            false
+        })
+    }
+
+    /// Returns true if the language of this automaton is finite. The
+    /// automaton must not have any dead states.
+    pub fn is_finite(a: &Automaton) -> bool {
+        if a.num_states() == 0 {
+            return true;
+        }
+        // 0: not visited, 1: on the current path, 2: finished
+        let mut color = vec![0u8; a.num_states()];
+        let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+        color[0] = 1;
+        while let Some(top) = stack.last_mut() {
+            let state = top.0;
+            if top.1 < a.num_transitions(state) {
+                let dest = a.transitions(state)[top.1].dest;
+                top.1 += 1;
+                match color[dest] {
+                    0 => {
+                        color[dest] = 1;
+                        stack.push((dest, 0));
+                    }
+                    1 => return false,
+                    _ => {}
+                }
+            } else {
+                color[state] = 2;
+                stack.pop();
+            }
+        }
+        true
+    }
+
+    /// If the given deterministic automaton accepts a single string, returns
+    /// the labels of that string, else `None`. The automaton must not have any
+    /// dead states.
+    pub fn get_singleton(a: &Automaton) -> Option<Vec<u32>> {
+        if a.num_states() == 0 {
+            return None;
+        }
+        let mut labels = Vec::new();
+        let mut visited = vec![false; a.num_states()];
+        let mut s = 0;
+        loop {
+            visited[s] = true;
+            let transitions = a.transitions(s);
+            if a.is_accept(s) {
+                return if transitions.is_empty() {
+                    Some(labels)
+                } else {
+                    None
+                };
+            }
+            if transitions.len() != 1 || transitions[0].min != transitions[0].max {
+                return None;
+            }
+            labels.push(transitions[0].min);
+            s = transitions[0].dest;
+            if visited[s] {
+                return None;
+            }
+        }
+    }
+
+    /// Returns the states reachable from the initial state in topological
+    /// order, i.e. every transition goes from a state to a later one. The
+    /// automaton must be acyclic.
+    pub fn topo_sort_states(a: &Automaton) -> Vec<usize> {
+        debug_assert!(Operations::is_finite(a));
+        let mut order = Vec::with_capacity(a.num_states());
+        if a.num_states() == 0 {
+            return order;
+        }
+        let mut visited = vec![false; a.num_states()];
+        let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+        visited[0] = true;
+        while let Some(top) = stack.last_mut() {
+            let state = top.0;
+            if top.1 < a.num_transitions(state) {
+                let dest = a.transitions(state)[top.1].dest;
+                top.1 += 1;
+                if !visited[dest] {
+                    visited[dest] = true;
+                    stack.push((dest, 0));
+                }
+            } else {
+                order.push(state);
+                stack.pop();
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// Returns the strings accepted by the given acyclic automaton, at most
+    /// `limit` of them if a limit is given. A non deterministic automaton may
+    /// return the same string more than once.
+    pub fn finite_strings(a: &Automaton, limit: Option<usize>) -> Vec<Vec<u32>> {
+        debug_assert!(Operations::is_finite(a));
+        let mut strings = Vec::new();
+        if a.num_states() == 0 {
+            return strings;
+        }
+        let mut labels: Vec<u32> = Vec::new();
+        // each entry is a state, the index of its current transition and the
+        // next label to take in that transition's range
+        let mut stack: Vec<(usize, usize, u32)> = vec![(0, 0, 0)];
+        if a.is_accept(0) {
+            strings.push(Vec::new());
+        }
+        while let Some(top) = stack.last_mut() {
+            if limit.map_or(false, |limit| strings.len() >= limit) {
+                break;
+            }
+            let (state, idx, label) = *top;
+            if idx < a.num_transitions(state) {
+                let t = a.transitions(state)[idx];
+                let label = label.max(t.min);
+                if label < t.max {
+                    top.2 = label + 1;
+                } else {
+                    top.1 += 1;
+                    top.2 = 0;
+                }
+                labels.truncate(stack.len() - 1);
+                labels.push(label);
+                if a.is_accept(t.dest) {
+                    strings.push(labels.clone());
+                }
+                stack.push((t.dest, 0, 0));
+            } else {
+                stack.pop();
+            }
+        }
+        if let Some(limit) = limit {
+            strings.truncate(limit);
+        }
+        strings
+    }
+
+    /// Returns the states that are reachable from the initial state.
+    fn reachable_states(a: &Automaton) -> Vec<bool> {
+        let mut reachable = vec![false; a.num_states()];
+        if a.num_states() == 0 {
+            return reachable;
+        }
+        let mut worklist = vec![0usize];
+        reachable[0] = true;
+        while let Some(s) = worklist.pop() {
+            for t in a.transitions(s) {
+                if !reachable[t.dest] {
+                    reachable[t.dest] = true;
+                    worklist.push(t.dest);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns the states that are reachable from the initial state and can reach
+    /// an accept state.
+    fn live_states(a: &Automaton) -> Vec<bool> {
+        let num_states = a.num_states();
+        let reachable = Operations::reachable_states(a);
+
+        let mut reverse: Vec<Vec<usize>> = vec![Vec::new(); num_states];
+        for s in 0..num_states {
+            for t in a.transitions(s) {
+                reverse[t.dest].push(s);
+            }
+        }
+        let mut live = vec![false; num_states];
+        let mut worklist: Vec<usize> = (0..num_states).filter(|&s| a.is_accept(s)).collect();
+        for &s in &worklist {
+            live[s] = true;
+        }
+        while let Some(s) = worklist.pop() {
+            for &p in &reverse[s] {
+                if !live[p] {
+                    live[p] = true;
+                    worklist.push(p);
+                }
+            }
+        }
+        for s in 0..num_states {
+            live[s] = live[s] && reachable[s];
+        }
+        live
+    }
+
+    /// Adds transitions to explicit crash state to ensure that the transition
+    /// function is total.
+    fn totalize(a: &Automaton) -> Automaton {
+        let mut result = a.clone();
+        if result.num_states() == 0 {
+            result.create_state();
+        }
+        let dead = result.create_state();
+        result.add_transition(dead, dead, 0, MAX_CODE_POINT);
+        for s in 0..dead {
+            let transitions = result.transitions(s).to_vec();
+            let mut next = 0u32;
+            for t in transitions {
+                if t.min > next {
+                    result.add_transition(s, dead, next, t.min - 1);
+                }
+                if t.max + 1 > next {
+                    next = t.max + 1;
+                }
+            }
+            if next <= MAX_CODE_POINT {
+                result.add_transition(s, dead, next, MAX_CODE_POINT);
+            }
+        }
+        result.finish();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+
+    fn labels(s: &str) -> Vec<u32> {
+        s.chars().map(|c| c as u32).collect()
+    }
+
+    #[test]
+    fn test_concatenate_and_union() {
+        let a =
+            Operations::concatenate(&[Automata::make_string("foo"), Automata::make_string("bar")]);
+        assert!(a.run(&labels("foobar")));
+        assert!(!a.run(&labels("foo")));
+
+        let u = Operations::union(&[Automata::make_string("foo"), Automata::make_string("bar")]);
+        assert!(u.run(&labels("foo")));
+        assert!(u.run(&labels("bar")));
+        assert!(!u.run(&labels("foobar")));
+
+        let d = Operations::determinize(&u, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert!(d.is_deterministic());
+        assert!(d.run(&labels("foo")));
+        assert!(d.run(&labels("bar")));
+        assert!(!d.run(&labels("ba")));
+    }
+
+    #[test]
+    fn test_repeat_and_finite() {
+        let a = Operations::repeat(&Automata::make_char('a' as u32));
+        assert!(a.run(&labels("")));
+        assert!(a.run(&labels("aaaa")));
+        assert!(!Operations::is_finite(&a));
+
+        let r = Operations::repeat_range(&Automata::make_char('a' as u32), 1, 3);
+        assert!(!r.run(&labels("")));
+        assert!(r.run(&labels("aaa")));
+        assert!(!r.run(&labels("aaaa")));
+        assert!(Operations::is_finite(&Operations::remove_dead_states(&r)));
+    }
+
+    #[test]
+    fn test_complement_and_singleton() {
+        let a = Automata::make_string("foo");
+        let c = Operations::complement(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert!(!c.run(&labels("foo")));
+        assert!(c.run(&labels("fo")));
+        assert!(c.run(&labels("fooo")));
+
+        assert_eq!(Operations::get_singleton(&a), Some(labels("foo")));
+        assert!(Operations::is_total(&Automata::make_any_string()));
+        assert!(Operations::is_empty(&Automata::make_empty()));
+    }
+
+    #[test]
+    fn test_minimize() {
+        // (ab|ab*) is the same language as ab*
+        let ab = Automata::make_string("ab");
+        let ab_star = Operations::concatenate(&[
+            Automata::make_char('a' as u32),
+            Operations::repeat(&Automata::make_char('b' as u32)),
+        ]);
+        let u = Operations::union(&[ab, ab_star.clone()]);
+        let m = Operations::minimize(&u, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert!(m.is_deterministic());
+        assert_eq!(m.num_states(), 2);
+        assert!(m.run(&labels("a")));
+        assert!(m.run(&labels("abbb")));
+        assert!(!m.run(&labels("b")));
+
+        let m2 = Operations::minimize(&ab_star, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert_eq!(m2.num_states(), m.num_states());
+
+        let empty = Operations::minimize(&Automata::make_empty(), DEFAULT_MAX_DETERMINIZED_STATES);
+        assert_eq!(empty.unwrap().num_states(), 0);
+    }
+
+    #[test]
+    fn test_finite_strings() {
+        let u = Operations::union(&[
+            Automata::make_string("ab"),
+            Automata::make_string("a"),
+            Automata::make_char_range('x' as u32, 'z' as u32),
+        ]);
+        let d = Operations::determinize(&u, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        let mut strings = Operations::finite_strings(&d, None);
+        strings.sort();
+        assert_eq!(
+            strings,
+            vec![
+                labels("a"),
+                labels("ab"),
+                labels("x"),
+                labels("y"),
+                labels("z")
+            ]
+        );
+        assert_eq!(Operations::finite_strings(&d, Some(2)).len(), 2);
+
+        let order = Operations::topo_sort_states(&d);
+        assert_eq!(order.len(), d.num_states());
+        let mut rank = vec![0; d.num_states()];
+        for (i, &s) in order.iter().enumerate() {
+            rank[s] = i;
+        }
+        for s in 0..d.num_states() {
+            for t in d.transitions(s) {
+                assert!(rank[s] < rank[t.dest]);
+            }
+        }
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{Automata, Automaton, Operations};
+
+use error::ErrorKind::IllegalArgument;
+use error::Result;
+
+use std::fmt;
+
+/// Regular Expression extension to `Automaton`.
+///
+/// Regular expressions are built from the following abstract syntax:
+///
+/// ```text
+/// regexp      ::= unionexp
+/// unionexp    ::= interexp | unionexp         (union)
+///              |  interexp
+/// interexp    ::= concatexp & interexp        (intersection)       [OPTIONAL]
+///              |  concatexp
+/// concatexp   ::= repeatexp concatexp         (concatenation)
+///              |  repeatexp
+/// repeatexp   ::= repeatexp ?                 (zero or one occurrence)
+///              |  repeatexp *                 (zero or more occurrences)
+///              |  repeatexp +                 (one or more occurrences)
+///              |  repeatexp {n}               (n occurrences)
+///              |  repeatexp {n,}              (n or more occurrences)
+///              |  repeatexp {n,m}             (n to m occurrences, including both)
+///              |  complexp
+/// complexp    ::= ~ complexp                  (complement)         [OPTIONAL]
+///              |  charclassexp
+/// charclassexp::= [ charclasses ]             (character class)
+///              |  [^ charclasses ]            (negated character class)
+///              |  simpleexp
+/// charclasses ::= charclass charclasses
+///              |  charclass
+/// charclass   ::= charexp - charexp           (character range, including end-points)
+///              |  charexp
+/// simpleexp   ::= charexp
+///              |  .                           (any single character)
+///              |  #                           (the empty language)  [OPTIONAL]
+///              |  @                           (any string)          [OPTIONAL]
+///              |  " <Unicode string without double-quotes> "    (a string)
+///              |  ( )                         (the empty string)
+///              |  ( unionexp )                (precedence override)
+/// charexp     ::= <Unicode character>         (a single non-reserved character)
+///              |  \ <Unicode character>       (a single character)
+/// ```
+///
+/// The productions marked `[OPTIONAL]` are only allowed if specified by the
+/// syntax flags passed to the `RegExp` constructor. The reserved characters
+/// used in the (enabled) syntax must be escaped with backslash (`\`) or double
+/// quotes (`"..."`).
+///
+/// Unlike the backtracking engine of the `regex` crate, the expression is
+/// always compiled into a finite-state automaton, so it can be intersected with
+/// the terms dictionary, and matching is linear in the length of the input.
+#[derive(Clone, Debug)]
+pub struct RegExp {
+    node: RegExpNode,
+    original: String,
+    flags: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum RegExpNode {
+    Union(Box<RegExpNode>, Box<RegExpNode>),
+    Concatenation(Box<RegExpNode>, Box<RegExpNode>),
+    Intersection(Box<RegExpNode>, Box<RegExpNode>),
+    Optional(Box<RegExpNode>),
+    Repeat(Box<RegExpNode>),
+    RepeatMin(Box<RegExpNode>, usize),
+    RepeatMinMax(Box<RegExpNode>, usize, usize),
+    Complement(Box<RegExpNode>),
+    Char(u32),
+    CharClass(Vec<(u32, u32)>, bool),
+    AnyChar,
+    Empty,
+    String(String),
+    AnyString,
+}
+
+impl RegExp {
+    /// Syntax flag, enables intersection (`&`).
+    pub const INTERSECTION: u32 = 0x0001;
+    /// Syntax flag, enables complement (`~`).
+    pub const COMPLEMENT: u32 = 0x0002;
+    /// Syntax flag, enables empty language (`#`).
+    pub const EMPTY: u32 = 0x0004;
+    /// Syntax flag, enables anystring (`@`).
+    pub const ANYSTRING: u32 = 0x0008;
+    /// Syntax flag, enables all optional regexp syntax.
+    pub const ALL: u32 = 0xffff;
+    /// Syntax flag, enables no optional regexp syntax.
+    pub const NONE: u32 = 0x0000;
+
+    /// Constructs new `RegExp` from a string. Same as `RegExp::with_flags(s, RegExp::ALL)`.
+    pub fn new(s: &str) -> Result<RegExp> {
+        RegExp::with_flags(s, RegExp::ALL)
+    }
+
+    /// Constructs new `RegExp` from a string, with the given optional syntax flags.
+    pub fn with_flags(s: &str, flags: u32) -> Result<RegExp> {
+        let node = if s.is_empty() {
+            RegExpNode::String(String::new())
+        } else {
+            let mut parser = RegExpParser {
+                chars: s.chars().collect(),
+                pos: 0,
+                flags,
+            };
+            let node = parser.parse_union_exp()?;
+            if parser.more() {
+                bail!(IllegalArgument(format!(
+                    "end-of-string expected at position {} in regexp '{}'",
+                    parser.pos, s
+                )));
+            }
+            node
+        };
+        Ok(RegExp {
+            node,
+            original: s.to_string(),
+            flags,
+        })
+    }
+
+    /// The string that was used to construct this regexp.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The syntax flags that was used to construct this regexp.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Constructs new `Automaton` over unicode code points from this `RegExp`.
+    ///
+    /// `max_determinized_states` is the maximum number of states that may be
+    /// created while determinizing complement and negated character class
+    /// sub expressions.
+    pub fn to_automaton(&self, max_determinized_states: usize) -> Result<Automaton> {
+        RegExp::node_to_automaton(&self.node, max_determinized_states)
+    }
+
+    fn node_to_automaton(node: &RegExpNode, max_states: usize) -> Result<Automaton> {
+        let automaton = match node {
+            RegExpNode::Union(e1, e2) => Operations::union(&[
+                RegExp::node_to_automaton(e1, max_states)?,
+                RegExp::node_to_automaton(e2, max_states)?,
+            ]),
+            RegExpNode::Concatenation(e1, e2) => Operations::concatenate(&[
+                RegExp::node_to_automaton(e1, max_states)?,
+                RegExp::node_to_automaton(e2, max_states)?,
+            ]),
+            RegExpNode::Intersection(e1, e2) => Operations::intersection(
+                &RegExp::node_to_automaton(e1, max_states)?,
+                &RegExp::node_to_automaton(e2, max_states)?,
+            ),
+            RegExpNode::Optional(e) => {
+                Operations::optional(&RegExp::node_to_automaton(e, max_states)?)
+            }
+            RegExpNode::Repeat(e) => Operations::repeat(&RegExp::node_to_automaton(e, max_states)?),
+            RegExpNode::RepeatMin(e, min) => {
+                Operations::repeat_min(&RegExp::node_to_automaton(e, max_states)?, *min)
+            }
+            RegExpNode::RepeatMinMax(e, min, max) => {
+                Operations::repeat_range(&RegExp::node_to_automaton(e, max_states)?, *min, *max)
+            }
+            RegExpNode::Complement(e) => {
+                Operations::complement(&RegExp::node_to_automaton(e, max_states)?, max_states)?
+            }
+            RegExpNode::Char(c) => Automata::make_char(*c),
+            RegExpNode::CharClass(ranges, negate) => {
+                let ranges: Vec<Automaton> = ranges
+                    .iter()
+                    .map(|&(min, max)| Automata::make_char_range(min, max))
+                    .collect();
+                let class = Operations::union(&ranges);
+                if *negate {
+                    Operations::minus(&Automata::make_any_char(), &class, max_states)?
+                } else {
+                    class
+                }
+            }
+            RegExpNode::AnyChar => Automata::make_any_char(),
+            RegExpNode::Empty => Automata::make_empty(),
+            RegExpNode::String(s) => Automata::make_string(s),
+            RegExpNode::AnyString => Automata::make_any_string(),
+        };
+        Ok(automaton)
+    }
+}
+
+impl fmt::Display for RegExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.original)
+    }
+}
+
+struct RegExpParser {
+    chars: Vec<char>,
+    pos: usize,
+    flags: u32,
+}
+
+impl RegExpParser {
+    fn parse_union_exp(&mut self) -> Result<RegExpNode> {
+        let e = self.parse_inter_exp()?;
+        if self.match_char('|') {
+            let e2 = self.parse_union_exp()?;
+            return Ok(RegExpNode::Union(Box::new(e), Box::new(e2)));
+        }
+        Ok(e)
+    }
+
+    fn parse_inter_exp(&mut self) -> Result<RegExpNode> {
+        let e = self.parse_concat_exp()?;
+        if self.check(RegExp::INTERSECTION) && self.match_char('&') {
+            let e2 = self.parse_inter_exp()?;
+            return Ok(RegExpNode::Intersection(Box::new(e), Box::new(e2)));
+        }
+        Ok(e)
+    }
+
+    fn parse_concat_exp(&mut self) -> Result<RegExpNode> {
+        let e = self.parse_repeat_exp()?;
+        if self.more() && !self.peek(")|") && (!self.check(RegExp::INTERSECTION) || !self.peek("&"))
+        {
+            let e2 = self.parse_concat_exp()?;
+            return Ok(RegExpNode::Concatenation(Box::new(e), Box::new(e2)));
+        }
+        Ok(e)
+    }
+
+    fn parse_repeat_exp(&mut self) -> Result<RegExpNode> {
+        let mut e = self.parse_complement_exp()?;
+        while self.peek("?*+{") {
+            if self.match_char('?') {
+                e = RegExpNode::Optional(Box::new(e));
+            } else if self.match_char('*') {
+                e = RegExpNode::Repeat(Box::new(e));
+            } else if self.match_char('+') {
+                e = RegExpNode::RepeatMin(Box::new(e), 1);
+            } else if self.match_char('{') {
+                let min = self.parse_number()?;
+                let max = if self.match_char(',') {
+                    if self.peek("0123456789") {
+                        Some(self.parse_number()?)
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(min)
+                };
+                if !self.match_char('}') {
+                    bail!(IllegalArgument(format!(
+                        "expected '}}' at position {}",
+                        self.pos
+                    )));
+                }
+                e = match max {
+                    Some(max) => {
+                        if min > max {
+                            bail!(IllegalArgument(format!(
+                                "invalid repetition {{{},{}}} at position {}",
+                                min, max, self.pos
+                            )));
+                        }
+                        RegExpNode::RepeatMinMax(Box::new(e), min, max)
+                    }
+                    None => RegExpNode::RepeatMin(Box::new(e), min),
+                };
+            }
+        }
+        Ok(e)
+    }
+
+    fn parse_complement_exp(&mut self) -> Result<RegExpNode> {
+        if self.check(RegExp::COMPLEMENT) && self.match_char('~') {
+            let e = self.parse_complement_exp()?;
+            return Ok(RegExpNode::Complement(Box::new(e)));
+        }
+        self.parse_char_class_exp()
+    }
+
+    fn parse_char_class_exp(&mut self) -> Result<RegExpNode> {
+        if self.match_char('[') {
+            let negate = self.match_char('^');
+            let mut ranges = Vec::new();
+            loop {
+                let min = self.parse_char_exp()?;
+                let max = if self.match_char('-') {
+                    self.parse_char_exp()?
+                } else {
+                    min
+                };
+                if min > max {
+                    bail!(IllegalArgument(format!(
+                        "invalid range: from ({}) cannot be > to ({})",
+                        min, max
+                    )));
+                }
+                ranges.push((min, max));
+                if !self.more() || self.peek("]") {
+                    break;
+                }
+            }
+            if !self.match_char(']') {
+                bail!(IllegalArgument(format!(
+                    "expected ']' at position {}",
+                    self.pos
+                )));
+            }
+            return Ok(RegExpNode::CharClass(ranges, negate));
+        }
+        self.parse_simple_exp()
+    }
+
+    fn parse_simple_exp(&mut self) -> Result<RegExpNode> {
+        if self.match_char('.') {
+            Ok(RegExpNode::AnyChar)
+        } else if self.check(RegExp::EMPTY) && self.match_char('#') {
+            Ok(RegExpNode::Empty)
+        } else if self.check(RegExp::ANYSTRING) && self.match_char('@') {
+            Ok(RegExpNode::AnyString)
+        } else if self.match_char('"') {
+            let start = self.pos;
+            while self.more() && !self.peek("\"") {
+                self.pos += 1;
+            }
+            if !self.match_char('"') {
+                bail!(IllegalArgument(format!(
+                    "expected '\"' at position {}",
+                    self.pos
+                )));
+            }
+            let s: String = self.chars[start..self.pos - 1].iter().collect();
+            Ok(RegExpNode::String(s))
+        } else if self.match_char('(') {
+            if self.match_char(')') {
+                return Ok(RegExpNode::String(String::new()));
+            }
+            let e = self.parse_union_exp()?;
+            if !self.match_char(')') {
+                bail!(IllegalArgument(format!(
+                    "expected ')' at position {}",
+                    self.pos
+                )));
+            }
+            Ok(e)
+        } else {
+            Ok(RegExpNode::Char(self.parse_char_exp()?))
+        }
+    }
+
+    fn parse_char_exp(&mut self) -> Result<u32> {
+        self.match_char('\\');
+        self.next()
+    }
+
+    fn parse_number(&mut self) -> Result<usize> {
+        let start = self.pos;
+        while self.peek("0123456789") {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            bail!(IllegalArgument(format!(
+                "integer expected at position {}",
+                self.pos
+            )));
+        }
+        let s: String = self.chars[start..self.pos].iter().collect();
+        s.parse::<usize>()
+            .map_err(|e| IllegalArgument(format!("invalid number '{}': {}", s, e)).into())
+    }
+
+    fn next(&mut self) -> Result<u32> {
+        if !self.more() {
+            bail!(IllegalArgument("unexpected end-of-string".into()));
+        }
+        let c = self.chars[self.pos];
+        self.pos += 1;
+        Ok(c as u32)
+    }
+
+    fn more(&self) -> bool {
+        self.pos < self.chars.len()
+    }
+
+    fn peek(&self, s: &str) -> bool {
+        self.more() && s.contains(self.chars[self.pos])
+    }
+
+    fn match_char(&mut self, c: char) -> bool {
+        if self.more() && self.chars[self.pos] == c {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn check(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::automaton::{CompiledAutomaton, DEFAULT_MAX_DETERMINIZED_STATES};
+
+    fn compile(regexp: &str) -> CompiledAutomaton {
+        let a = RegExp::new(regexp)
+            .unwrap()
+            .to_automaton(DEFAULT_MAX_DETERMINIZED_STATES)
+            .unwrap();
+        CompiledAutomaton::with_automaton(&a).unwrap()
+    }
+
+    #[test]
+    fn test_regexp_match() {
+        let a = compile("ab*c");
+        assert!(a.run(b"ac"));
+        assert!(a.run(b"abbbc"));
+        assert!(!a.run(b"abd"));
+
+        let a = compile("[a-c]{2,3}|foo");
+        assert!(a.run(b"ab"));
+        assert!(a.run(b"abc"));
+        assert!(a.run(b"foo"));
+        assert!(!a.run(b"a"));
+        assert!(!a.run(b"abca"));
+
+        let a = compile("[^a]x.");
+        assert!(a.run(b"bxz"));
+        assert!(a.run("中x中".as_bytes()));
+        assert!(!a.run(b"axz"));
+
+        let a = compile("\"a.b\"\\*");
+        assert!(a.run(b"a.b*"));
+        assert!(!a.run(b"acb*"));
+
+        let a = compile("@&~(foo.*)");
+        assert!(a.run(b"bar"));
+        assert!(!a.run(b"foobar"));
+    }
+
+    #[test]
+    fn test_regexp_type() {
+        use core::util::automaton::AutomatonType;
+
+        assert_eq!(compile("foo").automaton_type, AutomatonType::Single);
+        assert_eq!(compile("foo").term, Some(b"foo".to_vec()));
+        assert_eq!(compile(".*").automaton_type, AutomatonType::All);
+        assert_eq!(compile("#").automaton_type, AutomatonType::None);
+        assert_eq!(compile("fo+").automaton_type, AutomatonType::Normal);
+    }
+
+    #[test]
+    fn test_regexp_syntax_error() {
+        assert!(RegExp::new("ab(c").is_err());
+        assert!(RegExp::new("[abc").is_err());
+        assert!(RegExp::new("a{3,1}").is_err());
+        assert!(RegExp::new("a|").is_err());
+        assert!(RegExp::with_flags("a&b", RegExp::NONE).is_ok());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::Automaton;
+
+/// Finite-state automaton with fast run operation over byte labels.
+///
+/// The transition function is stored as a table of `256 * num_states` entries
+/// so that each step is a single array lookup.
+pub struct ByteRunAutomaton {
+    automaton: Automaton,
+    accept: Vec<bool>,
+    // transitions[state * 256 + label], -1 if the label is rejected
+    transitions: Vec<i32>,
+}
+
+impl ByteRunAutomaton {
+    /// Creates a run automaton from the given deterministic byte automaton.
+    pub fn new(automaton: Automaton) -> ByteRunAutomaton {
+        debug_assert!(automaton.is_deterministic());
+        let size = automaton.num_states();
+        let mut accept = Vec::with_capacity(size);
+        let mut transitions = vec![-1i32; size * 256];
+        for s in 0..size {
+            accept.push(automaton.is_accept(s));
+            for t in automaton.transitions(s) {
+                debug_assert!(t.max <= 255);
+                for label in t.min..=t.max {
+                    transitions[s * 256 + label as usize] = t.dest as i32;
+                }
+            }
+        }
+        ByteRunAutomaton {
+            automaton,
+            accept,
+            transitions,
+        }
+    }
+
+    /// Returns the underlying deterministic automaton, whose transitions are sorted
+    /// by label.
+    pub fn automaton(&self) -> &Automaton {
+        &self.automaton
+    }
+
+    /// Returns number of states in automaton.
+    pub fn size(&self) -> usize {
+        self.accept.len()
+    }
+
+    /// Returns acceptance status for given state.
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accept[state]
+    }
+
+    /// Returns the state obtained by reading the given label from the given
+    /// state, or `None` if the label is rejected.
+    #[inline]
+    pub fn step(&self, state: usize, label: u8) -> Option<usize> {
+        let dest = self.transitions[state * 256 + label as usize];
+        if dest >= 0 {
+            Some(dest as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the given bytes is accepted by this automaton.
+    pub fn run(&self, bytes: &[u8]) -> bool {
+        if self.size() == 0 {
+            return false;
+        }
+        let mut state = 0;
+        for &b in bytes {
+            match self.step(state, b) {
+                Some(s) => state = s,
+                None => return false,
+            }
+        }
+        self.accept[state]
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{Automaton, MAX_CODE_POINT};
+
+const MIN_SURROGATE: u32 = 0xd800;
+const MAX_SURROGATE: u32 = 0xdfff;
+
+/// Converts UTF-32 automata to the equivalent UTF-8 representation.
+///
+/// Every code point range transition is split into sequences of byte ranges,
+/// such that each sequence matches exactly the UTF-8 encoding of a sub range.
+/// Surrogate code points are not valid in UTF-8 and are dropped.
+pub struct Utf32ToUtf8;
+
+impl Utf32ToUtf8 {
+    /// Converts an incoming UTF-32 automaton to an equivalent UTF-8 one. The
+    /// result is usually non-deterministic.
+    pub fn convert(a: &Automaton) -> Automaton {
+        let mut result = Automaton::new();
+        for s in 0..a.num_states() {
+            let state = result.create_state();
+            result.set_accept(state, a.is_accept(s));
+        }
+        let mut sequences = Vec::new();
+        for s in 0..a.num_states() {
+            for t in a.transitions(s) {
+                sequences.clear();
+                Utf32ToUtf8::split(t.min, t.max.min(MAX_CODE_POINT), &mut sequences);
+                for sequence in &sequences {
+                    let mut from = s;
+                    for (i, &(min, max)) in sequence.iter().enumerate() {
+                        let to = if i + 1 == sequence.len() {
+                            t.dest
+                        } else {
+                            result.create_state()
+                        };
+                        result.add_transition(from, to, u32::from(min), u32::from(max));
+                        from = to;
+                    }
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Splits the code point range `[start, end]` into byte range sequences.
+    fn split(start: u32, end: u32, out: &mut Vec<Vec<(u8, u8)>>) {
+        if start > end {
+            return;
+        }
+        if start <= MAX_SURROGATE && end >= MIN_SURROGATE {
+            if start < MIN_SURROGATE {
+                Utf32ToUtf8::split(start, MIN_SURROGATE - 1, out);
+            }
+            if end > MAX_SURROGATE {
+                Utf32ToUtf8::split(MAX_SURROGATE + 1, end, out);
+            }
+            return;
+        }
+        // make sure both ends of the range are encoded with the same length
+        for &max in &[0x7fu32, 0x7ff, 0xffff] {
+            if start <= max && max < end {
+                Utf32ToUtf8::split(start, max, out);
+                Utf32ToUtf8::split(max + 1, end, out);
+                return;
+            }
+        }
+        if end <= 0x7f {
+            out.push(vec![(start as u8, end as u8)]);
+            return;
+        }
+        // make sure every continuation byte covers its full range, except the
+        // trailing ones which may be partial
+        for i in 1..4 {
+            let m = (1u32 << (6 * i)) - 1;
+            if start & !m != end & !m {
+                if start & m != 0 {
+                    Utf32ToUtf8::split(start, start | m, out);
+                    Utf32ToUtf8::split((start | m) + 1, end, out);
+                    return;
+                }
+                if end & m != m {
+                    Utf32ToUtf8::split(start, (end & !m) - 1, out);
+                    Utf32ToUtf8::split(end & !m, end, out);
+                    return;
+                }
+            }
+        }
+        let mut start_buf = [0u8; 4];
+        let mut end_buf = [0u8; 4];
+        let start_bytes = Utf32ToUtf8::encode(start, &mut start_buf);
+        let end_bytes = Utf32ToUtf8::encode(end, &mut end_buf);
+        debug_assert_eq!(start_bytes.len(), end_bytes.len());
+        out.push(
+            start_bytes
+                .iter()
+                .zip(end_bytes.iter())
+                .map(|(&s, &e)| (s, e))
+                .collect(),
+        );
+    }
+
+    fn encode(code_point: u32, buf: &mut [u8; 4]) -> &[u8] {
+        let c = ::std::char::from_u32(code_point).expect("invalid code point");
+        let len = c.encode_utf8(buf).len();
+        &buf[..len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::automaton::{Automata, Operations, DEFAULT_MAX_DETERMINIZED_STATES};
+
+    #[test]
+    fn test_convert() {
+        let a = Automata::make_any_char();
+        let utf8 =
+            Operations::determinize(&Utf32ToUtf8::convert(&a), DEFAULT_MAX_DETERMINIZED_STATES)
+                .unwrap();
+        let run = |s: &str| {
+            let labels: Vec<u32> = s.bytes().map(u32::from).collect();
+            utf8.run(&labels)
+        };
+        assert!(run("a"));
+        assert!(run("é"));
+        assert!(run("中"));
+        assert!(run("😀"));
+        assert!(!run("ab"));
+        assert!(!run(""));
+        assert!(!utf8.run(&[0xff]));
+
+        let range = Utf32ToUtf8::convert(&Automata::make_char_range(0x801, 0x8ff));
+        for c in 0x7ffu32..0x901 {
+            let s = ::std::char::from_u32(c).unwrap().to_string();
+            let labels: Vec<u32> = s.bytes().map(u32::from).collect();
+            assert_eq!(range.run(&labels), (0x801..=0x8ff).contains(&c));
+        }
+    }
+}
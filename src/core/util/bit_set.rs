@@ -234,6 +234,29 @@ impl FixedBitSet {
         self.bits[end_word] ^= end_mask;
     }
 
+    /// Returns the index of the last set bit at or before `index`, -1 if there
+    /// is no set bit before it.
+    pub fn prev_set_bit(&self, index: usize) -> i32 {
+        debug_assert!(index < self.num_bits);
+        let mut i = index >> 6;
+        let sub_index = index & 0x3fusize;
+        // skip all the bits to the left of index
+        let word = self.bits[i] << (63 - sub_index);
+
+        if word != 0 {
+            return (index as u32 - word.leading_zeros()) as i32;
+        }
+
+        while i > 0 {
+            i -= 1;
+            let word = self.bits[i];
+            if word != 0 {
+                return ((i << 6) as u32 + 63 - word.leading_zeros()) as i32;
+            }
+        }
+        -1
+    }
+
     /// returns true if the sets have any elements in common
     pub fn intersects(&self, other: &FixedBitSet) -> bool {
         // Depends on the ghost bits being clear!
@@ -250,6 +273,14 @@ impl FixedBitSet {
         self.do_or(&other.bits, other.num_words);
     }
 
+    /// Clears all the bits set in `other`.
+    pub fn and_not(&mut self, other: &FixedBitSet) {
+        let pos = self.num_words.min(other.num_words);
+        for i in 0..pos {
+            self.bits[i] &= !other.bits[i];
+        }
+    }
+
     fn do_or(&mut self, other_arr: &[i64], other_num_words: usize) {
         assert!(other_num_words <= self.num_words);
         let this_arr = &mut self.bits;
@@ -96,3 +96,40 @@ pub fn pop_array(arr: &[i64], word_offset: usize, num_words: usize) -> usize {
     }
     pop_count
 }
+
+const MAGIC: [u64; 6] = [
+    0x5555_5555_5555_5555,
+    0x3333_3333_3333_3333,
+    0x0F0F_0F0F_0F0F_0F0F,
+    0x00FF_00FF_00FF_00FF,
+    0x0000_FFFF_0000_FFFF,
+    0x0000_0000_FFFF_FFFF,
+];
+
+/// Interleaves the bits of two ints into a morton code: the bits of `even`
+/// go to the even positions and those of `odd` to the odd ones.
+pub fn interleave(even: u32, odd: u32) -> u64 {
+    spread(even) | (spread(odd) << 1)
+}
+
+/// Extracts the bits at the even positions of a morton code, shift it right
+/// by one first for the odd ones.
+pub fn deinterleave(b: u64) -> u32 {
+    let mut b = b & MAGIC[0];
+    b = (b ^ (b >> 1)) & MAGIC[1];
+    b = (b ^ (b >> 2)) & MAGIC[2];
+    b = (b ^ (b >> 4)) & MAGIC[3];
+    b = (b ^ (b >> 8)) & MAGIC[4];
+    b = (b ^ (b >> 16)) & MAGIC[5];
+    b as u32
+}
+
+/// Spreads the bits of an int to the even positions of a long.
+fn spread(v: u32) -> u64 {
+    let mut v = u64::from(v);
+    v = (v | (v << 16)) & MAGIC[4];
+    v = (v | (v << 8)) & MAGIC[3];
+    v = (v | (v << 4)) & MAGIC[2];
+    v = (v | (v << 2)) & MAGIC[1];
+    (v | (v << 1)) & MAGIC[0]
+}
@@ -27,6 +27,7 @@ use core::util::DocId;
 use error::{ErrorKind, Result};
 
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::sync::Arc;
 
 /// Used to track all state for a single call to {@link #intersect}.
@@ -62,6 +63,49 @@ impl<'a, IV: IntersectVisitor + 'a> IntersectState<'a, IV> {
     }
 }
 
+/// Guides `BKDReader::visit_best_first`, the points of the visited leaf cells
+/// are passed to `IntersectVisitor::visit_by_packed_value`.
+pub trait BestFirstVisitor: IntersectVisitor {
+    /// A lower bound of the distance between the target and any point of the cell.
+    fn cell_distance(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> f64;
+
+    /// The largest distance that is still competitive, the cells further away
+    /// are not visited.
+    fn max_distance(&self) -> f64;
+}
+
+// A cell of the tree waiting to be visited, the closest cell is the greatest
+struct BestFirstCell {
+    distance: f64,
+    node_id: i32,
+    min_packed_value: Vec<u8>,
+    max_packed_value: Vec<u8>,
+}
+
+impl Eq for BestFirstCell {}
+
+impl PartialEq for BestFirstCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Ord for BestFirstCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .partial_cmp(&self.distance)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node_id.cmp(&self.node_id))
+    }
+}
+
+impl PartialOrd for BestFirstCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 /// Handles intersection of an multi-dimensional shape in bytes space with a block KD-tree
 /// previously written with `BKDWriter`.
 
@@ -344,6 +388,17 @@ impl BKDReader {
         &self,
         visitor: &'a mut IV,
     ) -> Result<IntersectState<'a, IV>> {
+        Ok(IntersectState::new(
+            self.input.as_ref().clone()?,
+            self.num_dims,
+            self.packed_bytes_length,
+            self.max_points_in_leaf_node,
+            visitor,
+            self.new_index_tree()?,
+        ))
+    }
+
+    fn new_index_tree(&self) -> Result<Box<dyn IndexTree>> {
         let index_tree: Box<dyn IndexTree> = if !self.packed_index.is_empty() {
             Box::new(PackedIndexTree::new(
                 self.bytes_per_dim,
@@ -365,15 +420,98 @@ impl BKDReader {
                 self.leaf_node_offset,
             ))
         };
+        Ok(index_tree)
+    }
+
+    /// Visits the points of the leaf cells in increasing order of their
+    /// `BestFirstVisitor::cell_distance`, until the closest remaining cell is
+    /// further than `BestFirstVisitor::max_distance`.
+    ///
+    /// This is used for nearest neighbor searches: only the cells that may still
+    /// hold one of the closest points are ever read.
+    pub fn visit_best_first(&self, visitor: &mut impl BestFirstVisitor) -> Result<()> {
+        let mut input = self.input.as_ref().clone()?;
+        let mut scratch_doc_ids = vec![0i32; self.max_points_in_leaf_node];
+        let mut scratch_packed_value = vec![0u8; self.packed_bytes_length];
+        let mut common_prefix_lengths = vec![0i32; self.num_dims];
+
+        let mut queue = BinaryHeap::new();
+        queue.push(BestFirstCell {
+            distance: visitor.cell_distance(&self.min_packed_value, &self.max_packed_value),
+            node_id: 1,
+            min_packed_value: self.min_packed_value.clone(),
+            max_packed_value: self.max_packed_value.clone(),
+        });
+
+        while let Some(cell) = queue.pop() {
+            if cell.distance > visitor.max_distance() {
+                // all the remaining cells are even further away
+                break;
+            }
 
-        Ok(IntersectState::new(
-            self.input.as_ref().clone()?,
-            self.num_dims,
-            self.packed_bytes_length,
-            self.max_points_in_leaf_node,
-            visitor,
-            index_tree,
-        ))
+            // the index tree can only be walked downwards from the root
+            let mut index_tree = self.new_index_tree()?;
+            let depth = 31 - cell.node_id.leading_zeros() as i32;
+            for i in (0..depth).rev() {
+                if (cell.node_id >> i) & 1 == 0 {
+                    index_tree.push_left()?;
+                } else {
+                    index_tree.push_right()?;
+                }
+            }
+
+            if index_tree.is_leaf_node() {
+                // In the unbalanced case it's possible the left most node only has one child:
+                if index_tree.node_exists() {
+                    let count = self.read_doc_ids(
+                        input.as_mut(),
+                        index_tree.leaf_block_fp(),
+                        &mut scratch_doc_ids,
+                    )?;
+                    self.visit_doc_values(
+                        &mut common_prefix_lengths,
+                        &mut scratch_packed_value,
+                        input.as_mut(),
+                        &scratch_doc_ids,
+                        count,
+                        visitor,
+                    )?;
+                }
+            } else {
+                let split_dim = index_tree.split_dim() as usize;
+                let split_dim_value = index_tree.split_dim_value();
+                let start = split_dim * self.bytes_per_dim;
+                let end = start + self.bytes_per_dim;
+
+                let mut left_max_packed_value = cell.max_packed_value.clone();
+                left_max_packed_value[start..end].copy_from_slice(&split_dim_value);
+                let distance =
+                    visitor.cell_distance(&cell.min_packed_value, &left_max_packed_value);
+                if distance <= visitor.max_distance() {
+                    queue.push(BestFirstCell {
+                        distance,
+                        node_id: cell.node_id * 2,
+                        min_packed_value: cell.min_packed_value.clone(),
+                        max_packed_value: left_max_packed_value,
+                    });
+                }
+
+                let mut right_min_packed_value = cell.min_packed_value;
+                right_min_packed_value[start..end].copy_from_slice(&split_dim_value);
+                let distance =
+                    visitor.cell_distance(&right_min_packed_value, &cell.max_packed_value);
+                if distance <= visitor.max_distance() {
+                    queue.push(BestFirstCell {
+                        distance,
+                        node_id: cell.node_id * 2 + 1,
+                        min_packed_value: right_min_packed_value,
+                        max_packed_value: cell.max_packed_value,
+                    });
+                }
+            }
+        }
+
+        Ok(())
     }
 
     /// Fast path: this is called when the query box fully encompasses all cells under this
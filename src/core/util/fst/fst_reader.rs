@@ -842,7 +842,7 @@ impl<F: OutputFactory> FST<F> {
 
     #[allow(dead_code)]
     fn write_label(&self, out: &mut impl DataOutput, v: i32) -> Result<()> {
-        assert!(v > 0);
+        assert!(v >= 0);
         match self.input_type {
             InputType::Byte1 => {
                 assert!(v <= 255);
@@ -857,7 +857,7 @@ impl<F: OutputFactory> FST<F> {
     }
 
     fn write_label_local(&mut self, v: i32) -> Result<()> {
-        assert!(v > 0);
+        assert!(v >= 0);
         match self.input_type {
             InputType::Byte1 => {
                 assert!(v <= 255);
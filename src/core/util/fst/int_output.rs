@@ -0,0 +1,136 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::store::io::{DataInput, DataOutput};
+use core::util::fst::{Output, OutputFactory};
+use error::Result;
+
+/// An FST `Output` where each output is a non-negative long value. Common
+/// prefix of two outputs is their minimum, so outputs are pushed as close
+/// to the root as possible.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PositiveIntOutput {
+    value: i64,
+}
+
+impl PositiveIntOutput {
+    pub fn new(value: i64) -> PositiveIntOutput {
+        debug_assert!(value >= 0);
+        PositiveIntOutput { value }
+    }
+
+    #[inline]
+    pub fn get(&self) -> i64 {
+        self.value
+    }
+}
+
+impl Output for PositiveIntOutput {
+    type Value = i64;
+
+    fn prefix(&self, other: &PositiveIntOutput) -> PositiveIntOutput {
+        PositiveIntOutput::new(self.value.min(other.value))
+    }
+
+    fn cat(&self, other: &PositiveIntOutput) -> PositiveIntOutput {
+        PositiveIntOutput::new(self.value + other.value)
+    }
+
+    fn concat(&mut self, other: &PositiveIntOutput) {
+        self.value += other.value;
+    }
+
+    fn subtract(&self, other: &PositiveIntOutput) -> PositiveIntOutput {
+        debug_assert!(other.value <= self.value);
+        PositiveIntOutput::new(self.value - other.value)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.value == 0
+    }
+
+    #[inline]
+    fn value(&self) -> i64 {
+        self.value
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct PositiveIntOutputFactory {}
+
+impl PositiveIntOutputFactory {
+    pub fn new() -> PositiveIntOutputFactory {
+        PositiveIntOutputFactory {}
+    }
+}
+
+impl OutputFactory for PositiveIntOutputFactory {
+    type Value = PositiveIntOutput;
+
+    fn empty(&self) -> PositiveIntOutput {
+        PositiveIntOutput::default()
+    }
+
+    fn common(&self, o1: &PositiveIntOutput, o2: &PositiveIntOutput) -> PositiveIntOutput {
+        o1.prefix(o2)
+    }
+
+    fn subtract(&self, o1: &PositiveIntOutput, o2: &PositiveIntOutput) -> PositiveIntOutput {
+        Output::subtract(o1, o2)
+    }
+
+    fn add(&self, prefix: &PositiveIntOutput, output: &PositiveIntOutput) -> PositiveIntOutput {
+        prefix.cat(output)
+    }
+
+    fn read<T: DataInput + ?Sized>(&self, data_in: &mut T) -> Result<PositiveIntOutput> {
+        Ok(PositiveIntOutput::new(data_in.read_vlong()?))
+    }
+
+    fn write<T: DataOutput + ?Sized>(
+        &self,
+        output: &PositiveIntOutput,
+        data_out: &mut T,
+    ) -> Result<()> {
+        data_out.write_vlong(output.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::util::fst::tests::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let factory = PositiveIntOutputFactory::new();
+        let o1 = PositiveIntOutput::new(7);
+        let o2 = PositiveIntOutput::new(3);
+        assert_eq!(factory.common(&o1, &o2).get(), 3);
+        assert_eq!(factory.subtract(&o1, &o2).get(), 4);
+        assert_eq!(factory.add(&o1, &o2).get(), 10);
+        assert!(factory.empty().is_empty());
+    }
+
+    #[test]
+    fn test_read_write() {
+        let mut io = TestBufferedDataIO::default();
+        let factory = PositiveIntOutputFactory::new();
+        factory
+            .write(&PositiveIntOutput::new(300), &mut io)
+            .unwrap();
+        assert_eq!(factory.read(&mut io).unwrap().get(), 300);
+    }
+}
@@ -31,6 +31,14 @@ mod fst_reader;
 
 pub use self::fst_reader::*;
 
+mod int_output;
+
+pub use self::int_output::*;
+
+mod pair_output;
+
+pub use self::pair_output::*;
+
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::io;
@@ -0,0 +1,153 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::store::io::{DataInput, DataOutput};
+use core::util::fst::{Output, OutputFactory};
+use error::Result;
+
+/// An FST `Output` combining two other outputs, each component is handled
+/// by its own `OutputFactory`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PairOutput<A: Output, B: Output> {
+    pub output1: A,
+    pub output2: B,
+}
+
+impl<A: Output, B: Output> PairOutput<A, B> {
+    pub fn new(output1: A, output2: B) -> PairOutput<A, B> {
+        PairOutput { output1, output2 }
+    }
+}
+
+impl<A: Output, B: Output> Output for PairOutput<A, B> {
+    type Value = (A::Value, B::Value);
+
+    fn prefix(&self, other: &Self) -> Self {
+        PairOutput::new(
+            self.output1.prefix(&other.output1),
+            self.output2.prefix(&other.output2),
+        )
+    }
+
+    fn cat(&self, other: &Self) -> Self {
+        PairOutput::new(
+            self.output1.cat(&other.output1),
+            self.output2.cat(&other.output2),
+        )
+    }
+
+    fn concat(&mut self, other: &Self) {
+        self.output1.concat(&other.output1);
+        self.output2.concat(&other.output2);
+    }
+
+    fn subtract(&self, other: &Self) -> Self {
+        PairOutput::new(
+            self.output1.subtract(&other.output1),
+            self.output2.subtract(&other.output2),
+        )
+    }
+
+    fn is_empty(&self) -> bool {
+        self.output1.is_empty() && self.output2.is_empty()
+    }
+
+    fn value(&self) -> Self::Value {
+        (self.output1.value(), self.output2.value())
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct PairOutputFactory<F1: OutputFactory, F2: OutputFactory> {
+    outputs1: F1,
+    outputs2: F2,
+}
+
+impl<F1: OutputFactory, F2: OutputFactory> PairOutputFactory<F1, F2> {
+    pub fn new(outputs1: F1, outputs2: F2) -> Self {
+        PairOutputFactory { outputs1, outputs2 }
+    }
+}
+
+impl<F1: OutputFactory, F2: OutputFactory> OutputFactory for PairOutputFactory<F1, F2> {
+    type Value = PairOutput<F1::Value, F2::Value>;
+
+    fn empty(&self) -> Self::Value {
+        PairOutput::new(self.outputs1.empty(), self.outputs2.empty())
+    }
+
+    fn common(&self, o1: &Self::Value, o2: &Self::Value) -> Self::Value {
+        PairOutput::new(
+            self.outputs1.common(&o1.output1, &o2.output1),
+            self.outputs2.common(&o1.output2, &o2.output2),
+        )
+    }
+
+    fn subtract(&self, o1: &Self::Value, o2: &Self::Value) -> Self::Value {
+        PairOutput::new(
+            self.outputs1.subtract(&o1.output1, &o2.output1),
+            self.outputs2.subtract(&o1.output2, &o2.output2),
+        )
+    }
+
+    fn add(&self, prefix: &Self::Value, output: &Self::Value) -> Self::Value {
+        PairOutput::new(
+            self.outputs1.add(&prefix.output1, &output.output1),
+            self.outputs2.add(&prefix.output2, &output.output2),
+        )
+    }
+
+    fn read<T: DataInput + ?Sized>(&self, data_in: &mut T) -> Result<Self::Value> {
+        let output1 = self.outputs1.read(data_in)?;
+        let output2 = self.outputs2.read(data_in)?;
+        Ok(PairOutput::new(output1, output2))
+    }
+
+    fn write<T: DataOutput + ?Sized>(&self, output: &Self::Value, data_out: &mut T) -> Result<()> {
+        self.outputs1.write(&output.output1, data_out)?;
+        self.outputs2.write(&output.output2, data_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::util::fst::tests::*;
+    use core::util::fst::{
+        ByteSequenceOutput, ByteSequenceOutputFactory, PositiveIntOutput, PositiveIntOutputFactory,
+    };
+
+    #[test]
+    fn test_pair_read_write() {
+        let mut io = TestBufferedDataIO::default();
+        let factory = PairOutputFactory::new(
+            PositiveIntOutputFactory::new(),
+            ByteSequenceOutputFactory::new(),
+        );
+        let output = PairOutput::new(
+            PositiveIntOutput::new(5),
+            ByteSequenceOutput::new(vec![1, 2]),
+        );
+        factory.write(&output, &mut io).unwrap();
+        let read = factory.read(&mut io).unwrap();
+        assert_eq!(read, output);
+        assert_eq!(read.value(), (5, vec![1, 2]));
+
+        let common = factory.common(
+            &output,
+            &PairOutput::new(PositiveIntOutput::new(3), ByteSequenceOutput::new(vec![1])),
+        );
+        assert_eq!(common.value(), (3, vec![1]));
+    }
+}
@@ -13,6 +13,7 @@
 
 pub type DocId = i32;
 
+pub mod automaton;
 pub mod bkd;
 pub mod external;
 pub mod fst;
@@ -81,6 +81,23 @@ pub fn sort_key_length(prior_term: &[u8], current_term: &[u8]) -> usize {
     current_term.len().min(1 + prior_term.len())
 }
 
+/// Removes the backslash escapes of `s`: an escaped char is kept as is, and a
+/// trailing backslash is dropped.
+pub(crate) fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                result.push(escaped);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +108,12 @@ mod tests {
         let strv = id2str(&v[..]);
         assert_eq!("4161047F", strv);
     }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("a\\*b"), "a*b");
+        assert_eq!(unescape("a\\\\b"), "a\\b");
+        assert_eq!(unescape("ab\\"), "ab");
+        assert_eq!(unescape("搜\\索"), "搜索");
+    }
 }
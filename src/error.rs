@@ -18,7 +18,10 @@ use core::index;
 use core::search;
 use core::search::collector;
 
+use serde::{de, ser};
+
 use std::borrow::Cow;
+use std::fmt;
 use std::sync::PoisonError;
 
 error_chain! {
@@ -95,3 +98,16 @@ impl<Guard> From<PoisonError<Guard>> for Error {
         ErrorKind::Poisoned.into()
     }
 }
+
+// needed by the serde serializer and deserializer of `core::doc::mapping`
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        ErrorKind::Msg(msg.to_string()).into()
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        ErrorKind::Msg(msg.to_string()).into()
+    }
+}
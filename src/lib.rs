@@ -34,6 +34,7 @@ extern crate lazy_static;
 extern crate log;
 extern crate rand;
 extern crate regex;
+extern crate rust_stemmers;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -42,16 +43,22 @@ extern crate serde_json;
 extern crate alloc;
 extern crate byteorder;
 extern crate bytes;
+#[cfg(feature = "icu")]
+extern crate caseless;
 extern crate crc;
 extern crate crossbeam;
 extern crate fasthash;
 extern crate flate2;
+#[cfg(feature = "jieba")]
+extern crate jieba_rs;
 extern crate memmap;
 extern crate num_cpus;
 extern crate num_traits;
 extern crate smallvec;
 extern crate thread_local;
+extern crate unicode_normalization;
 extern crate unicode_reader;
+extern crate unicode_segmentation;
 
 pub mod core;
 pub mod error;